@@ -1,8 +1,33 @@
 use change_detection::ChangeDetection;
 use static_files::resource_dir;
+#[cfg(feature = "with-bigquery")]
+use std::path::Path;
 
 fn main() -> std::io::Result<()> {
-    ChangeDetection::path("static").path("build.rs").generate();
+    ChangeDetection::path("static")
+        .path("proto")
+        .path("build.rs")
+        .generate();
+
+    #[cfg(feature = "with-grpc")]
+    {
+        std::env::set_var(
+            "PROTOC",
+            protoc_bin_vendored::protoc_bin_path().expect("protoc binary not available"),
+        );
+        tonic_build::configure()
+            .build_client(false)
+            .compile(&["proto/pipeline.proto"], &["proto"])?;
+    }
+
+    #[cfg(feature = "with-bigquery")]
+    {
+        let include = protoc_bin_vendored::include_path().expect("protoc includes not available");
+        tonic_build::configure().build_server(false).compile(
+            &["proto/bigquery_storage.proto"],
+            &[Path::new("proto"), include.as_path()],
+        )?;
+    }
 
     resource_dir("./static").build()
 }
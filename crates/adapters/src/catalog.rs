@@ -1,6 +1,14 @@
-use std::{collections::BTreeMap, sync::Arc};
-
-use crate::{static_compile::ErasedDeScalarHandle, ControllerError};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::{
+    format::BinaryEncoding,
+    jit::schema::{ProgramSchema, TableSchema},
+    static_compile::ErasedDeScalarHandle,
+    ColumnStatsHandle, ControllerError, ViewStatistics,
+};
 use anyhow::Result as AnyResult;
 use dbsp::InputHandle;
 use serde::{Deserialize, Serialize};
@@ -20,10 +28,34 @@ pub enum RecordFormat {
     // * Raw - Only applicable to single-column tables.  Input records contain
     // raw encoding of this column only.  This is particularly useful for
     // tables that store raw JSON or binary data to be parsed using SQL.
-    Json,
-    Csv,
+    Json(JsonFlavor),
+    Csv(CsvFlavor),
+}
+
+/// Variations of the JSON encoding.
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct JsonFlavor {
+    /// Encode 64-bit integers and decimals as JSON strings instead of numbers.
+    ///
+    /// JavaScript clients cannot represent integers above 2^53 exactly and
+    /// silently corrupt such values when they are sent as JSON numbers.
+    #[serde(default)]
+    pub large_numbers_as_strings: bool,
+}
+
+/// Variations of the CSV encoding.
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct CsvFlavor {
+    /// Text encoding of binary values.
+    #[serde(default)]
+    pub binary_encoding: BinaryEncoding,
 }
 
+/// Number of neighborhood sessions maintained by the circuit for each table
+/// or view, i.e., the number of different neighborhoods of the same
+/// collection that clients can monitor concurrently.
+pub const NEIGHBORHOOD_SESSIONS: usize = 8;
+
 // This is only here so we can derive `ToSchema` for it without adding
 // a `utoipa` dependency to the `dbsp` crate to derive ToSchema for
 // `NeighborhoodDescr`.
@@ -56,6 +88,32 @@ where
     }
 }
 
+/// Suffix appended to the name of an output collection to form the name of
+/// its [change statistics](`ChangeStatistics`) stream.
+pub const CHANGES_SUFFIX: &str = "__changes";
+
+/// Number of records inserted into and deleted from an output collection in
+/// one step of the circuit.
+///
+/// Every output collection `V` has a companion output stream named
+/// `V__changes` (see [`CHANGES_SUFFIX`]), which carries one such record
+/// for each step that modifies `V`.  Subscribing to this stream allows
+/// clients to monitor the rate and direction of changes to a view without
+/// consuming its complete delta stream.
+#[derive(Serialize)]
+pub struct ChangeStatistics {
+    /// Total weight of records with positive weights.
+    pub inserts: i64,
+    /// Total weight of records with negative weights, as a positive number.
+    pub deletes: i64,
+}
+
+impl From<(i64, i64)> for ChangeStatistics {
+    fn from((inserts, deletes): (i64, i64)) -> Self {
+        Self { inserts, deletes }
+    }
+}
+
 /// An input handle that deserializes records before pushing them to a
 /// stream.
 ///
@@ -244,23 +302,94 @@ pub trait CircuitCatalog: Send {
     /// Look up output stream handles by name.
     fn output_handles(&self, name: &str) -> Option<&OutputCollectionHandles>;
 
+    /// Names of all input streams.
+    fn input_collection_names(&self) -> Vec<String>;
+
+    /// Names of all output streams.
+    fn output_collection_names(&self) -> Vec<String>;
+
+    /// Look up the SQL schema of a table or view by name.
+    ///
+    /// Returns `None` if the catalog was created without the program schema.
+    fn relation_schema(&self, _name: &str) -> Option<&TableSchema> {
+        None
+    }
+
+    /// Describe all tables and views in the catalog.
+    ///
+    /// Tables are streams that accept input; every other output stream is a
+    /// view.
+    fn relations(&self) -> Vec<RelationDescr> {
+        let inputs: BTreeSet<String> = self.input_collection_names().into_iter().collect();
+        let mut names = inputs.clone();
+        names.extend(self.output_collection_names());
+
+        names
+            .into_iter()
+            .map(|name| {
+                let queries = self
+                    .output_handles(&name)
+                    .map(|handles| handles.supported_queries())
+                    .unwrap_or_default();
+                let columns = self.relation_schema(&name).map(|schema| {
+                    schema
+                        .fields
+                        .iter()
+                        .map(|field| ColumnDescr {
+                            name: field.name.clone(),
+                            sql_type: field.columntype.typ.clone(),
+                            nullable: field.columntype.nullable,
+                        })
+                        .collect()
+                });
+                RelationDescr {
+                    kind: if inputs.contains(&name) {
+                        RelationKind::Table
+                    } else {
+                        RelationKind::View
+                    },
+                    name,
+                    columns,
+                    queries,
+                }
+            })
+            .collect()
+    }
+
+    /// Look up per-column statistics of an output stream by name.
+    ///
+    /// Returns `None` if the stream does not exist or if statistics are not
+    /// being maintained for it.
+    fn column_statistics(&self, name: &str) -> Option<ViewStatistics> {
+        self.output_handles(name)
+            .and_then(|handles| handles.column_stats_handle.as_ref())
+            .filter(|handle| handle.is_enabled())
+            .map(|handle| handle.statistics())
+    }
+
     /// Look up output query handles by stream name and query type.
-    fn output_query_handles(&self, name: &str, query: OutputQuery) -> Option<OutputQueryHandles> {
+    ///
+    /// `session` selects the neighborhood session for
+    /// [neighborhood](`OutputQuery::Neighborhood`) queries and is ignored by
+    /// all other queries.
+    fn output_query_handles(
+        &self,
+        name: &str,
+        query: OutputQuery,
+        session: usize,
+    ) -> Option<OutputQueryHandles> {
         self.output_handles(name).map(|handles| match query {
             OutputQuery::Table => OutputQueryHandles {
                 delta: Some(handles.delta_handle.fork()),
                 snapshot: None,
             },
-            OutputQuery::Neighborhood => OutputQueryHandles {
-                delta: handles
-                    .neighborhood_handle
-                    .as_ref()
-                    .map(|handle| handle.fork()),
-                snapshot: handles
-                    .neighborhood_snapshot_handle
-                    .as_ref()
-                    .map(|handle| handle.fork()),
-            },
+            OutputQuery::Neighborhood => {
+                let session = handles.neighborhood_sessions.get(session);
+                OutputQueryHandles {
+                    delta: session.map(|session| session.delta_handle.fork()),
+                    snapshot: session.map(|session| session.snapshot_handle.fork()),
+                }
+            }
             OutputQuery::Quantiles => OutputQueryHandles {
                 delta: None,
                 snapshot: handles
@@ -268,6 +397,10 @@ pub trait CircuitCatalog: Send {
                     .as_ref()
                     .map(|handle| handle.fork()),
             },
+            OutputQuery::Sample => OutputQueryHandles {
+                delta: None,
+                snapshot: handles.sample_handle.as_ref().map(|handle| handle.fork()),
+            },
         })
     }
 }
@@ -279,6 +412,7 @@ pub trait CircuitCatalog: Send {
 pub struct Catalog {
     pub(crate) input_collection_handles: BTreeMap<String, Box<dyn DeCollectionHandle>>,
     pub(crate) output_batch_handles: BTreeMap<String, OutputCollectionHandles>,
+    pub(crate) schema: Option<ProgramSchema>,
 }
 
 impl Default for Catalog {
@@ -292,9 +426,25 @@ impl Catalog {
         Self {
             input_collection_handles: BTreeMap::new(),
             output_batch_handles: BTreeMap::new(),
+            schema: None,
         }
     }
 
+    /// Attach the SQL schema of the program to the catalog, so that the
+    /// catalog can describe the columns of its tables and views.
+    pub fn set_schema(&mut self, schema: ProgramSchema) {
+        self.schema = Some(schema);
+    }
+
+    /// Like [`Self::set_schema`], but takes the schema in the JSON format
+    /// generated by the SQL compiler.
+    pub fn set_schema_json(&mut self, schema: &str) -> Result<(), ControllerError> {
+        let schema = serde_json::from_str(schema)
+            .map_err(|e| ControllerError::schema_parse_error(&e.to_string()))?;
+        self.set_schema(schema);
+        Ok(())
+    }
+
     pub fn register_input_collection_handle<H>(&mut self, name: &str, handle: H)
     where
         H: DeCollectionHandle + 'static,
@@ -316,13 +466,68 @@ impl CircuitCatalog for Catalog {
     fn output_handles(&self, name: &str) -> Option<&OutputCollectionHandles> {
         self.output_batch_handles.get(name)
     }
+
+    fn input_collection_names(&self) -> Vec<String> {
+        self.input_collection_handles.keys().cloned().collect()
+    }
+
+    fn output_collection_names(&self) -> Vec<String> {
+        self.output_batch_handles.keys().cloned().collect()
+    }
+
+    /// Look up the SQL schema of a table or view by name.
+    ///
+    /// The SQL compiler may use a different case for relation names in the
+    /// schema and in the circuit, so we fall back to case-insensitive
+    /// matching.
+    fn relation_schema(&self, name: &str) -> Option<&TableSchema> {
+        let schema = self.schema.as_ref()?;
+        let relations = || schema.inputs.iter().chain(schema.outputs.iter());
+        relations()
+            .find(|relation| relation.name == name)
+            .or_else(|| relations().find(|relation| relation.name.eq_ignore_ascii_case(name)))
+    }
 }
 
-/// A set of stream handles associated with each output collection.
-pub struct OutputCollectionHandles {
-    /// A stream of changes to the collection.
-    pub delta_handle: Box<dyn SerCollectionHandle>,
+/// Type of a relation in the catalog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RelationKind {
+    /// SQL table, which accepts input.
+    Table,
+    /// SQL view.
+    View,
+}
 
+/// Description of a table or view, returned by the `/catalog` endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RelationDescr {
+    /// Table or view name.
+    pub name: String,
+    pub kind: RelationKind,
+    /// Columns of the relation, or `None` if the program schema is not
+    /// available.
+    pub columns: Option<Vec<ColumnDescr>>,
+    /// Queries over the relation that can be submitted to the `/egress`
+    /// endpoint.  Empty for tables that are not also outputs of the
+    /// circuit.
+    pub queries: Vec<OutputQuery>,
+}
+
+/// Description of a column of a table or view.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ColumnDescr {
+    /// Column name.
+    pub name: String,
+    /// SQL type of the column, e.g., `VARCHAR` or `BIGINT`.
+    #[serde(rename = "type")]
+    pub sql_type: String,
+    /// Does the column accept `NULL` values?
+    pub nullable: bool,
+}
+
+/// Stream handles of a neighborhood session of an output collection.
+pub struct NeighborhoodHandles {
     /// Input stream used to submit neighborhood queries.
     ///
     /// The stream carries values of type `(bool, Option<NeighborhoodDescr<K,
@@ -332,22 +537,20 @@ pub struct OutputCollectionHandles {
     /// The first component of the tuple is the `reset` flag, which instructs
     /// the circuit to start executing the neighborhood query specified in the
     /// second component of the tuple.  The outputs of the neighborhood query
-    /// are emitted to [`neighborhood_handle`](`Self::neighborhood_handle`)
-    /// and
-    /// [`neighborhood_snapshot_handle`](`Self::neighborhood_snapshot_handle`)
-    /// streams.  When the flag is `false`, this input is ignored.
+    /// are emitted to [`delta_handle`](`Self::delta_handle`) and
+    /// [`snapshot_handle`](`Self::snapshot_handle`) streams.  When the flag
+    /// is `false`, this input is ignored.
     ///
     /// In more detail, the circuit handles inputs written to this stream as
     /// follows:
     ///
     /// * `(true, Some(descr))` - Start monitoring the specified descriptor. The
     ///   circuit will output a complete snapshot of the neighborhood to the
-    ///   [`neighborhood_snapshot_handle`](`Self::neighborhood_snapshot_handle`)
-    ///   sstream at the end of the current clock cycle.  The
-    ///   [`neighborhood_handle`](`Self::neighborhood_handle`) stream will
-    ///   output the difference between the previous and the new neighborhoods
-    ///   at the end of the current clock cycle and will contain changes to the
-    ///   new neighborhood going forward.
+    ///   [`snapshot_handle`](`Self::snapshot_handle`) stream at the end of
+    ///   the current clock cycle.  The [`delta_handle`](`Self::delta_handle`)
+    ///   stream will output the difference between the previous and the new
+    ///   neighborhoods at the end of the current clock cycle and will contain
+    ///   changes to the new neighborhood going forward.
     ///
     /// * `(true, None)` - Stop executing the neighborhood query.  This is
     ///   equivalent to writing `(true, Some(descr))`, where `descr` specifies
@@ -355,17 +558,31 @@ pub struct OutputCollectionHandles {
     ///
     /// * `(false, _)` - This is a no-op. The circuit will continue monitoring
     ///   the previously specified neighborhood if any.  Nothing is written to
-    ///   the [`neighborhood_snapshot_handle`](`Self::neighborhood_snapshot_handle`)
-    ///   stream.
-    pub neighborhood_descr_handle: Option<Box<dyn ErasedDeScalarHandle>>,
+    ///   the [`snapshot_handle`](`Self::snapshot_handle`) stream.
+    pub descr_handle: Box<dyn ErasedDeScalarHandle>,
 
     /// A stream of changes to the neighborhood, computed using the
     /// [`Stream::neighborhood`] operator.
-    pub neighborhood_handle: Option<Box<dyn SerCollectionHandle>>,
+    pub delta_handle: Box<dyn SerCollectionHandle>,
 
-    /// A stream that contains the full snapshot of the neighborhood.  Only produces
-    /// an output whenever the `neighborhood_descr_handle` input is set to `Some(..)`.
-    pub neighborhood_snapshot_handle: Option<Box<dyn SerCollectionHandle>>,
+    /// A stream that contains the full snapshot of the neighborhood.  Only
+    /// produces an output whenever the `descr_handle` input is set to
+    /// `Some(..)`.
+    pub snapshot_handle: Box<dyn SerCollectionHandle>,
+}
+
+/// A set of stream handles associated with each output collection.
+pub struct OutputCollectionHandles {
+    /// A stream of changes to the collection.
+    pub delta_handle: Box<dyn SerCollectionHandle>,
+
+    /// Neighborhood sessions of the collection.
+    ///
+    /// Each session evaluates an independent neighborhood query, so that
+    /// several clients can monitor different neighborhoods of the same
+    /// collection concurrently.  Empty if the circuit doesn't support
+    /// neighborhood queries.
+    pub neighborhood_sessions: Vec<NeighborhoodHandles>,
 
     /// Input stream used to submit the quantiles query.
     ///
@@ -382,13 +599,52 @@ pub struct OutputCollectionHandles {
     /// outputs up to `N` quantiles of the input collection, computed using
     /// the [`Stream::stream_key_quantiles`] operator.
     pub quantiles_handle: Option<Box<dyn SerCollectionHandle>>,
+
+    /// Input stream used to submit the sample query.
+    ///
+    /// The value in the stream specifies the size of the sample to
+    /// output.  When greater than zero, it triggers sampling of the
+    /// collection.  The result is output to the
+    /// [`sample_handle`](`Self::sample_handle`) stream at the end of
+    /// the current clock cycle.
+    pub sample_size_handle: Option<InputHandle<usize>>,
+
+    /// Sample stream.
+    ///
+    /// When the `sample_size_handle` input is set to `N`, `N>0`, this stream
+    /// outputs a random sample of up to `N` records of the collection,
+    /// maintained by the [`Stream::sample_reservoir`] operator.
+    pub sample_handle: Option<Box<dyn SerCollectionHandle>>,
+
+    /// Per-column statistics of the collection.
+    ///
+    /// Statistics are only maintained after being explicitly enabled with
+    /// [`ColumnStatsHandle::enable`].
+    pub column_stats_handle: Option<ColumnStatsHandle>,
+}
+
+impl OutputCollectionHandles {
+    /// Queries supported by the circuit for this collection.
+    pub fn supported_queries(&self) -> Vec<OutputQuery> {
+        let mut queries = vec![OutputQuery::Table];
+        if !self.neighborhood_sessions.is_empty() {
+            queries.push(OutputQuery::Neighborhood);
+        }
+        if self.num_quantiles_handle.is_some() && self.quantiles_handle.is_some() {
+            queries.push(OutputQuery::Quantiles);
+        }
+        if self.sample_size_handle.is_some() && self.sample_handle.is_some() {
+            queries.push(OutputQuery::Sample);
+        }
+        queries
+    }
 }
 
 /// A query over an output stream.
 ///
 /// We currently do not support ad hoc queries.  Instead the client can use
-/// three pre-defined queries to inspect the contents of a table or view.
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, ToSchema, Ord)]
+/// four pre-defined queries to inspect the contents of a table or view.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, ToSchema, Ord)]
 pub enum OutputQuery {
     /// Query the entire contents of the table (similar to `SELECT * FROM`).
     #[serde(rename = "table")]
@@ -399,6 +655,10 @@ pub enum OutputQuery {
     /// Quantiles query (see [`Stream::stream_key_quantiles`](`dbsp::Stream::stream_key_quantiles`)).
     #[serde(rename = "quantiles")]
     Quantiles,
+    /// Random sample of the contents of the table (see
+    /// [`Stream::sample_reservoir`](`dbsp::Stream::sample_reservoir`)).
+    #[serde(rename = "sample")]
+    Sample,
 }
 
 impl Default for OutputQuery {
@@ -412,7 +672,8 @@ impl Default for OutputQuery {
 /// Stores the result of a a [query](`OutputQuery`) as a pair of streams:
 /// a stream of changes and a snapshot, i.e., the integral, of all previous
 /// changes.  Not all queries return both streams, e.g., the
-/// [quantiles](`OutputQuery::Quantiles`) query only returns a snapshot,
+/// [quantiles](`OutputQuery::Quantiles`) and [sample](`OutputQuery::Sample`)
+/// queries only return a snapshot,
 /// while the [table](`OutputQuery::Table`) query currently only returns the
 /// delta stream; therefore the stream handles are wrapped in `Option`s.
 ///
@@ -1,5 +1,5 @@
 use crate::ControllerError;
-use dbsp::DBSPHandle;
+use dbsp::{profile::OperatorProfile, DBSPHandle};
 use std::path::PathBuf;
 
 /// Trait that captures common behavior of static and JIT-compiled circuits.
@@ -10,6 +10,29 @@ pub trait DbspCircuitHandle {
 
     fn dump_profile(&mut self, dir_path: &str) -> Result<PathBuf, ControllerError>;
 
+    /// Retrieve the profile of every operator, one vector per worker.
+    fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, ControllerError>;
+
+    /// Serialize the state of the circuit for a checkpoint.
+    ///
+    /// Invoked by the controller between steps.  The default implementation
+    /// fails, indicating that the circuit does not support checkpointing.
+    fn checkpoint(&mut self) -> Result<Vec<u8>, ControllerError> {
+        Err(ControllerError::checkpoint_error(
+            &"the circuit does not support checkpointing",
+        ))
+    }
+
+    /// Restore the state of the circuit from `state` previously returned by
+    /// [`Self::checkpoint`].
+    ///
+    /// Invoked by the controller before the circuit performs its first step.
+    fn restore(&mut self, _state: &[u8]) -> Result<(), ControllerError> {
+        Err(ControllerError::checkpoint_error(
+            &"the circuit does not support checkpointing",
+        ))
+    }
+
     fn kill(self: Box<Self>) -> std::thread::Result<()>;
 }
 
@@ -26,6 +49,18 @@ impl DbspCircuitHandle for DBSPHandle {
         DBSPHandle::dump_profile(self, dir_path).map_err(ControllerError::dbsp_error)
     }
 
+    fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, ControllerError> {
+        DBSPHandle::retrieve_profile(self).map_err(ControllerError::dbsp_error)
+    }
+
+    fn checkpoint(&mut self) -> Result<Vec<u8>, ControllerError> {
+        DBSPHandle::checkpoint(self).map_err(ControllerError::dbsp_error)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), ControllerError> {
+        DBSPHandle::restore(self, state).map_err(ControllerError::dbsp_error)
+    }
+
     fn kill(self: Box<Self>) -> std::thread::Result<()> {
         DBSPHandle::kill(*self)
     }
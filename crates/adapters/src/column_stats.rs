@@ -0,0 +1,344 @@
+//! Lightweight per-column statistics of tables and views.
+//!
+//! Statistics are maintained incrementally by the circuit for tables and
+//! views listed in [`RuntimeConfig::column_statistics`](`crate::RuntimeConfig::column_statistics`).
+//! For each such stream, we track the number of records and, for each
+//! column, the number of `NULL` values, the smallest and largest values
+//! observed, and an estimate of the number of distinct values computed
+//! using HyperLogLog.
+//!
+//! The minimum, maximum, and distinct value estimate only account for
+//! inserted values: deleting a record does not shrink them.  Record and
+//! `NULL` counts take both insertions and deletions into account.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::{
+    cmp::{max, Ordering},
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+};
+
+/// Number of bits of the hash used to select a HyperLogLog register.
+///
+/// `2^12` one-byte registers per column give a standard error of about 1.6%.
+const HLL_PRECISION: u32 = 12;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// Statistics of a table or view.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ViewStatistics {
+    /// Total number of records in the collection.
+    pub num_records: i64,
+
+    /// Statistics for individual columns.
+    pub columns: Vec<ColumnStatistics>,
+}
+
+/// Statistics of a single column.
+#[derive(Clone, Debug, Serialize)]
+pub struct ColumnStatistics {
+    /// Column name.
+    pub name: String,
+
+    /// Fraction of records where this column is `NULL`.
+    pub null_fraction: f64,
+
+    /// Smallest non-`NULL` value observed in this column.
+    pub min: Option<JsonValue>,
+
+    /// Largest non-`NULL` value observed in this column.
+    pub max: Option<JsonValue>,
+
+    /// Estimated number of distinct non-`NULL` values in this column.
+    pub distinct_estimate: u64,
+}
+
+/// Handle shared between the circuit, which updates statistics on every
+/// step, and the catalog, which reports them.
+///
+/// Statistics collection is disabled by default, in which case the circuit
+/// does not do any work to maintain them.
+#[derive(Clone, Default)]
+pub struct ColumnStatsHandle {
+    inner: Arc<ColumnStatsInner>,
+}
+
+#[derive(Default)]
+struct ColumnStatsInner {
+    enabled: AtomicBool,
+    state: Mutex<ViewState>,
+}
+
+impl ColumnStatsHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start maintaining statistics for this collection.
+    ///
+    /// Only changes received after this call are accounted for, so this
+    /// should be called before feeding any data to the circuit.
+    pub fn enable(&self) {
+        self.inner.enabled.store(true, AtomicOrdering::Release);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(AtomicOrdering::Acquire)
+    }
+
+    /// Update statistics with a record serialized as JSON and its weight.
+    ///
+    /// Records that serialize as JSON objects are split into columns by
+    /// field name.  Records that serialize as arrays (i.e., tuples) use
+    /// positional column names.  Any other value is treated as a single
+    /// column.
+    pub fn update<I>(&self, records: I)
+    where
+        I: IntoIterator<Item = (JsonValue, i64)>,
+    {
+        let mut state = self.inner.state.lock().unwrap();
+        for (record, weight) in records {
+            state.update(record, weight);
+        }
+    }
+
+    /// Returns a snapshot of current statistics.
+    pub fn statistics(&self) -> ViewStatistics {
+        self.inner.state.lock().unwrap().statistics()
+    }
+}
+
+#[derive(Default)]
+struct ViewState {
+    num_records: i64,
+    columns: Vec<ColumnState>,
+}
+
+impl ViewState {
+    fn update(&mut self, record: JsonValue, weight: i64) {
+        self.num_records += weight;
+
+        match record {
+            JsonValue::Object(fields) => {
+                for (i, (name, value)) in fields.into_iter().enumerate() {
+                    self.column(i, &name).update(&value, weight);
+                }
+            }
+            JsonValue::Array(fields) => {
+                for (i, value) in fields.into_iter().enumerate() {
+                    self.column(i, &i.to_string()).update(&value, weight);
+                }
+            }
+            value => self.column(0, "0").update(&value, weight),
+        }
+    }
+
+    /// Find column by name.  Records of the same type list columns in the
+    /// same order, so we first check the expected position.
+    fn column(&mut self, index: usize, name: &str) -> &mut ColumnState {
+        let index = if self
+            .columns
+            .get(index)
+            .map(|column| column.name == name)
+            .unwrap_or(false)
+        {
+            index
+        } else if let Some(index) = self.columns.iter().position(|column| column.name == name) {
+            index
+        } else {
+            self.columns.push(ColumnState::new(name));
+            self.columns.len() - 1
+        };
+
+        &mut self.columns[index]
+    }
+
+    fn statistics(&self) -> ViewStatistics {
+        ViewStatistics {
+            num_records: self.num_records,
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.statistics(self.num_records))
+                .collect(),
+        }
+    }
+}
+
+struct ColumnState {
+    name: String,
+    num_nulls: i64,
+    min: Option<JsonValue>,
+    max: Option<JsonValue>,
+    distinct: HyperLogLog,
+}
+
+impl ColumnState {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            num_nulls: 0,
+            min: None,
+            max: None,
+            distinct: HyperLogLog::new(),
+        }
+    }
+
+    fn update(&mut self, value: &JsonValue, weight: i64) {
+        if value.is_null() {
+            self.num_nulls += weight;
+            return;
+        }
+
+        if weight <= 0 {
+            return;
+        }
+
+        if self
+            .min
+            .as_ref()
+            .map(|min| compare_values(value, min) == Some(Ordering::Less))
+            .unwrap_or(true)
+        {
+            self.min = Some(value.clone());
+        }
+        if self
+            .max
+            .as_ref()
+            .map(|max| compare_values(value, max) == Some(Ordering::Greater))
+            .unwrap_or(true)
+        {
+            self.max = Some(value.clone());
+        }
+        self.distinct.insert(&value.to_string());
+    }
+
+    fn statistics(&self, num_records: i64) -> ColumnStatistics {
+        ColumnStatistics {
+            name: self.name.clone(),
+            null_fraction: if num_records > 0 {
+                self.num_nulls as f64 / num_records as f64
+            } else {
+                0.0
+            },
+            min: self.min.clone(),
+            max: self.max.clone(),
+            distinct_estimate: self.distinct.estimate(),
+        }
+    }
+}
+
+/// Compare two JSON values of the same type.  Returns `None` for values of
+/// different types and for objects and arrays, which don't have a natural
+/// order.
+///
+/// Strings are compared lexicographically, which also orders dates and
+/// timestamps correctly.
+pub(crate) fn compare_values(a: &JsonValue, b: &JsonValue) -> Option<Ordering> {
+    match (a, b) {
+        (JsonValue::Bool(a), JsonValue::Bool(b)) => Some(a.cmp(b)),
+        (JsonValue::Number(a), JsonValue::Number(b)) => {
+            if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+                Some(a.cmp(&b))
+            } else {
+                a.as_f64()?.partial_cmp(&b.as_f64()?)
+            }
+        }
+        (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// HyperLogLog distinct count estimator.
+struct HyperLogLog {
+    registers: Box<[u8]>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS].into_boxed_slice(),
+        }
+    }
+
+    fn insert<T>(&mut self, value: &T)
+    where
+        T: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // Position of the first set bit in the remaining bits; the sentinel
+        // bit bounds the rank when all remaining bits are zero.
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+        self.registers[index] = max(self.registers[index], rank as u8);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for register in self.registers.iter() {
+            sum += 1.0 / (1u64 << register) as f64;
+            if *register == 0 {
+                zeros += 1;
+            }
+        }
+
+        let estimate = alpha * m * m / sum;
+        if estimate <= 2.5 * m && zeros > 0 {
+            // Use linear counting for small cardinalities.
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColumnStatsHandle;
+    use serde_json::json;
+
+    #[test]
+    fn column_statistics() {
+        let handle = ColumnStatsHandle::new();
+        handle.enable();
+
+        handle.update((0..1000).map(|i| {
+            (
+                json!({"id": i, "name": if i % 4 == 0 { json!(null) } else { json!(format!("name{}", i % 10)) }}),
+                1,
+            )
+        }));
+        // Delete some of the records with `NULL` names.
+        handle.update((0..100).map(|i| (json!({"id": i * 4, "name": null}), -1)));
+
+        let stats = handle.statistics();
+        assert_eq!(stats.num_records, 900);
+        assert_eq!(stats.columns.len(), 2);
+
+        let id = &stats.columns[0];
+        assert_eq!(id.name, "id");
+        assert_eq!(id.null_fraction, 0.0);
+        assert_eq!(id.min, Some(json!(0)));
+        assert_eq!(id.max, Some(json!(999)));
+        assert!((950..=1050).contains(&id.distinct_estimate));
+
+        let name = &stats.columns[1];
+        assert_eq!(name.name, "name");
+        assert_eq!(name.null_fraction, 150.0 / 900.0);
+        assert_eq!(name.min, Some(json!("name0")));
+        assert_eq!(name.max, Some(json!("name9")));
+        assert!((9..=11).contains(&name.distinct_estimate));
+    }
+}
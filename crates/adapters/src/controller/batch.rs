@@ -0,0 +1,214 @@
+//! All-or-nothing ingestion of input chunks.
+//!
+//! Implements the `abort_batch` [`ErrorPolicy`](`super::ErrorPolicy`) for
+//! input endpoints.  Parsers push records to the circuit as they go, flushing
+//! the input stream whenever it's convenient, so by the time the parser
+//! reports an error, valid records from the same chunk may already be queued
+//! for the next step.  [`BatchCollectionHandle`] wraps the input collection
+//! handle of the endpoint and defers all flushes until the input probe
+//! decides whether to [commit](`InputBatch::commit`) or
+//! [abort](`InputBatch::abort`) the chunk.
+
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    ControllerError, DeCollectionHandle,
+};
+use anyhow::Result as AnyResult;
+use std::sync::{Arc, Mutex};
+
+type SharedStream = Arc<Mutex<Box<dyn DeCollectionStream>>>;
+
+/// Input streams used by a parser, whose buffered updates are flushed or
+/// discarded together.
+///
+/// A parser may use more than one stream, e.g., the `auto` format forks a
+/// stream for each format it supports.
+#[derive(Default)]
+pub(crate) struct InputBatch {
+    streams: Mutex<Vec<SharedStream>>,
+
+    /// Streams forked from `streams`, i.e., the batch of the parser returned
+    /// by the last `Parser::fork` call.
+    forked: Mutex<Option<Arc<InputBatch>>>,
+}
+
+impl InputBatch {
+    fn add_stream(
+        self: &Arc<Self>,
+        stream: Box<dyn DeCollectionStream>,
+    ) -> Box<dyn DeCollectionStream> {
+        let stream = Arc::new(Mutex::new(stream));
+        self.streams.lock().unwrap().push(stream.clone());
+        Box::new(BatchStream {
+            stream,
+            batch: self.clone(),
+        })
+    }
+
+    /// Push all updates parsed since the last `commit` or `abort` to the
+    /// circuit.
+    pub(crate) fn commit(&self) {
+        for stream in self.streams.lock().unwrap().iter() {
+            stream.lock().unwrap().flush();
+        }
+    }
+
+    /// Discard all updates parsed since the last `commit` or `abort`.
+    pub(crate) fn abort(&self) {
+        for stream in self.streams.lock().unwrap().iter() {
+            stream.lock().unwrap().clear_buffer();
+        }
+    }
+
+    /// Returns the batch of the parser forked from the parser that owns
+    /// `self`.
+    ///
+    /// Must be called right after `Parser::fork`; forking the same parser
+    /// concurrently from multiple threads is not supported.
+    pub(crate) fn take_fork(&self) -> Arc<InputBatch> {
+        self.forked.lock().unwrap().take().unwrap_or_default()
+    }
+}
+
+/// Input collection handle whose deserializers buffer updates until their
+/// [`InputBatch`] is committed.
+pub(crate) struct BatchCollectionHandle<'a> {
+    inner: &'a dyn DeCollectionHandle,
+    batch: Arc<InputBatch>,
+}
+
+impl<'a> BatchCollectionHandle<'a> {
+    pub(crate) fn new(inner: &'a dyn DeCollectionHandle) -> Self {
+        Self {
+            inner,
+            batch: Default::default(),
+        }
+    }
+
+    /// The batch that contains all deserializers created by this handle.
+    pub(crate) fn batch(&self) -> Arc<InputBatch> {
+        self.batch.clone()
+    }
+}
+
+impl<'a> DeCollectionHandle for BatchCollectionHandle<'a> {
+    fn configure_deserializer(
+        &self,
+        record_format: RecordFormat,
+    ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
+        let stream = self.inner.configure_deserializer(record_format)?;
+        Ok(self.batch.add_stream(stream))
+    }
+}
+
+/// Deserializer that ignores `flush` calls from the parser.  Updates are
+/// flushed by [`InputBatch::commit`] instead.
+struct BatchStream {
+    stream: SharedStream,
+    batch: Arc<InputBatch>,
+}
+
+impl DeCollectionStream for BatchStream {
+    fn insert(&mut self, data: &[u8]) -> AnyResult<()> {
+        self.stream.lock().unwrap().insert(data)
+    }
+
+    fn delete(&mut self, data: &[u8]) -> AnyResult<()> {
+        self.stream.lock().unwrap().delete(data)
+    }
+
+    fn reserve(&mut self, reservation: usize) {
+        self.stream.lock().unwrap().reserve(reservation)
+    }
+
+    fn flush(&mut self) {}
+
+    fn clear_buffer(&mut self) {
+        self.stream.lock().unwrap().clear_buffer()
+    }
+
+    fn fork(&self) -> Box<dyn DeCollectionStream> {
+        let stream = self.stream.lock().unwrap().fork();
+        self.batch
+            .forked
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default)
+            .add_stream(stream)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BatchCollectionHandle;
+    use crate::{
+        format::{InputFormat, JsonParserConfig, JsonUpdateFormat},
+        test::{MockDeZSet, TestStruct},
+    };
+
+    fn record(id: u32) -> (TestStruct, bool) {
+        (
+            TestStruct {
+                id,
+                b: true,
+                i: None,
+                s: "foo".to_string(),
+            },
+            true,
+        )
+    }
+
+    #[test]
+    fn test_input_batch() {
+        let input_handle = <MockDeZSet<TestStruct>>::new();
+        let handle = BatchCollectionHandle::new(&input_handle);
+        let batch = handle.batch();
+
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                &handle,
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap();
+
+        // Parsed records are not flushed until the batch is committed.
+        let (num_records, errors) = parser.input_chunk(
+            br#"{"id": 1, "b": true, "i": null, "s": "foo"}
+{"id": 2, "b": true, "i": null, "s": "foo"}"#,
+        );
+        assert_eq!(num_records, 2);
+        assert!(errors.is_empty());
+        assert!(input_handle.state().flushed.is_empty());
+
+        batch.commit();
+        assert_eq!(input_handle.state().flushed, vec![record(1), record(2)]);
+
+        // Valid records from an aborted batch are discarded.
+        let (_, errors) = parser.input_chunk(
+            br#"{"id": 3, "b": true, "i": null, "s": "foo"}
+{"id": "bar"}"#,
+        );
+        assert_eq!(errors.len(), 1);
+        batch.abort();
+        batch.commit();
+        assert_eq!(input_handle.state().flushed, vec![record(1), record(2)]);
+
+        // A forked parser gets its own batch.
+        let mut fork = parser.fork();
+        let fork_batch = batch.take_fork();
+        fork.input_chunk(br#"{"id": 4, "b": true, "i": null, "s": "foo"}"#);
+        batch.commit();
+        assert_eq!(input_handle.state().flushed.len(), 2);
+        fork_batch.commit();
+        assert_eq!(
+            input_handle.state().flushed,
+            vec![record(1), record(2), record(4)]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Checkpointing controller state.
+//!
+//! A checkpoint consists of the serialized state of the circuit and the
+//! positions of input endpoints in their input streams, captured between
+//! steps of the circuit.  When the pipeline restarts, the controller restores
+//! the circuit from the latest checkpoint and asks each endpoint to resume
+//! reading from its checkpointed position, so that inputs processed before
+//! the checkpoint are not processed again.
+//!
+//! Checkpoints are stored in a local directory or an object store.  Each
+//! checkpoint is written as two objects: `state-<seq>`, which holds the
+//! circuit state, followed by the `checkpoint.json` manifest, which points
+//! to the state object and stores endpoint positions.  The manifest is only
+//! replaced after the state object has been written, and both local and
+//! object store writes replace objects atomically, so a failure while
+//! taking a checkpoint leaves the previous checkpoint intact.
+
+use crate::transport::open_object_store;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use log::info;
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Runtime;
+use utoipa::ToSchema;
+
+/// Name of the checkpoint manifest object.
+const MANIFEST: &str = "checkpoint.json";
+
+/// Checkpoint configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CheckpointConfig {
+    /// Where to store checkpoints: either a path to a local directory or an
+    /// object store URL of the form `s3://<bucket>/<prefix>`,
+    /// `gs://<bucket>/<prefix>`, or `az://<container>/<prefix>`.  Object
+    /// store credentials are read from the environment.
+    pub location: String,
+
+    /// Take a checkpoint after a step of the circuit when at least
+    /// `interval_secs` seconds have passed since the previous checkpoint.
+    /// When not specified, checkpoints are only taken on request, via the
+    /// `/checkpoint` endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+}
+
+/// Contents of the checkpoint manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    /// Sequence number of the checkpoint.
+    seq: u64,
+
+    /// Positions of input endpoints, by endpoint name.
+    inputs: BTreeMap<String, JsonValue>,
+}
+
+/// Controller state restored from a checkpoint.
+pub(crate) struct Checkpoint {
+    /// Sequence number of the checkpoint.
+    pub seq: u64,
+
+    /// Serialized circuit state.
+    pub state: Vec<u8>,
+
+    /// Positions of input endpoints, by endpoint name.
+    pub inputs: BTreeMap<String, JsonValue>,
+}
+
+/// Writes and reads checkpoints.
+pub(crate) struct Checkpointer {
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+    runtime: Runtime,
+    interval: Option<Duration>,
+
+    /// Sequence number of the latest checkpoint, 0 if there is none.
+    seq: u64,
+
+    /// Time the latest checkpoint was taken or restored.
+    last_checkpoint: Instant,
+}
+
+impl Checkpointer {
+    pub fn new(config: &CheckpointConfig) -> AnyResult<Self> {
+        let (store, prefix) = open_object_store(&config.location)
+            .map_err(|e| anyhow!("invalid checkpoint location: {e}"))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            store,
+            prefix,
+            runtime,
+            interval: config.interval_secs.map(Duration::from_secs),
+            seq: 0,
+            last_checkpoint: Instant::now(),
+        })
+    }
+
+    fn path(&self, name: &str) -> ObjectPath {
+        self.prefix.child(name)
+    }
+
+    fn state_path(&self, seq: u64) -> ObjectPath {
+        self.path(&format!("state-{seq}"))
+    }
+
+    /// Returns `true` if a periodic checkpoint is due.
+    pub fn due(&self) -> bool {
+        self.interval
+            .map(|interval| self.last_checkpoint.elapsed() >= interval)
+            .unwrap_or(false)
+    }
+
+    /// Read the latest checkpoint, if any.
+    pub fn load(&mut self) -> AnyResult<Option<Checkpoint>> {
+        let manifest = match self.runtime.block_on(self.store.get(&self.path(MANIFEST))) {
+            Ok(result) => self.runtime.block_on(result.bytes())?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => bail!("failed to read checkpoint manifest: {e}"),
+        };
+        let manifest: Manifest = serde_json::from_slice(&manifest)
+            .map_err(|e| anyhow!("invalid checkpoint manifest: {e}"))?;
+
+        let state_path = self.state_path(manifest.seq);
+        let state = self
+            .runtime
+            .block_on(async { self.store.get(&state_path).await?.bytes().await })
+            .map_err(|e| anyhow!("failed to read checkpoint state '{state_path}': {e}"))?;
+
+        self.seq = manifest.seq;
+        self.last_checkpoint = Instant::now();
+
+        Ok(Some(Checkpoint {
+            seq: manifest.seq,
+            state: state.to_vec(),
+            inputs: manifest.inputs,
+        }))
+    }
+
+    /// Write a new checkpoint, returning its sequence number.
+    pub fn save(&mut self, state: Vec<u8>, inputs: BTreeMap<String, JsonValue>) -> AnyResult<u64> {
+        let seq = self.seq + 1;
+        let manifest = serde_json::to_vec(&Manifest { seq, inputs })?;
+
+        let state_path = self.state_path(seq);
+        self.runtime
+            .block_on(self.store.put(&state_path, state.into()))
+            .map_err(|e| anyhow!("failed to write checkpoint state '{state_path}': {e}"))?;
+        self.runtime
+            .block_on(self.store.put(&self.path(MANIFEST), manifest.into()))
+            .map_err(|e| anyhow!("failed to write checkpoint manifest: {e}"))?;
+
+        if self.seq > 0 {
+            let old_path = self.state_path(self.seq);
+            if let Err(e) = self.runtime.block_on(self.store.delete(&old_path)) {
+                // Not fatal: the new checkpoint is already in place.
+                info!("failed to delete old checkpoint state '{old_path}': {e}");
+            }
+        }
+
+        self.seq = seq;
+        self.last_checkpoint = Instant::now();
+        Ok(seq)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CheckpointConfig, Checkpointer};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let config = CheckpointConfig {
+            location: dir.path().join("checkpoints").display().to_string(),
+            interval_secs: None,
+        };
+
+        let mut checkpointer = Checkpointer::new(&config).unwrap();
+        assert!(checkpointer.load().unwrap().is_none());
+        assert!(!checkpointer.due());
+
+        let inputs = BTreeMap::from([("input1".to_string(), json!({"offset": 10}))]);
+        assert_eq!(
+            checkpointer
+                .save(b"state1".to_vec(), inputs.clone())
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            checkpointer
+                .save(b"state2".to_vec(), inputs.clone())
+                .unwrap(),
+            2
+        );
+
+        // Only the latest state is retained.
+        assert!(!dir.path().join("checkpoints/state-1").exists());
+
+        let mut checkpointer = Checkpointer::new(&config).unwrap();
+        let checkpoint = checkpointer.load().unwrap().unwrap();
+        assert_eq!(checkpoint.seq, 2);
+        assert_eq!(checkpoint.state, b"state2");
+        assert_eq!(checkpoint.inputs, inputs);
+
+        // Sequence numbers continue after the restored checkpoint.
+        assert_eq!(checkpointer.save(Vec::new(), inputs).unwrap(), 3);
+    }
+
+    #[test]
+    fn invalid_location() {
+        let config = CheckpointConfig {
+            location: "s3:///prefix".to_string(),
+            interval_secs: None,
+        };
+        assert!(Checkpointer::new(&config).is_err());
+    }
+}
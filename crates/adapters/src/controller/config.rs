@@ -5,9 +5,14 @@
 //! endpoint configs.  We represent these configs as opaque yaml values, so
 //! that the entire configuration tree can be deserialized from a yaml file.
 
+use super::{
+    checkpoint::CheckpointConfig, replay::ReplayConfig, retry::RetryConfig,
+    telemetry::TracingConfig,
+};
 use crate::{ControllerError, InputFormat, OutputFormat, OutputQuery};
 use actix_web::HttpRequest;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
 use std::{borrow::Cow, collections::BTreeMap};
 use utoipa::ToSchema;
@@ -69,6 +74,100 @@ pub struct RuntimeConfig {
     /// get buffered by the controller, defaults to 0.
     #[serde(default)]
     pub max_buffering_delay_usecs: u64,
+
+    /// Tables and views for which to maintain per-column statistics.
+    ///
+    /// For each listed table or view, the pipeline tracks the fraction of
+    /// `NULL` values, the smallest and largest value, and an estimate of
+    /// the number of distinct values in each column.  Statistics are
+    /// available via the `/views/{view_name}/column_stats` endpoint.
+    #[serde(default)]
+    pub column_statistics: Vec<String>,
+
+    /// Checkpoint the state of the pipeline to a local directory or an object
+    /// store, and restore it from the latest checkpoint on startup.
+    ///
+    /// Checkpointing is disabled by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<CheckpointConfig>,
+
+    /// Export OpenTelemetry spans for circuit steps and input and output
+    /// batches to an OTLP collector.
+    ///
+    /// Tracing is disabled by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracing: Option<TracingConfig>,
+
+    /// How endpoints react to data errors, i.e., input records that fail to
+    /// parse, output records that fail to encode, and transport errors.
+    ///
+    /// Applies to all endpoints that don't override it with their own
+    /// `on_error` setting.  The default is `skip`.
+    #[serde(default)]
+    pub on_error: ErrorPolicy,
+
+    /// Serve the HTTP API of the pipeline, including the `/ingress`,
+    /// `/egress` and `/stats` endpoints, over HTTPS.
+    ///
+    /// The API is served over plain HTTP by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+
+    /// Log a warning and set the `memory_warning` flag in pipeline
+    /// statistics when the approximate memory used by the state of the
+    /// circuit and by input buffers exceeds this many megabytes.
+    ///
+    /// The estimate is refreshed at most every few seconds and does not
+    /// include all allocations made by the process; compare with `rss_bytes`
+    /// to choose a threshold that leaves room for the rest.  Disabled by
+    /// default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_warning_threshold_mb: Option<u64>,
+
+    /// Views whose past versions the pipeline retains in memory, along with
+    /// the number of versions to retain for each view.
+    ///
+    /// A view with `N` retained versions keeps its contents after each of the
+    /// last `N` steps of the circuit, which can be read with the
+    /// `as_of_step` parameter of the `/egress` endpoint.  Steps are numbered
+    /// from 1 since the pipeline started (see `total_steps` in pipeline
+    /// statistics); step 0 is the empty view before the first step.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub history: BTreeMap<String, u64>,
+
+    /// Record the data received by all input endpoints to a replay log, or
+    /// replay a previously recorded log instead of reading from the
+    /// configured transports.
+    ///
+    /// Disabled by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<ReplayConfig>,
+}
+
+/// TLS configuration of the pipeline's HTTP server.
+///
+/// When neither `cert_path` nor `key_path` is specified, the pipeline
+/// generates a self-signed certificate on startup.  Either way, the pipeline
+/// writes the certificate it serves to the `cert.pem` file in its working
+/// directory, so that the pipeline manager can verify the pipeline's
+/// identity.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TlsConfig {
+    /// Path to a PEM file with the certificate chain of the server,
+    /// starting with the server's own certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_path: Option<String>,
+
+    /// Path to a PEM file with the private key of the server, in PKCS#8 or
+    /// PKCS#1 format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+
+    /// Host names and IP addresses that a generated certificate is valid
+    /// for, in addition to `localhost` and `127.0.0.1`.  Ignored when
+    /// `cert_path` is specified.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hostnames: Vec<String>,
 }
 
 impl RuntimeConfig {
@@ -88,11 +187,190 @@ pub struct InputEndpointConfig {
     /// connected to.
     pub stream: Cow<'static, str>,
 
+    /// Mapping from the fields of input records to the columns of the table,
+    /// along with simple transformations of the mapped records: constant
+    /// columns, type casts and filters.
+    ///
+    /// Allows a source whose records don't exactly match the schema of the
+    /// table to feed the table without an upstream transformation.  Only
+    /// supported by formats that produce JSON records (`json`, `avro`,
+    /// `protobuf`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<ColumnMappingConfig>,
+
+    /// Tag each input record with its origin by storing the name of the
+    /// endpoint, the location of the record in the source, and the time
+    /// the record was ingested in designated table columns.  Applied after
+    /// `columns`.  Only supported by formats that produce JSON records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<LineageConfig>,
+
+    /// Drop records whose key has already been received by the endpoint,
+    /// e.g., records redelivered by an at-least-once source after a
+    /// reconnect.  Applied after `columns`.  Only supported by formats that
+    /// produce JSON records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupConfig>,
+
     /// Connector configuration.
     #[serde(flatten)]
     pub connector_config: ConnectorConfig,
 }
 
+/// Mapping from the fields of input records to table columns.
+///
+/// Transformations are applied in the order of the fields of this struct:
+/// fields are renamed first; `defaults`, `constants`, `casts` and `filter`
+/// refer to columns by their names in the table.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ColumnMappingConfig {
+    /// Source fields to ingest, mapped to the names of the table columns
+    /// they populate.
+    ///
+    /// When specified, fields that are not listed are ignored.  When empty,
+    /// all fields are ingested under their original names.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+
+    /// Constant values, in JSON, for table columns that are missing from an
+    /// input record.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub defaults: BTreeMap<String, JsonValue>,
+
+    /// Constant values, in JSON, for table columns.  Unlike `defaults`,
+    /// constants replace the value in the input record, if any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = Object)]
+    pub constants: BTreeMap<String, JsonValue>,
+
+    /// Columns whose values are converted to a different JSON type, e.g.,
+    /// numbers received as strings.  `NULL` values are not converted.
+    ///
+    /// A value that cannot be converted is reported as a parse error.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub casts: BTreeMap<String, ColumnCast>,
+
+    /// Predicates that a record must satisfy to be ingested.  Records that
+    /// fail any of the predicates are dropped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter: Vec<ColumnPredicate>,
+}
+
+/// Target type of a column cast (see `ColumnMappingConfig::casts`).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnCast {
+    /// Convert numbers and booleans to strings.
+    String,
+
+    /// Parse strings as integers and convert booleans to 0 or 1.  Floating
+    /// point numbers must not have a fractional part.
+    Integer,
+
+    /// Parse strings as floating point numbers.
+    Float,
+
+    /// Parse the strings `true` and `false`, in any case, and convert the
+    /// numbers 0 and 1 to booleans.
+    Boolean,
+}
+
+/// A predicate on a single column of an input record (see
+/// `ColumnMappingConfig::filter`).
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ColumnPredicate {
+    /// Table column.  Columns missing from the record are `NULL`.
+    pub column: String,
+
+    /// Comparison between the value of the column and `value`.
+    pub op: PredicateOp,
+
+    /// Value, in JSON, to compare the column with.  Ignored by `is_null`
+    /// and `is_not_null`.
+    #[serde(default, skip_serializing_if = "JsonValue::is_null")]
+    #[schema(value_type = Object)]
+    pub value: JsonValue,
+}
+
+/// Comparison operator of a [`ColumnPredicate`].
+///
+/// Values are compared like in SQL: a comparison with a `NULL` value, or
+/// between values of different JSON types, is false.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PredicateOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    IsNull,
+    IsNotNull,
+}
+
+/// Lineage columns populated by an input endpoint (see
+/// `InputEndpointConfig::lineage`).
+///
+/// Lineage columns are regular, nullable table columns that the endpoint
+/// overwrites in every record it ingests.  They are `NULL` in records
+/// ingested by endpoints that don't populate them.  Views that project them
+/// allow consumers of their outputs to trace result rows back to the input
+/// messages they were computed from.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LineageConfig {
+    /// `VARCHAR` column to store the name of the input endpoint in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// `VARCHAR` column to store the location of the record in the source
+    /// in, as reported by the transport, e.g., the name of the file or S3
+    /// object, or a JSON object with the topic, partition and offset of a
+    /// Kafka message.  `NULL` for transports that don't report locations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+
+    /// `BIGINT` column to store the time the record was ingested in, in
+    /// milliseconds since the UNIX epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingested_at: Option<String>,
+}
+
+/// Default value of `DedupConfig::max_keys`.
+const fn default_dedup_max_keys() -> u64 {
+    1_000_000
+}
+
+/// Deduplication of input records by key (see
+/// `InputEndpointConfig::dedup`).
+///
+/// The endpoint remembers the keys of the records it has ingested, within
+/// the limits set by `max_keys` and `max_age_ms`, and drops records whose
+/// key it remembers.  Inserts and deletes are deduplicated separately, so
+/// deleting a record doesn't make the endpoint forget its insertion.
+/// Dropped records are counted in the `num_duplicate_records` endpoint
+/// metric.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DedupConfig {
+    /// Table columns that identify a record.  Columns missing from the
+    /// record are `NULL`.
+    pub key: Vec<String>,
+
+    /// Maximal number of keys to remember.  Once the limit is reached, the
+    /// oldest keys are forgotten first.
+    ///
+    /// The default is 1 million.
+    #[serde(default = "default_dedup_max_keys")]
+    pub max_keys: u64,
+
+    /// Forget keys received more than this many milliseconds ago.
+    ///
+    /// By default, keys are only forgotten to stay within `max_keys`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_ms: Option<u64>,
+}
+
 /// A data connector's configuration
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ConnectorConfig {
@@ -113,6 +391,171 @@ pub struct ConnectorConfig {
     /// The default is 1 million.
     #[serde(default = "default_max_buffered_records")]
     pub max_buffered_records: u64,
+
+    /// Maximal rate, in records per second, at which an input endpoint
+    /// feeds data to the circuit.
+    ///
+    /// When the endpoint exceeds this rate, the controller stalls it until
+    /// the average rate drops below the limit, allowing bursts of up to one
+    /// second worth of records.  Use this option to prevent a fast source,
+    /// e.g., one replaying historical data, from starving other endpoints
+    /// in the same pipeline.  Ignored by output connectors.
+    ///
+    /// The default is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_records_per_sec: Option<u64>,
+
+    /// Maximal rate, in bytes per second, at which an input endpoint
+    /// feeds data to the circuit.
+    ///
+    /// Works like `max_records_per_sec`, but limits the amount of raw data
+    /// received by the endpoint.  Ignored by output connectors.
+    ///
+    /// The default is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_per_sec: Option<u64>,
+
+    /// Policy for retrying transient transport errors.
+    ///
+    /// By default, errors are not retried: a fatal error stops an input
+    /// endpoint and a failure to send output drops the output buffer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+
+    /// Maximal time, in milliseconds, an output endpoint holds back outputs
+    /// of the circuit in order to combine the outputs of multiple steps into
+    /// a single batch.
+    ///
+    /// Batching trades latency for fewer, larger writes, which suits sinks
+    /// like object stores or analytical databases that handle many small
+    /// writes poorly.  The batch is sent once the oldest output in it has
+    /// waited for `max_batch_delay_ms` or it reaches `max_batch_size`
+    /// records, whichever comes first.  Ignored by input connectors.
+    ///
+    /// By default, the outputs of each step are sent as soon as they are
+    /// available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_delay_ms: Option<u64>,
+
+    /// Maximal number of records in a batch combined from the outputs of
+    /// multiple steps (see `max_batch_delay_ms`).
+    ///
+    /// The outputs of a single step are never split, so a batch can exceed
+    /// this size.  A batch is also sent when it reaches
+    /// `max_buffered_records`, to avoid stalling the circuit.  Ignored by
+    /// input connectors.
+    ///
+    /// The default is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+
+    /// Number of threads that parse the data received by an input endpoint.
+    ///
+    /// Parsing formats like JSON or CSV is CPU-intensive, and a single parser
+    /// can limit the throughput of an endpoint that feeds a circuit with
+    /// multiple workers.  With more than one parser, chunks of complete
+    /// records received by the endpoint, e.g., Kafka messages, are
+    /// distributed across parsers according to `parser_sharding`.  Data that
+    /// the transport receives as a byte stream, e.g., from a file, is always
+    /// parsed by a single thread, and so is data in formats whose result
+    /// depends on the order of records, such as JSON with the `upsert`
+    /// update format.  Ignored by output connectors.
+    ///
+    /// By default, data is parsed by the transport endpoint's own thread.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_parsers: Option<usize>,
+
+    /// How chunks of input data are assigned to parsers when `num_parsers`
+    /// is greater than 1.
+    ///
+    /// The default is `round_robin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parser_sharding: Option<ParserSharding>,
+
+    /// How the endpoint reacts to data errors.
+    ///
+    /// Overrides the pipeline-wide `on_error` policy for this endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<ErrorPolicy>,
+
+    /// What an output endpoint does when it falls behind the circuit, i.e.,
+    /// when `max_buffered_records` records are queued for it.  Ignored by
+    /// input connectors.
+    ///
+    /// The default is `stall`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overflow: Option<OverflowPolicy>,
+}
+
+/// Reaction of an output endpoint to falling behind the circuit (see
+/// `ConnectorConfig::overflow`).
+///
+/// Each output endpoint has its own queue and sends outputs at its own
+/// pace; this policy determines whether a slow endpoint holds back the rest
+/// of the pipeline.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Stop the circuit until the endpoint catches up.  All outputs are
+    /// delivered, but the slowest `stall` endpoint throttles the pipeline,
+    /// including all other endpoints.
+    #[default]
+    Stall,
+
+    /// Discard outputs produced while the endpoint is behind.  Dropped
+    /// records are counted in the `num_dropped_records` endpoint metric.
+    /// The initial snapshot of a `snapshot_and_changes` query is never
+    /// dropped.
+    Drop,
+
+    /// Encode outputs as soon as they are produced and buffer the encoded
+    /// data in a temporary file until the transport is ready to send it.
+    /// All outputs are delivered in order without stalling the circuit, at
+    /// the cost of disk space; the size of the file is reported in the
+    /// `spilled_bytes` endpoint metric.
+    Spill,
+}
+
+/// Reaction of the pipeline to data errors reported by an endpoint: records
+/// that fail to parse or encode and transport errors that aren't retried.
+///
+/// Errors are reported and counted in endpoint stats under all policies.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// Drop the failed record or output buffer and continue.  Dropped input
+    /// records and output buffers are counted in the `num_skipped_records`
+    /// and `num_skipped_buffers` endpoint metrics.
+    #[default]
+    Skip,
+
+    /// Drop the entire batch that contains the error: all records in the
+    /// chunk of input data passed to the parser, or the remainder of the
+    /// output batch being sent.  Records in the chunk that parsed
+    /// successfully are not pushed to the circuit.
+    AbortBatch,
+
+    /// Stop the pipeline.  The pipeline enters the failed state, reporting
+    /// the error that caused it to stop.
+    AbortPipeline,
+}
+
+/// Assignment of input chunks to parallel parsers (see
+/// `ConnectorConfig::num_parsers`).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParserSharding {
+    /// Assign chunks to parsers in round-robin order.  Balances load evenly,
+    /// but records from different chunks may reach the circuit in a different
+    /// order than they were received.
+    #[default]
+    RoundRobin,
+
+    /// Parse all chunks from the same partition of the source, e.g., a Kafka
+    /// partition, with the same parser, preserving the order of records
+    /// within each partition.  Chunks from transports that don't report
+    /// partitions are assigned in round-robin order.
+    Partition,
 }
 
 impl ConnectorConfig {
@@ -0,0 +1,404 @@
+//! Deduplication of input records by key.
+//!
+//! Implements [`DedupConfig`] as a wrapper around the input collection handle,
+//! which drops records whose key has already been ingested by the endpoint
+//! before they get deserialized.  The keys seen by the endpoint are stored in
+//! a [`DedupFilter`] that outlives the endpoint's parsers, so that records
+//! redelivered after the endpoint reconnects are recognized as duplicates.
+
+use super::{ControllerStatus, DedupConfig, EndpointId};
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    ControllerError, DeCollectionHandle,
+};
+use anyhow::Result as AnyResult;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Prefixes that distinguish the keys of inserted and deleted records.
+const INSERT: u8 = 0;
+const DELETE: u8 = 1;
+
+/// Keys ingested by an input endpoint.
+pub(crate) struct DedupFilter {
+    config: DedupConfig,
+    seen: Mutex<SeenKeys>,
+}
+
+#[derive(Default)]
+struct SeenKeys {
+    /// Remembered keys, along with their sequence numbers in `queue`.
+    keys: HashMap<Vec<u8>, u64>,
+
+    /// Keys in the order they were received.  May contain entries for keys
+    /// that have since been forgotten; such entries are skipped.
+    queue: VecDeque<(u64, Instant, Vec<u8>)>,
+
+    next_seq: u64,
+}
+
+impl SeenKeys {
+    fn pop_front(&mut self) {
+        if let Some((seq, _, key)) = self.queue.pop_front() {
+            if self.keys.get(&key) == Some(&seq) {
+                self.keys.remove(&key);
+            }
+        }
+    }
+}
+
+impl DedupFilter {
+    pub(crate) fn new(config: &DedupConfig) -> Self {
+        Self {
+            config: config.clone(),
+            seen: Mutex::new(SeenKeys::default()),
+        }
+    }
+
+    /// Remember `key`.  Returns `false` if the key is already known.
+    fn insert(&self, key: &[u8]) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+
+        if let Some(max_age) = self.config.max_age_ms.map(Duration::from_millis) {
+            while seen.queue.front().map_or(false, |(_, received, _)| {
+                now.duration_since(*received) > max_age
+            }) {
+                seen.pop_front();
+            }
+        }
+
+        if seen.keys.contains_key(key) {
+            return false;
+        }
+
+        let seq = seen.next_seq;
+        seen.next_seq += 1;
+        seen.keys.insert(key.to_vec(), seq);
+        seen.queue.push_back((seq, now, key.to_vec()));
+        while seen.queue.len() as u64 > self.config.max_keys {
+            seen.pop_front();
+        }
+        true
+    }
+
+    /// Forget `key`, e.g., because the record that carried it was discarded.
+    fn remove(&self, key: &[u8]) {
+        self.seen.lock().unwrap().keys.remove(key);
+    }
+}
+
+/// Input collection handle that drops duplicate records.
+pub(crate) struct DedupCollectionHandle {
+    endpoint_id: EndpointId,
+    endpoint_name: String,
+    filter: Arc<DedupFilter>,
+    status: Arc<ControllerStatus>,
+
+    /// Deserializer for JSON records connected to the underlying input
+    /// stream.  Streams returned by `configure_deserializer` are forks of
+    /// this stream.
+    json_stream: Box<dyn DeCollectionStream>,
+}
+
+impl DedupCollectionHandle {
+    pub(crate) fn new(
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        input_handle: &dyn DeCollectionHandle,
+        filter: Arc<DedupFilter>,
+        status: Arc<ControllerStatus>,
+    ) -> Result<Self, ControllerError> {
+        if filter.config.key.is_empty() {
+            return Err(ControllerError::parser_config_parse_error(
+                endpoint_name,
+                &"'dedup' requires at least one key column",
+                &serde_yaml::to_string(&filter.config).unwrap_or_default(),
+            ));
+        }
+
+        Ok(Self {
+            endpoint_id,
+            endpoint_name: endpoint_name.to_string(),
+            filter,
+            status,
+            json_stream: input_handle
+                .configure_deserializer(RecordFormat::Json(Default::default()))?,
+        })
+    }
+}
+
+impl DeCollectionHandle for DedupCollectionHandle {
+    fn configure_deserializer(
+        &self,
+        record_format: RecordFormat,
+    ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
+        match record_format {
+            RecordFormat::Json(flavor) if flavor == Default::default() => {
+                Ok(Box::new(DedupStream {
+                    inner: self.json_stream.fork(),
+                    endpoint_id: self.endpoint_id,
+                    filter: self.filter.clone(),
+                    status: self.status.clone(),
+                    pending: Vec::new(),
+                    num_duplicates: 0,
+                }))
+            }
+            _ => Err(ControllerError::parser_config_parse_error(
+                &self.endpoint_name,
+                &"deduplication ('dedup') is only supported by formats that produce JSON records, e.g., 'json' and 'avro'",
+                &serde_yaml::to_string(&self.filter.config).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Deserializer that forwards records whose key hasn't been seen before to
+/// the underlying deserializer.
+struct DedupStream {
+    inner: Box<dyn DeCollectionStream>,
+    endpoint_id: EndpointId,
+    filter: Arc<DedupFilter>,
+    status: Arc<ControllerStatus>,
+
+    /// Keys of the records buffered since the last flush.  They are
+    /// forgotten if the buffer is cleared, so the records can be ingested
+    /// again.
+    pending: Vec<Vec<u8>>,
+
+    /// Number of duplicates dropped since the last flush.
+    num_duplicates: u64,
+}
+
+impl DedupStream {
+    /// Extract the key of a serialized record, prefixed with `op`.
+    ///
+    /// Returns `None` for records that are not JSON objects; such records are
+    /// forwarded unmodified.
+    fn key(&self, op: u8, data: &[u8]) -> Option<Vec<u8>> {
+        let Ok(JsonValue::Object(record)) = serde_json::from_slice::<JsonValue>(data) else {
+            return None;
+        };
+
+        // Column names are case-insensitive.
+        let values = self
+            .filter
+            .config
+            .key
+            .iter()
+            .map(|column| {
+                record
+                    .iter()
+                    .find(|(field, _)| field.eq_ignore_ascii_case(column))
+                    .map_or(&JsonValue::Null, |(_, value)| value)
+            })
+            .collect::<Vec<_>>();
+
+        let mut key = vec![op];
+        // Serializing a `serde_json::Value` cannot fail.
+        serde_json::to_writer(&mut key, &values).unwrap();
+        Some(key)
+    }
+
+    /// Push `data` to the underlying deserializer using `push` unless its
+    /// key is known.
+    fn push<F>(&mut self, op: u8, data: &[u8], push: F) -> AnyResult<()>
+    where
+        F: FnOnce(&mut dyn DeCollectionStream, &[u8]) -> AnyResult<()>,
+    {
+        let Some(key) = self.key(op, data) else {
+            return push(self.inner.as_mut(), data);
+        };
+
+        if !self.filter.insert(&key) {
+            self.num_duplicates += 1;
+            return Ok(());
+        }
+
+        let result = push(self.inner.as_mut(), data);
+        if result.is_ok() {
+            self.pending.push(key);
+        } else {
+            self.filter.remove(&key);
+        }
+        result
+    }
+}
+
+impl DeCollectionStream for DedupStream {
+    fn insert(&mut self, data: &[u8]) -> AnyResult<()> {
+        self.push(INSERT, data, |inner, data| inner.insert(data))
+    }
+
+    fn delete(&mut self, data: &[u8]) -> AnyResult<()> {
+        self.push(DELETE, data, |inner, data| inner.delete(data))
+    }
+
+    fn reserve(&mut self, reservation: usize) {
+        self.inner.reserve(reservation)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+        self.pending.clear();
+        if self.num_duplicates > 0 {
+            self.status
+                .duplicate_records(self.endpoint_id, self.num_duplicates);
+            self.num_duplicates = 0;
+        }
+    }
+
+    fn clear_buffer(&mut self) {
+        self.inner.clear_buffer();
+        for key in self.pending.drain(..) {
+            self.filter.remove(&key);
+        }
+        self.num_duplicates = 0;
+    }
+
+    fn fork(&self) -> Box<dyn DeCollectionStream> {
+        Box::new(Self {
+            inner: self.inner.fork(),
+            endpoint_id: self.endpoint_id,
+            filter: self.filter.clone(),
+            status: self.status.clone(),
+            pending: Vec::new(),
+            num_duplicates: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DedupCollectionHandle, DedupFilter};
+    use crate::{
+        catalog::RecordFormat,
+        format::{InputFormat, JsonParserConfig, JsonUpdateFormat},
+        test::{MockDeZSet, TestStruct},
+        ControllerStatus, DeCollectionHandle, DedupConfig, InputEndpointConfig, Parser,
+        RuntimeConfig,
+    };
+    use std::sync::{atomic::Ordering, Arc};
+
+    fn new_parser(handle: &dyn DeCollectionHandle) -> Box<dyn Parser> {
+        <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                handle,
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::InsertDelete,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap()
+    }
+
+    fn record(id: u32, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b: true,
+            i: None,
+            s: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dedup() {
+        let status = Arc::new(ControllerStatus::new(
+            &serde_yaml::from_str::<RuntimeConfig>("workers: 1").unwrap(),
+        ));
+        let endpoint_config: InputEndpointConfig = serde_yaml::from_str(
+            r#"
+stream: test_input
+dedup:
+    key: [ID]
+    max_keys: 3
+transport:
+    name: file
+    config:
+        path: /dev/null
+format:
+    name: json
+"#,
+        )
+        .unwrap();
+        let filter = Arc::new(DedupFilter::new(endpoint_config.dedup.as_ref().unwrap()));
+        status.add_input(&0, "test", endpoint_config);
+
+        let input_handle = <MockDeZSet<TestStruct>>::new();
+        let handle =
+            DedupCollectionHandle::new(0, "test", &input_handle, filter.clone(), status.clone())
+                .unwrap();
+
+        // CSV records cannot be deduplicated.
+        assert!(handle
+            .configure_deserializer(RecordFormat::Csv(Default::default()))
+            .is_err());
+
+        let mut parser = new_parser(&handle);
+
+        // Records with the same key are dropped, even if other fields differ.
+        // Deletes are deduplicated separately from inserts.
+        let (_, errors) = parser.input_chunk(
+            br#"{"insert": {"id": 1, "b": true, "i": null, "s": "foo"}}
+{"insert": {"id": 1, "b": true, "i": null, "s": "bar"}}
+{"insert": {"id": 2, "b": true, "i": null, "s": "foo"}}
+{"delete": {"id": 1, "b": true, "i": null, "s": "foo"}}"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            input_handle.state().flushed,
+            vec![
+                (record(1, "foo"), true),
+                (record(2, "foo"), true),
+                (record(1, "foo"), false)
+            ]
+        );
+        let num_duplicates = || {
+            status.input_status()[&0]
+                .metrics
+                .num_duplicate_records
+                .load(Ordering::Acquire)
+        };
+        assert_eq!(num_duplicates(), 1);
+
+        // Inserting key 3 evicts the oldest key, 1, to stay within
+        // `max_keys`.  The filter is shared by all parsers of the endpoint.
+        let mut parser = new_parser(&handle);
+        parser.input_chunk(
+            br#"{"insert": {"id": 3, "b": true, "i": null, "s": "foo"}}
+{"insert": {"id": 2, "b": true, "i": null, "s": "baz"}}
+{"insert": {"id": 1, "b": true, "i": null, "s": "baz"}}"#,
+        );
+        assert_eq!(
+            input_handle.state().flushed[3..],
+            [(record(3, "foo"), true), (record(1, "baz"), true)]
+        );
+        assert_eq!(num_duplicates(), 2);
+
+        // Keys of records that are discarded before being flushed are
+        // forgotten.
+        let mut stream = handle
+            .configure_deserializer(RecordFormat::Json(Default::default()))
+            .unwrap();
+        stream
+            .insert(br#"{"id": 4, "b": true, "i": null, "s": "foo"}"#)
+            .unwrap();
+        stream.clear_buffer();
+        stream
+            .insert(br#"{"id": 4, "b": true, "i": null, "s": "foo"}"#)
+            .unwrap();
+        stream.flush();
+        assert_eq!(
+            input_handle.state().flushed[5..],
+            [(record(4, "foo"), true)]
+        );
+        assert_eq!(num_duplicates(), 2);
+    }
+}
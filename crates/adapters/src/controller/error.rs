@@ -75,6 +75,21 @@ pub enum ConfigError {
         endpoint_name: String,
         stream_name: String,
     },
+
+    /// Column statistics are requested for a stream that is not found
+    /// in the circuit catalog or does not support them.
+    ColumnStatisticsNotSupported { stream_name: String },
+
+    /// Invalid history retention setting for a view, e.g., the view is not
+    /// found in the circuit catalog.
+    InvalidHistoryConfig { stream_name: String, error: String },
+
+    /// The transport of a running input endpoint cannot apply a new
+    /// configuration.
+    TransportReconfigurationError {
+        endpoint_name: String,
+        error: String,
+    },
 }
 
 impl StdError for ConfigError {}
@@ -93,6 +108,11 @@ impl DetailedError for ConfigError {
             Self::UnknownOutputTransport { .. } => Cow::from("UnknownOutputTransport"),
             Self::UnknownInputStream { .. } => Cow::from("UnknownInputStream"),
             Self::UnknownOutputStream { .. } => Cow::from("UnknownOutputStream"),
+            Self::ColumnStatisticsNotSupported { .. } => Cow::from("ColumnStatisticsNotSupported"),
+            Self::InvalidHistoryConfig { .. } => Cow::from("InvalidHistoryConfig"),
+            Self::TransportReconfigurationError { .. } => {
+                Cow::from("TransportReconfigurationError")
+            }
         }
     }
 }
@@ -168,6 +188,18 @@ impl Display for ConfigError {
             } => {
                 write!(f, "Output endpoint '{endpoint_name}' specifies unknown output table or view '{stream_name}'")
             }
+            Self::ColumnStatisticsNotSupported { stream_name } => {
+                write!(f, "Column statistics are not supported for '{stream_name}': the table or view does not exist or was compiled without statistics support")
+            }
+            Self::InvalidHistoryConfig { stream_name, error } => {
+                write!(f, "Cannot retain the history of '{stream_name}': {error}")
+            }
+            Self::TransportReconfigurationError {
+                endpoint_name,
+                error,
+            } => {
+                write!(f, "Cannot change the transport configuration of input endpoint '{endpoint_name}': {error}")
+            }
         }
     }
 }
@@ -257,6 +289,32 @@ impl ConfigError {
             stream_name: stream_name.to_owned(),
         }
     }
+
+    pub fn column_statistics_not_supported(stream_name: &str) -> Self {
+        Self::ColumnStatisticsNotSupported {
+            stream_name: stream_name.to_owned(),
+        }
+    }
+
+    pub fn invalid_history_config<E>(stream_name: &str, error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::InvalidHistoryConfig {
+            stream_name: stream_name.to_owned(),
+            error: error.to_string(),
+        }
+    }
+
+    pub fn transport_reconfiguration_error<E>(endpoint_name: &str, error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::TransportReconfigurationError {
+            endpoint_name: endpoint_name.to_owned(),
+            error: error.to_string(),
+        }
+    }
 }
 
 /// Controller error.
@@ -334,6 +392,34 @@ pub enum ControllerError {
     /// Error inside the Prometheus module.
     PrometheusError { error: String },
 
+    /// Error taking or restoring a checkpoint.
+    CheckpointError { error: String },
+
+    /// Error initializing OpenTelemetry tracing.
+    TracingError { error: String },
+
+    /// Error configuring TLS for the HTTP server.
+    TlsError { error: String },
+
+    /// Error writing or reading the replay log.
+    ReplayLogError { error: String },
+
+    /// The pipeline has no input endpoint with the specified name.
+    UnknownInputEndpoint { endpoint_name: String },
+
+    /// The pipeline has no output endpoint with the specified name.
+    UnknownOutputEndpoint { endpoint_name: String },
+
+    /// All neighborhood sessions of the stream are used by other endpoints.
+    NeighborhoodSessionLimit {
+        stream_name: String,
+        max_sessions: usize,
+    },
+
+    /// The pipeline was stopped after an error at an endpoint with the
+    /// `abort_pipeline` error policy.
+    PipelineAborted { endpoint_name: String },
+
     // TODO: we currently don't have a way to include more info about the panic.
     /// Panic inside the DBSP runtime.
     DbspPanic,
@@ -425,6 +511,14 @@ impl DetailedError for ControllerError {
             Self::InputTransportError { .. } => Cow::from("InputTransportError"),
             Self::OutputTransportError { .. } => Cow::from("OutputTransportError"),
             Self::PrometheusError { .. } => Cow::from("PrometheusError"),
+            Self::CheckpointError { .. } => Cow::from("CheckpointError"),
+            Self::TracingError { .. } => Cow::from("TracingError"),
+            Self::TlsError { .. } => Cow::from("TlsError"),
+            Self::ReplayLogError { .. } => Cow::from("ReplayLogError"),
+            Self::UnknownInputEndpoint { .. } => Cow::from("UnknownInputEndpoint"),
+            Self::UnknownOutputEndpoint { .. } => Cow::from("UnknownOutputEndpoint"),
+            Self::NeighborhoodSessionLimit { .. } => Cow::from("NeighborhoodSessionLimit"),
+            Self::PipelineAborted { .. } => Cow::from("PipelineAborted"),
             Self::DbspError { error } => error.error_code(),
             Self::JitError { .. } => Cow::from("JitCompilerError"),
             Self::DbspPanic => Cow::from("DbspPanic"),
@@ -501,6 +595,33 @@ impl Display for ControllerError {
             Self::PrometheusError { error } => {
                 write!(f, "Error in the Prometheus metrics module: '{error}'")
             }
+            Self::CheckpointError { error } => {
+                write!(f, "Checkpoint error: {error}")
+            }
+            Self::TracingError { error } => {
+                write!(f, "Error initializing tracing: {error}")
+            }
+            Self::TlsError { error } => {
+                write!(f, "TLS configuration error: {error}")
+            }
+            Self::ReplayLogError { error } => {
+                write!(f, "Replay log error: {error}")
+            }
+            Self::UnknownInputEndpoint { endpoint_name } => {
+                write!(f, "Unknown input endpoint '{endpoint_name}'")
+            }
+            Self::UnknownOutputEndpoint { endpoint_name } => {
+                write!(f, "Unknown output endpoint '{endpoint_name}'")
+            }
+            Self::NeighborhoodSessionLimit {
+                stream_name,
+                max_sessions,
+            } => {
+                write!(f, "All {max_sessions} neighborhood sessions of '{stream_name}' are in use; close an existing neighborhood query or wait for one to complete")
+            }
+            Self::PipelineAborted { endpoint_name } => {
+                write!(f, "Pipeline stopped after an error at endpoint '{endpoint_name}', whose error policy is 'abort_pipeline'")
+            }
             Self::DbspError { error } => {
                 write!(f, "DBSP error: {error}")
             }
@@ -628,6 +749,30 @@ impl ControllerError {
         }
     }
 
+    pub fn column_statistics_not_supported(stream_name: &str) -> Self {
+        Self::Config {
+            config_error: ConfigError::column_statistics_not_supported(stream_name),
+        }
+    }
+
+    pub fn invalid_history_config<E>(stream_name: &str, error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::Config {
+            config_error: ConfigError::invalid_history_config(stream_name, error),
+        }
+    }
+
+    pub fn transport_reconfiguration_error<E>(endpoint_name: &str, error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::Config {
+            config_error: ConfigError::transport_reconfiguration_error(endpoint_name, error),
+        }
+    }
+
     pub fn input_transport_error(endpoint_name: &str, fatal: bool, error: AnyError) -> Self {
         Self::InputTransportError {
             endpoint_name: endpoint_name.to_owned(),
@@ -667,6 +812,67 @@ impl ControllerError {
         }
     }
 
+    pub fn checkpoint_error<E>(error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::CheckpointError {
+            error: error.to_string(),
+        }
+    }
+
+    pub fn tracing_error<E>(error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::TracingError {
+            error: error.to_string(),
+        }
+    }
+
+    pub fn tls_error<E>(error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::TlsError {
+            error: error.to_string(),
+        }
+    }
+
+    pub fn replay_log_error<E>(error: &E) -> Self
+    where
+        E: ToString,
+    {
+        Self::ReplayLogError {
+            error: error.to_string(),
+        }
+    }
+
+    pub fn unknown_input_endpoint(endpoint_name: &str) -> Self {
+        Self::UnknownInputEndpoint {
+            endpoint_name: endpoint_name.to_owned(),
+        }
+    }
+
+    pub fn unknown_output_endpoint(endpoint_name: &str) -> Self {
+        Self::UnknownOutputEndpoint {
+            endpoint_name: endpoint_name.to_owned(),
+        }
+    }
+
+    pub fn neighborhood_session_limit(stream_name: &str, max_sessions: usize) -> Self {
+        Self::NeighborhoodSessionLimit {
+            stream_name: stream_name.to_owned(),
+            max_sessions,
+        }
+    }
+
+    pub fn pipeline_aborted(endpoint_name: &str) -> Self {
+        Self::PipelineAborted {
+            endpoint_name: endpoint_name.to_owned(),
+        }
+    }
+
     pub fn jit_error(error: &str) -> Self {
         Self::JitError {
             error: error.to_string(),
@@ -0,0 +1,190 @@
+//! Retained history of output views.
+//!
+//! Implements the `history` setting of [`RuntimeConfig`](`super::RuntimeConfig`).
+//! For each configured view, the circuit thread feeds the changes produced by
+//! every step of the circuit to a [`ViewHistory`], which maintains the current
+//! contents of the view along with the changes made by the last few steps.
+//! Past versions of the view are reconstructed by reverting the changes made
+//! after the requested step.
+//!
+//! The history is kept in memory and is not part of checkpoints.
+
+use crate::{catalog::RecordFormat, SerBatch};
+use anyhow::Result as AnyResult;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::RangeInclusive,
+    sync::Arc,
+};
+
+/// Records of a view, serialized as JSON, with their weights.
+type Records = Vec<(String, i64)>;
+
+/// The last few versions of a view.
+pub(crate) struct ViewHistory {
+    /// Number of versions to retain, including the current one.
+    versions: u64,
+
+    /// Step that produced the current version, or 0 if the circuit hasn't
+    /// performed any steps.
+    latest_step: u64,
+
+    /// Current contents of the view.
+    current: BTreeMap<String, i64>,
+
+    /// Changes made by the last `versions - 1` steps, oldest first.
+    changes: VecDeque<Records>,
+}
+
+impl ViewHistory {
+    /// Create an empty history that retains `versions` versions of the view.
+    pub(crate) fn new(versions: u64) -> Self {
+        debug_assert!(versions > 0);
+
+        Self {
+            versions,
+            latest_step: 0,
+            current: BTreeMap::new(),
+            changes: VecDeque::new(),
+        }
+    }
+
+    /// Steps whose versions are retained.
+    pub(crate) fn retained_steps(&self) -> RangeInclusive<u64> {
+        self.latest_step - self.changes.len() as u64..=self.latest_step
+    }
+
+    /// Record the changes made to the view by the next step of the circuit.
+    pub(crate) fn record(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut changes = Vec::new();
+        let mut buffer = Vec::new();
+
+        for batch in batches {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                let weight = cursor.weight();
+                if weight != 0 {
+                    buffer.clear();
+                    cursor.serialize_key(&mut buffer)?;
+                    changes.push((String::from_utf8_lossy(&buffer).into_owned(), weight));
+                }
+                cursor.step_key();
+            }
+        }
+
+        for (record, weight) in changes.iter() {
+            apply(&mut self.current, record, *weight);
+        }
+
+        self.latest_step += 1;
+        self.changes.push_back(changes);
+        while self.changes.len() as u64 >= self.versions {
+            self.changes.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// Contents of the view after step `step`, or `None` if this version is
+    /// not retained.  Records with weight `N` are repeated `N` times.
+    pub(crate) fn as_of(&self, step: u64) -> Option<Vec<String>> {
+        if !self.retained_steps().contains(&step) {
+            return None;
+        }
+
+        let mut contents = self.current.clone();
+        let reverted = (self.latest_step - step) as usize;
+        for changes in self.changes.iter().rev().take(reverted) {
+            for (record, weight) in changes.iter() {
+                apply(&mut contents, record, -weight);
+            }
+        }
+
+        Some(
+            contents
+                .into_iter()
+                .flat_map(|(record, weight)| std::iter::repeat(record).take(weight.max(0) as usize))
+                .collect(),
+        )
+    }
+}
+
+/// Add `weight` to the weight of `record` in `contents`.
+fn apply(contents: &mut BTreeMap<String, i64>, record: &str, weight: i64) {
+    match contents.get_mut(record) {
+        Some(w) => {
+            *w += weight;
+            if *w == 0 {
+                contents.remove(record);
+            }
+        }
+        None => {
+            contents.insert(record.to_string(), weight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ViewHistory;
+    use crate::{static_compile::seroutput::SerBatchImpl, test::TestStruct, SerBatch};
+    use dbsp::{trace::Batch, OrdZSet};
+    use std::sync::Arc;
+
+    fn batch(records: Vec<(TestStruct, i64)>) -> Vec<Arc<dyn SerBatch>> {
+        let zset = OrdZSet::from_keys((), records);
+        vec![Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>]
+    }
+
+    fn record(id: u32, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b: true,
+            i: None,
+            s: s.to_string(),
+        }
+    }
+
+    fn json(id: u32, s: &str) -> String {
+        format!(r#"{{"id":{id},"b":true,"i":null,"s":"{s}"}}"#)
+    }
+
+    #[test]
+    fn test_view_history() {
+        let mut history = ViewHistory::new(3);
+        assert_eq!(history.retained_steps(), 0..=0);
+        assert_eq!(history.as_of(0), Some(vec![]));
+        assert_eq!(history.as_of(1), None);
+
+        // Step 1: insert two records.
+        history
+            .record(&batch(vec![(record(1, "foo"), 1), (record(2, "bar"), 1)]))
+            .unwrap();
+        // Step 2: update record 1.
+        history
+            .record(&batch(vec![(record(1, "foo"), -1), (record(1, "baz"), 1)]))
+            .unwrap();
+        assert_eq!(history.retained_steps(), 0..=2);
+        assert_eq!(history.as_of(0), Some(vec![]));
+
+        // Step 3: no changes.
+        history.record(&[]).unwrap();
+        assert_eq!(history.retained_steps(), 1..=3);
+        assert_eq!(history.as_of(0), None);
+        assert_eq!(history.as_of(1), Some(vec![json(1, "foo"), json(2, "bar")]));
+        assert_eq!(history.as_of(2), Some(vec![json(1, "baz"), json(2, "bar")]));
+        assert_eq!(history.as_of(3), history.as_of(2));
+
+        // Step 4: delete record 2 and insert record 3 twice.
+        history
+            .record(&batch(vec![(record(2, "bar"), -1), (record(3, "qux"), 2)]))
+            .unwrap();
+        assert_eq!(history.retained_steps(), 2..=4);
+        assert_eq!(history.as_of(1), None);
+        assert_eq!(
+            history.as_of(4),
+            Some(vec![json(1, "baz"), json(3, "qux"), json(3, "qux")])
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Lineage tagging of input records.
+//!
+//! Implements [`LineageConfig`] as a wrapper around the input collection
+//! handle, which stores the name of the endpoint, the location of the record
+//! in the source, and the ingest time in the configured columns of each JSON
+//! record before it gets deserialized.  Locations are reported by the
+//! transport via [`InputConsumer::source_offset`](`crate::InputConsumer::source_offset`);
+//! the input probe stores them in the [`LineageTag`] shared with the
+//! deserializers of its parser.
+//!
+//! Only inserted records are tagged.  Deleted records are forwarded
+//! unmodified, since a deletion must match the lineage columns of the record
+//! it deletes rather than describe the deletion itself.
+
+use super::{projection::column_mut, LineageConfig};
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    ControllerError, DeCollectionHandle,
+};
+use anyhow::Result as AnyResult;
+use serde_json::{Map, Value as JsonValue};
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Source offset of the data a parser is processing.
+#[derive(Default)]
+pub(crate) struct LineageTag {
+    /// The offset, as a JSON string, or `null` if the transport hasn't
+    /// reported one.
+    offset: Mutex<JsonValue>,
+
+    /// Tag of the parser returned by the last `Parser::fork` call.
+    forked: Mutex<Option<Arc<LineageTag>>>,
+}
+
+impl LineageTag {
+    /// Set the offset of records parsed after this call.  Offsets other than
+    /// strings are stored as JSON text.
+    pub(crate) fn set_offset(&self, offset: &JsonValue) {
+        let offset = match offset {
+            JsonValue::Null | JsonValue::String(_) => offset.clone(),
+            _ => JsonValue::String(offset.to_string()),
+        };
+        *self.offset.lock().unwrap() = offset;
+    }
+
+    /// Returns the tag of the parser forked from the parser that owns
+    /// `self`.
+    ///
+    /// Must be called right after `Parser::fork`; forking the same parser
+    /// concurrently from multiple threads is not supported.
+    pub(crate) fn take_fork(&self) -> Arc<LineageTag> {
+        self.forked.lock().unwrap().take().unwrap_or_default()
+    }
+
+    fn fork(&self) -> Arc<LineageTag> {
+        self.forked
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default)
+            .clone()
+    }
+}
+
+/// Input collection handle that tags all inserted records with their
+/// lineage.
+pub(crate) struct LineageCollectionHandle {
+    endpoint_name: String,
+    config: Arc<LineageConfig>,
+    tag: Arc<LineageTag>,
+
+    /// Deserializer for JSON records connected to the underlying input
+    /// stream.  Streams returned by `configure_deserializer` are forks of
+    /// this stream.
+    json_stream: Box<dyn DeCollectionStream>,
+}
+
+impl LineageCollectionHandle {
+    pub(crate) fn new(
+        endpoint_name: &str,
+        input_handle: &dyn DeCollectionHandle,
+        config: &LineageConfig,
+    ) -> Result<Self, ControllerError> {
+        Ok(Self {
+            endpoint_name: endpoint_name.to_string(),
+            config: Arc::new(config.clone()),
+            tag: Default::default(),
+            json_stream: input_handle
+                .configure_deserializer(RecordFormat::Json(Default::default()))?,
+        })
+    }
+
+    /// The tag shared by all deserializers created by this handle.
+    pub(crate) fn tag(&self) -> Arc<LineageTag> {
+        self.tag.clone()
+    }
+}
+
+impl DeCollectionHandle for LineageCollectionHandle {
+    fn configure_deserializer(
+        &self,
+        record_format: RecordFormat,
+    ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
+        match record_format {
+            RecordFormat::Json(flavor) if flavor == Default::default() => {
+                Ok(Box::new(LineageStream {
+                    inner: self.json_stream.fork(),
+                    endpoint_name: JsonValue::String(self.endpoint_name.clone()),
+                    config: self.config.clone(),
+                    tag: self.tag.clone(),
+                    buffer: Vec::new(),
+                }))
+            }
+            _ => Err(ControllerError::parser_config_parse_error(
+                &self.endpoint_name,
+                &"lineage tagging ('lineage') is only supported by formats that produce JSON records, e.g., 'json' and 'avro'",
+                &serde_yaml::to_string(&*self.config).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Deserializer that stores lineage columns in each inserted record before
+/// forwarding it to the underlying deserializer.
+struct LineageStream {
+    inner: Box<dyn DeCollectionStream>,
+    endpoint_name: JsonValue,
+    config: Arc<LineageConfig>,
+    tag: Arc<LineageTag>,
+
+    /// Buffer to serialize tagged records to.
+    buffer: Vec<u8>,
+}
+
+/// Set `column` of `record` to `value`, replacing its current value, if any.
+fn set_column(record: &mut Map<String, JsonValue>, column: &str, value: JsonValue) {
+    match column_mut(record, column) {
+        Some(old) => *old = value,
+        None => {
+            record.insert(column.to_string(), value);
+        }
+    }
+}
+
+impl DeCollectionStream for LineageStream {
+    fn insert(&mut self, data: &[u8]) -> AnyResult<()> {
+        let mut record = match serde_json::from_slice::<JsonValue>(data) {
+            Ok(JsonValue::Object(record)) => record,
+            // Records that aren't JSON objects, e.g., records encoded as
+            // arrays, are forwarded unmodified.
+            _ => return self.inner.insert(data),
+        };
+
+        if let Some(column) = &self.config.endpoint {
+            set_column(&mut record, column, self.endpoint_name.clone());
+        }
+        if let Some(column) = &self.config.offset {
+            set_column(&mut record, column, self.tag.offset.lock().unwrap().clone());
+        }
+        if let Some(column) = &self.config.ingested_at {
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis() as i64);
+            set_column(&mut record, column, JsonValue::from(millis));
+        }
+
+        self.buffer.clear();
+        // Serializing a `serde_json::Map` cannot fail.
+        serde_json::to_writer(&mut self.buffer, &record).unwrap();
+        self.inner.insert(&self.buffer)
+    }
+
+    fn delete(&mut self, data: &[u8]) -> AnyResult<()> {
+        self.inner.delete(data)
+    }
+
+    fn reserve(&mut self, reservation: usize) {
+        self.inner.reserve(reservation)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+
+    fn clear_buffer(&mut self) {
+        self.inner.clear_buffer()
+    }
+
+    fn fork(&self) -> Box<dyn DeCollectionStream> {
+        Box::new(Self {
+            inner: self.inner.fork(),
+            endpoint_name: self.endpoint_name.clone(),
+            config: self.config.clone(),
+            tag: self.tag.fork(),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LineageCollectionHandle;
+    use crate::{
+        catalog::RecordFormat,
+        format::{InputFormat, JsonParserConfig, JsonUpdateFormat},
+        test::{MockDeZSet, TestStruct},
+        DeCollectionHandle, LineageConfig,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_lineage() {
+        let config = LineageConfig {
+            endpoint: None,
+            offset: Some("S".to_string()),
+            ingested_at: Some("i".to_string()),
+        };
+
+        let input_handle = <MockDeZSet<TestStruct>>::new();
+        let handle = LineageCollectionHandle::new("test", &input_handle, &config).unwrap();
+        let tag = handle.tag();
+
+        // CSV records cannot be tagged.
+        assert!(handle
+            .configure_deserializer(RecordFormat::Csv(Default::default()))
+            .is_err());
+
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                &handle,
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap();
+
+        tag.set_offset(&json!("file.json"));
+        let (_, errors) = parser.input_chunk(br#"{"id": 1, "b": true, "i": null, "s": "foo"}"#);
+        assert!(errors.is_empty());
+
+        tag.set_offset(&json!({"partition": 0, "offset": 5}));
+        let (_, errors) = parser.input_chunk(br#"{"id": 2, "b": true, "s": "bar"}"#);
+        assert!(errors.is_empty());
+
+        // Forked parsers have their own offsets.
+        let mut forked_parser = parser.fork();
+        let forked_tag = tag.take_fork();
+        forked_tag.set_offset(&json!("other.json"));
+        let (_, errors) = forked_parser.input_chunk(br#"{"id": 3, "b": true, "s": "baz"}"#);
+        assert!(errors.is_empty());
+
+        let flushed = input_handle.state().flushed.clone();
+        let records: Vec<_> = flushed
+            .iter()
+            .map(|(record, _)| (record.id, record.s.as_str()))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (1, "file.json"),
+                (2, r#"{"offset":5,"partition":0}"#),
+                (3, "other.json")
+            ]
+        );
+        assert!(flushed
+            .iter()
+            .all(|(record, _)| record.i.map_or(false, |i| i > 0)));
+    }
+}
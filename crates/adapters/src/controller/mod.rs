@@ -35,37 +35,79 @@
 
 use crate::DbspCircuitHandle;
 use crate::{
-    catalog::SerBatch, Catalog, CircuitCatalog, Encoder, InputConsumer, InputEndpoint, InputFormat,
-    InputTransport, OutputConsumer, OutputEndpoint, OutputFormat, OutputQuery, OutputQueryHandles,
-    OutputTransport, ParseError, Parser, PipelineState,
+    catalog::SerBatch, Catalog, CircuitCatalog, DeCollectionHandle, Encoder, InputConsumer,
+    InputEndpoint, InputFormat, InputTransport, OutputConsumer, OutputEndpoint, OutputFormat,
+    OutputQuery, OutputQueryHandles, OutputTransport, ParseError, Parser, PipelineState,
 };
-use anyhow::Error as AnyError;
+use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
 use crossbeam::channel::{self, Sender};
 use crossbeam::{
     queue::SegQueue,
     sync::{Parker, ShardedLock, Unparker},
 };
-use log::{debug, error, info};
+use dbsp::{circuit::metadata::MetaItem, profile::OperatorProfile};
+use log::{debug, error, info, warn};
+use opentelemetry::{
+    trace::{Span, SpanContext},
+    KeyValue,
+};
+use opentelemetry_sdk::trace::Span as SdkSpan;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
 use std::{
+    cmp::min,
     collections::{BTreeMap, BTreeSet, HashSet},
+    mem::take,
+    ops::RangeInclusive,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex,
     },
-    thread::{spawn, JoinHandle},
+    thread::{sleep, spawn, JoinHandle},
     time::{Duration, Instant},
 };
 
+mod batch;
+mod checkpoint;
 mod config;
+mod dedup;
 mod error;
+mod history;
+mod lineage;
+mod parallel;
+mod projection;
+mod replay;
+mod retry;
+mod spill;
 mod stats;
+mod telemetry;
+mod throttle;
 
+use batch::{BatchCollectionHandle, InputBatch};
+pub use checkpoint::CheckpointConfig;
+use checkpoint::Checkpointer;
 pub use config::{
-    ConnectorConfig, FormatConfig, InputEndpointConfig, OutputEndpointConfig, PipelineConfig,
-    RuntimeConfig, TransportConfig,
+    ColumnCast, ColumnMappingConfig, ColumnPredicate, ConnectorConfig, DedupConfig, ErrorPolicy,
+    FormatConfig, InputEndpointConfig, LineageConfig, OutputEndpointConfig, OverflowPolicy,
+    ParserSharding, PipelineConfig, PredicateOp, RuntimeConfig, TlsConfig, TransportConfig,
 };
+use dedup::{DedupCollectionHandle, DedupFilter};
 pub use error::{ConfigError, ControllerError};
-pub use stats::{ControllerStatus, InputEndpointStatus, OutputEndpointStatus};
+use history::ViewHistory;
+use lineage::{LineageCollectionHandle, LineageTag};
+use parallel::{ParallelConsumer, ParserPool};
+use projection::ProjectedCollectionHandle;
+use replay::{RecordingConsumer, ReplayLog, Replayer};
+pub use replay::{ReplayConfig, ReplayMode};
+pub use retry::{is_transient_error, RetryConfig};
+use spill::SpillWriter;
+pub use stats::{
+    ControllerStatus, GlobalControllerMetrics, InputEndpointMetrics, InputEndpointStatus,
+    OutputEndpointMetrics, OutputEndpointStatus,
+};
+pub use telemetry::TracingConfig;
+use telemetry::{extract_trace_context, Telemetry};
+use throttle::Throttle;
 
 /// Maximal number of concurrent API connections per circuit
 /// (including both input and output connecions).
@@ -74,6 +116,77 @@ pub(crate) const MAX_API_CONNECTIONS: u64 = 100;
 
 pub(crate) type EndpointId = u64;
 
+/// Minimal interval between two samples of the memory used by the state of
+/// the circuit.  Computing the size of the state walks all traces, so we
+/// don't do it after every step.
+const MEMORY_SAMPLE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Callback invoked with the profile of every operator in each worker
+/// (see [`Controller::retrieve_profile`]).
+pub type ProfileCallback =
+    Box<dyn FnOnce(Result<Vec<Vec<OperatorProfile>>, ControllerError>) + Send>;
+
+/// Callback invoked with the sequence number of a checkpoint once it has been
+/// written (see [`Controller::checkpoint`]).
+pub type CheckpointCallback = Box<dyn FnOnce(Result<u64, ControllerError>) + Send>;
+
+/// Operator profiles captured before and after a number of circuit steps
+/// (see [`Controller::profile_steps`]).
+pub struct StepProfile {
+    /// Number of profiled steps.
+    pub steps: u64,
+
+    /// Time elapsed between the two profiles.
+    pub duration: Duration,
+
+    /// Profile of every operator in each worker before the first step.
+    pub before: Vec<Vec<OperatorProfile>>,
+
+    /// Profile of every operator in each worker after the last step.
+    pub after: Vec<Vec<OperatorProfile>>,
+}
+
+/// Callback invoked with the result of [`Controller::profile_steps`].
+pub type StepProfileCallback = Box<dyn FnOnce(Result<StepProfile, ControllerError>) + Send>;
+
+/// Number of records ingested and emitted by a circuit step (see
+/// [`Controller::step_with_stats`]).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepStats {
+    /// Number of input records processed by the step.
+    pub input_records: u64,
+
+    /// Number of records in the changes to all output streams produced by
+    /// the step.
+    pub output_records: u64,
+}
+
+/// Callback invoked with the result of [`Controller::step_with_stats`].
+pub type StepCallback = Box<dyn FnOnce(StepStats) + Send>;
+
+/// A [`Controller::profile_steps`] request in progress.
+struct PendingStepProfile {
+    steps: u64,
+    remaining_steps: u64,
+    start: Instant,
+    before: Vec<Vec<OperatorProfile>>,
+    cb: StepProfileCallback,
+}
+
+/// Approximate memory used by the state of the circuit: the sum of the
+/// `allocated bytes` reported in the metadata of all operators in all workers.
+fn state_bytes_of(profiles: &[Vec<OperatorProfile>]) -> u64 {
+    profiles
+        .iter()
+        .flatten()
+        .flat_map(|profile| profile.metadata.iter())
+        .filter_map(|(label, item)| match item {
+            MetaItem::Bytes(bytes) if label == "allocated bytes" => Some(bytes.bytes),
+            _ => None,
+        })
+        .sum()
+}
+
 /// Controller that coordinates the creation, reconfiguration, teardown of
 /// input/output adapters, and implements runtime flow control.
 ///
@@ -139,8 +252,24 @@ impl Controller {
         let backpressure_thread_parker = Parker::new();
         let backpressure_thread_unparker = backpressure_thread_parker.unparker().clone();
 
+        let telemetry = config
+            .global
+            .tracing
+            .as_ref()
+            .map(|tracing| Telemetry::new(tracing, config.name.as_deref()))
+            .transpose()
+            .map_err(|e| ControllerError::tracing_error(&e))?;
+
+        let replay = config.global.replay.as_ref();
+        let replay_log = replay
+            .filter(|replay| replay.mode == ReplayMode::Record)
+            .map(|replay| ReplayLog::create(&replay.path).map(Arc::new))
+            .transpose()?;
+
         let inner = Arc::new(ControllerInner::new(
             &config.global,
+            telemetry,
+            replay_log,
             circuit_thread_unparker,
             backpressure_thread_unparker,
             error_cb,
@@ -177,14 +306,26 @@ impl Controller {
             handle
         };
 
-        for (input_name, input_config) in config.inputs.iter() {
-            inner.connect_input(input_name, input_config)?;
+        // When replaying a log, input endpoints are connected by the replay.
+        let replayer = replay
+            .filter(|replay| replay.mode == ReplayMode::Replay)
+            .map(|replay| Replayer::open(&replay.path, inner.clone()))
+            .transpose()?;
+        if replayer.is_none() {
+            for (input_name, input_config) in config.inputs.iter() {
+                inner.connect_input(input_name, input_config)?;
+            }
         }
 
         for (output_name, output_config) in config.outputs.iter() {
             inner.connect_output(output_name, output_config)?;
         }
 
+        if let Some(replayer) = replayer {
+            inner.set_manual_stepping(true);
+            spawn(move || replayer.run());
+        }
+
         Ok(Self {
             inner,
             circuit_thread_handle,
@@ -223,6 +364,19 @@ impl Controller {
         self.inner.disconnect_input(endpoint_id)
     }
 
+    /// Disconnect all input endpoints and process the data they have received
+    /// so far.
+    ///
+    /// Requests a step of the circuit to process buffered inputs.  Use
+    /// [`Self::pipeline_complete`] to check whether all inputs have been
+    /// processed and the resulting outputs sent to all output endpoints.
+    /// Input endpoints may push a few more records while disconnecting;
+    /// the caller must request another step if
+    /// [`ControllerStatus::num_buffered_input_records`] is non-zero.
+    pub fn drain(&self) {
+        self.inner.drain();
+    }
+
     /// Connect a previously instantiated input endpoint.
     ///
     /// Used to connect an endpoint instantiated manually rather than from an
@@ -245,6 +399,51 @@ impl Controller {
             .add_input_endpoint(endpoint_name, endpoint_config, endpoint)
     }
 
+    /// Returns the id of input endpoint `endpoint_name`.
+    pub fn input_endpoint_id(&self, endpoint_name: &str) -> Result<EndpointId, ControllerError> {
+        self.inner
+            .status
+            .input_status()
+            .iter()
+            .find(|(_, endpoint)| endpoint.endpoint_name == endpoint_name)
+            .map(|(endpoint_id, _)| *endpoint_id)
+            .ok_or_else(|| ControllerError::unknown_input_endpoint(endpoint_name))
+    }
+
+    /// Connect a new output endpoint with specified name and configuration.
+    ///
+    /// Creates an endpoint with data transport and format specified by
+    /// `config`.  The endpoint receives the outputs of all steps performed
+    /// after it is connected.
+    ///
+    /// # Errors
+    ///
+    /// The method may fail for the following reasons:
+    ///
+    /// * The endpoint configuration is invalid, e.g., specifies an unknown
+    ///   transport or data format or a stream that doesn't exist.
+    ///
+    /// * The endpoint fails to initialize, e.g., because the network address or
+    ///   filename specified in the transport config is unreachable.
+    pub fn connect_output(
+        &self,
+        endpoint_name: &str,
+        config: &OutputEndpointConfig,
+    ) -> Result<EndpointId, ControllerError> {
+        self.inner.connect_output(endpoint_name, config)
+    }
+
+    /// Returns the id of output endpoint `endpoint_name`.
+    pub fn output_endpoint_id(&self, endpoint_name: &str) -> Result<EndpointId, ControllerError> {
+        self.inner
+            .status
+            .output_status()
+            .iter()
+            .find(|(_, endpoint)| endpoint.endpoint_name == endpoint_name)
+            .map(|(endpoint_id, _)| *endpoint_id)
+            .ok_or_else(|| ControllerError::unknown_output_endpoint(endpoint_name))
+    }
+
     /// Disconnect an existing output endpoint.
     ///
     /// This method is asynchronous and may return before all endpoint
@@ -273,7 +472,45 @@ impl Controller {
         endpoint: Box<dyn OutputEndpoint>,
     ) -> Result<EndpointId, ControllerError> {
         self.inner
-            .add_output_endpoint(endpoint_name, endpoint_config, endpoint)
+            .add_output_endpoint(endpoint_name, endpoint_config, endpoint, None)
+    }
+
+    /// Connect a previously instantiated output endpoint that monitors a
+    /// neighborhood of a table or view.
+    ///
+    /// Like [`Self::add_output_endpoint`], but assigns the endpoint to a
+    /// neighborhood session of the stream (see
+    /// [`NEIGHBORHOOD_SESSIONS`](`crate::NEIGHBORHOOD_SESSIONS`)).
+    /// Endpoints that monitor the same neighborhood, identified by
+    /// `params`, share a session; otherwise the endpoint gets a session
+    /// that isn't used by any other endpoint, so that clients monitoring
+    /// different neighborhoods of the same stream don't interfere.  Fails if
+    /// all sessions of the stream are in use.
+    ///
+    /// Use [`Self::neighborhood_session`] to find the session whose
+    /// descriptor handle the neighborhood query must be written to.
+    pub fn add_neighborhood_endpoint(
+        &self,
+        endpoint_name: &str,
+        endpoint_config: &OutputEndpointConfig,
+        endpoint: Box<dyn OutputEndpoint>,
+        params: &str,
+    ) -> Result<EndpointId, ControllerError> {
+        self.inner
+            .add_output_endpoint(endpoint_name, endpoint_config, endpoint, Some(params))
+    }
+
+    /// Returns the neighborhood session assigned to an output endpoint.
+    ///
+    /// Returns `None` if the endpoint doesn't exist.  Endpoints that don't
+    /// run neighborhood queries are assigned session `0`.
+    pub fn neighborhood_session(&self, endpoint_id: &EndpointId) -> Option<usize> {
+        self.inner
+            .outputs
+            .read()
+            .unwrap()
+            .lookup_by_id(endpoint_id)
+            .map(|endpoint| endpoint.session)
     }
 
     /// Increment the nubmber of active API connections.
@@ -313,6 +550,82 @@ impl Controller {
         self.inner.request_step();
     }
 
+    /// Enable or disable manual stepping mode.
+    ///
+    /// In manual stepping mode the circuit only performs a step when
+    /// explicitly requested via [`Self::step`].  Input endpoints keep
+    /// receiving data, which accumulates in their buffers (subject to
+    /// backpressure) until the next step.  This allows inspecting the state of
+    /// the pipeline between controlled steps.  Snapshot queries issued in this
+    /// mode are answered by the next step.
+    pub fn set_manual_stepping(&self, manual: bool) {
+        self.inner.set_manual_stepping(manual);
+    }
+
+    /// Perform a single step of the circuit, processing all buffered inputs.
+    ///
+    /// In manual stepping mode, this is the only way to advance the circuit.
+    /// Otherwise, equivalent to [`Self::request_step`].
+    pub fn step(&self) {
+        self.inner.step();
+    }
+
+    /// Like [`Self::step`], but also passes the number of records ingested
+    /// and emitted by the step to `cb` once the step has completed.
+    ///
+    /// `cb` is dropped without being invoked if the pipeline terminates
+    /// before performing the step.
+    pub fn step_with_stats(&self, cb: StepCallback) {
+        self.inner.step_with_stats(cb);
+    }
+
+    /// Contents of view `view_name` after step `step` of the circuit, as a
+    /// list of JSON records.
+    ///
+    /// Returns `None` if the pipeline does not retain the history of the
+    /// view (see [`RuntimeConfig::history`]) and `Some(Err(steps))`, where
+    /// `steps` are the steps whose versions are retained, if the requested
+    /// version is not retained.
+    pub fn view_as_of_step(
+        &self,
+        view_name: &str,
+        step: u64,
+    ) -> Option<Result<Vec<String>, RangeInclusive<u64>>> {
+        let histories = self.inner.histories.lock().unwrap();
+        let history = histories.get(view_name)?;
+        Some(history.as_of(step).ok_or_else(|| history.retained_steps()))
+    }
+
+    /// Submit parameters of the snapshot query of an output endpoint to the
+    /// circuit.
+    ///
+    /// `params` identifies the parameters of the query (e.g., the number of
+    /// quantiles) and is used to match subsequent queries against the
+    /// [snapshot cache](`Self::send_cached_snapshot`).  `submit` must write
+    /// the parameters to the input handles of the circuit.  Parameters are
+    /// only recorded if `submit` succeeds.
+    pub fn submit_snapshot_query<T, E>(
+        &self,
+        endpoint_id: &EndpointId,
+        params: &str,
+        submit: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        self.inner
+            .submit_snapshot_query(endpoint_id, params, submit)
+    }
+
+    /// Send a cached query result to an output endpoint.
+    ///
+    /// The controller caches snapshots produced by the most recent step of the
+    /// circuit until the next step.  If the cache contains a snapshot for the
+    /// stream and query of the endpoint, computed with parameters `params`,
+    /// queues this snapshot for the endpoint and returns `true`.  In this case
+    /// the caller doesn't need to submit the query to the circuit or request a
+    /// step.
+    pub fn send_cached_snapshot(&self, endpoint_id: &EndpointId, params: &str) -> bool {
+        self.inner.send_cached_snapshot(endpoint_id, params)
+    }
+
     /// Change the state of all input endpoints to running.
     ///
     /// Start streaming data through all connected input endpoints.
@@ -330,6 +643,55 @@ impl Controller {
         self.inner.pause();
     }
 
+    /// Pause input endpoint `endpoint_name`.
+    ///
+    /// The endpoint stays paused, even while the rest of the pipeline is
+    /// running, until it is resumed with [`Self::start_input_endpoint`].
+    pub fn pause_input_endpoint(&self, endpoint_name: &str) -> Result<(), ControllerError> {
+        self.inner.set_input_endpoint_paused(endpoint_name, true)
+    }
+
+    /// Resume input endpoint `endpoint_name` paused with
+    /// [`Self::pause_input_endpoint`].
+    ///
+    /// The endpoint only starts receiving data while the pipeline is running.
+    pub fn start_input_endpoint(&self, endpoint_name: &str) -> Result<(), ControllerError> {
+        self.inner.set_input_endpoint_paused(endpoint_name, false)
+    }
+
+    /// Apply a new transport configuration to input endpoint `endpoint_name`
+    /// without re-creating the endpoint.
+    ///
+    /// `config` replaces the `config` section of the endpoint's transport
+    /// configuration.  Transports only accept changes to settings that they
+    /// can apply to the live connection, e.g., poll intervals and
+    /// credentials; other changes are rejected and leave the endpoint
+    /// unmodified.  The new configuration is also used if the endpoint is
+    /// later re-created after a failure.
+    pub fn reconfigure_input_endpoint(
+        &self,
+        endpoint_name: &str,
+        config: &YamlValue,
+    ) -> Result<(), ControllerError> {
+        self.inner.reconfigure_input_endpoint(endpoint_name, config)
+    }
+
+    /// Pause output endpoint `endpoint_name`.
+    ///
+    /// Outputs are queued until the endpoint is resumed with
+    /// [`Self::start_output_endpoint`].  Once the endpoint's queue is full, the
+    /// circuit stops until there is room in the queue, unless the endpoint's
+    /// [`OverflowPolicy`] says otherwise.
+    pub fn pause_output_endpoint(&self, endpoint_name: &str) -> Result<(), ControllerError> {
+        self.inner.set_output_endpoint_paused(endpoint_name, true)
+    }
+
+    /// Resume output endpoint `endpoint_name` paused with
+    /// [`Self::pause_output_endpoint`].
+    pub fn start_output_endpoint(&self, endpoint_name: &str) -> Result<(), ControllerError> {
+        self.inner.set_output_endpoint_paused(endpoint_name, false)
+    }
+
     /// Returns controller status.
     pub fn status(&self) -> &ControllerStatus {
         // Update pipeline metrics computed on-demand.
@@ -345,6 +707,36 @@ impl Controller {
         self.inner.dump_profile();
     }
 
+    /// Retrieve the profile of every operator in the circuit.
+    ///
+    /// The profile is collected by the circuit thread between steps and
+    /// passed to `cb`.  Enables the CPU profiler if it is not already enabled,
+    /// so the first profile retrieved this way may not contain CPU usage.
+    pub fn retrieve_profile(&self, cb: ProfileCallback) {
+        self.inner.retrieve_profile(cb);
+    }
+
+    /// Profile the next `steps` steps of the circuit.
+    ///
+    /// Enables the CPU profiler if it is not already enabled and passes
+    /// operator profiles captured before the first and after the last of
+    /// these steps to `cb`.  The callback is not invoked until the circuit
+    /// has performed `steps` steps, e.g., it waits while the pipeline is
+    /// paused.  It is dropped without being invoked if the pipeline
+    /// terminates first.
+    pub fn profile_steps(&self, steps: u64, cb: StepProfileCallback) {
+        self.inner.profile_steps(steps, cb);
+    }
+
+    /// Checkpoint the state of the pipeline.
+    ///
+    /// The checkpoint is taken by the circuit thread between steps; its
+    /// sequence number is passed to `cb`.  Fails if checkpointing is not
+    /// enabled in the pipeline configuration.
+    pub fn checkpoint(&self, cb: CheckpointCallback) {
+        self.inner.checkpoint(cb);
+    }
+
     /// Terminate the controller, stop all input endpoints and destroy the
     /// circuit.
     pub fn stop(self) -> Result<(), ControllerError> {
@@ -392,30 +784,88 @@ impl Controller {
     {
         let mut start: Option<Instant> = None;
 
-        let mut circuit = match circuit_factory(controller.status.global_config.workers as usize) {
-            Ok((circuit, catalog)) => {
-                // Complete initialization before sending back the confirmation to
-                // prevent a race.
-                *controller.catalog.lock().unwrap() = catalog;
-                let _ = init_status_sender.send(Ok(()));
-                circuit
-            }
-            Err(e) => {
-                let _ = init_status_sender.send(Err(e));
-                return Ok(());
-            }
-        };
+        let (mut circuit, mut checkpointer) =
+            match circuit_factory(controller.status.global_config.workers as usize) {
+                Ok((mut circuit, catalog)) => {
+                    // Enable column statistics before the circuit receives any inputs.
+                    for stream_name in controller.status.global_config.column_statistics.iter() {
+                        match catalog
+                            .output_handles(stream_name)
+                            .and_then(|handles| handles.column_stats_handle.as_ref())
+                        {
+                            Some(handle) => handle.enable(),
+                            None => {
+                                let _ = init_status_sender.send(Err(
+                                    ControllerError::column_statistics_not_supported(stream_name),
+                                ));
+                                return Ok(());
+                            }
+                        }
+                    }
+
+                    // Start retaining the history of views before the circuit receives any
+                    // inputs.
+                    for (view_name, &versions) in controller.status.global_config.history.iter() {
+                        let error = if catalog.output_handles(view_name).is_none() {
+                            Some("the table or view does not exist")
+                        } else if versions == 0 {
+                            Some("the number of retained versions must be at least 1")
+                        } else {
+                            None
+                        };
+                        if let Some(error) = error {
+                            let _ = init_status_sender.send(Err(
+                                ControllerError::invalid_history_config(view_name, &error),
+                            ));
+                            return Ok(());
+                        }
+                        controller
+                            .histories
+                            .lock()
+                            .unwrap()
+                            .insert(view_name.clone(), ViewHistory::new(versions));
+                    }
+
+                    // Restore the circuit before it performs its first step.
+                    let checkpointer = match controller.restore_checkpoint(circuit.as_mut()) {
+                        Ok(checkpointer) => checkpointer,
+                        Err(e) => {
+                            let _ = init_status_sender.send(Err(e));
+                            return Ok(());
+                        }
+                    };
 
+                    // Complete initialization before sending back the confirmation to
+                    // prevent a race.
+                    *controller.catalog.lock().unwrap() = catalog;
+                    let _ = init_status_sender.send(Ok(()));
+                    (circuit, checkpointer)
+                }
+                Err(e) => {
+                    let _ = init_status_sender.send(Err(e));
+                    return Ok(());
+                }
+            };
+
+        let mut cpu_profiler_enabled = false;
         if controller.status.global_config.cpu_profiler {
             circuit.enable_cpu_profiler().unwrap_or_else(|e| {
                 error!("Failed to enable CPU profiler: {e}");
             });
+            cpu_profiler_enabled = true;
         }
 
         let max_buffering_delay =
             Duration::from_micros(controller.status.global_config.max_buffering_delay_usecs);
         let min_batch_size_records = controller.status.global_config.min_batch_size_records;
 
+        // `profile_steps` requests waiting for the circuit to perform the
+        // requested number of steps.
+        let mut step_profiles: Vec<PendingStepProfile> = Vec::new();
+
+        // Time of the last memory usage sample.
+        let mut memory_sampled: Option<Instant> = None;
+
         loop {
             let dump_profile = controller
                 .dump_profile_request
@@ -435,6 +885,42 @@ impl Controller {
                     }
                 }
             }
+            while let Some(cb) = controller.profile_requests.pop() {
+                if !cpu_profiler_enabled {
+                    circuit.enable_cpu_profiler().unwrap_or_else(|e| {
+                        error!("Failed to enable CPU profiler: {e}");
+                    });
+                    cpu_profiler_enabled = true;
+                }
+                cb(circuit.retrieve_profile());
+            }
+            while let Some((steps, cb)) = controller.step_profile_requests.pop() {
+                if !cpu_profiler_enabled {
+                    circuit.enable_cpu_profiler().unwrap_or_else(|e| {
+                        error!("Failed to enable CPU profiler: {e}");
+                    });
+                    cpu_profiler_enabled = true;
+                }
+                match circuit.retrieve_profile() {
+                    Ok(before) if steps == 0 => cb(Ok(StepProfile {
+                        steps,
+                        duration: Duration::ZERO,
+                        before: before.clone(),
+                        after: before,
+                    })),
+                    Ok(before) => step_profiles.push(PendingStepProfile {
+                        steps,
+                        remaining_steps: steps,
+                        start: Instant::now(),
+                        before,
+                        cb,
+                    }),
+                    Err(e) => cb(Err(e)),
+                }
+            }
+            while let Some(cb) = controller.checkpoint_requests.pop() {
+                cb(controller.take_checkpoint(circuit.as_mut(), checkpointer.as_mut()));
+            }
             match controller.state() {
                 PipelineState::Running | PipelineState::Paused => {
                     // Backpressure in the output pipeline: wait for room in output buffers to
@@ -447,41 +933,162 @@ impl Controller {
                     }
 
                     let buffered_records = controller.status.num_buffered_input_records();
+                    let manual_stepping = controller.status.manual_stepping();
 
                     // We have sufficient buffered inputs or the buffering delay has expired or
                     // the client explicitly requested the circuit to run -- kick the circuit to
                     // consume buffered data.
                     // Use strict inequality in case `min_batch_size_records` is 0.
-                    if controller.status.step_requested()
-                        || buffered_records > min_batch_size_records
-                        || start
-                            .map(|start| start.elapsed() >= max_buffering_delay)
-                            .unwrap_or(false)
+                    // In manual stepping mode, only run the circuit when the user asks for it.
+                    if (manual_stepping && controller.status.manual_step_requested())
+                        || (!manual_stepping
+                            && (controller.status.step_requested()
+                                || buffered_records > min_batch_size_records
+                                || start
+                                    .map(|start| start.elapsed() >= max_buffering_delay)
+                                    .unwrap_or(false)))
                     {
                         start = None;
+
+                        // While recording inputs, endpoints cannot record more data until
+                        // the step completes, so the log attributes all recorded data to the
+                        // step that processes it.
+                        let mut replay_log = controller.replay_log.as_ref().map(|log| log.lock());
+
                         // Reset all counters of buffered records and bytes to 0.
                         controller.status.consume_buffered_inputs();
 
                         // All input records accumulated so far (and possibly some more) will
                         // be fully processed after the `step()` call returns.
                         let processed_records = controller.status.num_total_input_records();
+                        let input_records = processed_records
+                            .saturating_sub(controller.status.num_total_processed_records());
+
+                        // `step_with_stats` requests answered by this step.  Requests that
+                        // arrive while the step is in progress are answered by the next step.
+                        let mut step_callbacks = Vec::new();
+                        while let Some(cb) = controller.step_requests.pop() {
+                            step_callbacks.push(cb);
+                        }
 
                         // Wake up the backpressure thread to unpause endpoints blocked due to
                         // backpressure.
                         controller.unpark_backpressure();
+
+                        // Cached snapshots become stale as soon as the circuit starts
+                        // processing new inputs.
+                        let snapshot_generation =
+                            controller.snapshot_cache.lock().unwrap().invalidate();
+
+                        // All inputs received by the endpoints so far will be processed by this
+                        // step.  Wait for parallel parsers to push these inputs to the circuit.
+                        // Flushing blocks until the parsers drain their queues, so release the
+                        // `inputs` lock first: parser threads may need it, e.g., to report errors.
+                        let parser_pools = controller
+                            .inputs
+                            .lock()
+                            .unwrap()
+                            .values()
+                            .filter_map(|input| {
+                                input.endpoint.step_started();
+                                input.parsers.clone()
+                            })
+                            .collect::<Vec<_>>();
+                        for parsers in parser_pools {
+                            parsers.flush();
+                        }
+
+                        let mut step_span = controller
+                            .telemetry
+                            .as_ref()
+                            .map(|telemetry| telemetry.step_span());
+
                         debug!("circuit thread: calling 'circuit.step'");
-                        circuit.step().unwrap_or_else(|e| controller.error(e));
+                        match circuit.step() {
+                            Ok(()) => {
+                                for input in controller.inputs.lock().unwrap().values() {
+                                    input.endpoint.step_completed();
+                                }
+                            }
+                            Err(e) => controller.error(e),
+                        }
+                        controller.status.step_completed();
                         debug!("circuit thread: 'circuit.step' returned");
 
+                        if let (Some(log), Some(writer)) =
+                            (&controller.replay_log, replay_log.as_mut())
+                        {
+                            if let Err(e) = writer.step_completed(log) {
+                                controller.error(e);
+                            }
+                        }
+                        drop(replay_log);
+
+                        let step_span_context = step_span.as_mut().map(|span| {
+                            span.end();
+                            span.span_context().clone()
+                        });
+
+                        for pending in step_profiles.iter_mut() {
+                            pending.remaining_steps -= 1;
+                        }
+                        let (completed, pending): (Vec<_>, Vec<_>) = take(&mut step_profiles)
+                            .into_iter()
+                            .partition(|pending| pending.remaining_steps == 0);
+                        step_profiles = pending;
+                        for completed in completed {
+                            (completed.cb)(circuit.retrieve_profile().map(|after| StepProfile {
+                                steps: completed.steps,
+                                duration: completed.start.elapsed(),
+                                before: completed.before,
+                                after,
+                            }));
+                        }
+
+                        if let Some(checkpointer) = checkpointer.as_mut() {
+                            if checkpointer.due() {
+                                if let Err(e) =
+                                    controller.take_checkpoint(circuit.as_mut(), Some(checkpointer))
+                                {
+                                    controller.error(e);
+                                }
+                            }
+                        }
+
+                        if memory_sampled
+                            .map_or(true, |sampled| sampled.elapsed() >= MEMORY_SAMPLE_PERIOD)
+                        {
+                            match circuit.retrieve_profile() {
+                                Ok(profiles) => controller
+                                    .status
+                                    .update_memory_usage(state_bytes_of(&profiles)),
+                                Err(e) => {
+                                    error!("Failed to estimate the size of the circuit state: {e}")
+                                }
+                            }
+                            memory_sampled = Some(Instant::now());
+                        }
+
                         controller
                             .status
                             .set_num_total_processed_records(processed_records);
 
                         // Push output batches to output pipelines.
                         let outputs = controller.outputs.read().unwrap();
-                        for ((_stream, _query), (output_handles, endpoints)) in
-                            outputs.iter_by_stream()
-                        {
+
+                        // Only cache snapshots if no query parameters were submitted
+                        // while the circuit was running, as we don't know whether the
+                        // snapshot reflects the old or the new parameters.
+                        let mut snapshot_cache = controller.snapshot_cache.lock().unwrap();
+                        let cache_snapshots = snapshot_cache.generation == snapshot_generation;
+
+                        // Changes to views with retained history, captured from the outputs of
+                        // `table` queries.
+                        let mut histories = controller.histories.lock().unwrap();
+                        let mut history_batches = BTreeMap::new();
+
+                        let mut output_records = 0;
+                        for (key, (output_handles, endpoints)) in outputs.iter_by_stream() {
                             // TODO: add an endpoint config option to consolidate output batches.
 
                             let mut delta_batch = output_handles
@@ -491,6 +1098,13 @@ impl Controller {
                             let num_delta_records = delta_batch
                                 .as_ref()
                                 .map(|batch| batch.iter().map(|b| b.len()).sum());
+                            output_records += num_delta_records.unwrap_or(0) as u64;
+
+                            if key.1 == OutputQuery::Table && histories.contains_key(&key.0) {
+                                if let Some(batch) = delta_batch.as_ref() {
+                                    history_batches.insert(key.0.clone(), batch.clone());
+                                }
+                            }
 
                             let mut snapshot_batch = output_handles
                                 .snapshot
@@ -500,6 +1114,19 @@ impl Controller {
                                 .as_ref()
                                 .map(|batch| batch.iter().map(|b| b.len()).sum());
 
+                            if cache_snapshots {
+                                if let Some(batch) = snapshot_batch.as_ref() {
+                                    if !batch.is_empty() {
+                                        snapshot_cache.insert(
+                                            key,
+                                            batch,
+                                            num_snapshot_records.unwrap(),
+                                            processed_records,
+                                        );
+                                    }
+                                }
+                            }
+
                             for (i, endpoint_id) in endpoints.iter().enumerate() {
                                 let endpoint = outputs.lookup_by_id(endpoint_id).unwrap();
 
@@ -534,9 +1161,24 @@ impl Controller {
                                         // been sent to the output endpoint, the endpoint will get
                                         // labeled with this
                                         // frontier.
-                                        endpoint.queue.push((batch, processed_records));
+                                        endpoint.queue.push((
+                                            batch,
+                                            processed_records,
+                                            step_span_context.clone(),
+                                        ));
                                         endpoint.snapshot_sent.store(true, Ordering::Release);
                                     }
+                                } else if delta_batch.is_some()
+                                    && endpoint.overflow == OverflowPolicy::Drop
+                                    && controller.status.output_buffer_full(*endpoint_id)
+                                {
+                                    // The endpoint is behind; discard the outputs of this step
+                                    // instead of holding back the circuit.
+                                    controller.status.dropped_records(
+                                        *endpoint_id,
+                                        processed_records,
+                                        num_delta_records.unwrap(),
+                                    );
                                 } else if delta_batch.is_some() {
                                     controller
                                         .status
@@ -548,7 +1190,11 @@ impl Controller {
                                         delta_batch.as_ref().unwrap().clone()
                                     };
 
-                                    endpoint.queue.push((batch, processed_records));
+                                    endpoint.queue.push((
+                                        batch,
+                                        processed_records,
+                                        step_span_context.clone(),
+                                    ));
                                 }
 
                                 // Wake up the output thread.  We're not trying to be smart here and
@@ -557,7 +1203,38 @@ impl Controller {
                                 endpoint.unparker.unpark();
                             }
                         }
-                    } else if buffered_records > 0 {
+                        drop(snapshot_cache);
+                        drop(outputs);
+
+                        // Views that aren't consumed by any `table` queries are read
+                        // directly from the catalog.
+                        if !histories.is_empty() {
+                            let catalog = controller.catalog.lock().unwrap();
+                            histories.retain(|view_name, history| {
+                                let batch = history_batches.remove(view_name).unwrap_or_else(|| {
+                                    catalog
+                                        .output_handles(view_name)
+                                        .map(|handles| handles.delta_handle.take_from_all())
+                                        .unwrap_or_default()
+                                });
+                                match history.record(&batch) {
+                                    Ok(()) => true,
+                                    Err(e) => {
+                                        error!("Failed to record the history of '{view_name}', no longer retaining it: {e}");
+                                        false
+                                    }
+                                }
+                            });
+                        }
+                        drop(histories);
+
+                        for cb in step_callbacks {
+                            cb(StepStats {
+                                input_records,
+                                output_records,
+                            });
+                        }
+                    } else if buffered_records > 0 && !manual_stepping {
                         // We have some buffered data, but less than `min_batch_size_records` --
                         // wait up to `max_buffering_delay` for more data to
                         // arrive.
@@ -587,7 +1264,7 @@ impl Controller {
         // `Controller::pause()` methods).
         let mut global_pause = true;
 
-        // Endpoints paused due to backpressure.
+        // Endpoints paused due to backpressure or by the user.
         let mut paused_endpoints = HashSet::new();
 
         loop {
@@ -614,11 +1291,14 @@ impl Controller {
                     global_pause = true;
                 }
                 PipelineState::Running => {
-                    // Resume endpoints that have buffer space, pause endpoints with full buffers.
+                    // Resume endpoints that have buffer space, pause endpoints with full buffers
+                    // and endpoints paused by the user.
                     for (epid, ep) in inputs.iter() {
-                        if controller.status.input_endpoint_full(epid) {
-                            // The endpoint is full and is not yet in the paused state -- pause it
-                            // now.
+                        if controller.status.input_endpoint_full(epid)
+                            || controller.status.input_endpoint_paused(epid)
+                        {
+                            // The endpoint is full or paused by the user and is not yet in the
+                            // paused state -- pause it now.
                             if !global_pause && !paused_endpoints.contains(epid) {
                                 ep.endpoint.pause().unwrap_or_else(|e| {
                                     controller.input_transport_error(
@@ -661,13 +1341,28 @@ impl Controller {
 struct InputEndpointDescr {
     endpoint_name: String,
     endpoint: Box<dyn InputEndpoint>,
+
+    /// Parser threads of the endpoint, if it is configured with multiple
+    /// parsers.
+    parsers: Option<Arc<ParserPool>>,
+
+    /// Keys received by the endpoint, if it is configured with `dedup`.
+    /// Preserved when the endpoint is re-created after a failure.
+    dedup: Option<Arc<DedupFilter>>,
 }
 
 impl InputEndpointDescr {
-    pub fn new(endpoint_name: &str, endpoint: Box<dyn InputEndpoint>) -> Self {
+    pub fn new(
+        endpoint_name: &str,
+        endpoint: Box<dyn InputEndpoint>,
+        parsers: Option<Arc<ParserPool>>,
+        dedup: Option<Arc<DedupFilter>>,
+    ) -> Self {
         Self {
             endpoint_name: endpoint_name.to_owned(),
             endpoint,
+            parsers,
+            dedup,
         }
     }
 }
@@ -676,8 +1371,46 @@ impl InputEndpointDescr {
 /// to output endpoint threads.  Each entry is annotated with a progress label
 /// that is equal to the number of input records fully processed by
 /// DBSP before emitting this batch of outputs.  The label increases
-/// monotonically over time.
-type BatchQueue = SegQueue<(Vec<Arc<dyn SerBatch>>, u64)>;
+/// monotonically over time.  When tracing is enabled, entries also carry the
+/// span context of the step that produced them.
+type BatchQueue = SegQueue<BatchQueueEntry>;
+
+type BatchQueueEntry = (Vec<Arc<dyn SerBatch>>, u64, Option<SpanContext>);
+
+/// Outputs of one or more steps waiting to be sent to an output endpoint as
+/// a single batch (see `ConnectorConfig::max_batch_delay_ms`).
+#[derive(Default)]
+struct PendingOutput {
+    batches: Vec<Arc<dyn SerBatch>>,
+
+    /// Total number of records in `batches`.
+    num_records: u64,
+
+    /// Number of steps whose outputs are in `batches`.
+    num_steps: usize,
+
+    /// Progress label of the latest step in the batch.
+    processed_records: u64,
+
+    /// Span context of the first step in the batch.
+    step_span_context: Option<SpanContext>,
+
+    /// Time the first step's outputs were dequeued.
+    since: Option<Instant>,
+}
+
+impl PendingOutput {
+    fn push(&mut self, (batches, processed_records, step_span_context): BatchQueueEntry) {
+        self.num_records += batches.iter().map(|b| b.len() as u64).sum::<u64>();
+        self.batches.extend(batches);
+        self.num_steps += 1;
+        self.processed_records = processed_records;
+        if self.step_span_context.is_none() {
+            self.step_span_context = step_span_context;
+        }
+        self.since.get_or_insert_with(Instant::now);
+    }
+}
 
 /// State tracked by the controller for each output endpoint.
 struct OutputEndpointDescr {
@@ -690,6 +1423,13 @@ struct OutputEndpointDescr {
     /// Query associated with the endpoint.
     query: OutputQuery,
 
+    /// Neighborhood session used by the endpoint; `0` for queries other than
+    /// [neighborhood](`OutputQuery::Neighborhood`).
+    session: usize,
+
+    /// What to do when the endpoint falls behind.
+    overflow: OverflowPolicy,
+
     /// FIFO queue of batches read from the stream.
     queue: Arc<BatchQueue>,
 
@@ -710,22 +1450,108 @@ impl OutputEndpointDescr {
         endpoint_name: &str,
         stream_name: &str,
         query: OutputQuery,
+        session: usize,
+        overflow: OverflowPolicy,
         unparker: Unparker,
     ) -> Self {
         Self {
             endpoint_name: endpoint_name.to_string(),
             stream_name: stream_name.to_string(),
             query,
+            session,
+            overflow,
             queue: Arc::new(SegQueue::new()),
             snapshot_sent: AtomicBool::new(false),
             disconnect_flag: Arc::new(AtomicBool::new(false)),
             unparker,
         }
     }
+
+    /// Key of the query results consumed by the endpoint.
+    fn key(&self) -> OutputStreamKey {
+        (self.stream_name.clone(), self.query, self.session)
+    }
+}
+
+/// Identifies the results of a query over an output stream: stream name,
+/// query, and neighborhood session (`0` for queries other than
+/// [neighborhood](`OutputQuery::Neighborhood`)).
+type OutputStreamKey = (String, OutputQuery, usize);
+
+/// Result of a snapshot query computed by the latest step of the circuit.
+struct CachedSnapshot {
+    /// Query parameters used to compute the snapshot.
+    params: String,
+    batch: Vec<Arc<dyn SerBatch>>,
+    num_records: usize,
+    /// Progress label of the batch (see [`BatchQueue`]).
+    processed_records: u64,
+}
+
+/// Results of snapshot queries (neighborhoods, quantiles, samples) computed
+/// by the latest step of the circuit.
+///
+/// Clients such as dashboards often issue identical snapshot queries every
+/// few seconds.  When the circuit hasn't performed a step since the
+/// previous identical query, the result is served from this cache instead of
+/// running the circuit and recomputing the query.  The cache is invalidated
+/// at the start of every step.
+#[derive(Default)]
+struct SnapshotCache {
+    /// Incremented whenever new query parameters are submitted to the
+    /// circuit.
+    generation: u64,
+
+    /// The most recent parameters submitted for each stream and query.
+    params: BTreeMap<OutputStreamKey, String>,
+
+    /// Snapshots produced by the latest step.
+    snapshots: BTreeMap<OutputStreamKey, CachedSnapshot>,
+}
+
+impl SnapshotCache {
+    /// Drop all cached snapshots; returns the current generation.
+    fn invalidate(&mut self) -> u64 {
+        self.snapshots.clear();
+        self.generation
+    }
+
+    fn set_params(&mut self, key: OutputStreamKey, params: &str) {
+        self.generation += 1;
+        self.params.insert(key, params.to_string());
+    }
+
+    /// The most recent parameters submitted for `key`.
+    fn params(&self, key: &OutputStreamKey) -> Option<&str> {
+        self.params.get(key).map(String::as_str)
+    }
+
+    fn insert(
+        &mut self,
+        key: &OutputStreamKey,
+        batch: &[Arc<dyn SerBatch>],
+        num_records: usize,
+        processed_records: u64,
+    ) {
+        if let Some(params) = self.params.get(key) {
+            let snapshot = CachedSnapshot {
+                params: params.clone(),
+                batch: batch.to_vec(),
+                num_records,
+                processed_records,
+            };
+            self.snapshots.insert(key.clone(), snapshot);
+        }
+    }
+
+    fn lookup(&self, key: &OutputStreamKey, params: &str) -> Option<&CachedSnapshot> {
+        self.snapshots
+            .get(key)
+            .filter(|snapshot| snapshot.params == params)
+    }
 }
 
-type StreamEndpointMap =
-    BTreeMap<(String, OutputQuery), (OutputQueryHandles, BTreeSet<EndpointId>)>;
+type StreamEndpointMap = BTreeMap<OutputStreamKey, (OutputQueryHandles, BTreeSet<EndpointId>)>;
 
 struct OutputEndpoints {
     by_id: BTreeMap<EndpointId, OutputEndpointDescr>,
@@ -744,13 +1570,53 @@ impl OutputEndpoints {
         &self,
     ) -> impl Iterator<
         Item = (
-            &'_ (String, OutputQuery),
+            &'_ OutputStreamKey,
             &'_ (OutputQueryHandles, BTreeSet<EndpointId>),
         ),
     > {
         self.by_stream.iter()
     }
 
+    /// Choose one of `num_sessions` neighborhood sessions of `stream_name`
+    /// for a new endpoint.
+    ///
+    /// Returns a session used by other endpoints to monitor the same
+    /// neighborhood (`params`) if there is one, and an unused session
+    /// otherwise.  Returns `None` if all sessions are in use.
+    ///
+    /// An unused session keeps monitoring its last neighborhood until it
+    /// gets reused, so a cached snapshot of this neighborhood remains valid
+    /// when an endpoint with the same `params` gets assigned to the session.
+    fn neighborhood_session(
+        &self,
+        stream_name: &str,
+        num_sessions: usize,
+        params: Option<&str>,
+        snapshot_cache: &SnapshotCache,
+    ) -> Option<usize> {
+        // Circuits without neighborhood support don't produce any outputs
+        // for neighborhood queries.
+        if num_sessions == 0 {
+            return Some(0);
+        }
+
+        let key = |session| (stream_name.to_string(), OutputQuery::Neighborhood, session);
+        let in_use = |session| {
+            self.by_stream
+                .get(&key(session))
+                .map(|(_, endpoints)| !endpoints.is_empty())
+                .unwrap_or(false)
+        };
+
+        params
+            .and_then(|params| {
+                (0..num_sessions).find(|session| {
+                    in_use(*session) && snapshot_cache.params(&key(*session)) == Some(params)
+                })
+            })
+            .or_else(|| (0..num_sessions).find(|session| !in_use(*session)))
+    }
+
     fn lookup_by_id(&self, endpoint_id: &EndpointId) -> Option<&OutputEndpointDescr> {
         self.by_id.get(endpoint_id)
     }
@@ -772,7 +1638,7 @@ impl OutputEndpoints {
         endpoint_descr: OutputEndpointDescr,
     ) {
         self.by_stream
-            .entry((endpoint_descr.stream_name.clone(), endpoint_descr.query))
+            .entry(endpoint_descr.key())
             .or_insert_with(|| (handles, BTreeSet::new()))
             .1
             .insert(endpoint_id);
@@ -782,7 +1648,7 @@ impl OutputEndpoints {
     fn remove(&mut self, endpoint_id: &EndpointId) -> Option<OutputEndpointDescr> {
         self.by_id.remove(endpoint_id).map(|descr| {
             self.by_stream
-                .get_mut(&(descr.stream_name.clone(), descr.query))
+                .get_mut(&descr.key())
                 .map(|(_, endpoints)| endpoints.remove(endpoint_id));
             descr
         })
@@ -797,17 +1663,38 @@ struct ControllerInner {
     status: Arc<ControllerStatus>,
     num_api_connections: AtomicU64,
     dump_profile_request: AtomicBool,
+    profile_requests: SegQueue<ProfileCallback>,
+    step_profile_requests: SegQueue<(u64, StepProfileCallback)>,
+    checkpoint_requests: SegQueue<CheckpointCallback>,
+    step_requests: SegQueue<StepCallback>,
+
+    /// Positions of input endpoints restored from a checkpoint, by endpoint
+    /// name.  An entry is removed once the endpoint has been connected.
+    restored_positions: Mutex<BTreeMap<String, JsonValue>>,
+
+    /// Retained history of views, by view name.
+    histories: Mutex<BTreeMap<String, ViewHistory>>,
     catalog: Arc<Mutex<Box<dyn CircuitCatalog>>>,
     inputs: Mutex<BTreeMap<EndpointId, InputEndpointDescr>>,
     outputs: ShardedLock<OutputEndpoints>,
+    snapshot_cache: Mutex<SnapshotCache>,
     circuit_thread_unparker: Unparker,
     backpressure_thread_unparker: Unparker,
     error_cb: Box<dyn Fn(ControllerError) + Send + Sync>,
+
+    /// OpenTelemetry tracing, `None` if tracing is disabled.
+    telemetry: Option<Telemetry>,
+
+    /// Log that input endpoints record their data to, `None` unless
+    /// recording is enabled.
+    replay_log: Option<Arc<ReplayLog>>,
 }
 
 impl ControllerInner {
     fn new(
         global_config: &RuntimeConfig,
+        telemetry: Option<Telemetry>,
+        replay_log: Option<Arc<ReplayLog>>,
         circuit_thread_unparker: Unparker,
         backpressure_thread_unparker: Unparker,
         error_cb: Box<dyn Fn(ControllerError) + Send + Sync>,
@@ -819,12 +1706,21 @@ impl ControllerInner {
             status,
             num_api_connections: AtomicU64::new(0),
             dump_profile_request,
+            profile_requests: SegQueue::new(),
+            step_profile_requests: SegQueue::new(),
+            checkpoint_requests: SegQueue::new(),
+            step_requests: SegQueue::new(),
+            restored_positions: Mutex::new(BTreeMap::new()),
+            histories: Mutex::new(BTreeMap::new()),
             catalog: Arc::new(Mutex::new(Box::new(Catalog::new()))),
             inputs: Mutex::new(BTreeMap::new()),
             outputs: ShardedLock::new(OutputEndpoints::new()),
+            snapshot_cache: Mutex::new(SnapshotCache::default()),
             circuit_thread_unparker,
             backpressure_thread_unparker,
             error_cb,
+            telemetry,
+            replay_log,
         }
     }
 
@@ -864,12 +1760,25 @@ impl ControllerInner {
         }
     }
 
+    fn drain(self: &Arc<Self>) {
+        let endpoint_ids: Vec<EndpointId> = self.inputs.lock().unwrap().keys().copied().collect();
+        for endpoint_id in endpoint_ids.iter() {
+            self.disconnect_input(endpoint_id);
+        }
+        self.step();
+    }
+
     fn add_input_endpoint(
         self: &Arc<Self>,
         endpoint_name: &str,
         endpoint_config: InputEndpointConfig,
         mut endpoint: Box<dyn InputEndpoint>,
     ) -> Result<EndpointId, ControllerError> {
+        // While recording inputs, lock the log until the endpoint is
+        // connected, so that the endpoint's configuration is recorded before
+        // any of its data.  The log is locked before `inputs`, as in the
+        // circuit thread.
+        let mut replay_log = self.replay_log.as_ref().map(|log| (log, log.lock()));
         let mut inputs = self.inputs.lock().unwrap();
 
         if inputs.values().any(|ep| ep.endpoint_name == endpoint_name) {
@@ -882,9 +1791,128 @@ impl ControllerInner {
         // ┌────────┐   ┌──────────┐   ┌──────┐
         // │endpoint├──►│InputProbe├──►│parser├──►
         // └────────┘   └──────────┘   └──────┘
+        //
+        // With multiple parsers, the endpoint feeds a pool of parser threads,
+        // each with its own probe and parser.
 
-        let catalog = self.catalog.lock().unwrap();
-        let input_stream = catalog
+        let endpoint_id = inputs.keys().next_back().map(|k| k + 1).unwrap_or(0);
+        let dedup = endpoint_config
+            .dedup
+            .as_ref()
+            .map(|config| Arc::new(DedupFilter::new(config)));
+        let (consumer, parsers) = self.new_input_consumer(
+            endpoint_id,
+            endpoint_name,
+            &endpoint_config,
+            dedup.as_ref(),
+            0,
+        )?;
+
+        // Resume from the checkpointed position, if any.
+        let position = self
+            .restored_positions
+            .lock()
+            .unwrap()
+            .remove(endpoint_name);
+        if let Some(position) = position {
+            endpoint
+                .seek(position)
+                .map_err(|e| ControllerError::input_transport_error(endpoint_name, true, e))?;
+        }
+
+        if let Some((log, writer)) = replay_log.as_mut() {
+            writer.connect(log, endpoint_name, &endpoint_config)?;
+        }
+
+        // Initialize endpoint stats.
+        self.status
+            .add_input(&endpoint_id, endpoint_name, endpoint_config);
+
+        endpoint
+            .connect(consumer)
+            .map_err(|e| ControllerError::input_transport_error(endpoint_name, true, e))?;
+        if self.state() == PipelineState::Running {
+            endpoint
+                .start()
+                .map_err(|e| ControllerError::input_transport_error(endpoint_name, true, e))?;
+        }
+
+        inputs.insert(
+            endpoint_id,
+            InputEndpointDescr::new(endpoint_name, endpoint, parsers, dedup),
+        );
+
+        drop(inputs);
+        drop(replay_log);
+
+        self.unpark_backpressure();
+        Ok(endpoint_id)
+    }
+
+    /// Create the consumer that input endpoint `endpoint_id` pushes data to:
+    /// either a probe and parser, or a pool of parser threads if the endpoint
+    /// is configured with multiple parsers.  While recording inputs, the
+    /// consumer also records the data in the replay log.
+    ///
+    /// `dedup` is the endpoint's deduplication filter, if any.  `retry` is
+    /// the number of consecutive failed attempts to run the endpoint
+    /// preceding this one.
+    fn new_input_consumer(
+        self: &Arc<Self>,
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        endpoint_config: &InputEndpointConfig,
+        dedup: Option<&Arc<DedupFilter>>,
+        retry: u32,
+    ) -> Result<(Box<dyn InputConsumer>, Option<Arc<ParserPool>>), ControllerError> {
+        let probe =
+            self.new_input_probe(endpoint_id, endpoint_name, endpoint_config, dedup, retry)?;
+        let connector_config = &endpoint_config.connector_config;
+        let (consumer, parsers): (Box<dyn InputConsumer>, _) = match connector_config.num_parsers {
+            // Parallel parsers may push records to the circuit in a different
+            // order than the endpoint received them.
+            Some(num_parsers) if num_parsers > 1 && probe.parser.is_order_sensitive() => {
+                info!(
+                    "input endpoint '{endpoint_name}': the input format depends on the order of records; using a single parser instead of {num_parsers}"
+                );
+                (probe, None)
+            }
+            Some(num_parsers) if num_parsers > 1 => {
+                let parsers = Arc::new(ParserPool::default());
+                let consumer = ParallelConsumer::new(
+                    probe,
+                    num_parsers,
+                    connector_config.parser_sharding.unwrap_or_default(),
+                    parsers.clone(),
+                );
+                (Box::new(consumer), Some(parsers))
+            }
+            _ => (probe, None),
+        };
+        match &self.replay_log {
+            Some(log) => Ok((
+                Box::new(RecordingConsumer::new(endpoint_name, log.clone(), consumer)),
+                parsers,
+            )),
+            None => Ok((consumer, parsers)),
+        }
+    }
+
+    /// Create a parser and a probe for input endpoint `endpoint_id`.
+    ///
+    /// `dedup` is the endpoint's deduplication filter, if any.  `retry` is
+    /// the number of consecutive failed attempts to run the endpoint
+    /// preceding this one.
+    fn new_input_probe(
+        self: &Arc<Self>,
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        endpoint_config: &InputEndpointConfig,
+        dedup: Option<&Arc<DedupFilter>>,
+        retry: u32,
+    ) -> Result<Box<InputProbe>, ControllerError> {
+        let catalog = self.catalog.lock().unwrap();
+        let input_stream = catalog
             .input_collection_handle(&endpoint_config.stream)
             .ok_or_else(|| {
                 ControllerError::unknown_input_stream(endpoint_name, &endpoint_config.stream)
@@ -899,45 +1927,187 @@ impl ControllerInner {
             )
         })?;
 
+        let projected_stream = endpoint_config
+            .columns
+            .as_ref()
+            .map(|columns| ProjectedCollectionHandle::new(endpoint_name, input_stream, columns))
+            .transpose()?;
+        let input_stream: &dyn DeCollectionHandle = match &projected_stream {
+            None => input_stream,
+            Some(projected_stream) => projected_stream,
+        };
+
+        let lineage_stream = endpoint_config
+            .lineage
+            .as_ref()
+            .map(|lineage| LineageCollectionHandle::new(endpoint_name, input_stream, lineage))
+            .transpose()?;
+        let input_stream: &dyn DeCollectionHandle = match &lineage_stream {
+            None => input_stream,
+            Some(lineage_stream) => lineage_stream,
+        };
+
+        let dedup_stream = dedup
+            .map(|filter| {
+                DedupCollectionHandle::new(
+                    endpoint_id,
+                    endpoint_name,
+                    input_stream,
+                    filter.clone(),
+                    self.status.clone(),
+                )
+            })
+            .transpose()?;
+        let input_stream: &dyn DeCollectionHandle = match &dedup_stream {
+            None => input_stream,
+            Some(dedup_stream) => dedup_stream,
+        };
+
+        // With the `abort_batch` policy, the probe decides when to push
+        // parsed records to the circuit.
+        let on_error = self.error_policy(&endpoint_config.connector_config);
+        let batch_stream =
+            (on_error == ErrorPolicy::AbortBatch).then(|| BatchCollectionHandle::new(input_stream));
         let parser = format.new_parser(
             endpoint_name,
-            input_stream,
+            match &batch_stream {
+                None => input_stream,
+                Some(batch_stream) => batch_stream,
+            },
             &endpoint_config.connector_config.format.config,
         )?;
 
-        // Create probe.
-        let endpoint_id = inputs.keys().next_back().map(|k| k + 1).unwrap_or(0);
-        let probe = Box::new(InputProbe::new(
+        Ok(Box::new(InputProbe::new(
             endpoint_id,
             endpoint_name,
             parser,
+            on_error,
+            batch_stream.as_ref().map(BatchCollectionHandle::batch),
+            lineage_stream.as_ref().map(LineageCollectionHandle::tag),
             self.clone(),
             self.circuit_thread_unparker.clone(),
             self.backpressure_thread_unparker.clone(),
-        ));
+            Throttle::new(&endpoint_config.connector_config)
+                .map(|throttle| Arc::new(Mutex::new(throttle))),
+            retry,
+        )))
+    }
 
-        // Initialize endpoint stats.
-        self.status
-            .add_input(&endpoint_id, endpoint_name, endpoint_config);
+    /// Process a fatal error reported by input endpoint `endpoint_id` or
+    /// encountered while re-creating the endpoint after an earlier failure.
+    ///
+    /// `retry` is the number of the retry that would follow this failure,
+    /// starting from 1.  If the endpoint has a retry policy that allows
+    /// another retry and the transport classifies the error as retryable,
+    /// reports the error as non-fatal and re-creates the endpoint after a
+    /// backoff delay.  Otherwise, reports the error as fatal.
+    fn input_endpoint_failed(
+        self: &Arc<Self>,
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        retry: u32,
+        error: AnyError,
+    ) {
+        let connector_config = self
+            .status
+            .input_status()
+            .get(&endpoint_id)
+            .map(|status| status.config.connector_config.clone());
+        let on_error = connector_config
+            .as_ref()
+            .map(|config| self.error_policy(config));
+        let delay = connector_config.and_then(|config| {
+            let transport = <dyn InputTransport>::get_transport(&config.transport.name)?;
+            if transport.is_retryable(&error) {
+                config.retry?.backoff(retry)
+            } else {
+                None
+            }
+        });
 
-        endpoint
-            .connect(probe)
-            .map_err(|e| ControllerError::input_transport_error(endpoint_name, true, e))?;
-        if self.state() == PipelineState::Running {
-            endpoint
-                .start()
-                .map_err(|e| ControllerError::input_transport_error(endpoint_name, true, e))?;
+        match delay {
+            None => {
+                self.input_transport_error(endpoint_id, endpoint_name, true, error);
+                if on_error == Some(ErrorPolicy::AbortPipeline) {
+                    self.abort_pipeline(endpoint_name);
+                }
+            }
+            Some(delay) => {
+                info!("Input endpoint '{endpoint_name}' failed with a transient error, retry #{retry} in {delay:?}");
+                self.input_transport_error(endpoint_id, endpoint_name, false, error);
+
+                let controller = Arc::downgrade(self);
+                let endpoint_name = endpoint_name.to_string();
+                spawn(move || {
+                    sleep(delay);
+                    if let Some(controller) = controller.upgrade() {
+                        controller.reconnect_input(endpoint_id, &endpoint_name, retry);
+                    }
+                });
+            }
         }
+    }
 
-        inputs.insert(
-            endpoint_id,
-            InputEndpointDescr::new(endpoint_name, endpoint),
-        );
+    /// Replace input endpoint `endpoint_id` with a new instance created from
+    /// the endpoint's configuration.
+    fn reconnect_input(self: &Arc<Self>, endpoint_id: EndpointId, endpoint_name: &str, retry: u32) {
+        if self.state() == PipelineState::Terminated {
+            return;
+        }
 
-        drop(inputs);
+        let mut inputs = self.inputs.lock().unwrap();
+        let (endpoint_config, dedup) = match (
+            self.status.input_status().get(&endpoint_id),
+            inputs.get(&endpoint_id),
+        ) {
+            (Some(status), Some(descr)) => (status.config.clone(), descr.dedup.clone()),
+            // The endpoint was disconnected in the meantime.
+            _ => return,
+        };
 
-        self.unpark_backpressure();
-        Ok(endpoint_id)
+        let result =
+            <dyn InputTransport>::get_transport(&endpoint_config.connector_config.transport.name)
+                .ok_or_else(|| anyhow!("unknown input transport"))
+                .and_then(|transport| {
+                    transport.new_endpoint(
+                        endpoint_name,
+                        &endpoint_config.connector_config.transport.config,
+                    )
+                })
+                .and_then(|mut endpoint| {
+                    let (consumer, parsers) = self
+                        .new_input_consumer(
+                            endpoint_id,
+                            endpoint_name,
+                            &endpoint_config,
+                            dedup.as_ref(),
+                            retry,
+                        )
+                        .map_err(|e| anyhow!(e.to_string()))?;
+                    endpoint.connect(consumer)?;
+                    if self.state() == PipelineState::Running {
+                        endpoint.start()?;
+                    }
+                    Ok((endpoint, parsers))
+                });
+
+        match result {
+            Ok((endpoint, parsers)) => {
+                let old_endpoint = inputs.insert(
+                    endpoint_id,
+                    InputEndpointDescr::new(endpoint_name, endpoint, parsers, dedup),
+                );
+                drop(inputs);
+                if let Some(old_endpoint) = old_endpoint {
+                    old_endpoint.endpoint.disconnect();
+                }
+                info!("Re-created input endpoint '{endpoint_name}'");
+            }
+            Err(error) => {
+                drop(inputs);
+                self.input_endpoint_failed(endpoint_id, endpoint_name, retry + 1, error);
+            }
+        }
     }
 
     fn register_api_connection(&self) -> Result<(), u64> {
@@ -964,6 +2134,82 @@ impl ControllerInner {
         self.status.request_step(&self.circuit_thread_unparker);
     }
 
+    fn set_manual_stepping(&self, manual: bool) {
+        self.status.set_manual_stepping(manual);
+        // Wake up the circuit thread to process inputs buffered while in
+        // manual mode.
+        self.unpark_circuit();
+    }
+
+    fn step(&self) {
+        if self.status.manual_stepping() {
+            self.status
+                .request_manual_step(&self.circuit_thread_unparker);
+        } else {
+            self.request_step();
+        }
+    }
+
+    fn step_with_stats(&self, cb: StepCallback) {
+        self.step_requests.push(cb);
+        self.step();
+    }
+
+    fn submit_snapshot_query<T, E>(
+        &self,
+        endpoint_id: &EndpointId,
+        params: &str,
+        submit: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let key = self
+            .outputs
+            .read()
+            .unwrap()
+            .lookup_by_id(endpoint_id)
+            .map(|endpoint| endpoint.key());
+
+        // Hold the lock while submitting parameters to the circuit, so that
+        // the circuit thread observes the new generation after any step that
+        // may have used these parameters.
+        let mut snapshot_cache = self.snapshot_cache.lock().unwrap();
+        let result = submit()?;
+        if let Some(key) = key {
+            snapshot_cache.set_params(key, params);
+        }
+        Ok(result)
+    }
+
+    fn send_cached_snapshot(&self, endpoint_id: &EndpointId, params: &str) -> bool {
+        // The write lock prevents the circuit thread from sending a fresh
+        // snapshot to the endpoint concurrently.
+        let outputs = self.outputs.write().unwrap();
+
+        let endpoint = match outputs.lookup_by_id(endpoint_id) {
+            Some(endpoint) => endpoint,
+            None => return false,
+        };
+
+        if endpoint.snapshot_sent.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let snapshot_cache = self.snapshot_cache.lock().unwrap();
+        match snapshot_cache.lookup(&endpoint.key(), params) {
+            Some(snapshot) => {
+                self.status
+                    .enqueue_batch(*endpoint_id, snapshot.num_records);
+                endpoint
+                    .queue
+                    .push((snapshot.batch.clone(), snapshot.processed_records, None));
+                endpoint.snapshot_sent.store(true, Ordering::Release);
+                endpoint.unparker.unpark();
+                self.status.snapshot_cache_hit();
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Unpark the circuit thread.
     fn unpark_circuit(&self) {
         self.circuit_thread_unparker.unpark();
@@ -992,7 +2238,7 @@ impl ControllerInner {
         let endpoint = transport
             .new_endpoint(endpoint_name, endpoint_config)
             .map_err(|e| ControllerError::output_transport_error(endpoint_name, true, e))?;
-        self.add_output_endpoint(endpoint_name, endpoint_config, endpoint)
+        self.add_output_endpoint(endpoint_name, endpoint_config, endpoint, None)
     }
 
     fn disconnect_output(self: &Arc<Self>, endpoint_id: &EndpointId) {
@@ -1010,6 +2256,7 @@ impl ControllerInner {
         endpoint_name: &str,
         endpoint_config: &OutputEndpointConfig,
         endpoint: Box<dyn OutputEndpoint>,
+        neighborhood_params: Option<&str>,
     ) -> Result<EndpointId, ControllerError> {
         let mut outputs = self.outputs.write().unwrap();
 
@@ -1023,13 +2270,44 @@ impl ControllerInner {
         // ┌───────┐   ┌───────────┐   ┌────────┐
         // │encoder├──►│OutputProbe├──►│endpoint├──►
         // └───────┘   └───────────┘   └────────┘
+        //
+        // With the `spill` overflow policy, the encoder writes to a spill
+        // file, which is forwarded to the probe by another thread (see
+        // [`spill`]).
 
         // Lookup output handle in catalog.
+        let num_sessions = self
+            .catalog
+            .lock()
+            .unwrap()
+            .output_handles(&endpoint_config.stream)
+            .ok_or_else(|| {
+                ControllerError::unknown_output_stream(endpoint_name, &endpoint_config.stream)
+            })?
+            .neighborhood_sessions
+            .len();
+        let session = if endpoint_config.query == OutputQuery::Neighborhood {
+            outputs
+                .neighborhood_session(
+                    &endpoint_config.stream,
+                    num_sessions,
+                    neighborhood_params,
+                    &self.snapshot_cache.lock().unwrap(),
+                )
+                .ok_or_else(|| {
+                    ControllerError::neighborhood_session_limit(
+                        &endpoint_config.stream,
+                        num_sessions,
+                    )
+                })?
+        } else {
+            0
+        };
         let handles = self
             .catalog
             .lock()
             .unwrap()
-            .output_query_handles(&endpoint_config.stream, endpoint_config.query)
+            .output_query_handles(&endpoint_config.stream, endpoint_config.query, session)
             .ok_or_else(|| {
                 ControllerError::unknown_output_stream(endpoint_name, &endpoint_config.stream)
             })?;
@@ -1037,22 +2315,45 @@ impl ControllerInner {
         let endpoint_id = outputs.alloc_endpoint_id();
         let endpoint_name_str = endpoint_name.to_string();
 
+        let on_error = self.error_policy(&endpoint_config.connector_config);
         let self_weak = Arc::downgrade(self);
         endpoint
             .connect(Box::new(move |fatal: bool, e: AnyError| {
                 if let Some(controller) = self_weak.upgrade() {
-                    controller.output_transport_error(endpoint_id, &endpoint_name_str, fatal, e)
+                    controller.output_transport_error(endpoint_id, &endpoint_name_str, fatal, e);
+                    if on_error == ErrorPolicy::AbortPipeline {
+                        controller.abort_pipeline(&endpoint_name_str);
+                    }
                 }
             }))
             .map_err(|e| ControllerError::output_transport_error(endpoint_name, true, e))?;
 
         // Create probe.
-        let probe = Box::new(OutputProbe::new(
+        let probe: Box<dyn OutputConsumer> = Box::new(OutputProbe::new(
             endpoint_id,
             endpoint_name,
             endpoint,
+            endpoint_config.connector_config.retry.clone(),
+            on_error,
+            <dyn OutputTransport>::get_transport(&endpoint_config.connector_config.transport.name),
             self.clone(),
         ));
+        let overflow = endpoint_config
+            .connector_config
+            .overflow
+            .unwrap_or_default();
+        let consumer: Box<dyn OutputConsumer> = if overflow == OverflowPolicy::Spill {
+            Box::new(
+                SpillWriter::new(endpoint_id, endpoint_name, probe, self.clone()).map_err(|e| {
+                    ControllerError::io_error(
+                        format!("creating spill file for output endpoint '{endpoint_name}'"),
+                        e,
+                    )
+                })?,
+            )
+        } else {
+            probe
+        };
 
         // Create encoder.
         let format = <dyn OutputFormat>::get_format(&endpoint_config.connector_config.format.name)
@@ -1063,7 +2364,7 @@ impl ControllerInner {
                 )
             })?;
         let encoder = format
-            .new_encoder(&endpoint_config.connector_config.format.config, probe)
+            .new_encoder(&endpoint_config.connector_config.format.config, consumer)
             .map_err(|e| ControllerError::encode_error(endpoint_name, e))?;
 
         let parker = Parker::new();
@@ -1071,6 +2372,8 @@ impl ControllerInner {
             endpoint_name,
             &endpoint_config.stream,
             endpoint_config.query,
+            session,
+            overflow,
             parker.unparker().clone(),
         );
         let queue = endpoint_descr.queue.clone();
@@ -1080,6 +2383,7 @@ impl ControllerInner {
         outputs.insert(endpoint_id, handles, endpoint_descr);
 
         let endpoint_name_string = endpoint_name.to_string();
+        let config = endpoint_config.clone();
         // Thread to run the output pipeline.
         spawn(move || {
             Self::output_thread_func(
@@ -1090,6 +2394,7 @@ impl ControllerInner {
                 queue,
                 disconnect_flag,
                 controller,
+                config,
             )
         });
 
@@ -1110,7 +2415,27 @@ impl ControllerInner {
         queue: Arc<BatchQueue>,
         disconnect_flag: Arc<AtomicBool>,
         controller: Arc<ControllerInner>,
+        config: OutputEndpointConfig,
     ) {
+        let max_batch_delay = config
+            .connector_config
+            .max_batch_delay_ms
+            .map(Duration::from_millis);
+
+        // Send outputs once the number of buffered records reaches
+        // `max_buffered_records` even if the batch delay hasn't expired yet;
+        // otherwise backpressure would stall the circuit until then.
+        let max_batch_size = config
+            .connector_config
+            .max_batch_size
+            .unwrap_or(u64::MAX)
+            .min(config.connector_config.max_buffered_records)
+            .max(1);
+
+        let on_error = controller.error_policy(&config.connector_config);
+        let spill = config.connector_config.overflow == Some(OverflowPolicy::Spill);
+        let mut pending = PendingOutput::default();
+
         loop {
             if controller.state() == PipelineState::Terminated {
                 return;
@@ -1120,30 +2445,80 @@ impl ControllerInner {
                 return;
             }
 
-            // Dequeue the next output batch and push it to the encoder.
-            if let Some((data, processed_records)) = queue.pop() {
-                let num_records = data.iter().map(|b| b.len()).sum();
+            // Leave output batches in the queue while the endpoint is paused.
+            // Once the queue fills up, backpressure stops the circuit.  Spilled
+            // outputs are held back by the spill file instead.
+            if !spill && controller.status.output_endpoint_paused(&endpoint_id) {
+                parker.park();
+                continue;
+            }
 
-                encoder.consumer().batch_start();
-                encoder
-                    .encode(data.as_slice())
-                    .unwrap_or_else(|e| controller.encode_error(endpoint_id, &endpoint_name, e));
-                encoder.consumer().batch_end();
+            // Dequeue output batches.  Without batching, the outputs of each
+            // step are sent to the encoder separately.
+            while pending.num_records < max_batch_size
+                && (max_batch_delay.is_some() || pending.num_steps == 0)
+            {
+                match queue.pop() {
+                    Some(batch) => pending.push(batch),
+                    None => break,
+                }
+            }
 
-                // `num_records` output records have been transmitted --
-                // update output stats, wake up the circuit thread if the
-                // number of queued records drops below high water mark.
-                controller.status.output_batch(
-                    endpoint_id,
-                    processed_records,
-                    num_records,
-                    &controller.circuit_thread_unparker,
-                );
-            } else {
+            let Some(since) = pending.since else {
                 // Queue is empty -- wait for the circuit thread to wake us up when
                 // more data is available.
                 parker.park();
+                continue;
+            };
+
+            // Hold back the outputs until the batch is full or its delay expires.
+            if let Some(max_batch_delay) = max_batch_delay {
+                let elapsed = since.elapsed();
+                if pending.num_records < max_batch_size && elapsed < max_batch_delay {
+                    parker.park_timeout(max_batch_delay - elapsed);
+                    continue;
+                }
+            }
+
+            let PendingOutput {
+                batches,
+                num_records,
+                num_steps,
+                processed_records,
+                step_span_context,
+                ..
+            } = take(&mut pending);
+
+            let mut span = controller
+                .telemetry
+                .as_ref()
+                .zip(step_span_context.as_ref())
+                .map(|(telemetry, step)| telemetry.output_batch_span(&endpoint_name, step));
+
+            encoder.consumer().batch_start();
+            if let Err(e) = encoder.encode(batches.as_slice()) {
+                controller.encode_error(endpoint_id, &endpoint_name, e);
+                if on_error == ErrorPolicy::AbortPipeline {
+                    controller.abort_pipeline(&endpoint_name);
+                }
+            }
+            encoder.consumer().batch_end();
+
+            if let Some(span) = span.as_mut() {
+                span.set_attribute(KeyValue::new("records", num_records as i64));
+                span.set_attribute(KeyValue::new("steps", num_steps as i64));
+                span.end();
             }
+
+            // `num_records` output records have been transmitted --
+            // update output stats, wake up the circuit thread if the
+            // number of queued records drops below high water mark.
+            controller.status.output_batch(
+                endpoint_id,
+                processed_records,
+                num_records as usize,
+                &controller.circuit_thread_unparker,
+            );
         }
     }
 
@@ -1161,6 +2536,69 @@ impl ControllerInner {
         self.unpark_backpressure();
     }
 
+    fn set_input_endpoint_paused(
+        &self,
+        endpoint_name: &str,
+        paused: bool,
+    ) -> Result<(), ControllerError> {
+        self.status
+            .input_status()
+            .values()
+            .find(|endpoint| endpoint.endpoint_name == endpoint_name)
+            .ok_or_else(|| ControllerError::unknown_input_endpoint(endpoint_name))?
+            .paused
+            .store(paused, Ordering::Release);
+
+        self.unpark_backpressure();
+        Ok(())
+    }
+
+    fn reconfigure_input_endpoint(
+        &self,
+        endpoint_name: &str,
+        config: &YamlValue,
+    ) -> Result<(), ControllerError> {
+        let inputs = self.inputs.lock().unwrap();
+        let (endpoint_id, endpoint) = inputs
+            .iter()
+            .find(|(_, endpoint)| endpoint.endpoint_name == endpoint_name)
+            .ok_or_else(|| ControllerError::unknown_input_endpoint(endpoint_name))?;
+
+        endpoint
+            .endpoint
+            .reconfigure(config)
+            .map_err(|e| ControllerError::transport_reconfiguration_error(endpoint_name, &e))?;
+        self.status
+            .set_input_transport_config(endpoint_id, config.clone());
+
+        info!("Input endpoint '{endpoint_name}': transport configuration updated");
+        Ok(())
+    }
+
+    fn set_output_endpoint_paused(
+        &self,
+        endpoint_name: &str,
+        paused: bool,
+    ) -> Result<(), ControllerError> {
+        let endpoint_id = {
+            let outputs = self.status.output_status();
+            let (endpoint_id, endpoint) = outputs
+                .iter()
+                .find(|(_, endpoint)| endpoint.endpoint_name == endpoint_name)
+                .ok_or_else(|| ControllerError::unknown_output_endpoint(endpoint_name))?;
+            endpoint.paused.store(paused, Ordering::Release);
+            *endpoint_id
+        };
+
+        // Wake up the endpoint thread to process queued batches.
+        if !paused {
+            if let Some(endpoint) = self.outputs.read().unwrap().lookup_by_id(&endpoint_id) {
+                endpoint.unparker.unpark();
+            }
+        }
+        Ok(())
+    }
+
     fn stop(self: &Arc<Self>) {
         let mut inputs = self.inputs.lock().unwrap();
 
@@ -1180,10 +2618,128 @@ impl ControllerInner {
         self.unpark_circuit();
     }
 
+    fn retrieve_profile(&self, cb: ProfileCallback) {
+        self.profile_requests.push(cb);
+        self.unpark_circuit();
+    }
+
+    fn profile_steps(&self, steps: u64, cb: StepProfileCallback) {
+        self.step_profile_requests.push((steps, cb));
+        self.unpark_circuit();
+    }
+
+    fn checkpoint(&self, cb: CheckpointCallback) {
+        if self.status.global_config.checkpoint.is_none() {
+            cb(Err(ControllerError::checkpoint_error(
+                &"checkpointing is not enabled in the pipeline configuration",
+            )));
+            return;
+        }
+        self.checkpoint_requests.push(cb);
+        self.unpark_circuit();
+    }
+
+    /// Create a checkpointer if checkpointing is enabled, and restore the
+    /// state of `circuit` and positions of input endpoints from the latest
+    /// checkpoint, if any.
+    fn restore_checkpoint(
+        &self,
+        circuit: &mut dyn DbspCircuitHandle,
+    ) -> Result<Option<Checkpointer>, ControllerError> {
+        let config = match &self.status.global_config.checkpoint {
+            None => return Ok(None),
+            Some(config) => config,
+        };
+
+        let mut checkpointer =
+            Checkpointer::new(config).map_err(|e| ControllerError::checkpoint_error(&e))?;
+        if let Some(checkpoint) = checkpointer
+            .load()
+            .map_err(|e| ControllerError::checkpoint_error(&e))?
+        {
+            circuit.restore(&checkpoint.state)?;
+            *self.restored_positions.lock().unwrap() = checkpoint.inputs;
+            info!(
+                "Restored checkpoint #{} from '{}'",
+                checkpoint.seq, config.location
+            );
+
+            // The history of views is not part of the checkpoint, so we
+            // cannot tell what the views looked like before the restart.
+            let mut histories = self.histories.lock().unwrap();
+            if !histories.is_empty() {
+                warn!(
+                    "Not retaining the history of views restored from a checkpoint: {}",
+                    histories.keys().cloned().collect::<Vec<_>>().join(", ")
+                );
+                histories.clear();
+            }
+        }
+
+        Ok(Some(checkpointer))
+    }
+
+    /// Write a checkpoint of the circuit and the positions of input
+    /// endpoints.  Must be invoked by the circuit thread between steps.
+    fn take_checkpoint(
+        &self,
+        circuit: &mut dyn DbspCircuitHandle,
+        checkpointer: Option<&mut Checkpointer>,
+    ) -> Result<u64, ControllerError> {
+        let checkpointer = checkpointer.ok_or_else(|| {
+            ControllerError::checkpoint_error(
+                &"checkpointing is not enabled in the pipeline configuration",
+            )
+        })?;
+
+        // Endpoints that don't track their position, e.g., HTTP connections,
+        // are not included in the checkpoint.
+        let positions = self
+            .inputs
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|input| {
+                input
+                    .endpoint
+                    .position()
+                    .map(|position| (input.endpoint_name.clone(), position))
+            })
+            .collect();
+
+        let state = circuit.checkpoint()?;
+        let seq = checkpointer
+            .save(state, positions)
+            .map_err(|e| ControllerError::checkpoint_error(&e))?;
+        info!("Checkpoint #{seq} complete");
+        Ok(seq)
+    }
+
     fn error(&self, error: ControllerError) {
         (self.error_cb)(error);
     }
 
+    /// Error policy of an endpoint with connector configuration `config`.
+    fn error_policy(&self, config: &ConnectorConfig) -> ErrorPolicy {
+        config
+            .on_error
+            .unwrap_or(self.status.global_config.on_error)
+    }
+
+    /// Stop the pipeline after an error at an endpoint with the
+    /// `abort_pipeline` error policy.
+    fn abort_pipeline(self: &Arc<Self>, endpoint_name: &str) {
+        if self.state() == PipelineState::Terminated {
+            return;
+        }
+        self.error(ControllerError::pipeline_aborted(endpoint_name));
+
+        // The caller may be an endpoint thread, which must not drop its own
+        // endpoint.
+        let controller = self.clone();
+        spawn(move || controller.stop());
+    }
+
     /// Process an input transport error.
     ///
     /// Update endpoint stats and notify the error callback.
@@ -1243,9 +2799,34 @@ struct InputProbe {
     endpoint_id: EndpointId,
     endpoint_name: String,
     parser: Box<dyn Parser>,
+    on_error: ErrorPolicy,
+
+    /// Streams of `parser` whose updates the probe pushes to the circuit
+    /// once the parser has processed a chunk without errors.  Only used
+    /// with the `abort_batch` error policy.
+    batch: Option<Arc<InputBatch>>,
+
+    /// Lineage tag of `parser`'s input streams, which the probe updates with
+    /// the source offsets reported by the endpoint.  Only used when lineage
+    /// tagging is enabled.
+    lineage: Option<Arc<LineageTag>>,
     controller: Arc<ControllerInner>,
     circuit_thread_unparker: Unparker,
     backpressure_thread_unparker: Unparker,
+
+    /// Enforces `max_records_per_sec` and `max_bytes_per_sec` limits.
+    /// Shared with forked probes, so that the limits apply to the endpoint
+    /// as a whole.
+    throttle: Option<Arc<Mutex<Throttle>>>,
+
+    /// Number of consecutive failed attempts to run the endpoint before
+    /// this instance of the endpoint.  Reset once the endpoint receives
+    /// data.
+    retry: u32,
+
+    /// Remote trace context of the data received next, set via
+    /// [`InputConsumer::trace_context`].
+    trace_context: Option<SpanContext>,
 }
 
 impl InputProbe {
@@ -1253,51 +2834,152 @@ impl InputProbe {
         endpoint_id: EndpointId,
         endpoint_name: &str,
         parser: Box<dyn Parser>,
+        on_error: ErrorPolicy,
+        batch: Option<Arc<InputBatch>>,
+        lineage: Option<Arc<LineageTag>>,
         controller: Arc<ControllerInner>,
         circuit_thread_unparker: Unparker,
         backpressure_thread_unparker: Unparker,
+        throttle: Option<Arc<Mutex<Throttle>>>,
+        retry: u32,
     ) -> Self {
         Self {
             endpoint_id,
             endpoint_name: endpoint_name.to_owned(),
             parser,
+            on_error,
+            batch,
+            lineage,
             controller,
             circuit_thread_unparker,
             backpressure_thread_unparker,
+            throttle,
+            retry,
+            trace_context: None,
         }
     }
-}
 
-/// `InputConsumer` interface exposed to the transport endpoint.
-impl InputConsumer for InputProbe {
-    fn input_fragment(&mut self, data: &[u8]) -> Vec<ParseError> {
-        // println!("input consumer {} bytes", data.len());
-        // Pass input buffer to the parser.
-        let (num_records, errors) = self.parser.input_fragment(data);
+    /// Start an input batch span if tracing is enabled.
+    fn start_span(&self) -> Option<SdkSpan> {
+        self.controller.telemetry.as_ref().map(|telemetry| {
+            telemetry.input_batch_span(&self.endpoint_name, self.trace_context.as_ref())
+        })
+    }
+
+    fn end_span(&self, span: Option<SdkSpan>, num_bytes: usize, num_records: usize) {
+        if let (Some(telemetry), Some(span)) = (&self.controller.telemetry, span) {
+            telemetry.input_batch_completed(span, num_bytes, num_records);
+        }
+    }
 
+    /// Report `errors` encountered while parsing a chunk of input data that
+    /// yielded `num_records` records and apply the endpoint's error policy.
+    ///
+    /// Returns the number of records pushed to the circuit.
+    fn parsed(&self, num_records: usize, errors: &[ParseError]) -> usize {
         for error in errors.iter() {
             self.controller
                 .parse_error(self.endpoint_id, &self.endpoint_name, error.clone());
         }
-        self.controller.status.input_batch(
-            self.endpoint_id,
-            data.len(),
+
+        if errors.is_empty() {
+            if let Some(batch) = &self.batch {
+                batch.commit();
+            }
+            return num_records;
+        }
+
+        match self.on_error {
+            ErrorPolicy::Skip => {
+                self.controller
+                    .status
+                    .skipped_records(self.endpoint_id, errors.len());
+            }
+            ErrorPolicy::AbortBatch => {
+                if let Some(batch) = &self.batch {
+                    batch.abort();
+                }
+                self.controller
+                    .status
+                    .skipped_records(self.endpoint_id, num_records + errors.len());
+                return 0;
+            }
+            ErrorPolicy::AbortPipeline => {
+                self.controller.abort_pipeline(&self.endpoint_name);
+            }
+        }
+
+        if let Some(batch) = &self.batch {
+            batch.commit();
+        }
+        num_records
+    }
+
+    /// Stall the endpoint thread if the endpoint exceeds its rate limits.
+    ///
+    /// Sleeps in short intervals, so that the endpoint can be disconnected
+    /// or the pipeline terminated in the meantime.
+    fn throttle(&mut self, num_records: usize, num_bytes: usize) {
+        const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+        let delay = match &self.throttle {
+            None => return,
+            Some(throttle) => throttle.lock().unwrap().consume(
+                num_records as u64,
+                num_bytes as u64,
+                Instant::now(),
+            ),
+        };
+        let deadline = Instant::now() + delay;
+        loop {
+            let now = Instant::now();
+            if now >= deadline
+                || self.controller.state() == PipelineState::Terminated
+                || !self
+                    .controller
+                    .status
+                    .input_status()
+                    .contains_key(&self.endpoint_id)
+            {
+                break;
+            }
+            sleep(min(deadline - now, MAX_SLEEP));
+        }
+    }
+}
+
+/// `InputConsumer` interface exposed to the transport endpoint.
+impl InputConsumer for InputProbe {
+    fn input_fragment(&mut self, data: &[u8]) -> Vec<ParseError> {
+        let span = self.start_span();
+
+        // println!("input consumer {} bytes", data.len());
+        // Pass input buffer to the parser.
+        let (num_records, errors) = self.parser.input_fragment(data);
+        let num_records = self.parsed(num_records, &errors);
+
+        self.controller.status.input_batch(
+            self.endpoint_id,
+            data.len(),
             num_records,
             &self.controller.status.global_config,
             &self.circuit_thread_unparker,
             &self.backpressure_thread_unparker,
         );
+        if !data.is_empty() {
+            self.retry = 0;
+        }
+        self.end_span(span, data.len(), num_records);
+        self.throttle(num_records, data.len());
 
         errors
     }
 
     fn input_chunk(&mut self, data: &[u8]) -> Vec<ParseError> {
+        let span = self.start_span();
         let (num_records, errors) = self.parser.input_chunk(data);
+        let num_records = self.parsed(num_records, &errors);
 
-        for error in errors.iter() {
-            self.controller
-                .parse_error(self.endpoint_id, &self.endpoint_name, error.clone());
-        }
         self.controller.status.input_batch(
             self.endpoint_id,
             data.len(),
@@ -1306,6 +2988,11 @@ impl InputConsumer for InputProbe {
             &self.circuit_thread_unparker,
             &self.backpressure_thread_unparker,
         );
+        if !data.is_empty() {
+            self.retry = 0;
+        }
+        self.end_span(span, data.len(), num_records);
+        self.throttle(num_records, data.len());
 
         errors
     }
@@ -1316,10 +3003,8 @@ impl InputConsumer for InputProbe {
         // parsed data and may be waiting for, e.g., and end-of-line or
         // end-of-file to finish parsing it).
         let (num_records, errors) = self.parser.eoi();
-        for error in errors.iter() {
-            self.controller
-                .parse_error(self.endpoint_id, &self.endpoint_name, error.clone());
-        }
+        let num_records = self.parsed(num_records, &errors);
+
         self.controller
             .status
             .eoi(self.endpoint_id, num_records, &self.circuit_thread_unparker);
@@ -1328,28 +3013,85 @@ impl InputConsumer for InputProbe {
     }
 
     fn error(&mut self, fatal: bool, error: AnyError) {
+        if fatal {
+            self.controller.input_endpoint_failed(
+                self.endpoint_id,
+                &self.endpoint_name,
+                self.retry + 1,
+                error,
+            );
+        } else {
+            self.controller.input_transport_error(
+                self.endpoint_id,
+                &self.endpoint_name,
+                fatal,
+                error,
+            );
+            if self.on_error == ErrorPolicy::AbortPipeline {
+                self.controller.abort_pipeline(&self.endpoint_name);
+            }
+        }
+    }
+
+    fn transport_metrics(&mut self, metrics: JsonValue) {
         self.controller
-            .input_transport_error(self.endpoint_id, &self.endpoint_name, fatal, error);
+            .status
+            .transport_metrics(self.endpoint_id, metrics);
+    }
+
+    fn trace_context(&mut self, headers: &[(&str, &str)]) {
+        if self.controller.telemetry.is_some() {
+            self.trace_context = extract_trace_context(headers);
+        }
+    }
+
+    fn source_offset(&mut self, offset: &JsonValue) {
+        if let Some(lineage) = &self.lineage {
+            lineage.set_offset(offset);
+        }
     }
 
     fn fork(&self) -> Box<dyn InputConsumer> {
+        let parser = self.parser.fork();
+        let batch = self.batch.as_ref().map(|batch| batch.take_fork());
+        let lineage = self.lineage.as_ref().map(|lineage| lineage.take_fork());
         Box::new(Self::new(
             self.endpoint_id,
             &self.endpoint_name,
-            self.parser.fork(),
+            parser,
+            self.on_error,
+            batch,
+            lineage,
             self.controller.clone(),
             self.circuit_thread_unparker.clone(),
             self.backpressure_thread_unparker.clone(),
+            self.throttle.clone(),
+            self.retry,
         ))
     }
 }
 
 /// An output probe inserted between the encoder and the output transport
-/// endpoint to track stats.
+/// endpoint to track stats and retry failed operations.
 struct OutputProbe {
     endpoint_id: EndpointId,
     endpoint_name: String,
     endpoint: Box<dyn OutputEndpoint>,
+
+    /// Retry policy for transient errors.
+    retry: Option<RetryConfig>,
+
+    /// What to do once an operation fails for good.
+    on_error: ErrorPolicy,
+
+    /// An operation in the current batch has failed and the endpoint's
+    /// error policy is `abort_batch`: drop the rest of the batch.
+    batch_aborted: bool,
+
+    /// Transport that created the endpoint; classifies errors as
+    /// retryable.  `None` for endpoints created outside of the transport
+    /// registry, which are never retried.
+    transport: Option<&'static dyn OutputTransport>,
     controller: Arc<ControllerInner>,
 }
 
@@ -1358,15 +3100,83 @@ impl OutputProbe {
         endpoint_id: EndpointId,
         endpoint_name: &str,
         endpoint: Box<dyn OutputEndpoint>,
+        retry: Option<RetryConfig>,
+        on_error: ErrorPolicy,
+        transport: Option<&'static dyn OutputTransport>,
         controller: Arc<ControllerInner>,
     ) -> Self {
         Self {
             endpoint_id,
             endpoint_name: endpoint_name.to_owned(),
             endpoint,
+            retry,
+            on_error,
+            batch_aborted: false,
+            transport,
             controller,
         }
     }
+
+    /// Run `op` against the endpoint, retrying it according to the
+    /// endpoint's retry policy while it fails with a retryable error.
+    ///
+    /// Every failed attempt is reported as a non-fatal error.  Gives up
+    /// when the retry budget is exhausted, the pipeline terminates, or the
+    /// endpoint is disconnected.
+    fn with_retry<F>(&mut self, mut op: F) -> bool
+    where
+        F: FnMut(&mut dyn OutputEndpoint) -> AnyResult<()>,
+    {
+        let mut retry = 0;
+        loop {
+            let error = match op(self.endpoint.as_mut()) {
+                Ok(()) => return true,
+                Err(error) => error,
+            };
+
+            retry += 1;
+            let delay = match (&self.retry, self.transport) {
+                (Some(config), Some(transport)) if transport.is_retryable(&error) => {
+                    config.backoff(retry)
+                }
+                _ => None,
+            };
+            self.controller.output_transport_error(
+                self.endpoint_id,
+                &self.endpoint_name,
+                false,
+                error,
+            );
+
+            let Some(delay) = delay else {
+                return false;
+            };
+            info!(
+                "Output endpoint '{}' failed with a transient error, retry #{retry} in {delay:?}",
+                self.endpoint_name
+            );
+            sleep(delay);
+            if self.controller.state() == PipelineState::Terminated
+                || !self
+                    .controller
+                    .status
+                    .output_status()
+                    .contains_key(&self.endpoint_id)
+            {
+                return false;
+            }
+        }
+    }
+
+    /// Apply the endpoint's error policy after an operation has failed
+    /// for good.
+    fn failed(&mut self) {
+        match self.on_error {
+            ErrorPolicy::Skip => {}
+            ErrorPolicy::AbortBatch => self.batch_aborted = true,
+            ErrorPolicy::AbortPipeline => self.controller.abort_pipeline(&self.endpoint_name),
+        }
+    }
 }
 
 impl OutputConsumer for OutputProbe {
@@ -1375,49 +3185,50 @@ impl OutputConsumer for OutputProbe {
     }
 
     fn batch_start(&mut self) {
-        self.endpoint.batch_start().unwrap_or_else(|e| {
-            self.controller
-                .output_transport_error(self.endpoint_id, &self.endpoint_name, false, e);
-        })
+        self.batch_aborted = false;
+        if !self.with_retry(|endpoint| endpoint.batch_start()) {
+            self.failed();
+        }
     }
 
     fn push_buffer(&mut self, buffer: &[u8]) {
         let num_bytes = buffer.len();
 
-        match self.endpoint.push_buffer(buffer) {
-            Ok(()) => {
-                self.controller
-                    .status
-                    .output_buffer(self.endpoint_id, num_bytes);
-            }
-            Err(error) => {
-                self.controller.output_transport_error(
-                    self.endpoint_id,
-                    &self.endpoint_name,
-                    false,
-                    error,
-                );
-            }
+        if self.batch_aborted {
+            self.controller.status.skipped_buffer(self.endpoint_id);
+        } else if self.with_retry(|endpoint| endpoint.push_buffer(buffer)) {
+            self.controller
+                .status
+                .output_buffer(self.endpoint_id, num_bytes);
+        } else {
+            self.controller.status.skipped_buffer(self.endpoint_id);
+            self.failed();
         }
     }
 
     fn batch_end(&mut self) {
-        self.endpoint.batch_end().unwrap_or_else(|e| {
-            self.controller
-                .output_transport_error(self.endpoint_id, &self.endpoint_name, false, e);
-        })
+        if !self.with_retry(|endpoint| endpoint.batch_end()) {
+            self.failed();
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::{EndpointId, StepStats};
     use crate::{
-        test::{generate_test_batch, test_circuit, wait, TestStruct},
-        Controller, PipelineConfig,
+        test::{generate_test_batch, run_checkpoint_pipeline, test_circuit, wait, TestStruct},
+        Controller, ControllerError, DetailedError, InputEndpointConfig, OutputEndpointConfig,
+        OutputEndpointMetrics, OutputQuery, OutputTransport, PipelineConfig, NEIGHBORHOOD_SESSIONS,
     };
     use csv::{ReaderBuilder as CsvReaderBuilder, WriterBuilder as CsvWriterBuilder};
-    use std::fs::remove_file;
-    use tempfile::NamedTempFile;
+    use std::{
+        fs::{remove_file, write, OpenOptions},
+        sync::{atomic::Ordering, Arc, Mutex},
+        thread::sleep,
+        time::Duration,
+    };
+    use tempfile::{NamedTempFile, TempDir};
 
     use proptest::prelude::*;
 
@@ -1521,4 +3332,717 @@ outputs:
             assert_eq!(actual, expected);
         }
     }
+
+    #[test]
+    fn manual_stepping() {
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let config_str = format!(
+            r#"
+name: test
+workers: 4
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                follow: false
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let data = (0..100)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: id.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for val in data.iter().cloned() {
+            writer.serialize(val).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.set_manual_stepping(true);
+        controller.start();
+
+        // Inputs are buffered, but not processed until we request a step.
+        wait(
+            || controller.status().num_buffered_input_records() == data.len() as u64,
+            Some(10_000),
+        )
+        .unwrap();
+        sleep(Duration::from_millis(500));
+        assert_eq!(controller.status().num_total_processed_records(), 0);
+        assert!(!controller.pipeline_complete());
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        controller.step_with_stats(Box::new(move |stats| sender.send(stats).unwrap()));
+        let stats = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(stats.input_records, data.len() as u64);
+        assert_eq!(stats.output_records, data.len() as u64);
+
+        wait(|| controller.pipeline_complete(), Some(10_000)).unwrap();
+        assert_eq!(
+            controller.status().num_total_processed_records(),
+            data.len() as u64
+        );
+
+        // A step without new inputs doesn't produce outputs.
+        let (sender, receiver) = std::sync::mpsc::channel();
+        controller.step_with_stats(Box::new(move |stats| sender.send(stats).unwrap()));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(10)).unwrap(),
+            StepStats::default()
+        );
+
+        controller.stop().unwrap();
+        remove_file(&output_path).unwrap();
+    }
+
+    #[test]
+    fn output_batching() {
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let config_str = format!(
+            r#"
+name: test
+workers: 4
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                follow: false
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        max_batch_delay_ms: 2000
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let data = (0..100)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: id.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for val in data.iter().cloned() {
+            writer.serialize(val).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.set_manual_stepping(true);
+        controller.start();
+
+        wait(
+            || controller.status().num_buffered_input_records() == data.len() as u64,
+            Some(10_000),
+        )
+        .unwrap();
+        controller.step();
+        wait(
+            || controller.status().num_total_processed_records() == data.len() as u64,
+            Some(10_000),
+        )
+        .unwrap();
+
+        // Outputs are held back until the batch delay expires.
+        sleep(Duration::from_millis(200));
+        let output_status = controller.status().output_status();
+        assert_eq!(output_status.get(&0).unwrap().transmitted_records(), 0);
+        drop(output_status);
+
+        wait(|| controller.pipeline_complete(), Some(10_000)).unwrap();
+        assert_eq!(
+            controller
+                .status()
+                .output_status()
+                .get(&0)
+                .unwrap()
+                .transmitted_records(),
+            data.len() as u64
+        );
+
+        controller.stop().unwrap();
+        remove_file(&output_path).unwrap();
+    }
+
+    #[test]
+    fn drain() {
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        // The input endpoint follows the file, so it never reaches end of input.
+        let config_str = format!(
+            r#"
+name: test
+workers: 4
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                follow: true
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let data = (0..100)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: id.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for val in data.iter().cloned() {
+            writer.serialize(val).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.set_manual_stepping(true);
+        controller.start();
+
+        wait(
+            || controller.status().num_buffered_input_records() == data.len() as u64,
+            Some(10_000),
+        )
+        .unwrap();
+        assert!(!controller.pipeline_complete());
+
+        // Draining processes buffered inputs and flushes outputs, even though
+        // the input endpoint has not reached end of input.
+        controller.drain();
+        wait(|| controller.pipeline_complete(), Some(10_000)).unwrap();
+        assert!(controller.status().input_status().is_empty());
+        assert_eq!(
+            controller
+                .status()
+                .output_status()
+                .get(&0)
+                .unwrap()
+                .transmitted_records(),
+            data.len() as u64
+        );
+
+        controller.stop().unwrap();
+        remove_file(&output_path).unwrap();
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let output_files = (0..3)
+            .map(|_| NamedTempFile::new().unwrap())
+            .collect::<Vec<_>>();
+
+        // `stalling` keeps up with the circuit; `dropping` and `spilling`
+        // are paused and fall behind after the first step.
+        let config_str = format!(
+            r#"
+name: test
+workers: 4
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                follow: true
+        format:
+            name: csv
+outputs:
+    stalling:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+    dropping:
+        stream: test_output1
+        max_buffered_records: 1
+        overflow: drop
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+    spilling:
+        stream: test_output1
+        max_buffered_records: 1
+        overflow: spill
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_files[0].path().to_str().unwrap(),
+            output_files[1].path().to_str().unwrap(),
+            output_files[2].path().to_str().unwrap(),
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.pause_output_endpoint("dropping").unwrap();
+        controller.pause_output_endpoint("spilling").unwrap();
+        controller.set_manual_stepping(true);
+        controller.start();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+
+        // Feed 10 records per step.
+        for step in 0..3 {
+            for id in step * 10..(step + 1) * 10 {
+                writer
+                    .serialize(TestStruct {
+                        id,
+                        b: id % 2 == 0,
+                        i: Some(id as i64),
+                        s: id.to_string(),
+                    })
+                    .unwrap();
+            }
+            writer.flush().unwrap();
+
+            wait(
+                || controller.status().num_buffered_input_records() == 10,
+                Some(10_000),
+            )
+            .unwrap();
+            controller.step();
+
+            // The circuit doesn't wait for the paused endpoints.
+            wait(
+                || controller.status().num_total_processed_records() == (step + 1) as u64 * 10,
+                Some(10_000),
+            )
+            .unwrap();
+        }
+
+        let endpoint_id = |name: &str| {
+            controller
+                .status()
+                .output_status()
+                .iter()
+                .find(|(_, status)| status.endpoint_name == name)
+                .map(|(endpoint_id, _)| *endpoint_id)
+                .unwrap()
+        };
+        let (stalling, dropping, spilling) = (
+            endpoint_id("stalling"),
+            endpoint_id("dropping"),
+            endpoint_id("spilling"),
+        );
+        let metric = |endpoint_id: EndpointId, f: fn(&OutputEndpointMetrics) -> u64| {
+            f(&controller
+                .status()
+                .output_status()
+                .get(&endpoint_id)
+                .unwrap()
+                .metrics)
+        };
+
+        wait(
+            || metric(stalling, |m| m.transmitted_records.load(Ordering::Acquire)) == 30,
+            Some(10_000),
+        )
+        .unwrap();
+
+        // The first batch is queued; subsequent batches are dropped.
+        assert_eq!(
+            metric(dropping, |m| m.num_dropped_records.load(Ordering::Acquire)),
+            20
+        );
+        assert_eq!(
+            metric(dropping, |m| m.buffered_records.load(Ordering::Acquire)),
+            10
+        );
+
+        // All outputs are encoded into the spill file.
+        wait(
+            || metric(spilling, |m| m.buffered_records.load(Ordering::Acquire)) == 0,
+            Some(10_000),
+        )
+        .unwrap();
+        assert!(metric(spilling, |m| m.spilled_bytes.load(Ordering::Acquire)) > 0);
+        assert_eq!(
+            metric(spilling, |m| m.transmitted_bytes.load(Ordering::Acquire)),
+            0
+        );
+
+        controller.start_output_endpoint("dropping").unwrap();
+        controller.start_output_endpoint("spilling").unwrap();
+
+        wait(
+            || metric(dropping, |m| m.transmitted_records.load(Ordering::Acquire)) == 10,
+            Some(10_000),
+        )
+        .unwrap();
+        wait(
+            || {
+                metric(spilling, |m| m.spilled_bytes.load(Ordering::Acquire)) == 0
+                    && metric(spilling, |m| m.transmitted_bytes.load(Ordering::Acquire))
+                        == metric(stalling, |m| m.transmitted_bytes.load(Ordering::Acquire))
+            },
+            Some(10_000),
+        )
+        .unwrap();
+
+        controller.stop().unwrap();
+    }
+
+    #[test]
+    fn neighborhood_sessions() {
+        let output_file = NamedTempFile::new().unwrap();
+
+        let config: PipelineConfig = serde_yaml::from_str(
+            r#"
+name: test
+workers: 4
+inputs: {}
+        "#,
+        )
+        .unwrap();
+        let mut endpoint_config: OutputEndpointConfig = serde_yaml::from_str(&format!(
+            r#"
+stream: test_output1
+transport:
+    name: file
+    config:
+        path: {:?}
+format:
+    name: csv
+        "#,
+            output_file.path().to_str().unwrap(),
+        ))
+        .unwrap();
+        endpoint_config.query = OutputQuery::Neighborhood;
+
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        // Connect a neighborhood endpoint and submit its query; returns the
+        // endpoint id and session.
+        let connect = |name: &str, params: &str| {
+            let endpoint = <dyn OutputTransport>::get_transport("file")
+                .unwrap()
+                .new_endpoint(name, &endpoint_config)
+                .unwrap();
+            let endpoint_id =
+                controller.add_neighborhood_endpoint(name, &endpoint_config, endpoint, params)?;
+            controller
+                .submit_snapshot_query(&endpoint_id, params, || Ok::<(), ControllerError>(()))?;
+            Ok::<_, ControllerError>((
+                endpoint_id,
+                controller.neighborhood_session(&endpoint_id).unwrap(),
+            ))
+        };
+
+        // Endpoints monitoring the same neighborhood share a session.
+        let (_, session1) = connect("hood1", "a").unwrap();
+        let (_, session2) = connect("hood2", "a").unwrap();
+        assert_eq!(session1, session2);
+
+        // Different neighborhoods get separate sessions until all sessions
+        // are in use.
+        let mut endpoints = Vec::new();
+        for i in 1..NEIGHBORHOOD_SESSIONS {
+            let (endpoint_id, session) = connect(&format!("hood_b{i}"), &i.to_string()).unwrap();
+            assert_ne!(session, session1);
+            assert!(!endpoints.iter().any(|(_, s)| *s == session));
+            endpoints.push((endpoint_id, session));
+        }
+        assert!(matches!(
+            connect("hood_c", "c"),
+            Err(ControllerError::NeighborhoodSessionLimit { .. })
+        ));
+
+        // Disconnecting the last endpoint of a session makes it available.
+        let (endpoint_id, session) = endpoints.pop().unwrap();
+        controller.disconnect_output(&endpoint_id);
+        assert_eq!(connect("hood_c", "c").unwrap().1, session);
+
+        controller.stop().unwrap();
+    }
+
+    /// Feed a CSV file with a malformed record to a pipeline with error
+    /// policy `on_error`.  Returns the number of records the pipeline
+    /// outputs, the number of skipped input records, and the error codes
+    /// reported by the controller.
+    fn run_error_policy(on_error: &str) -> (u64, u64, Vec<String>) {
+        let input_file = NamedTempFile::new().unwrap();
+        let output_file = NamedTempFile::new().unwrap();
+        write(
+            input_file.path(),
+            "1,true,1,foo\n2,false,2,bar\nthree,true,3,baz\n4,true,4,qux\n",
+        )
+        .unwrap();
+
+        let config: PipelineConfig = serde_yaml::from_str(&format!(
+            r#"
+name: test
+workers: 4
+on_error: {on_error}
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                follow: false
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            input_file.path().to_str().unwrap(),
+            output_file.path().to_str().unwrap(),
+        ))
+        .unwrap();
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_clone = errors.clone();
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &config,
+            Box::new(move |e| {
+                errors_clone
+                    .lock()
+                    .unwrap()
+                    .push(e.error_code().to_string())
+            }),
+        )
+        .unwrap();
+        controller.start();
+
+        wait(
+            || {
+                controller.pipeline_complete()
+                    || errors
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .any(|code| code == "PipelineAborted")
+            },
+            None,
+        );
+
+        let transmitted_records = controller
+            .status()
+            .output_status()
+            .values()
+            .next()
+            .unwrap()
+            .transmitted_records();
+        let skipped_records = controller
+            .status()
+            .input_status()
+            .values()
+            .next()
+            .map_or(0, |status| {
+                status.metrics.num_skipped_records.load(Ordering::Acquire)
+            });
+        controller.stop().unwrap();
+
+        let errors = errors.lock().unwrap().clone();
+        (transmitted_records, skipped_records, errors)
+    }
+
+    #[test]
+    fn error_policy() {
+        // The malformed record is dropped.
+        let (transmitted, skipped, errors) = run_error_policy("skip");
+        assert_eq!((transmitted, skipped), (3, 1));
+        assert_eq!(errors, vec!["ParseError"]);
+
+        // The entire input chunk is dropped.
+        let (transmitted, skipped, errors) = run_error_policy("abort_batch");
+        assert_eq!((transmitted, skipped), (0, 4));
+        assert_eq!(errors, vec!["ParseError"]);
+
+        // The pipeline stops.
+        let (_, _, errors) = run_error_policy("abort_pipeline");
+        assert_eq!(errors, vec!["ParseError", "PipelineAborted"]);
+    }
+
+    fn write_test_data(path: &str, data: &[TestStruct]) {
+        let file = OpenOptions::new().append(true).open(path).unwrap();
+        let mut writer = CsvWriterBuilder::new().has_headers(false).from_writer(file);
+        for val in data.iter().cloned() {
+            writer.serialize(val).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[test]
+    fn checkpoint_restore() {
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+        let dir = TempDir::new().unwrap();
+        let input: InputEndpointConfig = serde_yaml::from_str(&format!(
+            r#"
+stream: test_input1
+transport:
+    name: file
+    config:
+        path: {input_path:?}
+        follow: true
+format:
+    name: csv
+"#
+        ))
+        .unwrap();
+
+        let data = (0..20)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: id.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (before, after) = data.split_at(10);
+        let expected =
+            |data: &[TestStruct]| data.iter().map(|val| (val.clone(), 1)).collect::<Vec<_>>();
+
+        write_test_data(input_path, before);
+        assert_eq!(
+            run_checkpoint_pipeline(&input, dir.path(), before.len(), true),
+            expected(before)
+        );
+
+        // The restarted pipeline resumes reading after the checkpointed
+        // inputs, and the integral includes the inputs processed before the
+        // checkpoint, without counting them twice.
+        write_test_data(input_path, after);
+        assert_eq!(
+            run_checkpoint_pipeline(&input, dir.path(), after.len(), false),
+            expected(&data)
+        );
+
+        // Without a new checkpoint, the pipeline restarts from the first one.
+        assert_eq!(
+            run_checkpoint_pipeline(&input, dir.path(), after.len(), false),
+            expected(&data)
+        );
+    }
 }
@@ -0,0 +1,405 @@
+//! Parsing the data received by an input endpoint in multiple threads.
+//!
+//! By default, the transport endpoint thread parses the data it receives
+//! itself, so CPU-intensive formats like JSON or CSV can limit the throughput
+//! of an endpoint to what a single core can parse.  When an endpoint is
+//! configured with `num_parsers > 1`, the controller connects it to a
+//! [`ParallelConsumer`] instead, which hands the data off to a pool of parser
+//! threads, each running its own instance of the parser.
+//!
+//! Only chunks of complete records (see [`InputConsumer::input_chunk`]) are
+//! distributed across parsers.  Fragments of a byte stream may split records
+//! at arbitrary boundaries and are therefore always parsed by the first
+//! parser, along with errors, metrics, and the end-of-input notification
+//! reported by the endpoint.
+//!
+//! Parsed records only become visible to the circuit once a parser thread
+//! gets to them.  The controller calls [`ParserPool::flush`] after notifying
+//! the endpoint that a step is about to start, so that the step includes all
+//! data the endpoint has pushed to the consumer by then.
+
+use super::ParserSharding;
+use crate::{transport::InputConsumer, ParseError};
+use anyhow::Error as AnyError;
+use crossbeam::channel::{bounded, Receiver, Sender};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::spawn,
+};
+
+/// Number of chunks queued for each parser before the endpoint blocks waiting
+/// for the parser to catch up.
+const PARSER_QUEUE_CAPACITY: usize = 16;
+
+type TraceContext = Arc<Vec<(String, String)>>;
+
+enum Message {
+    /// Source offset (see [`InputConsumer::source_offset`]) of subsequent
+    /// data sent to the parser.
+    Offset(JsonValue),
+    Fragment(Vec<u8>),
+    Chunk(Vec<u8>, Option<TraceContext>),
+    Error(bool, AnyError),
+    Eoi,
+    Metrics(JsonValue),
+    Flush(Sender<()>),
+}
+
+/// Parser threads of an input endpoint, shared by the endpoint's
+/// [`ParallelConsumer`]s and the controller.
+///
+/// Each [`ParallelConsumer`] owns its parser threads.  When it is dropped,
+/// e.g., when a transport discards a consumer it forked for a partition
+/// that got reassigned, its threads exit after processing the data already
+/// queued for them.
+#[derive(Default)]
+pub(crate) struct ParserPool {
+    /// Queues of running parser threads, by a unique id.
+    queues: Mutex<BTreeMap<u64, Sender<Message>>>,
+    next_id: AtomicU64,
+    num_threads: Arc<AtomicUsize>,
+}
+
+impl ParserPool {
+    /// Wait until all parsers have processed the data queued before this
+    /// call.
+    pub(crate) fn flush(&self) {
+        let queues: Vec<Sender<Message>> = self.queues.lock().unwrap().values().cloned().collect();
+        let acks: Vec<Receiver<()>> = queues
+            .iter()
+            .filter_map(|queue| {
+                let (sender, receiver) = bounded(1);
+                queue.send(Message::Flush(sender)).ok()?;
+                Some(receiver)
+            })
+            .collect();
+
+        // A parser thread only drops the acknowledgement without sending it
+        // if it panics.
+        for ack in acks {
+            let _ = ack.recv();
+        }
+    }
+
+    /// Number of parser threads that haven't exited yet.
+    #[cfg(test)]
+    fn num_threads(&self) -> usize {
+        self.num_threads.load(Ordering::Acquire)
+    }
+
+    /// Start a parser thread that feeds data to `consumer`.  Returns the id
+    /// and the sending end of the thread's queue.
+    fn spawn(&self, mut consumer: Box<dyn InputConsumer>) -> (u64, Sender<Message>) {
+        let (sender, receiver) = bounded(PARSER_QUEUE_CAPACITY);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.queues.lock().unwrap().insert(id, sender.clone());
+
+        // The thread exits once its consumer drops its end of the queue and
+        // removes the other end from the pool (see [`Self::remove`]).
+        let num_threads = self.num_threads.clone();
+        num_threads.fetch_add(1, Ordering::AcqRel);
+        spawn(move || {
+            for message in receiver {
+                match message {
+                    Message::Offset(offset) => consumer.source_offset(&offset),
+                    Message::Fragment(data) => {
+                        consumer.input_fragment(&data);
+                    }
+                    Message::Chunk(data, trace_context) => {
+                        let headers = trace_context
+                            .iter()
+                            .flat_map(|headers| headers.iter())
+                            .map(|(name, value)| (name.as_str(), value.as_str()))
+                            .collect::<Vec<_>>();
+                        consumer.trace_context(&headers);
+                        consumer.input_chunk(&data);
+                    }
+                    Message::Error(fatal, error) => consumer.error(fatal, error),
+                    Message::Eoi => {
+                        consumer.eoi();
+                    }
+                    Message::Metrics(metrics) => consumer.transport_metrics(metrics),
+                    Message::Flush(ack) => {
+                        let _ = ack.send(());
+                    }
+                }
+            }
+            num_threads.fetch_sub(1, Ordering::AcqRel);
+        });
+
+        (id, sender)
+    }
+
+    /// Drop the pool's ends of the queues with the given `ids`.
+    fn remove(&self, ids: &[u64]) {
+        let mut queues = self.queues.lock().unwrap();
+        for id in ids {
+            queues.remove(id);
+        }
+    }
+}
+
+/// Consumer that distributes the data received from the transport endpoint
+/// across a pool of parser threads.
+///
+/// Parse errors are reported by the parsers, so `input_fragment`,
+/// `input_chunk` and `eoi` always return an empty vector.
+pub(crate) struct ParallelConsumer {
+    queues: Vec<Sender<Message>>,
+    /// Ids of `queues` in the pool.
+    queue_ids: Vec<u64>,
+    sharding: ParserSharding,
+    pool: Arc<ParserPool>,
+
+    /// Consumer to fork parsers from in [`InputConsumer::fork`].
+    template: Box<dyn InputConsumer>,
+
+    /// Parser that receives the next chunk in round-robin order.
+    next_parser: usize,
+
+    /// Partition of the data received next, set via
+    /// [`InputConsumer::partition`].
+    partition: Option<u32>,
+
+    /// Trace context of the data received next, set via
+    /// [`InputConsumer::trace_context`].
+    trace_context: Option<TraceContext>,
+
+    /// Source offset of the data received next, set via
+    /// [`InputConsumer::source_offset`], and the parsers that haven't
+    /// received it yet.
+    offset: Option<JsonValue>,
+    stale_offsets: Vec<bool>,
+}
+
+impl ParallelConsumer {
+    /// Start `num_parsers` parser threads in `pool`, feeding data to `consumer`
+    /// and its forks.
+    pub(crate) fn new(
+        consumer: Box<dyn InputConsumer>,
+        num_parsers: usize,
+        sharding: ParserSharding,
+        pool: Arc<ParserPool>,
+    ) -> Self {
+        let template = consumer.fork();
+        let mut forks = Vec::with_capacity(num_parsers.max(1));
+        for _ in 1..num_parsers {
+            forks.push(pool.spawn(consumer.fork()));
+        }
+        // The first parser, which receives fragments and end-of-input, uses
+        // the original consumer.
+        forks.insert(0, pool.spawn(consumer));
+        let (queue_ids, queues) = forks.into_iter().unzip();
+
+        Self {
+            queues,
+            queue_ids,
+            sharding,
+            pool,
+            template,
+            next_parser: 0,
+            partition: None,
+            trace_context: None,
+            offset: None,
+            stale_offsets: vec![false; num_parsers.max(1)],
+        }
+    }
+
+    /// Send the current source offset to parser `index` if it doesn't have it
+    /// yet.
+    fn send_offset(&mut self, index: usize) {
+        if self.stale_offsets[index] {
+            if let Some(offset) = &self.offset {
+                self.send(index, Message::Offset(offset.clone()));
+            }
+            self.stale_offsets[index] = false;
+        }
+    }
+
+    /// Send `message` to parser `index`.
+    fn send(&self, index: usize, message: Message) {
+        // Parser threads only exit after this consumer is dropped.
+        let _ = self.queues[index].send(message);
+    }
+
+    /// Choose the parser for the next chunk.
+    fn next_parser(&mut self) -> usize {
+        match (self.sharding, self.partition) {
+            (ParserSharding::Partition, Some(partition)) => partition as usize % self.queues.len(),
+            _ => {
+                let index = self.next_parser;
+                self.next_parser = (self.next_parser + 1) % self.queues.len();
+                index
+            }
+        }
+    }
+}
+
+impl InputConsumer for ParallelConsumer {
+    fn input_fragment(&mut self, data: &[u8]) -> Vec<ParseError> {
+        self.send_offset(0);
+        self.send(0, Message::Fragment(data.to_vec()));
+        Vec::new()
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> Vec<ParseError> {
+        let index = self.next_parser();
+        self.send_offset(index);
+        self.send(
+            index,
+            Message::Chunk(data.to_vec(), self.trace_context.clone()),
+        );
+        Vec::new()
+    }
+
+    fn error(&mut self, fatal: bool, error: AnyError) {
+        self.send(0, Message::Error(fatal, error));
+    }
+
+    fn eoi(&mut self) -> Vec<ParseError> {
+        // Chunks contain complete records, so only the first parser may hold
+        // a partially parsed record.  Make sure that all other parsers have
+        // pushed their records to the circuit before the first parser flushes
+        // its state and marks the endpoint as finished.
+        self.pool.flush();
+        self.send(0, Message::Eoi);
+        Vec::new()
+    }
+
+    fn transport_metrics(&mut self, metrics: JsonValue) {
+        self.send(0, Message::Metrics(metrics));
+    }
+
+    fn trace_context(&mut self, headers: &[(&str, &str)]) {
+        self.trace_context = (!headers.is_empty()).then(|| {
+            Arc::new(
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            )
+        });
+    }
+
+    fn partition(&mut self, partition: u32) {
+        self.partition = Some(partition);
+    }
+
+    fn source_offset(&mut self, offset: &JsonValue) {
+        self.offset = Some(offset.clone());
+        self.stale_offsets.fill(true);
+    }
+
+    fn fork(&self) -> Box<dyn InputConsumer> {
+        Box::new(Self::new(
+            self.template.fork(),
+            self.queues.len(),
+            self.sharding,
+            self.pool.clone(),
+        ))
+    }
+}
+
+impl Drop for ParallelConsumer {
+    fn drop(&mut self) {
+        // Let the parser threads exit once they have processed the data
+        // queued so far.
+        self.pool.remove(&self.queue_ids);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ParallelConsumer, ParserPool};
+    use crate::{
+        format::CsvParserConfig,
+        test::{mock_parser_pipeline, wait, TestStruct},
+        transport::InputConsumer,
+        FormatConfig, ParserSharding,
+    };
+    use std::{borrow::Cow, sync::Arc};
+
+    fn csv_format() -> FormatConfig {
+        FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(CsvParserConfig::default()).unwrap(),
+        }
+    }
+
+    fn test_parallel_consumer(sharding: ParserSharding) {
+        let format_config = csv_format();
+        let (mock_consumer, outputs) = mock_parser_pipeline::<TestStruct>(&format_config).unwrap();
+        let pool = Arc::new(ParserPool::default());
+        let mut consumer =
+            ParallelConsumer::new(Box::new(mock_consumer.clone()), 4, sharding, pool.clone());
+
+        for id in 0..100 {
+            consumer.partition(id % 3);
+            assert!(consumer
+                .input_chunk(format!("{id},true,,foo\n").as_bytes())
+                .is_empty());
+        }
+        pool.flush();
+
+        let mut ids = outputs
+            .state()
+            .flushed
+            .iter()
+            .map(|(record, polarity)| {
+                assert!(*polarity);
+                record.id
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, (0..100).collect::<Vec<_>>());
+
+        assert!(consumer.eoi().is_empty());
+        pool.flush();
+        assert!(mock_consumer.state().eoi);
+    }
+
+    #[test]
+    fn parallel_consumer_round_robin() {
+        test_parallel_consumer(ParserSharding::RoundRobin);
+    }
+
+    #[test]
+    fn parallel_consumer_partition() {
+        test_parallel_consumer(ParserSharding::Partition);
+    }
+
+    // Forking and dropping consumers, as transports do when partitions are
+    // reassigned, must not leak parser threads.
+    #[test]
+    fn forks_release_parser_threads() {
+        let (mock_consumer, outputs) = mock_parser_pipeline::<TestStruct>(&csv_format()).unwrap();
+        let pool = Arc::new(ParserPool::default());
+        let consumer = ParallelConsumer::new(
+            Box::new(mock_consumer),
+            4,
+            ParserSharding::RoundRobin,
+            pool.clone(),
+        );
+        assert_eq!(pool.num_threads(), 4);
+
+        for id in 0..50 {
+            let mut fork = consumer.fork();
+            assert!(fork
+                .input_chunk(format!("{id},true,,foo\n").as_bytes())
+                .is_empty());
+            drop(fork);
+            wait(|| pool.num_threads() == 4, Some(10_000)).unwrap();
+        }
+
+        // Records sent to a fork before it was dropped are still parsed.
+        assert_eq!(outputs.state().flushed.len(), 50);
+
+        drop(consumer);
+        wait(|| pool.num_threads() == 0, Some(10_000)).unwrap();
+    }
+}
@@ -0,0 +1,418 @@
+//! Mapping of input record fields to table columns.
+//!
+//! Implements [`ColumnMappingConfig`] as a wrapper around the input collection
+//! handle, which rewrites or drops each JSON record before it gets
+//! deserialized.
+
+use super::{ColumnCast, ColumnMappingConfig, ColumnPredicate, PredicateOp};
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    column_stats::compare_values,
+    ControllerError, DeCollectionHandle,
+};
+use anyhow::{anyhow, Result as AnyResult};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::{cmp::Ordering, sync::Arc};
+
+/// Input collection handle that applies a column mapping to all records.
+pub(crate) struct ProjectedCollectionHandle {
+    endpoint_name: String,
+    mapping: Arc<ColumnMappingConfig>,
+
+    /// Deserializer for JSON records connected to the underlying input
+    /// stream.  Streams returned by `configure_deserializer` are forks of
+    /// this stream.
+    json_stream: Box<dyn DeCollectionStream>,
+}
+
+impl ProjectedCollectionHandle {
+    pub(crate) fn new(
+        endpoint_name: &str,
+        input_handle: &dyn DeCollectionHandle,
+        mapping: &ColumnMappingConfig,
+    ) -> Result<Self, ControllerError> {
+        Ok(Self {
+            endpoint_name: endpoint_name.to_string(),
+            mapping: Arc::new(mapping.clone()),
+            json_stream: input_handle
+                .configure_deserializer(RecordFormat::Json(Default::default()))?,
+        })
+    }
+}
+
+impl DeCollectionHandle for ProjectedCollectionHandle {
+    fn configure_deserializer(
+        &self,
+        record_format: RecordFormat,
+    ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
+        match record_format {
+            RecordFormat::Json(flavor) if flavor == Default::default() => {
+                Ok(Box::new(ProjectedStream {
+                    inner: self.json_stream.fork(),
+                    mapping: self.mapping.clone(),
+                    buffer: Vec::new(),
+                }))
+            }
+            _ => Err(ControllerError::parser_config_parse_error(
+                &self.endpoint_name,
+                &"column mapping ('columns') is only supported by formats that produce JSON records, e.g., 'json' and 'avro'",
+                &serde_yaml::to_string(&*self.mapping).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Deserializer that applies a column mapping to each record before
+/// forwarding it to the underlying deserializer.
+struct ProjectedStream {
+    inner: Box<dyn DeCollectionStream>,
+    mapping: Arc<ColumnMappingConfig>,
+
+    /// Buffer to serialize mapped records to.
+    buffer: Vec<u8>,
+}
+
+/// Apply `mapping` to a record.
+///
+/// Returns `None` if the record doesn't pass the filter.
+fn project(
+    mapping: &ColumnMappingConfig,
+    record: Map<String, JsonValue>,
+) -> AnyResult<Option<Map<String, JsonValue>>> {
+    let mut result = if mapping.fields.is_empty() {
+        record
+    } else {
+        record
+            .into_iter()
+            .filter_map(|(field, value)| {
+                mapping
+                    .fields
+                    .get(&field)
+                    .map(|column| (column.clone(), value))
+            })
+            .collect()
+    };
+
+    // Column names are case-insensitive.
+    for (column, value) in mapping.defaults.iter() {
+        if column_mut(&mut result, column).is_none() {
+            result.insert(column.clone(), value.clone());
+        }
+    }
+
+    for (column, value) in mapping.constants.iter() {
+        match column_mut(&mut result, column) {
+            Some(old) => *old = value.clone(),
+            None => {
+                result.insert(column.clone(), value.clone());
+            }
+        }
+    }
+
+    for (column, cast_type) in mapping.casts.iter() {
+        if let Some(value) = column_mut(&mut result, column) {
+            *value = cast(value, *cast_type).ok_or_else(|| {
+                anyhow!("cannot cast value {value} of column '{column}' to {cast_type:?}")
+            })?;
+        }
+    }
+
+    if mapping
+        .filter
+        .iter()
+        .all(|predicate| eval_predicate(predicate, &result))
+    {
+        Ok(Some(result))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Find the value of `column` in `record` ignoring case.
+pub(super) fn column_mut<'a>(
+    record: &'a mut Map<String, JsonValue>,
+    column: &str,
+) -> Option<&'a mut JsonValue> {
+    record
+        .iter_mut()
+        .find(|(key, _)| key.eq_ignore_ascii_case(column))
+        .map(|(_, value)| value)
+}
+
+/// Convert `value` to `cast_type`.  Returns `None` if the value cannot be
+/// converted.
+fn cast(value: &JsonValue, cast_type: ColumnCast) -> Option<JsonValue> {
+    match (cast_type, value) {
+        (_, JsonValue::Null) => Some(JsonValue::Null),
+        (ColumnCast::String, JsonValue::String(_)) => Some(value.clone()),
+        (ColumnCast::String, JsonValue::Number(_) | JsonValue::Bool(_)) => {
+            Some(JsonValue::String(value.to_string()))
+        }
+        (ColumnCast::Integer, JsonValue::Number(n)) => match n.as_i64() {
+            Some(_) => Some(value.clone()),
+            None => n
+                .as_f64()
+                .filter(|f| f.fract() == 0.0 && *f >= i64::MIN as f64 && *f <= i64::MAX as f64)
+                .map(|f| JsonValue::from(f as i64)),
+        },
+        (ColumnCast::Integer, JsonValue::String(s)) => {
+            s.trim().parse::<i64>().ok().map(JsonValue::from)
+        }
+        (ColumnCast::Integer, JsonValue::Bool(b)) => Some(JsonValue::from(*b as i64)),
+        (ColumnCast::Float, JsonValue::Number(_)) => Some(value.clone()),
+        (ColumnCast::Float, JsonValue::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(JsonValue::Number),
+        (ColumnCast::Boolean, JsonValue::Bool(_)) => Some(value.clone()),
+        (ColumnCast::Boolean, JsonValue::String(s)) => {
+            if s.trim().eq_ignore_ascii_case("true") {
+                Some(JsonValue::Bool(true))
+            } else if s.trim().eq_ignore_ascii_case("false") {
+                Some(JsonValue::Bool(false))
+            } else {
+                None
+            }
+        }
+        (ColumnCast::Boolean, JsonValue::Number(n)) => match n.as_i64() {
+            Some(0) => Some(JsonValue::Bool(false)),
+            Some(1) => Some(JsonValue::Bool(true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Evaluate `predicate` on `record`.
+fn eval_predicate(predicate: &ColumnPredicate, record: &Map<String, JsonValue>) -> bool {
+    let value = record
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(&predicate.column))
+        .map_or(&JsonValue::Null, |(_, value)| value);
+
+    let ordering = || {
+        if value.is_null() || predicate.value.is_null() {
+            None
+        } else {
+            compare_values(value, &predicate.value)
+        }
+    };
+
+    match predicate.op {
+        PredicateOp::IsNull => value.is_null(),
+        PredicateOp::IsNotNull => !value.is_null(),
+        PredicateOp::Eq => ordering() == Some(Ordering::Equal),
+        PredicateOp::Ne => matches!(ordering(), Some(Ordering::Less | Ordering::Greater)),
+        PredicateOp::Lt => ordering() == Some(Ordering::Less),
+        PredicateOp::Le => matches!(ordering(), Some(Ordering::Less | Ordering::Equal)),
+        PredicateOp::Gt => ordering() == Some(Ordering::Greater),
+        PredicateOp::Ge => matches!(ordering(), Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+/// Result of applying a column mapping to a serialized record.
+enum Projection {
+    /// The record is not a JSON object, e.g., a record encoded as a JSON
+    /// array; such records are forwarded unmodified.
+    Unmodified,
+
+    /// The mapped record has been serialized to the buffer.
+    Mapped,
+
+    /// The record doesn't pass the filter.
+    Filtered,
+}
+
+/// Apply `mapping` to `data` and serialize the result to `buffer`.
+fn project_record(
+    mapping: &ColumnMappingConfig,
+    data: &[u8],
+    buffer: &mut Vec<u8>,
+) -> AnyResult<Projection> {
+    match serde_json::from_slice::<JsonValue>(data) {
+        Ok(JsonValue::Object(record)) => match project(mapping, record)? {
+            Some(record) => {
+                buffer.clear();
+                // Serializing a `serde_json::Value` cannot fail.
+                serde_json::to_writer(&mut *buffer, &record).unwrap();
+                Ok(Projection::Mapped)
+            }
+            None => Ok(Projection::Filtered),
+        },
+        _ => Ok(Projection::Unmodified),
+    }
+}
+
+impl DeCollectionStream for ProjectedStream {
+    fn insert(&mut self, data: &[u8]) -> AnyResult<()> {
+        match project_record(&self.mapping, data, &mut self.buffer)? {
+            Projection::Unmodified => self.inner.insert(data),
+            Projection::Mapped => self.inner.insert(&self.buffer),
+            Projection::Filtered => Ok(()),
+        }
+    }
+
+    fn delete(&mut self, data: &[u8]) -> AnyResult<()> {
+        match project_record(&self.mapping, data, &mut self.buffer)? {
+            Projection::Unmodified => self.inner.delete(data),
+            Projection::Mapped => self.inner.delete(&self.buffer),
+            Projection::Filtered => Ok(()),
+        }
+    }
+
+    fn reserve(&mut self, reservation: usize) {
+        self.inner.reserve(reservation)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+
+    fn clear_buffer(&mut self) {
+        self.inner.clear_buffer()
+    }
+
+    fn fork(&self) -> Box<dyn DeCollectionStream> {
+        Box::new(Self {
+            inner: self.inner.fork(),
+            mapping: self.mapping.clone(),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProjectedCollectionHandle;
+    use crate::{
+        catalog::RecordFormat,
+        format::{InputFormat, JsonParserConfig, JsonUpdateFormat},
+        test::{MockDeZSet, TestStruct},
+        ColumnMappingConfig, DeCollectionHandle,
+    };
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_column_mapping() {
+        let mapping = ColumnMappingConfig {
+            fields: BTreeMap::from([
+                ("user_id".to_string(), "id".to_string()),
+                ("name".to_string(), "s".to_string()),
+                ("flag".to_string(), "b".to_string()),
+            ]),
+            defaults: BTreeMap::from([("b".to_string(), json!(true)), ("i".to_string(), json!(5))]),
+            ..Default::default()
+        };
+
+        let input_handle = <MockDeZSet<TestStruct>>::new();
+        let handle = ProjectedCollectionHandle::new("test", &input_handle, &mapping).unwrap();
+
+        // CSV records cannot be mapped.
+        assert!(handle.configure_deserializer(RecordFormat::Csv(Default::default())).is_err());
+
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                &handle,
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap();
+
+        let (num_records, errors) = parser.input_chunk(
+            br#"{"user_id": 1, "name": "foo", "id": 100}
+{"user_id": 2, "name": "bar", "flag": false, "i": 3}"#,
+        );
+        assert_eq!(num_records, 2);
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            input_handle.state().flushed,
+            vec![
+                (
+                    TestStruct {
+                        id: 1,
+                        b: true,
+                        i: Some(5),
+                        s: "foo".to_string(),
+                    },
+                    true
+                ),
+                (
+                    TestStruct {
+                        id: 2,
+                        b: false,
+                        i: Some(5),
+                        s: "bar".to_string(),
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mapping: ColumnMappingConfig = serde_yaml::from_str(
+            r#"
+constants:
+    s: "const"
+casts:
+    id: integer
+    b: boolean
+    i: integer
+filter:
+    - column: id
+      op: gt
+      value: 1
+    - column: i
+      op: is_not_null
+"#,
+        )
+        .unwrap();
+
+        let input_handle = <MockDeZSet<TestStruct>>::new();
+        let handle = ProjectedCollectionHandle::new("test", &input_handle, &mapping).unwrap();
+
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                &handle,
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .unwrap();
+
+        let (_, errors) = parser.input_chunk(
+            br#"{"id": "1", "b": "true", "i": 1, "s": "foo"}
+{"id": "2", "b": "FALSE", "i": 2.0, "s": "bar"}
+{"id": 3, "b": 1, "i": null, "s": "baz"}
+{"id": 4, "b": true, "i": "four", "s": "qux"}"#,
+        );
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(
+            input_handle.state().flushed,
+            vec![(
+                TestStruct {
+                    id: 2,
+                    b: false,
+                    i: Some(2),
+                    s: "const".to_string(),
+                },
+                true
+            )]
+        );
+    }
+}
@@ -0,0 +1,781 @@
+//! Recording and replaying pipeline inputs.
+//!
+//! Implements [`ReplayConfig`].  In `record` mode, each input endpoint pushes
+//! its data through a [`RecordingConsumer`], which appends the data to the
+//! replay log, along with the name of the endpoint and the number of the
+//! step that processes it, before forwarding it to the parser.  The log is a
+//! file with one JSON entry per line:
+//!
+//! ```text
+//! {"kind":"connect","step":1,"endpoint":"kafka_in","config":{...}}
+//! {"kind":"chunk","step":1,"endpoint":"kafka_in","data":"eyJpZCI6IDF9"}
+//! {"kind":"step","step":1}
+//! {"kind":"eoi","step":2,"endpoint":"kafka_in"}
+//! {"kind":"step","step":2}
+//! ```
+//!
+//! To attribute data to steps exactly, the circuit thread holds the log from
+//! the start of each step until the step completes (see [`ReplayLog::lock`]),
+//! so endpoints cannot record data while a step is in progress.  The circuit
+//! thread then writes a `step` entry, so that steps that received no inputs
+//! are replayed too.
+//!
+//! In `replay` mode, the controller does not connect the endpoints listed in
+//! the pipeline configuration.  Instead, a [`Replayer`] thread reads the log,
+//! connects a [`ReplayEndpoint`] for each `connect` entry, pushes recorded
+//! data to these endpoints, and performs a step of the circuit for each
+//! `step` entry, in the order they were recorded.
+
+use super::{ControllerInner, InputEndpointConfig};
+use crate::{
+    transport::{InputConsumer, InputEndpoint},
+    ControllerError, ParseError, PipelineState,
+};
+use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crossbeam::channel;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Error as IoError, Write},
+    sync::{Arc, Mutex, MutexGuard},
+    thread::sleep,
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+/// How often the replay thread checks whether a paused pipeline has been
+/// resumed.
+const PAUSED_POLL_PERIOD: Duration = Duration::from_millis(100);
+
+/// Replay log configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayConfig {
+    /// Path to the replay log file.
+    pub path: String,
+
+    /// Whether to record a new log or replay an existing one.
+    #[serde(default)]
+    pub mode: ReplayMode,
+}
+
+/// Replay log mode.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayMode {
+    /// Append all data received by input endpoints to the log.  An existing
+    /// log at the same path is overwritten when the pipeline starts.
+    ///
+    /// While recording, endpoints wait for the step in progress, if any, to
+    /// complete before they can push more data to the pipeline.
+    #[default]
+    Record,
+
+    /// Re-feed the inputs recorded in the log to the pipeline, one step at a
+    /// time, instead of reading from the configured input endpoints.  The
+    /// pipeline runs in manual stepping mode; the replay is suspended while
+    /// the pipeline is paused.
+    Replay,
+}
+
+/// An entry of the replay log.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum LogEntry {
+    /// Input endpoint `endpoint` was connected with configuration `config`.
+    Connect {
+        step: u64,
+        endpoint: String,
+        config: InputEndpointConfig,
+    },
+
+    /// [`InputConsumer::input_fragment`], with base64-encoded `data`.
+    Fragment {
+        step: u64,
+        endpoint: String,
+        data: String,
+    },
+
+    /// [`InputConsumer::input_chunk`], with base64-encoded `data`.
+    Chunk {
+        step: u64,
+        endpoint: String,
+        data: String,
+    },
+
+    /// [`InputConsumer::source_offset`].
+    Offset {
+        step: u64,
+        endpoint: String,
+        offset: JsonValue,
+    },
+
+    /// [`InputConsumer::eoi`].
+    Eoi { step: u64, endpoint: String },
+
+    /// Step `step` of the circuit completed.
+    Step { step: u64 },
+}
+
+/// Replay log being recorded.
+pub(crate) struct ReplayLog {
+    path: String,
+    writer: Mutex<LogWriter>,
+}
+
+impl ReplayLog {
+    /// Create a new log at `path`, replacing the existing file, if any.
+    pub(crate) fn create(path: &str) -> Result<Self, ControllerError> {
+        let file = File::create(path).map_err(|e| {
+            ControllerError::replay_log_error(&format!("error creating '{path}': {e}"))
+        })?;
+
+        Ok(Self {
+            path: path.to_string(),
+            writer: Mutex::new(LogWriter {
+                file: BufWriter::new(file),
+                step: 1,
+            }),
+        })
+    }
+
+    /// Lock the log, preventing input endpoints from recording data until
+    /// the returned guard is dropped.
+    pub(crate) fn lock(&self) -> MutexGuard<'_, LogWriter> {
+        self.writer.lock().unwrap()
+    }
+
+    fn error(&self, error: IoError) -> ControllerError {
+        ControllerError::replay_log_error(&format!("error writing '{}': {error}", self.path))
+    }
+}
+
+pub(crate) struct LogWriter {
+    file: BufWriter<File>,
+
+    /// The step that will process the data recorded now.
+    step: u64,
+}
+
+impl LogWriter {
+    fn write(&mut self, entry: &LogEntry) -> Result<(), IoError> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")
+    }
+
+    /// Record that input endpoint `endpoint_name` was connected.
+    pub(crate) fn connect(
+        &mut self,
+        log: &ReplayLog,
+        endpoint_name: &str,
+        config: &InputEndpointConfig,
+    ) -> Result<(), ControllerError> {
+        self.write(&LogEntry::Connect {
+            step: self.step,
+            endpoint: endpoint_name.to_string(),
+            config: config.clone(),
+        })
+        .map_err(|e| log.error(e))
+    }
+
+    /// Record the completion of a step of the circuit.  Data recorded from
+    /// now on is processed by the next step.
+    pub(crate) fn step_completed(&mut self, log: &ReplayLog) -> Result<(), ControllerError> {
+        self.write(&LogEntry::Step { step: self.step })
+            .and_then(|()| self.file.flush())
+            .map_err(|e| log.error(e))?;
+        self.step += 1;
+        Ok(())
+    }
+}
+
+/// Input consumer that records all data pushed to it in the replay log.
+pub(crate) struct RecordingConsumer {
+    endpoint_name: String,
+    log: Arc<ReplayLog>,
+    inner: Box<dyn InputConsumer>,
+}
+
+impl RecordingConsumer {
+    pub(crate) fn new(
+        endpoint_name: &str,
+        log: Arc<ReplayLog>,
+        inner: Box<dyn InputConsumer>,
+    ) -> Self {
+        Self {
+            endpoint_name: endpoint_name.to_string(),
+            log,
+            inner,
+        }
+    }
+
+    /// Append the entry built by `entry` to `log`, returning the lock on
+    /// the log, which the caller holds while forwarding the data to the
+    /// inner consumer.  Write errors are reported as non-fatal transport
+    /// errors.
+    fn record<'a>(
+        &mut self,
+        log: &'a ReplayLog,
+        entry: impl FnOnce(u64, String) -> LogEntry,
+    ) -> MutexGuard<'a, LogWriter> {
+        let mut writer = log.lock();
+        let entry = entry(writer.step, self.endpoint_name.clone());
+        if let Err(e) = writer.write(&entry) {
+            self.inner.error(
+                false,
+                anyhow!("error writing replay log '{}': {e}", log.path),
+            );
+        }
+        writer
+    }
+}
+
+impl InputConsumer for RecordingConsumer {
+    fn input_fragment(&mut self, data: &[u8]) -> Vec<ParseError> {
+        let log = self.log.clone();
+        let _writer = self.record(&log, |step, endpoint| LogEntry::Fragment {
+            step,
+            endpoint,
+            data: BASE64.encode(data),
+        });
+        self.inner.input_fragment(data)
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> Vec<ParseError> {
+        let log = self.log.clone();
+        let _writer = self.record(&log, |step, endpoint| LogEntry::Chunk {
+            step,
+            endpoint,
+            data: BASE64.encode(data),
+        });
+        self.inner.input_chunk(data)
+    }
+
+    fn error(&mut self, fatal: bool, error: AnyError) {
+        self.inner.error(fatal, error)
+    }
+
+    fn eoi(&mut self) -> Vec<ParseError> {
+        let log = self.log.clone();
+        let _writer = self.record(&log, |step, endpoint| LogEntry::Eoi { step, endpoint });
+        self.inner.eoi()
+    }
+
+    fn transport_metrics(&mut self, metrics: JsonValue) {
+        self.inner.transport_metrics(metrics)
+    }
+
+    fn trace_context(&mut self, headers: &[(&str, &str)]) {
+        self.inner.trace_context(headers)
+    }
+
+    fn partition(&mut self, partition: u32) {
+        self.inner.partition(partition)
+    }
+
+    fn source_offset(&mut self, offset: &JsonValue) {
+        let log = self.log.clone();
+        let _writer = self.record(&log, |step, endpoint| LogEntry::Offset {
+            step,
+            endpoint,
+            offset: offset.clone(),
+        });
+        self.inner.source_offset(offset)
+    }
+
+    fn fork(&self) -> Box<dyn InputConsumer> {
+        Box::new(Self {
+            endpoint_name: self.endpoint_name.clone(),
+            log: self.log.clone(),
+            inner: self.inner.fork(),
+        })
+    }
+}
+
+/// Input endpoint fed by the [`Replayer`].
+#[derive(Clone, Default)]
+struct ReplayEndpoint {
+    consumer: Arc<Mutex<Option<Box<dyn InputConsumer>>>>,
+}
+
+impl ReplayEndpoint {
+    /// Apply `f` to the consumer the endpoint is connected to, if any.
+    /// Parse errors returned by `f` are discarded, since the input probe
+    /// reports them to the controller.
+    fn push<T>(&self, f: impl FnOnce(&mut dyn InputConsumer) -> T) {
+        if let Some(consumer) = self.consumer.lock().unwrap().as_mut() {
+            f(consumer.as_mut());
+        }
+    }
+}
+
+impl InputEndpoint for ReplayEndpoint {
+    fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        *self.consumer.lock().unwrap() = Some(consumer);
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        *self.consumer.lock().unwrap() = None;
+    }
+}
+
+/// Replays a log recorded in [`ReplayMode::Record`] mode.
+pub(crate) struct Replayer {
+    path: String,
+    reader: BufReader<File>,
+    controller: Arc<ControllerInner>,
+
+    /// Endpoints connected by the replay, by name.
+    endpoints: BTreeMap<String, ReplayEndpoint>,
+}
+
+impl Replayer {
+    /// Open the log at `path` for replay.
+    pub(crate) fn open(
+        path: &str,
+        controller: Arc<ControllerInner>,
+    ) -> Result<Self, ControllerError> {
+        let file = File::open(path).map_err(|e| {
+            ControllerError::replay_log_error(&format!("error opening '{path}': {e}"))
+        })?;
+
+        Ok(Self {
+            path: path.to_string(),
+            reader: BufReader::new(file),
+            controller,
+            endpoints: BTreeMap::new(),
+        })
+    }
+
+    /// Replay the log, reporting errors to the controller.  Runs until the
+    /// end of the log or until the pipeline terminates.
+    pub(crate) fn run(mut self) {
+        match self.replay() {
+            Ok(steps) => info!("Replayed {steps} steps from '{}'", self.path),
+            Err(e) => self.controller.error(e),
+        }
+    }
+
+    fn replay(&mut self) -> Result<u64, ControllerError> {
+        let mut steps = 0;
+        let mut line = String::new();
+        for line_number in 1.. {
+            line.clear();
+            let len = self.reader.read_line(&mut line).map_err(|e| {
+                ControllerError::replay_log_error(&format!("error reading '{}': {e}", self.path))
+            })?;
+            if len == 0 {
+                break;
+            }
+
+            let entry = serde_json::from_str::<LogEntry>(&line).map_err(|e| {
+                ControllerError::replay_log_error(&format!(
+                    "invalid entry at '{}', line {line_number}: {e}",
+                    self.path
+                ))
+            })?;
+            match entry {
+                LogEntry::Connect {
+                    endpoint, config, ..
+                } => {
+                    // An endpoint that failed to connect while recording may
+                    // have been connected again under the same name.
+                    if self.endpoints.contains_key(&endpoint) {
+                        debug!("replay: endpoint '{endpoint}' is already connected");
+                        continue;
+                    }
+                    let replay_endpoint = ReplayEndpoint::default();
+                    self.controller.add_input_endpoint(
+                        &endpoint,
+                        config,
+                        Box::new(replay_endpoint.clone()),
+                    )?;
+                    self.endpoints.insert(endpoint, replay_endpoint);
+                }
+                LogEntry::Fragment { endpoint, data, .. } => {
+                    let data = self.decode(&data, line_number)?;
+                    self.endpoint(&endpoint, line_number)?
+                        .push(|consumer| consumer.input_fragment(&data));
+                }
+                LogEntry::Chunk { endpoint, data, .. } => {
+                    let data = self.decode(&data, line_number)?;
+                    self.endpoint(&endpoint, line_number)?
+                        .push(|consumer| consumer.input_chunk(&data));
+                }
+                LogEntry::Offset {
+                    endpoint, offset, ..
+                } => {
+                    self.endpoint(&endpoint, line_number)?
+                        .push(|consumer| consumer.source_offset(&offset));
+                }
+                LogEntry::Eoi { endpoint, .. } => {
+                    self.endpoint(&endpoint, line_number)?
+                        .push(|consumer| consumer.eoi());
+                }
+                LogEntry::Step { .. } => {
+                    if !self.step() {
+                        break;
+                    }
+                    steps += 1;
+                }
+            }
+        }
+
+        Ok(steps)
+    }
+
+    fn endpoint(&self, name: &str, line_number: usize) -> Result<&ReplayEndpoint, ControllerError> {
+        self.endpoints.get(name).ok_or_else(|| {
+            ControllerError::replay_log_error(&format!(
+                "entry at '{}', line {line_number} refers to endpoint '{name}', which has not been connected",
+                self.path
+            ))
+        })
+    }
+
+    fn decode(&self, data: &str, line_number: usize) -> Result<Vec<u8>, ControllerError> {
+        BASE64.decode(data).map_err(|e| {
+            ControllerError::replay_log_error(&format!(
+                "invalid data at '{}', line {line_number}: {e}",
+                self.path
+            ))
+        })
+    }
+
+    /// Perform a step of the circuit once the pipeline is running and wait
+    /// for it to complete.  Returns `false` if the pipeline terminated.
+    fn step(&self) -> bool {
+        loop {
+            match self.controller.state() {
+                PipelineState::Running => break,
+                PipelineState::Paused => sleep(PAUSED_POLL_PERIOD),
+                PipelineState::Terminated => return false,
+            }
+        }
+
+        let (sender, receiver) = channel::bounded(1);
+        self.controller.step_with_stats(Box::new(move |_| {
+            let _ = sender.send(());
+        }));
+        receiver.recv().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LogEntry, RecordingConsumer, ReplayLog};
+    use crate::{
+        controller::StepStats,
+        test::{mock_parser_pipeline, test_circuit, wait, TestStruct},
+        transport::InputConsumer,
+        Controller, InputEndpointConfig, PipelineConfig,
+    };
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use csv::ReaderBuilder as CsvReaderBuilder;
+    use serde_json::json;
+    use std::{
+        fs::{read_to_string, OpenOptions},
+        io::Write,
+        sync::{mpsc, Arc},
+        time::Duration,
+    };
+    use tempfile::{NamedTempFile, TempPath};
+
+    #[test]
+    fn test_record() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        let log = Arc::new(ReplayLog::create(path).unwrap());
+
+        let config: InputEndpointConfig = serde_yaml::from_str(
+            r#"
+stream: test_input
+transport:
+    name: file
+    config:
+        path: "test.json"
+format:
+    name: json
+"#,
+        )
+        .unwrap();
+        log.lock().connect(&log, "test", &config).unwrap();
+
+        let (mock_consumer, _input_handle) =
+            mock_parser_pipeline::<TestStruct>(&config.connector_config.format).unwrap();
+        let mut consumer =
+            RecordingConsumer::new("test", log.clone(), Box::new(mock_consumer.clone()));
+        consumer.input_chunk(b"foo");
+        log.lock().step_completed(&log).unwrap();
+
+        // Forks record to the same log.
+        let mut forked = consumer.fork();
+        forked.input_fragment(b"bar");
+        consumer.eoi();
+        log.lock().step_completed(&log).unwrap();
+
+        // Recorded data is forwarded to the inner consumer.
+        assert_eq!(mock_consumer.state().data, b"foobar");
+
+        let entries: Vec<LogEntry> = read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                LogEntry::Connect {
+                    step: 1,
+                    endpoint: "test".to_string(),
+                    config,
+                },
+                LogEntry::Chunk {
+                    step: 1,
+                    endpoint: "test".to_string(),
+                    data: BASE64.encode(b"foo"),
+                },
+                LogEntry::Step { step: 1 },
+                LogEntry::Fragment {
+                    step: 2,
+                    endpoint: "test".to_string(),
+                    data: BASE64.encode(b"bar"),
+                },
+                LogEntry::Eoi {
+                    step: 2,
+                    endpoint: "test".to_string(),
+                },
+                LogEntry::Step { step: 2 },
+            ]
+        );
+    }
+
+    fn test_struct(id: u32) -> TestStruct {
+        TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64),
+            s: id.to_string(),
+        }
+    }
+
+    /// Path of a temporary file that doesn't exist yet.
+    fn temp_path() -> TempPath {
+        let path = NamedTempFile::new().unwrap().into_temp_path();
+        std::fs::remove_file(&path).unwrap();
+        path
+    }
+
+    fn pipeline_config(
+        input_path: &str,
+        output_path: &str,
+        log_path: &str,
+        mode: &str,
+    ) -> PipelineConfig {
+        serde_yaml::from_str(&format!(
+            r#"
+name: test
+workers: 4
+replay:
+    path: {log_path:?}
+    mode: {mode}
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {input_path:?}
+                follow: true
+        format:
+            name: json
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {output_path:?}
+        format:
+            name: csv
+"#
+        ))
+        .unwrap()
+    }
+
+    /// Read the changes written by a CSV output endpoint.
+    fn read_output(path: &str) -> Vec<(TestStruct, i32)> {
+        CsvReaderBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .unwrap()
+            .deserialize::<(TestStruct, i32)>()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// Split `output` into the changes produced by each step, sorted.
+    fn split_steps(
+        output: &[(TestStruct, i32)],
+        steps: &[StepStats],
+    ) -> Vec<Vec<(TestStruct, i32)>> {
+        let mut rest = output;
+        let result = steps
+            .iter()
+            .map(|stats| {
+                let (step, tail) = rest.split_at(stats.output_records as usize);
+                rest = tail;
+                let mut step = step.to_vec();
+                step.sort();
+                step
+            })
+            .collect();
+        assert!(rest.is_empty());
+        result
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let input_file = NamedTempFile::new().unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+        let log_path = temp_path();
+        let log_path = log_path.to_str().unwrap();
+        let recorded_output_path = temp_path();
+        let recorded_output_path = recorded_output_path.to_str().unwrap();
+        let replayed_output_path = temp_path();
+        let replayed_output_path = replayed_output_path.to_str().unwrap();
+
+        // Inputs of each step.  Step 2 deletes some of the records inserted
+        // by step 1, so replaying both steps as one would not output them.
+        // Step 3 receives no inputs.
+        let steps = vec![
+            (0..10)
+                .map(|id| json!({ "insert": test_struct(id) }))
+                .collect::<Vec<_>>(),
+            (0..5)
+                .map(|id| json!({ "delete": test_struct(id) }))
+                .chain((10..15).map(|id| json!({ "insert": test_struct(id) })))
+                .collect(),
+            vec![],
+            (15..20)
+                .map(|id| json!({ "insert": test_struct(id) }))
+                .collect(),
+        ];
+
+        // Record a run of the pipeline, one step at a time.
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &pipeline_config(input_path, recorded_output_path, log_path, "record"),
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+        controller.set_manual_stepping(true);
+        controller.start();
+
+        let mut input = OpenOptions::new().append(true).open(input_path).unwrap();
+        let mut recorded_steps = Vec::new();
+        for step in steps.iter() {
+            for record in step.iter() {
+                writeln!(input, "{record}").unwrap();
+            }
+            input.flush().unwrap();
+            wait(
+                || controller.status().num_buffered_input_records() == step.len() as u64,
+                Some(10_000),
+            )
+            .unwrap();
+
+            let (sender, receiver) = mpsc::channel();
+            controller.step_with_stats(Box::new(move |stats| sender.send(stats).unwrap()));
+            let stats = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+            assert_eq!(stats.input_records, step.len() as u64);
+            recorded_steps.push(stats);
+        }
+        let output_records: u64 = recorded_steps
+            .iter()
+            .map(|stats| stats.output_records)
+            .sum();
+        wait(
+            || {
+                controller
+                    .status()
+                    .output_status()
+                    .get(&0)
+                    .unwrap()
+                    .transmitted_records()
+                    == output_records
+            },
+            Some(10_000),
+        )
+        .unwrap();
+        controller.stop().unwrap();
+
+        // Replay the log into a fresh pipeline.  The input file is not read
+        // again: appending to it must not affect the replay.
+        writeln!(input, "{}", json!({ "insert": test_struct(100) })).unwrap();
+        input.flush().unwrap();
+        let controller = Controller::with_config(
+            |workers| Ok(test_circuit(workers)),
+            &pipeline_config(input_path, replayed_output_path, log_path, "replay"),
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+        controller.start();
+
+        wait(
+            || controller.status().num_total_steps() == steps.len() as u64,
+            Some(10_000),
+        )
+        .unwrap();
+        wait(
+            || {
+                controller
+                    .status()
+                    .output_status()
+                    .get(&0)
+                    .unwrap()
+                    .transmitted_records()
+                    == output_records
+            },
+            Some(10_000),
+        )
+        .unwrap();
+        // The replay stops at the end of the log.
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(controller.status().num_total_steps(), steps.len() as u64);
+        assert_eq!(
+            controller.status().num_total_processed_records(),
+            steps.iter().map(|step| step.len() as u64).sum::<u64>()
+        );
+        controller.stop().unwrap();
+
+        // The replayed pipeline produces the same outputs at each step.
+        let recorded = split_steps(&read_output(recorded_output_path), &recorded_steps);
+        let replayed = split_steps(&read_output(replayed_output_path), &recorded_steps);
+        assert_eq!(recorded, replayed);
+        assert_eq!(
+            recorded,
+            vec![
+                (0..10).map(|id| (test_struct(id), 1)).collect::<Vec<_>>(),
+                (0..5)
+                    .map(|id| (test_struct(id), -1))
+                    .chain((10..15).map(|id| (test_struct(id), 1)))
+                    .collect(),
+                vec![],
+                (15..20).map(|id| (test_struct(id), 1)).collect(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Retrying transient transport failures.
+
+use anyhow::Error as AnyError;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::ErrorKind,
+    time::{Duration, SystemTime},
+};
+use utoipa::ToSchema;
+
+const fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+const fn default_max_backoff_ms() -> u64 {
+    30_000
+}
+
+const fn default_jitter_percent() -> u32 {
+    20
+}
+
+/// Policy for retrying transport operations that fail with a transient
+/// error, e.g., because a message broker is temporarily unavailable.
+///
+/// Whether an error is transient is decided by the transport (see
+/// `InputTransport::is_retryable` and `OutputTransport::is_retryable`).
+/// When an input endpoint fails with a transient error, the controller
+/// creates a new instance of the endpoint after a backoff delay.  The new
+/// instance may re-read some of the data received by the failed instance,
+/// depending on the transport.  When an output endpoint fails to send a
+/// buffer, the controller retries sending the buffer, blocking the
+/// endpoint in the meantime.
+///
+/// Transports that reconnect on their own, such as `websocket` and
+/// `postgres_cdc`, use the same policy to space out reconnection attempts.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RetryConfig {
+    /// Maximal number of consecutive retries, after which the error is
+    /// reported as fatal.  When not specified, the controller keeps
+    /// retrying indefinitely.
+    pub max_retries: Option<u32>,
+
+    /// Delay in milliseconds before the first retry.  The delay doubles
+    /// after each consecutive failure, up to `max_backoff_ms`.  Defaults
+    /// to 500.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    /// Maximal delay in milliseconds between retries.  Defaults to 30,000.
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    /// Randomly reduce each delay by up to this percentage, so that
+    /// endpoints that failed at the same time don't retry in lockstep.
+    /// Defaults to 20.
+    #[serde(default = "default_jitter_percent")]
+    pub jitter_percent: u32,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: None,
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            jitter_percent: default_jitter_percent(),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Delay before retry number `retry` (starting from 1), or `None` if the
+    /// retry budget is exhausted.
+    pub(crate) fn backoff(&self, retry: u32) -> Option<Duration> {
+        if let Some(max_retries) = self.max_retries {
+            if retry > max_retries {
+                return None;
+            }
+        }
+        let delay = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << retry.saturating_sub(1).min(32))
+            .min(self.max_backoff_ms);
+        let jitter = self.jitter_percent.min(100) as f64 / 100.0;
+        let delay = delay as f64 * (1.0 - jitter * random_fraction());
+        Some(Duration::from_millis(delay as u64))
+    }
+}
+
+/// A pseudo-random number in `[0, 1)`.
+///
+/// Good enough for jitter, without pulling in a random number generator.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Returns `true` if `error` was caused by an I/O error that is likely to go
+/// away on retry, such as a refused or reset network connection.
+///
+/// This is the default classification used by transports that don't
+/// provide their own.
+pub fn is_transient_error(error: &AnyError) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .map_or(false, |error| {
+                matches!(
+                    error.kind(),
+                    ErrorKind::ConnectionRefused
+                        | ErrorKind::ConnectionReset
+                        | ErrorKind::ConnectionAborted
+                        | ErrorKind::NotConnected
+                        | ErrorKind::BrokenPipe
+                        | ErrorKind::TimedOut
+                        | ErrorKind::Interrupted
+                        | ErrorKind::UnexpectedEof
+                )
+            })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_transient_error, RetryConfig};
+    use anyhow::anyhow;
+    use std::{io, time::Duration};
+
+    #[test]
+    fn backoff() {
+        let config: RetryConfig = serde_yaml::from_str(
+            r#"
+max_retries: 5
+initial_backoff_ms: 100
+max_backoff_ms: 1000
+jitter_percent: 0
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.backoff(1), Some(Duration::from_millis(100)));
+        assert_eq!(config.backoff(2), Some(Duration::from_millis(200)));
+        assert_eq!(config.backoff(4), Some(Duration::from_millis(800)));
+        assert_eq!(config.backoff(5), Some(Duration::from_millis(1000)));
+        assert_eq!(config.backoff(6), None);
+
+        let config: RetryConfig = serde_yaml::from_str("initial_backoff_ms: 1000").unwrap();
+        for retry in 1..100 {
+            let delay = config.backoff(retry).unwrap();
+            assert!(delay <= Duration::from_millis(30_000));
+            assert!(delay >= Duration::from_millis(800));
+        }
+    }
+
+    #[test]
+    fn transient_errors() {
+        let error = anyhow!(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert!(is_transient_error(&error));
+        assert!(is_transient_error(&error.context("failed to connect")));
+        assert!(!is_transient_error(&anyhow!(io::Error::from(
+            io::ErrorKind::NotFound
+        ))));
+        assert!(!is_transient_error(&anyhow!("invalid configuration")));
+    }
+}
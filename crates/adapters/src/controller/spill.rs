@@ -0,0 +1,354 @@
+//! Disk-backed output buffering.
+//!
+//! Implements the `spill` [`OverflowPolicy`](`super::OverflowPolicy`) for
+//! output endpoints.  The output thread of the endpoint encodes the outputs
+//! of the circuit as soon as they are produced, and the encoder writes the
+//! encoded buffers to a [`SpillWriter`], which appends them to a temporary
+//! [`SpillFile`] instead of sending them to the transport.  A separate
+//! sender thread reads buffers back from the file in the order they were
+//! written and passes them to the endpoint's output probe.  The sender thread
+//! may block on a slow transport as long as it needs to without holding back
+//! the output thread, and therefore the circuit.
+//!
+//! ```text
+//! ┌───────┐   ┌───────────┐   ┌─────────┐   ┌──────┐   ┌───────────┐
+//! │encoder├──►│SpillWriter├──►│SpillFile├──►│sender├──►│OutputProbe├──►
+//! └───────┘   └───────────┘   └─────────┘   └──────┘   └───────────┘
+//! ```
+
+use super::{ControllerInner, EndpointId};
+use crate::{OutputConsumer, PipelineState};
+use anyhow::anyhow;
+use crossbeam::sync::{Parker, Unparker};
+use log::warn;
+use std::{
+    fs::{remove_file, File, OpenOptions},
+    io::{Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// How often the sender thread checks whether a paused endpoint has been
+/// resumed.
+const PAUSED_POLL_PERIOD: Duration = Duration::from_millis(100);
+
+const BATCH_START: u8 = 0;
+const BUFFER: u8 = 1;
+const BATCH_END: u8 = 2;
+
+/// An operation of the encoder on its output consumer.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Frame {
+    BatchStart,
+    Buffer(Vec<u8>),
+    BatchEnd,
+}
+
+/// FIFO queue of [`Frame`]s in a temporary file.
+///
+/// Each frame is stored as a one-byte tag, followed, for buffers, by the
+/// length of the buffer as a little-endian `u32` and the contents of the
+/// buffer.  The file is truncated whenever the reader catches up with the
+/// writer, so it only grows while the endpoint is behind.
+pub(crate) struct SpillFile {
+    path: PathBuf,
+    inner: Mutex<SpillFileInner>,
+
+    /// Set when the writer is dropped; no more frames will be written.
+    closed: AtomicBool,
+}
+
+struct SpillFileInner {
+    file: File,
+    read_pos: u64,
+    write_pos: u64,
+}
+
+impl SpillFile {
+    /// Create an empty spill file in the system's temporary directory.
+    pub(crate) fn new() -> Result<Self, IoError> {
+        let path = std::env::temp_dir().join(format!("feldera-spill-{}", Uuid::new_v4()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            inner: Mutex::new(SpillFileInner {
+                file,
+                read_pos: 0,
+                write_pos: 0,
+            }),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Append `frame` to the file.  Returns the number of unread bytes in the
+    /// file.
+    pub(crate) fn write(&self, frame: &Frame) -> Result<u64, IoError> {
+        let mut data = Vec::new();
+        match frame {
+            Frame::BatchStart => data.push(BATCH_START),
+            Frame::Buffer(buffer) => {
+                let len = u32::try_from(buffer.len()).map_err(|_| {
+                    IoError::new(ErrorKind::InvalidInput, "output buffer exceeds 4 GiB")
+                })?;
+                data.reserve(buffer.len() + 5);
+                data.push(BUFFER);
+                data.extend_from_slice(&len.to_le_bytes());
+                data.extend_from_slice(buffer);
+            }
+            Frame::BatchEnd => data.push(BATCH_END),
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let write_pos = inner.write_pos;
+        inner.file.seek(SeekFrom::Start(write_pos))?;
+        inner.file.write_all(&data)?;
+        inner.write_pos += data.len() as u64;
+        Ok(inner.write_pos - inner.read_pos)
+    }
+
+    /// Read the oldest unread frame from the file, if any.  Returns the frame
+    /// along with the number of bytes remaining in the file.
+    pub(crate) fn read(&self) -> Result<Option<(Frame, u64)>, IoError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.read_pos == inner.write_pos {
+            return Ok(None);
+        }
+
+        let read_pos = inner.read_pos;
+        inner.file.seek(SeekFrom::Start(read_pos))?;
+        let mut tag = [0u8; 1];
+        inner.file.read_exact(&mut tag)?;
+        let (frame, frame_len) = match tag[0] {
+            BATCH_START => (Frame::BatchStart, 1),
+            BATCH_END => (Frame::BatchEnd, 1),
+            BUFFER => {
+                let mut len = [0u8; 4];
+                inner.file.read_exact(&mut len)?;
+                let mut buffer = vec![0; u32::from_le_bytes(len) as usize];
+                inner.file.read_exact(&mut buffer)?;
+                let frame_len = buffer.len() as u64 + 5;
+                (Frame::Buffer(buffer), frame_len)
+            }
+            tag => {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid frame tag {tag} in spill file"),
+                ))
+            }
+        };
+
+        inner.read_pos += frame_len;
+        if inner.read_pos == inner.write_pos {
+            // Reclaim disk space once the reader has caught up.
+            inner.file.set_len(0)?;
+            inner.read_pos = 0;
+            inner.write_pos = 0;
+        }
+        Ok(Some((frame, inner.write_pos - inner.read_pos)))
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(e) = remove_file(&self.path) {
+            warn!("Failed to remove spill file '{}': {e}", self.path.display());
+        }
+    }
+}
+
+/// Output consumer that writes the output of the encoder to a spill file
+/// and starts a thread that forwards the contents of the file to `consumer`.
+pub(crate) struct SpillWriter {
+    file: Arc<SpillFile>,
+    max_buffer_size_bytes: usize,
+    endpoint_id: EndpointId,
+    endpoint_name: String,
+    controller: Arc<ControllerInner>,
+
+    /// Unparker for the sender thread.
+    unparker: Unparker,
+}
+
+impl SpillWriter {
+    pub(crate) fn new(
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        consumer: Box<dyn OutputConsumer>,
+        controller: Arc<ControllerInner>,
+    ) -> Result<Self, IoError> {
+        let file = Arc::new(SpillFile::new()?);
+        let parker = Parker::new();
+        let unparker = parker.unparker().clone();
+        let max_buffer_size_bytes = consumer.max_buffer_size_bytes();
+
+        let sender_file = file.clone();
+        let sender_controller = controller.clone();
+        let sender_endpoint_name = endpoint_name.to_string();
+        spawn(move || {
+            sender_thread_func(
+                endpoint_id,
+                sender_endpoint_name,
+                consumer,
+                sender_file,
+                parker,
+                sender_controller,
+            )
+        });
+
+        Ok(Self {
+            file,
+            max_buffer_size_bytes,
+            endpoint_id,
+            endpoint_name: endpoint_name.to_string(),
+            controller,
+            unparker,
+        })
+    }
+
+    fn write(&mut self, frame: Frame) {
+        match self.file.write(&frame) {
+            Ok(len) => self.controller.status.spilled_bytes(self.endpoint_id, len),
+            Err(e) => {
+                if matches!(frame, Frame::Buffer(_)) {
+                    self.controller.status.skipped_buffer(self.endpoint_id);
+                }
+                self.controller.output_transport_error(
+                    self.endpoint_id,
+                    &self.endpoint_name,
+                    false,
+                    anyhow!("error writing to spill file: {e}"),
+                );
+            }
+        }
+    }
+}
+
+impl OutputConsumer for SpillWriter {
+    fn max_buffer_size_bytes(&self) -> usize {
+        self.max_buffer_size_bytes
+    }
+
+    fn batch_start(&mut self) {
+        self.write(Frame::BatchStart);
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) {
+        self.write(Frame::Buffer(buffer.to_vec()));
+    }
+
+    fn batch_end(&mut self) {
+        self.write(Frame::BatchEnd);
+        self.unparker.unpark();
+    }
+}
+
+impl Drop for SpillWriter {
+    fn drop(&mut self) {
+        // Stop the sender thread.  The endpoint is being disconnected or the
+        // pipeline is terminating, so outputs that haven't been sent yet are
+        // discarded.
+        self.file.close();
+        self.unparker.unpark();
+    }
+}
+
+/// Sender thread: forwards frames from `file` to `consumer` until the writer
+/// is dropped.
+fn sender_thread_func(
+    endpoint_id: EndpointId,
+    endpoint_name: String,
+    mut consumer: Box<dyn OutputConsumer>,
+    file: Arc<SpillFile>,
+    parker: Parker,
+    controller: Arc<ControllerInner>,
+) {
+    loop {
+        if file.is_closed() || controller.state() == PipelineState::Terminated {
+            return;
+        }
+
+        if controller.status.output_endpoint_paused(&endpoint_id) {
+            parker.park_timeout(PAUSED_POLL_PERIOD);
+            continue;
+        }
+
+        match file.read() {
+            Ok(Some((frame, len))) => {
+                controller.status.spilled_bytes(endpoint_id, len);
+                match frame {
+                    Frame::BatchStart => consumer.batch_start(),
+                    Frame::Buffer(buffer) => consumer.push_buffer(&buffer),
+                    Frame::BatchEnd => consumer.batch_end(),
+                }
+            }
+            Ok(None) => parker.park(),
+            Err(e) => {
+                controller.output_transport_error(
+                    endpoint_id,
+                    &endpoint_name,
+                    true,
+                    anyhow!("error reading from spill file: {e}"),
+                );
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Frame, SpillFile};
+
+    #[test]
+    fn spill_file() {
+        let file = SpillFile::new().unwrap();
+        assert_eq!(file.read().unwrap(), None);
+
+        file.write(&Frame::BatchStart).unwrap();
+        file.write(&Frame::Buffer(b"foo".to_vec())).unwrap();
+        file.write(&Frame::Buffer(Vec::new())).unwrap();
+        assert_eq!(file.write(&Frame::BatchEnd).unwrap(), 1 + 8 + 5 + 1);
+
+        assert_eq!(file.read().unwrap(), Some((Frame::BatchStart, 14)));
+        assert_eq!(
+            file.read().unwrap(),
+            Some((Frame::Buffer(b"foo".to_vec()), 6))
+        );
+
+        // Frames written while the reader is behind are appended.
+        file.write(&Frame::BatchStart).unwrap();
+        assert_eq!(file.read().unwrap(), Some((Frame::Buffer(Vec::new()), 2)));
+        assert_eq!(file.read().unwrap(), Some((Frame::BatchEnd, 1)));
+        assert_eq!(file.read().unwrap(), Some((Frame::BatchStart, 0)));
+        assert_eq!(file.read().unwrap(), None);
+
+        // The file is reused once the reader has caught up.
+        file.write(&Frame::Buffer(b"bar".to_vec())).unwrap();
+        assert_eq!(
+            file.read().unwrap(),
+            Some((Frame::Buffer(b"bar".to_vec()), 0))
+        );
+
+        let path = file.path.clone();
+        drop(file);
+        assert!(!path.exists());
+    }
+}
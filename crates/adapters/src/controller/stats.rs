@@ -30,38 +30,66 @@
 //! by the circuit, but the counter shows that 10 records are still
 //! pending.
 
-use super::{EndpointId, InputEndpointConfig, OutputEndpointConfig, RuntimeConfig};
+use super::{EndpointId, InputEndpointConfig, OutputEndpointConfig, OverflowPolicy, RuntimeConfig};
 use crate::PipelineState;
 use anyhow::Error as AnyError;
 use crossbeam::sync::{ShardedLock, ShardedLockReadGuard, Unparker};
-use log::error;
+use log::{error, info, warn};
 use num_traits::FromPrimitive;
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 use psutil::process::{Process, ProcessError};
 use serde::{Serialize, Serializer};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
 use std::{
     collections::BTreeMap,
     sync::{
         atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
         Mutex,
     },
+    time::{Duration, Instant},
 };
+use utoipa::ToSchema;
 
-#[derive(Default, Serialize)]
+/// Minimal interval over which throughput is measured.
+const THROUGHPUT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Global controller metrics.
+#[derive(Default, Serialize, ToSchema)]
 pub struct GlobalControllerMetrics {
     /// State of the pipeline: running, paused, or terminating.
     #[serde(serialize_with = "serialize_pipeline_state")]
+    #[schema(value_type = PipelineState)]
     state: AtomicU32,
 
     /// Resident state size of the pipeline process.
     // This field is computed on-demand by calling `ControllerStatus::update`.
     #[cfg(any(target_os = "macos", target_os = "linux"))]
+    #[schema(value_type = Option<u64>)]
     pub rss_bytes: Option<AtomicU64>,
 
+    /// Approximate memory used by the state of the circuit, i.e., the traces
+    /// maintained by stateful operators, summed across all workers.
+    // Sampled periodically by the circuit thread.
+    #[schema(value_type = u64)]
+    pub state_bytes: AtomicU64,
+
+    /// Total number of bytes currently buffered by all input endpoints.
+    // This field is computed on-demand by calling `ControllerStatus::update`.
+    #[schema(value_type = u64)]
+    pub buffered_input_bytes: AtomicU64,
+
+    /// True if `state_bytes + buffered_input_bytes` exceeded
+    /// `memory_warning_threshold_mb` the last time memory usage was sampled.
+    #[schema(value_type = bool)]
+    pub memory_warning: AtomicBool,
+
     /// Total number of records currently buffered by all endpoints.
+    #[schema(value_type = u64)]
     pub buffered_input_records: AtomicU64,
 
     /// Total number of records received from all endpoints.
+    #[schema(value_type = u64)]
     pub total_input_records: AtomicU64,
 
     /// Total number of input records processed by the DBSP engine.
@@ -69,8 +97,13 @@ pub struct GlobalControllerMetrics {
     /// may still be buffered at the output endpoint.
     /// Use `OutputEndpointMetrics::total_processed_input_records`
     /// for end-to-end progress tracking.
+    #[schema(value_type = u64)]
     pub total_processed_records: AtomicU64,
 
+    /// Number of steps performed by the circuit since the pipeline started.
+    #[schema(value_type = u64)]
+    pub total_steps: AtomicU64,
+
     /// True if the pipeline has processed all input data to completion.
     /// This means that the following conditions hold:
     ///
@@ -80,12 +113,28 @@ pub struct GlobalControllerMetrics {
     /// * All output records have been sent to respective output transport
     ///   endponts.
     // This field is computed on-demand by calling `ControllerStatus::update`.
+    #[schema(value_type = bool)]
     pub pipeline_complete: AtomicBool,
 
+    /// Number of snapshot queries answered from the snapshot cache without
+    /// running the circuit.
+    #[schema(value_type = u64)]
+    pub snapshot_cache_hits: AtomicU64,
+
+    /// True if the circuit is in manual stepping mode, where it only performs
+    /// a step when explicitly requested by the user.  Input endpoints keep
+    /// buffering data between steps.
+    #[schema(value_type = bool)]
+    pub manual_stepping: AtomicBool,
+
     /// Forces the controller to perform a step regardless of the state of
     /// input buffers.
     #[serde(skip)]
     pub step_requested: AtomicBool,
+
+    /// Forces the controller to perform a step in manual stepping mode.
+    #[serde(skip)]
+    pub manual_step_requested: AtomicBool,
 }
 
 fn serialize_pipeline_state<S>(state: &AtomicU32, serializer: S) -> Result<S::Ok, S::Error>
@@ -103,11 +152,18 @@ impl GlobalControllerMetrics {
             state: AtomicU32::from(PipelineState::Paused as u32),
             #[cfg(any(target_os = "macos", target_os = "linux"))]
             rss_bytes: Some(AtomicU64::new(0)),
+            state_bytes: AtomicU64::new(0),
+            buffered_input_bytes: AtomicU64::new(0),
+            memory_warning: AtomicBool::new(false),
             buffered_input_records: AtomicU64::new(0),
             total_input_records: AtomicU64::new(0),
             total_processed_records: AtomicU64::new(0),
+            total_steps: AtomicU64::new(0),
             pipeline_complete: AtomicBool::new(false),
+            snapshot_cache_hits: AtomicU64::new(0),
+            manual_stepping: AtomicBool::new(false),
             step_requested: AtomicBool::new(false),
+            manual_step_requested: AtomicBool::new(false),
         }
     }
 
@@ -121,6 +177,7 @@ impl GlobalControllerMetrics {
     fn consume_buffered_inputs(&self) {
         self.buffered_input_records.store(0, Ordering::Release);
         self.step_requested.store(false, Ordering::Release);
+        self.manual_step_requested.store(false, Ordering::Release);
     }
 
     fn num_buffered_input_records(&self) -> u64 {
@@ -140,6 +197,14 @@ impl GlobalControllerMetrics {
             .store(total_processed_records, Ordering::Release);
     }
 
+    fn num_total_steps(&self) -> u64 {
+        self.total_steps.load(Ordering::Acquire)
+    }
+
+    fn step_completed(&self) -> u64 {
+        self.total_steps.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
     fn step_requested(&self) -> bool {
         self.step_requested.load(Ordering::Acquire)
     }
@@ -147,6 +212,26 @@ impl GlobalControllerMetrics {
     fn set_step_requested(&self) -> bool {
         self.step_requested.swap(true, Ordering::AcqRel)
     }
+
+    fn manual_stepping(&self) -> bool {
+        self.manual_stepping.load(Ordering::Acquire)
+    }
+
+    fn set_manual_stepping(&self, manual: bool) {
+        self.manual_stepping.store(manual, Ordering::Release);
+    }
+
+    fn manual_step_requested(&self) -> bool {
+        self.manual_step_requested.load(Ordering::Acquire)
+    }
+
+    fn set_manual_step_requested(&self) -> bool {
+        self.manual_step_requested.swap(true, Ordering::AcqRel)
+    }
+
+    fn snapshot_cache_hit(&self) {
+        self.snapshot_cache_hits.fetch_add(1, Ordering::AcqRel);
+    }
 }
 
 // `ShardedLock` is a read/write lock optimized for fast reads.
@@ -179,7 +264,7 @@ where
 }
 
 /// Controller statistics.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ControllerStatus {
     /// Global controller configuration.
     pub global_config: RuntimeConfig,
@@ -189,10 +274,12 @@ pub struct ControllerStatus {
 
     /// Input endpoint configs and metrics.
     #[serde(serialize_with = "serialize_inputs")]
+    #[schema(value_type = Vec<InputEndpointStatus>)]
     inputs: InputsStatus,
 
     /// Output endpoint configs and metrics.
     #[serde(serialize_with = "serialize_outputs")]
+    #[schema(value_type = Vec<OutputEndpointStatus>)]
     outputs: OutputsStatus,
 }
 
@@ -229,6 +316,13 @@ impl ControllerStatus {
         );
     }
 
+    /// Record the new transport configuration of an input endpoint.
+    pub fn set_input_transport_config(&self, endpoint_id: &EndpointId, config: YamlValue) {
+        if let Some(endpoint_stats) = self.inputs.write().unwrap().get_mut(endpoint_id) {
+            endpoint_stats.config.connector_config.transport.config = config;
+        }
+    }
+
     pub fn remove_input(&self, endpoint_id: &EndpointId) {
         self.inputs.write().unwrap().remove(endpoint_id);
     }
@@ -269,10 +363,44 @@ impl ControllerStatus {
             .set_num_total_processed_records(total_processed_records);
     }
 
+    pub fn num_total_steps(&self) -> u64 {
+        self.global_metrics.num_total_steps()
+    }
+
+    /// Count a completed step of the circuit; returns the number of the
+    /// step.
+    pub fn step_completed(&self) -> u64 {
+        self.global_metrics.step_completed()
+    }
+
     pub fn step_requested(&self) -> bool {
         self.global_metrics.step_requested()
     }
 
+    pub fn manual_stepping(&self) -> bool {
+        self.global_metrics.manual_stepping()
+    }
+
+    pub fn set_manual_stepping(&self, manual: bool) {
+        self.global_metrics.set_manual_stepping(manual);
+    }
+
+    pub fn manual_step_requested(&self) -> bool {
+        self.global_metrics.manual_step_requested()
+    }
+
+    pub fn request_manual_step(&self, circuit_thread_unparker: &Unparker) {
+        let old = self.global_metrics.set_manual_step_requested();
+        if !old {
+            circuit_thread_unparker.unpark();
+        }
+    }
+
+    /// Record a snapshot query answered from the snapshot cache.
+    pub fn snapshot_cache_hit(&self) {
+        self.global_metrics.snapshot_cache_hit();
+    }
+
     pub fn request_step(&self, circuit_thread_unparker: &Unparker) {
         let old = self.global_metrics.set_step_requested();
         if !old {
@@ -305,6 +433,60 @@ impl ControllerStatus {
         }
     }
 
+    /// Total number of bytes buffered by all input endpoints.
+    pub fn num_buffered_input_bytes(&self) -> u64 {
+        self.input_status()
+            .values()
+            .map(|endpoint_stats| {
+                endpoint_stats
+                    .metrics
+                    .buffered_bytes
+                    .load(Ordering::Acquire)
+            })
+            .sum()
+    }
+
+    /// Record a new sample of the memory used by the state of the circuit
+    /// and check it, along with input buffers, against
+    /// `memory_warning_threshold_mb`.
+    ///
+    /// Logs a warning when memory usage crosses the threshold and a message
+    /// when it falls back below it.
+    pub fn update_memory_usage(&self, state_bytes: u64) {
+        self.global_metrics
+            .state_bytes
+            .store(state_bytes, Ordering::Release);
+        let buffered_bytes = self.num_buffered_input_bytes();
+        self.global_metrics
+            .buffered_input_bytes
+            .store(buffered_bytes, Ordering::Release);
+
+        let threshold_mb = match self.global_config.memory_warning_threshold_mb {
+            Some(threshold_mb) => threshold_mb,
+            None => return,
+        };
+        let used_bytes = state_bytes + buffered_bytes;
+        let warning = used_bytes > threshold_mb.saturating_mul(1 << 20);
+        if self
+            .global_metrics
+            .memory_warning
+            .swap(warning, Ordering::AcqRel)
+            != warning
+        {
+            if warning {
+                warn!(
+                    "Pipeline memory usage ({} MiB of circuit state and input buffers) exceeds the configured threshold of {threshold_mb} MiB",
+                    used_bytes >> 20
+                );
+            } else {
+                info!(
+                    "Pipeline memory usage ({} MiB) is back below the configured threshold of {threshold_mb} MiB",
+                    used_bytes >> 20
+                );
+            }
+        }
+    }
+
     /// Reset all buffered record and byte counters to zero.
     ///
     /// This method is invoked before `DBSPHandle::step` to indicate that all
@@ -330,6 +512,26 @@ impl ControllerStatus {
         buffered_records >= max_buffered_records
     }
 
+    /// True if the input endpoint has been paused by the user.
+    pub fn input_endpoint_paused(&self, endpoint_id: &EndpointId) -> bool {
+        self.inputs
+            .read()
+            .unwrap()
+            .get(endpoint_id)
+            .map(|endpoint| endpoint.paused.load(Ordering::Acquire))
+            .unwrap_or(false)
+    }
+
+    /// True if the output endpoint has been paused by the user.
+    pub fn output_endpoint_paused(&self, endpoint_id: &EndpointId) -> bool {
+        self.outputs
+            .read()
+            .unwrap()
+            .get(endpoint_id)
+            .map(|endpoint| endpoint.paused.load(Ordering::Acquire))
+            .unwrap_or(false)
+    }
+
     /// Update counters after receiving a new input batch.
     ///
     /// # Arguments
@@ -454,16 +656,62 @@ impl ControllerStatus {
         };
     }
 
+    /// True if any output endpoint with the `stall`
+    /// [`OverflowPolicy`](`super::OverflowPolicy`) has reached its
+    /// `max_buffered_records` limit.
     pub fn output_buffers_full(&self) -> bool {
         self.output_status().values().any(|endpoint_stats| {
-            let num_buffered_records = endpoint_stats
-                .metrics
-                .buffered_records
-                .load(Ordering::Acquire);
-            num_buffered_records >= endpoint_stats.config.connector_config.max_buffered_records
+            endpoint_stats
+                .config
+                .connector_config
+                .overflow
+                .unwrap_or_default()
+                == OverflowPolicy::Stall
+                && endpoint_stats.buffer_full()
         })
     }
 
+    /// True if the endpoint has reached its `max_buffered_records` limit.
+    pub fn output_buffer_full(&self, endpoint_id: EndpointId) -> bool {
+        self.output_status()
+            .get(&endpoint_id)
+            .map(|endpoint_stats| endpoint_stats.buffer_full())
+            .unwrap_or(false)
+    }
+
+    /// Count output records discarded by the `drop`
+    /// [`OverflowPolicy`](`super::OverflowPolicy`).
+    /// The outputs of the circuit after processing
+    /// `total_processed_input_records` records have been dropped, so the
+    /// endpoint is considered to have caught up with them.
+    pub fn dropped_records(
+        &self,
+        endpoint_id: EndpointId,
+        total_processed_input_records: u64,
+        num_records: usize,
+    ) {
+        if let Some(endpoint_stats) = self.output_status().get(&endpoint_id) {
+            endpoint_stats
+                .metrics
+                .num_dropped_records
+                .fetch_add(num_records as u64, Ordering::AcqRel);
+            endpoint_stats
+                .metrics
+                .total_processed_input_records
+                .fetch_max(total_processed_input_records, Ordering::AcqRel);
+        }
+    }
+
+    /// Record the amount of data in the endpoint's spill file.
+    pub fn spilled_bytes(&self, endpoint_id: EndpointId, num_bytes: u64) {
+        if let Some(endpoint_stats) = self.output_status().get(&endpoint_id) {
+            endpoint_stats
+                .metrics
+                .spilled_bytes
+                .store(num_bytes, Ordering::Release);
+        }
+    }
+
     pub fn parse_error(&self, endpoint_id: EndpointId) {
         if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
             endpoint_stats.parse_error();
@@ -476,6 +724,47 @@ impl ControllerStatus {
         }
     }
 
+    /// Count input records dropped due to errors (see
+    /// [`ErrorPolicy`](`super::ErrorPolicy`)).
+    pub fn skipped_records(&self, endpoint_id: EndpointId, num_records: usize) {
+        if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
+            endpoint_stats
+                .metrics
+                .num_skipped_records
+                .fetch_add(num_records as u64, Ordering::AcqRel);
+        }
+    }
+
+    /// Count input records dropped as duplicates (see
+    /// [`DedupConfig`](`super::DedupConfig`)).
+    pub fn duplicate_records(&self, endpoint_id: EndpointId, num_records: u64) {
+        if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
+            endpoint_stats
+                .metrics
+                .num_duplicate_records
+                .fetch_add(num_records, Ordering::AcqRel);
+        }
+    }
+
+    /// Count output buffers dropped due to errors (see
+    /// [`ErrorPolicy`](`super::ErrorPolicy`)).
+    pub fn skipped_buffer(&self, endpoint_id: EndpointId) {
+        if let Some(endpoint_stats) = self.output_status().get(&endpoint_id) {
+            endpoint_stats
+                .metrics
+                .num_skipped_buffers
+                .fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    pub fn transport_metrics(&self, endpoint_id: EndpointId, metrics: JsonValue) {
+        if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
+            *endpoint_stats.metrics.lag.lock().unwrap() =
+                metrics.get("lag").and_then(JsonValue::as_u64);
+            *endpoint_stats.transport_metrics.lock().unwrap() = Some(metrics);
+        }
+    }
+
     pub fn input_transport_error(&self, endpoint_id: EndpointId, fatal: bool, error: &AnyError) {
         if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
             endpoint_stats.transport_error(fatal, error);
@@ -509,6 +798,7 @@ impl ControllerStatus {
         // Outputs have been pushed to their respective transport endpoints.
         if !self.output_status().values().all(|endpoint_stats| {
             endpoint_stats.num_total_processed_input_records() == total_input_records
+                && endpoint_stats.metrics.spilled_bytes.load(Ordering::Acquire) == 0
         }) {
             return false;
         }
@@ -526,6 +816,16 @@ impl ControllerStatus {
             .pipeline_complete
             .store(self.pipeline_complete(), Ordering::Release);
 
+        for endpoint_stats in self.input_status().values() {
+            endpoint_stats.update_throughput();
+        }
+        for endpoint_stats in self.output_status().values() {
+            endpoint_stats.update_throughput();
+        }
+        self.global_metrics
+            .buffered_input_bytes
+            .store(self.num_buffered_input_bytes(), Ordering::Release);
+
         #[cfg(any(target_os = "macos", target_os = "linux"))]
         {
             match Self::rss() {
@@ -544,42 +844,130 @@ impl ControllerStatus {
     }
 }
 
-#[derive(Default, Serialize)]
+/// Computes the throughput of an endpoint from its total record and byte
+/// counters.
+#[derive(Default)]
+struct ThroughputMeter {
+    /// Time and counter values at the start of the current interval.
+    last_sample: Option<(Instant, u64, u64)>,
+}
+
+impl ThroughputMeter {
+    /// Returns the number of records and bytes per second since the previous
+    /// sample, or `None` if less than `THROUGHPUT_INTERVAL` has passed.
+    fn sample(&mut self, records: u64, bytes: u64) -> Option<(u64, u64)> {
+        let now = Instant::now();
+        let Some((start, start_records, start_bytes)) = self.last_sample else {
+            self.last_sample = Some((now, records, bytes));
+            return None;
+        };
+
+        let elapsed = now.duration_since(start);
+        if elapsed < THROUGHPUT_INTERVAL {
+            return None;
+        }
+        self.last_sample = Some((now, records, bytes));
+
+        let secs = elapsed.as_secs_f64();
+        Some((
+            (records.saturating_sub(start_records) as f64 / secs) as u64,
+            (bytes.saturating_sub(start_bytes) as f64 / secs) as u64,
+        ))
+    }
+}
+
+/// Input endpoint metrics.
+#[derive(Default, Serialize, ToSchema)]
 pub struct InputEndpointMetrics {
     /// Total bytes pushed to the endpoint since it was created.
+    #[schema(value_type = u64)]
     pub total_bytes: AtomicU64,
 
     /// Total records pushed to the endpoint since it was created.
+    #[schema(value_type = u64)]
     pub total_records: AtomicU64,
 
+    /// Number of records per second received by the endpoint, measured over
+    /// the interval between consecutive stats requests (at least one
+    /// second).
+    #[schema(value_type = u64)]
+    pub records_per_sec: AtomicU64,
+
+    /// Number of bytes per second received by the endpoint, measured over the
+    /// same interval as `records_per_sec`.
+    #[schema(value_type = u64)]
+    pub bytes_per_sec: AtomicU64,
+
     /// Number of bytes currently buffered by the endpoint
     /// (not yet consumed by the circuit).
+    #[schema(value_type = u64)]
     pub buffered_bytes: AtomicU64,
 
     /// Number of records currently buffered by the endpoint
     /// (not yet consumed by the circuit).
+    #[schema(value_type = u64)]
     pub buffered_records: AtomicU64,
 
+    /// Number of records available at the source that the endpoint hasn't
+    /// read yet, e.g., the total consumer lag across all partitions of the
+    /// Kafka topics the endpoint reads from.  `None` if the transport doesn't
+    /// report lag.
+    #[schema(value_type = Option<u64>)]
+    pub lag: Mutex<Option<u64>>,
+
+    /// Number of transport errors.
+    #[schema(value_type = u64)]
     pub num_transport_errors: AtomicU64,
 
+    /// Number of records that failed to parse.
+    #[schema(value_type = u64)]
     pub num_parse_errors: AtomicU64,
 
+    /// Number of records dropped due to errors, including records that
+    /// failed to parse and, with the `abort_batch` error policy, valid
+    /// records in the same input chunk.
+    #[schema(value_type = u64)]
+    pub num_skipped_records: AtomicU64,
+
+    /// Number of records dropped because their key had already been received
+    /// (see [`DedupConfig`](`super::DedupConfig`)).
+    #[schema(value_type = u64)]
+    pub num_duplicate_records: AtomicU64,
+
+    /// True if the endpoint has reached the end of its input.
+    #[schema(value_type = bool)]
     pub end_of_input: AtomicBool,
 }
 
 /// Input endpoint status information.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct InputEndpointStatus {
     pub endpoint_name: String,
 
-    /// Endpoint configuration (doesn't change).
+    /// Endpoint configuration.  Only the transport configuration can
+    /// change, via
+    /// [`Controller::reconfigure_input_endpoint`](`super::Controller::reconfigure_input_endpoint`).
     pub config: InputEndpointConfig,
 
     /// Performance metrics.
     pub metrics: InputEndpointMetrics,
 
+    /// Transport-specific metrics reported by the endpoint, e.g., Kafka
+    /// partition offsets and consumer lag.
+    #[schema(value_type = Option<Object>)]
+    pub transport_metrics: Mutex<Option<JsonValue>>,
+
+    /// The endpoint has been paused via
+    /// [`Controller::pause_input_endpoint`](`super::Controller::pause_input_endpoint`).
+    #[schema(value_type = bool)]
+    pub paused: AtomicBool,
+
     /// The first fatal error that occurred at the endpoint.
+    #[schema(value_type = Option<String>)]
     pub fatal_error: Mutex<Option<String>>,
+
+    #[serde(skip)]
+    throughput: Mutex<ThroughputMeter>,
 }
 
 impl InputEndpointStatus {
@@ -588,7 +976,28 @@ impl InputEndpointStatus {
             endpoint_name: endpoint_name.to_string(),
             config,
             metrics: Default::default(),
+            transport_metrics: Mutex::new(None),
+            paused: AtomicBool::new(false),
             fatal_error: Mutex::new(None),
+            throughput: Mutex::new(ThroughputMeter::default()),
+        }
+    }
+
+    fn update_throughput(&self) {
+        let total_records = self.metrics.total_records.load(Ordering::Acquire);
+        let total_bytes = self.metrics.total_bytes.load(Ordering::Acquire);
+        if let Some((records_per_sec, bytes_per_sec)) = self
+            .throughput
+            .lock()
+            .unwrap()
+            .sample(total_records, total_bytes)
+        {
+            self.metrics
+                .records_per_sec
+                .store(records_per_sec, Ordering::Release);
+            self.metrics
+                .bytes_per_sec
+                .store(bytes_per_sec, Ordering::Release);
         }
     }
 
@@ -648,27 +1057,70 @@ impl InputEndpointStatus {
     }
 }
 
-#[derive(Default, Serialize)]
+/// Output endpoint metrics.
+#[derive(Default, Serialize, ToSchema)]
 pub struct OutputEndpointMetrics {
+    /// Total records sent by the endpoint since it was created.
+    #[schema(value_type = u64)]
     pub transmitted_records: AtomicU64,
+
+    /// Total bytes sent by the endpoint since it was created.
+    #[schema(value_type = u64)]
     pub transmitted_bytes: AtomicU64,
 
+    /// Number of records per second sent by the endpoint, measured over the
+    /// interval between consecutive stats requests (at least one second).
+    #[schema(value_type = u64)]
+    pub records_per_sec: AtomicU64,
+
+    /// Number of bytes per second sent by the endpoint, measured over the
+    /// same interval as `records_per_sec`.
+    #[schema(value_type = u64)]
+    pub bytes_per_sec: AtomicU64,
+
+    /// Number of records queued for the endpoint but not yet sent.
+    #[schema(value_type = u64)]
     pub buffered_records: AtomicU64,
+
+    /// Number of batches queued for the endpoint but not yet sent.
+    #[schema(value_type = u64)]
     pub buffered_batches: AtomicU64,
 
+    /// Number of batches that failed to encode.
+    #[schema(value_type = u64)]
     pub num_encode_errors: AtomicU64,
+
+    /// Number of transport errors.
+    #[schema(value_type = u64)]
     pub num_transport_errors: AtomicU64,
 
+    /// Number of output buffers dropped because they could not be sent,
+    /// including, with the `abort_batch` error policy, the remaining
+    /// buffers of the same batch.
+    #[schema(value_type = u64)]
+    pub num_skipped_buffers: AtomicU64,
+
+    /// Number of output records discarded because the endpoint was behind
+    /// (see the `drop` overflow policy).
+    #[schema(value_type = u64)]
+    pub num_dropped_records: AtomicU64,
+
+    /// Number of bytes in the endpoint's spill file waiting to be sent
+    /// (see the `spill` overflow policy).
+    #[schema(value_type = u64)]
+    pub spilled_bytes: AtomicU64,
+
     /// The number of input records processed by the circuit.
     ///
     /// This metric tracks the end-to-end progress of the pipeline: the output
     /// of this endpoint is equal to the output of the circuit after
     /// processing `total_processed_input_records` records.
+    #[schema(value_type = u64)]
     pub total_processed_input_records: AtomicU64,
 }
 
 /// Output endpoint status informations.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct OutputEndpointStatus {
     pub endpoint_name: String,
 
@@ -678,8 +1130,17 @@ pub struct OutputEndpointStatus {
     /// Performance metrics.
     pub metrics: OutputEndpointMetrics,
 
+    /// The endpoint has been paused via
+    /// [`Controller::pause_output_endpoint`](`super::Controller::pause_output_endpoint`).
+    #[schema(value_type = bool)]
+    pub paused: AtomicBool,
+
     /// The first fatal error that occurred at the endpoint.
+    #[schema(value_type = Option<String>)]
     pub fatal_error: Mutex<Option<String>>,
+
+    #[serde(skip)]
+    throughput: Mutex<ThroughputMeter>,
 }
 
 /// Public read API.
@@ -695,7 +1156,32 @@ impl OutputEndpointStatus {
             endpoint_name: endpoint_name.to_string(),
             config: config.clone(),
             metrics: Default::default(),
+            paused: AtomicBool::new(false),
             fatal_error: Mutex::new(None),
+            throughput: Mutex::new(ThroughputMeter::default()),
+        }
+    }
+
+    fn buffer_full(&self) -> bool {
+        self.metrics.buffered_records.load(Ordering::Acquire)
+            >= self.config.connector_config.max_buffered_records
+    }
+
+    fn update_throughput(&self) {
+        let transmitted_records = self.metrics.transmitted_records.load(Ordering::Acquire);
+        let transmitted_bytes = self.metrics.transmitted_bytes.load(Ordering::Acquire);
+        if let Some((records_per_sec, bytes_per_sec)) = self
+            .throughput
+            .lock()
+            .unwrap()
+            .sample(transmitted_records, transmitted_bytes)
+        {
+            self.metrics
+                .records_per_sec
+                .store(records_per_sec, Ordering::Release);
+            self.metrics
+                .bytes_per_sec
+                .store(bytes_per_sec, Ordering::Release);
         }
     }
 
@@ -707,9 +1193,11 @@ impl OutputEndpointStatus {
     }
 
     fn output_batch(&self, total_processed_input_records: u64, num_records: usize) -> u64 {
+        // Batches dropped by the `drop` overflow policy may advance the
+        // frontier past batches still in the queue.
         self.metrics
             .total_processed_input_records
-            .store(total_processed_input_records, Ordering::Release);
+            .fetch_max(total_processed_input_records, Ordering::AcqRel);
         self.metrics
             .transmitted_records
             .fetch_add(num_records as u64, Ordering::Relaxed);
@@ -0,0 +1,210 @@
+//! OpenTelemetry tracing of the controller.
+//!
+//! When tracing is enabled, the controller records three kinds of spans:
+//!
+//! * `input batch` - parsing a chunk of data received by an input endpoint.
+//!   If the transport supplies a W3C trace context with the data (e.g., in
+//!   Kafka message headers or HTTP request headers), the span becomes a child
+//!   of the remote span, linking the pipeline to upstream services.
+//!
+//! * `step` - a step of the circuit.  The span is linked to all input batch
+//!   spans received since the previous step, i.e., to all inputs processed by
+//!   the step.
+//!
+//! * `output batch` - encoding and transmitting the outputs of a step via an
+//!   output endpoint.  The span is a child of the corresponding step span.
+//!
+//! Spans are exported to an OpenTelemetry collector via OTLP.
+
+use anyhow::Result as AnyResult;
+use opentelemetry::{
+    global,
+    propagation::{Extractor, TextMapPropagator},
+    trace::{Link, Span, SpanContext, SpanKind, TraceContextExt, Tracer as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime::TokioCurrentThread,
+    trace::{config as trace_config, Span as SdkSpan, Tracer},
+    Resource,
+};
+use serde::{Deserialize, Serialize};
+use std::{mem::take, sync::Mutex};
+use utoipa::ToSchema;
+
+/// Service name reported to the collector when neither the tracing config
+/// nor the pipeline config specify a name.
+const DEFAULT_SERVICE_NAME: &str = "feldera-pipeline";
+
+/// OpenTelemetry tracing configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TracingConfig {
+    /// OTLP/gRPC endpoint of the OpenTelemetry collector, e.g.,
+    /// `http://localhost:4317`.
+    pub otlp_endpoint: String,
+
+    /// Service name attached to exported spans.  Defaults to the name of the
+    /// pipeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+}
+
+/// Creates controller spans and exports them via OTLP.
+pub(crate) struct Telemetry {
+    tracer: Tracer,
+
+    /// Input batch spans completed since the start of the current step.
+    input_spans: Mutex<Vec<SpanContext>>,
+}
+
+impl Telemetry {
+    pub fn new(config: &TracingConfig, pipeline_name: Option<&str>) -> AnyResult<Self> {
+        let service_name = config
+            .service_name
+            .as_deref()
+            .or(pipeline_name)
+            .unwrap_or(DEFAULT_SERVICE_NAME)
+            .to_string();
+
+        // The batch exporter runs in its own thread with a single-threaded
+        // tokio runtime, so the controller doesn't need to be created inside
+        // a tokio runtime.
+        let tracer =
+            opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(&config.otlp_endpoint),
+                )
+                .with_trace_config(trace_config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", service_name),
+                ])))
+                .install_batch(TokioCurrentThread)?;
+
+        Ok(Self {
+            tracer,
+            input_spans: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Start a span for a batch of data received by an input endpoint.
+    ///
+    /// `parent` is the remote trace context received with the data, if any.
+    pub fn input_batch_span(&self, endpoint_name: &str, parent: Option<&SpanContext>) -> SdkSpan {
+        let builder = self
+            .tracer
+            .span_builder("input batch")
+            .with_kind(SpanKind::Consumer)
+            .with_attributes(vec![KeyValue::new("endpoint", endpoint_name.to_string())]);
+
+        match parent {
+            Some(parent) => builder.start_with_context(
+                &self.tracer,
+                &Context::new().with_remote_span_context(parent.clone()),
+            ),
+            None => builder.start(&self.tracer),
+        }
+    }
+
+    /// Complete an input batch span and remember it, so that it gets linked
+    /// to the next step of the circuit.
+    pub fn input_batch_completed(&self, mut span: SdkSpan, num_bytes: usize, num_records: usize) {
+        span.set_attribute(KeyValue::new("bytes", num_bytes as i64));
+        span.set_attribute(KeyValue::new("records", num_records as i64));
+        span.end();
+        self.input_spans
+            .lock()
+            .unwrap()
+            .push(span.span_context().clone());
+    }
+
+    /// Start a span for a step of the circuit.
+    pub fn step_span(&self) -> SdkSpan {
+        let links = take(&mut *self.input_spans.lock().unwrap())
+            .into_iter()
+            .map(|span_context| Link::new(span_context, Vec::new()))
+            .collect();
+
+        self.tracer
+            .span_builder("step")
+            .with_kind(SpanKind::Internal)
+            .with_links(links)
+            .start(&self.tracer)
+    }
+
+    /// Start a span for a batch of outputs sent to an output endpoint.
+    ///
+    /// `step` is the context of the span of the step that produced the
+    /// outputs.
+    pub fn output_batch_span(&self, endpoint_name: &str, step: &SpanContext) -> SdkSpan {
+        self.tracer
+            .span_builder("output batch")
+            .with_kind(SpanKind::Producer)
+            .with_attributes(vec![KeyValue::new("endpoint", endpoint_name.to_string())])
+            .start_with_context(
+                &self.tracer,
+                &Context::new().with_remote_span_context(step.clone()),
+            )
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        // Flush pending spans.
+        global::shutdown_tracer_provider();
+    }
+}
+
+/// Extract a W3C trace context (`traceparent` and `tracestate` headers) from
+/// a list of headers.
+///
+/// Returns `None` if the headers don't contain a valid trace context.
+pub(crate) fn extract_trace_context(headers: &[(&str, &str)]) -> Option<SpanContext> {
+    let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then_some(span_context)
+}
+
+struct HeaderExtractor<'a>(&'a [(&'a str, &'a str)]);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| *value)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.iter().map(|(name, _)| *name).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::extract_trace_context;
+
+    #[test]
+    fn trace_context() {
+        let span_context = extract_trace_context(&[
+            ("Content-Type", "text/csv"),
+            (
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "0af7651916cd43dd8448eb211c80319c"
+        );
+        assert_eq!(span_context.span_id().to_string(), "b7ad6b7169203331");
+        assert!(span_context.is_remote());
+
+        assert!(extract_trace_context(&[]).is_none());
+        assert!(extract_trace_context(&[("traceparent", "garbage")]).is_none());
+    }
+}
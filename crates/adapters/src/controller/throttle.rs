@@ -0,0 +1,137 @@
+//! Rate limiting for input endpoints.
+
+use super::ConnectorConfig;
+use std::time::{Duration, Instant};
+
+/// Token bucket that limits the rate at which an input endpoint feeds data
+/// to the circuit.
+///
+/// Each bucket holds up to one second worth of tokens, so an endpoint that
+/// has been idle can send a burst of up to `max_records_per_sec` records
+/// (`max_bytes_per_sec` bytes) before being throttled.
+pub(crate) struct Throttle {
+    records: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl Throttle {
+    /// Create a throttle for the endpoint with connector configuration
+    /// `config`, or `None` if the configuration doesn't limit the input rate.
+    pub(crate) fn new(config: &ConnectorConfig) -> Option<Self> {
+        let now = Instant::now();
+        let records = config
+            .max_records_per_sec
+            .map(|rate| TokenBucket::new(rate, now));
+        let bytes = config
+            .max_bytes_per_sec
+            .map(|rate| TokenBucket::new(rate, now));
+        if records.is_none() && bytes.is_none() {
+            None
+        } else {
+            Some(Self { records, bytes })
+        }
+    }
+
+    /// Account for `num_records` records and `num_bytes` bytes received by
+    /// the endpoint at time `now`.
+    ///
+    /// Returns the time the endpoint must wait before sending more data in
+    /// order to stay within its limits.
+    pub(crate) fn consume(&mut self, num_records: u64, num_bytes: u64, now: Instant) -> Duration {
+        let records_delay = self
+            .records
+            .as_mut()
+            .map_or(Duration::ZERO, |bucket| bucket.consume(num_records, now));
+        let bytes_delay = self
+            .bytes
+            .as_mut()
+            .map_or(Duration::ZERO, |bucket| bucket.consume(num_bytes, now));
+        records_delay.max(bytes_delay)
+    }
+}
+
+struct TokenBucket {
+    /// Tokens added per second; also the capacity of the bucket.
+    rate: f64,
+
+    /// Available tokens.  Negative when the endpoint has overdrawn the
+    /// bucket.
+    tokens: f64,
+
+    /// Last time the bucket was refilled.
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        // A rate of 0 would stall the endpoint forever.
+        let rate = rate.max(1) as f64;
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    fn consume(&mut self, amount: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last_refill = now;
+
+        self.tokens -= amount as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Throttle;
+    use crate::ConnectorConfig;
+    use std::time::{Duration, Instant};
+
+    fn throttle(config: &str) -> Option<Throttle> {
+        let config: ConnectorConfig = serde_yaml::from_str(config).unwrap();
+        Throttle::new(&config)
+    }
+
+    #[test]
+    fn no_limits() {
+        assert!(throttle("transport:\n  name: file\nformat:\n  name: csv").is_none());
+    }
+
+    #[test]
+    fn limits() {
+        let mut throttle = throttle(
+            r#"
+transport:
+    name: file
+format:
+    name: csv
+max_records_per_sec: 100
+max_bytes_per_sec: 1000
+"#,
+        )
+        .unwrap();
+        let start = Instant::now();
+
+        // The initial burst is within limits.
+        assert_eq!(throttle.consume(100, 500, start), Duration::ZERO);
+
+        // Records are overdrawn by 50 -> wait 0.5s.
+        assert_eq!(throttle.consume(50, 0, start), Duration::from_secs_f64(0.5));
+
+        // After 1s, the record bucket has 50 tokens, the byte bucket is full.
+        // Bytes are overdrawn by 1000 -> wait 1s.
+        let now = start + Duration::from_secs(1);
+        assert_eq!(throttle.consume(10, 2000, now), Duration::from_secs(1));
+
+        // Buckets don't fill beyond their capacity.
+        let now = now + Duration::from_secs(100);
+        assert_eq!(throttle.consume(100, 1000, now), Duration::ZERO);
+        assert_eq!(throttle.consume(1, 0, now), Duration::from_secs_f64(0.01));
+    }
+}
@@ -0,0 +1,234 @@
+//! `EXPLAIN ANALYZE` for a running pipeline.
+//!
+//! We take two snapshots of the circuit profile (see
+//! [`DBSPHandle::retrieve_profile`](`dbsp::DBSPHandle::retrieve_profile`))
+//! some time apart and report, for each operator, how many times it was
+//! evaluated and how much CPU time it used between the two snapshots, along
+//! with the size of its state at the end of the interval.  Operators are
+//! mapped back to the SQL code they were generated from using the source map
+//! embedded in the pipeline binary by the SQL compiler.
+
+use dbsp::{circuit::metadata::MetaItem, profile::OperatorProfile};
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+
+/// Maximal length of the measurement interval, in seconds.
+pub const MAX_EXPLAIN_ANALYZE_SECS: u64 = 300;
+
+/// Maps lines of the generated `main.rs` file to the fragments of SQL code
+/// that the operators instantiated on these lines were compiled from.
+pub type SqlSourceMap = &'static [(u32, &'static str)];
+
+/// Name of the generated program file that [`SqlSourceMap`] refers to.
+const PROGRAM_FILE_NAME: &str = "main.rs";
+
+/// Result of `EXPLAIN ANALYZE`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExplainAnalyze {
+    /// Length of the measurement interval, in seconds.
+    pub duration_secs: f64,
+
+    /// Number of circuit steps performed during the interval.
+    pub steps: usize,
+
+    /// Operators, ordered by the CPU time they used during the interval,
+    /// most expensive first.
+    pub operators: Vec<AnalyzedOperator>,
+}
+
+/// Measurements of a single operator, summed across all workers.
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalyzedOperator {
+    /// Global id of the operator, e.g., `[5]` or `[7.2]` for an operator
+    /// in a nested circuit.
+    pub id: String,
+
+    /// Operator name.
+    pub name: String,
+
+    /// Location in the program source where the operator was instantiated.
+    pub location: Option<String>,
+
+    /// Fragment of the SQL program the operator implements, if known.
+    pub sql: Option<&'static str>,
+
+    /// Number of evaluations per second.
+    pub evaluations_per_sec: f64,
+
+    /// CPU time spent evaluating the operator, in seconds.
+    pub time_secs: f64,
+
+    /// Share of the CPU time used by all top-level operators spent in
+    /// this operator.  Time spent in a nested circuit is attributed both
+    /// to the circuit and to the operators inside it.
+    pub time_share: f64,
+
+    /// Number of records in the operator's state, if the operator is
+    /// stateful.
+    pub state_records: Option<usize>,
+}
+
+/// Computes `EXPLAIN ANALYZE` results from profiles taken at the start and
+/// the end of the measurement interval.
+///
+/// `before` and `after` contain one profile per worker.
+pub(crate) fn explain_analyze(
+    before: &[Vec<OperatorProfile>],
+    after: &[Vec<OperatorProfile>],
+    duration: Duration,
+    source_map: SqlSourceMap,
+) -> ExplainAnalyze {
+    let mut baseline = HashMap::new();
+    for profile in before.iter().flatten() {
+        let (invocations, time) = baseline
+            .entry(&profile.id)
+            .or_insert((0usize, Duration::ZERO));
+        *invocations += profile.invocations;
+        *time += profile.time;
+    }
+
+    let mut operators: Vec<(&OperatorProfile, usize, Duration, Option<usize>)> = Vec::new();
+    let mut index = HashMap::new();
+    for profile in after.iter().flatten() {
+        let i = *index.entry(&profile.id).or_insert_with(|| {
+            operators.push((profile, 0, Duration::ZERO, None));
+            operators.len() - 1
+        });
+        let (_, invocations, time, state_records) = &mut operators[i];
+        *invocations += profile.invocations;
+        *time += profile.time;
+        if let Some(records) = state_records_of(profile) {
+            *state_records = Some(state_records.unwrap_or(0) + records);
+        }
+    }
+
+    let workers = after.len().max(1);
+    let secs = duration.as_secs_f64();
+    let mut steps = 0;
+    let mut total_time = Duration::ZERO;
+
+    let mut operators: Vec<_> = operators
+        .into_iter()
+        .map(|(profile, invocations, time, state_records)| {
+            let (invocations_before, time_before) =
+                baseline.get(&profile.id).copied().unwrap_or_default();
+            let invocations = invocations.saturating_sub(invocations_before);
+            let time = time.saturating_sub(time_before);
+
+            if profile.id.path().len() == 1 {
+                // All top-level operators are evaluated once per step in
+                // each worker.
+                steps = steps.max(invocations / workers);
+                total_time += time;
+            }
+
+            let location = profile.location.map(|location| {
+                format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )
+            });
+            let sql = profile
+                .location
+                .filter(|location| location.file().ends_with(PROGRAM_FILE_NAME))
+                .and_then(|location| {
+                    source_map
+                        .iter()
+                        .find(|(line, _)| *line == location.line())
+                        .map(|(_, sql)| *sql)
+                });
+
+            AnalyzedOperator {
+                id: profile.id.to_string(),
+                name: profile.name.to_string(),
+                location,
+                sql,
+                evaluations_per_sec: if secs > 0.0 {
+                    invocations as f64 / secs
+                } else {
+                    0.0
+                },
+                time_secs: time.as_secs_f64(),
+                time_share: 0.0,
+                state_records,
+            }
+        })
+        .collect();
+
+    let total_secs = total_time.as_secs_f64();
+    if total_secs > 0.0 {
+        for operator in operators.iter_mut() {
+            operator.time_share = operator.time_secs / total_secs;
+        }
+    }
+    operators.sort_by(|a, b| b.time_secs.total_cmp(&a.time_secs));
+
+    ExplainAnalyze {
+        duration_secs: secs,
+        steps,
+        operators,
+    }
+}
+
+/// Number of records in the state of a stateful operator, as reported in its
+/// metadata.
+fn state_records_of(profile: &OperatorProfile) -> Option<usize> {
+    profile
+        .metadata
+        .iter()
+        .find_map(|(label, item)| match item {
+            MetaItem::Int(records) if label == "total size" => Some(*records),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::explain_analyze;
+    use dbsp::{operator::Generator, Circuit, Runtime};
+    use std::time::Duration;
+
+    #[test]
+    fn explain_analyze_deltas() {
+        let (mut handle, _) = Runtime::init_circuit(2, |circuit| {
+            circuit
+                .add_source(Generator::new(|| 5usize))
+                .apply_named("Double", |x| x * 2);
+            Ok(())
+        })
+        .unwrap();
+        handle.enable_cpu_profiler().unwrap();
+
+        handle.step().unwrap();
+        let before = handle.retrieve_profile().unwrap();
+        for _ in 0..3 {
+            handle.step().unwrap();
+        }
+        let after = handle.retrieve_profile().unwrap();
+        handle.kill().unwrap();
+
+        let source_map: &'static [(u32, &'static str)] = &[];
+        let result = explain_analyze(&before, &after, Duration::from_secs(1), source_map);
+        assert_eq!(result.steps, 3);
+
+        let double = result
+            .operators
+            .iter()
+            .find(|op| op.name == "Double")
+            .unwrap();
+        // 3 steps in each of 2 workers.
+        assert_eq!(double.evaluations_per_sec, 6.0);
+        assert!(double.location.as_ref().unwrap().contains("explain.rs:"));
+        assert_eq!(double.sql, None);
+
+        let total_share: f64 = result
+            .operators
+            .iter()
+            .filter(|op| !op.id.contains('.'))
+            .map(|op| op.time_share)
+            .sum();
+        assert!(total_share == 0.0 || (total_share - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,304 @@
+//! Arrow IPC format encoder.
+//!
+//! Each output batch is encoded as a complete Arrow IPC stream (a schema
+//! message followed by one or more record batches and an end-of-stream
+//! marker), which the encoder pushes to the transport as a single buffer.
+//! The output of an endpoint is therefore a sequence of IPC streams, one per
+//! step of the circuit, which clients read one after another, e.g., by
+//! opening a new `pyarrow.ipc` stream reader on the response body after the
+//! previous one is exhausted.
+//!
+//! The Arrow schema is inferred from the JSON representation of the records
+//! in each batch.  SQL `DATE`, `TIME`, and `TIMESTAMP` values are written as
+//! strings.  Each record batch contains an extra column with the weight of
+//! each change: a positive weight for insertions and a negative weight for
+//! deletions.
+
+use crate::{
+    catalog::{RecordFormat, SerBatch},
+    ControllerError, Encoder, OutputConsumer, OutputFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use arrow::{
+    ipc::writer::StreamWriter,
+    json::{reader::infer_json_schema_from_iterator, ReaderBuilder},
+};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, sync::Arc};
+use utoipa::ToSchema;
+
+/// Arrow IPC format encoder.
+pub struct ArrowOutputFormat;
+
+const fn default_batch_size() -> usize {
+    64 * 1024
+}
+
+fn default_weight_column() -> String {
+    "__feldera_weight".to_string()
+}
+
+/// Arrow encoder configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ArrowEncoderConfig {
+    /// Maximum number of rows in an Arrow record batch.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Name of the weight column.
+    #[serde(default = "default_weight_column")]
+    pub weight_column: String,
+}
+
+impl OutputFormat for ArrowOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("arrow")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            ArrowEncoderConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::encoder_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = ArrowEncoderConfig::deserialize(config)?;
+        if config.batch_size == 0 {
+            bail!("'batch_size' must be greater than 0");
+        }
+
+        Ok(Box::new(ArrowEncoder::new(consumer, config)))
+    }
+}
+
+struct ArrowEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+    config: ArrowEncoderConfig,
+    max_buffer_size: usize,
+}
+
+impl ArrowEncoder {
+    fn new(output_consumer: Box<dyn OutputConsumer>, config: ArrowEncoderConfig) -> Self {
+        let max_buffer_size = output_consumer.max_buffer_size_bytes();
+
+        Self {
+            output_consumer,
+            config,
+            max_buffer_size,
+        }
+    }
+
+    /// Rows of the output for `batches`, including the weight column.
+    fn rows(&self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<Vec<JsonValue>> {
+        let mut rows = Vec::new();
+        let mut record = Vec::new();
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                record.clear();
+                cursor.serialize_key(&mut record)?;
+                let mut row: JsonMap<String, JsonValue> =
+                    serde_json::from_slice(&record).map_err(|e| {
+                        anyhow!(
+                            "error parsing serialized record '{}': {e}",
+                            String::from_utf8_lossy(&record)
+                        )
+                    })?;
+                row.insert(
+                    self.config.weight_column.clone(),
+                    JsonValue::from(cursor.weight()),
+                );
+                rows.push(JsonValue::Object(row));
+                cursor.step_key();
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Encode `rows` as an Arrow IPC stream.
+    fn write_stream(&self, rows: &[JsonValue]) -> AnyResult<Vec<u8>> {
+        let schema = Arc::new(
+            infer_json_schema_from_iterator(rows.iter().map(|row| Ok(row.clone())))
+                .map_err(|e| anyhow!("error inferring Arrow schema: {e}"))?,
+        );
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        let mut decoder = ReaderBuilder::new(schema.clone())
+            .with_batch_size(self.config.batch_size)
+            .build_decoder()?;
+
+        for chunk in rows.chunks(self.config.batch_size) {
+            decoder.serialize(chunk)?;
+            if let Some(batch) = decoder.flush()? {
+                writer.write(&batch)?;
+            }
+        }
+        writer.finish()?;
+
+        Ok(writer.into_inner()?)
+    }
+}
+
+impl Encoder for ArrowEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let rows = self.rows(batches)?;
+        if rows.is_empty() {
+            // There is no way to infer the schema of an empty stream.
+            return Ok(());
+        }
+
+        let stream = self.write_stream(&rows)?;
+        if stream.len() > self.max_buffer_size {
+            bail!("Arrow IPC stream exceeds maximum buffer size supported by the output transport. Max supported buffer size is {} bytes, but the stream requires {} bytes.",
+                  self.max_buffer_size,
+                  stream.len());
+        }
+        self.output_consumer.push_buffer(&stream);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArrowEncoder, ArrowEncoderConfig};
+    use crate::{
+        catalog::SerBatch,
+        format::Encoder,
+        static_compile::seroutput::SerBatchImpl,
+        test::{MockOutputConsumer, TestStruct},
+    };
+    use arrow::{
+        array::{Array, BooleanArray, Int64Array, StringArray},
+        ipc::reader::StreamReader,
+        record_batch::RecordBatch,
+    };
+    use dbsp::{trace::Batch, OrdZSet};
+    use std::sync::Arc;
+
+    fn test_struct(id: u32, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64 * 10),
+            s: s.to_string(),
+        }
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+        batch
+            .column(batch.schema().index_of(name).unwrap())
+            .as_any()
+            .downcast_ref::<T>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_arrow_encoder() {
+        let config = ArrowEncoderConfig {
+            batch_size: 2,
+            weight_column: "w".to_string(),
+        };
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = ArrowEncoder::new(Box::new(consumer), config);
+
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![
+                (test_struct(1, "foo"), 1),
+                (test_struct(2, "bar"), -1),
+                (test_struct(3, "baz"), 2),
+            ],
+        );
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        let data = data.lock().unwrap();
+        let batches = StreamReader::try_new(data.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        // Record batches contain at most 2 rows.
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        let weights = batches
+            .iter()
+            .flat_map(|b| column::<Int64Array>(b, "w").values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(weights, vec![1, -1, 2]);
+        let ids = batches
+            .iter()
+            .flat_map(|b| column::<Int64Array>(b, "id").values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let flags = batches
+            .iter()
+            .flat_map(|b| {
+                let b = column::<BooleanArray>(b, "b");
+                (0..b.len()).map(|i| b.value(i)).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flags, vec![false, true, false]);
+        let names = batches
+            .iter()
+            .flat_map(|b| {
+                let s = column::<StringArray>(b, "s");
+                (0..s.len())
+                    .map(|i| s.value(i).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["foo", "bar", "baz"]);
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = ArrowEncoder::new(
+            Box::new(consumer),
+            ArrowEncoderConfig {
+                batch_size: 10,
+                weight_column: "w".to_string(),
+            },
+        );
+
+        let zset = OrdZSet::<TestStruct, i64>::from_keys((), vec![]);
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+        assert!(data.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+//! Input format that detects the format of each input file or message.
+//!
+//! The parser sniffs the first bytes of the input and forwards it to the
+//! first configured candidate format that matches.
+
+use crate::{
+    format::{InputFormat, ParseError, Parser},
+    ControllerError, DeCollectionHandle, FormatConfig,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, Result as AnyResult};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, mem::take};
+use utoipa::ToSchema;
+
+/// Magic bytes at the start of an Avro object container file.
+const AVRO_CONTAINER_MAGIC: &[u8] = b"Obj\x01";
+
+/// Maximal number of bytes to buffer while looking for the end of the first
+/// line of a text stream.
+const MAX_SNIFF_LEN: usize = 4096;
+
+/// Formats that can be recognized from the first bytes of the input.
+///
+/// Candidates with other formats match any input.
+const SNIFFABLE_FORMATS: [&str; 4] = ["avro", "csv", "json", "text"];
+
+/// Automatic format detection.
+pub struct AutoInputFormat;
+
+/// Automatic format detection configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct AutoParserConfig {
+    /// Candidate formats, in order of preference.
+    ///
+    /// The input is parsed using the first candidate that matches the
+    /// detected format.  Formats that cannot be detected, e.g., `raw`, match
+    /// any input and can be used as a catch-all at the end of the list.
+    #[serde(default = "default_formats")]
+    pub formats: Vec<FormatConfig>,
+}
+
+impl Default for AutoParserConfig {
+    fn default() -> Self {
+        Self {
+            formats: default_formats(),
+        }
+    }
+}
+
+fn default_formats() -> Vec<FormatConfig> {
+    ["json", "csv"].into_iter().map(candidate_config).collect()
+}
+
+/// Candidate format with default configuration.
+fn candidate_config(name: &str) -> FormatConfig {
+    FormatConfig {
+        name: Cow::from(name.to_string()),
+        config: YamlValue::Mapping(Default::default()),
+    }
+}
+
+impl AutoParserConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.formats.is_empty() {
+            return Err(anyhow!("'formats' must contain at least one format"));
+        }
+        if self.formats.iter().any(|format| format.name == "auto") {
+            return Err(anyhow!("'formats' cannot contain the 'auto' format"));
+        }
+        Ok(())
+    }
+}
+
+/// URL arguments accepted by the `auto` format.
+#[derive(Deserialize)]
+struct AutoHttpArgs {
+    /// Comma-separated list of candidate formats.
+    formats: Option<String>,
+}
+
+impl InputFormat for AutoInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("auto")
+    }
+
+    /// Candidate formats are specified as a comma-separated list in the
+    /// `formats` argument, e.g., `?format=auto&formats=json,csv`, and use
+    /// default configurations.
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        let args = AutoHttpArgs::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+            request.query_string().as_bytes(),
+        )))
+        .map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, request.query_string())
+        })?;
+
+        let config = match args.formats {
+            None => AutoParserConfig::default(),
+            Some(formats) => AutoParserConfig {
+                formats: formats
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(candidate_config)
+                    .collect(),
+            },
+        };
+        Ok(Box::new(config))
+    }
+
+    fn new_parser(
+        &self,
+        endpoint_name: &str,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
+        let config = AutoParserConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+        config.validate().map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+
+        let mut candidates = Vec::with_capacity(config.formats.len());
+        for format_config in config.formats.iter() {
+            let format = <dyn InputFormat>::get_format(&format_config.name).ok_or_else(|| {
+                ControllerError::unknown_input_format(endpoint_name, &format_config.name)
+            })?;
+            let parser = format.new_parser(endpoint_name, input_stream, &format_config.config)?;
+            candidates.push((format_config.name.clone(), parser));
+        }
+
+        Ok(Box::new(AutoParser::new(candidates)) as Box<dyn Parser>)
+    }
+}
+
+/// Result of inspecting the first bytes of the input.
+#[derive(Debug, PartialEq, Eq)]
+enum Sniffed {
+    /// More data is needed to recognize the format.
+    NeedMoreData,
+
+    /// Recognized format.
+    Format(&'static str),
+
+    /// The input doesn't match any of the formats we can recognize.
+    Unknown,
+}
+
+/// Recognize the format of `data`.
+///
+/// `complete` is `true` if `data` contains the entire input, i.e., no more
+/// data will follow.
+fn sniff(data: &[u8], complete: bool) -> Sniffed {
+    if data.starts_with(AVRO_CONTAINER_MAGIC) {
+        return Sniffed::Format("avro");
+    }
+    if !complete
+        && data.len() < AVRO_CONTAINER_MAGIC.len()
+        && AVRO_CONTAINER_MAGIC.starts_with(data)
+    {
+        return Sniffed::NeedMoreData;
+    }
+    // Text never starts with a NUL byte, which is the first byte of the
+    // Confluent wire format.
+    if data.first() == Some(&0) {
+        return Sniffed::Format("avro");
+    }
+
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let start = data.iter().position(|c| !c.is_ascii_whitespace());
+    let Some(start) = start else {
+        return if complete {
+            Sniffed::Unknown
+        } else {
+            Sniffed::NeedMoreData
+        };
+    };
+    let data = &data[start..];
+
+    if matches!(data[0], b'{' | b'[') {
+        return Sniffed::Format("json");
+    }
+
+    // Distinguish CSV from tab-separated text based on the first line.
+    let line = match data.iter().position(|c| *c == b'\n') {
+        Some(end) => &data[..end],
+        None if !complete && data.len() < MAX_SNIFF_LEN => return Sniffed::NeedMoreData,
+        None => &data[..data.len().min(MAX_SNIFF_LEN)],
+    };
+    let line = match std::str::from_utf8(line) {
+        Ok(line) => line,
+        // The line may have been truncated in the middle of a character.
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&line[..e.valid_up_to()]).unwrap(),
+        Err(_) => return Sniffed::Unknown,
+    };
+    if line.contains('\t') && !line.contains(',') {
+        Sniffed::Format("text")
+    } else {
+        Sniffed::Format("csv")
+    }
+}
+
+/// State of the parser while receiving a continuous byte stream.
+enum StreamState {
+    /// Buffering the start of the stream until its format can be recognized.
+    Sniffing(Vec<u8>),
+
+    /// Forwarding the stream to the candidate with the specified index.
+    Selected(usize),
+
+    /// None of the candidates match the stream; discard it.
+    Rejected,
+}
+
+struct AutoParser {
+    /// Candidate formats and their parsers.
+    candidates: Vec<(Cow<'static, str>, Box<dyn Parser>)>,
+
+    state: StreamState,
+}
+
+impl AutoParser {
+    fn new(candidates: Vec<(Cow<'static, str>, Box<dyn Parser>)>) -> Self {
+        Self {
+            candidates,
+            state: StreamState::Sniffing(Vec::new()),
+        }
+    }
+
+    /// Choose a candidate parser for input that starts with `data`.
+    ///
+    /// Returns `None` if more data is needed to make a decision.
+    fn select(&self, data: &[u8], complete: bool) -> Option<Result<usize, ParseError>> {
+        let sniffed = match sniff(data, complete) {
+            Sniffed::NeedMoreData => return None,
+            Sniffed::Format(format) => Some(format),
+            Sniffed::Unknown => None,
+        };
+
+        let index = self.candidates.iter().position(|(name, _)| {
+            Some(name.as_ref()) == sniffed || !SNIFFABLE_FORMATS.contains(&name.as_ref())
+        });
+
+        Some(index.ok_or_else(|| {
+            let formats = self
+                .candidates
+                .iter()
+                .map(|(name, _)| name.as_ref())
+                .collect::<Vec<_>>()
+                .join(", ");
+            ParseError::bin_envelope_error(
+                match sniffed {
+                    Some(format) => format!("input appears to be in the '{format}' format, which is not one of the configured formats ({formats})"),
+                    None => format!("unable to recognize the format of the input; configured formats: {formats}"),
+                },
+                &data[..data.len().min(MAX_SNIFF_LEN)],
+                Some(Cow::from(
+                    "add the format to the list of 'formats' or add a catch-all format, e.g., 'raw', at the end of the list",
+                )),
+            )
+        }))
+    }
+}
+
+impl Parser for AutoParser {
+    fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        match &mut self.state {
+            StreamState::Selected(index) => self.candidates[*index].1.input_fragment(data),
+            StreamState::Rejected => (0, Vec::new()),
+            StreamState::Sniffing(buffer) => {
+                buffer.extend_from_slice(data);
+                let buffer = take(buffer);
+                match self.select(&buffer, false) {
+                    None => {
+                        self.state = StreamState::Sniffing(buffer);
+                        (0, Vec::new())
+                    }
+                    Some(Ok(index)) => {
+                        self.state = StreamState::Selected(index);
+                        self.candidates[index].1.input_fragment(&buffer)
+                    }
+                    Some(Err(error)) => {
+                        self.state = StreamState::Rejected;
+                        (0, vec![error])
+                    }
+                }
+            }
+        }
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        match self.select(data, true) {
+            Some(Ok(index)) => self.candidates[index].1.input_chunk(data),
+            Some(Err(error)) => (0, vec![error]),
+            // Unreachable: complete inputs are always recognized or rejected.
+            None => (0, Vec::new()),
+        }
+    }
+
+    /// End of the stream.  The format of the next stream is detected anew.
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        match std::mem::replace(&mut self.state, StreamState::Sniffing(Vec::new())) {
+            StreamState::Selected(index) => self.candidates[index].1.eoi(),
+            StreamState::Rejected => (0, Vec::new()),
+            StreamState::Sniffing(buffer) if buffer.is_empty() => (0, Vec::new()),
+            StreamState::Sniffing(buffer) => match self.select(&buffer, true) {
+                Some(Ok(index)) => {
+                    let parser = &mut self.candidates[index].1;
+                    let (mut num_records, mut errors) = parser.input_fragment(&buffer);
+                    let (eoi_records, eoi_errors) = parser.eoi();
+                    num_records += eoi_records;
+                    errors.extend(eoi_errors);
+                    (num_records, errors)
+                }
+                Some(Err(error)) => (0, vec![error]),
+                None => (0, Vec::new()),
+            },
+        }
+    }
+
+    fn is_order_sensitive(&self) -> bool {
+        self.candidates
+            .iter()
+            .any(|(_, parser)| parser.is_order_sensitive())
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(
+            self.candidates
+                .iter()
+                .map(|(name, parser)| (name.clone(), parser.fork()))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sniff, AutoParserConfig, Sniffed};
+    use crate::{
+        test::{mock_parser_pipeline, TestStruct},
+        transport::InputConsumer,
+        FormatConfig,
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"Obj\x01\x02", false), Sniffed::Format("avro"));
+        assert_eq!(sniff(b"Ob", false), Sniffed::NeedMoreData);
+        assert_eq!(
+            sniff(b"\x00\x00\x00\x00\x01", true),
+            Sniffed::Format("avro")
+        );
+        assert_eq!(sniff(b"  \n", false), Sniffed::NeedMoreData);
+        assert_eq!(sniff(b"  \n", true), Sniffed::Unknown);
+        assert_eq!(sniff(b"\n {\"id\": 1}", false), Sniffed::Format("json"));
+        assert_eq!(sniff(b"[1,2]", true), Sniffed::Format("json"));
+        assert_eq!(sniff(b"1,true,", false), Sniffed::NeedMoreData);
+        assert_eq!(sniff(b"1,true,\n", false), Sniffed::Format("csv"));
+        assert_eq!(sniff(b"1,true", true), Sniffed::Format("csv"));
+        assert_eq!(sniff(b"1\ttrue\t\tfoo\n", false), Sniffed::Format("text"));
+        assert_eq!(sniff(b"\xff\xfe\n", false), Sniffed::Unknown);
+    }
+
+    #[test]
+    fn test_auto() {
+        let format_config = FormatConfig {
+            name: Cow::from("auto"),
+            config: serde_yaml::to_value(AutoParserConfig::default()).unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        let expected = |id| {
+            (
+                TestStruct {
+                    id,
+                    b: true,
+                    i: None,
+                    s: "foo".to_string(),
+                },
+                true,
+            )
+        };
+
+        // A JSON stream followed by a CSV stream.
+        assert!(consumer.input_fragment(b"  ").is_empty());
+        assert!(consumer
+            .input_fragment(b"{\"insert\": {\"id\": 1, \"b\": true, \"s\": \"foo\"}}\n")
+            .is_empty());
+        assert!(consumer.eoi().is_empty());
+        assert!(consumer.input_fragment(b"2,true,,foo").is_empty());
+        assert!(consumer.eoi().is_empty());
+
+        // Messages in different formats.
+        assert!(consumer.input_chunk(b"3,true,,foo\n").is_empty());
+        assert!(consumer
+            .input_chunk(b"{\"insert\": {\"id\": 4, \"b\": true, \"s\": \"foo\"}}")
+            .is_empty());
+
+        // Unrecognized input.
+        assert_eq!(consumer.input_chunk(b"\x00\x01\x02").len(), 1);
+
+        assert_eq!(
+            outputs.state().flushed,
+            vec![expected(1), expected(2), expected(3), expected(4)]
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Avro format parser.
+
+use super::{
+    load_schema, schema_registry::SchemaRegistryClient, value_to_json, AvroUpdateFormat,
+    SchemaRegistryConfig,
+};
+use crate::{
+    catalog::RecordFormat,
+    format::{
+        json::{JsonParser, JsonParserConfig, JsonUpdateFormat},
+        InputFormat, ParseError, Parser,
+    },
+    ControllerError, DeCollectionHandle,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, Result as AnyResult};
+use apache_avro::{from_avro_datum, Reader as AvroReader, Schema as AvroSchema};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, mem::take, sync::Arc};
+use utoipa::ToSchema;
+
+/// Magic bytes at the start of an Avro object container file.
+const CONTAINER_MAGIC: &[u8] = b"Obj\x01";
+
+/// Avro format parser.
+pub struct AvroInputFormat;
+
+/// Avro parser configuration.
+///
+/// Exactly one of `schema`, `schema_file`, and `schema_registry` must be
+/// specified.
+///
+/// Input data is either a sequence of Avro datums encoded with the
+/// configured schema or an Avro object container file, which embeds its own
+/// schema.  With `schema_registry`, each chunk of input data (e.g., a Kafka
+/// message) must contain a single datum framed with the schema registry
+/// header.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct AvroParserConfig {
+    /// Avro schema of input records in JSON.
+    pub schema: Option<String>,
+
+    /// Path to a file containing the Avro schema of input records.
+    pub schema_file: Option<String>,
+
+    /// Retrieve the schema of each record from a Confluent schema registry
+    /// using the schema id embedded in the record.
+    pub schema_registry: Option<SchemaRegistryConfig>,
+
+    /// Avro update format.
+    #[serde(default)]
+    pub update_format: AvroUpdateFormat,
+}
+
+/// Decodes Avro datums.  Shared by all forks of a parser.
+enum AvroDecoder {
+    Schema(AvroSchema),
+    Registry(SchemaRegistryClient),
+}
+
+impl AvroDecoder {
+    fn new(config: &AvroParserConfig) -> AnyResult<Self> {
+        let schema = load_schema(&config.schema, &config.schema_file)?;
+        match (schema, &config.schema_registry) {
+            (Some(_), Some(_)) => Err(anyhow!(
+                "'schema_registry' cannot be combined with 'schema' or 'schema_file'"
+            )),
+            (Some((_, schema)), None) => Ok(Self::Schema(schema)),
+            (None, Some(registry)) => Ok(Self::Registry(SchemaRegistryClient::new(registry))),
+            (None, None) => Err(anyhow!(
+                "Avro parser configuration must specify one of 'schema', 'schema_file', or 'schema_registry'"
+            )),
+        }
+    }
+}
+
+impl InputFormat for AvroInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("avro")
+    }
+
+    fn new_parser(
+        &self,
+        endpoint_name: &str,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
+        let config = AvroParserConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+        let decoder = AvroDecoder::new(&config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+
+        let update_format = match config.update_format {
+            AvroUpdateFormat::Raw => JsonUpdateFormat::Raw,
+            AvroUpdateFormat::InsertDelete => JsonUpdateFormat::InsertDelete,
+            AvroUpdateFormat::Debezium => JsonUpdateFormat::Debezium,
+        };
+        let input_stream =
+            input_stream.configure_deserializer(RecordFormat::Json(Default::default()))?;
+        let json_parser = JsonParser::new(
+            input_stream,
+            JsonParserConfig {
+                update_format,
+                array: false,
+                ..Default::default()
+            },
+        );
+
+        Ok(Box::new(AvroParser::new(Arc::new(decoder), Box::new(json_parser))) as Box<dyn Parser>)
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            AvroParserConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::parser_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+}
+
+struct AvroParser {
+    decoder: Arc<AvroDecoder>,
+    /// Parser that pushes records converted to JSON to the circuit.
+    json_parser: Box<dyn Parser>,
+    /// Data received via `input_fragment`, which is decoded at the end of
+    /// input, since Avro datums don't carry their own length.
+    leftover: Vec<u8>,
+    last_event_number: u64,
+}
+
+impl AvroParser {
+    fn new(decoder: Arc<AvroDecoder>, json_parser: Box<dyn Parser>) -> Self {
+        Self {
+            decoder,
+            json_parser,
+            leftover: Vec::new(),
+            last_event_number: 0,
+        }
+    }
+
+    /// Decode all datums in `data` and push them to the circuit.
+    fn input_from_slice(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let mut json = Vec::new();
+
+        match &*self.decoder {
+            AvroDecoder::Registry(registry) => match registry.decode(data) {
+                Ok(record) => json.extend_from_slice(&record),
+                Err(e) => errors.push(ParseError::bin_event_error(
+                    e.to_string(),
+                    self.last_event_number + 1,
+                    data,
+                    None,
+                )),
+            },
+            AvroDecoder::Schema(_) if data.starts_with(CONTAINER_MAGIC) => {
+                match AvroReader::new(data) {
+                    Err(e) => errors.push(ParseError::bin_envelope_error(
+                        format!("error reading Avro object container file: {e}"),
+                        &data[..data.len().min(CONTAINER_MAGIC.len())],
+                        None,
+                    )),
+                    Ok(reader) => {
+                        let schema = reader.writer_schema().clone();
+                        for (i, value) in reader.enumerate() {
+                            let record = value
+                                .map_err(|e| anyhow!("error decoding Avro record: {e}"))
+                                .and_then(|value| value_to_json(value, &schema));
+                            match record {
+                                Ok(record) => Self::push_json(&mut json, &record),
+                                Err(e) => {
+                                    errors.push(ParseError::new(
+                                        e.to_string(),
+                                        Some(self.last_event_number + i as u64 + 1),
+                                        None,
+                                        None,
+                                        None,
+                                        None,
+                                    ));
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            AvroDecoder::Schema(schema) => {
+                let mut rest = data;
+                let mut event_number = self.last_event_number;
+                while !rest.is_empty() {
+                    event_number += 1;
+                    let record = from_avro_datum(schema, &mut rest, None)
+                        .map_err(|e| anyhow!("error decoding Avro record: {e}"))
+                        .and_then(|value| value_to_json(value, schema));
+                    match record {
+                        Ok(record) => Self::push_json(&mut json, &record),
+                        Err(e) => {
+                            // Datums are not self-delimiting, so we cannot
+                            // skip to the next record.
+                            errors.push(ParseError::bin_event_error(
+                                e.to_string(),
+                                event_number,
+                                rest,
+                                None,
+                            ));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        let (num_records, json_errors) = self.json_parser.input_chunk(&json);
+        self.last_event_number +=
+            (json.iter().filter(|b| **b == b'\n').count() + usize::from(!errors.is_empty())) as u64;
+        errors.extend(json_errors);
+        (num_records, errors)
+    }
+
+    fn push_json(buffer: &mut Vec<u8>, record: &serde_json::Value) {
+        // Serializing a `serde_json::Value` cannot fail.
+        serde_json::to_writer(&mut *buffer, record).unwrap();
+        buffer.push(b'\n');
+    }
+}
+
+impl Parser for AvroParser {
+    fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        self.leftover.extend_from_slice(data);
+        (0, Vec::new())
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        self.input_from_slice(data)
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        if self.leftover.is_empty() {
+            return (0, Vec::new());
+        }
+
+        let leftover = take(&mut self.leftover);
+        self.input_from_slice(&leftover)
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(self.decoder.clone(), self.json_parser.fork()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        format::avro::{AvroInputFormat, AvroParserConfig, AvroUpdateFormat},
+        test::{mock_parser_pipeline, MockDeZSet, TestStruct},
+        transport::InputConsumer,
+        FormatConfig, InputFormat,
+    };
+    use apache_avro::{
+        to_avro_datum, types::Value as AvroValue, Schema as AvroSchema, Writer as AvroWriter,
+    };
+    use std::borrow::Cow;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "TestStruct",
+        "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "b", "type": "boolean"},
+            {"name": "i", "type": ["null", "long"]},
+            {"name": "s", "type": "string"}
+        ]
+    }"#;
+
+    fn record(id: u32, b: bool, i: Option<i64>, s: &str) -> AvroValue {
+        AvroValue::Record(vec![
+            ("id".to_string(), AvroValue::Long(id as i64)),
+            ("b".to_string(), AvroValue::Boolean(b)),
+            (
+                "i".to_string(),
+                match i {
+                    None => AvroValue::Union(0, Box::new(AvroValue::Null)),
+                    Some(i) => AvroValue::Union(1, Box::new(AvroValue::Long(i))),
+                },
+            ),
+            ("s".to_string(), AvroValue::String(s.to_string())),
+        ])
+    }
+
+    fn format_config() -> FormatConfig {
+        FormatConfig {
+            name: Cow::from("avro"),
+            config: serde_yaml::to_value(AvroParserConfig {
+                schema: Some(SCHEMA.to_string()),
+                schema_file: None,
+                schema_registry: None,
+                update_format: AvroUpdateFormat::Raw,
+            })
+            .unwrap(),
+        }
+    }
+
+    fn expected() -> Vec<(TestStruct, bool)> {
+        vec![
+            (
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "foo".to_string(),
+                },
+                true,
+            ),
+            (
+                TestStruct {
+                    id: 2,
+                    b: false,
+                    i: Some(10),
+                    s: "bar".to_string(),
+                },
+                true,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_avro_datums() {
+        let schema = AvroSchema::parse_str(SCHEMA).unwrap();
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config()).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        // One datum per chunk.
+        for value in [
+            record(1, true, None, "foo"),
+            record(2, false, Some(10), "bar"),
+        ] {
+            let datum = to_avro_datum(&schema, value).unwrap();
+            assert!(consumer.input_chunk(&datum).is_empty());
+        }
+        assert_eq!(&outputs.state().flushed, &expected());
+    }
+
+    #[test]
+    fn test_avro_container() {
+        let schema = AvroSchema::parse_str(SCHEMA).unwrap();
+        let mut writer = AvroWriter::new(&schema, Vec::new());
+        writer.append(record(1, true, None, "foo")).unwrap();
+        writer.append(record(2, false, Some(10), "bar")).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config()).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        // Fragments are decoded at the end of input.
+        let (first, second) = data.split_at(data.len() / 2);
+        assert!(consumer.input_fragment(first).is_empty());
+        assert!(consumer.input_fragment(second).is_empty());
+        assert!(outputs.state().flushed.is_empty());
+        assert!(consumer.eoi().is_empty());
+        assert_eq!(&outputs.state().flushed, &expected());
+    }
+
+    #[test]
+    fn test_avro_errors() {
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config()).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        let errors = consumer.input_chunk(b"\x02\x05");
+        assert_eq!(errors.len(), 1);
+        assert!(outputs.state().flushed.is_empty());
+
+        // Schema must be specified.
+        let config = serde_yaml::from_str("update_format: raw").unwrap();
+        assert!(AvroInputFormat
+            .new_parser("test", &MockDeZSet::<TestStruct>::new(), &config)
+            .is_err());
+    }
+}
@@ -0,0 +1,348 @@
+//! Avro format.
+//!
+//! Records are encoded as Avro binary datums.  The schema is specified
+//! inline, read from a file, or retrieved from a Confluent schema registry,
+//! in which case each datum is framed with the schema registry header.
+//!
+//! Internally, the parser converts Avro values to JSON documents and
+//! delegates to the JSON parser, so Avro records map to table columns the
+//! same way JSON objects do.  Likewise, the encoder converts records
+//! serialized as JSON into Avro values.
+//!
+//! The `date`, `time-*`, and `timestamp-*` logical types map to SQL `DATE`,
+//! `TIME`, and `TIMESTAMP` columns.  The `decimal` logical type maps to SQL
+//! `DECIMAL` columns.
+
+use crate::format::decimal::{to_unscaled, unscaled_to_string};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use apache_avro::{
+    schema::DecimalSchema, to_value, types::Value as AvroValue, Decimal as AvroDecimal,
+    Schema as AvroSchema,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::fs;
+use utoipa::ToSchema;
+
+mod input;
+mod output;
+pub(crate) mod schema_registry;
+
+pub use input::{AvroInputFormat, AvroParserConfig};
+pub use output::{AvroEncoderConfig, AvroOutputFormat};
+pub use schema_registry::{SchemaRegistryConfig, SubjectNameStrategy};
+
+/// Supported Avro data change event formats.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+pub enum AvroUpdateFormat {
+    /// Each Avro record is a row to be inserted into the table.
+    #[default]
+    #[serde(rename = "raw")]
+    Raw,
+
+    /// Each Avro record has nullable `insert` and `delete` fields, which
+    /// contain the record to be inserted or deleted.
+    #[serde(rename = "insert_delete")]
+    InsertDelete,
+
+    /// Debezium CDC events serialized using the Confluent Avro converter.
+    ///
+    /// Only the `op`, `before`, and `after` fields of each event are used.
+    #[serde(rename = "debezium")]
+    Debezium,
+}
+
+/// Parse the Avro schema specified inline or in a file.
+///
+/// Returns `None` if neither `schema` nor `schema_file` is specified.
+fn load_schema(
+    schema: &Option<String>,
+    schema_file: &Option<String>,
+) -> AnyResult<Option<(String, AvroSchema)>> {
+    let schema_str = match (schema, schema_file) {
+        (Some(_), Some(_)) => bail!("'schema' and 'schema_file' are mutually exclusive"),
+        (Some(schema), None) => schema.clone(),
+        (None, Some(path)) => fs::read_to_string(path)
+            .map_err(|e| anyhow!("error reading Avro schema file '{path}': {e}"))?,
+        (None, None) => return Ok(None),
+    };
+
+    let schema =
+        AvroSchema::parse_str(&schema_str).map_err(|e| anyhow!("invalid Avro schema: {e}"))?;
+    Ok(Some((schema_str, schema)))
+}
+
+/// Format used by SQL `TIMESTAMP` values in JSON.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Format used by SQL `DATE` values in JSON.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Format used by SQL `TIME` values in JSON.
+const TIME_FORMAT: &str = "%H:%M:%S%.f";
+
+fn epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1970, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// Convert an Avro `decimal` to its exact decimal representation.
+fn decimal_to_string(decimal: AvroDecimal, scale: usize) -> AnyResult<String> {
+    let bytes =
+        Vec::<u8>::try_from(decimal).map_err(|e| anyhow!("error decoding Avro decimal: {e}"))?;
+    if bytes.len() > 16 {
+        bail!("Avro decimal value exceeds the precision supported by SQL 'DECIMAL'");
+    }
+
+    // Sign-extend the big-endian two's complement representation.
+    let fill = if bytes.first().map_or(false, |b| b & 0x80 != 0) {
+        0xff
+    } else {
+        0x00
+    };
+    let mut unscaled = [fill; 16];
+    unscaled[16 - bytes.len()..].copy_from_slice(&bytes);
+    unscaled_to_string(i128::from_be_bytes(unscaled), scale as u32)
+}
+
+/// Number of bytes required to represent all unscaled values of a decimal
+/// with `precision` digits.
+fn decimal_len(precision: usize) -> usize {
+    (1..=16)
+        .find(|len| ((8 * len - 1) as f64 * 2f64.log10()).floor() as usize >= precision)
+        .unwrap_or(16)
+}
+
+/// Convert a decimal string to the binary representation of an Avro
+/// `decimal` that conforms to `schema`.
+///
+/// Returns the value as `bytes`, which `resolve` converts to a decimal after
+/// checking it against the precision declared in the schema.
+fn string_to_decimal(s: &str, schema: &DecimalSchema) -> AnyResult<AvroValue> {
+    let unscaled = to_unscaled(s, schema.scale as u32)?.to_be_bytes();
+
+    // Strip redundant sign bytes from the big-endian two's complement
+    // representation.
+    let sign = if unscaled[0] & 0x80 != 0 { 0xff } else { 0x00 };
+    let mut start = 0;
+    while start < unscaled.len() - 1
+        && unscaled[start] == sign
+        && unscaled[start + 1] & 0x80 == sign & 0x80
+    {
+        start += 1;
+    }
+    let min_len = unscaled.len() - start;
+
+    let len = match schema.inner.as_ref() {
+        AvroSchema::Fixed(fixed) => fixed.size,
+        _ => decimal_len(schema.precision).max(min_len),
+    };
+    if len < min_len {
+        bail!(
+            "decimal value '{s}' does not fit in the Avro decimal type with precision {}",
+            schema.precision
+        );
+    }
+
+    let mut bytes = vec![sign; len];
+    bytes[len - min_len..].copy_from_slice(&unscaled[start..]);
+    Ok(AvroValue::Bytes(bytes))
+}
+
+/// Convert a decoded Avro value to the JSON representation expected by
+/// table deserializers.
+///
+/// `schema` is the schema of the value, which determines the scale of
+/// `decimal` values.
+fn value_to_json(value: AvroValue, schema: &AvroSchema) -> AnyResult<JsonValue> {
+    let timestamp = |ts: Option<NaiveDateTime>| {
+        ts.map(|ts| JsonValue::String(ts.format(TIMESTAMP_FORMAT).to_string()))
+            .ok_or_else(|| anyhow!("Avro timestamp out of range"))
+    };
+    let time = |micros: i64| {
+        NaiveTime::from_num_seconds_from_midnight_opt(
+            (micros / 1_000_000) as u32,
+            (micros % 1_000_000) as u32 * 1_000,
+        )
+        .map(|t| JsonValue::String(t.format(TIME_FORMAT).to_string()))
+        .ok_or_else(|| anyhow!("Avro time {micros}us out of range"))
+    };
+
+    Ok(match value {
+        AvroValue::Union(index, value) => {
+            let schema = match schema {
+                AvroSchema::Union(union) => union.variants().get(index as usize),
+                _ => None,
+            };
+            value_to_json(*value, schema.unwrap_or(&AvroSchema::Null))?
+        }
+        AvroValue::Record(fields) => JsonValue::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let schema = match schema {
+                        AvroSchema::Record(record) => record
+                            .fields
+                            .iter()
+                            .find(|field| field.name == name)
+                            .map(|field| &field.schema),
+                        _ => None,
+                    };
+                    let value = value_to_json(value, schema.unwrap_or(&AvroSchema::Null))?;
+                    Ok((name, value))
+                })
+                .collect::<AnyResult<JsonMap<_, _>>>()?,
+        ),
+        AvroValue::Map(entries) => {
+            let schema = match schema {
+                AvroSchema::Map(values) => values,
+                _ => &AvroSchema::Null,
+            };
+            JsonValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(name, value)| Ok((name, value_to_json(value, schema)?)))
+                    .collect::<AnyResult<JsonMap<_, _>>>()?,
+            )
+        }
+        AvroValue::Array(values) => {
+            let schema = match schema {
+                AvroSchema::Array(items) => items,
+                _ => &AvroSchema::Null,
+            };
+            JsonValue::Array(
+                values
+                    .into_iter()
+                    .map(|value| value_to_json(value, schema))
+                    .collect::<AnyResult<Vec<_>>>()?,
+            )
+        }
+        AvroValue::Date(days) => JsonValue::String(
+            (epoch().date() + Duration::days(days as i64))
+                .format(DATE_FORMAT)
+                .to_string(),
+        ),
+        AvroValue::TimeMillis(millis) => time(millis as i64 * 1_000)?,
+        AvroValue::TimeMicros(micros) => time(micros)?,
+        AvroValue::TimestampMillis(millis) | AvroValue::LocalTimestampMillis(millis) => {
+            timestamp(epoch().checked_add_signed(Duration::milliseconds(millis)))?
+        }
+        AvroValue::TimestampMicros(micros) | AvroValue::LocalTimestampMicros(micros) => {
+            timestamp(epoch().checked_add_signed(Duration::microseconds(micros)))?
+        }
+        AvroValue::Decimal(decimal) => match schema {
+            AvroSchema::Decimal(DecimalSchema { scale, .. }) => {
+                JsonValue::String(decimal_to_string(decimal, *scale)?)
+            }
+            _ => bail!("unable to determine the scale of an Avro 'decimal' value"),
+        },
+        value => JsonValue::try_from(value)
+            .map_err(|e| anyhow!("error converting Avro value to JSON: {e}"))?,
+    })
+}
+
+/// Convert a record serialized as JSON into an Avro value that conforms to
+/// `schema`.
+///
+/// Converts SQL date and time strings into Avro logical types; other values
+/// are converted by resolving their generic Avro representation against the
+/// schema.
+fn json_to_value(json: &JsonValue, schema: &AvroSchema) -> AnyResult<AvroValue> {
+    let value = logical_json_to_value(json, schema)?;
+    value
+        .resolve(schema)
+        .map_err(|e| anyhow!("record {json} does not match the Avro schema: {e}"))
+}
+
+fn logical_json_to_value(json: &JsonValue, schema: &AvroSchema) -> AnyResult<AvroValue> {
+    let parse_timestamp = |s: &str| {
+        NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT)
+            .map(|ts| ts - epoch())
+            .map_err(|e| anyhow!("invalid timestamp '{s}': {e}"))
+    };
+    let parse_time = |s: &str| {
+        NaiveTime::parse_from_str(s, TIME_FORMAT)
+            .map(|t| {
+                t.num_seconds_from_midnight() as i64 * 1_000_000 + t.nanosecond() as i64 / 1_000
+            })
+            .map_err(|e| anyhow!("invalid time '{s}': {e}"))
+    };
+
+    Ok(match (schema, json) {
+        (AvroSchema::Date, JsonValue::String(s)) => AvroValue::Date(
+            (NaiveDate::parse_from_str(s, DATE_FORMAT)
+                .map_err(|e| anyhow!("invalid date '{s}': {e}"))?
+                - epoch().date())
+            .num_days() as i32,
+        ),
+        (AvroSchema::TimeMillis, JsonValue::String(s)) => {
+            AvroValue::TimeMillis((parse_time(s)? / 1_000) as i32)
+        }
+        (AvroSchema::TimeMicros, JsonValue::String(s)) => AvroValue::TimeMicros(parse_time(s)?),
+        (AvroSchema::TimestampMillis, JsonValue::String(s)) => {
+            AvroValue::TimestampMillis(parse_timestamp(s)?.num_milliseconds())
+        }
+        (AvroSchema::TimestampMicros, JsonValue::String(s)) => AvroValue::TimestampMicros(
+            parse_timestamp(s)?
+                .num_microseconds()
+                .ok_or_else(|| anyhow!("timestamp '{s}' out of range"))?,
+        ),
+        (AvroSchema::LocalTimestampMillis, JsonValue::String(s)) => {
+            AvroValue::LocalTimestampMillis(parse_timestamp(s)?.num_milliseconds())
+        }
+        (AvroSchema::LocalTimestampMicros, JsonValue::String(s)) => {
+            AvroValue::LocalTimestampMicros(
+                parse_timestamp(s)?
+                    .num_microseconds()
+                    .ok_or_else(|| anyhow!("timestamp '{s}' out of range"))?,
+            )
+        }
+        (AvroSchema::Decimal(decimal), JsonValue::String(s)) => string_to_decimal(s, decimal)?,
+        // Decimals serialized as numbers by JIT-compiled pipelines.
+        (AvroSchema::Decimal(decimal), JsonValue::Number(n)) => {
+            string_to_decimal(&n.to_string(), decimal)?
+        }
+        (AvroSchema::Record(record), JsonValue::Object(fields)) => {
+            let mut values = Vec::with_capacity(record.fields.len());
+            for field in record.fields.iter() {
+                // SQL column names are case-insensitive.
+                let value = fields.get(&field.name).or_else(|| {
+                    fields
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(&field.name))
+                        .map(|(_, value)| value)
+                });
+                match value {
+                    Some(value) => values.push((
+                        field.name.clone(),
+                        logical_json_to_value(value, &field.schema)?,
+                    )),
+                    // Let `resolve` fill in the default value.
+                    None if field.default.is_some() => {}
+                    None => values.push((field.name.clone(), AvroValue::Null)),
+                }
+            }
+            AvroValue::Record(values)
+        }
+        (AvroSchema::Array(items), JsonValue::Array(values)) => AvroValue::Array(
+            values
+                .iter()
+                .map(|value| logical_json_to_value(value, items))
+                .collect::<AnyResult<Vec<_>>>()?,
+        ),
+        (AvroSchema::Union(union), json) if !json.is_null() => union
+            .variants()
+            .iter()
+            .filter(|variant| **variant != AvroSchema::Null)
+            .find_map(|variant| {
+                logical_json_to_value(json, variant)
+                    .ok()
+                    .filter(|value| value.validate(variant))
+            })
+            .map_or_else(|| to_value(json), Ok)?,
+        (_, json) => to_value(json)?,
+    })
+}
@@ -0,0 +1,327 @@
+//! Avro format encoder.
+
+use super::{
+    json_to_value, load_schema,
+    schema_registry::{frame, SchemaRegistryClient},
+    SchemaRegistryConfig, SubjectNameStrategy,
+};
+use crate::{
+    catalog::{RecordFormat, SerBatch},
+    ControllerError, Encoder, OutputConsumer, OutputFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use apache_avro::{to_avro_datum, Schema as AvroSchema};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, sync::Arc};
+use utoipa::ToSchema;
+
+/// The largest weight of a record that can be output.  A record with weight
+/// `w` is output `w` times.
+const MAX_DUPLICATES: i64 = 1_000_000;
+
+/// Avro format encoder.
+pub struct AvroOutputFormat;
+
+/// Avro encoder configuration.
+///
+/// Exactly one of `schema`, `schema_file`, and `schema_registry` must be
+/// specified.  Each output record is encoded as a separate Avro datum and
+/// sent to the transport in its own buffer, e.g., as a separate Kafka
+/// message.
+///
+/// Avro records cannot represent deletions, which are therefore not
+/// included in the output.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct AvroEncoderConfig {
+    /// Avro schema of output records in JSON.
+    pub schema: Option<String>,
+
+    /// Path to a file containing the Avro schema of output records.
+    pub schema_file: Option<String>,
+
+    /// Register the output schema with a Confluent schema registry (or use
+    /// the latest schema registered for the subject) and frame each record
+    /// with the schema registry header.
+    pub schema_registry: Option<SchemaRegistryConfig>,
+
+    /// Topic name used to derive the schema registry subject.
+    ///
+    /// Required by the `topic_name` and `topic_record_name` subject naming
+    /// strategies.
+    pub topic: Option<String>,
+}
+
+impl OutputFormat for AvroOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("avro")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            AvroEncoderConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::encoder_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = AvroEncoderConfig::deserialize(config)?;
+
+        Ok(Box::new(AvroEncoder::new(consumer, &config)?))
+    }
+}
+
+struct AvroEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+    schema: AvroSchema,
+    /// Schema registry id of `schema`, if any.
+    schema_id: Option<u32>,
+    max_buffer_size: usize,
+}
+
+impl AvroEncoder {
+    fn new(
+        output_consumer: Box<dyn OutputConsumer>,
+        config: &AvroEncoderConfig,
+    ) -> AnyResult<Self> {
+        let max_buffer_size = output_consumer.max_buffer_size_bytes();
+        let schema = load_schema(&config.schema, &config.schema_file)?;
+
+        let (schema_id, schema) = match (schema, &config.schema_registry) {
+            (Some((_, schema)), None) => (None, schema),
+            (schema, Some(registry)) => {
+                if schema.is_some() && registry.schema.is_some() {
+                    bail!("the output schema can be specified either in the Avro encoder configuration or in the schema registry configuration, but not both");
+                }
+                let mut registry = registry.clone();
+                if let Some((schema_str, _)) = schema {
+                    registry.schema = Some(schema_str);
+                }
+                let topic = match (&config.topic, registry.subject_name_strategy) {
+                    (Some(topic), _) => topic.as_str(),
+                    (None, SubjectNameStrategy::RecordName) => "",
+                    (None, strategy) => {
+                        bail!("'topic' is required with the '{strategy:?}' subject naming strategy")
+                    }
+                };
+                let (id, schema) = SchemaRegistryClient::new(&registry).output_schema(topic)?;
+                (Some(id), schema)
+            }
+            (None, None) => bail!(
+                "Avro encoder configuration must specify one of 'schema', 'schema_file', or 'schema_registry'"
+            ),
+        };
+
+        Ok(Self {
+            output_consumer,
+            schema,
+            schema_id,
+            max_buffer_size,
+        })
+    }
+
+    /// Encode a record serialized as JSON.
+    fn encode_record(&self, record: &[u8]) -> AnyResult<Vec<u8>> {
+        let json: JsonValue = serde_json::from_slice(record)
+            .map_err(|e| anyhow!("error parsing serialized record: {e}"))?;
+        let datum = to_avro_datum(&self.schema, json_to_value(&json, &self.schema)?)
+            .map_err(|e| anyhow!("error encoding record {json} as Avro: {e}"))?;
+
+        Ok(match self.schema_id {
+            Some(schema_id) => frame(schema_id, &datum),
+            None => datum,
+        })
+    }
+}
+
+impl Encoder for AvroEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut record = Vec::new();
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                let w = cursor.weight();
+
+                // Deletions are not representable in Avro.
+                if w > 0 {
+                    record.clear();
+                    cursor.serialize_key(&mut record)?;
+
+                    if w > MAX_DUPLICATES {
+                        bail!(
+                            "Unable to output record '{}' with very large weight {w}. Consider adjusting your SQL queries to avoid duplicate output records, e.g., using 'SELECT DISTINCT'.",
+                            String::from_utf8_lossy(&record)
+                        );
+                    }
+
+                    let message = self.encode_record(&record)?;
+                    if message.len() > self.max_buffer_size {
+                        bail!("Avro record exceeds maximum buffer size supported by the output transport. Max supported buffer size is {} bytes, but the record requires {} bytes.",
+                              self.max_buffer_size,
+                              message.len());
+                    }
+                    for _ in 0..w {
+                        self.output_consumer.push_buffer(&message);
+                    }
+                }
+
+                cursor.step_key();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AvroEncoder, AvroEncoderConfig};
+    use crate::{
+        catalog::SerBatch,
+        format::{
+            avro::{json_to_value, value_to_json},
+            Encoder,
+        },
+        static_compile::seroutput::SerBatchImpl,
+        test::{MockOutputConsumer, TestStruct},
+    };
+    use apache_avro::{from_avro_datum, to_avro_datum, Schema as AvroSchema};
+    use dbsp::{trace::Batch, OrdZSet};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "TestStruct",
+        "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "b", "type": "boolean"},
+            {"name": "i", "type": ["null", "long"]},
+            {"name": "s", "type": "string"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_avro_encoder() {
+        let config = AvroEncoderConfig {
+            schema: Some(SCHEMA.to_string()),
+            schema_file: None,
+            schema_registry: None,
+            topic: None,
+        };
+        let consumer = MockOutputConsumer::new();
+        let consumer_data = consumer.data.clone();
+        let mut encoder = AvroEncoder::new(Box::new(consumer), &config).unwrap();
+
+        let foo = TestStruct {
+            id: 1,
+            b: true,
+            i: None,
+            s: "foo".to_string(),
+        };
+        let bar = TestStruct {
+            id: 2,
+            b: false,
+            i: Some(10),
+            s: "bar".to_string(),
+        };
+        let zset = OrdZSet::from_keys((), vec![(foo, 2), (bar, -1)]);
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        // The inserted record is output twice; the deletion is skipped.
+        let schema = AvroSchema::parse_str(SCHEMA).unwrap();
+        let data = consumer_data.lock().unwrap();
+        let mut rest = data.as_slice();
+        let mut records = Vec::new();
+        while !rest.is_empty() {
+            let value = from_avro_datum(&schema, &mut rest, None).unwrap();
+            records.push(value_to_json(value, &schema).unwrap());
+        }
+        let expected = json!({"id": 1, "b": true, "i": null, "s": "foo"});
+        assert_eq!(records, vec![expected.clone(), expected]);
+    }
+
+    #[test]
+    fn test_logical_types() {
+        let schema = AvroSchema::parse_str(
+            r#"{
+                "type": "record",
+                "name": "T",
+                "fields": [
+                    {"name": "d", "type": {"type": "int", "logicalType": "date"}},
+                    {"name": "t", "type": {"type": "long", "logicalType": "time-micros"}},
+                    {"name": "ts", "type": ["null", {"type": "long", "logicalType": "timestamp-millis"}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let record = json!({"D": "2023-10-05", "T": "12:30:00.5", "TS": "2023-10-05 12:30:00.25"});
+        let value = json_to_value(&record, &schema).unwrap();
+        assert_eq!(
+            value_to_json(value, &schema).unwrap(),
+            json!({"d": "2023-10-05", "t": "12:30:00.500", "ts": "2023-10-05 12:30:00.250"})
+        );
+
+        assert!(json_to_value(&json!({"d": "yesterday", "t": null, "ts": null}), &schema).is_err());
+    }
+
+    #[test]
+    fn test_decimal() {
+        let schema = AvroSchema::parse_str(
+            r#"{
+                "type": "record",
+                "name": "T",
+                "fields": [
+                    {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}},
+                    {"name": "fee", "type": {"type": "fixed", "name": "fee_t", "size": 8, "logicalType": "decimal", "precision": 18, "scale": 3}},
+                    {"name": "opt", "type": ["null", {"type": "bytes", "logicalType": "decimal", "precision": 5, "scale": 1}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let record = json!({"amount": "12345678.90", "fee": "-1.5", "opt": "1.2"});
+        let datum = to_avro_datum(&schema, json_to_value(&record, &schema).unwrap()).unwrap();
+        let value = from_avro_datum(&schema, &mut datum.as_slice(), None).unwrap();
+        assert_eq!(
+            value_to_json(value, &schema).unwrap(),
+            json!({"amount": "12345678.90", "fee": "-1.500", "opt": "1.2"})
+        );
+
+        // Values that would lose precision are rejected.
+        assert!(json_to_value(
+            &json!({"amount": "0.001", "fee": "0", "opt": null}),
+            &schema
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,388 @@
+//! Confluent schema registry support.
+//!
+//! Messages produced by Confluent serializers carry a 5-byte header: a zero
+//! magic byte followed by the 4-byte big-endian id of the writer schema in
+//! the registry.  Used by the `avro` format, which decodes and produces such
+//! messages directly, and by the Kafka transports, which convert them to and
+//! from JSON documents handled by the `json` format:  the input transport
+//! strips the header, looks up the schema by id, and converts the message to
+//! a JSON document, which is then parsed with `update_format: "raw"`.  The
+//! output transport does the reverse: it registers (or looks up) the schema
+//! of its subject and converts records produced by the `json` format into
+//! framed Avro messages.
+
+use super::{json_to_value, value_to_json};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use apache_avro::{from_avro_datum, to_avro_datum, Schema as AvroSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+use utoipa::ToSchema;
+
+/// Magic byte that starts every message framed by a Confluent serializer.
+const MAGIC_BYTE: u8 = 0;
+
+/// Size of the magic byte and schema id header.
+const HEADER_SIZE: usize = 5;
+
+/// Confluent schema registry configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SchemaRegistryConfig {
+    /// Schema registry URL, e.g., `http://localhost:8081`.
+    pub url: String,
+
+    /// Username for HTTP basic authentication.
+    pub username: Option<String>,
+
+    /// Password for HTTP basic authentication.
+    pub password: Option<String>,
+
+    /// Strategy used to derive the subject under which output schemas are
+    /// registered.
+    #[serde(default)]
+    pub subject_name_strategy: SubjectNameStrategy,
+
+    /// Avro schema of output records.
+    ///
+    /// Output endpoints register this schema under the subject derived
+    /// from `subject_name_strategy`.  When not specified, the latest schema
+    /// registered for the subject is used, which requires the
+    /// `topic_name` strategy.  Ignored by input endpoints, which use the
+    /// schema id embedded in each message.
+    pub schema: Option<String>,
+}
+
+/// Strategy used to derive schema registry subject names.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ToSchema)]
+pub enum SubjectNameStrategy {
+    /// `<topic>-value`.
+    #[default]
+    #[serde(rename = "topic_name")]
+    TopicName,
+
+    /// Fully qualified name of the Avro record.
+    #[serde(rename = "record_name")]
+    RecordName,
+
+    /// `<topic>-<fully qualified record name>`.
+    #[serde(rename = "topic_record_name")]
+    TopicRecordName,
+}
+
+/// Schema retrieved from the registry.
+#[derive(Clone, Debug)]
+enum RegistrySchema {
+    Avro(AvroSchema),
+    /// JSON schema: the payload is already a JSON document.
+    Json,
+}
+
+/// Response of `GET /schemas/ids/{id}` and `GET /subjects/{subject}/versions/latest`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaResponse {
+    id: Option<u32>,
+    schema: String,
+    schema_type: Option<String>,
+}
+
+/// Response of `POST /subjects/{subject}/versions`.
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: u32,
+}
+
+/// Schema registry client that caches schemas by id.
+pub(crate) struct SchemaRegistryClient {
+    config: SchemaRegistryConfig,
+    client: reqwest::blocking::Client,
+    schemas: Mutex<BTreeMap<u32, Arc<RegistrySchema>>>,
+}
+
+impl SchemaRegistryClient {
+    pub(crate) fn new(config: &SchemaRegistryConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::blocking::Client::new(),
+            schemas: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn request(
+        &self,
+        builder: reqwest::blocking::RequestBuilder,
+    ) -> reqwest::blocking::RequestBuilder {
+        match &self.config.username {
+            Some(username) => builder.basic_auth(username, self.config.password.as_ref()),
+            None => builder,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.config.url.trim_end_matches('/'))
+    }
+
+    fn get_schema(&self, path: &str) -> AnyResult<SchemaResponse> {
+        let response = self
+            .request(self.client.get(self.url(path)))
+            .send()
+            .map_err(|e| anyhow!("error retrieving schema from '{}': {e}", self.url(path)))?;
+        if !response.status().is_success() {
+            bail!(
+                "schema registry request '{}' failed with status {}: {}",
+                self.url(path),
+                response.status(),
+                response.text().unwrap_or_default()
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    fn parse_schema(response: &SchemaResponse) -> AnyResult<RegistrySchema> {
+        match response.schema_type.as_deref() {
+            None | Some("AVRO") => Ok(RegistrySchema::Avro(
+                AvroSchema::parse_str(&response.schema)
+                    .map_err(|e| anyhow!("invalid Avro schema in schema registry: {e}"))?,
+            )),
+            Some("JSON") => Ok(RegistrySchema::Json),
+            Some(schema_type) => {
+                bail!("schema type '{schema_type}' is not supported")
+            }
+        }
+    }
+
+    /// Retrieve the schema with the specified id.
+    fn schema_by_id(&self, id: u32) -> AnyResult<Arc<RegistrySchema>> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+
+        let response = self.get_schema(&format!("schemas/ids/{id}"))?;
+        let schema = Arc::new(Self::parse_schema(&response)?);
+        self.schemas.lock().unwrap().insert(id, schema.clone());
+        Ok(schema)
+    }
+
+    /// Convert a message framed by a Confluent serializer into a JSON document.
+    pub(crate) fn decode(&self, payload: &[u8]) -> AnyResult<Vec<u8>> {
+        let (id, body) = split_header(payload)?;
+
+        match &*self.schema_by_id(id)? {
+            RegistrySchema::Avro(schema) => avro_to_json(schema, body),
+            RegistrySchema::Json => Ok(body.to_vec()),
+        }
+    }
+
+    /// Determine the schema used to encode output records for `topic`,
+    /// registering `config.schema` if specified.
+    pub(crate) fn output_schema(&self, topic: &str) -> AnyResult<(u32, AvroSchema)> {
+        match &self.config.schema {
+            Some(schema_str) => {
+                let schema = AvroSchema::parse_str(schema_str)
+                    .map_err(|e| anyhow!("invalid Avro schema in schema registry config: {e}"))?;
+                let subject = subject_name(self.config.subject_name_strategy, topic, &schema)?;
+                let url = self.url(&format!("subjects/{subject}/versions"));
+                let response = self
+                    .request(self.client.post(&url))
+                    .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+                    .json(&json!({ "schema": schema_str }))
+                    .send()
+                    .map_err(|e| anyhow!("error registering schema at '{url}': {e}"))?;
+                if !response.status().is_success() {
+                    bail!(
+                        "error registering schema for subject '{subject}': status {}: {}",
+                        response.status(),
+                        response.text().unwrap_or_default()
+                    );
+                }
+                let id = response.json::<RegisterResponse>()?.id;
+                Ok((id, schema))
+            }
+            None => {
+                if self.config.subject_name_strategy != SubjectNameStrategy::TopicName {
+                    bail!("schema registry config must specify 'schema' when using a subject naming strategy other than 'topic_name'");
+                }
+                let response =
+                    self.get_schema(&format!("subjects/{topic}-value/versions/latest"))?;
+                let id = response
+                    .id
+                    .ok_or_else(|| anyhow!("schema registry response is missing schema id"))?;
+                match Self::parse_schema(&response)? {
+                    RegistrySchema::Avro(schema) => Ok((id, schema)),
+                    RegistrySchema::Json => {
+                        bail!("subject '{topic}-value' has a JSON schema; only Avro schemas are supported for output")
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Derive the subject name for `topic` and `schema` using `strategy`.
+fn subject_name(
+    strategy: SubjectNameStrategy,
+    topic: &str,
+    schema: &AvroSchema,
+) -> AnyResult<String> {
+    let record_name = || match schema {
+        AvroSchema::Record(record) => Ok(record.name.fullname(None)),
+        _ => Err(anyhow!(
+            "subject naming strategy '{strategy:?}' requires a record schema"
+        )),
+    };
+
+    Ok(match strategy {
+        SubjectNameStrategy::TopicName => format!("{topic}-value"),
+        SubjectNameStrategy::RecordName => record_name()?,
+        SubjectNameStrategy::TopicRecordName => format!("{topic}-{}", record_name()?),
+    })
+}
+
+/// Split a framed message into schema id and body.
+fn split_header(payload: &[u8]) -> AnyResult<(u32, &[u8])> {
+    if payload.len() < HEADER_SIZE || payload[0] != MAGIC_BYTE {
+        bail!("message is not framed using the schema registry wire format");
+    }
+    let id = u32::from_be_bytes(payload[1..HEADER_SIZE].try_into().unwrap());
+    Ok((id, &payload[HEADER_SIZE..]))
+}
+
+/// Decode an Avro datum into a newline-terminated JSON document.
+fn avro_to_json(schema: &AvroSchema, mut body: &[u8]) -> AnyResult<Vec<u8>> {
+    let value = from_avro_datum(schema, &mut body, None)
+        .map_err(|e| anyhow!("error decoding Avro message: {e}"))?;
+    let json = value_to_json(value, schema)
+        .map_err(|e| anyhow!("error converting Avro message to JSON: {e}"))?;
+    let mut result = serde_json::to_vec(&json)?;
+    result.push(b'\n');
+    Ok(result)
+}
+
+/// Encode a JSON record as a framed Avro message.
+fn json_to_avro(schema_id: u32, schema: &AvroSchema, record: &JsonValue) -> AnyResult<Vec<u8>> {
+    let value = json_to_value(record, schema)?;
+    let datum = to_avro_datum(schema, value)
+        .map_err(|e| anyhow!("error encoding record {record} as Avro: {e}"))?;
+
+    Ok(frame(schema_id, &datum))
+}
+
+/// Prepend the schema registry header to an Avro datum.
+pub(crate) fn frame(schema_id: u32, datum: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(HEADER_SIZE + datum.len());
+    result.push(MAGIC_BYTE);
+    result.extend_from_slice(&schema_id.to_be_bytes());
+    result.extend_from_slice(datum);
+    result
+}
+
+/// Convert a buffer produced by the `json` output format into framed Avro
+/// messages, one per inserted record.
+///
+/// Deletions cannot be represented in a plain Avro record and are skipped.
+pub(crate) fn encode_json_buffer(
+    schema_id: u32,
+    schema: &AvroSchema,
+    buffer: &[u8],
+) -> AnyResult<Vec<Vec<u8>>> {
+    let mut messages = Vec::new();
+
+    for value in serde_json::Deserializer::from_slice(buffer).into_iter::<JsonValue>() {
+        let value = value.map_err(|e| anyhow!("error parsing output buffer as JSON: {e}"))?;
+        let updates = match value {
+            JsonValue::Array(updates) => updates,
+            update => vec![update],
+        };
+
+        for update in updates.iter() {
+            if let Some(record) = update.get("insert") {
+                messages.push(json_to_avro(schema_id, schema, record)?);
+            }
+        }
+    }
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        avro_to_json, encode_json_buffer, split_header, subject_name, SubjectNameStrategy,
+    };
+    use apache_avro::Schema as AvroSchema;
+    use serde_json::{json, Value as JsonValue};
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "TestStruct",
+        "namespace": "feldera.test",
+        "fields": [
+            {"name": "id", "type": "int"},
+            {"name": "b", "type": "boolean"},
+            {"name": "i", "type": ["null", "long"]},
+            {"name": "s", "type": "string"}
+        ]
+    }"#;
+
+    #[test]
+    fn avro_round_trip() {
+        let schema = AvroSchema::parse_str(SCHEMA).unwrap();
+        let buffer = br#"{"insert":{"id":1,"b":true,"i":null,"s":"foo"}}
+{"delete":{"id":2,"b":false,"i":5,"s":"bar"}}
+{"insert":{"id":3,"b":false,"i":10,"s":"baz"}}
+"#;
+
+        let messages = encode_json_buffer(42, &schema, buffer).unwrap();
+        assert_eq!(messages.len(), 2);
+
+        let decoded = messages
+            .iter()
+            .map(|message| {
+                let (id, body) = split_header(message).unwrap();
+                assert_eq!(id, 42);
+                serde_json::from_slice::<JsonValue>(&avro_to_json(&schema, body).unwrap()).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            decoded,
+            vec![
+                json!({"id": 1, "b": true, "i": null, "s": "foo"}),
+                json!({"id": 3, "b": false, "i": 10, "s": "baz"}),
+            ]
+        );
+
+        // Array-encoded buffers produce the same messages.
+        let array_buffer = br#"[{"insert":{"id":1,"b":true,"i":null,"s":"foo"}},{"insert":{"id":3,"b":false,"i":10,"s":"baz"}}]"#;
+        assert_eq!(
+            encode_json_buffer(42, &schema, array_buffer).unwrap(),
+            messages
+        );
+
+        assert!(split_header(b"{\"id\": 1}").is_err());
+        assert!(encode_json_buffer(42, &schema, br#"{"insert":{"id":"x"}}"#).is_err());
+    }
+
+    #[test]
+    fn subject_names() {
+        let schema = AvroSchema::parse_str(SCHEMA).unwrap();
+        assert_eq!(
+            subject_name(SubjectNameStrategy::TopicName, "t", &schema).unwrap(),
+            "t-value"
+        );
+        assert_eq!(
+            subject_name(SubjectNameStrategy::RecordName, "t", &schema).unwrap(),
+            "feldera.test.TestStruct"
+        );
+        assert_eq!(
+            subject_name(SubjectNameStrategy::TopicRecordName, "t", &schema).unwrap(),
+            "t-feldera.test.TestStruct"
+        );
+
+        let schema = AvroSchema::parse_str(r#""string""#).unwrap();
+        assert!(subject_name(SubjectNameStrategy::RecordName, "t", &schema).is_err());
+    }
+}
@@ -0,0 +1,515 @@
+//! Text encodings of binary values.
+//!
+//! SQL `BINARY` and `VARBINARY` values are serialized as byte arrays.  Text
+//! formats cannot represent raw bytes, so parsers and encoders convert them
+//! to and from a textual encoding: JSON always uses base64, CSV supports
+//! both base64 and hex.
+
+use anyhow::{anyhow, Result as AnyResult};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+use std::fmt::Write;
+use utoipa::ToSchema;
+
+/// Text encoding of the values of `BINARY` and `VARBINARY` columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryEncoding {
+    /// Standard base64 encoding with padding, e.g., `"3q2+7w=="`.
+    #[default]
+    Base64,
+
+    /// Hexadecimal encoding, two digits per byte, e.g., `"deadbeef"`.
+    ///
+    /// The parser accepts both upper and lower case digits and an optional
+    /// `\x` or `0x` prefix.  The encoder produces lower case digits without
+    /// a prefix.
+    Hex,
+}
+
+impl BinaryEncoding {
+    /// Encode `bytes` as text.
+    pub(crate) fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base64 => BASE64.encode(bytes),
+            Self::Hex => {
+                let mut text = String::with_capacity(bytes.len() * 2);
+                for byte in bytes {
+                    // Writing to a `String` cannot fail.
+                    write!(text, "{byte:02x}").unwrap();
+                }
+                text
+            }
+        }
+    }
+
+    /// Decode text produced by [`Self::encode`].
+    pub(crate) fn decode(&self, text: &[u8]) -> AnyResult<Vec<u8>> {
+        match self {
+            Self::Base64 => BASE64.decode(trim(text)).map_err(|e| {
+                anyhow!(
+                    "'{}' is not a valid base64 string: {e}",
+                    String::from_utf8_lossy(text)
+                )
+            }),
+            Self::Hex => decode_hex(text),
+        }
+    }
+}
+
+/// Strip leading and trailing ASCII whitespace.
+fn trim(text: &[u8]) -> &[u8] {
+    let start = text
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(text.len());
+    let end = text
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    &text[start..end]
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn decode_hex(text: &[u8]) -> AnyResult<Vec<u8>> {
+    let error = |reason: &str| {
+        anyhow!(
+            "'{}' is not a valid hex string: {reason}",
+            String::from_utf8_lossy(text)
+        )
+    };
+
+    let mut digits = trim(text);
+    for prefix in [&b"\\x"[..], b"0x", b"0X"] {
+        if let Some(rest) = digits.strip_prefix(prefix) {
+            digits = rest;
+            break;
+        }
+    }
+    if digits.len() % 2 != 0 {
+        return Err(error("odd number of digits"));
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(high), Some(low)) => Ok(high << 4 | low),
+            _ => Err(error("invalid hex digit")),
+        })
+        .collect()
+}
+
+/// Serializer adapter that encodes binary values as strings.
+pub(crate) struct BinaryAsText<S> {
+    serializer: S,
+    encoding: BinaryEncoding,
+}
+
+impl<S> BinaryAsText<S> {
+    pub(crate) fn new(serializer: S, encoding: BinaryEncoding) -> Self {
+        Self {
+            serializer,
+            encoding,
+        }
+    }
+}
+
+/// Value serialized with [`BinaryAsText`].
+pub(crate) struct AsText<'a, T: ?Sized> {
+    value: &'a T,
+    encoding: BinaryEncoding,
+}
+
+impl<'a, T> AsText<'a, T>
+where
+    T: ?Sized,
+{
+    pub(crate) fn new(value: &'a T, encoding: BinaryEncoding) -> Self {
+        Self { value, encoding }
+    }
+}
+
+impl<'a, T> Serialize for AsText<'a, T>
+where
+    T: ?Sized + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value
+            .serialize(BinaryAsText::new(serializer, self.encoding))
+    }
+}
+
+/// Compound serializer that serializes its elements with [`BinaryAsText`].
+pub(crate) struct Compound<C> {
+    compound: C,
+    encoding: BinaryEncoding,
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(fn $method(self, v: $type) -> Result<Self::Ok, Self::Error> {
+            self.serializer.$method(v)
+        })*
+    };
+}
+
+impl<S> Serializer for BinaryAsText<S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    forward_serialize!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.serializer.serialize_str(&self.encoding.encode(v))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serializer
+            .serialize_some(&AsText::new(value, self.encoding))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serializer
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serializer
+            .serialize_newtype_struct(name, &AsText::new(value, self.encoding))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serializer.serialize_newtype_variant(
+            name,
+            variant_index,
+            variant,
+            &AsText::new(value, self.encoding),
+        )
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        let encoding = self.encoding;
+        self.serializer
+            .serialize_seq(len)
+            .map(|compound| Compound { compound, encoding })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        let encoding = self.encoding;
+        self.serializer
+            .serialize_tuple(len)
+            .map(|compound| Compound { compound, encoding })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        let encoding = self.encoding;
+        self.serializer
+            .serialize_tuple_struct(name, len)
+            .map(|compound| Compound { compound, encoding })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.serializer
+            .serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        let encoding = self.encoding;
+        self.serializer
+            .serialize_map(len)
+            .map(|compound| Compound { compound, encoding })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        let encoding = self.encoding;
+        self.serializer
+            .serialize_struct(name, len)
+            .map(|compound| Compound { compound, encoding })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.serializer
+            .serialize_struct_variant(name, variant_index, variant, len)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.serializer.is_human_readable()
+    }
+}
+
+impl<C> ser::SerializeSeq for Compound<C>
+where
+    C: ser::SerializeSeq,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_element(&AsText::new(value, self.encoding))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.compound.end()
+    }
+}
+
+impl<C> ser::SerializeTuple for Compound<C>
+where
+    C: ser::SerializeTuple,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_element(&AsText::new(value, self.encoding))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.compound.end()
+    }
+}
+
+impl<C> ser::SerializeTupleStruct for Compound<C>
+where
+    C: ser::SerializeTupleStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_field(&AsText::new(value, self.encoding))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.compound.end()
+    }
+}
+
+impl<C> ser::SerializeMap for Compound<C>
+where
+    C: ser::SerializeMap,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_key(&AsText::new(key, self.encoding))
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_value(&AsText::new(value, self.encoding))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.compound.end()
+    }
+}
+
+impl<C> ser::SerializeStruct for Compound<C>
+where
+    C: ser::SerializeStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compound
+            .serialize_field(key, &AsText::new(value, self.encoding))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.compound.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.compound.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AsText, BinaryAsText, BinaryEncoding};
+    use bstr::BString;
+    use serde::Serialize;
+
+    #[test]
+    fn test_binary_encoding() {
+        let bytes = [0xde, 0xad, 0xbe, 0xef];
+
+        assert_eq!(BinaryEncoding::Base64.encode(&bytes), "3q2+7w==");
+        assert_eq!(BinaryEncoding::Base64.decode(b"3q2+7w==").unwrap(), bytes);
+        assert!(BinaryEncoding::Base64.decode(b"3q2+7w=").is_err());
+
+        assert_eq!(BinaryEncoding::Hex.encode(&bytes), "deadbeef");
+        assert_eq!(BinaryEncoding::Hex.decode(b"DEADbeef").unwrap(), bytes);
+        assert_eq!(BinaryEncoding::Hex.decode(b"\\xdeadbeef").unwrap(), bytes);
+        assert_eq!(BinaryEncoding::Hex.decode(b"0xdeadbeef").unwrap(), bytes);
+        assert!(BinaryEncoding::Hex.decode(b"dead0").is_err());
+        assert!(BinaryEncoding::Hex.decode(b"deadbeeg").is_err());
+
+        assert!(BinaryEncoding::Hex.decode(b"").unwrap().is_empty());
+        assert_eq!(BinaryEncoding::Base64.encode(&[]), "");
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        id: i32,
+        data: BString,
+        maybe: Option<BString>,
+        list: Vec<BString>,
+    }
+
+    #[test]
+    fn test_binary_as_text() {
+        let record = Record {
+            id: 1,
+            data: BString::from(vec![0xde, 0xad]),
+            maybe: Some(BString::from(vec![0xbe, 0xef])),
+            list: vec![BString::from(vec![0x00])],
+        };
+
+        let mut json = Vec::new();
+        record
+            .serialize(BinaryAsText::new(
+                &mut serde_json::Serializer::new(&mut json),
+                BinaryEncoding::Base64,
+            ))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"{"id":1,"data":"3q0=","maybe":"vu8=","list":["AA=="]}"#
+        );
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new());
+        let record = (
+            1,
+            BString::from(vec![0xde, 0xad]),
+            Some(BString::from(vec![0x0a])),
+        );
+        writer
+            .serialize(AsText::new(&record, BinaryEncoding::Hex))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+            "1,dead,0a\n"
+        );
+    }
+}
@@ -1,17 +1,31 @@
 use crate::{
-    catalog::{DeCollectionStream, RecordFormat, SerBatch},
-    format::{Encoder, InputFormat, OutputFormat, ParseError, Parser},
-    util::{split_on_newline, truncate_ellipse},
+    catalog::{CsvFlavor, DeCollectionStream, RecordFormat, SerBatch},
+    format::{
+        binary::BinaryEncoding,
+        datetime::{DateTimeConverter, DateTimeFormat},
+        decimal::{DecimalConverter, DecimalFormat},
+        Encoder, FieldParseError, InputFormat, OutputFormat, ParseError, Parser,
+    },
+    util::{count_newlines, split_on_newline, truncate_ellipse},
     ControllerError, DeCollectionHandle, OutputConsumer,
 };
 use actix_web::HttpRequest;
-use anyhow::{bail, Result as AnyResult};
-use csv_core::{ReadRecordResult, Reader as CsvReader};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use csv::{
+    ByteRecord, Reader as CsvRecordReader, ReaderBuilder as CsvReaderBuilder, Trim,
+    Writer as CsvWriter, WriterBuilder as CsvWriterBuilder,
+};
+use csv_core::{ReadRecordResult, ReaderBuilder as CsvCoreReaderBuilder};
 use erased_serde::Serialize as ErasedSerialize;
 use serde::{Deserialize, Serialize};
 use serde_urlencoded::Deserializer as UrlDeserializer;
 use serde_yaml::Value as YamlValue;
-use std::{borrow::Cow, mem::take, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, VecDeque},
+    mem::take,
+    sync::Arc,
+};
 use utoipa::ToSchema;
 
 pub(crate) mod deserializer;
@@ -25,8 +39,460 @@ static MAX_RECORD_LEN_IN_ERRMSG: usize = 4096;
 /// CSV format parser.
 pub struct CsvInputFormat;
 
-#[derive(Deserialize, Serialize, ToSchema)]
-pub struct CsvParserConfig {}
+const fn default_delimiter() -> char {
+    ','
+}
+
+const fn default_quote() -> char {
+    '"'
+}
+
+/// Convert a dialect character to a byte, failing if it is not ASCII.
+fn dialect_byte(name: &str, c: char) -> AnyResult<u8> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(anyhow!("'{name}' must be an ASCII character, found '{c}'"))
+    }
+}
+
+/// Converters for the formats in `option`, keyed by 0-based column
+/// position.
+fn position_converters<F, C>(
+    option: &str,
+    formats: &BTreeMap<String, F>,
+    new: impl Fn(&F) -> AnyResult<C>,
+) -> AnyResult<Vec<(usize, C)>> {
+    formats
+        .iter()
+        .map(|(position, format)| {
+            let position = position.parse::<usize>().map_err(|_| {
+                anyhow!("'{option}' keys must be 0-based column positions, found '{position}'")
+            })?;
+            Ok((position, new(format)?))
+        })
+        .collect()
+}
+
+/// Converters of columns with custom formats, keyed by 0-based column
+/// position.
+#[derive(Default)]
+struct ColumnConverters {
+    datetime: Vec<(usize, DateTimeConverter)>,
+    decimal: Vec<(usize, DecimalConverter)>,
+}
+
+impl ColumnConverters {
+    fn new(
+        datetime_formats: &BTreeMap<String, DateTimeFormat>,
+        decimal_formats: &BTreeMap<String, DecimalFormat>,
+    ) -> AnyResult<Self> {
+        Ok(Self {
+            datetime: position_converters(
+                "datetime_formats",
+                datetime_formats,
+                DateTimeConverter::new,
+            )?,
+            decimal: position_converters(
+                "decimal_formats",
+                decimal_formats,
+                DecimalConverter::new,
+            )?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.datetime.is_empty() && self.decimal.is_empty()
+    }
+}
+
+/// CSV parser configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct CsvParserConfig {
+    /// Field delimiter.  The default is `,`.
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+
+    /// Quote character.  The default is `"`.
+    #[serde(default = "default_quote")]
+    pub quote: char,
+
+    /// Escape character for quotes inside quoted fields, e.g., `\`.
+    ///
+    /// When not specified, quotes inside quoted fields are escaped by
+    /// doubling them (`""`).
+    #[serde(default)]
+    pub escape: Option<char>,
+
+    /// The first record of the input stream is a header row.
+    ///
+    /// The header row is skipped.  Columns are always matched to table
+    /// columns by position.
+    #[serde(default)]
+    pub headers: bool,
+
+    /// Field value that represents SQL `NULL`, e.g., `\N`.
+    ///
+    /// Fields equal to this value are treated as empty fields, which are
+    /// parsed as `NULL` values for nullable columns.
+    #[serde(default)]
+    pub null_value: Option<String>,
+
+    /// Trim leading and trailing whitespace from fields.
+    #[serde(default)]
+    pub trim: bool,
+
+    /// Replace fields that fail to parse with `NULL` (or the default value of
+    /// the column type for `NOT NULL` columns) instead of rejecting the
+    /// record.
+    ///
+    /// Each substitution is still reported as a parse error.
+    #[serde(default)]
+    pub lenient: bool,
+
+    /// Custom formats of `DATE`, `TIME`, and `TIMESTAMP` columns, keyed by
+    /// the 0-based position of the column.
+    ///
+    /// Values that don't match the custom format are parsed using the
+    /// default ISO format.
+    #[serde(default)]
+    pub datetime_formats: BTreeMap<String, DateTimeFormat>,
+
+    /// Encodings of `DECIMAL` columns, keyed by the 0-based position of the
+    /// column.
+    ///
+    /// Only the `scaled_integer` encoding differs from the default
+    /// encoding in CSV.  Values that cannot be converted are passed to the
+    /// deserializer unmodified.
+    #[serde(default)]
+    pub decimal_formats: BTreeMap<String, DecimalFormat>,
+
+    /// Encoding of `BINARY` and `VARBINARY` values.  The default is
+    /// `base64`.
+    #[serde(default)]
+    pub binary_encoding: BinaryEncoding,
+}
+
+impl Default for CsvParserConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: default_delimiter(),
+            quote: default_quote(),
+            escape: None,
+            headers: false,
+            null_value: None,
+            trim: false,
+            lenient: false,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
+            binary_encoding: BinaryEncoding::default(),
+        }
+    }
+}
+
+impl CsvParserConfig {
+    fn validate(&self) -> AnyResult<()> {
+        dialect_byte("delimiter", self.delimiter)?;
+        dialect_byte("quote", self.quote)?;
+        if let Some(escape) = self.escape {
+            dialect_byte("escape", escape)?;
+        }
+        ColumnConverters::new(&self.datetime_formats, &self.decimal_formats)?;
+        Ok(())
+    }
+
+    /// True if records must be converted to the default dialect before they
+    /// can be deserialized.
+    fn needs_transcoding(&self) -> bool {
+        self.delimiter != default_delimiter()
+            || self.quote != default_quote()
+            || self.escape.is_some()
+            || self.null_value.is_some()
+            || self.trim
+            || !self.datetime_formats.is_empty()
+            || !self.decimal_formats.is_empty()
+    }
+
+    /// Reader that splits the input stream into records.
+    fn record_splitter(&self) -> csv_core::Reader {
+        CsvCoreReaderBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .quote(self.quote as u8)
+            .escape(self.escape.map(|escape| escape as u8))
+            .double_quote(self.escape.is_none())
+            .build()
+    }
+
+    /// Transcoder from this dialect to the default dialect.
+    fn transcoder(&self) -> CsvTranscoder {
+        let mut reader = CsvReaderBuilder::new();
+        reader
+            .delimiter(self.delimiter as u8)
+            .quote(self.quote as u8)
+            .escape(self.escape.map(|escape| escape as u8))
+            .double_quote(self.escape.is_none())
+            .trim(if self.trim { Trim::Fields } else { Trim::None });
+
+        CsvTranscoder::new(
+            reader,
+            CsvWriterBuilder::new(),
+            self.null_value
+                .as_ref()
+                .map(|null| null.as_bytes().to_vec()),
+            // Formats are checked by `validate`.
+            ColumnConverters::new(&self.datetime_formats, &self.decimal_formats)
+                .unwrap_or_default(),
+            Conversion::Parse,
+        )
+    }
+}
+
+/// Error deserializing event number `event_number`.
+///
+/// `raw_record` is the record as it appears in the input.
+fn record_error<E: ToString>(error: E, event_number: u64, raw_record: &[u8]) -> ParseError {
+    ParseError::text_event_error(
+        "failed to deserialize CSV record",
+        error,
+        event_number,
+        Some(
+            &std::str::from_utf8(raw_record)
+                .map(|s| s.to_string())
+                .unwrap_or_else(|_| format!("{raw_record:?}")),
+        ),
+        None,
+    )
+}
+
+/// Push `record` in the default CSV dialect to `input_stream`.
+///
+/// `raw_record` is the record as it appears in the input and
+/// `event_number` is its event number, used in error messages.  Returns `true` if the record was ingested along with the errors
+/// encountered while parsing it.  In lenient mode, fields that fail to
+/// parse are replaced with `NULL` or the default value of the column type
+/// and the record is ingested; each substitution is still reported as an
+/// error.
+pub(crate) fn insert_record(
+    input_stream: &mut dyn DeCollectionStream,
+    record: &[u8],
+    raw_record: &[u8],
+    event_number: u64,
+    lenient: bool,
+) -> (bool, Vec<ParseError>) {
+    let mut errors = Vec::new();
+    let mut record = Cow::Borrowed(record);
+
+    loop {
+        let e = match input_stream.insert(&record) {
+            Ok(()) => return (true, errors),
+            Err(e) => e,
+        };
+        let field_error = FieldParseError::from_error_str(&e.to_string());
+        let mut error = record_error(e, event_number, raw_record);
+
+        // Locate the value of the field that failed to parse.
+        let Some((position, expected_type, fields)) = field_error.and_then(|field_error| {
+            let fields = split_record(&record)?;
+            let position = field_error
+                .position
+                .filter(|position| *position < fields.len())?;
+            Some((position, field_error.expected_type, fields))
+        }) else {
+            errors.push(error);
+            return (false, errors);
+        };
+        error.set_field_value(
+            String::from_utf8_lossy(&fields[position]).into_owned(),
+            expected_type.clone(),
+        );
+
+        // Each retry replaces a field, so the number of retries is bounded
+        // by the number of fields in the record.
+        let substitute = if lenient && errors.len() < fields.len() {
+            expected_type.as_deref().and_then(lenient_value)
+        } else {
+            None
+        };
+        let Some(substitute) = substitute else {
+            errors.push(error);
+            return (false, errors);
+        };
+        error.set_suggestion(Cow::from(if substitute.is_empty() {
+            "Lenient mode: the invalid value was replaced with NULL".to_string()
+        } else {
+            format!("Lenient mode: the invalid value was replaced with '{substitute}'")
+        }));
+        errors.push(error);
+
+        let new_fields = fields.iter().enumerate().map(|(i, field)| {
+            if i == position {
+                substitute.as_bytes()
+            } else {
+                field
+            }
+        });
+        match join_record(new_fields) {
+            Some(new_record) => record = Cow::Owned(new_record),
+            None => return (false, errors),
+        }
+    }
+}
+
+/// Split a record in the default CSV dialect into fields.
+fn split_record(record: &[u8]) -> Option<ByteRecord> {
+    let mut fields = ByteRecord::new();
+    CsvReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(record)
+        .read_byte_record(&mut fields)
+        .ok()?;
+    Some(fields)
+}
+
+/// Encode `fields` as a record in the default CSV dialect.
+pub(crate) fn join_record<'a>(fields: impl IntoIterator<Item = &'a [u8]>) -> Option<Vec<u8>> {
+    let mut writer = CsvWriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(Vec::new());
+    writer.write_record(fields).ok()?;
+    writer.into_inner().ok()
+}
+
+/// Value substituted in lenient mode for a field of SQL type
+/// `expected_type` that fails to parse: an empty field (`NULL`) for nullable
+/// columns, the default value of the type for `NOT NULL` columns, or `None`
+/// if the type has no default value.
+fn lenient_value(expected_type: &str) -> Option<&'static str> {
+    match expected_type.strip_suffix(" NOT NULL") {
+        None => Some(""),
+        Some("TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT" | "REAL" | "DOUBLE" | "DECIMAL") => {
+            Some("0")
+        }
+        Some("BOOLEAN") => Some("false"),
+        Some("VARCHAR") => Some(""),
+        Some(_) => None,
+    }
+}
+
+/// Direction of column conversions performed by [`CsvTranscoder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Conversion {
+    /// From a custom format to the canonical format.
+    Parse,
+    /// From the canonical format to a custom format.
+    Format,
+}
+
+/// Converts individual CSV records from one dialect to another.
+struct CsvTranscoder {
+    reader: CsvRecordReader<VecDeque<u8>>,
+    writer: CsvWriter<Vec<u8>>,
+    record: ByteRecord,
+
+    /// Input fields equal to this value are written as empty fields.
+    null_value: Option<Vec<u8>>,
+
+    /// Converters for individual columns.
+    converters: ColumnConverters,
+    conversion: Conversion,
+}
+
+impl CsvTranscoder {
+    fn new(
+        mut reader: CsvReaderBuilder,
+        mut writer: CsvWriterBuilder,
+        null_value: Option<Vec<u8>>,
+        converters: ColumnConverters,
+        conversion: Conversion,
+    ) -> Self {
+        Self {
+            reader: reader
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(VecDeque::new()),
+            writer: writer
+                .has_headers(false)
+                .flexible(true)
+                .from_writer(Vec::new()),
+            record: ByteRecord::new(),
+            null_value,
+            converters,
+            conversion,
+        }
+    }
+
+    /// Convert the value of the field at `position` if it has a custom
+    /// date/time format or decimal encoding.
+    fn convert_field<'a>(&self, position: usize, field: &'a [u8]) -> AnyResult<Cow<'a, [u8]>> {
+        if field.is_empty() {
+            return Ok(Cow::Borrowed(field));
+        }
+        let datetime = self
+            .converters
+            .datetime
+            .iter()
+            .find(|(column, _)| *column == position)
+            .map(|(_, converter)| converter);
+        let decimal = self
+            .converters
+            .decimal
+            .iter()
+            .find(|(column, _)| *column == position)
+            .map(|(_, converter)| converter);
+
+        let convert = |value: &str| match self.conversion {
+            Conversion::Parse => datetime
+                .map(|converter| converter.parse(value))
+                .or_else(|| decimal.map(|converter| converter.parse(value))),
+            Conversion::Format => datetime
+                .map(|converter| converter.format(value))
+                .or_else(|| decimal.map(|converter| converter.format(value))),
+        };
+
+        match self.conversion {
+            // Values that don't match the custom format are passed through,
+            // so they can be parsed using the default format or reported by
+            // the deserializer.
+            Conversion::Parse => Ok(std::str::from_utf8(field)
+                .ok()
+                .and_then(|value| convert(value)?.ok())
+                .map_or(Cow::Borrowed(field), |value| Cow::Owned(value.into_bytes()))),
+            Conversion::Format => match convert(std::str::from_utf8(field)?) {
+                None => Ok(Cow::Borrowed(field)),
+                Some(value) => Ok(Cow::Owned(value?.into_bytes())),
+            },
+        }
+    }
+
+    /// Convert a single CSV record.
+    ///
+    /// The transcoder accumulates output records in memory, so it should
+    /// only be used to convert a bounded amount of data.
+    fn transcode(&mut self, data: &[u8]) -> AnyResult<&[u8]> {
+        self.reader.get_mut().extend(data.iter());
+        self.reader.read_byte_record(&mut self.record)?;
+
+        let start = self.writer.get_ref().len();
+        if self.null_value.is_none() && self.converters.is_empty() {
+            self.writer.write_byte_record(&self.record)?;
+        } else {
+            let mut fields = Vec::with_capacity(self.record.len());
+            for (position, field) in self.record.iter().enumerate() {
+                let field = match &self.null_value {
+                    Some(null_value) if field == null_value.as_slice() => &[][..],
+                    _ => field,
+                };
+                fields.push(self.convert_field(position, field)?);
+            }
+            self.writer.write_record(fields)?;
+        }
+        self.writer.flush()?;
+
+        Ok(&self.writer.get_ref()[start..])
+    }
+}
 
 impl InputFormat for CsvInputFormat {
     fn name(&self) -> Cow<'static, str> {
@@ -38,20 +504,41 @@ impl InputFormat for CsvInputFormat {
     // HTTP query, but a specialized method gives us more flexibility.
     fn config_from_http_request(
         &self,
-        _endpoint_name: &str,
-        _request: &HttpRequest,
+        endpoint_name: &str,
+        request: &HttpRequest,
     ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
-        Ok(Box::new(CsvParserConfig {}))
+        Ok(Box::new(
+            CsvParserConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::parser_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
     }
 
     fn new_parser(
         &self,
-        _endpoint_name: &str,
+        endpoint_name: &str,
         input_stream: &dyn DeCollectionHandle,
-        _config: &YamlValue,
+        config: &YamlValue,
     ) -> Result<Box<dyn Parser>, ControllerError> {
-        let input_stream = input_stream.configure_deserializer(RecordFormat::Csv)?;
-        Ok(Box::new(CsvParser::new(input_stream)) as Box<dyn Parser>)
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
+        let config = CsvParserConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+        config.validate().map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+
+        let input_stream = input_stream.configure_deserializer(RecordFormat::Csv(CsvFlavor {
+            binary_encoding: config.binary_encoding,
+        }))?;
+        Ok(Box::new(CsvParser::new(input_stream, config)) as Box<dyn Parser>)
     }
 }
 
@@ -59,28 +546,53 @@ struct CsvParser {
     /// Input handle to push parsed data to.
     input_stream: Box<dyn DeCollectionStream>,
 
+    config: CsvParserConfig,
+
+    /// The header row of the input stream has not been received yet.
+    expect_headers: bool,
+
     /// Since we cannot assume that the input buffer ends on line end,
     /// we save the "leftover" part of the buffer after the last new-line
     /// character and prepend it to the next input buffer.
     leftover: Vec<u8>,
 
     last_event_number: u64,
+
+    /// Number of lines received via `input_fragment` and parsed so far.
+    num_lines: u64,
 }
 
 impl CsvParser {
-    fn new(input_stream: Box<dyn DeCollectionStream>) -> Self {
+    fn new(input_stream: Box<dyn DeCollectionStream>, config: CsvParserConfig) -> Self {
         Self {
             input_stream,
+            expect_headers: config.headers,
+            config,
             leftover: Vec::new(),
             last_event_number: 0,
+            num_lines: 0,
         }
     }
 
-    fn parse_from_buffer(&mut self, mut buffer: &[u8]) -> (usize, Vec<ParseError>) {
+    /// Parse CSV records in `buffer`.
+    ///
+    /// When `buffer` is part of a continuous text stream, `first_line` is the
+    /// number of lines in the stream preceding `buffer` and is used to
+    /// attribute errors to lines.
+    fn parse_from_buffer(
+        &mut self,
+        mut buffer: &[u8],
+        first_line: Option<u64>,
+    ) -> (usize, Vec<ParseError>) {
         let mut errors = Vec::new();
         let mut num_records = 0;
+        let mut line = first_line.map(|first_line| first_line + 1);
 
-        let mut csv_reader = CsvReader::new();
+        let mut csv_reader = self.config.record_splitter();
+        let mut transcoder = self
+            .config
+            .needs_transcoding()
+            .then(|| self.config.transcoder());
 
         // println!("parse_from_buffer:{}", std::str::from_utf8(buffer).unwrap());
 
@@ -104,32 +616,48 @@ impl CsvParser {
                         std::str::from_utf8(&record_buffer[0..total_bytes_read])
                             .unwrap_or("invalid utf-8")
                     );*/
-                    match self
-                        .input_stream
-                        .insert(&record_buffer[0..total_bytes_read])
-                    {
-                        Err(e) => {
-                            errors.push(ParseError::text_event_error(
-                                "failed to deserialize CSV record",
-                                e,
-                                self.last_event_number + 1,
-                                Some(
-                                    &std::str::from_utf8(&record_buffer[0..total_bytes_read])
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_else(|_| {
-                                            format!("{:?}", &record_buffer[0..total_bytes_read])
-                                        })
-                                        .to_string(),
+                    let record = &record_buffer[0..total_bytes_read];
+                    if take(&mut self.expect_headers) {
+                        // Skip the header row.
+                    } else {
+                        let (inserted, mut record_errors) = match transcoder.as_mut() {
+                            Some(transcoder) => match transcoder.transcode(record) {
+                                Ok(transcoded) => insert_record(
+                                    self.input_stream.as_mut(),
+                                    transcoded,
+                                    record,
+                                    self.last_event_number + 1,
+                                    self.config.lenient,
                                 ),
-                                None,
-                            ));
-                        }
-                        Ok(()) => {
+                                Err(e) => (
+                                    false,
+                                    vec![record_error(e, self.last_event_number + 1, record)],
+                                ),
+                            },
+                            None => insert_record(
+                                self.input_stream.as_mut(),
+                                record,
+                                record,
+                                self.last_event_number + 1,
+                                self.config.lenient,
+                            ),
+                        };
+                        if inserted {
                             num_records += 1;
                         }
+                        if let Some(line) = line {
+                            for error in record_errors.iter_mut() {
+                                error.set_line_number(line);
+                            }
+                        }
+                        errors.append(&mut record_errors);
+                        self.last_event_number += 1;
+                    }
+                    if let Some(line) = line.as_mut() {
+                        // Quoted fields may contain newlines.
+                        *line += count_newlines(&record_buffer[0..total_bytes_read]);
                     }
                     record_buffer = &buffer[bytes_read..];
-                    self.last_event_number += 1;
                     total_bytes_read = 0;
                     if result == ReadRecordResult::InputEmpty {
                         break;
@@ -167,7 +695,8 @@ impl Parser for CsvParser {
             let mut leftover_buf = take(&mut self.leftover);
             leftover_buf.extend_from_slice(&data[0..leftover]);
 
-            let res = self.parse_from_buffer(leftover_buf.as_slice());
+            let res = self.parse_from_buffer(leftover_buf.as_slice(), Some(self.num_lines));
+            self.num_lines += count_newlines(&leftover_buf);
             // println!("parse returned: {res:?}");
 
             leftover_buf.clear();
@@ -178,6 +707,12 @@ impl Parser for CsvParser {
         }
     }
 
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        // Chunks are not part of a continuous stream of text, so errors are
+        // not attributed to lines.
+        self.parse_from_buffer(data, None)
+    }
+
     fn eoi(&mut self) -> (usize, Vec<ParseError>) {
         if self.leftover.is_empty() {
             return (0, Vec::new());
@@ -185,14 +720,15 @@ impl Parser for CsvParser {
 
         // Try to interpret the leftover chunk as a complete CSV line.
         let mut leftover_buf = take(&mut self.leftover);
-        let res = self.parse_from_buffer(leftover_buf.as_slice());
+        let res = self.parse_from_buffer(leftover_buf.as_slice(), Some(self.num_lines));
+        self.num_lines += count_newlines(&leftover_buf);
         leftover_buf.clear();
         self.leftover = leftover_buf;
         res
     }
 
     fn fork(&self) -> Box<dyn Parser> {
-        Box::new(Self::new(self.input_stream.fork()))
+        Box::new(Self::new(self.input_stream.fork(), self.config.clone()))
     }
 }
 
@@ -203,10 +739,77 @@ const fn default_buffer_size_records() -> usize {
     10_000
 }
 
+/// CSV encoder configuration.
 #[derive(Deserialize, Serialize, ToSchema)]
 pub struct CsvEncoderConfig {
     #[serde(default = "default_buffer_size_records")]
     buffer_size_records: usize,
+
+    /// Field delimiter.  The default is `,`.
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+
+    /// Quote character.  The default is `"`.
+    #[serde(default = "default_quote")]
+    quote: char,
+
+    /// Escape character for quotes inside quoted fields.
+    ///
+    /// When not specified, quotes inside quoted fields are escaped by
+    /// doubling them (`""`).
+    #[serde(default)]
+    escape: Option<char>,
+
+    /// Custom formats of `DATE`, `TIME`, and `TIMESTAMP` columns, keyed by
+    /// the 0-based position of the column.
+    #[serde(default)]
+    datetime_formats: BTreeMap<String, DateTimeFormat>,
+
+    /// Encodings of `DECIMAL` columns, keyed by the 0-based position of the
+    /// column.
+    #[serde(default)]
+    decimal_formats: BTreeMap<String, DecimalFormat>,
+
+    /// Encoding of `BINARY` and `VARBINARY` values.  The default is
+    /// `base64`.
+    #[serde(default)]
+    binary_encoding: BinaryEncoding,
+}
+
+impl CsvEncoderConfig {
+    /// Transcoder from the default dialect to the configured dialect or
+    /// `None` if the configured dialect is the default one.
+    fn transcoder(&self) -> AnyResult<Option<CsvTranscoder>> {
+        let delimiter = dialect_byte("delimiter", self.delimiter)?;
+        let quote = dialect_byte("quote", self.quote)?;
+        let escape = self
+            .escape
+            .map(|escape| dialect_byte("escape", escape))
+            .transpose()?;
+        let converters = ColumnConverters::new(&self.datetime_formats, &self.decimal_formats)?;
+
+        if self.delimiter == default_delimiter()
+            && self.quote == default_quote()
+            && escape.is_none()
+            && converters.is_empty()
+        {
+            return Ok(None);
+        }
+
+        let mut writer = CsvWriterBuilder::new();
+        writer.delimiter(delimiter).quote(quote);
+        if let Some(escape) = escape {
+            writer.escape(escape).double_quote(false);
+        }
+
+        Ok(Some(CsvTranscoder::new(
+            CsvReaderBuilder::new(),
+            writer,
+            None,
+            converters,
+            Conversion::Format,
+        )))
+    }
 }
 
 impl OutputFormat for CsvOutputFormat {
@@ -239,6 +842,8 @@ impl OutputFormat for CsvOutputFormat {
         consumer: Box<dyn OutputConsumer>,
     ) -> AnyResult<Box<dyn Encoder>> {
         let config = CsvEncoderConfig::deserialize(config)?;
+        // Validate the dialect.
+        config.transcoder()?;
 
         Ok(Box::new(CsvEncoder::new(consumer, config)))
     }
@@ -275,15 +880,25 @@ impl Encoder for CsvEncoder {
         let mut buffer = take(&mut self.buffer);
         //let mut writer = self.builder.from_writer(buffer);
         let mut num_records = 0;
+        let mut transcoder = self.config.transcoder()?;
+        let mut record = Vec::new();
 
         for batch in batches.iter() {
-            let mut cursor = batch.cursor(RecordFormat::Csv)?;
+            let mut cursor = batch.cursor(RecordFormat::Csv(CsvFlavor {
+                binary_encoding: self.config.binary_encoding,
+            }))?;
 
             while cursor.key_valid() {
                 let prev_len = buffer.len();
 
                 // `serialize_key_weight`
-                cursor.serialize_key_weight(&mut buffer)?;
+                if let Some(transcoder) = transcoder.as_mut() {
+                    record.clear();
+                    cursor.serialize_key_weight(&mut record)?;
+                    buffer.extend_from_slice(transcoder.transcode(&record)?);
+                } else {
+                    cursor.serialize_key_weight(&mut buffer)?;
+                }
 
                 // Drop the last encoded record if it exceeds max_buffer_size.
                 // The record will be included in the next buffer.
@@ -330,3 +945,468 @@ impl Encoder for CsvEncoder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CsvEncoder, CsvEncoderConfig, CsvParserConfig};
+    use crate::{
+        catalog::SerBatch,
+        deserialize_table_record,
+        format::Encoder,
+        format::{BinaryEncoding, DateTimeFormat, DecimalEncoding, DecimalFormat},
+        static_compile::seroutput::SerBatchImpl,
+        test::{mock_parser_pipeline, MockOutputConsumer, TestStruct},
+        transport::InputConsumer,
+        FormatConfig, ParseError,
+    };
+    use bstr::BString;
+    use dbsp::{trace::Batch, OrdZSet};
+    use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Record {
+        id: i64,
+        b: Option<bool>,
+    }
+
+    deserialize_table_record!(Record["Record", 2] {
+        (id, "ID", false, i64, None),
+        (b, "B", false, Option<bool>, Some(None))
+    });
+
+    /// Parse `data` and return parse errors and the contents of the table.
+    fn parse_records(
+        config: CsvParserConfig,
+        data: &str,
+    ) -> (Vec<ParseError>, Vec<(Record, bool)>) {
+        let format_config = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(config).unwrap(),
+        };
+
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+        let mut errors = consumer.input_fragment(data.as_bytes());
+        errors.extend(consumer.eoi());
+        let records = outputs.state().flushed.drain(..).collect();
+        (errors, records)
+    }
+
+    /// Expected error for a field that failed to parse.
+    fn field_error(
+        event_number: u64,
+        field: &str,
+        description: &str,
+        record: &str,
+        (value, expected_type): (&str, &str),
+        suggestion: Option<&'static str>,
+    ) -> ParseError {
+        let mut error = ParseError::new(
+            format!(
+                "failed to deserialize CSV record: error parsing field '{field}': {description}"
+            ),
+            Some(event_number),
+            Some(field.to_string()),
+            Some(record),
+            None,
+            suggestion.map(Cow::from),
+        );
+        error.set_line_number(event_number);
+        error.set_field_value(value.to_string(), Some(expected_type.to_string()));
+        error
+    }
+
+    #[test]
+    fn test_csv_dialect() {
+        let config = CsvParserConfig {
+            delimiter: ';',
+            escape: Some('\\'),
+            headers: true,
+            null_value: Some("\\N".to_string()),
+            trim: true,
+            ..Default::default()
+        };
+        let format_config = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(config).unwrap(),
+        };
+
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+        assert!(consumer
+            .input_fragment(b"id;b;i;s\n1; true ;\\N;")
+            .is_empty());
+        assert!(consumer
+            .input_fragment(b"\"foo\\\"bar\"\n2;false;5;a,b\n")
+            .is_empty());
+        assert!(consumer.eoi().is_empty());
+
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    TestStruct {
+                        id: 1,
+                        b: true,
+                        i: None,
+                        s: "foo\"bar".to_string()
+                    },
+                    true
+                ),
+                (
+                    TestStruct {
+                        id: 2,
+                        b: false,
+                        i: Some(5),
+                        s: "a,b".to_string()
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_coercion_errors() {
+        let data = "1,true\nx,false\n2,maybe\n";
+
+        let (errors, records) = parse_records(CsvParserConfig::default(), data);
+        assert_eq!(
+            errors,
+            vec![
+                field_error(
+                    2,
+                    "ID",
+                    "field 0: invalid digit found in string",
+                    "x,false\n",
+                    ("x", "BIGINT NOT NULL"),
+                    None
+                ),
+                field_error(
+                    3,
+                    "B",
+                    "field 1: provided string was not `true` or `false`",
+                    "2,maybe\n",
+                    ("maybe", "BOOLEAN"),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            records,
+            vec![(
+                Record {
+                    id: 1,
+                    b: Some(true)
+                },
+                true
+            )]
+        );
+
+        // Lenient mode: substitute default values for invalid fields.
+        let (errors, records) = parse_records(
+            CsvParserConfig {
+                lenient: true,
+                ..Default::default()
+            },
+            data,
+        );
+        assert_eq!(
+            errors,
+            vec![
+                field_error(
+                    2,
+                    "ID",
+                    "field 0: invalid digit found in string",
+                    "x,false\n",
+                    ("x", "BIGINT NOT NULL"),
+                    Some("Lenient mode: the invalid value was replaced with '0'")
+                ),
+                field_error(
+                    3,
+                    "B",
+                    "field 1: provided string was not `true` or `false`",
+                    "2,maybe\n",
+                    ("maybe", "BOOLEAN"),
+                    Some("Lenient mode: the invalid value was replaced with NULL")
+                ),
+            ]
+        );
+        assert_eq!(
+            records,
+            vec![
+                (
+                    Record {
+                        id: 1,
+                        b: Some(true)
+                    },
+                    true
+                ),
+                (
+                    Record {
+                        id: 0,
+                        b: Some(false)
+                    },
+                    true
+                ),
+                (Record { id: 2, b: None }, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_encoder_dialect() {
+        let config = CsvEncoderConfig {
+            buffer_size_records: 10,
+            delimiter: ';',
+            quote: '\'',
+            escape: None,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
+            binary_encoding: BinaryEncoding::default(),
+        };
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = CsvEncoder::new(Box::new(consumer), config);
+
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "a;b".to_string(),
+                },
+                1,
+            )],
+        );
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&data.lock().unwrap()).unwrap(),
+            "1;true;;'a;b';1\n"
+        );
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Event {
+        id: i64,
+        ts: Option<String>,
+    }
+
+    deserialize_table_record!(Event["Event", 2] {
+        (id, "ID", false, i64, None),
+        (ts, "TS", false, Option<String>, Some(None))
+    });
+
+    #[test]
+    fn test_csv_datetime_formats() {
+        let datetime_formats = BTreeMap::from([(
+            "1".to_string(),
+            DateTimeFormat {
+                format: "%d/%m/%Y %H:%M".to_string(),
+                timezone: Some("+02:00".to_string()),
+            },
+        )]);
+
+        let format_config = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(CsvParserConfig {
+                datetime_formats: datetime_formats.clone(),
+                ..Default::default()
+            })
+            .unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        // Values that don't match the format are passed through unmodified.
+        assert!(consumer
+            .input_fragment(b"1,31/12/2023 01:30\n2,2023-01-01 00:00:00\n3,\n")
+            .is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    Event {
+                        id: 1,
+                        ts: Some("2023-12-30 23:30:00".to_string())
+                    },
+                    true
+                ),
+                (
+                    Event {
+                        id: 2,
+                        ts: Some("2023-01-01 00:00:00".to_string())
+                    },
+                    true
+                ),
+                (Event { id: 3, ts: None }, true),
+            ]
+        );
+
+        let config = CsvEncoderConfig {
+            buffer_size_records: 10,
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            // Column `s` of `TestStruct`.
+            datetime_formats: BTreeMap::from([("3".to_string(), datetime_formats["1"].clone())]),
+            decimal_formats: BTreeMap::new(),
+            binary_encoding: BinaryEncoding::default(),
+        };
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = CsvEncoder::new(Box::new(consumer), config);
+
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "2023-12-30 23:30:00".to_string(),
+                },
+                1,
+            )],
+        );
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&data.lock().unwrap()).unwrap(),
+            "1,true,,31/12/2023 01:30,1\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_decimal_formats() {
+        let decimal_formats = BTreeMap::from([(
+            "1".to_string(),
+            DecimalFormat {
+                encoding: DecimalEncoding::ScaledInteger,
+                scale: 2,
+            },
+        )]);
+
+        let format_config = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(CsvParserConfig {
+                decimal_formats: decimal_formats.clone(),
+                ..Default::default()
+            })
+            .unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        assert!(consumer.input_fragment(b"1,1230\n2,-5\n3,\n").is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    Event {
+                        id: 1,
+                        ts: Some("12.30".to_string())
+                    },
+                    true
+                ),
+                (
+                    Event {
+                        id: 2,
+                        ts: Some("-0.05".to_string())
+                    },
+                    true
+                ),
+                (Event { id: 3, ts: None }, true),
+            ]
+        );
+
+        let config = CsvEncoderConfig {
+            buffer_size_records: 10,
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            datetime_formats: BTreeMap::new(),
+            // Column `s` of `TestStruct`.
+            decimal_formats: BTreeMap::from([("3".to_string(), decimal_formats["1"].clone())]),
+            binary_encoding: BinaryEncoding::default(),
+        };
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = CsvEncoder::new(Box::new(consumer), config);
+
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "12345678901234567.8".to_string(),
+                },
+                1,
+            )],
+        );
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&data.lock().unwrap()).unwrap(),
+            "1,true,,1234567890123456780,1\n"
+        );
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Blob {
+        id: i64,
+        data: Option<BString>,
+    }
+
+    deserialize_table_record!(Blob["Blob", 2] {
+        (id, "ID", false, i64, None),
+        (data, "DATA", false, Option<BString>, Some(None))
+    });
+
+    #[test]
+    fn test_csv_binary_encoding() {
+        let format_config = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::to_value(CsvParserConfig {
+                binary_encoding: BinaryEncoding::Hex,
+                ..Default::default()
+            })
+            .unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        assert!(consumer
+            .input_fragment(b"1,deadbeef\n2,\\x0A\n3,\n")
+            .is_empty());
+        assert_eq!(consumer.input_fragment(b"4,xyz\n").len(), 1);
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    Blob {
+                        id: 1,
+                        data: Some(BString::from(vec![0xde, 0xad, 0xbe, 0xef]))
+                    },
+                    true
+                ),
+                (
+                    Blob {
+                        id: 2,
+                        data: Some(BString::from(vec![0x0a]))
+                    },
+                    true
+                ),
+                (Blob { id: 3, data: None }, true),
+            ]
+        );
+    }
+}
@@ -37,6 +37,8 @@ use serde::serde_if_integer128;
 use csv::StringRecordIter;
 use csv::{ByteRecord, ByteRecordIter, StringRecord};
 
+use crate::format::BinaryEncoding;
+
 use self::DeserializeErrorKind as DEK;
 
 /// Create a deserializer for a single CSV record stored as a UTF-8 string.
@@ -48,6 +50,7 @@ pub fn string_record_deserializer<'de>(
         it: record.iter().peekable(),
         headers: headers.map(|r| r.iter()),
         field: 0,
+        binary_encoding: None,
     })
 }
 
@@ -60,6 +63,7 @@ pub fn byte_record_deserializer<'de>(
         it: record.iter().peekable(),
         headers: headers.map(|r| r.iter()),
         field: 0,
+        binary_encoding: None,
     })
 }
 
@@ -101,6 +105,9 @@ trait DeRecord<'r> {
     /// Returns an error corresponding to the most recently extracted field.
     fn error(&self, kind: DeserializeErrorKind) -> DeserializeError;
 
+    /// Text encoding of binary fields or `None` to pass raw field bytes.
+    fn binary_encoding(&self) -> Option<BinaryEncoding>;
+
     /// Infer the type of the next field and deserialize it.
     fn infer_deserialize<'de, V: Visitor<'de>>(
         &mut self,
@@ -116,6 +123,15 @@ pub type StringRecordDeserializer<'r> = DeRecordWrap<DeStringRecord<'r>>;
 /// Deserializer for a single CSV record stored as raw bytes.
 pub type ByteRecordDeserializer<'r> = DeRecordWrap<DeByteRecord<'r>>;
 
+impl<'r> ByteRecordDeserializer<'r> {
+    /// Decode binary fields from text using `encoding` instead of passing
+    /// the raw bytes of the field to the visitor.
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.0.binary_encoding = Some(encoding);
+        self
+    }
+}
+
 impl<'r, T: DeRecord<'r>> DeRecord<'r> for DeRecordWrap<T> {
     #[inline]
     fn has_headers(&self) -> bool {
@@ -152,6 +168,11 @@ impl<'r, T: DeRecord<'r>> DeRecord<'r> for DeRecordWrap<T> {
         self.0.error(kind)
     }
 
+    #[inline]
+    fn binary_encoding(&self) -> Option<BinaryEncoding> {
+        self.0.binary_encoding()
+    }
+
     #[inline]
     fn infer_deserialize<'de, V: Visitor<'de>>(
         &mut self,
@@ -165,6 +186,7 @@ pub struct DeStringRecord<'r> {
     it: iter::Peekable<StringRecordIter<'r>>,
     headers: Option<StringRecordIter<'r>>,
     field: u64,
+    binary_encoding: Option<BinaryEncoding>,
 }
 
 impl<'r> DeRecord<'r> for DeStringRecord<'r> {
@@ -214,6 +236,11 @@ impl<'r> DeRecord<'r> for DeStringRecord<'r> {
         }
     }
 
+    #[inline]
+    fn binary_encoding(&self) -> Option<BinaryEncoding> {
+        self.binary_encoding
+    }
+
     fn infer_deserialize<'de, V: Visitor<'de>>(
         &mut self,
         visitor: V,
@@ -247,6 +274,7 @@ pub struct DeByteRecord<'r> {
     it: iter::Peekable<ByteRecordIter<'r>>,
     headers: Option<ByteRecordIter<'r>>,
     field: u64,
+    binary_encoding: Option<BinaryEncoding>,
 }
 
 impl<'r> DeRecord<'r> for DeByteRecord<'r> {
@@ -304,6 +332,11 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
         }
     }
 
+    #[inline]
+    fn binary_encoding(&self) -> Option<BinaryEncoding> {
+        self.binary_encoding
+    }
+
     fn infer_deserialize<'de, V: Visitor<'de>>(
         &mut self,
         visitor: V,
@@ -417,13 +450,27 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.next_field_bytes()
-            .and_then(|f| visitor.visit_borrowed_bytes(f))
+        let field = self.next_field_bytes()?;
+        match self.binary_encoding() {
+            None => visitor.visit_borrowed_bytes(field),
+            Some(encoding) => visitor.visit_byte_buf(
+                encoding
+                    .decode(field)
+                    .map_err(|err| self.error(DEK::Message(err.to_string())))?,
+            ),
+        }
     }
 
     fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.next_field_bytes()
-            .and_then(|f| visitor.visit_byte_buf(f.to_vec()))
+        let field = self.next_field_bytes()?;
+        match self.binary_encoding() {
+            None => visitor.visit_byte_buf(field.to_vec()),
+            Some(encoding) => visitor.visit_byte_buf(
+                encoding
+                    .decode(field)
+                    .map_err(|err| self.error(DEK::Message(err.to_string())))?,
+            ),
+        }
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
@@ -0,0 +1,218 @@
+//! Custom date and time formats for text-based data formats.
+//!
+//! SQL `DATE`, `TIME`, and `TIMESTAMP` values are serialized in a fixed
+//! ISO-like format.  Parsers and encoders that support per-column formats
+//! convert values between the user-specified format and the canonical one.
+
+use anyhow::{anyhow, Result as AnyResult};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use utoipa::ToSchema;
+
+/// Canonical format of `TIMESTAMP` values.
+const TIMESTAMP_FORMAT: &str = "%F %T%.f";
+
+/// Canonical format of `DATE` values.
+const DATE_FORMAT: &str = "%F";
+
+/// Canonical format of `TIME` values.
+const TIME_FORMAT: &str = "%H:%M:%S%.f";
+
+/// Format of a `DATE`, `TIME`, or `TIMESTAMP` column.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct DateTimeFormat {
+    /// `strftime`-style format string, e.g., `%d/%m/%Y %H:%M`.
+    ///
+    /// See <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
+    /// for the supported specifiers.
+    pub format: String,
+
+    /// Time zone of timestamps that don't specify a UTC offset, as a fixed
+    /// offset from UTC, e.g., `+05:30`.
+    ///
+    /// Timestamps are converted to UTC on input and from UTC on output.
+    /// The default is UTC.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// Parse a UTC offset of the form `Z`, `UTC`, `+HH`, `+HH:MM`, or `+HHMM`.
+fn parse_offset(offset: &str) -> AnyResult<FixedOffset> {
+    let error = || {
+        anyhow!(
+            "invalid time zone '{offset}': expected 'UTC' or an offset from UTC, such as '+05:30'"
+        )
+    };
+
+    if offset.eq_ignore_ascii_case("utc") || offset == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let (sign, rest) = match offset.as_bytes().first() {
+        Some(b'+') => (1, &offset[1..]),
+        Some(b'-') => (-1, &offset[1..]),
+        _ => return Err(error()),
+    };
+    let digits = rest.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(error());
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>()?, 0),
+        4 => (digits[..2].parse::<i32>()?, digits[2..].parse::<i32>()?),
+        _ => return Err(error()),
+    };
+    if minutes >= 60 {
+        return Err(error());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(error)
+}
+
+/// True if `format` contains a UTC offset specifier.
+fn has_offset(format: &str) -> bool {
+    ["%z", "%:z", "%::z", "%#z"]
+        .iter()
+        .any(|specifier| format.contains(specifier))
+}
+
+/// Converts the values of a column between a custom format and the
+/// canonical format.
+#[derive(Clone, Debug)]
+pub(crate) struct DateTimeConverter {
+    format: String,
+    offset: FixedOffset,
+}
+
+impl DateTimeConverter {
+    pub(crate) fn new(config: &DateTimeFormat) -> AnyResult<Self> {
+        Ok(Self {
+            format: config.format.clone(),
+            offset: config
+                .timezone
+                .as_deref()
+                .map(parse_offset)
+                .transpose()?
+                .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap()),
+        })
+    }
+
+    /// Convert `value` in the custom format to the canonical format.
+    ///
+    /// The type of the value is determined by the fields present in the
+    /// format: a format that contains both date and time fields produces a
+    /// timestamp, a format with only date or time fields produces a date or a
+    /// time respectively.
+    pub(crate) fn parse(&self, value: &str) -> AnyResult<String> {
+        let value = value.trim();
+
+        let timestamp = if has_offset(&self.format) {
+            DateTime::parse_from_str(value, &self.format).map(|timestamp| timestamp.naive_utc())
+        } else {
+            NaiveDateTime::parse_from_str(value, &self.format).map(|timestamp| {
+                timestamp - chrono::Duration::seconds(self.offset.local_minus_utc() as i64)
+            })
+        };
+        let error = match timestamp {
+            Ok(timestamp) => return Ok(timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            Err(e) => e,
+        };
+        if let Ok(date) = NaiveDate::parse_from_str(value, &self.format) {
+            return Ok(date.format(DATE_FORMAT).to_string());
+        }
+        if let Ok(time) = NaiveTime::parse_from_str(value, &self.format) {
+            return Ok(time.format(TIME_FORMAT).to_string());
+        }
+
+        Err(anyhow!(
+            "'{value}' does not match the date/time format '{}': {error}",
+            self.format
+        ))
+    }
+
+    /// Convert `value` in the canonical format to the custom format.
+    pub(crate) fn format(&self, value: &str) -> AnyResult<String> {
+        let mut result = String::new();
+
+        let written = if let Ok(timestamp) = NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT)
+        {
+            write!(
+                result,
+                "{}",
+                self.offset
+                    .from_utc_datetime(&timestamp)
+                    .format(&self.format)
+            )
+        } else if let Ok(date) = NaiveDate::parse_from_str(value, DATE_FORMAT) {
+            write!(result, "{}", date.format(&self.format))
+        } else if let Ok(time) = NaiveTime::parse_from_str(value, TIME_FORMAT) {
+            write!(result, "{}", time.format(&self.format))
+        } else {
+            return Err(anyhow!("'{value}' is not a valid date, time, or timestamp"));
+        };
+
+        // Formatting fails if the format contains fields that are not
+        // present in the value, e.g., time fields for a date.
+        written.map_err(|_| {
+            anyhow!(
+                "unable to format '{value}' using the date/time format '{}'",
+                self.format
+            )
+        })?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DateTimeConverter, DateTimeFormat};
+
+    fn converter(format: &str, timezone: Option<&str>) -> DateTimeConverter {
+        DateTimeConverter::new(&DateTimeFormat {
+            format: format.to_string(),
+            timezone: timezone.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_datetime_format() {
+        let timestamp = converter("%d/%m/%Y %H:%M", Some("+02:00"));
+        assert_eq!(
+            timestamp.parse("31/12/2023 01:30").unwrap(),
+            "2023-12-30 23:30:00"
+        );
+        assert_eq!(
+            timestamp.format("2023-12-30 23:30:00").unwrap(),
+            "31/12/2023 01:30"
+        );
+        assert!(timestamp.parse("2023-12-30 23:30:00").is_err());
+
+        let with_offset = converter("%Y-%m-%dT%H:%M:%S%:z", None);
+        assert_eq!(
+            with_offset.parse("2023-12-31T01:30:00-01:00").unwrap(),
+            "2023-12-31 02:30:00"
+        );
+
+        let epoch = converter("%s", None);
+        assert_eq!(epoch.parse("1700000000").unwrap(), "2023-11-14 22:13:20");
+
+        let date = converter("%m/%d/%Y", None);
+        assert_eq!(date.parse("02/29/2024").unwrap(), "2024-02-29");
+        assert_eq!(date.format("2024-02-29").unwrap(), "02/29/2024");
+
+        let time = converter("%I:%M %p", None);
+        assert_eq!(time.parse("01:15 PM").unwrap(), "13:15:00");
+        assert_eq!(time.format("13:15:00").unwrap(), "01:15 PM");
+
+        // Time fields cannot be formatted for a date.
+        assert!(timestamp.format("2024-02-29").is_err());
+
+        assert!(DateTimeConverter::new(&DateTimeFormat {
+            format: "%F".to_string(),
+            timezone: Some("CET".to_string()),
+        })
+        .is_err());
+    }
+}
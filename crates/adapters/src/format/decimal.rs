@@ -0,0 +1,204 @@
+//! Custom encodings of `DECIMAL` values for text-based data formats.
+//!
+//! SQL `DECIMAL` values are serialized as strings that contain the exact
+//! decimal representation of the value, e.g., `"12.30"`.  Parsers and
+//! encoders that support per-column encodings convert values between the
+//! user-specified encoding and the canonical one without going through
+//! binary floating point, so no precision is lost.
+
+use anyhow::{anyhow, bail, Result as AnyResult};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use utoipa::ToSchema;
+
+/// The largest scale of a `DECIMAL` value.
+const MAX_SCALE: u32 = 28;
+
+/// Encoding of the values of a `DECIMAL` column.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalEncoding {
+    /// Decimal string, e.g., `"12.30"`.
+    ///
+    /// In JSON, values are encoded as strings.  This is the default encoding
+    /// of `DECIMAL` values.
+    #[default]
+    String,
+
+    /// Decimal number, e.g., `12.30`.
+    ///
+    /// In JSON, values are encoded as numbers.  The parser reads the exact
+    /// digits of the number rather than converting it to a floating point
+    /// value.
+    Number,
+
+    /// Integer number of units of `10^-scale`, e.g., `1230` for `12.30` with
+    /// `scale` 2.
+    ScaledInteger,
+}
+
+/// Format of a `DECIMAL` column.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct DecimalFormat {
+    /// Encoding of the values of the column.  The default is `string`.
+    #[serde(default)]
+    pub encoding: DecimalEncoding,
+
+    /// Number of fractional digits represented by a `scaled_integer`
+    /// value.  Ignored by other encodings.
+    #[serde(default)]
+    pub scale: u32,
+}
+
+/// Parse a decimal number without losing precision.
+fn parse_decimal(value: &str) -> AnyResult<Decimal> {
+    Decimal::from_str_exact(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .map_err(|e| anyhow!("'{value}' is not a valid decimal number: {e}"))
+}
+
+/// Decimal representation of `unscaled * 10^-scale`.
+pub(crate) fn unscaled_to_string(unscaled: i128, scale: u32) -> AnyResult<String> {
+    Decimal::try_from_i128_with_scale(unscaled, scale)
+        .map(|value| value.to_string())
+        .map_err(|e| anyhow!("decimal value {unscaled}E-{scale} out of range: {e}"))
+}
+
+/// Unscaled integer value of decimal `value` with `scale` fractional digits.
+///
+/// Fails if `value` has more than `scale` significant fractional digits.
+pub(crate) fn to_unscaled(value: &str, scale: u32) -> AnyResult<i128> {
+    let mut decimal = parse_decimal(value.trim())?;
+    if decimal.normalize().scale() > scale {
+        bail!("decimal value '{value}' cannot be represented with {scale} fractional digits without losing precision");
+    }
+    decimal.rescale(scale);
+    if decimal.scale() != scale {
+        bail!("decimal value '{value}' is out of range for {scale} fractional digits");
+    }
+    Ok(decimal.mantissa())
+}
+
+/// Converts the values of a column between a custom encoding and the
+/// canonical format.
+#[derive(Clone, Debug)]
+pub(crate) struct DecimalConverter {
+    encoding: DecimalEncoding,
+    scale: u32,
+}
+
+impl DecimalConverter {
+    pub(crate) fn new(config: &DecimalFormat) -> AnyResult<Self> {
+        if config.scale > MAX_SCALE {
+            bail!(
+                "decimal scale {} exceeds the maximum scale {MAX_SCALE}",
+                config.scale
+            );
+        }
+        Ok(Self {
+            encoding: config.encoding,
+            scale: config.scale,
+        })
+    }
+
+    /// Convert `value` in the custom encoding to the canonical format.
+    pub(crate) fn parse(&self, value: &str) -> AnyResult<String> {
+        let value = value.trim();
+        match self.encoding {
+            DecimalEncoding::ScaledInteger => {
+                let unscaled = value
+                    .parse::<i128>()
+                    .map_err(|e| anyhow!("'{value}' is not a valid scaled integer: {e}"))?;
+                unscaled_to_string(unscaled, self.scale)
+            }
+            DecimalEncoding::String | DecimalEncoding::Number => {
+                Ok(parse_decimal(value)?.to_string())
+            }
+        }
+    }
+
+    /// Convert `value` in the canonical format to the custom encoding.
+    pub(crate) fn format(&self, value: &str) -> AnyResult<String> {
+        match self.encoding {
+            DecimalEncoding::ScaledInteger => Ok(to_unscaled(value, self.scale)?.to_string()),
+            DecimalEncoding::String | DecimalEncoding::Number => {
+                Ok(parse_decimal(value.trim())?.to_string())
+            }
+        }
+    }
+
+    /// Convert a JSON value in the custom encoding to a JSON string in the
+    /// canonical format.
+    ///
+    /// Both strings and numbers are accepted.  Returns `None` if the value
+    /// cannot be converted.
+    pub(crate) fn parse_json(&self, value: &RawValue) -> Option<String> {
+        let text = value.get();
+        let converted = if text.starts_with('"') {
+            self.parse(&serde_json::from_str::<String>(text).ok()?)
+        } else {
+            self.parse(text)
+        };
+        converted
+            .ok()
+            .map(|value| serde_json::to_string(&value).unwrap())
+    }
+
+    /// Convert `value` in the canonical format to a JSON value in the custom
+    /// encoding.
+    pub(crate) fn format_json(&self, value: &str) -> AnyResult<String> {
+        let value = self.format(value)?;
+        Ok(match self.encoding {
+            DecimalEncoding::String => serde_json::to_string(&value).unwrap(),
+            DecimalEncoding::Number | DecimalEncoding::ScaledInteger => value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DecimalConverter, DecimalEncoding, DecimalFormat};
+    use serde_json::value::RawValue;
+
+    fn converter(encoding: DecimalEncoding, scale: u32) -> DecimalConverter {
+        DecimalConverter::new(&DecimalFormat { encoding, scale }).unwrap()
+    }
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_format() {
+        // Numbers that cannot be represented exactly as floats.
+        let number = converter(DecimalEncoding::Number, 0);
+        assert_eq!(
+            number.parse_json(&raw("12345678901234567.89")).unwrap(),
+            "\"12345678901234567.89\""
+        );
+        assert_eq!(number.parse_json(&raw("\"0.10\"")).unwrap(), "\"0.10\"");
+        assert_eq!(number.parse_json(&raw("1.5e3")).unwrap(), "\"1500\"");
+        assert!(number.parse_json(&raw("true")).is_none());
+        assert_eq!(number.format_json("-0.30").unwrap(), "-0.30");
+
+        let string = converter(DecimalEncoding::String, 0);
+        assert_eq!(string.format_json("12.30").unwrap(), "\"12.30\"");
+
+        let cents = converter(DecimalEncoding::ScaledInteger, 2);
+        assert_eq!(cents.parse("1230").unwrap(), "12.30");
+        assert_eq!(cents.parse("-5").unwrap(), "-0.05");
+        assert_eq!(cents.format("12.3").unwrap(), "1230");
+        assert_eq!(cents.format("12.300").unwrap(), "1230");
+        assert_eq!(cents.format_json("-0.05").unwrap(), "-5");
+        assert!(cents.parse("12.30").is_err());
+        // Formatting must not round.
+        assert!(cents.format("0.001").is_err());
+
+        assert!(DecimalConverter::new(&DecimalFormat {
+            encoding: DecimalEncoding::ScaledInteger,
+            scale: 29,
+        })
+        .is_err());
+    }
+}
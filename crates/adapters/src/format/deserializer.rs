@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::any::type_name;
 
 // TODO: make parsing configurable, e.g., with a subset of columns.
 
@@ -8,6 +9,72 @@ use serde::{Deserialize, Serialize};
 pub struct FieldParseError {
     pub field: String,
     pub description: String,
+
+    /// Index of the field in the record.
+    ///
+    /// Only set when the record is deserialized from a sequence of fields,
+    /// e.g., a CSV record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+
+    /// SQL type of the field, e.g., `BIGINT NOT NULL`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_type: Option<String>,
+}
+
+impl FieldParseError {
+    /// Extract `FieldParseError` from the text of an error returned by a
+    /// deserializer generated by the `deserialize_table_record` macro.
+    ///
+    /// Returns `None` if this is not a field-specific error.
+    pub fn from_error_str(error: &str) -> Option<Self> {
+        let offset = error.find("{\"field\":")?;
+        serde_json::Deserializer::from_str(&error[offset..])
+            .into_iter::<FieldParseError>()
+            .next()?
+            .ok()
+    }
+}
+
+/// SQL name of the type of a table column with Rust type `T`, used in error
+/// messages.
+///
+/// Returns the name of the Rust type for types that don't correspond to a
+/// primitive SQL type.
+#[doc(hidden)]
+pub fn sql_type_name<T: ?Sized>() -> String {
+    let rust_type = type_name::<T>();
+
+    match rust_type
+        .strip_prefix("core::option::Option<")
+        .and_then(|inner| inner.strip_suffix('>'))
+    {
+        Some(inner) => sql_base_type_name(inner),
+        None => format!("{} NOT NULL", sql_base_type_name(rust_type)),
+    }
+}
+
+fn sql_base_type_name(rust_type: &str) -> String {
+    let (path, args) = rust_type.split_at(rust_type.find('<').unwrap_or(rust_type.len()));
+    let name = path.rsplit("::").next().unwrap_or(path);
+
+    match name {
+        "bool" => "BOOLEAN",
+        "i8" => "TINYINT",
+        "i16" => "SMALLINT",
+        "i32" => "INTEGER",
+        "i64" => "BIGINT",
+        "f32" | "F32" => "REAL",
+        "f64" | "F64" => "DOUBLE",
+        "String" => "VARCHAR",
+        "Decimal" => "DECIMAL",
+        "Date" => "DATE",
+        "Time" => "TIME",
+        "Timestamp" => "TIMESTAMP",
+        "Vec" => "ARRAY",
+        _ => return format!("{name}{args}"),
+    }
+    .to_string()
 }
 
 /// Generate an `impl Deserialize` for a SQL table row type.
@@ -51,7 +118,7 @@ macro_rules! deserialize_table_record {
                                     // deserializer type `D`, so we instead encode it as a JSON
                                     // object, which the client will have to parse.
                                     $field_name = Some(map.next_value::<$type>()
-                                                  .map_err(|e| serde::de::Error::custom(serde_json::to_string(&$crate::format::FieldParseError{field: $column_name.to_string(), description: e.to_string()}).unwrap()))?);
+                                                  .map_err(|e| serde::de::Error::custom(serde_json::to_string(&$crate::format::FieldParseError{field: $column_name.to_string(), description: e.to_string(), position: None, expected_type: Some($crate::format::sql_type_name::<$type>())}).unwrap()))?);
                                 } else
                             )*
                             {let _ = map.next_value::<serde::de::IgnoredAny>()?;}
@@ -70,7 +137,7 @@ macro_rules! deserialize_table_record {
                                 _cols += 1;
                                 seq.next_element::<$type>()
                                     .map_err(|e| {
-                                        serde::de::Error::custom(serde_json::to_string(&$crate::format::FieldParseError{field: $column_name.to_string(), description: e.to_string()}).unwrap())
+                                        serde::de::Error::custom(serde_json::to_string(&$crate::format::FieldParseError{field: $column_name.to_string(), description: e.to_string(), position: Some(_cols - 1), expected_type: Some($crate::format::sql_type_name::<$type>())}).unwrap())
                                     })?
                                     .ok_or_else(|| {
                                         serde::de::Error::invalid_length(_cols-1, &format!("{} columns", $num_cols).as_str())
@@ -226,15 +293,15 @@ mod test {
     #[test]
     fn error_reporting() {
         // Correctly report parsing errors for individual fields.
-        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": 10, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 13"} at line 1 column 13"#.to_string()));
-        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"[10, "foo", null]"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 3"} at line 1 column 5"#.to_string()));
+        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": 10, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 13","expected_type":"BOOLEAN NOT NULL"} at line 1 column 13"#.to_string()));
+        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"[10, "foo", null]"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 3","position":0,"expected_type":"BOOLEAN NOT NULL"} at line 1 column 5"#.to_string()));
         assert_eq!(
             serde_json::from_str::<CaseSensitive>(r#"[true]"#).map_err(|e| e.to_string()),
             Err(r#"invalid length 1, expected 3 columns at line 1 column 6"#.to_string())
         );
-        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": null, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: null, expected a boolean at line 1 column 15"} at line 1 column 15"#.to_string()));
-        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": false, "field2": "foo", "FIELD3": true}"#).map_err(|e| e.to_string()), Err(r#"{"field":"FIELD3","description":"invalid type: boolean `true`, expected u8 at line 1 column 49"} at line 1 column 50"#.to_string()));
-        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": 10, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 13"} at line 1 column 13"#.to_string()));
+        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": null, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: null, expected a boolean at line 1 column 15","expected_type":"BOOLEAN NOT NULL"} at line 1 column 15"#.to_string()));
+        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": false, "field2": "foo", "FIELD3": true}"#).map_err(|e| e.to_string()), Err(r#"{"field":"FIELD3","description":"invalid type: boolean `true`, expected u8 at line 1 column 49","expected_type":"u8"} at line 1 column 50"#.to_string()));
+        assert_eq!(serde_json::from_str::<CaseSensitive>(r#"{"fIeLd1": 10, "field2": "foo"}"#).map_err(|e| e.to_string()), Err(r#"{"field":"fIeLd1","description":"invalid type: integer `10`, expected a boolean at line 1 column 13","expected_type":"BOOLEAN NOT NULL"} at line 1 column 13"#.to_string()));
     }
 
     #[derive(Debug, Eq, PartialEq)]
@@ -268,13 +335,13 @@ mod test {
             serde_json::from_str::<UnicodeStruct>(
                 r#"{"αΒβΓγδε": true, "Українська": 10, "unicode⌛👏": 100}"#
             ).map_err(|e| e.to_string()),
-            Err(r#"{"field":"УКРАЇНСЬКА","description":"invalid type: integer `10`, expected a string at line 1 column 51"} at line 1 column 51"#.to_string())
+            Err(r#"{"field":"УКРАЇНСЬКА","description":"invalid type: integer `10`, expected a string at line 1 column 51","expected_type":"VARCHAR NOT NULL"} at line 1 column 51"#.to_string())
         );
         assert_eq!(
             serde_json::from_str::<UnicodeStruct>(
                 r#"[true, 10, 100]"#
             ).map_err(|e| e.to_string()),
-            Err(r#"{"field":"УКРАЇНСЬКА","description":"invalid type: integer `10`, expected a string at line 1 column 9"} at line 1 column 11"#.to_string())
+            Err(r#"{"field":"УКРАЇНСЬКА","description":"invalid type: integer `10`, expected a string at line 1 column 9","position":1,"expected_type":"VARCHAR NOT NULL"} at line 1 column 11"#.to_string())
         );
     }
 
@@ -294,6 +361,6 @@ true,bar,buzz"#;
                 FIELD3: Some(5)
             }
         );
-        assert_eq!(records.next().unwrap().map_err(|e| e.to_string()), Err(r#"CSV deserialize error: record 1 (line: 2, byte: 13): {"field":"FIELD3","description":"field 2: invalid digit found in string"}"#.to_string()));
+        assert_eq!(records.next().unwrap().map_err(|e| e.to_string()), Err(r#"CSV deserialize error: record 1 (line: 2, byte: 13): {"field":"FIELD3","description":"field 2: invalid digit found in string","position":2,"expected_type":"u8"}"#.to_string()));
     }
 }
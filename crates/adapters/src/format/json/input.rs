@@ -1,19 +1,31 @@
 //! JSON format parser.
 
-use super::{DebeziumUpdate, InsDelUpdate, JsonUpdateFormat, WeightedUpdate};
+use super::{
+    DebeziumOp, DebeziumUpdate, InsDelUpdate, JsonLayout, JsonUpdateFormat, RawObject,
+    WeightedUpdate,
+};
 use crate::{
     catalog::{DeCollectionStream, RecordFormat},
-    format::{InputFormat, ParseError, Parser},
-    util::split_on_newline,
+    format::{
+        datetime::{DateTimeConverter, DateTimeFormat},
+        decimal::{DecimalConverter, DecimalFormat},
+        InputFormat, ParseError, Parser,
+    },
+    util::{count_newlines, split_on_newline},
     ControllerError, DeCollectionHandle,
 };
 use actix_web::HttpRequest;
 use erased_serde::Serialize as ErasedSerialize;
 use serde::{Deserialize, Serialize};
-use serde_json::value::RawValue;
+use serde_json::{value::RawValue, Map as JsonMap, Value as JsonValue};
 use serde_urlencoded::Deserializer as UrlDeserializer;
 use serde_yaml::Value as YamlValue;
-use std::{borrow::Cow, mem::take};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    mem::take,
+    sync::{Arc, Mutex},
+};
 use utoipa::ToSchema;
 
 /// JSON format parser.
@@ -51,11 +63,28 @@ pub struct JsonInputFormat;
 /// ```json
 /// [{"insert": {"b": true, "i": 0}}, {"delete": {"b": false, "i": 100, "s": "foo"}}]
 /// ```
-#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+///
+/// A configuration with `update_format="upsert"` and `key_columns=["id"]`
+/// replaces the previous record with the same `id`, so the following stream
+/// leaves a single record in the table:
+///
+/// ```json
+/// {"id": 1, "status": "pending"}
+/// {"id": 1, "status": "shipped"}
+/// ```
+///
+/// A configuration with `update_format="raw"`,
+/// `paths={"id": "user.id", "sku": "items.sku"}`, and `explode="items"`
+/// turns each nested event into one row per element of its `items` array:
+///
+/// ```json
+/// {"user": {"id": 1}, "items": [{"sku": "a"}, {"sku": "b"}]}
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
 pub struct JsonParserConfig {
     /// JSON update format.
     #[serde(default)]
-    update_format: JsonUpdateFormat,
+    pub(crate) update_format: JsonUpdateFormat,
 
     /// Set to `true` if updates in this stream are packaged into JSON arrays.
     ///
@@ -65,7 +94,220 @@ pub struct JsonParserConfig {
     /// [{"b": true, "i": 0},{"b": false, "i": 100, "s": "foo"}]
     /// ```
     #[serde(default)]
-    array: bool,
+    pub(crate) array: bool,
+
+    /// Layout of each message or request body.
+    ///
+    /// With the `array` and `object` layouts, input received in fragments,
+    /// e.g., as the body of an HTTP request, is parsed as a single JSON
+    /// document once the whole body has been received; the `array` flag is
+    /// ignored.
+    #[serde(default)]
+    pub(crate) layout: JsonLayout,
+
+    /// Bind table columns to values nested inside input records.
+    ///
+    /// Maps column names to paths inside the record, written either as
+    /// dot-separated field names and array indexes (`payload.user.id`) or
+    /// as JSON pointers (`/payload/user/id`).  Columns that are not listed
+    /// here are read from the top-level fields of the record.  Columns whose
+    /// path does not exist in a record are treated as missing.
+    #[serde(default)]
+    pub(crate) paths: BTreeMap<String, String>,
+
+    /// Path to an array inside each input record, in the same syntax as
+    /// `paths`.
+    ///
+    /// When specified, each record is expanded into one row per element of
+    /// the array, with the array replaced by the element, so that `paths`
+    /// can refer both to the fields of the element and to the fields of the
+    /// enclosing record.  A record whose array is missing or `null` produces
+    /// no rows.
+    #[serde(default)]
+    pub(crate) explode: Option<String>,
+
+    /// Custom formats of `DATE`, `TIME`, and `TIMESTAMP` columns, keyed by
+    /// column name.
+    ///
+    /// String values that don't match the custom format are parsed using the
+    /// default ISO format.
+    #[serde(default)]
+    pub(crate) datetime_formats: BTreeMap<String, DateTimeFormat>,
+
+    /// Encodings of `DECIMAL` columns, keyed by the name of the top-level
+    /// field of the record that contains the column.
+    ///
+    /// Values of the listed fields are converted to decimals without loss
+    /// of precision.  Values that cannot be converted are passed to the
+    /// deserializer unmodified.
+    #[serde(default)]
+    pub(crate) decimal_formats: BTreeMap<String, DecimalFormat>,
+
+    /// Columns that uniquely identify a record in the `upsert` update
+    /// format.
+    ///
+    /// Required when `update_format` is `upsert`, ignored otherwise.
+    #[serde(default)]
+    pub(crate) key_columns: Vec<String>,
+}
+
+/// Convert a path in the parser configuration to a JSON pointer.
+fn json_pointer(path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        path.to_string()
+    } else {
+        path.split('.')
+            .map(|field| format!("/{}", field.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+}
+
+/// Transformation of nested input records into rows, configured via the
+/// `paths` and `explode` properties of [`JsonParserConfig`].
+#[derive(Clone)]
+struct RecordMapping {
+    /// Column names and JSON pointers to their values.
+    paths: Vec<(String, String)>,
+    /// Path to the exploded array and its JSON pointer.
+    explode: Option<(String, String)>,
+    /// Column names and converters of columns with custom date/time formats.
+    datetime: Vec<(String, DateTimeConverter)>,
+    /// Field names and converters of fields with custom decimal encodings.
+    decimal: Vec<(String, DecimalConverter)>,
+}
+
+impl RecordMapping {
+    fn new(config: &JsonParserConfig) -> Option<Self> {
+        if config.paths.is_empty()
+            && config.explode.is_none()
+            && config.datetime_formats.is_empty()
+            && config.decimal_formats.is_empty()
+        {
+            return None;
+        }
+
+        Some(Self {
+            paths: config
+                .paths
+                .iter()
+                .map(|(column, path)| (column.clone(), json_pointer(path)))
+                .collect(),
+            explode: config
+                .explode
+                .as_ref()
+                .map(|path| (path.clone(), json_pointer(path))),
+            // Formats are validated when the parser is created.
+            datetime: config
+                .datetime_formats
+                .iter()
+                .filter_map(|(column, format)| {
+                    Some((column.clone(), DateTimeConverter::new(format).ok()?))
+                })
+                .collect(),
+            decimal: config
+                .decimal_formats
+                .iter()
+                .filter_map(|(field, format)| {
+                    Some((field.clone(), DecimalConverter::new(format).ok()?))
+                })
+                .collect(),
+        })
+    }
+
+    /// Convert values of fields with custom decimal encodings in `record`
+    /// to the canonical format.
+    ///
+    /// Operates on the raw text of the record, since parsing it into a
+    /// `serde_json::Value` would round decimal numbers to the nearest
+    /// floating point value.  Returns `None` if `record` is not a JSON
+    /// object.
+    fn convert_decimals(&self, record: &RawValue) -> Option<String> {
+        let mut fields: RawObject = serde_json::from_str(record.get()).ok()?;
+        for (field, converter) in self.decimal.iter() {
+            if let Some(value) = fields.get_mut(field) {
+                if let Some(converted) = converter.parse_json(value) {
+                    // `parse_json` returns a valid JSON string.
+                    *value = RawValue::from_string(converted).unwrap();
+                }
+            }
+        }
+        serde_json::to_string(&fields).ok()
+    }
+
+    /// Convert values of columns with custom date/time formats in `row` to
+    /// the canonical format.
+    fn convert_datetime(&self, row: &mut JsonMap<String, JsonValue>) {
+        for (column, value) in row.iter_mut() {
+            let converter = self
+                .datetime
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(column));
+            if let (Some((_, converter)), JsonValue::String(s)) = (converter, &*value) {
+                if let Ok(converted) = converter.parse(s) {
+                    *value = JsonValue::String(converted);
+                }
+            }
+        }
+    }
+
+    /// Transform `record` into zero or more rows serialized as JSON.
+    fn apply(&self, record: &RawValue) -> Result<Vec<String>, String> {
+        let converted = if self.decimal.is_empty() {
+            None
+        } else {
+            self.convert_decimals(record)
+        };
+        if self.paths.is_empty() && self.explode.is_none() && self.datetime.is_empty() {
+            return Ok(vec![converted.unwrap_or_else(|| record.get().to_string())]);
+        }
+
+        let mut record: JsonValue =
+            serde_json::from_str(converted.as_deref().unwrap_or(record.get()))
+                .map_err(|e| e.to_string())?;
+
+        let records = match &self.explode {
+            None => vec![record],
+            Some((path, pointer)) => {
+                let elements = match record.pointer_mut(pointer).map(JsonValue::take) {
+                    None | Some(JsonValue::Null) => Vec::new(),
+                    Some(JsonValue::Array(elements)) => elements,
+                    Some(_) => return Err(format!("value at path '{path}' is not an array")),
+                };
+                elements
+                    .into_iter()
+                    .map(|element| {
+                        let mut record = record.clone();
+                        // The pointer is valid, since we just took the array from it.
+                        *record.pointer_mut(pointer).unwrap() = element;
+                        record
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                if self.paths.is_empty() && self.datetime.is_empty() {
+                    return record.to_string();
+                }
+                let mut row = match &record {
+                    JsonValue::Object(fields) => fields.clone(),
+                    // Records encoded as arrays are not affected by `paths`
+                    // or `datetime_formats`.
+                    _ if self.paths.is_empty() => return record.to_string(),
+                    _ => JsonMap::new(),
+                };
+                for (column, pointer) in self.paths.iter() {
+                    if let Some(value) = record.pointer(pointer) {
+                        row.insert(column.clone(), value.clone());
+                    }
+                }
+                self.convert_datetime(&mut row);
+                JsonValue::Object(row).to_string()
+            })
+            .collect())
+    }
 }
 
 trait UpdateFormat {
@@ -97,13 +339,11 @@ impl<'a> UpdateFormat for InsDelUpdate<&'a RawValue> {
         let mut updates = 0;
 
         if let Some(val) = self.insert {
-            parser.insert(val)?;
-            updates += 1;
+            updates += parser.insert(val)?;
         }
 
         if let Some(val) = self.delete {
-            parser.delete(val)?;
-            updates += 1;
+            updates += parser.delete(val)?;
         }
 
         Ok(updates)
@@ -138,29 +378,53 @@ impl<'a> UpdateFormat for DebeziumUpdate<&'a RawValue> {
             check that table name matches??
         };*/
 
-        // TODO: validate CDC op code (c|d|u).  This opcode seems redundant.
-        // We must always delete the `before` record and insert the `after`
-        // record (if present).
-        /*
-        match update.payload.op {
-            CdcOp::Create =>,
-            CdcOp::Delete =>,
-            CdcOp::Update =>
-        }*/
-
-        let mut updates = 0;
-
-        if let Some(before) = &self.payload.before {
-            parser.delete(before)?;
-            updates += 1;
+        let payload = match self.payload {
+            // Tombstone.
+            None => return Ok(0),
+            Some(payload) => payload,
         };
 
-        if let Some(after) = &self.payload.after {
-            parser.insert(after)?;
-            updates += 1;
+        let op = match payload.op {
+            Some(op) => op,
+            // Schema change event.
+            None if payload.ddl.is_some() => return Ok(0),
+            None => {
+                return Err(parser
+                    .event_error("Debezium CDC event does not specify an operation ('op' field)"))
+            }
         };
 
-        Ok(updates)
+        match op {
+            DebeziumOp::Create | DebeziumOp::Read => {
+                let after = payload.after.ok_or_else(|| {
+                    parser.event_error("Debezium CDC insert event is missing the 'after' record")
+                })?;
+                parser.insert(after)
+            }
+            DebeziumOp::Delete => {
+                let before = payload.before.ok_or_else(|| {
+                    parser.event_error("Debezium CDC delete event is missing the 'before' record")
+                })?;
+                parser.delete(before)
+            }
+            DebeziumOp::Update => {
+                // The old value of the record is only available if the source
+                // is configured to capture it, e.g., Postgres tables with
+                // `REPLICA IDENTITY FULL`.
+                let (before, after) = match (payload.before, payload.after) {
+                    (Some(before), Some(after)) => (before, after),
+                    _ => return Err(parser.event_error(
+                        "Debezium CDC update event must contain both 'before' and 'after' records; configure the CDC source to capture the complete old value of updated records",
+                    )),
+                };
+                Ok(parser.delete(before)? + parser.insert(after)?)
+            }
+            DebeziumOp::Truncate => {
+                Err(parser.event_error("Debezium CDC truncate events are not supported"))
+            }
+            // Logical decoding messages don't modify the table.
+            DebeziumOp::Message => Ok(0),
+        }
     }
 }
 
@@ -204,11 +468,46 @@ impl<'a> UpdateFormat for &'a RawValue {
     }
 
     fn apply(self, parser: &mut JsonParser) -> Result<usize, ParseError> {
-        parser.insert(self)?;
-        Ok(1)
+        parser.insert(self)
     }
 }
 
+/// A complete record in the upsert format.
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct UpsertRecord<'a>(#[serde(borrow)] &'a RawValue);
+
+impl<'a> UpdateFormat for UpsertRecord<'a> {
+    fn error() -> &'static str {
+        "failed to parse JSON string"
+    }
+
+    fn array_error() -> &'static str {
+        "error deserializing string as a JSON array"
+    }
+
+    fn example() -> Option<&'static str> {
+        None
+    }
+
+    fn array_example() -> Option<&'static str> {
+        None
+    }
+
+    fn apply(self, parser: &mut JsonParser) -> Result<usize, ParseError> {
+        parser.upsert(self.0)
+    }
+}
+
+/// Last received record for each key in the upsert format, shared by all
+/// forks of a parser.
+#[derive(Default)]
+struct UpsertState {
+    /// Maps the key of each record, serialized as a JSON array of the raw
+    /// values of the key columns, to the record.
+    records: HashMap<String, String>,
+}
+
 impl InputFormat for JsonInputFormat {
     fn name(&self) -> Cow<'static, str> {
         Cow::Borrowed("json")
@@ -220,14 +519,29 @@ impl InputFormat for JsonInputFormat {
         input_stream: &dyn DeCollectionHandle,
         config: &YamlValue,
     ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
         let config = JsonParserConfig::deserialize(config).map_err(|e| {
-            ControllerError::parser_config_parse_error(
-                endpoint_name,
-                &e,
-                &serde_yaml::to_string(&config).unwrap_or_default(),
-            )
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
         })?;
-        let input_stream = input_stream.configure_deserializer(RecordFormat::Json)?;
+        for format in config.datetime_formats.values() {
+            DateTimeConverter::new(format).map_err(|e| {
+                ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+            })?;
+        }
+        for format in config.decimal_formats.values() {
+            DecimalConverter::new(format).map_err(|e| {
+                ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+            })?;
+        }
+        if config.update_format == JsonUpdateFormat::Upsert && config.key_columns.is_empty() {
+            return Err(ControllerError::parser_config_parse_error(
+                endpoint_name,
+                &"the 'upsert' update format requires at least one key column ('key_columns')",
+                &config_str(),
+            ));
+        }
+        let input_stream =
+            input_stream.configure_deserializer(RecordFormat::Json(Default::default()))?;
         Ok(Box::new(JsonParser::new(input_stream, config)) as Box<dyn Parser>)
     }
 
@@ -251,36 +565,109 @@ impl InputFormat for JsonInputFormat {
     }
 }
 
-struct JsonParser {
+pub(crate) struct JsonParser {
     /// Input handle to push parsed data to.
     input_stream: Box<dyn DeCollectionStream>,
     config: JsonParserConfig,
+    mapping: Option<RecordMapping>,
     leftover: Vec<u8>,
     last_event_number: u64,
+    /// Number of lines received via `input_fragment` and parsed so far.
+    num_lines: u64,
+    /// Records received in the upsert format.
+    upserts: Arc<Mutex<UpsertState>>,
+    /// Previous values of the keys upserted since the last flush, used to
+    /// roll back `upserts` when buffered updates are discarded.
+    upsert_log: Vec<(String, Option<String>)>,
 }
 
 impl JsonParser {
-    fn new(input_stream: Box<dyn DeCollectionStream>, config: JsonParserConfig) -> Self {
+    pub(crate) fn new(input_stream: Box<dyn DeCollectionStream>, config: JsonParserConfig) -> Self {
+        Self::with_upserts(input_stream, config, Default::default())
+    }
+
+    fn with_upserts(
+        input_stream: Box<dyn DeCollectionStream>,
+        config: JsonParserConfig,
+        upserts: Arc<Mutex<UpsertState>>,
+    ) -> Self {
+        let mapping = RecordMapping::new(&config);
+
         Self {
             input_stream,
             config,
+            mapping,
             leftover: Vec::new(),
             last_event_number: 0,
+            num_lines: 0,
+            upserts,
+            upsert_log: Vec::new(),
         }
     }
 
     fn flush(&mut self) {
         self.input_stream.flush();
+        self.upsert_log.clear();
     }
 
     fn clear(&mut self) {
         self.input_stream.clear_buffer();
+
+        let mut upserts = self.upserts.lock().unwrap();
+        for (key, previous) in self.upsert_log.drain(..).rev() {
+            match previous {
+                Some(record) => upserts.records.insert(key, record),
+                None => upserts.records.remove(&key),
+            };
+        }
+    }
+
+    /// Delete the rows produced from `val`, returning the number of rows.
+    fn delete(&mut self, val: &RawValue) -> Result<usize, ParseError> {
+        self.update(val, false)
+    }
+
+    /// Error in the envelope of the current event.
+    fn event_error(&self, description: &str) -> ParseError {
+        ParseError::new(
+            description.to_string(),
+            Some(self.last_event_number + 1),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Insert the rows produced from `val`, returning the number of rows.
+    fn insert(&mut self, val: &RawValue) -> Result<usize, ParseError> {
+        self.update(val, true)
     }
 
-    fn delete(&mut self, val: &RawValue) -> Result<(), ParseError> {
-        self.input_stream.delete(val.get().as_bytes()).map_err(|e| {
+    fn update(&mut self, val: &RawValue, insert: bool) -> Result<usize, ParseError> {
+        let rows = match &self.mapping {
+            None => {
+                self.push_row(val.get(), insert)?;
+                return Ok(1);
+            }
+            Some(mapping) => self.map_record(mapping, val)?,
+        };
+
+        for row in rows.iter() {
+            self.push_row(row, insert)?;
+        }
+        Ok(rows.len())
+    }
+
+    /// Transform `val` into table rows using `mapping`.
+    fn map_record(
+        &self,
+        mapping: &RecordMapping,
+        val: &RawValue,
+    ) -> Result<Vec<String>, ParseError> {
+        mapping.apply(val).map_err(|e| {
             ParseError::text_event_error(
-                "failed to deserialize JSON record",
+                "failed to map JSON record to table columns",
                 e,
                 self.last_event_number + 1,
                 Some(val.get()),
@@ -289,25 +676,105 @@ impl JsonParser {
         })
     }
 
-    fn insert(&mut self, val: &RawValue) -> Result<(), ParseError> {
-        self.input_stream.insert(val.get().as_bytes()).map_err(|e| {
+    /// Replace the rows with the same keys as the rows produced from `val`,
+    /// returning the number of rows.
+    fn upsert(&mut self, val: &RawValue) -> Result<usize, ParseError> {
+        let rows = match &self.mapping {
+            None => vec![val.get().to_string()],
+            Some(mapping) => self.map_record(mapping, val)?,
+        };
+
+        for row in rows.iter() {
+            self.upsert_row(row)?;
+        }
+        Ok(rows.len())
+    }
+
+    /// The key of `row`: a JSON array of the raw values of its key columns.
+    fn upsert_key(&self, row: &str) -> Result<String, ParseError> {
+        let error = |description: String| {
+            ParseError::text_event_error(
+                "failed to extract the key of an upsert record",
+                description,
+                self.last_event_number + 1,
+                Some(row),
+                None,
+            )
+        };
+
+        let mut fields: RawObject = serde_json::from_str(row)
+            .map_err(|_| error("upsert records must be JSON objects".to_string()))?;
+        let mut values = Vec::with_capacity(self.config.key_columns.len());
+        for column in self.config.key_columns.iter() {
+            match fields.get_mut(column) {
+                Some(value) => values.push(value.get().to_string()),
+                None => return Err(error(format!("missing key column '{column}'"))),
+            }
+        }
+        Ok(format!("[{}]", values.join(",")))
+    }
+
+    /// Delete the previous row with the same key as `row` and insert `row`.
+    fn upsert_row(&mut self, row: &str) -> Result<(), ParseError> {
+        let key = self.upsert_key(row)?;
+
+        // Hold the lock for the entire update, so that concurrent forks of
+        // this parser don't interleave updates to the same key.
+        let upserts = self.upserts.clone();
+        let mut upserts = upserts.lock().unwrap();
+        let previous = upserts.records.get(&key).cloned();
+
+        if let Some(previous) = &previous {
+            self.push_row(previous, false)?;
+        }
+        if let Err(e) = self.push_row(row, true) {
+            // Restore the previous row, which was successfully deserialized
+            // before.
+            if let Some(previous) = &previous {
+                let _ = self.push_row(previous, true);
+            }
+            return Err(e);
+        }
+
+        upserts.records.insert(key.clone(), row.to_string());
+        self.upsert_log.push((key, previous));
+        Ok(())
+    }
+
+    fn push_row(&mut self, row: &str, insert: bool) -> Result<(), ParseError> {
+        let result = if insert {
+            self.input_stream.insert(row.as_bytes())
+        } else {
+            self.input_stream.delete(row.as_bytes())
+        };
+
+        result.map_err(|e| {
             ParseError::text_event_error(
                 "failed to deserialize JSON record",
                 e,
                 self.last_event_number + 1,
-                Some(val.get()),
+                Some(row),
                 None,
             )
         })
     }
 
+    /// Whether each JSON document contains an array of updates.
+    fn array(&self) -> bool {
+        match self.config.layout {
+            JsonLayout::Ndjson => self.config.array,
+            JsonLayout::Array => true,
+            JsonLayout::Object => false,
+        }
+    }
+
     fn apply_update<'de, F>(&mut self, update: &'de RawValue, errors: &mut Vec<ParseError>) -> usize
     where
         F: UpdateFormat + Deserialize<'de>,
     {
         let mut num_updates = 0;
 
-        if self.config.array {
+        if self.array() {
             match serde_json::from_str::<Vec<F>>(update.get()) {
                 Err(e) => {
                     errors.push(ParseError::text_envelope_error(
@@ -331,7 +798,9 @@ impl JsonParser {
                         self.last_event_number += 1;
                     }
                     if error {
+                        // The entire array is discarded.
                         self.clear();
+                        num_updates = 0;
                     } else {
                         self.flush();
                     }
@@ -363,22 +832,46 @@ impl JsonParser {
         num_updates
     }
 
-    fn input_from_slice(&mut self, bytes: &[u8]) -> (usize, Vec<ParseError>) {
+    /// Parse JSON documents in `bytes`.
+    ///
+    /// When `bytes` is part of a continuous text stream, `first_line` is the
+    /// number of lines in the stream preceding `bytes` and is used to
+    /// attribute errors to lines.
+    fn input_from_slice(
+        &mut self,
+        bytes: &[u8],
+        first_line: Option<u64>,
+    ) -> (usize, Vec<ParseError>) {
         let mut num_updates = 0;
         let mut errors = Vec::new();
 
+        // Current line number and the offset in `bytes` up to which it has been
+        // computed.
+        let mut line = first_line.map(|first_line| first_line + 1);
+        let mut line_offset = 0;
+
         let mut stream = serde_json::Deserializer::from_slice(bytes).into_iter::<&RawValue>();
 
         while let Some(update) = stream.next() {
             let update = match update {
                 Err(e) => {
-                    let json_str = String::from_utf8_lossy(&bytes[stream.byte_offset()..]);
-                    errors.push(ParseError::text_envelope_error(
+                    let offset = stream.byte_offset();
+                    let json_str = String::from_utf8_lossy(&bytes[offset..]);
+                    let mut error = ParseError::text_envelope_error(
                         format!("failed to parse string as a JSON document: {e}"),
                         &json_str,
                         None,
-                    ));
-                    if !self.config.array {
+                    );
+                    if let Some(line) = line {
+                        let start = offset
+                            + bytes[offset..]
+                                .iter()
+                                .take_while(|b| b.is_ascii_whitespace())
+                                .count();
+                        error.set_line_number(line + count_newlines(&bytes[line_offset..start]));
+                    }
+                    errors.push(error);
+                    if !self.array() {
                         self.flush();
                     }
                     return (num_updates, errors);
@@ -386,6 +879,7 @@ impl JsonParser {
                 Ok(update) => update,
             };
 
+            let first_error = errors.len();
             num_updates += match self.config.update_format {
                 JsonUpdateFormat::InsertDelete => {
                     self.apply_update::<InsDelUpdate<_>>(update, &mut errors)
@@ -397,10 +891,21 @@ impl JsonParser {
                     self.apply_update::<WeightedUpdate<_>>(update, &mut errors)
                 }
                 JsonUpdateFormat::Raw => self.apply_update::<&RawValue>(update, &mut errors),
+                JsonUpdateFormat::Upsert => self.apply_update::<UpsertRecord>(update, &mut errors),
+            };
+
+            if let Some(line) = line.as_mut() {
+                // `update` points inside `bytes`.
+                let offset = update.get().as_ptr() as usize - bytes.as_ptr() as usize;
+                *line += count_newlines(&bytes[line_offset..offset]);
+                line_offset = offset;
+                for error in errors[first_error..].iter_mut() {
+                    error.set_line_number(*line);
+                }
             }
         }
 
-        if !self.config.array {
+        if !self.array() {
             self.flush();
         }
         (num_updates, errors)
@@ -410,6 +915,13 @@ impl JsonParser {
 impl Parser for JsonParser {
     fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
         // println!("input_fragment {}", std::str::from_utf8(data).unwrap());
+        if self.config.layout != JsonLayout::Ndjson {
+            // The message is a single JSON document; wait for all of it to
+            // arrive.
+            self.leftover.extend_from_slice(data);
+            return (0, Vec::new());
+        }
+
         let leftover = split_on_newline(data);
 
         if leftover == 0 {
@@ -421,7 +933,8 @@ impl Parser for JsonParser {
         } else {
             self.leftover.extend_from_slice(&data[0..leftover]);
             let mut leftover_data = take(&mut self.leftover);
-            let res = self.input_from_slice(&leftover_data);
+            let res = self.input_from_slice(&leftover_data, Some(self.num_lines));
+            self.num_lines += count_newlines(&leftover_data);
             leftover_data.clear();
             leftover_data.extend_from_slice(&data[leftover..]);
             self.leftover = leftover_data;
@@ -430,7 +943,7 @@ impl Parser for JsonParser {
     }
 
     fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
-        self.input_from_slice(data)
+        self.input_from_slice(data, None)
     }
 
     fn eoi(&mut self) -> (usize, Vec<ParseError>) {
@@ -444,12 +957,21 @@ impl Parser for JsonParser {
 
         // Try to interpret the leftover chunk as a complete JSON.
         let leftover = take(&mut self.leftover);
-        let res = self.input_from_slice(leftover.as_slice());
+        let res = self.input_from_slice(leftover.as_slice(), Some(self.num_lines));
+        self.num_lines += count_newlines(&leftover);
         res
     }
 
+    fn is_order_sensitive(&self) -> bool {
+        self.config.update_format == JsonUpdateFormat::Upsert
+    }
+
     fn fork(&self) -> Box<dyn Parser> {
-        Box::new(Self::new(self.input_stream.fork(), self.config.clone()))
+        Box::new(Self::with_upserts(
+            self.input_stream.fork(),
+            self.config.clone(),
+            self.upserts.clone(),
+        ))
     }
 }
 
@@ -457,14 +979,17 @@ impl Parser for JsonParser {
 mod test {
     use crate::{
         deserialize_table_record,
-        format::{JsonParserConfig, JsonUpdateFormat},
-        test::mock_parser_pipeline,
+        format::{
+            DateTimeFormat, DecimalEncoding, DecimalFormat, InputFormat, JsonLayout,
+            JsonParserConfig, JsonUpdateFormat,
+        },
+        test::{mock_parser_pipeline, MockDeZSet},
         transport::InputConsumer,
         FormatConfig, ParseError,
     };
     use log::trace;
     use serde::Deserialize;
-    use std::{borrow::Cow, fmt::Debug};
+    use std::{borrow::Cow, collections::BTreeMap, fmt::Debug};
 
     #[derive(PartialEq, Debug, Eq)]
     struct TestStruct {
@@ -564,6 +1089,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"b": true, "i": 0}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true)],
@@ -574,6 +1100,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"[true, 0, "a"]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, Some("a")), true)],
@@ -584,6 +1111,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[{"b": true, "i": 0}]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true)],
@@ -594,6 +1122,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[[true, 0, "b"]]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, Some("b")), true)],
@@ -605,6 +1134,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"b": true, "i": 0}{"b": false, "i": 100, "s": "foo"}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 100, Some("foo")), true)],
@@ -615,6 +1145,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"[true, 0, "c"][false, 100, "foo"]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, Some("c")), true), (TestStruct::new(false, 100, Some("foo")), true)],
@@ -625,6 +1156,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[{"b": true, "i": 0},{"b": false, "i": 100, "s": "foo"}]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 100, Some("foo")), true)],
@@ -635,6 +1167,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[[true, 0, "d"],[false, 100, "foo"]]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, Some("d")), true), (TestStruct::new(false, 100, Some("foo")), true)],
@@ -646,6 +1179,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 100, "s": "foo"}"#.to_string(), Vec::new())],
@@ -657,6 +1191,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"[true, 0, "e"]"#.to_string(), Vec::new())
                     , (r#"[false, 100, "foo"]"#.to_string(), Vec::new())],
@@ -668,6 +1203,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"b": true, "i": 0}]"#.to_string(), Vec::new())
                     , (r#"[{"b": false, "i": 100, "s": "foo"}]"#.to_string(), Vec::new())],
@@ -679,6 +1215,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[[true, 0, "e"]]"#.to_string(), Vec::new())
                     , (r#"[[false, 100, "foo"]]"#.to_string(), Vec::new())],
@@ -691,6 +1228,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 27".to_string(), "{\"b\": false, \"i\": 100, \"s\":", None)])],
@@ -702,6 +1240,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"[true, 0, "f"]"#.to_string(), Vec::new())
                     , (r#"[false, 100, "#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 13".to_string(), "[false, 100, ", None)])],
@@ -713,6 +1252,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"b": true, "i": 0}]"#.to_string(), Vec::new())
                     , (r#"[{"b": false, "i": 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 28".to_string(), "[{\"b\": false, \"i\": 100, \"s\":", None)])],
@@ -724,6 +1264,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[[true, 0, "g"]]"#.to_string(), Vec::new())
                     , (r#"[[false, 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: expected `,` or `]` at line 1 column 18".to_string(), "[[false, 100, \"s\":", None)])],
@@ -736,6 +1277,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 5}{"b": false}{"b": false, "I": "hello"}"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None), ParseError::new("failed to deserialize JSON record: error parsing field 'I': invalid type: string \"hello\", expected i32 at line 1 column 25".to_string(), Some(4), Some("I".to_string()), Some("{\"b\": false, \"I\": \"hello\"}"), None, None)])],
@@ -747,6 +1289,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"b": true, "i": 0}]"#.to_string(), Vec::new())
                     , (r#"[{"b": false, "i": 5},{"b": false}]"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None)])
@@ -760,6 +1303,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[[true, 0, "h"]]"#.to_string(), Vec::new())
                     , (r#"[{"b": false, "i": 5},[false]]"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: invalid length 1, expected 3 columns at line 1 column 7".to_string(), Some(3), None, Some("[false]"), None, None)])],
@@ -772,6 +1316,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 5}
@@ -785,6 +1330,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"[true, 0, "i"]"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 5}
@@ -798,6 +1344,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"b": true, "i": 0}]"#.to_string(), Vec::new())
                     , (r#"[{"b": false, "i": 5}, {"b": false, "i":"#.to_string(), Vec::new())
@@ -811,6 +1358,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"{"b": false, "i": 5}
@@ -825,6 +1373,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Raw,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"b": true, "i": 0}"#.to_string(), Vec::new())
                     , (r#"[false, 5, ""]
@@ -843,6 +1392,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true)],
@@ -853,6 +1403,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true)],
@@ -864,6 +1415,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"insert": {"b": true, "i": 0}}{"delete": {"b": false, "i": 100, "s": "foo"}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 100, Some("foo")), false)],
@@ -874,6 +1426,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[{"insert": {"b": true, "i": 0}}, {"delete": {"b": false, "i": 100, "s": "foo"}}]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 100, Some("foo")), false)],
@@ -884,6 +1437,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![(r#"[{"insert": [true, 0, "a"]}, {"delete": {"b": false, "i": 100, "s": "foo"}}]"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, Some("a")), true), (TestStruct::new(false, 100, Some("foo")), false)],
@@ -895,6 +1449,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())
                     , (r#"{"delete": {"b": false, "i": 100, "s": "foo"}}"#.to_string(), Vec::new())],
@@ -907,6 +1462,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())
                     , (r#"{"delete": {"b": false, "i": 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 38".to_string(), "{\"delete\": {\"b\": false, \"i\": 100, \"s\":", None)])],
@@ -918,6 +1474,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())
                     , (r#"[{"delete": {"b": false, "i": 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 39".to_string(), "[{\"delete\": {\"b\": false, \"i\": 100, \"s\":", None)])],
@@ -930,6 +1487,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())
                     , (r#"{"insert": {"b": false, "i": 5}}{"delete": {"b": false}}"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None)])],
@@ -941,6 +1499,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())
                     , (r#"[{"insert": {"b": false, "i": 5}},{"delete": {"b": false}}]"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None)])],
@@ -952,6 +1511,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())
                     , (r#"[{"insert": {"b": false, "i": 5}},{"delete": {"b": false}}]"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None)])
@@ -967,6 +1527,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())
                     , (r#"{"insert": {"b": false, "i": 5}}
@@ -980,6 +1541,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())
                     , (r#"[{"insert": {"b": false, "i": 5}}, {"delete": {"b": false, "i":"#.to_string(), Vec::new())
@@ -993,6 +1555,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"insert": {"b": true, "i": 0}}"#.to_string(), Vec::new())
                     , (r#"{"insert": {"b": false, "i": 5}}
@@ -1007,6 +1570,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": {"b": true, "i": 0}}]"#.to_string(), Vec::new())
                     , (r#"[{"insert": {"b": false, "i": 5}},{"delete""#.to_string(), Vec::new())
@@ -1020,6 +1584,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::InsertDelete,
                     array: true,
+                    ..Default::default()
                 },
                 vec![ (r#"[{"insert": [true, 0, "a"]}]"#.to_string(), Vec::new())
                     , (r#"[{"insert": [false, 5, "b"]},{"delete""#.to_string(), Vec::new())
@@ -1037,6 +1602,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true)],
@@ -1048,6 +1614,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"payload": {"op": "u", "before": {"b": true, "i": 123}, "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 123, None), false), (TestStruct::new(true, 0, None), true)],
@@ -1058,6 +1625,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"payload": {"op": "u", "before": [true, 123, "abc"], "after": [true, 0, "def"]}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 123, Some("abc")), false), (TestStruct::new(true, 0, Some("def")), true)],
@@ -1069,6 +1637,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![(r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}{"payload": {"op": "d", "before": {"b": false, "i": 100, "s": "foo"}}}"#.to_string(), Vec::new())],
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 100, Some("foo")), false)],
@@ -1080,6 +1649,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())
                     , (r#"{"payload": {"op": "d", "before": {"b": false, "i": 100, "s": "foo"}}}"#.to_string(), Vec::new())],
@@ -1092,6 +1662,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())
                     , (r#"{"payload": {"op": "d", "before": {"b": false, "i": 100, "s":"#.to_string(), vec![ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 61".to_string(), "{\"payload\": {\"op\": \"d\", \"before\": {\"b\": false, \"i\": 100, \"s\":", None)])],
@@ -1104,6 +1675,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())
                     , (r#"{"payload": {"op": "c", "after": {"b": false, "i": 5}}}{"payload": {"op": "d", "before": {"b": false}}}"#.to_string(), vec![ParseError::new("failed to deserialize JSON record: missing field `I` at line 1 column 12".to_string(), Some(3), None, Some("{\"b\": false}"), None, None)])],
@@ -1116,6 +1688,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())
                     , (r#"{"payload": {"op": "c", "after": {"b": false, "i": 5}}}
@@ -1130,6 +1703,7 @@ mod test {
                 JsonParserConfig {
                     update_format: JsonUpdateFormat::Debezium,
                     array: false,
+                    ..Default::default()
                 },
                 vec![ (r#"{"payload": {"op": "c", "after": {"b": true, "i": 0}}}"#.to_string(), Vec::new())
                     , (r#"{"payload": {"op": "c", "after": {"b": false, "i": 5}}}
@@ -1139,6 +1713,445 @@ mod test {
                 vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(false, 5, None), true), (TestStruct::new(false, 5, None), false)],
                 Vec::new()
             ),
+            // debezium: envelope with schema; bare payload (`schemas.enable=false`).
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Debezium,
+                    array: false,
+                    ..Default::default()
+                },
+                vec![ (r#"{"schema": {"type": "struct", "fields": []}, "payload": {"op": "r", "before": null, "after": {"b": true, "i": 0}, "source": {"table": "t"}}}"#.to_string(), Vec::new())
+                    , (r#"{"op": "u", "before": {"b": true, "i": 0}, "after": {"b": true, "i": 1}, "ts_ms": 1690000000000}"#.to_string(), Vec::new())],
+                vec![(TestStruct::new(true, 0, None), true), (TestStruct::new(true, 0, None), false), (TestStruct::new(true, 1, None), true)],
+                Vec::new()
+            ),
+            // debezium: tombstones and schema change events are skipped.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Debezium,
+                    array: false,
+                    ..Default::default()
+                },
+                vec![ (r#"{"payload": {"op": "d", "before": {"b": true, "i": 0}, "after": null}}"#.to_string(), Vec::new())
+                    , (r#"null"#.to_string(), Vec::new())
+                    , (r#"{"schema": null, "payload": null}"#.to_string(), Vec::new())
+                    , (r#"{"payload": {"source": {"table": "t"}, "databaseName": "inventory", "ddl": "ALTER TABLE t ADD COLUMN s VARCHAR(255)"}}"#.to_string(), Vec::new())],
+                vec![(TestStruct::new(true, 0, None), false)],
+                Vec::new()
+            ),
+            // debezium: update without the old record.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Debezium,
+                    array: false,
+                    ..Default::default()
+                },
+                vec![(r#"{"payload": {"op": "u", "before": null, "after": {"b": true, "i": 1}}}"#.to_string(), vec![ParseError::new("Debezium CDC update event must contain both 'before' and 'after' records; configure the CDC source to capture the complete old value of updated records".to_string(), Some(1), None, None, None, None)])],
+                Vec::new(),
+                Vec::new()
+            ),
+            // debezium: truncate.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Debezium,
+                    array: false,
+                    ..Default::default()
+                },
+                vec![(r#"{"payload": {"op": "t", "before": null, "after": null}}"#.to_string(), vec![ParseError::new("Debezium CDC truncate events are not supported".to_string(), Some(1), None, None, None, None)])],
+                Vec::new(),
+                Vec::new()
+            ),
+        ];
+
+        run_test_cases(test_cases);
+    }
+
+    #[test]
+    fn test_json_paths() {
+        let test_cases: Vec<TestCase<_>> = vec! [
+            // raw: explode nested array, one row per element.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    array: false,
+                    paths: BTreeMap::from([
+                        ("i".to_string(), "payload.id".to_string()),
+                        ("s".to_string(), "payload.items.name".to_string()),
+                    ]),
+                    explode: Some("payload.items".to_string()),
+                    datetime_formats: BTreeMap::new(),
+                    decimal_formats: BTreeMap::new(),
+                },
+                vec![ (r#"{"b": true, "payload": {"id": 5, "items": [{"name": "x"}, {"name": "y"}]}}"#.to_string(), Vec::new())
+                    , (r#"{"b": true, "payload": {"id": 6, "items": 7}}"#.to_string(), vec![ParseError::new("failed to map JSON record to table columns: value at path 'payload.items' is not an array".to_string(), Some(2), None, Some(r#"{"b": true, "payload": {"id": 6, "items": 7}}"#), None, None)])
+                    , (r#"{"b": false, "payload": {"id": 8, "items": []}}"#.to_string(), Vec::new())
+                    , (r#"{"b": false, "payload": {"id": 9}}"#.to_string(), Vec::new())],
+                vec![(TestStruct::new(true, 5, Some("x")), true), (TestStruct::new(true, 5, Some("y")), true)],
+                Vec::new()
+            ),
+            // insert_delete: JSON pointers; missing paths leave columns unset.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::InsertDelete,
+                    array: false,
+                    paths: BTreeMap::from([
+                        ("i".to_string(), "/data/0/i".to_string()),
+                        ("s".to_string(), "/data/0/s".to_string()),
+                    ]),
+                    explode: None,
+                    datetime_formats: BTreeMap::new(),
+                    decimal_formats: BTreeMap::new(),
+                },
+                vec![ (r#"{"insert": {"b": false, "data": [{"i": 1, "s": "foo"}]}}"#.to_string(), Vec::new())
+                    , (r#"{"delete": {"b": true, "data": [{"i": 2}]}}"#.to_string(), Vec::new())],
+                vec![(TestStruct::new(false, 1, Some("foo")), true), (TestStruct::new(true, 2, None), false)],
+                Vec::new()
+            ),
+        ];
+
+        run_test_cases(test_cases);
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Event {
+        id: i64,
+        ts: String,
+    }
+
+    deserialize_table_record!(Event["Event", 2] {
+        (id, "ID", false, i64, None),
+        (ts, "TS", false, String, None)
+    });
+
+    #[test]
+    fn test_json_datetime_formats() {
+        let config = JsonParserConfig {
+            update_format: JsonUpdateFormat::Raw,
+            datetime_formats: BTreeMap::from([(
+                "TS".to_string(),
+                DateTimeFormat {
+                    format: "%s".to_string(),
+                    timezone: None,
+                },
+            )]),
+            ..Default::default()
+        };
+        let format_config = FormatConfig {
+            name: Cow::from("json"),
+            config: serde_yaml::to_value(config).unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        // Values that don't match the format are passed through unmodified.
+        assert!(consumer
+            .input_fragment(b"{\"id\": 1, \"ts\": \"1700000000\"}\n{\"id\": 2, \"ts\": \"2023-01-01 00:00:00\"}\n")
+            .is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    Event {
+                        id: 1,
+                        ts: "2023-11-14 22:13:20".to_string()
+                    },
+                    true
+                ),
+                (
+                    Event {
+                        id: 2,
+                        ts: "2023-01-01 00:00:00".to_string()
+                    },
+                    true
+                ),
+            ]
+        );
+
+        // Invalid time zone.
+        let config = serde_yaml::to_value(JsonParserConfig {
+            datetime_formats: BTreeMap::from([(
+                "TS".to_string(),
+                DateTimeFormat {
+                    format: "%s".to_string(),
+                    timezone: Some("Mars/Olympus".to_string()),
+                },
+            )]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(<dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser("test", &<MockDeZSet<Event>>::new(), &config)
+            .is_err());
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Payment {
+        id: i64,
+        amount: String,
+        fee: String,
+    }
+
+    deserialize_table_record!(Payment["Payment", 3] {
+        (id, "ID", false, i64, None),
+        (amount, "AMOUNT", false, String, None),
+        (fee, "FEE", false, String, None)
+    });
+
+    #[test]
+    fn test_json_decimal_formats() {
+        let config = JsonParserConfig {
+            update_format: JsonUpdateFormat::Raw,
+            decimal_formats: BTreeMap::from([
+                (
+                    "amount".to_string(),
+                    DecimalFormat {
+                        encoding: DecimalEncoding::Number,
+                        scale: 0,
+                    },
+                ),
+                (
+                    "fee".to_string(),
+                    DecimalFormat {
+                        encoding: DecimalEncoding::ScaledInteger,
+                        scale: 2,
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+        let format_config = FormatConfig {
+            name: Cow::from("json"),
+            config: serde_yaml::to_value(config).unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        assert!(consumer
+            .input_fragment(b"{\"id\": 1, \"amount\": 12345678901234567.89, \"fee\": 105}\n{\"ID\": 2, \"AMOUNT\": \"0.10\", \"FEE\": -5}\n")
+            .is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (
+                    Payment {
+                        id: 1,
+                        amount: "12345678901234567.89".to_string(),
+                        fee: "1.05".to_string(),
+                    },
+                    true
+                ),
+                (
+                    Payment {
+                        id: 2,
+                        amount: "0.10".to_string(),
+                        fee: "-0.05".to_string(),
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_upsert() {
+        let config = JsonParserConfig {
+            update_format: JsonUpdateFormat::Upsert,
+            key_columns: vec!["i".to_string()],
+            ..Default::default()
+        };
+        let format_config = FormatConfig {
+            name: Cow::from("json"),
+            config: serde_yaml::to_value(config.clone()).unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        assert!(consumer
+            .input_fragment(
+                br#"{"b": true, "i": 1, "s": "foo"}
+{"b": false, "i": 2}
+{"b": false, "I": 1, "s": "bar"}
+"#
+            )
+            .is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (TestStruct::new(true, 1, Some("foo")), true),
+                (TestStruct::new(false, 2, None), true),
+                (TestStruct::new(true, 1, Some("foo")), false),
+                (TestStruct::new(false, 1, Some("bar")), true),
+            ]
+        );
+        outputs.state().flushed.clear();
+
+        // Records without a key are rejected.
+        assert_eq!(consumer.input_fragment(b"{\"b\": true}\n").len(), 1);
+
+        // Updates in an array that contains an invalid record are discarded,
+        // including the record following the invalid one.
+        let format_config = FormatConfig {
+            name: Cow::from("json"),
+            config: serde_yaml::to_value(JsonParserConfig {
+                array: true,
+                ..config.clone()
+            })
+            .unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+        assert!(consumer
+            .input_fragment(
+                br#"[{"b": true, "i": 1}]
+"#
+            )
+            .is_empty());
+        assert_eq!(
+            consumer
+                .input_fragment(
+                    br#"[{"b": false, "i": 1}, {"b": "x", "i": 2}]
+"#
+                )
+                .len(),
+            1
+        );
+        assert!(consumer
+            .input_fragment(
+                br#"[{"b": false, "i": 1, "s": "baz"}]
+"#
+            )
+            .is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                (TestStruct::new(true, 1, None), true),
+                (TestStruct::new(true, 1, None), false),
+                (TestStruct::new(false, 1, Some("baz")), true),
+            ]
+        );
+
+        // Key columns are required.
+        assert!(<dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(
+                "test",
+                &<MockDeZSet<TestStruct>>::new(),
+                &serde_yaml::to_value(JsonParserConfig {
+                    update_format: JsonUpdateFormat::Upsert,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_json_line_numbers() {
+        let error = |event_number, line_number| {
+            let mut error = ParseError::new(
+                "failed to deserialize JSON record: missing field `I` at line 1 column 12"
+                    .to_string(),
+                Some(event_number),
+                None,
+                Some("{\"b\": false}"),
+                None,
+                None,
+            );
+            error.set_line_number(line_number);
+            error
+        };
+
+        let test_cases: Vec<TestCase<_>> = vec![
+            // Errors in a stream of newline-delimited records are attributed to lines.
+            TestCase::new(
+                false,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    ..Default::default()
+                },
+                vec![
+                    (
+                        "{\"b\": true, \"i\": 0}\n{\"b\": false}\n".to_string(),
+                        vec![error(2, 2)],
+                    ),
+                    (
+                        "{\"b\": false, \"i\": 5}\n\n{\"b\": false}\n".to_string(),
+                        vec![error(4, 5)],
+                    ),
+                ],
+                vec![
+                    (TestStruct::new(true, 0, None), true),
+                    (TestStruct::new(false, 5, None), true),
+                ],
+                Vec::new(),
+            ),
+        ];
+
+        run_test_cases(test_cases);
+    }
+
+    #[test]
+    fn test_json_layouts() {
+        let test_cases: Vec<TestCase<_>> = vec![
+            // array layout: pretty-printed array received in fragments.
+            TestCase::new(
+                false,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    layout: JsonLayout::Array,
+                    ..Default::default()
+                },
+                vec![
+                    ("[\n  {\"b\": true, \"i\": 0},\n".to_string(), Vec::new()),
+                    ("  {\"b\": false, \"i\": 1}\n]\n".to_string(), Vec::new()),
+                ],
+                vec![
+                    (TestStruct::new(true, 0, None), true),
+                    (TestStruct::new(false, 1, None), true),
+                ],
+                Vec::new(),
+            ),
+            // object layout: pretty-printed update received in fragments.
+            TestCase::new(
+                false,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::InsertDelete,
+                    layout: JsonLayout::Object,
+                    ..Default::default()
+                },
+                vec![
+                    ("{\n  \"insert\": {\n".to_string(), Vec::new()),
+                    ("    \"b\": true, \"i\": 5\n  }\n}".to_string(), Vec::new()),
+                ],
+                vec![(TestStruct::new(true, 5, None), true)],
+                Vec::new(),
+            ),
+            // array layout: complete array in a single chunk.
+            TestCase::new(
+                true,
+                JsonParserConfig {
+                    update_format: JsonUpdateFormat::Raw,
+                    layout: JsonLayout::Array,
+                    ..Default::default()
+                },
+                vec![(
+                    "[{\"b\": true, \"i\": 0},\n {\"b\": false, \"i\": 1, \"s\": \"foo\"}]"
+                        .to_string(),
+                    Vec::new(),
+                )],
+                vec![
+                    (TestStruct::new(true, 0, None), true),
+                    (TestStruct::new(false, 1, Some("foo")), true),
+                ],
+                Vec::new(),
+            ),
         ];
 
         run_test_cases(test_cases);
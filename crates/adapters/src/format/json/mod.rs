@@ -1,9 +1,20 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{Error as DeError, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::value::RawValue;
+use std::{
+    fmt::{Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
 
 mod input;
+mod numbers;
 mod output;
 
+pub(crate) use input::JsonParser;
 pub use input::{JsonInputFormat, JsonParserConfig};
+pub(crate) use numbers::{LargeNumbersAsStrings, LenientNumbers};
 pub use output::{JsonEncoderConfig, JsonOutputFormat};
 use utoipa::ToSchema;
 
@@ -30,15 +41,21 @@ pub enum JsonUpdateFormat {
     #[serde(rename = "weighted")]
     Weighted,
 
-    /// Simplified Debezium CDC format.
+    /// Debezium CDC format.
     ///
-    /// We support a simplified version of the Debezium CDC format.  All fields
-    /// except `payload` are ignored.
+    /// Data change events produced by Debezium and serialized using the
+    /// Kafka Connect JSON converter, with or without the schema envelope
+    /// (`schemas.enable=true` or `false`).  The schema is ignored.  Only the
+    /// `op`, `before`, and `after` fields of the payload are used.
+    ///
+    /// Tombstones (`null` events) and events received from the schema
+    /// change topic are skipped.
     ///
     /// # Example
     ///
     /// ```json
     /// {"payload": {"op": "u", "before": {"b": true, "i": 123}, "after": {"b": true, "i": 0}}}
+    /// {"op": "d", "before": {"b": true, "i": 0}, "after": null}
     /// ```
     #[serde(rename = "debezium")]
     Debezium,
@@ -50,6 +67,26 @@ pub enum JsonUpdateFormat {
     /// additional envelope that gets inserted in the input table.
     #[serde(rename = "raw")]
     Raw,
+
+    /// Upsert format.
+    ///
+    /// Each element in the input stream contains a complete record, as in
+    /// the raw format.  The record replaces the previously received record
+    /// with the same values of the columns listed in `key_columns`: the
+    /// parser deletes the previous record and inserts the new one.
+    ///
+    /// The parser tracks the last received value of each key in memory.
+    /// Records inserted into the table by other connectors or before the
+    /// connector was started are not replaced.
+    ///
+    /// # Example
+    ///
+    /// ```json
+    /// {"id": 1, "status": "pending"}
+    /// {"id": 1, "status": "shipped"}
+    /// ```
+    #[serde(rename = "upsert")]
+    Upsert,
 }
 
 impl Default for JsonUpdateFormat {
@@ -58,10 +95,44 @@ impl Default for JsonUpdateFormat {
     }
 }
 
+/// Layout of a JSON message or request body.
+///
+/// Determines how the JSON documents that make up a message, e.g., the body
+/// of an HTTP request or a Kafka message, are arranged.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+pub enum JsonLayout {
+    /// Newline-delimited JSON: a sequence of JSON documents, one per line.
+    /// Each document contains an update or, when the `array` flag is set,
+    /// an array of updates.
+    #[default]
+    #[serde(rename = "ndjson")]
+    Ndjson,
+
+    /// The entire message is a single JSON array of updates, which may span
+    /// multiple lines.
+    ///
+    /// # Example
+    ///
+    /// ```json
+    /// [
+    ///   {"insert": {"b": true, "i": 0}},
+    ///   {"delete": {"b": false, "i": 100, "s": "foo"}}
+    /// ]
+    /// ```
+    #[serde(rename = "array")]
+    Array,
+
+    /// The entire message is a single update, which may span multiple lines.
+    #[serde(rename = "object")]
+    Object,
+}
+
 /// Debezium CDC operation.
 ///
 /// A record in a Debezium CDC stream contains an `op` field, which specifies
-/// one of create ("c"), delete ("d") or update ("u") operations.
+/// one of create ("c"), delete ("d"), update ("u"), read ("r"), truncate ("t")
+/// or message ("m") operations.  Read events are produced while Debezium
+/// takes the initial snapshot of a table.
 #[derive(Debug, Deserialize)]
 pub enum DebeziumOp {
     #[serde(rename = "c")]
@@ -72,6 +143,10 @@ pub enum DebeziumOp {
     Update,
     #[serde(rename = "r")]
     Read,
+    #[serde(rename = "t")]
+    Truncate,
+    #[serde(rename = "m")]
+    Message,
 }
 
 /// Debezium CDC source specification describes the origin of the record,
@@ -84,22 +159,93 @@ pub struct DebeziumSource {
 
 /// A Debezium data change event.
 ///
-/// Only the `payload` field is currently supported; other fields are ignored.
-#[derive(Debug, Deserialize)]
+/// Accepts both the envelope produced by the Kafka Connect JSON converter
+/// with `schemas.enable=true`, which consists of `schema` and `payload`
+/// fields, and the bare payload produced with `schemas.enable=false`.
+/// The `schema` field is ignored.
+#[derive(Debug)]
 pub struct DebeziumUpdate<T> {
-    payload: DebeziumPayload<T>,
+    /// `None` for tombstones, which Debezium emits after each delete event
+    /// to enable Kafka log compaction.
+    payload: Option<DebeziumPayload<T>>,
+}
+
+impl<'de, T> Deserialize<'de> for DebeziumUpdate<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct UpdateVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for UpdateVisitor<T>
+        where
+            T: Deserialize<'de>,
+        {
+            type Value = DebeziumUpdate<T>;
+
+            fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+                formatter.write_str("a Debezium data change event")
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: DeError,
+            {
+                Ok(DebeziumUpdate { payload: None })
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                // Payload wrapped in an envelope.
+                let mut payload: Option<Option<DebeziumPayload<T>>> = None;
+                // Bare payload.
+                let mut bare = DebeziumPayload {
+                    op: None,
+                    before: None,
+                    after: None,
+                    ddl: None,
+                };
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "payload" => payload = Some(map.next_value()?),
+                        "op" => bare.op = map.next_value()?,
+                        "before" => bare.before = map.next_value()?,
+                        "after" => bare.after = map.next_value()?,
+                        "ddl" => bare.ddl = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(DebeziumUpdate {
+                    payload: payload.unwrap_or(Some(bare)),
+                })
+            }
+        }
+
+        deserializer.deserialize_any(UpdateVisitor(PhantomData))
+    }
 }
 
 /// Schema of the `payload` field of a Debezium data change event.
 #[derive(Debug, Deserialize)]
 pub struct DebeziumPayload<T> {
     // source: Option<DebeziumSource>,
-    #[allow(dead_code)]
-    op: DebeziumOp,
+    /// Missing in schema change events.
+    op: Option<DebeziumOp>,
     /// When present and not `null`, this field specifies a record to be deleted from the table.
     before: Option<T>,
     /// When present and not `null`, this field specifies a record to be inserted to the table.
     after: Option<T>,
+    /// DDL statement of a schema change event.
+    ddl: Option<IgnoredAny>,
 }
 
 /// A data change event in the insert/delete format.
@@ -130,3 +276,59 @@ pub struct WeightedUpdate<T: ?Sized> {
     weight: i64,
     data: T,
 }
+
+/// JSON object with unparsed field values, in the order in which they appear
+/// in the document.
+///
+/// Used to rewrite individual fields of a record without converting the
+/// remaining fields, and in particular numbers, to `serde_json::Value`.
+pub(crate) struct RawObject(pub(crate) Vec<(String, Box<RawValue>)>);
+
+impl RawObject {
+    /// Value of field `name`, matched case-insensitively.
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut Box<RawValue>> {
+        self.0
+            .iter_mut()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawObject {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RawObjectVisitor;
+
+        impl<'de> Visitor<'de> for RawObjectVisitor {
+            type Value = RawObject;
+
+            fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut fields = Vec::with_capacity(map.size_hint().unwrap_or_default());
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(RawObject(fields))
+            }
+        }
+
+        deserializer.deserialize_map(RawObjectVisitor)
+    }
+}
+
+impl Serialize for RawObject {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
@@ -0,0 +1,806 @@
+//! Serde adapters that encode large numbers as JSON strings.
+//!
+//! JavaScript represents all numbers as 64-bit floats, which can't represent
+//! integers above 2^53 exactly, so JavaScript clients silently corrupt 64-bit
+//! integers received as JSON numbers.  [`LargeNumbersAsStrings`] makes the
+//! JSON serializer encode such integers as strings, and [`LenientNumbers`]
+//! allows the JSON deserializer to parse integers from either form.
+//!
+//! [`LenientNumbers`] also decodes binary values from base64 strings, which
+//! is how binary values are encoded in JSON.
+
+use crate::format::BinaryEncoding;
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Unexpected, Visitor},
+    ser::{self, Serialize, Serializer},
+};
+use std::fmt;
+
+/// Serializer adapter that encodes 64-bit and 128-bit integers as strings.
+pub(crate) struct LargeNumbersAsStrings<S>(pub S);
+
+/// Value serialized with [`LargeNumbersAsStrings`].
+struct AsStrings<'a, T: ?Sized>(&'a T);
+
+impl<'a, T> Serialize for AsStrings<'a, T>
+where
+    T: ?Sized + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(LargeNumbersAsStrings(serializer))
+    }
+}
+
+/// Compound serializer that serializes its elements with
+/// [`LargeNumbersAsStrings`].
+pub(crate) struct Compound<C>(C);
+
+macro_rules! forward_serialize {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(fn $method(self, v: $type) -> Result<Self::Ok, Self::Error> {
+            self.0.$method(v)
+        })*
+    };
+}
+
+macro_rules! serialize_as_string {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(fn $method(self, v: $type) -> Result<Self::Ok, Self::Error> {
+            self.0.serialize_str(&v.to_string())
+        })*
+    };
+}
+
+impl<S> Serializer for LargeNumbersAsStrings<S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    forward_serialize!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    serialize_as_string!(
+        serialize_i64(i64),
+        serialize_u64(u64),
+        serialize_i128(i128),
+        serialize_u128(u128),
+    );
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_some(&AsStrings(value))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_newtype_struct(name, &AsStrings(value))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0
+            .serialize_newtype_variant(name, variant_index, variant, &AsStrings(value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.0.serialize_seq(len).map(Compound)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.0.serialize_tuple(len).map(Compound)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.0.serialize_tuple_struct(name, len).map(Compound)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.0
+            .serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.0.serialize_map(len).map(Compound)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.0.serialize_struct(name, len).map(Compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.0
+            .serialize_struct_variant(name, variant_index, variant, len)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+impl<C> ser::SerializeSeq for Compound<C>
+where
+    C: ser::SerializeSeq,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_element(&AsStrings(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C> ser::SerializeTuple for Compound<C>
+where
+    C: ser::SerializeTuple,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_element(&AsStrings(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C> ser::SerializeTupleStruct for Compound<C>
+where
+    C: ser::SerializeTupleStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_field(&AsStrings(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C> ser::SerializeMap for Compound<C>
+where
+    C: ser::SerializeMap,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // JSON map keys are always strings.
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_value(&AsStrings(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C> ser::SerializeStruct for Compound<C>
+where
+    C: ser::SerializeStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_field(key, &AsStrings(value))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.0.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+/// Deserializer adapter that accepts integers encoded as either numbers or
+/// strings and binary values encoded as base64 strings.
+pub(crate) struct LenientNumbers<D>(pub D);
+
+/// How [`LenientVisitor`] handles strings.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Strings {
+    /// Forward strings to the wrapped visitor.
+    Forward,
+
+    /// Parse strings as integers.
+    Integer,
+
+    /// Decode strings as base64-encoded binary values.
+    Base64,
+}
+
+/// Visitor that forwards values to the wrapped visitor, converting strings
+/// as specified by `strings`.
+struct LenientVisitor<V> {
+    visitor: V,
+    strings: Strings,
+}
+
+impl<V> LenientVisitor<V> {
+    fn new(visitor: V) -> Self {
+        Self {
+            visitor,
+            strings: Strings::Forward,
+        }
+    }
+
+    fn integer(visitor: V) -> Self {
+        Self {
+            visitor,
+            strings: Strings::Integer,
+        }
+    }
+
+    fn bytes(visitor: V) -> Self {
+        Self {
+            visitor,
+            strings: Strings::Base64,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident),* $(,)?) => {
+        $(fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.0.$method(LenientVisitor::new(visitor))
+        })*
+    };
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident),* $(,)?) => {
+        $(fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.0.deserialize_any(LenientVisitor::integer(visitor))
+        })*
+    };
+}
+
+impl<'de, D> Deserializer<'de> for LenientNumbers<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_deserialize!(
+        deserialize_any,
+        deserialize_bool,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    );
+
+    deserialize_integer!(
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+    );
+
+    // Binary values are encoded as base64 strings.  Arrays of bytes are
+    // accepted too.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_any(LenientVisitor::bytes(visitor))
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_any(LenientVisitor::bytes(visitor))
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .deserialize_unit_struct(name, LenientVisitor::new(visitor))
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .deserialize_newtype_struct(name, LenientVisitor::new(visitor))
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_tuple(len, LenientVisitor::new(visitor))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .deserialize_tuple_struct(name, len, LenientVisitor::new(visitor))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .deserialize_struct(name, fields, LenientVisitor::new(visitor))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(fn $method<E>(self, v: $type) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            self.visitor.$method(v)
+        })*
+    };
+}
+
+impl<V> LenientVisitor<V> {
+    /// Convert `v` as specified by `self.strings` and pass it to the wrapped
+    /// visitor.
+    fn parse<'de, E>(self, v: &str) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        if self.strings == Strings::Base64 {
+            return BinaryEncoding::Base64
+                .decode(v.as_bytes())
+                .map_err(|e| E::custom(e.to_string()))
+                .and_then(|bytes| self.visitor.visit_byte_buf(bytes));
+        }
+
+        let s = v.trim();
+        if let Ok(i) = s.parse::<i64>() {
+            self.visitor.visit_i64(i)
+        } else if let Ok(u) = s.parse::<u64>() {
+            self.visitor.visit_u64(u)
+        } else if let Ok(i) = s.parse::<i128>() {
+            self.visitor.visit_i128(i)
+        } else if let Ok(u) = s.parse::<u128>() {
+            self.visitor.visit_u128(u)
+        } else {
+            Err(E::invalid_type(Unexpected::Str(v), &self.visitor))
+        }
+    }
+}
+
+impl<'de, V> Visitor<'de> for LenientVisitor<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    forward_visit!(
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    );
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if self.strings == Strings::Forward {
+            self.visitor.visit_str(v)
+        } else {
+            self.parse(v)
+        }
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if self.strings == Strings::Forward {
+            self.visitor.visit_borrowed_str(v)
+        } else {
+            self.parse(v)
+        }
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if self.strings == Strings::Forward {
+            self.visitor.visit_string(v)
+        } else {
+            self.parse(&v)
+        }
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.visitor.visit_some(LenientNumbers(deserializer))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.visitor
+            .visit_newtype_struct(LenientNumbers(deserializer))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visitor.visit_seq(LenientAccess(seq))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visitor.visit_map(LenientAccess(map))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.visitor.visit_enum(data)
+    }
+}
+
+/// Sequence or map whose elements are deserialized with [`LenientNumbers`].
+struct LenientAccess<A>(A);
+
+/// Seed that deserializes its value with [`LenientNumbers`].
+struct LenientSeed<S>(S);
+
+impl<'de, S> DeserializeSeed<'de> for LenientSeed<S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize(LenientNumbers(deserializer))
+    }
+}
+
+impl<'de, A> SeqAccess<'de> for LenientAccess<A>
+where
+    A: SeqAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.0.next_element_seed(LenientSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, A> MapAccess<'de> for LenientAccess<A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.0.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.0.next_value_seed(LenientSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LargeNumbersAsStrings, LenientNumbers};
+    use bstr::BString;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct TestStruct {
+        i: i32,
+        l: i64,
+        u: Option<u64>,
+        v: Vec<i64>,
+        s: String,
+    }
+
+    fn to_json<T: Serialize>(val: &T) -> String {
+        let mut buf = Vec::new();
+        val.serialize(LargeNumbersAsStrings(&mut serde_json::Serializer::new(
+            &mut buf,
+        )))
+        .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn from_json<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T, String> {
+        T::deserialize(LenientNumbers(&mut serde_json::Deserializer::from_str(
+            json,
+        )))
+        .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn large_numbers_as_strings() {
+        let val = TestStruct {
+            i: 5,
+            l: 9007199254740993,
+            u: Some(u64::MAX),
+            v: vec![1, -1],
+            s: "10".to_string(),
+        };
+        let json = to_json(&val);
+        assert_eq!(
+            json,
+            r#"{"i":5,"l":"9007199254740993","u":"18446744073709551615","v":["1","-1"],"s":"10"}"#
+        );
+        assert_eq!(from_json::<TestStruct>(&json), Ok(val));
+    }
+
+    #[test]
+    fn lenient_numbers() {
+        assert_eq!(
+            from_json::<TestStruct>(r#"{"i":"5","l":-10,"u":null,"v":[1,"2"],"s":"10"}"#),
+            Ok(TestStruct {
+                i: 5,
+                l: -10,
+                u: None,
+                v: vec![1, 2],
+                s: "10".to_string(),
+            })
+        );
+        assert!(from_json::<TestStruct>(r#"{"i":"foo","l":0,"u":null,"v":[],"s":""}"#).is_err());
+        // Strings are not parsed as numbers.
+        assert!(from_json::<TestStruct>(r#"{"i":0,"l":0,"u":null,"v":[],"s":10}"#).is_err());
+    }
+
+    #[test]
+    fn base64_bytes() {
+        #[derive(Debug, PartialEq, Eq, Deserialize)]
+        struct Binary {
+            b: BString,
+            o: Option<BString>,
+        }
+
+        assert_eq!(
+            from_json::<Binary>(r#"{"b":"3q2+7w==","o":[1,2]}"#),
+            Ok(Binary {
+                b: BString::from(vec![0xde, 0xad, 0xbe, 0xef]),
+                o: Some(BString::from(vec![1, 2])),
+            })
+        );
+        assert_eq!(
+            from_json::<Binary>(r#"{"b":"","o":null}"#),
+            Ok(Binary {
+                b: BString::from(vec![]),
+                o: None,
+            })
+        );
+        assert!(from_json::<Binary>(r#"{"b":"not base64!","o":null}"#).is_err());
+    }
+}
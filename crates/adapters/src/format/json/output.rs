@@ -1,15 +1,21 @@
+use super::{JsonLayout, RawObject};
 use crate::{
-    catalog::{RecordFormat, SerBatch},
+    catalog::{JsonFlavor, RecordFormat, SerBatch},
+    format::{
+        datetime::{DateTimeConverter, DateTimeFormat},
+        decimal::{DecimalConverter, DecimalFormat},
+    },
     util::truncate_ellipse,
     ControllerError, Encoder, OutputConsumer, OutputFormat,
 };
 use actix_web::HttpRequest;
-use anyhow::{bail, Result as AnyResult};
+use anyhow::{anyhow, bail, Result as AnyResult};
 use erased_serde::Serialize as ErasedSerialize;
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_urlencoded::Deserializer as UrlDeserializer;
 use serde_yaml::Value as YamlValue;
-use std::{borrow::Cow, mem::take, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, mem::take, sync::Arc};
 use utoipa::ToSchema;
 
 /// JSON format encoder.
@@ -37,6 +43,34 @@ pub struct JsonEncoderConfig {
     buffer_size_records: usize,
     #[serde(default)]
     array: bool,
+    /// Layout of each buffer sent to the transport.
+    ///
+    /// `array` wraps the records in each buffer in a JSON array (equivalent
+    /// to setting the `array` flag); `object` sends each record in a
+    /// separate buffer, e.g., as a separate Kafka message.
+    #[serde(default)]
+    layout: JsonLayout,
+    /// Encode `BIGINT` and `DECIMAL` values as JSON strings rather than
+    /// numbers.
+    ///
+    /// JavaScript clients cannot represent integers above 2^53 exactly and
+    /// silently corrupt such values when they are received as JSON numbers.
+    /// Note that natively compiled pipelines always encode `DECIMAL` values
+    /// as strings.
+    #[serde(default)]
+    large_numbers_as_strings: bool,
+    /// Output formats of `DATE`, `TIME`, and `TIMESTAMP` columns, indexed
+    /// by column name.
+    ///
+    /// Columns not listed here are encoded in the default ISO format.
+    #[serde(default)]
+    datetime_formats: BTreeMap<String, DateTimeFormat>,
+    /// Output encodings of `DECIMAL` columns, indexed by column name.
+    ///
+    /// Columns not listed here are encoded as strings, or as numbers in
+    /// JIT-compiled pipelines unless `large_numbers_as_strings` is set.
+    #[serde(default)]
+    decimal_formats: BTreeMap<String, DecimalFormat>,
 }
 
 impl OutputFormat for JsonOutputFormat {
@@ -61,7 +95,11 @@ impl OutputFormat for JsonOutputFormat {
         // TODO: When we support raw output mode (no chunks), check whatever http
         // request field we use to choose the mode and only override the `array`
         // flag in the chunked mode.
-        config.array = true;
+        // Chunks in the `object` layout contain a single record, which is
+        // already a valid JSON document.
+        if config.layout != JsonLayout::Object {
+            config.array = true;
+        }
         Ok(Box::new(config))
     }
 
@@ -71,6 +109,12 @@ impl OutputFormat for JsonOutputFormat {
         consumer: Box<dyn OutputConsumer>,
     ) -> AnyResult<Box<dyn Encoder>> {
         let config = JsonEncoderConfig::deserialize(config)?;
+        for format in config.datetime_formats.values() {
+            DateTimeConverter::new(format)?;
+        }
+        for format in config.decimal_formats.values() {
+            DecimalConverter::new(format)?;
+        }
 
         Ok(Box::new(JsonEncoder::new(consumer, config)))
     }
@@ -82,19 +126,88 @@ struct JsonEncoder {
     config: JsonEncoderConfig,
     buffer: Vec<u8>,
     max_buffer_size: usize,
+
+    /// Converters for columns listed in `config.datetime_formats`.
+    datetime: Vec<(String, DateTimeConverter)>,
+    /// Converters for columns listed in `config.decimal_formats`.
+    decimal: Vec<(String, DecimalConverter)>,
 }
 
 impl JsonEncoder {
-    fn new(output_consumer: Box<dyn OutputConsumer>, config: JsonEncoderConfig) -> Self {
+    fn new(output_consumer: Box<dyn OutputConsumer>, mut config: JsonEncoderConfig) -> Self {
         let max_buffer_size = output_consumer.max_buffer_size_bytes();
 
+        match config.layout {
+            JsonLayout::Ndjson => (),
+            JsonLayout::Array => config.array = true,
+            JsonLayout::Object => {
+                config.array = false;
+                config.buffer_size_records = 1;
+            }
+        }
+
+        // Formats are validated by `new_encoder`.
+        let datetime = config
+            .datetime_formats
+            .iter()
+            .filter_map(|(column, format)| {
+                Some((column.clone(), DateTimeConverter::new(format).ok()?))
+            })
+            .collect();
+        let decimal = config
+            .decimal_formats
+            .iter()
+            .filter_map(|(column, format)| {
+                Some((column.clone(), DecimalConverter::new(format).ok()?))
+            })
+            .collect();
+
         Self {
             output_consumer,
             config,
             buffer: Vec::new(),
             max_buffer_size,
+            datetime,
+            decimal,
         }
     }
+
+    /// Re-encode `record` with custom date/time formats and decimal
+    /// encodings and append it to `buffer`.
+    fn format_columns(&self, record: &[u8], buffer: &mut Vec<u8>) -> AnyResult<()> {
+        let mut record: RawObject = serde_json::from_slice(record)?;
+
+        // Column names are case-insensitive.
+        for (column, converter) in self.datetime.iter() {
+            if let Some(value) = record.get_mut(column) {
+                if let Ok(s) = serde_json::from_str::<String>(value.get()) {
+                    let formatted = converter
+                        .format(&s)
+                        .map_err(|e| anyhow!("error encoding column '{column}': {e}"))?;
+                    *value = RawValue::from_string(serde_json::to_string(&formatted)?)?;
+                }
+            }
+        }
+
+        for (column, converter) in self.decimal.iter() {
+            if let Some(value) = record.get_mut(column) {
+                // Decimals are serialized as strings or, by JIT-compiled
+                // pipelines, as numbers.
+                let s = match serde_json::from_str::<String>(value.get()) {
+                    Ok(s) => s,
+                    Err(_) if value.get() == "null" => continue,
+                    Err(_) => value.get().to_string(),
+                };
+                let formatted = converter
+                    .format_json(&s)
+                    .map_err(|e| anyhow!("error encoding column '{column}': {e}"))?;
+                *value = RawValue::from_string(formatted)?;
+            }
+        }
+
+        serde_json::to_writer(buffer, &record)?;
+        Ok(())
+    }
 }
 
 impl Encoder for JsonEncoder {
@@ -112,9 +225,13 @@ impl Encoder for JsonEncoder {
             self.max_buffer_size
         };
 
+        let flavor = JsonFlavor {
+            large_numbers_as_strings: self.config.large_numbers_as_strings,
+        };
+
         let mut num_records = 0;
         for batch in batches.iter() {
-            let mut cursor = batch.cursor(RecordFormat::Json)?;
+            let mut cursor = batch.cursor(RecordFormat::Json(flavor))?;
 
             while cursor.key_valid() {
                 let mut w = cursor.weight();
@@ -152,7 +269,13 @@ impl Encoder for JsonEncoder {
                     } else {
                         buffer.extend_from_slice(br#"{"delete":"#);
                     }
-                    cursor.serialize_key(&mut buffer)?;
+                    if self.datetime.is_empty() && self.decimal.is_empty() {
+                        cursor.serialize_key(&mut buffer)?;
+                    } else {
+                        let mut record = Vec::new();
+                        cursor.serialize_key(&mut record)?;
+                        self.format_columns(&record, &mut buffer)?;
+                    }
                     buffer.push(b'}');
 
                     // Drop the last encoded record if it exceeds max_buffer_size.
@@ -213,21 +336,33 @@ impl Encoder for JsonEncoder {
 
 #[cfg(test)]
 mod test {
-    use super::{JsonEncoder, JsonEncoderConfig};
+    use super::{JsonEncoder, JsonEncoderConfig, JsonLayout};
     use crate::{
         catalog::SerBatch,
-        format::{json::InsDelUpdate, Encoder},
+        format::{
+            json::InsDelUpdate, DateTimeFormat, DecimalEncoding, DecimalFormat, Encoder,
+            LenientNumbers,
+        },
         static_compile::seroutput::SerBatchImpl,
         test::{MockOutputConsumer, TestStruct},
     };
     use dbsp::{trace::Batch, IndexedZSet, OrdZSet};
     use log::trace;
-    use std::sync::Arc;
-
-    fn test_json(array: bool, batches: Vec<Vec<(TestStruct, i64)>>) {
+    use serde::Deserialize;
+    use std::{collections::BTreeMap, sync::Arc};
+
+    fn test_json(
+        array: bool,
+        large_numbers_as_strings: bool,
+        batches: Vec<Vec<(TestStruct, i64)>>,
+    ) {
         let config = JsonEncoderConfig {
             buffer_size_records: 3,
             array,
+            layout: Default::default(),
+            large_numbers_as_strings,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
         };
 
         let consumer = MockOutputConsumer::new();
@@ -278,17 +413,22 @@ mod test {
             std::str::from_utf8(&consumer_data.lock().unwrap()).unwrap()
         );
 
-        let actual_output = if array {
-            serde_json::Deserializer::from_slice(&consumer_data.lock().unwrap())
-                .into_iter::<Vec<InsDelUpdate<TestStruct>>>()
-                .flat_map(|item| item.unwrap())
-                .collect::<Vec<_>>()
-        } else {
-            serde_json::Deserializer::from_slice(&consumer_data.lock().unwrap())
-                .into_iter::<InsDelUpdate<TestStruct>>()
-                .map(|item| item.unwrap())
-                .collect::<Vec<_>>()
-        };
+        let consumer_data = consumer_data.lock().unwrap();
+        let mut deserializer = serde_json::Deserializer::from_slice(&consumer_data);
+        let mut actual_output = Vec::new();
+        while deserializer.end().is_err() {
+            if array {
+                actual_output.extend(
+                    <Vec<InsDelUpdate<TestStruct>>>::deserialize(LenientNumbers(&mut deserializer))
+                        .unwrap(),
+                );
+            } else {
+                actual_output.push(
+                    <InsDelUpdate<TestStruct>>::deserialize(LenientNumbers(&mut deserializer))
+                        .unwrap(),
+                );
+            }
+        }
 
         assert_eq!(actual_output, expected_output);
     }
@@ -363,6 +503,10 @@ mod test {
         let config = JsonEncoderConfig {
             buffer_size_records: 3,
             array: false,
+            layout: Default::default(),
+            large_numbers_as_strings: false,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
         };
 
         let consumer = MockOutputConsumer::with_max_buffer_size_bytes(32);
@@ -377,12 +521,177 @@ mod test {
 
     #[test]
     fn test_ndjson() {
-        test_json(false, test_data());
+        test_json(false, false, test_data());
     }
 
     #[test]
     fn test_arrayjson() {
-        test_json(true, test_data());
+        test_json(true, false, test_data());
+    }
+
+    #[test]
+    fn test_large_numbers_as_strings() {
+        test_json(true, true, test_data());
+
+        let config = JsonEncoderConfig {
+            buffer_size_records: 3,
+            array: false,
+            layout: Default::default(),
+            large_numbers_as_strings: true,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
+        };
+
+        let consumer = MockOutputConsumer::new();
+        let consumer_data = consumer.data.clone();
+        let mut encoder = JsonEncoder::new(Box::new(consumer), config);
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 0,
+                    b: true,
+                    i: Some(9007199254740993),
+                    s: "foo".to_string(),
+                },
+                1,
+            )],
+        );
+        encoder
+            .encode(&[Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>])
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&consumer_data.lock().unwrap()).unwrap(),
+            "{\"insert\":{\"id\":0,\"b\":true,\"i\":\"9007199254740993\",\"s\":\"foo\"}}"
+        );
+    }
+
+    #[test]
+    fn test_object_layout() {
+        let config = JsonEncoderConfig {
+            buffer_size_records: 3,
+            array: true,
+            layout: JsonLayout::Object,
+            large_numbers_as_strings: false,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::new(),
+        };
+
+        let consumer = MockOutputConsumer::new();
+        let consumer_data = consumer.data.clone();
+        let mut encoder = JsonEncoder::new(Box::new(consumer), config);
+        let zset = OrdZSet::from_keys((), test_data()[0].clone());
+        encoder
+            .encode(&[Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>])
+            .unwrap();
+
+        // Each record is pushed as a separate, unwrapped JSON object.
+        let consumer_data = consumer_data.lock().unwrap();
+        let updates = serde_json::Deserializer::from_slice(&consumer_data)
+            .into_iter::<serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(updates.len(), test_data()[0].len());
+        assert!(updates.iter().all(|update| update.is_object()));
+    }
+
+    #[test]
+    fn test_datetime_formats() {
+        let config = JsonEncoderConfig {
+            buffer_size_records: 3,
+            array: false,
+            layout: Default::default(),
+            large_numbers_as_strings: false,
+            datetime_formats: BTreeMap::from([(
+                "S".to_string(),
+                DateTimeFormat {
+                    format: "%d/%m/%Y %H:%M".to_string(),
+                    timezone: Some("+02:00".to_string()),
+                },
+            )]),
+            decimal_formats: BTreeMap::new(),
+        };
+
+        let consumer = MockOutputConsumer::new();
+        let consumer_data = consumer.data.clone();
+        let mut encoder = JsonEncoder::new(Box::new(consumer), config);
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 0,
+                    b: true,
+                    i: None,
+                    s: "2023-12-30 23:30:00".to_string(),
+                },
+                1,
+            )],
+        );
+        encoder
+            .encode(&[Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>])
+            .unwrap();
+        let update: serde_json::Value =
+            serde_json::from_slice(&consumer_data.lock().unwrap()).unwrap();
+        assert_eq!(update["insert"]["s"], "31/12/2023 01:30");
+
+        // Values that cannot be formatted are reported as errors.
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "foo".to_string(),
+                },
+                1,
+            )],
+        );
+        assert!(encoder
+            .encode(&[Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>])
+            .is_err());
+    }
+
+    #[test]
+    fn test_decimal_formats() {
+        let config = JsonEncoderConfig {
+            buffer_size_records: 3,
+            array: false,
+            layout: Default::default(),
+            large_numbers_as_strings: false,
+            datetime_formats: BTreeMap::new(),
+            decimal_formats: BTreeMap::from([(
+                "S".to_string(),
+                DecimalFormat {
+                    encoding: DecimalEncoding::ScaledInteger,
+                    scale: 2,
+                },
+            )]),
+        };
+
+        let consumer = MockOutputConsumer::new();
+        let consumer_data = consumer.data.clone();
+        let mut encoder = JsonEncoder::new(Box::new(consumer), config);
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(
+                TestStruct {
+                    id: 0,
+                    b: true,
+                    i: None,
+                    s: "12345678901234567.80".to_string(),
+                },
+                1,
+            )],
+        );
+        encoder
+            .encode(&[Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>])
+            .unwrap();
+        // Field order and the exact value are preserved.
+        assert_eq!(
+            std::str::from_utf8(&consumer_data.lock().unwrap()).unwrap(),
+            "{\"insert\":{\"id\":0,\"b\":true,\"i\":null,\"s\":1234567890123456780}}"
+        );
     }
 
     use crate::test::generate_test_batches_with_weights;
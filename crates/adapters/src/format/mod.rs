@@ -1,4 +1,4 @@
-use crate::{catalog::SerBatch, ControllerError, DeCollectionHandle};
+use crate::{catalog::SerBatch, util::truncate_ellipse, ControllerError, DeCollectionHandle};
 use actix_web::HttpRequest;
 use anyhow::Result as AnyResult;
 use erased_serde::Serialize as ErasedSerialize;
@@ -13,20 +13,69 @@ use std::{
     sync::Arc,
 };
 
+#[cfg(feature = "with-arrow")]
+mod arrow;
+mod auto;
+#[cfg(feature = "with-avro")]
+pub(crate) mod avro;
+mod binary;
 pub(crate) mod csv;
+mod datetime;
+mod decimal;
 mod deserializer;
 mod json;
-
-pub use self::{
-    csv::{
-        byte_record_deserializer, string_record_deserializer, CsvEncoderConfig, CsvParserConfig,
-    },
-    deserializer::FieldParseError,
-    json::{JsonEncoderConfig, JsonParserConfig, JsonUpdateFormat},
+#[cfg(feature = "with-parquet")]
+mod parquet;
+pub mod plugin;
+#[cfg(feature = "with-protobuf")]
+mod protobuf;
+mod raw;
+mod sql;
+mod text;
+
+#[cfg(feature = "with-arrow")]
+pub use self::arrow::ArrowEncoderConfig;
+#[cfg(feature = "with-arrow")]
+use self::arrow::ArrowOutputFormat;
+#[cfg(feature = "with-avro")]
+pub use self::avro::{
+    AvroEncoderConfig, AvroParserConfig, AvroUpdateFormat, SchemaRegistryConfig,
+    SubjectNameStrategy,
 };
+#[cfg(feature = "with-avro")]
+use self::avro::{AvroInputFormat, AvroOutputFormat};
+pub(crate) use self::binary::{AsText, BinaryAsText};
+pub(crate) use self::json::{LargeNumbersAsStrings, LenientNumbers};
+#[cfg(feature = "with-parquet")]
+use self::parquet::ParquetOutputFormat;
+#[cfg(feature = "with-parquet")]
+pub use self::parquet::{ParquetCompression, ParquetEncoderConfig, ParquetOutputMode};
+#[cfg(feature = "with-protobuf")]
+pub use self::protobuf::ProtobufConfig;
+#[cfg(feature = "with-protobuf")]
+use self::protobuf::{ProtobufInputFormat, ProtobufOutputFormat};
 use self::{
+    auto::AutoInputFormat,
     csv::{CsvInputFormat, CsvOutputFormat},
     json::{JsonInputFormat, JsonOutputFormat},
+    raw::RawInputFormat,
+    sql::SqlOutputFormat,
+    text::TextInputFormat,
+};
+pub use self::{
+    auto::AutoParserConfig,
+    binary::BinaryEncoding,
+    csv::{
+        byte_record_deserializer, string_record_deserializer, CsvEncoderConfig, CsvParserConfig,
+    },
+    datetime::DateTimeFormat,
+    decimal::{DecimalEncoding, DecimalFormat},
+    deserializer::{sql_type_name, FieldParseError},
+    json::{JsonEncoderConfig, JsonLayout, JsonParserConfig, JsonUpdateFormat},
+    plugin::{register_input_format, register_output_format},
+    raw::{RawEncoding, RawParserConfig},
+    sql::SqlEncoderConfig,
+    text::{FixedWidthColumn, TextLayout, TextParserConfig},
 };
 
 /// Error parsing input data.
@@ -118,8 +167,29 @@ impl ParseError {
             suggestion,
         )))
     }
+
+    /// Attribute the error to line `line_number` of the input stream.
+    pub fn set_line_number(&mut self, line_number: u64) {
+        self.0.line_number = Some(line_number);
+    }
+
+    /// Record the raw value of the field that failed to parse and its
+    /// expected SQL type.
+    pub fn set_field_value(&mut self, field_value: String, expected_type: Option<String>) {
+        self.0.field_value =
+            Some(truncate_ellipse(&field_value, MAX_INVALID_TEXT_LEN, "...").to_string());
+        self.0.expected_type = expected_type;
+    }
+
+    pub fn set_suggestion(&mut self, suggestion: Cow<'static, str>) {
+        self.0.suggestion = Some(suggestion);
+    }
 }
 
+/// When including a long fragment of invalid input in a parse error,
+/// truncate it to `MAX_INVALID_TEXT_LEN` bytes.
+const MAX_INVALID_TEXT_LEN: usize = 4096;
+
 #[derive(Clone, Debug, Serialize, PartialEq, Eq)]
 pub struct ParseErrorInner {
     /// Error description.
@@ -134,12 +204,29 @@ pub struct ParseErrorInner {
     /// block of events unparseable.
     event_number: Option<u64>,
 
+    /// Line number (starting from 1) of the event that caused the error,
+    /// relative to the start of the stream.
+    ///
+    /// Only set by text-based formats for input received as a continuous
+    /// stream of text, e.g., the body of an HTTP request, and not for
+    /// transports that deliver input in separate messages.
+    line_number: Option<u64>,
+
     /// Field that failed to parse.
     ///
     /// Only set when the parsing error can be attributed to a
     /// specific field.
     field: Option<String>,
 
+    /// Raw value of the field that failed to parse.
+    ///
+    /// Only set by formats that can extract the value of an individual
+    /// field from an invalid record, e.g., CSV.
+    field_value: Option<String>,
+
+    /// SQL type of the field that failed to parse, e.g., `BIGINT NOT NULL`.
+    expected_type: Option<String>,
+
     /// Invalid fragment of input data.
     ///
     /// Used for binary data formats and for text-based formats when the input
@@ -158,10 +245,13 @@ pub struct ParseErrorInner {
 
 impl Display for ParseErrorInner {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
-        let event = if let Some(event_number) = self.event_number {
-            format!(" (event #{})", event_number)
-        } else {
-            String::new()
+        let event = match (self.event_number, self.line_number) {
+            (Some(event_number), Some(line_number)) => {
+                format!(" (event #{event_number}, line {line_number})")
+            }
+            (Some(event_number), None) => format!(" (event #{})", event_number),
+            (None, Some(line_number)) => format!(" (line {line_number})"),
+            (None, None) => String::new(),
         };
 
         let invalid_fragment = if let Some(invalid_bytes) = &self.invalid_bytes {
@@ -172,6 +262,14 @@ impl Display for ParseErrorInner {
             String::new()
         };
 
+        let invalid_value = match (&self.field_value, &self.expected_type) {
+            (Some(field_value), Some(expected_type)) => {
+                format!("\nInvalid value: '{field_value}' (expected {expected_type})")
+            }
+            (Some(field_value), None) => format!("\nInvalid value: '{field_value}'"),
+            (None, _) => String::new(),
+        };
+
         let suggestion = if let Some(suggestion) = &self.suggestion {
             format!("\n{suggestion}")
         } else {
@@ -180,7 +278,7 @@ impl Display for ParseErrorInner {
 
         write!(
             f,
-            "Parse error{event}: {}{invalid_fragment}{suggestion}",
+            "Parse error{event}: {}{invalid_value}{invalid_fragment}{suggestion}",
             self.description
         )
     }
@@ -198,8 +296,12 @@ impl ParseErrorInner {
         Self {
             description,
             event_number,
+            line_number: None,
             field,
-            invalid_text: invalid_text.map(str::to_string),
+            field_value: None,
+            expected_type: None,
+            invalid_text: invalid_text
+                .map(|text| truncate_ellipse(text, MAX_INVALID_TEXT_LEN, "...").to_string()),
             invalid_bytes: invalid_bytes.map(ToOwned::to_owned),
             suggestion,
         }
@@ -220,15 +322,8 @@ impl ParseErrorInner {
         // Try to parse the error as `FieldParseError`.  If this is not a field-specific error or
         // the error was not returned by the `deserialize_table_record` macro, this will fail and
         // we'll store the error as is.
-        let (descr, field) = if let Some(offset) = err_str.find("{\"field\":") {
-            if let Some(Ok(err)) = serde_json::Deserializer::from_str(&err_str[offset..])
-                .into_iter::<FieldParseError>()
-                .next()
-            {
-                (err.description, Some(err.field))
-            } else {
-                (err_str, None)
-            }
+        let (descr, field) = if let Some(err) = FieldParseError::from_error_str(&err_str) {
+            (err.description, Some(err.field))
         } else {
             (err_str, None)
         };
@@ -302,21 +397,52 @@ impl ParseErrorInner {
     }
 }
 
-/// Static map of supported input formats.
+/// Static map of built-in input formats.
+///
+/// Formats registered at runtime are tracked separately in
+/// [`plugin`](self::plugin).
 // TODO: support for registering new formats at runtime in order to allow
 // external crates to implement new formats.
 static INPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn InputFormat>>> = Lazy::new(|| {
     BTreeMap::from([
+        ("auto", Box::new(AutoInputFormat) as Box<dyn InputFormat>),
+        #[cfg(feature = "with-avro")]
+        ("avro", Box::new(AvroInputFormat) as Box<dyn InputFormat>),
         ("csv", Box::new(CsvInputFormat) as Box<dyn InputFormat>),
         ("json", Box::new(JsonInputFormat) as Box<dyn InputFormat>),
+        #[cfg(feature = "with-protobuf")]
+        (
+            "protobuf",
+            Box::new(ProtobufInputFormat) as Box<dyn InputFormat>,
+        ),
+        ("raw", Box::new(RawInputFormat) as Box<dyn InputFormat>),
+        ("text", Box::new(TextInputFormat) as Box<dyn InputFormat>),
     ])
 });
 
-/// Static map of supported output formats.
+/// Static map of built-in output formats.
 static OUTPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn OutputFormat>>> = Lazy::new(|| {
     BTreeMap::from([
+        #[cfg(feature = "with-arrow")]
+        (
+            "arrow",
+            Box::new(ArrowOutputFormat) as Box<dyn OutputFormat>,
+        ),
+        #[cfg(feature = "with-avro")]
+        ("avro", Box::new(AvroOutputFormat) as Box<dyn OutputFormat>),
         ("csv", Box::new(CsvOutputFormat) as Box<dyn OutputFormat>),
         ("json", Box::new(JsonOutputFormat) as Box<dyn OutputFormat>),
+        #[cfg(feature = "with-parquet")]
+        (
+            "parquet",
+            Box::new(ParquetOutputFormat) as Box<dyn OutputFormat>,
+        ),
+        #[cfg(feature = "with-protobuf")]
+        (
+            "protobuf",
+            Box::new(ProtobufOutputFormat) as Box<dyn OutputFormat>,
+        ),
+        ("sql", Box::new(SqlOutputFormat) as Box<dyn OutputFormat>),
     ])
 });
 
@@ -363,7 +489,10 @@ pub trait InputFormat: Send + Sync {
 impl dyn InputFormat {
     /// Lookup input format by name.
     pub fn get_format(name: &str) -> Option<&'static dyn InputFormat> {
-        INPUT_FORMATS.get(name).map(|f| &**f)
+        INPUT_FORMATS
+            .get(name)
+            .map(|f| &**f)
+            .or_else(|| plugin::plugin_input_format(name))
     }
 }
 
@@ -415,6 +544,14 @@ pub trait Parser: Send {
     /// error if parsing fails.
     fn eoi(&mut self) -> (usize, Vec<ParseError>);
 
+    /// Returns `true` if the updates pushed to the circuit depend on the
+    /// order in which records are parsed, e.g., because a record replaces
+    /// the previous record with the same key.  Such parsers must not be
+    /// forked to parse chunks of the same stream in parallel.
+    fn is_order_sensitive(&self) -> bool {
+        false
+    }
+
     /// Create a new parser with the same configuration as `self`.
     ///
     /// Used by multithreaded transport endpoints to create multiple parallel
@@ -453,7 +590,10 @@ pub trait OutputFormat: Send + Sync {
 impl dyn OutputFormat {
     /// Lookup output format by name.
     pub fn get_format(name: &str) -> Option<&'static dyn OutputFormat> {
-        OUTPUT_FORMATS.get(name).map(|f| &**f)
+        OUTPUT_FORMATS
+            .get(name)
+            .map(|f| &**f)
+            .or_else(|| plugin::plugin_output_format(name))
     }
 }
 
@@ -0,0 +1,433 @@
+//! Parquet format encoder.
+//!
+//! Each output batch is written as a complete Parquet file, which the
+//! encoder pushes to the transport as a single buffer.  Transports that
+//! append buffers to a single object, such as the `file` transport, must
+//! therefore start a new file for every batch, e.g., by enabling file
+//! rotation with `max_file_size_bytes: 1`.
+//!
+//! The Parquet schema is inferred from the JSON representation of the
+//! records in each batch.  SQL `DATE`, `TIME`, and `TIMESTAMP` values are
+//! written as strings.
+
+use crate::{
+    catalog::{RecordFormat, SerBatch},
+    ControllerError, Encoder, OutputConsumer, OutputFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use arrow::json::{reader::infer_json_schema_from_iterator, ReaderBuilder};
+use erased_serde::Serialize as ErasedSerialize;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+use utoipa::ToSchema;
+
+/// The largest weight of a record that can be output in the `snapshot` mode,
+/// which duplicates records with weight `w` `w` times.
+const MAX_DUPLICATES: i64 = 1_000_000;
+
+/// Parquet format encoder.
+pub struct ParquetOutputFormat;
+
+const fn default_row_group_size() -> usize {
+    1024 * 1024
+}
+
+fn default_weight_column() -> String {
+    "__feldera_weight".to_string()
+}
+
+/// Parquet encoder configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ParquetEncoderConfig {
+    /// Maximum number of rows in a row group.
+    #[serde(default = "default_row_group_size")]
+    pub row_group_size: usize,
+
+    /// Compression codec applied to column chunks.
+    #[serde(default)]
+    pub compression: ParquetCompression,
+
+    /// Contents of the file written for each output batch.
+    #[serde(default)]
+    pub mode: ParquetOutputMode,
+
+    /// Name of the weight column added in the `deltas` mode.
+    #[serde(default = "default_weight_column")]
+    pub weight_column: String,
+}
+
+/// Compression codec used by the Parquet encoder.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ToSchema)]
+pub enum ParquetCompression {
+    #[serde(rename = "uncompressed")]
+    Uncompressed,
+
+    #[default]
+    #[serde(rename = "snappy")]
+    Snappy,
+
+    #[serde(rename = "gzip")]
+    Gzip,
+
+    #[serde(rename = "zstd")]
+    Zstd,
+
+    #[serde(rename = "lz4")]
+    Lz4,
+}
+
+impl From<ParquetCompression> for Compression {
+    fn from(compression: ParquetCompression) -> Self {
+        match compression {
+            ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+            ParquetCompression::Lz4 => Compression::LZ4_RAW,
+        }
+    }
+}
+
+/// Contents of the Parquet files written by the encoder.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ToSchema)]
+pub enum ParquetOutputMode {
+    /// Each file contains the changes to the view produced by one step, with
+    /// an extra column containing the weight of each change: a positive
+    /// weight for insertions and a negative weight for deletions.
+    #[default]
+    #[serde(rename = "deltas")]
+    Deltas,
+
+    /// Each file contains the complete contents of the view after one step.
+    ///
+    /// The encoder keeps a copy of the view in memory.  No file is written
+    /// while the view is empty.
+    #[serde(rename = "snapshot")]
+    Snapshot,
+}
+
+impl OutputFormat for ParquetOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("parquet")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            ParquetEncoderConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::encoder_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = ParquetEncoderConfig::deserialize(config)?;
+        if config.row_group_size == 0 {
+            bail!("'row_group_size' must be greater than 0");
+        }
+
+        Ok(Box::new(ParquetEncoder::new(consumer, config)))
+    }
+}
+
+struct ParquetEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+    config: ParquetEncoderConfig,
+    max_buffer_size: usize,
+
+    /// Current contents of the view in the `snapshot` mode: records
+    /// serialized as JSON and their weights.
+    snapshot: BTreeMap<Vec<u8>, i64>,
+}
+
+impl ParquetEncoder {
+    fn new(output_consumer: Box<dyn OutputConsumer>, config: ParquetEncoderConfig) -> Self {
+        let max_buffer_size = output_consumer.max_buffer_size_bytes();
+
+        Self {
+            output_consumer,
+            config,
+            max_buffer_size,
+            snapshot: BTreeMap::new(),
+        }
+    }
+
+    /// Rows of the `deltas` mode output for `batches`.
+    fn deltas(&self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<Vec<JsonValue>> {
+        let mut rows = Vec::new();
+        let mut record = Vec::new();
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                record.clear();
+                cursor.serialize_key(&mut record)?;
+                let mut row = record_to_row(&record)?;
+                row.insert(
+                    self.config.weight_column.clone(),
+                    JsonValue::from(cursor.weight()),
+                );
+                rows.push(JsonValue::Object(row));
+                cursor.step_key();
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Apply `batches` to the snapshot and return its rows.
+    fn snapshot(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<Vec<JsonValue>> {
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                let mut record = Vec::new();
+                cursor.serialize_key(&mut record)?;
+                let weight = self.snapshot.entry(record).or_insert(0);
+                *weight += cursor.weight();
+                cursor.step_key();
+            }
+        }
+        self.snapshot.retain(|_, weight| *weight != 0);
+
+        let mut rows = Vec::with_capacity(self.snapshot.len());
+        for (record, weight) in self.snapshot.iter() {
+            if *weight < 0 {
+                bail!(
+                    "view contains record '{}' with negative weight {weight}",
+                    String::from_utf8_lossy(record)
+                );
+            }
+            if *weight > MAX_DUPLICATES {
+                bail!(
+                    "Unable to output record '{}' with very large weight {weight}. Consider adjusting your SQL queries to avoid duplicate output records, e.g., using 'SELECT DISTINCT'.",
+                    String::from_utf8_lossy(record)
+                );
+            }
+            let row = JsonValue::Object(record_to_row(record)?);
+            for _ in 1..*weight {
+                rows.push(row.clone());
+            }
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+
+    /// Encode `rows` as a Parquet file.
+    fn write_file(&self, rows: &[JsonValue]) -> AnyResult<Vec<u8>> {
+        let schema = Arc::new(
+            infer_json_schema_from_iterator(rows.iter().map(|row| Ok(row.clone())))
+                .map_err(|e| anyhow!("error inferring Parquet schema: {e}"))?,
+        );
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(self.config.row_group_size)
+            .set_compression(self.config.compression.into())
+            .build();
+        let mut writer = ArrowWriter::try_new(Vec::new(), schema.clone(), Some(properties))?;
+        let mut decoder = ReaderBuilder::new(schema.clone())
+            .with_batch_size(self.config.row_group_size)
+            .build_decoder()?;
+
+        for chunk in rows.chunks(self.config.row_group_size) {
+            decoder.serialize(chunk)?;
+            if let Some(batch) = decoder.flush()? {
+                writer.write(&batch)?;
+            }
+        }
+
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// Parse a record serialized as JSON.
+fn record_to_row(record: &[u8]) -> AnyResult<JsonMap<String, JsonValue>> {
+    serde_json::from_slice(record).map_err(|e| {
+        anyhow!(
+            "error parsing serialized record '{}': {e}",
+            String::from_utf8_lossy(record)
+        )
+    })
+}
+
+impl Encoder for ParquetEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        if batches.iter().all(|batch| batch.is_empty()) {
+            return Ok(());
+        }
+
+        let rows = match self.config.mode {
+            ParquetOutputMode::Deltas => self.deltas(batches)?,
+            ParquetOutputMode::Snapshot => self.snapshot(batches)?,
+        };
+        if rows.is_empty() {
+            // There is no way to infer the schema of an empty file.
+            return Ok(());
+        }
+
+        let file = self.write_file(&rows)?;
+        if file.len() > self.max_buffer_size {
+            bail!("Parquet file exceeds maximum buffer size supported by the output transport. Max supported buffer size is {} bytes, but the file requires {} bytes.",
+                  self.max_buffer_size,
+                  file.len());
+        }
+        self.output_consumer.push_buffer(&file);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ParquetCompression, ParquetEncoder, ParquetEncoderConfig, ParquetOutputMode};
+    use crate::{
+        catalog::SerBatch,
+        format::Encoder,
+        static_compile::seroutput::SerBatchImpl,
+        test::{MockOutputConsumer, TestStruct},
+    };
+    use arrow::{
+        array::{Array, Int64Array, StringArray},
+        record_batch::RecordBatch,
+    };
+    use dbsp::{trace::Batch, OrdZSet};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+    use tempfile::NamedTempFile;
+
+    fn test_struct(id: u32, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64 * 10),
+            s: s.to_string(),
+        }
+    }
+
+    fn encode(encoder: &mut ParquetEncoder, records: Vec<(TestStruct, i64)>) {
+        let zset = OrdZSet::from_keys((), records);
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+    }
+
+    fn read_parquet(data: &[u8]) -> Vec<RecordBatch> {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(data).unwrap();
+        ParquetRecordBatchReaderBuilder::try_new(file.reopen().unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+        batch
+            .column(batch.schema().index_of(name).unwrap())
+            .as_any()
+            .downcast_ref::<T>()
+            .unwrap()
+    }
+
+    fn encoder(mode: ParquetOutputMode) -> (ParquetEncoder, Arc<Mutex<Vec<u8>>>) {
+        let config = ParquetEncoderConfig {
+            row_group_size: 2,
+            compression: ParquetCompression::Zstd,
+            mode,
+            weight_column: "w".to_string(),
+        };
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        (ParquetEncoder::new(Box::new(consumer), config), data)
+    }
+
+    #[test]
+    fn test_deltas() {
+        let (mut encoder, data) = encoder(ParquetOutputMode::Deltas);
+        encode(
+            &mut encoder,
+            vec![
+                (test_struct(1, "foo"), 1),
+                (test_struct(2, "bar"), -1),
+                (test_struct(3, "baz"), 2),
+            ],
+        );
+
+        let batches = read_parquet(&data.lock().unwrap());
+        // Row groups contain at most 2 rows.
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        let weights = batches
+            .iter()
+            .flat_map(|b| column::<Int64Array>(b, "w").values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(weights, vec![1, -1, 2]);
+        let names = batches
+            .iter()
+            .flat_map(|b| {
+                let s = column::<StringArray>(b, "s");
+                (0..s.len())
+                    .map(|i| s.value(i).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["foo", "bar", "baz"]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let (mut encoder, data) = encoder(ParquetOutputMode::Snapshot);
+        encode(
+            &mut encoder,
+            vec![(test_struct(1, "foo"), 1), (test_struct(2, "bar"), 1)],
+        );
+        data.lock().unwrap().clear();
+
+        encode(
+            &mut encoder,
+            vec![(test_struct(1, "foo"), -1), (test_struct(3, "baz"), 1)],
+        );
+
+        let batches = read_parquet(&data.lock().unwrap());
+        let ids = batches
+            .iter()
+            .flat_map(|b| column::<Int64Array>(b, "id").values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3]);
+        assert!(batches[0].schema().index_of("w").is_err());
+    }
+}
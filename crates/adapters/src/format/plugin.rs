@@ -0,0 +1,194 @@
+//! Data formats registered at runtime.
+//!
+//! In addition to the built-in formats, the adapters crate can use formats
+//! implemented outside of it, e.g., proprietary wire formats.  An application
+//! that links against `dbsp_adapters` implements [`InputFormat`] and
+//! [`Parser`](crate::Parser) and/or [`OutputFormat`] and
+//! [`Encoder`](crate::Encoder) for the format and calls
+//! [`register_input_format`] and [`register_output_format`] before creating a
+//! controller or starting the server.
+//!
+//! Registered formats are looked up by name along with built-in formats via
+//! [`InputFormat::get_format`] and [`OutputFormat::get_format`], so they can
+//! be used in connector configurations and in the `?format=` argument of the
+//! `/ingress` and `/egress` endpoints.  A registered format can't replace a
+//! built-in format or a previously registered one.
+
+use super::{InputFormat, OutputFormat, INPUT_FORMATS, OUTPUT_FORMATS};
+use anyhow::{bail, Result as AnyResult};
+use once_cell::sync::Lazy;
+use std::{collections::BTreeMap, sync::RwLock};
+
+/// Input formats registered at runtime.
+///
+/// Formats are leaked on registration, since [`InputFormat::get_format`]
+/// hands out `'static` references to them.
+static PLUGIN_INPUT_FORMATS: Lazy<RwLock<BTreeMap<String, &'static dyn InputFormat>>> =
+    Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Output formats registered at runtime.
+static PLUGIN_OUTPUT_FORMATS: Lazy<RwLock<BTreeMap<String, &'static dyn OutputFormat>>> =
+    Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Register an input format under the name returned by
+/// [`InputFormat::name`].
+///
+/// # Errors
+///
+/// Fails if a format with the same name already exists.
+pub fn register_input_format(format: Box<dyn InputFormat>) -> AnyResult<()> {
+    let name = format.name().into_owned();
+    let mut formats = PLUGIN_INPUT_FORMATS.write().unwrap();
+    if INPUT_FORMATS.contains_key(name.as_str()) || formats.contains_key(&name) {
+        bail!("input format '{name}' is already registered");
+    }
+    formats.insert(name, Box::leak(format));
+    Ok(())
+}
+
+/// Register an output format under the name returned by
+/// [`OutputFormat::name`].
+///
+/// # Errors
+///
+/// Fails if a format with the same name already exists.
+pub fn register_output_format(format: Box<dyn OutputFormat>) -> AnyResult<()> {
+    let name = format.name().into_owned();
+    let mut formats = PLUGIN_OUTPUT_FORMATS.write().unwrap();
+    if OUTPUT_FORMATS.contains_key(name.as_str()) || formats.contains_key(&name) {
+        bail!("output format '{name}' is already registered");
+    }
+    formats.insert(name, Box::leak(format));
+    Ok(())
+}
+
+pub(super) fn plugin_input_format(name: &str) -> Option<&'static dyn InputFormat> {
+    PLUGIN_INPUT_FORMATS.read().unwrap().get(name).copied()
+}
+
+pub(super) fn plugin_output_format(name: &str) -> Option<&'static dyn OutputFormat> {
+    PLUGIN_OUTPUT_FORMATS.read().unwrap().get(name).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{register_input_format, register_output_format};
+    use crate::{
+        format::csv::{CsvInputFormat, CsvOutputFormat},
+        test::{MockDeZSet, MockOutputConsumer, TestStruct},
+        ControllerError, DeCollectionHandle, Encoder, InputFormat, OutputConsumer, OutputFormat,
+        ParseError, Parser, SerBatch,
+    };
+    use actix_web::HttpRequest;
+    use anyhow::Result as AnyResult;
+    use erased_serde::Serialize as ErasedSerialize;
+    use serde_yaml::Value as YamlValue;
+    use std::{borrow::Cow, sync::Arc};
+
+    /// Input format that counts the lines of its input without parsing them.
+    struct LineCountInputFormat;
+
+    struct LineCountParser;
+
+    impl Parser for LineCountParser {
+        fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+            (data.iter().filter(|b| **b == b'\n').count(), Vec::new())
+        }
+
+        fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+            (0, Vec::new())
+        }
+
+        fn fork(&self) -> Box<dyn Parser> {
+            Box::new(LineCountParser)
+        }
+    }
+
+    impl InputFormat for LineCountInputFormat {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::Borrowed("plugin_test_lines")
+        }
+
+        fn config_from_http_request(
+            &self,
+            _endpoint_name: &str,
+            _request: &HttpRequest,
+        ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+            Ok(Box::new(()))
+        }
+
+        fn new_parser(
+            &self,
+            _endpoint_name: &str,
+            _input_stream: &dyn DeCollectionHandle,
+            _config: &YamlValue,
+        ) -> Result<Box<dyn Parser>, ControllerError> {
+            Ok(Box::new(LineCountParser))
+        }
+    }
+
+    /// Output format that outputs the number of records in each batch.
+    struct CountOutputFormat;
+
+    struct CountEncoder(Box<dyn OutputConsumer>);
+
+    impl Encoder for CountEncoder {
+        fn consumer(&mut self) -> &mut dyn OutputConsumer {
+            self.0.as_mut()
+        }
+
+        fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+            let count = batches.iter().map(|batch| batch.len()).sum::<usize>();
+            self.0.push_buffer(count.to_string().as_bytes());
+            Ok(())
+        }
+    }
+
+    impl OutputFormat for CountOutputFormat {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::Borrowed("plugin_test_count")
+        }
+
+        fn config_from_http_request(
+            &self,
+            _endpoint_name: &str,
+            _request: &HttpRequest,
+        ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+            Ok(Box::new(()))
+        }
+
+        fn new_encoder(
+            &self,
+            _config: &YamlValue,
+            consumer: Box<dyn OutputConsumer>,
+        ) -> AnyResult<Box<dyn Encoder>> {
+            Ok(Box::new(CountEncoder(consumer)))
+        }
+    }
+
+    #[test]
+    fn register() {
+        assert!(<dyn InputFormat>::get_format("plugin_test_lines").is_none());
+        register_input_format(Box::new(LineCountInputFormat)).unwrap();
+        let mut parser = <dyn InputFormat>::get_format("plugin_test_lines")
+            .unwrap()
+            .new_parser("test", &<MockDeZSet<TestStruct>>::new(), &YamlValue::Null)
+            .unwrap();
+        assert_eq!(parser.input_fragment(b"a\nb\nc").0, 2);
+
+        register_output_format(Box::new(CountOutputFormat)).unwrap();
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = <dyn OutputFormat>::get_format("plugin_test_count")
+            .unwrap()
+            .new_encoder(&YamlValue::Null, Box::new(consumer))
+            .unwrap();
+        encoder.encode(&[]).unwrap();
+        assert_eq!(data.lock().unwrap().as_slice(), b"0");
+
+        // Names must be unique, including built-in formats.
+        assert!(register_input_format(Box::new(LineCountInputFormat)).is_err());
+        assert!(register_input_format(Box::new(CsvInputFormat)).is_err());
+        assert!(register_output_format(Box::new(CsvOutputFormat)).is_err());
+    }
+}
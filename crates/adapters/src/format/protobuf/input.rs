@@ -0,0 +1,225 @@
+//! Protobuf format parser.
+
+use super::{ProtobufConfig, ProtobufSchema};
+use crate::{
+    catalog::RecordFormat,
+    format::{
+        json::{JsonParser, JsonParserConfig, JsonUpdateFormat},
+        InputFormat, ParseError, Parser,
+    },
+    ControllerError, DeCollectionHandle,
+};
+use actix_web::HttpRequest;
+use erased_serde::Serialize as ErasedSerialize;
+use prost_reflect::DynamicMessage;
+use serde::Deserialize;
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, mem::take};
+
+/// Protobuf format parser.
+pub struct ProtobufInputFormat;
+
+impl InputFormat for ProtobufInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("protobuf")
+    }
+
+    fn new_parser(
+        &self,
+        endpoint_name: &str,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
+        let config = ProtobufConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+        let schema = ProtobufSchema::new(&config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+
+        let input_stream =
+            input_stream.configure_deserializer(RecordFormat::Json(Default::default()))?;
+        let json_parser = JsonParser::new(
+            input_stream,
+            JsonParserConfig {
+                update_format: JsonUpdateFormat::Raw,
+                array: false,
+                ..Default::default()
+            },
+        );
+
+        Ok(Box::new(ProtobufParser::new(
+            schema,
+            config.length_delimited,
+            Box::new(json_parser),
+        )) as Box<dyn Parser>)
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            ProtobufConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::parser_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+}
+
+/// Decode a varint length prefix.
+///
+/// Returns the length and the size of the prefix, `Ok(None)` if `data`
+/// ends in the middle of the prefix, or an error if the prefix is invalid.
+fn decode_length(data: &[u8]) -> Result<Option<(usize, usize)>, ()> {
+    let mut length = 0u64;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        length |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return usize::try_from(length)
+                .map(|length| Some((length, i + 1)))
+                .map_err(|_| ());
+        }
+    }
+    if data.len() >= 10 {
+        Err(())
+    } else {
+        Ok(None)
+    }
+}
+
+struct ProtobufParser {
+    schema: ProtobufSchema,
+    length_delimited: bool,
+    /// Parser that pushes records converted to JSON to the circuit.
+    json_parser: Box<dyn Parser>,
+    /// Incomplete message received via `input_fragment`.
+    leftover: Vec<u8>,
+    last_event_number: u64,
+}
+
+impl ProtobufParser {
+    fn new(schema: ProtobufSchema, length_delimited: bool, json_parser: Box<dyn Parser>) -> Self {
+        Self {
+            schema,
+            length_delimited,
+            json_parser,
+            leftover: Vec::new(),
+            last_event_number: 0,
+        }
+    }
+
+    /// Decode a single message and append it to `json`.
+    fn decode_message(&mut self, message: &[u8], json: &mut Vec<u8>, errors: &mut Vec<ParseError>) {
+        self.last_event_number += 1;
+        match DynamicMessage::decode(self.schema.descriptor.clone(), message) {
+            Ok(message) => {
+                // Serializing a `serde_json::Value` cannot fail.
+                serde_json::to_writer(&mut *json, &self.schema.message_to_json(&message)).unwrap();
+                json.push(b'\n');
+            }
+            Err(e) => errors.push(ParseError::bin_event_error(
+                format!("error decoding protobuf message: {e}"),
+                self.last_event_number,
+                message,
+                None,
+            )),
+        }
+    }
+
+    /// Decode complete messages in `data`.
+    ///
+    /// Returns the number of bytes consumed.  Unless `eoi` is true, an
+    /// incomplete length-delimited message at the end of `data` is not
+    /// consumed.
+    fn input_from_slice(&mut self, data: &[u8], eoi: bool) -> (usize, (usize, Vec<ParseError>)) {
+        let mut errors = Vec::new();
+        let mut json = Vec::new();
+        let mut consumed = 0;
+
+        if self.length_delimited {
+            while consumed < data.len() {
+                match decode_length(&data[consumed..]) {
+                    Err(()) => {
+                        errors.push(ParseError::bin_envelope_error(
+                            "invalid message length prefix".to_string(),
+                            &data[consumed..],
+                            None,
+                        ));
+                        consumed = data.len();
+                    }
+                    Ok(Some((length, prefix))) if consumed + prefix + length <= data.len() => {
+                        let start = consumed + prefix;
+                        self.decode_message(&data[start..start + length], &mut json, &mut errors);
+                        consumed = start + length;
+                    }
+                    Ok(_) => {
+                        if eoi {
+                            errors.push(ParseError::bin_envelope_error(
+                                "incomplete message at the end of input".to_string(),
+                                &data[consumed..],
+                                None,
+                            ));
+                            consumed = data.len();
+                        }
+                        break;
+                    }
+                }
+            }
+        } else {
+            self.decode_message(data, &mut json, &mut errors);
+            consumed = data.len();
+        }
+
+        let (num_records, json_errors) = self.json_parser.input_chunk(&json);
+        errors.extend(json_errors);
+        (consumed, (num_records, errors))
+    }
+}
+
+impl Parser for ProtobufParser {
+    fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        self.leftover.extend_from_slice(data);
+        if !self.length_delimited {
+            // Message boundaries are unknown: treat the entire input as one
+            // message.
+            return (0, Vec::new());
+        }
+
+        let leftover = take(&mut self.leftover);
+        let (consumed, result) = self.input_from_slice(&leftover, false);
+        self.leftover = leftover[consumed..].to_vec();
+        result
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        self.input_from_slice(data, true).1
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        if self.leftover.is_empty() {
+            return (0, Vec::new());
+        }
+
+        let leftover = take(&mut self.leftover);
+        self.input_from_slice(&leftover, true).1
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(
+            self.schema.clone(),
+            self.length_delimited,
+            self.json_parser.fork(),
+        ))
+    }
+}
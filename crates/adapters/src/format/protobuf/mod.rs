@@ -0,0 +1,698 @@
+//! Protobuf format.
+//!
+//! Messages are described by a compiled descriptor set, produced, e.g., by
+//! `protoc --include_imports --descriptor_set_out=<file>`, and the fully
+//! qualified name of the message type.
+//!
+//! Message fields map to table columns by name.  Fields of nested messages
+//! are flattened into columns named `<field><separator><nested field>`,
+//! unless `flatten_nested` is disabled, in which case each nested message
+//! maps to a single column.  Repeated and map fields map to array and map
+//! columns.  `google.protobuf.Timestamp` maps to SQL `TIMESTAMP`, and the
+//! `google.protobuf.*Value` wrappers map to nullable columns of the wrapped
+//! type.
+//!
+//! Like the Avro format, the parser converts messages to JSON documents and
+//! delegates to the JSON parser, and the encoder converts records serialized
+//! as JSON into messages.
+
+use anyhow::{anyhow, bail, Result as AnyResult};
+use chrono::{NaiveDateTime, Timelike};
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MapKey,
+    MessageDescriptor, Value as ProtoValue,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::{collections::HashMap, fs};
+use utoipa::ToSchema;
+
+mod input;
+mod output;
+
+pub use input::ProtobufInputFormat;
+pub use output::ProtobufOutputFormat;
+
+/// Format used by SQL `TIMESTAMP` values in JSON.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+const TIMESTAMP_MESSAGE: &str = "google.protobuf.Timestamp";
+
+fn default_flatten_nested() -> bool {
+    true
+}
+
+fn default_flatten_separator() -> String {
+    "_".to_string()
+}
+
+/// Protobuf format configuration, used by both the parser and the encoder.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProtobufConfig {
+    /// Path to a file containing a serialized `FileDescriptorSet` that
+    /// describes `message` and all its dependencies.
+    pub descriptor_set_file: String,
+
+    /// Fully qualified name of the message type, e.g., `my.package.Event`.
+    pub message: String,
+
+    /// Flatten the fields of nested messages into separate columns.
+    ///
+    /// When `false`, a nested message maps to a single column.
+    #[serde(default = "default_flatten_nested")]
+    pub flatten_nested: bool,
+
+    /// Separator between the names of a field and its nested fields in the
+    /// names of flattened columns.
+    #[serde(default = "default_flatten_separator")]
+    pub flatten_separator: String,
+
+    /// Messages are prefixed with their varint-encoded length.
+    ///
+    /// Required to read or write more than one message per buffer, e.g.,
+    /// with the `file` transport.  Otherwise, each buffer, e.g., a Kafka
+    /// message, contains exactly one protobuf message.
+    #[serde(default)]
+    pub length_delimited: bool,
+}
+
+/// Message descriptor along with the field mapping rules.
+#[derive(Clone)]
+struct ProtobufSchema {
+    descriptor: MessageDescriptor,
+    /// Separator used to flatten nested messages or `None` if nested
+    /// messages are not flattened.
+    separator: Option<String>,
+}
+
+impl ProtobufSchema {
+    fn new(config: &ProtobufConfig) -> AnyResult<Self> {
+        let descriptor_set = fs::read(&config.descriptor_set_file).map_err(|e| {
+            anyhow!(
+                "error reading descriptor set file '{}': {e}",
+                config.descriptor_set_file
+            )
+        })?;
+        let pool = DescriptorPool::decode(descriptor_set.as_slice()).map_err(|e| {
+            anyhow!(
+                "invalid descriptor set in '{}': {e}",
+                config.descriptor_set_file
+            )
+        })?;
+        let descriptor = pool.get_message_by_name(&config.message).ok_or_else(|| {
+            anyhow!(
+                "message '{}' not found in descriptor set '{}'",
+                config.message,
+                config.descriptor_set_file
+            )
+        })?;
+
+        let schema = Self {
+            descriptor,
+            separator: config
+                .flatten_nested
+                .then(|| config.flatten_separator.clone()),
+        };
+        if schema.separator.is_some() {
+            schema.check_flattenable(&schema.descriptor, &mut Vec::new())?;
+        }
+        Ok(schema)
+    }
+
+    /// Returns the descriptor of the nested message that `field` is
+    /// flattened into, if any.
+    fn flattened(&self, field: &FieldDescriptor) -> Option<MessageDescriptor> {
+        if self.separator.is_none() || field.is_list() || field.is_map() {
+            return None;
+        }
+        match field.kind() {
+            Kind::Message(message) if !is_well_known(&message) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Recursive message types cannot be flattened into a finite number of
+    /// columns.
+    fn check_flattenable(
+        &self,
+        descriptor: &MessageDescriptor,
+        path: &mut Vec<String>,
+    ) -> AnyResult<()> {
+        if path.iter().any(|name| name == descriptor.full_name()) {
+            bail!(
+                "message '{}' is recursive and cannot be flattened; set 'flatten_nested' to false",
+                descriptor.full_name()
+            );
+        }
+        path.push(descriptor.full_name().to_string());
+        for field in descriptor.fields() {
+            if let Some(nested) = self.flattened(&field) {
+                self.check_flattenable(&nested, path)?;
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    /// Convert a message to the JSON representation expected by table
+    /// deserializers.
+    fn message_to_json(&self, message: &DynamicMessage) -> JsonValue {
+        let mut columns = JsonMap::new();
+        self.flatten_message(Some(message), &message.descriptor(), "", &mut columns);
+        JsonValue::Object(columns)
+    }
+
+    /// Add the columns of `message` to `columns`, or nulls if the message is
+    /// not present.
+    fn flatten_message(
+        &self,
+        message: Option<&DynamicMessage>,
+        descriptor: &MessageDescriptor,
+        prefix: &str,
+        columns: &mut JsonMap<String, JsonValue>,
+    ) {
+        for field in descriptor.fields() {
+            let name = format!("{prefix}{}", field.name());
+            let present = message.map_or(false, |message| {
+                !field.supports_presence() || message.has_field(&field)
+            });
+
+            if let Some(nested) = self.flattened(&field) {
+                let nested_message = if present {
+                    message.unwrap().get_field(&field).as_message().cloned()
+                } else {
+                    None
+                };
+                let prefix = format!("{name}{}", self.separator.as_deref().unwrap_or_default());
+                self.flatten_message(nested_message.as_ref(), &nested, &prefix, columns);
+            } else if present {
+                let value = message.unwrap().get_field(&field);
+                columns.insert(name, value_to_json(&value, &field.kind()));
+            } else {
+                columns.insert(name, JsonValue::Null);
+            }
+        }
+    }
+
+    /// Convert a record serialized as JSON into a message.
+    fn json_to_message(&self, record: &JsonValue) -> AnyResult<DynamicMessage> {
+        let columns = record
+            .as_object()
+            .ok_or_else(|| anyhow!("expected a JSON object, found {record}"))?;
+        Ok(self
+            .unflatten_message(columns, &self.descriptor, "")?
+            .unwrap_or_else(|| DynamicMessage::new(self.descriptor.clone())))
+    }
+
+    /// Build a message from `columns`.  Returns `None` if all columns of the
+    /// message are null.
+    fn unflatten_message(
+        &self,
+        columns: &JsonMap<String, JsonValue>,
+        descriptor: &MessageDescriptor,
+        prefix: &str,
+    ) -> AnyResult<Option<DynamicMessage>> {
+        let mut message = DynamicMessage::new(descriptor.clone());
+        let mut present = false;
+
+        for field in descriptor.fields() {
+            let name = format!("{prefix}{}", field.name());
+
+            if let Some(nested) = self.flattened(&field) {
+                let prefix = format!("{name}{}", self.separator.as_deref().unwrap_or_default());
+                if let Some(nested) = self.unflatten_message(columns, &nested, &prefix)? {
+                    message.set_field(&field, ProtoValue::Message(nested));
+                    present = true;
+                }
+            } else {
+                match column(columns, &name) {
+                    None | Some(JsonValue::Null) => {}
+                    Some(value) => {
+                        let value = field_from_json(value, &field)
+                            .map_err(|e| anyhow!("error converting column '{name}': {e}"))?;
+                        message.set_field(&field, value);
+                        present = true;
+                    }
+                }
+            }
+        }
+
+        Ok(present.then_some(message))
+    }
+}
+
+/// Look up a column by name.  SQL column names are case-insensitive.
+fn column<'a>(columns: &'a JsonMap<String, JsonValue>, name: &str) -> Option<&'a JsonValue> {
+    columns.get(name).or_else(|| {
+        columns
+            .iter()
+            .find(|(column, _)| column.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    })
+}
+
+/// Well-known types that map to a single column.
+fn is_well_known(message: &MessageDescriptor) -> bool {
+    message.full_name() == TIMESTAMP_MESSAGE || wrapped_field(message).is_some()
+}
+
+/// The `value` field of a `google.protobuf.*Value` wrapper.
+fn wrapped_field(message: &MessageDescriptor) -> Option<FieldDescriptor> {
+    let name = message.full_name();
+    if name.starts_with("google.protobuf.")
+        && name.ends_with("Value")
+        && name != "google.protobuf.Value"
+    {
+        message.get_field_by_name("value")
+    } else {
+        None
+    }
+}
+
+fn enum_to_json(descriptor: &EnumDescriptor, number: i32) -> JsonValue {
+    match descriptor.get_value(number) {
+        Some(value) => JsonValue::String(value.name().to_string()),
+        None => JsonValue::from(number),
+    }
+}
+
+fn map_key_to_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(b) => b.to_string(),
+        MapKey::I32(i) => i.to_string(),
+        MapKey::I64(i) => i.to_string(),
+        MapKey::U32(i) => i.to_string(),
+        MapKey::U64(i) => i.to_string(),
+        MapKey::String(s) => s.clone(),
+    }
+}
+
+fn value_to_json(value: &ProtoValue, kind: &Kind) -> JsonValue {
+    match value {
+        ProtoValue::Bool(b) => JsonValue::Bool(*b),
+        ProtoValue::I32(i) => JsonValue::from(*i),
+        ProtoValue::I64(i) => JsonValue::from(*i),
+        ProtoValue::U32(i) => JsonValue::from(*i),
+        ProtoValue::U64(i) => JsonValue::from(*i),
+        ProtoValue::F32(f) => JsonValue::from(*f),
+        ProtoValue::F64(f) => JsonValue::from(*f),
+        ProtoValue::String(s) => JsonValue::String(s.clone()),
+        ProtoValue::Bytes(bytes) => JsonValue::from(bytes.to_vec()),
+        ProtoValue::EnumNumber(number) => match kind {
+            Kind::Enum(descriptor) => enum_to_json(descriptor, *number),
+            _ => JsonValue::from(*number),
+        },
+        ProtoValue::List(values) => {
+            JsonValue::Array(values.iter().map(|v| value_to_json(v, kind)).collect())
+        }
+        ProtoValue::Map(entries) => {
+            let value_kind = match kind {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                _ => kind.clone(),
+            };
+            JsonValue::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (map_key_to_string(key), value_to_json(value, &value_kind)))
+                    .collect(),
+            )
+        }
+        ProtoValue::Message(message) => message_to_json(message),
+    }
+}
+
+/// Convert a message that is not flattened, e.g., an element of a repeated
+/// field, to JSON.
+fn message_to_json(message: &DynamicMessage) -> JsonValue {
+    let descriptor = message.descriptor();
+
+    if descriptor.full_name() == TIMESTAMP_MESSAGE {
+        let seconds = message
+            .get_field_by_name("seconds")
+            .and_then(|v| v.as_i64())
+            .unwrap_or_default();
+        let nanos = message
+            .get_field_by_name("nanos")
+            .and_then(|v| v.as_i32())
+            .unwrap_or_default();
+        return match NaiveDateTime::from_timestamp_opt(seconds, nanos as u32) {
+            Some(ts) => JsonValue::String(ts.format(TIMESTAMP_FORMAT).to_string()),
+            None => JsonValue::Null,
+        };
+    }
+    if let Some(field) = wrapped_field(&descriptor) {
+        return value_to_json(&message.get_field(&field), &field.kind());
+    }
+
+    JsonValue::Object(
+        descriptor
+            .fields()
+            .map(|field| {
+                let value = if field.supports_presence() && !message.has_field(&field) {
+                    JsonValue::Null
+                } else {
+                    value_to_json(&message.get_field(&field), &field.kind())
+                };
+                (field.name().to_string(), value)
+            })
+            .collect(),
+    )
+}
+
+/// Convert a JSON value to the value of `field`.
+fn field_from_json(json: &JsonValue, field: &FieldDescriptor) -> AnyResult<ProtoValue> {
+    if field.is_map() {
+        let Kind::Message(entry) = field.kind() else {
+            bail!("map field '{}' has an invalid type", field.name());
+        };
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+        let entries = json
+            .as_object()
+            .ok_or_else(|| anyhow!("expected a JSON object, found {json}"))?;
+        let map = entries
+            .iter()
+            .map(|(key, value)| {
+                Ok((
+                    map_key_from_string(key, &key_kind)?,
+                    value_from_json(value, &value_kind)?,
+                ))
+            })
+            .collect::<AnyResult<HashMap<_, _>>>()?;
+        Ok(ProtoValue::Map(map))
+    } else if field.is_list() {
+        let values = json
+            .as_array()
+            .ok_or_else(|| anyhow!("expected a JSON array, found {json}"))?;
+        Ok(ProtoValue::List(
+            values
+                .iter()
+                .map(|value| value_from_json(value, &field.kind()))
+                .collect::<AnyResult<Vec<_>>>()?,
+        ))
+    } else {
+        value_from_json(json, &field.kind())
+    }
+}
+
+fn map_key_from_string(key: &str, kind: &Kind) -> AnyResult<MapKey> {
+    let invalid = || anyhow!("invalid map key '{key}'");
+    Ok(match kind {
+        Kind::Bool => MapKey::Bool(key.parse().map_err(|_| invalid())?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            MapKey::I32(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            MapKey::I64(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => MapKey::U32(key.parse().map_err(|_| invalid())?),
+        Kind::Uint64 | Kind::Fixed64 => MapKey::U64(key.parse().map_err(|_| invalid())?),
+        _ => MapKey::String(key.to_string()),
+    })
+}
+
+/// Convert a JSON value to a singular protobuf value of type `kind`.
+fn value_from_json(json: &JsonValue, kind: &Kind) -> AnyResult<ProtoValue> {
+    let mismatch = || anyhow!("cannot convert {json} to protobuf type {kind:?}");
+    let int = || json.as_i64().ok_or_else(mismatch);
+    let uint = || json.as_u64().ok_or_else(mismatch);
+
+    Ok(match kind {
+        Kind::Double => ProtoValue::F64(json.as_f64().ok_or_else(mismatch)?),
+        Kind::Float => ProtoValue::F32(json.as_f64().ok_or_else(mismatch)? as f32),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            ProtoValue::I32(int()?.try_into().map_err(|_| mismatch())?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => ProtoValue::I64(int()?),
+        Kind::Uint32 | Kind::Fixed32 => {
+            ProtoValue::U32(uint()?.try_into().map_err(|_| mismatch())?)
+        }
+        Kind::Uint64 | Kind::Fixed64 => ProtoValue::U64(uint()?),
+        Kind::Bool => ProtoValue::Bool(json.as_bool().ok_or_else(mismatch)?),
+        Kind::String => ProtoValue::String(json.as_str().ok_or_else(mismatch)?.to_string()),
+        Kind::Bytes => match json {
+            JsonValue::String(s) => ProtoValue::Bytes(s.as_bytes().to_vec().into()),
+            JsonValue::Array(bytes) => ProtoValue::Bytes(
+                bytes
+                    .iter()
+                    .map(|b| {
+                        b.as_u64()
+                            .and_then(|b| u8::try_from(b).ok())
+                            .ok_or_else(mismatch)
+                    })
+                    .collect::<AnyResult<Vec<u8>>>()?
+                    .into(),
+            ),
+            _ => return Err(mismatch()),
+        },
+        Kind::Enum(descriptor) => match json {
+            JsonValue::String(s) => ProtoValue::EnumNumber(
+                descriptor
+                    .get_value_by_name(s)
+                    .ok_or_else(|| {
+                        anyhow!("'{s}' is not a value of enum '{}'", descriptor.full_name())
+                    })?
+                    .number(),
+            ),
+            _ => ProtoValue::EnumNumber(int()?.try_into().map_err(|_| mismatch())?),
+        },
+        Kind::Message(descriptor) => ProtoValue::Message(message_from_json(json, descriptor)?),
+    })
+}
+
+/// Convert a JSON value to a message that is not flattened.
+fn message_from_json(
+    json: &JsonValue,
+    descriptor: &MessageDescriptor,
+) -> AnyResult<DynamicMessage> {
+    let mut message = DynamicMessage::new(descriptor.clone());
+
+    if descriptor.full_name() == TIMESTAMP_MESSAGE {
+        let s = json
+            .as_str()
+            .ok_or_else(|| anyhow!("expected a timestamp string, found {json}"))?;
+        let ts = NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT)
+            .map_err(|e| anyhow!("invalid timestamp '{s}': {e}"))?;
+        message.set_field_by_name("seconds", ProtoValue::I64(ts.timestamp()));
+        message.set_field_by_name("nanos", ProtoValue::I32(ts.nanosecond() as i32));
+        return Ok(message);
+    }
+    if let Some(field) = wrapped_field(descriptor) {
+        message.set_field(&field, value_from_json(json, &field.kind())?);
+        return Ok(message);
+    }
+
+    let columns = json
+        .as_object()
+        .ok_or_else(|| anyhow!("expected a JSON object, found {json}"))?;
+    for field in descriptor.fields() {
+        match column(columns, field.name()) {
+            None | Some(JsonValue::Null) => {}
+            Some(value) => message.set_field(&field, field_from_json(value, &field)?),
+        }
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ProtobufConfig, ProtobufSchema};
+    use crate::{
+        catalog::SerBatch,
+        format::{protobuf::output::ProtobufOutputFormat, OutputFormat},
+        static_compile::seroutput::SerBatchImpl,
+        test::{mock_parser_pipeline, MockOutputConsumer, TestStruct},
+        transport::InputConsumer,
+        FormatConfig,
+    };
+    use dbsp::{trace::Batch, OrdZSet};
+    use prost::Message;
+    use prost_reflect::{
+        prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        },
+        DynamicMessage,
+    };
+    use serde_json::json;
+    use std::{borrow::Cow, io::Write, sync::Arc};
+    use tempfile::NamedTempFile;
+
+    fn field(name: &str, number: i32, ty: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(ty as i32),
+            type_name: type_name.map(str::to_string),
+            label: Some(Label::Optional as i32),
+            ..Default::default()
+        }
+    }
+
+    fn message(name: &str, fields: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field: fields,
+            ..Default::default()
+        }
+    }
+
+    /// Write a descriptor set with the following messages to a file:
+    ///
+    /// ```proto
+    /// message Nested { int64 x = 1; string y = 2; }
+    /// message Event { uint32 id = 1; Nested nested = 2; repeated string tags = 3; }
+    /// message Flat { uint32 id = 1; bool b = 2; int64 i = 3; string s = 4; }
+    /// ```
+    fn descriptor_set() -> NamedTempFile {
+        let mut tags = field("tags", 3, Type::String, None);
+        tags.label = Some(Label::Repeated as i32);
+
+        let set = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("test.proto".to_string()),
+                package: Some("test".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![
+                    message(
+                        "Nested",
+                        vec![
+                            field("x", 1, Type::Int64, None),
+                            field("y", 2, Type::String, None),
+                        ],
+                    ),
+                    message(
+                        "Event",
+                        vec![
+                            field("id", 1, Type::Uint32, None),
+                            field("nested", 2, Type::Message, Some(".test.Nested")),
+                            tags,
+                        ],
+                    ),
+                    message(
+                        "Flat",
+                        vec![
+                            field("id", 1, Type::Uint32, None),
+                            field("b", 2, Type::Bool, None),
+                            field("i", 3, Type::Int64, None),
+                            field("s", 4, Type::String, None),
+                        ],
+                    ),
+                ],
+                ..Default::default()
+            }],
+        };
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&set.encode_to_vec()).unwrap();
+        file
+    }
+
+    fn config(file: &NamedTempFile, message: &str) -> ProtobufConfig {
+        ProtobufConfig {
+            descriptor_set_file: file.path().display().to_string(),
+            message: message.to_string(),
+            flatten_nested: true,
+            flatten_separator: "_".to_string(),
+            length_delimited: true,
+        }
+    }
+
+    fn test_struct(id: u32, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64 * 10),
+            s: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_flatten() {
+        let file = descriptor_set();
+        let schema = ProtobufSchema::new(&config(&file, "test.Event")).unwrap();
+
+        let record = json!({"id": 1, "nested_x": 5, "nested_y": "a", "tags": ["t1", "t2"]});
+        let message = schema.json_to_message(&record).unwrap();
+        let bytes = message.encode_to_vec();
+        let decoded = DynamicMessage::decode(schema.descriptor.clone(), bytes.as_slice()).unwrap();
+        assert_eq!(schema.message_to_json(&decoded), record);
+
+        // Columns of a missing nested message are null.
+        let message = schema.json_to_message(&json!({"ID": 2})).unwrap();
+        assert_eq!(
+            schema.message_to_json(&message),
+            json!({"id": 2, "nested_x": null, "nested_y": null, "tags": []})
+        );
+
+        let mut unflattened = config(&file, "test.Event");
+        unflattened.flatten_nested = false;
+        let schema = ProtobufSchema::new(&unflattened).unwrap();
+        let record = json!({"id": 1, "nested": {"x": 5, "y": "a"}, "tags": []});
+        let message = schema.json_to_message(&record).unwrap();
+        assert_eq!(schema.message_to_json(&message), record);
+
+        assert!(ProtobufSchema::new(&config(&file, "test.Missing")).is_err());
+    }
+
+    #[test]
+    fn test_parser() {
+        let file = descriptor_set();
+        let config = config(&file, "test.Flat");
+        let schema = ProtobufSchema::new(&config).unwrap();
+        let format_config = FormatConfig {
+            name: Cow::from("protobuf"),
+            config: serde_yaml::to_value(&config).unwrap(),
+        };
+
+        let records = vec![test_struct(1, "foo"), test_struct(2, "bar")];
+        let mut data = Vec::new();
+        for record in records.iter() {
+            let message = schema
+                .json_to_message(&serde_json::to_value(record).unwrap())
+                .unwrap();
+            data.extend(message.encode_length_delimited_to_vec());
+        }
+
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        // Split the input in the middle of a message.
+        let (first, second) = data.split_at(data.len() / 2 + 1);
+        assert!(consumer.input_fragment(first).is_empty());
+        assert!(consumer.input_fragment(second).is_empty());
+        assert!(consumer.eoi().is_empty());
+        assert_eq!(
+            outputs.state().flushed,
+            records.into_iter().map(|r| (r, true)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_encoder() {
+        let file = descriptor_set();
+        let mut config = config(&file, "test.Flat");
+        config.length_delimited = false;
+        let schema = ProtobufSchema::new(&config).unwrap();
+
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = ProtobufOutputFormat
+            .new_encoder(&serde_yaml::to_value(&config).unwrap(), Box::new(consumer))
+            .unwrap();
+
+        let zset = OrdZSet::from_keys(
+            (),
+            vec![(test_struct(1, "foo"), 1), (test_struct(2, "bar"), -1)],
+        );
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        // The deletion is skipped.
+        let message =
+            DynamicMessage::decode(schema.descriptor.clone(), data.lock().unwrap().as_slice())
+                .unwrap();
+        assert_eq!(
+            schema.message_to_json(&message),
+            serde_json::to_value(test_struct(1, "foo")).unwrap()
+        );
+    }
+}
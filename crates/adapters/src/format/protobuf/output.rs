@@ -0,0 +1,153 @@
+//! Protobuf format encoder.
+
+use super::{ProtobufConfig, ProtobufSchema};
+use crate::{
+    catalog::{RecordFormat, SerBatch},
+    ControllerError, Encoder, OutputConsumer, OutputFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use erased_serde::Serialize as ErasedSerialize;
+use prost::Message;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, sync::Arc};
+
+/// The largest weight of a record that can be output.  A record with weight
+/// `w` is output `w` times.
+const MAX_DUPLICATES: i64 = 1_000_000;
+
+/// Protobuf format encoder.
+///
+/// Each output record is encoded as a separate message and sent to the
+/// transport in its own buffer.  Protobuf messages cannot represent
+/// deletions, which are therefore not included in the output.
+pub struct ProtobufOutputFormat;
+
+impl OutputFormat for ProtobufOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("protobuf")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            ProtobufConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::encoder_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = ProtobufConfig::deserialize(config)?;
+        let schema = ProtobufSchema::new(&config)?;
+
+        Ok(Box::new(ProtobufEncoder::new(
+            consumer,
+            schema,
+            config.length_delimited,
+        )))
+    }
+}
+
+struct ProtobufEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+    schema: ProtobufSchema,
+    length_delimited: bool,
+    max_buffer_size: usize,
+}
+
+impl ProtobufEncoder {
+    fn new(
+        output_consumer: Box<dyn OutputConsumer>,
+        schema: ProtobufSchema,
+        length_delimited: bool,
+    ) -> Self {
+        let max_buffer_size = output_consumer.max_buffer_size_bytes();
+
+        Self {
+            output_consumer,
+            schema,
+            length_delimited,
+            max_buffer_size,
+        }
+    }
+
+    /// Encode a record serialized as JSON.
+    fn encode_record(&self, record: &[u8]) -> AnyResult<Vec<u8>> {
+        let json: JsonValue = serde_json::from_slice(record)
+            .map_err(|e| anyhow!("error parsing serialized record: {e}"))?;
+        let message = self
+            .schema
+            .json_to_message(&json)
+            .map_err(|e| anyhow!("error encoding record {json} as protobuf: {e}"))?;
+
+        Ok(if self.length_delimited {
+            message.encode_length_delimited_to_vec()
+        } else {
+            message.encode_to_vec()
+        })
+    }
+}
+
+impl Encoder for ProtobufEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut record = Vec::new();
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+            while cursor.key_valid() {
+                let w = cursor.weight();
+
+                // Deletions are not representable in protobuf.
+                if w > 0 {
+                    record.clear();
+                    cursor.serialize_key(&mut record)?;
+
+                    if w > MAX_DUPLICATES {
+                        bail!(
+                            "Unable to output record '{}' with very large weight {w}. Consider adjusting your SQL queries to avoid duplicate output records, e.g., using 'SELECT DISTINCT'.",
+                            String::from_utf8_lossy(&record)
+                        );
+                    }
+
+                    let message = self.encode_record(&record)?;
+                    if message.len() > self.max_buffer_size {
+                        bail!("Protobuf message exceeds maximum buffer size supported by the output transport. Max supported buffer size is {} bytes, but the message requires {} bytes.",
+                              self.max_buffer_size,
+                              message.len());
+                    }
+                    for _ in 0..w {
+                        self.output_consumer.push_buffer(&message);
+                    }
+                }
+
+                cursor.step_key();
+            }
+        }
+
+        Ok(())
+    }
+}
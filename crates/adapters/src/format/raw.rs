@@ -0,0 +1,281 @@
+//! Raw format parser.
+//!
+//! Maps each input message to a row of a single-column `VARCHAR` table,
+//! leaving it to SQL to parse the contents of the message.
+
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    format::{InputFormat, ParseError, Parser},
+    util::split_on_newline,
+    ControllerError, DeCollectionHandle,
+};
+use actix_web::HttpRequest;
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, fmt::Write, mem::take};
+use utoipa::ToSchema;
+
+/// Raw format parser.
+pub struct RawInputFormat;
+
+/// Conversion of message bytes to a string.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RawEncoding {
+    /// Messages are UTF-8 strings.  Messages that are not valid UTF-8 are
+    /// rejected.
+    #[default]
+    Utf8,
+
+    /// Encode message bytes as a hexadecimal string.
+    Hex,
+}
+
+/// Raw format parser configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct RawParserConfig {
+    /// Conversion of message bytes to a string.  The default is `utf8`.
+    #[serde(default)]
+    pub encoding: RawEncoding,
+}
+
+impl InputFormat for RawInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("raw")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            RawParserConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::parser_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_parser(
+        &self,
+        endpoint_name: &str,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config = RawParserConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(
+                endpoint_name,
+                &e,
+                &serde_yaml::to_string(&config).unwrap_or_default(),
+            )
+        })?;
+
+        let input_stream =
+            input_stream.configure_deserializer(RecordFormat::Json(Default::default()))?;
+        Ok(Box::new(RawParser::new(input_stream, config)) as Box<dyn Parser>)
+    }
+}
+
+struct RawParser {
+    /// Input handle to push parsed data to.
+    input_stream: Box<dyn DeCollectionStream>,
+
+    config: RawParserConfig,
+
+    /// Incomplete line received via `input_fragment`.
+    leftover: Vec<u8>,
+
+    last_event_number: u64,
+}
+
+impl RawParser {
+    fn new(input_stream: Box<dyn DeCollectionStream>, config: RawParserConfig) -> Self {
+        Self {
+            input_stream,
+            config,
+            leftover: Vec::new(),
+            last_event_number: 0,
+        }
+    }
+
+    /// Push `message` to the input stream as a single-column record.
+    fn insert(&mut self, message: &[u8], errors: &mut Vec<ParseError>) -> usize {
+        self.last_event_number += 1;
+
+        let value = match self.config.encoding {
+            RawEncoding::Utf8 => match std::str::from_utf8(message) {
+                Ok(value) => Cow::Borrowed(value),
+                Err(e) => {
+                    errors.push(ParseError::bin_event_error(
+                        format!("message is not a valid UTF-8 string ({e})"),
+                        self.last_event_number,
+                        message,
+                        Some(Cow::from(
+                            "use the 'hex' encoding to ingest binary messages",
+                        )),
+                    ));
+                    return 0;
+                }
+            },
+            RawEncoding::Hex => {
+                let mut value = String::with_capacity(message.len() * 2);
+                for byte in message {
+                    // Writing to a `String` cannot fail.
+                    let _ = write!(value, "{byte:02x}");
+                }
+                Cow::Owned(value)
+            }
+        };
+
+        // Serializing a string cannot fail.
+        let record = serde_json::to_vec(&[value.as_ref()]).unwrap();
+        match self.input_stream.insert(&record) {
+            Ok(()) => 1,
+            Err(e) => {
+                errors.push(ParseError::text_event_error(
+                    "failed to insert raw message; the raw format requires a table with a single VARCHAR column",
+                    e,
+                    self.last_event_number,
+                    None,
+                    None,
+                ));
+                0
+            }
+        }
+    }
+
+    /// Insert each line of `data` as a separate record.
+    fn insert_lines(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let mut num_records = 0;
+
+        for line in data.split(|c| *c == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if !line.is_empty() {
+                num_records += self.insert(line, &mut errors);
+            }
+        }
+
+        self.input_stream.flush();
+        (num_records, errors)
+    }
+}
+
+impl Parser for RawParser {
+    /// Input received as a continuous byte stream, e.g., an HTTP request
+    /// body or a file, has no message boundaries, so each line of the stream
+    /// becomes a separate record.
+    fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let leftover = split_on_newline(data);
+
+        if leftover == 0 {
+            self.leftover.extend_from_slice(data);
+            (0, Vec::new())
+        } else {
+            let mut leftover_buf = take(&mut self.leftover);
+            leftover_buf.extend_from_slice(&data[0..leftover]);
+            let res = self.insert_lines(&leftover_buf);
+            leftover_buf.clear();
+            leftover_buf.extend_from_slice(&data[leftover..]);
+            self.leftover = leftover_buf;
+            res
+        }
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let num_records = self.insert(data, &mut errors);
+        self.input_stream.flush();
+        (num_records, errors)
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        let leftover = take(&mut self.leftover);
+        self.insert_lines(&leftover)
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(self.input_stream.fork(), self.config.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RawEncoding, RawParserConfig};
+    use crate::{
+        deserialize_table_record, test::mock_parser_pipeline, transport::InputConsumer,
+        FormatConfig,
+    };
+    use std::borrow::Cow;
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Message {
+        body: String,
+    }
+
+    deserialize_table_record!(Message["Message", 1] {
+        (body, "BODY", false, String, None)
+    });
+
+    fn message(body: &str) -> (Message, bool) {
+        (
+            Message {
+                body: body.to_string(),
+            },
+            true,
+        )
+    }
+
+    #[test]
+    fn test_raw() {
+        let format_config = FormatConfig {
+            name: Cow::from("raw"),
+            config: serde_yaml::to_value(RawParserConfig::default()).unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline::<Message>(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        // Each chunk is a message.
+        assert!(consumer.input_chunk(b"{\"a\": 1}\nfoo").is_empty());
+        assert_eq!(consumer.input_chunk(&[0xff, 0xfe]).len(), 1);
+        // Each line of a stream is a message.
+        assert!(consumer.input_fragment(b"line 1\r\nline").is_empty());
+        assert!(consumer.input_fragment(b" \"2\"\n\nline 3").is_empty());
+        assert!(consumer.eoi().is_empty());
+
+        assert_eq!(
+            outputs.state().flushed,
+            vec![
+                message("{\"a\": 1}\nfoo"),
+                message("line 1"),
+                message("line \"2\""),
+                message("line 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_hex() {
+        let format_config = FormatConfig {
+            name: Cow::from("raw"),
+            config: serde_yaml::to_value(RawParserConfig {
+                encoding: RawEncoding::Hex,
+            })
+            .unwrap(),
+        };
+        let (mut consumer, outputs) = mock_parser_pipeline::<Message>(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+
+        assert!(consumer.input_chunk(&[0x00, 0xff, 0x10]).is_empty());
+        assert_eq!(outputs.state().flushed, vec![message("00ff10")]);
+    }
+}
@@ -0,0 +1,481 @@
+//! SQL statement output format.
+//!
+//! Renders each output change as an `INSERT` or `DELETE` statement that can
+//! be applied to a database, e.g., using `psql`.
+
+use crate::{
+    catalog::{RecordFormat, SerBatch},
+    util::truncate_ellipse,
+    ControllerError, Encoder, OutputConsumer, OutputFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value as JsonValue;
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, fmt, fmt::Write, mem::take, sync::Arc};
+use utoipa::ToSchema;
+
+/// SQL statement format encoder.
+pub struct SqlOutputFormat;
+
+const fn default_buffer_size_records() -> usize {
+    10_000
+}
+
+/// When including a long statement in an error message,
+/// truncate it to `MAX_RECORD_LEN_IN_ERRMSG` bytes.
+static MAX_RECORD_LEN_IN_ERRMSG: usize = 4096;
+
+/// SQL statement encoder configuration.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SqlEncoderConfig {
+    /// Name of the table that the generated statements modify.
+    ///
+    /// When reading from the `/egress` endpoint, defaults to the name of the
+    /// view.
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Comma-separated list of columns that uniquely identify a row.
+    ///
+    /// `DELETE` statements match rows on these columns only.  When not
+    /// specified, rows are matched on all columns, and `DELETE` statements
+    /// use the PostgreSQL `ctid` column to remove only as many copies of a
+    /// row as were deleted from the view.
+    #[serde(default)]
+    pub key_columns: Option<String>,
+
+    #[serde(default = "default_buffer_size_records")]
+    pub buffer_size_records: usize,
+}
+
+impl OutputFormat for SqlOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("sql")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        let mut config = SqlEncoderConfig::deserialize(UrlDeserializer::new(
+            form_urlencoded::parse(request.query_string().as_bytes()),
+        ))
+        .map_err(|e| {
+            ControllerError::encoder_config_parse_error(endpoint_name, &e, request.query_string())
+        })?;
+
+        if config.table.is_none() {
+            config.table = request
+                .match_info()
+                .get("table_name")
+                .or_else(|| request.match_info().get("view_name"))
+                .map(str::to_string);
+        }
+        Ok(Box::new(config))
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = SqlEncoderConfig::deserialize(config)?;
+
+        Ok(Box::new(SqlEncoder::new(consumer, config)?))
+    }
+}
+
+/// Record serialized as a JSON object, with columns in declaration order.
+struct Columns(Vec<(String, JsonValue)>);
+
+impl<'de> Deserialize<'de> for Columns {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ColumnsVisitor;
+
+        impl<'de> Visitor<'de> for ColumnsVisitor {
+            type Value = Columns;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut columns = Vec::with_capacity(map.size_hint().unwrap_or_default());
+                while let Some(column) = map.next_entry()? {
+                    columns.push(column);
+                }
+                Ok(Columns(columns))
+            }
+        }
+
+        deserializer.deserialize_map(ColumnsVisitor)
+    }
+}
+
+/// Append `name` to `dst`, quoting it unless it is a plain identifier.
+///
+/// Unquoted identifiers are case-insensitive in most databases, which
+/// matches the semantics of unquoted identifiers in Feldera SQL.
+fn write_identifier(dst: &mut String, name: &str) {
+    let plain = name
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if plain {
+        dst.push_str(name);
+    } else {
+        dst.push('"');
+        dst.push_str(&name.replace('"', "\"\""));
+        dst.push('"');
+    }
+}
+
+/// Append a SQL literal representing `value` to `dst`.
+///
+/// Strings, including dates and timestamps, are rendered as quoted string
+/// literals, which the database casts to the type of the column.
+fn write_literal(dst: &mut String, value: &JsonValue) {
+    match value {
+        JsonValue::Null => dst.push_str("NULL"),
+        JsonValue::Bool(true) => dst.push_str("TRUE"),
+        JsonValue::Bool(false) => dst.push_str("FALSE"),
+        JsonValue::Number(n) => {
+            let _ = write!(dst, "{n}");
+        }
+        JsonValue::String(s) => {
+            dst.push('\'');
+            dst.push_str(&s.replace('\'', "''"));
+            dst.push('\'');
+        }
+        JsonValue::Array(values) => {
+            dst.push_str("ARRAY[");
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                write_literal(dst, v);
+            }
+            dst.push(']');
+        }
+        JsonValue::Object(entries) => {
+            dst.push_str("MAP[");
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                write_literal(dst, &JsonValue::String(k.clone()));
+                dst.push_str(", ");
+                write_literal(dst, v);
+            }
+            dst.push(']');
+        }
+    }
+}
+
+struct SqlEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+    config: SqlEncoderConfig,
+    table: String,
+    key_columns: Option<Vec<String>>,
+    buffer: Vec<u8>,
+    max_buffer_size: usize,
+}
+
+impl SqlEncoder {
+    fn new(output_consumer: Box<dyn OutputConsumer>, config: SqlEncoderConfig) -> AnyResult<Self> {
+        let max_buffer_size = output_consumer.max_buffer_size_bytes();
+
+        let mut table = String::new();
+        write_identifier(
+            &mut table,
+            config
+                .table
+                .as_deref()
+                .ok_or_else(|| anyhow!("'table' must be specified for the SQL output format"))?,
+        );
+
+        let key_columns = config.key_columns.as_ref().map(|columns| {
+            columns
+                .split(',')
+                .map(|column| column.trim().to_string())
+                .filter(|column| !column.is_empty())
+                .collect::<Vec<_>>()
+        });
+        let key_columns = key_columns.filter(|columns| !columns.is_empty());
+
+        Ok(Self {
+            output_consumer,
+            config,
+            table,
+            key_columns,
+            buffer: Vec::new(),
+            max_buffer_size,
+        })
+    }
+
+    fn insert_statement(&self, columns: &Columns) -> String {
+        let mut statement = format!("INSERT INTO {} (", self.table);
+        for (i, (name, _)) in columns.0.iter().enumerate() {
+            if i > 0 {
+                statement.push_str(", ");
+            }
+            write_identifier(&mut statement, name);
+        }
+        statement.push_str(") VALUES (");
+        for (i, (_, value)) in columns.0.iter().enumerate() {
+            if i > 0 {
+                statement.push_str(", ");
+            }
+            write_literal(&mut statement, value);
+        }
+        statement.push_str(");\n");
+        statement
+    }
+
+    /// Condition that matches the rows deleted by a `DELETE` statement.
+    fn delete_condition(&self, columns: &Columns) -> AnyResult<String> {
+        let mut condition = String::new();
+        let mut num_keys = 0;
+
+        for (name, value) in columns.0.iter() {
+            if let Some(key_columns) = &self.key_columns {
+                if !key_columns.iter().any(|key| key.eq_ignore_ascii_case(name)) {
+                    continue;
+                }
+            }
+            num_keys += 1;
+
+            if !condition.is_empty() {
+                condition.push_str(" AND ");
+            }
+
+            write_identifier(&mut condition, name);
+            if value.is_null() {
+                condition.push_str(" IS NULL");
+            } else {
+                condition.push_str(" = ");
+                write_literal(&mut condition, value);
+            }
+        }
+
+        if let Some(key_columns) = &self.key_columns {
+            if num_keys != key_columns.len() {
+                bail!(
+                    "'key_columns' ({}) must be a subset of the columns of the view ({})",
+                    key_columns.join(", "),
+                    columns
+                        .0
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        Ok(condition)
+    }
+
+    /// `DELETE` statement that removes `count` copies of a row.
+    fn delete_statement(&self, columns: &Columns, count: i64) -> AnyResult<String> {
+        let condition = self.delete_condition(columns)?;
+        let table = &self.table;
+
+        Ok(if self.key_columns.is_some() {
+            // Rows are unique by key, so the statement matches at most one
+            // row.
+            format!("DELETE FROM {table} WHERE {condition};\n")
+        } else {
+            // Without a key, the table can contain multiple copies of the
+            // row, of which only `count` must be deleted.
+            format!("DELETE FROM {table} WHERE ctid IN (SELECT ctid FROM {table} WHERE {condition} LIMIT {count});\n")
+        })
+    }
+}
+
+impl Encoder for SqlEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut buffer = take(&mut self.buffer);
+        let mut num_records = 0;
+        let mut key = Vec::new();
+
+        // Output all deletions before insertions, so that an update to a
+        // row is applied as a `DELETE` of the old row followed by an
+        // `INSERT` of the new one.
+        for deletes in [true, false] {
+            for batch in batches.iter() {
+                let mut cursor = batch.cursor(RecordFormat::Json(Default::default()))?;
+
+                while cursor.key_valid() {
+                    let w = cursor.weight();
+                    if (w < 0) != deletes || w == 0 {
+                        cursor.step_key();
+                        continue;
+                    }
+
+                    key.clear();
+                    cursor.serialize_key(&mut key)?;
+                    let columns: Columns = serde_json::from_slice(&key)?;
+
+                    let (statement, count) = if deletes {
+                        // A single `DELETE` statement removes all deleted
+                        // copies of the row.
+                        (self.delete_statement(&columns, -w)?, 1)
+                    } else {
+                        (self.insert_statement(&columns), w)
+                    };
+
+                    if statement.len() > self.max_buffer_size {
+                        bail!("SQL statement exceeds maximum buffer size supported by the output transport. Max supported buffer size is {} bytes, but the following statement requires {} bytes: '{}'.",
+                              self.max_buffer_size,
+                              statement.len(),
+                              truncate_ellipse(&statement, MAX_RECORD_LEN_IN_ERRMSG, "..."));
+                    }
+
+                    for _ in 0..count {
+                        if buffer.len() + statement.len() > self.max_buffer_size {
+                            self.output_consumer.push_buffer(&buffer);
+                            buffer.clear();
+                            num_records = 0;
+                        }
+
+                        buffer.extend_from_slice(statement.as_bytes());
+                        num_records += 1;
+
+                        if num_records >= self.config.buffer_size_records {
+                            self.output_consumer.push_buffer(&buffer);
+                            buffer.clear();
+                            num_records = 0;
+                        }
+                    }
+
+                    cursor.step_key();
+                }
+            }
+        }
+
+        if num_records > 0 {
+            self.output_consumer.push_buffer(&buffer);
+            buffer.clear();
+        }
+
+        self.buffer = buffer;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SqlEncoder, SqlEncoderConfig};
+    use crate::{
+        catalog::SerBatch,
+        format::Encoder,
+        static_compile::seroutput::SerBatchImpl,
+        test::{MockOutputConsumer, TestStruct},
+    };
+    use dbsp::{trace::Batch, OrdZSet};
+    use std::sync::Arc;
+
+    fn encode(config: SqlEncoderConfig, records: Vec<(TestStruct, i64)>) -> String {
+        let consumer = MockOutputConsumer::new();
+        let data = consumer.data.clone();
+        let mut encoder = SqlEncoder::new(Box::new(consumer), config).unwrap();
+
+        let zset = OrdZSet::from_keys((), records);
+        let batch = Arc::new(<SerBatchImpl<_, TestStruct, ()>>::new(zset)) as Arc<dyn SerBatch>;
+        encoder.encode(&[batch]).unwrap();
+
+        let data = data.lock().unwrap();
+        std::str::from_utf8(&data).unwrap().to_string()
+    }
+
+    fn test_records() -> Vec<(TestStruct, i64)> {
+        vec![
+            (
+                TestStruct {
+                    id: 1,
+                    b: true,
+                    i: None,
+                    s: "it's".to_string(),
+                },
+                -2,
+            ),
+            (
+                TestStruct {
+                    id: 2,
+                    b: false,
+                    i: Some(5),
+                    s: "foo".to_string(),
+                },
+                2,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_sql_encoder() {
+        let config = SqlEncoderConfig {
+            table: Some("T".to_string()),
+            key_columns: None,
+            buffer_size_records: 10,
+        };
+
+        assert_eq!(
+            encode(config, test_records()),
+            r#"DELETE FROM T WHERE ctid IN (SELECT ctid FROM T WHERE id = 1 AND b = TRUE AND i IS NULL AND s = 'it''s' LIMIT 2);
+INSERT INTO T (id, b, i, s) VALUES (2, FALSE, 5, 'foo');
+INSERT INTO T (id, b, i, s) VALUES (2, FALSE, 5, 'foo');
+"#
+        );
+    }
+
+    #[test]
+    fn test_sql_encoder_key_columns() {
+        let config = SqlEncoderConfig {
+            table: Some("my table".to_string()),
+            key_columns: Some("ID".to_string()),
+            buffer_size_records: 10,
+        };
+
+        assert_eq!(
+            encode(config, test_records()),
+            r#"DELETE FROM "my table" WHERE id = 1;
+INSERT INTO "my table" (id, b, i, s) VALUES (2, FALSE, 5, 'foo');
+INSERT INTO "my table" (id, b, i, s) VALUES (2, FALSE, 5, 'foo');
+"#
+        );
+    }
+
+    #[test]
+    fn test_sql_encoder_no_table() {
+        let config = SqlEncoderConfig {
+            table: None,
+            key_columns: None,
+            buffer_size_records: 10,
+        };
+
+        assert!(SqlEncoder::new(Box::new(MockOutputConsumer::new()), config).is_err());
+    }
+}
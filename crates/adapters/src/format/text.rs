@@ -0,0 +1,453 @@
+//! Parser for line-oriented text formats that aren't valid CSV: tab-separated
+//! values and fixed-width columns.
+//!
+//! Each line of input is split into fields according to the configured
+//! layout, converted to a CSV record, and deserialized using the CSV record
+//! deserializer, so fields are parsed according to the same rules as
+//! CSV fields.
+
+use super::csv::{insert_record, join_record};
+use crate::{
+    catalog::{DeCollectionStream, RecordFormat},
+    format::{InputFormat, ParseError, Parser},
+    util::{count_newlines, split_on_newline},
+    ControllerError, DeCollectionHandle,
+};
+use actix_web::HttpRequest;
+use anyhow::{bail, Result as AnyResult};
+use erased_serde::Serialize as ErasedSerialize;
+use serde::{Deserialize, Serialize};
+use serde_urlencoded::Deserializer as UrlDeserializer;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, mem::take};
+use utoipa::ToSchema;
+
+/// Text format parser.
+pub struct TextInputFormat;
+
+/// Layout of columns in a line of text.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextLayout {
+    /// Tab-separated values.
+    ///
+    /// Fields are separated by tab characters and are not quoted.  The
+    /// escape sequences `\t`, `\n`, `\r`, and `\\` in a field are replaced
+    /// with tab, newline, carriage return, and backslash characters.
+    #[default]
+    Tsv,
+
+    /// Columns at fixed character offsets, specified in `columns`.
+    ///
+    /// Leading and trailing whitespace is trimmed from each field.
+    FixedWidth,
+}
+
+/// Position of a column in the fixed-width layout.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct FixedWidthColumn {
+    /// Offset of the first character of the column in the line, starting
+    /// from 0.
+    pub offset: usize,
+
+    /// Width of the column in characters.
+    pub width: usize,
+}
+
+/// Text parser configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct TextParserConfig {
+    /// Layout of columns in a line of text.  The default is `tsv`.
+    #[serde(default)]
+    pub layout: TextLayout,
+
+    /// Positions of table columns in each line, in the order of table
+    /// columns.
+    ///
+    /// Required by the `fixed_width` layout.
+    #[serde(default)]
+    pub columns: Vec<FixedWidthColumn>,
+
+    /// The first line of the input stream is a header line, which is skipped.
+    #[serde(default)]
+    pub headers: bool,
+
+    /// Field value that represents SQL `NULL`, e.g., `\N`.
+    #[serde(default)]
+    pub null_value: Option<String>,
+
+    /// Replace fields that fail to parse with `NULL` (or the default value of
+    /// the column type for `NOT NULL` columns) instead of rejecting the
+    /// record.
+    #[serde(default)]
+    pub lenient: bool,
+}
+
+impl TextParserConfig {
+    fn validate(&self) -> AnyResult<()> {
+        match self.layout {
+            TextLayout::Tsv if !self.columns.is_empty() => {
+                bail!("'columns' can only be specified with the 'fixed_width' layout")
+            }
+            TextLayout::FixedWidth if self.columns.is_empty() => {
+                bail!("the 'fixed_width' layout requires 'columns'")
+            }
+            TextLayout::FixedWidth if self.columns.iter().any(|column| column.width == 0) => {
+                bail!("column width must be greater than 0")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl InputFormat for TextInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("text")
+    }
+
+    fn config_from_http_request(
+        &self,
+        endpoint_name: &str,
+        request: &HttpRequest,
+    ) -> Result<Box<dyn ErasedSerialize>, ControllerError> {
+        Ok(Box::new(
+            TextParserConfig::deserialize(UrlDeserializer::new(form_urlencoded::parse(
+                request.query_string().as_bytes(),
+            )))
+            .map_err(|e| {
+                ControllerError::parser_config_parse_error(
+                    endpoint_name,
+                    &e,
+                    request.query_string(),
+                )
+            })?,
+        ))
+    }
+
+    fn new_parser(
+        &self,
+        endpoint_name: &str,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> Result<Box<dyn Parser>, ControllerError> {
+        let config_str = || serde_yaml::to_string(&config).unwrap_or_default();
+        let config = TextParserConfig::deserialize(config).map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+        config.validate().map_err(|e| {
+            ControllerError::parser_config_parse_error(endpoint_name, &e, &config_str())
+        })?;
+
+        let input_stream =
+            input_stream.configure_deserializer(RecordFormat::Csv(Default::default()))?;
+        Ok(Box::new(TextParser::new(input_stream, config)) as Box<dyn Parser>)
+    }
+}
+
+/// Replace escape sequences in a TSV field.
+fn unescape_tsv(field: &str) -> Cow<'_, str> {
+    if !field.contains('\\') {
+        return Cow::Borrowed(field);
+    }
+
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Extract the field at `column` from `line`.
+///
+/// Returns an empty field if the line is too short to contain the column.
+fn fixed_width_field<'a>(line: &'a str, column: &FixedWidthColumn) -> &'a str {
+    let mut indices = line
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(line.len()))
+        .skip(column.offset);
+    let Some(start) = indices.next() else {
+        return "";
+    };
+    let end = indices
+        .nth(column.width.saturating_sub(1))
+        .unwrap_or(line.len());
+    line[start..end].trim()
+}
+
+struct TextParser {
+    /// Input handle to push parsed data to.
+    input_stream: Box<dyn DeCollectionStream>,
+
+    config: TextParserConfig,
+
+    /// The header line of the input stream has not been received yet.
+    expect_headers: bool,
+
+    /// Incomplete line received via `input_fragment`.
+    leftover: Vec<u8>,
+
+    last_event_number: u64,
+
+    /// Number of lines received via `input_fragment` and parsed so far.
+    num_lines: u64,
+}
+
+impl TextParser {
+    fn new(input_stream: Box<dyn DeCollectionStream>, config: TextParserConfig) -> Self {
+        Self {
+            input_stream,
+            expect_headers: config.headers,
+            config,
+            leftover: Vec::new(),
+            last_event_number: 0,
+            num_lines: 0,
+        }
+    }
+
+    /// Split `line` into fields.
+    fn fields<'a>(&self, line: &'a str) -> Vec<Cow<'a, str>> {
+        match self.config.layout {
+            TextLayout::Tsv => line.split('\t').map(unescape_tsv).collect(),
+            TextLayout::FixedWidth => self
+                .config
+                .columns
+                .iter()
+                .map(|column| Cow::Borrowed(fixed_width_field(line, column)))
+                .collect(),
+        }
+    }
+
+    /// Parse lines of text in `data`.
+    ///
+    /// When `data` is part of a continuous text stream, `first_line` is the
+    /// number of lines in the stream preceding `data` and is used to
+    /// attribute errors to lines.
+    fn parse_lines(&mut self, data: &[u8], first_line: Option<u64>) -> (usize, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let mut num_records = 0;
+
+        for (index, line) in data.split(|c| *c == b'\n').enumerate() {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+            if take(&mut self.expect_headers) {
+                continue;
+            }
+
+            self.last_event_number += 1;
+            let mut line_errors = match std::str::from_utf8(line) {
+                Err(e) => vec![ParseError::bin_event_error(
+                    format!("line is not a valid UTF-8 string: {e}"),
+                    self.last_event_number,
+                    line,
+                    None,
+                )],
+                Ok(text) => {
+                    let fields = self.fields(text);
+                    let fields = fields.iter().map(|field| {
+                        if Some(field.as_ref()) == self.config.null_value.as_deref() {
+                            &[][..]
+                        } else {
+                            field.as_bytes()
+                        }
+                    });
+                    // Encoding a record in memory cannot fail.
+                    let record = join_record(fields).unwrap_or_default();
+                    let (inserted, line_errors) = insert_record(
+                        self.input_stream.as_mut(),
+                        &record,
+                        line,
+                        self.last_event_number,
+                        self.config.lenient,
+                    );
+                    if inserted {
+                        num_records += 1;
+                    }
+                    line_errors
+                }
+            };
+            if let Some(first_line) = first_line {
+                for error in line_errors.iter_mut() {
+                    error.set_line_number(first_line + index as u64 + 1);
+                }
+            }
+            errors.append(&mut line_errors);
+        }
+
+        self.input_stream.flush();
+        (num_records, errors)
+    }
+}
+
+impl Parser for TextParser {
+    fn input_fragment(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let leftover = split_on_newline(data);
+
+        if leftover == 0 {
+            // `data` doesn't contain a new-line character; append it to
+            // the `leftover` buffer so it gets processed with the next input
+            // buffer.
+            self.leftover.extend_from_slice(data);
+            (0, Vec::new())
+        } else {
+            let mut leftover_buf = take(&mut self.leftover);
+            leftover_buf.extend_from_slice(&data[0..leftover]);
+
+            let res = self.parse_lines(&leftover_buf, Some(self.num_lines));
+            self.num_lines += count_newlines(&leftover_buf);
+
+            leftover_buf.clear();
+            leftover_buf.extend_from_slice(&data[leftover..]);
+            self.leftover = leftover_buf;
+
+            res
+        }
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        self.parse_lines(data, None)
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        if self.leftover.is_empty() {
+            return (0, Vec::new());
+        }
+
+        let leftover = take(&mut self.leftover);
+        let res = self.parse_lines(&leftover, Some(self.num_lines));
+        self.num_lines += count_newlines(&leftover);
+        res
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(self.input_stream.fork(), self.config.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FixedWidthColumn, TextLayout, TextParserConfig};
+    use crate::{
+        test::{mock_parser_pipeline, TestStruct},
+        transport::InputConsumer,
+        FormatConfig,
+    };
+    use std::borrow::Cow;
+
+    fn parse(config: TextParserConfig, fragments: &[&str]) -> Vec<(TestStruct, bool)> {
+        let format_config = FormatConfig {
+            name: Cow::from("text"),
+            config: serde_yaml::to_value(config).unwrap(),
+        };
+
+        let (mut consumer, outputs) = mock_parser_pipeline(&format_config).unwrap();
+        consumer.on_error(Some(Box::new(|_| {})));
+        for fragment in fragments {
+            assert_eq!(consumer.input_fragment(fragment.as_bytes()), vec![]);
+        }
+        assert_eq!(consumer.eoi(), vec![]);
+
+        let flushed = outputs.state().flushed.drain(..).collect();
+        flushed
+    }
+
+    #[test]
+    fn test_tsv() {
+        let records = parse(
+            TextParserConfig {
+                headers: true,
+                null_value: Some("\\N".to_string()),
+                ..Default::default()
+            },
+            &[
+                "id\tb\ti\ts\n1\ttrue\t\\N\tfoo, \"bar\"\n",
+                "2\tfalse\t5\ta\\tb\\\\c\r\n3\tfalse\t6\t",
+            ],
+        );
+        assert_eq!(
+            records,
+            vec![
+                (
+                    TestStruct {
+                        id: 1,
+                        b: true,
+                        i: None,
+                        s: "foo, \"bar\"".to_string()
+                    },
+                    true
+                ),
+                (
+                    TestStruct {
+                        id: 2,
+                        b: false,
+                        i: Some(5),
+                        s: "a\tb\\c".to_string()
+                    },
+                    true
+                ),
+                (
+                    TestStruct {
+                        id: 3,
+                        b: false,
+                        i: Some(6),
+                        s: "".to_string()
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixed_width() {
+        let column = |offset, width| FixedWidthColumn { offset, width };
+        let records = parse(
+            TextParserConfig {
+                layout: TextLayout::FixedWidth,
+                columns: vec![column(0, 4), column(4, 6), column(10, 5), column(15, 10)],
+                ..Default::default()
+            },
+            &["   1true     42 hello wor", "ld\n  2 false      ünï\n"],
+        );
+        assert_eq!(
+            records,
+            vec![
+                (
+                    TestStruct {
+                        id: 1,
+                        b: true,
+                        i: Some(42),
+                        s: "hello wor".to_string()
+                    },
+                    true
+                ),
+                (
+                    TestStruct {
+                        id: 2,
+                        b: false,
+                        i: None,
+                        s: "ünï".to_string()
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! In-process test harness for pipelines.
+//!
+//! [`TestPipeline`] runs a [`Controller`] for a circuit in the current
+//! process, in manual stepping mode, so that tests can push records to
+//! tables, run the circuit one step at a time, and check the changes
+//! produced by each step in views without going through the HTTP API or
+//! external transports.
+//!
+//! ```text
+//!              insert/delete                                    changes/contents
+//!                   │                                                  ▲
+//!                   ▼                                                  │
+//!           ┌──────────────┐     ┌──────────────────┐     ┌────────────┴─┐
+//!           │PushEndpoint  ├────►│controller+circuit├────►│Collector     │
+//!           │(one per table)     └──────────────────┘     │(one per view)│
+//!           └──────────────┘              ▲               └──────────────┘
+//!                                         │
+//!                                       step
+//! ```
+//!
+//! Records are exchanged as JSON values, encoded and decoded by the `json`
+//! format.  Endpoints listed in the pipeline configuration are connected as
+//! usual, so the harness can also be used to test transports and formats
+//! end-to-end.
+
+use crate::{
+    AsyncErrorCallback, CircuitCatalog, ConnectorConfig, Controller, ControllerError,
+    DbspCircuitHandle, FormatConfig, InputConsumer, InputEndpoint, InputEndpointConfig,
+    OutputEndpoint, OutputEndpointConfig, OutputQuery, PipelineConfig, StepStats, TransportConfig,
+};
+use anyhow::{anyhow, Result as AnyResult};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    mem::take,
+    sync::{mpsc, Arc, Mutex},
+    thread::sleep,
+    time::Duration,
+};
+
+/// A pipeline running in the current process, for use in tests.
+///
+/// # Example
+///
+/// ```ignore
+/// let pipeline = TestPipeline::new(&config, |workers| Ok(my_circuit(workers)))?;
+///
+/// pipeline.insert("t", &[json!({"id": 1, "name": "foo"})])?;
+/// pipeline.step()?;
+/// assert_eq!(pipeline.contents("v")?, vec![json!({"id": 1, "name": "foo"})]);
+///
+/// pipeline.delete("t", &[json!({"id": 1, "name": "foo"})])?;
+/// pipeline.step()?;
+/// assert_eq!(pipeline.changes("v")?, vec![(json!({"id": 1, "name": "foo"}), -1)]);
+///
+/// pipeline.stop()?;
+/// ```
+pub struct TestPipeline {
+    controller: Option<Controller>,
+
+    /// Endpoints that push records to tables, created on first use.
+    inputs: Mutex<BTreeMap<String, PushEndpoint>>,
+
+    /// Collectors of the outputs of all views.
+    outputs: BTreeMap<String, Collector>,
+
+    /// Errors reported by the controller.
+    errors: Arc<Mutex<Vec<ControllerError>>>,
+}
+
+impl TestPipeline {
+    /// Start a pipeline for the circuit created by `circuit_factory`, with
+    /// endpoints and settings specified by `config`.
+    ///
+    /// The pipeline runs in manual stepping mode: inputs are only processed
+    /// by [`Self::step`].  All views of the circuit are collected, starting
+    /// with the first step.
+    pub fn new<F>(config: &PipelineConfig, circuit_factory: F) -> Result<Self, ControllerError>
+    where
+        F: FnOnce(
+                usize,
+            )
+                -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+            + Send
+            + 'static,
+    {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let controller = {
+            let errors = errors.clone();
+            Controller::with_config(
+                circuit_factory,
+                config,
+                Box::new(move |e| errors.lock().unwrap().push(e)),
+            )?
+        };
+        controller.set_manual_stepping(true);
+
+        let views = controller
+            .catalog()
+            .lock()
+            .unwrap()
+            .output_collection_names();
+        let mut outputs = BTreeMap::new();
+        for view in views {
+            let collector = Collector::new(controller.status().num_total_steps());
+            controller.add_output_endpoint(
+                &format!("test-output-{view}"),
+                &OutputEndpointConfig {
+                    stream: Cow::from(view.clone()),
+                    query: OutputQuery::Table,
+                    connector_config: connector_config(),
+                },
+                Box::new(collector.clone()),
+            )?;
+            outputs.insert(view, collector);
+        }
+
+        controller.start();
+
+        Ok(Self {
+            controller: Some(controller),
+            inputs: Mutex::new(BTreeMap::new()),
+            outputs,
+            errors,
+        })
+    }
+
+    /// The controller that runs the pipeline.
+    pub fn controller(&self) -> &Controller {
+        self.controller.as_ref().unwrap()
+    }
+
+    /// Insert `records` into table `table`.
+    ///
+    /// The records are processed by the next [`step`](`Self::step`).
+    pub fn insert(&self, table: &str, records: &[JsonValue]) -> AnyResult<()> {
+        self.push(table, "insert", records)
+    }
+
+    /// Delete `records` from table `table`.
+    ///
+    /// The records are processed by the next [`step`](`Self::step`).
+    pub fn delete(&self, table: &str, records: &[JsonValue]) -> AnyResult<()> {
+        self.push(table, "delete", records)
+    }
+
+    fn push(&self, table: &str, command: &str, records: &[JsonValue]) -> AnyResult<()> {
+        let mut inputs = self.inputs.lock().unwrap();
+        if !inputs.contains_key(table) {
+            let endpoint = PushEndpoint::default();
+            self.controller().add_input_endpoint(
+                &format!("test-input-{table}"),
+                InputEndpointConfig {
+                    stream: Cow::from(table.to_string()),
+                    columns: None,
+                    lineage: None,
+                    dedup: None,
+                    connector_config: connector_config(),
+                },
+                Box::new(endpoint.clone()),
+            )?;
+            inputs.insert(table.to_string(), endpoint);
+        }
+
+        let mut data = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut data, &serde_json::json!({ command: record }))?;
+            data.push(b'\n');
+        }
+
+        let errors = inputs[table]
+            .consumer
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or_else(|| anyhow!("input endpoint for table '{table}' is disconnected"))?
+            .input_chunk(&data);
+        match errors.into_iter().next() {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Perform a step of the circuit, processing all records pushed since
+    /// the previous step, and wait until the outputs of the step have been
+    /// collected.
+    pub fn step(&self) -> Result<StepStats, ControllerError> {
+        let controller = self.controller();
+
+        let (sender, receiver) = mpsc::channel();
+        controller.step_with_stats(Box::new(move |stats| {
+            let _ = sender.send(stats);
+        }));
+        let Ok(stats) = receiver.recv() else {
+            // The callback is dropped without being invoked if the pipeline
+            // terminates.
+            return Err(self
+                .errors
+                .lock()
+                .unwrap()
+                .pop()
+                .unwrap_or_else(ControllerError::controller_panic));
+        };
+
+        let steps = controller.status().num_total_steps();
+        for collector in self.outputs.values() {
+            while collector.steps() < steps {
+                sleep(Duration::from_millis(1));
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Changes to view `view` collected since the previous call, as records
+    /// with weights.
+    pub fn changes(&self, view: &str) -> Result<Vec<(JsonValue, i64)>, ControllerError> {
+        Ok(take(
+            &mut self.collector(view)?.state.lock().unwrap().changes,
+        ))
+    }
+
+    /// Contents of view `view` after the latest step, ordered by their
+    /// JSON encoding.  Records with weight `N` are repeated `N` times.
+    pub fn contents(&self, view: &str) -> Result<Vec<JsonValue>, ControllerError> {
+        let state = self.collector(view)?.state.lock().unwrap();
+        Ok(state
+            .contents
+            .iter()
+            .flat_map(|(_, (record, weight))| {
+                std::iter::repeat(record.clone()).take((*weight).max(0) as usize)
+            })
+            .collect())
+    }
+
+    /// Errors reported by the controller since the previous call, e.g.,
+    /// records that failed to parse or encode.
+    pub fn take_errors(&self) -> Vec<ControllerError> {
+        take(&mut *self.errors.lock().unwrap())
+    }
+
+    /// Stop the pipeline.
+    pub fn stop(mut self) -> Result<(), ControllerError> {
+        self.controller.take().unwrap().stop()
+    }
+
+    fn collector(&self, view: &str) -> Result<&Collector, ControllerError> {
+        self.outputs
+            .get(view)
+            .ok_or_else(|| ControllerError::unknown_output_stream("test-output", view))
+    }
+}
+
+impl Drop for TestPipeline {
+    fn drop(&mut self) {
+        if let Some(controller) = self.controller.take() {
+            let _ = controller.stop();
+        }
+    }
+}
+
+/// Connector configuration of the endpoints created by the harness.
+fn connector_config() -> ConnectorConfig {
+    ConnectorConfig {
+        transport: TransportConfig {
+            name: Cow::from("test"),
+            config: YamlValue::Null,
+        },
+        format: FormatConfig {
+            name: Cow::from("json"),
+            config: YamlValue::Mapping(Default::default()),
+        },
+        max_buffered_records: u64::MAX,
+        max_records_per_sec: None,
+        max_bytes_per_sec: None,
+        retry: None,
+        max_batch_delay_ms: None,
+        max_batch_size: None,
+        num_parsers: None,
+        parser_sharding: None,
+        on_error: None,
+        overflow: None,
+    }
+}
+
+/// Input endpoint that pushes records submitted via [`TestPipeline`] to
+/// the controller.  Records are pushed even while the endpoint is paused.
+#[derive(Clone, Default)]
+struct PushEndpoint {
+    consumer: Arc<Mutex<Option<Box<dyn InputConsumer>>>>,
+}
+
+impl InputEndpoint for PushEndpoint {
+    fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        *self.consumer.lock().unwrap() = Some(consumer);
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        *self.consumer.lock().unwrap() = None;
+    }
+}
+
+#[derive(Default)]
+struct CollectorState {
+    /// Number of steps whose outputs have been collected.
+    steps: u64,
+
+    /// Changes not yet retrieved by [`TestPipeline::changes`].
+    changes: Vec<(JsonValue, i64)>,
+
+    /// Contents of the view, indexed by the JSON encoding of the record.
+    contents: BTreeMap<String, (JsonValue, i64)>,
+}
+
+/// Output endpoint that collects the changes to a view.
+///
+/// The controller sends the outputs of each step in a separate batch, so
+/// the number of completed batches is the number of steps performed since
+/// the endpoint was connected.
+#[derive(Clone)]
+struct Collector {
+    /// The number of steps performed before the endpoint was connected.
+    base_steps: u64,
+    state: Arc<Mutex<CollectorState>>,
+}
+
+impl Collector {
+    fn new(base_steps: u64) -> Self {
+        Self {
+            base_steps,
+            state: Default::default(),
+        }
+    }
+
+    /// Number of steps whose outputs have been collected.
+    fn steps(&self) -> u64 {
+        self.base_steps + self.state.lock().unwrap().steps
+    }
+}
+
+impl OutputEndpoint for Collector {
+    fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        usize::MAX
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        let mut state = self.state.lock().unwrap();
+        for line in buffer.split(|c| *c == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let (record, weight) = match serde_json::from_slice(line)? {
+                JsonValue::Object(mut update) => {
+                    match (update.remove("insert"), update.remove("delete")) {
+                        (Some(record), None) => (record, 1),
+                        (None, Some(record)) => (record, -1),
+                        _ => {
+                            return Err(anyhow!(
+                                "invalid update: {}",
+                                String::from_utf8_lossy(line)
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(anyhow!("invalid update: {}", String::from_utf8_lossy(line))),
+            };
+
+            let key = record.to_string();
+            let entry = state
+                .contents
+                .entry(key.clone())
+                .or_insert_with(|| (record.clone(), 0));
+            entry.1 += weight;
+            if entry.1 == 0 {
+                state.contents.remove(&key);
+            }
+            state.changes.push((record, weight));
+        }
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        self.state.lock().unwrap().steps += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TestPipeline;
+    use crate::{test::test_circuit, PipelineConfig};
+    use serde_json::json;
+
+    #[test]
+    fn test_pipeline() {
+        let config: PipelineConfig = serde_yaml::from_str(
+            r#"
+name: test
+workers: 1
+inputs: {}
+"#,
+        )
+        .unwrap();
+
+        let pipeline = TestPipeline::new(&config, |workers| Ok(test_circuit(workers))).unwrap();
+
+        let foo = json!({"id": 1, "b": true, "i": null, "s": "foo"});
+        let bar = json!({"id": 2, "b": true, "i": null, "s": "bar"});
+
+        pipeline
+            .insert("test_input1", &[foo.clone(), bar.clone()])
+            .unwrap();
+        let stats = pipeline.step().unwrap();
+        assert_eq!(stats.input_records, 2);
+        assert_eq!(
+            pipeline.contents("test_output1").unwrap(),
+            vec![foo.clone(), bar.clone()]
+        );
+
+        pipeline.delete("test_input1", &[foo.clone()]).unwrap();
+        pipeline.step().unwrap();
+        assert_eq!(
+            pipeline.contents("test_output1").unwrap(),
+            vec![bar.clone()]
+        );
+        assert_eq!(
+            pipeline.changes("test_output1").unwrap(),
+            vec![(foo.clone(), 1), (bar, 1), (foo, -1)]
+        );
+
+        // Steps without inputs produce no changes.
+        pipeline.step().unwrap();
+        assert!(pipeline.changes("test_output1").unwrap().is_empty());
+
+        assert!(pipeline
+            .insert("test_input1", &[json!({"id": "x"})])
+            .is_err());
+        assert!(pipeline.insert("no_such_table", &[]).is_err());
+        assert!(pipeline.contents("no_such_view").is_err());
+
+        pipeline.stop().unwrap();
+    }
+}
@@ -8,7 +8,7 @@ impl Catalog {
     /// accompanying neighborhood/quantile handles.
     ///
     /// Used for JIT-compiled circuits, which don't yet support
-    /// neighborhoods and quantiles.
+    /// neighborhoods, quantiles, and samples.
     pub fn register_output_collection_handle(
         &mut self,
         name: &str,
@@ -18,11 +18,12 @@ impl Catalog {
             name.to_string(),
             OutputCollectionHandles {
                 delta_handle: handle,
-                neighborhood_descr_handle: None,
-                neighborhood_handle: None,
-                neighborhood_snapshot_handle: None,
+                neighborhood_sessions: Vec::new(),
                 num_quantiles_handle: None,
                 quantiles_handle: None,
+                sample_size_handle: None,
+                sample_handle: None,
+                column_stats_handle: None,
             },
         );
     }
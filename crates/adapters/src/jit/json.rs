@@ -29,12 +29,23 @@ pub(crate) fn build_json_deser_config(
 }
 
 /// Build JSON serializer configuration for specified layout and table schema.
-pub(crate) fn build_json_ser_config(layout: LayoutId, table_schema: &TableSchema) -> JsonSerConfig {
+///
+/// When `large_numbers_as_strings` is set, the serializer encodes `BIGINT`
+/// and `DECIMAL` columns as JSON strings.
+pub(crate) fn build_json_ser_config(
+    layout: LayoutId,
+    table_schema: &TableSchema,
+    large_numbers_as_strings: bool,
+) -> JsonSerConfig {
     let mappings = table_schema
         .fields
         .iter()
         .enumerate()
         .map(|(index, column)| (index, column_from_schema(column, false)))
         .collect();
-    JsonSerConfig { layout, mappings }
+    JsonSerConfig {
+        layout,
+        mappings,
+        large_numbers_as_strings,
+    }
 }
@@ -9,17 +9,19 @@ pub mod seroutput;
 
 use crate::Catalog;
 pub use schema::ProgramSchema;
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
 
 use dataflow_jit::{
     codegen::CodegenConfig,
     dataflow::RowOutput,
     facade::Demands,
     ir::{DemandId, Graph, GraphExt, NodeId},
+    sql_graph::SqlGraph,
     DbspCircuit,
 };
 
 use crate::{CircuitCatalog, ControllerError, DbspCircuitHandle};
+use dbsp::profile::OperatorProfile;
 
 use self::{
     deinput::DeZSetHandles,
@@ -74,11 +76,42 @@ impl DbspCircuitHandle for DbspCircuit {
         DbspCircuit::dump_profile(self, dir_path).map_err(ControllerError::dbsp_error)
     }
 
+    fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, ControllerError> {
+        DbspCircuit::retrieve_profile(self).map_err(ControllerError::dbsp_error)
+    }
+
     fn kill(self: Box<Self>) -> std::thread::Result<()> {
         DbspCircuit::kill(*self)
     }
 }
 
+/// Read the program schema and dataflow IR generated by the SQL compiler from
+/// `schema_file` and `ir_file`, then JIT-compile and instantiate the circuit
+/// (see [`start_circuit`]).
+#[allow(clippy::type_complexity)]
+pub fn start_circuit_from_files(
+    schema_file: &str,
+    ir_file: &str,
+    config: CircuitConfig,
+) -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError> {
+    let schema = File::open(schema_file).map_err(|e| {
+        ControllerError::io_error(format!("reading program schema file '{schema_file}'"), e)
+    })?;
+
+    let schema: ProgramSchema = serde_json::from_reader(BufReader::new(schema))
+        .map_err(|e| ControllerError::schema_parse_error(&e.to_string()))?;
+
+    let graph = File::open(ir_file).map_err(|e| {
+        ControllerError::io_error(format!("reading program IR file '{ir_file}'"), e)
+    })?;
+
+    let graph = serde_json::from_reader::<_, SqlGraph>(BufReader::new(graph))
+        .map_err(|e| ControllerError::ir_parse_error(&e.to_string()))?
+        .rematerialize();
+
+    start_circuit(&schema, graph, config)
+}
+
 /// JIT-compile and instantiate a circuit.
 ///
 /// Generates serializers and deserializers for all supported formats.
@@ -128,13 +161,22 @@ pub fn start_circuit(
         })
         .collect();
 
-    let mut json_output_demands: HashMap<NodeId, DemandId> = HashMap::new();
+    // Compile two JSON serializers per output: one that encodes large numbers
+    // as JSON numbers and one that encodes them as strings.
+    let mut json_output_demands: HashMap<NodeId, (DemandId, DemandId)> = HashMap::new();
     for table_schema in schema.outputs.iter() {
         let (node, layout) = sink_names.get(&table_schema.name).ok_or_else(|| ControllerError::schema_validation_error(&format!("program schema specifies output view '{}', which does not exist in the dataflow graph", &table_schema.name)))?;
 
         println!("table_name: {}, layout: {}", &table_schema.name, layout);
-        let json_config = build_json_ser_config(*layout, table_schema);
-        json_output_demands.insert(*node, demands.add_json_serialize(json_config));
+        let json_config = build_json_ser_config(*layout, table_schema, false);
+        let json_strings_config = build_json_ser_config(*layout, table_schema, true);
+        json_output_demands.insert(
+            *node,
+            (
+                demands.add_json_serialize(json_config),
+                demands.add_json_serialize(json_strings_config),
+            ),
+        );
 
         // let csv_config = build_csv_ser_config(table_schema);
         // demands.add_csv_serialize(*layout, csv_config);
@@ -153,6 +195,7 @@ pub fn start_circuit(
     );
 
     let mut catalog = Catalog::new();
+    catalog.set_schema(schema.clone());
 
     for table_schema in schema.inputs.iter() {
         let node_id = source_names[&table_schema.name].0;
@@ -197,18 +240,27 @@ pub fn start_circuit(
 
         // FIXME: This is unsafe. The correct fix is to make sure `endpoint.disconnect`
         // returns after all endpoint threads have terminated.
+        let (json_demand, json_strings_demand) = json_output_demands[&node_id];
         let json =
-            unsafe { circuit.serialization_function(json_output_demands[&node_id], layout_id) }
-                .ok_or_else(|| {
+            unsafe { circuit.serialization_function(json_demand, layout_id) }.ok_or_else(|| {
+                ControllerError::jit_error(&format!(
+                "JSON serialization function not found (view name: '{}', layout id: {layout_id})",
+                table_schema.name,
+            ))
+            })?;
+        let json_strings =
+            unsafe { circuit.serialization_function(json_strings_demand, layout_id) }.ok_or_else(
+                || {
                     ControllerError::jit_error(&format!(
                 "JSON serialization function not found (view name: '{}', layout id: {layout_id})",
                 table_schema.name,
             ))
-                })?;
+                },
+            )?;
 
         catalog.register_output_collection_handle(
             &table_schema.name,
-            Box::new(SerZSetHandle::new(zset_handle.clone(), json)),
+            Box::new(SerZSetHandle::new(zset_handle.clone(), json, json_strings)),
         )
     }
 
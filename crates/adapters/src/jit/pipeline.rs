@@ -9,13 +9,11 @@
 
 use anyhow::Result as AnyResult;
 use clap::{Args, Command, FromArgMatches, Parser};
-use dataflow_jit::sql_graph::SqlGraph;
 use dbsp_adapters::{
-    jit::{start_circuit, CircuitConfig, ProgramSchema},
+    jit::{start_circuit_from_files, CircuitConfig},
     server::{run_server, ServerArgs},
     CircuitCatalog, ControllerError, DbspCircuitHandle,
 };
-use std::{fs::File, io::BufReader};
 
 #[derive(Parser, Debug)]
 struct JitArgs {
@@ -39,27 +37,12 @@ fn run(
     workers: usize,
     args: JitArgs,
 ) -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError> {
-    let schema = File::open(&args.schema).map_err(|e| {
-        ControllerError::io_error(format!("reading program schema file '{}'", &args.schema), e)
-    })?;
-
-    let schema: ProgramSchema = serde_json::from_reader(BufReader::new(schema))
-        .map_err(|e| ControllerError::schema_parse_error(&e.to_string()))?;
-
-    let graph = File::open(&args.ir).map_err(|e| {
-        ControllerError::io_error(format!("reading program IR file '{}'", &args.ir), e)
-    })?;
-
-    let graph = serde_json::from_reader::<_, SqlGraph>(BufReader::new(graph))
-        .map_err(|e| ControllerError::ir_parse_error(&e.to_string()))?
-        .rematerialize();
-
     let config = CircuitConfig::default()
         .release(args.release)
         .optimize(args.optimize)
         .workers(workers);
 
-    start_circuit(&schema, graph, config)
+    start_circuit_from_files(&args.schema, &args.ir, config)
 }
 
 pub fn main() -> AnyResult<()> {
@@ -74,7 +57,7 @@ pub fn main() -> AnyResult<()> {
 
     // Do all input validation and initialization inside `run`, so that all errors can be
     // reported via REST API.
-    run_server(server_args, move |workers| run(workers, jit_args))?;
+    run_server(server_args, &[], move |workers| run(workers, jit_args))?;
 
     Ok(())
 }
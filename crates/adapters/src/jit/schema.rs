@@ -13,7 +13,7 @@ use serde::Deserialize;
 ///
 /// This is a type-safe representation of the JSON schema file
 /// output by the SQL compiler.
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct ProgramSchema {
     #[serde(default)]
     pub inputs: Vec<TableSchema>,
@@ -22,21 +22,23 @@ pub struct ProgramSchema {
 }
 
 /// SQL table or view schema.
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct TableSchema {
     pub name: String,
     pub fields: Vec<ColumnSchema>,
 }
 
 /// Table column schema.
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct ColumnSchema {
     pub name: String,
     pub columntype: ColumnType,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct ColumnType {
     #[serde(rename = "type")]
     pub typ: String,
+    #[serde(default)]
+    pub nullable: bool,
 }
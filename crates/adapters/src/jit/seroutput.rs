@@ -19,11 +19,17 @@ type RowZSet = OrdZSet<Row, i32>;
 struct SerZSet {
     zset: RowZSet,
     json: SerializeFn,
+    /// JSON serializer that encodes large numbers as strings.
+    json_strings: SerializeFn,
 }
 
 impl SerZSet {
-    fn new(zset: RowZSet, json: SerializeFn) -> Self {
-        Self { zset, json }
+    fn new(zset: RowZSet, json: SerializeFn, json_strings: SerializeFn) -> Self {
+        Self {
+            zset,
+            json,
+            json_strings,
+        }
     }
 }
 
@@ -41,8 +47,15 @@ impl SerBatch for SerZSet {
         record_format: RecordFormat,
     ) -> Result<Box<dyn SerCursor + 'a>, ControllerError> {
         match record_format {
-            RecordFormat::Csv => todo!(),
-            RecordFormat::Json => Ok(Box::new(SerZSetCursor::new(self.zset.cursor(), self.json))),
+            RecordFormat::Csv(_) => todo!(),
+            RecordFormat::Json(flavor) => {
+                let serfn = if flavor.large_numbers_as_strings {
+                    self.json_strings
+                } else {
+                    self.json
+                };
+                Ok(Box::new(SerZSetCursor::new(self.zset.cursor(), serfn)))
+            }
         }
     }
 }
@@ -111,32 +124,43 @@ impl<'a> SerCursor for SerZSetCursor<'a> {
 pub struct SerZSetHandle {
     handle: OutputHandle<RowZSet>,
     json: SerializeFn,
+    json_strings: SerializeFn,
 }
 
 impl SerZSetHandle {
-    pub fn new(handle: OutputHandle<OrdZSet<Row, i32>>, json: SerializeFn) -> Self {
-        Self { handle, json }
+    pub fn new(
+        handle: OutputHandle<OrdZSet<Row, i32>>,
+        json: SerializeFn,
+        json_strings: SerializeFn,
+    ) -> Self {
+        Self {
+            handle,
+            json,
+            json_strings,
+        }
     }
 }
 
 impl SerCollectionHandle for SerZSetHandle {
     fn take_from_worker(&self, worker: usize) -> Option<Box<dyn SerBatch>> {
-        self.handle
-            .take_from_worker(worker)
-            .map(|batch| Box::new(SerZSet::new(batch, self.json)) as Box<dyn SerBatch>)
+        self.handle.take_from_worker(worker).map(|batch| {
+            Box::new(SerZSet::new(batch, self.json, self.json_strings)) as Box<dyn SerBatch>
+        })
     }
 
     fn take_from_all(&self) -> Vec<Arc<dyn SerBatch>> {
         self.handle
             .take_from_all()
             .into_iter()
-            .map(|batch| Arc::new(SerZSet::new(batch, self.json)) as Arc<dyn SerBatch>)
+            .map(|batch| {
+                Arc::new(SerZSet::new(batch, self.json, self.json_strings)) as Arc<dyn SerBatch>
+            })
             .collect()
     }
 
     fn consolidate(&self) -> Box<dyn SerBatch> {
         let batch = self.handle.consolidate();
-        Box::new(SerZSet::new(batch, self.json))
+        Box::new(SerZSet::new(batch, self.json, self.json_strings))
     }
 
     fn fork(&self) -> Box<dyn SerCollectionHandle> {
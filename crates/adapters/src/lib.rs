@@ -110,15 +110,24 @@
 //!
 //! * a [`Catalog`] object, which stores dictionaries of input and output
 //!   streams of the circuit.
+//!
+//! Several independent circuits can share one controller by combining their
+//! factories with [`multi_circuit_factory`].
 
 use num_derive::FromPrimitive;
 use serde::Serialize;
+use utoipa::ToSchema;
 
 mod catalog;
 mod circuit_handle;
+mod column_stats;
 mod controller;
+mod explain;
 pub mod format;
+mod harness;
 pub mod jit;
+pub mod multi_circuit;
+mod profile;
 pub mod server;
 pub mod static_compile;
 pub mod transport;
@@ -127,7 +136,7 @@ pub(crate) mod util;
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, Serialize, ToSchema)]
 pub enum PipelineState {
     /// All input endpoints are paused (or are in the process of being paused).
     Paused = 0,
@@ -144,17 +153,32 @@ pub use dbsp::DetailedError;
 
 pub use circuit_handle::DbspCircuitHandle;
 
+pub use multi_circuit::{multi_circuit_factory, CircuitFactory, MultiCatalog, MultiCircuitHandle};
+
+pub use column_stats::{ColumnStatistics, ColumnStatsHandle, ViewStatistics};
+
+pub use harness::TestPipeline;
+
+pub use explain::{AnalyzedOperator, ExplainAnalyze, SqlSourceMap, MAX_EXPLAIN_ANALYZE_SECS};
+pub use profile::{CircuitProfile, ProfiledOperator, MAX_PROFILE_STEPS};
+
 pub use server::{EgressMode, ErrorResponse, PipelineError};
 
 pub use catalog::{
-    Catalog, CircuitCatalog, DeCollectionHandle, DeCollectionStream, NeighborhoodQuery,
-    OutputQuery, OutputQueryHandles, SerBatch, SerCollectionHandle,
+    Catalog, ChangeStatistics, CircuitCatalog, ColumnDescr, DeCollectionHandle, DeCollectionStream,
+    NeighborhoodHandles, NeighborhoodQuery, OutputQuery, OutputQueryHandles, RelationDescr,
+    RelationKind, SerBatch, SerCollectionHandle, CHANGES_SUFFIX, NEIGHBORHOOD_SESSIONS,
 };
 pub use format::{Encoder, InputFormat, OutputConsumer, OutputFormat, ParseError, Parser};
 
 pub use controller::{
-    ConfigError, ConnectorConfig, Controller, ControllerError, ControllerStatus, FormatConfig,
-    InputEndpointConfig, OutputEndpointConfig, PipelineConfig, RuntimeConfig, TransportConfig,
+    CheckpointCallback, CheckpointConfig, ColumnCast, ColumnMappingConfig, ColumnPredicate,
+    ConfigError, ConnectorConfig, Controller, ControllerError, ControllerStatus, DedupConfig,
+    ErrorPolicy, FormatConfig, GlobalControllerMetrics, InputEndpointConfig, InputEndpointMetrics,
+    InputEndpointStatus, LineageConfig, OutputEndpointConfig, OutputEndpointMetrics,
+    OutputEndpointStatus, OverflowPolicy, ParserSharding, PipelineConfig, PredicateOp,
+    ProfileCallback, ReplayConfig, ReplayMode, RetryConfig, RuntimeConfig, StepCallback,
+    StepProfile, StepProfileCallback, StepStats, TlsConfig, TracingConfig, TransportConfig,
 };
 pub use transport::{
     AsyncErrorCallback, FileInputTransport, InputConsumer, InputEndpoint, InputTransport,
@@ -0,0 +1,286 @@
+//! Hosting several independent circuits in a single pipeline process.
+//!
+//! A pipeline process normally runs exactly one circuit.  Programs that are
+//! small enough (or auxiliary circuits, such as a lightweight monitoring
+//! circuit running next to the main one) can instead share a process, a
+//! controller, and an HTTP server.  [`multi_circuit_factory`] combines
+//! several circuit factories into one factory that can be passed to
+//! [`Controller::with_config`](`crate::Controller::with_config`) or
+//! [`server_main`](`crate::server::server_main`).
+//!
+//! The combined circuit is driven by the controller as a single unit: every
+//! step of the controller steps all circuits.  Each circuit keeps its own
+//! catalog.  Streams of the first (primary) circuit are addressed by their
+//! plain names, so configurations written for a single-circuit pipeline keep
+//! working.  Streams of any circuit, including the primary one, can also be
+//! addressed by their qualified name `<circuit>.<stream>`.
+
+use crate::{
+    catalog::OutputCollectionHandles, jit::schema::TableSchema, CircuitCatalog, ControllerError,
+    DbspCircuitHandle, DeCollectionHandle,
+};
+use dbsp::profile::OperatorProfile;
+use std::{collections::BTreeSet, fs::create_dir_all, path::PathBuf};
+
+/// Factory function that instantiates a circuit and its catalog given the
+/// number of worker threads.
+pub type CircuitFactory = Box<
+    dyn FnOnce(
+            usize,
+        )
+            -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+        + Send,
+>;
+
+/// Combine several named circuit factories into a single factory.
+///
+/// Circuits are instantiated in order, all with the same number of workers.
+/// The first circuit in the list is the primary circuit (see
+/// [module-level documentation](`crate::multi_circuit`)).  Circuit names
+/// must be unique, non-empty, and must not contain the `.` character.
+pub fn multi_circuit_factory(
+    factories: Vec<(String, CircuitFactory)>,
+) -> impl FnOnce(
+    usize,
+) -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+       + Send
+       + 'static {
+    move |workers| {
+        if factories.is_empty() {
+            return Err(ControllerError::pipeline_config_parse_error(
+                &"a multi-circuit pipeline must contain at least one circuit",
+            ));
+        }
+
+        let mut names = BTreeSet::new();
+        for (name, _) in factories.iter() {
+            if name.is_empty() || name.contains('.') {
+                return Err(ControllerError::pipeline_config_parse_error(&format!(
+                    "invalid circuit name '{name}': circuit names must be non-empty and must not contain '.'"
+                )));
+            }
+            if !names.insert(name.clone()) {
+                return Err(ControllerError::pipeline_config_parse_error(&format!(
+                    "duplicate circuit name '{name}'"
+                )));
+            }
+        }
+
+        let mut circuits = Vec::with_capacity(factories.len());
+        let mut catalogs = Vec::with_capacity(factories.len());
+
+        for (name, factory) in factories.into_iter() {
+            match factory(workers) {
+                Ok((circuit, catalog)) => {
+                    circuits.push((name.clone(), circuit));
+                    catalogs.push((name, catalog));
+                }
+                Err(e) => {
+                    // Shut down circuits we have already started.
+                    let _ = Box::new(MultiCircuitHandle { circuits }).kill();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok((
+            Box::new(MultiCircuitHandle { circuits }) as Box<dyn DbspCircuitHandle>,
+            Box::new(MultiCatalog { catalogs }) as Box<dyn CircuitCatalog>,
+        ))
+    }
+}
+
+/// A group of circuits driven in lockstep.
+pub struct MultiCircuitHandle {
+    circuits: Vec<(String, Box<dyn DbspCircuitHandle>)>,
+}
+
+impl DbspCircuitHandle for MultiCircuitHandle {
+    fn step(&mut self) -> Result<(), ControllerError> {
+        for (_, circuit) in self.circuits.iter_mut() {
+            circuit.step()?;
+        }
+        Ok(())
+    }
+
+    fn enable_cpu_profiler(&mut self) -> Result<(), ControllerError> {
+        for (_, circuit) in self.circuits.iter_mut() {
+            circuit.enable_cpu_profiler()?;
+        }
+        Ok(())
+    }
+
+    /// Dump the profile of each circuit into a separate subdirectory of
+    /// `dir_path` named after the circuit.
+    fn dump_profile(&mut self, dir_path: &str) -> Result<PathBuf, ControllerError> {
+        let dir = PathBuf::from(dir_path);
+
+        for (name, circuit) in self.circuits.iter_mut() {
+            let circuit_dir = dir.join(name.as_str());
+            create_dir_all(&circuit_dir).map_err(|e| {
+                ControllerError::io_error(
+                    format!("creating profile directory '{}'", circuit_dir.display()),
+                    e,
+                )
+            })?;
+            circuit.dump_profile(&circuit_dir.to_string_lossy())?;
+        }
+
+        Ok(dir)
+    }
+
+    /// Retrieve operator profiles of all circuits.
+    ///
+    /// Profiles of the `i`th worker of all circuits are concatenated in the
+    /// order circuits were created.
+    fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, ControllerError> {
+        let mut result: Vec<Vec<OperatorProfile>> = Vec::new();
+
+        for (_, circuit) in self.circuits.iter_mut() {
+            for (worker, profile) in circuit.retrieve_profile()?.into_iter().enumerate() {
+                if worker >= result.len() {
+                    result.push(Vec::new());
+                }
+                result[worker].extend(profile);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Kill all circuits.  Returns the first panic encountered, if any, after
+    /// attempting to kill every circuit.
+    fn kill(self: Box<Self>) -> std::thread::Result<()> {
+        let mut result = Ok(());
+
+        for (_, circuit) in self.circuits.into_iter() {
+            let status = circuit.kill();
+            if result.is_ok() {
+                result = status;
+            }
+        }
+
+        result
+    }
+}
+
+/// Catalog that routes stream lookups to the catalogs of individual circuits.
+pub struct MultiCatalog {
+    catalogs: Vec<(String, Box<dyn CircuitCatalog>)>,
+}
+
+impl MultiCatalog {
+    /// Find the catalog responsible for stream `name` and the name of the
+    /// stream within that catalog.
+    ///
+    /// Qualified names (`<circuit>.<stream>`) are resolved first; any other
+    /// name is looked up in the primary circuit.
+    fn resolve<'a>(&self, name: &'a str) -> Option<(&dyn CircuitCatalog, &'a str)> {
+        if let Some((circuit, stream)) = name.split_once('.') {
+            if let Some((_, catalog)) = self.catalogs.iter().find(|(n, _)| n == circuit) {
+                return Some((catalog.as_ref(), stream));
+            }
+        }
+
+        self.catalogs
+            .first()
+            .map(|(_, catalog)| (catalog.as_ref(), name))
+    }
+
+    /// Stream names returned by `names` for each circuit: plain names for
+    /// the primary circuit, qualified names for all other circuits.
+    fn qualified_names(&self, names: impl Fn(&dyn CircuitCatalog) -> Vec<String>) -> Vec<String> {
+        let mut result = Vec::new();
+        for (index, (circuit, catalog)) in self.catalogs.iter().enumerate() {
+            for name in names(catalog.as_ref()) {
+                if index == 0 {
+                    result.push(name);
+                } else {
+                    result.push(format!("{circuit}.{name}"));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl CircuitCatalog for MultiCatalog {
+    fn input_collection_handle(&self, name: &str) -> Option<&dyn DeCollectionHandle> {
+        let (catalog, stream) = self.resolve(name)?;
+        catalog.input_collection_handle(stream)
+    }
+
+    fn output_handles(&self, name: &str) -> Option<&OutputCollectionHandles> {
+        let (catalog, stream) = self.resolve(name)?;
+        catalog.output_handles(stream)
+    }
+
+    fn input_collection_names(&self) -> Vec<String> {
+        self.qualified_names(|catalog| catalog.input_collection_names())
+    }
+
+    fn output_collection_names(&self) -> Vec<String> {
+        self.qualified_names(|catalog| catalog.output_collection_names())
+    }
+
+    fn relation_schema(&self, name: &str) -> Option<&TableSchema> {
+        let (catalog, stream) = self.resolve(name)?;
+        catalog.relation_schema(stream)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{multi_circuit_factory, CircuitFactory};
+    use crate::test::test_circuit;
+
+    fn factory() -> CircuitFactory {
+        Box::new(|workers| Ok(test_circuit(workers)))
+    }
+
+    #[test]
+    fn multi_catalog_lookup() {
+        let (mut circuit, catalog) = multi_circuit_factory(vec![
+            ("main".to_string(), factory()),
+            ("monitor".to_string(), factory()),
+        ])(2)
+        .unwrap();
+
+        // Unqualified names resolve to the primary circuit.
+        assert!(catalog.input_collection_handle("test_input1").is_some());
+        assert!(catalog.output_handles("test_output1").is_some());
+
+        // Qualified names resolve to the named circuit.
+        assert!(catalog
+            .input_collection_handle("main.test_input1")
+            .is_some());
+        assert!(catalog
+            .input_collection_handle("monitor.test_input1")
+            .is_some());
+        assert!(catalog.output_handles("monitor.test_output1").is_some());
+        assert_eq!(
+            catalog.input_collection_names(),
+            vec!["test_input1".to_string(), "monitor.test_input1".to_string()]
+        );
+
+        assert!(catalog.input_collection_handle("monitor.missing").is_none());
+        assert!(catalog
+            .input_collection_handle("other.test_input1")
+            .is_none());
+
+        circuit.step().unwrap();
+        assert_eq!(circuit.retrieve_profile().unwrap().len(), 2);
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn invalid_circuit_names() {
+        assert!(multi_circuit_factory(vec![])(1).is_err());
+        assert!(multi_circuit_factory(vec![
+            ("a".to_string(), factory()),
+            ("a".to_string(), factory())
+        ])(1)
+        .is_err());
+        assert!(multi_circuit_factory(vec![("a.b".to_string(), factory())])(1).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Profiling a running pipeline over a number of circuit steps.
+//!
+//! The controller captures the circuit profile (see
+//! [`DBSPHandle::retrieve_profile`](`dbsp::DBSPHandle::retrieve_profile`))
+//! before and after the requested number of steps.  We report, for each
+//! operator, the CPU time it used during these steps along with the size of
+//! its state at the end, either as JSON or as folded stacks that can be
+//! rendered as a flamegraph, e.g., by `inferno-flamegraph` or
+//! `flamegraph.pl`.
+
+use crate::StepProfile;
+use dbsp::{
+    circuit::{metadata::MetaItem, NodeId},
+    profile::OperatorProfile,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    time::Duration,
+};
+
+/// Maximal number of steps that can be profiled in one request.
+pub const MAX_PROFILE_STEPS: u64 = 10_000;
+
+/// Circuit profile over a number of steps.
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitProfile {
+    /// Number of profiled steps.
+    pub steps: u64,
+
+    /// Time it took to perform the profiled steps, in seconds, including
+    /// time the pipeline spent waiting for inputs.
+    pub duration_secs: f64,
+
+    /// Operators, ordered by the CPU time they used during the profiled
+    /// steps, most expensive first.
+    pub operators: Vec<ProfiledOperator>,
+}
+
+/// Measurements of a single operator, summed across all workers.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfiledOperator {
+    /// Global id of the operator, e.g., `[5]` or `[7.2]` for an operator
+    /// in a nested circuit.
+    pub id: String,
+
+    /// Operator name.
+    pub name: String,
+
+    /// Location in the program source where the operator was instantiated.
+    pub location: Option<String>,
+
+    /// Number of times the operator was evaluated during the profiled steps.
+    pub invocations: usize,
+
+    /// CPU time spent evaluating the operator during the profiled steps, in
+    /// seconds.  Time spent in a nested circuit is attributed both to the
+    /// circuit and to the operators inside it.
+    pub time_secs: f64,
+
+    /// Number of records in the operator's state, if the operator is
+    /// stateful.
+    pub state_records: Option<usize>,
+
+    /// Memory used by the operator's state, in bytes, if the operator is
+    /// stateful.
+    pub used_bytes: Option<u64>,
+
+    /// Memory allocated for the operator's state, in bytes, if known.
+    pub allocated_bytes: Option<u64>,
+}
+
+/// Per-operator measurements between two profiles, summed across workers.
+struct Deltas<'a> {
+    operators: Vec<(&'a OperatorProfile, ProfiledOperator)>,
+}
+
+impl<'a> Deltas<'a> {
+    fn new(before: &[Vec<OperatorProfile>], after: &'a [Vec<OperatorProfile>]) -> Self {
+        let mut baseline = HashMap::new();
+        for profile in before.iter().flatten() {
+            let (invocations, time) = baseline
+                .entry(&profile.id)
+                .or_insert((0usize, Duration::ZERO));
+            *invocations += profile.invocations;
+            *time += profile.time;
+        }
+
+        let mut totals: Vec<(&OperatorProfile, usize, Duration, ProfiledOperator)> = Vec::new();
+        let mut index = HashMap::new();
+        for profile in after.iter().flatten() {
+            let i = *index.entry(&profile.id).or_insert_with(|| {
+                totals.push((
+                    profile,
+                    0,
+                    Duration::ZERO,
+                    ProfiledOperator {
+                        id: profile.id.to_string(),
+                        name: profile.name.to_string(),
+                        location: profile.location.map(|location| {
+                            format!(
+                                "{}:{}:{}",
+                                location.file(),
+                                location.line(),
+                                location.column()
+                            )
+                        }),
+                        invocations: 0,
+                        time_secs: 0.0,
+                        state_records: None,
+                        used_bytes: None,
+                        allocated_bytes: None,
+                    },
+                ));
+                totals.len() - 1
+            });
+            let (_, invocations, time, operator) = &mut totals[i];
+            *invocations += profile.invocations;
+            *time += profile.time;
+
+            for (label, item) in profile.metadata.iter() {
+                match (&**label, item) {
+                    ("total size", MetaItem::Int(records)) => {
+                        operator.state_records =
+                            Some(operator.state_records.unwrap_or(0) + records);
+                    }
+                    ("used bytes", MetaItem::Bytes(bytes)) => {
+                        operator.used_bytes = Some(operator.used_bytes.unwrap_or(0) + bytes.bytes);
+                    }
+                    ("allocated bytes", MetaItem::Bytes(bytes)) => {
+                        operator.allocated_bytes =
+                            Some(operator.allocated_bytes.unwrap_or(0) + bytes.bytes);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let operators = totals
+            .into_iter()
+            .map(|(profile, invocations, time, mut operator)| {
+                let (invocations_before, time_before) =
+                    baseline.get(&profile.id).copied().unwrap_or_default();
+                operator.invocations = invocations.saturating_sub(invocations_before);
+                operator.time_secs = time.saturating_sub(time_before).as_secs_f64();
+                (profile, operator)
+            })
+            .collect();
+
+        Self { operators }
+    }
+}
+
+/// Computes the profile of the circuit over the steps profiled by
+/// [`Controller::profile_steps`](`crate::Controller::profile_steps`).
+pub(crate) fn circuit_profile(profile: &StepProfile) -> CircuitProfile {
+    let mut operators: Vec<_> = Deltas::new(&profile.before, &profile.after)
+        .operators
+        .into_iter()
+        .map(|(_, operator)| operator)
+        .collect();
+    operators.sort_by(|a, b| b.time_secs.total_cmp(&a.time_secs));
+
+    CircuitProfile {
+        steps: profile.steps,
+        duration_secs: profile.duration.as_secs_f64(),
+        operators,
+    }
+}
+
+/// Renders the CPU time used by each operator during the profiled steps in
+/// the folded stacks format, one line per operator, with time in
+/// microseconds.
+///
+/// Operators inside nested circuits are stacked on top of the operator that
+/// evaluates the nested circuit, which is only attributed the time not spent
+/// in its children.
+pub(crate) fn folded_stacks(profile: &StepProfile) -> String {
+    let deltas = Deltas::new(&profile.before, &profile.after);
+
+    let frames: HashMap<&[NodeId], String> = deltas
+        .operators
+        .iter()
+        .map(|(profile, operator)| {
+            (
+                profile.id.path(),
+                format!("{} {}", operator.name, operator.id),
+            )
+        })
+        .collect();
+
+    // Time spent in the children of each nested circuit.
+    let mut child_time: HashMap<&[NodeId], f64> = HashMap::new();
+    for (profile, operator) in deltas.operators.iter() {
+        let path = profile.id.path();
+        if path.len() > 1 {
+            *child_time.entry(&path[..path.len() - 1]).or_default() += operator.time_secs;
+        }
+    }
+
+    // Sort stacks for deterministic output.
+    let mut stacks = BTreeMap::new();
+    for (profile, operator) in deltas.operators.iter() {
+        let path = profile.id.path();
+        let self_secs =
+            (operator.time_secs - child_time.get(path).copied().unwrap_or(0.0)).max(0.0);
+        let micros = (self_secs * 1_000_000.0).round() as u64;
+        if micros == 0 {
+            continue;
+        }
+
+        let stack = (1..=path.len())
+            .map(|len| {
+                frames
+                    .get(&path[..len])
+                    .map(String::as_str)
+                    .unwrap_or("<unknown>")
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        *stacks.entry(stack).or_insert(0) += micros;
+    }
+
+    let mut result = String::new();
+    for (stack, micros) in stacks {
+        let _ = writeln!(result, "{stack} {micros}");
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{circuit_profile, folded_stacks};
+    use crate::StepProfile;
+    use dbsp::{operator::Generator, Circuit, Runtime};
+    use std::time::Duration;
+
+    #[test]
+    fn profile_steps() {
+        let (mut handle, _) = Runtime::init_circuit(2, |circuit| {
+            circuit
+                .add_source(Generator::new(|| 5usize))
+                .apply_named("Double", |x| x * 2);
+            Ok(())
+        })
+        .unwrap();
+        handle.enable_cpu_profiler().unwrap();
+
+        handle.step().unwrap();
+        let before = handle.retrieve_profile().unwrap();
+        for _ in 0..3 {
+            handle.step().unwrap();
+        }
+        let after = handle.retrieve_profile().unwrap();
+        handle.kill().unwrap();
+
+        let profile = StepProfile {
+            steps: 3,
+            duration: Duration::from_secs(1),
+            before,
+            after,
+        };
+
+        let result = circuit_profile(&profile);
+        assert_eq!(result.steps, 3);
+        let double = result
+            .operators
+            .iter()
+            .find(|op| op.name == "Double")
+            .unwrap();
+        // 3 steps in each of 2 workers.
+        assert_eq!(double.invocations, 6);
+        assert!(double.location.as_ref().unwrap().contains("profile.rs:"));
+
+        for line in folded_stacks(&profile).lines() {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            assert!(!stack.is_empty());
+            assert!(micros.parse::<u64>().unwrap() > 0);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Bearer token authentication for the pipeline's APIs.
+//!
+//! When the pipeline is started with `--auth-token-file`, every HTTP and
+//! gRPC request must carry the token stored in the file in an
+//! `Authorization: Bearer <token>` header.  The pipeline manager generates
+//! a fresh token each time it deploys the pipeline and injects it into the
+//! requests it forwards to the pipeline, so that other clients that can
+//! reach the pipeline's port can't feed it data or control it.
+
+use crate::ControllerError;
+
+/// Read the bearer token from `path`, ignoring leading and trailing
+/// whitespace.
+pub(crate) fn read_token_file(path: &str) -> Result<String, ControllerError> {
+    let token = std::fs::read_to_string(path)
+        .map_err(|e| ControllerError::io_error(format!("reading token file '{path}'"), e))?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(ControllerError::cli_args_error(&format!(
+            "token file '{path}' is empty"
+        )));
+    }
+    Ok(token.to_string())
+}
+
+/// Checks the value of an `Authorization` header against `token`.
+///
+/// Returns `true` if no token is required.
+pub fn authorized(token: Option<&str>, authorization: Option<&[u8]>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let Some(credentials) = authorization.and_then(|value| value.strip_prefix(b"Bearer ")) else {
+        return false;
+    };
+    constant_time_eq(credentials, token.as_bytes())
+}
+
+/// Compares two byte strings in time that only depends on their lengths.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::authorized;
+
+    #[test]
+    fn bearer_token() {
+        assert!(authorized(None, None));
+        assert!(authorized(None, Some(b"Bearer foo")));
+
+        assert!(authorized(Some("secret"), Some(b"Bearer secret")));
+        assert!(!authorized(Some("secret"), None));
+        assert!(!authorized(Some("secret"), Some(b"secret")));
+        assert!(!authorized(Some("secret"), Some(b"Basic secret")));
+        assert!(!authorized(Some("secret"), Some(b"Bearer secre")));
+        assert!(!authorized(Some("secret"), Some(b"Bearer secret1")));
+        assert!(!authorized(Some("secret"), Some(b"Bearer SECRET")));
+    }
+}
@@ -49,12 +49,18 @@
 //! Finally, we implement the `actix-web` `ResponseError` trait for [`PipelineError`],
 //! which allows [`PipelineError`] to be returned as an error type by HTTP endpoints.
 
-use crate::{ConfigError, ControllerError, ParseError};
+use super::pagination::MAX_SNAPSHOT_PAGE_SIZE;
+use crate::{
+    ConfigError, ControllerError, ParseError, MAX_EXPLAIN_ANALYZE_SECS, MAX_PROFILE_STEPS,
+};
 use actix_web::{
     body::BoxBody, http::StatusCode, HttpResponse, HttpResponseBuilder, ResponseError,
 };
 use anyhow::Error as AnyError;
-use dbsp::{operator::sample::MAX_QUANTILES, DetailedError};
+use dbsp::{
+    operator::sample::{DEFAULT_RESERVOIR_CAPACITY, MAX_QUANTILES},
+    DetailedError,
+};
 use log::{error, log, warn, Level};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
@@ -163,18 +169,59 @@ pub enum PipelineError {
         param: &'static str,
     },
     ApiConnectionLimit,
-    TableSnapshotNotImplemented,
+    Unauthorized,
+    SnapshotPageSizeOutOfRange {
+        page_size: usize,
+    },
+    InvalidSnapshotPage {
+        reason: String,
+    },
     QuantileStreamingNotSupported,
     NumQuantilesOutOfRange {
         quantiles: u32,
     },
     QuantilesNotSupported,
+    InvalidQuantileColumns {
+        reason: String,
+    },
+    SampleStreamingNotSupported,
+    SampleSizeOutOfRange {
+        sample_size: u32,
+    },
+    SampleNotSupported,
+    ColumnStatisticsNotEnabled {
+        stream_name: String,
+    },
+    InvalidHistoryRequest {
+        reason: String,
+    },
+    HistoryNotRetained {
+        stream_name: String,
+    },
+    StepNotRetained {
+        stream_name: String,
+        step: u64,
+        oldest_step: u64,
+        latest_step: u64,
+    },
     MissingNeighborhoodSpec,
     InvalidNeighborhoodSpec {
         spec: JsonValue,
         parse_error: String,
     },
     NeighborhoodNotSupported,
+    ExplainDurationOutOfRange {
+        duration_secs: u64,
+    },
+    ProfileStepsOutOfRange {
+        steps: u64,
+    },
+    ChangelogNotSupported {
+        format: String,
+    },
+    InvalidEndpointAction {
+        action: String,
+    },
     ControllerError {
         // Fold `ControllerError` directly into `PipelineError` to simplify
         // the error hierarchy from the user's pespective.
@@ -182,6 +229,9 @@ pub enum PipelineError {
         error: Arc<ControllerError>,
     },
     ParseErrors {
+        /// Number of records successfully ingested before the request
+        /// completed or was aborted.
+        num_records: u64,
         num_errors: u64,
         errors: Vec<ParseError>,
     },
@@ -218,14 +268,41 @@ impl Display for PipelineError {
             Self::ApiConnectionLimit => {
                 f.write_str("The API connections limit has been exceded. Close some of the existing connections before opening new ones.")
             }
+            Self::Unauthorized => {
+                f.write_str("Missing or invalid bearer token in the 'Authorization' header.")
+            }
             Self::QuantileStreamingNotSupported => {
                 f.write_str("Continuous monitoring is not supported for quantiles. Use '?mode=snapshot' to retrieve a single set of quantiles.")
             }
             Self::QuantilesNotSupported => {
                 f.write_str("Quantiles queries are not supported for this table.")
             }
-            Self::TableSnapshotNotImplemented => {
-                f.write_str("Taking a snapshot of a table or view is not yet supported.")
+            Self::SampleStreamingNotSupported => {
+                f.write_str("Continuous monitoring is not supported for samples. Use '?mode=snapshot' to retrieve a single sample.")
+            }
+            Self::SampleNotSupported => {
+                f.write_str("Sample queries are not supported for this table.")
+            }
+            Self::SampleSizeOutOfRange{sample_size} => {
+                write!(f, "The requested sample size, {sample_size}, is beyond the allowed range 1 to {DEFAULT_RESERVOIR_CAPACITY}.")
+            }
+            Self::ColumnStatisticsNotEnabled{stream_name} => {
+                write!(f, "Column statistics are not enabled for '{stream_name}'. Add it to the 'column_statistics' list in the pipeline configuration.")
+            }
+            Self::InvalidHistoryRequest{reason} => {
+                write!(f, "Invalid request for a past version of a view: {reason}.")
+            }
+            Self::HistoryNotRetained{stream_name} => {
+                write!(f, "The history of '{stream_name}' is not retained. Add it to the 'history' map in the pipeline configuration.")
+            }
+            Self::StepNotRetained{stream_name, step, oldest_step, latest_step} => {
+                write!(f, "The version of '{stream_name}' after step {step} is not retained. Retained versions are from steps {oldest_step} to {latest_step}.")
+            }
+            Self::SnapshotPageSizeOutOfRange{page_size} => {
+                write!(f, "The requested page size, {page_size}, is beyond the allowed range 1 to {MAX_SNAPSHOT_PAGE_SIZE}.")
+            }
+            Self::InvalidSnapshotPage{reason} => {
+                write!(f, "Invalid snapshot page request: {reason}.")
             }
             Self::MissingNeighborhoodSpec => {
                 f.write_str(r#"Neighborhood request must specify neighborhood in the body of the request: '{"anchor": ..., "before": 100, "after": 100}'."#)
@@ -233,24 +310,39 @@ impl Display for PipelineError {
             Self::NumQuantilesOutOfRange{quantiles} => {
                 write!(f, "The requested number of quantiles, {quantiles}, is beyond the allowed range 1 to {MAX_QUANTILES}.")
             }
+            Self::InvalidQuantileColumns{reason} => {
+                write!(f, "Invalid column quantiles query: {reason}.")
+            }
             Self::InvalidNeighborhoodSpec{spec, parse_error} => {
                 write!(f, "Unable to parse neighborhood descriptor '{spec}'. Error returned by the parser: '{parse_error}'.")
             }
             Self::NeighborhoodNotSupported => {
                 f.write_str("Neighborhood queries are not supported for this table.")
             }
+            Self::ExplainDurationOutOfRange{duration_secs} => {
+                write!(f, "The requested measurement interval, {duration_secs} seconds, is beyond the allowed range 1 to {MAX_EXPLAIN_ANALYZE_SECS}.")
+            }
+            Self::ProfileStepsOutOfRange{steps} => {
+                write!(f, "The requested number of steps to profile, {steps}, is beyond the allowed range 1 to {MAX_PROFILE_STEPS}.")
+            }
+            Self::ChangelogNotSupported{format} => {
+                write!(f, "The changelog envelope is not supported for the '{format}' output format, which is not framed as JSON chunks.")
+            }
+            Self::InvalidEndpointAction{action} => {
+                write!(f, "Invalid endpoint action '{action}'; valid actions are 'pause' and 'start'.")
+            }
             Self::ControllerError{ error } => {
                 error.fmt(f)
             }
-            Self::ParseErrors{ num_errors, errors } => {
+            Self::ParseErrors{ num_records, num_errors, errors } => {
                 if *num_errors > errors.len() as u64 {
-                    write!(f, "Errors parsing input data (reporting {} out of {} total errors):", errors.len(), num_errors)?;
+                    write!(f, "Errors parsing input data ({num_records} records accepted, reporting {} out of {} total errors):", errors.len(), num_errors)?;
                     for error in errors.iter() {
                         write!(f, "\n    {error}")?;
                     }
                     Ok(())
                 } else {
-                    write!(f, "Errors parsing input data ({} errors):", errors.len())?;
+                    write!(f, "Errors parsing input data ({num_records} records accepted, {} errors):", errors.len())?;
                     for error in errors.iter() {
                         write!(f, "\n    {error}")?;
                     }
@@ -270,13 +362,27 @@ impl DetailedError for PipelineError {
             Self::PrometheusError { .. } => Cow::from("PrometheusError"),
             Self::MissingUrlEncodedParam { .. } => Cow::from("MissingUrlEncodedParam"),
             Self::ApiConnectionLimit => Cow::from("ApiConnectionLimit"),
+            Self::Unauthorized => Cow::from("Unauthorized"),
             Self::QuantileStreamingNotSupported => Cow::from("QuantileStreamingNotSupported"),
             Self::QuantilesNotSupported => Cow::from("QuantilesNotSupported"),
-            Self::TableSnapshotNotImplemented => Cow::from("TableSnapshotNotImplemented"),
+            Self::SampleStreamingNotSupported => Cow::from("SampleStreamingNotSupported"),
+            Self::SampleNotSupported => Cow::from("SampleNotSupported"),
+            Self::SampleSizeOutOfRange { .. } => Cow::from("SampleSizeOutOfRange"),
+            Self::ColumnStatisticsNotEnabled { .. } => Cow::from("ColumnStatisticsNotEnabled"),
+            Self::InvalidHistoryRequest { .. } => Cow::from("InvalidHistoryRequest"),
+            Self::HistoryNotRetained { .. } => Cow::from("HistoryNotRetained"),
+            Self::StepNotRetained { .. } => Cow::from("StepNotRetained"),
+            Self::SnapshotPageSizeOutOfRange { .. } => Cow::from("SnapshotPageSizeOutOfRange"),
+            Self::InvalidSnapshotPage { .. } => Cow::from("InvalidSnapshotPage"),
             Self::MissingNeighborhoodSpec => Cow::from("MissingNeighborhoodSpec"),
             Self::NeighborhoodNotSupported => Cow::from("NeighborhoodNotSupported"),
             Self::NumQuantilesOutOfRange { .. } => Cow::from("NumQuantilesOutOfRange"),
+            Self::InvalidQuantileColumns { .. } => Cow::from("InvalidQuantileColumns"),
             Self::InvalidNeighborhoodSpec { .. } => Cow::from("InvalidNeighborhoodSpec"),
+            Self::ExplainDurationOutOfRange { .. } => Cow::from("ExplainDurationOutOfRange"),
+            Self::ProfileStepsOutOfRange { .. } => Cow::from("ProfileStepsOutOfRange"),
+            Self::ChangelogNotSupported { .. } => Cow::from("ChangelogNotSupported"),
+            Self::InvalidEndpointAction { .. } => Cow::from("InvalidEndpointAction"),
             Self::ParseErrors { .. } => Cow::from("ParseErrors"),
             Self::ControllerError { error } => error.error_code(),
         }
@@ -286,6 +392,7 @@ impl DetailedError for PipelineError {
         match self {
             Self::Initializing => Level::Info,
             Self::Terminating => Level::Info,
+            Self::Unauthorized => Level::Warn,
             Self::ControllerError { error } => error.log_level(),
             _ => Level::Error,
         }
@@ -302,6 +409,9 @@ impl ResponseError for ControllerError {
                 config_error: ConfigError::UnknownOutputStream { .. },
             } => StatusCode::NOT_FOUND,
             Self::Config { .. } => StatusCode::BAD_REQUEST,
+            Self::UnknownInputEndpoint { .. } => StatusCode::NOT_FOUND,
+            Self::UnknownOutputEndpoint { .. } => StatusCode::NOT_FOUND,
+            Self::NeighborhoodSessionLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
             Self::ParseError { .. } => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -323,13 +433,27 @@ impl ResponseError for PipelineError {
             Self::PrometheusError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::MissingUrlEncodedParam { .. } => StatusCode::BAD_REQUEST,
             Self::ApiConnectionLimit => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
             Self::QuantileStreamingNotSupported => StatusCode::METHOD_NOT_ALLOWED,
             Self::QuantilesNotSupported => StatusCode::METHOD_NOT_ALLOWED,
-            Self::TableSnapshotNotImplemented => StatusCode::NOT_IMPLEMENTED,
+            Self::SampleStreamingNotSupported => StatusCode::METHOD_NOT_ALLOWED,
+            Self::SampleNotSupported => StatusCode::METHOD_NOT_ALLOWED,
+            Self::SampleSizeOutOfRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::ColumnStatisticsNotEnabled { .. } => StatusCode::NOT_FOUND,
+            Self::InvalidHistoryRequest { .. } => StatusCode::BAD_REQUEST,
+            Self::HistoryNotRetained { .. } => StatusCode::NOT_FOUND,
+            Self::StepNotRetained { .. } => StatusCode::NOT_FOUND,
+            Self::SnapshotPageSizeOutOfRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::InvalidSnapshotPage { .. } => StatusCode::BAD_REQUEST,
             Self::MissingNeighborhoodSpec => StatusCode::BAD_REQUEST,
             Self::NeighborhoodNotSupported => StatusCode::METHOD_NOT_ALLOWED,
             Self::NumQuantilesOutOfRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::InvalidQuantileColumns { .. } => StatusCode::BAD_REQUEST,
             Self::InvalidNeighborhoodSpec { .. } => StatusCode::BAD_REQUEST,
+            Self::ExplainDurationOutOfRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::ProfileStepsOutOfRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::ChangelogNotSupported { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidEndpointAction { .. } => StatusCode::BAD_REQUEST,
             Self::ParseErrors { .. } => StatusCode::BAD_REQUEST,
             Self::ControllerError { error } => error.status_code(),
         }
@@ -342,10 +466,12 @@ impl ResponseError for PipelineError {
 
 impl PipelineError {
     pub fn parse_errors<'a, I: IntoIterator<Item = &'a ParseError>>(
+        num_records: u64,
         num_errors: usize,
         errors: I,
     ) -> Self {
         Self::ParseErrors {
+            num_records,
             num_errors: num_errors as u64,
             errors: errors.into_iter().cloned().collect(),
         }
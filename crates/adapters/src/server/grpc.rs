@@ -0,0 +1,461 @@
+//! gRPC ingress and egress service.
+//!
+//! The service defined in `proto/pipeline.proto` offers the functionality of
+//! the `/ingress` and `/egress` HTTP endpoints over gRPC streams, where each
+//! message carries complete records.  Ingress streams are attached to the
+//! pipeline as input endpoints and egress streams as output endpoints, just
+//! like HTTP connections, and count toward the API connection limit.
+
+use super::{
+    auth::authorized, missing_controller_error, PipelineError, ServerState,
+    MAX_REPORTED_PARSE_ERRORS,
+};
+use crate::{
+    controller::{ConnectorConfig, EndpointId},
+    transport::http::{HttpInputTransport, HttpOutputTransport},
+    AsyncErrorCallback, FormatConfig, InputConsumer, InputEndpoint, InputEndpointConfig,
+    OutputEndpoint, OutputEndpointConfig, OutputQuery, ParseError, PipelineState, TransportConfig,
+};
+use actix_web::{web::Data as WebData, ResponseError};
+use anyhow::{anyhow, Result as AnyResult};
+use async_stream::stream;
+use futures::Stream;
+use log::{debug, error, info};
+use num_traits::FromPrimitive;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+};
+use tokio::sync::{mpsc, watch};
+use tonic::{transport::Server, Code, Request, Response, Status, Streaming};
+use uuid::Uuid;
+
+mod proto {
+    tonic::include_proto!("feldera.pipeline");
+}
+
+use proto::{
+    pipeline_server::{Pipeline, PipelineServer},
+    EgressRequest, EgressResponse, IngressRequest, IngressResponse,
+};
+
+/// Number of encoded buffers queued for an egress stream before the output
+/// endpoint blocks waiting for the client.
+const EGRESS_QUEUE_CAPACITY: usize = 16;
+
+/// Start the gRPC server in a separate thread.
+pub(super) fn start_grpc_server(
+    address: SocketAddr,
+    state: WebData<ServerState>,
+    auth_token: Option<String>,
+) {
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to start gRPC server: {e}");
+                return;
+            }
+        };
+
+        runtime.block_on(async move {
+            info!("Started gRPC server on {address}");
+            let service = PipelineServer::with_interceptor(
+                GrpcService { state },
+                move |request: Request<()>| {
+                    let authorization = request
+                        .metadata()
+                        .get("authorization")
+                        .map(|value| value.as_bytes());
+                    if authorized(auth_token.as_deref(), authorization) {
+                        Ok(request)
+                    } else {
+                        Err(status_from_error(PipelineError::Unauthorized))
+                    }
+                },
+            );
+            if let Err(e) = Server::builder().add_service(service).serve(address).await {
+                error!("gRPC server failed: {e}");
+            }
+        });
+    });
+}
+
+fn status_from_error(error: PipelineError) -> Status {
+    let code = match error.status_code().as_u16() {
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        404 => Code::NotFound,
+        429 => Code::ResourceExhausted,
+        503 => Code::Unavailable,
+        _ => Code::Internal,
+    };
+    Status::new(code, error.to_string())
+}
+
+fn format_config(format: &str, config: &str) -> Result<FormatConfig, Status> {
+    if format.is_empty() {
+        return Err(Status::invalid_argument("data format not specified"));
+    }
+    let config = if config.is_empty() {
+        YamlValue::Null
+    } else {
+        serde_yaml::from_str(config)
+            .map_err(|e| Status::invalid_argument(format!("invalid format configuration: {e}")))?
+    };
+    Ok(FormatConfig {
+        name: Cow::from(format.to_string()),
+        config,
+    })
+}
+
+fn transport_config() -> TransportConfig {
+    TransportConfig {
+        name: Cow::from("grpc"),
+        config: YamlValue::Null,
+    }
+}
+
+struct GrpcService {
+    state: WebData<ServerState>,
+}
+
+#[tonic::async_trait]
+impl Pipeline for GrpcService {
+    async fn ingress(
+        &self,
+        request: Request<Streaming<IngressRequest>>,
+    ) -> Result<Response<IngressResponse>, Status> {
+        let mut stream = request.into_inner();
+
+        let first = match stream.message().await? {
+            None => return Ok(Response::new(IngressResponse::default())),
+            Some(message) => message,
+        };
+        if first.table.is_empty() {
+            return Err(Status::invalid_argument("input table not specified"));
+        }
+
+        let endpoint_name = format!("api-grpc-ingress-{}-{}", first.table, Uuid::new_v4());
+        let endpoint = GrpcInputEndpoint::new();
+        let config = InputEndpointConfig {
+            stream: Cow::from(first.table.clone()),
+            columns: None,
+            lineage: None,
+            dedup: None,
+            connector_config: ConnectorConfig {
+                transport: transport_config(),
+                format: format_config(&first.format, &first.format_config)?,
+                max_buffered_records: HttpInputTransport::default_max_buffered_records(),
+                max_records_per_sec: None,
+                max_bytes_per_sec: None,
+                retry: None,
+                max_batch_delay_ms: None,
+                max_batch_size: None,
+                num_parsers: None,
+                parser_sharding: None,
+                on_error: None,
+                overflow: None,
+            },
+        };
+
+        let endpoint_id = match &*self.state.controller.lock().unwrap() {
+            Some(controller) => {
+                if controller.register_api_connection().is_err() {
+                    return Err(status_from_error(PipelineError::ApiConnectionLimit));
+                }
+                match controller.add_input_endpoint(
+                    &endpoint_name,
+                    config,
+                    Box::new(endpoint.clone()) as Box<dyn InputEndpoint>,
+                ) {
+                    Ok(endpoint_id) => endpoint_id,
+                    Err(e) => {
+                        controller.unregister_api_connection();
+                        return Err(status_from_error(e.into()));
+                    }
+                }
+            }
+            None => return Err(status_from_error(missing_controller_error(&self.state))),
+        };
+
+        let result = endpoint.receive(first, &mut stream).await;
+
+        if let Some(controller) = self.state.controller.lock().unwrap().as_ref() {
+            controller.disconnect_input(&endpoint_id);
+            controller.unregister_api_connection();
+        }
+
+        result.map(Response::new)
+    }
+
+    type EgressStream = Pin<Box<dyn Stream<Item = Result<EgressResponse, Status>> + Send>>;
+
+    async fn egress(
+        &self,
+        request: Request<EgressRequest>,
+    ) -> Result<Response<Self::EgressStream>, Status> {
+        let request = request.into_inner();
+        if request.view.is_empty() {
+            return Err(Status::invalid_argument("output view not specified"));
+        }
+
+        let endpoint_name = format!("api-grpc-egress-{}-{}", request.view, Uuid::new_v4());
+        let (sender, mut receiver) = mpsc::channel(EGRESS_QUEUE_CAPACITY);
+        let endpoint = GrpcOutputEndpoint { sender };
+        let config = OutputEndpointConfig {
+            stream: Cow::from(request.view.clone()),
+            query: OutputQuery::Table,
+            connector_config: ConnectorConfig {
+                transport: transport_config(),
+                format: format_config(&request.format, &request.format_config)?,
+                max_buffered_records: HttpOutputTransport::default_max_buffered_records(),
+                max_records_per_sec: None,
+                max_bytes_per_sec: None,
+                retry: None,
+                max_batch_delay_ms: None,
+                max_batch_size: None,
+                num_parsers: None,
+                parser_sharding: None,
+                on_error: None,
+                overflow: None,
+            },
+        };
+
+        let endpoint_id = match &*self.state.controller.lock().unwrap() {
+            Some(controller) => {
+                if controller.register_api_connection().is_err() {
+                    return Err(status_from_error(PipelineError::ApiConnectionLimit));
+                }
+                match controller.add_output_endpoint(
+                    &endpoint_name,
+                    &config,
+                    Box::new(endpoint) as Box<dyn OutputEndpoint>,
+                ) {
+                    Ok(endpoint_id) => endpoint_id,
+                    Err(e) => {
+                        controller.unregister_api_connection();
+                        return Err(status_from_error(e.into()));
+                    }
+                }
+            }
+            None => return Err(status_from_error(missing_controller_error(&self.state))),
+        };
+
+        // Disconnect the endpoint when the client closes the stream.  Use a
+        // weak reference, so the stream doesn't prevent the controller from
+        // shutting down.
+        let guard = EgressGuard {
+            state: Arc::downgrade(&self.state.clone().into_inner()),
+            endpoint_id,
+        };
+
+        let output = stream! {
+            let _guard = guard;
+            let mut sequence_number = 0;
+            while let Some(data) = receiver.recv().await {
+                yield Ok(EgressResponse { sequence_number, data });
+                sequence_number += 1;
+            }
+        };
+
+        Ok(Response::new(Box::pin(output) as Self::EgressStream))
+    }
+}
+
+/// Disconnects an egress endpoint when dropped.
+struct EgressGuard {
+    state: Weak<ServerState>,
+    endpoint_id: EndpointId,
+}
+
+impl Drop for EgressGuard {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.upgrade() {
+            // Handle a poisoned lock without causing a nested panic.
+            if let Ok(guard) = state.controller.lock() {
+                if let Some(controller) = guard.as_ref() {
+                    controller.disconnect_output(&self.endpoint_id);
+                    controller.unregister_api_connection();
+                }
+            }
+        }
+    }
+}
+
+struct GrpcInputEndpointInner {
+    state: AtomicU32,
+    status_notifier: watch::Sender<()>,
+    consumer: Mutex<Option<Box<dyn InputConsumer>>>,
+}
+
+/// Input endpoint fed by an `Ingress` stream.
+#[derive(Clone)]
+struct GrpcInputEndpoint {
+    inner: Arc<GrpcInputEndpointInner>,
+}
+
+impl GrpcInputEndpoint {
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(GrpcInputEndpointInner {
+                state: AtomicU32::new(PipelineState::Paused as u32),
+                status_notifier: watch::channel(()).0,
+                consumer: Mutex::new(None),
+            }),
+        }
+    }
+
+    fn state(&self) -> PipelineState {
+        PipelineState::from_u32(self.inner.state.load(Ordering::Acquire)).unwrap()
+    }
+
+    fn set_state(&self, state: PipelineState) {
+        self.inner.state.store(state as u32, Ordering::Release);
+        self.inner.status_notifier.send_replace(());
+    }
+
+    /// Push messages received from `stream` to the consumer, starting with
+    /// `message`.
+    async fn receive(
+        &self,
+        message: IngressRequest,
+        stream: &mut Streaming<IngressRequest>,
+    ) -> Result<IngressResponse, Status> {
+        let mut response = IngressResponse::default();
+        let mut status_watch = self.inner.status_notifier.subscribe();
+        let mut message = Some(message);
+
+        loop {
+            match self.state() {
+                PipelineState::Paused => {
+                    let _ = status_watch.changed().await;
+                }
+                PipelineState::Terminated => {
+                    return Err(status_from_error(PipelineError::Terminating));
+                }
+                PipelineState::Running => {
+                    let data = match message.take() {
+                        Some(message) => message.data,
+                        None => match stream.message().await? {
+                            Some(message) => message.data,
+                            None => break,
+                        },
+                    };
+                    response.num_bytes += data.len() as u64;
+                    let errors = self
+                        .inner
+                        .consumer
+                        .lock()
+                        .unwrap()
+                        .as_mut()
+                        .unwrap()
+                        .input_chunk(&data);
+                    Self::record_errors(&mut response, errors);
+                }
+            }
+        }
+
+        let errors = self.inner.consumer.lock().unwrap().as_mut().unwrap().eoi();
+        Self::record_errors(&mut response, errors);
+        debug!(
+            "gRPC ingress: end of stream, {} bytes received",
+            response.num_bytes
+        );
+
+        Ok(response)
+    }
+
+    fn record_errors(response: &mut IngressResponse, errors: Vec<ParseError>) {
+        response.num_parse_errors += errors.len() as u64;
+        for error in errors {
+            if response.parse_errors.len() < MAX_REPORTED_PARSE_ERRORS {
+                response.parse_errors.push(error.to_string());
+            }
+        }
+    }
+}
+
+impl InputEndpoint for GrpcInputEndpoint {
+    fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        *self.inner.consumer.lock().unwrap() = Some(consumer);
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Paused);
+        Ok(())
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Running);
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        self.set_state(PipelineState::Terminated);
+    }
+}
+
+/// Output endpoint that forwards encoded buffers to an `Egress` stream.
+struct GrpcOutputEndpoint {
+    sender: mpsc::Sender<Vec<u8>>,
+}
+
+impl OutputEndpoint for GrpcOutputEndpoint {
+    fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        // Stay below the default 4MiB message size limit of gRPC clients.
+        4_000_000
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        // Blocks the output thread while the client is not keeping up.
+        self.sender
+            .blocking_send(buffer.to_vec())
+            .map_err(|_| anyhow!("gRPC egress stream closed by the client"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::format_config;
+    use serde_yaml::Value as YamlValue;
+    use tonic::Code;
+
+    #[test]
+    fn parse_format_config() {
+        let config = format_config("csv", "").unwrap();
+        assert_eq!(config.name, "csv");
+        assert_eq!(config.config, YamlValue::Null);
+
+        let config = format_config("json", r#"{"update_format": "raw"}"#).unwrap();
+        assert_eq!(config.name, "json");
+        assert_eq!(
+            config.config,
+            serde_yaml::from_str::<YamlValue>("update_format: raw").unwrap()
+        );
+
+        assert_eq!(
+            format_config("", "").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            format_config("json", "{").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+}
@@ -1,50 +1,75 @@
 use crate::{
     controller::ConnectorConfig,
     transport::http::{
-        HttpInputEndpoint, HttpInputTransport, HttpOutputEndpoint, HttpOutputTransport,
+        ChangelogConfig, CompressionArg, EgressCompression, HttpInputEndpoint, HttpInputTransport,
+        HttpOutputEndpoint, HttpOutputTransport,
     },
     CircuitCatalog, Controller, ControllerError, DbspCircuitHandle, FormatConfig, InputEndpoint,
     InputEndpointConfig, OutputEndpoint, OutputEndpointConfig, OutputQuery, PipelineConfig,
+    SqlSourceMap, MAX_EXPLAIN_ANALYZE_SECS, MAX_PROFILE_STEPS,
 };
 use actix_web::{
-    dev::{ServiceFactory, ServiceRequest},
+    delete,
+    dev::{Service, ServiceFactory, ServiceRequest},
     get,
+    http::header,
     middleware::Logger,
-    post, rt, web,
+    post, put, rt, web,
     web::{Data as WebData, Json, Payload, Query},
     App, Error as ActixError, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 use actix_web_static_files::ResourceFiles;
 use clap::Parser;
 use colored::Colorize;
-use dbsp::operator::sample::MAX_QUANTILES;
+use dbsp::operator::sample::{DEFAULT_RESERVOIR_CAPACITY, MAX_QUANTILES};
+use dbsp::profile::OperatorProfile;
 use env_logger::Env;
 use erased_serde::Deserializer as ErasedDeserializer;
+use futures::future::{ready, Either};
 use log::{debug, error, info, warn};
 use serde::Deserialize;
 use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
 use std::io::Write;
 use std::{
     borrow::Cow,
     net::TcpListener,
+    path::PathBuf,
     sync::{
+        atomic::{AtomicBool, Ordering},
         mpsc::{self, Sender as StdSender},
         Arc, Mutex, RwLock, Weak,
     },
     thread,
+    time::{Duration, Instant},
 };
 use tokio::{
     spawn,
-    sync::mpsc::{channel, Sender},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        oneshot,
+    },
 };
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+pub mod auth;
 pub mod error;
+#[cfg(feature = "with-grpc")]
+mod grpc;
+mod pagination;
 mod prometheus;
+mod quantiles;
+mod tls;
 
 pub use self::error::{ErrorResponse, PipelineError, MAX_REPORTED_PARSE_ERRORS};
-use self::prometheus::PrometheusMetrics;
+use self::{
+    pagination::{page_query, SnapshotPageEndpoint, MAX_SNAPSHOT_PAGE_SIZE},
+    prometheus::PrometheusMetrics,
+    quantiles::ColumnQuantilesEndpoint,
+    tls::ServerIdentity,
+};
+pub use tls::SERVER_CERT_FILE;
 
 /// By default actix will start the number of threads equal to the number of cores,
 /// which is an overkill and can lead to file descriptor exhaustion when running
@@ -95,18 +120,39 @@ struct ServerState {
     /// the self-destruct task when shutting down
     /// the server.
     terminate_sender: Option<Sender<()>>,
+    /// Set when the pipeline terminates after a `/drain` request.
+    drained: AtomicBool,
+    /// Used to map operators to SQL code in `/explain_analyze` output.
+    sql_source_map: SqlSourceMap,
+    /// Directory where the server writes its port and certificate files.
+    working_directory: PathBuf,
 }
 
 impl ServerState {
-    fn new(terminate_sender: Option<Sender<()>>) -> Self {
+    fn new(
+        terminate_sender: Option<Sender<()>>,
+        sql_source_map: SqlSourceMap,
+        working_directory: PathBuf,
+    ) -> Self {
         Self {
             phase: RwLock::new(PipelinePhase::Initializing),
             metadata: RwLock::new(String::new()),
             controller: Mutex::new(None),
             prometheus: RwLock::new(None),
             terminate_sender,
+            drained: AtomicBool::new(false),
+            sql_source_map,
+            working_directory,
         }
     }
+
+    fn port_file(&self) -> PathBuf {
+        self.working_directory.join(SERVER_PORT_FILE)
+    }
+
+    fn cert_file(&self) -> PathBuf {
+        self.working_directory.join(SERVER_CERT_FILE)
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -129,11 +175,44 @@ pub struct ServerArgs {
     /// automatically
     #[arg(short = 'p', long)]
     default_port: Option<u16>,
+
+    /// Require clients to authenticate with the bearer token stored in
+    /// this file.  All endpoints are open if no token file is specified
+    #[arg(long)]
+    auth_token_file: Option<String>,
+
+    /// Serve the gRPC ingress/egress API on this port.  The gRPC server is
+    /// disabled if no port is specified
+    #[cfg(feature = "with-grpc")]
+    #[arg(long)]
+    grpc_port: Option<u16>,
+
+    /// Load connectors from this transport plugin (a shared library).  May
+    /// be specified multiple times
+    #[cfg(feature = "with-plugins")]
+    #[arg(long)]
+    transport_plugin: Vec<String>,
+
+    /// Directory where the server writes its port and certificate files.
+    /// Defaults to the current directory
+    #[arg(long)]
+    working_directory: Option<String>,
+
+    /// The server runs inside another process (see
+    /// [`start_embedded_server`]), which owns logging and the process exit
+    /// status.
+    #[arg(skip)]
+    embedded: bool,
 }
 
 // This file indicates the port used by the server
 pub const SERVER_PORT_FILE: &str = "port";
 
+/// Exit code of the pipeline process after the pipeline has been terminated
+/// via the `/drain` endpoint, i.e., after all its inputs have been processed
+/// to completion.  Distinguishes data-complete runs from other terminations.
+pub const DRAINED_EXIT_CODE: i32 = 3;
+
 /// Server main function.
 ///
 /// This function is intended to be invoked from the code generated by,
@@ -150,6 +229,24 @@ pub const SERVER_PORT_FILE: &str = "port";
 ///   input/output stream
 /// catalog.
 pub fn server_main<F>(circuit_factory: F) -> Result<(), ControllerError>
+where
+    F: FnOnce(
+            usize,
+        )
+            -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+        + Send
+        + 'static,
+{
+    server_main_with_source_map(&[], circuit_factory)
+}
+
+/// Like [`server_main`], but additionally takes a map from the lines of the
+/// program that builds the circuit to the SQL code they implement, which is
+/// used to annotate the output of the `/explain_analyze` endpoint.
+pub fn server_main_with_source_map<F>(
+    sql_source_map: SqlSourceMap,
+    circuit_factory: F,
+) -> Result<(), ControllerError>
 where
     F: FnOnce(
             usize,
@@ -160,7 +257,7 @@ where
 {
     let args = ServerArgs::try_parse().map_err(|e| ControllerError::cli_args_error(&e))?;
 
-    run_server(args, circuit_factory).map_err(|e| {
+    run_server(args, sql_source_map, circuit_factory).map_err(|e| {
         // Write to stderror in case the error happened before logging
         // has been enabled.
         eprintln!("{e}");
@@ -169,7 +266,146 @@ where
     })
 }
 
-pub fn run_server<F>(args: ServerArgs, circuit_factory: F) -> Result<(), ControllerError>
+pub fn run_server<F>(
+    args: ServerArgs,
+    sql_source_map: SqlSourceMap,
+    circuit_factory: F,
+) -> Result<(), ControllerError>
+where
+    F: FnOnce(
+            usize,
+        )
+            -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+        + Send
+        + 'static,
+{
+    let (terminate_sender, terminate_receiver) = channel(1);
+    let state = WebData::new(ServerState::new(
+        Some(terminate_sender),
+        sql_source_map,
+        args.working_directory(),
+    ));
+
+    serve(args, state.clone(), terminate_receiver, circuit_factory)?;
+
+    if state.drained.load(Ordering::Acquire) {
+        info!("Pipeline drained, exiting with status {DRAINED_EXIT_CODE}");
+        std::process::exit(DRAINED_EXIT_CODE);
+    }
+
+    Ok(())
+}
+
+/// A pipeline server running on a thread of the current process, started by
+/// [`start_embedded_server`].
+///
+/// Dropping the handle stops the pipeline and the server without waiting
+/// for the server to terminate.
+pub struct EmbeddedServer {
+    state: WebData<ServerState>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl EmbeddedServer {
+    /// True once the server has terminated, e.g., after a `/shutdown` or
+    /// `/drain` request, or because it failed to start.
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map_or(true, |thread| thread.is_finished())
+    }
+
+    /// True if the pipeline terminated after a `/drain` request.
+    pub fn is_drained(&self) -> bool {
+        self.state.drained.load(Ordering::Acquire)
+    }
+
+    /// Stop the pipeline and the server, and wait for the server to
+    /// terminate.
+    pub fn stop(mut self) {
+        self.terminate();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn terminate(&self) {
+        let controller = self.state.controller.lock().unwrap().take();
+        if let Some(controller) = controller {
+            if let Err(e) = controller.stop() {
+                warn!("Failed to stop the pipeline: {e}");
+            }
+        }
+        if let Some(sender) = &self.state.terminate_sender {
+            let _ = sender.try_send(());
+        }
+    }
+}
+
+impl Drop for EmbeddedServer {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+/// Start a pipeline server on a new thread of the current process.
+///
+/// This is the in-process counterpart of [`run_server`], for hosts that run
+/// several pipelines in one process.  The host owns logging, and the process
+/// does not exit when the pipeline is drained (see
+/// [`EmbeddedServer::is_drained`]).  Since the servers share the current
+/// directory, `args` should specify `--working-directory`.
+///
+/// Errors that prevent the server from starting are logged, after which
+/// [`EmbeddedServer::is_finished`] returns `true`.
+pub fn start_embedded_server<F>(
+    mut args: ServerArgs,
+    sql_source_map: SqlSourceMap,
+    circuit_factory: F,
+) -> EmbeddedServer
+where
+    F: FnOnce(
+            usize,
+        )
+            -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+        + Send
+        + 'static,
+{
+    args.embedded = true;
+
+    let (terminate_sender, terminate_receiver) = channel(1);
+    let state = WebData::new(ServerState::new(
+        Some(terminate_sender),
+        sql_source_map,
+        args.working_directory(),
+    ));
+
+    let server_state = state.clone();
+    let thread = thread::spawn(move || {
+        if let Err(e) = serve(args, server_state, terminate_receiver, circuit_factory) {
+            error!("{e}");
+        }
+    });
+
+    EmbeddedServer {
+        state,
+        thread: Some(thread),
+    }
+}
+
+impl ServerArgs {
+    fn working_directory(&self) -> PathBuf {
+        PathBuf::from(self.working_directory.as_deref().unwrap_or("."))
+    }
+}
+
+/// Run the server until it terminates.
+fn serve<F>(
+    args: ServerArgs,
+    state: WebData<ServerState>,
+    mut terminate_receiver: Receiver<()>,
+    circuit_factory: F,
+) -> Result<(), ControllerError>
 where
     F: FnOnce(
             usize,
@@ -178,6 +414,30 @@ where
         + Send
         + 'static,
 {
+    // Register plugin transports before the pipeline configuration, which
+    // may refer to them, is processed.
+    #[cfg(feature = "with-plugins")]
+    for plugin in args.transport_plugin.iter() {
+        // SAFETY: the user is responsible for only loading compatible plugins.
+        unsafe { crate::transport::load_transport_plugin(std::path::Path::new(plugin)) }
+            .map_err(|e| ControllerError::cli_args_error(&format!("{e:#}")))?;
+    }
+
+    // The server must be configured for TLS before it starts listening, i.e.,
+    // before `bootstrap` has parsed the pipeline configuration.  Other errors
+    // in the configuration are reported by `bootstrap`.
+    let tls = read_config(&args.config_file)
+        .ok()
+        .and_then(|config| config.global.tls)
+        .map(|config| ServerIdentity::new(&config))
+        .transpose()?;
+
+    let auth_token = args
+        .auth_token_file
+        .as_deref()
+        .map(auth::read_token_file)
+        .transpose()?;
+
     let bind_address = args.bind_address.clone();
     let port = args.default_port.unwrap_or(0);
     let listener = TcpListener::bind((bind_address, port))
@@ -193,10 +453,27 @@ where
         })?
         .port();
 
-    let (terminate_sender, mut terminate_receiver) = channel(1);
+    #[cfg(feature = "with-grpc")]
+    let grpc_address = match args.grpc_port {
+        Some(grpc_port) => Some(
+            std::net::ToSocketAddrs::to_socket_addrs(&(args.bind_address.as_str(), grpc_port))
+                .map_err(|e| {
+                    ControllerError::io_error("resolving gRPC bind address".to_string(), e)
+                })?
+                .next()
+                .ok_or_else(|| {
+                    ControllerError::io_error(
+                        "resolving gRPC bind address".to_string(),
+                        std::io::Error::from(std::io::ErrorKind::AddrNotAvailable),
+                    )
+                })?,
+        ),
+        None => None,
+    };
 
-    let state = WebData::new(ServerState::new(Some(terminate_sender)));
     let state_clone = state.clone();
+    let port_file = state.port_file();
+    let cert_file = state.cert_file();
 
     // The bootstrap thread will read the config, including pipeline name,
     // and initalize the logger.  Use this channel to wait for the log to
@@ -209,15 +486,39 @@ where
     thread::spawn(move || bootstrap(args, circuit_factory, state_clone, loginit_sender));
     let _ = loginit_receiver.recv();
 
+    #[cfg(feature = "with-grpc")]
+    if let Some(grpc_address) = grpc_address {
+        grpc::start_grpc_server(grpc_address, state.clone(), auth_token.clone());
+    }
+
     let server = HttpServer::new(move || {
         let state = state.clone();
-        build_app(App::new().wrap(Logger::default()), state)
+        let auth_token = auth_token.clone();
+        build_app(
+            App::new()
+                .wrap_fn(move |req, srv| {
+                    let authorization = req
+                        .headers()
+                        .get(header::AUTHORIZATION)
+                        .map(|value| value.as_bytes());
+                    if auth::authorized(auth_token.as_deref(), authorization) {
+                        Either::Left(srv.call(req))
+                    } else {
+                        Either::Right(ready(Err(PipelineError::Unauthorized.into())))
+                    }
+                })
+                .wrap(Logger::default()),
+            state,
+        )
     })
     // Set timeout for graceful shutdown of workers.
     // The default in actix is 30s. We may consider making this configurable.
     .shutdown_timeout(10)
-    .workers(NUM_HTTP_WORKERS)
-    .listen(listener)
+    .workers(NUM_HTTP_WORKERS);
+    let server = match &tls {
+        Some(identity) => server.listen_rustls(listener, identity.server_config()?),
+        None => server.listen(listener),
+    }
     .map_err(|e| ControllerError::io_error("binding server to the listener".to_string(), e))?
     .run();
 
@@ -229,29 +530,53 @@ where
             server_handle.stop(true).await
         });
 
-        info!("Started HTTP server on port {port}");
-        tokio::fs::write(SERVER_PORT_FILE, format!("{}\n", port))
+        // Clients expect the certificate file to be in place by the time
+        // they discover the port.  Remove a stale certificate left by a
+        // previous run, so they don't attempt to use HTTPS.
+        match &tls {
+            Some(identity) => {
+                info!("Started HTTPS server on port {port}");
+                tokio::fs::write(&cert_file, &identity.cert_pem)
+                    .await
+                    .map_err(|e| {
+                        ControllerError::io_error("writing server certificate file".to_string(), e)
+                    })?;
+            }
+            None => {
+                info!("Started HTTP server on port {port}");
+                let _ = tokio::fs::remove_file(&cert_file).await;
+            }
+        }
+        tokio::fs::write(&port_file, format!("{}\n", port))
             .await
             .map_err(|e| ControllerError::io_error("writing server port file".to_string(), e))?;
         server
             .await
             .map_err(|e| ControllerError::io_error("in the HTTP server".to_string(), e))
-    })?;
-
-    Ok(())
+    })
 }
 
-fn parse_config(config_file: &str) -> Result<PipelineConfig, ControllerError> {
+fn read_config_file(config_file: &str) -> Result<String, ControllerError> {
     let yaml_config = std::fs::read(config_file).map_err(|e| {
         ControllerError::io_error(format!("reading configuration file '{}'", config_file), e)
     })?;
 
-    let yaml_config = String::from_utf8(yaml_config).map_err(|e| {
+    String::from_utf8(yaml_config).map_err(|e| {
         ControllerError::pipeline_config_parse_error(&format!(
             "invalid UTF8 string in configuration file '{}' ({e})",
             &config_file
         ))
-    })?;
+    })
+}
+
+/// Like [`parse_config`], but doesn't print the configuration.
+fn read_config(config_file: &str) -> Result<PipelineConfig, ControllerError> {
+    serde_yaml::from_str(read_config_file(config_file)?.as_str())
+        .map_err(|e| ControllerError::pipeline_config_parse_error(&e))
+}
+
+fn parse_config(config_file: &str) -> Result<PipelineConfig, ControllerError> {
+    let yaml_config = read_config_file(config_file)?;
 
     // Still running without logger here.
     eprintln!("Pipeline configuration:\n{yaml_config}");
@@ -286,7 +611,9 @@ fn bootstrap<F>(
 fn is_fatal_controller_error(error: &ControllerError) -> bool {
     matches!(
         error,
-        ControllerError::DbspError { .. } | ControllerError::DbspPanic
+        ControllerError::DbspError { .. }
+            | ControllerError::DbspPanic
+            | ControllerError::PipelineAborted { .. }
     )
 }
 
@@ -340,6 +667,14 @@ where
         + Send
         + 'static,
 {
+    // An embedded server logs through its host, which has already
+    // initialized logging.
+    if args.embedded {
+        let _ = loginit_sender.send(());
+        let config = read_config(&args.config_file)?;
+        return start_controller(args, config, circuit_factory, state);
+    }
+
     // Print error directly to stdout until we've initialized the logger.
     let config = parse_config(&args.config_file).map_err(|e| {
         let _ = loginit_sender.send(());
@@ -367,6 +702,23 @@ where
         });
     let _ = loginit_sender.send(());
 
+    start_controller(args, config, circuit_factory, state)
+}
+
+fn start_controller<F>(
+    args: ServerArgs,
+    config: PipelineConfig,
+    circuit_factory: F,
+    state: &WebData<ServerState>,
+) -> Result<(), ControllerError>
+where
+    F: FnOnce(
+            usize,
+        )
+            -> Result<(Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>), ControllerError>
+        + Send
+        + 'static,
+{
     *state.metadata.write().unwrap() = match args.metadata_file {
         None => String::new(),
         Some(metadata_file) => {
@@ -428,26 +780,88 @@ where
         .service(ResourceFiles::new("/static", generated))
         .service(start)
         .service(pause)
+        .service(input_endpoint_action)
+        .service(output_endpoint_action)
+        .service(connect_input_endpoint)
+        .service(connect_output_endpoint)
+        .service(disconnect_input_endpoint)
+        .service(reconfigure_input_endpoint)
+        .service(disconnect_output_endpoint)
+        .service(step)
         .service(shutdown)
+        .service(drain)
         .service(stats)
         .service(metrics)
         .service(metadata)
         .service(dump_profile)
+        .service(explain_analyze)
+        .service(profile)
+        .service(checkpoint)
         .service(input_endpoint)
         .service(output_endpoint)
+        .service(sample_endpoint)
+        .service(column_stats)
+        .service(catalog)
+}
+
+/// How the circuit is driven once the pipeline is running.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+enum StepMode {
+    /// The circuit performs a step whenever input data is available.
+    #[default]
+    #[serde(rename = "automatic")]
+    Automatic,
+    /// The circuit only performs a step when requested via `POST /step`.
+    /// Input data accumulates in endpoint buffers between steps.
+    #[serde(rename = "manual")]
+    Manual,
+}
+
+/// URL-encoded arguments to the `/start` endpoint.
+#[derive(Debug, Deserialize)]
+struct StartArgs {
+    #[serde(default)]
+    mode: StepMode,
 }
 
 #[get("/start")]
-async fn start(state: WebData<ServerState>) -> impl Responder {
+async fn start(state: WebData<ServerState>, args: Query<StartArgs>) -> impl Responder {
     match &*state.controller.lock().unwrap() {
         Some(controller) => {
+            controller.set_manual_stepping(args.mode == StepMode::Manual);
             controller.start();
-            Ok(HttpResponse::Ok().json("The pipeline is running"))
+            match args.mode {
+                StepMode::Automatic => Ok(HttpResponse::Ok().json("The pipeline is running")),
+                StepMode::Manual => {
+                    Ok(HttpResponse::Ok().json("The pipeline is running in manual stepping mode"))
+                }
+            }
         }
         None => Err(missing_controller_error(&state)),
     }
 }
 
+/// Perform a circuit step and return the number of records it ingested and
+/// emitted once it has completed.
+///
+/// In manual stepping mode, this is the only way to advance the circuit.
+#[post("/step")]
+async fn step(state: WebData<ServerState>) -> Result<HttpResponse, PipelineError> {
+    let (sender, receiver) = oneshot::channel();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => controller.step_with_stats(Box::new(move |stats| {
+            let _ = sender.send(stats);
+        })),
+        None => return Err(missing_controller_error(&state)),
+    }
+
+    let stats = receiver.await.map_err(|_| PipelineError::Terminating)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "input_records": stats.input_records,
+        "output_records": stats.output_records,
+    })))
+}
+
 #[get("/pause")]
 async fn pause(state: WebData<ServerState>) -> impl Responder {
     match &*state.controller.lock().unwrap() {
@@ -459,6 +873,148 @@ async fn pause(state: WebData<ServerState>) -> impl Responder {
     }
 }
 
+/// Pause or resume (`action` = `pause` or `start`) a single input endpoint.
+#[post("/input_endpoints/{endpoint_name}/{action}")]
+async fn input_endpoint_action(
+    state: WebData<ServerState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, PipelineError> {
+    let (endpoint_name, action) = path.into_inner();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            match action.as_str() {
+                "pause" => controller.pause_input_endpoint(&endpoint_name)?,
+                "start" => controller.start_input_endpoint(&endpoint_name)?,
+                _ => return Err(PipelineError::InvalidEndpointAction { action }),
+            }
+            Ok(HttpResponse::Ok().json(format!(
+                "Input endpoint '{endpoint_name}': '{action}' action applied"
+            )))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Pause or resume (`action` = `pause` or `start`) a single output endpoint.
+#[post("/output_endpoints/{endpoint_name}/{action}")]
+async fn output_endpoint_action(
+    state: WebData<ServerState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, PipelineError> {
+    let (endpoint_name, action) = path.into_inner();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            match action.as_str() {
+                "pause" => controller.pause_output_endpoint(&endpoint_name)?,
+                "start" => controller.start_output_endpoint(&endpoint_name)?,
+                _ => return Err(PipelineError::InvalidEndpointAction { action }),
+            }
+            Ok(HttpResponse::Ok().json(format!(
+                "Output endpoint '{endpoint_name}': '{action}' action applied"
+            )))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Name and configuration of an endpoint to connect to a running pipeline.
+#[derive(Debug, Deserialize)]
+struct NewEndpoint<C> {
+    /// Endpoint name, unique among the input (output) endpoints of the
+    /// pipeline.
+    name: String,
+
+    /// Endpoint configuration.
+    config: C,
+}
+
+/// Connect a new input endpoint to the pipeline.
+///
+/// The endpoint starts receiving data immediately if the pipeline is
+/// running.
+#[post("/input_endpoints")]
+async fn connect_input_endpoint(
+    state: WebData<ServerState>,
+    endpoint: Json<NewEndpoint<InputEndpointConfig>>,
+) -> Result<HttpResponse, PipelineError> {
+    let NewEndpoint { name, config } = endpoint.into_inner();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            controller.connect_input(&name, &config)?;
+            Ok(HttpResponse::Ok().json(format!("Input endpoint '{name}' connected")))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Connect a new output endpoint to the pipeline.
+///
+/// The endpoint receives outputs produced by the circuit after it has been
+/// connected.
+#[post("/output_endpoints")]
+async fn connect_output_endpoint(
+    state: WebData<ServerState>,
+    endpoint: Json<NewEndpoint<OutputEndpointConfig>>,
+) -> Result<HttpResponse, PipelineError> {
+    let NewEndpoint { name, config } = endpoint.into_inner();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            controller.connect_output(&name, &config)?;
+            Ok(HttpResponse::Ok().json(format!("Output endpoint '{name}' connected")))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Disconnect input endpoint `endpoint_name` from the pipeline.
+#[delete("/input_endpoints/{endpoint_name}")]
+async fn disconnect_input_endpoint(
+    state: WebData<ServerState>,
+    endpoint_name: web::Path<String>,
+) -> Result<HttpResponse, PipelineError> {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            controller.disconnect_input(&controller.input_endpoint_id(&endpoint_name)?);
+            Ok(HttpResponse::Ok().json(format!("Input endpoint '{endpoint_name}' disconnected")))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Apply a new transport configuration, i.e., the `config` section of the
+/// endpoint's `transport`, to running input endpoint `endpoint_name`.
+#[put("/input_endpoints/{endpoint_name}/transport_config")]
+async fn reconfigure_input_endpoint(
+    state: WebData<ServerState>,
+    endpoint_name: web::Path<String>,
+    config: Json<YamlValue>,
+) -> Result<HttpResponse, PipelineError> {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            controller.reconfigure_input_endpoint(&endpoint_name, &config)?;
+            Ok(HttpResponse::Ok().json(format!(
+                "Input endpoint '{endpoint_name}': transport configuration updated"
+            )))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Disconnect output endpoint `endpoint_name` from the pipeline.
+#[delete("/output_endpoints/{endpoint_name}")]
+async fn disconnect_output_endpoint(
+    state: WebData<ServerState>,
+    endpoint_name: web::Path<String>,
+) -> Result<HttpResponse, PipelineError> {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            controller.disconnect_output(&controller.output_endpoint_id(&endpoint_name)?);
+            Ok(HttpResponse::Ok().json(format!("Output endpoint '{endpoint_name}' disconnected")))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
 #[get("/stats")]
 async fn stats(state: WebData<ServerState>) -> impl Responder {
     match &*state.controller.lock().unwrap() {
@@ -473,8 +1029,14 @@ async fn stats(state: WebData<ServerState>) -> impl Responder {
 }
 
 /// This endpoint is invoked by the Prometheus server.
+///
+/// In addition to endpoint metrics, reports per-operator circuit metrics
+/// computed from operator profiles.  The first request enables CPU profiling
+/// in the circuit; until then, operator evaluation times are reported as 0.
 #[get("/metrics")]
-async fn metrics(state: WebData<ServerState>) -> impl Responder {
+async fn metrics(state: WebData<ServerState>) -> Result<HttpResponse, PipelineError> {
+    let profiles = retrieve_profile(&state).await?;
+
     match &*state.controller.lock().unwrap() {
         Some(controller) => match state
             .prometheus
@@ -482,7 +1044,7 @@ async fn metrics(state: WebData<ServerState>) -> impl Responder {
             .unwrap()
             .as_ref()
             .unwrap()
-            .metrics(controller)
+            .metrics(controller, &profiles)
         {
             Ok(metrics) => Ok(HttpResponse::Ok()
                 .content_type(mime::TEXT_PLAIN)
@@ -495,6 +1057,48 @@ async fn metrics(state: WebData<ServerState>) -> impl Responder {
     }
 }
 
+/// Per-column statistics of a table or view.
+///
+/// Only available for tables and views listed in the `column_statistics`
+/// section of the pipeline configuration.
+#[get("/views/{view_name}/column_stats")]
+async fn column_stats(state: WebData<ServerState>, req: HttpRequest) -> impl Responder {
+    let view_name = match req.match_info().get("view_name") {
+        None => {
+            return Err(PipelineError::MissingUrlEncodedParam { param: "view_name" });
+        }
+        Some(view_name) => view_name.to_string(),
+    };
+
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            let stats = controller
+                .catalog()
+                .lock()
+                .unwrap()
+                .column_statistics(&view_name)
+                .ok_or(PipelineError::ColumnStatisticsNotEnabled {
+                    stream_name: view_name,
+                })?;
+            Ok(HttpResponse::Ok().json(stats))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
+/// Tables and views of the pipeline, along with their columns and the
+/// queries that `/egress` supports for each of them.
+#[get("/catalog")]
+async fn catalog(state: WebData<ServerState>) -> impl Responder {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            let relations = controller.catalog().lock().unwrap().relations();
+            Ok(HttpResponse::Ok().json(relations))
+        }
+        None => Err(missing_controller_error(&state)),
+    }
+}
+
 #[get("/metadata")]
 async fn metadata(state: WebData<ServerState>) -> impl Responder {
     HttpResponse::Ok()
@@ -513,6 +1117,140 @@ async fn dump_profile(state: WebData<ServerState>) -> impl Responder {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct ExplainAnalyzeArgs {
+    /// Length of the measurement interval.
+    #[serde(default = "default_explain_analyze_secs")]
+    duration_secs: u64,
+}
+
+fn default_explain_analyze_secs() -> u64 {
+    10
+}
+
+/// Profile the circuit for `duration_secs` seconds and return its operators
+/// annotated with measured evaluation rates, CPU time share, and state sizes,
+/// mapped back to the SQL code they implement.
+#[get("/explain_analyze")]
+async fn explain_analyze(
+    state: WebData<ServerState>,
+    args: Query<ExplainAnalyzeArgs>,
+) -> impl Responder {
+    if args.duration_secs == 0 || args.duration_secs > MAX_EXPLAIN_ANALYZE_SECS {
+        return Err(PipelineError::ExplainDurationOutOfRange {
+            duration_secs: args.duration_secs,
+        });
+    }
+
+    let before = retrieve_profile(&state).await?;
+    let start = Instant::now();
+    rt::time::sleep(Duration::from_secs(args.duration_secs)).await;
+    let after = retrieve_profile(&state).await?;
+
+    Ok(HttpResponse::Ok().json(crate::explain::explain_analyze(
+        &before,
+        &after,
+        start.elapsed(),
+        state.sql_source_map,
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileArgs {
+    /// Number of circuit steps to profile.
+    #[serde(default = "default_profile_steps")]
+    steps: u64,
+
+    /// Output format.
+    #[serde(default)]
+    format: ProfileFormat,
+}
+
+fn default_profile_steps() -> u64 {
+    10
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ProfileFormat {
+    /// Per-operator measurements in JSON.
+    #[default]
+    Json,
+
+    /// CPU time of each operator in the folded stacks format accepted by
+    /// flamegraph tools.
+    Folded,
+}
+
+/// Profile the next `steps` steps of the circuit and return the CPU time
+/// and memory used by each operator.
+///
+/// The request completes once the circuit has performed the requested
+/// number of steps, so it does not complete while the pipeline is paused.
+#[get("/profile")]
+async fn profile(
+    state: WebData<ServerState>,
+    args: Query<ProfileArgs>,
+) -> Result<HttpResponse, PipelineError> {
+    if args.steps == 0 || args.steps > MAX_PROFILE_STEPS {
+        return Err(PipelineError::ProfileStepsOutOfRange { steps: args.steps });
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => controller.profile_steps(
+            args.steps,
+            Box::new(move |profile| {
+                let _ = sender.send(profile);
+            }),
+        ),
+        None => return Err(missing_controller_error(&state)),
+    }
+
+    // The sender is dropped without sending a profile if the pipeline
+    // terminates before the profiled steps complete.
+    let profile = receiver.await.map_err(|_| PipelineError::Terminating)??;
+
+    Ok(match args.format {
+        ProfileFormat::Json => HttpResponse::Ok().json(crate::profile::circuit_profile(&profile)),
+        ProfileFormat::Folded => HttpResponse::Ok()
+            .content_type(mime::TEXT_PLAIN)
+            .body(crate::profile::folded_stacks(&profile)),
+    })
+}
+
+/// Retrieve operator profiles from the circuit thread.
+async fn retrieve_profile(state: &ServerState) -> Result<Vec<Vec<OperatorProfile>>, PipelineError> {
+    let (sender, receiver) = oneshot::channel();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => controller.retrieve_profile(Box::new(move |profile| {
+            let _ = sender.send(profile);
+        })),
+        None => return Err(missing_controller_error(state)),
+    }
+
+    // The sender is dropped without sending a profile if the pipeline
+    // terminates before the request is processed.
+    let profile = receiver.await.map_err(|_| PipelineError::Terminating)?;
+    profile.map_err(PipelineError::from)
+}
+
+/// Take a checkpoint of the pipeline and return its sequence number once the
+/// checkpoint is complete.
+#[post("/checkpoint")]
+async fn checkpoint(state: WebData<ServerState>) -> impl Responder {
+    let (sender, receiver) = oneshot::channel();
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => controller.checkpoint(Box::new(move |seq| {
+            let _ = sender.send(seq);
+        })),
+        None => return Err(missing_controller_error(&state)),
+    }
+
+    let seq = receiver.await.map_err(|_| PipelineError::Terminating)??;
+    Ok(HttpResponse::Ok().json(json!({ "checkpoint": seq })))
+}
+
 #[get("/shutdown")]
 async fn shutdown(state: WebData<ServerState>) -> impl Responder {
     let controller = state.controller.lock().unwrap().take();
@@ -522,7 +1260,7 @@ async fn shutdown(state: WebData<ServerState>) -> impl Responder {
                 if let Some(sender) = &state.terminate_sender {
                     let _ = sender.send(()).await;
                 }
-                if let Err(e) = tokio::fs::remove_file(SERVER_PORT_FILE).await {
+                if let Err(e) = tokio::fs::remove_file(state.port_file()).await {
                     warn!("Failed to remove server port file: {e}");
                 }
                 Ok(HttpResponse::Ok().json("Pipeline terminated"))
@@ -535,6 +1273,54 @@ async fn shutdown(state: WebData<ServerState>) -> impl Responder {
     }
 }
 
+/// Interval at which the `/drain` endpoint checks whether all inputs have
+/// been processed.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Disconnect all input endpoints, run the circuit until all data received so
+/// far has been processed and the outputs sent to every output endpoint, and
+/// terminate the pipeline.
+///
+/// The request completes once the pipeline has terminated.  The pipeline
+/// process then exits with [`DRAINED_EXIT_CODE`].
+#[post("/drain")]
+async fn drain(state: WebData<ServerState>) -> Result<HttpResponse, PipelineError> {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => controller.drain(),
+        None => return Err(missing_controller_error(&state)),
+    }
+
+    loop {
+        match &*state.controller.lock().unwrap() {
+            Some(controller) if controller.pipeline_complete() => break,
+            Some(controller) => {
+                // Process records pushed by input endpoints while they were
+                // being disconnected.
+                if controller.status().num_buffered_input_records() > 0 {
+                    controller.step();
+                }
+            }
+            None => return Err(missing_controller_error(&state)),
+        }
+        rt::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+
+    let controller = state.controller.lock().unwrap().take();
+    match controller {
+        Some(controller) => controller.stop()?,
+        None => return Err(missing_controller_error(&state)),
+    }
+
+    state.drained.store(true, Ordering::Release);
+    if let Some(sender) = &state.terminate_sender {
+        let _ = sender.send(()).await;
+    }
+    if let Err(e) = tokio::fs::remove_file(state.port_file()).await {
+        warn!("Failed to remove server port file: {e}");
+    }
+    Ok(HttpResponse::Ok().json("Pipeline drained"))
+}
+
 #[derive(Debug, Deserialize)]
 struct IngressArgs {
     // #[serde(default = "HttpInputTransport::default_mode")]
@@ -544,6 +1330,13 @@ struct IngressArgs {
     /// Push data to the pipeline even if the pipeline is in a paused state.
     #[serde(default)]
     force: bool,
+    /// Stop processing the request at the first parse error.
+    ///
+    /// By default, invalid records are skipped and the rest of the request
+    /// is ingested.  In either case, records ingested before the error are
+    /// not rolled back.
+    #[serde(default)]
+    abort_on_error: bool,
 }
 
 #[post("/ingress/{table_name}")]
@@ -573,6 +1366,9 @@ async fn input_endpoint(
     // Create endpoint config.
     let config = InputEndpointConfig {
         stream: Cow::from(table_name),
+        columns: None,
+        lineage: None,
+        dedup: None,
         connector_config: ConnectorConfig {
             transport: HttpInputTransport::config(),
             format: FormatConfig::parser_config_from_http_request(
@@ -581,6 +1377,15 @@ async fn input_endpoint(
                 &req,
             )?,
             max_buffered_records: HttpInputTransport::default_max_buffered_records(),
+            max_records_per_sec: None,
+            max_bytes_per_sec: None,
+            retry: None,
+            max_batch_delay_ms: None,
+            max_batch_size: None,
+            num_parsers: None,
+            parser_sharding: None,
+            on_error: None,
+            overflow: None,
         },
     };
 
@@ -609,17 +1414,39 @@ async fn input_endpoint(
         }
     };
 
+    // Associate the request with the client's trace, if any.
+    endpoint.trace_context(req.headers());
+
     // Call endpoint to complete request.
-    let response = endpoint.complete_request(payload).await;
+    let result = endpoint
+        .complete_request(payload, args.abort_on_error)
+        .await;
     drop(endpoint);
 
     // Delete endpoint on completion/error.
+    let mut num_records = 0;
     if let Some(controller) = state.controller.lock().unwrap().as_ref() {
+        if let Some(status) = controller.status().input_status().get(&endpoint_id) {
+            num_records = status.metrics.total_records.load(Ordering::Acquire);
+        }
         controller.disconnect_input(&endpoint_id);
         controller.unregister_api_connection();
     }
 
-    response
+    // Summarize accepted and rejected records.
+    let (num_errors, errors) = result?;
+    if num_errors == 0 {
+        Ok(HttpResponse::Ok().json(json!({
+            "num_records": num_records,
+            "num_errors": 0,
+        })))
+    } else {
+        Err(PipelineError::parse_errors(
+            num_records,
+            num_errors,
+            errors.iter(),
+        ))
+    }
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, ToSchema)]
@@ -636,8 +1463,9 @@ pub enum EgressMode {
     Watch,
     /// Output a single snapshot of query results.
     ///
-    /// Currently only supported for [quantile](`OutputQuery::Quantiles`)
-    /// and [neighborhood](`OutputQuery::Neighborhood`) queries.
+    /// For [table](`OutputQuery::Table`) queries, outputs one page of the
+    /// current contents of the table, followed by a cursor used to request
+    /// the next page.  Only supported with the JSON format.
     #[serde(rename = "snapshot")]
     Snapshot,
 }
@@ -669,6 +1497,64 @@ struct EgressArgs {
     /// the number of quantiles to output.
     #[serde(default = "dbsp::operator::sample::default_quantiles")]
     quantiles: u32,
+
+    /// For [`quantiles`](`OutputQuery::Quantiles`) queries: comma-separated
+    /// list of columns to compute quantiles over.  When specified, the
+    /// endpoint outputs the quantiles of each column in a separate chunk
+    /// labeled with the column name instead of quantiles of entire records.
+    /// Only supported with the JSON format.
+    #[serde(default)]
+    columns: Option<String>,
+
+    /// For [`sample`](`OutputQuery::Sample`) queries:
+    /// the maximal number of records to output.
+    #[serde(default = "dbsp::operator::sample::default_sample_size")]
+    sample_size: u32,
+
+    /// For [`table`](`OutputQuery::Table`) queries in the `snapshot` mode:
+    /// the maximal number of records in a page.
+    #[serde(default = "pagination::default_page_size")]
+    page_size: usize,
+
+    /// When the response is compressed (see `Accept-Encoding`): the minimal
+    /// amount of uncompressed output, in bytes, to accumulate before
+    /// compressing it and sending it to the client.
+    #[serde(default = "HttpOutputTransport::default_min_compressed_chunk_size")]
+    min_chunk_size: usize,
+
+    /// Compression to apply to the response ('gzip', 'zstd', or 'none').
+    /// Overrides the `Accept-Encoding` header.
+    #[serde(default)]
+    compression: Option<CompressionArg>,
+
+    /// Wrap output chunks in the changelog envelope, which tags each chunk
+    /// with the step that produced it and marks the end of each step.
+    #[serde(default)]
+    changelog: bool,
+
+    /// In changelog mode: emit a snapshot marker every `snapshot_interval`
+    /// steps.
+    #[serde(default)]
+    snapshot_interval: Option<u64>,
+
+    /// For [`table`](`OutputQuery::Table`) queries in the `snapshot` mode:
+    /// output the version of the view produced by this step of the circuit
+    /// instead of the current contents of the view.  Requires the pipeline
+    /// to retain the history of the view (see `RuntimeConfig::history`).
+    #[serde(default)]
+    as_of_step: Option<u64>,
+}
+
+/// URL-encoded arguments to the `/views/{view_name}/sample` endpoint.
+#[derive(Debug, Deserialize)]
+struct SampleArgs {
+    /// The maximal number of records to output.
+    #[serde(default = "dbsp::operator::sample::default_sample_size")]
+    n: u32,
+
+    /// Data format used to encode the sample, e.g., 'csv', 'json' etc.
+    #[serde(default = "HttpOutputTransport::default_format")]
+    format: String,
 }
 
 #[post("/egress/{table_name}")]
@@ -680,8 +1566,6 @@ async fn output_endpoint(
 ) -> impl Responder {
     debug!("/egress request:{req:?}");
 
-    let state = state.into_inner();
-
     let table_name = match req.match_info().get("table_name") {
         None => {
             return Err(PipelineError::MissingUrlEncodedParam {
@@ -691,13 +1575,113 @@ async fn output_endpoint(
         Some(table_name) => table_name.to_string(),
     };
 
+    do_output_endpoint(state, &req, table_name, args.into_inner(), body)
+}
+
+/// Output a uniform random sample of the current contents of a table or view.
+///
+/// Shorthand for `/egress/{view_name}?mode=snapshot&query=sample`.  The
+/// sample is read from a reservoir maintained incrementally by the circuit
+/// (see [`dbsp::Stream::sample_reservoir`]), so its cost is proportional to
+/// the size of the sample and not the size of the view.
+#[get("/views/{view_name}/sample")]
+async fn sample_endpoint(
+    state: WebData<ServerState>,
+    req: HttpRequest,
+    args: Query<SampleArgs>,
+) -> impl Responder {
+    debug!("/sample request:{req:?}");
+
+    let view_name = match req.match_info().get("view_name") {
+        None => {
+            return Err(PipelineError::MissingUrlEncodedParam { param: "view_name" });
+        }
+        Some(view_name) => view_name.to_string(),
+    };
+
+    let args = args.into_inner();
+    let args = EgressArgs {
+        query: OutputQuery::Sample,
+        mode: EgressMode::Snapshot,
+        format: args.format,
+        quantiles: dbsp::operator::sample::default_quantiles(),
+        columns: None,
+        sample_size: args.n,
+        page_size: pagination::default_page_size(),
+        min_chunk_size: HttpOutputTransport::default_min_compressed_chunk_size(),
+        compression: None,
+        changelog: false,
+        snapshot_interval: None,
+        as_of_step: None,
+    };
+
+    do_output_endpoint(state, &req, view_name, args, None)
+}
+
+/// Output a past version of a view retained by the controller.
+///
+/// The entire version is sent in a single JSON object of the form
+/// `{"step": ..., "records": [...]}`.
+fn view_as_of_step(
+    state: &ServerState,
+    view_name: &str,
+    args: &EgressArgs,
+    step: u64,
+) -> Result<HttpResponse, PipelineError> {
+    if args.mode != EgressMode::Snapshot || args.query != OutputQuery::Table {
+        return Err(PipelineError::InvalidHistoryRequest {
+            reason: "'as_of_step' is only supported by 'table' queries in the 'snapshot' mode"
+                .to_string(),
+        });
+    }
+    if args.format != "json" {
+        return Err(PipelineError::InvalidHistoryRequest {
+            reason: format!(
+                "past versions of views are only supported with the 'json' format, not '{}'",
+                args.format
+            ),
+        });
+    }
+
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => match controller.view_as_of_step(view_name, step) {
+            None => Err(PipelineError::HistoryNotRetained {
+                stream_name: view_name.to_string(),
+            }),
+            Some(Err(retained_steps)) => Err(PipelineError::StepNotRetained {
+                stream_name: view_name.to_string(),
+                step,
+                oldest_step: *retained_steps.start(),
+                latest_step: *retained_steps.end(),
+            }),
+            Some(Ok(records)) => {
+                // Records are already serialized as JSON.
+                let body = format!(r#"{{"step":{step},"records":[{}]}}"#, records.join(","));
+                Ok(HttpResponse::Ok()
+                    .content_type(mime::APPLICATION_JSON)
+                    .body(body))
+            }
+        },
+        None => Err(missing_controller_error(state)),
+    }
+}
+
+fn do_output_endpoint(
+    state: WebData<ServerState>,
+    req: &HttpRequest,
+    table_name: String,
+    mut args: EgressArgs,
+    body: Option<Json<JsonValue>>,
+) -> Result<HttpResponse, PipelineError> {
+    let state = state.into_inner();
+
     // Check for unsupported combinations.
     match (args.mode, args.query) {
         (EgressMode::Watch, OutputQuery::Quantiles) => {
             return Err(PipelineError::QuantileStreamingNotSupported);
         }
-        (EgressMode::Snapshot, OutputQuery::Table) => {
-            return Err(PipelineError::TableSnapshotNotImplemented);
+        (EgressMode::Watch, OutputQuery::Sample) => {
+            return Err(PipelineError::SampleStreamingNotSupported);
         }
         _ => {}
     };
@@ -712,8 +1696,84 @@ async fn output_endpoint(
         return Err(PipelineError::NumQuantilesOutOfRange {
             quantiles: args.quantiles,
         });
+    } else if args.query == OutputQuery::Sample
+        && (args.sample_size as usize > DEFAULT_RESERVOIR_CAPACITY || args.sample_size == 0)
+    {
+        return Err(PipelineError::SampleSizeOutOfRange {
+            sample_size: args.sample_size,
+        });
     }
 
+    if let Some(step) = args.as_of_step {
+        return view_as_of_step(&state, &table_name, &args, step);
+    }
+
+    // Snapshots of tables and views are split into pages.  The cursor of the
+    // page is submitted in the body of the request.
+    let paginated = args.mode == EgressMode::Snapshot && args.query == OutputQuery::Table;
+    let cursor = if paginated {
+        if args.page_size > MAX_SNAPSHOT_PAGE_SIZE || args.page_size == 0 {
+            return Err(PipelineError::SnapshotPageSizeOutOfRange {
+                page_size: args.page_size,
+            });
+        }
+        if args.format != "json" {
+            return Err(PipelineError::InvalidSnapshotPage {
+                reason: format!(
+                    "snapshots of tables and views are only supported with the 'json' format, not '{}'",
+                    args.format
+                ),
+            });
+        }
+        match body.as_ref().map(|body| &**body) {
+            None | Some(JsonValue::Null) => None,
+            Some(JsonValue::Object(request)) => request
+                .get("cursor")
+                .filter(|cursor| !cursor.is_null())
+                .cloned(),
+            Some(_) => {
+                return Err(PipelineError::InvalidSnapshotPage {
+                    reason:
+                        "the request body must be a JSON object of the form '{\"cursor\": ...}'"
+                            .to_string(),
+                });
+            }
+        }
+    } else {
+        None
+    };
+
+    let columns = match args.columns.take() {
+        Some(_) if args.query != OutputQuery::Quantiles => {
+            return Err(PipelineError::InvalidQuantileColumns {
+                reason: "the 'columns' argument is only supported by 'quantiles' queries"
+                    .to_string(),
+            });
+        }
+        Some(_) if args.format != "json" => {
+            return Err(PipelineError::InvalidQuantileColumns {
+                reason: format!(
+                    "column quantiles are only supported with the 'json' format, not '{}'",
+                    args.format
+                ),
+            });
+        }
+        Some(columns) => {
+            let columns: Vec<String> = columns
+                .split(',')
+                .map(|column| column.trim().to_string())
+                .filter(|column| !column.is_empty())
+                .collect();
+            if columns.is_empty() {
+                return Err(PipelineError::InvalidQuantileColumns {
+                    reason: "the 'columns' argument must list at least one column".to_string(),
+                });
+            }
+            Some(columns)
+        }
+        None => None,
+    };
+
     // Generate endpoint name depending on the query and output mode.
     let endpoint_name = format!(
         "api-{}-{table_name}-{}{}",
@@ -725,10 +1785,28 @@ async fn output_endpoint(
             OutputQuery::Table => "",
             OutputQuery::Neighborhood => "neighborhood-",
             OutputQuery::Quantiles => "quantiles-",
+            OutputQuery::Sample => "sample-",
         },
         Uuid::new_v4()
     );
 
+    // Column quantiles are computed from a sample of the same size as the one
+    // used by the circuit to compute record quantiles.
+    if columns.is_some() {
+        args.query = OutputQuery::Sample;
+        args.sample_size = (args.quantiles * args.quantiles).min(DEFAULT_RESERVOIR_CAPACITY as u32);
+    }
+
+    // Pages are computed by neighborhood queries anchored at the cursor.
+    let neighborhood = if paginated {
+        args.query = OutputQuery::Neighborhood;
+        Some(page_query(cursor.as_ref(), args.page_size))
+    } else if args.query == OutputQuery::Neighborhood {
+        body.map(|body| body.into_inner())
+    } else {
+        None
+    };
+
     // debug!("Endpoint name: '{endpoint_name}'");
 
     // Create HTTP endpoint.
@@ -737,11 +1815,22 @@ async fn output_endpoint(
         &args.format,
         matches!(
             args.query,
-            OutputQuery::Neighborhood | OutputQuery::Quantiles
+            OutputQuery::Neighborhood | OutputQuery::Quantiles | OutputQuery::Sample
         ),
         args.mode == EgressMode::Watch,
+        EgressCompression::negotiate(req, args.compression),
+        args.min_chunk_size,
+        args.changelog.then(|| ChangelogConfig {
+            snapshot_interval: args.snapshot_interval.filter(|interval| *interval > 0),
+        }),
     );
 
+    if args.changelog && !endpoint.is_framed() {
+        return Err(PipelineError::ChangelogNotSupported {
+            format: args.format,
+        });
+    }
+
     // Create endpoint config.
     let config = OutputEndpointConfig {
         stream: Cow::from(table_name),
@@ -751,9 +1840,18 @@ async fn output_endpoint(
             format: FormatConfig::encoder_config_from_http_request(
                 &endpoint_name,
                 &args.format,
-                &req,
+                req,
             )?,
             max_buffered_records: HttpOutputTransport::default_max_buffered_records(),
+            max_records_per_sec: None,
+            max_bytes_per_sec: None,
+            retry: None,
+            max_batch_delay_ms: None,
+            max_batch_size: None,
+            num_parsers: None,
+            parser_sharding: None,
+            on_error: None,
+            overflow: None,
         },
     };
 
@@ -770,11 +1868,33 @@ async fn output_endpoint(
                 return Err(PipelineError::ApiConnectionLimit);
             }
 
-            let endpoint_id = match controller.add_output_endpoint(
-                &endpoint_name,
-                &config,
-                Box::new(endpoint.clone()) as Box<dyn OutputEndpoint>,
-            ) {
+            let output_endpoint = Box::new(endpoint.clone()) as Box<dyn OutputEndpoint>;
+            let output_endpoint = match columns {
+                Some(columns) => Box::new(ColumnQuantilesEndpoint::new(
+                    output_endpoint,
+                    columns,
+                    args.quantiles as usize,
+                )),
+                None if paginated => Box::new(SnapshotPageEndpoint::new(
+                    output_endpoint,
+                    cursor,
+                    args.page_size,
+                )),
+                None => output_endpoint,
+            };
+
+            // Neighborhood queries with different parameters run in separate
+            // neighborhood sessions, so they don't interfere with each other.
+            let endpoint_id = match &neighborhood {
+                Some(neighborhood) => controller.add_neighborhood_endpoint(
+                    &endpoint_name,
+                    &config,
+                    output_endpoint,
+                    &neighborhood.to_string(),
+                ),
+                None => controller.add_output_endpoint(&endpoint_name, &config, output_endpoint),
+            };
+            let endpoint_id = match endpoint_id {
                 Ok(endpoint_id) => endpoint_id,
                 Err(e) => {
                     controller.unregister_api_connection();
@@ -809,48 +1929,99 @@ async fn output_endpoint(
             }));
 
             // The endpoint is ready to receive data from the pipeline.
+            //
+            // Snapshot queries identical to a query answered by the latest step of
+            // the circuit are served from the controller's snapshot cache without
+            // running the circuit.
             match args.query {
                 // Send reset signal to produce a complete neighborhood snapshot.
                 OutputQuery::Neighborhood => {
-                    let body = body.unwrap();
-
-                    if let Err(e) = controller
-                        .catalog()
-                        .lock()
-                        .unwrap()
-                        .output_handles(&config.stream)
-                        // The following `unwrap` is safe because `table_name` was previously
-                        // validated by `add_output_endpoint`.
-                        .unwrap()
-                        .neighborhood_descr_handle
-                        .as_ref()
-                        .ok_or_else(|| PipelineError::NeighborhoodNotSupported)?
-                        .set_for_all(&mut <dyn ErasedDeserializer>::erase(json!([
-                            json!(true),
-                            body
-                        ])))
-                    {
-                        // Dropping `response` triggers the finalizer closure, which will
-                        // disconnect this endpoint.
-                        return Err(PipelineError::InvalidNeighborhoodSpec {
-                            spec: body.into_inner(),
-                            parse_error: e.to_string(),
-                        });
+                    let neighborhood = neighborhood.unwrap();
+                    let params = neighborhood.to_string();
+
+                    if controller.send_cached_snapshot(&endpoint_id, &params) {
+                        return Ok(response);
                     }
+
+                    // The endpoint exists at this point, since it can only be
+                    // disconnected by dropping `response`.
+                    let session = controller
+                        .neighborhood_session(&endpoint_id)
+                        .unwrap_or_default();
+
+                    controller.submit_snapshot_query(&endpoint_id, &params, || {
+                        if let Err(e) = controller
+                            .catalog()
+                            .lock()
+                            .unwrap()
+                            .output_handles(&config.stream)
+                            // The following `unwrap` is safe because `table_name` was
+                            // previously validated by `add_output_endpoint`.
+                            .unwrap()
+                            .neighborhood_sessions
+                            .get(session)
+                            .ok_or_else(|| PipelineError::NeighborhoodNotSupported)?
+                            .descr_handle
+                            .set_for_all(&mut <dyn ErasedDeserializer>::erase(json!([
+                                json!(true),
+                                neighborhood
+                            ])))
+                        {
+                            // Dropping `response` triggers the finalizer closure, which
+                            // will disconnect this endpoint.
+                            return Err(PipelineError::InvalidNeighborhoodSpec {
+                                spec: neighborhood,
+                                parse_error: e.to_string(),
+                            });
+                        }
+                        Ok(())
+                    })?;
                     controller.request_step();
                 }
                 // Write quantiles size.
                 OutputQuery::Quantiles => {
-                    controller
-                        .catalog()
-                        .lock()
-                        .unwrap()
-                        .output_handles(&config.stream)
-                        .unwrap()
-                        .num_quantiles_handle
-                        .as_ref()
-                        .ok_or(PipelineError::QuantilesNotSupported)?
-                        .set_for_all(args.quantiles as usize);
+                    let params = args.quantiles.to_string();
+
+                    if controller.send_cached_snapshot(&endpoint_id, &params) {
+                        return Ok(response);
+                    }
+
+                    controller.submit_snapshot_query(&endpoint_id, &params, || {
+                        controller
+                            .catalog()
+                            .lock()
+                            .unwrap()
+                            .output_handles(&config.stream)
+                            .unwrap()
+                            .num_quantiles_handle
+                            .as_ref()
+                            .ok_or(PipelineError::QuantilesNotSupported)?
+                            .set_for_all(args.quantiles as usize);
+                        Ok::<(), PipelineError>(())
+                    })?;
+                    controller.request_step();
+                }
+                // Write sample size.
+                OutputQuery::Sample => {
+                    let params = args.sample_size.to_string();
+
+                    if controller.send_cached_snapshot(&endpoint_id, &params) {
+                        return Ok(response);
+                    }
+
+                    controller.submit_snapshot_query(&endpoint_id, &params, || {
+                        controller
+                            .catalog()
+                            .lock()
+                            .unwrap()
+                            .output_handles(&config.stream)
+                            .unwrap()
+                            .sample_size_handle
+                            .as_ref()
+                            .ok_or(PipelineError::SampleNotSupported)?
+                            .set_for_all(args.sample_size as usize);
+                        Ok::<(), PipelineError>(())
+                    })?;
                     controller.request_step();
                 }
                 OutputQuery::Table => {}
@@ -872,7 +2043,7 @@ mod test_with_kafka {
             generate_test_batches,
             http::{TestHttpReceiver, TestHttpSender},
             kafka::{BufferConsumer, KafkaResources, TestProducer},
-            test_circuit,
+            test_circuit, TestStruct,
         },
     };
     use actix_web::{
@@ -888,7 +2059,9 @@ mod test_with_kafka {
     };
     use serde_json::{self, json, Value as JsonValue};
     use std::{
+        collections::BTreeSet,
         io::Write,
+        path::PathBuf,
         thread,
         thread::sleep,
         time::{Duration, Instant},
@@ -920,6 +2093,7 @@ mod test_with_kafka {
         // Config string
         let config_str = r#"
 name: test
+column_statistics: [test_output1]
 inputs:
     test_input1:
         stream: test_input1
@@ -948,7 +2122,7 @@ outputs:
 
         println!("Creating HTTP server");
 
-        let state = WebData::new(ServerState::new(None));
+        let state = WebData::new(ServerState::new(None, &[], PathBuf::from(".")));
         let state_clone = state.clone();
 
         let args = ServerArgs {
@@ -956,6 +2130,13 @@ outputs:
             metadata_file: None,
             bind_address: "127.0.0.1".to_string(),
             default_port: None,
+            auth_token_file: None,
+            #[cfg(feature = "with-grpc")]
+            grpc_port: None,
+            #[cfg(feature = "with-plugins")]
+            transport_plugin: Vec::new(),
+            working_directory: None,
+            embedded: false,
         };
         thread::spawn(move || {
             bootstrap(
@@ -1013,6 +2194,13 @@ outputs:
         let resp = server.get("/metadata").send().await.unwrap();
         assert!(resp.status().is_success());
 
+        println!("/metrics");
+        let mut resp = server.get("/metrics").send().await.unwrap();
+        assert!(resp.status().is_success());
+        let body = String::from_utf8(resp.body().await.unwrap().to_vec()).unwrap();
+        assert!(body.contains("input_total_records"));
+        assert!(body.contains("operator_invocations"));
+
         // Pause command; send more data, receive none.
         println!("/pause");
         let resp = server.get("/pause").send().await.unwrap();
@@ -1031,6 +2219,61 @@ outputs:
         buffer_consumer.wait_for_output_unordered(&data);
         buffer_consumer.clear();
 
+        // Pause a single input endpoint; send more data, receive none.
+        println!("/input_endpoints/test_input1/pause");
+        let resp = server
+            .post("/input_endpoints/test_input1/pause")
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        sleep(Duration::from_millis(1000));
+
+        producer.send_to_topic(&data, "test_server_input_topic");
+        sleep(Duration::from_millis(2000));
+        assert_eq!(buffer_consumer.len(), 0);
+
+        // Resume the endpoint; wait for data.
+        println!("/input_endpoints/test_input1/start");
+        let resp = server
+            .post("/input_endpoints/test_input1/start")
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        buffer_consumer.wait_for_output_unordered(&data);
+        buffer_consumer.clear();
+
+        let resp = server
+            .post("/input_endpoints/no_such_endpoint/pause")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // The Kafka transport cannot change its configuration on the fly.
+        let resp = server
+            .put("/input_endpoints/test_input1/transport_config")
+            .send_json(&json!({"topics": ["test_server_input_topic"]}))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = server
+            .put("/input_endpoints/no_such_endpoint/transport_config")
+            .send_json(&json!({}))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = server
+            .post("/output_endpoints/test_output2/stop")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
         println!("Testing invalid input");
         producer.send_string("invalid\n", "test_server_input_topic");
         loop {
@@ -1126,6 +2369,31 @@ outputs:
         let body = serde_json::from_slice::<JsonValue>(&body.unwrap()).unwrap();
         println!("Quantiles: {body}");
 
+        // Repeat the query.  The circuit hasn't received any new inputs, so the
+        // result must be served from the snapshot cache.
+        let mut quantiles_resp2 = server
+            .post("/egress/test_output1?mode=snapshot&query=quantiles")
+            .send()
+            .await
+            .unwrap();
+        assert!(quantiles_resp2.status().is_success());
+        let body2 = quantiles_resp2.body().await;
+        let body2 = serde_json::from_slice::<JsonValue>(&body2.unwrap()).unwrap();
+        assert_eq!(body, body2);
+
+        let stats = server
+            .get("/stats")
+            .send()
+            .await
+            .unwrap()
+            .json::<JsonValue>()
+            .await
+            .unwrap();
+        assert_eq!(
+            stats["global_metrics"]["snapshot_cache_hits"].as_u64(),
+            Some(1)
+        );
+
         // Request quantiles for the input collection -- inputs must also behave as
         // outputs.
         let mut input_quantiles = server
@@ -1138,6 +2406,87 @@ outputs:
         let body = serde_json::from_slice::<JsonValue>(&body.unwrap()).unwrap();
         println!("Input quantiles: {body}");
 
+        // Request quantiles of individual columns.
+        let mut column_quantiles = server
+            .post("/egress/test_output1?mode=snapshot&query=quantiles&quantiles=4&columns=id,s&format=json")
+            .send()
+            .await
+            .unwrap();
+        assert!(column_quantiles.status().is_success());
+        let body = column_quantiles.body().await.unwrap();
+        let chunks = std::str::from_utf8(&body)
+            .unwrap()
+            .split("\r\n")
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| serde_json::from_str::<JsonValue>(chunk).unwrap())
+            .collect::<Vec<_>>();
+        println!("Column quantiles: {chunks:?}");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0]["json_data"]["column"], json!("id"));
+        assert_eq!(chunks[1]["json_data"]["column"], json!("s"));
+        assert!(
+            chunks[0]["json_data"]["quantiles"]
+                .as_array()
+                .unwrap()
+                .len()
+                <= 4
+        );
+
+        // Column quantiles require a quantiles query.
+        let resp = server
+            .post("/egress/test_output1?mode=snapshot&query=sample&columns=id")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // Request a sample of the view.
+        let mut sample_resp = server
+            .get("/views/test_output1/sample?n=10&format=json")
+            .send()
+            .await
+            .unwrap();
+        assert!(sample_resp.status().is_success());
+        let body = sample_resp.body().await;
+        let body = serde_json::from_slice::<JsonValue>(&body.unwrap()).unwrap();
+        println!("Sample: {body}");
+
+        // Sample size must be within bounds.
+        let resp = server
+            .get("/views/test_output1/sample?n=0")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        // Samples can only be retrieved as snapshots.
+        let resp = server
+            .post("/egress/test_output1?mode=watch&query=sample")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // Request column statistics.
+        let mut stats_resp = server
+            .get("/views/test_output1/column_stats")
+            .send()
+            .await
+            .unwrap();
+        assert!(stats_resp.status().is_success());
+        let body = stats_resp.body().await;
+        let body = serde_json::from_slice::<JsonValue>(&body.unwrap()).unwrap();
+        println!("Column statistics: {body}");
+        assert!(body["num_records"].as_i64().unwrap() > 0);
+
+        // Statistics are not enabled for the input table.
+        let resp = server
+            .get("/views/test_input1/column_stats")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
         // Request neighborhood snapshot.
         let mut hood_resp1 = server
             .post("/egress/test_output1?mode=snapshot&query=neighborhood")
@@ -1178,6 +2527,56 @@ outputs:
         let body = serde_json::from_slice::<JsonValue>(&body.unwrap()).unwrap();
         println!("Neighborhood: {body}");
 
+        // Export the view one page at a time.
+        let mut cursor = JsonValue::Null;
+        let mut records = Vec::new();
+        loop {
+            let mut page_resp = server
+                .post("/egress/test_output1?mode=snapshot&format=json&page_size=100")
+                .send_json(&json!({ "cursor": cursor }))
+                .await
+                .unwrap();
+            assert!(page_resp.status().is_success());
+            let body = page_resp.body().await.unwrap();
+            let chunks = std::str::from_utf8(&body)
+                .unwrap()
+                .split("\r\n")
+                .filter(|chunk| !chunk.is_empty())
+                .map(|chunk| serde_json::from_str::<JsonValue>(chunk).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(chunks.len(), 1);
+            let page = &chunks[0]["json_data"];
+            let page_records = page["records"].as_array().unwrap();
+            assert!(page_records.len() <= 100);
+            records.extend(
+                page_records
+                    .iter()
+                    .map(|record| serde_json::from_value::<TestStruct>(record.clone()).unwrap()),
+            );
+            cursor = page["cursor"].clone();
+            if cursor.is_null() {
+                break;
+            }
+        }
+        let expected = data.iter().flatten().cloned().collect::<BTreeSet<_>>();
+        assert_eq!(records, expected.into_iter().collect::<Vec<_>>());
+
+        // Pages are only supported with the JSON format.
+        let resp = server
+            .post("/egress/test_output1?mode=snapshot&format=csv")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // Page size must be within bounds.
+        let resp = server
+            .post("/egress/test_output1?mode=snapshot&format=json&page_size=0")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
         // Request neighborhood stream.
         let mut hood_resp2 = server
             .post("/egress/test_output1?mode=watch&query=neighborhood")
@@ -1193,6 +2592,49 @@ outputs:
         let body = serde_json::from_slice::<JsonValue>(&bytes).unwrap();
         println!("Neighborhood: {body}");
 
+        // Attach an output endpoint to the running pipeline.
+        let output_file = NamedTempFile::new().unwrap();
+        let new_endpoint = json!({
+            "name": "test_output3",
+            "config": {
+                "stream": "test_output1",
+                "transport": {
+                    "name": "file",
+                    "config": {"path": output_file.path().display().to_string()}
+                },
+                "format": {"name": "csv"}
+            }
+        });
+        println!("/output_endpoints");
+        let resp = server
+            .post("/output_endpoints")
+            .send_json(&new_endpoint)
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        // Endpoint names must be unique.
+        let resp = server
+            .post("/output_endpoints")
+            .send_json(&new_endpoint)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = server
+            .delete("/output_endpoints/test_output3")
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        let resp = server
+            .delete("/output_endpoints/test_output3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
         println!("/pause");
         let resp = server.get("/pause").send().await.unwrap();
         assert!(resp.status().is_success());
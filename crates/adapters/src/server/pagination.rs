@@ -0,0 +1,248 @@
+//! Paginated snapshots of tables and views.
+//!
+//! A `table` query in `snapshot` mode returns the current contents of a
+//! table or view one bounded page at a time, so that clients can export
+//! views that are too large to receive in a single response.  Pages are
+//! ordered by the key of the collection.  Each page ends with a cursor,
+//! which the client submits in the body of the next request to retrieve the
+//! following page.
+//!
+//! Pages are computed by the neighborhood operator of the collection (see
+//! `Stream::neighborhood` in `dbsp`): the page after `cursor` is the
+//! neighborhood of `cursor` with `before = 0`.  The neighborhood starts at
+//! the first record greater than or equal to the anchor, so it includes the
+//! last record of the previous page unless this record has been deleted
+//! since, and we request two extra records to be able to skip it and still
+//! tell whether there are any records after the page.  [`SnapshotPageEndpoint`]
+//! converts the neighborhood into a page.
+
+use crate::{transport::AsyncErrorCallback, OutputEndpoint};
+use anyhow::{anyhow, Result as AnyResult};
+use serde_json::{json, Value as JsonValue};
+use std::mem::take;
+
+/// Largest number of records in a snapshot page.
+pub(crate) const MAX_SNAPSHOT_PAGE_SIZE: usize = 100_000;
+
+/// Default number of records in a snapshot page.
+pub(crate) fn default_page_size() -> usize {
+    1_000
+}
+
+/// Neighborhood query that retrieves the page of `page_size` records that
+/// follows `cursor`, or the first page if `cursor` is `None`.
+pub(crate) fn page_query(cursor: Option<&JsonValue>, page_size: usize) -> JsonValue {
+    json!({
+        "anchor": cursor,
+        "before": 0,
+        "after": page_size + 2,
+    })
+}
+
+/// Output endpoint that converts the snapshot of a neighborhood computed by
+/// [`page_query`] into a single page, which it sends to the inner endpoint
+/// when the batch ends.
+///
+/// The page is sent as a JSON object of the form
+/// `{"records": [...], "cursor": ...}`, where `cursor` is the last record in
+/// the page or `null` if this is the last page.
+pub(crate) struct SnapshotPageEndpoint {
+    inner: Box<dyn OutputEndpoint>,
+    page_size: usize,
+
+    /// Cursor submitted with the request.
+    cursor: Option<JsonValue>,
+
+    /// Neighborhood entries received so far, along with their indexes.
+    records: Vec<(i64, JsonValue)>,
+}
+
+impl SnapshotPageEndpoint {
+    pub(crate) fn new(
+        inner: Box<dyn OutputEndpoint>,
+        cursor: Option<JsonValue>,
+        page_size: usize,
+    ) -> Self {
+        Self {
+            inner,
+            page_size,
+            cursor,
+            records: Vec::new(),
+        }
+    }
+
+    /// Assemble the page from the records received so far.
+    fn page(&mut self) -> JsonValue {
+        let mut records = take(&mut self.records);
+        records.sort_by_key(|(index, _)| *index);
+
+        let mut records: Vec<JsonValue> = records
+            .into_iter()
+            .filter(|(index, _)| *index >= 0)
+            .map(|(_, record)| record)
+            .collect();
+
+        // Skip the last record of the previous page.
+        if self.cursor.is_some() && records.first() == self.cursor.as_ref() {
+            records.remove(0);
+        }
+
+        let cursor = if records.len() > self.page_size {
+            records.truncate(self.page_size);
+            records.last().cloned()
+        } else {
+            None
+        };
+
+        json!({
+            "records": records,
+            "cursor": cursor,
+        })
+    }
+}
+
+impl OutputEndpoint for SnapshotPageEndpoint {
+    fn connect(&self, async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        self.inner.connect(async_error_callback)
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        self.inner.max_buffer_size_bytes()
+    }
+
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.inner.batch_start()
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        // The JSON encoder outputs either an array of updates or a single
+        // update per buffer.
+        let updates = match serde_json::from_slice::<JsonValue>(buffer)
+            .map_err(|e| anyhow!("received an invalid JSON string from encoder: {e}"))?
+        {
+            JsonValue::Array(updates) => updates,
+            update => vec![update],
+        };
+
+        // Snapshots only contain insertions.
+        for update in updates.into_iter() {
+            let Some(entry) = update.get("insert") else {
+                continue;
+            };
+            let index = entry
+                .get("index")
+                .and_then(JsonValue::as_i64)
+                .ok_or_else(|| anyhow!("neighborhood entry without an index: {entry}"))?;
+            let record = entry
+                .get("key")
+                .cloned()
+                .ok_or_else(|| anyhow!("neighborhood entry without a key: {entry}"))?;
+            self.records.push((index, record));
+        }
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        let page = self.page();
+        self.inner.push_buffer(page.to_string().as_bytes())?;
+        self.inner.batch_end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SnapshotPageEndpoint;
+    use crate::{transport::AsyncErrorCallback, OutputEndpoint};
+    use anyhow::Result as AnyResult;
+    use serde_json::{json, Value as JsonValue};
+    use std::sync::{Arc, Mutex};
+
+    /// Endpoint that stores all buffers it receives.
+    #[derive(Clone, Default)]
+    struct BufferEndpoint(Arc<Mutex<Vec<JsonValue>>>);
+
+    impl OutputEndpoint for BufferEndpoint {
+        fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn max_buffer_size_bytes(&self) -> usize {
+            usize::MAX
+        }
+
+        fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+            self.0.lock().unwrap().push(serde_json::from_slice(buffer)?);
+            Ok(())
+        }
+    }
+
+    /// Feed the neighborhood of records `ids` to a `SnapshotPageEndpoint`
+    /// and return the resulting page.
+    fn page(cursor: Option<u32>, page_size: usize, ids: &[u32]) -> JsonValue {
+        let output = BufferEndpoint::default();
+        let mut endpoint = SnapshotPageEndpoint::new(
+            Box::new(output.clone()),
+            cursor.map(|id| json!({ "id": id })),
+            page_size,
+        );
+
+        // Send entries in reverse order, split across two buffers.
+        let updates: Vec<JsonValue> = ids
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, id)| json!({"insert": {"index": index, "key": {"id": id}}}))
+            .collect();
+        let (first, second) = updates.split_at(updates.len() / 2);
+
+        endpoint.batch_start().unwrap();
+        endpoint
+            .push_buffer(json!(first).to_string().as_bytes())
+            .unwrap();
+        endpoint
+            .push_buffer(json!(second).to_string().as_bytes())
+            .unwrap();
+        endpoint.batch_end().unwrap();
+
+        let mut buffers = output.0.lock().unwrap();
+        assert_eq!(buffers.len(), 1);
+        buffers.pop().unwrap()
+    }
+
+    #[test]
+    fn snapshot_pages() {
+        // First page.
+        assert_eq!(
+            page(None, 2, &[1, 2, 3, 4]),
+            json!({"records": [{"id": 1}, {"id": 2}], "cursor": {"id": 2}})
+        );
+
+        // The last record of the previous page is skipped.
+        assert_eq!(
+            page(Some(2), 2, &[2, 3, 4, 5]),
+            json!({"records": [{"id": 3}, {"id": 4}], "cursor": {"id": 4}})
+        );
+
+        // ...unless it has been deleted.
+        assert_eq!(
+            page(Some(2), 2, &[3, 4, 5]),
+            json!({"records": [{"id": 3}, {"id": 4}], "cursor": {"id": 4}})
+        );
+
+        // Last page.
+        assert_eq!(
+            page(Some(4), 2, &[4, 5, 6]),
+            json!({"records": [{"id": 5}, {"id": 6}], "cursor": null})
+        );
+
+        // Empty page.
+        assert_eq!(
+            page(Some(6), 2, &[]),
+            json!({"records": [], "cursor": null})
+        );
+    }
+}
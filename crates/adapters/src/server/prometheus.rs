@@ -3,25 +3,34 @@ use crate::{
     Controller,
 };
 use anyhow::{Error as AnyError, Result as AnyResult};
-use prometheus::{Encoder, IntGauge, Opts, Registry, TextEncoder};
-use std::{collections::BTreeMap, sync::atomic::Ordering};
+use dbsp::{circuit::metadata::MetaItem, profile::OperatorProfile};
+use prometheus::{Encoder, GaugeVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::{collections::BTreeMap, sync::atomic::Ordering, time::Duration};
+
+/// Labels attached to per-operator metrics.
+const OPERATOR_LABELS: &[&str] = &["operator_id", "operator"];
 
 /// Prometheus metrics of the controller.
 ///
 /// The primary metrics are stored in `controller.status` and are mirrored
-/// to Prometheus metrics on demand.
+/// to Prometheus metrics on demand.  Per-operator metrics are computed from
+/// circuit profiles retrieved from all workers on each scrape.
 pub(crate) struct PrometheusMetrics {
     registry: Registry,
     input_metrics: BTreeMap<EndpointId, InputMetrics>,
     output_metrics: BTreeMap<EndpointId, OutputMetrics>,
+    operator_metrics: OperatorMetrics,
 }
 
 impl PrometheusMetrics {
     pub(crate) fn new(controller: &Controller) -> AnyResult<Self> {
+        let registry = Registry::new();
+        let operator_metrics = OperatorMetrics::new(&registry)?;
         let mut result = Self {
-            registry: Registry::new(),
+            registry,
             input_metrics: BTreeMap::new(),
             output_metrics: BTreeMap::new(),
+            operator_metrics,
         };
 
         let status = controller.status();
@@ -51,6 +60,9 @@ impl PrometheusMetrics {
             self.create_gauge("input_num_transport_errors", &status.endpoint_name)?;
         let num_parse_errors =
             self.create_gauge("input_num_parse_errors", &status.endpoint_name)?;
+        let records_per_sec = self.create_gauge("input_records_per_sec", &status.endpoint_name)?;
+        let bytes_per_sec = self.create_gauge("input_bytes_per_sec", &status.endpoint_name)?;
+        let lag = self.create_gauge("input_lag", &status.endpoint_name)?;
 
         let input_metrics = InputMetrics {
             total_bytes,
@@ -59,6 +71,9 @@ impl PrometheusMetrics {
             buffered_records,
             num_transport_errors,
             num_parse_errors,
+            records_per_sec,
+            bytes_per_sec,
+            lag,
         };
 
         self.input_metrics.insert(endpoint_id, input_metrics);
@@ -92,6 +107,15 @@ impl PrometheusMetrics {
         metrics
             .num_parse_errors
             .set(status.metrics.num_parse_errors.load(Ordering::Acquire) as i64);
+        metrics
+            .records_per_sec
+            .set(status.metrics.records_per_sec.load(Ordering::Acquire) as i64);
+        metrics
+            .bytes_per_sec
+            .set(status.metrics.bytes_per_sec.load(Ordering::Acquire) as i64);
+        // Endpoints that don't report lag export -1.
+        let lag = *status.metrics.lag.lock().unwrap();
+        metrics.lag.set(lag.map_or(-1, |lag| lag as i64));
 
         Ok(())
     }
@@ -113,6 +137,8 @@ impl PrometheusMetrics {
             self.create_gauge("output_num_transport_errors", &status.endpoint_name)?;
         let num_encode_errors =
             self.create_gauge("output_num_encode_errors", &status.endpoint_name)?;
+        let records_per_sec = self.create_gauge("output_records_per_sec", &status.endpoint_name)?;
+        let bytes_per_sec = self.create_gauge("output_bytes_per_sec", &status.endpoint_name)?;
 
         let output_metrics = OutputMetrics {
             transmitted_bytes,
@@ -121,6 +147,8 @@ impl PrometheusMetrics {
             buffered_batches,
             num_transport_errors,
             num_encode_errors,
+            records_per_sec,
+            bytes_per_sec,
         };
 
         self.output_metrics.insert(endpoint_id, output_metrics);
@@ -154,14 +182,29 @@ impl PrometheusMetrics {
         metrics
             .num_encode_errors
             .set(status.metrics.num_encode_errors.load(Ordering::Acquire) as i64);
+        metrics
+            .records_per_sec
+            .set(status.metrics.records_per_sec.load(Ordering::Acquire) as i64);
+        metrics
+            .bytes_per_sec
+            .set(status.metrics.bytes_per_sec.load(Ordering::Acquire) as i64);
 
         Ok(())
     }
 
     /// Extract metrics in the format expected by the Prometheus server.
-    pub(crate) fn metrics(&self, controller: &Controller) -> AnyResult<Vec<u8>> {
+    ///
+    /// `profiles` contains operator profiles of all workers, as returned
+    /// by [`Controller::retrieve_profile`].
+    pub(crate) fn metrics(
+        &self,
+        controller: &Controller,
+        profiles: &[Vec<OperatorProfile>],
+    ) -> AnyResult<Vec<u8>> {
         let status = controller.status();
 
+        self.operator_metrics.update(profiles);
+
         for (endpoint_id, endpoint_status) in status.input_status().iter() {
             self.update_input_metrics(*endpoint_id, endpoint_status)?;
         }
@@ -194,6 +237,9 @@ struct InputMetrics {
     buffered_records: IntGauge,
     num_transport_errors: IntGauge,
     num_parse_errors: IntGauge,
+    records_per_sec: IntGauge,
+    bytes_per_sec: IntGauge,
+    lag: IntGauge,
 }
 
 struct OutputMetrics {
@@ -203,4 +249,130 @@ struct OutputMetrics {
     buffered_batches: IntGauge,
     num_transport_errors: IntGauge,
     num_encode_errors: IntGauge,
+    records_per_sec: IntGauge,
+    bytes_per_sec: IntGauge,
+}
+
+/// Per-operator circuit metrics, aggregated across workers.
+struct OperatorMetrics {
+    /// Number of times the operator has been evaluated.
+    invocations: IntGaugeVec,
+
+    /// Total time spent evaluating the operator.
+    time_seconds: GaugeVec,
+
+    /// Number of records in the operator's state (traces and integrals).
+    state_records: IntGaugeVec,
+
+    /// Number of batches in the operator's state.
+    state_batches: IntGaugeVec,
+}
+
+impl OperatorMetrics {
+    fn new(registry: &Registry) -> AnyResult<Self> {
+        let invocations = IntGaugeVec::new(
+            Opts::new(
+                "operator_invocations",
+                "Number of times the operator has been evaluated",
+            ),
+            OPERATOR_LABELS,
+        )?;
+        let time_seconds = GaugeVec::new(
+            Opts::new(
+                "operator_time_seconds",
+                "Total time spent evaluating the operator",
+            ),
+            OPERATOR_LABELS,
+        )?;
+        let state_records = IntGaugeVec::new(
+            Opts::new(
+                "operator_state_records",
+                "Number of records in the operator's state",
+            ),
+            OPERATOR_LABELS,
+        )?;
+        let state_batches = IntGaugeVec::new(
+            Opts::new(
+                "operator_state_batches",
+                "Number of batches in the operator's state",
+            ),
+            OPERATOR_LABELS,
+        )?;
+
+        registry.register(Box::new(invocations.clone()))?;
+        registry.register(Box::new(time_seconds.clone()))?;
+        registry.register(Box::new(state_records.clone()))?;
+        registry.register(Box::new(state_batches.clone()))?;
+
+        Ok(Self {
+            invocations,
+            time_seconds,
+            state_records,
+            state_batches,
+        })
+    }
+
+    fn update(&self, profiles: &[Vec<OperatorProfile>]) {
+        // Operator metrics are recomputed from scratch on each scrape.
+        self.invocations.reset();
+        self.time_seconds.reset();
+        self.state_records.reset();
+        self.state_batches.reset();
+
+        let mut operators: BTreeMap<String, OperatorTotals> = BTreeMap::new();
+        for profile in profiles.iter().flatten() {
+            let totals =
+                operators
+                    .entry(profile.id.to_string())
+                    .or_insert_with(|| OperatorTotals {
+                        name: &profile.name,
+                        ..Default::default()
+                    });
+            totals.invocations += profile.invocations;
+            totals.time += profile.time;
+
+            for (label, item) in profile.metadata.iter() {
+                match (&**label, item) {
+                    ("total size", MetaItem::Int(records)) => {
+                        totals.state_records = Some(totals.state_records.unwrap_or(0) + records);
+                    }
+                    ("batch sizes", MetaItem::Array(batches)) => {
+                        totals.state_batches =
+                            Some(totals.state_batches.unwrap_or(0) + batches.len());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (id, totals) in operators.iter() {
+            let labels = [id.as_str(), totals.name];
+            self.invocations
+                .with_label_values(&labels)
+                .set(totals.invocations as i64);
+            self.time_seconds
+                .with_label_values(&labels)
+                .set(totals.time.as_secs_f64());
+            if let Some(records) = totals.state_records {
+                self.state_records
+                    .with_label_values(&labels)
+                    .set(records as i64);
+            }
+            if let Some(batches) = totals.state_batches {
+                self.state_batches
+                    .with_label_values(&labels)
+                    .set(batches as i64);
+            }
+        }
+    }
+}
+
+/// Metrics of one operator, summed across workers.
+#[derive(Default)]
+struct OperatorTotals<'a> {
+    name: &'a str,
+    invocations: usize,
+    time: Duration,
+    state_records: Option<usize>,
+    state_batches: Option<usize>,
 }
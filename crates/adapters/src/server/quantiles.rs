@@ -0,0 +1,238 @@
+//! Quantiles of individual columns of a table or view.
+//!
+//! A `quantiles` query with the `columns` argument computes quantiles of the
+//! listed columns rather than of entire records.  The circuit computes record
+//! quantiles by picking every `quantiles`'th element of a uniform random
+//! sample of `quantiles^2` records (see `Stream::stream_key_quantiles` in
+//! `dbsp`).  We follow the same approach for columns: the server requests a
+//! sample of the same size from the circuit, encoded as JSON, and
+//! [`ColumnQuantilesEndpoint`] computes quantiles of each column over the
+//! sample before forwarding them to the client, one chunk per column.
+
+use crate::{column_stats::compare_values, transport::AsyncErrorCallback, OutputEndpoint};
+use anyhow::{anyhow, Result as AnyResult};
+use serde_json::{json, Value as JsonValue};
+use std::{cmp::Ordering, mem::take};
+
+/// Output endpoint that computes quantiles of selected columns over the JSON
+/// records it receives and sends them to the inner endpoint when the batch
+/// ends.
+///
+/// Each column is sent as a separate buffer containing a JSON object of the
+/// form `{"column": "name", "quantiles": [...]}`.
+pub(crate) struct ColumnQuantilesEndpoint {
+    inner: Box<dyn OutputEndpoint>,
+    num_quantiles: usize,
+
+    /// Non-`NULL` values received so far, indexed by column name.
+    columns: Vec<(String, Vec<JsonValue>)>,
+}
+
+impl ColumnQuantilesEndpoint {
+    pub(crate) fn new(
+        inner: Box<dyn OutputEndpoint>,
+        columns: Vec<String>,
+        num_quantiles: usize,
+    ) -> Self {
+        Self {
+            inner,
+            num_quantiles,
+            columns: columns
+                .into_iter()
+                .map(|column| (column, Vec::new()))
+                .collect(),
+        }
+    }
+
+    /// Collect the values of all selected columns in `record`.
+    ///
+    /// Like [column statistics](`crate::ColumnStatistics`), records that
+    /// serialize as arrays (i.e., tuples) use positional column names.
+    fn add_record(&mut self, record: &JsonValue) {
+        for (name, values) in self.columns.iter_mut() {
+            let value = match record {
+                JsonValue::Object(fields) => fields.get(name),
+                JsonValue::Array(fields) => name
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| fields.get(index)),
+                _ => None,
+            };
+            if let Some(value) = value.filter(|value| !value.is_null()) {
+                values.push(value.clone());
+            }
+        }
+    }
+}
+
+impl OutputEndpoint for ColumnQuantilesEndpoint {
+    fn connect(&self, async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        self.inner.connect(async_error_callback)
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        self.inner.max_buffer_size_bytes()
+    }
+
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.inner.batch_start()
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        // The JSON encoder outputs either an array of updates or a single
+        // update per buffer.
+        let updates = match serde_json::from_slice::<JsonValue>(buffer)
+            .map_err(|e| anyhow!("received an invalid JSON string from encoder: {e}"))?
+        {
+            JsonValue::Array(updates) => updates,
+            update => vec![update],
+        };
+
+        // Samples only contain insertions.
+        for update in updates.iter() {
+            if let Some(record) = update.get("insert") {
+                self.add_record(record);
+            }
+        }
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        for (name, values) in self.columns.iter_mut() {
+            let quantiles = quantiles(take(values), self.num_quantiles);
+            let chunk = json!({
+                "column": name,
+                "quantiles": quantiles,
+            });
+            self.inner.push_buffer(chunk.to_string().as_bytes())?;
+        }
+        self.inner.batch_end()
+    }
+}
+
+/// Picks `num_quantiles` values that partition `values` into
+/// `num_quantiles + 1` approximately equal-size quantiles.
+///
+/// Returns fewer values if `values` contains fewer than `num_quantiles`
+/// distinct values.
+fn quantiles(mut values: Vec<JsonValue>, num_quantiles: usize) -> Vec<JsonValue> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    // Decimal columns are encoded as strings; compare them as numbers as
+    // long as all values in the column are numeric.
+    if values
+        .iter()
+        .all(|value| matches!(value, JsonValue::String(s) if s.parse::<f64>().is_ok()))
+    {
+        values.sort_by(|a, b| {
+            let a = a.as_str().unwrap().parse::<f64>().unwrap();
+            let b = b.as_str().unwrap().parse::<f64>().unwrap();
+            a.total_cmp(&b)
+        });
+    } else {
+        values.sort_by(total_order);
+    }
+
+    let mut result: Vec<JsonValue> = (1..=num_quantiles)
+        .map(|i| values[i * values.len() / (num_quantiles + 1)].clone())
+        .collect();
+    result.dedup();
+    result
+}
+
+/// Total order on JSON values: values of different types are ordered by
+/// type, values of the same type by [`compare_values`].
+fn total_order(a: &JsonValue, b: &JsonValue) -> Ordering {
+    fn rank(value: &JsonValue) -> u8 {
+        match value {
+            JsonValue::Null => 0,
+            JsonValue::Bool(_) => 1,
+            JsonValue::Number(_) => 2,
+            JsonValue::String(_) => 3,
+            JsonValue::Array(_) => 4,
+            JsonValue::Object(_) => 5,
+        }
+    }
+
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| compare_values(a, b).unwrap_or(Ordering::Equal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{quantiles, ColumnQuantilesEndpoint};
+    use crate::{transport::AsyncErrorCallback, OutputEndpoint};
+    use anyhow::Result as AnyResult;
+    use serde_json::{json, Value as JsonValue};
+    use std::sync::{Arc, Mutex};
+
+    /// Endpoint that stores all buffers it receives.
+    #[derive(Clone, Default)]
+    struct BufferEndpoint(Arc<Mutex<Vec<JsonValue>>>);
+
+    impl OutputEndpoint for BufferEndpoint {
+        fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn max_buffer_size_bytes(&self) -> usize {
+            usize::MAX
+        }
+
+        fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+            self.0.lock().unwrap().push(serde_json::from_slice(buffer)?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let values = (0..99).rev().map(|i| json!(i)).collect();
+        assert_eq!(quantiles(values, 3), vec![json!(24), json!(49), json!(74)]);
+
+        let values = vec![json!("10.5"), json!("9"), json!("100")];
+        assert_eq!(quantiles(values, 1), vec![json!("10.5")]);
+
+        let values = vec![json!("b"), json!("a"), json!("a")];
+        assert_eq!(quantiles(values, 5), vec![json!("a"), json!("b")]);
+
+        assert!(quantiles(Vec::new(), 10).is_empty());
+    }
+
+    #[test]
+    fn test_column_quantiles_endpoint() {
+        let output = BufferEndpoint::default();
+        let mut endpoint = ColumnQuantilesEndpoint::new(
+            Box::new(output.clone()),
+            vec!["id".to_string(), "s".to_string(), "missing".to_string()],
+            2,
+        );
+
+        endpoint.batch_start().unwrap();
+        endpoint
+            .push_buffer(
+                br#"[{"insert":{"id":3,"s":"c"}},{"insert":{"id":1,"s":null}},{"insert":{"id":2,"s":"a"}}]"#,
+            )
+            .unwrap();
+        endpoint
+            .push_buffer(br#"{"insert":{"id":4,"s":"b"}}"#)
+            .unwrap();
+        endpoint.batch_end().unwrap();
+
+        assert_eq!(
+            *output.0.lock().unwrap(),
+            vec![
+                json!({"column": "id", "quantiles": [2, 3]}),
+                json!({"column": "s", "quantiles": ["b", "c"]}),
+                json!({"column": "missing", "quantiles": []}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! HTTPS support for the pipeline's HTTP server.
+
+use crate::{ControllerError, TlsConfig};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use std::{fs::File, io::BufReader};
+
+/// File in the working directory of the pipeline that contains the
+/// certificate served by the pipeline, in PEM format.  Written before
+/// [`SERVER_PORT_FILE`](`super::SERVER_PORT_FILE`).
+pub const SERVER_CERT_FILE: &str = "cert.pem";
+
+/// Host names that generated certificates are always valid for.
+const DEFAULT_HOSTNAMES: [&str; 2] = ["localhost", "127.0.0.1"];
+
+/// Certificate chain and private key of the server.
+pub(crate) struct ServerIdentity {
+    /// Certificate chain in PEM format, as written to [`SERVER_CERT_FILE`].
+    pub(crate) cert_pem: String,
+    pub(crate) certs: Vec<Certificate>,
+    pub(crate) key: PrivateKey,
+}
+
+impl ServerIdentity {
+    /// Load the certificate and key specified in `config` or generate a
+    /// self-signed certificate if there are none.
+    pub(crate) fn new(config: &TlsConfig) -> Result<Self, ControllerError> {
+        match (&config.cert_path, &config.key_path) {
+            (Some(cert_path), Some(key_path)) => Self::load(cert_path, key_path),
+            (None, None) => Self::generate(&config.hostnames),
+            _ => Err(ControllerError::tls_error(
+                &"'cert_path' and 'key_path' must be specified together",
+            )),
+        }
+    }
+
+    fn load(cert_path: &str, key_path: &str) -> Result<Self, ControllerError> {
+        let cert_pem = std::fs::read_to_string(cert_path).map_err(|e| {
+            ControllerError::io_error(format!("reading TLS certificate file '{cert_path}'"), e)
+        })?;
+        let certs = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+            .map_err(|e| {
+                ControllerError::io_error(format!("parsing TLS certificate file '{cert_path}'"), e)
+            })?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<_>>();
+        if certs.is_empty() {
+            return Err(ControllerError::tls_error(&format!(
+                "'{cert_path}' does not contain any certificates"
+            )));
+        }
+
+        let key = Self::load_key(key_path)?;
+
+        Ok(Self {
+            cert_pem,
+            certs,
+            key,
+        })
+    }
+
+    fn load_key(key_path: &str) -> Result<PrivateKey, ControllerError> {
+        let mut reader = BufReader::new(File::open(key_path).map_err(|e| {
+            ControllerError::io_error(format!("opening TLS key file '{key_path}'"), e)
+        })?);
+
+        loop {
+            match rustls_pemfile::read_one(&mut reader).map_err(|e| {
+                ControllerError::io_error(format!("parsing TLS key file '{key_path}'"), e)
+            })? {
+                Some(rustls_pemfile::Item::PKCS8Key(key))
+                | Some(rustls_pemfile::Item::RSAKey(key))
+                | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+                Some(_) => continue,
+                None => {
+                    return Err(ControllerError::tls_error(&format!(
+                        "'{key_path}' does not contain a private key"
+                    )))
+                }
+            }
+        }
+    }
+
+    fn generate(hostnames: &[String]) -> Result<Self, ControllerError> {
+        let mut names: Vec<String> = DEFAULT_HOSTNAMES.iter().map(|s| s.to_string()).collect();
+        for name in hostnames {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        let cert = rcgen::generate_simple_self_signed(names)
+            .map_err(|e| ControllerError::tls_error(&e))?;
+        let cert_der = cert
+            .serialize_der()
+            .map_err(|e| ControllerError::tls_error(&e))?;
+        let cert_pem = cert
+            .serialize_pem()
+            .map_err(|e| ControllerError::tls_error(&e))?;
+
+        Ok(Self {
+            cert_pem,
+            certs: vec![Certificate(cert_der)],
+            key: PrivateKey(cert.serialize_private_key_der()),
+        })
+    }
+
+    /// Create `rustls` server configuration for this identity.
+    pub(crate) fn server_config(&self) -> Result<ServerConfig, ControllerError> {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(self.certs.clone(), self.key.clone())
+            .map_err(|e| ControllerError::tls_error(&e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServerIdentity;
+    use crate::TlsConfig;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn server_identity() {
+        // Generate a certificate.
+        let generated = ServerIdentity::new(&TlsConfig {
+            hostnames: vec!["pipeline.example.com".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(generated.certs.len(), 1);
+        assert!(generated.cert_pem.contains("BEGIN CERTIFICATE"));
+        generated.server_config().unwrap();
+
+        // Load it back from files.
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let mut cert_file = NamedTempFile::new().unwrap();
+        cert_file
+            .write_all(cert.serialize_pem().unwrap().as_bytes())
+            .unwrap();
+        let mut key_file = NamedTempFile::new().unwrap();
+        key_file
+            .write_all(cert.serialize_private_key_pem().as_bytes())
+            .unwrap();
+
+        let loaded = ServerIdentity::new(&TlsConfig {
+            cert_path: Some(cert_file.path().display().to_string()),
+            key_path: Some(key_file.path().display().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(loaded.certs[0].0, cert.serialize_der().unwrap());
+        loaded.server_config().unwrap();
+
+        // A certificate without a key is an error.
+        assert!(ServerIdentity::new(&TlsConfig {
+            cert_path: Some(cert_file.path().display().to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
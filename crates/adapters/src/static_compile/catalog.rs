@@ -1,17 +1,32 @@
 use crate::{
-    catalog::{NeighborhoodEntry, OutputCollectionHandles, SerCollectionHandle},
+    catalog::{
+        ChangeStatistics, NeighborhoodEntry, NeighborhoodHandles, OutputCollectionHandles,
+        SerCollectionHandle, CHANGES_SUFFIX, NEIGHBORHOOD_SESSIONS,
+    },
     static_compile::{DeScalarHandleImpl, ErasedDeScalarHandle},
-    Catalog,
+    Catalog, ColumnStatsHandle,
 };
 use dbsp::{
     algebra::ZRingValue,
-    operator::{DelayedFeedback, NeighborhoodDescr},
-    CollectionHandle, RootCircuit, Stream, UpsertHandle, ZSet,
+    operator::{sample::DEFAULT_RESERVOIR_CAPACITY, DelayedFeedback, NeighborhoodDescr},
+    trace::{Batch, BatchReader, Cursor},
+    CollectionHandle, OrdZSet, RootCircuit, Stream, UpsertHandle, ZSet,
 };
 use serde::{Deserialize, Serialize};
 
 use super::{DeSetHandle, DeZSetHandle, SerCollectionHandleImpl};
 
+/// A Z-set with one `(inserts, deletes)` record, or an empty Z-set if there
+/// were no changes.
+fn changes_zset(inserts: i64, deletes: i64) -> OrdZSet<(i64, i64), i64> {
+    let changes = if inserts == 0 && deletes == 0 {
+        Vec::new()
+    } else {
+        vec![((inserts, deletes), 1)]
+    };
+    OrdZSet::from_keys((), changes)
+}
+
 impl Catalog {
     /// Add an input stream of Z-sets to the catalog.
     ///
@@ -65,85 +80,312 @@ impl Catalog {
         // Create handle for the stream itself.
         let delta_handle = stream.output();
 
+        // Per-column statistics, updated from the stream of changes.  This is a
+        // no-op until statistics are enabled for the stream.
+        let column_stats_handle = ColumnStatsHandle::new();
+        let column_stats = column_stats_handle.clone();
+        stream.inspect(move |batch| {
+            if !column_stats.is_enabled() {
+                return;
+            }
+            let mut records = Vec::with_capacity(batch.key_count());
+            let mut cursor = batch.cursor();
+            while cursor.key_valid() {
+                if let Ok(record) = serde_json::to_value(D::from(cursor.key().clone())) {
+                    records.push((record, cursor.weight().into()));
+                }
+                cursor.step_key();
+            }
+            column_stats.update(records);
+        });
+
+        // Number of inserts and deletes in each step.  Each worker counts its
+        // own changes; only the per-worker counts are gathered in one worker,
+        // which adds them up, so that each step produces at most one record.
+        let changes_handle = stream
+            .apply(|batch| {
+                let mut inserts = 0;
+                let mut deletes = 0;
+                let mut cursor = batch.cursor();
+                while cursor.key_valid() {
+                    let weight: i64 = cursor.weight().into();
+                    if weight > 0 {
+                        inserts += weight;
+                    } else {
+                        deletes -= weight;
+                    }
+                    cursor.step_key();
+                }
+                changes_zset(inserts, deletes)
+            })
+            .gather(0)
+            .apply(|counts| {
+                let mut inserts = 0;
+                let mut deletes = 0;
+                let mut cursor = counts.cursor();
+                while cursor.key_valid() {
+                    // Workers with identical counts are consolidated into a
+                    // single record whose weight is the number of workers.
+                    let (worker_inserts, worker_deletes) = cursor.key();
+                    let workers = cursor.weight();
+                    inserts += worker_inserts * workers;
+                    deletes += worker_deletes * workers;
+                    cursor.step_key();
+                }
+                changes_zset(inserts, deletes)
+            })
+            .output();
+        self.register_output_collection_handle(
+            &format!("{name}{CHANGES_SUFFIX}"),
+            Box::new(<SerCollectionHandleImpl<_, ChangeStatistics, ()>>::new(
+                changes_handle,
+            )),
+        );
+
         // Improve the odds that `integrate_trace` below reuses the trace of `stream`
         // if one exists.
         let stream = stream.try_sharded_version();
 
-        // Create handles for the neighborhood query.
-        let (neighborhood_descr_stream, neighborhood_descr_handle) =
-            circuit.add_input_stream::<(bool, Option<NeighborhoodDescr<D, ()>>)>();
-        let neighborhood_stream = {
-            // Create a feedback loop to latch the latest neighborhood descriptor
-            // when `reset=true`.
-            let feedback =
-                <DelayedFeedback<RootCircuit, Option<NeighborhoodDescr<Z::Key, ()>>>>::new(
-                    stream.circuit(),
-                );
-            let new_neighborhood =
-                feedback
-                    .stream()
-                    .apply2(&neighborhood_descr_stream, |old, (reset, new)| {
-                        if *reset {
-                            // Convert anchor of type `D` into `Z::Key`.
-                            new.clone().map(|new| {
-                                NeighborhoodDescr::new(
-                                    new.anchor.map(From::from),
-                                    (),
-                                    new.before,
-                                    new.after,
-                                )
-                            })
-                        } else {
-                            old.clone()
-                        }
-                    });
-            feedback.connect(&new_neighborhood);
-            stream.neighborhood(&new_neighborhood)
-        };
+        // Create handles for neighborhood queries, one set per session.
+        let neighborhood_sessions = (0..NEIGHBORHOOD_SESSIONS)
+            .map(|_| {
+                let (neighborhood_descr_stream, neighborhood_descr_handle) =
+                    circuit.add_input_stream::<(bool, Option<NeighborhoodDescr<D, ()>>)>();
+                let neighborhood_stream = {
+                    // Create a feedback loop to latch the latest neighborhood descriptor
+                    // when `reset=true`.
+                    let feedback = <DelayedFeedback<
+                        RootCircuit,
+                        Option<NeighborhoodDescr<Z::Key, ()>>,
+                    >>::new(stream.circuit());
+                    let new_neighborhood = feedback.stream().apply2(
+                        &neighborhood_descr_stream,
+                        |old, (reset, new)| {
+                            if *reset {
+                                // Convert anchor of type `D` into `Z::Key`.
+                                new.clone().map(|new| {
+                                    NeighborhoodDescr::new(
+                                        new.anchor.map(From::from),
+                                        (),
+                                        new.before,
+                                        new.after,
+                                    )
+                                })
+                            } else {
+                                old.clone()
+                            }
+                        },
+                    );
+                    feedback.connect(&new_neighborhood);
+                    // All sessions share the trace of `stream`.
+                    stream.neighborhood(&new_neighborhood)
+                };
+
+                // Neighborhood delta stream.
+                let neighborhood_handle = neighborhood_stream.output();
 
-        // Neighborhood delta stream.
-        let neighborhood_handle = neighborhood_stream.output();
+                // Neighborhood snapshot stream.  The integral computation
+                // is essentially free thanks to stream caching.
+                let neighborhood_snapshot_stream = neighborhood_stream.integrate();
+                let neighborhood_snapshot_handle = neighborhood_snapshot_stream
+                    .output_guarded(&neighborhood_descr_stream.apply(|(reset, _descr)| *reset));
 
-        // Neighborhood snapshot stream.  The integral computation
-        // is essentially free thanks to stream caching.
-        let neighborhood_snapshot_stream = neighborhood_stream.integrate();
-        let neighborhood_snapshot_handle = neighborhood_snapshot_stream
-            .output_guarded(&neighborhood_descr_stream.apply(|(reset, _descr)| *reset));
+                NeighborhoodHandles {
+                    descr_handle: Box::new(DeScalarHandleImpl::new(neighborhood_descr_handle))
+                        as Box<dyn ErasedDeScalarHandle>,
+                    delta_handle: Box::new(
+                        <SerCollectionHandleImpl<_, NeighborhoodEntry<D>, ()>>::new(
+                            neighborhood_handle,
+                        ),
+                    ) as Box<dyn SerCollectionHandle>,
+                    snapshot_handle: Box::new(
+                        <SerCollectionHandleImpl<_, NeighborhoodEntry<D>, ()>>::new(
+                            neighborhood_snapshot_handle,
+                        ),
+                    ) as Box<dyn SerCollectionHandle>,
+                }
+            })
+            .collect();
 
         // Handle for the quantiles query.
         let (num_quantiles_stream, num_quantiles_handle) = circuit.add_input_stream::<usize>();
 
         // Output of the quantiles query, only produced when `num_quantiles>0`.
-        let quantiles_stream = stream
-            .integrate_trace()
-            .stream_key_quantiles(&num_quantiles_stream);
+        let stream_trace = stream.integrate_trace();
+        let quantiles_stream = stream_trace.stream_key_quantiles(&num_quantiles_stream);
         let quantiles_handle = quantiles_stream
             .output_guarded(&num_quantiles_stream.apply(|num_quantiles| *num_quantiles > 0));
 
+        // Handle for the sample query.
+        let (sample_size_stream, sample_size_handle) = circuit.add_input_stream::<usize>();
+
+        // Output of the sample query, only produced when `sample_size>0`.  The
+        // reservoir is maintained incrementally and shares the trace of the
+        // quantiles query.
+        let sample_stream =
+            stream.sample_reservoir(DEFAULT_RESERVOIR_CAPACITY, &sample_size_stream);
+        let sample_handle =
+            sample_stream.output_guarded(&sample_size_stream.apply(|sample_size| *sample_size > 0));
+
         let handles = OutputCollectionHandles {
             delta_handle: Box::new(<SerCollectionHandleImpl<_, D, ()>>::new(delta_handle))
                 as Box<dyn SerCollectionHandle>,
 
-            neighborhood_descr_handle: Some(Box::new(DeScalarHandleImpl::new(
-                neighborhood_descr_handle,
-            )) as Box<dyn ErasedDeScalarHandle>),
-            neighborhood_handle: Some(Box::new(
-                <SerCollectionHandleImpl<_, NeighborhoodEntry<D>, ()>>::new(neighborhood_handle),
-            ) as Box<dyn SerCollectionHandle>),
-            neighborhood_snapshot_handle: Some(Box::new(<SerCollectionHandleImpl<
-                _,
-                NeighborhoodEntry<D>,
-                (),
-            >>::new(
-                neighborhood_snapshot_handle
-            )) as Box<dyn SerCollectionHandle>),
+            neighborhood_sessions,
 
             num_quantiles_handle: Some(num_quantiles_handle),
             quantiles_handle: Some(Box::new(<SerCollectionHandleImpl<_, D, ()>>::new(
                 quantiles_handle,
             )) as Box<dyn SerCollectionHandle>),
+
+            sample_size_handle: Some(sample_size_handle),
+            sample_handle: Some(
+                Box::new(<SerCollectionHandleImpl<_, D, ()>>::new(sample_handle))
+                    as Box<dyn SerCollectionHandle>,
+            ),
+
+            column_stats_handle: Some(column_stats_handle),
         };
 
         self.output_batch_handles.insert(name.to_owned(), handles);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        catalog::RecordFormat, test::TestStruct, Catalog, CircuitCatalog, ColumnDescr, OutputQuery,
+        RelationDescr, RelationKind,
+    };
+    use dbsp::Runtime;
+
+    const NUM_WORKERS: usize = 4;
+
+    fn test_struct(id: u32) -> TestStruct {
+        TestStruct {
+            id,
+            b: false,
+            i: None,
+            s: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn change_statistics() {
+        let (mut dbsp, (catalog, input_handle)) = Runtime::init_circuit(NUM_WORKERS, |circuit| {
+            let mut catalog = Catalog::new();
+            let (input, input_handle) = circuit.add_input_zset::<TestStruct, i32>();
+            catalog.register_output_zset("test_output", input);
+            Ok((catalog, input_handle))
+        })
+        .unwrap();
+
+        let changes = &catalog
+            .output_handles("test_output__changes")
+            .unwrap()
+            .delta_handle;
+        let mut step = |updates: Vec<(TestStruct, i32)>| {
+            for (record, weight) in updates {
+                input_handle.push(record, weight);
+            }
+            dbsp.step().unwrap();
+
+            let batch = changes.consolidate();
+            let mut cursor = batch
+                .cursor(RecordFormat::Json(Default::default()))
+                .unwrap();
+            let mut records = Vec::new();
+            while cursor.key_valid() {
+                let mut buf = Vec::new();
+                cursor.serialize_key(&mut buf).unwrap();
+                records.push((String::from_utf8(buf).unwrap(), cursor.weight()));
+                cursor.step_key();
+            }
+            records
+        };
+
+        assert_eq!(
+            step((0..10).map(|id| (test_struct(id), 1)).collect()),
+            vec![(r#"{"inserts":10,"deletes":0}"#.to_string(), 1)]
+        );
+        assert_eq!(
+            step(vec![
+                (test_struct(0), -1),
+                (test_struct(1), -2),
+                (test_struct(10), 1)
+            ]),
+            vec![(r#"{"inserts":1,"deletes":3}"#.to_string(), 1)]
+        );
+
+        // Steps that don't modify the collection don't produce statistics.
+        assert_eq!(step(Vec::new()), Vec::new());
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn relations() {
+        let (dbsp, mut catalog) = Runtime::init_circuit(NUM_WORKERS, |circuit| {
+            let mut catalog = Catalog::new();
+            let (input, input_handle) = circuit.add_input_zset::<TestStruct, i32>();
+            catalog.register_input_zset("test_input", input.clone(), input_handle);
+            catalog.register_output_zset("test_output", input);
+            Ok(catalog)
+        })
+        .unwrap();
+
+        // Schema names don't have to match the case of stream names.
+        catalog
+            .set_schema_json(
+                r#"{"inputs": [{"name": "TEST_INPUT", "fields": [
+                    {"name": "id", "columntype": {"type": "INTEGER", "nullable": false}},
+                    {"name": "s", "columntype": {"type": "VARCHAR", "nullable": true}}
+                ]}], "outputs": []}"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            catalog.relations(),
+            vec![
+                RelationDescr {
+                    name: "test_input".to_string(),
+                    kind: RelationKind::Table,
+                    columns: Some(vec![
+                        ColumnDescr {
+                            name: "id".to_string(),
+                            sql_type: "INTEGER".to_string(),
+                            nullable: false,
+                        },
+                        ColumnDescr {
+                            name: "s".to_string(),
+                            sql_type: "VARCHAR".to_string(),
+                            nullable: true,
+                        },
+                    ]),
+                    queries: Vec::new(),
+                },
+                RelationDescr {
+                    name: "test_output".to_string(),
+                    kind: RelationKind::View,
+                    columns: None,
+                    queries: vec![
+                        OutputQuery::Table,
+                        OutputQuery::Neighborhood,
+                        OutputQuery::Quantiles,
+                        OutputQuery::Sample,
+                    ],
+                },
+                RelationDescr {
+                    name: "test_output__changes".to_string(),
+                    kind: RelationKind::View,
+                    columns: None,
+                    queries: vec![OutputQuery::Table],
+                },
+            ]
+        );
+
+        assert!(catalog.set_schema_json("not a schema").is_err());
+
+        dbsp.kill().unwrap();
+    }
+}
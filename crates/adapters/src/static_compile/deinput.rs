@@ -1,6 +1,6 @@
 use crate::{
-    catalog::{DeCollectionStream, RecordFormat},
-    format::byte_record_deserializer,
+    catalog::{CsvFlavor, DeCollectionStream, RecordFormat},
+    format::{byte_record_deserializer, LenientNumbers},
     ControllerError, DeCollectionHandle,
 };
 use anyhow::{anyhow, Result as AnyResult};
@@ -11,8 +11,9 @@ use std::{collections::VecDeque, marker::PhantomData};
 
 /// A deserializer that parses byte arrays into a strongly typed representation.
 pub trait DeserializerFromBytes {
-    /// Create an instance of a deserializer.
-    fn create() -> Self;
+    /// Create a new instance of the deserializer with the same configuration
+    /// as `self`.
+    fn fork(&self) -> Self;
 
     /// Parse an object of type `T` from `data`.
     fn deserialize<T>(&mut self, data: &[u8]) -> AnyResult<T>
@@ -27,18 +28,27 @@ pub struct CsvDeserializerFromBytes {
     reader: csv::Reader<VecDeque<u8>>,
     // Byte record to read CSV records into.
     record: csv::ByteRecord,
+    flavor: CsvFlavor,
 }
 
-impl DeserializerFromBytes for CsvDeserializerFromBytes {
-    fn create() -> Self {
+impl CsvDeserializerFromBytes {
+    /// Create a deserializer for CSV records encoded using `flavor`.
+    pub fn new(flavor: CsvFlavor) -> Self {
         CsvDeserializerFromBytes {
             reader: csv::ReaderBuilder::new()
                 .has_headers(false)
                 .flexible(true)
                 .from_reader(VecDeque::new()),
             record: csv::ByteRecord::new(),
+            flavor,
         }
     }
+}
+
+impl DeserializerFromBytes for CsvDeserializerFromBytes {
+    fn fork(&self) -> Self {
+        Self::new(self.flavor)
+    }
     fn deserialize<T>(&mut self, data: &[u8]) -> AnyResult<T>
     where
         T: for<'de> Deserialize<'de>,
@@ -47,8 +57,11 @@ impl DeserializerFromBytes for CsvDeserializerFromBytes {
         self.reader.get_mut().extend(data.iter());
         self.reader.read_byte_record(&mut self.record)?;
 
-        T::deserialize(&mut byte_record_deserializer(&self.record, None))
-            .map_err(|e| anyhow!(e.to_string()))
+        T::deserialize(
+            &mut byte_record_deserializer(&self.record, None)
+                .with_binary_encoding(self.flavor.binary_encoding),
+        )
+        .map_err(|e| anyhow!(e.to_string()))
     }
 }
 
@@ -56,15 +69,18 @@ impl DeserializerFromBytes for CsvDeserializerFromBytes {
 pub struct JsonDeserializerFromBytes;
 
 impl DeserializerFromBytes for JsonDeserializerFromBytes {
-    fn create() -> Self {
+    fn fork(&self) -> Self {
         JsonDeserializerFromBytes
     }
     fn deserialize<T>(&mut self, data: &[u8]) -> AnyResult<T>
     where
         T: for<'de> Deserialize<'de>,
     {
-        T::deserialize(&mut serde_json::Deserializer::from_slice(data))
-            .map_err(|e| anyhow!(e.to_string()))
+        // Accept 64-bit integers encoded as either JSON numbers or strings.
+        T::deserialize(LenientNumbers(&mut serde_json::Deserializer::from_slice(
+            data,
+        )))
+        .map_err(|e| anyhow!(e.to_string()))
     }
 }
 
@@ -190,12 +206,14 @@ where
         record_format: RecordFormat,
     ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
         match record_format {
-            RecordFormat::Csv => Ok(Box::new(
-                DeZSetStream::<CsvDeserializerFromBytes, K, D, R>::new(self.handle.clone()),
-            )),
-            RecordFormat::Json => Ok(Box::new(
-                DeZSetStream::<JsonDeserializerFromBytes, K, D, R>::new(self.handle.clone()),
-            )),
+            RecordFormat::Csv(flavor) => Ok(Box::new(DeZSetStream::<_, K, D, R>::new(
+                self.handle.clone(),
+                CsvDeserializerFromBytes::new(flavor),
+            ))),
+            RecordFormat::Json(_) => Ok(Box::new(DeZSetStream::<_, K, D, R>::new(
+                self.handle.clone(),
+                JsonDeserializerFromBytes,
+            ))),
         }
     }
 }
@@ -221,11 +239,11 @@ impl<De, K, D, R> DeZSetStream<De, K, D, R>
 where
     De: DeserializerFromBytes,
 {
-    pub fn new(handle: CollectionHandle<K, R>) -> Self {
+    pub fn new(handle: CollectionHandle<K, R>, deserializer: De) -> Self {
         Self {
             updates: Vec::new(),
             handle,
-            deserializer: De::create(),
+            deserializer,
             phantom: PhantomData,
         }
     }
@@ -273,7 +291,7 @@ where
     }
 
     fn fork(&self) -> Box<dyn DeCollectionStream> {
-        Box::new(Self::new(self.handle.clone()))
+        Box::new(Self::new(self.handle.clone(), self.deserializer.fork()))
     }
 }
 
@@ -301,12 +319,14 @@ where
         record_format: RecordFormat,
     ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
         match record_format {
-            RecordFormat::Csv => Ok(Box::new(
-                DeSetStream::<CsvDeserializerFromBytes, K, D>::new(self.handle.clone()),
-            )),
-            RecordFormat::Json => Ok(Box::new(
-                DeSetStream::<JsonDeserializerFromBytes, K, D>::new(self.handle.clone()),
-            )),
+            RecordFormat::Csv(flavor) => Ok(Box::new(DeSetStream::<_, K, D>::new(
+                self.handle.clone(),
+                CsvDeserializerFromBytes::new(flavor),
+            ))),
+            RecordFormat::Json(_) => Ok(Box::new(DeSetStream::<_, K, D>::new(
+                self.handle.clone(),
+                JsonDeserializerFromBytes,
+            ))),
         }
     }
 }
@@ -333,11 +353,11 @@ impl<De, K, D> DeSetStream<De, K, D>
 where
     De: DeserializerFromBytes,
 {
-    pub fn new(handle: UpsertHandle<K, bool>) -> Self {
+    pub fn new(handle: UpsertHandle<K, bool>, deserializer: De) -> Self {
         Self {
             updates: Vec::new(),
             handle,
-            deserializer: De::create(),
+            deserializer,
             phantom: PhantomData,
         }
     }
@@ -378,7 +398,7 @@ where
     }
 
     fn fork(&self) -> Box<dyn DeCollectionStream> {
-        Box::new(Self::new(self.handle.clone()))
+        Box::new(Self::new(self.handle.clone(), self.deserializer.fork()))
     }
 }
 
@@ -404,18 +424,16 @@ where
         record_format: RecordFormat,
     ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
         match record_format {
-            RecordFormat::Csv => Ok(Box::new(
-                DeMapStream::<CsvDeserializerFromBytes, K, V, F>::new(
-                    self.handle.clone(),
-                    self.key_func.clone(),
-                ),
-            )),
-            RecordFormat::Json => Ok(Box::new(
-                DeMapStream::<JsonDeserializerFromBytes, K, V, F>::new(
-                    self.handle.clone(),
-                    self.key_func.clone(),
-                ),
-            )),
+            RecordFormat::Csv(flavor) => Ok(Box::new(DeMapStream::new(
+                self.handle.clone(),
+                self.key_func.clone(),
+                CsvDeserializerFromBytes::new(flavor),
+            ))),
+            RecordFormat::Json(_) => Ok(Box::new(DeMapStream::new(
+                self.handle.clone(),
+                self.key_func.clone(),
+                JsonDeserializerFromBytes,
+            ))),
         }
     }
 }
@@ -443,12 +461,12 @@ impl<De, K, V, F> DeMapStream<De, K, V, F>
 where
     De: DeserializerFromBytes,
 {
-    pub fn new(handle: UpsertHandle<K, Option<V>>, key_func: F) -> Self {
+    pub fn new(handle: UpsertHandle<K, Option<V>>, key_func: F, deserializer: De) -> Self {
         Self {
             updates: Vec::new(),
             key_func,
             handle,
-            deserializer: De::create(),
+            deserializer,
         }
     }
 }
@@ -490,7 +508,11 @@ where
     }
 
     fn fork(&self) -> Box<dyn DeCollectionStream> {
-        Box::new(Self::new(self.handle.clone(), self.key_func.clone()))
+        Box::new(Self::new(
+            self.handle.clone(),
+            self.key_func.clone(),
+            self.deserializer.fork(),
+        ))
     }
 }
 
@@ -666,15 +688,15 @@ mod test {
     ) {
         let mut zset_stream = input_handles
             .0
-            .configure_deserializer(RecordFormat::Csv)
+            .configure_deserializer(RecordFormat::Csv(Default::default()))
             .unwrap();
         let mut set_stream = input_handles
             .1
-            .configure_deserializer(RecordFormat::Csv)
+            .configure_deserializer(RecordFormat::Csv(Default::default()))
             .unwrap();
         let mut map_stream = input_handles
             .2
-            .configure_deserializer(RecordFormat::Csv)
+            .configure_deserializer(RecordFormat::Csv(Default::default()))
             .unwrap();
 
         let zset_output = &output_handles.0;
@@ -754,15 +776,15 @@ mod test {
     ) {
         let mut zset_input = input_handles
             .0
-            .configure_deserializer(RecordFormat::Json)
+            .configure_deserializer(RecordFormat::Json(Default::default()))
             .unwrap();
         let mut set_input = input_handles
             .1
-            .configure_deserializer(RecordFormat::Json)
+            .configure_deserializer(RecordFormat::Json(Default::default()))
             .unwrap();
         let mut map_input = input_handles
             .2
-            .configure_deserializer(RecordFormat::Json)
+            .configure_deserializer(RecordFormat::Json(Default::default()))
             .unwrap();
 
         let zset_output = &output_handles.0;
@@ -1,5 +1,6 @@
 use crate::{
-    catalog::{RecordFormat, SerBatch, SerCollectionHandle, SerCursor},
+    catalog::{CsvFlavor, RecordFormat, SerBatch, SerCollectionHandle, SerCursor},
+    format::{AsText, BinaryAsText, BinaryEncoding, LargeNumbersAsStrings},
     ControllerError,
 };
 use anyhow::Result as AnyResult;
@@ -56,48 +57,71 @@ where
 
 /// A serializer that encodes values to a byte array.
 trait BytesSerializer: Send {
-    fn create() -> Self;
     fn serialize<T: Serialize>(&mut self, val: &T, buf: &mut Vec<u8>) -> AnyResult<()>;
 }
 
 struct CsvSerializer {
     writer: CsvWriter<SwappableWrite<Vec<u8>>>,
+    flavor: CsvFlavor,
 }
 
-impl BytesSerializer for CsvSerializer {
-    fn create() -> Self {
+impl CsvSerializer {
+    fn new(flavor: CsvFlavor) -> Self {
         Self {
             writer: CsvWriterBuilder::new()
                 .has_headers(false)
                 .flexible(true)
                 .from_writer(SwappableWrite::new()),
+            flavor,
         }
     }
+}
 
+impl BytesSerializer for CsvSerializer {
     fn serialize<T>(&mut self, val: &T, buf: &mut Vec<u8>) -> AnyResult<()>
     where
         T: Serialize,
     {
         let owned_buf = std::mem::take(buf);
         self.writer.get_ref().swap(Some(owned_buf));
-        let res = self.writer.serialize(val);
+        let res = self
+            .writer
+            .serialize(AsText::new(val, self.flavor.binary_encoding));
         let _ = self.writer.flush();
         *buf = self.writer.get_ref().swap(None).unwrap();
         Ok(res?)
     }
 }
 
+/// JSON serializer that encodes binary values as base64 strings.
 struct JsonSerializer;
 
 impl BytesSerializer for JsonSerializer {
-    fn create() -> Self {
-        Self
+    fn serialize<T>(&mut self, val: &T, buf: &mut Vec<u8>) -> AnyResult<()>
+    where
+        T: Serialize,
+    {
+        val.serialize(BinaryAsText::new(
+            &mut serde_json::Serializer::new(buf),
+            BinaryEncoding::Base64,
+        ))?;
+        Ok(())
     }
+}
+
+/// JSON serializer that encodes 64-bit integers as strings and binary
+/// values as base64 strings.
+struct JsonLargeNumbersAsStringsSerializer;
+
+impl BytesSerializer for JsonLargeNumbersAsStringsSerializer {
     fn serialize<T>(&mut self, val: &T, buf: &mut Vec<u8>) -> AnyResult<()>
     where
         T: Serialize,
     {
-        serde_json::to_writer(buf, val)?;
+        val.serialize(LargeNumbersAsStrings(BinaryAsText::new(
+            &mut serde_json::Serializer::new(buf),
+            BinaryEncoding::Base64,
+        )))?;
         Ok(())
     }
 }
@@ -198,11 +222,19 @@ where
         record_format: RecordFormat,
     ) -> Result<Box<dyn SerCursor + 'a>, ControllerError> {
         Ok(match record_format {
-            RecordFormat::Csv => Box::new(<SerCursorImpl<'a, CsvSerializer, B, KD, VD>>::new(
+            RecordFormat::Csv(flavor) => Box::new(<SerCursorImpl<'a, _, B, KD, VD>>::new(
                 &self.batch,
+                CsvSerializer::new(flavor),
             )),
-            RecordFormat::Json => Box::new(<SerCursorImpl<'a, JsonSerializer, B, KD, VD>>::new(
+            RecordFormat::Json(flavor) if flavor.large_numbers_as_strings => {
+                Box::new(<SerCursorImpl<'a, _, B, KD, VD>>::new(
+                    &self.batch,
+                    JsonLargeNumbersAsStringsSerializer,
+                ))
+            }
+            RecordFormat::Json(_) => Box::new(<SerCursorImpl<'a, _, B, KD, VD>>::new(
                 &self.batch,
+                JsonSerializer,
             )),
         })
     }
@@ -228,12 +260,12 @@ where
     KD: From<B::Key> + Serialize,
     VD: From<B::Val> + Serialize,
 {
-    pub fn new(batch: &'a B) -> Self {
+    pub fn new(batch: &'a B, serializer: Ser) -> Self {
         let cursor = batch.cursor();
 
         let mut result = Self {
             cursor,
-            serializer: Ser::create(),
+            serializer,
             key: None,
             val: None,
             phantom: PhantomData,
@@ -79,12 +79,14 @@ where
         record_format: RecordFormat,
     ) -> Result<Box<dyn DeCollectionStream>, ControllerError> {
         match record_format {
-            RecordFormat::Csv => Ok(Box::new(
-                MockDeZSetStream::<CsvDeserializerFromBytes, T>::new(self.clone()),
-            )),
-            RecordFormat::Json => Ok(Box::new(
-                MockDeZSetStream::<JsonDeserializerFromBytes, T>::new(self.clone()),
-            )),
+            RecordFormat::Csv(flavor) => Ok(Box::new(MockDeZSetStream::new(
+                self.clone(),
+                CsvDeserializerFromBytes::new(flavor),
+            ))),
+            RecordFormat::Json(_) => Ok(Box::new(MockDeZSetStream::new(
+                self.clone(),
+                JsonDeserializerFromBytes,
+            ))),
         }
     }
 }
@@ -99,10 +101,10 @@ impl<De, T> MockDeZSetStream<De, T>
 where
     De: DeserializerFromBytes,
 {
-    pub fn new(handle: MockDeZSet<T>) -> Self {
+    pub fn new(handle: MockDeZSet<T>, deserializer: De) -> Self {
         Self {
             handle,
-            deserializer: De::create(),
+            deserializer,
         }
     }
 }
@@ -138,6 +140,6 @@ where
     }
 
     fn fork(&self) -> Box<dyn DeCollectionStream> {
-        Box::new(Self::new(self.handle.clone()))
+        Box::new(Self::new(self.handle.clone(), self.deserializer.fork()))
     }
 }
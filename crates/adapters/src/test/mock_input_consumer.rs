@@ -2,6 +2,7 @@ use crate::{
     controller::FormatConfig, DeCollectionHandle, InputConsumer, InputFormat, ParseError, Parser,
 };
 use anyhow::{anyhow, Error as AnyError};
+use serde_json::Value as JsonValue;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 pub type ErrorCallback = Box<dyn FnMut(&AnyError) + Send>;
@@ -20,6 +21,9 @@ pub struct MockInputConsumerState {
     /// The last result returned by the parser.
     pub parser_result: Option<(usize, Vec<ParseError>)>,
 
+    /// The last transport metrics reported by the endpoint.
+    pub transport_metrics: Option<JsonValue>,
+
     /// Parser to push data to.
     parser: Box<dyn Parser>,
 
@@ -36,6 +40,7 @@ impl MockInputConsumerState {
             eoi: false,
             endpoint_error: None,
             parser_result: None,
+            transport_metrics: None,
             parser,
             error_cb: None,
         }
@@ -58,6 +63,7 @@ impl MockInputConsumerState {
         self.eoi = false;
         self.endpoint_error = None;
         self.parser_result = None;
+        self.transport_metrics = None;
     }
 }
 
@@ -148,6 +154,10 @@ impl InputConsumer for MockInputConsumer {
         errors
     }
 
+    fn transport_metrics(&mut self, metrics: JsonValue) {
+        self.state().transport_metrics = Some(metrics);
+    }
+
     fn fork(&self) -> Box<dyn InputConsumer> {
         Box::new(self.clone())
     }
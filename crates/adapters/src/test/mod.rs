@@ -1,14 +1,17 @@
 //! Test framework for the `adapters` crate.
 
 use crate::{
-    controller::InputEndpointConfig, Catalog, CircuitCatalog, DbspCircuitHandle, FormatConfig,
-    InputEndpoint, InputTransport,
+    controller::InputEndpointConfig, Catalog, CircuitCatalog, Controller, DbspCircuitHandle,
+    FormatConfig, InputEndpoint, InputTransport, PipelineConfig,
 };
 use anyhow::Result as AnyResult;
+use csv::ReaderBuilder as CsvReaderBuilder;
 use dbsp::Runtime;
 use log::{Log, Metadata, Record};
 use serde::Deserialize;
 use std::{
+    path::Path,
+    sync::mpsc::channel,
     thread::sleep,
     time::{Duration, Instant},
 };
@@ -132,3 +135,112 @@ pub fn test_circuit(workers: usize) -> (Box<dyn DbspCircuitHandle>, Box<dyn Circ
     .unwrap();
     (Box::new(circuit), Box::new(catalog))
 }
+
+/// Create a test circuit whose output depends on the state of the circuit: it
+/// outputs the integral of the input stream.
+pub fn checkpoint_test_circuit(
+    workers: usize,
+) -> (Box<dyn DbspCircuitHandle>, Box<dyn CircuitCatalog>) {
+    let (circuit, catalog) = Runtime::init_circuit(workers, |circuit| {
+        let mut catalog = Catalog::new();
+        let (input, hinput) = circuit.add_input_zset::<TestStruct, i32>();
+
+        catalog.register_input_zset("test_input1", input.clone(), hinput);
+        catalog.register_output_zset("test_output1", input.integrate());
+
+        Ok(catalog)
+    })
+    .unwrap();
+    (Box::new(circuit), Box::new(catalog))
+}
+
+/// Run a pipeline built from [`checkpoint_test_circuit`] with checkpoints
+/// stored under `dir`, restoring it from the latest checkpoint, if any.
+///
+/// Performs one step once `inputs` records have been received from `input`,
+/// optionally takes a checkpoint, and returns the records written to the
+/// output in the step.
+pub fn run_checkpoint_pipeline(
+    input: &InputEndpointConfig,
+    dir: &Path,
+    inputs: usize,
+    checkpoint: bool,
+) -> Vec<(TestStruct, i32)> {
+    let output_path = dir.join("output.csv");
+    let mut config: PipelineConfig = serde_yaml::from_str(&format!(
+        r#"
+name: test
+workers: 4
+checkpoint:
+    location: {:?}
+inputs: {{}}
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+        dir.join("checkpoints").to_str().unwrap(),
+        output_path.to_str().unwrap(),
+    ))
+    .unwrap();
+    config.inputs.insert("test_input1".into(), input.clone());
+
+    let controller = Controller::with_config(
+        |workers| Ok(checkpoint_test_circuit(workers)),
+        &config,
+        Box::new(|e| panic!("error: {e}")),
+    )
+    .unwrap();
+    controller.set_manual_stepping(true);
+    controller.start();
+
+    wait(
+        || controller.status().num_buffered_input_records() == inputs as u64,
+        Some(20_000),
+    )
+    .expect("timeout waiting for inputs");
+    let (sender, receiver) = channel();
+    controller.step_with_stats(Box::new(move |stats| sender.send(stats).unwrap()));
+    let stats = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(stats.input_records, inputs as u64);
+    wait(
+        || {
+            controller
+                .status()
+                .output_status()
+                .get(&0)
+                .unwrap()
+                .transmitted_records()
+                == stats.output_records
+        },
+        Some(10_000),
+    )
+    .expect("timeout waiting for outputs");
+
+    if checkpoint {
+        let (sender, receiver) = channel();
+        controller.checkpoint(Box::new(move |result| {
+            sender.send(result.map_err(|e| e.to_string())).unwrap()
+        }));
+        receiver
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .unwrap();
+    }
+    controller.stop().unwrap();
+
+    let mut output: Vec<_> = CsvReaderBuilder::new()
+        .has_headers(false)
+        .from_path(&output_path)
+        .unwrap()
+        .deserialize::<(TestStruct, i32)>()
+        .map(Result::unwrap)
+        .collect();
+    output.sort();
+    output
+}
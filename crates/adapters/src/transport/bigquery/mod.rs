@@ -0,0 +1,8 @@
+mod output;
+mod rows;
+
+mod proto {
+    tonic::include_proto!("google.cloud.bigquery.storage.v1");
+}
+
+pub use output::{BigQueryOutputConfig, BigQueryOutputTransport};
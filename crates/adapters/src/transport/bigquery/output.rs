@@ -0,0 +1,502 @@
+use super::{
+    proto::{
+        append_rows_request::{ProtoData, Rows},
+        append_rows_response::Response as AppendResponse,
+        big_query_write_client::BigQueryWriteClient,
+        write_stream::Type as WriteStreamType,
+        AppendRowsRequest, BatchCommitWriteStreamsRequest, CreateWriteStreamRequest,
+        FinalizeWriteStreamRequest, ProtoRows, ProtoSchema, WriteStream,
+    },
+    rows::RowEncoder,
+};
+use crate::{AsyncErrorCallback, OutputEndpoint, OutputEndpointConfig, OutputTransport};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use log::{debug, info};
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Runtime;
+use tonic::{
+    metadata::MetadataValue,
+    transport::{Channel, ClientTlsConfig},
+    Request,
+};
+use utoipa::ToSchema;
+
+/// Maximal total size of rows sent in one `AppendRows` request.  The API
+/// limits requests to 10MB.
+const MAX_APPEND_REQUEST_BYTES: usize = 9 * 1024 * 1024;
+
+/// URL of the access token of the default service account on Google Cloud
+/// compute instances.
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Refresh access tokens obtained from the metadata server this long before
+/// they expire.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// [`OutputTransport`] implementation that writes to a BigQuery table using
+/// the BigQuery Storage Write API.
+///
+/// The endpoint must be configured with the `json` format.  All records
+/// produced by the pipeline in one step are appended to a new pending write
+/// stream, which is committed atomically at the end of the step, so each
+/// step becomes visible in the table exactly once, in its entirety.
+///
+/// Columns of the view are matched to columns of the table by name.  Values
+/// are converted to the type of the table column, which should be chosen
+/// according to the SQL type of the view column:
+///
+/// | SQL type                   | BigQuery type           |
+/// |----------------------------|-------------------------|
+/// | `BOOLEAN`                  | `BOOL`                  |
+/// | `TINYINT` ... `BIGINT`     | `INT64`                 |
+/// | `REAL`, `DOUBLE`           | `FLOAT64`               |
+/// | `DECIMAL`                  | `NUMERIC`, `BIGNUMERIC` |
+/// | `CHAR`, `VARCHAR`          | `STRING`                |
+/// | `BINARY`, `VARBINARY`      | `BYTES`                 |
+/// | `DATE`                     | `DATE`                  |
+/// | `TIME`                     | `TIME`                  |
+/// | `TIMESTAMP`                | `TIMESTAMP`, `DATETIME` |
+/// | `ARRAY`                    | `REPEATED` column       |
+///
+/// The table is written as an append-only log.  Views that delete records
+/// require a `change_type_column`, which records whether each row was
+/// inserted or deleted.
+///
+/// This output transport is only available if the crate is configured with
+/// `with-bigquery` feature.
+///
+/// The output transport factory gives this transport the name `bigquery`.
+pub struct BigQueryOutputTransport;
+
+impl OutputTransport for BigQueryOutputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("bigquery")
+    }
+
+    /// Creates a new [`OutputEndpoint`] for writing to a BigQuery table,
+    /// interpreting `config` as a [`BigQueryOutputConfig`].
+    ///
+    /// See [`OutputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(
+        &self,
+        _name: &str,
+        config: &OutputEndpointConfig,
+    ) -> AnyResult<Box<dyn OutputEndpoint>> {
+        let config = BigQueryOutputConfig::deserialize(&config.connector_config.transport.config)?;
+        let ep = BigQueryOutputEndpoint::new(config)?;
+
+        Ok(Box::new(ep))
+    }
+}
+
+fn default_endpoint() -> String {
+    "https://bigquerystorage.googleapis.com".to_string()
+}
+
+/// Configuration for writing to a BigQuery table with
+/// [`BigQueryOutputTransport`].
+#[derive(Clone, Deserialize, ToSchema)]
+pub struct BigQueryOutputConfig {
+    /// Google Cloud project that contains the dataset.
+    pub project_id: String,
+
+    /// BigQuery dataset that contains the table.
+    pub dataset: String,
+
+    /// Name of the table to write to.
+    pub table: String,
+
+    /// OAuth 2.0 access token with BigQuery write permission.  When not
+    /// specified, tokens of the default service account are obtained from
+    /// the metadata server of the Google Cloud compute instance.
+    pub access_token: Option<String>,
+
+    /// STRING column set to `insert` or `delete` for each row written to
+    /// the table.  Required for views that delete records.
+    pub change_type_column: Option<String>,
+
+    /// BigQuery Storage API endpoint.  Defaults to
+    /// `https://bigquerystorage.googleapis.com`.
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+}
+
+impl BigQueryOutputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.project_id.is_empty() || self.dataset.is_empty() || self.table.is_empty() {
+            bail!(
+                "BigQuery output endpoint configuration must specify a project, dataset, and table"
+            );
+        }
+        Ok(())
+    }
+
+    /// Resource name of the table.
+    fn table_path(&self) -> String {
+        format!(
+            "projects/{}/datasets/{}/tables/{}",
+            self.project_id, self.dataset, self.table
+        )
+    }
+}
+
+/// Attach the routing header expected by the BigQuery API to a request.
+fn request<T>(message: T, params: &str) -> AnyResult<Request<T>> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("x-goog-request-params", MetadataValue::try_from(params)?);
+    Ok(request)
+}
+
+/// Split `rows` into chunks that fit in an `AppendRows` request.
+fn chunk_rows(rows: Vec<Vec<u8>>) -> Vec<Vec<Vec<u8>>> {
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    let mut chunk_size = 0;
+    for row in rows.into_iter() {
+        if !chunk.is_empty() && chunk_size + row.len() > MAX_APPEND_REQUEST_BYTES {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_size = 0;
+        }
+        chunk_size += row.len();
+        chunk.push(row);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Write `records` to the table with a pending write stream and commit the
+/// stream.
+///
+/// `encoder` is created from the table schema returned when creating the
+/// first stream.
+async fn write_batch(
+    config: &BigQueryOutputConfig,
+    channel: Channel,
+    token: &str,
+    encoder: &mut Option<RowEncoder>,
+    records: Vec<(JsonMap<String, JsonValue>, bool)>,
+) -> AnyResult<()> {
+    let authorization = MetadataValue::try_from(format!("Bearer {token}"))?;
+    let mut client = BigQueryWriteClient::with_interceptor(
+        channel,
+        move |mut request: Request<()>| -> Result<Request<()>, tonic::Status> {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+            Ok(request)
+        },
+    );
+
+    let table = config.table_path();
+    let stream = client
+        .create_write_stream(request(
+            CreateWriteStreamRequest {
+                parent: table.clone(),
+                write_stream: Some(WriteStream {
+                    r#type: WriteStreamType::Pending as i32,
+                    ..Default::default()
+                }),
+            },
+            &format!("parent={table}"),
+        )?)
+        .await?
+        .into_inner();
+
+    if encoder.is_none() {
+        let schema = stream
+            .table_schema
+            .as_ref()
+            .ok_or_else(|| anyhow!("BigQuery did not return the schema of table '{table}'"))?;
+        *encoder = Some(RowEncoder::new(schema)?);
+    }
+    let encoder = encoder.as_ref().unwrap();
+
+    let op_column = config.change_type_column.as_deref();
+    let rows = records
+        .iter()
+        .map(|(record, insert)| {
+            let op = op_column.map(|column| (column, if *insert { "insert" } else { "delete" }));
+            encoder.encode(record, op)
+        })
+        .collect::<AnyResult<Vec<_>>>()?;
+
+    let mut requests = Vec::new();
+    let mut offset = 0;
+    for chunk in chunk_rows(rows).into_iter() {
+        let num_rows = chunk.len() as i64;
+        requests.push(AppendRowsRequest {
+            write_stream: stream.name.clone(),
+            offset: Some(offset),
+            rows: Some(Rows::ProtoRows(ProtoData {
+                writer_schema: Some(ProtoSchema {
+                    proto_descriptor: Some(encoder.descriptor().clone()),
+                }),
+                rows: Some(ProtoRows {
+                    serialized_rows: chunk,
+                }),
+            })),
+            trace_id: "feldera".to_string(),
+        });
+        offset += num_rows;
+    }
+
+    let num_requests = requests.len();
+    let mut responses = client
+        .append_rows(request(
+            futures::stream::iter(requests),
+            &format!("write_stream={}", stream.name),
+        )?)
+        .await?
+        .into_inner();
+    for _ in 0..num_requests {
+        let response = responses
+            .message()
+            .await?
+            .ok_or_else(|| anyhow!("BigQuery closed the append stream unexpectedly"))?;
+        if let Some(error) = response.row_errors.first() {
+            bail!(
+                "BigQuery rejected row {} ({} rows rejected in total): {}",
+                error.index,
+                response.row_errors.len(),
+                error.message
+            );
+        }
+        if let Some(AppendResponse::Error(status)) = response.response {
+            bail!("BigQuery append failed: {}", status.message);
+        }
+    }
+    drop(responses);
+
+    client
+        .finalize_write_stream(request(
+            FinalizeWriteStreamRequest {
+                name: stream.name.clone(),
+            },
+            &format!("name={}", stream.name),
+        )?)
+        .await?;
+
+    let commit = client
+        .batch_commit_write_streams(request(
+            BatchCommitWriteStreamsRequest {
+                parent: table.clone(),
+                write_streams: vec![stream.name.clone()],
+            },
+            &format!("parent={table}"),
+        )?)
+        .await?
+        .into_inner();
+    if let Some(error) = commit.stream_errors.first() {
+        bail!(
+            "failed to commit BigQuery write stream: {}",
+            error.error_message
+        );
+    }
+    if commit.commit_time.is_none() {
+        bail!("BigQuery did not commit write stream '{}'", stream.name);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct MetadataToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct BigQueryOutputEndpoint {
+    config: BigQueryOutputConfig,
+    runtime: Runtime,
+    channel: Option<Channel>,
+
+    /// Access token obtained from the metadata server and its expiration
+    /// time.
+    token: Option<(String, Instant)>,
+
+    /// Created from the table schema when writing the first batch.
+    encoder: Option<RowEncoder>,
+
+    /// Records in the current batch and whether they are inserted (`true`)
+    /// or deleted (`false`).
+    records: Vec<(JsonMap<String, JsonValue>, bool)>,
+}
+
+impl BigQueryOutputEndpoint {
+    fn new(config: BigQueryOutputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!(
+            "Starting BigQuery output endpoint for table '{}'",
+            config.table_path()
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            config,
+            runtime,
+            channel: None,
+            token: None,
+            encoder: None,
+            records: Vec::new(),
+        })
+    }
+
+    fn access_token(&mut self) -> AnyResult<String> {
+        if let Some(token) = &self.config.access_token {
+            return Ok(token.clone());
+        }
+        if let Some((token, expires)) = &self.token {
+            if Instant::now() + TOKEN_REFRESH_MARGIN < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        info!("Requesting BigQuery access token from the metadata server");
+        let token: MetadataToken = reqwest::blocking::Client::new()
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| anyhow!("failed to obtain an access token from the metadata server (specify 'access_token' when running outside of Google Cloud): {e}"))?;
+        let expires = Instant::now() + Duration::from_secs(token.expires_in);
+        self.token = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    fn channel(&mut self) -> AnyResult<Channel> {
+        if let Some(channel) = &self.channel {
+            return Ok(channel.clone());
+        }
+
+        let mut endpoint = Channel::from_shared(self.config.endpoint.clone())?;
+        if self.config.endpoint.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        let channel = self.runtime.block_on(endpoint.connect())?;
+        self.channel = Some(channel.clone());
+        Ok(channel)
+    }
+
+    fn add_record(&mut self, record: JsonValue, insert: bool) -> AnyResult<()> {
+        let record = match record {
+            JsonValue::Object(record) => record,
+            record => bail!("expected a JSON object, found '{record}'"),
+        };
+        if !insert && self.config.change_type_column.is_none() {
+            bail!("the view deleted a record, but the endpoint doesn't specify a 'change_type_column' to record deletions");
+        }
+        self.records.push((record, insert));
+        Ok(())
+    }
+}
+
+impl OutputEndpoint for BigQueryOutputEndpoint {
+    fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        usize::MAX
+    }
+
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.records.clear();
+        Ok(())
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        for value in serde_json::Deserializer::from_slice(buffer).into_iter::<JsonValue>() {
+            let value = value.map_err(|e| anyhow!("error parsing output buffer as JSON: {e}"))?;
+            let updates = match value {
+                JsonValue::Array(updates) => updates,
+                update => vec![update],
+            };
+
+            for mut update in updates.into_iter() {
+                if let Some(record) = update.get_mut("delete") {
+                    self.add_record(record.take(), false)?;
+                }
+                if let Some(record) = update.get_mut("insert") {
+                    self.add_record(record.take(), true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+        let records = std::mem::take(&mut self.records);
+        let token = self.access_token()?;
+        let channel = self.channel()?;
+        self.runtime
+            .block_on(write_batch(
+                &self.config,
+                channel,
+                &token,
+                &mut self.encoder,
+                records,
+            ))
+            .map_err(|e| {
+                anyhow!(
+                    "failed to write to BigQuery table '{}': {e}",
+                    self.config.table_path()
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chunk_rows, BigQueryOutputConfig, MAX_APPEND_REQUEST_BYTES};
+
+    #[test]
+    fn config() {
+        let config: BigQueryOutputConfig = serde_yaml::from_str(
+            r#"
+project_id: analytics
+dataset: shop
+table: orders
+"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.table_path(),
+            "projects/analytics/datasets/shop/tables/orders"
+        );
+        assert_eq!(config.endpoint, "https://bigquerystorage.googleapis.com");
+
+        let config: BigQueryOutputConfig =
+            serde_yaml::from_str("project_id: analytics\ndataset: shop\ntable: ''").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn chunks() {
+        let row = vec![0u8; MAX_APPEND_REQUEST_BYTES / 3 + 1];
+        let chunks = chunk_rows(vec![row.clone(); 5]);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert!(chunk_rows(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,368 @@
+//! Conversion of JSON records produced by the pipeline to protobuf rows
+//! accepted by the BigQuery Storage Write API.
+
+use super::proto::{
+    table_field_schema::{Mode, Type as BigQueryType},
+    TableFieldSchema, TableSchema,
+};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use prost::encoding;
+use prost_types::{
+    field_descriptor_proto::{Label, Type as ProtoType},
+    DescriptorProto, FieldDescriptorProto,
+};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+/// Representation of a column value in the protobuf row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    String,
+    Int64,
+    Double,
+    Bool,
+    Bytes,
+    /// Microseconds since the UNIX epoch.
+    TimestampMicros,
+    /// Days since the UNIX epoch.
+    Date,
+}
+
+impl Encoding {
+    fn for_type(typ: BigQueryType) -> Option<Self> {
+        Some(match typ {
+            BigQueryType::String
+            | BigQueryType::Numeric
+            | BigQueryType::Bignumeric
+            | BigQueryType::Datetime
+            | BigQueryType::Time
+            | BigQueryType::Json
+            | BigQueryType::Geography
+            | BigQueryType::Interval => Self::String,
+            BigQueryType::Int64 => Self::Int64,
+            BigQueryType::Double => Self::Double,
+            BigQueryType::Bool => Self::Bool,
+            BigQueryType::Bytes => Self::Bytes,
+            BigQueryType::Timestamp => Self::TimestampMicros,
+            BigQueryType::Date => Self::Date,
+            BigQueryType::Struct | BigQueryType::Unspecified => return None,
+        })
+    }
+
+    fn proto_type(&self) -> ProtoType {
+        match self {
+            Self::String => ProtoType::String,
+            Self::Int64 | Self::TimestampMicros => ProtoType::Int64,
+            Self::Double => ProtoType::Double,
+            Self::Bool => ProtoType::Bool,
+            Self::Bytes => ProtoType::Bytes,
+            Self::Date => ProtoType::Int32,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    /// Protobuf field number.
+    tag: u32,
+    encoding: Encoding,
+    repeated: bool,
+}
+
+/// Encodes JSON records as protobuf messages that match the schema of the
+/// destination table.
+#[derive(Debug)]
+pub(super) struct RowEncoder {
+    columns: Vec<Column>,
+    descriptor: DescriptorProto,
+}
+
+impl RowEncoder {
+    /// Create an encoder for a table with `schema`.
+    pub(super) fn new(schema: &TableSchema) -> AnyResult<Self> {
+        let columns = schema
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| Self::column(i as u32 + 1, field))
+            .collect::<AnyResult<Vec<_>>>()?;
+
+        let descriptor = DescriptorProto {
+            name: Some("FelderaRow".to_string()),
+            field: columns
+                .iter()
+                .map(|column| FieldDescriptorProto {
+                    name: Some(column.name.clone()),
+                    number: Some(column.tag as i32),
+                    label: Some(if column.repeated {
+                        Label::Repeated
+                    } else {
+                        Label::Optional
+                    } as i32),
+                    r#type: Some(column.encoding.proto_type() as i32),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            columns,
+            descriptor,
+        })
+    }
+
+    fn column(tag: u32, field: &TableFieldSchema) -> AnyResult<Column> {
+        let typ = BigQueryType::try_from(field.r#type).unwrap_or(BigQueryType::Unspecified);
+        let encoding = Encoding::for_type(typ).ok_or_else(|| {
+            anyhow!(
+                "column '{}' has type {}, which is not supported by the BigQuery connector",
+                field.name,
+                typ.as_str_name()
+            )
+        })?;
+        Ok(Column {
+            name: field.name.clone(),
+            tag,
+            encoding,
+            repeated: field.mode == Mode::Repeated as i32,
+        })
+    }
+
+    /// Protobuf descriptor of encoded rows.
+    pub(super) fn descriptor(&self) -> &DescriptorProto {
+        &self.descriptor
+    }
+
+    /// Find a column by name.  Exact matches take precedence over
+    /// case-insensitive ones, since BigQuery column names are
+    /// case-insensitive.
+    fn find_column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .find(|column| column.name.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Encode `record` as a protobuf message.
+    ///
+    /// If `op` is specified, it is a `(column, value)` pair that is added to
+    /// the record.
+    pub(super) fn encode(
+        &self,
+        record: &JsonMap<String, JsonValue>,
+        op: Option<(&str, &str)>,
+    ) -> AnyResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        for (name, value) in record.iter() {
+            let column = self
+                .find_column(name)
+                .ok_or_else(|| anyhow!("column '{name}' not found in the BigQuery table"))?;
+            Self::encode_column(column, value, &mut buffer)
+                .map_err(|e| anyhow!("error encoding column '{name}': {e}"))?;
+        }
+        if let Some((name, value)) = op {
+            let column = self
+                .find_column(name)
+                .ok_or_else(|| anyhow!("column '{name}' not found in the BigQuery table"))?;
+            if column.encoding != Encoding::String || column.repeated {
+                bail!("change type column '{name}' must have type STRING");
+            }
+            encoding::string::encode(column.tag, &value.to_string(), &mut buffer);
+        }
+        Ok(buffer)
+    }
+
+    fn encode_column(column: &Column, value: &JsonValue, buffer: &mut Vec<u8>) -> AnyResult<()> {
+        match value {
+            JsonValue::Null => Ok(()),
+            JsonValue::Array(values) if column.repeated => {
+                for value in values.iter() {
+                    if value.is_null() {
+                        bail!("BigQuery arrays cannot contain NULL values");
+                    }
+                    Self::encode_value(column, value, buffer)?;
+                }
+                Ok(())
+            }
+            _ if column.repeated => bail!("expected an array, found '{value}'"),
+            value => Self::encode_value(column, value, buffer),
+        }
+    }
+
+    fn encode_value(column: &Column, value: &JsonValue, buffer: &mut Vec<u8>) -> AnyResult<()> {
+        let tag = column.tag;
+        match column.encoding {
+            Encoding::String => {
+                let value = match value {
+                    JsonValue::String(s) => s.clone(),
+                    // JSON columns, numbers, and booleans.
+                    value => value.to_string(),
+                };
+                encoding::string::encode(tag, &value, buffer);
+            }
+            Encoding::Int64 => {
+                let value = match value {
+                    JsonValue::Number(n) => n.as_i64(),
+                    JsonValue::String(s) => s.parse().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| anyhow!("expected an integer, found '{value}'"))?;
+                encoding::int64::encode(tag, &value, buffer);
+            }
+            Encoding::Double => {
+                let value = match value {
+                    JsonValue::Number(n) => n.as_f64(),
+                    JsonValue::String(s) => s.parse().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| anyhow!("expected a number, found '{value}'"))?;
+                encoding::double::encode(tag, &value, buffer);
+            }
+            Encoding::Bool => {
+                let value = value
+                    .as_bool()
+                    .ok_or_else(|| anyhow!("expected a boolean, found '{value}'"))?;
+                encoding::bool::encode(tag, &value, buffer);
+            }
+            Encoding::Bytes => {
+                let value = match value {
+                    JsonValue::String(s) => s.as_bytes().to_vec(),
+                    JsonValue::Array(bytes) => bytes
+                        .iter()
+                        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or_else(|| anyhow!("expected an array of bytes, found '{value}'"))?,
+                    _ => bail!("expected bytes, found '{value}'"),
+                };
+                encoding::bytes::encode(tag, &value, buffer);
+            }
+            Encoding::TimestampMicros => {
+                let value = match value {
+                    JsonValue::Number(n) => n.as_i64(),
+                    JsonValue::String(s) => parse_timestamp_micros(s),
+                    _ => None,
+                }
+                .ok_or_else(|| anyhow!("expected a timestamp, found '{value}'"))?;
+                encoding::int64::encode(tag, &value, buffer);
+            }
+            Encoding::Date => {
+                let value = match value {
+                    JsonValue::Number(n) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+                    JsonValue::String(s) => parse_date_days(s),
+                    _ => None,
+                }
+                .ok_or_else(|| anyhow!("expected a date, found '{value}'"))?;
+                encoding::int32::encode(tag, &value, buffer);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a SQL timestamp (`2023-10-01 12:00:00.123`) or an RFC 3339
+/// timestamp as microseconds since the UNIX epoch.
+fn parse_timestamp_micros(s: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+        .map(|timestamp| timestamp.timestamp_micros())
+        .or_else(|_| DateTime::parse_from_rfc3339(s).map(|timestamp| timestamp.timestamp_micros()))
+        .ok()
+}
+
+/// Parse a SQL date (`2023-10-01`) as days since the UNIX epoch.
+fn parse_date_days(s: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    i32::try_from((date - epoch).num_days()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_date_days, parse_timestamp_micros, RowEncoder};
+    use crate::transport::bigquery::proto::{
+        table_field_schema::{Mode, Type},
+        TableFieldSchema, TableSchema,
+    };
+    use prost::encoding;
+    use serde_json::json;
+
+    fn field(name: &str, typ: Type, mode: Mode) -> TableFieldSchema {
+        TableFieldSchema {
+            name: name.to_string(),
+            r#type: typ as i32,
+            mode: mode as i32,
+            fields: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn encode() {
+        let schema = TableSchema {
+            fields: vec![
+                field("id", Type::Int64, Mode::Required),
+                field("name", Type::String, Mode::Nullable),
+                field("created", Type::Date, Mode::Nullable),
+                field("tags", Type::String, Mode::Repeated),
+                field("op", Type::String, Mode::Nullable),
+            ],
+        };
+        let encoder = RowEncoder::new(&schema).unwrap();
+        assert_eq!(encoder.descriptor().field.len(), 5);
+
+        let record = json!({"ID": 5, "name": null, "created": "1970-01-11", "tags": ["a", "b"]});
+        let row = encoder
+            .encode(record.as_object().unwrap(), Some(("op", "insert")))
+            .unwrap();
+
+        let mut expected = Vec::new();
+        encoding::int64::encode(1, &5, &mut expected);
+        encoding::int32::encode(3, &10, &mut expected);
+        encoding::string::encode(4, &"a".to_string(), &mut expected);
+        encoding::string::encode(4, &"b".to_string(), &mut expected);
+        encoding::string::encode(5, &"insert".to_string(), &mut expected);
+
+        assert_eq!(row, expected);
+
+        assert!(encoder
+            .encode(json!({"unknown": 1}).as_object().unwrap(), None)
+            .is_err());
+        assert!(encoder
+            .encode(json!({"id": "abc"}).as_object().unwrap(), None)
+            .is_err());
+        assert!(encoder
+            .encode(
+                json!({"id": 1}).as_object().unwrap(),
+                Some(("id", "insert"))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn unsupported_type() {
+        let schema = TableSchema {
+            fields: vec![field("s", Type::Struct, Mode::Nullable)],
+        };
+        assert!(RowEncoder::new(&schema).is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date_days("1970-01-02"), Some(1));
+        assert_eq!(parse_date_days("1969-12-31"), Some(-1));
+        assert_eq!(
+            parse_timestamp_micros("1970-01-01 00:00:01"),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            parse_timestamp_micros("1970-01-01T00:00:00.5+00:00"),
+            Some(500_000)
+        );
+        assert_eq!(parse_timestamp_micros("yesterday"), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! Decompression of files and objects read by input transports.
+
+use anyhow::Result as AnyResult;
+use bzip2::write::BzDecoder;
+use flate2::write::GzDecoder;
+use serde::Deserialize;
+use std::io::Write;
+use utoipa::ToSchema;
+
+/// Compression codec of files and objects read by input transports.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum ObjectCompression {
+    /// Data is not compressed.
+    #[serde(rename = "none")]
+    None,
+
+    /// Choose the codec based on the file name or object key: names ending
+    /// in `.gz` are decompressed with gzip, `.zst` with zstd, `.bz2` with
+    /// bzip2, and all other data is read uncompressed.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+
+    /// Data is compressed with gzip.
+    #[serde(rename = "gzip")]
+    Gzip,
+
+    /// Data is compressed with zstd.
+    #[serde(rename = "zstd")]
+    Zstd,
+
+    /// Data is compressed with bzip2.
+    #[serde(rename = "bzip2")]
+    Bzip2,
+}
+
+impl ObjectCompression {
+    /// Codec to use for the file or object called `name`.
+    pub(crate) fn for_name(self, name: &str) -> Self {
+        match self {
+            Self::Auto if name.ends_with(".gz") => Self::Gzip,
+            Self::Auto if name.ends_with(".zst") => Self::Zstd,
+            Self::Auto if name.ends_with(".bz2") => Self::Bzip2,
+            Self::Auto => Self::None,
+            codec => codec,
+        }
+    }
+}
+
+/// Decoder that writes decompressed data to an in-memory buffer.
+///
+/// The caller is expected to drain the buffer returned by
+/// [`decode`](`Self::decode`) and [`finish`](`Self::finish`).
+pub(crate) enum Decompressor {
+    None(Vec<u8>),
+    Gzip(GzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+    Bzip2(BzDecoder<Vec<u8>>),
+}
+
+impl Decompressor {
+    /// Create a decoder for `compression`, which must not be
+    /// [`ObjectCompression::Auto`]; use [`ObjectCompression::for_name`] to
+    /// resolve it first.
+    pub(crate) fn new(compression: ObjectCompression) -> AnyResult<Self> {
+        Ok(match compression {
+            ObjectCompression::None | ObjectCompression::Auto => Self::None(Vec::new()),
+            ObjectCompression::Gzip => Self::Gzip(GzDecoder::new(Vec::new())),
+            ObjectCompression::Zstd => Self::Zstd(zstd::stream::write::Decoder::new(Vec::new())?),
+            ObjectCompression::Bzip2 => Self::Bzip2(BzDecoder::new(Vec::new())),
+        })
+    }
+
+    /// Decode `data` and return the decoded bytes.
+    pub(crate) fn decode(&mut self, data: &[u8]) -> AnyResult<&mut Vec<u8>> {
+        match self {
+            Self::None(buffer) => {
+                buffer.extend_from_slice(data);
+                Ok(buffer)
+            }
+            Self::Gzip(decoder) => {
+                decoder.write_all(data)?;
+                Ok(decoder.get_mut())
+            }
+            Self::Zstd(decoder) => {
+                decoder.write_all(data)?;
+                Ok(decoder.get_mut())
+            }
+            Self::Bzip2(decoder) => {
+                decoder.write_all(data)?;
+                Ok(decoder.get_mut())
+            }
+        }
+    }
+
+    /// Flush the remaining decoded bytes at the end of the input.
+    pub(crate) fn finish(&mut self) -> AnyResult<&mut Vec<u8>> {
+        match self {
+            Self::None(buffer) => Ok(buffer),
+            Self::Gzip(decoder) => {
+                decoder.try_finish()?;
+                Ok(decoder.get_mut())
+            }
+            Self::Zstd(decoder) => {
+                decoder.flush()?;
+                Ok(decoder.get_mut())
+            }
+            Self::Bzip2(decoder) => {
+                decoder.try_finish()?;
+                Ok(decoder.get_mut())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decompressor, ObjectCompression};
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn decompress(compression: ObjectCompression, data: &[u8]) -> Vec<u8> {
+        let mut decompressor = Decompressor::new(compression).unwrap();
+        let mut result = Vec::new();
+        // Feed the decoder in small chunks.
+        for chunk in data.chunks(7) {
+            result.append(decompressor.decode(chunk).unwrap());
+        }
+        result.append(decompressor.finish().unwrap());
+        result
+    }
+
+    #[test]
+    fn codecs() {
+        let data = b"foo,true,1\nbar,false,2\n".repeat(100);
+
+        let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&data).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        let zstd = zstd::stream::encode_all(data.as_slice(), 0).unwrap();
+
+        let mut bzip2 = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(&data).unwrap();
+        let bzip2 = bzip2.finish().unwrap();
+
+        assert_eq!(decompress(ObjectCompression::None, &data), data);
+        assert_eq!(decompress(ObjectCompression::Gzip, &gzip), data);
+        assert_eq!(decompress(ObjectCompression::Zstd, &zstd), data);
+        assert_eq!(decompress(ObjectCompression::Bzip2, &bzip2), data);
+    }
+
+    #[test]
+    fn auto() {
+        let auto = ObjectCompression::Auto;
+        assert_eq!(auto.for_name("data/1.csv.gz"), ObjectCompression::Gzip);
+        assert_eq!(auto.for_name("data/1.csv.zst"), ObjectCompression::Zstd);
+        assert_eq!(auto.for_name("data/1.csv.bz2"), ObjectCompression::Bzip2);
+        assert_eq!(auto.for_name("data/1.csv"), ObjectCompression::None);
+        assert_eq!(
+            ObjectCompression::Gzip.for_name("data/1.csv"),
+            ObjectCompression::Gzip
+        );
+    }
+}
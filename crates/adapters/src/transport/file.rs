@@ -1,26 +1,37 @@
-use super::{InputConsumer, InputEndpoint, InputTransport, OutputEndpoint, OutputTransport};
-use crate::{OutputEndpointConfig, PipelineState};
-use anyhow::{Error as AnyError, Result as AnyResult};
+use super::{
+    compression::{Decompressor, ObjectCompression},
+    InputConsumer, InputEndpoint, InputTransport, OutputEndpoint, OutputTransport,
+};
+use crate::{AsyncErrorCallback, OutputEndpointConfig, PipelineState};
+use anyhow::{anyhow, bail, Error as AnyError, Result as AnyResult};
+use chrono::Utc;
 use crossbeam::sync::{Parker, Unparker};
+use flate2::write::GzEncoder;
+use glob::Pattern;
+use log::warn;
 use num_traits::FromPrimitive;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
 use std::{
     borrow::Cow,
-    fs::File,
-    io::{BufRead, BufReader, Write},
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU32, Ordering},
-        Arc,
+        Arc, Condvar, Mutex, MutexGuard, RwLock,
     },
     thread::{sleep, spawn},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use utoipa::ToSchema;
 
 const SLEEP_MS: u64 = 200;
 
-/// [`InputTransport`] implementation that reads data from a file.
+/// [`InputTransport`] implementation that reads data from a file or a set
+/// of files matching a glob pattern.
 ///
 /// The input transport factory gives this transport the name `file`.
 pub struct FileInputTransport;
@@ -36,7 +47,7 @@ impl InputTransport for FileInputTransport {
     /// See [`InputTransport::new_endpoint()`] for more information.
     fn new_endpoint(&self, _name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
         let config = FileInputConfig::deserialize(config)?;
-        let ep = FileInputEndpoint::new(config);
+        let ep = FileInputEndpoint::new(config)?;
         Ok(Box::new(ep))
     }
 }
@@ -44,7 +55,10 @@ impl InputTransport for FileInputTransport {
 /// Configuration for reading data from a file with [`FileInputTransport`].
 #[derive(Deserialize, ToSchema)]
 pub struct FileInputConfig {
-    /// File path.
+    /// File path or glob pattern, e.g., `/data/orders/*.csv`.
+    ///
+    /// When the path is a pattern, all matching files are read in
+    /// lexicographic order of their paths.  Records must not span files.
     pub path: String,
 
     /// Read buffer size.
@@ -56,26 +70,354 @@ pub struct FileInputConfig {
     /// Enable file following.
     ///
     /// When `false`, the endpoint outputs an [`eoi`](`InputConsumer::eoi`)
-    /// message and stops upon reaching the end of file.  When `true`, the
-    /// endpoint will keep watching the file and outputting any new content
-    /// appended to it.
+    /// message and stops upon reaching the end of the last file.  When
+    /// `true`, the endpoint will keep watching the file and outputting any
+    /// new content appended to it.  When `path` is a pattern, the endpoint
+    /// additionally picks up new files matching the pattern as they appear.
     #[serde(default)]
     pub follow: bool,
+
+    /// Compression codec used to decode files.
+    ///
+    /// By default, the codec is chosen based on the file extension.
+    /// Compressed files are read once, from start to end, so they must be
+    /// complete when they appear in the directory: data appended to a
+    /// compressed file after it was read is ignored.
+    #[serde(default)]
+    pub compression: ObjectCompression,
+}
+
+/// A file being read by a [`FileInputEndpoint`].
+struct CurrentFile {
+    path: PathBuf,
+    reader: BufReader<File>,
+
+    /// Decoder for compressed files.
+    decompressor: Option<Decompressor>,
+}
+
+/// Position of a [`FileInputEndpoint`] in its input files.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct FilePosition {
+    /// For each uncompressed file, the number of bytes up to the end of the
+    /// last complete record pushed to the consumer.
+    offsets: BTreeMap<PathBuf, u64>,
+
+    /// Compressed files that have been pushed to the consumer in full.
+    /// Compressed files are read from the start unless they are complete.
+    completed: BTreeSet<PathBuf>,
+}
+
+/// Tracks the position of a [`FileInputEndpoint`] for checkpointing.
+#[derive(Default)]
+struct FileProgress {
+    /// Position after the last buffer pushed to the consumer.
+    current: FilePosition,
+
+    /// Position as of the start of the latest step.
+    step: FilePosition,
+
+    /// A step is in progress: data pushed to the consumer now could be
+    /// processed by the step, without being covered by `step`.
+    stepping: bool,
+}
+
+/// [`FileProgress`] shared by a [`FileInputEndpoint`] and its worker thread.
+#[derive(Default)]
+struct SharedProgress {
+    progress: Mutex<FileProgress>,
+
+    /// Signaled when a step completes.
+    step_completed: Condvar,
+}
+
+impl SharedProgress {
+    fn lock(&self) -> MutexGuard<'_, FileProgress> {
+        self.progress.lock().unwrap()
+    }
+
+    /// Lock the progress once no step is in progress.  The worker thread
+    /// holds this lock while pushing data to the consumer, so that the
+    /// position reported at the start of a step covers exactly the data
+    /// processed by the step.
+    fn lock_between_steps(&self) -> MutexGuard<'_, FileProgress> {
+        self.step_completed
+            .wait_while(self.lock(), |progress| progress.stepping)
+            .unwrap()
+    }
+}
+
+/// The set of files read by a [`FileInputEndpoint`], along with the number
+/// of bytes consumed from each file.
+struct FileSet {
+    /// Glob pattern or `None` if the endpoint reads a single file.
+    pattern: Option<String>,
+    path: String,
+    buffer_size: Option<usize>,
+    compression: ObjectCompression,
+
+    /// Bytes consumed from each file seen so far.
+    offsets: BTreeMap<PathBuf, u64>,
+
+    /// Compressed files that have been read to the end.
+    completed: BTreeSet<PathBuf>,
+
+    /// The file currently being read.
+    current: Option<CurrentFile>,
+
+    fragments: FragmentTracker,
+
+    /// Shared with the endpoint, which reports the position at the start of
+    /// each step.  Updated while holding the lock together with pushing
+    /// data to the consumer, so that the position never gets out of sync
+    /// with the data seen by a step.
+    progress: Arc<SharedProgress>,
+}
+
+impl FileSet {
+    fn new(config: &FileInputConfig) -> AnyResult<Self> {
+        Self::with_progress(config, Arc::new(SharedProgress::default()))
+    }
+
+    /// Create a file set that resumes from the current position in
+    /// `progress`.
+    fn with_progress(config: &FileInputConfig, progress: Arc<SharedProgress>) -> AnyResult<Self> {
+        let pattern = if Pattern::escape(&config.path) != config.path {
+            Pattern::new(&config.path)
+                .map_err(|e| anyhow!("invalid file pattern '{}': {e}", config.path))?;
+            Some(config.path.clone())
+        } else {
+            None
+        };
+        let position = progress.lock().current.clone();
+        Ok(Self {
+            pattern,
+            path: config.path.clone(),
+            buffer_size: config.buffer_size_bytes,
+            compression: config.compression,
+            offsets: position.offsets,
+            completed: position.completed,
+            current: None,
+            fragments: FragmentTracker::default(),
+            progress,
+        })
+    }
+
+    /// Paths of all files in the set, in the order they should be read.
+    fn list(&self) -> AnyResult<Vec<PathBuf>> {
+        match &self.pattern {
+            None => Ok(vec![PathBuf::from(&self.path)]),
+            Some(pattern) => {
+                let mut paths = Vec::new();
+                for entry in glob::glob(pattern)? {
+                    match entry {
+                        Ok(path) if path.is_file() => paths.push(path),
+                        Ok(_) => (),
+                        Err(e) => warn!("Error scanning files matching '{pattern}': {e}"),
+                    }
+                }
+                paths.sort();
+                Ok(paths)
+            }
+        }
+    }
+
+    fn open(&self, path: &Path, offset: u64) -> AnyResult<BufReader<File>> {
+        let mut file = File::open(path)
+            .map_err(|e| anyhow!("Failed to open input file '{}': {e}", path.display()))?;
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        Ok(match self.buffer_size {
+            Some(buffer_size) if buffer_size > 0 => BufReader::with_capacity(buffer_size, file),
+            _ => BufReader::new(file),
+        })
+    }
+
+    /// Open the first file in `paths` that has unread data.
+    ///
+    /// Returns `false` if there is no such file.
+    fn open_next(&mut self, paths: &[PathBuf]) -> AnyResult<bool> {
+        for path in paths {
+            if self.completed.contains(path) {
+                continue;
+            }
+            let len = match path.metadata() {
+                Ok(metadata) => metadata.len(),
+                // The file was removed after it was listed.
+                Err(_) => continue,
+            };
+            let offset = self.offsets.entry(path.clone()).or_insert(0);
+            if len < *offset {
+                warn!(
+                    "Input file '{}' was truncated, reading it from the beginning",
+                    path.display()
+                );
+                *offset = 0;
+                self.progress.lock().current.offsets.insert(path.clone(), 0);
+            }
+            if len > *offset {
+                let offset = *offset;
+                let decompressor = match self.compression.for_name(&path.to_string_lossy()) {
+                    ObjectCompression::None => None,
+                    compression => Some(Decompressor::new(compression)?),
+                };
+                self.current = Some(CurrentFile {
+                    path: path.clone(),
+                    reader: self.open(path, offset)?,
+                    decompressor,
+                });
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Forget files that no longer exist, so that they are read again if
+    /// they are re-created.
+    fn retain(&mut self, paths: &[PathBuf]) {
+        self.offsets.retain(|path, _| paths.contains(path));
+        self.completed.retain(|path| paths.contains(path));
+
+        let mut progress = self.progress.lock();
+        let position = &mut progress.current;
+        position.offsets.retain(|path, _| paths.contains(path));
+        position.completed.retain(|path| paths.contains(path));
+    }
+
+    /// Read the next buffer from the current file and pass it to `consumer`.
+    ///
+    /// Returns `false` when the current file is exhausted.
+    fn read(&mut self, consumer: &mut Box<dyn InputConsumer>) -> AnyResult<bool> {
+        let file = match &mut self.current {
+            None => return Ok(false),
+            Some(file) => file,
+        };
+        let data = file.reader.fill_buf()?;
+        let len = data.len();
+        let mut progress = self.progress.lock_between_steps();
+
+        if len == 0 {
+            if let Some(decompressor) = &mut file.decompressor {
+                let decoded = decompressor
+                    .finish()
+                    .map_err(|e| decompression_error(&file.path, e))?;
+                let terminated = self.fragments.push(consumer, &file.path, decoded);
+                decoded.clear();
+                self.completed.insert(file.path.clone());
+                progress.current.completed.insert(file.path.clone());
+                Self::terminated(&self.offsets, &mut progress.current, terminated);
+            }
+            self.current = None;
+            return Ok(false);
+        }
+
+        let offset = self.offsets.get_mut(&file.path).unwrap();
+        let terminated = match &mut file.decompressor {
+            None => {
+                let terminated = self.fragments.push(consumer, &file.path, data);
+                if let Some(end) = data.iter().rposition(|b| *b == b'\n') {
+                    progress
+                        .current
+                        .offsets
+                        .insert(file.path.clone(), *offset + end as u64 + 1);
+                }
+                terminated
+            }
+            Some(decompressor) => {
+                let decoded = decompressor
+                    .decode(data)
+                    .map_err(|e| decompression_error(&file.path, e))?;
+                let terminated = self.fragments.push(consumer, &file.path, decoded);
+                decoded.clear();
+                terminated
+            }
+        };
+        file.reader.consume(len);
+        *offset += len as u64;
+        Self::terminated(&self.offsets, &mut progress.current, terminated);
+        Ok(true)
+    }
+
+    /// The last record of file `path` has been terminated: the file has been
+    /// pushed to the consumer in full.
+    fn terminated(
+        offsets: &BTreeMap<PathBuf, u64>,
+        position: &mut FilePosition,
+        path: Option<PathBuf>,
+    ) {
+        if let Some(path) = path {
+            if let Some(offset) = position.offsets.get_mut(&path) {
+                *offset = offsets.get(&path).copied().unwrap_or(*offset);
+            }
+        }
+    }
+}
+
+fn decompression_error(path: &Path, error: AnyError) -> AnyError {
+    anyhow!("error decompressing file '{}': {error}", path.display())
+}
+
+/// Tracks the source of input fragments, to make sure that the last record
+/// in a file doesn't get glued to the first record of the next file.
+#[derive(Default)]
+struct FragmentTracker {
+    /// The file that the last input fragment came from.
+    last_path: Option<PathBuf>,
+
+    /// The last fragment did not end with a newline.
+    incomplete_line: bool,
+}
+
+impl FragmentTracker {
+    /// Push `data` read from `path` to the consumer.
+    ///
+    /// Returns the previous file if this call terminated its last record.
+    fn push(
+        &mut self,
+        consumer: &mut Box<dyn InputConsumer>,
+        path: &Path,
+        data: &[u8],
+    ) -> Option<PathBuf> {
+        if data.is_empty() {
+            return None;
+        }
+        let mut terminated = None;
+        if self.last_path.as_deref() != Some(path) {
+            // Terminate the last record of the previous file.
+            if self.incomplete_line {
+                let _ = consumer.input_fragment(b"\n");
+                terminated = self.last_path.clone();
+            }
+            self.last_path = Some(path.to_path_buf());
+            consumer.source_offset(&JsonValue::from(path.display().to_string()));
+        }
+        self.incomplete_line = data.last() != Some(&b'\n');
+
+        // Leave it to the controller to handle errors.  There is noone we can
+        // forward the error to upstream.
+        let _ = consumer.input_fragment(data);
+        terminated
+    }
 }
 
 struct FileInputEndpoint {
     config: FileInputConfig,
     status: Arc<AtomicU32>,
     unparker: Option<Unparker>,
+    progress: Arc<SharedProgress>,
 }
 
 impl FileInputEndpoint {
-    fn new(config: FileInputConfig) -> Self {
-        Self {
+    fn new(config: FileInputConfig) -> AnyResult<Self> {
+        // Validate the pattern.
+        FileSet::new(&config)?;
+        Ok(Self {
             config,
             status: Arc::new(AtomicU32::new(PipelineState::Paused as u32)),
             unparker: None,
-        }
+            progress: Arc::new(SharedProgress::default()),
+        })
     }
 
     fn unpark(&self) {
@@ -85,7 +427,8 @@ impl FileInputEndpoint {
     }
 
     fn worker_thread(
-        mut reader: BufReader<File>,
+        mut files: FileSet,
+        mut paths: Vec<PathBuf>,
         mut consumer: Box<dyn InputConsumer>,
         parker: Parker,
         status: Arc<AtomicU32>,
@@ -95,13 +438,23 @@ impl FileInputEndpoint {
             match PipelineState::from_u32(status.load(Ordering::Acquire)) {
                 Some(PipelineState::Paused) => parker.park(),
                 Some(PipelineState::Running) => {
-                    let data = reader.fill_buf();
-                    match data {
+                    let result = files.read(&mut consumer).and_then(|progress| {
+                        if progress {
+                            return Ok(true);
+                        }
+                        if follow {
+                            // Pick up new files and data appended to existing files.
+                            paths = files.list()?;
+                            files.retain(&paths);
+                        }
+                        files.open_next(&paths)
+                    });
+                    match result {
                         Err(e) => {
-                            consumer.error(true, AnyError::from(e));
+                            consumer.error(true, e);
                             return;
                         }
-                        Ok(data) if data.is_empty() => {
+                        Ok(false) => {
                             if !follow {
                                 let _ = consumer.eoi();
                                 return;
@@ -109,15 +462,7 @@ impl FileInputEndpoint {
                                 sleep(Duration::from_millis(SLEEP_MS));
                             }
                         }
-                        Ok(data) => {
-                            // println!("read {} bytes from file", data.len());
-
-                            // Leave it to the controller to handle errors.  There is noone we can
-                            // forward the error to upstream.
-                            let _ = consumer.input_fragment(data);
-                            let len = data.len();
-                            reader.consume(len);
-                        }
+                        Ok(true) => (),
                     }
                 }
                 Some(PipelineState::Terminated) => return,
@@ -129,22 +474,19 @@ impl FileInputEndpoint {
 
 impl InputEndpoint for FileInputEndpoint {
     fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
-        let file = File::open(&self.config.path).map_err(|e| {
-            AnyError::msg(format!(
-                "Failed to open input file '{}': {e}",
-                self.config.path
-            ))
-        })?;
-        let reader = match self.config.buffer_size_bytes {
-            Some(buffer_size) if buffer_size > 0 => BufReader::with_capacity(buffer_size, file),
-            _ => BufReader::new(file),
-        };
+        let mut files = FileSet::with_progress(&self.config, self.progress.clone())?;
+        let paths = files.list()?;
+        if files.pattern.is_none() {
+            // Fail early if the file doesn't exist.
+            files.open(&paths[0], 0)?;
+        }
 
         let parker = Parker::new();
         self.unparker = Some(parker.unparker().clone());
         let status = self.status.clone();
         let follow = self.config.follow;
-        let _worker = spawn(move || Self::worker_thread(reader, consumer, parker, status, follow));
+        let _worker =
+            spawn(move || Self::worker_thread(files, paths, consumer, parker, status, follow));
         Ok(())
     }
 
@@ -169,8 +511,33 @@ impl InputEndpoint for FileInputEndpoint {
         self.status
             .store(PipelineState::Terminated as u32, Ordering::Release);
 
-        // Wake up the worker if it's paused.
+        // Wake up the worker if it's paused or waiting for a step to complete.
         self.unpark();
+        self.step_completed();
+    }
+
+    fn step_started(&self) {
+        let mut progress = self.progress.lock();
+        progress.step = progress.current.clone();
+        progress.stepping = true;
+    }
+
+    fn step_completed(&self) {
+        self.progress.lock().stepping = false;
+        self.progress.step_completed.notify_all();
+    }
+
+    fn position(&self) -> Option<JsonValue> {
+        serde_json::to_value(&self.progress.lock().step).ok()
+    }
+
+    fn seek(&mut self, position: JsonValue) -> AnyResult<()> {
+        let position = FilePosition::deserialize(position)
+            .map_err(|e| anyhow!("invalid file input position: {e}"))?;
+        let mut progress = self.progress.lock();
+        progress.current = position.clone();
+        progress.step = position;
+        Ok(())
     }
 }
 
@@ -206,34 +573,237 @@ impl OutputTransport for FileOutputTransport {
     }
 }
 
+fn default_timestamp_format() -> String {
+    "%Y%m%dT%H%M%S".to_string()
+}
+
 /// Configuration for writing data to a file with [`FileOutputTransport`].
 #[derive(Deserialize, ToSchema)]
 pub struct FileOutputConfig {
     /// File path.
+    ///
+    /// When file rotation is enabled, i.e., `max_file_size_bytes` or
+    /// `max_file_duration_secs` is set, the path is a template for the names
+    /// of the files the endpoint writes to and must contain `{index}`,
+    /// `{timestamp}`, or both.  `{index}` is replaced with the sequence
+    /// number of the file, starting from 0, and `{timestamp}` with the time
+    /// the file was created, formatted according to `timestamp_format`,
+    /// e.g., `/data/output-{timestamp}-{index}.csv`.
     pub path: String,
+
+    /// Start a new file when the size of the current file reaches this
+    /// many bytes.
+    ///
+    /// Files are rotated between output batches, so a file can exceed this
+    /// size by up to one batch.
+    pub max_file_size_bytes: Option<u64>,
+
+    /// Start a new file when the current file has been open for this many
+    /// seconds.
+    ///
+    /// The check is performed at the start of each output batch, so a file
+    /// remains open until the pipeline produces more output.
+    pub max_file_duration_secs: Option<u64>,
+
+    /// Format of the `{timestamp}` placeholder in `path`, using the
+    /// [`strftime`](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
+    /// syntax.  The timestamp is in UTC.  The default is `%Y%m%dT%H%M%S`.
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+
+    /// Compress files after rotating them.
+    ///
+    /// Compression runs in the background.  The compressed file gets a
+    /// `.gz` or `.zst` suffix and the uncompressed file is removed.  The
+    /// file that is being written when the pipeline stops is not
+    /// compressed.
+    #[serde(default)]
+    pub compression: FileCompression,
 }
 
-struct FileOutputEndpoint {
+impl FileOutputConfig {
+    fn rotation_enabled(&self) -> bool {
+        self.max_file_size_bytes.is_some() || self.max_file_duration_secs.is_some()
+    }
+
+    fn validate(&self) -> AnyResult<()> {
+        if self.rotation_enabled() {
+            if !self.path.contains("{index}") && !self.path.contains("{timestamp}") {
+                bail!(
+                    "output file path '{}' must contain '{{index}}' or '{{timestamp}}' when file rotation is enabled",
+                    self.path
+                );
+            }
+        } else if self.compression != FileCompression::None {
+            bail!("file compression requires file rotation to be enabled");
+        }
+        Ok(())
+    }
+
+    /// Path of the file with sequence number `index`.
+    fn file_path(&self, index: u64) -> PathBuf {
+        if !self.rotation_enabled() {
+            return PathBuf::from(&self.path);
+        }
+        let timestamp = Utc::now().format(&self.timestamp_format).to_string();
+        PathBuf::from(
+            self.path
+                .replace("{index}", &index.to_string())
+                .replace("{timestamp}", &timestamp),
+        )
+    }
+}
+
+/// Compression codec applied to files written by [`FileOutputTransport`]
+/// after rotation.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum FileCompression {
+    /// Files are not compressed.
+    #[default]
+    #[serde(rename = "none")]
+    None,
+
+    /// Compress files with gzip.
+    #[serde(rename = "gzip")]
+    Gzip,
+
+    /// Compress files with zstd.
+    #[serde(rename = "zstd")]
+    Zstd,
+}
+
+impl FileCompression {
+    /// Compress the file at `path` and remove the original file.
+    fn compress(self, path: &Path) -> AnyResult<()> {
+        let extension = match self {
+            Self::None => return Ok(()),
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        };
+        let mut compressed_path = path.as_os_str().to_owned();
+        compressed_path.push(".");
+        compressed_path.push(extension);
+
+        let mut input = File::open(path)?;
+        let output = File::create(&compressed_path)?;
+        match self {
+            Self::None => unreachable!(),
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(output, flate2::Compression::default());
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.sync_all()?;
+            }
+            Self::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(output, 0)?;
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.sync_all()?;
+            }
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+/// The file currently written by a [`FileOutputEndpoint`].
+struct OutputFile {
     file: File,
+    path: PathBuf,
+
+    /// Sequence number of the file.
+    index: u64,
+
+    /// Bytes written to the file.
+    size: u64,
+
+    /// Time when the file was created.
+    created: Instant,
 }
 
-impl FileOutputEndpoint {
-    fn new(config: FileOutputConfig) -> AnyResult<Self> {
-        let file = File::create(&config.path).map_err(|e| {
+impl OutputFile {
+    fn create(config: &FileOutputConfig, index: u64) -> AnyResult<Self> {
+        let path = config.file_path(index);
+        let file = File::create(&path).map_err(|e| {
             AnyError::msg(format!(
                 "Failed to create output file '{}': {e}",
-                config.path
+                path.display()
             ))
         })?;
-        Ok(Self { file })
+        Ok(Self {
+            file,
+            path,
+            index,
+            size: 0,
+            created: Instant::now(),
+        })
+    }
+}
+
+struct FileOutputEndpoint {
+    config: FileOutputConfig,
+    file: OutputFile,
+
+    /// Used to report errors compressing rotated files.
+    async_error_callback: Arc<RwLock<Option<AsyncErrorCallback>>>,
+}
+
+impl FileOutputEndpoint {
+    fn new(config: FileOutputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        let file = OutputFile::create(&config, 0)?;
+        Ok(Self {
+            config,
+            file,
+            async_error_callback: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    fn needs_rotation(&self) -> bool {
+        if self.file.size == 0 {
+            return false;
+        }
+        if let Some(max_size) = self.config.max_file_size_bytes {
+            if self.file.size >= max_size {
+                return true;
+            }
+        }
+        if let Some(max_duration) = self.config.max_file_duration_secs {
+            if self.file.created.elapsed() >= Duration::from_secs(max_duration) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Close the current file and start a new one.
+    fn rotate(&mut self) -> AnyResult<()> {
+        let file = OutputFile::create(&self.config, self.file.index + 1)?;
+        let old_file = std::mem::replace(&mut self.file, file);
+        old_file.file.sync_all()?;
+        drop(old_file.file);
+
+        let compression = self.config.compression;
+        if compression != FileCompression::None {
+            let async_error_callback = self.async_error_callback.clone();
+            spawn(move || {
+                if let Err(e) = compression.compress(&old_file.path) {
+                    let error = anyhow!(
+                        "error compressing output file '{}': {e}",
+                        old_file.path.display()
+                    );
+                    match async_error_callback.read().unwrap().as_ref() {
+                        Some(callback) => callback(false, error),
+                        None => warn!("{error}"),
+                    }
+                }
+            });
+        }
+        Ok(())
     }
 }
 
 impl OutputEndpoint for FileOutputEndpoint {
-    fn connect(
-        &self,
-        _async_error_callback: Box<dyn Fn(bool, AnyError) + Send + Sync>,
-    ) -> AnyResult<()> {
+    fn connect(&self, async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        *self.async_error_callback.write().unwrap() = Some(async_error_callback);
         Ok(())
     }
 
@@ -241,19 +811,39 @@ impl OutputEndpoint for FileOutputEndpoint {
         usize::MAX
     }
 
+    fn batch_start(&mut self) -> AnyResult<()> {
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
     fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
-        self.file.write_all(buffer)?;
+        self.file.file.write_all(buffer)?;
+        self.file.size += buffer.len() as u64;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::test::{mock_input_pipeline, wait};
+    use super::{FileInputTransport, FileOutputEndpoint};
+    use crate::{
+        test::{mock_input_pipeline, mock_parser_pipeline, wait},
+        transport::InputTransport,
+        InputEndpointConfig, OutputEndpoint,
+    };
     use csv::WriterBuilder as CsvWriterBuilder;
+    use flate2::{read::GzDecoder, write::GzEncoder, Compression};
     use serde::{Deserialize, Serialize};
-    use std::{io::Write, thread::sleep, time::Duration};
-    use tempfile::NamedTempFile;
+    use serde_json::json;
+    use std::{
+        fs::{self, File},
+        io::{Read, Write},
+        thread::sleep,
+        time::Duration,
+    };
+    use tempfile::{NamedTempFile, TempDir};
 
     #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
     struct TestStruct {
@@ -400,4 +990,256 @@ format:
 
         endpoint.disconnect();
     }
+
+    #[test]
+    fn test_csv_file_pattern() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("b.csv"), "bar,false,-10\n").unwrap();
+        // Last record of the file is not terminated by a newline.
+        fs::write(dir.path().join("a.csv"), "foo,true,10").unwrap();
+        fs::write(dir.path().join("ignored.txt"), "xxx\n").unwrap();
+
+        let config_str = format!(
+            r#"
+stream: test_input
+transport:
+    name: file
+    config:
+        path: {:?}
+        follow: true
+format:
+    name: csv
+"#,
+            dir.path().join("*.csv").to_str().unwrap()
+        );
+
+        let (endpoint, _consumer, zset) =
+            mock_input_pipeline::<TestStruct>(serde_yaml::from_str(&config_str).unwrap()).unwrap();
+        endpoint.start().unwrap();
+
+        // Existing files are read in order.
+        let expected = vec![
+            TestStruct::new("foo".to_string(), true, 10),
+            TestStruct::new("bar".to_string(), false, -10),
+        ];
+        wait(|| zset.state().flushed.len() == expected.len(), None);
+        for (i, (val, polarity)) in zset.state().flushed.iter().enumerate() {
+            assert!(polarity);
+            assert_eq!(val, &expected[i]);
+        }
+        zset.reset();
+
+        // Data appended to existing files and new files are picked up.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("b.csv"))
+            .unwrap()
+            .write_all(b"baz,true,1\n")
+            .unwrap();
+        wait(|| zset.state().flushed.len() == 1, None);
+        fs::write(dir.path().join("c.csv"), "qux,false,2\n").unwrap();
+        wait(|| zset.state().flushed.len() == 2, None);
+        let expected = vec![
+            TestStruct::new("baz".to_string(), true, 1),
+            TestStruct::new("qux".to_string(), false, 2),
+        ];
+        for (i, (val, _polarity)) in zset.state().flushed.iter().enumerate() {
+            assert_eq!(val, &expected[i]);
+        }
+
+        endpoint.disconnect();
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let config: serde_yaml::Value = serde_yaml::from_str("path: \"/tmp/[*.csv\"").unwrap();
+        assert!(FileInputTransport.new_endpoint("test", &config).is_err());
+    }
+
+    #[test]
+    fn test_csv_file_compressed() {
+        let dir = TempDir::new().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"foo,true,10\nbar,false,-10").unwrap();
+        fs::write(dir.path().join("1.csv.gz"), encoder.finish().unwrap()).unwrap();
+        fs::write(dir.path().join("2.csv"), "baz,true,1\n").unwrap();
+
+        let config_str = format!(
+            r#"
+stream: test_input
+transport:
+    name: file
+    config:
+        path: {:?}
+        buffer_size_bytes: 5
+format:
+    name: csv
+"#,
+            dir.path().join("*.csv*").to_str().unwrap()
+        );
+
+        let (endpoint, consumer, zset) =
+            mock_input_pipeline::<TestStruct>(serde_yaml::from_str(&config_str).unwrap()).unwrap();
+        endpoint.start().unwrap();
+        wait(|| consumer.state().eoi, None);
+
+        let expected = vec![
+            TestStruct::new("foo".to_string(), true, 10),
+            TestStruct::new("bar".to_string(), false, -10),
+            TestStruct::new("baz".to_string(), true, 1),
+        ];
+        let flushed = zset
+            .state()
+            .flushed
+            .iter()
+            .map(|(val, _)| val.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, expected);
+    }
+
+    #[test]
+    fn test_csv_file_seek() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.csv"), "foo,true,10\nbar,false,-10\n").unwrap();
+
+        let config_str = format!(
+            r#"
+stream: test_input
+transport:
+    name: file
+    config:
+        path: {:?}
+        follow: true
+format:
+    name: csv
+"#,
+            dir.path().join("*.csv").to_str().unwrap()
+        );
+        let config: InputEndpointConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let (endpoint, _consumer, zset) =
+            mock_input_pipeline::<TestStruct>(config.clone()).unwrap();
+        assert_eq!(
+            endpoint.position(),
+            Some(json!({"offsets": {}, "completed": []}))
+        );
+        endpoint.start().unwrap();
+        wait(|| zset.state().flushed.len() == 2, None);
+
+        // The position is captured at the start of a step.
+        endpoint.step_started();
+        let position = endpoint.position().unwrap();
+        endpoint.disconnect();
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("a.csv"))
+            .unwrap()
+            .write_all(b"baz,true,1\n")
+            .unwrap();
+
+        // A new endpoint resumes from the saved position.
+        let (consumer, zset) =
+            mock_parser_pipeline::<TestStruct>(&config.connector_config.format).unwrap();
+        let mut endpoint = FileInputTransport
+            .new_endpoint("test", &config.connector_config.transport.config)
+            .unwrap();
+        endpoint.seek(position).unwrap();
+        endpoint.connect(Box::new(consumer)).unwrap();
+        endpoint.start().unwrap();
+        wait(|| zset.state().flushed.len() == 1, None);
+        sleep(Duration::from_millis(100));
+        let flushed = zset
+            .state()
+            .flushed
+            .iter()
+            .map(|(val, _)| val.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, vec![TestStruct::new("baz".to_string(), true, 1)]);
+
+        assert!(endpoint.seek(json!("invalid")).is_err());
+        endpoint.disconnect();
+    }
+
+    fn output_endpoint(config: &str) -> FileOutputEndpoint {
+        FileOutputEndpoint::new(serde_yaml::from_str(config).unwrap()).unwrap()
+    }
+
+    fn write_batch(endpoint: &mut FileOutputEndpoint, data: &[u8]) {
+        endpoint.batch_start().unwrap();
+        endpoint.push_buffer(data).unwrap();
+        endpoint.batch_end().unwrap();
+    }
+
+    #[test]
+    fn test_file_output_rotation() {
+        let dir = TempDir::new().unwrap();
+        let mut endpoint = output_endpoint(&format!(
+            "path: {:?}\nmax_file_size_bytes: 10",
+            dir.path().join("out-{index}.csv").to_str().unwrap()
+        ));
+
+        // The first batch exceeds the size limit, the second and third
+        // batches go to separate files.
+        write_batch(&mut endpoint, b"foo,true,10\n");
+        write_batch(&mut endpoint, b"bar,false,-10\n");
+        write_batch(&mut endpoint, b"baz,true,1\n");
+        drop(endpoint);
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out-0.csv")).unwrap(),
+            "foo,true,10\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out-1.csv")).unwrap(),
+            "bar,false,-10\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out-2.csv")).unwrap(),
+            "baz,true,1\n"
+        );
+    }
+
+    #[test]
+    fn test_file_output_compression() {
+        let dir = TempDir::new().unwrap();
+        let mut endpoint = output_endpoint(&format!(
+            "path: {:?}\nmax_file_size_bytes: 1\ncompression: gzip",
+            dir.path().join("out-{index}.csv").to_str().unwrap()
+        ));
+
+        write_batch(&mut endpoint, b"foo,true,10\n");
+        write_batch(&mut endpoint, b"bar,false,-10\n");
+
+        // The first file is compressed in the background.
+        let compressed = dir.path().join("out-0.csv.gz");
+        wait(
+            || compressed.exists() && !dir.path().join("out-0.csv").exists(),
+            None,
+        );
+        let mut decoded = String::new();
+        GzDecoder::new(File::open(&compressed).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "foo,true,10\n");
+
+        // The current file is not compressed.
+        drop(endpoint);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out-1.csv")).unwrap(),
+            "bar,false,-10\n"
+        );
+    }
+
+    #[test]
+    fn test_file_output_invalid_config() {
+        for config in [
+            // Rotation without a placeholder in the path.
+            "path: /tmp/out.csv\nmax_file_size_bytes: 10",
+            // Compression without rotation.
+            "path: /tmp/out.csv\ncompression: zstd",
+        ] {
+            assert!(FileOutputEndpoint::new(serde_yaml::from_str(config).unwrap()).is_err());
+        }
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     ControllerError, InputConsumer, InputEndpoint, ParseError, PipelineState, TransportConfig,
 };
 use actix::Message;
-use actix_web::{web::Payload, HttpResponse};
+use actix_web::{http::header::HeaderMap, web::Payload};
 use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
 use circular_queue::CircularQueue;
 use futures_util::StreamExt;
@@ -137,14 +137,35 @@ impl HttpInputEndpoint {
             .error(fatal, error);
     }
 
+    /// Pass the trace context in HTTP request headers, if any, to the consumer.
+    pub(crate) fn trace_context(&self, headers: &HeaderMap) {
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .collect::<Vec<_>>();
+        self.inner
+            .consumer
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .trace_context(&headers);
+    }
+
     /// Read the `payload` stream and push it to the pipeline.
     ///
     /// Returns on reaching the end of the `payload` stream
-    /// (if any) or when the pipeline terminates.
+    /// (if any) or when the pipeline terminates.  If `abort_on_error` is
+    /// true, stops reading the stream after the first chunk of input that
+    /// contains parse errors.
+    ///
+    /// On success, returns the total number of parse errors and the last
+    /// `MAX_REPORTED_PARSE_ERRORS` errors.
     pub(crate) async fn complete_request(
         &self,
         mut payload: Payload,
-    ) -> Result<HttpResponse, PipelineError> {
+        abort_on_error: bool,
+    ) -> Result<(usize, Vec<ParseError>), PipelineError> {
         debug!("HTTP input endpoint '{}': start of request", self.name());
 
         let mut num_bytes = 0;
@@ -171,6 +192,13 @@ impl HttpInputEndpoint {
                             for error in new_errors.drain(..) {
                                 errors.push(error);
                             }
+                            if abort_on_error && num_errors > 0 {
+                                debug!(
+                                    "HTTP input endpoint '{}': aborting request on parse error",
+                                    self.name()
+                                );
+                                break;
+                            }
                         }
                         Ok(Some(Err(e))) => {
                             self.error(true, anyhow!(e.to_string()));
@@ -197,11 +225,7 @@ impl HttpInputEndpoint {
             "HTTP input endpoint '{}': end of request, {num_bytes} received",
             self.name()
         );
-        if errors.is_empty() {
-            Ok(HttpResponse::Ok().finish())
-        } else {
-            Err(PipelineError::parse_errors(num_errors, errors.asc_iter()))
-        }
+        Ok((num_errors, errors.asc_iter().cloned().collect()))
     }
 }
 
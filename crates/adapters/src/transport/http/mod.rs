@@ -11,10 +11,42 @@ mod output;
 /// start of the stream and can be used to implement reliable delivery.
 /// The payload is stored in the `bin_data`, `text_data`, or `json_data` field
 /// depending on the data format used.
+///
+/// When the stream is requested with `?changelog=true`, chunks additionally
+/// carry the changelog envelope: data chunks are tagged with the `step` that
+/// produced them, and each step is followed by a `step_end` marker chunk that
+/// stores the number of data chunks and payload bytes in the step.  If
+/// `?snapshot_interval=N` is specified, every `N` steps the stream also
+/// contains a `snapshot` marker with cumulative counts since the start of the
+/// stream.
 #[derive(Deserialize, ToSchema)]
 pub struct Chunk {
     pub sequence_number: u64,
 
+    /// Changelog envelope only: the step that produced this chunk, counting
+    /// from 0 at the start of the stream.  For marker chunks, the step that
+    /// the marker follows.
+    pub step: Option<u64>,
+
+    /// Changelog envelope only: set in marker chunks, which carry no payload.
+    pub marker: Option<ChunkMarker>,
+
+    /// `snapshot` markers only: the name of the stream, which is unique for
+    /// each connection.
+    pub stream: Option<String>,
+
+    /// `snapshot` markers only: the number of steps since the start of the
+    /// stream.
+    pub steps: Option<u64>,
+
+    /// Marker chunks only: the number of data chunks in the step
+    /// (`step_end`) or since the start of the stream (`snapshot`).
+    pub chunks: Option<u64>,
+
+    /// Marker chunks only: the number of payload bytes in the step
+    /// (`step_end`) or since the start of the stream (`snapshot`).
+    pub bytes: Option<u64>,
+
     // Exactly one of the following fields must be set.
     // This should be an enum inlined with `#[serde(flatten)]`, but `utoipa`
     // struggles to generate a schema for that.
@@ -29,5 +61,17 @@ pub struct Chunk {
     pub json_data: Option<JsonValue>,
 }
 
+/// Type of a marker chunk in a changelog stream.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkMarker {
+    /// End of a step.
+    StepEnd,
+    /// Periodic snapshot marker.
+    Snapshot,
+}
+
 pub(crate) use input::{HttpInputEndpoint, HttpInputTransport};
-pub(crate) use output::{HttpOutputEndpoint, HttpOutputTransport};
+pub(crate) use output::{
+    ChangelogConfig, CompressionArg, EgressCompression, HttpOutputEndpoint, HttpOutputTransport,
+};
@@ -1,18 +1,25 @@
 use crate::{AsyncErrorCallback, OutputEndpoint, TransportConfig};
-use actix_web::{http::header::ContentType, web::Bytes, HttpResponse};
+use actix_web::{
+    http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, VARY},
+    web::Bytes,
+    HttpRequest, HttpResponse,
+};
 use anyhow::{anyhow, Result as AnyResult};
 use async_stream::stream;
 use crossbeam::sync::ShardedLock;
+use flate2::{write::GzEncoder, Compression};
 use log::debug;
 use log::error;
-use serde::{ser::SerializeStruct, Serializer};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use serde_json::value::RawValue;
 use serde_yaml::Value as YamlValue;
 use std::{
     borrow::Cow,
+    io::{Result as IoResult, Write},
+    mem::take,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     time::Duration,
 };
@@ -25,6 +32,7 @@ use tokio::{
 const MAX_BUFFERS: usize = 100;
 
 enum Format {
+    /// Buffers produced by the encoder are sent to the client as is.
     Binary,
     Text,
     #[allow(dead_code)]
@@ -53,6 +61,218 @@ impl HttpOutputTransport {
     pub(crate) fn default_max_buffered_records() -> u64 {
         100_000
     }
+
+    pub(crate) fn default_min_compressed_chunk_size() -> usize {
+        0
+    }
+}
+
+/// Value of the `?compression=` argument of the `/egress` endpoint.
+///
+/// Overrides the encoding negotiated via the `Accept-Encoding` header, e.g.,
+/// for clients that cannot set request headers.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CompressionArg {
+    /// Send the response uncompressed.
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Content encoding used to compress the body of an egress response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EgressCompression {
+    Gzip,
+    Zstd,
+}
+
+impl EgressCompression {
+    /// Choose the encoding to use based on the `?compression=` argument, if
+    /// specified, or the `Accept-Encoding` header of `req` otherwise.
+    ///
+    /// Returns `None` if the client doesn't accept any of the supported
+    /// encodings, in which case the response is sent uncompressed.  When the
+    /// client accepts both with the same preference, `zstd` wins.
+    pub(crate) fn negotiate(req: &HttpRequest, arg: Option<CompressionArg>) -> Option<Self> {
+        match arg {
+            Some(CompressionArg::None) => return None,
+            Some(CompressionArg::Gzip) => return Some(Self::Gzip),
+            Some(CompressionArg::Zstd) => return Some(Self::Zstd),
+            None => {}
+        }
+        let header = req.headers().get(ACCEPT_ENCODING)?.to_str().ok()?;
+        Self::from_accept_encoding(header)
+    }
+
+    fn from_accept_encoding(header: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+
+        for item in header.split(',') {
+            let mut parts = item.split(';');
+            let encoding = match parts.next().unwrap().trim() {
+                "gzip" | "x-gzip" => Self::Gzip,
+                "zstd" => Self::Zstd,
+                _ => continue,
+            };
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality <= 0.0 {
+                continue;
+            }
+            match best {
+                Some((best_encoding, best_quality))
+                    if best_quality > quality
+                        || (best_quality == quality && best_encoding == Self::Zstd) => {}
+                _ => best = Some((encoding, quality)),
+            }
+        }
+
+        best.map(|(encoding, _)| encoding)
+    }
+
+    fn content_encoding(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+/// Compresses the body of an egress response on the fly.
+///
+/// Chunks are accumulated until at least `min_chunk_size` bytes are pending
+/// and then compressed and flushed together, so that the client can decode
+/// them without waiting for the end of the stream.  Compressing several small
+/// chunks at once improves the compression ratio at the cost of latency.
+struct ChunkCompressor {
+    encoder: Encoder,
+    pending: Vec<u8>,
+    min_chunk_size: usize,
+}
+
+impl ChunkCompressor {
+    fn new(compression: EgressCompression, min_chunk_size: usize) -> IoResult<Self> {
+        let encoder = match compression {
+            EgressCompression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::fast()))
+            }
+            EgressCompression::Zstd => {
+                Encoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), 0)?)
+            }
+        };
+        Ok(Self {
+            encoder,
+            pending: Vec::new(),
+            min_chunk_size,
+        })
+    }
+
+    /// Add `data` to the stream.  Returns compressed data to send to the
+    /// client, if enough data has accumulated or `force` is `true`.
+    fn push(&mut self, data: &[u8], force: bool) -> IoResult<Option<Bytes>> {
+        self.pending.extend_from_slice(data);
+        if force || self.pending.len() >= self.min_chunk_size {
+            self.flush()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Compress and flush all pending data.
+    fn flush(&mut self) -> IoResult<Option<Bytes>> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let pending = take(&mut self.pending);
+        let output = match &mut self.encoder {
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(&pending)?;
+                encoder.flush()?;
+                take(encoder.get_mut())
+            }
+            Encoder::Zstd(encoder) => {
+                encoder.write_all(&pending)?;
+                encoder.flush()?;
+                take(encoder.get_mut())
+            }
+        };
+        Ok(Some(Bytes::from(output)))
+    }
+
+    /// Compress all pending data and terminate the compressed stream.
+    fn finish(mut self) -> IoResult<Bytes> {
+        let pending = take(&mut self.pending);
+        let output = match self.encoder {
+            Encoder::Gzip(mut encoder) => {
+                encoder.write_all(&pending)?;
+                encoder.finish()?
+            }
+            Encoder::Zstd(mut encoder) => {
+                encoder.write_all(&pending)?;
+                encoder.finish()?
+            }
+        };
+        Ok(Bytes::from(output))
+    }
+}
+
+/// Configuration of the changelog envelope.
+///
+/// In changelog mode, each chunk produced by a step of the circuit is tagged
+/// with the step number, and the end of each step is marked by an explicit
+/// `step_end` chunk that carries the number of chunks and bytes in the step.
+/// Together with the `sequence_number` field, this allows the client to detect
+/// missing or truncated steps.  Optionally, every `snapshot_interval` steps,
+/// the endpoint emits a `snapshot` marker with cumulative counts for the
+/// entire stream, which the client can use as a resume point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ChangelogConfig {
+    /// Emit a snapshot marker after every `snapshot_interval` steps.
+    pub snapshot_interval: Option<u64>,
+}
+
+/// Progress of a changelog stream.
+#[derive(Default)]
+struct ChangelogProgress {
+    /// Number of completed steps.
+    steps: u64,
+    /// Number of data chunks in the current step.
+    step_chunks: u64,
+    /// Number of payload bytes in the current step.
+    step_bytes: u64,
+    /// Number of data chunks in the stream.
+    total_chunks: u64,
+    /// Number of payload bytes in the stream.
+    total_bytes: u64,
+}
+
+/// Changelog fields attached to a chunk.
+enum Envelope {
+    /// No changelog fields (changelog mode is disabled or this is a
+    /// keep-alive chunk).
+    None,
+    /// Data chunk produced by step `step`.
+    Data { step: u64 },
+    /// Marks the end of step `step`, which produced `chunks` data chunks
+    /// containing `bytes` bytes of payload.
+    StepEnd { step: u64, chunks: u64, bytes: u64 },
+    /// Periodic snapshot marker emitted after step `step`.  `steps`, `chunks`
+    /// and `bytes` count all steps, data chunks and bytes of payload since the
+    /// start of the stream.
+    Snapshot {
+        step: u64,
+        steps: u64,
+        chunks: u64,
+        bytes: u64,
+    },
 }
 
 #[derive(Clone)]
@@ -73,31 +293,117 @@ impl Buffer {
 struct HttpOutputEndpointInner {
     name: String,
     format: Format,
+    content_type: &'static str,
 
     total_buffers: AtomicU64,
     sender: ShardedLock<Option<broadcast::Sender<Buffer>>>,
     // This endpoint starts with sending a snapshot of a relation.
     snapshot: bool,
     stream: bool,
+    // Compression applied to the response body, if any.
+    compression: Option<EgressCompression>,
+    min_chunk_size: usize,
+    // Changelog envelope configuration; `None` if changelog mode is disabled.
+    changelog: Option<ChangelogConfig>,
+    changelog_progress: Mutex<ChangelogProgress>,
     // async_error_callback: RwLock<Option<AsyncErrorCallback>>,
 }
 
 impl HttpOutputEndpointInner {
-    pub(crate) fn new(name: &str, format: Format, snapshot: bool, stream: bool) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        name: &str,
+        format: Format,
+        content_type: &'static str,
+        snapshot: bool,
+        stream: bool,
+        compression: Option<EgressCompression>,
+        min_chunk_size: usize,
+        changelog: Option<ChangelogConfig>,
+    ) -> Self {
         Self {
             name: name.to_string(),
             format,
+            content_type,
             total_buffers: AtomicU64::new(0),
             sender: ShardedLock::new(Some(broadcast::channel(MAX_BUFFERS).0)),
             snapshot,
             stream,
+            compression,
+            min_chunk_size,
+            changelog,
+            changelog_progress: Mutex::new(ChangelogProgress::default()),
             // async_error_callback: RwLock::new(None),
         }
     }
 
-    fn push_buffer(&self, buffer: Option<&[u8]>) -> AnyResult<()> {
+    /// Push a data chunk produced by the current step.
+    fn push_data(&self, buffer: &[u8]) -> AnyResult<()> {
+        let envelope = if self.changelog.is_some() {
+            let mut progress = self.changelog_progress.lock().unwrap();
+            progress.step_chunks += 1;
+            progress.step_bytes += buffer.len() as u64;
+            progress.total_chunks += 1;
+            progress.total_bytes += buffer.len() as u64;
+            Envelope::Data {
+                step: progress.steps,
+            }
+        } else {
+            Envelope::None
+        };
+        self.push_buffer(Some(buffer), envelope)
+    }
+
+    /// In changelog mode, output the end of step marker for the current step
+    /// followed by a snapshot marker if it is due.
+    fn end_step(&self) -> AnyResult<()> {
+        let Some(changelog) = self.changelog else {
+            return Ok(());
+        };
+
+        let (step_end, snapshot) = {
+            let mut progress = self.changelog_progress.lock().unwrap();
+            let step = progress.steps;
+            let step_end = Envelope::StepEnd {
+                step,
+                chunks: take(&mut progress.step_chunks),
+                bytes: take(&mut progress.step_bytes),
+            };
+            progress.steps += 1;
+            let snapshot = match changelog.snapshot_interval {
+                Some(interval) if progress.steps % interval == 0 => Some(Envelope::Snapshot {
+                    step,
+                    steps: progress.steps,
+                    chunks: progress.total_chunks,
+                    bytes: progress.total_bytes,
+                }),
+                _ => None,
+            };
+            (step_end, snapshot)
+        };
+
+        self.push_buffer(None, step_end)?;
+        if let Some(snapshot) = snapshot {
+            self.push_buffer(None, snapshot)?;
+        }
+        Ok(())
+    }
+
+    fn push_buffer(&self, buffer: Option<&[u8]>, envelope: Envelope) -> AnyResult<()> {
         let seq_number = self.total_buffers.fetch_add(1, Ordering::AcqRel);
 
+        if let Format::Binary = self.format {
+            // Binary output is not framed, so there is no way to send an
+            // empty chunk to the client.
+            match buffer {
+                Some(buffer) if !buffer.is_empty() => {
+                    self.send(Buffer::new(seq_number, Bytes::copy_from_slice(buffer)))
+                }
+                _ => (),
+            }
+            return Ok(());
+        }
+
         let json_buf = Vec::with_capacity(buffer.map(|b| b.len()).unwrap_or(0) + 1024);
         let mut serializer = serde_json::Serializer::new(json_buf);
         let mut struct_serializer = serializer
@@ -107,9 +413,39 @@ impl HttpOutputEndpointInner {
             .serialize_field("sequence_number", &seq_number)
             .map_err(|e| anyhow!("error serializing 'sequence_number' field: '{e}'"))?;
 
+        match envelope {
+            Envelope::None => {}
+            Envelope::Data { step } => {
+                serialize_field(&mut struct_serializer, "step", &step)?;
+            }
+            Envelope::StepEnd {
+                step,
+                chunks,
+                bytes,
+            } => {
+                serialize_field(&mut struct_serializer, "step", &step)?;
+                serialize_field(&mut struct_serializer, "marker", "step_end")?;
+                serialize_field(&mut struct_serializer, "chunks", &chunks)?;
+                serialize_field(&mut struct_serializer, "bytes", &bytes)?;
+            }
+            Envelope::Snapshot {
+                step,
+                steps,
+                chunks,
+                bytes,
+            } => {
+                serialize_field(&mut struct_serializer, "step", &step)?;
+                serialize_field(&mut struct_serializer, "marker", "snapshot")?;
+                serialize_field(&mut struct_serializer, "stream", &self.name)?;
+                serialize_field(&mut struct_serializer, "steps", &steps)?;
+                serialize_field(&mut struct_serializer, "chunks", &chunks)?;
+                serialize_field(&mut struct_serializer, "bytes", &bytes)?;
+            }
+        }
+
         if let Some(buffer) = buffer {
             match self.format {
-                Format::Binary => unimplemented!(),
+                Format::Binary => unreachable!(),
                 Format::Text => {
                     let data_str = std::str::from_utf8(buffer).map_err(|e| {
                         anyhow!("received an invalid UTF8 string from encoder: {e}")
@@ -141,17 +477,31 @@ impl HttpOutputEndpointInner {
         json_buf.push(b'\r');
         json_buf.push(b'\n');
 
+        self.send(Buffer::new(seq_number, Bytes::from(json_buf)));
+        Ok(())
+    }
+
+    fn send(&self, buffer: Buffer) {
         // A failure simply means that there are no receivers.
         let _ = self
             .sender
             .read()
             .unwrap()
             .as_ref()
-            .map(|sender| sender.send(Buffer::new(seq_number, Bytes::from(json_buf))));
-        Ok(())
+            .map(|sender| sender.send(buffer));
     }
 }
 
+fn serialize_field<S, T>(serializer: &mut S, field: &'static str, value: &T) -> AnyResult<()>
+where
+    S: SerializeStruct,
+    T: Serialize + ?Sized,
+{
+    serializer
+        .serialize_field(field, value)
+        .map_err(|e| anyhow!("error serializing '{field}' field: '{e}'"))
+}
+
 struct RequestGuard {
     finalizer: Box<dyn FnMut()>,
 }
@@ -172,21 +522,50 @@ impl Drop for RequestGuard {
 /// HTTP request.
 ///
 /// This implementation provides no support for reliable delivery
-/// and is mostly intended for browser-based testing.
+/// and is mostly intended for browser-based testing.  In changelog mode
+/// (see [`ChangelogConfig`]) the client can at least detect gaps in the
+/// stream and decide where to resume after reconnecting.
 #[derive(Clone)]
 pub(crate) struct HttpOutputEndpoint {
     inner: Arc<HttpOutputEndpointInner>,
 }
 
 impl HttpOutputEndpoint {
-    pub(crate) fn new(name: &str, format: &str, snapshot: bool, stream: bool) -> Self {
-        let format = match format {
-            "csv" => Format::Text,
-            "json" => Format::Json,
-            _ => Format::Binary,
+    /// Create a new endpoint.
+    ///
+    /// If `compression` is specified, the body of the response is
+    /// compressed, accumulating at least `min_chunk_size` bytes of output
+    /// before compressing it and sending it to the client.  Pending data is
+    /// flushed whenever the endpoint is idle for more than 3 seconds.
+    ///
+    /// If `changelog` is specified, chunks are wrapped in the changelog
+    /// envelope.
+    pub(crate) fn new(
+        name: &str,
+        format: &str,
+        snapshot: bool,
+        stream: bool,
+        compression: Option<EgressCompression>,
+        min_chunk_size: usize,
+        changelog: Option<ChangelogConfig>,
+    ) -> Self {
+        let (format, content_type) = match format {
+            "csv" => (Format::Text, "application/json"),
+            "json" => (Format::Json, "application/json"),
+            "arrow" => (Format::Binary, "application/vnd.apache.arrow.stream"),
+            _ => (Format::Binary, "application/octet-stream"),
         };
         Self {
-            inner: Arc::new(HttpOutputEndpointInner::new(name, format, snapshot, stream)),
+            inner: Arc::new(HttpOutputEndpointInner::new(
+                name,
+                format,
+                content_type,
+                snapshot,
+                stream,
+                compression,
+                min_chunk_size,
+                changelog,
+            )),
         }
     }
 
@@ -194,6 +573,12 @@ impl HttpOutputEndpoint {
         self.inner.name.as_str()
     }
 
+    /// Returns `true` if the output of the endpoint is framed as a stream of
+    /// JSON chunks, which is required by the changelog envelope.
+    pub(crate) fn is_framed(&self) -> bool {
+        !matches!(self.inner.format, Format::Binary)
+    }
+
     fn connect(&self) -> broadcast::Receiver<Buffer> {
         self.inner
             .sender
@@ -217,11 +602,32 @@ impl HttpOutputEndpoint {
         let guard = RequestGuard::new(finalizer);
 
         let inner = self.inner.clone();
+        let compression = inner.compression;
+        let min_chunk_size = inner.min_chunk_size;
+
+        let mut builder = HttpResponse::Ok();
+        builder.insert_header((CONTENT_TYPE, inner.content_type));
+        if let Some(compression) = compression {
+            builder
+                .insert_header((CONTENT_ENCODING, compression.content_encoding()))
+                .insert_header((VARY, "accept-encoding"));
+        }
 
-        HttpResponse::Ok()
-            .insert_header(ContentType::json())
+        builder
             .streaming(stream! {
                 let _guard = guard;
+                let mut compressor = match compression.map(|c| ChunkCompressor::new(c, min_chunk_size)) {
+                    None => None,
+                    Some(Ok(compressor)) => Some(compressor),
+                    Some(Err(e)) => {
+                        yield <AnyResult<_>>::Err(anyhow!("error creating compressor: {e}"));
+                        return;
+                    }
+                };
+                // Set when the endpoint has been idle; forces the compressor
+                // to flush the next chunk, so keep-alive chunks reach the
+                // client.
+                let mut idle = false;
                 loop {
                     // There is a bug in actix (https://github.com/actix/actix-web/issues/1313)
                     // that prevents it from dropping HTTP connections on client disconnect
@@ -233,7 +639,8 @@ impl HttpOutputEndpoint {
                         Err(_) => {
                             // Send the empty chunk via the `push_buffer` method to
                             // make sure it gets assigned correct sequence number.
-                            let _ = inner.push_buffer(None);
+                            let _ = inner.push_buffer(None, Envelope::None);
+                            idle = true;
                         }
                         Ok(Err(RecvError::Closed)) => break,
                         Ok(Err(RecvError::Lagged(_))) => (),
@@ -244,10 +651,24 @@ impl HttpOutputEndpoint {
                                 buffer.sequence_number,
                                 buffer.data.len(),
                             );
-                            yield <AnyResult<_>>::Ok(buffer.data);
+                            match compressor.as_mut().map(|c| c.push(&buffer.data, idle)) {
+                                None => yield Ok(buffer.data),
+                                Some(Ok(Some(data))) => yield Ok(data),
+                                Some(Ok(None)) => (),
+                                Some(Err(e)) => {
+                                    yield Err(anyhow!("error compressing chunk: {e}"));
+                                    return;
+                                }
+                            }
+                            idle = false;
                         },
                     }
                 }
+                if let Some(compressor) = compressor {
+                    yield compressor
+                        .finish()
+                        .map_err(|e| anyhow!("error compressing chunk: {e}"));
+                }
             })
     }
 }
@@ -264,7 +685,7 @@ impl OutputEndpoint for HttpOutputEndpoint {
     }
 
     fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
-        self.inner.push_buffer(Some(buffer))
+        self.inner.push_data(buffer)
     }
 
     fn batch_end(&mut self) -> AnyResult<()> {
@@ -276,12 +697,176 @@ impl OutputEndpoint for HttpOutputEndpoint {
         // the snapshot mode.  The receiver will receive all buffered
         // messages followed by a `RecvError::Closed` notification.
         if self.inner.snapshot && self.inner.total_buffers.load(Ordering::Acquire) == 0 {
-            let _ = self.inner.push_buffer(Some(&[]));
+            let _ = self.inner.push_data(&[]);
         }
 
+        self.inner.end_step()?;
+
         if !self.inner.stream {
             *self.inner.sender.write().unwrap() = None;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ChangelogConfig, ChunkCompressor, CompressionArg, EgressCompression, HttpOutputEndpoint,
+    };
+    use crate::{transport::http::Chunk, transport::http::ChunkMarker, OutputEndpoint};
+    use actix_web::{http::header::ACCEPT_ENCODING, test::TestRequest};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn negotiate_compression() {
+        assert_eq!(EgressCompression::from_accept_encoding("identity"), None);
+        assert_eq!(
+            EgressCompression::from_accept_encoding("gzip, deflate, br"),
+            Some(EgressCompression::Gzip)
+        );
+        assert_eq!(
+            EgressCompression::from_accept_encoding("gzip, zstd"),
+            Some(EgressCompression::Zstd)
+        );
+        assert_eq!(
+            EgressCompression::from_accept_encoding("zstd;q=0.5, gzip;q=0.8"),
+            Some(EgressCompression::Gzip)
+        );
+        assert_eq!(
+            EgressCompression::from_accept_encoding("zstd;q=0, gzip;q=0"),
+            None
+        );
+    }
+
+    #[test]
+    fn compression_argument() {
+        let req = TestRequest::default()
+            .insert_header((ACCEPT_ENCODING, "gzip"))
+            .to_http_request();
+        assert_eq!(
+            EgressCompression::negotiate(&req, None),
+            Some(EgressCompression::Gzip)
+        );
+        assert_eq!(
+            EgressCompression::negotiate(&req, Some(CompressionArg::Zstd)),
+            Some(EgressCompression::Zstd)
+        );
+        assert_eq!(
+            EgressCompression::negotiate(&req, Some(CompressionArg::None)),
+            None
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(EgressCompression::negotiate(&req, None), None);
+        assert_eq!(
+            EgressCompression::negotiate(&req, Some(CompressionArg::Gzip)),
+            Some(EgressCompression::Gzip)
+        );
+    }
+
+    fn compress(compression: EgressCompression, min_chunk_size: usize) -> (Vec<u8>, usize) {
+        let mut compressor = ChunkCompressor::new(compression, min_chunk_size).unwrap();
+        let mut output = Vec::new();
+        let mut chunks = 0;
+        for i in 0..10 {
+            let chunk = format!("{{\"sequence_number\":{i},\"text_data\":\"foo,bar\\n\"}}\r\n");
+            if let Some(data) = compressor.push(chunk.as_bytes(), false).unwrap() {
+                output.extend_from_slice(&data);
+                chunks += 1;
+            }
+        }
+        output.extend_from_slice(&compressor.finish().unwrap());
+        (output, chunks)
+    }
+
+    fn expected() -> String {
+        (0..10)
+            .map(|i| format!("{{\"sequence_number\":{i},\"text_data\":\"foo,bar\\n\"}}\r\n"))
+            .collect()
+    }
+
+    #[test]
+    fn gzip_chunks() {
+        let (output, chunks) = compress(EgressCompression::Gzip, 0);
+        assert_eq!(chunks, 10);
+
+        let mut decoded = String::new();
+        GzDecoder::new(output.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected());
+    }
+
+    #[test]
+    fn zstd_min_chunk_size() {
+        // Each input chunk is 47 bytes long, so every third chunk triggers
+        // a flush.
+        let (output, chunks) = compress(EgressCompression::Zstd, 100);
+        assert_eq!(chunks, 3);
+
+        let decoded = zstd::stream::decode_all(output.as_slice()).unwrap();
+        assert_eq!(String::from_utf8(decoded).unwrap(), expected());
+    }
+
+    #[test]
+    fn changelog() {
+        let mut endpoint = HttpOutputEndpoint::new(
+            "test",
+            "csv",
+            false,
+            true,
+            None,
+            0,
+            Some(ChangelogConfig {
+                snapshot_interval: Some(2),
+            }),
+        );
+        let mut receiver = endpoint.connect();
+
+        for step in 0..3 {
+            for _ in 0..=step {
+                endpoint.push_buffer(b"foo,bar\n").unwrap();
+            }
+            endpoint.batch_end().unwrap();
+        }
+
+        let mut chunks = Vec::new();
+        while let Ok(buffer) = receiver.try_recv() {
+            chunks.push(serde_json::from_slice::<Chunk>(&buffer.data).unwrap());
+        }
+
+        // 6 data chunks, 3 step_end markers, and a snapshot marker after the
+        // second step.
+        assert_eq!(chunks.len(), 10);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.sequence_number, i as u64);
+        }
+
+        let summary = chunks
+            .iter()
+            .map(|chunk| (chunk.step.unwrap(), chunk.marker, chunk.chunks, chunk.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (0, None, None, None),
+                (0, Some(ChunkMarker::StepEnd), Some(1), Some(8)),
+                (1, None, None, None),
+                (1, None, None, None),
+                (1, Some(ChunkMarker::StepEnd), Some(2), Some(16)),
+                (1, Some(ChunkMarker::Snapshot), Some(3), Some(24)),
+                (2, None, None, None),
+                (2, None, None, None),
+                (2, None, None, None),
+                (2, Some(ChunkMarker::StepEnd), Some(3), Some(24)),
+            ]
+        );
+
+        assert_eq!(chunks[0].text_data.as_deref(), Some("foo,bar\n"));
+        assert_eq!(chunks[5].steps, Some(2));
+        assert_eq!(chunks[5].stream.as_deref(), Some("test"));
+        assert!(chunks[1].text_data.is_none());
+    }
+}
@@ -1,23 +1,30 @@
-use super::{default_redpanda_server, refine_kafka_error, KafkaLogLevel};
-use crate::{InputConsumer, InputEndpoint, InputTransport, PipelineState};
+use super::{default_redpanda_server, refine_kafka_error, KafkaLogLevel, SchemaRegistryConfig};
+use crate::{
+    format::avro::schema_registry::SchemaRegistryClient, InputConsumer, InputEndpoint,
+    InputTransport, PipelineState,
+};
 use anyhow::{anyhow, bail, Error as AnyError, Result as AnyResult};
 use crossbeam::queue::ArrayQueue;
 use log::debug;
 use num_traits::FromPrimitive;
 use rdkafka::{
     config::{FromClientConfigAndContext, RDKafkaLogLevel},
-    consumer::{BaseConsumer, Consumer, ConsumerContext, Rebalance, RebalanceProtocol},
+    consumer::{BaseConsumer, CommitMode, Consumer, ConsumerContext, Rebalance, RebalanceProtocol},
     error::{KafkaError, KafkaResult},
-    ClientConfig, ClientContext, Message,
+    message::Headers,
+    statistics::Statistics,
+    ClientConfig, ClientContext, Message, Offset, TopicPartitionList,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
 use serde_yaml::Value as YamlValue;
 use std::{
     borrow::Cow,
     collections::BTreeMap,
+    mem::take,
     sync::{
         atomic::{AtomicU32, Ordering},
-        Arc, Mutex, Weak,
+        Arc, Condvar, Mutex, Weak,
     },
     thread::spawn,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
@@ -32,6 +39,14 @@ use utoipa::{
 
 const POLL_TIMEOUT: Duration = Duration::from_millis(100);
 
+/// Timeout for broker requests issued when positioning newly assigned
+/// partitions at the configured start offset.
+const OFFSETS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default interval at which librdkafka reports statistics used to compute
+/// consumer lag.
+const DEFAULT_STATISTICS_INTERVAL_MS: &str = "5000";
+
 // Size of the circular buffer used to pass errors from ClientContext
 // to the worker thread.
 const ERROR_BUFFER_SIZE: usize = 1000;
@@ -78,6 +93,11 @@ pub struct KafkaInputConfig {
     ///
     /// * "enable.auto.commit", if present, must be set to "false",
     /// * "enable.auto.offset.store", if present, must be set to "false"
+    ///
+    /// Set "group.id" to make the endpoint a member of a consumer group, e.g.,
+    /// to share partitions among several pipelines or to resume from
+    /// committed offsets (see `commit_offsets`).  By default, each endpoint
+    /// uses a unique group id.
     #[serde(flatten)]
     pub kafka_options: BTreeMap<String, String>,
 
@@ -94,6 +114,52 @@ pub struct KafkaInputConfig {
     /// consumer group during initialization.
     #[serde(default = "default_group_join_timeout_secs")]
     pub group_join_timeout_secs: u32,
+
+    /// Where to start reading partitions that don't have a committed offset
+    /// in the consumer group.
+    ///
+    /// When not specified, the "auto.offset.reset" librdkafka option
+    /// determines the start offset.
+    pub start_offset: Option<KafkaStartOffset>,
+
+    /// Use the cooperative incremental rebalancing protocol, which only
+    /// moves the partitions that change owners when consumers join or leave
+    /// the group, instead of revoking all partitions on each rebalance.
+    #[serde(default)]
+    pub cooperative_rebalancing: bool,
+
+    /// Commit offsets of consumed messages to the consumer group once the
+    /// circuit has processed them.
+    ///
+    /// Offsets are committed after the step of the circuit that processed
+    /// the messages completes, so a consumer that restarts with the same
+    /// "group.id" resumes after the last processed message.
+    #[serde(default)]
+    pub commit_offsets: bool,
+
+    /// Decode messages framed by Confluent serializers using the schema
+    /// registry.
+    ///
+    /// Each message is converted into a JSON document, which should be
+    /// parsed using the `json` format with `update_format: "raw"`.
+    pub schema_registry: Option<SchemaRegistryConfig>,
+}
+
+/// Where a Kafka consumer starts reading a partition.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, ToSchema)]
+pub enum KafkaStartOffset {
+    /// Start from the earliest available message.
+    #[serde(rename = "earliest")]
+    Earliest,
+
+    /// Only read messages produced after the consumer has joined the group.
+    #[serde(rename = "latest")]
+    Latest,
+
+    /// Start from the earliest message whose timestamp, in milliseconds
+    /// since the UNIX epoch, is greater than or equal to the specified value.
+    #[serde(rename = "timestamp")]
+    Timestamp(i64),
 }
 
 // The auto-derived implementation gets confused by the flattened
@@ -123,6 +189,26 @@ impl<'s> ToSchema<'s> for KafkaInputConfig {
                         .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int32)))
                         .description(Some("Maximum timeout in seconds to wait for the endpoint to join the Kafka consumer group during initialization.")),
                 )
+                .property(
+                    "start_offset",
+                    KafkaStartOffset::schema().1
+                )
+                .property(
+                    "cooperative_rebalancing",
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::Boolean)
+                        .description(Some("Use the cooperative incremental rebalancing protocol.")),
+                )
+                .property(
+                    "commit_offsets",
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::Boolean)
+                        .description(Some("Commit offsets of consumed messages to the consumer group once the circuit has processed them.")),
+                )
+                .property(
+                    "schema_registry",
+                    SchemaRegistryConfig::schema().1
+                )
                 .additional_properties(Some(
                         ObjectBuilder::new()
                         .schema_type(SchemaType::String)
@@ -138,14 +224,13 @@ used to configure the Kafka producer."#))))
 impl KafkaInputConfig {
     /// Set `option` to `val`; return an error if `option` is set to a different
     /// value.
-    #[allow(dead_code)]
     fn enforce_option(&mut self, option: &str, val: &str) -> AnyResult<()> {
         let option_val = self
             .kafka_options
             .entry(option.to_string())
             .or_insert_with(|| val.to_string());
         if option_val != val {
-            Err(AnyError::msg("cannot override '{option}' option: the Kafka transport adapter sets this option to '{val}'"))?;
+            bail!("cannot override '{option}' option: the Kafka transport adapter sets this option to '{val}'");
         }
         Ok(())
     }
@@ -167,19 +252,41 @@ impl KafkaInputConfig {
         // start reading from the offset specified in `auto.offset.reset`.  We used to set these to
         // `true`, which caused `rdkafka` to hang in some circumstances
         // (https://github.com/confluentinc/librdkafka/issues/3954).  Besides, the new behavior
-        // is probably more correct given that circuit state currently does not survive across
-        // pipeline restarts, so it makes sense to start feeding messages from the start rather
-        // than from the last offset consumed by the previous instance of the pipeline, whose state
-        // is lost.  Once we add fault tolerance, we will likely use explicit commits,
-        // which also do not require these options.
+        // is probably more correct given that circuit state does not survive across pipeline
+        // restarts unless the pipeline is restored from a checkpoint, so it makes sense to start
+        // feeding messages from the start rather than from the last offset consumed by the
+        // previous instance of the pipeline, whose state is lost.  When the pipeline is restored
+        // from a checkpoint, the endpoint resumes from the offsets stored in the checkpoint
+        // instead (see `InputEndpoint::seek`).
         //
         // See https://docs.confluent.io/platform/current/clients/consumer.html#offset-management
         //
         // Note: we allow the user to override the options, so they can still enable auto commit
         // if they know what they are doing, e.g., the secops demo requires the pipeline to commit
-        // its offset for the generator to know when to resume sending.
-        self.set_option_if_missing("enable.auto.commit", "false");
-        self.set_option_if_missing("enable.auto.offset.store", "false");
+        // its offset for the generator to know when to resume sending.  With `commit_offsets`,
+        // the endpoint commits offsets explicitly once the circuit has processed the data.
+        if self.commit_offsets {
+            self.enforce_option("enable.auto.commit", "false")?;
+            self.enforce_option("enable.auto.offset.store", "false")?;
+        } else {
+            self.set_option_if_missing("enable.auto.commit", "false");
+            self.set_option_if_missing("enable.auto.offset.store", "false");
+        }
+
+        match self.start_offset {
+            Some(KafkaStartOffset::Earliest) => {
+                self.enforce_option("auto.offset.reset", "earliest")?
+            }
+            Some(KafkaStartOffset::Latest) => self.enforce_option("auto.offset.reset", "latest")?,
+            Some(KafkaStartOffset::Timestamp(_)) | None => {}
+        }
+
+        if self.cooperative_rebalancing {
+            self.enforce_option("partition.assignment.strategy", "cooperative-sticky")?;
+        }
+
+        // Statistics are used to report consumer lag in endpoint stats.
+        self.set_option_if_missing("statistics.interval.ms", DEFAULT_STATISTICS_INTERVAL_MS);
 
         let group_id = format!(
             "{}",
@@ -235,37 +342,115 @@ impl ClientContext for KafkaInputContext {
         }
     }
 
-    /*fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
-        println!("log: {} {}", fac, log_message);
+    fn stats(&self, statistics: Statistics) {
+        if let Some(endpoint) = self.endpoint.lock().unwrap().upgrade() {
+            *endpoint.statistics.lock().unwrap() = Some(statistics);
+        }
     }
 
-    fn stats(&self, statistics: rdkafka::Statistics) {
-        println!("stats: {:?}", statistics)
+    /*fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
+        println!("log: {} {}", fac, log_message);
     }*/
 }
 
 impl ConsumerContext for KafkaInputContext {
     fn post_rebalance(&self, rebalance: &Rebalance<'_>) {
         // println!("Rebalance: {rebalance:?}");
-        if matches!(rebalance, Rebalance::Assign(_)) {
-            if let Some(endpoint) = self.endpoint.lock().unwrap().upgrade() {
-                if endpoint.state() == PipelineState::Running {
-                    let _ = endpoint.resume_partitions();
-                } else {
-                    let _ = endpoint.pause_partitions();
+        match rebalance {
+            Rebalance::Assign(partitions) => {
+                if let Some(endpoint) = self.endpoint.lock().unwrap().upgrade() {
+                    if endpoint.state() == PipelineState::Running {
+                        let _ = endpoint.resume_partitions();
+                    } else {
+                        let _ = endpoint.pause_partitions();
+                    }
+
+                    // Newly assigned partitions get positioned at the start offset by the
+                    // worker thread, since this requires blocking broker requests.
+                    endpoint.new_partitions.lock().unwrap().extend(
+                        partitions
+                            .elements()
+                            .iter()
+                            .map(|elem| (elem.topic().to_string(), elem.partition())),
+                    );
+                }
+            }
+            Rebalance::Revoke(partitions) => {
+                // Stop tracking partitions owned by other consumers now.
+                if let Some(endpoint) = self.endpoint.lock().unwrap().upgrade() {
+                    let mut consumed_offsets = endpoint.consumed_offsets.lock().unwrap();
+                    for elem in partitions.elements() {
+                        consumed_offsets.remove(&(elem.topic().to_string(), elem.partition()));
+                    }
                 }
             }
+            Rebalance::Error(_) => {}
         }
 
         // println!("Rebalance complete");
     }
 }
 
+/// Message offsets by topic and partition.
+type PartitionOffsets = BTreeMap<(String, i32), i64>;
+
+/// Position of a Kafka input endpoint stored in a checkpoint.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct KafkaPosition {
+    partitions: Vec<KafkaPartitionPosition>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct KafkaPartitionPosition {
+    topic: String,
+    partition: i32,
+
+    /// Offset of the next message to read from the partition.
+    offset: i64,
+}
+
 struct KafkaInputEndpointInner {
     config: KafkaInputConfig,
     state: AtomicU32,
     kafka_consumer: BaseConsumer<KafkaInputContext>,
     errors: ArrayQueue<(KafkaError, String)>,
+
+    /// Offsets of the last messages pushed to the input consumer.
+    consumed_offsets: Mutex<PartitionOffsets>,
+
+    /// Snapshot of `consumed_offsets` taken when the current step of the
+    /// circuit started.  These offsets get committed once the step completes.
+    pending_offsets: Mutex<Option<PartitionOffsets>>,
+
+    /// Snapshot of `consumed_offsets` taken when the latest step of the
+    /// circuit started, reported as the position of the endpoint in
+    /// checkpoints.
+    step_offsets: Mutex<PartitionOffsets>,
+
+    /// Offsets of the next messages to read, restored from a checkpoint, for
+    /// partitions that haven't been assigned to the consumer yet.
+    restored_offsets: Mutex<PartitionOffsets>,
+
+    /// A step of the circuit is in progress.  Messages are not pushed to the
+    /// consumer during a step, so that `step_offsets` covers exactly the
+    /// messages processed by the step.
+    stepping: Mutex<bool>,
+
+    /// Signaled when `stepping` is cleared.
+    step_done: Condvar,
+
+    /// Offsets committed to the consumer group.
+    committed_offsets: Mutex<PartitionOffsets>,
+
+    /// Partitions assigned to the consumer that haven't been positioned at
+    /// `config.start_offset` yet.
+    new_partitions: Mutex<Vec<(String, i32)>>,
+
+    /// The latest statistics reported by librdkafka.
+    statistics: Mutex<Option<Statistics>>,
+
+    /// Schema registry client used to decode messages.
+    schema_registry: Option<SchemaRegistryClient>,
 }
 
 impl KafkaInputEndpointInner {
@@ -291,11 +476,26 @@ impl KafkaInputEndpointInner {
         // Create Kafka consumer.
         let kafka_consumer = BaseConsumer::from_config_and_context(&client_config, context)?;
 
+        let schema_registry = config
+            .schema_registry
+            .as_ref()
+            .map(SchemaRegistryClient::new);
+
         let endpoint = Arc::new(Self {
             config,
             state: AtomicU32::new(PipelineState::Paused as u32),
             kafka_consumer,
             errors: ArrayQueue::new(ERROR_BUFFER_SIZE),
+            consumed_offsets: Mutex::new(BTreeMap::new()),
+            pending_offsets: Mutex::new(None),
+            step_offsets: Mutex::new(BTreeMap::new()),
+            restored_offsets: Mutex::new(BTreeMap::new()),
+            stepping: Mutex::new(false),
+            step_done: Condvar::new(),
+            committed_offsets: Mutex::new(BTreeMap::new()),
+            new_partitions: Mutex::new(Vec::new()),
+            statistics: Mutex::new(None),
+            schema_registry,
         });
 
         Ok(endpoint)
@@ -351,6 +551,271 @@ impl KafkaInputEndpointInner {
     fn refine_error(&self, e: KafkaError) -> (bool, AnyError) {
         refine_kafka_error(self.kafka_consumer.client(), e)
     }
+
+    /// Push the payload of `message` to `consumer` and record its offset.
+    fn input_message<M: Message>(&self, message: &M, consumer: &mut dyn InputConsumer) {
+        // Wait for the current step to complete, and keep the next step from
+        // starting until the message has been pushed and its offset recorded.
+        let _stepping = self
+            .step_done
+            .wait_while(self.stepping.lock().unwrap(), |stepping| *stepping)
+            .unwrap();
+
+        // Skip messages at or before the last consumed offset, e.g., messages
+        // fetched before the partition was positioned at an offset restored
+        // from a checkpoint.  They have already been processed.
+        let key = (message.topic().to_string(), message.partition());
+        if let Some(consumed_offset) = self.consumed_offsets.lock().unwrap().get(&key) {
+            if message.offset() <= *consumed_offset {
+                return;
+            }
+        }
+
+        // Pass the trace context in message headers, if any, to the consumer.
+        // Messages without headers clear the context of the previous message.
+        let headers = message
+            .headers()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|header| {
+                        Some((header.key, std::str::from_utf8(header.value?).ok()?))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        consumer.trace_context(&headers);
+        consumer.partition(message.partition() as u32);
+        consumer.source_offset(&json!({
+            "topic": message.topic(),
+            "partition": message.partition(),
+            "offset": message.offset(),
+        }));
+
+        if let Some(payload) = message.payload() {
+            match &self.schema_registry {
+                None => {
+                    // Leave it to the controller to handle errors.  There is noone we can
+                    // forward the error to upstream.
+                    let _ = consumer.input_chunk(payload);
+                }
+                Some(schema_registry) => match schema_registry.decode(payload) {
+                    Ok(json) => {
+                        let _ = consumer.input_chunk(&json);
+                    }
+                    Err(e) => consumer.error(false, e),
+                },
+            }
+        }
+
+        // Record the offset _after_ pushing the message to the consumer, so
+        // that a step that starts after this point is guaranteed to process
+        // the message.
+        self.consumed_offsets
+            .lock()
+            .unwrap()
+            .insert(key, message.offset());
+    }
+
+    /// Position partitions assigned since the last call at the offsets
+    /// restored from a checkpoint or at the configured start timestamp.
+    ///
+    /// Checkpointed offsets take precedence over committed offsets.  Other
+    /// partitions that have a committed offset in the consumer group resume
+    /// from that offset.
+    fn seek_new_partitions(&self) -> KafkaResult<()> {
+        let new_partitions = take(&mut *self.new_partitions.lock().unwrap());
+        if new_partitions.is_empty() {
+            return Ok(());
+        }
+
+        let mut partitions = TopicPartitionList::new();
+        for (topic, partition) in new_partitions.into_iter() {
+            let restored_offset = self
+                .restored_offsets
+                .lock()
+                .unwrap()
+                .remove(&(topic.clone(), partition));
+            match restored_offset {
+                Some(offset) => self.kafka_consumer.seek(
+                    &topic,
+                    partition,
+                    Offset::Offset(offset),
+                    OFFSETS_TIMEOUT,
+                )?,
+                None => {
+                    partitions.add_partition(&topic, partition);
+                }
+            }
+        }
+
+        let timestamp = match self.config.start_offset {
+            Some(KafkaStartOffset::Timestamp(timestamp)) => timestamp,
+            _ => return Ok(()),
+        };
+        if partitions.count() == 0 {
+            return Ok(());
+        }
+
+        let committed = self
+            .kafka_consumer
+            .committed_offsets(partitions, OFFSETS_TIMEOUT)?;
+
+        let mut timestamps = TopicPartitionList::new();
+        for elem in committed.elements() {
+            if elem.offset() == Offset::Invalid {
+                timestamps.add_partition_offset(
+                    elem.topic(),
+                    elem.partition(),
+                    Offset::Offset(timestamp),
+                )?;
+            }
+        }
+        if timestamps.count() == 0 {
+            return Ok(());
+        }
+
+        let offsets = self
+            .kafka_consumer
+            .offsets_for_times(timestamps, OFFSETS_TIMEOUT)?;
+        for elem in offsets.elements() {
+            self.kafka_consumer.seek(
+                elem.topic(),
+                elem.partition(),
+                elem.offset(),
+                OFFSETS_TIMEOUT,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn step_started(&self) {
+        let mut stepping = self.stepping.lock().unwrap();
+        *stepping = true;
+
+        let consumed_offsets = self.consumed_offsets.lock().unwrap().clone();
+        if self.config.commit_offsets {
+            *self.pending_offsets.lock().unwrap() = Some(consumed_offsets.clone());
+        }
+        *self.step_offsets.lock().unwrap() = consumed_offsets;
+    }
+
+    fn position(&self) -> KafkaPosition {
+        let partitions = self
+            .step_offsets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((topic, partition), offset)| KafkaPartitionPosition {
+                topic: topic.clone(),
+                partition: *partition,
+                offset: offset + 1,
+            })
+            .collect();
+        KafkaPosition { partitions }
+    }
+
+    /// Resume from `position` once the partitions in it get assigned to the
+    /// consumer.
+    fn seek(&self, position: KafkaPosition) {
+        let mut consumed_offsets = self.consumed_offsets.lock().unwrap();
+        let mut restored_offsets = self.restored_offsets.lock().unwrap();
+        for KafkaPartitionPosition {
+            topic,
+            partition,
+            offset,
+        } in position.partitions
+        {
+            // Treat messages before the checkpointed offset as consumed, so
+            // that they are included in the position of the endpoint until it
+            // reads new messages from the partition.
+            consumed_offsets.insert((topic.clone(), partition), offset - 1);
+            restored_offsets.insert((topic, partition), offset);
+        }
+        *self.step_offsets.lock().unwrap() = consumed_offsets.clone();
+    }
+
+    /// Let the worker thread push messages to the consumer again.
+    fn end_step(&self) {
+        *self.stepping.lock().unwrap() = false;
+        self.step_done.notify_all();
+    }
+
+    fn step_completed(&self) {
+        self.end_step();
+
+        let pending_offsets = match self.pending_offsets.lock().unwrap().take() {
+            Some(offsets) => offsets,
+            None => return,
+        };
+
+        let mut committed_offsets = self.committed_offsets.lock().unwrap();
+        let mut partitions = TopicPartitionList::new();
+
+        for ((topic, partition), offset) in pending_offsets.into_iter() {
+            if committed_offsets.get(&(topic.clone(), partition)) == Some(&offset) {
+                continue;
+            }
+            // The committed offset is the offset of the next message to read.
+            if partitions
+                .add_partition_offset(&topic, partition, Offset::Offset(offset + 1))
+                .is_ok()
+            {
+                committed_offsets.insert((topic, partition), offset);
+            }
+        }
+
+        if partitions.count() > 0 {
+            if let Err(e) = self.kafka_consumer.commit(&partitions, CommitMode::Async) {
+                self.push_error(e, "failed to commit Kafka consumer offsets");
+            }
+        }
+    }
+
+    /// Transport metrics reported to the controller: consumed and committed
+    /// offsets and consumer lag for each partition, and the total lag across
+    /// all partitions.
+    fn metrics(&self, statistics: &Statistics) -> JsonValue {
+        let consumed_offsets = self.consumed_offsets.lock().unwrap();
+        let committed_offsets = self.committed_offsets.lock().unwrap();
+
+        let mut partitions = Vec::new();
+        let mut total_lag = None;
+        for (topic_name, topic) in statistics.topics.iter() {
+            for (partition_id, partition) in topic.partitions.iter() {
+                // Skip the internal unassigned partition.
+                if *partition_id < 0 {
+                    continue;
+                }
+                let key = (topic_name.clone(), *partition_id);
+                let consumed_offset = consumed_offsets.get(&key).copied();
+                let committed_offset = committed_offsets.get(&key).copied();
+
+                // Number of messages that haven't been processed by the pipeline yet.
+                let processed_offset = if self.config.commit_offsets {
+                    committed_offset
+                } else {
+                    consumed_offset
+                };
+                let lag = processed_offset.map(|offset| (partition.hi_offset - offset - 1).max(0));
+                if let Some(lag) = lag {
+                    *total_lag.get_or_insert(0) += lag;
+                }
+
+                partitions.push(json!({
+                    "topic": topic_name,
+                    "partition": partition_id,
+                    "high_watermark": partition.hi_offset,
+                    "consumed_offset": consumed_offset,
+                    "committed_offset": committed_offset,
+                    "lag": lag,
+                }));
+            }
+        }
+
+        json!({ "partitions": partitions, "lag": total_lag })
+    }
 }
 
 impl KafkaInputEndpoint {
@@ -401,14 +866,23 @@ impl KafkaInputEndpoint {
                     // println!("received {} bytes", message.payload().unwrap().len());
                     // message.payload().map(|payload| consumer.input(payload));
 
-                    if let Some(payload) = message.payload() {
-                        // Leave it to the controller to handle errors.  There is noone we can
-                        // forward the error to upstream.
-                        let _ = consumer.input_chunk(payload);
-                    }
+                    endpoint.input_message(&message, consumer.as_mut());
                 }
             }
 
+            if let Err(e) = endpoint.seek_new_partitions() {
+                let (fatal, e) = endpoint.refine_error(e);
+                consumer.error(fatal, e);
+                if fatal {
+                    return;
+                }
+            }
+
+            let statistics = endpoint.statistics.lock().unwrap().take();
+            if let Some(statistics) = statistics {
+                consumer.transport_metrics(endpoint.metrics(&statistics));
+            }
+
             while let Some((error, reason)) = endpoint.pop_error() {
                 let (fatal, _e) = endpoint.refine_error(error);
                 // `reason` contains a human-readable description of the
@@ -456,11 +930,7 @@ impl InputEndpoint for KafkaInputEndpoint {
                     // `KafkaInputContext` should instantly pause the topic upon connecting to it.
                     // Hopefully, this guarantees that we won't see any messages from it, but if
                     // that's not the case, there shouldn't be any harm in sending them downstream.
-                    if let Some(payload) = message.payload() {
-                        // Leave it to the controller to handle errors.  There is noone we can
-                        // forward the error to upstream.
-                        let _ = consumer.input_chunk(payload);
-                    }
+                    self.0.input_message(&message, consumer.as_mut());
                 }
                 _ => (),
             }
@@ -509,6 +979,29 @@ impl InputEndpoint for KafkaInputEndpoint {
 
     fn disconnect(&self) {
         self.0.set_state(PipelineState::Terminated);
+
+        // Don't leave the worker thread waiting for a step that may never
+        // complete.
+        self.0.end_step();
+    }
+
+    fn step_started(&self) {
+        self.0.step_started();
+    }
+
+    fn step_completed(&self) {
+        self.0.step_completed();
+    }
+
+    fn position(&self) -> Option<JsonValue> {
+        serde_json::to_value(self.0.position()).ok()
+    }
+
+    fn seek(&mut self, position: JsonValue) -> AnyResult<()> {
+        let position = KafkaPosition::deserialize(position)
+            .map_err(|e| anyhow!("invalid Kafka input position: {e}"))?;
+        self.0.seek(position);
+        Ok(())
     }
 }
 
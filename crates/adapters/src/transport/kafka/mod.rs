@@ -15,7 +15,8 @@ mod output;
 #[cfg(test)]
 pub mod test;
 
-pub use input::{KafkaInputConfig, KafkaInputTransport};
+pub use crate::format::{SchemaRegistryConfig, SubjectNameStrategy};
+pub use input::{KafkaInputConfig, KafkaInputTransport, KafkaStartOffset};
 pub use output::{KafkaOutputConfig, KafkaOutputTransport};
 
 pub(crate) fn default_redpanda_server() -> String {
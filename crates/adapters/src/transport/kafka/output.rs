@@ -1,6 +1,11 @@
-use super::{default_redpanda_server, KafkaLogLevel};
-use crate::{AsyncErrorCallback, OutputEndpoint, OutputEndpointConfig, OutputTransport};
+use super::{default_redpanda_server, KafkaLogLevel, SchemaRegistryConfig};
+use crate::{
+    controller::is_transient_error,
+    format::avro::schema_registry::{encode_json_buffer, SchemaRegistryClient},
+    AsyncErrorCallback, OutputEndpoint, OutputEndpointConfig, OutputTransport,
+};
 use anyhow::{anyhow, bail, Error as AnyError, Result as AnyResult};
+use apache_avro::Schema as AvroSchema;
 use crossbeam::{
     queue::ArrayQueue,
     sync::{Parker, Unparker},
@@ -66,6 +71,29 @@ impl OutputTransport for KafkaOutputTransport {
 
         Ok(Box::new(ep))
     }
+
+    /// In addition to transient I/O errors, treats a full producer queue
+    /// and broker connectivity errors as retryable.
+    fn is_retryable(&self, error: &AnyError) -> bool {
+        let kafka_retryable = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<KafkaError>()
+                .and_then(KafkaError::rdkafka_error_code)
+                .map_or(false, |code| {
+                    matches!(
+                        code,
+                        RDKafkaErrorCode::QueueFull
+                            | RDKafkaErrorCode::BrokerTransportFailure
+                            | RDKafkaErrorCode::AllBrokersDown
+                            | RDKafkaErrorCode::MessageTimedOut
+                            | RDKafkaErrorCode::RequestTimedOut
+                            | RDKafkaErrorCode::NotEnoughReplicas
+                            | RDKafkaErrorCode::LeaderNotAvailable
+                    )
+                })
+        });
+        kafka_retryable || is_transient_error(error)
+    }
 }
 
 const fn default_max_inflight_messages() -> u32 {
@@ -114,6 +142,14 @@ pub struct KafkaOutputConfig {
     /// Defaults to 10.
     #[serde(default = "default_initialization_timeout_secs")]
     pub initialization_timeout_secs: u32,
+
+    /// Encode output records as Avro messages framed for Confluent
+    /// deserializers, using a schema from the schema registry.
+    ///
+    /// The endpoint must be configured with the `json` format.  Each
+    /// inserted record is written as a separate Kafka message; deletions
+    /// are not written to the topic.
+    pub schema_registry: Option<SchemaRegistryConfig>,
 }
 
 impl KafkaOutputConfig {
@@ -164,6 +200,10 @@ blocks until additional acknowledgements arrive from the broker.
 
 Defaults to 1000."#)),
                 )
+                .property(
+                    "schema_registry",
+                    SchemaRegistryConfig::schema().1
+                )
                 .additional_properties(Some(
                         ObjectBuilder::new()
                         .schema_type(SchemaType::String)
@@ -261,6 +301,13 @@ struct KafkaOutputEndpoint {
     config: KafkaOutputConfig,
     parker: Parker,
     max_message_size: usize,
+
+    /// Schema registry client used to retrieve the output schema.
+    schema_registry: Option<SchemaRegistryClient>,
+
+    /// Id and schema used to encode output records, retrieved from the
+    /// schema registry on the first write.
+    output_schema: Option<(u32, AvroSchema)>,
 }
 
 impl KafkaOutputEndpoint {
@@ -306,11 +353,18 @@ impl KafkaOutputEndpoint {
         // Create Kafka producer.
         let kafka_producer = ThreadedProducer::from_config_and_context(&client_config, context)?;
 
+        let schema_registry = config
+            .schema_registry
+            .as_ref()
+            .map(SchemaRegistryClient::new);
+
         Ok(Self {
             kafka_producer,
             config,
             parker,
             max_message_size,
+            schema_registry,
+            output_schema: None,
         })
     }
 
@@ -325,6 +379,28 @@ impl KafkaOutputEndpoint {
     fn status_ok(stats: &Statistics) -> bool {
         stats.brokers.values().any(|broker| broker.state == "UP")
     }
+
+    fn send_message(&mut self, payload: &[u8]) -> AnyResult<()> {
+        // Wait for the number of unacknowledged messages to drop
+        // below `max_inflight_messages`.
+        while self.kafka_producer.in_flight_count() as i64
+            > self.config.max_inflight_messages as i64
+        {
+            // FIXME: It appears that the delivery callback can be invoked before the
+            // in-flight counter is decremented, in which case we may never get
+            // unparked and may need to poll the in-flight counter.  This
+            // shouldn't cause performance issues in practice, but
+            // it would still be nice to have a more reliable way to wake up the endpoint
+            // thread _after_ the in-flight counter has been decremented.
+            self.parker.park_timeout(OUTPUT_POLLING_INTERVAL);
+        }
+
+        let record = <BaseRecord<(), [u8], ()>>::to(&self.config.topic).payload(payload);
+        self.kafka_producer
+            .send(record)
+            .map_err(|(err, _record)| err)?;
+        Ok(())
+    }
 }
 
 impl OutputEndpoint for KafkaOutputEndpoint {
@@ -368,24 +444,19 @@ impl OutputEndpoint for KafkaOutputEndpoint {
     }
 
     fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
-        // Wait for the number of unacknowledged messages to drop
-        // below `max_inflight_messages`.
-        while self.kafka_producer.in_flight_count() as i64
-            > self.config.max_inflight_messages as i64
-        {
-            // FIXME: It appears that the delivery callback can be invoked before the
-            // in-flight counter is decremented, in which case we may never get
-            // unparked and may need to poll the in-flight counter.  This
-            // shouldn't cause performance issues in practice, but
-            // it would still be nice to have a more reliable way to wake up the endpoint
-            // thread _after_ the in-flight counter has been decremented.
-            self.parker.park_timeout(OUTPUT_POLLING_INTERVAL);
+        let schema_registry = match &self.schema_registry {
+            None => return self.send_message(buffer),
+            Some(schema_registry) => schema_registry,
+        };
+
+        if self.output_schema.is_none() {
+            self.output_schema = Some(schema_registry.output_schema(&self.config.topic)?);
         }
+        let (schema_id, schema) = self.output_schema.as_ref().unwrap();
 
-        let record = <BaseRecord<(), [u8], ()>>::to(&self.config.topic).payload(buffer);
-        self.kafka_producer
-            .send(record)
-            .map_err(|(err, _record)| err)?;
+        for message in encode_json_buffer(*schema_id, schema, buffer)? {
+            self.send_message(&message)?;
+        }
         Ok(())
     }
 }
@@ -1,14 +1,22 @@
+use super::default_redpanda_server;
 use crate::{
     test::{
         generate_test_batches,
         kafka::{BufferConsumer, KafkaResources, TestProducer},
-        mock_input_pipeline, test_circuit, wait, MockDeZSet, TestStruct,
+        mock_input_pipeline, mock_parser_pipeline, run_checkpoint_pipeline, test_circuit, wait,
+        MockDeZSet, TestStruct,
     },
-    Controller, PipelineConfig,
+    transport::InputTransport,
+    Controller, InputEndpointConfig, PipelineConfig,
 };
 use env_logger::Env;
 use log::info;
 use proptest::prelude::*;
+use rdkafka::{
+    consumer::{BaseConsumer, Consumer},
+    ClientConfig, Offset, TopicPartitionList,
+};
+use serde_json::json;
 use std::{
     io::Write,
     sync::{
@@ -18,6 +26,7 @@ use std::{
     thread::sleep,
     time::Duration,
 };
+use tempfile::TempDir;
 
 /// Wait to receive all records in `data` in the same order.
 fn wait_for_output_ordered(zset: &MockDeZSet<TestStruct>, data: &[Vec<TestStruct>]) {
@@ -328,6 +337,251 @@ format:
     assert_eq!(zset.state().flushed.len(), 0);
 }
 
+#[test]
+fn test_kafka_commit_offsets() {
+    init_test_logger();
+
+    let topic = "commit_offsets_test_topic";
+    let group_id = "commit_offsets_test_group";
+    let _kafka_resources = KafkaResources::create_topics(&[(topic, 1)]);
+
+    let data = (0..10)
+        .map(|batch| {
+            (0..10)
+                .map(|i| TestStruct {
+                    id: batch * 10 + i,
+                    b: i % 2 == 0,
+                    i: Some(i as i64),
+                    s: format!("{batch}-{i}"),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let num_records: usize = data.iter().map(Vec::len).sum();
+
+    let producer = TestProducer::new();
+    producer.send_to_topic(&data, topic);
+
+    let config_str = format!(
+        r#"
+name: test
+workers: 4
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: kafka
+            config:
+                group.id: {group_id}
+                topics: [{topic}]
+                start_offset: earliest
+                commit_offsets: true
+                statistics.interval.ms: "100"
+                log_level: debug
+        format:
+            name: csv
+outputs:
+"#
+    );
+
+    let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+    let controller = Controller::with_config(
+        |workers| Ok(test_circuit(workers)),
+        &config,
+        Box::new(|e| panic!("test_kafka_commit_offsets: error: {e}")),
+    )
+    .unwrap();
+    controller.start();
+
+    wait(
+        || controller.status().num_total_processed_records() == num_records as u64,
+        Some(20_000),
+    )
+    .expect("timeout waiting for inputs to be processed");
+
+    // Offsets are committed once the circuit has processed the messages.
+    let kafka_consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &default_redpanda_server())
+        .set("group.id", group_id)
+        .create()
+        .unwrap();
+    let mut partitions = TopicPartitionList::new();
+    partitions.add_partition(topic, 0);
+
+    wait(
+        || {
+            kafka_consumer
+                .committed_offsets(partitions.clone(), Duration::from_secs(5))
+                .unwrap()
+                .find_partition(topic, 0)
+                .unwrap()
+                .offset()
+                == Offset::Offset(data.len() as i64)
+        },
+        Some(20_000),
+    )
+    .expect("timeout waiting for offsets to be committed");
+
+    // Committed offsets and lag are reported in endpoint stats.
+    wait(
+        || {
+            controller
+                .status()
+                .input_status()
+                .get(&0)
+                .unwrap()
+                .transport_metrics
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|metrics| {
+                    metrics["partitions"][0]["committed_offset"] == json!(data.len() - 1)
+                        && metrics["partitions"][0]["lag"] == json!(0)
+                })
+                .unwrap_or(false)
+        },
+        Some(20_000),
+    )
+    .expect("timeout waiting for Kafka consumer metrics");
+
+    // Total lag is reported as the lag of the endpoint.
+    assert_eq!(
+        *controller.status().input_status()[&0]
+            .metrics
+            .lag
+            .lock()
+            .unwrap(),
+        Some(0)
+    );
+
+    controller.stop().unwrap();
+}
+
+#[test]
+fn test_kafka_checkpoint_offsets() {
+    init_test_logger();
+
+    let topic = "checkpoint_offsets_test_topic";
+    let _kafka_resources = KafkaResources::create_topics(&[(topic, 1)]);
+
+    let data = (0..2)
+        .map(|batch| {
+            (0..10)
+                .map(|i| TestStruct {
+                    id: batch * 10 + i,
+                    b: i % 2 == 0,
+                    i: Some(i as i64),
+                    s: format!("{batch}-{i}"),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let config_str = format!(
+        r#"
+stream: test_input
+transport:
+    name: kafka
+    config:
+        auto.offset.reset: "earliest"
+        topics: [{topic}]
+        log_level: debug
+format:
+    name: csv
+"#
+    );
+    let config: InputEndpointConfig = serde_yaml::from_str(&config_str).unwrap();
+
+    let (endpoint, _consumer, zset) = mock_input_pipeline::<TestStruct>(config.clone()).unwrap();
+    endpoint.start().unwrap();
+
+    let producer = TestProducer::new();
+    producer.send_to_topic(&data[0..1], topic);
+    wait_for_output_ordered(&zset, &data[0..1]);
+
+    // The position reported at the start of a step includes all messages
+    // received so far.
+    endpoint.step_started();
+    let position = endpoint.position().unwrap();
+    assert_eq!(
+        position,
+        json!({"partitions": [{"topic": topic, "partition": 0, "offset": 1}]})
+    );
+    endpoint.disconnect();
+
+    // A new endpoint restored from the position only receives new messages,
+    // even though it starts from the earliest offset.
+    producer.send_to_topic(&data[1..2], topic);
+
+    let (consumer, zset) =
+        mock_parser_pipeline::<TestStruct>(&config.connector_config.format).unwrap();
+    let mut endpoint = <dyn InputTransport>::get_transport("kafka")
+        .unwrap()
+        .new_endpoint("test_input", &config.connector_config.transport.config)
+        .unwrap();
+    endpoint.seek(position).unwrap();
+    endpoint.connect(Box::new(consumer)).unwrap();
+    endpoint.start().unwrap();
+
+    wait_for_output_ordered(&zset, &data[1..2]);
+    sleep(Duration::from_millis(1000));
+    assert_eq!(zset.state().flushed.len(), data[1].len());
+
+    endpoint.disconnect();
+}
+
+#[test]
+fn test_kafka_checkpoint_restore() {
+    init_test_logger();
+
+    let topic = "checkpoint_restore_test_topic";
+    let _kafka_resources = KafkaResources::create_topics(&[(topic, 1)]);
+    let dir = TempDir::new().unwrap();
+
+    let data = (0..20)
+        .map(|id| TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64),
+            s: id.to_string(),
+        })
+        .collect::<Vec<_>>();
+    let (before, after) = data.split_at(10);
+    let expected =
+        |data: &[TestStruct]| data.iter().map(|val| (val.clone(), 1)).collect::<Vec<_>>();
+
+    let input: InputEndpointConfig = serde_yaml::from_str(&format!(
+        r#"
+stream: test_input1
+transport:
+    name: kafka
+    config:
+        topics: [{topic}]
+        start_offset: earliest
+        log_level: debug
+format:
+    name: csv
+"#
+    ))
+    .unwrap();
+
+    let producer = TestProducer::new();
+    producer.send_to_topic(&[before.to_vec()], topic);
+    assert_eq!(
+        run_checkpoint_pipeline(&input, dir.path(), before.len(), true),
+        expected(before)
+    );
+
+    // The restarted pipeline resumes from the checkpointed offset rather
+    // than `start_offset`, and its state includes the messages processed
+    // before the checkpoint.
+    producer.send_to_topic(&[after.to_vec()], topic);
+    assert_eq!(
+        run_checkpoint_pipeline(&input, dir.path(), after.len(), false),
+        expected(&data)
+    );
+}
+
 /// If Kafka tests are going to fail because the server is not running or
 /// not functioning properly, it's good to fail quickly without printing a
 /// thousand records as part of the failure.
@@ -16,47 +16,124 @@
 //!
 //! The following transports are currently supported:
 //!
-//!   * `file`, for input from a file via [`FileInputTransport`] or output to a
-//!     file via [`FileOutputTransport`].
+//!   * `file`, for input from a file or a set of files matching a glob
+//!     pattern via [`FileInputTransport`] or output to a file via
+//!     [`FileOutputTransport`].
 //!
 //!   * `url`, for input from an HTTP or HTTPS url via [`UrlInputTransport`].
 //!
+//!   * `websocket`, for input from a WebSocket server via
+//!     [`WebSocketInputTransport`].
+//!
+//!   * `s3_input`, for input from objects in an S3 bucket, an Azure Blob
+//!     Storage container, or a Google Cloud Storage bucket via
+//!     [`S3InputTransport`].
+//!
 //!   * `kafka`, for input from [Kafka](https://kafka.apache.org/) via
 //!     [`KafkaInputTransport`] or output to Kafka via [`KafkaOutputTransport`],
 //!     if the `with-kafka` feature is enabled.
 //!
+//!   * `mqtt`, for input from an [MQTT](https://mqtt.org/) broker via
+//!     [`MqttInputTransport`], if the `with-mqtt` feature is enabled.
+//!
+//!   * `postgres_cdc`, for mirroring a PostgreSQL table using logical
+//!     replication via [`PostgresCdcInputTransport`], and `postgres`, for
+//!     upserting output records into a PostgreSQL table via
+//!     [`PostgresOutputTransport`], if the `with-postgres` feature is enabled.
+//!
+//!   * `bigquery`, for writing output records to a BigQuery table via
+//!     [`BigQueryOutputTransport`], if the `with-bigquery` feature is
+//!     enabled.
+//!
+//!   * `snowflake`, for merging output records into a Snowflake table via
+//!     [`SnowflakeOutputTransport`], if the `with-snowflake` feature is
+//!     enabled.
+//!
+//!   * `skew`, for testing, wraps another input transport and delays its data
+//!     to simulate processing-time skew via [`SkewInputTransport`].
+//!
+//! Additional transports can be registered at runtime, either directly or by
+//! loading a shared library plugin; see [`plugin`](self::plugin).
+//!
 //! To obtain a transport and create an endpoint with it:
 //!
 //! ```ignore
 //! let transport = <dyn InputTransport>::get_transport(transport_name).unwrap();
 //! let endpoint = transport.new_endpoint(endpoint_name, &config, consumer);
 //! ```
-use crate::{format::ParseError, OutputEndpointConfig};
-use anyhow::{Error as AnyError, Result as AnyResult};
+use crate::{controller::is_transient_error, format::ParseError, OutputEndpointConfig};
+use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
 use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 
+mod compression;
 mod file;
 pub mod http;
+pub mod plugin;
+mod s3;
+mod skew;
 
 pub mod url;
+mod websocket;
 
 #[cfg(feature = "with-kafka")]
 pub(crate) mod kafka;
 
-pub use file::{FileInputConfig, FileInputTransport, FileOutputConfig, FileOutputTransport};
+#[cfg(feature = "with-mqtt")]
+mod mqtt;
+
+#[cfg(feature = "with-postgres")]
+mod postgres;
+
+#[cfg(feature = "with-snowflake")]
+mod snowflake;
+
+#[cfg(feature = "with-bigquery")]
+mod bigquery;
+
+pub use compression::ObjectCompression;
+pub use file::{
+    FileCompression, FileInputConfig, FileInputTransport, FileOutputConfig, FileOutputTransport,
+};
+#[cfg(feature = "with-plugins")]
+pub use plugin::load_transport_plugin;
+pub use plugin::{
+    register_input_transport, register_output_transport, TransportRegistrar,
+    TRANSPORT_PLUGIN_API_VERSION,
+};
+pub use s3::{open_object_store, ObjectStoreProvider, S3InputConfig, S3InputTransport};
+pub use skew::{SkewInputConfig, SkewInputTransport};
 pub use url::{UrlInputConfig, UrlInputTransport};
+pub use websocket::{WebSocketInputConfig, WebSocketInputTransport};
 
 #[cfg(feature = "with-kafka")]
 pub use kafka::{
     KafkaInputConfig, KafkaInputTransport, KafkaLogLevel, KafkaOutputConfig, KafkaOutputTransport,
+    KafkaStartOffset, SchemaRegistryConfig, SubjectNameStrategy,
+};
+
+#[cfg(feature = "with-mqtt")]
+pub use mqtt::{MqttInputConfig, MqttInputTransport, MqttProtocolVersion, MqttQos};
+
+#[cfg(feature = "with-postgres")]
+pub use postgres::{
+    PostgresCdcInputConfig, PostgresCdcInputTransport, PostgresOutputConfig,
+    PostgresOutputTransport,
 };
 
-/// Static map of supported input transports.
-// TODO: support for registering new transports at runtime in order to allow
-// external crates to implement new transports.
+#[cfg(feature = "with-bigquery")]
+pub use bigquery::{BigQueryOutputConfig, BigQueryOutputTransport};
+
+#[cfg(feature = "with-snowflake")]
+pub use snowflake::{SnowflakeOutputConfig, SnowflakeOutputTransport, SnowflakeTokenType};
+
+/// Static map of built-in input transports.
+///
+/// Transports registered at runtime are tracked separately in
+/// [`plugin`](self::plugin).
 static INPUT_TRANSPORT: Lazy<BTreeMap<&'static str, Box<dyn InputTransport>>> = Lazy::new(|| {
     BTreeMap::from([
         (
@@ -67,15 +144,37 @@ static INPUT_TRANSPORT: Lazy<BTreeMap<&'static str, Box<dyn InputTransport>>> =
             "url",
             Box::new(UrlInputTransport) as Box<dyn InputTransport>,
         ),
+        (
+            "s3_input",
+            Box::new(S3InputTransport) as Box<dyn InputTransport>,
+        ),
+        (
+            "skew",
+            Box::new(SkewInputTransport) as Box<dyn InputTransport>,
+        ),
+        (
+            "websocket",
+            Box::new(WebSocketInputTransport) as Box<dyn InputTransport>,
+        ),
         #[cfg(feature = "with-kafka")]
         (
             "kafka",
             Box::new(KafkaInputTransport) as Box<dyn InputTransport>,
         ),
+        #[cfg(feature = "with-mqtt")]
+        (
+            "mqtt",
+            Box::new(MqttInputTransport) as Box<dyn InputTransport>,
+        ),
+        #[cfg(feature = "with-postgres")]
+        (
+            "postgres_cdc",
+            Box::new(PostgresCdcInputTransport) as Box<dyn InputTransport>,
+        ),
     ])
 });
 
-/// Static map of supported output transports.
+/// Static map of built-in output transports.
 static OUTPUT_TRANSPORT: Lazy<BTreeMap<&'static str, Box<dyn OutputTransport>>> = Lazy::new(|| {
     BTreeMap::from([
         (
@@ -87,6 +186,21 @@ static OUTPUT_TRANSPORT: Lazy<BTreeMap<&'static str, Box<dyn OutputTransport>>>
             "kafka",
             Box::new(KafkaOutputTransport) as Box<dyn OutputTransport>,
         ),
+        #[cfg(feature = "with-postgres")]
+        (
+            "postgres",
+            Box::new(PostgresOutputTransport) as Box<dyn OutputTransport>,
+        ),
+        #[cfg(feature = "with-bigquery")]
+        (
+            "bigquery",
+            Box::new(BigQueryOutputTransport) as Box<dyn OutputTransport>,
+        ),
+        #[cfg(feature = "with-snowflake")]
+        (
+            "snowflake",
+            Box::new(SnowflakeOutputTransport) as Box<dyn OutputTransport>,
+        ),
     ])
 });
 
@@ -118,13 +232,27 @@ pub trait InputTransport: Send + Sync {
     /// to initialize (e.g., the endpoint was not able to establish a network
     /// connection).
     fn new_endpoint(&self, name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>>;
+
+    /// Returns `true` if `error`, reported by an endpoint of this transport
+    /// as fatal or returned by [`new_endpoint`](`Self::new_endpoint`), is
+    /// likely to go away if the endpoint is re-created.
+    ///
+    /// The controller uses this method to decide whether to apply the retry
+    /// policy configured for the endpoint.  The default implementation
+    /// treats network I/O errors as retryable.
+    fn is_retryable(&self, error: &AnyError) -> bool {
+        is_transient_error(error)
+    }
 }
 
 impl dyn InputTransport {
     /// Lookup input transport by `name`, which should be e.g. `file` for a file
     /// transport.
     pub fn get_transport(name: &str) -> Option<&'static dyn InputTransport> {
-        INPUT_TRANSPORT.get(name).map(|f| &**f)
+        INPUT_TRANSPORT
+            .get(name)
+            .map(|f| &**f)
+            .or_else(|| plugin::plugin_input_transport(name))
     }
 }
 
@@ -153,6 +281,59 @@ pub trait InputEndpoint: Send {
     /// data buffers may be pushed downstream before the endpoint gets
     /// disconnected.
     fn disconnect(&self);
+
+    /// Invoked by the controller right before the circuit starts a step.
+    ///
+    /// All data pushed to the consumer before this call will be processed
+    /// by the step.
+    fn step_started(&self) {}
+
+    /// Invoked by the controller after the circuit has completed a step.
+    ///
+    /// All data pushed to the consumer before the preceding
+    /// [`step_started`](`Self::step_started`) call has been fully processed
+    /// by the circuit.  Endpoints that track progress in an external system,
+    /// e.g., by committing Kafka consumer offsets, use this notification to
+    /// acknowledge processed inputs.
+    fn step_completed(&self) {}
+
+    /// Returns the position of the endpoint in its input stream as of the
+    /// latest [`step_started`](`Self::step_started`) call, or `None` if the
+    /// endpoint cannot resume from a position.
+    ///
+    /// The controller stores the position in a checkpoint, taken after the
+    /// step has completed, and passes it to [`seek`](`Self::seek`) when
+    /// restoring the pipeline from the checkpoint.  The position must
+    /// therefore cover exactly the data pushed to the consumer before the
+    /// step started.
+    fn position(&self) -> Option<JsonValue> {
+        None
+    }
+
+    /// Resume reading from `position`, previously returned by
+    /// [`position`](`Self::position`), instead of the start of the input
+    /// stream.
+    ///
+    /// Invoked before [`connect`](`Self::connect`).
+    fn seek(&mut self, _position: JsonValue) -> AnyResult<()> {
+        Err(anyhow!(
+            "this transport cannot resume from a checkpointed position"
+        ))
+    }
+
+    /// Apply a new transport configuration, in the same form as the
+    /// configuration passed to [`InputTransport::new_endpoint`], to the
+    /// running endpoint without interrupting the connection.
+    ///
+    /// The endpoint must reject the configuration, leaving the current one
+    /// in effect, if it changes any settings that cannot be applied on the
+    /// fly.  Settings that only affect new requests or connections, e.g.,
+    /// credentials, take effect when the endpoint next uses them.
+    fn reconfigure(&self, _config: &YamlValue) -> AnyResult<()> {
+        Err(anyhow!(
+            "this transport does not support changing its configuration at runtime"
+        ))
+    }
 }
 
 /// Input stream consumer.
@@ -184,6 +365,43 @@ pub trait InputConsumer: Send {
     /// No more data will be received from the endpoint.
     fn eoi(&mut self) -> Vec<ParseError>;
 
+    /// Report transport-specific metrics, e.g., Kafka partition offsets and
+    /// consumer lag, to be included in endpoint stats.
+    ///
+    /// Each call replaces metrics reported previously.  If `metrics` is an
+    /// object with a numeric `lag` field, its value is also reported as the
+    /// lag of the endpoint, i.e., the number of records available at the
+    /// source that haven't been read yet.
+    fn transport_metrics(&mut self, metrics: JsonValue);
+
+    /// Associate a distributed trace context with the data pushed to the
+    /// consumer after this call.
+    ///
+    /// `headers` are the headers received with the data, e.g., Kafka message
+    /// headers or HTTP request headers, which may contain a W3C trace context
+    /// (`traceparent` and `tracestate` headers).  When tracing is enabled, the
+    /// controller records the processing of the data as part of this trace.
+    /// The context remains in effect until the next call to this method.
+    fn trace_context(&mut self, _headers: &[(&str, &str)]) {}
+
+    /// Identify the partition of the source, e.g., a Kafka partition, that
+    /// the data pushed to the consumer after this call comes from.
+    ///
+    /// When the endpoint is configured with multiple parsers, the controller
+    /// can use the partition to parse all data from the same partition in
+    /// order.  The partition remains in effect until the next call to this
+    /// method.
+    fn partition(&mut self, _partition: u32) {}
+
+    /// Identify the location in the source, e.g., the Kafka topic, partition
+    /// and offset of a message or the name of a file, that the data pushed to
+    /// the consumer after this call comes from.
+    ///
+    /// When lineage tagging is enabled for the endpoint, the controller
+    /// attaches the offset to the records parsed from the data.  The offset
+    /// remains in effect until the next call to this method.
+    fn source_offset(&mut self, _offset: &JsonValue) {}
+
     /// Create a new consumer instance.
     ///
     /// Used by multithreaded transport endpoints to create multiple parallel
@@ -230,12 +448,25 @@ pub trait OutputTransport: Send + Sync {
         name: &str,
         config: &OutputEndpointConfig,
     ) -> AnyResult<Box<dyn OutputEndpoint>>;
+
+    /// Returns `true` if `error`, returned by an [`OutputEndpoint`] method,
+    /// is likely to go away if the operation is retried.
+    ///
+    /// The controller uses this method to decide whether to apply the retry
+    /// policy configured for the endpoint.  The default implementation
+    /// treats network I/O errors as retryable.
+    fn is_retryable(&self, error: &AnyError) -> bool {
+        is_transient_error(error)
+    }
 }
 
 impl dyn OutputTransport {
     /// Lookup output transport by name.
     pub fn get_transport(name: &str) -> Option<&'static dyn OutputTransport> {
-        OUTPUT_TRANSPORT.get(name).map(|f| &**f)
+        OUTPUT_TRANSPORT
+            .get(name)
+            .map(|f| &**f)
+            .or_else(|| plugin::plugin_output_transport(name))
     }
 }
 
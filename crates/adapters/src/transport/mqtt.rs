@@ -0,0 +1,537 @@
+use super::{InputConsumer, InputEndpoint, InputTransport};
+use crate::PipelineState;
+use anyhow::{anyhow, bail, Error as AnyError, Result as AnyResult};
+use crossbeam::sync::{Parker, Unparker};
+use log::{debug, info};
+use num_traits::FromPrimitive;
+use rumqttc::{v5, Transport};
+use serde::Deserialize;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+/// Interval at which the worker thread checks for state changes while
+/// waiting for messages.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Capacity of the request channel between the MQTT client and its event loop.
+const REQUEST_CHANNEL_CAPACITY: usize = 100;
+
+/// [`InputTransport`] implementation that subscribes to topics on an MQTT
+/// broker.
+///
+/// Each MQTT message is passed to the parser as a separate chunk.
+///
+/// This input transport is only available if the crate is configured with
+/// `with-mqtt` feature.
+///
+/// The input transport factory gives this transport the name `mqtt`.
+pub struct MqttInputTransport;
+
+impl InputTransport for MqttInputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("mqtt")
+    }
+
+    /// Creates a new [`InputEndpoint`] for subscribing to MQTT topics,
+    /// interpreting `config` as a [`MqttInputConfig`].
+    ///
+    /// See [`InputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(&self, _name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
+        let config = MqttInputConfig::deserialize(config)?;
+        let ep = MqttInputEndpoint::new(config)?;
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_port() -> u16 {
+    1883
+}
+
+const fn default_keep_alive_secs() -> u64 {
+    30
+}
+
+const fn default_connect_timeout_secs() -> u32 {
+    10
+}
+
+const fn default_reconnect_delay_ms() -> u64 {
+    1000
+}
+
+/// MQTT quality of service level.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum MqttQos {
+    /// QoS 0: messages may be lost.
+    #[serde(rename = "at_most_once")]
+    AtMostOnce,
+
+    /// QoS 1: messages may be delivered more than once.
+    #[default]
+    #[serde(rename = "at_least_once")]
+    AtLeastOnce,
+
+    /// QoS 2: each message is delivered exactly once.
+    #[serde(rename = "exactly_once")]
+    ExactlyOnce,
+}
+
+impl From<MqttQos> for rumqttc::QoS {
+    fn from(qos: MqttQos) -> Self {
+        match qos {
+            MqttQos::AtMostOnce => Self::AtMostOnce,
+            MqttQos::AtLeastOnce => Self::AtLeastOnce,
+            MqttQos::ExactlyOnce => Self::ExactlyOnce,
+        }
+    }
+}
+
+impl From<MqttQos> for v5::mqttbytes::QoS {
+    fn from(qos: MqttQos) -> Self {
+        match qos {
+            MqttQos::AtMostOnce => Self::AtMostOnce,
+            MqttQos::AtLeastOnce => Self::AtLeastOnce,
+            MqttQos::ExactlyOnce => Self::ExactlyOnce,
+        }
+    }
+}
+
+/// MQTT protocol version.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum MqttProtocolVersion {
+    /// MQTT 3.1.1.
+    #[default]
+    #[serde(rename = "v3")]
+    V3,
+
+    /// MQTT 5.
+    #[serde(rename = "v5")]
+    V5,
+}
+
+/// Configuration for subscribing to MQTT topics with [`InputTransport`].
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct MqttInputConfig {
+    /// Broker host name or address.
+    pub host: String,
+
+    /// Broker port.  Defaults to 1883.
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// Connect to the broker over TLS, validating the broker's certificate
+    /// against the system root certificates.
+    #[serde(default)]
+    pub tls: bool,
+
+    /// MQTT protocol version.  Defaults to `v3` (MQTT 3.1.1).
+    #[serde(default)]
+    pub protocol_version: MqttProtocolVersion,
+
+    /// Client identifier.
+    ///
+    /// The broker associates the session, i.e., subscriptions and messages
+    /// queued while the client is disconnected, with the client identifier.
+    /// Set a fixed identifier to resume the session after a pipeline
+    /// restart.  When not specified, a random identifier is generated, and
+    /// the session can only be resumed when reconnecting after a network
+    /// failure.
+    pub client_id: Option<String>,
+
+    /// Topic filters to subscribe to.  Filters may contain the `+` and `#`
+    /// wildcards.
+    pub topics: Vec<String>,
+
+    /// Maximum quality of service level of received messages.
+    /// Defaults to `at_least_once`.
+    #[serde(default)]
+    pub qos: MqttQos,
+
+    /// Start a new session on connect, discarding any session state
+    /// stored by the broker.
+    ///
+    /// By default, the endpoint resumes its previous session, so that
+    /// messages with QoS 1 or 2 published while the endpoint was
+    /// disconnected or paused are delivered once it reconnects.
+    #[serde(default)]
+    pub clean_session: bool,
+
+    /// How long, in seconds, the broker keeps the session after the client
+    /// disconnects (MQTT 5 only).  When not specified, the session does not
+    /// expire.
+    pub session_expiry_secs: Option<u32>,
+
+    /// Keep-alive interval in seconds.  Defaults to 30.
+    #[serde(default = "default_keep_alive_secs")]
+    pub keep_alive_secs: u64,
+
+    /// Username used to authenticate with the broker.
+    pub username: Option<String>,
+
+    /// Password used to authenticate with the broker.
+    pub password: Option<String>,
+
+    /// Maximum timeout in seconds to wait for the endpoint to connect to
+    /// the broker during initialization.  Defaults to 10.
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u32,
+
+    /// Delay in milliseconds between attempts to reconnect to the broker
+    /// after losing connection.  Defaults to 1000.
+    #[serde(default = "default_reconnect_delay_ms")]
+    pub reconnect_delay_ms: u64,
+}
+
+impl MqttInputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.topics.is_empty() {
+            bail!("MQTT input endpoint configuration must specify at least one topic");
+        }
+        if self.session_expiry_secs.is_some() && self.protocol_version != MqttProtocolVersion::V5 {
+            bail!("'session_expiry_secs' is only supported with MQTT protocol version 'v5'");
+        }
+        Ok(())
+    }
+
+    fn client_id(&self) -> String {
+        self.client_id
+            .clone()
+            .unwrap_or_else(|| format!("feldera-{}", uuid::Uuid::new_v4()))
+    }
+}
+
+/// Protocol-independent subset of MQTT events handled by the endpoint.
+enum MqttEvent {
+    ConnAck { session_present: bool },
+    Publish(Vec<u8>),
+    Other,
+}
+
+/// MQTT client for one of the supported protocol versions.
+enum MqttClient {
+    V3(rumqttc::Client, rumqttc::Connection),
+    V5(v5::Client, v5::Connection),
+}
+
+impl MqttClient {
+    fn new(config: &MqttInputConfig) -> Self {
+        let client_id = config.client_id();
+        let keep_alive = Duration::from_secs(config.keep_alive_secs);
+
+        match config.protocol_version {
+            MqttProtocolVersion::V3 => {
+                let mut options = rumqttc::MqttOptions::new(client_id, &config.host, config.port);
+                options
+                    .set_keep_alive(keep_alive)
+                    .set_clean_session(config.clean_session);
+                if let Some(username) = &config.username {
+                    options.set_credentials(username, config.password.clone().unwrap_or_default());
+                }
+                if config.tls {
+                    options.set_transport(Transport::tls_with_default_config());
+                }
+                let (client, connection) = rumqttc::Client::new(options, REQUEST_CHANNEL_CAPACITY);
+                Self::V3(client, connection)
+            }
+            MqttProtocolVersion::V5 => {
+                let mut options = v5::MqttOptions::new(client_id, &config.host, config.port);
+                options
+                    .set_keep_alive(keep_alive)
+                    .set_clean_start(config.clean_session)
+                    .set_session_expiry_interval(Some(
+                        config.session_expiry_secs.unwrap_or(u32::MAX),
+                    ));
+                if let Some(username) = &config.username {
+                    options.set_credentials(username, config.password.clone().unwrap_or_default());
+                }
+                if config.tls {
+                    options.set_transport(Transport::tls_with_default_config());
+                }
+                let (client, connection) = v5::Client::new(options, REQUEST_CHANNEL_CAPACITY);
+                Self::V5(client, connection)
+            }
+        }
+    }
+
+    /// Subscribe to `topics`.  The subscription requests are sent to the
+    /// broker by the event loop.
+    fn subscribe(&mut self, topics: &[String], qos: MqttQos) -> AnyResult<()> {
+        for topic in topics.iter() {
+            match self {
+                Self::V3(client, _) => client.try_subscribe(topic, qos.into())?,
+                Self::V5(client, _) => client.try_subscribe(topic, qos.into())?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Drive the event loop until the next event or `timeout`.
+    ///
+    /// Returns `Ok(None)` on timeout.  After a connection error, the next
+    /// call attempts to reconnect.
+    fn next_event(&mut self, timeout: Duration) -> AnyResult<Option<MqttEvent>> {
+        match self {
+            Self::V3(_, connection) => match connection.recv_timeout(timeout) {
+                Err(_) => Ok(None),
+                Ok(Err(e)) => Err(AnyError::from(e)),
+                Ok(Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(ack)))) => {
+                    if ack.code != rumqttc::ConnectReturnCode::Success {
+                        bail!("MQTT broker refused connection: {:?}", ack.code);
+                    }
+                    Ok(Some(MqttEvent::ConnAck {
+                        session_present: ack.session_present,
+                    }))
+                }
+                Ok(Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)))) => {
+                    Ok(Some(MqttEvent::Publish(publish.payload.to_vec())))
+                }
+                Ok(Ok(_)) => Ok(Some(MqttEvent::Other)),
+            },
+            Self::V5(_, connection) => match connection.recv_timeout(timeout) {
+                Err(_) => Ok(None),
+                Ok(Err(e)) => Err(AnyError::from(e)),
+                Ok(Ok(v5::Event::Incoming(v5::Incoming::ConnAck(ack)))) => {
+                    if ack.code != v5::mqttbytes::v5::ConnectReturnCode::Success {
+                        bail!("MQTT broker refused connection: {:?}", ack.code);
+                    }
+                    Ok(Some(MqttEvent::ConnAck {
+                        session_present: ack.session_present,
+                    }))
+                }
+                Ok(Ok(v5::Event::Incoming(v5::Incoming::Publish(publish)))) => {
+                    Ok(Some(MqttEvent::Publish(publish.payload.to_vec())))
+                }
+                Ok(Ok(_)) => Ok(Some(MqttEvent::Other)),
+            },
+        }
+    }
+
+    fn disconnect(&mut self) {
+        let _ = match self {
+            Self::V3(client, _) => client.try_disconnect().map_err(|e| anyhow!("{e}")),
+            Self::V5(client, _) => client.try_disconnect().map_err(|e| anyhow!("{e}")),
+        };
+    }
+}
+
+struct MqttInputEndpoint {
+    config: MqttInputConfig,
+    state: Arc<AtomicU32>,
+    unparker: Option<Unparker>,
+}
+
+impl MqttInputEndpoint {
+    fn new(config: MqttInputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!("Starting MQTT input endpoint: {config:?}");
+
+        Ok(Self {
+            config,
+            state: Arc::new(AtomicU32::new(PipelineState::Paused as u32)),
+            unparker: None,
+        })
+    }
+
+    fn set_state(&self, state: PipelineState) {
+        self.state.store(state as u32, Ordering::Release);
+        if let Some(unparker) = &self.unparker {
+            unparker.unpark();
+        }
+    }
+
+    /// Handle an MQTT event.  Returns `true` if the event is a connection
+    /// acknowledgement.
+    fn handle_event(
+        config: &MqttInputConfig,
+        client: &mut MqttClient,
+        event: MqttEvent,
+        consumer: &mut dyn InputConsumer,
+    ) -> AnyResult<bool> {
+        match event {
+            MqttEvent::ConnAck { session_present } => {
+                info!(
+                    "Connected to MQTT broker {}:{} (session present: {session_present})",
+                    config.host, config.port
+                );
+                // The broker remembers subscriptions of a resumed session.
+                if !session_present {
+                    client.subscribe(&config.topics, config.qos)?;
+                }
+                Ok(true)
+            }
+            MqttEvent::Publish(payload) => {
+                // Leave it to the controller to handle errors.  There is noone we can
+                // forward the error to upstream.
+                let _ = consumer.input_chunk(&payload);
+                Ok(false)
+            }
+            MqttEvent::Other => Ok(false),
+        }
+    }
+
+    fn worker_thread(
+        config: MqttInputConfig,
+        mut client: MqttClient,
+        state: Arc<AtomicU32>,
+        parker: Parker,
+        mut consumer: Box<dyn InputConsumer>,
+    ) {
+        loop {
+            match PipelineState::from_u32(state.load(Ordering::Acquire)).unwrap() {
+                PipelineState::Terminated => {
+                    client.disconnect();
+                    return;
+                }
+                PipelineState::Paused => {
+                    // Stop driving the event loop while paused.  If the broker
+                    // drops the connection in the meantime, the client
+                    // reconnects on resume, and the broker delivers messages
+                    // queued in the session.
+                    parker.park_timeout(POLL_TIMEOUT);
+                    continue;
+                }
+                PipelineState::Running => {}
+            }
+
+            let result = client
+                .next_event(POLL_TIMEOUT)
+                .and_then(|event| match event {
+                    Some(event) => {
+                        Self::handle_event(&config, &mut client, event, consumer.as_mut())
+                    }
+                    None => Ok(false),
+                });
+
+            if let Err(e) = result {
+                consumer.error(false, anyhow!("MQTT connection error: {e}"));
+                sleep(Duration::from_millis(config.reconnect_delay_ms));
+            }
+        }
+    }
+}
+
+impl InputEndpoint for MqttInputEndpoint {
+    fn connect(&mut self, mut consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        let mut client = MqttClient::new(&self.config);
+
+        // Wait for the broker to acknowledge the connection, so that invalid
+        // broker addresses and credentials are reported to the user.
+        let start = Instant::now();
+        loop {
+            match client.next_event(POLL_TIMEOUT) {
+                Err(e) => bail!(
+                    "failed to connect to MQTT broker {}:{}: {e}",
+                    self.config.host,
+                    self.config.port
+                ),
+                Ok(Some(event)) => {
+                    if Self::handle_event(&self.config, &mut client, event, consumer.as_mut())? {
+                        break;
+                    }
+                }
+                Ok(None) => {}
+            }
+
+            if start.elapsed() >= Duration::from_secs(self.config.connect_timeout_secs as u64) {
+                bail!(
+                    "failed to connect to MQTT broker {}:{}, giving up after {}s",
+                    self.config.host,
+                    self.config.port,
+                    self.config.connect_timeout_secs
+                );
+            }
+        }
+
+        let parker = Parker::new();
+        self.unparker = Some(parker.unparker().clone());
+
+        let config = self.config.clone();
+        let state = self.state.clone();
+        spawn(move || Self::worker_thread(config, client, state, parker, consumer));
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Paused);
+        Ok(())
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Running);
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        self.set_state(PipelineState::Terminated);
+    }
+}
+
+impl Drop for MqttInputEndpoint {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MqttInputTransport;
+    use crate::{test::mock_input_pipeline, transport::InputTransport};
+    use serde_yaml::Value as YamlValue;
+
+    #[test]
+    fn invalid_config() {
+        let config: YamlValue = serde_yaml::from_str(
+            r#"
+host: localhost
+topics: []
+"#,
+        )
+        .unwrap();
+        assert!(MqttInputTransport.new_endpoint("test", &config).is_err());
+
+        let config: YamlValue = serde_yaml::from_str(
+            r#"
+host: localhost
+topics: [sensors/+/temperature]
+session_expiry_secs: 60
+"#,
+        )
+        .unwrap();
+        assert!(MqttInputTransport.new_endpoint("test", &config).is_err());
+    }
+
+    #[test]
+    fn unreachable_broker() {
+        let config_str = r#"
+stream: test_input
+transport:
+    name: mqtt
+    config:
+        host: 127.0.0.1
+        port: 1
+        topics: [sensors/#]
+        connect_timeout_secs: 5
+format:
+    name: csv
+"#;
+
+        let result =
+            mock_input_pipeline::<(u32, bool, String)>(serde_yaml::from_str(config_str).unwrap());
+        let error = match result {
+            Ok(_) => panic!("connecting to an unreachable broker should fail"),
+            Err(error) => error,
+        };
+        assert!(error
+            .to_string()
+            .contains("failed to connect to MQTT broker"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Transports registered at runtime.
+//!
+//! In addition to the built-in transports, the adapters crate can use
+//! transports implemented outside of it, e.g., proprietary connectors.  Such
+//! transports are registered at runtime in one of two ways:
+//!
+//! * An application that links against `dbsp_adapters` directly calls
+//!   [`register_input_transport`] and [`register_output_transport`] before
+//!   creating a controller.
+//!
+//! * A transport packaged as a shared library uses the
+//!   [`declare_transport_plugin`](crate::declare_transport_plugin) macro to
+//!   export a registration function, which the pipeline server invokes when
+//!   loading the library with [`load_transport_plugin`] (requires the
+//!   `with-plugins` feature).
+//!
+//! Registered transports are looked up by name along with built-in
+//! transports via [`InputTransport::get_transport`] and
+//! [`OutputTransport::get_transport`].  A plugin can't replace a built-in
+//! transport or a previously registered one.
+//!
+//! # Compatibility
+//!
+//! Plugins exchange Rust trait objects with the server, so a shared library
+//! plugin must be built with the same compiler version and the same version
+//! of `dbsp_adapters` as the server that loads it.
+//! [`TRANSPORT_PLUGIN_API_VERSION`] is checked at load time to catch the
+//! most common mismatch, but cannot detect all incompatibilities.
+
+use super::{InputTransport, OutputTransport, INPUT_TRANSPORT, OUTPUT_TRANSPORT};
+use anyhow::{bail, Result as AnyResult};
+use once_cell::sync::Lazy;
+use std::{collections::BTreeMap, sync::RwLock};
+
+#[cfg(feature = "with-plugins")]
+use std::path::Path;
+
+/// Version of the transport plugin interface.
+///
+/// Bumped on every incompatible change to [`InputTransport`],
+/// [`OutputTransport`], or the traits they depend on.
+pub const TRANSPORT_PLUGIN_API_VERSION: u32 = 1;
+
+/// Input transports registered at runtime.
+///
+/// Transports are leaked on registration, since
+/// [`InputTransport::get_transport`] hands out `'static` references to them.
+static PLUGIN_INPUT_TRANSPORTS: Lazy<RwLock<BTreeMap<String, &'static dyn InputTransport>>> =
+    Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Output transports registered at runtime.
+static PLUGIN_OUTPUT_TRANSPORTS: Lazy<RwLock<BTreeMap<String, &'static dyn OutputTransport>>> =
+    Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Register an input transport under the name returned by
+/// [`InputTransport::name`].
+///
+/// # Errors
+///
+/// Fails if a transport with the same name already exists.
+pub fn register_input_transport(transport: Box<dyn InputTransport>) -> AnyResult<()> {
+    let name = transport.name().into_owned();
+    let mut transports = PLUGIN_INPUT_TRANSPORTS.write().unwrap();
+    if INPUT_TRANSPORT.contains_key(name.as_str()) || transports.contains_key(&name) {
+        bail!("input transport '{name}' is already registered");
+    }
+    transports.insert(name, Box::leak(transport));
+    Ok(())
+}
+
+/// Register an output transport under the name returned by
+/// [`OutputTransport::name`].
+///
+/// # Errors
+///
+/// Fails if a transport with the same name already exists.
+pub fn register_output_transport(transport: Box<dyn OutputTransport>) -> AnyResult<()> {
+    let name = transport.name().into_owned();
+    let mut transports = PLUGIN_OUTPUT_TRANSPORTS.write().unwrap();
+    if OUTPUT_TRANSPORT.contains_key(name.as_str()) || transports.contains_key(&name) {
+        bail!("output transport '{name}' is already registered");
+    }
+    transports.insert(name, Box::leak(transport));
+    Ok(())
+}
+
+pub(super) fn plugin_input_transport(name: &str) -> Option<&'static dyn InputTransport> {
+    PLUGIN_INPUT_TRANSPORTS.read().unwrap().get(name).copied()
+}
+
+pub(super) fn plugin_output_transport(name: &str) -> Option<&'static dyn OutputTransport> {
+    PLUGIN_OUTPUT_TRANSPORTS.read().unwrap().get(name).copied()
+}
+
+/// Collects the transports provided by a plugin.
+///
+/// Passed to the registration function exported by
+/// [`declare_transport_plugin`](crate::declare_transport_plugin).
+#[derive(Default)]
+pub struct TransportRegistrar {
+    input_transports: Vec<Box<dyn InputTransport>>,
+    output_transports: Vec<Box<dyn OutputTransport>>,
+}
+
+impl TransportRegistrar {
+    pub fn register_input_transport(&mut self, transport: Box<dyn InputTransport>) {
+        self.input_transports.push(transport);
+    }
+
+    pub fn register_output_transport(&mut self, transport: Box<dyn OutputTransport>) {
+        self.output_transports.push(transport);
+    }
+
+    /// Register all collected transports.
+    ///
+    /// Stops at the first transport whose name is already taken.
+    pub fn register(self) -> AnyResult<()> {
+        for transport in self.input_transports {
+            register_input_transport(transport)?;
+        }
+        for transport in self.output_transports {
+            register_output_transport(transport)?;
+        }
+        Ok(())
+    }
+}
+
+/// Export the symbols that make a `cdylib` crate loadable as a transport
+/// plugin.
+///
+/// `$register` is a function that takes a `&mut TransportRegistrar` and
+/// registers the transports implemented by the plugin:
+///
+/// ```ignore
+/// fn register(registrar: &mut TransportRegistrar) {
+///     registrar.register_input_transport(Box::new(MyInputTransport));
+/// }
+///
+/// declare_transport_plugin!(register);
+/// ```
+#[macro_export]
+macro_rules! declare_transport_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub static FELDERA_TRANSPORT_PLUGIN_API_VERSION: u32 =
+            $crate::transport::TRANSPORT_PLUGIN_API_VERSION;
+
+        #[no_mangle]
+        pub fn feldera_register_transports(registrar: &mut $crate::transport::TransportRegistrar) {
+            $register(registrar)
+        }
+    };
+}
+
+/// Load the transport plugin from the shared library at `path` and register
+/// its transports.
+///
+/// The library is never unloaded, since the transports it registers must
+/// outlive the process.
+///
+/// # Safety
+///
+/// Loading a library runs its initialization code.  The library must be a
+/// plugin built with [`declare_transport_plugin`](crate::declare_transport_plugin)
+/// against the same compiler and `dbsp_adapters` version as the current
+/// process (see [module documentation](self)).
+#[cfg(feature = "with-plugins")]
+pub unsafe fn load_transport_plugin(path: &Path) -> AnyResult<()> {
+    use anyhow::Context;
+    use libloading::{Library, Symbol};
+
+    // Transports registered by the plugin contain code from the library, so
+    // it must never be unloaded.
+    let library: &'static Library =
+        Box::leak(Box::new(Library::new(path).with_context(|| {
+            format!("failed to load transport plugin '{}'", path.display())
+        })?));
+
+    let version: Symbol<*const u32> = library
+        .get(b"FELDERA_TRANSPORT_PLUGIN_API_VERSION\0")
+        .with_context(|| format!("'{}' is not a transport plugin", path.display()))?;
+    let version = **version;
+    if version != TRANSPORT_PLUGIN_API_VERSION {
+        bail!(
+            "transport plugin '{}' was built for plugin API version {version}, but this pipeline requires version {TRANSPORT_PLUGIN_API_VERSION}",
+            path.display()
+        );
+    }
+
+    let register: Symbol<fn(&mut TransportRegistrar)> = library
+        .get(b"feldera_register_transports\0")
+        .with_context(|| format!("'{}' is not a transport plugin", path.display()))?;
+    let mut registrar = TransportRegistrar::default();
+    register(&mut registrar);
+
+    registrar
+        .register()
+        .with_context(|| format!("failed to register transport plugin '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{register_input_transport, register_output_transport, TransportRegistrar};
+    use crate::{
+        transport::{FileInputTransport, FileOutputTransport},
+        InputEndpoint, InputTransport, OutputEndpoint, OutputEndpointConfig, OutputTransport,
+    };
+    use anyhow::{anyhow, Result as AnyResult};
+    use serde_yaml::Value as YamlValue;
+    use std::borrow::Cow;
+
+    struct TestInputTransport(&'static str);
+
+    impl InputTransport for TestInputTransport {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::Borrowed(self.0)
+        }
+
+        fn new_endpoint(
+            &self,
+            _name: &str,
+            _config: &YamlValue,
+        ) -> AnyResult<Box<dyn InputEndpoint>> {
+            Err(anyhow!("test transport"))
+        }
+    }
+
+    struct TestOutputTransport(&'static str);
+
+    impl OutputTransport for TestOutputTransport {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::Borrowed(self.0)
+        }
+
+        fn new_endpoint(
+            &self,
+            _name: &str,
+            _config: &OutputEndpointConfig,
+        ) -> AnyResult<Box<dyn OutputEndpoint>> {
+            Err(anyhow!("test transport"))
+        }
+    }
+
+    #[test]
+    fn register() {
+        assert!(<dyn InputTransport>::get_transport("plugin_test_input").is_none());
+        register_input_transport(Box::new(TestInputTransport("plugin_test_input"))).unwrap();
+        assert_eq!(
+            <dyn InputTransport>::get_transport("plugin_test_input")
+                .unwrap()
+                .name(),
+            "plugin_test_input"
+        );
+
+        register_output_transport(Box::new(TestOutputTransport("plugin_test_output"))).unwrap();
+        assert!(<dyn OutputTransport>::get_transport("plugin_test_output").is_some());
+
+        // Names must be unique, including built-in transports.
+        assert!(
+            register_input_transport(Box::new(TestInputTransport("plugin_test_input"))).is_err()
+        );
+        assert!(register_input_transport(Box::new(FileInputTransport)).is_err());
+        assert!(register_output_transport(Box::new(FileOutputTransport)).is_err());
+    }
+
+    #[test]
+    fn registrar() {
+        let mut registrar = TransportRegistrar::default();
+        registrar.register_input_transport(Box::new(TestInputTransport("registrar_test_input")));
+        registrar.register_output_transport(Box::new(TestOutputTransport("registrar_test_output")));
+        registrar.register().unwrap();
+
+        assert!(<dyn InputTransport>::get_transport("registrar_test_input").is_some());
+        assert!(<dyn OutputTransport>::get_transport("registrar_test_output").is_some());
+    }
+}
@@ -0,0 +1,824 @@
+use super::{
+    pgoutput::{format_lsn, tuple_to_json, Lsn, Message, Tuple, TupleValue},
+    quote_ident,
+    replication::ReplicationConnection,
+    TableInfo,
+};
+use crate::{
+    transport::{InputConsumer, InputEndpoint, InputTransport},
+    PipelineState, RetryConfig,
+};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use crossbeam::sync::{Parker, Unparker};
+use log::{debug, info, warn};
+use num_traits::FromPrimitive;
+use postgres::{Client, IsolationLevel, NoTls};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread::spawn,
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+/// Number of rows fetched from the table at a time during the initial
+/// snapshot.
+const SNAPSHOT_BATCH_SIZE: i32 = 10_000;
+
+/// [`InputTransport`] implementation that mirrors a Postgres table using
+/// logical replication.
+///
+/// The endpoint first reads a consistent snapshot of the table and then
+/// streams changes decoded by the built-in `pgoutput` plugin.  Changes are
+/// converted to the JSON `insert_delete` format, one update per line:
+/// inserts become `{"insert": row}`, deletes become `{"delete": row}`, and
+/// an update becomes a deletion of the old row followed by an insertion of
+/// the new row.  The endpoint must therefore be configured with the `json`
+/// format and `update_format: insert_delete`.
+///
+/// Deleting a row requires the values of all of its columns, so the table
+/// must be configured with `REPLICA IDENTITY FULL`.
+///
+/// The endpoint creates a replication slot that exports a snapshot of the
+/// database, and reads the table from this snapshot, so that the snapshot
+/// and the changes streamed from the slot neither overlap nor leave a gap.
+/// Changes are only removed from the slot after they have been forwarded to
+/// the pipeline, which allows the endpoint to reconnect after losing the
+/// connection to the database while streaming changes.  The slot is dropped
+/// when the endpoint disconnects; if the pipeline terminates abnormally, the
+/// slot must be dropped manually, since it prevents Postgres from removing
+/// WAL segments.  Since the state of the pipeline is not persistent, every
+/// new instance of the endpoint starts with a fresh snapshot of the table.
+///
+/// Requires Postgres 11 or later.
+///
+/// This input transport is only available if the crate is configured with
+/// `with-postgres` feature.
+///
+/// The input transport factory gives this transport the name `postgres_cdc`.
+pub struct PostgresCdcInputTransport;
+
+impl InputTransport for PostgresCdcInputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("postgres_cdc")
+    }
+
+    /// Creates a new [`InputEndpoint`] for mirroring a Postgres table,
+    /// interpreting `config` as a [`PostgresCdcInputConfig`].
+    ///
+    /// See [`InputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(&self, _name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
+        let config = PostgresCdcInputConfig::deserialize(config)?;
+        let ep = PostgresCdcInputEndpoint::new(config)?;
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_poll_interval_ms() -> u64 {
+    500
+}
+
+const fn default_max_changes_per_poll() -> u32 {
+    10_000
+}
+
+/// Configuration for mirroring a Postgres table with [`InputTransport`].
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct PostgresCdcInputConfig {
+    /// Postgres connection string, either in the key-value format, e.g.,
+    /// `host=localhost user=postgres dbname=shop`, or as a URI, e.g.,
+    /// `postgresql://postgres@localhost/shop`.
+    ///
+    /// The user must have the `REPLICATION` attribute.  TLS connections are
+    /// not supported.
+    pub uri: String,
+
+    /// Name of the table to mirror, optionally qualified with the schema
+    /// name.
+    pub table: String,
+
+    /// Publication to subscribe to.
+    ///
+    /// The publication is created for `table` if it does not exist.
+    pub publication: String,
+
+    /// Name of the replication slot, which must not exist.  When not
+    /// specified, a unique name is generated.
+    pub slot_name: Option<String>,
+
+    /// Read the current contents of the table before streaming changes.
+    /// Defaults to `true`.
+    ///
+    /// When `false`, only changes committed after the endpoint has
+    /// connected to the database are ingested.
+    #[serde(default = "default_true")]
+    pub snapshot: bool,
+
+    /// Interval in milliseconds between polls for new changes when the
+    /// replication slot is empty.  Defaults to 500.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Maximum number of changes to read from the replication slot per
+    /// poll.  Defaults to 10,000.
+    #[serde(default = "default_max_changes_per_poll")]
+    pub max_changes_per_poll: u32,
+
+    /// Policy for reconnecting after losing the connection while streaming
+    /// changes.  By default, the endpoint keeps reconnecting indefinitely.
+    ///
+    /// Losing the connection while reading the initial snapshot is fatal.
+    #[serde(default)]
+    pub reconnect: RetryConfig,
+}
+
+impl PostgresCdcInputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.table.is_empty() {
+            bail!("Postgres CDC input endpoint configuration must specify a table");
+        }
+        if self.publication.is_empty() {
+            bail!("Postgres CDC input endpoint configuration must specify a publication");
+        }
+        if self.max_changes_per_poll == 0 || self.max_changes_per_poll > i32::MAX as u32 {
+            bail!("'max_changes_per_poll' must be between 1 and {}", i32::MAX);
+        }
+        Ok(())
+    }
+
+    fn slot_name(&self) -> String {
+        self.slot_name
+            .clone()
+            .unwrap_or_else(|| format!("feldera_{}", uuid::Uuid::new_v4().simple()))
+    }
+}
+
+/// Converts `pgoutput` messages for the mirrored table to JSON updates.
+struct ChangeDecoder {
+    table: TableInfo,
+
+    /// Relation id of the table in the replication stream.
+    relation: Option<u32>,
+
+    /// Commit LSN of the last decoded transaction.
+    ///
+    /// The slot sends transactions again if the endpoint reconnects before
+    /// it was advanced past them.  Such transactions are skipped.
+    last_commit: Option<Lsn>,
+
+    /// End of the commit record of the last decoded transaction.  The slot
+    /// is advanced to this LSN once the changes have been forwarded.
+    end_lsn: Option<Lsn>,
+
+    /// Skip changes in the current transaction.
+    skip_transaction: bool,
+}
+
+impl ChangeDecoder {
+    fn new(table: TableInfo) -> Self {
+        Self {
+            table,
+            relation: None,
+            last_commit: None,
+            end_lsn: None,
+            skip_transaction: false,
+        }
+    }
+
+    fn push_update(buffer: &mut Vec<u8>, command: &str, row: JsonValue) {
+        serde_json::to_writer(&mut *buffer, &json!({ command: row })).unwrap();
+        buffer.push(b'\n');
+    }
+
+    /// Decode `message`, appending resulting updates to `buffer`.
+    fn handle(&mut self, message: Message, buffer: &mut Vec<u8>) -> AnyResult<()> {
+        match message {
+            Message::Begin { final_lsn } => {
+                self.skip_transaction = Some(final_lsn) <= self.last_commit;
+            }
+            Message::Commit {
+                commit_lsn,
+                end_lsn,
+            } => {
+                if !self.skip_transaction {
+                    self.last_commit = Some(commit_lsn);
+                    self.end_lsn = Some(end_lsn);
+                }
+                self.skip_transaction = false;
+            }
+            Message::Relation {
+                id,
+                namespace,
+                name,
+                columns,
+            } => {
+                if namespace == self.table.namespace && name == self.table.name {
+                    self.relation = Some(id);
+                    self.table.columns = columns;
+                }
+            }
+            Message::Insert { relation, new } if self.is_target(relation) => {
+                Self::push_update(buffer, "insert", self.to_json(&new)?);
+            }
+            Message::Update { relation, old, new } if self.is_target(relation) => {
+                let old = self.old_tuple(old)?;
+                let new = new
+                    .into_iter()
+                    .zip(old.iter())
+                    .map(|(new, old)| match new {
+                        TupleValue::Unchanged => old.clone(),
+                        new => new,
+                    })
+                    .collect::<Tuple>();
+                Self::push_update(buffer, "delete", self.to_json(&old)?);
+                Self::push_update(buffer, "insert", self.to_json(&new)?);
+            }
+            Message::Delete { relation, old } if self.is_target(relation) => {
+                let old = self.old_tuple(old)?;
+                Self::push_update(buffer, "delete", self.to_json(&old)?);
+            }
+            Message::Truncate { relations }
+                if relations.iter().any(|relation| self.is_target(*relation)) =>
+            {
+                bail!(
+                    "table '{}' was truncated; TRUNCATE is not supported by the Postgres CDC connector",
+                    self.table.name
+                );
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn is_target(&self, relation: u32) -> bool {
+        !self.skip_transaction && self.relation == Some(relation)
+    }
+
+    fn old_tuple(&self, old: Option<Tuple>) -> AnyResult<Tuple> {
+        old.ok_or_else(|| {
+            anyhow!(
+                "replication stream does not include the old row of table '{}'; the table must be configured with REPLICA IDENTITY FULL",
+                self.table.name
+            )
+        })
+    }
+
+    fn to_json(&self, tuple: &Tuple) -> AnyResult<JsonValue> {
+        tuple_to_json(&self.table.columns, tuple)
+    }
+}
+
+struct PostgresCdcInputEndpoint {
+    config: PostgresCdcInputConfig,
+    state: Arc<AtomicU32>,
+    unparker: Option<Unparker>,
+}
+
+impl PostgresCdcInputEndpoint {
+    fn new(config: PostgresCdcInputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!("Starting Postgres CDC input endpoint: {config:?}");
+
+        Ok(Self {
+            config,
+            state: Arc::new(AtomicU32::new(PipelineState::Paused as u32)),
+            unparker: None,
+        })
+    }
+
+    fn set_state(&self, state: PipelineState) {
+        self.state.store(state as u32, Ordering::Release);
+        if let Some(unparker) = &self.unparker {
+            unparker.unpark();
+        }
+    }
+
+    fn connect_client(config: &PostgresCdcInputConfig) -> AnyResult<Client> {
+        Client::connect(&config.uri, NoTls)
+            .map_err(|e| anyhow!("failed to connect to Postgres: {e}"))
+    }
+
+    /// Replace `client` with a new connection if it has been closed.
+    fn reconnect(config: &PostgresCdcInputConfig, client: &mut Client) -> AnyResult<()> {
+        if client.is_closed() {
+            info!("Reconnecting to Postgres");
+            *client = Self::connect_client(config)?;
+        }
+        Ok(())
+    }
+
+    /// Create the publication if it does not exist.
+    fn create_publication(
+        client: &mut Client,
+        publication: &str,
+        table: &TableInfo,
+    ) -> AnyResult<()> {
+        let exists = !client
+            .query(
+                "SELECT 1 FROM pg_publication WHERE pubname = $1",
+                &[&publication],
+            )?
+            .is_empty();
+        if !exists {
+            info!(
+                "Creating publication '{publication}' for table {}",
+                table.qualified_name()
+            );
+            client
+                .batch_execute(&format!(
+                    "CREATE PUBLICATION {} FOR TABLE {}",
+                    quote_ident(publication),
+                    table.qualified_name()
+                ))
+                .map_err(|e| anyhow!("failed to create publication '{publication}': {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Drop the replication slot, which otherwise prevents Postgres from
+    /// removing WAL segments.
+    fn drop_slot(config: &PostgresCdcInputConfig, slot_name: &str, client: &mut Client) {
+        let result = Self::reconnect(config, client).and_then(|()| {
+            client.execute("SELECT pg_drop_replication_slot($1)", &[&slot_name])?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            warn!("failed to drop replication slot '{slot_name}', which must be dropped manually: {e}");
+        }
+    }
+
+    /// Wait while the endpoint is paused.  Returns `false` if the endpoint
+    /// has been terminated.
+    fn wait_running(state: &AtomicU32, parker: &Parker) -> bool {
+        loop {
+            match PipelineState::from_u32(state.load(Ordering::Acquire)).unwrap() {
+                PipelineState::Terminated => return false,
+                PipelineState::Paused => parker.park(),
+                PipelineState::Running => return true,
+            }
+        }
+    }
+
+    /// Read the current contents of the table from the snapshot exported by
+    /// `replication` when it created the replication slot.
+    ///
+    /// Returns `false` if the endpoint was terminated.
+    fn snapshot(
+        client: &mut Client,
+        replication: ReplicationConnection,
+        snapshot_name: &str,
+        table: &TableInfo,
+        state: &AtomicU32,
+        parker: &Parker,
+        consumer: &mut dyn InputConsumer,
+    ) -> AnyResult<bool> {
+        let mut transaction = client
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()?;
+
+        // Must be the first statement of the transaction.
+        transaction.batch_execute(&format!(
+            "SET TRANSACTION SNAPSHOT '{}'",
+            snapshot_name.replace('\'', "''")
+        ))?;
+
+        // The exported snapshot is only needed until it has been imported.
+        drop(replication);
+
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| format!("{}::text", quote_ident(&column.name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!("SELECT ARRAY[{columns}] FROM {}", table.qualified_name());
+        let portal = transaction.bind(&query, &[])?;
+
+        let mut buffer = Vec::new();
+        loop {
+            if !Self::wait_running(state, parker) {
+                return Ok(false);
+            }
+
+            let rows = transaction.query_portal(&portal, SNAPSHOT_BATCH_SIZE)?;
+            if rows.is_empty() {
+                break;
+            }
+
+            for row in rows.iter() {
+                let values: Vec<Option<String>> = row.get(0);
+                let tuple = values
+                    .into_iter()
+                    .map(|value| value.map_or(TupleValue::Null, TupleValue::Text))
+                    .collect::<Tuple>();
+                ChangeDecoder::push_update(
+                    &mut buffer,
+                    "insert",
+                    tuple_to_json(&table.columns, &tuple)?,
+                );
+            }
+            // Leave it to the controller to handle errors.  There is noone we can
+            // forward the error to upstream.
+            let _ = consumer.input_chunk(&buffer);
+            buffer.clear();
+        }
+
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    /// Consume available changes from the replication slot.  Returns the
+    /// number of changes consumed.
+    fn poll(
+        config: &PostgresCdcInputConfig,
+        slot_name: &str,
+        client: &mut Client,
+        decoder: &mut ChangeDecoder,
+        consumer: &mut dyn InputConsumer,
+    ) -> AnyResult<usize> {
+        // Changes are only removed from the slot after they have been
+        // forwarded, so that none are lost if the connection fails before.
+        let rows = client.query(
+            "SELECT data FROM pg_logical_slot_peek_binary_changes($1, NULL, $2, \
+             'proto_version', '1', 'publication_names', $3)",
+            &[
+                &slot_name,
+                &(config.max_changes_per_poll as i32),
+                &config.publication,
+            ],
+        )?;
+
+        let mut buffer = Vec::new();
+        for row in rows.iter() {
+            let data: Vec<u8> = row.get(0);
+            decoder.handle(Message::decode(&data)?, &mut buffer)?;
+        }
+        if !buffer.is_empty() {
+            let _ = consumer.input_chunk(&buffer);
+        }
+
+        if let (false, Some(end_lsn)) = (rows.is_empty(), decoder.end_lsn) {
+            client.execute(
+                "SELECT 1 FROM pg_replication_slot_advance($1, $2::text::pg_lsn)",
+                &[&slot_name, &format_lsn(end_lsn)],
+            )?;
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Stream changes from the replication slot until the endpoint is
+    /// terminated, reconnecting to the database when the connection fails.
+    fn stream(
+        config: &PostgresCdcInputConfig,
+        slot_name: &str,
+        client: &mut Client,
+        table: TableInfo,
+        state: &AtomicU32,
+        parker: &Parker,
+        consumer: &mut dyn InputConsumer,
+    ) -> AnyResult<()> {
+        info!(
+            "Streaming changes to table {} from replication slot '{slot_name}'",
+            table.qualified_name()
+        );
+        let mut decoder = ChangeDecoder::new(table);
+
+        // Number of consecutive failed attempts to read from the slot.
+        let mut failures = 0;
+
+        loop {
+            if !Self::wait_running(state, parker) {
+                return Ok(());
+            }
+
+            match Self::reconnect(config, client)
+                .and_then(|()| Self::poll(config, slot_name, client, &mut decoder, consumer))
+            {
+                Ok(changes) => {
+                    failures = 0;
+                    if changes == 0 {
+                        parker.park_timeout(Duration::from_millis(config.poll_interval_ms));
+                    }
+                }
+                Err(error) if client.is_closed() => {
+                    failures += 1;
+                    let Some(delay) = config.reconnect.backoff(failures) else {
+                        bail!(
+                            "Postgres replication error: {error}; giving up after {} reconnection attempts",
+                            failures - 1
+                        );
+                    };
+                    consumer.error(false, anyhow!("Postgres replication error: {error}"));
+
+                    // Back off, unless the endpoint is paused or terminated
+                    // in the meantime.
+                    parker.park_timeout(delay);
+                }
+                Err(error) => bail!("Postgres replication error: {error}"),
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn worker_thread(
+        config: PostgresCdcInputConfig,
+        slot_name: String,
+        mut client: Client,
+        snapshot: Option<(ReplicationConnection, String)>,
+        table: TableInfo,
+        state: Arc<AtomicU32>,
+        parker: Parker,
+        mut consumer: Box<dyn InputConsumer>,
+    ) {
+        let result = match snapshot {
+            Some((replication, snapshot_name)) => Self::snapshot(
+                &mut client,
+                replication,
+                &snapshot_name,
+                &table,
+                &state,
+                &parker,
+                consumer.as_mut(),
+            )
+            .map_err(|e| anyhow!("failed to read table snapshot: {e}")),
+            None => Ok(true),
+        }
+        .and_then(|running| {
+            if running {
+                Self::stream(
+                    &config,
+                    &slot_name,
+                    &mut client,
+                    table,
+                    &state,
+                    &parker,
+                    consumer.as_mut(),
+                )
+            } else {
+                Ok(())
+            }
+        });
+
+        if let Err(e) = result {
+            consumer.error(true, e);
+        }
+        Self::drop_slot(&config, &slot_name, &mut client);
+    }
+}
+
+impl InputEndpoint for PostgresCdcInputEndpoint {
+    fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        let mut client = Self::connect_client(&self.config)?;
+
+        let table = TableInfo::resolve(&mut client, &self.config.table)?;
+        if !table.full_replica_identity {
+            bail!(
+                "table '{}' must be configured with REPLICA IDENTITY FULL, so that deleted and updated rows can be retracted (run 'ALTER TABLE {} REPLICA IDENTITY FULL')",
+                self.config.table,
+                self.config.table
+            );
+        }
+        Self::create_publication(&mut client, &self.config.publication, &table)?;
+
+        // Creating the slot over a replication connection exports a
+        // snapshot at the slot's consistent point, which we read the table
+        // from.  The replication connection must stay open until the
+        // snapshot has been imported.
+        let slot_name = self.config.slot_name();
+        let mut replication = ReplicationConnection::connect(&self.config.uri)
+            .map_err(|e| anyhow!("failed to open replication connection to Postgres: {e}"))?;
+        let snapshot = replication
+            .create_slot(&slot_name, self.config.snapshot)
+            .map_err(|e| anyhow!("failed to create replication slot '{slot_name}': {e}"))?
+            .map(|snapshot_name| (replication, snapshot_name));
+
+        let parker = Parker::new();
+        self.unparker = Some(parker.unparker().clone());
+
+        let config = self.config.clone();
+        let state = self.state.clone();
+        spawn(move || {
+            Self::worker_thread(
+                config, slot_name, client, snapshot, table, state, parker, consumer,
+            )
+        });
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Paused);
+        Ok(())
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        self.set_state(PipelineState::Running);
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        self.set_state(PipelineState::Terminated);
+    }
+}
+
+impl Drop for PostgresCdcInputEndpoint {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChangeDecoder, PostgresCdcInputTransport};
+    use crate::{
+        test::mock_input_pipeline,
+        transport::{
+            postgres::{
+                pgoutput::{Column, Message, TupleValue},
+                TableInfo,
+            },
+            InputTransport,
+        },
+    };
+    use serde_json::{json, Value as JsonValue};
+    use serde_yaml::Value as YamlValue;
+
+    fn text(s: &str) -> TupleValue {
+        TupleValue::Text(s.to_string())
+    }
+
+    #[test]
+    fn decode_changes() {
+        let columns = vec![
+            Column {
+                name: "id".to_string(),
+                type_oid: 23,
+            },
+            Column {
+                name: "doc".to_string(),
+                type_oid: 25,
+            },
+        ];
+        let table = TableInfo {
+            namespace: "public".to_string(),
+            name: "t".to_string(),
+            columns: columns.clone(),
+            full_replica_identity: true,
+        };
+        let mut decoder = ChangeDecoder::new(table);
+        let mut buffer = Vec::new();
+
+        let messages = vec![
+            Message::Relation {
+                id: 1,
+                namespace: "public".to_string(),
+                name: "t".to_string(),
+                columns: columns.clone(),
+            },
+            Message::Relation {
+                id: 2,
+                namespace: "public".to_string(),
+                name: "other".to_string(),
+                columns: columns.clone(),
+            },
+            Message::Begin { final_lsn: 50 },
+            Message::Insert {
+                relation: 1,
+                new: vec![text("0"), text("old")],
+            },
+            Message::Commit {
+                commit_lsn: 50,
+                end_lsn: 60,
+            },
+            Message::Begin { final_lsn: 150 },
+            Message::Insert {
+                relation: 1,
+                new: vec![text("1"), text("foo")],
+            },
+            // Another table: skipped.
+            Message::Insert {
+                relation: 2,
+                new: vec![text("2"), text("bar")],
+            },
+            Message::Update {
+                relation: 1,
+                old: Some(vec![text("1"), text("foo")]),
+                new: vec![text("2"), TupleValue::Unchanged],
+            },
+            Message::Delete {
+                relation: 1,
+                old: Some(vec![text("2"), TupleValue::Null]),
+            },
+            Message::Commit {
+                commit_lsn: 150,
+                end_lsn: 160,
+            },
+            // Sent again after a reconnect: skipped.
+            Message::Begin { final_lsn: 150 },
+            Message::Insert {
+                relation: 1,
+                new: vec![text("1"), text("foo")],
+            },
+            Message::Commit {
+                commit_lsn: 150,
+                end_lsn: 160,
+            },
+        ];
+        for message in messages {
+            decoder.handle(message, &mut buffer).unwrap();
+        }
+
+        let updates = serde_json::Deserializer::from_slice(&buffer)
+            .into_iter::<JsonValue>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            updates,
+            vec![
+                json!({"insert": {"id": 0, "doc": "old"}}),
+                json!({"insert": {"id": 1, "doc": "foo"}}),
+                json!({"delete": {"id": 1, "doc": "foo"}}),
+                json!({"insert": {"id": 2, "doc": "foo"}}),
+                json!({"delete": {"id": 2, "doc": null}}),
+            ]
+        );
+        assert_eq!(decoder.end_lsn, Some(160));
+
+        assert!(decoder
+            .handle(
+                Message::Delete {
+                    relation: 1,
+                    old: None
+                },
+                &mut buffer
+            )
+            .is_err());
+        assert!(decoder
+            .handle(Message::Truncate { relations: vec![1] }, &mut buffer)
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config: YamlValue = serde_yaml::from_str(
+            r#"
+uri: host=localhost user=postgres
+table: ""
+publication: feldera
+"#,
+        )
+        .unwrap();
+        assert!(PostgresCdcInputTransport
+            .new_endpoint("test", &config)
+            .is_err());
+
+        let config: YamlValue = serde_yaml::from_str(
+            r#"
+uri: host=localhost user=postgres
+table: orders
+publication: feldera
+max_changes_per_poll: 0
+"#,
+        )
+        .unwrap();
+        assert!(PostgresCdcInputTransport
+            .new_endpoint("test", &config)
+            .is_err());
+    }
+
+    #[test]
+    fn unreachable_server() {
+        let config_str = r#"
+stream: test_input
+transport:
+    name: postgres_cdc
+    config:
+        uri: host=127.0.0.1 port=1 user=postgres connect_timeout=5
+        table: orders
+        publication: feldera
+format:
+    name: json
+"#;
+
+        let result =
+            mock_input_pipeline::<(u32, bool, String)>(serde_yaml::from_str(config_str).unwrap());
+        let error = match result {
+            Ok(_) => panic!("connecting to an unreachable server should fail"),
+            Err(error) => error,
+        };
+        assert!(error.to_string().contains("failed to connect to Postgres"));
+    }
+}
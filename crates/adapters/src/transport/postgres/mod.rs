@@ -0,0 +1,91 @@
+use anyhow::{anyhow, bail, Result as AnyResult};
+use pgoutput::Column;
+use postgres::Client;
+
+mod input;
+mod output;
+mod pgoutput;
+mod replication;
+
+pub use input::{PostgresCdcInputConfig, PostgresCdcInputTransport};
+pub use output::{PostgresOutputConfig, PostgresOutputTransport};
+
+/// Quote a Postgres identifier.
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// A Postgres table read or written by an endpoint.
+#[derive(Clone, Debug)]
+struct TableInfo {
+    namespace: String,
+    name: String,
+    columns: Vec<Column>,
+
+    /// The table is configured with `REPLICA IDENTITY FULL`.
+    full_replica_identity: bool,
+}
+
+impl TableInfo {
+    /// Look up the table in the catalog.
+    fn resolve(client: &mut Client, table: &str) -> AnyResult<Self> {
+        let row = client
+            .query_one(
+                "SELECT c.oid, n.nspname::text, c.relname::text, c.relreplident::text \
+                 FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE c.oid = $1::text::regclass",
+                &[&table],
+            )
+            .map_err(|e| anyhow!("failed to look up table '{table}': {e}"))?;
+        let oid: u32 = row.get(0);
+        let namespace: String = row.get(1);
+        let name: String = row.get(2);
+        let replica_identity: String = row.get(3);
+
+        let columns = client
+            .query(
+                "SELECT attname::text, atttypid FROM pg_attribute \
+                 WHERE attrelid = $1 AND attnum > 0 AND NOT attisdropped \
+                 ORDER BY attnum",
+                &[&oid],
+            )?
+            .into_iter()
+            .map(|row| Column {
+                name: row.get(0),
+                type_oid: row.get(1),
+            })
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            bail!("table '{table}' has no columns");
+        }
+
+        Ok(Self {
+            namespace,
+            name,
+            columns,
+            full_replica_identity: replica_identity == "f",
+        })
+    }
+
+    fn qualified_name(&self) -> String {
+        format!(
+            "{}.{}",
+            quote_ident(&self.namespace),
+            quote_ident(&self.name)
+        )
+    }
+
+    /// Find a column by name.  Exact matches take precedence over
+    /// case-insensitive ones, since unquoted SQL identifiers may be
+    /// normalized to different cases by Postgres and the SQL compiler.
+    fn find_column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .find(|column| column.name.eq_ignore_ascii_case(name))
+            })
+    }
+}
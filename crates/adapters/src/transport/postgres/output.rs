@@ -0,0 +1,389 @@
+use super::{quote_ident, TableInfo};
+use crate::{AsyncErrorCallback, OutputEndpoint, OutputEndpointConfig, OutputTransport};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use log::{debug, info};
+use postgres::{Client, NoTls};
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::{borrow::Cow, collections::BTreeMap};
+use utoipa::ToSchema;
+
+/// [`OutputTransport`] implementation that applies changes to a Postgres
+/// table.
+///
+/// The endpoint must be configured with the `json` format.  Inserted
+/// records are upserted into the table with `INSERT ... ON CONFLICT DO
+/// UPDATE`, and deleted records are deleted by key.  All changes produced
+/// by the pipeline in one step are applied in a single transaction.
+///
+/// This output transport is only available if the crate is configured with
+/// `with-postgres` feature.
+///
+/// The output transport factory gives this transport the name `postgres`.
+pub struct PostgresOutputTransport;
+
+impl OutputTransport for PostgresOutputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("postgres")
+    }
+
+    /// Creates a new [`OutputEndpoint`] for writing to a Postgres table,
+    /// interpreting `config` as a [`PostgresOutputConfig`].
+    ///
+    /// See [`OutputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(
+        &self,
+        _name: &str,
+        config: &OutputEndpointConfig,
+    ) -> AnyResult<Box<dyn OutputEndpoint>> {
+        let config = PostgresOutputConfig::deserialize(&config.connector_config.transport.config)?;
+        let ep = PostgresOutputEndpoint::new(config)?;
+
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_batch_size() -> usize {
+    1000
+}
+
+/// Configuration for writing to a Postgres table with [`OutputTransport`].
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct PostgresOutputConfig {
+    /// Postgres connection string, either in the key-value format, e.g.,
+    /// `host=localhost user=postgres dbname=shop`, or as a URI, e.g.,
+    /// `postgresql://postgres@localhost/shop`.
+    ///
+    /// TLS connections are not supported.
+    pub uri: String,
+
+    /// Name of the table to write to, optionally qualified with the schema
+    /// name.
+    ///
+    /// The table must contain a column for each column of the view.
+    pub table: String,
+
+    /// Columns that uniquely identify a row of the table.
+    ///
+    /// The table must have a primary key or a unique constraint on these
+    /// columns.
+    pub key_columns: Vec<String>,
+
+    /// Maximum number of rows written by a single `INSERT` or `DELETE`
+    /// statement.  Defaults to 1000.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl PostgresOutputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.table.is_empty() {
+            bail!("Postgres output endpoint configuration must specify a table");
+        }
+        if self.key_columns.is_empty() {
+            bail!("Postgres output endpoint configuration must specify at least one key column");
+        }
+        if self.batch_size == 0 {
+            bail!("'batch_size' must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
+/// Statements used to apply changes to the table.
+///
+/// Rows are passed to the statements as a JSON array, which
+/// `json_populate_recordset` converts to records of the table type,
+/// letting Postgres convert each value to the type of its column.
+struct Statements {
+    upsert: String,
+    delete: String,
+}
+
+impl Statements {
+    fn new(table: &TableInfo, columns: &[String], key_columns: &[String]) -> Self {
+        let table_name = table.qualified_name();
+        let quoted_columns = columns
+            .iter()
+            .map(|column| quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let quoted_keys = key_columns
+            .iter()
+            .map(|column| quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let updates = columns
+            .iter()
+            .filter(|column| !key_columns.contains(column))
+            .map(|column| format!("{0} = EXCLUDED.{0}", quote_ident(column)))
+            .collect::<Vec<_>>();
+        let on_conflict = if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        };
+
+        let upsert = format!(
+            "INSERT INTO {table_name} ({quoted_columns}) \
+             SELECT {quoted_columns} FROM json_populate_recordset(NULL::{table_name}, $1::text::json) \
+             ON CONFLICT ({quoted_keys}) {on_conflict}"
+        );
+
+        let condition = key_columns
+            .iter()
+            .map(|column| format!("target.{0} = deleted.{0}", quote_ident(column)))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let delete = format!(
+            "DELETE FROM {table_name} AS target \
+             USING json_populate_recordset(NULL::{table_name}, $1::text::json) AS deleted \
+             WHERE {condition}"
+        );
+
+        Self { upsert, delete }
+    }
+}
+
+struct PostgresOutputEndpoint {
+    config: PostgresOutputConfig,
+    client: Client,
+    table: TableInfo,
+
+    /// Key columns, as named in the table.
+    key_columns: Vec<String>,
+
+    /// Created when the first record is received, since the set of columns
+    /// to write is determined by the schema of the view.
+    statements: Option<Statements>,
+
+    /// Changes in the current batch, indexed by key.  `None` means that
+    /// the row is deleted.
+    changes: BTreeMap<String, Option<JsonMap<String, JsonValue>>>,
+}
+
+impl PostgresOutputEndpoint {
+    fn new(config: PostgresOutputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!("Starting Postgres output endpoint: {config:?}");
+
+        let mut client = Self::connect_client(&config)?;
+        let table = TableInfo::resolve(&mut client, &config.table)?;
+        let key_columns = config
+            .key_columns
+            .iter()
+            .map(|key| {
+                table
+                    .find_column(key)
+                    .map(|column| column.name.clone())
+                    .ok_or_else(|| {
+                        anyhow!("key column '{key}' not found in table '{}'", config.table)
+                    })
+            })
+            .collect::<AnyResult<Vec<_>>>()?;
+
+        Ok(Self {
+            config,
+            client,
+            table,
+            key_columns,
+            statements: None,
+            changes: BTreeMap::new(),
+        })
+    }
+
+    fn connect_client(config: &PostgresOutputConfig) -> AnyResult<Client> {
+        Client::connect(&config.uri, NoTls)
+            .map_err(|e| anyhow!("failed to connect to Postgres: {e}"))
+    }
+
+    /// Rename the fields of a record produced by the JSON encoder after the
+    /// columns of the table.
+    fn normalize(&self, record: JsonValue) -> AnyResult<JsonMap<String, JsonValue>> {
+        let record = match record {
+            JsonValue::Object(record) => record,
+            record => bail!("expected a JSON object, found '{record}'"),
+        };
+
+        record
+            .into_iter()
+            .map(|(field, value)| {
+                let column = self.table.find_column(&field).ok_or_else(|| {
+                    anyhow!(
+                        "column '{field}' not found in table '{}'",
+                        self.config.table
+                    )
+                })?;
+                Ok((column.name.clone(), value))
+            })
+            .collect()
+    }
+
+    fn key(&self, row: &JsonMap<String, JsonValue>) -> AnyResult<String> {
+        let key = self
+            .key_columns
+            .iter()
+            .map(|column| {
+                row.get(column)
+                    .ok_or_else(|| anyhow!("output record is missing key column '{column}'"))
+            })
+            .collect::<AnyResult<Vec<_>>>()?;
+        Ok(serde_json::to_string(&key)?)
+    }
+
+    fn add_change(&mut self, record: JsonValue, insert: bool) -> AnyResult<()> {
+        let row = self.normalize(record)?;
+        if self.statements.is_none() {
+            let columns = row.keys().cloned().collect::<Vec<_>>();
+            self.statements = Some(Statements::new(&self.table, &columns, &self.key_columns));
+        }
+
+        let key = self.key(&row)?;
+        if insert {
+            self.changes.insert(key, Some(row));
+        } else {
+            // An update consists of a deletion and an insertion of a record
+            // with the same key; the insertion wins regardless of the order.
+            self.changes.entry(key).or_insert(None);
+        }
+        Ok(())
+    }
+
+    /// Apply `changes` in a single transaction.
+    fn apply(
+        &mut self,
+        changes: BTreeMap<String, Option<JsonMap<String, JsonValue>>>,
+    ) -> AnyResult<()> {
+        if self.client.is_closed() {
+            info!("Reconnecting to Postgres");
+            self.client = Self::connect_client(&self.config)?;
+        }
+
+        let statements = match &self.statements {
+            None => return Ok(()),
+            Some(statements) => statements,
+        };
+
+        let mut deletes = Vec::new();
+        let mut upserts = Vec::new();
+        for (key, row) in changes.into_iter() {
+            match row {
+                Some(row) => upserts.push(JsonValue::Object(row)),
+                None => {
+                    let key: Vec<JsonValue> = serde_json::from_str(&key)?;
+                    deletes.push(JsonValue::Object(
+                        self.key_columns.iter().cloned().zip(key).collect(),
+                    ));
+                }
+            }
+        }
+
+        let mut transaction = self.client.transaction()?;
+        for chunk in deletes.chunks(self.config.batch_size) {
+            transaction.execute(&statements.delete, &[&serde_json::to_string(chunk)?])?;
+        }
+        for chunk in upserts.chunks(self.config.batch_size) {
+            transaction.execute(&statements.upsert, &[&serde_json::to_string(chunk)?])?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+impl OutputEndpoint for PostgresOutputEndpoint {
+    fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        usize::MAX
+    }
+
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.changes.clear();
+        Ok(())
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        for value in serde_json::Deserializer::from_slice(buffer).into_iter::<JsonValue>() {
+            let value = value.map_err(|e| anyhow!("error parsing output buffer as JSON: {e}"))?;
+            let updates = match value {
+                JsonValue::Array(updates) => updates,
+                update => vec![update],
+            };
+
+            for mut update in updates.into_iter() {
+                if let Some(record) = update.get_mut("delete") {
+                    self.add_change(record.take(), false)?;
+                }
+                if let Some(record) = update.get_mut("insert") {
+                    self.add_change(record.take(), true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        let changes = std::mem::take(&mut self.changes);
+        self.apply(changes)
+            .map_err(|e| anyhow!("failed to write to table '{}': {e}", self.config.table))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PostgresOutputConfig, Statements};
+    use crate::transport::postgres::{pgoutput::Column, TableInfo};
+
+    #[test]
+    fn statements() {
+        let table = TableInfo {
+            namespace: "public".to_string(),
+            name: "Orders".to_string(),
+            columns: ["id", "region", "total"]
+                .into_iter()
+                .map(|name| Column {
+                    name: name.to_string(),
+                    type_oid: 25,
+                })
+                .collect(),
+            full_replica_identity: false,
+        };
+        assert_eq!(table.find_column("ID").unwrap().name, "id");
+        assert!(table.find_column("customer").is_none());
+
+        let columns = vec!["id".to_string(), "region".to_string(), "total".to_string()];
+        let keys = vec!["id".to_string(), "region".to_string()];
+        let statements = Statements::new(&table, &columns, &keys);
+        assert_eq!(
+            statements.upsert,
+            r#"INSERT INTO "public"."Orders" ("id", "region", "total") SELECT "id", "region", "total" FROM json_populate_recordset(NULL::"public"."Orders", $1::text::json) ON CONFLICT ("id", "region") DO UPDATE SET "total" = EXCLUDED."total""#
+        );
+        assert_eq!(
+            statements.delete,
+            r#"DELETE FROM "public"."Orders" AS target USING json_populate_recordset(NULL::"public"."Orders", $1::text::json) AS deleted WHERE target."id" = deleted."id" AND target."region" = deleted."region""#
+        );
+
+        let statements = Statements::new(&table, &keys, &keys);
+        assert!(statements
+            .upsert
+            .ends_with("ON CONFLICT (\"id\", \"region\") DO NOTHING"));
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config: PostgresOutputConfig = serde_yaml::from_str(
+            r#"
+uri: host=localhost user=postgres
+table: orders
+key_columns: []
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+}
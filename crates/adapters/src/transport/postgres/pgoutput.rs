@@ -0,0 +1,440 @@
+//! Decoder for the `pgoutput` logical replication protocol (version 1).
+//!
+//! See <https://www.postgresql.org/docs/current/protocol-logicalrep-message-formats.html>.
+
+use anyhow::{anyhow, bail, Result as AnyResult};
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+
+/// Log sequence number.
+pub(super) type Lsn = u64;
+
+/// Format an LSN in its textual representation.
+pub(super) fn format_lsn(lsn: Lsn) -> String {
+    format!("{:X}/{:X}", lsn >> 32, lsn as u32)
+}
+
+/// A column of a relation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Column {
+    pub name: String,
+    pub type_oid: u32,
+}
+
+/// A single column value in a tuple.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum TupleValue {
+    Null,
+    /// Unchanged TOASTed value; the actual value is not sent.
+    Unchanged,
+    Text(String),
+}
+
+pub(super) type Tuple = Vec<TupleValue>;
+
+/// A decoded `pgoutput` message.
+///
+/// Messages that the connector does not use, such as `Type` and `Origin`,
+/// are decoded as [`Message::Other`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Message {
+    Begin {
+        final_lsn: Lsn,
+    },
+    Commit {
+        commit_lsn: Lsn,
+        /// End of the commit record.
+        end_lsn: Lsn,
+    },
+    Relation {
+        id: u32,
+        namespace: String,
+        name: String,
+        columns: Vec<Column>,
+    },
+    Insert {
+        relation: u32,
+        new: Tuple,
+    },
+    Update {
+        relation: u32,
+        /// Old tuple.  Only sent for tables with `REPLICA IDENTITY FULL`
+        /// (or the key columns of the old tuple, if the key changed, which
+        /// we treat as missing).
+        old: Option<Tuple>,
+        new: Tuple,
+    },
+    Delete {
+        relation: u32,
+        old: Option<Tuple>,
+    },
+    Truncate {
+        relations: Vec<u32>,
+    },
+    Other,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> AnyResult<&'a [u8]> {
+        if self.data.len() < n {
+            bail!("truncated pgoutput message");
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> AnyResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> AnyResult<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> AnyResult<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> AnyResult<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Null-terminated string.
+    fn string(&mut self) -> AnyResult<String> {
+        let len = self
+            .data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| anyhow!("unterminated string in pgoutput message"))?;
+        let s = String::from_utf8(self.take(len)?.to_vec())?;
+        self.take(1)?;
+        Ok(s)
+    }
+
+    fn tuple(&mut self) -> AnyResult<Tuple> {
+        let ncols = self.u16()?;
+        let mut tuple = Vec::with_capacity(ncols as usize);
+        for _ in 0..ncols {
+            let value = match self.u8()? {
+                b'n' => TupleValue::Null,
+                b'u' => TupleValue::Unchanged,
+                b't' => {
+                    let len = self.u32()? as usize;
+                    TupleValue::Text(String::from_utf8(self.take(len)?.to_vec())?)
+                }
+                kind => bail!("unsupported tuple value kind '{}'", kind as char),
+            };
+            tuple.push(value);
+        }
+        Ok(tuple)
+    }
+}
+
+impl Message {
+    pub(super) fn decode(data: &[u8]) -> AnyResult<Self> {
+        let mut reader = Reader { data };
+
+        let message = match reader.u8()? {
+            b'B' => {
+                let final_lsn = reader.u64()?;
+                Self::Begin { final_lsn }
+            }
+            b'C' => {
+                let _flags = reader.u8()?;
+                let commit_lsn = reader.u64()?;
+                let end_lsn = reader.u64()?;
+                Self::Commit {
+                    commit_lsn,
+                    end_lsn,
+                }
+            }
+            b'R' => {
+                let id = reader.u32()?;
+                let namespace = reader.string()?;
+                let name = reader.string()?;
+                let _replica_identity = reader.u8()?;
+                let ncols = reader.u16()?;
+                let mut columns = Vec::with_capacity(ncols as usize);
+                for _ in 0..ncols {
+                    let _flags = reader.u8()?;
+                    let name = reader.string()?;
+                    let type_oid = reader.u32()?;
+                    let _type_modifier = reader.u32()?;
+                    columns.push(Column { name, type_oid });
+                }
+                Self::Relation {
+                    id,
+                    namespace,
+                    name,
+                    columns,
+                }
+            }
+            b'I' => {
+                let relation = reader.u32()?;
+                match reader.u8()? {
+                    b'N' => {}
+                    tag => bail!("unexpected tag '{}' in insert message", tag as char),
+                }
+                Self::Insert {
+                    relation,
+                    new: reader.tuple()?,
+                }
+            }
+            b'U' => {
+                let relation = reader.u32()?;
+                let mut old = None;
+                let mut tag = reader.u8()?;
+                if tag == b'K' || tag == b'O' {
+                    let tuple = reader.tuple()?;
+                    if tag == b'O' {
+                        old = Some(tuple);
+                    }
+                    tag = reader.u8()?;
+                }
+                if tag != b'N' {
+                    bail!("unexpected tag '{}' in update message", tag as char);
+                }
+                Self::Update {
+                    relation,
+                    old,
+                    new: reader.tuple()?,
+                }
+            }
+            b'D' => {
+                let relation = reader.u32()?;
+                let tag = reader.u8()?;
+                let tuple = reader.tuple()?;
+                let old = match tag {
+                    b'O' => Some(tuple),
+                    b'K' => None,
+                    tag => bail!("unexpected tag '{}' in delete message", tag as char),
+                };
+                Self::Delete { relation, old }
+            }
+            b'T' => {
+                let nrelations = reader.u32()?;
+                let _options = reader.u8()?;
+                let relations = (0..nrelations)
+                    .map(|_| reader.u32())
+                    .collect::<AnyResult<Vec<_>>>()?;
+                Self::Truncate { relations }
+            }
+            b'Y' | b'O' | b'M' => Self::Other,
+            tag => bail!("unknown pgoutput message type '{}'", tag as char),
+        };
+
+        Ok(message)
+    }
+}
+
+// Type OIDs from `pg_type.dat`.
+const BOOLOID: u32 = 16;
+const INT8OID: u32 = 20;
+const INT2OID: u32 = 21;
+const INT4OID: u32 = 23;
+const OIDOID: u32 = 26;
+const JSONOID: u32 = 114;
+const FLOAT4OID: u32 = 700;
+const FLOAT8OID: u32 = 701;
+const NUMERICOID: u32 = 1700;
+const JSONBOID: u32 = 3802;
+
+/// Convert a column value in Postgres text format to JSON.
+///
+/// Booleans and numbers are converted to the corresponding JSON values,
+/// JSON columns are embedded as is, and all other values, e.g., strings,
+/// dates and timestamps, are passed to the parser as JSON strings in
+/// Postgres output format.
+pub(super) fn text_to_json(type_oid: u32, text: &str) -> JsonValue {
+    let value = match type_oid {
+        BOOLOID => Some(JsonValue::Bool(text == "t")),
+        INT2OID | INT4OID | INT8OID | OIDOID => text.parse::<i64>().ok().map(JsonValue::from),
+        FLOAT4OID | FLOAT8OID => text
+            .parse::<f64>()
+            .ok()
+            .and_then(JsonNumber::from_f64)
+            .map(JsonValue::Number),
+        NUMERICOID | JSONOID | JSONBOID => serde_json::from_str::<JsonValue>(text).ok(),
+        _ => None,
+    };
+
+    value.unwrap_or_else(|| JsonValue::String(text.to_string()))
+}
+
+/// Convert a tuple to a JSON object.
+pub(super) fn tuple_to_json(columns: &[Column], tuple: &[TupleValue]) -> AnyResult<JsonValue> {
+    if columns.len() != tuple.len() {
+        bail!(
+            "tuple has {} columns, but the relation has {} columns",
+            tuple.len(),
+            columns.len()
+        );
+    }
+
+    let mut map = JsonMap::with_capacity(columns.len());
+    for (column, value) in columns.iter().zip(tuple.iter()) {
+        let value = match value {
+            TupleValue::Null => JsonValue::Null,
+            TupleValue::Text(text) => text_to_json(column.type_oid, text),
+            TupleValue::Unchanged => bail!(
+                "value of column '{}' is missing from the replication stream",
+                column.name
+            ),
+        };
+        map.insert(column.name.clone(), value);
+    }
+
+    Ok(JsonValue::Object(map))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_lsn, text_to_json, tuple_to_json, Column, Message, TupleValue};
+    use serde_json::json;
+
+    fn string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(s.as_bytes());
+        buf.push(0);
+    }
+
+    fn tuple(buf: &mut Vec<u8>, values: &[Option<&str>]) {
+        buf.extend_from_slice(&(values.len() as u16).to_be_bytes());
+        for value in values {
+            match value {
+                None => buf.push(b'n'),
+                Some(v) => {
+                    buf.push(b't');
+                    buf.extend_from_slice(&(v.len() as u32).to_be_bytes());
+                    buf.extend_from_slice(v.as_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lsn() {
+        assert_eq!(format_lsn(0), "0/0");
+        assert_eq!(format_lsn(0x16_B374_D848), "16/B374D848");
+        assert_eq!(format_lsn(0x1_0000_0000), "1/0");
+    }
+
+    #[test]
+    fn decode_messages() {
+        let mut begin = vec![b'B'];
+        begin.extend_from_slice(&0x1234u64.to_be_bytes());
+        begin.extend_from_slice(&0u64.to_be_bytes());
+        begin.extend_from_slice(&7u32.to_be_bytes());
+        assert_eq!(
+            Message::decode(&begin).unwrap(),
+            Message::Begin { final_lsn: 0x1234 }
+        );
+
+        let mut commit = vec![b'C', 0];
+        commit.extend_from_slice(&0x1234u64.to_be_bytes());
+        commit.extend_from_slice(&0x1260u64.to_be_bytes());
+        commit.extend_from_slice(&0u64.to_be_bytes());
+        assert_eq!(
+            Message::decode(&commit).unwrap(),
+            Message::Commit {
+                commit_lsn: 0x1234,
+                end_lsn: 0x1260
+            }
+        );
+
+        let mut relation = vec![b'R'];
+        relation.extend_from_slice(&42u32.to_be_bytes());
+        string(&mut relation, "public");
+        string(&mut relation, "t");
+        relation.push(b'f');
+        relation.extend_from_slice(&2u16.to_be_bytes());
+        for (name, oid) in [("id", 23u32), ("name", 25)] {
+            relation.push(1);
+            string(&mut relation, name);
+            relation.extend_from_slice(&oid.to_be_bytes());
+            relation.extend_from_slice(&u32::MAX.to_be_bytes());
+        }
+        let columns = vec![
+            Column {
+                name: "id".to_string(),
+                type_oid: 23,
+            },
+            Column {
+                name: "name".to_string(),
+                type_oid: 25,
+            },
+        ];
+        assert_eq!(
+            Message::decode(&relation).unwrap(),
+            Message::Relation {
+                id: 42,
+                namespace: "public".to_string(),
+                name: "t".to_string(),
+                columns: columns.clone(),
+            }
+        );
+
+        let mut update = vec![b'U'];
+        update.extend_from_slice(&42u32.to_be_bytes());
+        update.push(b'O');
+        tuple(&mut update, &[Some("1"), Some("foo")]);
+        update.push(b'N');
+        tuple(&mut update, &[Some("1"), None]);
+        let message = Message::decode(&update).unwrap();
+        let (relation, old, new) = match message {
+            Message::Update { relation, old, new } => (relation, old, new),
+            message => panic!("expected update message, found {message:?}"),
+        };
+        assert_eq!(relation, 42);
+        assert_eq!(
+            tuple_to_json(&columns, old.as_ref().unwrap()).unwrap(),
+            json!({"id": 1, "name": "foo"})
+        );
+        assert_eq!(
+            tuple_to_json(&columns, &new).unwrap(),
+            json!({"id": 1, "name": null})
+        );
+
+        // Delete without the old tuple (`REPLICA IDENTITY DEFAULT`).
+        let mut delete = vec![b'D'];
+        delete.extend_from_slice(&42u32.to_be_bytes());
+        delete.push(b'K');
+        tuple(&mut delete, &[Some("1"), None]);
+        assert_eq!(
+            Message::decode(&delete).unwrap(),
+            Message::Delete {
+                relation: 42,
+                old: None
+            }
+        );
+
+        let mut truncated = vec![b'I'];
+        truncated.extend_from_slice(&42u32.to_be_bytes());
+        truncated.push(b'N');
+        truncated.extend_from_slice(&2u16.to_be_bytes());
+        assert!(Message::decode(&truncated).is_err());
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(text_to_json(16, "t"), json!(true));
+        assert_eq!(text_to_json(20, "-5"), json!(-5));
+        assert_eq!(text_to_json(701, "1.5"), json!(1.5));
+        assert_eq!(text_to_json(701, "NaN"), json!("NaN"));
+        assert_eq!(text_to_json(1700, "123.45"), json!(123.45));
+        assert_eq!(text_to_json(3802, r#"{"a": 1}"#), json!({"a": 1}));
+        assert_eq!(
+            text_to_json(1114, "2023-01-01 12:00:00"),
+            json!("2023-01-01 12:00:00")
+        );
+
+        let columns = [Column {
+            name: "x".to_string(),
+            type_oid: 25,
+        }];
+        assert!(tuple_to_json(&columns, &[TupleValue::Unchanged]).is_err());
+    }
+}
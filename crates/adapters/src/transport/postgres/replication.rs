@@ -0,0 +1,301 @@
+//! Minimal client for Postgres replication connections.
+//!
+//! The `postgres` crate cannot open connections in replication mode, which
+//! are required to create a replication slot together with a snapshot of the
+//! database at the slot's consistent point (`CREATE_REPLICATION_SLOT ...
+//! EXPORT_SNAPSHOT`).  This module implements just enough of the protocol to
+//! start a `replication=database` session, authenticate, and run replication
+//! commands using the simple query protocol.
+//!
+//! See <https://www.postgresql.org/docs/current/protocol-replication.html>.
+
+use super::quote_ident;
+use anyhow::{anyhow, bail, Result as AnyResult};
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use postgres::config::{Config, Host};
+use postgres_protocol::{
+    authentication::{
+        md5_hash,
+        sasl::{ChannelBinding, ScramSha256, SCRAM_SHA_256},
+    },
+    message::{
+        backend::{ErrorResponseBody, Message},
+        frontend,
+    },
+};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    time::Duration,
+};
+
+const DEFAULT_PORT: u16 = 5432;
+
+trait Socket: Read + Write + Send {}
+
+impl<S> Socket for S where S: Read + Write + Send {}
+
+/// A connection in logical replication mode.
+///
+/// The connection is closed when dropped.
+pub(super) struct ReplicationConnection {
+    socket: Box<dyn Socket>,
+    input: BytesMut,
+    output: BytesMut,
+}
+
+impl ReplicationConnection {
+    /// Connect to the database specified by the connection string `uri`,
+    /// which uses the same syntax as the `postgres` crate.  Hosts are tried
+    /// in order.
+    pub(super) fn connect(uri: &str) -> AnyResult<Self> {
+        let config = Config::from_str(uri)?;
+        let user = config
+            .get_user()
+            .ok_or_else(|| anyhow!("the connection string does not specify a user"))?;
+
+        let ports = config.get_ports();
+        let mut error = anyhow!("the connection string does not specify a host");
+        for (i, host) in config.get_hosts().iter().enumerate() {
+            let port = match ports {
+                [] => DEFAULT_PORT,
+                [port] => *port,
+                ports => ports.get(i).copied().unwrap_or(DEFAULT_PORT),
+            };
+            match Self::open(host, port, config.get_connect_timeout()) {
+                Ok(socket) => {
+                    let mut connection = Self {
+                        socket,
+                        input: BytesMut::new(),
+                        output: BytesMut::new(),
+                    };
+                    connection.startup(&config, user)?;
+                    return Ok(connection);
+                }
+                Err(e) => error = e.into(),
+            }
+        }
+        Err(error)
+    }
+
+    fn open(host: &Host, port: u16, timeout: Option<&Duration>) -> io::Result<Box<dyn Socket>> {
+        match host {
+            Host::Tcp(host) => {
+                let mut error = io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("could not resolve host '{host}'"),
+                );
+                for address in (host.as_str(), port).to_socket_addrs()? {
+                    let stream = match timeout {
+                        Some(timeout) => TcpStream::connect_timeout(&address, *timeout),
+                        None => TcpStream::connect(address),
+                    };
+                    match stream {
+                        Ok(stream) => {
+                            stream.set_nodelay(true)?;
+                            return Ok(Box::new(stream));
+                        }
+                        Err(e) => error = e,
+                    }
+                }
+                Err(error)
+            }
+            #[cfg(unix)]
+            Host::Unix(path) => Ok(Box::new(UnixStream::connect(
+                path.join(format!(".s.PGSQL.{port}")),
+            )?)),
+        }
+    }
+
+    fn password(config: &Config) -> AnyResult<&[u8]> {
+        config.get_password().ok_or_else(|| {
+            anyhow!(
+                "the server requested a password, but the connection string does not specify one"
+            )
+        })
+    }
+
+    /// Start the session and authenticate.
+    fn startup(&mut self, config: &Config, user: &str) -> AnyResult<()> {
+        let mut parameters = vec![("user", user), ("replication", "database")];
+        if let Some(dbname) = config.get_dbname() {
+            parameters.push(("database", dbname));
+        }
+        frontend::startup_message(parameters, &mut self.output)?;
+        self.flush()?;
+
+        loop {
+            match self.read_message()? {
+                Message::AuthenticationOk => {}
+                Message::AuthenticationCleartextPassword => {
+                    frontend::password_message(Self::password(config)?, &mut self.output)?;
+                    self.flush()?;
+                }
+                Message::AuthenticationMd5Password(body) => {
+                    let hash = md5_hash(user.as_bytes(), Self::password(config)?, body.salt());
+                    frontend::password_message(hash.as_bytes(), &mut self.output)?;
+                    self.flush()?;
+                }
+                Message::AuthenticationSasl(body) => {
+                    if !body
+                        .mechanisms()
+                        .any(|mechanism| Ok(mechanism == SCRAM_SHA_256))?
+                    {
+                        bail!("the server requested an unsupported SASL authentication mechanism");
+                    }
+                    let mut scram =
+                        ScramSha256::new(Self::password(config)?, ChannelBinding::unsupported());
+                    frontend::sasl_initial_response(
+                        SCRAM_SHA_256,
+                        scram.message(),
+                        &mut self.output,
+                    )?;
+                    self.flush()?;
+
+                    match self.read_message()? {
+                        Message::AuthenticationSaslContinue(body) => scram.update(body.data())?,
+                        Message::ErrorResponse(body) => bail!(error_message(&body)?),
+                        _ => bail!("unexpected message during SASL authentication"),
+                    }
+                    frontend::sasl_response(scram.message(), &mut self.output)?;
+                    self.flush()?;
+
+                    match self.read_message()? {
+                        Message::AuthenticationSaslFinal(body) => scram.finish(body.data())?,
+                        Message::ErrorResponse(body) => bail!(error_message(&body)?),
+                        _ => bail!("unexpected message during SASL authentication"),
+                    }
+                }
+                Message::ReadyForQuery(_) => return Ok(()),
+                Message::ErrorResponse(body) => bail!(error_message(&body)?),
+                // Parameter status, backend key data, notices.
+                _ => {}
+            }
+        }
+    }
+
+    /// Create a persistent logical replication slot that uses the
+    /// `pgoutput` plugin.
+    ///
+    /// If `export_snapshot` is `true`, returns the name of a snapshot of the
+    /// database at the slot's consistent point: the changes streamed from the
+    /// slot are exactly the changes committed after this snapshot.  Another
+    /// connection can import the snapshot with `SET TRANSACTION SNAPSHOT`
+    /// until the next command on this connection or until it is closed.
+    pub(super) fn create_slot(
+        &mut self,
+        slot_name: &str,
+        export_snapshot: bool,
+    ) -> AnyResult<Option<String>> {
+        let rows = self.simple_query(&format!(
+            "CREATE_REPLICATION_SLOT {} LOGICAL pgoutput {}",
+            quote_ident(slot_name),
+            if export_snapshot {
+                "EXPORT_SNAPSHOT"
+            } else {
+                "NOEXPORT_SNAPSHOT"
+            }
+        ))?;
+
+        // Columns: slot name, consistent point, snapshot name, output plugin.
+        let snapshot = rows
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("CREATE_REPLICATION_SLOT returned no result"))?
+            .into_iter()
+            .nth(2)
+            .flatten();
+        if export_snapshot && snapshot.is_none() {
+            bail!("CREATE_REPLICATION_SLOT did not export a snapshot");
+        }
+        Ok(snapshot)
+    }
+
+    /// Run `query` and return the rows of its result in text format.
+    fn simple_query(&mut self, query: &str) -> AnyResult<Vec<Vec<Option<String>>>> {
+        frontend::query(query, &mut self.output)?;
+        self.flush()?;
+
+        let mut rows = Vec::new();
+        let mut error = None;
+        loop {
+            match self.read_message()? {
+                Message::DataRow(body) => {
+                    let buffer = body.buffer();
+                    let row = body
+                        .ranges()
+                        .map(|range| {
+                            Ok(range
+                                .map(|range| String::from_utf8_lossy(&buffer[range]).into_owned()))
+                        })
+                        .collect::<Vec<_>>()?;
+                    rows.push(row);
+                }
+                Message::ErrorResponse(body) => error = Some(error_message(&body)?),
+                Message::ReadyForQuery(_) => break,
+                _ => {}
+            }
+        }
+
+        match error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(rows),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.socket.write_all(&self.output)?;
+        self.output.clear();
+        self.socket.flush()
+    }
+
+    fn read_message(&mut self) -> AnyResult<Message> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            if let Some(message) = Message::parse(&mut self.input)? {
+                return Ok(message);
+            }
+            let n = self.socket.read(&mut chunk)?;
+            if n == 0 {
+                bail!("connection closed by the server");
+            }
+            self.input.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+impl Drop for ReplicationConnection {
+    fn drop(&mut self) {
+        frontend::terminate(&mut self.output);
+        let _ = self.flush();
+    }
+}
+
+/// Extract the message text from an error response.
+fn error_message(body: &ErrorResponseBody) -> io::Result<String> {
+    let message = body
+        .fields()
+        .find(|field| Ok(field.type_() == b'M'))?
+        .map(|field| field.value().to_string());
+    Ok(message.unwrap_or_else(|| "unknown server error".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReplicationConnection;
+
+    #[test]
+    fn connect_errors() {
+        assert!(ReplicationConnection::connect("host=127.0.0.1 port=1").is_err());
+        assert!(ReplicationConnection::connect(
+            "host=127.0.0.1 port=1 user=postgres connect_timeout=5"
+        )
+        .is_err());
+        assert!(ReplicationConnection::connect("user=postgres").is_err());
+        assert!(ReplicationConnection::connect("host=127.0.0.1 port=x user=postgres").is_err());
+    }
+}
@@ -0,0 +1,686 @@
+use super::{
+    compression::{Decompressor, ObjectCompression},
+    InputConsumer, InputEndpoint, InputTransport,
+};
+use crate::PipelineState;
+use actix::{clock::sleep, System};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use futures::{StreamExt, TryStreamExt};
+use object_store::{
+    aws::AmazonS3Builder, azure::MicrosoftAzureBuilder, gcp::GoogleCloudStorageBuilder,
+    local::LocalFileSystem, path::Path, ObjectMeta, ObjectStore,
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs::create_dir_all,
+    sync::{Arc, Mutex},
+    thread::spawn,
+    time::Duration,
+};
+use tokio::sync::watch::{channel, Receiver, Sender};
+use utoipa::ToSchema;
+
+/// [`InputTransport`] implementation that reads objects from an S3 bucket,
+/// an S3-compatible object store, an Azure Blob Storage container, or a
+/// Google Cloud Storage bucket.
+///
+/// The input transport factory gives this transport the name `s3_input`.
+pub struct S3InputTransport;
+
+impl InputTransport for S3InputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("s3_input")
+    }
+
+    /// Creates a new [`InputEndpoint`] for reading objects from S3,
+    /// interpreting `config` as a [`S3InputConfig`].
+    ///
+    /// See [`InputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(&self, _name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
+        let config = S3InputConfig::deserialize(config)?;
+        let store = config.object_store()?;
+        Ok(Box::new(S3InputEndpoint::new(config, store)))
+    }
+}
+
+/// Configuration for reading data from S3 with [`S3InputTransport`].
+///
+/// Options prefixed with `aws_`, `azure_`, or `gcs_` only apply to the
+/// corresponding `provider`.
+///
+/// Credentials and `poll_interval_secs` can be changed on a running endpoint
+/// (see [`InputEndpoint::reconfigure`]).
+#[derive(Clone, PartialEq, Eq, Deserialize, ToSchema)]
+pub struct S3InputConfig {
+    /// Object store provider.  Defaults to `s3`.
+    #[serde(default)]
+    pub provider: ObjectStoreProvider,
+
+    /// Bucket name, or container name for Azure Blob Storage.
+    pub bucket_name: String,
+
+    /// Read all objects whose keys start with this prefix.  The default
+    /// value is the empty string, which selects all objects in the bucket.
+    #[serde(default)]
+    pub prefix: String,
+
+    /// AWS region, e.g., `us-east-1`.  When not specified, the region is
+    /// read from the `AWS_REGION` environment variable.
+    pub region: Option<String>,
+
+    /// Custom endpoint URL for S3-compatible object stores such as MinIO.
+    pub endpoint: Option<String>,
+
+    /// AWS access key id.  When not specified, credentials are read from
+    /// the environment.
+    pub aws_access_key_id: Option<String>,
+
+    /// AWS secret access key.
+    pub aws_secret_access_key: Option<String>,
+
+    /// Azure storage account name.  When not specified, it is read from the
+    /// `AZURE_STORAGE_ACCOUNT_NAME` environment variable.
+    pub azure_storage_account: Option<String>,
+
+    /// Azure storage account access key.  When not specified, credentials
+    /// are read from the environment.
+    pub azure_access_key: Option<String>,
+
+    /// Path to a Google Cloud service account JSON file.  When neither this
+    /// option nor `gcs_service_account_key` is specified, credentials are
+    /// read from the environment.
+    pub gcs_service_account_path: Option<String>,
+
+    /// Google Cloud service account key, as a JSON string.
+    pub gcs_service_account_key: Option<String>,
+
+    /// Compression codec used to decode objects.
+    #[serde(default)]
+    pub compression: ObjectCompression,
+
+    /// When set, keep polling the bucket for new objects every
+    /// `poll_interval_secs` seconds after reading existing objects.
+    /// Otherwise, the endpoint signals end of input after reading all
+    /// objects that match the prefix.
+    pub poll_interval_secs: Option<u64>,
+}
+
+impl S3InputConfig {
+    /// Returns `self` with the credentials and poll interval, which can be
+    /// changed on a running endpoint, replaced by those of `other`.
+    fn with_live_settings(&self, other: &Self) -> Self {
+        Self {
+            aws_access_key_id: other.aws_access_key_id.clone(),
+            aws_secret_access_key: other.aws_secret_access_key.clone(),
+            azure_access_key: other.azure_access_key.clone(),
+            gcs_service_account_path: other.gcs_service_account_path.clone(),
+            gcs_service_account_key: other.gcs_service_account_key.clone(),
+            poll_interval_secs: other.poll_interval_secs,
+            ..self.clone()
+        }
+    }
+
+    fn validate(&self) -> AnyResult<()> {
+        let aws_options = self.region.is_some()
+            || self.endpoint.is_some()
+            || self.aws_access_key_id.is_some()
+            || self.aws_secret_access_key.is_some();
+        let azure_options = self.azure_storage_account.is_some() || self.azure_access_key.is_some();
+        let gcs_options =
+            self.gcs_service_account_path.is_some() || self.gcs_service_account_key.is_some();
+        let invalid = match self.provider {
+            ObjectStoreProvider::S3 => azure_options || gcs_options,
+            ObjectStoreProvider::Azure => aws_options || gcs_options,
+            ObjectStoreProvider::Gcs => aws_options || azure_options,
+        };
+        if invalid {
+            bail!(
+                "configuration contains options that don't apply to object store provider '{}'",
+                self.provider.name()
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn object_store(&self) -> AnyResult<Arc<dyn ObjectStore>> {
+        self.validate()?;
+        match self.provider {
+            ObjectStoreProvider::S3 => {
+                let mut builder = AmazonS3Builder::from_env().with_bucket_name(&self.bucket_name);
+                if let Some(region) = &self.region {
+                    builder = builder.with_region(region);
+                }
+                if let Some(endpoint) = &self.endpoint {
+                    builder = builder
+                        .with_endpoint(endpoint)
+                        .with_allow_http(endpoint.starts_with("http://"));
+                }
+                if let Some(access_key_id) = &self.aws_access_key_id {
+                    builder = builder.with_access_key_id(access_key_id);
+                }
+                if let Some(secret_access_key) = &self.aws_secret_access_key {
+                    builder = builder.with_secret_access_key(secret_access_key);
+                }
+                Ok(Arc::new(builder.build()?))
+            }
+            ObjectStoreProvider::Azure => {
+                let mut builder =
+                    MicrosoftAzureBuilder::from_env().with_container_name(&self.bucket_name);
+                if let Some(account) = &self.azure_storage_account {
+                    builder = builder.with_account(account);
+                }
+                if let Some(access_key) = &self.azure_access_key {
+                    builder = builder.with_access_key(access_key);
+                }
+                Ok(Arc::new(builder.build()?))
+            }
+            ObjectStoreProvider::Gcs => {
+                let mut builder =
+                    GoogleCloudStorageBuilder::from_env().with_bucket_name(&self.bucket_name);
+                if let Some(path) = &self.gcs_service_account_path {
+                    builder = builder.with_service_account_path(path);
+                }
+                if let Some(key) = &self.gcs_service_account_key {
+                    builder = builder.with_service_account_key(key);
+                }
+                Ok(Arc::new(builder.build()?))
+            }
+        }
+    }
+}
+
+/// Open the object store at `location`: either a path to a local directory,
+/// which is created if it doesn't exist, or an object store URL of the form
+/// `s3://<bucket>/<prefix>`, `gs://<bucket>/<prefix>`, or
+/// `az://<container>/<prefix>`.  Object store credentials are read from the
+/// environment.
+///
+/// Returns the store along with the prefix of `location` within the store.
+pub fn open_object_store(location: &str) -> AnyResult<(Arc<dyn ObjectStore>, Path)> {
+    let provider = [
+        ("s3://", ObjectStoreProvider::S3),
+        ("gs://", ObjectStoreProvider::Gcs),
+        ("az://", ObjectStoreProvider::Azure),
+    ]
+    .into_iter()
+    .find_map(|(scheme, provider)| {
+        location
+            .strip_prefix(scheme)
+            .map(|bucket_and_prefix| (provider, bucket_and_prefix))
+    });
+
+    match provider {
+        None => {
+            create_dir_all(location)
+                .map_err(|e| anyhow!("failed to create directory '{location}': {e}"))?;
+            Ok((
+                Arc::new(LocalFileSystem::new_with_prefix(location)?),
+                Path::default(),
+            ))
+        }
+        Some((provider, bucket_and_prefix)) => {
+            let (bucket_name, prefix) = bucket_and_prefix
+                .split_once('/')
+                .unwrap_or((bucket_and_prefix, ""));
+            if bucket_name.is_empty() {
+                bail!("location '{location}' does not specify a bucket");
+            }
+            let config = S3InputConfig {
+                provider,
+                bucket_name: bucket_name.to_string(),
+                prefix: prefix.to_string(),
+                region: None,
+                endpoint: None,
+                aws_access_key_id: None,
+                aws_secret_access_key: None,
+                azure_storage_account: None,
+                azure_access_key: None,
+                gcs_service_account_path: None,
+                gcs_service_account_key: None,
+                compression: Default::default(),
+                poll_interval_secs: None,
+            };
+            Ok((config.object_store()?, Path::from(prefix)))
+        }
+    }
+}
+
+/// Object store provider used by [`S3InputTransport`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum ObjectStoreProvider {
+    /// Amazon S3 or an S3-compatible object store.
+    #[default]
+    #[serde(rename = "s3")]
+    S3,
+
+    /// Azure Blob Storage.
+    #[serde(rename = "azure")]
+    Azure,
+
+    /// Google Cloud Storage.
+    #[serde(rename = "gcs")]
+    Gcs,
+}
+
+impl ObjectStoreProvider {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::S3 => "s3",
+            Self::Azure => "azure",
+            Self::Gcs => "gcs",
+        }
+    }
+}
+
+/// Settings of a running [`S3InputEndpoint`] that can be changed by
+/// [`InputEndpoint::reconfigure`].
+#[derive(Clone)]
+struct LiveSettings {
+    store: Arc<dyn ObjectStore>,
+    poll_interval_secs: Option<u64>,
+}
+
+struct S3InputEndpoint {
+    config: Mutex<S3InputConfig>,
+    sender: Sender<PipelineState>,
+    receiver: Receiver<PipelineState>,
+    settings_sender: Sender<LiveSettings>,
+    settings_receiver: Receiver<LiveSettings>,
+}
+
+impl S3InputEndpoint {
+    fn new(config: S3InputConfig, store: Arc<dyn ObjectStore>) -> Self {
+        let (sender, receiver) = channel(PipelineState::Paused);
+        let (settings_sender, settings_receiver) = channel(LiveSettings {
+            store,
+            poll_interval_secs: config.poll_interval_secs,
+        });
+        Self {
+            config: Mutex::new(config),
+            sender,
+            receiver,
+            settings_sender,
+            settings_receiver,
+        }
+    }
+
+    /// Wait until the endpoint is running.  Returns `false` if the endpoint
+    /// has been terminated.
+    async fn wait_running(receiver: &mut Receiver<PipelineState>) -> AnyResult<bool> {
+        loop {
+            let state = *receiver.borrow();
+            match state {
+                PipelineState::Terminated => return Ok(false),
+                PipelineState::Running => return Ok(true),
+                PipelineState::Paused => receiver.changed().await?,
+            }
+        }
+    }
+
+    /// List objects that match the configured prefix, ordered by key.
+    async fn list_objects(store: &dyn ObjectStore, prefix: &str) -> AnyResult<Vec<ObjectMeta>> {
+        // Object store listings operate on whole path segments; list the
+        // parent "directory" of the prefix and filter the results.
+        let parent = prefix
+            .rsplit_once('/')
+            .map(|(parent, _)| Path::from(parent));
+        let mut objects: Vec<ObjectMeta> = store
+            .list(parent.as_ref())
+            .await?
+            .try_filter(|meta| futures::future::ready(meta.location.as_ref().starts_with(prefix)))
+            .try_collect()
+            .await?;
+        objects.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(objects)
+    }
+
+    /// Read object at `location` and push its contents to `consumer`.
+    ///
+    /// Returns `false` if the endpoint was terminated before the object was
+    /// fully read.
+    async fn read_object(
+        store: &dyn ObjectStore,
+        location: &Path,
+        compression: ObjectCompression,
+        consumer: &mut Box<dyn InputConsumer>,
+        receiver: &mut Receiver<PipelineState>,
+    ) -> AnyResult<bool> {
+        let mut decoder = Decompressor::new(compression.for_name(location.as_ref()))?;
+        let mut stream = store.get(location).await?.into_stream();
+        consumer.source_offset(&JsonValue::from(location.to_string()));
+
+        // Last byte of decoded object contents pushed to `consumer`.
+        let mut last_byte = None;
+
+        while let Some(data) = stream.next().await {
+            if !Self::wait_running(receiver).await? {
+                return Ok(false);
+            }
+            let decoded = decoder
+                .decode(&data?)
+                .map_err(|e| anyhow!("error decompressing object '{location}': {e}"))?;
+            if let Some(byte) = decoded.last() {
+                last_byte = Some(*byte);
+                let _ = consumer.input_fragment(decoded);
+                decoded.clear();
+            }
+        }
+
+        let decoded = decoder
+            .finish()
+            .map_err(|e| anyhow!("error decompressing object '{location}': {e}"))?;
+        if let Some(byte) = decoded.last() {
+            last_byte = Some(*byte);
+        }
+        // Make sure that the last record in the object doesn't get glued to
+        // the first record of the next object.
+        if last_byte.is_some() && last_byte != Some(b'\n') {
+            decoded.push(b'\n');
+        }
+        if !decoded.is_empty() {
+            let _ = consumer.input_fragment(decoded);
+        }
+
+        Ok(true)
+    }
+
+    async fn worker_thread(
+        config: S3InputConfig,
+        consumer: &mut Box<dyn InputConsumer>,
+        mut receiver: Receiver<PipelineState>,
+        mut settings: Receiver<LiveSettings>,
+    ) -> AnyResult<()> {
+        // Objects that have already been read.
+        let mut seen = HashSet::new();
+
+        loop {
+            if !Self::wait_running(&mut receiver).await? {
+                return Ok(());
+            }
+
+            let LiveSettings {
+                store,
+                poll_interval_secs,
+            } = settings.borrow_and_update().clone();
+
+            for object in Self::list_objects(store.as_ref(), &config.prefix).await? {
+                if seen.contains(&object.location) {
+                    continue;
+                }
+                if !Self::read_object(
+                    store.as_ref(),
+                    &object.location,
+                    config.compression,
+                    consumer,
+                    &mut receiver,
+                )
+                .await?
+                {
+                    return Ok(());
+                }
+                seen.insert(object.location);
+            }
+
+            match poll_interval_secs {
+                None => {
+                    let _ = consumer.eoi();
+                    return Ok(());
+                }
+                Some(interval) => {
+                    tokio::select! {
+                        _ = sleep(Duration::from_secs(interval)) => (),
+                        _ = receiver.changed() => (),
+                        _ = settings.changed() => (),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl InputEndpoint for S3InputEndpoint {
+    fn connect(&mut self, mut consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        let config = self.config.lock().unwrap().clone();
+        let receiver = self.receiver.clone();
+        let settings = self.settings_receiver.clone();
+        let _worker = spawn(move || {
+            System::new().block_on(async move {
+                if let Err(error) =
+                    Self::worker_thread(config, &mut consumer, receiver, settings).await
+                {
+                    consumer.error(true, error);
+                }
+            });
+        });
+        Ok(())
+    }
+
+    fn reconfigure(&self, config: &YamlValue) -> AnyResult<()> {
+        let new_config = S3InputConfig::deserialize(config)?;
+        let mut config = self.config.lock().unwrap();
+        if config.with_live_settings(&new_config) != new_config {
+            bail!("only credentials and 'poll_interval_secs' can be changed on a running endpoint");
+        }
+
+        // Keep the existing client unless the credentials have changed.
+        let credentials_changed = S3InputConfig {
+            poll_interval_secs: config.poll_interval_secs,
+            ..new_config.clone()
+        } != *config;
+        let store = if !credentials_changed {
+            self.settings_receiver.borrow().store.clone()
+        } else {
+            new_config.object_store()?
+        };
+        self.settings_sender.send_replace(LiveSettings {
+            store,
+            poll_interval_secs: new_config.poll_interval_secs,
+        });
+        *config = new_config;
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        Ok(self.sender.send(PipelineState::Paused)?)
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        Ok(self.sender.send(PipelineState::Running)?)
+    }
+
+    fn disconnect(&self) {
+        let _ = self.sender.send(PipelineState::Terminated);
+    }
+}
+
+impl Drop for S3InputEndpoint {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ObjectStoreProvider, S3InputConfig, S3InputEndpoint, S3InputTransport};
+    use crate::{
+        test::{mock_parser_pipeline, wait, MockDeZSet, MockInputConsumer},
+        transport::{InputTransport, ObjectCompression},
+        FormatConfig, InputEndpoint,
+    };
+    use actix::System;
+    use flate2::{write::GzEncoder, Compression};
+    use object_store::{memory::InMemory, path::Path, ObjectStore};
+    use serde::{Deserialize, Serialize};
+    use std::{borrow::Cow, io::Write, sync::Arc};
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+    struct TestStruct {
+        s: String,
+        b: bool,
+        i: i64,
+    }
+
+    fn put(store: &InMemory, key: &str, data: Vec<u8>) {
+        System::new().block_on(async {
+            store.put(&Path::from(key), data.into()).await.unwrap();
+        });
+    }
+
+    fn setup(
+        prefix: &str,
+        poll_interval_secs: Option<u64>,
+    ) -> (
+        Arc<InMemory>,
+        Box<dyn InputEndpoint>,
+        MockInputConsumer,
+        MockDeZSet<TestStruct>,
+    ) {
+        let store = Arc::new(InMemory::new());
+        let config = S3InputConfig {
+            provider: ObjectStoreProvider::S3,
+            bucket_name: "test".to_string(),
+            prefix: prefix.to_string(),
+            region: None,
+            endpoint: None,
+            aws_access_key_id: None,
+            aws_secret_access_key: None,
+            azure_storage_account: None,
+            azure_access_key: None,
+            gcs_service_account_path: None,
+            gcs_service_account_key: None,
+            compression: ObjectCompression::Auto,
+            poll_interval_secs,
+        };
+        let format = FormatConfig {
+            name: Cow::from("csv"),
+            config: serde_yaml::Value::Null,
+        };
+        let (consumer, zset) = mock_parser_pipeline::<TestStruct>(&format).unwrap();
+        let mut endpoint: Box<dyn InputEndpoint> =
+            Box::new(S3InputEndpoint::new(config, store.clone()));
+        endpoint.connect(Box::new(consumer.clone())).unwrap();
+        (store, endpoint, consumer, zset)
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_s3_input_prefix() {
+        let (store, endpoint, consumer, zset) = setup("data/part", None);
+        put(
+            &store,
+            "data/part-1.csv",
+            b"foo,true,1\nbar,false,2".to_vec(),
+        );
+        put(&store, "data/part-2.csv.gz", gzip(b"baz,true,3\n"));
+        put(&store, "data/other.csv", b"ignored,true,4\n".to_vec());
+        put(&store, "part-3.csv", b"ignored,true,5\n".to_vec());
+
+        endpoint.start().unwrap();
+        wait(|| consumer.state().eoi, None);
+
+        let flushed = zset
+            .state()
+            .flushed
+            .iter()
+            .map(|(val, _)| val.i)
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_s3_input_poll() {
+        let (store, endpoint, consumer, zset) = setup("data/", Some(0));
+        put(&store, "data/1.csv", b"foo,true,1\n".to_vec());
+
+        endpoint.start().unwrap();
+        wait(|| zset.state().flushed.len() == 1, None);
+
+        put(&store, "data/2.csv", b"bar,false,2\n".to_vec());
+        wait(|| zset.state().flushed.len() == 2, None);
+        assert!(!consumer.state().eoi);
+
+        endpoint.disconnect();
+    }
+
+    #[test]
+    fn test_s3_input_reconfigure() {
+        let (store, endpoint, _consumer, zset) = setup("data/", Some(3600));
+        put(&store, "data/1.csv", b"foo,true,1\n".to_vec());
+
+        endpoint.start().unwrap();
+        wait(|| zset.state().flushed.len() == 1, None);
+
+        // Only credentials and the poll interval can change.
+        let err = endpoint
+            .reconfigure(
+                &serde_yaml::from_str(
+                    r#"
+bucket_name: other
+prefix: data/
+poll_interval_secs: 0
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("only credentials"));
+
+        // A shorter poll interval takes effect without waiting for the current
+        // one to expire.
+        put(&store, "data/2.csv", b"bar,false,2\n".to_vec());
+        endpoint
+            .reconfigure(
+                &serde_yaml::from_str(
+                    r#"
+bucket_name: test
+prefix: data/
+poll_interval_secs: 0
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        wait(|| zset.state().flushed.len() == 2, None);
+
+        endpoint.disconnect();
+    }
+
+    #[test]
+    fn test_provider_options() {
+        let config: serde_yaml::Value = serde_yaml::from_str(
+            r#"
+provider: gcs
+bucket_name: test
+azure_access_key: secret
+"#,
+        )
+        .unwrap();
+        let err = S3InputTransport
+            .new_endpoint("test", &config)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("don't apply to object store provider 'gcs'"));
+
+        let config: S3InputConfig = serde_yaml::from_str(
+            r#"
+provider: azure
+bucket_name: test
+azure_storage_account: account
+azure_access_key: c2VjcmV0
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.provider, ObjectStoreProvider::Azure);
+        config.object_store().unwrap();
+    }
+}
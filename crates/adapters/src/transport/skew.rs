@@ -0,0 +1,305 @@
+use super::{InputConsumer, InputEndpoint, InputTransport};
+use crate::{format::ParseError, TransportConfig};
+use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
+use crossbeam::channel::{unbounded, Sender};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+/// [`InputTransport`] implementation that wraps another input transport and
+/// delays the data it produces to simulate processing-time skew and clock
+/// drift.
+///
+/// This transport is intended for testing.  Wrapping one or more of the
+/// pipeline's input endpoints makes their data arrive late relative to other
+/// endpoints, which helps to validate watermark and lateness settings before
+/// connecting the pipeline to production sources.
+///
+/// The input transport factory gives this transport the name `skew`.
+pub struct SkewInputTransport;
+
+impl InputTransport for SkewInputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("skew")
+    }
+
+    /// Creates a new [`InputEndpoint`] that wraps the endpoint of the inner
+    /// transport, interpreting `config` as a [`SkewInputConfig`].
+    ///
+    /// See [`InputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(&self, name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
+        let config = SkewInputConfig::deserialize(config)?;
+        let transport = <dyn InputTransport>::get_transport(&config.transport.name)
+            .ok_or_else(|| anyhow!("unknown input transport '{}'", config.transport.name))?;
+        let inner = transport.new_endpoint(name, &config.transport.config)?;
+        Ok(Box::new(SkewInputEndpoint::new(config, inner)))
+    }
+}
+
+/// Configuration for simulating processing-time skew with
+/// [`SkewInputTransport`].
+///
+/// Data received from the inner transport at time `t` is forwarded to the
+/// pipeline at `t + skew_ms + drift_ms_per_sec * s`, where `s` is the number
+/// of seconds since the endpoint was connected.  The order of the data is
+/// preserved.
+#[derive(Deserialize, ToSchema)]
+pub struct SkewInputConfig {
+    /// Transport that supplies the data.
+    pub transport: TransportConfig,
+
+    /// Constant delay applied to all data, in milliseconds.
+    #[serde(default)]
+    pub skew_ms: u64,
+
+    /// Additional delay accumulated for every second the endpoint is
+    /// connected, in milliseconds.  A negative value makes the delay shrink
+    /// over time, until it reaches zero.
+    #[serde(default)]
+    pub drift_ms_per_sec: f64,
+}
+
+struct SkewInputEndpoint {
+    skew: Duration,
+    drift_ms_per_sec: f64,
+    inner: Box<dyn InputEndpoint>,
+}
+
+impl SkewInputEndpoint {
+    fn new(config: SkewInputConfig, inner: Box<dyn InputEndpoint>) -> Self {
+        Self {
+            skew: Duration::from_millis(config.skew_ms),
+            drift_ms_per_sec: config.drift_ms_per_sec,
+            inner,
+        }
+    }
+}
+
+impl InputEndpoint for SkewInputEndpoint {
+    fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        let consumer =
+            SkewConsumer::new(self.skew, self.drift_ms_per_sec, Instant::now(), consumer);
+        self.inner.connect(Box::new(consumer))
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        self.inner.pause()
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        self.inner.start()
+    }
+
+    fn disconnect(&self) {
+        self.inner.disconnect()
+    }
+}
+
+enum Message {
+    Fragment(Vec<u8>),
+    Chunk(Vec<u8>),
+    Error(bool, AnyError),
+    Eoi,
+    Metrics(JsonValue),
+    TraceContext(Vec<(String, String)>),
+    Partition(u32),
+}
+
+/// Consumer that queues all data received from the inner endpoint and
+/// forwards it to the actual consumer from a separate thread once it is due.
+///
+/// Parse errors are reported by the downstream consumer when the data is
+/// forwarded, so `input_fragment`, `input_chunk` and `eoi` always return an
+/// empty vector.
+struct SkewConsumer {
+    skew: Duration,
+    drift_ms_per_sec: f64,
+    start: Instant,
+    consumer: Arc<Mutex<Box<dyn InputConsumer>>>,
+    sender: Sender<(Instant, Message)>,
+}
+
+impl SkewConsumer {
+    fn new(
+        skew: Duration,
+        drift_ms_per_sec: f64,
+        start: Instant,
+        consumer: Box<dyn InputConsumer>,
+    ) -> Self {
+        let (sender, receiver) = unbounded::<(Instant, Message)>();
+        let consumer = Arc::new(Mutex::new(consumer));
+        let downstream = consumer.clone();
+        // The worker exits when the consumer is dropped and the channel gets
+        // disconnected.
+        let _worker = spawn(move || {
+            for (due, message) in receiver {
+                let now = Instant::now();
+                if due > now {
+                    sleep(due - now);
+                }
+                let mut downstream = downstream.lock().unwrap();
+                match message {
+                    Message::Fragment(data) => {
+                        downstream.input_fragment(&data);
+                    }
+                    Message::Chunk(data) => {
+                        downstream.input_chunk(&data);
+                    }
+                    Message::Error(fatal, error) => downstream.error(fatal, error),
+                    Message::Eoi => {
+                        downstream.eoi();
+                    }
+                    Message::Metrics(metrics) => downstream.transport_metrics(metrics),
+                    Message::TraceContext(headers) => {
+                        let headers = headers
+                            .iter()
+                            .map(|(name, value)| (name.as_str(), value.as_str()))
+                            .collect::<Vec<_>>();
+                        downstream.trace_context(&headers);
+                    }
+                    Message::Partition(partition) => downstream.partition(partition),
+                }
+            }
+        });
+
+        Self {
+            skew,
+            drift_ms_per_sec,
+            start,
+            consumer,
+            sender,
+        }
+    }
+
+    /// Delay to apply to data received at `now`.
+    fn delay(&self, now: Instant) -> Duration {
+        let drift_ms = self.drift_ms_per_sec * (now - self.start).as_secs_f64();
+        let delay_ms = self.skew.as_millis() as f64 + drift_ms;
+        Duration::from_secs_f64(delay_ms.max(0.0) / 1000.0)
+    }
+
+    fn send(&self, message: Message) {
+        let now = Instant::now();
+        // The worker thread only exits after the sender is dropped.
+        let _ = self.sender.send((now + self.delay(now), message));
+    }
+}
+
+impl InputConsumer for SkewConsumer {
+    fn input_fragment(&mut self, data: &[u8]) -> Vec<ParseError> {
+        self.send(Message::Fragment(data.to_vec()));
+        Vec::new()
+    }
+
+    fn input_chunk(&mut self, data: &[u8]) -> Vec<ParseError> {
+        self.send(Message::Chunk(data.to_vec()));
+        Vec::new()
+    }
+
+    fn error(&mut self, fatal: bool, error: AnyError) {
+        self.send(Message::Error(fatal, error));
+    }
+
+    fn eoi(&mut self) -> Vec<ParseError> {
+        self.send(Message::Eoi);
+        Vec::new()
+    }
+
+    fn transport_metrics(&mut self, metrics: JsonValue) {
+        self.send(Message::Metrics(metrics));
+    }
+
+    fn trace_context(&mut self, headers: &[(&str, &str)]) {
+        self.send(Message::TraceContext(
+            headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        ));
+    }
+
+    fn partition(&mut self, partition: u32) {
+        self.send(Message::Partition(partition));
+    }
+
+    fn fork(&self) -> Box<dyn InputConsumer> {
+        Box::new(SkewConsumer::new(
+            self.skew,
+            self.drift_ms_per_sec,
+            self.start,
+            self.consumer.lock().unwrap().fork(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{mock_input_pipeline, wait};
+    use serde::{Deserialize, Serialize};
+    use std::{io::Write, thread::sleep, time::Duration};
+    use tempfile::NamedTempFile;
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+    struct TestStruct {
+        s: String,
+        b: bool,
+        i: i64,
+    }
+
+    #[test]
+    fn test_skewed_file() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"foo,true,10\nbar,false,-10\n")
+            .unwrap();
+        temp_file.flush().unwrap();
+
+        let config_str = format!(
+            r#"
+stream: test_input
+transport:
+    name: skew
+    config:
+        transport:
+            name: file
+            config:
+                path: {:?}
+        skew_ms: 500
+format:
+    name: csv
+"#,
+            temp_file.path().to_str().unwrap()
+        );
+
+        let (endpoint, consumer, zset) =
+            mock_input_pipeline::<TestStruct>(serde_yaml::from_str(&config_str).unwrap()).unwrap();
+
+        endpoint.start().unwrap();
+
+        // The file has been read, but the data is still held back.
+        sleep(Duration::from_millis(100));
+        assert!(consumer.state().data.is_empty());
+        assert!(zset.state().flushed.is_empty());
+
+        wait(|| zset.state().flushed.len() == 2, None);
+        wait(|| consumer.state().eoi, None);
+        assert_eq!(
+            zset.state().flushed[0],
+            (
+                TestStruct {
+                    s: "foo".to_string(),
+                    b: true,
+                    i: 10
+                },
+                true
+            )
+        );
+    }
+}
@@ -0,0 +1,563 @@
+use super::S3InputConfig;
+use crate::{AsyncErrorCallback, OutputEndpoint, OutputEndpointConfig, OutputTransport};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use log::{debug, info};
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use reqwest::{blocking::Client, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc, thread::sleep, time::Duration};
+use tokio::runtime::Runtime;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Field of a staged record that tells whether the record is inserted or
+/// deleted.
+const OP_FIELD: &str = "__feldera_op";
+
+/// Interval between requests polling for the result of a long-running
+/// statement.
+const STATEMENT_POLLING_INTERVAL: Duration = Duration::from_millis(500);
+
+/// [`OutputTransport`] implementation that applies changes to a Snowflake
+/// table.
+///
+/// The endpoint must be configured with the `json` format.  At the end of
+/// each batch, the endpoint writes the changes produced by the pipeline to
+/// a file in cloud storage that backs a Snowflake external stage, and
+/// merges the file into the table by key with a single `MERGE` statement
+/// executed via the Snowflake SQL API.
+///
+/// The stage must be created with `FILE_FORMAT = (TYPE = JSON)` or the
+/// endpoint must be configured with a JSON file format.  Values are
+/// converted from `VARIANT` to the types of the table columns by Snowflake.
+///
+/// This output transport is only available if the crate is configured with
+/// `with-snowflake` feature.
+///
+/// The output transport factory gives this transport the name `snowflake`.
+pub struct SnowflakeOutputTransport;
+
+impl OutputTransport for SnowflakeOutputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("snowflake")
+    }
+
+    /// Creates a new [`OutputEndpoint`] for writing to a Snowflake table,
+    /// interpreting `config` as a [`SnowflakeOutputConfig`].
+    ///
+    /// See [`OutputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(
+        &self,
+        _name: &str,
+        config: &OutputEndpointConfig,
+    ) -> AnyResult<Box<dyn OutputEndpoint>> {
+        let config = SnowflakeOutputConfig::deserialize(&config.connector_config.transport.config)?;
+        let ep = SnowflakeOutputEndpoint::new(config)?;
+
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_statement_timeout_secs() -> u64 {
+    600
+}
+
+const fn default_purge_staged_files() -> bool {
+    true
+}
+
+/// Configuration for writing to a Snowflake table with
+/// [`SnowflakeOutputTransport`].
+#[derive(Clone, Deserialize, ToSchema)]
+pub struct SnowflakeOutputConfig {
+    /// Snowflake account URL, e.g.,
+    /// `https://myorg-myaccount.snowflakecomputing.com`.
+    pub account_url: String,
+
+    /// Token used to authenticate with the Snowflake SQL API.
+    pub token: String,
+
+    /// Type of `token`.  Defaults to `oauth`.
+    #[serde(default)]
+    pub token_type: SnowflakeTokenType,
+
+    /// Warehouse that executes the statements issued by the endpoint.  When
+    /// not specified, the default warehouse of the user is used.
+    pub warehouse: Option<String>,
+
+    /// Database containing the table and the stage.
+    pub database: String,
+
+    /// Schema containing the table and the stage.
+    pub schema: String,
+
+    /// Role used to execute statements.  When not specified, the default
+    /// role of the user is used.
+    pub role: Option<String>,
+
+    /// Name of the table to write to.
+    ///
+    /// The table must contain a column for each column of the view.
+    pub table: String,
+
+    /// Columns that uniquely identify a row of the table.
+    pub key_columns: Vec<String>,
+
+    /// Name of the external stage backed by `staging`.
+    pub stage: String,
+
+    /// Name of the JSON file format used to read staged files.  When not
+    /// specified, the file format of the stage is used.
+    pub file_format: Option<String>,
+
+    /// Cloud storage location of the stage, using the same options as the
+    /// `s3_input` transport.  `bucket_name` and `prefix` must match the URL
+    /// of the stage.  `compression` and `poll_interval_secs` are ignored.
+    pub staging: S3InputConfig,
+
+    /// Delete staged files after merging them into the table.  Defaults to
+    /// `true`.
+    #[serde(default = "default_purge_staged_files")]
+    pub purge_staged_files: bool,
+
+    /// Maximum time in seconds a statement is allowed to run.  Defaults to
+    /// 600.
+    #[serde(default = "default_statement_timeout_secs")]
+    pub statement_timeout_secs: u64,
+}
+
+impl SnowflakeOutputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if self.table.is_empty() {
+            bail!("Snowflake output endpoint configuration must specify a table");
+        }
+        if self.key_columns.is_empty() {
+            bail!("Snowflake output endpoint configuration must specify at least one key column");
+        }
+        if self.stage.is_empty() {
+            bail!("Snowflake output endpoint configuration must specify a stage");
+        }
+        Ok(())
+    }
+}
+
+/// Type of the token used to authenticate with Snowflake.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum SnowflakeTokenType {
+    /// OAuth access token.
+    #[default]
+    #[serde(rename = "oauth")]
+    OAuth,
+
+    /// JSON Web Token signed with the private key of a key pair registered
+    /// with the user.
+    #[serde(rename = "keypair_jwt")]
+    KeypairJwt,
+}
+
+impl SnowflakeTokenType {
+    fn header_value(&self) -> &'static str {
+        match self {
+            Self::OAuth => "OAUTH",
+            Self::KeypairJwt => "KEYPAIR_JWT",
+        }
+    }
+}
+
+/// Quote a Snowflake identifier, unless it is a valid unquoted identifier.
+///
+/// Unquoted identifiers are case-insensitive in Snowflake, which matches
+/// the behavior of unquoted column names in the SQL program.
+fn quote_ident(ident: &str) -> String {
+    let mut chars = ident.chars();
+    let unquoted = chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if unquoted {
+        ident.to_string()
+    } else {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+}
+
+/// Quote a JSON field name in a Snowflake semi-structured data path.
+fn quote_field(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// Build the statement that merges the staged file `file` into the table.
+///
+/// `columns` are the fields of records produced by the JSON encoder.
+fn merge_statement(config: &SnowflakeOutputConfig, columns: &[String], file: &str) -> String {
+    let source_columns = std::iter::once(format!(
+        "$1:{}::STRING AS {}",
+        quote_field(OP_FIELD),
+        quote_ident(OP_FIELD)
+    ))
+    .chain(
+        columns
+            .iter()
+            .map(|column| format!("$1:{} AS {}", quote_field(column), quote_ident(column))),
+    )
+    .collect::<Vec<_>>()
+    .join(", ");
+    let file_format = match &config.file_format {
+        None => String::new(),
+        Some(file_format) => format!(" (FILE_FORMAT => '{}')", file_format.replace('\'', "''")),
+    };
+
+    let condition = config
+        .key_columns
+        .iter()
+        .map(|column| format!("target.{0} = source.{0}", quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let op = format!("source.{}", quote_ident(OP_FIELD));
+
+    let updates = columns
+        .iter()
+        .filter(|column| !config.key_columns.contains(column))
+        .map(|column| format!("target.{0} = source.{0}", quote_ident(column)))
+        .collect::<Vec<_>>();
+    let update = if updates.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " WHEN MATCHED AND {op} = 'insert' THEN UPDATE SET {}",
+            updates.join(", ")
+        )
+    };
+
+    let insert_columns = columns
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let insert_values = columns
+        .iter()
+        .map(|column| format!("source.{}", quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "MERGE INTO {table} AS target \
+         USING (SELECT {source_columns} FROM @{stage}/{file}{file_format}) AS source \
+         ON {condition} \
+         WHEN MATCHED AND {op} = 'delete' THEN DELETE{update} \
+         WHEN NOT MATCHED AND {op} = 'insert' THEN INSERT ({insert_columns}) VALUES ({insert_values})",
+        table = quote_ident(&config.table),
+        stage = config.stage,
+    )
+}
+
+/// Client for the Snowflake SQL API.
+struct SqlApiClient {
+    client: Client,
+    config: SnowflakeOutputConfig,
+}
+
+impl SqlApiClient {
+    fn new(config: SnowflakeOutputConfig) -> Self {
+        Self {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    fn request(
+        &self,
+        builder: reqwest::blocking::RequestBuilder,
+    ) -> AnyResult<(StatusCode, JsonValue)> {
+        let response = builder
+            .bearer_auth(&self.config.token)
+            .header(
+                "X-Snowflake-Authorization-Token-Type",
+                self.config.token_type.header_value(),
+            )
+            .header("Accept", "application/json")
+            .send()?;
+        let status = response.status();
+        let body = response.json::<JsonValue>().unwrap_or(JsonValue::Null);
+        Ok((status, body))
+    }
+
+    /// Execute `statement` and wait for it to complete.
+    fn execute(&self, statement: &str) -> AnyResult<()> {
+        let url = format!(
+            "{}/api/v2/statements",
+            self.config.account_url.trim_end_matches('/')
+        );
+        let mut body = json!({
+            "statement": statement,
+            "timeout": self.config.statement_timeout_secs,
+            "database": self.config.database,
+            "schema": self.config.schema,
+        });
+        if let Some(warehouse) = &self.config.warehouse {
+            body["warehouse"] = json!(warehouse);
+        }
+        if let Some(role) = &self.config.role {
+            body["role"] = json!(role);
+        }
+
+        let (mut status, mut response) = self.request(self.client.post(&url).json(&body))?;
+        while status == StatusCode::ACCEPTED {
+            let handle = response
+                .get("statementHandle")
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| anyhow!("Snowflake response is missing a statement handle"))?;
+            sleep(STATEMENT_POLLING_INTERVAL);
+            (status, response) = self.request(self.client.get(format!("{url}/{handle}")))?;
+        }
+
+        if !status.is_success() {
+            let message = response
+                .get("message")
+                .and_then(JsonValue::as_str)
+                .unwrap_or("unknown error");
+            bail!("Snowflake statement failed with status {status}: {message}");
+        }
+        Ok(())
+    }
+}
+
+struct SnowflakeOutputEndpoint {
+    config: SnowflakeOutputConfig,
+    client: SqlApiClient,
+    store: Arc<dyn ObjectStore>,
+    runtime: Runtime,
+
+    /// Unique id of the endpoint, used to name staged files.
+    id: Uuid,
+
+    /// Number of files staged by the endpoint.
+    files: u64,
+
+    /// Fields of records produced by the encoder, determined from the first
+    /// inserted record.
+    columns: Option<Vec<String>>,
+
+    /// Changes in the current batch, indexed by key.  `None` means that
+    /// the row is deleted.
+    changes: BTreeMap<String, Option<JsonMap<String, JsonValue>>>,
+}
+
+impl SnowflakeOutputEndpoint {
+    fn new(config: SnowflakeOutputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!(
+            "Starting Snowflake output endpoint for table '{}'",
+            config.table
+        );
+
+        let store = config.staging.object_store()?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            client: SqlApiClient::new(config.clone()),
+            config,
+            store,
+            runtime,
+            id: Uuid::new_v4(),
+            files: 0,
+            columns: None,
+            changes: BTreeMap::new(),
+        })
+    }
+
+    fn key(&self, row: &JsonMap<String, JsonValue>) -> AnyResult<String> {
+        let key = self
+            .config
+            .key_columns
+            .iter()
+            .map(|column| {
+                row.get(column)
+                    .ok_or_else(|| anyhow!("output record is missing key column '{column}'"))
+            })
+            .collect::<AnyResult<Vec<_>>>()?;
+        Ok(serde_json::to_string(&key)?)
+    }
+
+    fn add_change(&mut self, record: JsonValue, insert: bool) -> AnyResult<()> {
+        let row = match record {
+            JsonValue::Object(row) => row,
+            record => bail!("expected a JSON object, found '{record}'"),
+        };
+        if self.columns.is_none() {
+            self.columns = Some(row.keys().cloned().collect());
+        }
+
+        let key = self.key(&row)?;
+        if insert {
+            self.changes.insert(key, Some(row));
+        } else {
+            // An update consists of a deletion and an insertion of a record
+            // with the same key; the insertion wins regardless of the order.
+            self.changes.entry(key).or_insert(None);
+        }
+        Ok(())
+    }
+
+    /// Encode `changes` as newline-delimited JSON records tagged with the
+    /// operation.
+    fn encode(
+        &self,
+        changes: BTreeMap<String, Option<JsonMap<String, JsonValue>>>,
+    ) -> AnyResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        for (key, row) in changes.into_iter() {
+            let record = match row {
+                Some(mut row) => {
+                    row.insert(OP_FIELD.to_string(), json!("insert"));
+                    row
+                }
+                None => {
+                    let key: Vec<JsonValue> = serde_json::from_str(&key)?;
+                    let mut record: JsonMap<String, JsonValue> =
+                        self.config.key_columns.iter().cloned().zip(key).collect();
+                    record.insert(OP_FIELD.to_string(), json!("delete"));
+                    record
+                }
+            };
+            serde_json::to_writer(&mut buffer, &JsonValue::Object(record))?;
+            buffer.push(b'\n');
+        }
+        Ok(buffer)
+    }
+
+    /// Stage `changes` and merge them into the table.
+    fn apply(
+        &mut self,
+        changes: BTreeMap<String, Option<JsonMap<String, JsonValue>>>,
+    ) -> AnyResult<()> {
+        let columns = match &self.columns {
+            // Only deletions of rows that were never inserted by this
+            // endpoint; we don't know the full set of columns yet.
+            None => self.config.key_columns.clone(),
+            Some(columns) => columns.clone(),
+        };
+
+        let file = format!("feldera-{}/{:010}.json", self.id, self.files);
+        self.files += 1;
+        let prefix = self.config.staging.prefix.trim_end_matches('/');
+        let path = if prefix.is_empty() {
+            ObjectPath::from(file.as_str())
+        } else {
+            ObjectPath::from(format!("{prefix}/{file}"))
+        };
+
+        let data = self.encode(changes)?;
+        self.runtime
+            .block_on(self.store.put(&path, data.into()))
+            .map_err(|e| anyhow!("failed to stage file '{path}': {e}"))?;
+
+        self.client
+            .execute(&merge_statement(&self.config, &columns, &file))?;
+
+        if self.config.purge_staged_files {
+            if let Err(e) = self.runtime.block_on(self.store.delete(&path)) {
+                // Not fatal: the data has already been merged.
+                info!("failed to delete staged file '{path}': {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OutputEndpoint for SnowflakeOutputEndpoint {
+    fn connect(&self, _async_error_callback: AsyncErrorCallback) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn max_buffer_size_bytes(&self) -> usize {
+        usize::MAX
+    }
+
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.changes.clear();
+        Ok(())
+    }
+
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        for value in serde_json::Deserializer::from_slice(buffer).into_iter::<JsonValue>() {
+            let value = value.map_err(|e| anyhow!("error parsing output buffer as JSON: {e}"))?;
+            let updates = match value {
+                JsonValue::Array(updates) => updates,
+                update => vec![update],
+            };
+
+            for mut update in updates.into_iter() {
+                if let Some(record) = update.get_mut("delete") {
+                    self.add_change(record.take(), false)?;
+                }
+                if let Some(record) = update.get_mut("insert") {
+                    self.add_change(record.take(), true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        let changes = std::mem::take(&mut self.changes);
+        self.apply(changes)
+            .map_err(|e| anyhow!("failed to write to table '{}': {e}", self.config.table))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merge_statement, quote_ident, SnowflakeOutputConfig, SnowflakeTokenType};
+
+    fn config(yaml: &str) -> SnowflakeOutputConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    const CONFIG: &str = r#"
+account_url: https://myorg-myaccount.snowflakecomputing.com
+token: secret
+database: shop
+schema: public
+table: orders
+key_columns: [id, region]
+stage: feldera_stage
+staging:
+    bucket_name: my-bucket
+    prefix: stage/
+"#;
+
+    #[test]
+    fn merge() {
+        assert_eq!(quote_ident("total"), "total");
+        assert_eq!(quote_ident("Order Total"), "\"Order Total\"");
+
+        let config = config(CONFIG);
+        assert_eq!(config.token_type, SnowflakeTokenType::OAuth);
+        assert!(config.purge_staged_files);
+        config.validate().unwrap();
+
+        let columns = vec!["id".to_string(), "region".to_string(), "total".to_string()];
+        assert_eq!(
+            merge_statement(&config, &columns, "feldera-1/0.json"),
+            r#"MERGE INTO orders AS target USING (SELECT $1:"__feldera_op"::STRING AS __feldera_op, $1:"id" AS id, $1:"region" AS region, $1:"total" AS total FROM @feldera_stage/feldera-1/0.json) AS source ON target.id = source.id AND target.region = source.region WHEN MATCHED AND source.__feldera_op = 'delete' THEN DELETE WHEN MATCHED AND source.__feldera_op = 'insert' THEN UPDATE SET target.total = source.total WHEN NOT MATCHED AND source.__feldera_op = 'insert' THEN INSERT (id, region, total) VALUES (source.id, source.region, source.total)"#
+        );
+
+        // No non-key columns: nothing to update.
+        let columns = vec!["id".to_string(), "region".to_string()];
+        assert!(!merge_statement(&config, &columns, "f.json").contains("UPDATE"));
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = config(&CONFIG.replace("key_columns: [id, region]", "key_columns: []"));
+        assert!(config.validate().is_err());
+    }
+}
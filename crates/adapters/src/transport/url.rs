@@ -227,7 +227,7 @@ impl Drop for UrlInputEndpoint {
     }
 }
 
-fn rustls_config() -> Arc<ClientConfig> {
+pub(crate) fn rustls_config() -> Arc<ClientConfig> {
     lazy_static! {
         static ref ROOT_STORE: Arc<ClientConfig> = {
             let mut root_store = RootCertStore::empty();
@@ -0,0 +1,323 @@
+use super::{url::rustls_config, InputConsumer, InputEndpoint, InputTransport};
+use crate::{PipelineState, RetryConfig};
+use actix::{clock::sleep, System};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use awc::{
+    ws::{Frame, Item, Message},
+    Client, Connector,
+};
+use futures::{SinkExt, StreamExt};
+use log::{debug, info};
+use serde::Deserialize;
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, collections::BTreeMap, thread::spawn};
+use tokio::{
+    select,
+    sync::watch::{channel, Receiver, Sender},
+};
+use utoipa::ToSchema;
+
+/// [`InputTransport`] implementation that receives messages from a WebSocket
+/// server.
+///
+/// Each complete WebSocket message, text or binary, is passed to the parser
+/// as a separate chunk.  The endpoint reconnects with exponential backoff
+/// when the connection fails or is closed by the server, and drops the
+/// connection while the pipeline is paused.  Messages sent by the server
+/// while the endpoint is disconnected are lost.
+///
+/// The input transport factory gives this transport the name `websocket`.
+pub struct WebSocketInputTransport;
+
+impl InputTransport for WebSocketInputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("websocket")
+    }
+
+    /// Creates a new [`InputEndpoint`] for receiving messages from a
+    /// WebSocket server, interpreting `config` as a [`WebSocketInputConfig`].
+    ///
+    /// See [`InputTransport::new_endpoint()`] for more information.
+    fn new_endpoint(&self, _name: &str, config: &YamlValue) -> AnyResult<Box<dyn InputEndpoint>> {
+        let config = WebSocketInputConfig::deserialize(config)?;
+        let ep = WebSocketInputEndpoint::new(config)?;
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_max_message_size() -> usize {
+    16 * 1024 * 1024
+}
+
+/// Configuration for receiving messages from a WebSocket server with
+/// [`WebSocketInputTransport`].
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct WebSocketInputConfig {
+    /// WebSocket URL, e.g., `wss://stream.example.com/feed`.
+    pub url: String,
+
+    /// Additional HTTP headers sent with the handshake request, e.g.,
+    /// `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Text messages sent to the server after each connection is
+    /// established, e.g., to subscribe to channels.
+    #[serde(default)]
+    pub subscribe: Vec<String>,
+
+    /// Policy for reconnecting after a connection failure.  By default,
+    /// the endpoint keeps reconnecting indefinitely.
+    #[serde(default)]
+    pub reconnect: RetryConfig,
+
+    /// Maximum size of a WebSocket message in bytes.  Defaults to 16 MiB.
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+}
+
+impl WebSocketInputConfig {
+    fn validate(&self) -> AnyResult<()> {
+        if !self.url.starts_with("ws://") && !self.url.starts_with("wss://") {
+            bail!(
+                "invalid WebSocket URL '{}': URL must start with 'ws://' or 'wss://'",
+                self.url
+            );
+        }
+        Ok(())
+    }
+}
+
+struct WebSocketInputEndpoint {
+    config: WebSocketInputConfig,
+    sender: Sender<PipelineState>,
+    receiver: Receiver<PipelineState>,
+}
+
+impl WebSocketInputEndpoint {
+    fn new(config: WebSocketInputConfig) -> AnyResult<Self> {
+        config.validate()?;
+        debug!("Starting WebSocket input endpoint: {config:?}");
+
+        let (sender, receiver) = channel(PipelineState::Paused);
+        Ok(Self {
+            config,
+            sender,
+            receiver,
+        })
+    }
+
+    /// Connect to the server and receive messages until the endpoint is
+    /// paused or terminated.
+    ///
+    /// Returns an error if the connection fails or is closed by the server.
+    async fn receive(
+        client: &Client,
+        config: &WebSocketInputConfig,
+        consumer: &mut Box<dyn InputConsumer>,
+        receiver: &mut Receiver<PipelineState>,
+        failures: &mut u32,
+    ) -> AnyResult<()> {
+        let mut request = client
+            .ws(&config.url)
+            .max_frame_size(config.max_message_size);
+        for (name, value) in config.headers.iter() {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let (_response, mut connection) = request
+            .connect()
+            .await
+            // `awc` intentionally uses errors that aren't `Sync`, but
+            // `anyhow::Error` requires `Sync`.  Transform the error so we can
+            // return it.
+            .map_err(|e| anyhow!("failed to connect to '{}': {e}", config.url))?;
+        info!("Connected to WebSocket server '{}'", config.url);
+        *failures = 0;
+
+        for message in config.subscribe.iter() {
+            connection
+                .send(Message::Text(message.clone().into()))
+                .await
+                .map_err(|e| anyhow!("failed to send subscription message: {e}"))?;
+        }
+
+        // Fragments of a message split across continuation frames.
+        let mut fragments = Vec::new();
+
+        loop {
+            select! {
+                _ = receiver.changed() => {
+                    if *receiver.borrow() != PipelineState::Running {
+                        let _ = connection.send(Message::Close(None)).await;
+                        return Ok(());
+                    }
+                }
+                frame = connection.next() => {
+                    let frame = match frame {
+                        None => bail!("WebSocket connection closed"),
+                        Some(frame) => frame.map_err(|e| anyhow!("WebSocket protocol error: {e}"))?,
+                    };
+                    match frame {
+                        Frame::Text(data) | Frame::Binary(data) => {
+                            // Leave it to the controller to handle errors.  There is noone we can
+                            // forward the error to upstream.
+                            let _ = consumer.input_chunk(&data);
+                        }
+                        Frame::Continuation(Item::FirstText(data) | Item::FirstBinary(data)) => {
+                            fragments.clear();
+                            fragments.extend_from_slice(&data);
+                        }
+                        Frame::Continuation(Item::Continue(data)) => {
+                            fragments.extend_from_slice(&data);
+                        }
+                        Frame::Continuation(Item::Last(data)) => {
+                            fragments.extend_from_slice(&data);
+                            let _ = consumer.input_chunk(&fragments);
+                            fragments.clear();
+                        }
+                        Frame::Ping(data) => {
+                            connection
+                                .send(Message::Pong(data))
+                                .await
+                                .map_err(|e| anyhow!("failed to send pong: {e}"))?;
+                        }
+                        Frame::Pong(_) => {}
+                        Frame::Close(reason) => {
+                            bail!("WebSocket connection closed by server: {reason:?}")
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn worker_thread(
+        config: WebSocketInputConfig,
+        consumer: &mut Box<dyn InputConsumer>,
+        mut receiver: Receiver<PipelineState>,
+    ) -> AnyResult<()> {
+        let client = Client::builder()
+            .connector(Connector::new().rustls(rustls_config()))
+            .finish();
+
+        // Number of consecutive failed connection attempts.
+        let mut failures = 0;
+
+        loop {
+            let state = *receiver.borrow();
+            match state {
+                PipelineState::Terminated => return Ok(()),
+                PipelineState::Paused => {
+                    // Wait for a state change.
+                    receiver.changed().await?;
+                }
+                PipelineState::Running => {
+                    if let Err(error) =
+                        Self::receive(&client, &config, consumer, &mut receiver, &mut failures)
+                            .await
+                    {
+                        failures += 1;
+                        let Some(delay) = config.reconnect.backoff(failures) else {
+                            bail!(
+                                "{error}; giving up after {} reconnection attempts",
+                                failures - 1
+                            );
+                        };
+                        consumer.error(false, error);
+
+                        // Back off, unless the endpoint is paused or terminated
+                        // in the meantime.
+                        select! {
+                            _ = receiver.changed() => (),
+                            _ = sleep(delay) => (),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl InputEndpoint for WebSocketInputEndpoint {
+    fn connect(&mut self, mut consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
+        let config = self.config.clone();
+        let receiver = self.receiver.clone();
+        let _worker = spawn(move || {
+            System::new().block_on(async move {
+                if let Err(error) = Self::worker_thread(config, &mut consumer, receiver).await {
+                    consumer.error(true, error);
+                }
+            });
+        });
+        Ok(())
+    }
+
+    fn pause(&self) -> AnyResult<()> {
+        Ok(self.sender.send(PipelineState::Paused)?)
+    }
+
+    fn start(&self) -> AnyResult<()> {
+        Ok(self.sender.send(PipelineState::Running)?)
+    }
+
+    fn disconnect(&self) {
+        let _ = self.sender.send(PipelineState::Terminated);
+    }
+}
+
+impl Drop for WebSocketInputEndpoint {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WebSocketInputTransport;
+    use crate::{
+        test::{mock_input_pipeline, wait},
+        transport::InputTransport,
+    };
+    use serde_yaml::Value as YamlValue;
+
+    #[test]
+    fn invalid_config() {
+        let config: YamlValue = serde_yaml::from_str("url: http://localhost/feed").unwrap();
+        assert!(WebSocketInputTransport
+            .new_endpoint("test", &config)
+            .is_err());
+    }
+
+    #[test]
+    fn unreachable_server() {
+        let config_str = r#"
+stream: test_input
+transport:
+    name: websocket
+    config:
+        url: ws://127.0.0.1:1/feed
+        reconnect:
+            initial_backoff_ms: 10
+            max_retries: 2
+format:
+    name: csv
+"#;
+
+        let (endpoint, consumer, _zset) =
+            mock_input_pipeline::<(u32, bool, String)>(serde_yaml::from_str(config_str).unwrap())
+                .unwrap();
+        consumer.on_error(Some(Box::new(|_| ())));
+
+        endpoint.start().unwrap();
+        wait(
+            || {
+                consumer
+                    .state()
+                    .endpoint_error
+                    .as_ref()
+                    .map_or(false, |e| e.to_string().contains("giving up"))
+            },
+            None,
+        );
+    }
+}
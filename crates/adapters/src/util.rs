@@ -13,6 +13,11 @@ pub(crate) fn split_on_newline(data: &[u8]) -> usize {
     data_len - index
 }
 
+/// Returns the number of newline characters in `data`.
+pub(crate) fn count_newlines(data: &[u8]) -> u64 {
+    data.iter().filter(|&&x| x == b'\n').count() as u64
+}
+
 pub(crate) fn truncate_ellipse<'a>(s: &'a str, len: usize, ellipse: &str) -> Cow<'a, str> {
     if s.len() <= len {
         return Cow::Borrowed(s);
@@ -50,6 +50,71 @@ impl CodegenCtx<'_> {
 
             "dbsp.str.with.capacity" => self.string_with_capacity(expr_id, call, builder),
 
+            // `fn(string: str, pattern: str) -> bool`
+            "dbsp.str.regexp_match" => {
+                self.string_intrinsic(
+                    "string_regexp_match",
+                    ColumnType::Bool,
+                    expr_id,
+                    call,
+                    builder,
+                );
+            }
+
+            // `fn(string: str, pattern: str) -> str`
+            "dbsp.str.regexp_extract" => self.string_intrinsic(
+                "string_regexp_extract",
+                ColumnType::String,
+                expr_id,
+                call,
+                builder,
+            ),
+
+            // `fn(string: str, pattern: str, replacement: str) -> str`
+            "dbsp.str.regexp_replace" => self.string_intrinsic(
+                "string_regexp_replace",
+                ColumnType::String,
+                expr_id,
+                call,
+                builder,
+            ),
+
+            // `fn(document: str, path: str) -> str`
+            "dbsp.str.json_value" => self.string_intrinsic(
+                "string_json_value",
+                ColumnType::String,
+                expr_id,
+                call,
+                builder,
+            ),
+
+            // `fn(document: str, path: str) -> bool`
+            "dbsp.str.json_value_defined" => self.string_intrinsic(
+                "string_json_value_defined",
+                ColumnType::Bool,
+                expr_id,
+                call,
+                builder,
+            ),
+
+            // `fn(document: str, path: str) -> str`
+            "dbsp.str.json_query" => self.string_intrinsic(
+                "string_json_query",
+                ColumnType::String,
+                expr_id,
+                call,
+                builder,
+            ),
+
+            // `fn(document: str, path: str) -> bool`
+            "dbsp.str.json_query_defined" => self.string_intrinsic(
+                "string_json_query_defined",
+                ColumnType::Bool,
+                expr_id,
+                call,
+                builder,
+            ),
+
             // `fn(timestamp) -> date
             "dbsp.timestamp.to_date" => self.timestamp_to_date(expr_id, call, builder),
 
@@ -817,6 +882,32 @@ impl CodegenCtx<'_> {
         }
     }
 
+    /// Calls a string intrinsic (e.g. a regex or json function), passing each
+    /// string argument as a pointer and a length
+    fn string_intrinsic(
+        &mut self,
+        intrinsic: &str,
+        ret_ty: ColumnType,
+        expr_id: ExprId,
+        call: &Call,
+        builder: &mut FunctionBuilder<'_>,
+    ) {
+        let mut args = Vec::with_capacity(call.args().len() * 2);
+        for &string_id in call.args() {
+            let string = self.value(string_id);
+            args.push(self.string_ptr(string, builder));
+            args.push(self.string_length(string, self.is_readonly(string_id), builder));
+        }
+
+        let regexp = self.imports.get(intrinsic, self.module, builder.func);
+        let result = builder.call_fn(regexp, &args);
+        self.add_expr(expr_id, result, ret_ty, None);
+
+        self.comment(builder.value_def(result), || {
+            format!("call @{}({:?})", call.function(), call.args())
+        });
+    }
+
     fn string_with_capacity(
         &mut self,
         expr_id: ExprId,
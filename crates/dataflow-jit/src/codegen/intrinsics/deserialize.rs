@@ -43,6 +43,14 @@ pub(super) extern "C" fn deserialize_json_bool(
     }
 }
 
+/// Integers may be encoded as strings, since JavaScript numbers can't
+/// represent 64-bit integers exactly
+fn json_as_i64(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str()?.trim().parse().ok())
+}
+
 pub(super) extern "C" fn deserialize_json_i64(
     place: &mut MaybeUninit<i64>,
     json_pointer_ptr: *const u8,
@@ -52,7 +60,7 @@ pub(super) extern "C" fn deserialize_json_i64(
     // The json pointer we're accessing the map with
     let json_pointer = unsafe { str_from_raw_parts(json_pointer_ptr, json_pointer_len) };
 
-    if let Some(int) = map.pointer(json_pointer).and_then(Value::as_i64) {
+    if let Some(int) = map.pointer(json_pointer).and_then(json_as_i64) {
         place.write(int);
         false
 
@@ -71,7 +79,7 @@ pub(super) extern "C" fn deserialize_json_i32(
     // The json pointer we're accessing the map with
     let json_pointer = unsafe { str_from_raw_parts(json_pointer_ptr, json_pointer_len) };
 
-    if let Some(int) = map.pointer(json_pointer).and_then(Value::as_i64) {
+    if let Some(int) = map.pointer(json_pointer).and_then(json_as_i64) {
         place.write(int as i32);
         false
 
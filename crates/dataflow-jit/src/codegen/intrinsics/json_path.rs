@@ -0,0 +1,322 @@
+//! Intrinsics for the SQL/JSON path functions `JSON_VALUE` and `JSON_QUERY`
+
+use crate::{codegen::utils::str_from_raw_parts, utils::HashMap, ThinStr};
+use serde_json::Value;
+use std::cell::RefCell;
+
+/// Max number of compiled paths cached by each thread
+const PATH_CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    /// Compiled paths indexed by their text, invalid paths are cached as
+    /// `None`. Paths are almost always constants, so this saves us from
+    /// parsing the same path for every row
+    static PATH_CACHE: RefCell<HashMap<String, Option<JsonPath>>> = RefCell::new(HashMap::default());
+
+    /// The last document parsed by this thread along with its parsed value
+    /// (or `None` if it isn't valid json). Both the null flag and the value of
+    /// a json function are computed by separate calls and queries usually
+    /// extract multiple fields from the same column, so this lets us parse
+    /// each row's document once
+    static DOCUMENT_CACHE: RefCell<Option<(String, Option<Value>)>> = RefCell::new(None);
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// `.name` or `."name"`
+    Member(String),
+    /// `.*`
+    AnyMember,
+    /// `[n]`
+    Index(usize),
+    /// `[*]`
+    AnyIndex,
+}
+
+/// A parsed SQL/JSON path, e.g. `lax $.items[0].name`
+#[derive(Debug, PartialEq)]
+struct JsonPath {
+    /// In strict mode structural errors (e.g. accessing a member of an array)
+    /// make the whole path fail, in lax mode (the default) arrays are
+    /// unwrapped automatically and missing items are skipped
+    strict: bool,
+    steps: Vec<Step>,
+}
+
+impl JsonPath {
+    fn parse(path: &str) -> Option<Self> {
+        let path = path.trim();
+        let (strict, path) = if let Some(rest) = path.strip_prefix("strict ") {
+            (true, rest.trim_start())
+        } else if let Some(rest) = path.strip_prefix("lax ") {
+            (false, rest.trim_start())
+        } else {
+            (false, path)
+        };
+
+        let mut rest = path.strip_prefix('$')?.trim_start();
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(member) = rest.strip_prefix('.') {
+                let member = member.trim_start();
+                if let Some(after) = member.strip_prefix('*') {
+                    steps.push(Step::AnyMember);
+                    rest = after;
+                } else if let Some(quoted) = member.strip_prefix('"') {
+                    let end = quoted.find('"')?;
+                    steps.push(Step::Member(quoted[..end].to_owned()));
+                    rest = &quoted[end + 1..];
+                } else {
+                    let end = member
+                        .find(|char: char| !(char.is_alphanumeric() || char == '_' || char == '$'))
+                        .unwrap_or(member.len());
+                    if end == 0 {
+                        return None;
+                    }
+                    steps.push(Step::Member(member[..end].to_owned()));
+                    rest = &member[end..];
+                }
+            } else if let Some(index) = rest.strip_prefix('[') {
+                let end = index.find(']')?;
+                let index_text = index[..end].trim();
+                if index_text == "*" {
+                    steps.push(Step::AnyIndex);
+                } else {
+                    steps.push(Step::Index(index_text.parse().ok()?));
+                }
+                rest = &index[end + 1..];
+            } else {
+                return None;
+            }
+            rest = rest.trim_start();
+        }
+
+        Some(Self { strict, steps })
+    }
+
+    /// Returns the sequence of items selected by the path or `None` if
+    /// evaluation fails in strict mode
+    fn evaluate<'a>(&self, root: &'a Value) -> Option<Vec<&'a Value>> {
+        let mut items = vec![root];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for item in items {
+                match (step, item) {
+                    (Step::Member(name), Value::Object(object)) => match object.get(name) {
+                        Some(value) => next.push(value),
+                        None if self.strict => return None,
+                        None => {}
+                    },
+                    (Step::AnyMember, Value::Object(object)) => next.extend(object.values()),
+                    (Step::Member(_) | Step::AnyMember, Value::Array(array)) if !self.strict => {
+                        for element in array {
+                            match (step, element) {
+                                (Step::Member(name), Value::Object(object)) => {
+                                    next.extend(object.get(name))
+                                }
+                                (Step::AnyMember, Value::Object(object)) => {
+                                    next.extend(object.values())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Step::Index(index), Value::Array(array)) => match array.get(*index) {
+                        Some(value) => next.push(value),
+                        None if self.strict => return None,
+                        None => {}
+                    },
+                    (Step::AnyIndex, Value::Array(array)) => next.extend(array),
+                    // Lax mode treats any other item as an array of one element
+                    (Step::Index(0) | Step::AnyIndex, item) if !self.strict => next.push(item),
+                    _ if self.strict => return None,
+                    _ => {}
+                }
+            }
+            items = next;
+        }
+        Some(items)
+    }
+}
+
+/// Evaluates `path` over `document` and calls `with` on the selected items,
+/// returns `None` if the path or document are invalid or if evaluation fails
+fn with_items<T>(
+    document: &str,
+    path: &str,
+    with: impl FnOnce(&[&Value]) -> Option<T>,
+) -> Option<T> {
+    PATH_CACHE.with(|paths| {
+        let mut paths = paths.borrow_mut();
+        if !paths.contains_key(path) {
+            if paths.len() >= PATH_CACHE_CAPACITY {
+                paths.clear();
+            }
+            paths.insert(path.to_owned(), JsonPath::parse(path));
+        }
+        let path = paths[path].as_ref()?;
+
+        DOCUMENT_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if !matches!(&*cache, Some((text, _)) if text == document) {
+                let value = serde_json::from_str(document).ok();
+                *cache = Some((document.to_owned(), value));
+            }
+
+            let root = cache.as_ref()?.1.as_ref()?;
+            with(&path.evaluate(root)?)
+        })
+    })
+}
+
+/// Returns the text of the scalar selected by `path` if it selects exactly
+/// one string, number or boolean
+fn json_value(document: &str, path: &str) -> Option<String> {
+    with_items(document, path, |items| match items {
+        [Value::String(string)] => Some(string.clone()),
+        [Value::Number(number)] => Some(number.to_string()),
+        [Value::Bool(bool)] => Some(bool.to_string()),
+        _ => None,
+    })
+}
+
+/// Returns the serialized object or array selected by `path` if it selects
+/// exactly one object or array
+fn json_query(document: &str, path: &str) -> Option<String> {
+    with_items(document, path, |items| match items {
+        [item @ (Value::Object(_) | Value::Array(_))] => Some(item.to_string()),
+        _ => None,
+    })
+}
+
+pub(super) unsafe extern "C" fn string_json_value_defined(
+    ptr: *const u8,
+    len: usize,
+    path_ptr: *const u8,
+    path_len: usize,
+) -> bool {
+    let (document, path) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(path_ptr, path_len),
+        )
+    };
+    json_value(document, path).is_some()
+}
+
+/// Returns an empty string if the value is null, callers use
+/// `string_json_value_defined()` to tell the two apart
+pub(super) unsafe extern "C" fn string_json_value(
+    ptr: *const u8,
+    len: usize,
+    path_ptr: *const u8,
+    path_len: usize,
+) -> ThinStr {
+    let (document, path) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(path_ptr, path_len),
+        )
+    };
+    ThinStr::from(json_value(document, path).as_deref().unwrap_or(""))
+}
+
+pub(super) unsafe extern "C" fn string_json_query_defined(
+    ptr: *const u8,
+    len: usize,
+    path_ptr: *const u8,
+    path_len: usize,
+) -> bool {
+    let (document, path) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(path_ptr, path_len),
+        )
+    };
+    json_query(document, path).is_some()
+}
+
+/// Returns an empty string if the value is null, callers use
+/// `string_json_query_defined()` to tell the two apart
+pub(super) unsafe extern "C" fn string_json_query(
+    ptr: *const u8,
+    len: usize,
+    path_ptr: *const u8,
+    path_len: usize,
+) -> ThinStr {
+    let (document, path) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(path_ptr, path_len),
+        )
+    };
+    ThinStr::from(json_query(document, path).as_deref().unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_query, json_value, JsonPath, Step};
+
+    #[test]
+    fn parse_paths() {
+        assert_eq!(
+            JsonPath::parse("lax $.items[0].\"unit price\""),
+            Some(JsonPath {
+                strict: false,
+                steps: vec![
+                    Step::Member("items".to_owned()),
+                    Step::Index(0),
+                    Step::Member("unit price".to_owned()),
+                ],
+            }),
+        );
+        assert_eq!(
+            JsonPath::parse("strict $.*[*]"),
+            Some(JsonPath {
+                strict: true,
+                steps: vec![Step::AnyMember, Step::AnyIndex],
+            }),
+        );
+        assert_eq!(JsonPath::parse("$."), None);
+        assert_eq!(JsonPath::parse("items"), None);
+        assert_eq!(JsonPath::parse("$[one]"), None);
+    }
+
+    #[test]
+    fn evaluate_paths() {
+        let document = r#"{"id": 7, "user": {"name": "alice", "admin": false}, "tags": ["a", "b"], "none": null}"#;
+
+        assert_eq!(json_value(document, "$.id").as_deref(), Some("7"));
+        assert_eq!(
+            json_value(document, "$.user.name").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            json_value(document, "$.user.admin").as_deref(),
+            Some("false")
+        );
+        assert_eq!(json_value(document, "$.tags[1]").as_deref(), Some("b"));
+        assert_eq!(json_value(document, "$.user"), None);
+        assert_eq!(json_value(document, "$.none"), None);
+        assert_eq!(json_value(document, "$.missing"), None);
+        assert_eq!(json_value(document, "$.tags[*]"), None);
+
+        // Lax mode unwraps arrays and scalars, strict mode doesn't
+        assert_eq!(json_value(document, "lax $.id[0]").as_deref(), Some("7"));
+        assert_eq!(json_value(document, "strict $.id[0]"), None);
+        assert_eq!(json_value(document, "strict $.tags[5]"), None);
+
+        assert_eq!(
+            json_query(document, "$.user").as_deref(),
+            Some(r#"{"admin":false,"name":"alice"}"#),
+        );
+        assert_eq!(
+            json_query(document, "$.tags").as_deref(),
+            Some(r#"["a","b"]"#)
+        );
+        assert_eq!(json_query(document, "$.id"), None);
+
+        assert_eq!(json_value("not json", "$"), None);
+        assert_eq!(json_value("\"text\"", "$").as_deref(), Some("text"));
+    }
+}
@@ -1,4 +1,5 @@
 mod deserialize;
+mod json_path;
 mod serialize;
 
 use self::{
@@ -7,6 +8,9 @@ use self::{
         deserialize_json_i32, deserialize_json_i64, deserialize_json_string,
         deserialize_json_timestamp,
     },
+    json_path::{
+        string_json_query, string_json_query_defined, string_json_value, string_json_value_defined,
+    },
     serialize::{
         byte_vec_push, byte_vec_reserve, write_date_to_byte_vec, write_decimal_to_byte_vec,
         write_escaped_string_to_byte_vec, write_f32_to_byte_vec, write_f64_to_byte_vec,
@@ -37,6 +41,7 @@ use cranelift::{
 use cranelift_jit::{JITBuilder, JITModule};
 use cranelift_module::{FuncId, Linkage, Module};
 use csv::StringRecord;
+use regex::Regex;
 use rust_decimal::{prelude::ToPrimitive, Decimal};
 use std::{
     alloc::Layout,
@@ -448,6 +453,13 @@ intrinsics! {
     string_is_lowercase = fn(ptr, usize) -> bool,
     string_is_uppercase = fn(ptr, usize) -> bool,
     string_is_ascii = fn(ptr, usize) -> bool,
+    string_regexp_match = fn(ptr, usize, ptr, usize) -> bool,
+    string_regexp_extract = fn(ptr, usize, ptr, usize) -> str,
+    string_regexp_replace = fn(ptr, usize, ptr, usize, ptr, usize) -> str,
+    string_json_value = fn(ptr, usize, ptr, usize) -> str,
+    string_json_value_defined = fn(ptr, usize, ptr, usize) -> bool,
+    string_json_query = fn(ptr, usize, ptr, usize) -> str,
+    string_json_query_defined = fn(ptr, usize, ptr, usize) -> bool,
 
     // Timestamp functions
     // timestamp_year = fn(i64) -> i64,
@@ -923,6 +935,128 @@ unsafe extern "C" fn string_is_ascii(ptr: *const u8, len: usize) -> bool {
     string.is_ascii()
 }
 
+/// Max number of compiled regular expressions cached by each thread
+const REGEX_CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    /// Compiled regular expressions indexed by pattern, invalid patterns are
+    /// cached as `None`. Patterns are almost always constants, so this saves
+    /// us from compiling the same pattern for every row
+    static REGEX_CACHE: RefCell<HashMap<String, Option<Regex>>> = RefCell::new(HashMap::default());
+}
+
+fn with_regex<T>(pattern: &str, with: impl FnOnce(Option<&Regex>) -> T) -> T {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if !cache.contains_key(pattern) {
+            if cache.len() >= REGEX_CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(pattern.to_owned(), Regex::new(pattern).ok());
+        }
+        with(cache[pattern].as_ref())
+    })
+}
+
+unsafe extern "C" fn string_regexp_match(
+    ptr: *const u8,
+    len: usize,
+    pattern_ptr: *const u8,
+    pattern_len: usize,
+) -> bool {
+    let (string, pattern) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(pattern_ptr, pattern_len),
+        )
+    };
+    with_regex(pattern, |regex| {
+        regex.map_or(false, |regex| regex.is_match(string))
+    })
+}
+
+/// Returns the text matched by the first capturing group of the pattern (or
+/// by the entire pattern if it has no groups), or an empty string if the
+/// pattern doesn't match. Callers use `string_regexp_match()` to tell a
+/// failed match apart from an empty one
+unsafe extern "C" fn string_regexp_extract(
+    ptr: *const u8,
+    len: usize,
+    pattern_ptr: *const u8,
+    pattern_len: usize,
+) -> ThinStr {
+    let (string, pattern) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(pattern_ptr, pattern_len),
+        )
+    };
+
+    with_regex(pattern, |regex| {
+        let extracted = regex
+            .and_then(|regex| regex.captures(string))
+            .and_then(|captures| {
+                if captures.len() > 1 {
+                    captures.get(1)
+                } else {
+                    captures.get(0)
+                }
+            })
+            .map_or("", |matched| matched.as_str());
+
+        ThinStr::from(extracted)
+    })
+}
+
+/// Replaces all matches of the pattern, `\N` within the replacement refers to
+/// the `N`th capturing group
+unsafe extern "C" fn string_regexp_replace(
+    ptr: *const u8,
+    len: usize,
+    pattern_ptr: *const u8,
+    pattern_len: usize,
+    replacement_ptr: *const u8,
+    replacement_len: usize,
+) -> ThinStr {
+    let (string, pattern, replacement) = unsafe {
+        (
+            str_from_raw_parts(ptr, len),
+            str_from_raw_parts(pattern_ptr, pattern_len),
+            str_from_raw_parts(replacement_ptr, replacement_len),
+        )
+    };
+
+    with_regex(pattern, |regex| match regex {
+        Some(regex) => {
+            // Translate the replacement into the syntax used by `Regex`
+            let mut translated = String::with_capacity(replacement.len());
+            let mut chars = replacement.chars().peekable();
+            while let Some(char) = chars.next() {
+                match char {
+                    '\\' => match chars.peek().copied() {
+                        Some(digit) if digit.is_ascii_digit() => {
+                            translated.push_str("${");
+                            translated.push(digit);
+                            translated.push('}');
+                            chars.next();
+                        }
+                        Some('\\') => {
+                            translated.push('\\');
+                            chars.next();
+                        }
+                        _ => translated.push('\\'),
+                    },
+                    '$' => translated.push_str("$$"),
+                    char => translated.push(char),
+                }
+            }
+
+            ThinStr::from(&*regex.replace_all(string, translated.as_str()))
+        }
+        None => ThinStr::from(string),
+    })
+}
+
 unsafe extern "C" fn fmod(lhs: f64, rhs: f64) -> f64 {
     libm::fmod(lhs, rhs)
 }
@@ -23,6 +23,10 @@ pub struct JsonSerConfig {
     // TODO: Allow serializing into nested structures
     // TODO: Allow specifying date & timestamp formats
     pub mappings: HashMap<ColumnIdx, JsonColumn>,
+    /// Serialize 64-bit integers and decimals as JSON strings, since they
+    /// can't be represented exactly by JavaScript numbers
+    #[serde(default)]
+    pub large_numbers_as_strings: bool,
 }
 
 impl Codegen {
@@ -209,6 +213,15 @@ impl Codegen {
                     }
 
                     ty if ty.is_int() || ty.is_float() => {
+                        let quote = (mappings.large_numbers_as_strings
+                            && matches!(ty, ColumnType::I64 | ColumnType::U64))
+                        .then(|| ctx.import_string("\"", &mut builder));
+                        if let Some((quote_ptr, quote_len)) = quote {
+                            builder
+                                .ins()
+                                .call(push_bytes, &[buffer, quote_ptr, quote_len]);
+                        }
+
                         let intrinsic = match ty {
                             ColumnType::I8 => "write_i8_to_byte_vec",
                             ColumnType::U8 => "write_u8_to_byte_vec",
@@ -225,6 +238,12 @@ impl Codegen {
                         let intrinsic = ctx.imports.get(intrinsic, ctx.module, builder.func);
 
                         builder.ins().call(intrinsic, &[buffer, value]);
+
+                        if let Some((quote_ptr, quote_len)) = quote {
+                            builder
+                                .ins()
+                                .call(push_bytes, &[buffer, quote_ptr, quote_len]);
+                        }
                     }
 
                     ColumnType::Decimal => {
@@ -232,8 +251,23 @@ impl Codegen {
                             ctx.imports
                                 .get("write_decimal_to_byte_vec", ctx.module, builder.func);
 
+                        let quote = mappings
+                            .large_numbers_as_strings
+                            .then(|| ctx.import_string("\"", &mut builder));
+                        if let Some((quote_ptr, quote_len)) = quote {
+                            builder
+                                .ins()
+                                .call(push_bytes, &[buffer, quote_ptr, quote_len]);
+                        }
+
                         let (lo, hi) = builder.ins().isplit(value);
                         builder.ins().call(intrinsic, &[buffer, lo, hi]);
+
+                        if let Some((quote_ptr, quote_len)) = quote {
+                            builder
+                                .ins()
+                                .call(push_bytes, &[buffer, quote_ptr, quote_len]);
+                        }
                     }
 
                     ty @ (ColumnType::Date | ColumnType::Timestamp) => {
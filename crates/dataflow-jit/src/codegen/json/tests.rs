@@ -62,6 +62,7 @@ fn deserialize_json_smoke() {
             mappings.insert(6, JsonColumn::datetime("bang", "%F"));
             mappings
         },
+        large_numbers_as_strings: false,
     };
 
     let deserialize_json = codegen.deserialize_json(&deserialize);
@@ -73,6 +74,7 @@ fn deserialize_json_smoke() {
         r#"{ "foo": "second foo data string", "bar": null, "baz": -10000, "bing": null, "bop": -0.0, "boop": null, "bang": "1999-09-09" }"#,
         r#"{ "baz": -32, "bar": null, "foo": "woah, now we switched the field orderings", "bop": 0.3, "bang": "2000-01-01" }"#,
         r#"{ "baz": 0, "bar": null, "foo": "", "bop": "NaN", "boop": "Inf", "bang": "2098-11-28" }"#,
+        r#"{ "foo": "", "baz": "9007199254740993", "bing": "-5", "bop": 1.0, "bang": "2000-01-01" }"#,
     ];
 
     #[rustfmt::skip]
@@ -81,6 +83,7 @@ fn deserialize_json_smoke() {
         row!["second foo data string", null, -10000i64, null, -0.0, null, NaiveDate::from_ymd_opt(1999, 9, 9).unwrap()],
         row!["woah, now we switched the field orderings", null, -32i64, null, 0.3, null, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()],
         row!["", null, 0i64, null, f64::NAN, ?f64::INFINITY, NaiveDate::from_ymd_opt(2098, 11, 28).unwrap()],
+        row!["", null, 9007199254740993i64, ?-5i64, 1.0, null, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()],
     ];
 
     let (jit, layout_cache) = codegen.finalize_definitions();
@@ -180,3 +183,88 @@ fn deserialize_invalid_json() {
         jit.free_memory();
     }
 }
+
+#[test]
+fn serialize_large_numbers_as_strings() {
+    utils::test_logger();
+
+    let layout_cache = RowLayoutCache::new();
+    let layout = layout_cache.add(
+        RowLayoutBuilder::new()
+            .with_column(ColumnType::I64, false)
+            .with_column(ColumnType::I64, true)
+            .with_column(ColumnType::I32, false)
+            .build(),
+    );
+
+    let mut codegen = Codegen::new(layout_cache, CodegenConfig::debug());
+
+    let deserialize = JsonDeserConfig {
+        layout,
+        mappings: {
+            let mut mappings = HashMap::default();
+            mappings.insert(0, JsonColumn::normal("/foo"));
+            mappings.insert(1, JsonColumn::normal("/bar"));
+            mappings.insert(2, JsonColumn::normal("/baz"));
+            mappings
+        },
+    };
+    let serialize = JsonSerConfig {
+        layout,
+        mappings: {
+            let mut mappings = HashMap::default();
+            mappings.insert(0, JsonColumn::normal("foo"));
+            mappings.insert(1, JsonColumn::normal("bar"));
+            mappings.insert(2, JsonColumn::normal("baz"));
+            mappings
+        },
+        large_numbers_as_strings: true,
+    };
+
+    let deserialize_json = codegen.deserialize_json(&deserialize);
+    let serialize_json = codegen.serialize_json(&serialize);
+    let vtable = codegen.vtable_for(layout);
+
+    let json_snippets = &[
+        (
+            r#"{ "foo": 9007199254740993, "bar": "-10", "baz": 5 }"#,
+            r#"{"foo":"9007199254740993","bar":"-10","baz":5}"#,
+        ),
+        (
+            r#"{ "foo": "0", "bar": null, "baz": -5 }"#,
+            r#"{"foo":"0","bar":null,"baz":-5}"#,
+        ),
+    ];
+
+    let (jit, _layout_cache) = codegen.finalize_definitions();
+    let vtable = Box::into_raw(Box::new(vtable.marshalled(&jit)));
+
+    {
+        let (deserialize_json, serialize_json) = unsafe {
+            (
+                transmute::<_, DeserializeJsonFn>(jit.get_finalized_function(deserialize_json)),
+                transmute::<_, SerializeFn>(jit.get_finalized_function(serialize_json)),
+            )
+        };
+
+        let mut serialize_buffer = Vec::new();
+        for &(json, expected) in json_snippets {
+            let json_value = serde_json::from_str(json).unwrap();
+            let mut uninit = UninitRow::new(unsafe { &*vtable });
+
+            let row = unsafe {
+                call_deserialize_fn(deserialize_json, uninit.as_mut_ptr(), &json_value).unwrap();
+                uninit.assume_init()
+            };
+
+            unsafe { serialize_json(row.as_ptr(), &mut serialize_buffer) }
+            assert_eq!(std::str::from_utf8(&serialize_buffer).unwrap(), expected);
+            serialize_buffer.clear();
+        }
+    }
+
+    unsafe {
+        drop(Box::from_raw(vtable));
+        jit.free_memory();
+    }
+}
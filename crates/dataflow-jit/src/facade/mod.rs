@@ -24,6 +24,7 @@ use chrono::{TimeZone, Utc};
 use cranelift_module::FuncId;
 use csv::StringRecord;
 use dbsp::{
+    profile::OperatorProfile,
     trace::{BatchReader, Cursor},
     DBSPHandle, Error, Runtime,
 };
@@ -192,6 +193,10 @@ impl DbspCircuit {
         self.runtime.dump_profile(path)
     }
 
+    pub fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, Error> {
+        self.runtime.retrieve_profile()
+    }
+
     pub fn step(&mut self) -> Result<(), Error> {
         tracing::info!("stepping circuit");
         let start = Instant::now();
@@ -15,6 +15,13 @@ use serde::{Deserialize, Serialize};
 /// - `@dbsp.str.clear(str)`
 /// - `@dbsp.str.concat(str, str)`
 /// - `@dbsp.str.concat_clone(str, str) -> str`
+/// - `@dbsp.str.regexp_match(str, str) -> bool`
+/// - `@dbsp.str.regexp_extract(str, str) -> str`
+/// - `@dbsp.str.regexp_replace(str, str, str) -> str`
+/// - `@dbsp.str.json_value(str, str) -> str`
+/// - `@dbsp.str.json_value_defined(str, str) -> bool`
+/// - `@dbsp.str.json_query(str, str) -> str`
+/// - `@dbsp.str.json_query_defined(str, str) -> bool`
 /// - `@dbsp.timestamp.epoch(timestamp) -> i64`
 /// - `@dbsp.date.second(date) -> i32`
 /// - `@dbsp.date.minute(date) -> i32`
@@ -1097,6 +1097,48 @@ impl FunctionValidator {
                 assert_eq!(call.ret_ty(), ColumnType::Bool);
             }
 
+            "dbsp.str.regexp_match"
+            | "dbsp.str.regexp_extract"
+            | "dbsp.str.regexp_replace"
+            | "dbsp.str.json_value"
+            | "dbsp.str.json_value_defined"
+            | "dbsp.str.json_query"
+            | "dbsp.str.json_query_defined" => {
+                let expected_args = if call.function() == "dbsp.str.regexp_replace" {
+                    3
+                } else {
+                    2
+                };
+                if call.args().len() != expected_args {
+                    return Err(ValidationError::IncorrectFunctionArgLen {
+                        expr_id,
+                        function: call.function().to_owned(),
+                        expected_args,
+                        args: call.args().len(),
+                    });
+                }
+
+                for (idx, arg) in actual_arg_types.iter().enumerate() {
+                    if arg != &RowOrScalar::Scalar(ColumnType::String) {
+                        todo!(
+                            "mismatched argument type in {expr_id}, argument {idx} should be a string but instead got {:?}",
+                            arg,
+                        );
+                    }
+                }
+
+                if matches!(
+                    call.function(),
+                    "dbsp.str.regexp_match"
+                        | "dbsp.str.json_value_defined"
+                        | "dbsp.str.json_query_defined"
+                ) {
+                    assert_eq!(call.ret_ty(), ColumnType::Bool);
+                } else {
+                    assert_eq!(call.ret_ty(), ColumnType::String);
+                }
+            }
+
             "dbsp.str.write" => {
                 if call.args().len() != 2 {
                     return Err(ValidationError::IncorrectFunctionArgLen {
@@ -0,0 +1,47 @@
+//! Encoding of circuit checkpoints.
+//!
+//! Checkpoints are serialized with `rkyv`, which does not validate its input.
+//! To avoid deserializing a truncated or corrupted buffer, we prefix the
+//! serialized data with a magic number and a hash of its contents.
+
+use crate::{
+    trace::{unaligned_deserialize, Deserializable, Serializer},
+    Error,
+};
+use rkyv::{to_bytes, Serialize};
+use xxhash_rust::xxh3::xxh3_64;
+
+const MAGIC: &[u8; 8] = b"DBSPCKPT";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+/// Serialize `value` into a buffer that can be decoded with [`decode`].
+pub(crate) fn encode<T>(value: &T) -> Vec<u8>
+where
+    T: Serialize<Serializer>,
+{
+    let bytes = to_bytes(value).expect("Can't encode checkpoint");
+
+    let mut result = Vec::with_capacity(HEADER_LEN + bytes.len());
+    result.extend_from_slice(MAGIC);
+    result.extend_from_slice(&xxh3_64(&bytes).to_le_bytes());
+    result.extend_from_slice(&bytes);
+    result
+}
+
+/// Deserialize a buffer produced by [`encode`].
+pub(crate) fn decode<T>(bytes: &[u8]) -> Result<T, Error>
+where
+    T: Deserializable,
+{
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(Error::Checkpoint("invalid checkpoint format".to_string()));
+    }
+
+    let hash = u64::from_le_bytes(bytes[MAGIC.len()..HEADER_LEN].try_into().unwrap());
+    let payload = &bytes[HEADER_LEN..];
+    if xxh3_64(payload) != hash {
+        return Err(Error::Checkpoint("checkpoint is corrupted".to_string()));
+    }
+
+    Ok(unaligned_deserialize(payload))
+}
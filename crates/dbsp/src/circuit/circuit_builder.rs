@@ -35,6 +35,7 @@ use crate::{
 use crate::{
     circuit::{
         cache::{CircuitCache, CircuitStoreMarker},
+        checkpoint,
         metadata::OperatorMeta,
         operator_traits::{
             BinaryOperator, BinarySinkOperator, Data, ImportOperator, NaryOperator,
@@ -789,7 +790,19 @@ pub trait Node {
 
     fn fixedpoint(&self, scope: Scope) -> bool;
 
+    /// Serialize the state of the inner operator (see
+    /// [`Operator::checkpoint()`](super::operator_traits::Operator::checkpoint)).
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restore the state of the inner operator (see
+    /// [`Operator::restore()`](super::operator_traits::Operator::restore)).
+    fn restore(&mut self, _state: &[u8]) {}
+
     fn map_nodes_recursive(&self, _f: &mut dyn FnMut(&dyn Node)) {}
+
+    fn map_nodes_recursive_mut(&mut self, _f: &mut dyn FnMut(&mut dyn Node)) {}
 }
 
 /// Id of an operator, guaranteed to be unique within a circuit.
@@ -2148,6 +2161,14 @@ where
         }
     }
 
+    /// Recursively apply `f` to all nodes in `self` and its children.
+    pub(crate) fn map_nodes_recursive_mut(&self, f: &mut dyn FnMut(&mut dyn Node)) {
+        for node in self.inner_mut().nodes.iter_mut() {
+            f(node.as_mut());
+            node.map_nodes_recursive_mut(f);
+        }
+    }
+
     fn clear(&mut self) {
         self.inner_mut().clear();
     }
@@ -3160,6 +3181,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3236,6 +3265,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3318,6 +3355,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3393,6 +3438,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3488,6 +3541,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3603,6 +3664,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3722,6 +3791,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3859,6 +3936,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -3981,6 +4066,14 @@ where
         self.operator.metadata(output);
     }
 
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.operator.restore(state);
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
@@ -4078,6 +4171,16 @@ where
         unsafe { (*self.operator.get()).metadata(output) }
     }
 
+    // The input half of the feedback node shares the operator with this node
+    // and doesn't report its state.
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        unsafe { (*self.operator.get()).checkpoint() }
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        unsafe { (*self.operator.get()).restore(state) }
+    }
+
     fn fixedpoint(&self, scope: Scope) -> bool {
         unsafe { (*self.operator.get()).fixedpoint(scope) }
     }
@@ -4300,6 +4403,10 @@ where
     fn map_nodes_recursive(&self, f: &mut dyn FnMut(&dyn Node)) {
         self.circuit.map_nodes_recursive(f);
     }
+
+    fn map_nodes_recursive_mut(&mut self, f: &mut dyn FnMut(&mut dyn Node)) {
+        self.circuit.map_nodes_recursive_mut(f);
+    }
 }
 
 /// Top-level circuit with executor.
@@ -4347,6 +4454,48 @@ impl CircuitHandle {
         self.executor.run(&self.circuit)
     }
 
+    /// Serialize the state of all operators in the circuit.
+    ///
+    /// Must be invoked between clock cycles.  The result can be passed to
+    /// [`Self::restore`] in a new instance of the same circuit.
+    pub fn checkpoint(&self) -> Vec<u8> {
+        let mut operators = Vec::new();
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            operators.push((node.name().into_owned(), node.checkpoint()));
+        });
+
+        checkpoint::encode(&operators)
+    }
+
+    /// Restore the state of all operators in the circuit from a buffer
+    /// returned by [`Self::checkpoint`].
+    ///
+    /// Must be invoked before the first clock cycle.  Fails without modifying
+    /// the circuit if `state` was produced by a different circuit.
+    pub fn restore(&self, state: &[u8]) -> Result<(), DBSPError> {
+        let operators: Vec<(String, Option<Vec<u8>>)> = checkpoint::decode(state)?;
+
+        let mut names = Vec::new();
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            names.push(node.name().into_owned());
+        });
+        if !names.iter().eq(operators.iter().map(|(name, _state)| name)) {
+            return Err(DBSPError::Checkpoint(
+                "checkpoint does not match the structure of the circuit".to_string(),
+            ));
+        }
+
+        let mut states = operators.into_iter().map(|(_name, state)| state);
+        self.circuit
+            .map_nodes_recursive_mut(&mut |node: &mut dyn Node| {
+                if let Some(state) = states.next().unwrap() {
+                    node.restore(&state);
+                }
+            });
+
+        Ok(())
+    }
+
     /// Attach a scheduler event handler to the circuit.
     ///
     /// This method is identical to
@@ -1,6 +1,7 @@
 use crate::{
-    circuit::runtime::RuntimeHandle, profile::Profiler, Error as DBSPError, RootCircuit, Runtime,
-    RuntimeError, SchedulerError,
+    circuit::{checkpoint, runtime::RuntimeHandle},
+    profile::{OperatorProfile, Profiler},
+    Error as DBSPError, RootCircuit, Runtime, RuntimeError, SchedulerError,
 };
 use anyhow::Error as AnyError;
 use core::fmt;
@@ -17,6 +18,7 @@ use std::{
     net::SocketAddr,
     ops::Range,
     path::{Path, PathBuf},
+    sync::Arc,
     thread::Result as ThreadResult,
     time::Instant,
 };
@@ -330,6 +332,28 @@ impl Runtime {
                             return;
                         }
                     }
+                    Ok(Command::RetrieveProfile) => {
+                        if status_sender
+                            .send(Ok(Response::OperatorProfiles(profiler.operator_profiles())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::Checkpoint) => {
+                        if status_sender
+                            .send(Ok(Response::Checkpoint(circuit.checkpoint())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::Restore(states)) => {
+                        let status = circuit.restore(&states[worker_index]);
+                        if status_sender.send(Ok(Response::Restore(status))).is_err() {
+                            return;
+                        }
+                    }
                     // Nothing to do: do some housekeeping and relinquish the CPU if there's none
                     // left.
                     Err(TryRecvError::Empty) => {
@@ -391,11 +415,18 @@ enum Command {
     Step,
     EnableProfiler,
     DumpProfile,
+    RetrieveProfile,
+    Checkpoint,
+    // Checkpointed state of each worker.
+    Restore(Arc<Vec<Vec<u8>>>),
 }
 
 enum Response {
     Unit,
     Profile(String),
+    OperatorProfiles(Vec<OperatorProfile>),
+    Checkpoint(Vec<u8>),
+    Restore(Result<(), DBSPError>),
 }
 
 /// A handle to control the execution of a circuit in a multithreaded runtime.
@@ -509,6 +540,68 @@ impl DBSPHandle {
         Ok(dir_path)
     }
 
+    /// Retrieve the profile of every operator in each worker.
+    ///
+    /// Returns a vector with one element per worker thread.  CPU usage is
+    /// only reported if CPU profiling was enabled (see
+    /// [`Self::enable_cpu_profiler`]).
+    pub fn retrieve_profile(&mut self) -> Result<Vec<Vec<OperatorProfile>>, DBSPError> {
+        let mut profiles = Vec::with_capacity(self.status_receivers.len());
+
+        self.broadcast_command(Command::RetrieveProfile, |resp| {
+            if let Response::OperatorProfiles(prof) = resp {
+                profiles.push(prof);
+            }
+        })?;
+
+        Ok(profiles)
+    }
+
+    /// Serialize the state of the circuit.
+    ///
+    /// Must be invoked between steps.  The result can be passed to
+    /// [`Self::restore`] in a new instance of the same circuit with the same
+    /// number of workers.  All values stored by stateful operators must
+    /// support serialization with `rkyv`.
+    pub fn checkpoint(&mut self) -> Result<Vec<u8>, DBSPError> {
+        let mut states = Vec::with_capacity(self.status_receivers.len());
+
+        self.broadcast_command(Command::Checkpoint, |resp| {
+            if let Response::Checkpoint(state) = resp {
+                states.push(state);
+            }
+        })?;
+
+        Ok(checkpoint::encode(&states))
+    }
+
+    /// Restore the state of the circuit from a buffer returned by
+    /// [`Self::checkpoint`].
+    ///
+    /// Must be invoked before the first step.  Fails if the checkpoint was
+    /// taken from a different circuit or with a different number of workers.
+    pub fn restore(&mut self, state: &[u8]) -> Result<(), DBSPError> {
+        let states: Vec<Vec<u8>> = checkpoint::decode(state)?;
+        if states.len() != self.command_senders.len() {
+            return Err(DBSPError::Checkpoint(format!(
+                "checkpoint was taken with {} workers, but the circuit has {} workers",
+                states.len(),
+                self.command_senders.len()
+            )));
+        }
+
+        let mut result = Ok(());
+        self.broadcast_command(Command::Restore(Arc::new(states)), |resp| {
+            if let Response::Restore(Err(error)) = resp {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        })?;
+
+        result
+    }
+
     /// Terminate the execution of the circuit, exiting all worker threads.
     ///
     /// If one or more of the worker threads panics, returns the argument the
@@ -536,9 +629,94 @@ impl Drop for DBSPHandle {
 
 #[cfg(test)]
 mod tests {
-    use crate::{operator::Generator, Circuit, Error as DBSPError, Runtime, RuntimeError};
+    use crate::{
+        operator::Generator, zset, Circuit, CollectionHandle, DBSPHandle, Error as DBSPError,
+        OrdZSet, OutputHandle, Runtime, RuntimeError,
+    };
     use anyhow::anyhow;
 
+    #[test]
+    fn test_retrieve_profile() {
+        let (mut handle, _) = Runtime::init_circuit(2, |circuit| {
+            circuit
+                .add_source(Generator::new(|| 5usize))
+                .apply_named("Double", |x| x * 2);
+            Ok(())
+        })
+        .unwrap();
+
+        handle.enable_cpu_profiler().unwrap();
+        handle.step().unwrap();
+        handle.step().unwrap();
+
+        let profiles = handle.retrieve_profile().unwrap();
+        assert_eq!(profiles.len(), 2);
+        for worker in profiles {
+            let double = worker.iter().find(|op| op.name == "Double").unwrap();
+            assert_eq!(double.invocations, 2);
+            assert_eq!(double.location.unwrap().file(), file!());
+        }
+
+        handle.kill().unwrap();
+    }
+
+    type CheckpointTestCircuit = (
+        DBSPHandle,
+        (
+            CollectionHandle<u64, isize>,
+            OutputHandle<OrdZSet<u64, isize>>,
+            OutputHandle<OrdZSet<u64, isize>>,
+        ),
+    );
+
+    fn checkpoint_test_circuit(workers: usize) -> CheckpointTestCircuit {
+        Runtime::init_circuit(workers, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<u64, isize>();
+            let integral_handle = input.integrate().output();
+            let distinct_handle = input.distinct().output();
+            Ok((input_handle, integral_handle, distinct_handle))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let (mut dbsp, (input, _integral, _distinct)) = checkpoint_test_circuit(4);
+        input.push(1, 1);
+        input.push(2, 1);
+        dbsp.step().unwrap();
+        input.push(2, 1);
+        dbsp.step().unwrap();
+        let checkpoint = dbsp.checkpoint().unwrap();
+        dbsp.kill().unwrap();
+
+        let (mut dbsp, (input, integral, distinct)) = checkpoint_test_circuit(4);
+
+        let mut corrupted = checkpoint.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            dbsp.restore(&corrupted),
+            Err(DBSPError::Checkpoint(_))
+        ));
+
+        dbsp.restore(&checkpoint).unwrap();
+        input.push(1, -1);
+        dbsp.step().unwrap();
+        // Both the integral and the trace maintained by `distinct` resume
+        // from the checkpointed state.
+        assert_eq!(integral.consolidate(), zset! { 2 => 2 });
+        assert_eq!(distinct.consolidate(), zset! { 1 => -1 });
+        dbsp.kill().unwrap();
+
+        // The checkpoint can't be restored with a different number of workers.
+        let (mut dbsp, _) = checkpoint_test_circuit(2);
+        assert!(matches!(
+            dbsp.restore(&checkpoint),
+            Err(DBSPError::Checkpoint(_))
+        ));
+        dbsp.kill().unwrap();
+    }
+
     // Panic during initialization in worker thread.
     #[test]
     fn test_panic_in_worker1() {
@@ -15,6 +15,7 @@
 //! output.
 
 mod activations;
+mod checkpoint;
 mod dbsp_handle;
 
 pub(crate) mod runtime;
@@ -27,6 +27,22 @@ pub trait Operator: 'static {
     /// Collects metadata about the current operator
     fn metadata(&self, _meta: &mut OperatorMeta) {}
 
+    /// Serialize the state of the operator.
+    ///
+    /// Invoked between clock cycles of the root circuit to checkpoint the
+    /// circuit (see [`DBSPHandle::checkpoint`](`crate::DBSPHandle::checkpoint`)).
+    /// Returns `None` for operators whose output only depends on their
+    /// current inputs.
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restore the state of the operator from a buffer returned by
+    /// [`checkpoint`](`Self::checkpoint`).
+    ///
+    /// Invoked before the first clock cycle of the root circuit.
+    fn restore(&mut self, _state: &[u8]) {}
+
     /// Notify the operator about the start of a new clock epoch.
     ///
     /// `clock_start` and `clock_end` methods support the nested circuit
@@ -22,6 +22,8 @@ pub enum Error {
     Runtime(RuntimeError),
     IO(IOError),
     Constructor(AnyError),
+    /// Failed to checkpoint or restore the state of the circuit.
+    Checkpoint(String),
 }
 
 impl DetailedError for Error {
@@ -31,6 +33,7 @@ impl DetailedError for Error {
             Self::Runtime(error) => Cow::from(format!("RuntimeError.{}", error.error_code())),
             Self::IO(_) => Cow::from("IOError"),
             Self::Constructor(_) => Cow::from("CircuitConstructorError"),
+            Self::Checkpoint(_) => Cow::from("CheckpointError"),
         }
     }
 }
@@ -52,6 +55,11 @@ impl Serialize for Error {
             Self::Constructor(_) => serializer
                 .serialize_struct("CircuitConstructorError", 0)?
                 .end(),
+            Self::Checkpoint(error) => {
+                let mut ser = serializer.serialize_struct("CheckpointError", 1)?;
+                ser.serialize_field("error", error)?;
+                ser.end()
+            }
         }
     }
 }
@@ -73,6 +81,9 @@ impl Display for Error {
             Self::Constructor(error) => {
                 write!(f, "circuit construction error: {error}")
             }
+            Self::Checkpoint(error) => {
+                write!(f, "checkpoint error: {error}")
+            }
         }
     }
 }
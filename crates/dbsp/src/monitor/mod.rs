@@ -164,6 +164,16 @@ impl TraceMonitor {
         )))
     }
 
+    /// Source location of the operator with the given id, if known.
+    pub fn operator_location(&self, id: &GlobalNodeId) -> OperatorLocation {
+        self.0
+            .lock()
+            .unwrap()
+            .circuit
+            .node_ref(id)
+            .and_then(|node| node.location)
+    }
+
     pub fn visualize_circuit(&self) -> VisGraph {
         self.visualize_circuit_annotate(|_| "".to_string())
     }
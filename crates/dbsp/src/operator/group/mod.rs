@@ -17,8 +17,11 @@ use crate::{
 use std::{borrow::Cow, marker::PhantomData, ops::Neg};
 
 mod lag;
+mod pattern;
 mod topk;
 
+pub use pattern::{AfterMatch, Pattern, PatternMatch, PatternVariable};
+
 #[cfg(test)]
 mod test;
 
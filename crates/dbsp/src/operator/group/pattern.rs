@@ -0,0 +1,544 @@
+//! Sequence pattern recognition over ordered groups, in the style of SQL's
+//! `MATCH_RECOGNIZE` clause.
+
+use super::{GroupTransformer, Monotonicity};
+use crate::{
+    algebra::ZRingValue,
+    operator::FilterMap,
+    trace::{cursor::CursorPair, Cursor},
+    DBData, DBWeight, IndexedZSet, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::{fmt, fmt::Debug, marker::PhantomData, mem::swap};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+{
+    /// Find occurrences of `pattern` in each group.
+    ///
+    /// For each key in the input stream, treats the associated values as a
+    /// sequence of events in ascending order, finds all matches of `pattern`
+    /// in this sequence, and outputs the result of applying `measures` to
+    /// each match.  A value with positive weight `w` counts as `w`
+    /// consecutive identical events; values with non-positive weight are
+    /// ignored.
+    ///
+    /// The operator is incremental: when a group changes, the matches that
+    /// were found without examining any changed event are kept, and the
+    /// search resumes after the last of them.  The search stops early when
+    /// it finds a match that was already found before the change and starts
+    /// past the last changed event, since all following matches are also
+    /// unchanged.  Appending events to a group therefore only examines the
+    /// events that follow the last match.
+    #[allow(clippy::type_complexity)]
+    pub fn match_recognize<O, MF>(
+        &self,
+        pattern: Pattern<B::Val>,
+        measures: MF,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, O, B::R>>
+    where
+        B::R: ZRingValue,
+        O: DBData,
+        MF: Fn(&PatternMatch<'_, B::Val>) -> O + 'static,
+    {
+        self.group_transform(MatchRecognize::new(pattern, measures))
+            .map_index(|(key, record)| (key.clone(), record.3.clone()))
+    }
+}
+
+/// A pattern variable, i.e., a predicate over events along with the number
+/// of consecutive events it must match.
+pub struct PatternVariable<V> {
+    /// Invoked with the current event and the event that precedes it in the
+    /// group, if any.
+    predicate: Box<dyn Fn(&V, Option<&V>) -> bool>,
+    min: usize,
+    max: usize,
+}
+
+impl<V> PatternVariable<V> {
+    /// Create a variable that matches between `min` and `max` (inclusive)
+    /// consecutive events that satisfy `predicate`.  Use `usize::MAX` for an
+    /// unbounded `max`.
+    pub fn new<F>(predicate: F, min: usize, max: usize) -> Self
+    where
+        F: Fn(&V, Option<&V>) -> bool + 'static,
+    {
+        assert!(min <= max);
+        Self {
+            predicate: Box::new(predicate),
+            min,
+            max,
+        }
+    }
+
+    /// Variable that matches exactly one event (`A`).
+    pub fn one<F>(predicate: F) -> Self
+    where
+        F: Fn(&V, Option<&V>) -> bool + 'static,
+    {
+        Self::new(predicate, 1, 1)
+    }
+
+    /// Variable that matches zero or one event (`A?`).
+    pub fn optional<F>(predicate: F) -> Self
+    where
+        F: Fn(&V, Option<&V>) -> bool + 'static,
+    {
+        Self::new(predicate, 0, 1)
+    }
+
+    /// Variable that matches any number of events (`A*`).
+    pub fn zero_or_more<F>(predicate: F) -> Self
+    where
+        F: Fn(&V, Option<&V>) -> bool + 'static,
+    {
+        Self::new(predicate, 0, usize::MAX)
+    }
+
+    /// Variable that matches one or more events (`A+`).
+    pub fn one_or_more<F>(predicate: F) -> Self
+    where
+        F: Fn(&V, Option<&V>) -> bool + 'static,
+    {
+        Self::new(predicate, 1, usize::MAX)
+    }
+
+    /// Largest number of matched events that the automaton distinguishes.
+    /// Beyond `min`, the count of an unbounded variable no longer affects
+    /// which events it can match.
+    fn max_state(&self) -> usize {
+        if self.max == usize::MAX {
+            self.min
+        } else {
+            self.max
+        }
+    }
+}
+
+impl<V> Debug for PatternVariable<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternVariable")
+            .field("min", &self.min)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+/// Where to resume the search after a match is found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AfterMatch {
+    /// Resume at the event that follows the last event of the match, so
+    /// matches never overlap (`AFTER MATCH SKIP PAST LAST ROW`).
+    #[default]
+    SkipPastLastRow,
+    /// Resume at the event that follows the first event of the match
+    /// (`AFTER MATCH SKIP TO NEXT ROW`).
+    SkipToNextRow,
+}
+
+impl AfterMatch {
+    /// The event of a match after which the search resumes.
+    fn anchor<'a, T>(self, first: &'a T, last: &'a T) -> &'a T {
+        match self {
+            Self::SkipPastLastRow => last,
+            Self::SkipToNextRow => first,
+        }
+    }
+}
+
+/// A sequence of pattern variables, e.g., `A B+ C?`.
+///
+/// Quantifiers are greedy: each variable matches as many events as possible
+/// as long as the rest of the pattern can still match.
+///
+/// Patterns are evaluated by simulating a nondeterministic finite automaton
+/// with one state per variable and number of events matched by the variable,
+/// up to the variable's `max` (or `min`, for unbounded variables).  Partial
+/// matches that reach the same state are merged, keeping the preferred one,
+/// so finding a match takes time linear in the number of events examined
+/// times the number of states.
+#[derive(Debug)]
+pub struct Pattern<V> {
+    variables: Vec<PatternVariable<V>>,
+    after_match: AfterMatch,
+    /// Index of the first automaton state of each variable.
+    offsets: Vec<usize>,
+    num_states: usize,
+}
+
+/// A partial match, i.e., a thread of the automaton.
+#[derive(Clone)]
+struct Thread {
+    /// Index of the first event of the match.
+    start: usize,
+    /// Index of the variable that matches the next event.
+    variable: usize,
+    /// Number of events matched by each variable.
+    counts: Vec<usize>,
+}
+
+/// A match found by [`Pattern::next_match`].
+struct Found {
+    /// Index of the first event of the match.
+    start: usize,
+    /// Number of events matched by each variable.
+    counts: Vec<usize>,
+    /// Index of the last event examined to find the match, or `None` if the
+    /// search examined all events, in which case the match may change when
+    /// events are appended.
+    decided: Option<usize>,
+}
+
+impl Found {
+    fn new(thread: Thread, decided: Option<usize>) -> Self {
+        Self {
+            start: thread.start,
+            counts: thread.counts,
+            decided,
+        }
+    }
+
+    /// Index of the last event of the match.
+    fn end(&self) -> usize {
+        self.start + self.counts.iter().sum::<usize>() - 1
+    }
+
+    fn to_match<'a, V>(&self, events: &[&'a V]) -> PatternMatch<'a, V> {
+        PatternMatch {
+            rows: events[self.start..=self.end()].to_vec(),
+            variables: self
+                .counts
+                .iter()
+                .enumerate()
+                .flat_map(|(variable, count)| std::iter::repeat(variable).take(*count))
+                .collect(),
+        }
+    }
+}
+
+impl<V> Pattern<V> {
+    /// Create a pattern that matches `variables` in order.
+    pub fn new(variables: Vec<PatternVariable<V>>) -> Self {
+        let mut offsets = Vec::with_capacity(variables.len());
+        let mut num_states = 0;
+        for variable in variables.iter() {
+            offsets.push(num_states);
+            num_states += variable.max_state() + 1;
+        }
+
+        Self {
+            variables,
+            after_match: AfterMatch::default(),
+            offsets,
+            num_states,
+        }
+    }
+
+    /// Set where to resume the search after a match.
+    pub fn with_after_match(mut self, after_match: AfterMatch) -> Self {
+        self.after_match = after_match;
+        self
+    }
+
+    /// Find all matches of the pattern in `events`, from left to right.
+    ///
+    /// Empty matches are not reported.
+    pub fn find_matches<'a>(&self, events: &[&'a V]) -> Vec<PatternMatch<'a, V>> {
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while let Some(found) = self.next_match(events, from) {
+            from = self.resume(&found);
+            matches.push(found.to_match(events));
+        }
+        matches
+    }
+
+    /// Index of the event at which the search resumes after `found`.
+    fn resume(&self, found: &Found) -> usize {
+        self.after_match.anchor(&found.start, &found.end()) + 1
+    }
+
+    fn state(&self, variable: usize, count: usize) -> usize {
+        self.offsets[variable] + count.min(self.variables[variable].max_state())
+    }
+
+    /// Find the first non-empty match that starts at or after `from`.
+    ///
+    /// Runs one thread per partial match, with threads ordered by priority:
+    /// matches that start earlier come first and, among matches with the
+    /// same start, the ones where earlier variables matched more events.  A
+    /// match is final once all higher-priority threads have died.  Predicates
+    /// see `events[from - 1]` as the event that precedes `events[from]`.
+    fn next_match(&self, events: &[&V], from: usize) -> Option<Found> {
+        let mut threads = Vec::new();
+        let mut occupied = vec![false; self.num_states];
+        let mut next_threads = Vec::new();
+        let mut next_occupied = vec![false; self.num_states];
+        let mut found = None;
+
+        for pos in from..events.len() {
+            if found.is_none() {
+                // Start a match at `pos`, with the lowest priority.  The
+                // only match this can complete is an empty one.
+                let thread = Thread {
+                    start: pos,
+                    variable: 0,
+                    counts: vec![0; self.variables.len()],
+                };
+                self.add_thread(&mut threads, &mut occupied, thread, pos);
+            }
+
+            let prev = pos.checked_sub(1).map(|prev| events[prev]);
+            for mut thread in threads.drain(..) {
+                if (self.variables[thread.variable].predicate)(events[pos], prev) {
+                    thread.counts[thread.variable] += 1;
+                    if let Some(thread) =
+                        self.add_thread(&mut next_threads, &mut next_occupied, thread, pos + 1)
+                    {
+                        // Lower-priority threads can only find less preferred
+                        // matches.
+                        found = Some(thread);
+                        break;
+                    }
+                }
+            }
+            swap(&mut threads, &mut next_threads);
+            swap(&mut occupied, &mut next_occupied);
+            next_occupied.fill(false);
+
+            if threads.is_empty() {
+                if let Some(thread) = found {
+                    return Some(Found::new(thread, Some(pos)));
+                }
+            }
+        }
+
+        found.map(|thread| Found::new(thread, None))
+    }
+
+    /// Add `thread`, which has consumed the events before `pos`, to
+    /// `threads`, followed by the threads it reaches by moving on to the next
+    /// variable without consuming an event.  Skips states that are already
+    /// occupied by higher-priority threads.  Returns the thread if it
+    /// completes a non-empty match.
+    fn add_thread(
+        &self,
+        threads: &mut Vec<Thread>,
+        occupied: &mut [bool],
+        mut thread: Thread,
+        pos: usize,
+    ) -> Option<Thread> {
+        loop {
+            if thread.variable == self.variables.len() {
+                return (pos > thread.start).then_some(thread);
+            }
+
+            let variable = &self.variables[thread.variable];
+            let count = thread.counts[thread.variable];
+            let state = self.state(thread.variable, count);
+            if occupied[state] {
+                return None;
+            }
+            occupied[state] = true;
+
+            // Quantifiers are greedy: matching another event with the current
+            // variable takes priority over moving on to the next one.
+            if count < variable.max {
+                threads.push(thread.clone());
+            }
+            if count < variable.min {
+                return None;
+            }
+            thread.variable += 1;
+        }
+    }
+}
+
+/// An occurrence of a [`Pattern`] in a sequence of events.
+#[derive(Debug)]
+pub struct PatternMatch<'a, V> {
+    rows: Vec<&'a V>,
+    /// Index of the variable that matched each row.
+    variables: Vec<usize>,
+}
+
+impl<'a, V> PatternMatch<'a, V> {
+    /// Events that make up the match.
+    pub fn rows(&self) -> &[&'a V] {
+        &self.rows
+    }
+
+    /// Index of the pattern variable that matched each event in
+    /// [`rows`](`Self::rows`).
+    pub fn variables(&self) -> &[usize] {
+        &self.variables
+    }
+
+    /// Events matched by pattern variable `variable`.
+    pub fn rows_of(&self, variable: usize) -> impl Iterator<Item = &'a V> + '_ {
+        self.rows
+            .iter()
+            .zip(self.variables.iter())
+            .filter(move |(_, v)| **v == variable)
+            .map(|(row, _)| *row)
+    }
+
+    /// First event matched by pattern variable `variable`, if any.
+    pub fn first(&self, variable: usize) -> Option<&'a V> {
+        self.rows_of(variable).next()
+    }
+
+    /// Last event matched by pattern variable `variable`, if any.
+    pub fn last(&self, variable: usize) -> Option<&'a V> {
+        self.rows_of(variable).last()
+    }
+}
+
+/// Position of an event in a group: the event, and which of its copies it
+/// is, for events with weight greater than one.
+type Position<V> = (V, usize);
+
+/// A match as recorded in the output trace of [`MatchRecognize`]: its first
+/// and last positions, the last position examined to find it (`None` if the
+/// search examined the whole group), and its measures.
+type MatchRecord<V, O> = (Position<V>, Position<V>, Option<Position<V>>, O);
+
+struct MatchRecognize<I, O, R, MF> {
+    pattern: Pattern<I>,
+    measures: MF,
+    _phantom: PhantomData<(O, R)>,
+}
+
+impl<I, O, R, MF> MatchRecognize<I, O, R, MF> {
+    fn new(pattern: Pattern<I>, measures: MF) -> Self {
+        Self {
+            pattern,
+            measures,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, O, R, MF> GroupTransformer<I, MatchRecord<I, O>, R> for MatchRecognize<I, O, R, MF>
+where
+    I: DBData,
+    O: DBData,
+    R: DBWeight + ZRingValue,
+    MF: Fn(&PatternMatch<'_, I>) -> O + 'static,
+{
+    fn name(&self) -> &str {
+        "match_recognize"
+    }
+
+    fn monotonicity(&self) -> Monotonicity {
+        // Records are ordered by the first position of the match, and
+        // matches are found in this order.
+        Monotonicity::Ascending
+    }
+
+    fn transform<C1, C2, C3, CB>(
+        &mut self,
+        input_delta: &mut C1,
+        input_trace: &mut C2,
+        output_trace: &mut C3,
+        mut output_cb: CB,
+    ) where
+        C1: Cursor<I, (), (), R>,
+        C2: Cursor<I, (), (), R>,
+        C3: Cursor<MatchRecord<I, O>, (), (), R>,
+        CB: FnMut(MatchRecord<I, O>, R),
+    {
+        if !input_delta.key_valid() {
+            return;
+        }
+        let first_changed = input_delta.key().clone();
+        input_delta.fast_forward_keys();
+        let last_changed = input_delta.key().clone();
+        input_delta.rewind_keys();
+
+        // Matches found without examining a changed event are still valid.
+        // Keep them and resume the search after the last one.
+        let mut resume_after = None;
+        while output_trace.key_valid() {
+            if !output_trace.weight().is_zero() {
+                let (first, last, decided, _) = output_trace.key();
+                match decided {
+                    Some(decided) if decided.0 < first_changed => {
+                        resume_after = Some(self.pattern.after_match.anchor(first, last).clone());
+                    }
+                    _ => break,
+                }
+            }
+            output_trace.step_key();
+        }
+
+        // Materialize the rest of the group, starting from the event that
+        // precedes the first one examined, which predicates can refer to.
+        let mut group = CursorPair::new(input_delta, input_trace);
+        let mut from = 0;
+        if let Some((event, copy)) = &resume_after {
+            group.seek_key(event);
+            from = copy + 1;
+        }
+        let mut events: Vec<Position<I>> = Vec::new();
+        while group.key_valid() {
+            let weight = group.weight();
+            let mut copies = R::zero();
+            let mut copy = 0;
+            while copies < weight {
+                events.push((group.key().clone(), copy));
+                copies += R::one();
+                copy += 1;
+            }
+            group.step_key();
+        }
+        let refs: Vec<&I> = events.iter().map(|(event, _)| event).collect();
+
+        // Find new matches and merge them with the old ones, retracting old
+        // matches that are no longer found.
+        loop {
+            let found = self.pattern.next_match(&refs, from);
+            let record = found.as_ref().map(|found| {
+                (
+                    events[found.start].clone(),
+                    events[found.end()].clone(),
+                    found.decided.map(|decided| events[decided].clone()),
+                    (self.measures)(&found.to_match(&refs)),
+                )
+            });
+
+            while output_trace.key_valid()
+                && record
+                    .as_ref()
+                    .map_or(true, |record| output_trace.key() < record)
+            {
+                let weight = output_trace.weight();
+                if !weight.is_zero() {
+                    output_cb(output_trace.key().clone(), weight.neg());
+                }
+                output_trace.step_key();
+            }
+
+            let (found, record) = match (found, record) {
+                (Some(found), Some(record)) => (found, record),
+                _ => break,
+            };
+            if output_trace.key_valid()
+                && output_trace.key() == &record
+                && !output_trace.weight().is_zero()
+            {
+                output_trace.step_key();
+                // The search resumes after an unchanged event, from the same
+                // position as before the change, so the remaining old
+                // matches are still valid.
+                if self.pattern.after_match.anchor(&record.0, &record.1).0 > last_changed {
+                    return;
+                }
+            } else {
+                output_cb(record, R::one());
+            }
+            from = self.pattern.resume(&found);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{
     algebra::ZRingValue,
+    indexed_zset,
+    operator::{AfterMatch, Pattern, PatternMatch, PatternVariable},
     trace::{
         cursor::Cursor,
         test_batch::{assert_batch_eq, TestBatch},
@@ -126,6 +128,48 @@ where
     }
 }
 
+/// Reference implementation of the patterns in
+/// [`match_recognize_test_circuit`]: runs of at least two events whose last
+/// digit increases, as long as possible.
+fn rising_runs(
+    batch: &TestBatch<i32, i32, (), i32>,
+    after_match: AfterMatch,
+) -> TestBatch<i32, (i32, i32), (), i32> {
+    let mut result = Vec::new();
+    let mut cursor = batch.cursor();
+
+    while cursor.key_valid() {
+        let mut events = Vec::new();
+        while cursor.val_valid() {
+            for _ in 0..cursor.weight().max(0) {
+                events.push(*cursor.val());
+            }
+            cursor.step_val();
+        }
+
+        let mut start = 0;
+        while start < events.len() {
+            let mut end = start + 1;
+            while end < events.len() && events[end] % 10 > events[end - 1] % 10 {
+                end += 1;
+            }
+            if end - start >= 2 {
+                result.push(((*cursor.key(), (events[start], events[end - 1]), ()), 1));
+                start = match after_match {
+                    AfterMatch::SkipPastLastRow => end,
+                    AfterMatch::SkipToNextRow => start + 1,
+                };
+            } else {
+                start += 1;
+            }
+        }
+
+        cursor.step_key();
+    }
+
+    TestBatch::from_data(&result)
+}
+
 fn topk_test_circuit(
     circuit: &mut RootCircuit,
 ) -> AnyResult<(
@@ -141,6 +185,38 @@ fn topk_test_circuit(
     Ok((input_handle, topk_asc_handle, topk_desc_handle))
 }
 
+fn match_recognize_test_circuit(
+    circuit: &mut RootCircuit,
+) -> AnyResult<(
+    CollectionHandle<i32, (i32, i32)>,
+    OutputHandle<OrdIndexedZSet<i32, (i32, i32), i32>>,
+    OutputHandle<OrdIndexedZSet<i32, (i32, i32), i32>>,
+)> {
+    let (input_stream, input_handle) = circuit.add_input_indexed_zset::<i32, i32, i32>();
+
+    let pattern = |after_match| {
+        Pattern::new(vec![
+            PatternVariable::one(|_: &i32, _| true),
+            PatternVariable::one_or_more(|v: &i32, prev: Option<&i32>| {
+                prev.map_or(false, |prev| v % 10 > prev % 10)
+            }),
+        ])
+        .with_after_match(after_match)
+    };
+    let measures = |m: &PatternMatch<'_, i32>| (*m.first(0).unwrap(), *m.last(1).unwrap());
+
+    let past_last_row_handle = input_stream
+        .match_recognize(pattern(AfterMatch::SkipPastLastRow), measures)
+        .integrate()
+        .output();
+    let to_next_row_handle = input_stream
+        .match_recognize(pattern(AfterMatch::SkipToNextRow), measures)
+        .integrate()
+        .output();
+
+    Ok((input_handle, past_last_row_handle, to_next_row_handle))
+}
+
 fn lag_test_circuit(
     circuit: &mut RootCircuit,
 ) -> AnyResult<(
@@ -196,6 +272,34 @@ proptest! {
         }
     }
 
+    #[test]
+    fn test_match_recognize_proptest(trace in input_trace(3, 50, 50, 20)) {
+        let (mut dbsp, (input_handle, past_last_row_handle, to_next_row_handle)) = Runtime::init_circuit(4, match_recognize_test_circuit).unwrap();
+
+        let mut ref_trace = TestBatch::new(None);
+
+        for batch in trace.into_iter() {
+            let records = batch.iter().map(|(k, v, r)| ((*k, *v, ()), *r)).collect::<Vec<_>>();
+
+            let ref_batch = TestBatch::from_data(&records);
+            ref_trace.insert(ref_batch);
+
+            for (k, v, r) in batch.into_iter() {
+                input_handle.push(k, (v, r));
+            }
+            dbsp.step().unwrap();
+
+            let past_last_row_result = past_last_row_handle.consolidate();
+            let to_next_row_result = to_next_row_handle.consolidate();
+
+            let ref_past_last_row = rising_runs(&ref_trace, AfterMatch::SkipPastLastRow);
+            let ref_to_next_row = rising_runs(&ref_trace, AfterMatch::SkipToNextRow);
+
+            assert_batch_eq(&past_last_row_result, &ref_past_last_row);
+            assert_batch_eq(&to_next_row_result, &ref_to_next_row);
+        }
+    }
+
     #[test]
     fn test_lag(trace in input_trace(5, 100, 200, 20)) {
         let (mut dbsp, (input_handle, lag_handle)) = Runtime::init_circuit(4, lag_test_circuit).unwrap();
@@ -244,3 +348,78 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn test_find_matches() {
+    // Strictly increasing runs of length at least 2.
+    let pattern = || {
+        Pattern::new(vec![
+            PatternVariable::one(|_: &i32, _| true),
+            PatternVariable::one_or_more(|v: &i32, prev: Option<&i32>| Some(v) > prev),
+        ])
+    };
+    let events = [1, 2, 3, 1, 5, 4, 4];
+    let events: Vec<&i32> = events.iter().collect();
+
+    let matches = pattern().find_matches(&events);
+    let runs: Vec<Vec<i32>> = matches
+        .iter()
+        .map(|m| m.rows().iter().map(|v| **v).collect())
+        .collect();
+    assert_eq!(runs, vec![vec![1, 2, 3], vec![1, 5]]);
+    assert_eq!(matches[0].variables(), &[0, 1, 1]);
+    assert_eq!(matches[0].first(1), Some(&2));
+    assert_eq!(matches[0].last(1), Some(&3));
+
+    let matches = pattern()
+        .with_after_match(AfterMatch::SkipToNextRow)
+        .find_matches(&events);
+    let runs: Vec<Vec<i32>> = matches
+        .iter()
+        .map(|m| m.rows().iter().map(|v| **v).collect())
+        .collect();
+    assert_eq!(runs, vec![vec![1, 2, 3], vec![2, 3], vec![1, 5]]);
+}
+
+#[test]
+fn test_match_recognize() {
+    // An even value followed by one or more odd values.
+    let (circuit, (input_handle, output_handle)) = RootCircuit::build(|circuit| {
+        let (input_stream, input_handle) = circuit.add_input_indexed_zset::<i32, i32, i32>();
+        let pattern = Pattern::new(vec![
+            PatternVariable::one(|v: &i32, _| v % 2 == 0),
+            PatternVariable::one_or_more(|v: &i32, _| v % 2 != 0),
+        ]);
+        let output_handle = input_stream
+            .match_recognize(pattern, |m| (*m.first(0).unwrap(), *m.last(1).unwrap()))
+            .integrate()
+            .output();
+        Ok((input_handle, output_handle))
+    })
+    .unwrap();
+
+    for v in [2, 3, 5, 6, 7, 10] {
+        input_handle.push(1, (v, 1));
+    }
+    input_handle.push(2, (1, 1));
+    circuit.step().unwrap();
+    assert_eq!(
+        output_handle.consolidate(),
+        indexed_zset! { 1 => { (2, 5) => 1, (6, 7) => 1 } }
+    );
+
+    input_handle.push(1, (4, 1));
+    input_handle.push(2, (0, 1));
+    circuit.step().unwrap();
+    assert_eq!(
+        output_handle.consolidate(),
+        indexed_zset! { 1 => { (2, 3) => 1, (4, 5) => 1, (6, 7) => 1 }, 2 => { (0, 1) => 1 } }
+    );
+
+    input_handle.push(1, (3, -1));
+    circuit.step().unwrap();
+    assert_eq!(
+        output_handle.consolidate(),
+        indexed_zset! { 1 => { (4, 5) => 1, (6, 7) => 1 }, 2 => { (0, 1) => 1 } }
+    );
+}
@@ -9,7 +9,7 @@ use crate::{
         z1::{DelayedFeedback, DelayedNestedFeedback},
         Plus,
     },
-    NumEntries,
+    NumEntries, Rkyv,
 };
 use size_of::SizeOf;
 use std::ops::Add;
@@ -28,6 +28,7 @@ where
         + HasZero
         + SizeOf
         + NumEntries
+        + Rkyv
         + 'static,
 {
     /// Integrate the input stream.
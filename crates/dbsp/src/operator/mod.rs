@@ -34,6 +34,7 @@ pub mod sample;
 mod semijoin;
 mod stream_fold;
 mod sum;
+pub mod text_index;
 pub mod time_series;
 mod trace;
 mod z1;
@@ -47,6 +48,7 @@ pub use delta0::Delta0;
 pub use distinct::Distinct;
 pub use filter_map::{FilterKeys, FilterMap, FilterVals, FlatMap, Map, MapKeys};
 pub use generator::{Generator, GeneratorNested};
+pub use group::{AfterMatch, Pattern, PatternMatch, PatternVariable};
 pub use index::Index;
 use input::Mailbox;
 pub use input::{CollectionHandle, InputHandle, UpsertHandle};
@@ -1,25 +1,49 @@
 //! Compute random samples of data.
 
 use crate::{
-    algebra::{HasOne, ZRingValue},
+    algebra::{AddAssignByRef, HasOne, HasZero, IndexedZSet, ZRingValue},
     circuit::{
-        operator_traits::{BinaryOperator, Operator},
+        operator_traits::{BinaryOperator, Operator, TernaryOperator},
         Scope,
     },
-    trace::{Batch, BatchReader, Builder},
+    trace::{cursor::Cursor, unaligned_deserialize, Batch, BatchReader, Builder, Spine},
     Circuit, DBData, DBWeight, OrdZSet, RootCircuit, Stream,
 };
+use ordered_float::OrderedFloat;
 use rand::thread_rng;
-use std::{borrow::Cow, cmp::min, marker::PhantomData};
+use rkyv::to_bytes;
+use std::{
+    borrow::Cow,
+    cmp::min,
+    collections::{BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+use xxhash_rust::xxh3::Xxh3;
 
 // Prevent gigantic memory allocations by bounding sample size.
 pub const MAX_SAMPLE_SIZE: usize = 10_000_000;
 pub const MAX_QUANTILES: usize = 1_000;
 
+/// Number of keys tracked by the reservoir of a
+/// [`sample_reservoir`](`Stream::sample_reservoir`) operator unless the
+/// application needs larger samples.
+pub const DEFAULT_RESERVOIR_CAPACITY: usize = 10_000;
+
+/// Seed of the hash function that assigns random priorities to keys in
+/// [`sample_reservoir`](`Stream::sample_reservoir`).  It differs from the
+/// seed used to shard records across workers, so that the priorities of keys
+/// don't depend on the worker they are assigned to.
+const RESERVOIR_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
 pub const fn default_quantiles() -> u32 {
     100
 }
 
+pub const fn default_sample_size() -> u32 {
+    100
+}
+
 // TODO: Operator to randomly sample `(K, V)` pairs.  This is
 // a little more tricky and also more expensive to implement than
 // sampling keys, as at the low level we need to pick random
@@ -130,6 +154,342 @@ where
     }
 }
 
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue + Into<i64>,
+{
+    /// Maintains a weighted random sample of the keys of `self`.
+    ///
+    /// Unlike [`stream_sample_keys`](`Self::stream_sample_keys`), which
+    /// samples the batch received at the current clock cycle, this operator
+    /// samples the integral of the input stream and maintains the sample
+    /// incrementally.  Each key with a positive weight in the integral (the
+    /// sum of the weights of all its values) gets a random priority that
+    /// depends on the key and its weight, and the operator keeps track of up
+    /// to `capacity` keys with the highest priorities, updating them as keys
+    /// are inserted and deleted.  Priorities are chosen so that the
+    /// probability to sample a key is proportional to its weight, i.e., the
+    /// sample is uniform over the records of the collection counting
+    /// duplicates.
+    ///
+    /// The `sample_size` stream specifies the size of the sample to output
+    /// at the current clock cycle (use `0` when no sample is needed at the
+    /// current clock cycle to make sure the operator doesn't waste CPU
+    /// cycles).  `sample_size` values larger than `capacity` are treated as
+    /// `capacity`.
+    ///
+    /// Outputs a Z-set containing up to `sample_size` sampled keys.  Each key
+    /// is output with weight `1` regardless of its weight in the input
+    /// collection.  Since priorities are derived from the hash of each key,
+    /// the operator outputs the same sample as long as the weights of the
+    /// keys it contains and of the keys that would displace them don't
+    /// change.
+    ///
+    /// The cost of maintaining the sample is a lookup in the trace of the
+    /// input stream for each key in the input batch.  Deleting sampled keys
+    /// shrinks the reservoir.  When it no longer contains `sample_size` keys,
+    /// the operator refills it from the trace the next time a sample is
+    /// requested.
+    pub fn sample_reservoir(
+        &self,
+        capacity: usize,
+        sample_size: &Stream<RootCircuit, usize>,
+    ) -> Stream<RootCircuit, OrdZSet<B::Key, B::R>> {
+        self.circuit().region("sample_reservoir", || {
+            let stream = self.shard();
+            let trace = stream.integrate_trace();
+
+            // Maintain a reservoir in each worker.
+            let local_output = self.circuit().add_ternary_operator(
+                ReservoirSample::new(capacity),
+                &stream,
+                &trace,
+                sample_size,
+            );
+
+            // Pick keys with the highest priorities across all workers.
+            self.circuit().add_binary_operator(
+                MergeReservoirSamples::new(capacity),
+                &local_output.gather(0),
+                sample_size,
+            )
+        })
+    }
+}
+
+/// Priority of a key with a positive `weight` in a weighted reservoir sample.
+///
+/// Uses the Efraimidis-Spirakis scheme: a key with weight `w` gets priority
+/// `u^(1/w)`, where `u` is uniformly distributed in `(0, 1)`, and the sample
+/// consists of the keys with the highest priorities.  We compute `ln(u)/w`
+/// instead, which preserves the order of priorities without underflowing for
+/// large weights.  `u` is derived from the hash of the key, so the priority
+/// of a key only changes when its weight changes.
+fn reservoir_priority<K: Hash>(key: &K, weight: i64) -> OrderedFloat<f64> {
+    let mut hasher = Xxh3::with_seed(RESERVOIR_SEED);
+    key.hash(&mut hasher);
+    // Map 53 random bits to the open interval `(0, 1)`.
+    let u = ((hasher.finish() >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    OrderedFloat(u.ln() / weight as f64)
+}
+
+/// Builds a Z-set from up to `sample_size` keys with the highest priorities.
+fn top_keys<'a, K, R>(
+    priorities: impl DoubleEndedIterator<Item = &'a (OrderedFloat<f64>, K)>,
+    sample_size: usize,
+    weight: impl Fn(&K) -> R,
+) -> OrdZSet<K, R>
+where
+    K: DBData + 'a,
+    R: DBWeight,
+{
+    let mut sample = priorities
+        .rev()
+        .take(sample_size)
+        .map(|(_priority, key)| (key.clone(), weight(key)))
+        .collect::<Vec<_>>();
+    sample.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
+
+    let mut builder = <<OrdZSet<_, _> as Batch>::Builder>::with_capacity((), sample.len());
+    for tuple in sample.into_iter() {
+        builder.push(tuple);
+    }
+    builder.done()
+}
+
+/// Maintains the reservoir of a single worker.
+///
+/// Invariant: `keys` contains all keys with positive weights in the trace
+/// whose priority exceeds `threshold`, and no other keys.
+struct ReservoirSample<B>
+where
+    B: IndexedZSet,
+{
+    capacity: usize,
+    /// Sampled keys with their weights and priorities.
+    keys: BTreeMap<B::Key, (B::R, OrderedFloat<f64>)>,
+    /// Sampled keys ordered by priority.
+    priorities: BTreeSet<(OrderedFloat<f64>, B::Key)>,
+    /// Priority of the last key evicted from the reservoir.  All keys outside
+    /// the reservoir have priorities lower than or equal to this value.
+    /// `None` if the reservoir has never overflown, i.e., it contains all keys
+    /// with positive weights.
+    threshold: Option<OrderedFloat<f64>>,
+}
+
+impl<B> ReservoirSample<B>
+where
+    B: IndexedZSet,
+    B::R: ZRingValue + Into<i64>,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: min(capacity, MAX_SAMPLE_SIZE),
+            keys: BTreeMap::new(),
+            priorities: BTreeSet::new(),
+            threshold: None,
+        }
+    }
+
+    /// Total weight of the key under `cursor`.
+    fn key_weight<C>(cursor: &mut C) -> B::R
+    where
+        C: Cursor<B::Key, B::Val, (), B::R>,
+    {
+        let mut weight = B::R::zero();
+        while cursor.val_valid() {
+            weight.add_assign_by_ref(&cursor.weight());
+            cursor.step_val();
+        }
+        weight
+    }
+
+    /// Update the reservoir after the weight of `key` changed to `weight`.
+    fn update(&mut self, key: &B::Key, weight: B::R) {
+        if let Some((_weight, priority)) = self.keys.remove(key) {
+            self.priorities.remove(&(priority, key.clone()));
+        }
+
+        if weight.le0() {
+            return;
+        }
+
+        let priority = reservoir_priority(key, weight.clone().into());
+        if self
+            .threshold
+            .map_or(true, |threshold| priority > threshold)
+        {
+            self.keys.insert(key.clone(), (weight, priority));
+            self.priorities.insert((priority, key.clone()));
+
+            if self.keys.len() > self.capacity {
+                let (priority, key) = self.priorities.pop_first().unwrap();
+                self.keys.remove(&key);
+                self.threshold = Some(priority);
+            }
+        }
+    }
+
+    /// Recompute the reservoir from scratch.
+    fn refill(&mut self, trace: &Spine<B>) {
+        self.keys.clear();
+        self.priorities.clear();
+        self.threshold = None;
+
+        let mut cursor = trace.cursor();
+        while cursor.key_valid() {
+            let weight = Self::key_weight(&mut cursor);
+            let key = cursor.key().clone();
+            self.update(&key, weight);
+            cursor.step_key();
+        }
+    }
+}
+
+impl<B> Operator for ReservoirSample<B>
+where
+    B: IndexedZSet,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("ReservoirSample")
+    }
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|(key, (weight, priority))| (key.clone(), weight.clone(), priority.0))
+            .collect::<Vec<_>>();
+        let threshold = self.threshold.map(|threshold| threshold.0);
+
+        let bytes = to_bytes(&(keys, threshold)).expect("Can't encode reservoir");
+        Some(bytes.into_vec())
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        #[allow(clippy::type_complexity)]
+        let (keys, threshold): (Vec<(B::Key, B::R, f64)>, Option<f64>) =
+            unaligned_deserialize(state);
+
+        self.keys.clear();
+        self.priorities.clear();
+        for (key, weight, priority) in keys {
+            self.priorities
+                .insert((OrderedFloat(priority), key.clone()));
+            self.keys.insert(key, (weight, OrderedFloat(priority)));
+        }
+        self.threshold = threshold.map(OrderedFloat);
+    }
+}
+
+impl<B> TernaryOperator<B, Spine<B>, usize, OrdZSet<B::Key, B::R>> for ReservoirSample<B>
+where
+    B: IndexedZSet,
+    B::R: ZRingValue + Into<i64>,
+{
+    /// * `delta` - changes to the input collection at the current clock cycle.
+    /// * `trace` - trace of the input collection, including `delta`.
+    /// * `sample_size` - size of the sample to output.
+    fn eval(
+        &mut self,
+        delta: Cow<'_, B>,
+        trace: Cow<'_, Spine<B>>,
+        sample_size: Cow<'_, usize>,
+    ) -> OrdZSet<B::Key, B::R> {
+        let trace = trace.as_ref();
+
+        let mut delta_cursor = delta.cursor();
+        let mut trace_cursor = trace.cursor();
+        while delta_cursor.key_valid() {
+            let key = delta_cursor.key();
+            trace_cursor.seek_key(key);
+            let weight = if trace_cursor.get_key() == Some(key) {
+                Self::key_weight(&mut trace_cursor)
+            } else {
+                B::R::zero()
+            };
+            self.update(key, weight);
+            delta_cursor.step_key();
+        }
+
+        let sample_size = min(*sample_size, self.capacity);
+        if sample_size == 0 {
+            return <OrdZSet<_, _>>::empty(());
+        }
+
+        if self.keys.len() < sample_size && self.threshold.is_some() {
+            self.refill(trace);
+        }
+
+        // Output keys with their weights, which `MergeReservoirSamples` needs
+        // to recompute their priorities.
+        top_keys(self.priorities.iter(), sample_size, |key| {
+            self.keys[key].0.clone()
+        })
+    }
+}
+
+/// Picks keys with the highest priorities from the union of per-worker
+/// samples produced by [`ReservoirSample`].
+struct MergeReservoirSamples<K, R> {
+    capacity: usize,
+    _phantom: PhantomData<(K, R)>,
+}
+
+impl<K, R> MergeReservoirSamples<K, R> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: min(capacity, MAX_SAMPLE_SIZE),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, R> Operator for MergeReservoirSamples<K, R>
+where
+    K: 'static,
+    R: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("MergeReservoirSamples")
+    }
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+}
+
+impl<K, R> BinaryOperator<OrdZSet<K, R>, usize, OrdZSet<K, R>> for MergeReservoirSamples<K, R>
+where
+    K: DBData,
+    R: DBWeight + ZRingValue + Into<i64>,
+{
+    fn eval(&mut self, samples: &OrdZSet<K, R>, &sample_size: &usize) -> OrdZSet<K, R> {
+        let sample_size = min(sample_size, self.capacity);
+        if sample_size == 0 {
+            return <OrdZSet<_, _>>::empty(());
+        }
+
+        let mut priorities = BTreeSet::new();
+        let mut cursor = samples.cursor();
+        while cursor.key_valid() {
+            let weight = cursor.weight();
+            if !weight.le0() {
+                priorities.insert((
+                    reservoir_priority(cursor.key(), weight.into()),
+                    cursor.key().clone(),
+                ));
+            }
+            cursor.step_key();
+        }
+
+        top_keys(priorities.iter(), sample_size, |_key| R::one())
+    }
+}
+
 struct SampleKeys<T>
 where
     T: BatchReader,
@@ -187,6 +547,7 @@ where
 
 #[cfg(test)]
 mod test {
+    use super::reservoir_priority;
     use crate::{
         trace::{
             cursor::Cursor,
@@ -196,7 +557,9 @@ mod test {
         CollectionHandle, InputHandle, OrdZSet, OutputHandle, RootCircuit, Runtime,
     };
     use anyhow::Result as AnyResult;
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    const RESERVOIR_CAPACITY: usize = 10;
 
     fn test_circuit(
         circuit: &mut RootCircuit,
@@ -306,4 +669,121 @@ mod test {
             }
         }
     }
+
+    fn reservoir_test_circuit(
+        circuit: &mut RootCircuit,
+    ) -> AnyResult<(
+        InputHandle<usize>,
+        CollectionHandle<i32, (i32, i32)>,
+        OutputHandle<OrdZSet<i32, i32>>,
+    )> {
+        let (sample_size_stream, sample_size_handle) = circuit.add_input_stream::<usize>();
+        let (input_stream, input_handle) = circuit.add_input_indexed_zset::<i32, i32, i32>();
+
+        let sample_handle = input_stream
+            .sample_reservoir(RESERVOIR_CAPACITY, &sample_size_stream)
+            .output();
+
+        Ok((sample_size_handle, input_handle, sample_handle))
+    }
+
+    #[test]
+    fn sample_reservoir_weighted() {
+        let (mut dbsp, (sample_size_handle, input_handle, output_sample_handle)) =
+            Runtime::init_circuit(4, reservoir_test_circuit).unwrap();
+
+        // Keys `0..5` with weight 1 and keys `5..10` with weight 1000000, and
+        // a few keys with non-positive weights that must never be sampled.
+        for key in 0..10 {
+            input_handle.push(key, (0, if key < 5 { 1 } else { 1_000_000 }));
+        }
+        input_handle.push(10, (0, -1));
+        input_handle.push(11, (0, 1));
+        input_handle.push(11, (1, -1));
+        sample_size_handle.set_for_all(5);
+        dbsp.step().unwrap();
+
+        let sample = output_sample_handle.consolidate();
+        let mut cursor = sample.cursor();
+        let mut keys = Vec::new();
+        while cursor.key_valid() {
+            assert_eq!(cursor.weight(), 1);
+            keys.push(*cursor.key());
+            cursor.step_key();
+        }
+
+        // A weight-1 key has a priority above a weight-1000000 key with
+        // probability of about 1e-6, so the sample consists of the heavy
+        // keys.
+        assert_eq!(keys, vec![5, 6, 7, 8, 9]);
+
+        // Deleting sampled keys replaces them with the remaining keys.
+        for key in 5..9 {
+            input_handle.push(key, (0, -1_000_000));
+        }
+        dbsp.step().unwrap();
+        let sample = output_sample_handle.consolidate();
+        assert_eq!(sample.key_count(), 5);
+        let mut cursor = sample.cursor();
+        cursor.seek_key(&9);
+        assert_eq!(cursor.get_key(), Some(&9));
+
+        // No sample is output unless requested.
+        sample_size_handle.set_for_all(0);
+        dbsp.step().unwrap();
+        assert!(output_sample_handle.consolidate().is_empty());
+
+        dbsp.kill().unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn sample_reservoir_proptest(trace in input_trace(100, 5, 200, 20)) {
+            let (mut dbsp, (sample_size_handle, input_handle, output_sample_handle)) =
+                Runtime::init_circuit(4, reservoir_test_circuit).unwrap();
+
+            let mut ref_weights = BTreeMap::<i32, i32>::new();
+
+            for (batch, sample_size) in trace.into_iter() {
+                // Also exercise samples that exceed the capacity.
+                let sample_size = sample_size % (2 * RESERVOIR_CAPACITY);
+
+                for (k, v, r) in batch.into_iter() {
+                    *ref_weights.entry(k).or_default() += r;
+                    input_handle.push(k, (v, r));
+                }
+
+                sample_size_handle.set_for_all(sample_size);
+
+                dbsp.step().unwrap();
+
+                let output_sample = output_sample_handle.consolidate();
+
+                // The sample must consist of the keys with positive weights
+                // that have the highest priorities.
+                let mut expected = ref_weights
+                    .iter()
+                    .filter(|(_k, w)| **w > 0)
+                    .map(|(k, w)| (reservoir_priority(k, *w as i64), *k))
+                    .collect::<Vec<_>>();
+                expected.sort();
+                let expected = expected
+                    .into_iter()
+                    .rev()
+                    .take(sample_size.min(RESERVOIR_CAPACITY))
+                    .map(|(_priority, k)| k)
+                    .collect::<BTreeSet<_>>();
+
+                let mut actual = BTreeSet::new();
+                let mut cursor = output_sample.cursor();
+                while cursor.key_valid() {
+                    assert_eq!(cursor.weight(), 1);
+                    actual.insert(*cursor.key());
+                    cursor.step_key();
+                }
+
+                assert_eq!(actual, expected);
+            }
+        }
+    }
 }
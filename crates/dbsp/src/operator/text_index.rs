@@ -0,0 +1,270 @@
+//! Full-text search operators.
+//!
+//! Records are split into tokens by [`tokenize`].  The
+//! [`inverted_index`](`Stream::inverted_index`) operator maintains a mapping
+//! from tokens to the records that contain them, and
+//! [`text_search`](`Stream::text_search`) uses it to incrementally match a
+//! collection of queries against a collection of records without scanning
+//! the text of every record on each step.
+//! [`text_filter`](`Stream::text_filter`) keeps the records that match any
+//! query, preserving their weights.
+
+use crate::{
+    algebra::ZRingValue,
+    circuit::{Circuit, Stream, WithClock},
+    operator::FilterMap,
+    DBData, DBTimestamp, OrdIndexedZSet, OrdZSet,
+};
+use std::{collections::BTreeSet, iter::once};
+
+/// Splits `text` into lowercase alphanumeric tokens.
+///
+/// Any character that is not alphanumeric acts as a separator.  Tokens are
+/// returned in the order in which they occur in `text` and may repeat.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Returns the set of distinct tokens in `text`.
+fn distinct_tokens(text: &str) -> BTreeSet<String> {
+    tokenize(text).collect()
+}
+
+impl<C, K, R> Stream<C, OrdZSet<K, R>>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    K: DBData,
+    R: ZRingValue,
+{
+    /// Incrementally maintains an inverted index over the text of the records
+    /// in `self`.
+    ///
+    /// `text_func` extracts the text to index from a record.  The output
+    /// stream maps each token to the records whose text contains it.  A
+    /// record contributes to the index at most once per distinct token.
+    pub fn inverted_index<F>(&self, text_func: F) -> Stream<C, OrdIndexedZSet<String, K, R>>
+    where
+        F: Fn(&K) -> &str + 'static,
+    {
+        self.flat_map_index(move |record| {
+            distinct_tokens(text_func(record))
+                .into_iter()
+                .map(|token| (token, record.clone()))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// Incrementally matches the queries in `queries` against the records in
+    /// `self`.
+    ///
+    /// A record matches a query if the text of the record, extracted by
+    /// `text_func`, contains every token of the query text, extracted by
+    /// `query_func`.  Token order and position are ignored; phrase matching
+    /// can be implemented by applying an exact filter to the output of this
+    /// operator.  Queries without tokens match nothing.
+    ///
+    /// The output stream contains a `(query, record)` pair with weight `1`
+    /// for each match, i.e., it is a set, regardless of the multiplicities
+    /// of queries and records in the inputs.
+    ///
+    /// Matching is performed by joining the tokens of each query with the
+    /// [`inverted_index`](`Self::inverted_index`) of `self` and counting the
+    /// number of distinct tokens each record has in common with each query,
+    /// so the cost of a step is proportional to the number of records that
+    /// share tokens with changed queries and vice versa.
+    pub fn text_search<Q, F, QF>(
+        &self,
+        queries: &Stream<C, OrdZSet<Q, R>>,
+        text_func: F,
+        query_func: QF,
+    ) -> Stream<C, OrdZSet<(Q, K), R>>
+    where
+        Q: DBData,
+        F: Fn(&K) -> &str + 'static,
+        QF: Fn(&Q) -> &str + 'static,
+    {
+        let index = self.inverted_index(text_func).distinct();
+
+        // Index queries by token.  Each query carries the number of distinct
+        // tokens it contains, i.e., the number of tokens a record must match.
+        let query_tokens = queries
+            .flat_map_index(move |query| {
+                let tokens = distinct_tokens(query_func(query));
+                let required = tokens.len();
+                tokens
+                    .into_iter()
+                    .map(|token| (token, (query.clone(), required)))
+                    .collect::<Vec<_>>()
+            })
+            .distinct();
+
+        query_tokens
+            .join_index(&index, |_token, (query, required), record| {
+                once((((query.clone(), *required), record.clone()), ()))
+            })
+            .weighted_count()
+            .flat_map(|(((query, required), record), matched)| {
+                (*matched == weight_of::<R>(*required)).then(|| (query.clone(), record.clone()))
+            })
+    }
+
+    /// Incrementally filters `self`, keeping the records whose text, extracted
+    /// by `text_func`, contains every token of at least one query in
+    /// `queries`.
+    ///
+    /// Unlike [`text_search`](`Self::text_search`), the output preserves the
+    /// weights of the records in `self`, so this operator can replace a
+    /// filter over a multiset.  The SQL compiler uses it to evaluate
+    /// `TEXT_CONTAINS` and `TEXT_PHRASE` predicates.
+    pub fn text_filter<F>(
+        &self,
+        queries: &Stream<C, OrdZSet<String, R>>,
+        text_func: F,
+    ) -> Stream<C, OrdZSet<K, R>>
+    where
+        F: Fn(&K) -> &str + 'static,
+    {
+        let matches = self
+            .text_search(queries, text_func, |query: &String| query.as_str())
+            .map_index(|(_query, record)| (record.clone(), ()))
+            .distinct();
+
+        self.map_index(|record| (record.clone(), ()))
+            .join(&matches, |record, (), ()| record.clone())
+    }
+}
+
+/// Returns `n` as a weight, i.e., the sum of `n` copies of `R::one()`.
+fn weight_of<R>(n: usize) -> R
+where
+    R: ZRingValue,
+{
+    let mut weight = R::zero();
+    for _ in 0..n {
+        weight += R::one();
+    }
+    weight
+}
+
+#[cfg(test)]
+mod test {
+    use super::tokenize;
+    use crate::{zset, Circuit, RootCircuit};
+
+    #[test]
+    fn tokenize_test() {
+        assert_eq!(
+            tokenize("Connection reset by peer: 10.0.0.1").collect::<Vec<_>>(),
+            vec!["connection", "reset", "by", "peer", "10", "0", "0", "1"]
+        );
+        assert_eq!(tokenize("  --  ").count(), 0);
+    }
+
+    #[test]
+    fn text_search_test() {
+        let (circuit, (records, queries, output)) = RootCircuit::build(move |circuit| {
+            let (records, records_handle) = circuit.add_input_zset::<(u64, String), isize>();
+            let (queries, queries_handle) = circuit.add_input_zset::<String, isize>();
+
+            let output = records
+                .text_search(
+                    &queries,
+                    |(_id, text): &(u64, String)| text.as_str(),
+                    |query: &String| query.as_str(),
+                )
+                .map(|(query, (id, _text))| (query.clone(), *id))
+                .integrate()
+                .output();
+
+            Ok((records_handle, queries_handle, output))
+        })
+        .unwrap();
+
+        queries.push("disk full".to_string(), 1);
+        queries.push("timeout".to_string(), 1);
+        queries.push("".to_string(), 1);
+        records.push((1, "Disk /dev/sda1 is full".to_string()), 1);
+        records.push((2, "Request TIMEOUT after 30s".to_string()), 1);
+        records.push((3, "disk ok".to_string()), 1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                ("disk full".to_string(), 1) => 1,
+                ("timeout".to_string(), 2) => 1,
+            }
+        );
+
+        // Duplicate records and tokens don't affect the result.
+        records.push((1, "Disk /dev/sda1 is full".to_string()), 1);
+        records.push((4, "full full disk".to_string()), 1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                ("disk full".to_string(), 1) => 1,
+                ("disk full".to_string(), 4) => 1,
+                ("timeout".to_string(), 2) => 1,
+            }
+        );
+
+        // Retract records and queries.
+        records.push((4, "full full disk".to_string()), -1);
+        queries.push("timeout".to_string(), -1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { ("disk full".to_string(), 1) => 1 }
+        );
+    }
+
+    #[test]
+    fn text_filter_test() {
+        let (circuit, (records, queries, output)) = RootCircuit::build(move |circuit| {
+            let (records, records_handle) = circuit.add_input_zset::<(u64, String), isize>();
+            let (queries, queries_handle) = circuit.add_input_zset::<String, isize>();
+
+            let output = records
+                .text_filter(&queries, |(_id, text): &(u64, String)| text.as_str())
+                .integrate()
+                .output();
+
+            Ok((records_handle, queries_handle, output))
+        })
+        .unwrap();
+
+        // Records matching several queries are output once, with their
+        // original weights.
+        queries.push("disk".to_string(), 1);
+        queries.push("full disk".to_string(), 1);
+        records.push((1, "Disk /dev/sda1 is full".to_string()), 2);
+        records.push((2, "Request TIMEOUT after 30s".to_string()), 1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { (1, "Disk /dev/sda1 is full".to_string()) => 2 }
+        );
+
+        records.push((1, "Disk /dev/sda1 is full".to_string()), -1);
+        queries.push("timeout".to_string(), 1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (1, "Disk /dev/sda1 is full".to_string()) => 1,
+                (2, "Request TIMEOUT after 30s".to_string()) => 1,
+            }
+        );
+
+        queries.push("disk".to_string(), -1);
+        queries.push("full disk".to_string(), -1);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { (2, "Request TIMEOUT after 30s".to_string()) => 1 }
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! Online anomaly detection over time series.
+//!
+//! The operators in this module score each data point of a partitioned time
+//! series against the statistics of a rolling window of points in the same
+//! partition:
+//!
+//! * [`partitioned_rolling_zscore`](`Stream::partitioned_rolling_zscore`)
+//!   computes the z-score of each value with respect to the mean and sample
+//!   standard deviation of the window.
+//! * [`partitioned_ewma_bands`](`Stream::partitioned_ewma_bands`) computes
+//!   exponentially weighted moving average (EWMA) control bands, where more
+//!   recent points in the window carry more weight.
+//!
+//! Both operators are built on top of
+//! [`partitioned_rolling_aggregate`](`Stream::partitioned_rolling_aggregate`)
+//! and are therefore incremental: late or retracted data points update the
+//! scores of all affected points.  To score a point against its history
+//! only, use a range that ends before the point itself, e.g.,
+//! `RelRange::new(RelOffset::Before(window), RelOffset::Before(1))`.
+
+use crate::{
+    algebra::{Semigroup, ZRingValue, F64},
+    operator::{
+        time_series::{OrdPartitionedIndexedZSet, RelRange},
+        Aggregator, FilterMap,
+    },
+    trace::Cursor,
+    DBData, DBWeight, RootCircuit, Stream,
+};
+use num::{PrimInt, ToPrimitive};
+use rkyv::{Archive, Deserialize, Serialize};
+use size_of::SizeOf;
+use std::{iter::once, marker::PhantomData};
+
+/// Converts a Z-set weight into a floating point multiplier.
+fn weight_to_f64<R: ToPrimitive>(weight: &R) -> f64 {
+    weight
+        .to_f64()
+        .expect("weight is not representable as a floating point number")
+}
+
+/// Count, sum, and sum of squares of a set of values.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Eq,
+    Hash,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    SizeOf,
+    Archive,
+    Serialize,
+    Deserialize,
+)]
+pub struct Moments {
+    count: F64,
+    sum: F64,
+    sum_sq: F64,
+}
+
+impl Moments {
+    /// Number of values.
+    pub fn count(&self) -> f64 {
+        self.count.into_inner()
+    }
+
+    /// Mean of the values, or `None` if the set is empty.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count() > 0.0).then(|| self.sum.into_inner() / self.count())
+    }
+
+    /// Sample standard deviation of the values, or `None` if the set
+    /// contains fewer than two values.
+    pub fn stddev(&self) -> Option<f64> {
+        let count = self.count();
+        if count < 2.0 {
+            return None;
+        }
+        let sum = self.sum.into_inner();
+        let variance = (self.sum_sq.into_inner() - sum * sum / count) / (count - 1.0);
+        Some(variance.max(0.0).sqrt())
+    }
+
+    /// Z-score of `value` with respect to the values in the set, or `None`
+    /// if the standard deviation is undefined or zero.
+    pub fn zscore(&self, value: f64) -> Option<f64> {
+        let stddev = self.stddev()?;
+        let mean = self.mean()?;
+        (stddev > 0.0).then(|| (value - mean) / stddev)
+    }
+}
+
+/// Semigroup over [`Moments`].
+#[derive(Clone)]
+pub struct MomentsSemigroup;
+
+impl Semigroup<Moments> for MomentsSemigroup {
+    fn combine(left: &Moments, right: &Moments) -> Moments {
+        Moments {
+            count: left.count + right.count,
+            sum: left.sum + right.sum,
+            sum_sq: left.sum_sq + right.sum_sq,
+        }
+    }
+}
+
+/// Aggregator that computes the [`Moments`] of a set of values.
+#[derive(Clone)]
+pub struct MomentsAggregator;
+
+impl<R> Aggregator<F64, (), R> for MomentsAggregator
+where
+    R: DBWeight + ToPrimitive,
+{
+    type Accumulator = Moments;
+    type Semigroup = MomentsSemigroup;
+    type Output = Moments;
+
+    fn aggregate<C>(&self, cursor: &mut C) -> Option<Moments>
+    where
+        C: Cursor<F64, (), (), R>,
+    {
+        let mut moments = None;
+
+        while cursor.key_valid() {
+            let weight = weight_to_f64(&cursor.weight());
+            let value = cursor.key().into_inner();
+            let acc = moments.get_or_insert_with(Moments::default);
+            acc.count += F64::new(weight);
+            acc.sum += F64::new(value * weight);
+            acc.sum_sq += F64::new(value * value * weight);
+            cursor.step_key();
+        }
+
+        moments
+    }
+
+    fn finalize(&self, accumulator: Moments) -> Moments {
+        accumulator
+    }
+}
+
+/// Exponentially weighted sums of a set of timestamped values, normalized to
+/// the time of the most recent value.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Eq,
+    Hash,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    SizeOf,
+    Archive,
+    Serialize,
+    Deserialize,
+)]
+pub struct EwmaAccumulator<TS> {
+    /// Time of the most recent value.
+    time: TS,
+    /// Decay rate per unit of time.
+    rate: F64,
+    weight: F64,
+    sum: F64,
+    sum_sq: F64,
+}
+
+impl<TS> EwmaAccumulator<TS>
+where
+    TS: PrimInt,
+{
+    /// Weight, sum, and sum of squares decayed to `time`.
+    fn decayed_to(&self, time: TS) -> (f64, f64, f64) {
+        let elapsed = (time - self.time).to_f64().unwrap_or(f64::INFINITY);
+        let decay = (-self.rate.into_inner() * elapsed).exp();
+        (
+            self.weight.into_inner() * decay,
+            self.sum.into_inner() * decay,
+            self.sum_sq.into_inner() * decay,
+        )
+    }
+}
+
+/// Semigroup over [`EwmaAccumulator`]s.  Combines two accumulators by
+/// decaying both to the time of the more recent one.
+#[derive(Clone)]
+pub struct EwmaSemigroup<TS>(PhantomData<TS>);
+
+impl<TS> Semigroup<EwmaAccumulator<TS>> for EwmaSemigroup<TS>
+where
+    TS: PrimInt,
+{
+    fn combine(left: &EwmaAccumulator<TS>, right: &EwmaAccumulator<TS>) -> EwmaAccumulator<TS> {
+        let time = left.time.max(right.time);
+        let (lweight, lsum, lsum_sq) = left.decayed_to(time);
+        let (rweight, rsum, rsum_sq) = right.decayed_to(time);
+
+        EwmaAccumulator {
+            time,
+            rate: left.rate.max(right.rate),
+            weight: F64::new(lweight + rweight),
+            sum: F64::new(lsum + rsum),
+            sum_sq: F64::new(lsum_sq + rsum_sq),
+        }
+    }
+}
+
+/// EWMA control band: the weighted mean of a window of values plus/minus a
+/// multiple of their weighted standard deviation.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Eq,
+    Hash,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    SizeOf,
+    Archive,
+    Serialize,
+    Deserialize,
+)]
+pub struct ControlBand {
+    pub mean: F64,
+    pub stddev: F64,
+    pub lower: F64,
+    pub upper: F64,
+}
+
+impl ControlBand {
+    /// Returns `true` if `value` lies within the band.
+    pub fn contains(&self, value: F64) -> bool {
+        self.lower <= value && value <= self.upper
+    }
+}
+
+/// Aggregator that computes EWMA control bands over `(timestamp, value)`
+/// pairs.
+pub struct EwmaAggregator<TS> {
+    rate: f64,
+    width: f64,
+    phantom: PhantomData<TS>,
+}
+
+impl<TS> Clone for EwmaAggregator<TS> {
+    fn clone(&self) -> Self {
+        Self {
+            rate: self.rate,
+            width: self.width,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<TS> EwmaAggregator<TS>
+where
+    TS: PrimInt,
+{
+    /// Create an aggregator where the weight of a value halves every
+    /// `half_life` units of time, producing bands that are `width` standard
+    /// deviations wide on each side of the mean.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `half_life` is not positive.
+    pub fn new(half_life: TS, width: f64) -> Self {
+        assert!(half_life > TS::zero(), "EWMA half-life must be positive");
+        Self {
+            rate: std::f64::consts::LN_2 / half_life.to_f64().unwrap(),
+            width,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<TS, R> Aggregator<(TS, F64), (), R> for EwmaAggregator<TS>
+where
+    TS: DBData + PrimInt + Default,
+    R: DBWeight + ToPrimitive,
+{
+    type Accumulator = EwmaAccumulator<TS>;
+    type Semigroup = EwmaSemigroup<TS>;
+    type Output = ControlBand;
+
+    fn aggregate<C>(&self, cursor: &mut C) -> Option<EwmaAccumulator<TS>>
+    where
+        C: Cursor<(TS, F64), (), (), R>,
+    {
+        let mut values = Vec::new();
+        while cursor.key_valid() {
+            let (time, value) = *cursor.key();
+            values.push((time, value.into_inner(), weight_to_f64(&cursor.weight())));
+            cursor.step_key();
+        }
+
+        let time = values.iter().map(|(time, _, _)| *time).max()?;
+        let mut acc = EwmaAccumulator {
+            time,
+            rate: F64::new(self.rate),
+            ..Default::default()
+        };
+        for (t, value, weight) in values.into_iter() {
+            let elapsed = (time - t).to_f64().unwrap_or(f64::INFINITY);
+            let weight = weight * (-self.rate * elapsed).exp();
+            acc.weight += F64::new(weight);
+            acc.sum += F64::new(value * weight);
+            acc.sum_sq += F64::new(value * value * weight);
+        }
+
+        Some(acc)
+    }
+
+    fn finalize(&self, acc: EwmaAccumulator<TS>) -> ControlBand {
+        let weight = acc.weight.into_inner();
+        let (mean, variance) = if weight > 0.0 {
+            let mean = acc.sum.into_inner() / weight;
+            (
+                mean,
+                (acc.sum_sq.into_inner() / weight - mean * mean).max(0.0),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let stddev = variance.sqrt();
+
+        ControlBand {
+            mean: F64::new(mean),
+            stddev: F64::new(stddev),
+            lower: F64::new(mean - self.width * stddev),
+            upper: F64::new(mean + self.width * stddev),
+        }
+    }
+}
+
+impl<PK, TS, R> Stream<RootCircuit, OrdPartitionedIndexedZSet<PK, TS, F64, R>>
+where
+    PK: DBData,
+    TS: DBData + PrimInt + Default,
+    R: DBWeight + ZRingValue + ToPrimitive,
+{
+    /// Rolling z-score of a partitioned time series.
+    ///
+    /// For each input record `(p, (ts, v))`, computes the mean and sample
+    /// standard deviation of the values in partition `p` whose timestamps
+    /// fall within `range.range_of(ts)`, and outputs
+    /// `(p, (ts, (v, z)))`, where `z` is the z-score of `v`.  `z` is `None`
+    /// if the window contains fewer than two values or all values in the
+    /// window are equal.
+    pub fn partitioned_rolling_zscore(
+        &self,
+        range: RelRange<TS>,
+    ) -> Stream<RootCircuit, OrdPartitionedIndexedZSet<PK, TS, (F64, Option<F64>), R>> {
+        self.circuit().region("partitioned_rolling_zscore", || {
+            let moments = self.partitioned_rolling_aggregate(MomentsAggregator, range);
+            self.join_scores(&moments, |value, moments: &Moments| {
+                moments.zscore(value.into_inner()).map(F64::new)
+            })
+        })
+    }
+
+    /// Rolling EWMA control bands of a partitioned time series.
+    ///
+    /// For each input record `(p, (ts, v))`, computes the exponentially
+    /// weighted mean and standard deviation of the values in partition `p`
+    /// whose timestamps fall within `range.range_of(ts)`, where the weight
+    /// of a value halves every `half_life` units of time before the most
+    /// recent value in the window.  Outputs `(p, (ts, (v, band)))`, where
+    /// `band` spans `width` standard deviations on each side of the mean,
+    /// or is `None` if the window is empty.  Values outside their band
+    /// (see [`ControlBand::contains`]) are anomalies.
+    pub fn partitioned_ewma_bands(
+        &self,
+        half_life: TS,
+        width: f64,
+        range: RelRange<TS>,
+    ) -> Stream<RootCircuit, OrdPartitionedIndexedZSet<PK, TS, (F64, Option<ControlBand>), R>> {
+        self.circuit().region("partitioned_ewma_bands", || {
+            let aggregator = EwmaAggregator::new(half_life, width);
+            let bands = self
+                .map_index(|(pk, (ts, v))| (pk.clone(), (*ts, (*ts, *v))))
+                .partitioned_rolling_aggregate(aggregator, range);
+            self.join_scores(&bands, |_value, band: &ControlBand| Some(band.clone()))
+        })
+    }
+
+    /// Join input records with rolling aggregates computed for their
+    /// timestamps, applying `score` to each value and its aggregate.
+    fn join_scores<A, S, F>(
+        &self,
+        aggregates: &Stream<RootCircuit, OrdPartitionedIndexedZSet<PK, TS, Option<A>, R>>,
+        score: F,
+    ) -> Stream<RootCircuit, OrdPartitionedIndexedZSet<PK, TS, (F64, Option<S>), R>>
+    where
+        A: DBData,
+        S: DBData,
+        F: Fn(F64, &A) -> Option<S> + Clone + 'static,
+    {
+        let values = self.map_index(|(pk, (ts, v))| ((pk.clone(), *ts), *v));
+        let aggregates = aggregates.map_index(|(pk, (ts, agg))| ((pk.clone(), *ts), agg.clone()));
+
+        values.join_index(&aggregates, move |(pk, ts), value, agg| {
+            let score = agg.as_ref().and_then(|agg| score(*value, agg));
+            once((pk.clone(), (*ts, (*value, score))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ControlBand;
+    use crate::{
+        algebra::F64,
+        operator::time_series::{RelOffset, RelRange},
+        trace::{BatchReader, Cursor},
+        RootCircuit,
+    };
+    use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+    #[test]
+    fn rolling_zscore() {
+        let output = Rc::new(RefCell::new(BTreeMap::new()));
+        let output_clone = output.clone();
+
+        let (circuit, input) = RootCircuit::build(move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<u64, (u64, F64), i64>();
+
+            let range = RelRange::new(RelOffset::Before(4), RelOffset::Before(1));
+            input_stream
+                .partitioned_rolling_zscore(range)
+                .integrate()
+                .inspect(move |batch| {
+                    let mut output = output_clone.borrow_mut();
+                    output.clear();
+                    let mut cursor = batch.cursor();
+                    while cursor.key_valid() {
+                        while cursor.val_valid() {
+                            let (ts, (_v, z)) = *cursor.val();
+                            output.insert((*cursor.key(), ts), z.map(|z| z.into_inner()));
+                            cursor.step_val();
+                        }
+                        cursor.step_key();
+                    }
+                });
+            Ok(input_handle)
+        })
+        .unwrap();
+
+        input.append(&mut vec![
+            (0, ((1, F64::new(10.0)), 1)),
+            (0, ((2, F64::new(12.0)), 1)),
+            (0, ((3, F64::new(14.0)), 1)),
+            (0, ((4, F64::new(30.0)), 1)),
+            (1, ((1, F64::new(5.0)), 1)),
+            (1, ((2, F64::new(5.0)), 1)),
+            (1, ((3, F64::new(6.0)), 1)),
+        ]);
+        circuit.step().unwrap();
+
+        {
+            let output = output.borrow();
+            assert_eq!(output[&(0, 1)], None);
+            assert_eq!(output[&(0, 2)], None);
+            // Window {10, 12}: mean 11, stddev sqrt(2).
+            assert!((output[&(0, 3)].unwrap() - 3.0 / 2f64.sqrt()).abs() < 1e-9);
+            // Window {10, 12, 14}: mean 12, stddev 2.
+            assert!((output[&(0, 4)].unwrap() - 9.0).abs() < 1e-9);
+            // Window {5, 5} has zero deviation.
+            assert_eq!(output[&(1, 3)], None);
+        }
+
+        // A late point changes the scores of later points.
+        input.append(&mut vec![(1, ((0, F64::new(7.0)), 1))]);
+        circuit.step().unwrap();
+
+        let output = output.borrow();
+        // Window {7, 5, 5}: mean 17/3.
+        let mean = 17.0 / 3.0;
+        let stddev = ((4.0 / 9.0 * 4.0 + 1.0 / 9.0 * 4.0 * 2.0) / 2.0f64).sqrt();
+        assert!((output[&(1, 3)].unwrap() - (6.0 - mean) / stddev).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ewma_bands() {
+        let output = Rc::new(RefCell::new(BTreeMap::new()));
+        let output_clone = output.clone();
+
+        let (circuit, input) = RootCircuit::build(move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<u64, (u64, F64), i64>();
+
+            let range = RelRange::new(RelOffset::Before(100), RelOffset::Before(1));
+            input_stream
+                .partitioned_ewma_bands(1, 3.0, range)
+                .integrate()
+                .inspect(move |batch| {
+                    let mut output = output_clone.borrow_mut();
+                    output.clear();
+                    let mut cursor = batch.cursor();
+                    while cursor.key_valid() {
+                        while cursor.val_valid() {
+                            let (ts, (v, band)) = cursor.val().clone();
+                            output.insert(ts, (v, band));
+                            cursor.step_val();
+                        }
+                        cursor.step_key();
+                    }
+                });
+            Ok(input_handle)
+        })
+        .unwrap();
+
+        input.append(&mut vec![
+            (0, ((1, F64::new(8.0)), 1)),
+            (0, ((2, F64::new(16.0)), 1)),
+            (0, ((3, F64::new(15.0)), 1)),
+            (0, ((4, F64::new(100.0)), 1)),
+        ]);
+        circuit.step().unwrap();
+
+        let output = output.borrow();
+        assert_eq!(output[&1].1, None);
+
+        // Single point: zero-width band.
+        let band: &ControlBand = output[&2].1.as_ref().unwrap();
+        assert_eq!(band.mean, F64::new(8.0));
+        assert_eq!(band.stddev, F64::new(0.0));
+        assert!(!band.contains(output[&2].0));
+
+        // Window {8 @ t=1, 16 @ t=2}: with a half-life of 1, the more recent
+        // point has twice the weight of the older one.
+        let band = output[&3].1.as_ref().unwrap();
+        let mean = (8.0 + 16.0 * 2.0) / 3.0;
+        assert!((band.mean.into_inner() - mean).abs() < 1e-9);
+        let variance = (64.0 + 256.0 * 2.0) / 3.0 - mean * mean;
+        assert!((band.stddev.into_inner() - variance.sqrt()).abs() < 1e-9);
+        assert!(band.contains(output[&3].0));
+
+        // The spike is outside its band.
+        let band = output[&4].1.as_ref().unwrap();
+        assert!(!band.contains(output[&4].0));
+    }
+}
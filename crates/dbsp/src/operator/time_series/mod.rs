@@ -1,3 +1,4 @@
+pub mod anomaly;
 mod partitioned;
 mod radix_tree;
 mod range;
@@ -5,6 +6,7 @@ mod rolling_aggregate;
 mod watermark;
 mod window;
 
+pub use anomaly::{ControlBand, Moments};
 pub use partitioned::{
     OrdPartitionedIndexedZSet, PartitionCursor, PartitionedBatch, PartitionedBatchReader,
     PartitionedIndexedZSet,
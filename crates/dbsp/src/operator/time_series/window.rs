@@ -7,8 +7,9 @@ use crate::{
         Circuit, OwnershipPreference, Scope, Stream,
     },
     operator::trace::TraceBound,
-    trace::{cursor::Cursor, BatchReader, Spine},
+    trace::{cursor::Cursor, unaligned_deserialize, BatchReader, Spine},
 };
+use rkyv::to_bytes;
 use std::{borrow::Cow, cmp::max, marker::PhantomData};
 
 impl<C, B> Stream<C, B>
@@ -125,6 +126,15 @@ where
         // Do we have meaningful examples of using windows inside nested scopes?
         panic!("'Window' operator used in fixedpoint iteration")
     }
+
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        let bytes = to_bytes(&self.window).expect("Can't encode window bounds");
+        Some(bytes.into_vec())
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.window = unaligned_deserialize(state);
+    }
 }
 
 impl<B> TernaryOperator<Spine<B>, B, (B::Key, B::Key), B> for Window<B>
@@ -6,11 +6,14 @@ use crate::{
         Scope, Stream, WithClock,
     },
     circuit_cache_key,
-    trace::{cursor::Cursor, Batch, BatchReader, Builder, Spine, Trace},
+    trace::{cursor::Cursor, unaligned_deserialize, Batch, BatchReader, Builder, Spine, Trace},
     DBData, Timestamp,
 };
+use rkyv::to_bytes;
 use size_of::SizeOf;
-use std::{borrow::Cow, cell::RefCell, marker::PhantomData, ops::DerefMut, rc::Rc};
+use std::{
+    borrow::Cow, cell::RefCell, collections::BTreeMap, marker::PhantomData, ops::DerefMut, rc::Rc,
+};
 
 circuit_cache_key!(TraceId<B, D, K, V>(GlobalNodeId => (Stream<B, D>, TraceBounds<K, V>)));
 circuit_cache_key!(DelayedTraceId<B, D>(GlobalNodeId => Stream<B, D>));
@@ -645,6 +648,54 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         !self.dirty[scope as usize]
     }
+
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        // `Spine` doesn't support `rkyv`, so we serialize its contents as a
+        // flat list of updates.
+        let mut updates = Vec::new();
+        if let Some(trace) = self.trace.as_ref() {
+            let mut cursor = trace.cursor();
+            while cursor.key_valid() {
+                while cursor.val_valid() {
+                    let key = cursor.key().clone();
+                    let val = cursor.val().clone();
+                    cursor.map_times(|time, weight| {
+                        updates.push((key.clone(), val.clone(), time.clone(), weight.clone()))
+                    });
+                    cursor.step_val();
+                }
+                cursor.step_key();
+            }
+        }
+
+        let bytes = to_bytes(&(self.time.clone(), updates)).expect("Can't encode trace");
+        Some(bytes.into_vec())
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        #[allow(clippy::type_complexity)]
+        let (time, updates): (T::Time, Vec<(T::Key, T::Val, T::Time, T::R)>) =
+            unaligned_deserialize(state);
+
+        // A batch has a single timestamp, so we build one batch per timestamp.
+        let mut batches = BTreeMap::<_, Vec<_>>::new();
+        for (key, val, time, weight) in updates {
+            batches
+                .entry(time)
+                .or_default()
+                .push((T::Batch::item_from(key, val), weight));
+        }
+
+        let mut trace = T::new(None);
+        for (time, tuples) in batches {
+            trace.insert(T::Batch::from_tuples(time, tuples));
+        }
+
+        self.time = time;
+        self.trace = Some(trace);
+        self.effective_key_bound = None;
+        self.effective_val_bound = None;
+    }
 }
 
 impl<T> StrictOperator<T> for Z1Trace<T>
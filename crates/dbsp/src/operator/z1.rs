@@ -8,8 +8,11 @@ use crate::{
         Circuit, ExportId, ExportStream, FeedbackConnector, GlobalNodeId, OwnershipPreference,
         Scope, Stream,
     },
-    circuit_cache_key, NumEntries,
+    circuit_cache_key,
+    trace::unaligned_deserialize,
+    NumEntries, Rkyv,
 };
+use rkyv::to_bytes;
 use size_of::{Context, SizeOf};
 use std::{borrow::Cow, mem::replace};
 
@@ -33,7 +36,7 @@ where
 impl<C, D> DelayedFeedback<C, D>
 where
     C: Circuit,
-    D: Eq + SizeOf + NumEntries + Clone + HasZero + 'static,
+    D: Eq + SizeOf + NumEntries + Clone + HasZero + Rkyv + 'static,
 {
     /// Create a feedback loop with `Z1` operator.  Use [`Self::connect`] to
     /// close the loop.
@@ -82,7 +85,7 @@ pub struct DelayedNestedFeedback<C, D> {
 impl<C, D> DelayedNestedFeedback<C, D>
 where
     C: Circuit,
-    D: Eq + SizeOf + NumEntries + Clone + 'static,
+    D: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     /// Create a feedback loop with `Z1` operator.  Use [`Self::connect`] to
     /// close the loop.
@@ -116,7 +119,7 @@ where
     /// Applies [`Z1`] operator to `self`.
     pub fn delay(&self) -> Stream<C, D>
     where
-        D: Eq + SizeOf + NumEntries + Clone + HasZero + 'static,
+        D: Eq + SizeOf + NumEntries + Clone + HasZero + Rkyv + 'static,
     {
         self.circuit()
             .cache_get_or_insert_with(DelayedId::new(self.origin_node_id().clone()), || {
@@ -128,7 +131,7 @@ where
     /// Applies [`Z1Nested`] operator to `self`.
     pub fn delay_nested(&self) -> Stream<C, D>
     where
-        D: Eq + Clone + HasZero + SizeOf + NumEntries + 'static,
+        D: Eq + Clone + HasZero + SizeOf + NumEntries + Rkyv + 'static,
     {
         self.circuit()
             .cache_get_or_insert_with(NestedDelayedId::new(self.origin_node_id().clone()), || {
@@ -182,7 +185,7 @@ where
 
 impl<T> Operator for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn name(&self) -> Cow<'static, str> {
         Cow::from("Z^-1")
@@ -212,11 +215,20 @@ where
             true
         }
     }
+
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        let bytes = to_bytes(&self.values).expect("Can't encode `Z^-1` state");
+        Some(bytes.into_vec())
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.values = unaligned_deserialize(state);
+    }
 }
 
 impl<T> UnaryOperator<T, T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn eval(&mut self, i: &T) -> T {
         replace(&mut self.values, i.clone())
@@ -233,7 +245,7 @@ where
 
 impl<T> StrictOperator<T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn get_output(&mut self) -> T {
         self.empty_output = self.values.num_entries_shallow() == 0;
@@ -247,7 +259,7 @@ where
 
 impl<T> StrictUnaryOperator<T, T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn eval_strict(&mut self, i: &T) {
         self.values = i.clone();
@@ -320,7 +332,7 @@ impl<T> Z1Nested<T> {
 
 impl<T> Operator for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn name(&self) -> Cow<'static, str> {
         Cow::from("Z^-1 (nested)")
@@ -380,11 +392,23 @@ where
             false
         }
     }
+
+    fn checkpoint(&self) -> Option<Vec<u8>> {
+        // Only the prefix up to `timestamp` survives the next `clock_start`.
+        let values = self.values[..self.timestamp].to_vec();
+        let bytes = to_bytes(&values).expect("Can't encode `Z^-1 (nested)` state");
+        Some(bytes.into_vec())
+    }
+
+    fn restore(&mut self, state: &[u8]) {
+        self.values = unaligned_deserialize(state);
+        self.timestamp = self.values.len();
+    }
 }
 
 impl<T> UnaryOperator<T, T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn eval(&mut self, i: &T) -> T {
         debug_assert!(self.timestamp <= self.values.len());
@@ -423,7 +447,7 @@ where
 
 impl<T> StrictOperator<T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn get_output(&mut self) -> T {
         if self.timestamp >= self.values.len() {
@@ -447,7 +471,7 @@ where
 
 impl<T> StrictUnaryOperator<T, T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Clone + Rkyv + 'static,
 {
     fn eval_strict(&mut self, i: &T) {
         debug_assert!(self.timestamp < self.values.len());
@@ -3,17 +3,37 @@
 use crate::{
     circuit::{
         circuit_builder::Node,
-        metadata::{MetaItem, OperatorMeta},
+        metadata::{MetaItem, OperatorLocation, OperatorMeta},
         GlobalNodeId,
     },
     monitor::TraceMonitor,
     RootCircuit,
 };
-use std::{borrow::Cow, collections::HashMap, fmt::Write};
+use std::{borrow::Cow, collections::HashMap, fmt::Write, time::Duration};
 
 mod cpu;
 pub use cpu::CPUProfiler;
 
+/// Profile of a single operator in one worker, as returned by
+/// [`Profiler::operator_profiles`].
+#[derive(Clone, Debug)]
+pub struct OperatorProfile {
+    /// Global id of the operator.
+    pub id: GlobalNodeId,
+    /// Operator name.
+    pub name: Cow<'static, str>,
+    /// Location in the program where the operator was instantiated.
+    pub location: OperatorLocation,
+    /// Number of times the operator was evaluated.  Always 0 unless CPU
+    /// profiling is enabled.
+    pub invocations: usize,
+    /// Total time spent evaluating the operator.  Always 0 unless CPU
+    /// profiling is enabled.
+    pub time: Duration,
+    /// Operator metadata, e.g., the size of its state.
+    pub metadata: OperatorMeta,
+}
+
 /// Rudimentary circuit profiler.
 ///
 /// Records circuit topology, operator metadata, and optionally CPU usage, and
@@ -46,6 +66,35 @@ impl Profiler {
         self.cpu_profiler.attach(&self.circuit, "cpu_profiler");
     }
 
+    /// Returns the profile of every operator in the circuit, including
+    /// operators in nested circuits.
+    pub fn operator_profiles(&self) -> Vec<OperatorProfile> {
+        let mut profiles = Vec::new();
+
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            let mut metadata = OperatorMeta::new();
+            node.metadata(&mut metadata);
+
+            let id = node.global_id().clone();
+            let (invocations, time) = self
+                .cpu_profiler
+                .operator_profile(&id)
+                .map(|profile| (profile.invocations(), profile.total_time()))
+                .unwrap_or_default();
+
+            profiles.push(OperatorProfile {
+                location: self.monitor.operator_location(&id),
+                id,
+                name: node.name(),
+                invocations,
+                time,
+                metadata,
+            });
+        });
+
+        profiles
+    }
+
     /// Dump profile in graphviz format.
     pub fn dump_profile(&self) -> String {
         let mut metadata = HashMap::<GlobalNodeId, OperatorMeta>::new();
@@ -0,0 +1,3 @@
+-- Per-webhook retry policy for failed deliveries (see `WebhookRetryPolicy`).
+ALTER TABLE webhook ADD COLUMN max_attempts integer NOT NULL DEFAULT 5;
+ALTER TABLE webhook ADD COLUMN initial_retry_delay_ms bigint NOT NULL DEFAULT 1000;
@@ -0,0 +1,4 @@
+-- Certificate served by the pipeline when its HTTP server is configured for
+-- TLS (see `TlsConfig`), used to verify the pipeline when forwarding
+-- requests to it.  NULL if the pipeline is served over plain HTTP.
+ALTER TABLE pipeline_runtime_state ADD COLUMN tls_certificate varchar;
@@ -0,0 +1,4 @@
+-- Bearer token that the pipeline requires from its clients, generated by
+-- the runner each time the pipeline is deployed and injected into requests
+-- forwarded to the pipeline.
+ALTER TABLE pipeline_runtime_state ADD COLUMN auth_token varchar;
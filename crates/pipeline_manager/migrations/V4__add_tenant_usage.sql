@@ -0,0 +1,18 @@
+-- Cumulative resource usage per tenant, used for chargeback.
+--
+-- Counters are only ever incremented: the compiler adds the wall-clock
+-- time of each compilation job, and pipeline automata periodically add
+-- the time pipelines spent running along with the number of bytes
+-- ingested and emitted by their endpoints.
+CREATE TABLE IF NOT EXISTS tenant_usage (
+    tenant_id uuid PRIMARY KEY,
+    -- Total time spent compiling programs, in milliseconds.
+    compile_time_ms bigint NOT NULL DEFAULT 0,
+    -- Total time pipelines were deployed, in milliseconds.
+    run_time_ms bigint NOT NULL DEFAULT 0,
+    -- Total number of bytes received by pipeline input endpoints.
+    ingress_bytes bigint NOT NULL DEFAULT 0,
+    -- Total number of bytes sent by pipeline output endpoints.
+    egress_bytes bigint NOT NULL DEFAULT 0,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
@@ -0,0 +1,42 @@
+-- Deleting a pipeline or a connector moves it to the trash instead of
+-- removing it from the database.  Objects in the trash are hidden from
+-- the API, can be restored, and are permanently removed by a background
+-- task once they have been in the trash for longer than the configured
+-- retention period.
+--
+-- `deleted_at` is the time (seconds since the epoch) when the object was
+-- moved to the trash, or NULL for live objects.
+ALTER TABLE pipeline
+ADD COLUMN deleted_at bigint;
+
+ALTER TABLE connector
+ADD COLUMN deleted_at bigint;
+
+-- History tables are populated using `SELECT *` from the corresponding
+-- table, so they must have the same columns.
+ALTER TABLE pipeline_history
+ADD COLUMN deleted_at bigint;
+
+ALTER TABLE connector_history
+ADD COLUMN deleted_at bigint;
+
+-- Names only need to be unique among live objects, so that an object can
+-- reuse the name of one in the trash.  Restoring an object fails if its
+-- name has been taken in the meantime.
+ALTER TABLE pipeline
+DROP CONSTRAINT unique_pipeline_name;
+CREATE UNIQUE INDEX unique_pipeline_name ON pipeline (tenant_id, name)
+WHERE deleted_at IS NULL;
+
+ALTER TABLE connector
+DROP CONSTRAINT unique_connector_name;
+CREATE UNIQUE INDEX unique_connector_name ON connector (tenant_id, name)
+WHERE deleted_at IS NULL;
+
+-- Attachments of connectors in the trash are kept, so that they come back
+-- when the connector is restored, but are hidden from pipelines.
+CREATE VIEW live_attached_connector AS
+SELECT ac.*
+FROM attached_connector ac
+INNER JOIN connector c ON ac.connector_id = c.id
+WHERE c.deleted_at IS NULL;
@@ -0,0 +1,12 @@
+-- Webhooks notified about pipeline lifecycle events.
+--
+-- `events` lists the names of the events the webhook is subscribed to
+-- (see `WebhookEvent`).  Payloads sent to `url` are signed with `secret`.
+CREATE TABLE IF NOT EXISTS webhook (
+    id uuid PRIMARY KEY,
+    tenant_id uuid NOT NULL,
+    url varchar NOT NULL,
+    secret varchar NOT NULL,
+    events varchar[] NOT NULL,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
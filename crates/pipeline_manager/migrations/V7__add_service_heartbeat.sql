@@ -0,0 +1,11 @@
+-- Background services (the compiler and the runner) periodically record a
+-- heartbeat, which the API server uses to report its readiness.  Services
+-- may run in separate processes, so the database is the only place where
+-- their liveness can be observed.
+--
+-- `last_seen` is the time (seconds since the epoch) of the most recent
+-- heartbeat.
+CREATE TABLE IF NOT EXISTS service_heartbeat (
+    service varchar PRIMARY KEY,
+    last_seen bigint NOT NULL
+);
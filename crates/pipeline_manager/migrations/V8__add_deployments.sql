@@ -0,0 +1,16 @@
+-- Groups of pipelines that are started and shut down together.
+--
+-- `pipelines` lists the pipelines of the deployment in the order in which
+-- they are started; they are shut down in reverse order.  When set, `config`
+-- is the runtime configuration (in YAML) shared by all pipelines of the
+-- deployment.
+CREATE TABLE IF NOT EXISTS deployment (
+    id uuid PRIMARY KEY,
+    tenant_id uuid NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    pipelines uuid[] NOT NULL,
+    config varchar,
+    UNIQUE(tenant_id, name),
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Warnings reported by the SQL compiler for the current version of the
+-- program, as a JSON array of `SqlCompilerMessage`s.  Errors are stored in
+-- the `error` column along with the `sql_error` status; warnings are stored
+-- separately since they are also reported for programs that compile
+-- successfully.
+ALTER TABLE program
+ADD COLUMN warnings varchar;
+
+-- History tables are populated using `SELECT *` from the corresponding
+-- table, so they must have the same columns.
+ALTER TABLE program_history
+ADD COLUMN warnings varchar;
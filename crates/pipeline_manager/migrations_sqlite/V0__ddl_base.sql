@@ -0,0 +1,224 @@
+-- Schema of the embedded SQLite database used in lite mode.
+--
+-- Mirrors the Postgres schema in `migrations/` after all of its migrations
+-- have been applied, with the following differences:
+--
+-- * UUIDs are stored as hyphenated strings.
+-- * Arrays are stored as JSON arrays.
+-- * Booleans are stored as integers.
+-- * `program.status_since` is stored in microseconds since the epoch, so
+--   that it orders the compilation queue as precisely as a Postgres
+--   timestamp.
+-- * Notifications are sent by calling the `notify` function, which the
+--   manager registers on its connection, instead of `pg_notify`.
+
+CREATE TABLE tenant (
+    id varchar PRIMARY KEY,
+    tenant varchar NOT NULL,
+    provider varchar NOT NULL,
+    UNIQUE (tenant, provider)
+);
+
+CREATE TABLE program (
+    id varchar PRIMARY KEY,
+    version bigint NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    code varchar NOT NULL,
+    schema varchar,
+    status varchar,
+    error varchar,
+    status_since bigint NOT NULL,
+    warnings varchar,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE,
+    CONSTRAINT unique_program_id UNIQUE (id, tenant_id),
+    CONSTRAINT unique_program_name UNIQUE (tenant_id, name)
+);
+CREATE TABLE program_history (
+    revision varchar,
+    id varchar NOT NULL,
+    version bigint NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    code varchar NOT NULL,
+    schema varchar,
+    status varchar,
+    error varchar,
+    status_since bigint NOT NULL,
+    warnings varchar,
+    PRIMARY KEY (id, revision),
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE,
+    CONSTRAINT unique_program_history_id UNIQUE (id, revision, tenant_id),
+    CONSTRAINT unique_program_history_name UNIQUE (tenant_id, revision, name)
+);
+
+CREATE TABLE pipeline (
+    id varchar PRIMARY KEY,
+    program_id varchar,
+    version bigint NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    config varchar NOT NULL,
+    last_revision varchar,
+    deleted_at bigint,
+    FOREIGN KEY (program_id, tenant_id) REFERENCES program(id, tenant_id)
+);
+CREATE UNIQUE INDEX unique_pipeline_name ON pipeline (tenant_id, name)
+WHERE deleted_at IS NULL;
+CREATE TABLE pipeline_history (
+    revision varchar,
+    id varchar NOT NULL,
+    program_id varchar,
+    version bigint NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    config varchar NOT NULL,
+    last_revision varchar,
+    deleted_at bigint,
+    PRIMARY KEY (id, revision),
+    CONSTRAINT unique_pipeline_history_name UNIQUE (tenant_id, revision, name)
+);
+
+CREATE TABLE connector (
+    id varchar PRIMARY KEY,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    config varchar NOT NULL,
+    deleted_at bigint,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+CREATE UNIQUE INDEX unique_connector_name ON connector (tenant_id, name)
+WHERE deleted_at IS NULL;
+CREATE TABLE connector_history (
+    revision varchar,
+    id varchar NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    config varchar NOT NULL,
+    deleted_at bigint,
+    PRIMARY KEY (id, revision),
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE,
+    CONSTRAINT unique_connector_history_name UNIQUE (tenant_id, revision, name)
+);
+
+CREATE TABLE attached_connector (
+    pipeline_id varchar NOT NULL,
+    connector_id varchar NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar,
+    config varchar,
+    is_input integer NOT NULL,
+    PRIMARY KEY (pipeline_id, name),
+    FOREIGN KEY (pipeline_id) REFERENCES pipeline(id) ON DELETE CASCADE,
+    FOREIGN KEY (connector_id) REFERENCES connector(id) ON DELETE CASCADE,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+CREATE TABLE attached_connector_history (
+    revision varchar,
+    pipeline_id varchar NOT NULL,
+    connector_id varchar NOT NULL,
+    tenant_id varchar NOT NULL,
+    name varchar,
+    config varchar,
+    is_input integer NOT NULL,
+    PRIMARY KEY (pipeline_id, name, revision),
+    FOREIGN KEY (pipeline_id, revision) REFERENCES pipeline_history(id, revision) ON DELETE CASCADE,
+    FOREIGN KEY (connector_id, revision) REFERENCES connector_history(id, revision) ON DELETE CASCADE,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+
+CREATE VIEW live_attached_connector AS
+SELECT ac.*
+FROM attached_connector ac
+INNER JOIN connector c ON ac.connector_id = c.id
+WHERE c.deleted_at IS NULL;
+
+CREATE TABLE api_key (
+    hash varchar PRIMARY KEY,
+    tenant_id varchar NOT NULL,
+    scopes varchar NOT NULL,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+
+CREATE TABLE pipeline_runtime_state (
+    id varchar PRIMARY KEY,
+    tenant_id varchar NOT NULL,
+    location varchar,
+    desired_status varchar NOT NULL,
+    current_status varchar NOT NULL,
+    status_since bigint NOT NULL,
+    error varchar,
+    created bigint NOT NULL,
+    tls_certificate varchar,
+    auth_token varchar,
+    FOREIGN KEY (id) REFERENCES pipeline(id) ON DELETE CASCADE
+);
+
+CREATE TABLE compiled_binary (
+    program_id varchar NOT NULL,
+    version bigint NOT NULL,
+    url varchar NOT NULL,
+    PRIMARY KEY (program_id, version),
+    FOREIGN KEY (program_id) REFERENCES program(id) ON DELETE CASCADE
+);
+
+CREATE TABLE tenant_usage (
+    tenant_id varchar PRIMARY KEY,
+    compile_time_ms bigint NOT NULL DEFAULT 0,
+    run_time_ms bigint NOT NULL DEFAULT 0,
+    ingress_bytes bigint NOT NULL DEFAULT 0,
+    egress_bytes bigint NOT NULL DEFAULT 0,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+
+CREATE TABLE webhook (
+    id varchar PRIMARY KEY,
+    tenant_id varchar NOT NULL,
+    url varchar NOT NULL,
+    secret varchar NOT NULL,
+    events varchar NOT NULL,
+    max_attempts integer NOT NULL DEFAULT 5,
+    initial_retry_delay_ms bigint NOT NULL DEFAULT 1000,
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+
+CREATE TABLE service_heartbeat (
+    service varchar PRIMARY KEY,
+    last_seen bigint NOT NULL
+);
+
+CREATE TABLE deployment (
+    id varchar PRIMARY KEY,
+    tenant_id varchar NOT NULL,
+    name varchar NOT NULL,
+    description varchar NOT NULL,
+    pipelines varchar NOT NULL,
+    config varchar,
+    UNIQUE (tenant_id, name),
+    FOREIGN KEY (tenant_id) REFERENCES tenant(id) ON DELETE CASCADE
+);
+
+-- Notifications for the reconciliation loops, in the same format as the
+-- Postgres `notification()` trigger function: <operation> <tenant_id> <id>.
+CREATE TRIGGER pipeline_insert_notify AFTER INSERT ON pipeline
+BEGIN SELECT notify('pipeline', 'A ' || NEW.tenant_id || ' ' || NEW.id); END;
+CREATE TRIGGER pipeline_update_notify AFTER UPDATE ON pipeline
+BEGIN SELECT notify('pipeline', 'U ' || NEW.tenant_id || ' ' || NEW.id); END;
+CREATE TRIGGER pipeline_delete_notify AFTER DELETE ON pipeline
+BEGIN SELECT notify('pipeline', 'D ' || OLD.tenant_id || ' ' || OLD.id); END;
+
+CREATE TRIGGER pipeline_runtime_state_update_notify AFTER UPDATE ON pipeline_runtime_state
+BEGIN SELECT notify('pipeline_runtime_state', 'U ' || NEW.tenant_id || ' ' || NEW.id); END;
+
+CREATE TRIGGER program_insert_notify AFTER INSERT ON program
+BEGIN SELECT notify('program', 'A ' || NEW.tenant_id || ' ' || NEW.id); END;
+CREATE TRIGGER program_update_notify AFTER UPDATE ON program
+BEGIN SELECT notify('program', 'U ' || NEW.tenant_id || ' ' || NEW.id); END;
+CREATE TRIGGER program_delete_notify AFTER DELETE ON program
+BEGIN SELECT notify('program', 'D ' || OLD.tenant_id || ' ' || OLD.id); END;
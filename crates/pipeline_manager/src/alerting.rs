@@ -0,0 +1,563 @@
+//! Alerting on pipeline and program failures.
+//!
+//! The alert monitor periodically evaluates a set of rules against the state
+//! of all pipelines and programs in the database and against statistics
+//! scraped from running pipelines.  When the condition of a rule starts to
+//! hold for some pipeline or program, the monitor sends an alert to all
+//! notification channels of the rule.  When the condition stops holding, it
+//! sends a message that the alert has been resolved.
+//!
+//! Rules and channels are configured in a YAML file passed to the api-server
+//! via `--alerting-config`:
+//!
+//! ```yaml
+//! poll_interval_secs: 30
+//! channels:
+//!   oncall:
+//!     type: pager_duty
+//!     routing_key: "..."
+//!   team:
+//!     type: slack
+//!     webhook_url: "https://hooks.slack.com/services/..."
+//! rules:
+//!   - name: pipeline crashed
+//!     condition:
+//!       type: pipeline_failed
+//!     channels: [oncall, team]
+//!   - name: input backlog
+//!     condition:
+//!       type: input_backlog
+//!       threshold: 1000000
+//!     channels: [team]
+//! ```
+use crate::{
+    auth::TenantId,
+    db::{storage::Storage, PipelineId, PipelineRuntimeState, PipelineStatus, ProjectDB},
+    runner::{pipeline_http_client, pipeline_url},
+};
+use anyhow::{Error as AnyError, Result as AnyResult};
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{io::AsyncWriteExt, process::Command, sync::Mutex};
+
+const fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_pager_duty_url() -> String {
+    "https://events.pagerduty.com/v2/enqueue".to_string()
+}
+
+fn default_sendmail_path() -> String {
+    "/usr/sbin/sendmail".to_string()
+}
+
+/// Timeout for scraping pipeline statistics and for delivering
+/// notifications.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Alerting configuration read from a YAML file.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AlertingConfig {
+    /// How often rules are evaluated.
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+
+    /// Notification channels by name.
+    #[serde(default)]
+    pub channels: BTreeMap<String, ChannelConfig>,
+
+    /// Alerting rules.
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+impl AlertingConfig {
+    /// Read and validate the configuration from a YAML file.
+    pub async fn from_file(path: &str) -> AnyResult<Self> {
+        let yaml = tokio::fs::read_to_string(path).await.map_err(|e| {
+            AnyError::msg(format!("error reading alerting config file '{path}': {e}"))
+        })?;
+        let config: Self = serde_yaml::from_str(&yaml).map_err(|e| {
+            AnyError::msg(format!("error parsing alerting config file '{path}': {e}"))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that rules only reference existing channels.
+    fn validate(&self) -> AnyResult<()> {
+        for rule in self.rules.iter() {
+            for channel in rule.channels.iter() {
+                if !self.channels.contains_key(channel) {
+                    return Err(AnyError::msg(format!(
+                        "alerting rule '{}' references unknown channel '{channel}'",
+                        rule.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A destination for alerts.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelConfig {
+    /// Post alerts to a Slack incoming webhook.
+    Slack { webhook_url: String },
+    /// Trigger and resolve PagerDuty incidents via the Events API v2.
+    PagerDuty {
+        routing_key: String,
+        #[serde(default = "default_pager_duty_url")]
+        events_url: String,
+    },
+    /// Send alerts by email using the local `sendmail` program.
+    Email {
+        from: String,
+        to: Vec<String>,
+        #[serde(default = "default_sendmail_path")]
+        sendmail_path: String,
+    },
+}
+
+/// A condition and the channels notified when it holds.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertRule {
+    /// Rule name, included in alerts.
+    pub name: String,
+    /// Only apply the rule to objects of this tenant.  Applies to all
+    /// tenants by default.
+    #[serde(default)]
+    pub tenant_id: Option<TenantId>,
+    pub condition: AlertCondition,
+    /// Names of channels to notify.
+    pub channels: Vec<String>,
+}
+
+/// Alert conditions.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// A pipeline is in the `Failed` state.
+    PipelineFailed,
+    /// The latest version of a program failed to compile.
+    CompilationFailed,
+    /// The number of input records buffered by a running pipeline exceeds
+    /// `threshold`, i.e., the pipeline is falling behind its inputs.
+    InputBacklog { threshold: u64 },
+}
+
+/// An object the condition of a rule holds for.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Firing {
+    rule: usize,
+    /// Unique identifier of the object, e.g., the pipeline id.
+    object: String,
+}
+
+/// Facts about the system that rules are evaluated against.
+#[derive(Default)]
+struct Snapshot {
+    /// Failed pipelines: (tenant, pipeline id, error message).
+    failed_pipelines: Vec<(TenantId, String, String)>,
+    /// Programs that failed to compile: (tenant, program name).
+    failed_programs: Vec<(TenantId, String)>,
+    /// Number of buffered input records of running pipelines:
+    /// (tenant, pipeline id, buffered records).
+    backlogs: Vec<(TenantId, String, u64)>,
+}
+
+struct AlertMonitor {
+    config: AlertingConfig,
+    client: reqwest::Client,
+    /// Alerts that have been sent and not yet resolved.
+    active: BTreeMap<Firing, String>,
+}
+
+/// Run the alert monitor until the process exits.
+pub(crate) async fn run(db: Arc<Mutex<ProjectDB>>, config: AlertingConfig) {
+    if config.rules.is_empty() {
+        return;
+    }
+    info!("Alert monitor started with {} rules", config.rules.len());
+    let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs));
+    let mut monitor = AlertMonitor {
+        config,
+        client: reqwest::Client::new(),
+        active: BTreeMap::new(),
+    };
+    loop {
+        interval.tick().await;
+        match monitor.snapshot(&db).await {
+            Ok(snapshot) => monitor.evaluate(&snapshot).await,
+            Err(e) => error!("Alert monitor failed to read pipeline state: {e}"),
+        }
+    }
+}
+
+impl AlertMonitor {
+    fn needs(&self, pred: impl Fn(&AlertCondition) -> bool) -> bool {
+        self.config.rules.iter().any(|r| pred(&r.condition))
+    }
+
+    async fn snapshot(&self, db: &Arc<Mutex<ProjectDB>>) -> AnyResult<Snapshot> {
+        let mut snapshot = Snapshot::default();
+        let mut running = Vec::new();
+
+        {
+            let db = db.lock().await;
+            for (tenant_id, pipeline_id) in db.all_pipelines().await? {
+                let state = db
+                    .get_pipeline_runtime_state(tenant_id, pipeline_id)
+                    .await?;
+                match state.current_status {
+                    PipelineStatus::Failed => {
+                        let error = state
+                            .error
+                            .map(|e| e.message)
+                            .unwrap_or_else(|| "unknown error".to_string());
+                        snapshot
+                            .failed_pipelines
+                            .push((tenant_id, pipeline_id.to_string(), error));
+                    }
+                    PipelineStatus::Running | PipelineStatus::Paused => {
+                        running.push((tenant_id, pipeline_id, state));
+                    }
+                    _ => {}
+                }
+            }
+            if self.needs(|c| *c == AlertCondition::CompilationFailed) {
+                for (tenant_id, program) in db.all_programs().await? {
+                    if program.status.has_failed_to_compile() {
+                        snapshot.failed_programs.push((tenant_id, program.name));
+                    }
+                }
+            }
+        }
+
+        // Scrape statistics without holding the database lock.
+        if self.needs(|c| matches!(c, AlertCondition::InputBacklog { .. })) {
+            for (tenant_id, pipeline_id, state) in running {
+                match self.buffered_input_records(pipeline_id, &state).await {
+                    Ok(records) => {
+                        snapshot
+                            .backlogs
+                            .push((tenant_id, pipeline_id.to_string(), records))
+                    }
+                    Err(e) => warn!("Failed to scrape statistics of pipeline {pipeline_id}: {e}"),
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    async fn buffered_input_records(
+        &self,
+        pipeline_id: PipelineId,
+        state: &PipelineRuntimeState,
+    ) -> AnyResult<u64> {
+        // Use a client that trusts the pipeline's certificate and presents
+        // its token.
+        let stats: JsonValue = pipeline_http_client(pipeline_id, state)?
+            .get(pipeline_url(state, "stats"))
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?
+            .json()
+            .await?;
+        stats["global_metrics"]["buffered_input_records"]
+            .as_u64()
+            .ok_or_else(|| AnyError::msg("statistics do not report buffered input records"))
+    }
+
+    /// Objects the condition of each rule currently holds for, along with
+    /// a description of the problem.
+    fn firing(&self, snapshot: &Snapshot) -> BTreeMap<Firing, String> {
+        let mut result = BTreeMap::new();
+        for (index, rule) in self.config.rules.iter().enumerate() {
+            let applies = |tenant_id: &TenantId| rule.tenant_id.map_or(true, |t| t == *tenant_id);
+            let mut fire = |object: String, message: String| {
+                result.insert(
+                    Firing {
+                        rule: index,
+                        object,
+                    },
+                    message,
+                );
+            };
+            match &rule.condition {
+                AlertCondition::PipelineFailed => {
+                    for (tenant_id, pipeline_id, error) in snapshot.failed_pipelines.iter() {
+                        if applies(tenant_id) {
+                            fire(
+                                format!("pipeline:{pipeline_id}"),
+                                format!(
+                                    "Pipeline {pipeline_id} (tenant {tenant_id}) failed: {error}"
+                                ),
+                            );
+                        }
+                    }
+                }
+                AlertCondition::CompilationFailed => {
+                    for (tenant_id, program) in snapshot.failed_programs.iter() {
+                        if applies(tenant_id) {
+                            fire(
+                                format!("program:{tenant_id}:{program}"),
+                                format!(
+                                    "Program '{program}' (tenant {tenant_id}) failed to compile"
+                                ),
+                            );
+                        }
+                    }
+                }
+                AlertCondition::InputBacklog { threshold } => {
+                    for (tenant_id, pipeline_id, records) in snapshot.backlogs.iter() {
+                        if applies(tenant_id) && records > threshold {
+                            fire(
+                                format!("pipeline:{pipeline_id}"),
+                                format!("Pipeline {pipeline_id} (tenant {tenant_id}) has {records} buffered input records, above the threshold of {threshold}"),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    async fn evaluate(&mut self, snapshot: &Snapshot) {
+        let firing = self.firing(snapshot);
+
+        let resolved: Vec<(Firing, String)> = self
+            .active
+            .iter()
+            .filter(|(f, _)| !firing.contains_key(f))
+            .map(|(f, m)| (f.clone(), m.clone()))
+            .collect();
+        for (f, message) in resolved {
+            self.active.remove(&f);
+            self.notify(&f, false, &format!("Resolved: {message}"))
+                .await;
+        }
+
+        let triggered: BTreeSet<Firing> = firing
+            .keys()
+            .filter(|f| !self.active.contains_key(f))
+            .cloned()
+            .collect();
+        for f in triggered {
+            let message = firing[&f].clone();
+            self.notify(&f, true, &message).await;
+            self.active.insert(f, message);
+        }
+    }
+
+    async fn notify(&self, firing: &Firing, trigger: bool, message: &str) {
+        let rule = &self.config.rules[firing.rule];
+        let summary = format!("[{}] {message}", rule.name);
+        info!("Alert: {summary}");
+        for name in rule.channels.iter() {
+            let channel = &self.config.channels[name];
+            let dedup_key = format!("{}:{}", rule.name, firing.object);
+            if let Err(e) = self.send(channel, trigger, &dedup_key, &summary).await {
+                error!("Failed to send alert to channel '{name}': {e}");
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        channel: &ChannelConfig,
+        trigger: bool,
+        dedup_key: &str,
+        summary: &str,
+    ) -> AnyResult<()> {
+        match channel {
+            ChannelConfig::Slack { webhook_url } => {
+                self.client
+                    .post(webhook_url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .json(&json!({ "text": summary }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            ChannelConfig::PagerDuty {
+                routing_key,
+                events_url,
+            } => {
+                let event = if trigger {
+                    json!({
+                        "routing_key": routing_key,
+                        "event_action": "trigger",
+                        "dedup_key": dedup_key,
+                        "payload": {
+                            "summary": summary,
+                            "source": "feldera-pipeline-manager",
+                            "severity": "error",
+                        },
+                    })
+                } else {
+                    json!({
+                        "routing_key": routing_key,
+                        "event_action": "resolve",
+                        "dedup_key": dedup_key,
+                    })
+                };
+                self.client
+                    .post(events_url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .json(&event)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            ChannelConfig::Email {
+                from,
+                to,
+                sendmail_path,
+            } => {
+                // Pass recipients as arguments rather than having sendmail
+                // extract them from headers (`-t`), so that alert contents
+                // can never add recipients.  `-i` keeps a line consisting of
+                // a single `.` in the message from ending it early.
+                let mut child = Command::new(sendmail_path)
+                    .arg("-i")
+                    .arg("--")
+                    .args(to)
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                let mail = email_message(from, to, summary);
+                let mut stdin = child.stdin.take().unwrap();
+                stdin.write_all(mail.as_bytes()).await?;
+                drop(stdin);
+                let status = child.wait().await?;
+                if !status.success() {
+                    return Err(AnyError::msg(format!("sendmail exited with {status}")));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format an email alert.  Messages can include arbitrary text, e.g., error
+/// messages, so line breaks are stripped from header values to prevent them
+/// from injecting headers.
+fn email_message(from: &str, to: &[String], summary: &str) -> String {
+    format!(
+        "From: {}\nTo: {}\nSubject: {}\n\n{summary}\n",
+        header_value(from),
+        header_value(&to.join(", ")),
+        header_value(summary)
+    )
+}
+
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        email_message, AlertCondition, AlertMonitor, AlertingConfig, ChannelConfig, Snapshot,
+    };
+    use crate::auth::TenantRecord;
+    use std::collections::BTreeMap;
+
+    const CONFIG: &str = r#"
+channels:
+  team:
+    type: slack
+    webhook_url: "http://localhost/slack"
+  oncall:
+    type: pager_duty
+    routing_key: "key"
+rules:
+  - name: crashed
+    condition:
+      type: pipeline_failed
+    channels: [oncall]
+  - name: backlog
+    condition:
+      type: input_backlog
+      threshold: 100
+    channels: [team]
+"#;
+
+    fn monitor() -> AlertMonitor {
+        let config: AlertingConfig = serde_yaml::from_str(CONFIG).unwrap();
+        config.validate().unwrap();
+        AlertMonitor {
+            config,
+            client: reqwest::Client::new(),
+            active: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn parse_config() {
+        let monitor = monitor();
+        assert_eq!(monitor.config.poll_interval_secs, 30);
+        assert!(matches!(
+            monitor.config.channels["oncall"],
+            ChannelConfig::PagerDuty { .. }
+        ));
+        assert_eq!(
+            monitor.config.rules[1].condition,
+            AlertCondition::InputBacklog { threshold: 100 }
+        );
+
+        let mut config = monitor.config;
+        config.rules[0].channels.push("unknown".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn firing() {
+        let monitor = monitor();
+        let tenant_id = TenantRecord::default().id;
+        let snapshot = Snapshot {
+            failed_pipelines: vec![(tenant_id, "p1".to_string(), "boom".to_string())],
+            failed_programs: vec![(tenant_id, "prog".to_string())],
+            backlogs: vec![
+                (tenant_id, "p2".to_string(), 50),
+                (tenant_id, "p3".to_string(), 500),
+            ],
+        };
+        let firing = monitor.firing(&snapshot);
+        let objects: Vec<_> = firing.keys().map(|f| (f.rule, f.object.as_str())).collect();
+        // No rule for compilation failures; p2 is below the threshold.
+        assert_eq!(objects, vec![(0, "pipeline:p1"), (1, "pipeline:p3")]);
+    }
+
+    #[test]
+    fn email_headers() {
+        let mail = email_message(
+            "alerts@example.com",
+            &[
+                "a@example.com".to_string(),
+                "b@example.com\nBcc: c@example.com".to_string(),
+            ],
+            "[crashed] boom\r\nBcc: d@example.com",
+        );
+        assert_eq!(
+            mail,
+            "From: alerts@example.com\n\
+             To: a@example.com, b@example.com Bcc: c@example.com\n\
+             Subject: [crashed] boom  Bcc: d@example.com\n\
+             \n\
+             [crashed] boom\r\nBcc: d@example.com\n"
+        );
+    }
+}
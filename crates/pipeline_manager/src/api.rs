@@ -32,7 +32,7 @@ use actix_web::{
         Method,
     },
     middleware::{Condition, Logger},
-    patch, post,
+    patch, post, put,
     web::Data as WebData,
     web::{self, ReqData},
     App, HttpRequest, HttpResponse, HttpServer,
@@ -41,12 +41,12 @@ use actix_web_httpauth::middleware::HttpAuthentication;
 use actix_web_static_files::ResourceFiles;
 use anyhow::{Error as AnyError, Result as AnyResult};
 use dbsp_adapters::{
-    ConnectorConfig, ControllerError, ErrorResponse, ParseError, PipelineConfig, PipelineError,
-    RuntimeConfig,
+    ConnectorConfig, ControllerError, ErrorResponse, InputEndpointConfig, OutputEndpointConfig,
+    OutputQuery, ParseError, PipelineConfig, PipelineError, RelationDescr, RuntimeConfig,
 };
-use log::{debug, info};
+use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
-use std::{env, net::TcpListener, sync::Arc, time::Duration};
+use std::{borrow::Cow, env, net::TcpListener, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 use utoipa::{openapi::Server, IntoParams, Modify, OpenApi, ToSchema};
 use utoipa_swagger_ui::SwaggerUi;
@@ -55,10 +55,13 @@ use uuid::{uuid, Uuid};
 pub(crate) use crate::compiler::ProgramStatus;
 pub(crate) use crate::config::ApiServerConfig;
 use crate::db::{
-    storage::Storage, AttachedConnector, AttachedConnectorId, ConnectorId, DBError, PipelineId,
-    PipelineRevision, PipelineStatus, ProgramDescr, ProgramId, ProjectDB, Version,
+    storage::Storage, AttachedConnector, AttachedConnectorId, CompilationJob, ConnectorId, DBError,
+    DeploymentDescr, DeploymentId, PipelineId, PipelineRevision, PipelineStatus, ProgramDescr,
+    ProgramId, ProjectDB, TenantUsage, Version, WebhookDescr, WebhookEvent, WebhookId,
+    WebhookRetryPolicy,
 };
 pub use crate::error::ManagerError;
+use crate::metrics::{track_request, ManagerMetrics};
 use crate::runner::{RunnerApi, RunnerError};
 
 use crate::auth::TenantId;
@@ -131,62 +134,154 @@ request is rejected."
         update_program,
         compile_program,
         delete_program,
+        compiler_queue,
         new_pipeline,
         update_pipeline,
         list_pipelines,
         pipeline_stats,
+        pipeline_column_stats,
+        pipeline_catalog,
+        pipeline_explain_analyze,
+        pipeline_profile,
+        input_endpoint_action,
+        reconfigure_input_endpoint,
+        output_endpoint_action,
+        attach_connector,
+        detach_connector,
         get_pipeline,
         get_pipeline_config,
         pipeline_validate,
         pipeline_action,
         pipeline_deployed,
         pipeline_delete,
+        list_deleted_pipelines,
+        restore_pipeline,
         list_connectors,
         get_connector,
         new_connector,
         update_connector,
         delete_connector,
+        list_deleted_connectors,
+        restore_connector,
         http_input,
         http_output,
+        view_sample,
+        get_usage,
+        apply,
+        list_webhooks,
+        new_webhook,
+        delete_webhook,
+        list_deployments,
+        get_deployment,
+        new_deployment,
+        delete_deployment,
+        deployment_action,
     ),
     components(schemas(
         crate::compiler::SqlCompilerMessage,
         crate::db::AttachedConnector,
         crate::db::ProgramDescr,
         crate::db::ProgramSchema,
+        crate::db::CompilationJob,
         crate::db::Relation,
         crate::db::Field,
         crate::db::ColumnType,
         crate::db::ConnectorDescr,
+        crate::db::DeletedConnector,
+        crate::db::DeletedPipeline,
         crate::db::Pipeline,
         crate::db::PipelineRuntimeState,
         crate::db::PipelineDescr,
         crate::db::PipelineRevision,
         crate::db::Revision,
         crate::db::PipelineStatus,
+        crate::db::TenantUsage,
         dbsp_adapters::EgressMode,
         dbsp_adapters::PipelineConfig,
         dbsp_adapters::InputEndpointConfig,
+        dbsp_adapters::ColumnMappingConfig,
+        dbsp_adapters::ColumnCast,
+        dbsp_adapters::ColumnPredicate,
+        dbsp_adapters::DedupConfig,
+        dbsp_adapters::LineageConfig,
+        dbsp_adapters::PredicateOp,
         dbsp_adapters::NeighborhoodQuery,
         dbsp_adapters::OutputEndpointConfig,
         dbsp_adapters::OutputQuery,
+        dbsp_adapters::RelationDescr,
+        dbsp_adapters::RelationKind,
+        dbsp_adapters::ColumnDescr,
         dbsp_adapters::TransportConfig,
         dbsp_adapters::FormatConfig,
         dbsp_adapters::RuntimeConfig,
         dbsp_adapters::ConnectorConfig,
+        dbsp_adapters::RetryConfig,
+        dbsp_adapters::ParserSharding,
+        dbsp_adapters::ErrorPolicy,
+        dbsp_adapters::OverflowPolicy,
+        dbsp_adapters::CheckpointConfig,
+        dbsp_adapters::ReplayConfig,
+        dbsp_adapters::ReplayMode,
+        dbsp_adapters::TracingConfig,
+        dbsp_adapters::TlsConfig,
+        dbsp_adapters::ControllerStatus,
+        dbsp_adapters::GlobalControllerMetrics,
+        dbsp_adapters::InputEndpointStatus,
+        dbsp_adapters::InputEndpointMetrics,
+        dbsp_adapters::OutputEndpointStatus,
+        dbsp_adapters::OutputEndpointMetrics,
+        dbsp_adapters::PipelineState,
         dbsp_adapters::TransportConfig,
         dbsp_adapters::FormatConfig,
         dbsp_adapters::transport::FileInputConfig,
         dbsp_adapters::transport::FileOutputConfig,
+        dbsp_adapters::transport::FileCompression,
+        dbsp_adapters::transport::SkewInputConfig,
+        dbsp_adapters::transport::S3InputConfig,
+        dbsp_adapters::transport::ObjectStoreProvider,
+        dbsp_adapters::transport::ObjectCompression,
+        dbsp_adapters::transport::WebSocketInputConfig,
         dbsp_adapters::transport::KafkaInputConfig,
         dbsp_adapters::transport::KafkaOutputConfig,
         dbsp_adapters::transport::KafkaLogLevel,
+        dbsp_adapters::transport::KafkaStartOffset,
+        dbsp_adapters::transport::SchemaRegistryConfig,
+        dbsp_adapters::transport::SubjectNameStrategy,
+        dbsp_adapters::transport::MqttInputConfig,
+        dbsp_adapters::transport::MqttQos,
+        dbsp_adapters::transport::MqttProtocolVersion,
+        dbsp_adapters::transport::PostgresCdcInputConfig,
+        dbsp_adapters::transport::PostgresOutputConfig,
+        dbsp_adapters::transport::SnowflakeOutputConfig,
+        dbsp_adapters::transport::SnowflakeTokenType,
+        dbsp_adapters::transport::BigQueryOutputConfig,
         dbsp_adapters::transport::http::Chunk,
+        dbsp_adapters::transport::http::ChunkMarker,
+        dbsp_adapters::format::ArrowEncoderConfig,
+        dbsp_adapters::format::AutoParserConfig,
+        dbsp_adapters::format::AvroEncoderConfig,
+        dbsp_adapters::format::AvroParserConfig,
+        dbsp_adapters::format::AvroUpdateFormat,
+        dbsp_adapters::format::BinaryEncoding,
         dbsp_adapters::format::CsvEncoderConfig,
         dbsp_adapters::format::CsvParserConfig,
+        dbsp_adapters::format::DateTimeFormat,
+        dbsp_adapters::format::DecimalEncoding,
+        dbsp_adapters::format::DecimalFormat,
+        dbsp_adapters::format::FixedWidthColumn,
         dbsp_adapters::format::JsonEncoderConfig,
+        dbsp_adapters::format::JsonLayout,
         dbsp_adapters::format::JsonParserConfig,
         dbsp_adapters::format::JsonUpdateFormat,
+        dbsp_adapters::format::ParquetCompression,
+        dbsp_adapters::format::ParquetEncoderConfig,
+        dbsp_adapters::format::ParquetOutputMode,
+        dbsp_adapters::format::ProtobufConfig,
+        dbsp_adapters::format::RawEncoding,
+        dbsp_adapters::format::RawParserConfig,
+        dbsp_adapters::format::SqlEncoderConfig,
+        dbsp_adapters::format::TextLayout,
+        dbsp_adapters::format::TextParserConfig,
         TenantId,
         ProgramId,
         PipelineId,
@@ -209,11 +304,36 @@ request is rejected."
         NewConnectorResponse,
         UpdateConnectorRequest,
         UpdateConnectorResponse,
+        ProfileFormat,
+        crate::apply::Manifest,
+        crate::apply::ProgramSpec,
+        crate::apply::ConnectorSpec,
+        crate::apply::PipelineSpec,
+        crate::apply::AttachedConnectorSpec,
+        crate::apply::Change,
+        crate::apply::ObjectKind,
+        crate::apply::ChangeAction,
+        ApplyResponse,
+        crate::db::WebhookDescr,
+        crate::db::WebhookEvent,
+        crate::db::WebhookRetryPolicy,
+        WebhookId,
+        NewWebhookRequest,
+        NewWebhookResponse,
+        DeploymentDescr,
+        DeploymentId,
+        NewDeploymentRequest,
+        NewDeploymentResponse,
     ),),
     tags(
         (name = "Programs", description = "Manage programs"),
         (name = "Pipelines", description = "Manage pipelines"),
         (name = "Connectors", description = "Manage data connectors"),
+        (name = "Usage", description = "Resource usage accounting"),
+        (name = "Trash", description = "Restore deleted pipelines and connectors"),
+        (name = "Apply", description = "Declarative provisioning"),
+        (name = "Webhooks", description = "Program and pipeline lifecycle notifications"),
+        (name = "Deployments", description = "Start and stop groups of pipelines together"),
     ),
 )]
 pub struct ApiDoc;
@@ -226,6 +346,7 @@ pub(crate) struct ServerState {
     runner: RunnerApi,
     _config: ApiServerConfig,
     pub jwk_cache: Arc<Mutex<JwkCache>>,
+    metrics: Arc<ManagerMetrics>,
 }
 
 impl ServerState {
@@ -237,6 +358,7 @@ impl ServerState {
             runner,
             _config: config,
             jwk_cache: Arc::new(Mutex::new(JwkCache::new())),
+            metrics: Arc::new(ManagerMetrics::new()?),
         })
     }
 }
@@ -254,39 +376,75 @@ fn create_listener(api_config: &ApiServerConfig) -> AnyResult<TcpListener> {
     Ok(listener)
 }
 
+/// How often the trash is checked for objects past their retention period.
+const TRASH_PURGE_PERIOD: Duration = Duration::from_secs(3600);
+
+/// Periodically purge pipelines and connectors that have been in the trash
+/// for longer than `retention_days`.
+async fn purge_trash(db: Arc<Mutex<ProjectDB>>, retention_days: u32) {
+    let mut interval = tokio::time::interval(TRASH_PURGE_PERIOD);
+    loop {
+        interval.tick().await;
+        let deleted_before = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+        match db.lock().await.purge_deleted(deleted_before).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {purged} objects from the trash"),
+            Err(e) => error!("Failed to purge the trash: {e}"),
+        }
+    }
+}
+
 pub async fn run(db: Arc<Mutex<ProjectDB>>, api_config: ApiServerConfig) -> AnyResult<()> {
     let listener = create_listener(&api_config)?;
+    tokio::spawn(purge_trash(db.clone(), api_config.trash_retention_days));
+    if let Some(alerting_config) = &api_config.alerting_config {
+        let alerting_config = crate::alerting::AlertingConfig::from_file(alerting_config).await?;
+        tokio::spawn(crate::alerting::run(db.clone(), alerting_config));
+    }
     let state = WebData::new(ServerState::new(api_config.clone(), db).await?);
+    let metrics = state.metrics.clone();
     let server = if api_config.use_auth {
         let server = HttpServer::new(move || {
             let auth_middleware = HttpAuthentication::with_fn(crate::auth::auth_validator);
             let auth_configuration = crate::auth::aws_auth_config();
 
+            let metrics = metrics.clone();
             App::new()
                 .app_data(state.clone())
                 .app_data(auth_configuration)
+                .wrap_fn(move |req, srv| track_request(metrics.clone(), req, srv))
                 .wrap(Logger::default())
                 .wrap(Condition::new(
                     api_config.dev_mode,
                     actix_cors::Cors::permissive(),
                 ))
+                .wrap_fn(|req, srv| crate::logging::with_request_context(req, srv))
                 .service(api_scope().wrap(auth_middleware))
+                .service(healthz)
+                .service(readyz)
+                .service(metrics_endpoint)
                 .service(static_website_scope())
         });
         server.listen(listener)?.run()
     } else {
         let server = HttpServer::new(move || {
+            let metrics = metrics.clone();
             App::new()
                 .app_data(state.clone())
+                .wrap_fn(move |req, srv| track_request(metrics.clone(), req, srv))
                 .wrap(Logger::default())
                 .wrap(Condition::new(
                     api_config.dev_mode,
                     actix_cors::Cors::permissive(),
                 ))
+                .wrap_fn(|req, srv| crate::logging::with_request_context(req, srv))
                 .service(api_scope().wrap_fn(|req, srv| {
                     let req = crate::auth::tag_with_default_tenant_id(req);
                     srv.call(req)
                 }))
+                .service(healthz)
+                .service(readyz)
+                .service(metrics_endpoint)
                 .service(static_website_scope())
         });
         server.listen(listener)?.run()
@@ -314,6 +472,44 @@ Documentation: https://www.feldera.com/docs/
     Ok(())
 }
 
+/// Liveness probe.  Succeeds as long as the API server is able to handle
+/// requests.  Does not require authentication.
+#[get("/healthz")]
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness probe.  Succeeds if the database is reachable and the compiler
+/// and the runner are alive; responds with `503 Service Unavailable` and a
+/// report of the failed checks otherwise.  Does not require authentication.
+#[get("/readyz")]
+async fn readyz(state: WebData<ServerState>) -> HttpResponse {
+    let report = crate::health::readiness(&state.db).await;
+    let mut response = if report.ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(report)
+}
+
+/// Metrics of the manager process in the Prometheus text format: request
+/// latencies per route, database connection pool utilization, compilation
+/// queue depth, and the number of pipelines in each status.  Does not require
+/// authentication.
+#[get("/metrics")]
+async fn metrics_endpoint(state: WebData<ServerState>) -> Result<HttpResponse, ManagerError> {
+    let metrics = state.metrics.gather(&state.db).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics))
+}
+
 // `static_files` magic.
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
@@ -339,23 +535,48 @@ fn api_scope() -> Scope {
         .service(update_program)
         .service(compile_program)
         .service(delete_program)
+        .service(compiler_queue)
         .service(new_pipeline)
         .service(update_pipeline)
         .service(list_pipelines)
         .service(pipeline_stats)
+        .service(pipeline_column_stats)
+        .service(pipeline_catalog)
+        .service(pipeline_explain_analyze)
+        .service(pipeline_profile)
+        .service(input_endpoint_action)
+        .service(reconfigure_input_endpoint)
+        .service(output_endpoint_action)
+        .service(attach_connector)
+        .service(detach_connector)
         .service(get_pipeline)
         .service(get_pipeline_config)
         .service(pipeline_action)
         .service(pipeline_validate)
         .service(pipeline_deployed)
         .service(pipeline_delete)
+        .service(list_deleted_pipelines)
+        .service(restore_pipeline)
         .service(list_connectors)
         .service(get_connector)
         .service(new_connector)
         .service(update_connector)
         .service(delete_connector)
+        .service(list_deleted_connectors)
+        .service(restore_connector)
         .service(http_input)
         .service(http_output)
+        .service(view_sample)
+        .service(get_usage)
+        .service(apply)
+        .service(list_webhooks)
+        .service(new_webhook)
+        .service(delete_webhook)
+        .service(list_deployments)
+        .service(get_deployment)
+        .service(new_deployment)
+        .service(delete_deployment)
+        .service(deployment_action)
 }
 
 // Example errors for use in OpenApi docs.
@@ -485,7 +706,7 @@ fn example_parse_errors() -> ErrorResponse {
         ParseError::text_envelope_error("failed to parse string as a JSON document: EOF while parsing a value at line 1 column 27".to_string(), "{\"b\": false, \"i\": 100, \"s\":", None),
         ParseError::text_event_error("failed to deserialize JSON record '{\"b\": false}'", "missing field `i` at line 3 column 12", 3, Some("{\"b\": false}"), None),
     ];
-    ErrorResponse::from_error_nolog(&PipelineError::parse_errors(errors.len(), errors.iter()))
+    ErrorResponse::from_error_nolog(&PipelineError::parse_errors(5, errors.len(), errors.iter()))
 }
 
 fn example_unknown_output_format() -> ErrorResponse {
@@ -942,6 +1163,26 @@ async fn do_delete_program(
         .map(|_| HttpResponse::Ok().finish())
 }
 
+/// Fetch the compilation queue.
+///
+/// Lists programs that are waiting to be compiled or are being compiled,
+/// across all tenants, in the order in which they entered their current
+/// phase.  Pending jobs are picked up by the compiler in this order.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Compilation queue retrieved successfully.", body = [CompilationJob])
+    ),
+    tag = "Programs"
+)]
+#[get("/compiler/queue")]
+async fn compiler_queue(state: WebData<ServerState>) -> Result<HttpResponse, ManagerError> {
+    let jobs = state.db.lock().await.list_compilation_jobs().await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(jobs))
+}
+
 /// Request to create a new pipeline.
 #[derive(Debug, Deserialize, ToSchema)]
 struct NewPipelineRequest {
@@ -1178,11 +1419,13 @@ async fn pipeline_deployed(
 }
 
 /// Retrieve pipeline metrics and performance counters.
+///
+/// Includes global metrics as well as throughput, buffered records, error
+/// counts, and source lag (where reported by the transport) of each input
+/// and output endpoint.
 #[utoipa::path(
     responses(
-        // TODO: Implement `ToSchema` for `ControllerStatus`, which is the
-        // actual type returned by this endpoint.
-        (status = OK, description = "Pipeline metrics retrieved successfully.", body = Object),
+        (status = OK, description = "Pipeline metrics retrieved successfully.", body = ControllerStatus),
         (status = BAD_REQUEST
             , description = "Specified pipeline id is not a valid uuid."
             , body = ErrorResponse
@@ -1211,55 +1454,74 @@ async fn pipeline_stats(
         .await
 }
 
-/// Fetch a pipeline by ID.
+/// Retrieve per-column statistics of a table or view.
+///
+/// Returns the number of records in the table or view along with the
+/// fraction of `NULL` values, smallest and largest value, and an estimate of
+/// the number of distinct values in each column.  Statistics are only
+/// maintained for tables and views listed in the `column_statistics`
+/// field of the pipeline's runtime configuration.
 #[utoipa::path(
     responses(
-        (status = OK, description = "Pipeline descriptor retrieved successfully.",content(
-            ("application/json" = Pipeline, example = json!(example_pipeline_config())),
-        )),
+        (status = OK, description = "Column statistics retrieved successfully.", body = Object),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
-            , description = "Specified pipeline ID does not exist."
+            , description = "Specified pipeline id does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
+        (status = NOT_FOUND
+            , description = "Column statistics are not enabled for the specified table or view."
+            , body = ErrorResponse),
     ),
     params(
         ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("view_name" = String, Path, description = "SQL table or view name."),
     ),
     tag = "Pipelines"
 )]
-#[get("/pipelines/{pipeline_id}")]
-async fn get_pipeline(
+#[get("/pipelines/{pipeline_id}/views/{view_name}/column_stats")]
+async fn pipeline_column_stats(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ManagerError> {
     let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
-    let pipeline: crate::db::Pipeline = state
-        .db
-        .lock()
+
+    let view_name = match req.match_info().get("view_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam { param: "view_name" });
+        }
+        Some(view_name) => view_name,
+    };
+
+    state
+        .runner
+        .forward_to_pipeline(
+            *tenant_id,
+            pipeline_id,
+            Method::GET,
+            &format!("views/{view_name}/column_stats"),
+        )
         .await
-        .get_pipeline_by_id(*tenant_id, pipeline_id)
-        .await?;
-    Ok(HttpResponse::Ok()
-        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&pipeline))
 }
 
-/// Fetch a pipeline's configuration.
+/// Retrieve the tables and views of a running pipeline.
 ///
-/// When defining a pipeline, clients have to provide an optional
-/// `RuntimeConfig` for the pipelines and references to existing
-/// connectors to attach to the pipeline. This endpoint retrieves
-/// the *expanded* definition of the pipeline's configuration,
-/// which comprises both the `RuntimeConfig` and the complete
-/// definitions of the attached connectors.
+/// Returns the name of each table and view, its columns with their SQL types,
+/// and the queries (`table`, `neighborhood`, `quantiles`, `sample`) that the
+/// `egress` endpoint supports for it.
 #[utoipa::path(
     responses(
-        (status = OK, description = "Expanded pipeline configuration retrieved successfully.",content(
-            ("application/json" = PipelineConfig),
-        )),
+        (status = OK, description = "Pipeline catalog retrieved successfully.", body = [RelationDescr]),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
-            , description = "Specified pipeline ID does not exist."
+            , description = "Specified pipeline id does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
     ),
@@ -1268,627 +1530,1867 @@ async fn get_pipeline(
     ),
     tag = "Pipelines"
 )]
-#[get("/pipelines/{pipeline_id}/config")]
-async fn get_pipeline_config(
+#[get("/pipelines/{pipeline_id}/catalog")]
+async fn pipeline_catalog(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ManagerError> {
     let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
-    let expanded_config = state
-        .db
-        .lock()
+
+    state
+        .runner
+        .forward_to_pipeline(*tenant_id, pipeline_id, Method::GET, "catalog")
         .await
-        .pipeline_config(*tenant_id, pipeline_id)
-        .await?;
-    Ok(HttpResponse::Ok()
-        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&expanded_config))
 }
 
-/// Validate a pipeline.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExplainAnalyzeQuery {
+    /// Length of the measurement interval in seconds (default: 10, maximum:
+    /// 300).
+    duration_secs: Option<u64>,
+}
+
+/// Profile a running pipeline and return its query plan annotated with
+/// measurements.
 ///
-/// Checks whether a pipeline is configured correctly. This includes
-/// checking whether the pipeline references a valid compiled program,
-/// whether the connectors reference valid tables/views in the program,
-/// and more.
+/// Runs the profiler for `duration_secs` seconds and returns the operators of
+/// the pipeline's circuit, ordered by CPU time, along with their evaluation
+/// rates, share of the total CPU time, state sizes, and the fragments of the
+/// SQL program they were compiled from.  The request completes after the
+/// measurement interval has elapsed.
 #[utoipa::path(
     responses(
-        (status = OK
-            , description = "Validate a Pipeline config."
-            , content_type = "application/json"
-            , body = String),
+        // TODO: Implement `ToSchema` for `ExplainAnalyze`, which is the
+        // actual type returned by this endpoint.
+        (status = OK, description = "Pipeline profiled successfully.", body = Object),
         (status = BAD_REQUEST
-            , description = "Invalid pipeline."
+            , description = "Specified pipeline id is not a valid uuid."
             , body = ErrorResponse
-            , examples(
-                ("Invalid Pipeline ID" = (description = "Specified pipeline id is not a valid uuid.", value = json!(example_invalid_uuid_param()))),
-                ("Program not set" = (description = "Pipeline does not have a program set.", value = json!(example_program_not_set()))),
-                ("Program not compiled" = (description = "The program associated with this pipeline has not been compiled.", value = json!(example_program_not_compiled()))),
-                ("Program has compilation errors" = (description = "The program associated with the pipeline raised compilation error.", value = json!(example_program_has_errors()))),
-                ("Invalid table reference" = (description = "Connectors reference a table that doesn't exist.", value = json!(example_pipeline_invalid_input_ac()))),
-                ("Invalid table or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
-            )
-        ),
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
             , description = "Specified pipeline id does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
+        (status = RANGE_NOT_SATISFIABLE
+            , description = "The measurement interval is out of range."
+            , body = ErrorResponse),
     ),
     params(
         ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ExplainAnalyzeQuery,
     ),
     tag = "Pipelines"
 )]
-#[get("/pipelines/{pipeline_id}/validate")]
-async fn pipeline_validate(
+#[get("/pipelines/{pipeline_id}/explain_analyze")]
+async fn pipeline_explain_analyze(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
+    query: web::Query<ExplainAnalyzeQuery>,
 ) -> Result<HttpResponse, ManagerError> {
     let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
 
-    let db = state.db.lock().await;
-    Ok(db
-        .pipeline_is_committable(*tenant_id, pipeline_id)
+    let endpoint = match query.duration_secs {
+        Some(duration_secs) => format!("explain_analyze?duration_secs={duration_secs}"),
+        None => "explain_analyze".to_string(),
+    };
+
+    state
+        .runner
+        .forward_to_pipeline(*tenant_id, pipeline_id, Method::GET, &endpoint)
         .await
-        .map(|_| HttpResponse::Ok().json("Pipeline successfully validated."))?)
 }
 
-/// Change the desired state of the pipeline.
-///
-/// This endpoint allows the user to control the execution of the pipeline,
-/// by changing its desired state attribute (see the discussion of the desired
-/// state model in the [`PipelineStatus`] documentation).
-///
-/// The endpoint returns immediately after validating the request and forwarding
-/// it to the pipeline. The requested status change completes asynchronously.
-/// On success, the pipeline enters the requested desired state.  On error, the
-/// pipeline transitions to the `Failed` state. The user
-/// can monitor the current status of the pipeline by polling the `GET
-/// /pipeline` endpoint.
-///
-/// The following values of the `action` argument are accepted by this endpoint:
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProfileQuery {
+    /// Number of circuit steps to profile (default: 10, maximum: 10000).
+    steps: Option<u64>,
+    /// Output format (default: `json`).
+    format: Option<ProfileFormat>,
+}
+
+/// Output format of a pipeline profile.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    /// Per-operator measurements in JSON.
+    Json,
+    /// CPU time of each operator in the folded stacks format accepted by
+    /// flamegraph tools.
+    Folded,
+}
+
+/// Profile the next steps of a running pipeline.
 ///
-/// - 'start': Start processing data.
-/// - 'pause': Pause the pipeline.
-/// - 'shutdown': Terminate the execution of the pipeline.
+/// Enables the circuit profiler for `steps` steps and returns the CPU time
+/// used by each operator during these steps, along with the number of
+/// records and bytes in its state.  The request completes once the pipeline
+/// has performed the requested number of steps.
 #[utoipa::path(
     responses(
-        (status = ACCEPTED
-            , description = "Request accepted."),
+        // TODO: Implement `ToSchema` for `CircuitProfile`, which is the
+        // actual type returned by this endpoint in the JSON format.
+        (status = OK, description = "Pipeline profiled successfully.", body = Object),
         (status = BAD_REQUEST
-            , description = "Pipeline desired state is not valid."
+            , description = "Specified pipeline id is not a valid uuid."
             , body = ErrorResponse
-            , examples(
-                ("Invalid Pipeline ID" = (description = "Specified pipeline id is not a valid uuid.", value = json!(example_invalid_uuid_param()))),
-                ("Program not set" = (description = "Pipeline does not have a program set.", value = json!(example_program_not_set()))),
-                ("Program not compiled" = (description = "The program associated with this pipeline has not been compiled.", value = json!(example_program_not_compiled()))),
-                ("Program has compilation errors" = (description = "The program associated with the pipeline raised compilation error.", value = json!(example_program_has_errors()))),
-                ("Invalid table reference" = (description = "Connectors reference a table that doesn't exist.", value = json!(example_pipeline_invalid_input_ac()))),
-                ("Invalid table or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
-                ("Invalidtable or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
-                ("Invalid action" = (description = "Invalid action specified", value = json!(example_invalid_pipeline_action()))),
-                ("Action cannot be applied" = (description = "Action is not applicable in the current state of the pipeline.", value = json!(example_illegal_pipeline_action()))),
-            )
-        ),
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
             , description = "Specified pipeline id does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
-        (status = INTERNAL_SERVER_ERROR
-            , description = "Timeout waiting for the pipeline to initialize."
-            , body = ErrorResponse
-            , example = json!(example_pipeline_timeout())),
+        (status = RANGE_NOT_SATISFIABLE
+            , description = "The number of steps is out of range."
+            , body = ErrorResponse),
     ),
     params(
         ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
-        ("action" = String, Path, description = "Pipeline action [start, pause, shutdown]")
+        ProfileQuery,
     ),
     tag = "Pipelines"
 )]
-#[post("/pipelines/{pipeline_id}/{action}")]
-async fn pipeline_action(
+#[get("/pipelines/{pipeline_id}/profile")]
+async fn pipeline_profile(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
+    query: web::Query<ProfileQuery>,
 ) -> Result<HttpResponse, ManagerError> {
     let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
-    let action = parse_pipeline_action(&req)?;
 
-    match action {
-        "start" => state.runner.start_pipeline(*tenant_id, pipeline_id).await?,
-        "pause" => state.runner.pause_pipeline(*tenant_id, pipeline_id).await?,
-        "shutdown" => {
-            state
-                .runner
-                .shutdown_pipeline(*tenant_id, pipeline_id)
-                .await?
-        }
-        _ => Err(ManagerError::InvalidPipelineAction {
-            action: action.to_string(),
-        })?,
+    let mut args = Vec::new();
+    if let Some(steps) = query.steps {
+        args.push(format!("steps={steps}"));
+    }
+    match query.format {
+        Some(ProfileFormat::Json) => args.push("format=json".to_string()),
+        Some(ProfileFormat::Folded) => args.push("format=folded".to_string()),
+        None => {}
     }
+    let endpoint = if args.is_empty() {
+        "profile".to_string()
+    } else {
+        format!("profile?{}", args.join("&"))
+    };
 
-    info!(
-        "Accepted '{action}' action for pipeline {pipeline_id} (tenant:{})",
-        *tenant_id
-    );
-    Ok(HttpResponse::Accepted().finish())
+    state
+        .runner
+        .forward_to_pipeline(*tenant_id, pipeline_id, Method::GET, &endpoint)
+        .await
 }
 
-/// Delete a pipeline. The pipeline must be in the shutdown state.
+/// Pause or resume a single input endpoint of a running pipeline.
+///
+/// A paused input endpoint stops receiving data while the rest of the
+/// pipeline keeps running, e.g., to stop a misbehaving data source.  The
+/// endpoint stays paused until it is resumed with the `start` action.
 #[utoipa::path(
     responses(
-        (status = OK
-            , description = "Pipeline successfully deleted."),
+        (status = OK, description = "Action applied successfully."),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid or the action is invalid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
-            , description = "Specified pipeline id does not exist."
+            , description = "Specified pipeline id or endpoint name does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
-        (status = BAD_REQUEST
-            , description = "Pipeline ID is invalid or pipeline is already running."
-            , body = ErrorResponse
-            , examples(
-                ("Pipeline is running" =
-                    (description = "Pipeline cannot be deleted while executing. Shutdown the pipeline first.",
-                    value = json!(example_cannot_delete_when_running()))),
-                ("Invalid Pipeline ID" =
-                    (value = json!(example_invalid_uuid_param())))
-        )),
     ),
     params(
-        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier")
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("endpoint_name" = String, Path, description = "Input endpoint name."),
+        ("action" = String, Path, description = "Endpoint action [start, pause]"),
     ),
     tag = "Pipelines"
 )]
-#[delete("/pipelines/{pipeline_id}")]
-async fn pipeline_delete(
+#[post("/pipelines/{pipeline_id}/input_endpoints/{endpoint_name}/{action}")]
+async fn input_endpoint_action(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ManagerError> {
     let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
 
+    let endpoint_name = match req.match_info().get("endpoint_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "endpoint_name",
+            });
+        }
+        Some(endpoint_name) => endpoint_name,
+    };
+    let action = match req.match_info().get("action") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam { param: "action" });
+        }
+        Some(action) => action,
+    };
+
     state
         .runner
-        .delete_pipeline(*tenant_id, pipeline_id)
-        .await?;
-
-    info!("Deleted pipeline {pipeline_id} (tenant:{})", *tenant_id);
-    Ok(HttpResponse::Ok().finish())
+        .forward_to_pipeline(
+            *tenant_id,
+            pipeline_id,
+            Method::POST,
+            &format!("input_endpoints/{endpoint_name}/{action}"),
+        )
+        .await
 }
 
-/// Fetch connectors, optionally filtered by name or ID
+/// Change the transport configuration of an input endpoint of a running
+/// pipeline.
+///
+/// The request body replaces the `config` section of the endpoint's
+/// transport configuration, e.g., to rotate credentials or change a poll
+/// interval without reconnecting the endpoint.  Transports only accept
+/// changes that they can apply on the fly; other changes are rejected and the
+/// endpoint keeps its current configuration.  The pipeline and connector
+/// configurations stored by the pipeline manager are not modified.
 #[utoipa::path(
+    request_body = Object,
     responses(
-        (status = OK, description = "List of connectors retrieved successfully", body = [ConnectorDescr]),
+        (status = OK, description = "Transport configuration updated successfully."),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid or the transport cannot apply the configuration."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
-            , description = "Specified connector name or ID does not exist"
+            , description = "Specified pipeline id or endpoint name does not exist."
             , body = ErrorResponse
-            , examples(
-                ("Unknown connector name" = (value = json!(example_unknown_name()))),
-                ("Unknown connector ID" = (value = json!(example_unknown_connector())))
-            ),
-        )
+            , example = json!(example_unknown_pipeline())),
     ),
-    params(ConnectorIdOrNameQuery),
-    tag = "Connectors"
-)]
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("endpoint_name" = String, Path, description = "Input endpoint name."),
+    ),
+    tag = "Pipelines"
+)]
+#[put("/pipelines/{pipeline_id}/input_endpoints/{endpoint_name}/transport_config")]
+async fn reconfigure_input_endpoint(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    let endpoint_name = match req.match_info().get("endpoint_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "endpoint_name",
+            });
+        }
+        Some(endpoint_name) => endpoint_name,
+    };
+
+    state
+        .runner
+        .forward_json_to_pipeline(
+            *tenant_id,
+            pipeline_id,
+            Method::PUT,
+            &format!("input_endpoints/{endpoint_name}/transport_config"),
+            &body,
+        )
+        .await
+}
+
+/// Pause or resume a single output endpoint of a running pipeline.
+///
+/// A paused output endpoint stops sending data.  Its outputs are buffered
+/// until the endpoint is resumed with the `start` action; once the buffer is
+/// full, the pipeline stops processing new inputs.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Action applied successfully."),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid or the action is invalid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id or endpoint name does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("endpoint_name" = String, Path, description = "Output endpoint name."),
+        ("action" = String, Path, description = "Endpoint action [start, pause]"),
+    ),
+    tag = "Pipelines"
+)]
+#[post("/pipelines/{pipeline_id}/output_endpoints/{endpoint_name}/{action}")]
+async fn output_endpoint_action(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    let endpoint_name = match req.match_info().get("endpoint_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "endpoint_name",
+            });
+        }
+        Some(endpoint_name) => endpoint_name,
+    };
+    let action = match req.match_info().get("action") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam { param: "action" });
+        }
+        Some(action) => action,
+    };
+
+    state
+        .runner
+        .forward_to_pipeline(
+            *tenant_id,
+            pipeline_id,
+            Method::POST,
+            &format!("output_endpoints/{endpoint_name}/{action}"),
+        )
+        .await
+}
+
+/// Attach a connector to a running pipeline.
+///
+/// Connects a new input or output endpoint with the configuration of the
+/// specified connector to a table or view of the running pipeline, without
+/// restarting the pipeline.  The connector stays attached until it is
+/// detached or the pipeline is shut down.  To attach the connector
+/// permanently, add it to the pipeline configuration.
+#[utoipa::path(
+    request_body = AttachedConnector,
+    responses(
+        (status = OK, description = "Connector attached successfully."),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid or the connector configuration is invalid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id, connector id, table, or view does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_connector())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+    ),
+    tag = "Pipelines"
+)]
+#[post("/pipelines/{pipeline_id}/connectors")]
+async fn attach_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Json<AttachedConnector>,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    let connector = state
+        .db
+        .lock()
+        .await
+        .get_connector_by_id(*tenant_id, body.connector_id)
+        .await?;
+
+    let stream = Cow::from(body.relation_name.clone());
+    let (endpoint, config) = if body.is_input {
+        (
+            "input_endpoints",
+            serde_json::to_value(InputEndpointConfig {
+                stream,
+                columns: None,
+                lineage: None,
+                dedup: None,
+                connector_config: connector.config,
+            }),
+        )
+    } else {
+        (
+            "output_endpoints",
+            serde_json::to_value(OutputEndpointConfig {
+                stream,
+                query: OutputQuery::default(),
+                connector_config: connector.config,
+            }),
+        )
+    };
+    // Serializing endpoint configs cannot fail.
+    let config = config.unwrap();
+
+    state
+        .runner
+        .forward_json_to_pipeline(
+            *tenant_id,
+            pipeline_id,
+            Method::POST,
+            endpoint,
+            &serde_json::json!({ "name": body.name, "config": config }),
+        )
+        .await
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DetachConnectorQuery {
+    /// `true` to detach an input connector, `false` to detach an output
+    /// connector.
+    is_input: bool,
+}
+
+/// Detach a connector from a running pipeline.
+///
+/// Disconnects the input or output endpoint `connector_name`, which may have
+/// been attached with the pipeline configuration or at runtime, from the
+/// running pipeline.  The pipeline configuration is not modified, so the
+/// connector is attached again when the pipeline restarts.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Connector detached successfully."),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id or connector name does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("connector_name" = String, Path, description = "Name of the attached connector."),
+        DetachConnectorQuery,
+    ),
+    tag = "Pipelines"
+)]
+#[delete("/pipelines/{pipeline_id}/connectors/{connector_name}")]
+async fn detach_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    query: web::Query<DetachConnectorQuery>,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    let connector_name = match req.match_info().get("connector_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "connector_name",
+            });
+        }
+        Some(connector_name) => connector_name,
+    };
+    let endpoint = if query.is_input {
+        format!("input_endpoints/{connector_name}")
+    } else {
+        format!("output_endpoints/{connector_name}")
+    };
+
+    state
+        .runner
+        .forward_to_pipeline(*tenant_id, pipeline_id, Method::DELETE, &endpoint)
+        .await
+}
+
+/// Fetch a pipeline by ID.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Pipeline descriptor retrieved successfully.",content(
+            ("application/json" = Pipeline, example = json!(example_pipeline_config())),
+        )),
+        (status = NOT_FOUND
+            , description = "Specified pipeline ID does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+    ),
+    tag = "Pipelines"
+)]
+#[get("/pipelines/{pipeline_id}")]
+async fn get_pipeline(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    let pipeline: crate::db::Pipeline = state
+        .db
+        .lock()
+        .await
+        .get_pipeline_by_id(*tenant_id, pipeline_id)
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&pipeline))
+}
+
+/// Fetch a pipeline's configuration.
+///
+/// When defining a pipeline, clients have to provide an optional
+/// `RuntimeConfig` for the pipelines and references to existing
+/// connectors to attach to the pipeline. This endpoint retrieves
+/// the *expanded* definition of the pipeline's configuration,
+/// which comprises both the `RuntimeConfig` and the complete
+/// definitions of the attached connectors.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Expanded pipeline configuration retrieved successfully.",content(
+            ("application/json" = PipelineConfig),
+        )),
+        (status = NOT_FOUND
+            , description = "Specified pipeline ID does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+    ),
+    tag = "Pipelines"
+)]
+#[get("/pipelines/{pipeline_id}/config")]
+async fn get_pipeline_config(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    let expanded_config = state
+        .db
+        .lock()
+        .await
+        .pipeline_config(*tenant_id, pipeline_id)
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&expanded_config))
+}
+
+/// Validate a pipeline.
+///
+/// Checks whether a pipeline is configured correctly. This includes
+/// checking whether the pipeline references a valid compiled program,
+/// whether the connectors reference valid tables/views in the program,
+/// and more.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Validate a Pipeline config."
+            , content_type = "application/json"
+            , body = String),
+        (status = BAD_REQUEST
+            , description = "Invalid pipeline."
+            , body = ErrorResponse
+            , examples(
+                ("Invalid Pipeline ID" = (description = "Specified pipeline id is not a valid uuid.", value = json!(example_invalid_uuid_param()))),
+                ("Program not set" = (description = "Pipeline does not have a program set.", value = json!(example_program_not_set()))),
+                ("Program not compiled" = (description = "The program associated with this pipeline has not been compiled.", value = json!(example_program_not_compiled()))),
+                ("Program has compilation errors" = (description = "The program associated with the pipeline raised compilation error.", value = json!(example_program_has_errors()))),
+                ("Invalid table reference" = (description = "Connectors reference a table that doesn't exist.", value = json!(example_pipeline_invalid_input_ac()))),
+                ("Invalid table or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
+            )
+        ),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+    ),
+    tag = "Pipelines"
+)]
+#[get("/pipelines/{pipeline_id}/validate")]
+async fn pipeline_validate(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    let db = state.db.lock().await;
+    Ok(db
+        .pipeline_is_committable(*tenant_id, pipeline_id)
+        .await
+        .map(|_| HttpResponse::Ok().json("Pipeline successfully validated."))?)
+}
+
+/// Change the desired state of the pipeline.
+///
+/// This endpoint allows the user to control the execution of the pipeline,
+/// by changing its desired state attribute (see the discussion of the desired
+/// state model in the [`PipelineStatus`] documentation).
+///
+/// The endpoint returns immediately after validating the request and forwarding
+/// it to the pipeline. The requested status change completes asynchronously.
+/// On success, the pipeline enters the requested desired state.  On error, the
+/// pipeline transitions to the `Failed` state. The user
+/// can monitor the current status of the pipeline by polling the `GET
+/// /pipeline` endpoint.
+///
+/// The following values of the `action` argument are accepted by this endpoint:
+///
+/// - 'start': Start processing data.
+/// - 'pause': Pause the pipeline.
+/// - 'shutdown': Terminate the execution of the pipeline.
+#[utoipa::path(
+    responses(
+        (status = ACCEPTED
+            , description = "Request accepted."),
+        (status = BAD_REQUEST
+            , description = "Pipeline desired state is not valid."
+            , body = ErrorResponse
+            , examples(
+                ("Invalid Pipeline ID" = (description = "Specified pipeline id is not a valid uuid.", value = json!(example_invalid_uuid_param()))),
+                ("Program not set" = (description = "Pipeline does not have a program set.", value = json!(example_program_not_set()))),
+                ("Program not compiled" = (description = "The program associated with this pipeline has not been compiled.", value = json!(example_program_not_compiled()))),
+                ("Program has compilation errors" = (description = "The program associated with the pipeline raised compilation error.", value = json!(example_program_has_errors()))),
+                ("Invalid table reference" = (description = "Connectors reference a table that doesn't exist.", value = json!(example_pipeline_invalid_input_ac()))),
+                ("Invalid table or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
+                ("Invalidtable or view reference" = (description = "Connectors reference a view that doesn't exist.", value = json!(example_pipeline_invalid_output_ac()))),
+                ("Invalid action" = (description = "Invalid action specified", value = json!(example_invalid_pipeline_action()))),
+                ("Action cannot be applied" = (description = "Action is not applicable in the current state of the pipeline.", value = json!(example_illegal_pipeline_action()))),
+            )
+        ),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = INTERNAL_SERVER_ERROR
+            , description = "Timeout waiting for the pipeline to initialize."
+            , body = ErrorResponse
+            , example = json!(example_pipeline_timeout())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier"),
+        ("action" = String, Path, description = "Pipeline action [start, pause, shutdown]")
+    ),
+    tag = "Pipelines"
+)]
+#[post("/pipelines/{pipeline_id}/{action}")]
+async fn pipeline_action(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    let action = parse_pipeline_action(&req)?;
+
+    match action {
+        "start" => state.runner.start_pipeline(*tenant_id, pipeline_id).await?,
+        "pause" => state.runner.pause_pipeline(*tenant_id, pipeline_id).await?,
+        "shutdown" => {
+            state
+                .runner
+                .shutdown_pipeline(*tenant_id, pipeline_id)
+                .await?
+        }
+        _ => Err(ManagerError::InvalidPipelineAction {
+            action: action.to_string(),
+        })?,
+    }
+
+    info!(
+        "Accepted '{action}' action for pipeline {pipeline_id} (tenant:{})",
+        *tenant_id
+    );
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Delete a pipeline. The pipeline must be in the shutdown state.
+///
+/// The pipeline is moved to the trash, where it can be restored from
+/// until it is permanently deleted after the trash retention period.
+/// The name of a deleted pipeline can be reused by other pipelines.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Pipeline successfully deleted."),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = BAD_REQUEST
+            , description = "Pipeline ID is invalid or pipeline is already running."
+            , body = ErrorResponse
+            , examples(
+                ("Pipeline is running" =
+                    (description = "Pipeline cannot be deleted while executing. Shutdown the pipeline first.",
+                    value = json!(example_cannot_delete_when_running()))),
+                ("Invalid Pipeline ID" =
+                    (value = json!(example_invalid_uuid_param())))
+        )),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier")
+    ),
+    tag = "Pipelines"
+)]
+#[delete("/pipelines/{pipeline_id}")]
+async fn pipeline_delete(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    state
+        .runner
+        .delete_pipeline(*tenant_id, pipeline_id)
+        .await?;
+
+    info!("Deleted pipeline {pipeline_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Fetch pipelines in the trash.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Deleted pipelines retrieved successfully.", body = [DeletedPipeline])
+    ),
+    tag = "Trash"
+)]
+#[get("/trash/pipelines")]
+async fn list_deleted_pipelines(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+) -> Result<HttpResponse, ManagerError> {
+    let pipelines = state
+        .db
+        .lock()
+        .await
+        .list_deleted_pipelines(*tenant_id)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(pipelines))
+}
+
+/// Restore a pipeline from the trash.
+///
+/// The pipeline is restored in the shutdown state, with its configuration
+/// and attached connectors at the time it was deleted.  Attachments to
+/// connectors that are in the trash reappear once those connectors are
+/// restored.  Fails if another pipeline has taken the name of the pipeline
+/// since it was deleted.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Pipeline successfully restored."),
+        (status = CONFLICT
+            , description = "A pipeline with this name already exists in the database."
+            , body = ErrorResponse
+            , example = json!(example_duplicate_name())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id is not in the trash."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier")
+    ),
+    tag = "Trash"
+)]
+#[post("/trash/pipelines/{pipeline_id}/restore")]
+async fn restore_pipeline(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+
+    state
+        .db
+        .lock()
+        .await
+        .restore_pipeline(*tenant_id, pipeline_id)
+        .await?;
+
+    info!("Restored pipeline {pipeline_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Fetch connectors, optionally filtered by name or ID
+#[utoipa::path(
+    responses(
+        (status = OK, description = "List of connectors retrieved successfully", body = [ConnectorDescr]),
+        (status = NOT_FOUND
+            , description = "Specified connector name or ID does not exist"
+            , body = ErrorResponse
+            , examples(
+                ("Unknown connector name" = (value = json!(example_unknown_name()))),
+                ("Unknown connector ID" = (value = json!(example_unknown_connector())))
+            ),
+        )
+    ),
+    params(ConnectorIdOrNameQuery),
+    tag = "Connectors"
+)]
 #[get("/connectors")]
 async fn list_connectors(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
-    req: web::Query<ConnectorIdOrNameQuery>,
-) -> Result<HttpResponse, DBError> {
-    let descr = if let Some(id) = req.id {
-        vec![
-            state
-                .db
-                .lock()
-                .await
-                .get_connector_by_id(*tenant_id, ConnectorId(id))
-                .await?,
-        ]
-    } else if let Some(name) = req.name.clone() {
-        vec![
-            state
-                .db
-                .lock()
-                .await
-                .get_connector_by_name(*tenant_id, name)
-                .await?,
-        ]
-    } else {
-        state.db.lock().await.list_connectors(*tenant_id).await?
+    req: web::Query<ConnectorIdOrNameQuery>,
+) -> Result<HttpResponse, DBError> {
+    let descr = if let Some(id) = req.id {
+        vec![
+            state
+                .db
+                .lock()
+                .await
+                .get_connector_by_id(*tenant_id, ConnectorId(id))
+                .await?,
+        ]
+    } else if let Some(name) = req.name.clone() {
+        vec![
+            state
+                .db
+                .lock()
+                .await
+                .get_connector_by_name(*tenant_id, name)
+                .await?,
+        ]
+    } else {
+        state.db.lock().await.list_connectors(*tenant_id).await?
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&descr))
+}
+
+/// Request to create a new connector.
+#[derive(Deserialize, ToSchema)]
+pub(self) struct NewConnectorRequest {
+    /// Connector name.
+    name: String,
+    /// Connector description.
+    description: String,
+    /// Connector configuration.
+    config: ConnectorConfig,
+}
+
+/// Response to a connector creation request.
+#[derive(Serialize, ToSchema)]
+struct NewConnectorResponse {
+    /// Unique id assigned to the new connector.
+    connector_id: ConnectorId,
+}
+
+/// Create a new connector.
+#[utoipa::path(
+    request_body = NewConnectorRequest,
+    responses(
+        (status = OK, description = "Connector successfully created.", body = NewConnectorResponse),
+    ),
+    tag = "Connectors"
+)]
+#[post("/connectors")]
+async fn new_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    request: web::Json<NewConnectorRequest>,
+) -> Result<HttpResponse, DBError> {
+    let connector_id = state
+        .db
+        .lock()
+        .await
+        .new_connector(
+            *tenant_id,
+            Uuid::now_v7(),
+            &request.name,
+            &request.description,
+            &request.config,
+        )
+        .await?;
+
+    info!("Created connector {connector_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&NewConnectorResponse { connector_id }))
+}
+
+/// Request to update an existing data-connector.
+#[derive(Deserialize, ToSchema)]
+struct UpdateConnectorRequest {
+    /// New connector name.
+    name: String,
+    /// New connector description.
+    description: String,
+    /// New config YAML. If absent, existing YAML will be kept unmodified.
+    config: Option<ConnectorConfig>,
+}
+
+/// Response to a config update request.
+#[derive(Serialize, ToSchema)]
+struct UpdateConnectorResponse {}
+
+/// Change a connector's name, description or configuration.
+#[utoipa::path(
+    request_body = UpdateConnectorRequest,
+    responses(
+        (status = OK, description = "connector successfully updated.", body = UpdateConnectorResponse),
+        (status = NOT_FOUND
+            , description = "Specified connector id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_connector())),
+    ),
+    params(
+        ("connector_id" = Uuid, Path, description = "Unique connector identifier")
+    ),
+    tag = "Connectors"
+)]
+#[patch("/connectors/{connector_id}")]
+async fn update_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Json<UpdateConnectorRequest>,
+) -> Result<HttpResponse, ManagerError> {
+    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
+    state
+        .db
+        .lock()
+        .await
+        .update_connector(
+            *tenant_id,
+            connector_id,
+            &body.name,
+            &body.description,
+            &body.config,
+        )
+        .await?;
+
+    info!("Updated connector {connector_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&UpdateConnectorResponse {}))
+}
+
+/// Delete an existing connector.
+///
+/// The connector is moved to the trash, where it can be restored from until
+/// it is permanently deleted after the trash retention period.  While in
+/// the trash, the connector is hidden from the pipelines it is attached to,
+/// and its name can be reused by other connectors.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "connector successfully deleted."),
+        (status = BAD_REQUEST
+            , description = "Specified connector id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified connector id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_connector())),
+    ),
+    params(
+        ("connector_id" = Uuid, Path, description = "Unique connector identifier")
+    ),
+    tag = "Connectors"
+)]
+#[delete("/connectors/{connector_id}")]
+async fn delete_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
+
+    state
+        .db
+        .lock()
+        .await
+        .delete_connector(*tenant_id, connector_id)
+        .await?;
+
+    info!("Deleted connector {connector_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Fetch connectors in the trash.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Deleted connectors retrieved successfully.", body = [DeletedConnector])
+    ),
+    tag = "Trash"
+)]
+#[get("/trash/connectors")]
+async fn list_deleted_connectors(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+) -> Result<HttpResponse, ManagerError> {
+    let connectors = state
+        .db
+        .lock()
+        .await
+        .list_deleted_connectors(*tenant_id)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(connectors))
+}
+
+/// Restore a connector from the trash.
+///
+/// The restored connector is attached again to the pipelines it was
+/// attached to when it was deleted, unless their connectors have been
+/// replaced since then.  Fails if another connector has taken the name of
+/// the connector since it was deleted.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Connector successfully restored."),
+        (status = CONFLICT
+            , description = "A connector with this name already exists in the database."
+            , body = ErrorResponse
+            , example = json!(example_duplicate_name())),
+        (status = BAD_REQUEST
+            , description = "Specified connector id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified connector id is not in the trash."
+            , body = ErrorResponse
+            , example = json!(example_unknown_connector())),
+    ),
+    params(
+        ("connector_id" = Uuid, Path, description = "Unique connector identifier")
+    ),
+    tag = "Trash"
+)]
+#[post("/trash/connectors/{connector_id}/restore")]
+async fn restore_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
+
+    state
+        .db
+        .lock()
+        .await
+        .restore_connector(*tenant_id, connector_id)
+        .await?;
+
+    info!("Restored connector {connector_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Fetch a connector by ID.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Connector retrieved successfully.", body = ConnectorDescr),
+        (status = BAD_REQUEST
+            , description = "Specified connector id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+    ),
+    params(
+        ("connector_id" = Uuid, Path, description = "Unique connector identifier"),
+    ),
+    tag = "Connectors"
+)]
+#[get("/connectors/{connector_id}")]
+async fn get_connector(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ManagerError> {
+    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
+    let descr = state
+        .db
+        .lock()
+        .await
+        .get_connector_by_id(*tenant_id, connector_id)
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&descr))
+}
+
+// Duplicate the same structure twice, since
+// these doc comments will be automatically used
+// in the OpenAPI spec.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ConnectorIdOrNameQuery {
+    /// Unique connector identifier.
+    id: Option<Uuid>,
+    /// Unique connector name.
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProgramIdOrNameQuery {
+    /// Unique program identifier.
+    id: Option<Uuid>,
+    /// Unique program name.
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WithCodeQuery {
+    /// Option to include the SQL program code or not
+    /// in the Program objects returned by the query.
+    /// If false (default), the returned program object
+    /// will not include the code.
+    with_code: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PipelineIdOrNameQuery {
+    /// Unique pipeline id.
+    id: Option<Uuid>,
+    /// Unique pipeline name.
+    name: Option<String>,
+}
+
+/// Push data to a SQL table.
+///
+/// The client sends data encoded using the format specified in the `?format=`
+/// parameter as a body of the request.  The contents of the data must match
+/// the SQL table schema specified in `table_name`
+///
+/// The pipeline ingests data as it arrives without waiting for the end of
+/// the request.  Successful HTTP response indicates that all data has been
+/// ingested successfully.
+// TODO: implement chunked and batch modes.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Data successfully delivered to the pipeline."
+            , content_type = "application/json"),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = NOT_FOUND
+            , description = "Specified table does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_input_table("MyTable"))),
+        (status = NOT_FOUND
+            , description = "Pipeline is not currently running because it has been shutdown or not yet started."
+            , body = ErrorResponse
+            , example = json!(example_pipeline_shutdown())),
+        (status = BAD_REQUEST
+            , description = "Unknown data format specified in the '?format=' argument."
+            , body = ErrorResponse
+            , example = json!(example_unknown_input_format())),
+        (status = BAD_REQUEST
+            , description = "Error parsing input data."
+            , body = ErrorResponse
+            , example = json!(example_parse_errors())),
+        (status = INTERNAL_SERVER_ERROR
+            , description = "Request failed."
+            , body = ErrorResponse),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier."),
+        ("table_name" = String, Path, description = "SQL table name."),
+        ("force" = bool, Query, description = "When `true`, push data to the pipeline even if the pipeline is paused. The default value is `false`"),
+        ("abort_on_error" = Option<bool>, Query, description = "When `true`, stop processing the request at the first parse error. Records ingested before the error are not rolled back. The default value is `false`."),
+        ("format" = String, Query, description = "Input data format, e.g., 'csv' or 'json'."),
+        ("array" = Option<bool>, Query, description = "Set to `true` if updates in this stream are packaged into JSON arrays (used in conjunction with `format=json`). The default values is `false`."),
+        ("update_format" = Option<JsonUpdateFormat>, Query, description = "JSON data change event format (used in conjunction with `format=json`).  The default value is 'insert_delete'."),
+    ),
+    tag = "Pipelines",
+    request_body(
+        content = String,
+        description = "Contains the new input data in CSV.",
+        content_type = "text/csv",
+    ),
+)]
+#[post("/pipelines/{pipeline_id}/ingress/{table_name}")]
+async fn http_input(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, ManagerError> {
+    debug!("Received {req:?}");
+
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    debug!("Pipeline_id {:?}", pipeline_id);
+
+    let table_name = match req.match_info().get("table_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "table_name",
+            });
+        }
+        Some(table_name) => table_name,
+    };
+    debug!("Table name {table_name:?}");
+
+    let endpoint = format!("ingress/{table_name}");
+
+    state
+        .runner
+        .forward_to_pipeline_as_stream(*tenant_id, pipeline_id, &endpoint, req, body)
+        .await
+}
+
+/// Subscribe to a stream of updates from a SQL view or table.
+///
+/// The pipeline responds with a continuous stream of changes to the specified
+/// table or view, encoded using the format specified in the `?format=`
+/// parameter. Updates are split into `Chunk`'s.
+///
+/// The pipeline continuous sending updates until the client closes the
+/// connection or the pipeline is shut down.
+///
+/// The response is compressed if the client sends an `Accept-Encoding` header
+/// that includes `gzip` or `zstd`.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Connection to the endpoint successfully established. The body of the response contains a stream of data chunks."
+            , content_type = "application/json"
+            , body = Chunk),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = NOT_FOUND
+            , description = "Specified table or view does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_output_table("MyTable"))),
+        (status = GONE
+            , description = "Pipeline is not currently running because it has been shutdown or not yet started."
+            , body = ErrorResponse
+            , example = json!(example_pipeline_shutdown())),
+        (status = BAD_REQUEST
+            , description = "Unknown data format specified in the '?format=' argument."
+            , body = ErrorResponse
+            , example = json!(example_unknown_output_format())),
+        (status = INTERNAL_SERVER_ERROR
+            , description = "Request failed."
+            , body = ErrorResponse),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier."),
+        ("table_name" = String, Path, description = "SQL table or view name."),
+        ("format" = String, Query, description = "Output data format, e.g., 'csv' or 'json'."),
+        ("query" = Option<OutputQuery>, Query, description = "Query to execute on the table. Must be one of 'table', 'neighborhood', 'quantiles', or 'sample'. The default value is 'table'"),
+        ("mode" = Option<EgressMode>, Query, description = "Output mode. Must be one of 'watch' or 'snapshot'. The default value is 'watch'"),
+        ("quantiles" = Option<u32>, Query, description = "For 'quantiles' queries: the number of quantiles to output. The default value is 100."),
+        ("columns" = Option<String>, Query, description = "For 'quantiles' queries: comma-separated list of columns to compute quantiles over. When specified, the quantiles of each column are output in a separate chunk of the form `{\"column\": \"name\", \"quantiles\": [...]}` instead of quantiles of entire records. Requires `format=json`."),
+        ("sample_size" = Option<u32>, Query, description = "For 'sample' queries: the maximal number of records to output. The default value is 100."),
+        ("page_size" = Option<usize>, Query, description = "For 'table' queries in the 'snapshot' mode: the maximal number of records in a page, between 1 and 100000. The default value is 1000."),
+        ("min_chunk_size" = Option<usize>, Query, description = "For compressed responses: the minimal number of bytes of output to accumulate before compressing and sending it to the client. The default value is 0."),
+        ("compression" = Option<String>, Query, description = "Compression to apply to the response: 'gzip', 'zstd', or 'none'. Overrides the encoding negotiated via the `Accept-Encoding` header. By default, the response is compressed if the client accepts 'gzip' or 'zstd' encoding."),
+        ("changelog" = Option<bool>, Query, description = "Set to `true` to wrap output chunks in the changelog envelope, which tags each chunk with the step that produced it and follows each step with a `step_end` marker chunk containing the number of chunks and bytes in the step. Not supported for binary formats. The default value is `false`."),
+        ("snapshot_interval" = Option<u64>, Query, description = "When `changelog` is `true`: emit a `snapshot` marker chunk with cumulative counts every `snapshot_interval` steps. By default, no snapshot markers are emitted."),
+        ("as_of_step" = Option<u64>, Query, description = "For 'table' queries in the 'snapshot' mode: output the version of the view produced by this step of the circuit, as a single `{\"step\": ..., \"records\": [...]}` object. Only the versions retained according to the `history` setting of the pipeline are available. Requires `format=json`."),
+        ("array" = Option<bool>, Query, description = "Set to `true` to group updates in this stream into JSON arrays (used in conjunction with `format=json`). The default value is `false`"),
+        ("large_numbers_as_strings" = Option<bool>, Query, description = "Set to `true` to encode `BIGINT` and `DECIMAL` values as JSON strings rather than numbers, so that JavaScript clients do not lose precision (used in conjunction with `format=json`). The default value is `false`."),
+    ),
+    request_body(
+        content = Option<NeighborhoodQuery>,
+        description = "When the `query` parameter is set to 'neighborhood', the body of the request must contain a neighborhood specification. For 'table' queries in the 'snapshot' mode, the body may contain a `{\"cursor\": ...}` object with the cursor returned with the previous page; the response is a single `{\"records\": [...], \"cursor\": ...}` object whose cursor is `null` on the last page. Snapshots of tables and views require `format=json`.",
+        content_type = "application/json",
+    ),
+    tag = "Pipelines"
+)]
+#[post("/pipelines/{pipeline_id}/egress/{table_name}")]
+async fn http_output(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, ManagerError> {
+    debug!("Received {req:?}");
+
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    debug!("Pipeline_id {:?}", pipeline_id);
+
+    let table_name = match req.match_info().get("table_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam {
+                param: "table_name",
+            });
+        }
+        Some(table_name) => table_name,
+    };
+    debug!("Table name {table_name:?}");
+
+    let endpoint = format!("egress/{table_name}");
+
+    state
+        .runner
+        .forward_to_pipeline_as_stream(*tenant_id, pipeline_id, &endpoint, req, body)
+        .await
+}
+
+/// Retrieve a uniform random sample of the contents of a SQL view or table.
+///
+/// The pipeline maintains a random sample of each view incrementally as the
+/// view changes.  Records that occur in the view multiple times are
+/// proportionally more likely to be sampled.  The sample is returned as a
+/// single snapshot encoded using the format specified in the `?format=`
+/// parameter.  The cost of the request is proportional to the size of the
+/// sample rather than the size of the view, making it suitable for
+/// previewing large views.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Sample retrieved successfully. The body of the response contains a stream of data chunks."
+            , content_type = "application/json"
+            , body = Chunk),
+        (status = BAD_REQUEST
+            , description = "Specified pipeline id is not a valid uuid."
+            , body = ErrorResponse
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified pipeline id does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_pipeline())),
+        (status = NOT_FOUND
+            , description = "Specified table or view does not exist."
+            , body = ErrorResponse
+            , example = json!(example_unknown_output_table("MyTable"))),
+        (status = GONE
+            , description = "Pipeline is not currently running because it has been shutdown or not yet started."
+            , body = ErrorResponse
+            , example = json!(example_pipeline_shutdown())),
+        (status = RANGE_NOT_SATISFIABLE
+            , description = "Requested sample size is out of range."
+            , body = ErrorResponse),
+        (status = INTERNAL_SERVER_ERROR
+            , description = "Request failed."
+            , body = ErrorResponse),
+    ),
+    params(
+        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier."),
+        ("view_name" = String, Path, description = "SQL table or view name."),
+        ("n" = Option<u32>, Query, description = "The maximal number of records to return, between 1 and 10000. The default value is 100."),
+        ("format" = Option<String>, Query, description = "Output data format, e.g., 'csv' or 'json'. The default value is 'csv'."),
+    ),
+    tag = "Pipelines"
+)]
+#[get("/pipelines/{pipeline_id}/views/{view_name}/sample")]
+async fn view_sample(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, ManagerError> {
+    debug!("Received {req:?}");
+
+    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
+    debug!("Pipeline_id {:?}", pipeline_id);
+
+    let view_name = match req.match_info().get("view_name") {
+        None => {
+            return Err(ManagerError::MissingUrlEncodedParam { param: "view_name" });
+        }
+        Some(view_name) => view_name,
     };
+    debug!("View name {view_name:?}");
+
+    let endpoint = format!("views/{view_name}/sample");
+
+    state
+        .runner
+        .forward_to_pipeline_as_stream(*tenant_id, pipeline_id, &endpoint, req, body)
+        .await
+}
+
+/// Retrieve resources consumed by the tenant.
+///
+/// Returns cumulative counters of the time spent compiling the tenant's
+/// programs, the time its pipelines were deployed, and the number of bytes
+/// ingested and emitted by pipeline endpoints.  Run time and byte counters
+/// are sampled periodically while pipelines are running, so they may lag
+/// behind the actual usage by a few seconds.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Resource usage retrieved successfully.", body = TenantUsage),
+    ),
+    tag = "Usage"
+)]
+#[get("/usage")]
+async fn get_usage(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+) -> Result<HttpResponse, ManagerError> {
+    let usage = state.db.lock().await.get_tenant_usage(*tenant_id).await?;
 
     Ok(HttpResponse::Ok()
         .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&descr))
+        .json(&usage))
 }
 
-/// Request to create a new connector.
-#[derive(Deserialize, ToSchema)]
-pub(self) struct NewConnectorRequest {
-    /// Connector name.
-    name: String,
-    /// Connector description.
-    description: String,
-    /// Connector configuration.
-    config: ConnectorConfig,
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ApplyQuery {
+    /// Compute the changes without applying them.
+    #[serde(default)]
+    dry_run: bool,
 }
 
-/// Response to a connector creation request.
+/// Response to an apply request.
 #[derive(Serialize, ToSchema)]
-struct NewConnectorResponse {
-    /// Unique id assigned to the new connector.
-    connector_id: ConnectorId,
+struct ApplyResponse {
+    /// Changes needed to converge to the manifest, in the order they are
+    /// applied.
+    changes: Vec<crate::apply::Change>,
 }
 
-/// Create a new connector.
+/// Converge programs, connectors, and pipelines to a manifest.
+///
+/// The manifest describes the complete desired set of programs, connectors,
+/// and pipelines of the tenant, indexed by name.  Objects that are missing
+/// from the database are created, objects that differ from the manifest are
+/// updated, and objects that are not in the manifest are deleted.  Created
+/// and updated programs are queued for compilation.  Pipelines must be shut
+/// down before they can be deleted.
+///
+/// Returns the list of changes.  With `?dry_run=true`, the changes are
+/// computed but not applied.
+///
+/// Changes are applied one at a time.  If a change fails, the request fails
+/// and the changes applied before it remain in effect.  Applying the same
+/// manifest again after fixing the error completes the remaining changes.
 #[utoipa::path(
-    request_body = NewConnectorRequest,
+    request_body = Manifest,
     responses(
-        (status = OK, description = "Connector successfully created.", body = NewConnectorResponse),
+        (status = OK, description = "Manifest applied successfully.", body = ApplyResponse),
+        (status = BAD_REQUEST
+            , description = "The manifest references undeclared programs or connectors, or a pipeline to be deleted is running."
+            , body = ErrorResponse),
     ),
-    tag = "Connectors"
+    params(ApplyQuery),
+    tag = "Apply"
 )]
-#[post("/connectors")]
-async fn new_connector(
+#[post("/apply")]
+async fn apply(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
-    request: web::Json<NewConnectorRequest>,
-) -> Result<HttpResponse, DBError> {
-    let connector_id = state
-        .db
-        .lock()
-        .await
-        .new_connector(
-            *tenant_id,
-            Uuid::now_v7(),
-            &request.name,
-            &request.description,
-            &request.config,
-        )
-        .await?;
+    query: web::Query<ApplyQuery>,
+    body: web::Json<crate::apply::Manifest>,
+) -> Result<HttpResponse, ManagerError> {
+    let plan = crate::apply::plan(&*state.db.lock().await, *tenant_id, &body).await?;
+    let changes = plan.changes.clone();
+
+    if !query.dry_run {
+        crate::apply::apply(&state.db, &state.runner, *tenant_id, &body, plan).await?;
+        info!(
+            "Applied manifest with {} changes (tenant:{})",
+            changes.len(),
+            *tenant_id
+        );
+    }
 
-    info!("Created connector {connector_id} (tenant:{})", *tenant_id);
     Ok(HttpResponse::Ok()
         .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&NewConnectorResponse { connector_id }))
+        .json(&ApplyResponse { changes }))
 }
 
-/// Request to update an existing data-connector.
+/// Fetch the webhooks of the tenant.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Webhooks retrieved successfully.", body = [WebhookDescr])
+    ),
+    tag = "Webhooks"
+)]
+#[get("/webhooks")]
+async fn list_webhooks(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+) -> Result<HttpResponse, ManagerError> {
+    let webhooks = state.db.lock().await.list_webhooks(*tenant_id).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(webhooks))
+}
+
+/// Request to register a new webhook.
 #[derive(Deserialize, ToSchema)]
-struct UpdateConnectorRequest {
-    /// New connector name.
-    name: String,
-    /// New connector description.
-    description: String,
-    /// New config YAML. If absent, existing YAML will be kept unmodified.
-    config: Option<ConnectorConfig>,
+struct NewWebhookRequest {
+    /// `http` or `https` URL that event notifications are posted to.
+    url: String,
+    /// Program and pipeline lifecycle events to notify the webhook about.
+    events: Vec<WebhookEvent>,
+    /// How failed deliveries are retried.  By default, deliveries are
+    /// attempted up to 5 times, starting with a 1 second delay.  At most
+    /// 20 attempts are allowed, and delays are capped at one hour.
+    #[serde(default)]
+    retry_policy: WebhookRetryPolicy,
 }
 
-/// Response to a config update request.
+/// Response to a webhook registration request.
 #[derive(Serialize, ToSchema)]
-struct UpdateConnectorResponse {}
+struct NewWebhookResponse {
+    /// Unique id assigned to the new webhook.
+    webhook_id: WebhookId,
+    /// Secret used to sign notification payloads.  This is the only time
+    /// the secret is revealed.
+    secret: String,
+}
 
-/// Change a connector's name, description or configuration.
+/// Register a webhook for program and pipeline lifecycle events.
+///
+/// Whenever a program of the tenant finishes compiling or fails to compile,
+/// a pipeline is deployed, fails, is shut down, or is deployed again after
+/// a failure, or a connector of a running pipeline reports errors, the
+/// manager posts a JSON payload describing the event to the webhook URL.
+/// Payloads are signed with the secret returned by this request: the
+/// `X-Feldera-Signature` header contains `sha256=` followed by the
+/// hex-encoded HMAC-SHA256 of `<X-Feldera-Timestamp>.<body>`.  Failed
+/// deliveries are retried with exponential backoff according to the
+/// retry policy of the webhook.
 #[utoipa::path(
-    request_body = UpdateConnectorRequest,
+    request_body = NewWebhookRequest,
     responses(
-        (status = OK, description = "connector successfully updated.", body = UpdateConnectorResponse),
-        (status = NOT_FOUND
-            , description = "Specified connector id does not exist."
-            , body = ErrorResponse
-            , example = json!(example_unknown_connector())),
-    ),
-    params(
-        ("connector_id" = Uuid, Path, description = "Unique connector identifier")
+        (status = OK, description = "Webhook successfully registered.", body = NewWebhookResponse),
+        (status = BAD_REQUEST
+            , description = "Webhook URL is not a valid http or https URL, or the retry policy is out of range."
+            , body = ErrorResponse),
     ),
-    tag = "Connectors"
+    tag = "Webhooks"
 )]
-#[patch("/connectors/{connector_id}")]
-async fn update_connector(
+#[post("/webhooks")]
+async fn new_webhook(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
-    req: HttpRequest,
-    body: web::Json<UpdateConnectorRequest>,
+    request: web::Json<NewWebhookRequest>,
 ) -> Result<HttpResponse, ManagerError> {
-    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
-    state
+    match url::Url::parse(&request.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        Ok(url) => {
+            return Err(ManagerError::InvalidWebhookUrl {
+                url: request.url.clone(),
+                error: format!("unsupported scheme '{}'", url.scheme()),
+            })
+        }
+        Err(e) => {
+            return Err(ManagerError::InvalidWebhookUrl {
+                url: request.url.clone(),
+                error: e.to_string(),
+            })
+        }
+    }
+    request
+        .retry_policy
+        .validate()
+        .map_err(|error| ManagerError::InvalidWebhookRetryPolicy { error })?;
+
+    let secret = crate::webhooks::generate_secret();
+    let webhook_id = state
         .db
         .lock()
         .await
-        .update_connector(
+        .new_webhook(
             *tenant_id,
-            connector_id,
-            &body.name,
-            &body.description,
-            &body.config,
+            Uuid::now_v7(),
+            &request.url,
+            &secret,
+            &request.events,
+            &request.retry_policy,
         )
         .await?;
 
-    info!("Updated connector {connector_id} (tenant:{})", *tenant_id);
+    info!("Created webhook {webhook_id} (tenant:{})", *tenant_id);
     Ok(HttpResponse::Ok()
         .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&UpdateConnectorResponse {}))
+        .json(&NewWebhookResponse { webhook_id, secret }))
 }
 
-/// Delete an existing connector.
+/// Delete a webhook.
 #[utoipa::path(
     responses(
-        (status = OK, description = "connector successfully deleted."),
+        (status = OK, description = "Webhook successfully deleted."),
         (status = BAD_REQUEST
-            , description = "Specified connector id is not a valid uuid."
+            , description = "Specified webhook id is not a valid uuid."
             , body = ErrorResponse
             , example = json!(example_invalid_uuid_param())),
         (status = NOT_FOUND
-            , description = "Specified connector id does not exist."
-            , body = ErrorResponse
-            , example = json!(example_unknown_connector())),
+            , description = "Specified webhook id does not exist."
+            , body = ErrorResponse),
     ),
     params(
-        ("connector_id" = Uuid, Path, description = "Unique connector identifier")
+        ("webhook_id" = Uuid, Path, description = "Unique webhook identifier")
     ),
-    tag = "Connectors"
+    tag = "Webhooks"
 )]
-#[delete("/connectors/{connector_id}")]
-async fn delete_connector(
+#[delete("/webhooks/{webhook_id}")]
+async fn delete_webhook(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ManagerError> {
-    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
+    let webhook_id = WebhookId(parse_uuid_param(&req, "webhook_id")?);
 
     state
         .db
         .lock()
         .await
-        .delete_connector(*tenant_id, connector_id)
+        .delete_webhook(*tenant_id, webhook_id)
         .await?;
 
-    info!("Deleted connector {connector_id} (tenant:{})", *tenant_id);
+    info!("Deleted webhook {webhook_id} (tenant:{})", *tenant_id);
     Ok(HttpResponse::Ok().finish())
 }
 
-/// Fetch a connector by ID.
+/// Fetch the deployments of the tenant.
 #[utoipa::path(
     responses(
-        (status = OK, description = "Connector retrieved successfully.", body = ConnectorDescr),
+        (status = OK, description = "Deployments retrieved successfully.", body = [DeploymentDescr])
+    ),
+    tag = "Deployments"
+)]
+#[get("/deployments")]
+async fn list_deployments(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+) -> Result<HttpResponse, ManagerError> {
+    let deployments = state.db.lock().await.list_deployments(*tenant_id).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(deployments))
+}
+
+/// Fetch a deployment by ID.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Deployment descriptor retrieved successfully.", body = DeploymentDescr),
         (status = BAD_REQUEST
-            , description = "Specified connector id is not a valid uuid."
+            , description = "Specified deployment id is not a valid uuid."
             , body = ErrorResponse
             , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified deployment id does not exist."
+            , body = ErrorResponse),
     ),
     params(
-        ("connector_id" = Uuid, Path, description = "Unique connector identifier"),
+        ("deployment_id" = Uuid, Path, description = "Unique deployment identifier")
     ),
-    tag = "Connectors"
+    tag = "Deployments"
 )]
-#[get("/connectors/{connector_id}")]
-async fn get_connector(
+#[get("/deployments/{deployment_id}")]
+async fn get_deployment(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
 ) -> Result<HttpResponse, ManagerError> {
-    let connector_id = ConnectorId(parse_uuid_param(&req, "connector_id")?);
-    let descr = state
+    let deployment_id = DeploymentId(parse_uuid_param(&req, "deployment_id")?);
+    let deployment = state
         .db
         .lock()
         .await
-        .get_connector_by_id(*tenant_id, connector_id)
+        .get_deployment_by_id(*tenant_id, deployment_id)
         .await?;
+
     Ok(HttpResponse::Ok()
         .insert_header(CacheControl(vec![CacheDirective::NoCache]))
-        .json(&descr))
-}
-
-// Duplicate the same structure twice, since
-// these doc comments will be automatically used
-// in the OpenAPI spec.
-#[derive(Debug, Deserialize, IntoParams)]
-pub struct ConnectorIdOrNameQuery {
-    /// Unique connector identifier.
-    id: Option<Uuid>,
-    /// Unique connector name.
-    name: Option<String>,
-}
-
-#[derive(Debug, Deserialize, IntoParams)]
-pub struct ProgramIdOrNameQuery {
-    /// Unique program identifier.
-    id: Option<Uuid>,
-    /// Unique program name.
-    name: Option<String>,
+        .json(deployment))
 }
 
-#[derive(Debug, Deserialize, IntoParams)]
-pub struct WithCodeQuery {
-    /// Option to include the SQL program code or not
-    /// in the Program objects returned by the query.
-    /// If false (default), the returned program object
-    /// will not include the code.
-    with_code: Option<bool>,
+/// Request to create a new deployment.
+#[derive(Deserialize, ToSchema)]
+struct NewDeploymentRequest {
+    /// Deployment name.
+    name: String,
+    /// Deployment description.
+    description: String,
+    /// Pipelines of the deployment, in the order in which they are started.
+    /// Pipelines are shut down in reverse order.
+    pipelines: Vec<PipelineId>,
+    /// Runtime configuration shared by all pipelines of the deployment.
+    ///
+    /// When set, it replaces the configuration of each pipeline that is
+    /// deployed by the deployment's `start` action.
+    config: Option<RuntimeConfig>,
 }
 
-#[derive(Debug, Deserialize, IntoParams)]
-pub struct PipelineIdOrNameQuery {
-    /// Unique pipeline id.
-    id: Option<Uuid>,
-    /// Unique pipeline name.
-    name: Option<String>,
+/// Response to a deployment creation request.
+#[derive(Serialize, ToSchema)]
+struct NewDeploymentResponse {
+    /// Unique id assigned to the new deployment.
+    deployment_id: DeploymentId,
 }
 
-/// Push data to a SQL table.
-///
-/// The client sends data encoded using the format specified in the `?format=`
-/// parameter as a body of the request.  The contents of the data must match
-/// the SQL table schema specified in `table_name`
-///
-/// The pipeline ingests data as it arrives without waiting for the end of
-/// the request.  Successful HTTP response indicates that all data has been
-/// ingested successfully.
-// TODO: implement chunked and batch modes.
+/// Create a new deployment, i.e., a group of pipelines that are started and
+/// shut down together.
 #[utoipa::path(
+    request_body = NewDeploymentRequest,
     responses(
-        (status = OK
-            , description = "Data successfully delivered to the pipeline."
-            , content_type = "application/json"),
+        (status = OK, description = "Deployment successfully created.", body = NewDeploymentResponse),
         (status = BAD_REQUEST
-            , description = "Specified pipeline id is not a valid uuid."
-            , body = ErrorResponse
-            , example = json!(example_invalid_uuid_param())),
+            , description = "The deployment has no pipelines or lists a pipeline more than once."
+            , body = ErrorResponse),
         (status = NOT_FOUND
-            , description = "Specified pipeline id does not exist."
+            , description = "A pipeline of the deployment does not exist."
             , body = ErrorResponse
             , example = json!(example_unknown_pipeline())),
-        (status = NOT_FOUND
-            , description = "Specified table does not exist."
-            , body = ErrorResponse
-            , example = json!(example_unknown_input_table("MyTable"))),
-        (status = NOT_FOUND
-            , description = "Pipeline is not currently running because it has been shutdown or not yet started."
-            , body = ErrorResponse
-            , example = json!(example_pipeline_shutdown())),
-        (status = BAD_REQUEST
-            , description = "Unknown data format specified in the '?format=' argument."
+        (status = CONFLICT
+            , description = "A deployment with this name already exists."
             , body = ErrorResponse
-            , example = json!(example_unknown_input_format())),
+            , example = json!(example_duplicate_name())),
+    ),
+    tag = "Deployments"
+)]
+#[post("/deployments")]
+async fn new_deployment(
+    state: WebData<ServerState>,
+    tenant_id: ReqData<TenantId>,
+    request: web::Json<NewDeploymentRequest>,
+) -> Result<HttpResponse, ManagerError> {
+    if request.pipelines.is_empty() {
+        return Err(ManagerError::InvalidDeployment {
+            error: "a deployment must contain at least one pipeline".to_string(),
+        });
+    }
+    for (i, pipeline_id) in request.pipelines.iter().enumerate() {
+        if request.pipelines[..i].contains(pipeline_id) {
+            return Err(ManagerError::InvalidDeployment {
+                error: format!("pipeline '{pipeline_id}' is listed more than once"),
+            });
+        }
+    }
+
+    let db = state.db.lock().await;
+    for pipeline_id in request.pipelines.iter() {
+        db.get_pipeline_descr_by_id(*tenant_id, *pipeline_id)
+            .await?;
+    }
+    let deployment_id = db
+        .new_deployment(
+            *tenant_id,
+            Uuid::now_v7(),
+            &request.name,
+            &request.description,
+            &request.pipelines,
+            &request.config,
+        )
+        .await?;
+
+    info!("Created deployment {deployment_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .json(&NewDeploymentResponse { deployment_id }))
+}
+
+/// Delete a deployment.  The pipelines of the deployment are not affected.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Deployment successfully deleted."),
         (status = BAD_REQUEST
-            , description = "Error parsing input data."
+            , description = "Specified deployment id is not a valid uuid."
             , body = ErrorResponse
-            , example = json!(example_parse_errors())),
-        (status = INTERNAL_SERVER_ERROR
-            , description = "Request failed."
+            , example = json!(example_invalid_uuid_param())),
+        (status = NOT_FOUND
+            , description = "Specified deployment id does not exist."
             , body = ErrorResponse),
     ),
     params(
-        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier."),
-        ("table_name" = String, Path, description = "SQL table name."),
-        ("force" = bool, Query, description = "When `true`, push data to the pipeline even if the pipeline is paused. The default value is `false`"),
-        ("format" = String, Query, description = "Input data format, e.g., 'csv' or 'json'."),
-        ("array" = Option<bool>, Query, description = "Set to `true` if updates in this stream are packaged into JSON arrays (used in conjunction with `format=json`). The default values is `false`."),
-        ("update_format" = Option<JsonUpdateFormat>, Query, description = "JSON data change event format (used in conjunction with `format=json`).  The default value is 'insert_delete'."),
-    ),
-    tag = "Pipelines",
-    request_body(
-        content = String,
-        description = "Contains the new input data in CSV.",
-        content_type = "text/csv",
+        ("deployment_id" = Uuid, Path, description = "Unique deployment identifier")
     ),
+    tag = "Deployments"
 )]
-#[post("/pipelines/{pipeline_id}/ingress/{table_name}")]
-async fn http_input(
+#[delete("/deployments/{deployment_id}")]
+async fn delete_deployment(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
-    body: web::Payload,
 ) -> Result<HttpResponse, ManagerError> {
-    debug!("Received {req:?}");
-
-    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
-    debug!("Pipeline_id {:?}", pipeline_id);
-
-    let table_name = match req.match_info().get("table_name") {
-        None => {
-            return Err(ManagerError::MissingUrlEncodedParam {
-                param: "table_name",
-            });
-        }
-        Some(table_name) => table_name,
-    };
-    debug!("Table name {table_name:?}");
-
-    let endpoint = format!("ingress/{table_name}");
+    let deployment_id = DeploymentId(parse_uuid_param(&req, "deployment_id")?);
 
     state
-        .runner
-        .forward_to_pipeline_as_stream(*tenant_id, pipeline_id, &endpoint, req, body)
+        .db
+        .lock()
         .await
+        .delete_deployment(*tenant_id, deployment_id)
+        .await?;
+
+    info!("Deleted deployment {deployment_id} (tenant:{})", *tenant_id);
+    Ok(HttpResponse::Ok().finish())
 }
 
-/// Subscribe to a stream of updates from a SQL view or table.
+/// Start or shut down all pipelines of a deployment.
 ///
-/// The pipeline responds with a continuous stream of changes to the specified
-/// table or view, encoded using the format specified in the `?format=`
-/// parameter. Updates are split into `Chunk`'s.
+/// The following values of the `action` argument are accepted:
 ///
-/// The pipeline continuous sending updates until the client closes the
-/// connection or the pipeline is shut down.
+/// - 'start': Start the pipelines in order, waiting for each pipeline to
+///   run before starting the next one.  If the shared runtime configuration
+///   of the deployment is set, it replaces the configuration of pipelines
+///   that are not yet deployed.  If a pipeline fails to start, the pipelines
+///   started by this request are shut down and the error is returned.
+///
+/// - 'shutdown': Shut down the pipelines in reverse order, waiting for each
+///   pipeline to shut down before shutting down the previous one.
+///
+/// Unlike pipeline actions, deployment actions complete before the response
+/// is returned.
 #[utoipa::path(
     responses(
         (status = OK
-            , description = "Connection to the endpoint successfully established. The body of the response contains a stream of data chunks."
-            , content_type = "application/json"
-            , body = Chunk),
+            , description = "All pipelines of the deployment are running or have been shut down."),
         (status = BAD_REQUEST
-            , description = "Specified pipeline id is not a valid uuid."
-            , body = ErrorResponse
-            , example = json!(example_invalid_uuid_param())),
-        (status = NOT_FOUND
-            , description = "Specified pipeline id does not exist."
-            , body = ErrorResponse
-            , example = json!(example_unknown_pipeline())),
+            , description = "Invalid action, or the action cannot be applied to a pipeline of the deployment."
+            , body = ErrorResponse),
         (status = NOT_FOUND
-            , description = "Specified table or view does not exist."
-            , body = ErrorResponse
-            , example = json!(example_unknown_output_table("MyTable"))),
-        (status = GONE
-            , description = "Pipeline is not currently running because it has been shutdown or not yet started."
-            , body = ErrorResponse
-            , example = json!(example_pipeline_shutdown())),
-        (status = BAD_REQUEST
-            , description = "Unknown data format specified in the '?format=' argument."
-            , body = ErrorResponse
-            , example = json!(example_unknown_output_format())),
+            , description = "Specified deployment id or one of its pipelines does not exist."
+            , body = ErrorResponse),
         (status = INTERNAL_SERVER_ERROR
-            , description = "Request failed."
+            , description = "A pipeline failed to start or shut down in time."
             , body = ErrorResponse),
     ),
     params(
-        ("pipeline_id" = Uuid, Path, description = "Unique pipeline identifier."),
-        ("table_name" = String, Path, description = "SQL table or view name."),
-        ("format" = String, Query, description = "Output data format, e.g., 'csv' or 'json'."),
-        ("query" = Option<OutputQuery>, Query, description = "Query to execute on the table. Must be one of 'table', 'neighborhood', or 'quantiles'. The default value is 'table'"),
-        ("mode" = Option<EgressMode>, Query, description = "Output mode. Must be one of 'watch' or 'snapshot'. The default value is 'watch'"),
-        ("quantiles" = Option<u32>, Query, description = "For 'quantiles' queries: the number of quantiles to output. The default value is 100."),
-        ("array" = Option<bool>, Query, description = "Set to `true` to group updates in this stream into JSON arrays (used in conjunction with `format=json`). The default value is `false`"),
-    ),
-    request_body(
-        content = Option<NeighborhoodQuery>,
-        description = "When the `query` parameter is set to 'neighborhood', the body of the request must contain a neighborhood specification.",
-        content_type = "application/json",
+        ("deployment_id" = Uuid, Path, description = "Unique deployment identifier"),
+        ("action" = String, Path, description = "Deployment action [start, shutdown]")
     ),
-    tag = "Pipelines"
+    tag = "Deployments"
 )]
-#[post("/pipelines/{pipeline_id}/egress/{table_name}")]
-async fn http_output(
+#[post("/deployments/{deployment_id}/{action}")]
+async fn deployment_action(
     state: WebData<ServerState>,
     tenant_id: ReqData<TenantId>,
     req: HttpRequest,
-    body: web::Payload,
 ) -> Result<HttpResponse, ManagerError> {
-    debug!("Received {req:?}");
-
-    let pipeline_id = PipelineId(parse_uuid_param(&req, "pipeline_id")?);
-    debug!("Pipeline_id {:?}", pipeline_id);
+    let deployment_id = DeploymentId(parse_uuid_param(&req, "deployment_id")?);
+    let action = parse_pipeline_action(&req)?;
 
-    let table_name = match req.match_info().get("table_name") {
-        None => {
-            return Err(ManagerError::MissingUrlEncodedParam {
-                param: "table_name",
-            });
+    match action {
+        "start" => {
+            state
+                .runner
+                .start_deployment(*tenant_id, deployment_id)
+                .await?
         }
-        Some(table_name) => table_name,
-    };
-    debug!("Table name {table_name:?}");
-
-    let endpoint = format!("egress/{table_name}");
+        "shutdown" => {
+            state
+                .runner
+                .shutdown_deployment(*tenant_id, deployment_id)
+                .await?
+        }
+        _ => Err(ManagerError::InvalidDeploymentAction {
+            action: action.to_string(),
+        })?,
+    }
 
-    state
-        .runner
-        .forward_to_pipeline_as_stream(*tenant_id, pipeline_id, &endpoint, req, body)
-        .await
+    info!(
+        "Completed '{action}' action for deployment {deployment_id} (tenant:{})",
+        *tenant_id
+    );
+    Ok(HttpResponse::Ok().finish())
 }
@@ -0,0 +1,631 @@
+//! Declarative provisioning of programs, connectors, and pipelines.
+//!
+//! A [`Manifest`] describes the complete desired set of programs, connectors,
+//! and pipelines of a tenant, keyed by name.  [`plan`] compares the manifest
+//! against the current state of the database and computes the list of
+//! [`Change`]s needed to converge, and [`apply`] executes them:
+//!
+//! * objects that are in the manifest but not in the database are created,
+//! * objects whose description, code, or configuration differ from the
+//!   manifest are updated,
+//! * objects that are not in the manifest are deleted.
+//!
+//! Changes are executed one at a time in dependency order: programs and
+//! connectors are created and updated before pipelines that reference them,
+//! and pipelines are deleted before the programs and connectors they use.
+//! Execution is not atomic: if a change fails, changes executed before it
+//! remain in effect.  Since applying a manifest is idempotent, the client can
+//! fix the cause of the error and apply the same manifest again.
+
+use crate::{
+    api::ManagerError,
+    auth::TenantId,
+    db::{
+        storage::Storage, AttachedConnector, ConnectorDescr, ConnectorId, Pipeline, ProgramDescr,
+        ProgramId, ProjectDB,
+    },
+    runner::RunnerApi,
+};
+use dbsp_adapters::{ConnectorConfig, RuntimeConfig};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Desired state of all programs, connectors, and pipelines of a tenant.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub(crate) struct Manifest {
+    /// Programs, indexed by name.
+    #[serde(default)]
+    pub programs: BTreeMap<String, ProgramSpec>,
+    /// Connectors, indexed by name.
+    #[serde(default)]
+    pub connectors: BTreeMap<String, ConnectorSpec>,
+    /// Pipelines, indexed by name.
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, PipelineSpec>,
+}
+
+/// Desired state of a program.
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct ProgramSpec {
+    /// Program description.
+    pub description: String,
+    /// SQL code of the program.
+    pub code: String,
+}
+
+/// Desired state of a connector.
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct ConnectorSpec {
+    /// Connector description.
+    pub description: String,
+    /// Connector configuration.
+    pub config: ConnectorConfig,
+}
+
+/// Desired state of a pipeline.
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct PipelineSpec {
+    /// Pipeline description.
+    pub description: String,
+    /// Name of the program to run in the pipeline.  Must be declared in the
+    /// same manifest.
+    pub program: Option<String>,
+    /// Pipeline configuration parameters.
+    pub config: RuntimeConfig,
+    /// Attached connectors.
+    #[serde(default)]
+    pub connectors: Vec<AttachedConnectorSpec>,
+}
+
+/// Connector attached to a pipeline in a [`PipelineSpec`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, ToSchema)]
+pub(crate) struct AttachedConnectorSpec {
+    /// A unique identifier for this attachement.
+    pub name: String,
+    /// Name of the connector to attach.  Must be declared in the same
+    /// manifest.
+    pub connector: String,
+    /// Is this an input or an output?
+    pub is_input: bool,
+    /// The table or view this connector is attached to.
+    pub relation_name: String,
+}
+
+/// Type of object affected by a [`Change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ObjectKind {
+    Program,
+    Connector,
+    Pipeline,
+}
+
+/// Operation performed by a [`Change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A single change needed to converge the database to a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub(crate) struct Change {
+    pub kind: ObjectKind,
+    pub action: ChangeAction,
+    /// Name of the affected object.
+    pub name: String,
+}
+
+impl Change {
+    fn new(kind: ObjectKind, action: ChangeAction, name: &str) -> Self {
+        Self {
+            kind,
+            action,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// Current state of the database and the changes needed to converge to
+/// a manifest.
+pub(crate) struct Plan {
+    /// Changes in the order they must be executed.
+    pub changes: Vec<Change>,
+    programs: BTreeMap<String, ProgramDescr>,
+    connectors: BTreeMap<String, ConnectorDescr>,
+    pipelines: BTreeMap<String, Pipeline>,
+}
+
+/// Compute the changes needed to converge the database to `manifest`.
+///
+/// Fails if the manifest references programs or connectors it doesn't
+/// declare.
+pub(crate) async fn plan(
+    db: &ProjectDB,
+    tenant_id: TenantId,
+    manifest: &Manifest,
+) -> Result<Plan, ManagerError> {
+    validate(manifest)?;
+
+    let programs: BTreeMap<String, ProgramDescr> = db
+        .list_programs(tenant_id, true)
+        .await?
+        .into_iter()
+        .map(|program| (program.name.clone(), program))
+        .collect();
+    let connectors: BTreeMap<String, ConnectorDescr> = db
+        .list_connectors(tenant_id)
+        .await?
+        .into_iter()
+        .map(|connector| (connector.name.clone(), connector))
+        .collect();
+    let pipelines: BTreeMap<String, Pipeline> = db
+        .list_pipelines(tenant_id)
+        .await?
+        .into_iter()
+        .map(|pipeline| (pipeline.descriptor.name.clone(), pipeline))
+        .collect();
+
+    // Programs used by pipelines in the trash cannot be deleted until the
+    // pipelines are purged.  We leave them in place; they will be deleted by
+    // the first `apply` after the purge.
+    let programs_in_trash: Vec<ProgramId> = db
+        .list_deleted_pipelines(tenant_id)
+        .await?
+        .into_iter()
+        .filter_map(|pipeline| pipeline.descriptor.program_id)
+        .collect();
+
+    let program_names: BTreeMap<ProgramId, &str> = programs
+        .values()
+        .map(|program| (program.program_id, program.name.as_str()))
+        .collect();
+    let connector_names: BTreeMap<ConnectorId, &str> = connectors
+        .values()
+        .map(|connector| (connector.connector_id, connector.name.as_str()))
+        .collect();
+
+    let mut changes = Vec::new();
+
+    for (name, spec) in manifest.programs.iter() {
+        match programs.get(name) {
+            None => changes.push(Change::new(ObjectKind::Program, ChangeAction::Create, name)),
+            Some(program) => {
+                if program.description != spec.description
+                    || program.code.as_deref() != Some(spec.code.as_str())
+                {
+                    changes.push(Change::new(ObjectKind::Program, ChangeAction::Update, name));
+                }
+            }
+        }
+    }
+
+    for (name, spec) in manifest.connectors.iter() {
+        match connectors.get(name) {
+            None => changes.push(Change::new(
+                ObjectKind::Connector,
+                ChangeAction::Create,
+                name,
+            )),
+            Some(connector) => {
+                if connector.description != spec.description || connector.config != spec.config {
+                    changes.push(Change::new(
+                        ObjectKind::Connector,
+                        ChangeAction::Update,
+                        name,
+                    ));
+                }
+            }
+        }
+    }
+
+    for (name, spec) in manifest.pipelines.iter() {
+        match pipelines.get(name) {
+            None => changes.push(Change::new(
+                ObjectKind::Pipeline,
+                ChangeAction::Create,
+                name,
+            )),
+            Some(pipeline) => {
+                let descr = &pipeline.descriptor;
+                let program = descr
+                    .program_id
+                    .and_then(|program_id| program_names.get(&program_id).copied());
+
+                let mut current_connectors: Vec<AttachedConnectorSpec> = descr
+                    .attached_connectors
+                    .iter()
+                    .map(|ac| AttachedConnectorSpec {
+                        name: ac.name.clone(),
+                        connector: connector_names
+                            .get(&ac.connector_id)
+                            .map(|name| name.to_string())
+                            .unwrap_or_default(),
+                        is_input: ac.is_input,
+                        relation_name: ac.relation_name.clone(),
+                    })
+                    .collect();
+                current_connectors.sort();
+                let mut desired_connectors = spec.connectors.clone();
+                desired_connectors.sort();
+
+                // A program or connector that is about to be updated doesn't
+                // change its name, so comparing names is sufficient here.
+                if descr.description != spec.description
+                    || program != spec.program.as_deref()
+                    || descr.config != spec.config
+                    || current_connectors != desired_connectors
+                {
+                    changes.push(Change::new(
+                        ObjectKind::Pipeline,
+                        ChangeAction::Update,
+                        name,
+                    ));
+                }
+            }
+        }
+    }
+
+    for name in pipelines.keys() {
+        if !manifest.pipelines.contains_key(name) {
+            changes.push(Change::new(
+                ObjectKind::Pipeline,
+                ChangeAction::Delete,
+                name,
+            ));
+        }
+    }
+
+    for name in connectors.keys() {
+        if !manifest.connectors.contains_key(name) {
+            changes.push(Change::new(
+                ObjectKind::Connector,
+                ChangeAction::Delete,
+                name,
+            ));
+        }
+    }
+
+    for (name, program) in programs.iter() {
+        if !manifest.programs.contains_key(name) && !programs_in_trash.contains(&program.program_id)
+        {
+            changes.push(Change::new(ObjectKind::Program, ChangeAction::Delete, name));
+        }
+    }
+
+    Ok(Plan {
+        changes,
+        programs,
+        connectors,
+        pipelines,
+    })
+}
+
+/// Check that all programs and connectors referenced by pipelines are
+/// declared in the manifest.
+fn validate(manifest: &Manifest) -> Result<(), ManagerError> {
+    for (name, pipeline) in manifest.pipelines.iter() {
+        if let Some(program) = &pipeline.program {
+            if !manifest.programs.contains_key(program) {
+                return Err(ManagerError::InvalidManifest {
+                    error: format!(
+                        "pipeline '{name}' references program '{program}', which is not declared in the manifest"
+                    ),
+                });
+            }
+        }
+        for ac in pipeline.connectors.iter() {
+            if !manifest.connectors.contains_key(&ac.connector) {
+                return Err(ManagerError::InvalidManifest {
+                    error: format!(
+                        "pipeline '{name}' references connector '{}', which is not declared in the manifest",
+                        ac.connector
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute the changes in `plan`.
+///
+/// Created and updated programs are queued for compilation.
+pub(crate) async fn apply(
+    db: &Arc<Mutex<ProjectDB>>,
+    runner: &RunnerApi,
+    tenant_id: TenantId,
+    manifest: &Manifest,
+    plan: Plan,
+) -> Result<(), ManagerError> {
+    let mut program_ids: BTreeMap<String, ProgramId> = plan
+        .programs
+        .iter()
+        .map(|(name, program)| (name.clone(), program.program_id))
+        .collect();
+    let mut connector_ids: BTreeMap<String, ConnectorId> = plan
+        .connectors
+        .iter()
+        .map(|(name, connector)| (name.clone(), connector.connector_id))
+        .collect();
+
+    for change in plan.changes.iter() {
+        let name = change.name.as_str();
+        match (change.kind, change.action) {
+            (ObjectKind::Program, ChangeAction::Create) => {
+                let spec = &manifest.programs[name];
+                let db = db.lock().await;
+                let (program_id, version) = db
+                    .new_program(
+                        tenant_id,
+                        Uuid::now_v7(),
+                        name,
+                        &spec.description,
+                        &spec.code,
+                    )
+                    .await?;
+                db.prepare_program_for_compilation(tenant_id, program_id, version)
+                    .await?;
+                program_ids.insert(name.to_string(), program_id);
+            }
+            (ObjectKind::Program, ChangeAction::Update) => {
+                let spec = &manifest.programs[name];
+                let program_id = plan.programs[name].program_id;
+                let db = db.lock().await;
+                let version = db
+                    .update_program(
+                        tenant_id,
+                        program_id,
+                        name,
+                        &spec.description,
+                        &Some(spec.code.clone()),
+                    )
+                    .await?;
+                db.prepare_program_for_compilation(tenant_id, program_id, version)
+                    .await?;
+            }
+            (ObjectKind::Program, ChangeAction::Delete) => {
+                let program_id = plan.programs[name].program_id;
+                db.lock()
+                    .await
+                    .delete_program(tenant_id, program_id)
+                    .await?;
+            }
+            (ObjectKind::Connector, ChangeAction::Create) => {
+                let spec = &manifest.connectors[name];
+                let connector_id = db
+                    .lock()
+                    .await
+                    .new_connector(
+                        tenant_id,
+                        Uuid::now_v7(),
+                        name,
+                        &spec.description,
+                        &spec.config,
+                    )
+                    .await?;
+                connector_ids.insert(name.to_string(), connector_id);
+            }
+            (ObjectKind::Connector, ChangeAction::Update) => {
+                let spec = &manifest.connectors[name];
+                db.lock()
+                    .await
+                    .update_connector(
+                        tenant_id,
+                        plan.connectors[name].connector_id,
+                        name,
+                        &spec.description,
+                        &Some(spec.config.clone()),
+                    )
+                    .await?;
+            }
+            (ObjectKind::Connector, ChangeAction::Delete) => {
+                db.lock()
+                    .await
+                    .delete_connector(tenant_id, plan.connectors[name].connector_id)
+                    .await?;
+            }
+            (ObjectKind::Pipeline, ChangeAction::Create) => {
+                let spec = &manifest.pipelines[name];
+                let program_id = spec.program.as_ref().map(|program| program_ids[program]);
+                let connectors = attached_connectors(spec, &connector_ids);
+                db.lock()
+                    .await
+                    .new_pipeline(
+                        tenant_id,
+                        Uuid::now_v7(),
+                        program_id,
+                        name,
+                        &spec.description,
+                        &spec.config,
+                        &Some(connectors),
+                    )
+                    .await?;
+            }
+            (ObjectKind::Pipeline, ChangeAction::Update) => {
+                let spec = &manifest.pipelines[name];
+                let program_id = spec.program.as_ref().map(|program| program_ids[program]);
+                let connectors = attached_connectors(spec, &connector_ids);
+                db.lock()
+                    .await
+                    .update_pipeline(
+                        tenant_id,
+                        plan.pipelines[name].descriptor.pipeline_id,
+                        program_id,
+                        name,
+                        &spec.description,
+                        &Some(spec.config.clone()),
+                        &Some(connectors),
+                    )
+                    .await?;
+            }
+            (ObjectKind::Pipeline, ChangeAction::Delete) => {
+                runner
+                    .delete_pipeline(tenant_id, plan.pipelines[name].descriptor.pipeline_id)
+                    .await?;
+            }
+        }
+        info!(
+            "Applied change {:?} {:?} '{name}' (tenant:{})",
+            change.action, change.kind, tenant_id
+        );
+    }
+
+    Ok(())
+}
+
+fn attached_connectors(
+    spec: &PipelineSpec,
+    connector_ids: &BTreeMap<String, ConnectorId>,
+) -> Vec<AttachedConnector> {
+    spec.connectors
+        .iter()
+        .map(|ac| AttachedConnector {
+            name: ac.name.clone(),
+            is_input: ac.is_input,
+            connector_id: connector_ids[&ac.connector],
+            relation_name: ac.relation_name.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, plan, Change, ChangeAction, Manifest, ObjectKind};
+    use crate::auth::TenantRecord;
+    use crate::db::storage::Storage;
+    use crate::runner::RunnerApi;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn manifest(with_pipeline: bool) -> Manifest {
+        let mut manifest = serde_json::json!({
+            "programs": {
+                "program1": {
+                    "description": "",
+                    "code": "CREATE TABLE t1(c1 INTEGER);"
+                }
+            },
+            "connectors": {
+                "connector1": {
+                    "description": "",
+                    "config": {
+                        "transport": {
+                            "name": "file",
+                            "config": { "path": "input.csv" }
+                        },
+                        "format": { "name": "csv" }
+                    }
+                }
+            }
+        });
+        if with_pipeline {
+            manifest["pipelines"] = serde_json::json!({
+                "pipeline1": {
+                    "description": "",
+                    "program": "program1",
+                    "config": { "workers": 2 },
+                    "connectors": [{
+                        "name": "input1",
+                        "connector": "connector1",
+                        "is_input": true,
+                        "relation_name": "t1"
+                    }]
+                }
+            });
+        }
+        serde_json::from_value(manifest).unwrap()
+    }
+
+    #[tokio::test]
+    async fn apply_manifest() {
+        let (conn, _temp) = crate::db::test::setup_pg().await;
+        let db = Arc::new(Mutex::new(conn));
+        let runner = RunnerApi::new(db.clone());
+        let tenant_id = TenantRecord::default().id;
+
+        let manifest = manifest(true);
+        let p = plan(&*db.lock().await, tenant_id, &manifest).await.unwrap();
+        assert_eq!(
+            p.changes,
+            vec![
+                Change::new(ObjectKind::Program, ChangeAction::Create, "program1"),
+                Change::new(ObjectKind::Connector, ChangeAction::Create, "connector1"),
+                Change::new(ObjectKind::Pipeline, ChangeAction::Create, "pipeline1"),
+            ]
+        );
+        apply(&db, &runner, tenant_id, &manifest, p).await.unwrap();
+
+        // Applying the same manifest again is a no-op.
+        let p = plan(&*db.lock().await, tenant_id, &manifest).await.unwrap();
+        assert!(p.changes.is_empty());
+
+        let pipeline = db
+            .lock()
+            .await
+            .get_pipeline_by_name(tenant_id, "pipeline1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(pipeline.descriptor.config.workers, 2);
+        assert_eq!(pipeline.descriptor.attached_connectors.len(), 1);
+
+        // Remove the pipeline and change the program.
+        let mut manifest = manifest(false);
+        manifest.programs.get_mut("program1").unwrap().code =
+            "CREATE TABLE t1(c1 INTEGER, c2 INTEGER);".to_string();
+        let p = plan(&*db.lock().await, tenant_id, &manifest).await.unwrap();
+        assert_eq!(
+            p.changes,
+            vec![
+                Change::new(ObjectKind::Program, ChangeAction::Update, "program1"),
+                Change::new(ObjectKind::Pipeline, ChangeAction::Delete, "pipeline1"),
+            ]
+        );
+        apply(&db, &runner, tenant_id, &manifest, p).await.unwrap();
+        assert!(db
+            .lock()
+            .await
+            .list_pipelines(tenant_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Remove everything.  The program is kept while the deleted pipeline
+        // that uses it is in the trash.
+        let manifest = Manifest::default();
+        let p = plan(&*db.lock().await, tenant_id, &manifest).await.unwrap();
+        assert_eq!(
+            p.changes,
+            vec![Change::new(
+                ObjectKind::Connector,
+                ChangeAction::Delete,
+                "connector1"
+            )]
+        );
+        apply(&db, &runner, tenant_id, &manifest, p).await.unwrap();
+        assert!(db
+            .lock()
+            .await
+            .list_connectors(tenant_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn undeclared_reference() {
+        let (conn, _temp) = crate::db::test::setup_pg().await;
+        let tenant_id = TenantRecord::default().id;
+
+        let mut manifest = manifest(true);
+        manifest.connectors.clear();
+        assert!(plan(&conn, tenant_id, &manifest).await.is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Storage for executables built by remote compile workers.
+//!
+//! The artifact store is configured via
+//! [`CompilerConfig::artifact_store`](`crate::config::CompilerConfig::artifact_store`)
+//! and shared by the compiler service, compile workers, and runners: workers
+//! upload the executables they build, the compiler records the URL of each
+//! executable as the binary reference of its program, and runners download
+//! executables from these URLs.
+//!
+//! The store is an object store bucket (`s3://`, `gs://`, or `az://`) or a
+//! local directory, which must be shared by all hosts, e.g., via a network
+//! file system.
+
+use crate::config::CompilerConfig;
+use crate::db::{ProgramId, Version};
+use crate::error::ManagerError;
+use dbsp_adapters::transport::open_object_store;
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use std::sync::Arc;
+
+pub(crate) struct ArtifactStore {
+    /// Location of the store, without a trailing `/`.
+    location: String,
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+}
+
+impl ArtifactStore {
+    pub(crate) fn open(location: &str) -> Result<Self, ManagerError> {
+        let (store, prefix) = open_object_store(location).map_err(artifact_store_error)?;
+        Ok(Self {
+            location: location.trim_end_matches('/').to_string(),
+            store,
+            prefix,
+        })
+    }
+
+    fn executable_path(&self, program_id: ProgramId, version: Version) -> ObjectPath {
+        self.prefix
+            .child(CompilerConfig::binary_name(program_id, version))
+    }
+
+    /// URL of the executable of a program version, to be recorded as its
+    /// binary reference.
+    pub(crate) fn executable_url(&self, program_id: ProgramId, version: Version) -> String {
+        let name = CompilerConfig::binary_name(program_id, version);
+        if is_object_store_url(&self.location) {
+            format!("{}/{name}", self.location)
+        } else {
+            format!("file://{}/{name}", self.location)
+        }
+    }
+
+    pub(crate) async fn put_executable(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+        executable: Vec<u8>,
+    ) -> Result<(), ManagerError> {
+        self.store
+            .put(
+                &self.executable_path(program_id, version),
+                executable.into(),
+            )
+            .await
+            .map_err(artifact_store_error)?;
+        Ok(())
+    }
+
+    pub(crate) async fn has_executable(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<bool, ManagerError> {
+        match self
+            .store
+            .head(&self.executable_path(program_id, version))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(artifact_store_error(e)),
+        }
+    }
+
+    /// Download the object at `url`, which must be an object store URL.
+    pub(crate) async fn fetch(url: &str) -> Result<Vec<u8>, ManagerError> {
+        let (location, name) = url
+            .rsplit_once('/')
+            .filter(|_| is_object_store_url(url))
+            .ok_or_else(|| artifact_store_error(format!("invalid artifact URL '{url}'")))?;
+        let (store, prefix) = open_object_store(location).map_err(artifact_store_error)?;
+        let object = store
+            .get(&prefix.child(name))
+            .await
+            .map_err(artifact_store_error)?;
+        Ok(object.bytes().await.map_err(artifact_store_error)?.to_vec())
+    }
+}
+
+/// Returns `true` if `url` refers to an object store rather than a local
+/// directory.
+pub(crate) fn is_object_store_url(url: &str) -> bool {
+    ["s3://", "gs://", "az://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+fn artifact_store_error<E: ToString>(error: E) -> ManagerError {
+    ManagerError::ArtifactStoreError {
+        error: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArtifactStore;
+    use crate::db::{ProgramId, Version};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn local_artifact_store() {
+        let dir = TempDir::new().unwrap();
+        let location = dir.path().display().to_string();
+        let store = ArtifactStore::open(&location).unwrap();
+        let pid = ProgramId(Uuid::nil());
+
+        assert!(!store.has_executable(pid, Version(1)).await.unwrap());
+        store
+            .put_executable(pid, Version(1), b"binary".to_vec())
+            .await
+            .unwrap();
+        assert!(store.has_executable(pid, Version(1)).await.unwrap());
+        assert!(!store.has_executable(pid, Version(2)).await.unwrap());
+
+        let url = store.executable_url(pid, Version(1));
+        let path = url.strip_prefix("file://").unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"binary");
+    }
+}
@@ -68,6 +68,7 @@ use crate::db::{storage::Storage, ApiPermission, DBError, ProjectDB};
 // Used when no auth is configured, so we tag the request with the default user
 // and passthrough
 pub(crate) fn tag_with_default_tenant_id(req: ServiceRequest) -> ServiceRequest {
+    crate::logging::set_tenant_id(DEFAULT_TENANT_ID);
     req.extensions_mut().insert(DEFAULT_TENANT_ID);
     req.extensions_mut()
         .insert(vec![ApiPermission::Read, ApiPermission::Write]);
@@ -109,6 +110,7 @@ async fn bearer_auth(
 
             match tenant {
                 Ok(tenant_id) => {
+                    crate::logging::set_tenant_id(tenant_id);
                     req.extensions_mut().insert(tenant_id);
                     req.extensions_mut()
                         .insert(vec![ApiPermission::Read, ApiPermission::Write]);
@@ -169,6 +171,7 @@ async fn api_key_auth(
             };
             match validate {
                 Ok((tenant_id, permissions)) => {
+                    crate::logging::set_tenant_id(tenant_id);
                     req.extensions_mut().insert(tenant_id);
                     req.extensions_mut().insert(permissions);
                     Ok(req)
@@ -584,7 +587,7 @@ mod test {
     use crate::{
         api::ServerState,
         auth::{self, fetch_jwk_aws_cognito_keys, AuthConfiguration, AwsCognitoClaim, Provider},
-        config::ApiServerConfig,
+        config::{ApiServerConfig, LogFormat},
         db::{storage::Storage, ApiPermission},
     };
 
@@ -659,6 +662,10 @@ mod test {
             dev_mode: false,
             dump_openapi: false,
             config_file: None,
+            trash_retention_days: 7,
+            alerting_config: None,
+            log_format: LogFormat::Text,
+            lite: false,
         };
 
         let (conn, _temp) = crate::db::test::setup_pg().await;
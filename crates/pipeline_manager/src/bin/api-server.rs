@@ -11,8 +11,6 @@ use tokio::sync::Mutex;
 // Entrypoint to bring up a standalone api-server.
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let name = "[api-server]".magenta();
-    pipeline_manager::logging::init_logging(name);
     let cli = Command::new("Feldera API server");
     let cli = DatabaseConfig::augment_args(cli);
     let cli = ApiServerConfig::augment_args(cli);
@@ -24,6 +22,8 @@ async fn main() -> anyhow::Result<()> {
     let api_config = ApiServerConfig::from_arg_matches(&matches)
         .map_err(|err| err.exit())
         .unwrap();
+    let name = "[api-server]".magenta();
+    pipeline_manager::logging::init_logging(name, api_config.log_format);
     let api_config = api_config.canonicalize().unwrap();
     let db = ProjectDB::connect(
         &database_config,
@@ -0,0 +1,34 @@
+use clap::{Args, Command, FromArgMatches};
+
+use colored::Colorize;
+use pipeline_manager::compile_worker::CompileWorker;
+use pipeline_manager::config::{CompileWorkerConfig, CompilerConfig, LogFormat};
+use tokio::select;
+use tokio::signal::unix::{signal, SignalKind};
+
+// Entrypoint to bring up a remote compile worker.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let name = "[compile-worker]".cyan();
+    pipeline_manager::logging::init_logging(name, LogFormat::Text);
+    let cli = Command::new("Feldera remote compile worker");
+    let cli = CompilerConfig::augment_args(cli);
+    let cli = CompileWorkerConfig::augment_args(cli);
+    let matches = cli.get_matches();
+
+    let compiler_config = CompilerConfig::from_arg_matches(&matches)
+        .map_err(|err| err.exit())
+        .unwrap();
+    let compiler_config = compiler_config.canonicalize().unwrap();
+    let worker_config = CompileWorkerConfig::from_arg_matches(&matches)
+        .map_err(|err| err.exit())
+        .unwrap();
+
+    let mut sigterm = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    select! {
+        r = CompileWorker::run(&compiler_config, &worker_config) => r?,
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+    Ok(())
+}
@@ -4,16 +4,17 @@ use clap::{Args, Command, FromArgMatches};
 
 use colored::Colorize;
 use pipeline_manager::compiler::Compiler;
-use pipeline_manager::config::{CompilerConfig, DatabaseConfig};
+use pipeline_manager::config::{CompilerConfig, DatabaseConfig, LogFormat};
 use pipeline_manager::db::ProjectDB;
-use tokio::spawn;
-use tokio::sync::Mutex;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{watch, Mutex};
+use tokio::{select, spawn};
 
 // Entrypoint to bring up the standalone compiler service.
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let name = "[compiler]".cyan();
-    pipeline_manager::logging::init_logging(name);
+    pipeline_manager::logging::init_logging(name, LogFormat::Text);
     let cli = Command::new("Feldera compiler service");
     let cli = DatabaseConfig::augment_args(cli);
     let cli = CompilerConfig::augment_args(cli);
@@ -39,11 +40,18 @@ async fn main() -> anyhow::Result<()> {
     .await
     .unwrap();
     let db = Arc::new(Mutex::new(db));
-    let _compiler = spawn(async move {
-        Compiler::run(&compiler_config.clone(), db).await.unwrap();
+    let (shutdown_sender, shutdown) = watch::channel(false);
+    let compiler = spawn(async move {
+        Compiler::run(&compiler_config.clone(), db, shutdown)
+            .await
+            .unwrap();
     });
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to listen for ctrl-c event");
+    let mut sigterm = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+    let _ = shutdown_sender.send(true);
+    compiler.await?;
     Ok(())
 }
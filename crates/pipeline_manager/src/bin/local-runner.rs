@@ -4,7 +4,7 @@ use clap::{Args, Command, FromArgMatches};
 
 use colored::Colorize;
 
-use pipeline_manager::config::{DatabaseConfig, LocalRunnerConfig};
+use pipeline_manager::config::{DatabaseConfig, LocalRunnerConfig, LogFormat};
 use pipeline_manager::db::ProjectDB;
 use pipeline_manager::local_runner;
 use tokio::spawn;
@@ -14,7 +14,7 @@ use tokio::sync::Mutex;
 #[tokio::main]
 async fn main() {
     let name = "[local-runner]".cyan();
-    pipeline_manager::logging::init_logging(name);
+    pipeline_manager::logging::init_logging(name, LogFormat::Text);
     let cli = Command::new("Feldera local runner service");
     let cli = DatabaseConfig::augment_args(cli);
     let cli = LocalRunnerConfig::augment_args(cli);
@@ -11,17 +11,13 @@ use pipeline_manager::config::{
 use pipeline_manager::db::ProjectDB;
 use pipeline_manager::local_runner;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use utoipa::OpenApi;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Stay in single-threaded mode (no tokio) until calling `daemonize`.
 
-    // Create env logger.
-    let name = "[manager]".cyan();
-    pipeline_manager::logging::init_logging(name);
-
     let cli = Command::new("Pipeline manager CLI");
     let cli = DatabaseConfig::augment_args(cli);
     let cli = ApiServerConfig::augment_args(cli);
@@ -47,13 +43,29 @@ async fn main() -> anyhow::Result<()> {
             anyhow::Error::msg(format!("error parsing config file '{config_file}': {e}"))
         })?;
     }
-    let compiler_config = CompilerConfig::from_arg_matches(&matches)
+
+    // Create env logger.
+    let name = "[manager]".cyan();
+    pipeline_manager::logging::init_logging(name, api_config.log_format);
+
+    let mut compiler_config = CompilerConfig::from_arg_matches(&matches)
         .map_err(|err| err.exit())
         .unwrap();
-    let local_runner_config = LocalRunnerConfig::from_arg_matches(&matches)
+    let mut local_runner_config = LocalRunnerConfig::from_arg_matches(&matches)
         .map_err(|err| err.exit())
         .unwrap();
 
+    // Lite mode: embedded database, JIT compilation, in-process pipelines.
+    if api_config.lite {
+        if !cfg!(feature = "lite") {
+            return Err(anyhow::Error::msg(
+                "lite mode is not available: the manager was built without the 'lite' feature",
+            ));
+        }
+        compiler_config.jit = true;
+        local_runner_config.in_process = true;
+    }
+
     let api_config = api_config.canonicalize()?;
     let compiler_config = compiler_config.canonicalize()?;
     let local_runner_config = local_runner_config.canonicalize()?;
@@ -61,9 +73,13 @@ async fn main() -> anyhow::Result<()> {
         Compiler::precompile_dependencies(&compiler_config).await?;
         return Ok(());
     }
-    let database_config = DatabaseConfig::from_arg_matches(&matches)
+    let mut database_config = DatabaseConfig::from_arg_matches(&matches)
         .map_err(|err| err.exit())
         .unwrap();
+    if api_config.lite {
+        database_config.db_connection_string =
+            format!("sqlite://{}", api_config.lite_database_path().display());
+    }
     let db: ProjectDB = ProjectDB::connect(
         &database_config,
         #[cfg(feature = "pg-embed")]
@@ -73,8 +89,9 @@ async fn main() -> anyhow::Result<()> {
     .unwrap();
     let db = Arc::new(Mutex::new(db));
     let db_clone = db.clone();
-    let _compiler = tokio::spawn(async move {
-        Compiler::run(&compiler_config.clone(), db_clone)
+    let (shutdown_sender, shutdown) = watch::channel(false);
+    let compiler = tokio::spawn(async move {
+        Compiler::run(&compiler_config.clone(), db_clone, shutdown)
             .await
             .unwrap();
     });
@@ -82,7 +99,11 @@ async fn main() -> anyhow::Result<()> {
     let _local_runner = tokio::spawn(async move {
         local_runner::run(db_clone, &local_runner_config.clone()).await;
     });
-    // The api-server blocks forever
+    // The api-server runs until the process is signalled to terminate, at
+    // which point it stops accepting connections and completes in-flight
+    // requests.  Then drain the compiler before exiting.
     pipeline_manager::api::run(db, api_config).await.unwrap();
+    let _ = shutdown_sender.send(true);
+    compiler.await?;
     Ok(())
 }
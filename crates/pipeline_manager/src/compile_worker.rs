@@ -0,0 +1,430 @@
+//! Remote compile workers.
+//!
+//! Building the Rust code generated for a program is CPU-heavy.  When the
+//! compiler service runs with
+//! [`CompilerConfig::remote_workers`](`crate::config::CompilerConfig::remote_workers`)
+//! set, it still runs the SQL compiler itself, but queues the subsequent
+//! `cargo build` for remote workers instead of running it locally.
+//!
+//! Workers pull jobs from the HTTP server the compiler uses to serve binaries
+//! to runners:
+//!
+//! * `POST /jobs/claim` returns the next queued [`RemoteJob`], or `204 No
+//!   Content` if there is none.
+//! * `POST /jobs/{program_id}/{version}/binary` reports a successful build.
+//!   The worker uploads the compiled executable to the
+//!   [artifact store](`crate::artifact_store`) first; the compiler records
+//!   its URL as the binary reference of the program, and runners fetch it
+//!   from there.
+//! * `POST /jobs/{program_id}/{version}/error` reports a failed build along
+//!   with the output of `cargo`.
+//!
+//! All requests must carry the token stored in
+//! [`CompilerConfig::worker_token_file`](`crate::config::CompilerConfig::worker_token_file`)
+//! in an `Authorization: Bearer <token>` header.
+//!
+//! A job that isn't completed within
+//! [`CompilerConfig::remote_worker_timeout_secs`](`crate::config::CompilerConfig::remote_worker_timeout_secs`)
+//! of being claimed is returned to the queue for another worker to pick up.
+
+use crate::artifact_store::ArtifactStore;
+use crate::compiler::Compiler;
+use crate::config::{CompileWorkerConfig, CompilerConfig};
+use crate::db::{ProgramId, Version};
+use crate::error::ManagerError;
+use actix_web::{http::header::AUTHORIZATION, post, web, HttpRequest, HttpResponse};
+use dbsp_adapters::server::auth::authorized;
+use log::{error, info, warn};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{fs, sync::oneshot, time::sleep};
+
+/// How often an idle worker asks the compiler for a new job.
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// A Rust build dispatched to a remote worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RemoteJob {
+    pub program_id: ProgramId,
+    pub version: Version,
+    /// Generated `main.rs`.  The worker generates `Cargo.toml` files using
+    /// its own configuration.
+    pub main_rs: String,
+}
+
+/// Output of a failed remote build.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RemoteBuildError {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+struct QueuedJob {
+    job: RemoteJob,
+    /// Time when a worker claimed the job.
+    claimed: Option<Instant>,
+    /// Resolves to `true` if the build succeeded and `false` otherwise.
+    result: oneshot::Sender<bool>,
+}
+
+/// Rust builds waiting for or assigned to a remote worker.
+#[derive(Default)]
+pub(crate) struct RemoteJobQueue {
+    jobs: Mutex<Vec<QueuedJob>>,
+}
+
+impl RemoteJobQueue {
+    /// Queue a new build.  The returned receiver resolves once a worker
+    /// reports the outcome of the build.
+    pub(crate) fn push(&self, job: RemoteJob) -> oneshot::Receiver<bool> {
+        let (sender, receiver) = oneshot::channel();
+        self.jobs.lock().unwrap().push(QueuedJob {
+            job,
+            claimed: None,
+            result: sender,
+        });
+        receiver
+    }
+
+    /// Assign the oldest unclaimed job to a worker.
+    fn claim(&self) -> Option<RemoteJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = jobs.iter_mut().find(|queued| queued.claimed.is_none())?;
+        queued.claimed = Some(Instant::now());
+        Some(queued.job.clone())
+    }
+
+    fn is_claimed(&self, program_id: ProgramId, version: Version) -> bool {
+        self.jobs.lock().unwrap().iter().any(|queued| {
+            queued.job.program_id == program_id
+                && queued.job.version == version
+                && queued.claimed.is_some()
+        })
+    }
+
+    /// Remove a job from the queue, notifying the compiler of the outcome.
+    fn complete(&self, program_id: ProgramId, version: Version, success: bool) -> bool {
+        match self.remove(program_id, version) {
+            Some(queued) => {
+                let _ = queued.result.send(success);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a job from the queue without notifying the compiler.
+    pub(crate) fn cancel(&self, program_id: ProgramId, version: Version) {
+        self.remove(program_id, version);
+    }
+
+    fn remove(&self, program_id: ProgramId, version: Version) -> Option<QueuedJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let index = jobs.iter().position(|queued| {
+            queued.job.program_id == program_id && queued.job.version == version
+        })?;
+        Some(jobs.remove(index))
+    }
+
+    /// Return jobs claimed more than `timeout` ago to the queue.
+    pub(crate) fn requeue_expired(&self, timeout: Duration) {
+        for queued in self.jobs.lock().unwrap().iter_mut() {
+            if matches!(queued.claimed, Some(claimed) if claimed.elapsed() > timeout) {
+                warn!(
+                    "Remote build of program {} version {} timed out; returning it to the queue",
+                    queued.job.program_id, queued.job.version
+                );
+                queued.claimed = None;
+            }
+        }
+    }
+}
+
+/// Token that remote workers must present to the compiler service.
+pub(crate) struct WorkerToken(pub String);
+
+impl WorkerToken {
+    /// Checks the `Authorization` header of `request` against the token.
+    fn authorize(&self, request: &HttpRequest) -> Result<(), ManagerError> {
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.as_bytes());
+        if authorized(Some(&self.0), authorization) {
+            Ok(())
+        } else {
+            Err(ManagerError::UnauthorizedWorker)
+        }
+    }
+}
+
+#[post("/jobs/claim")]
+pub(crate) async fn claim_job(
+    request: HttpRequest,
+    token: web::Data<WorkerToken>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ManagerError> {
+    token.authorize(&request)?;
+    match queue.claim() {
+        Some(job) => {
+            info!(
+                "Remote worker claimed program {} version {}",
+                job.program_id, job.version
+            );
+            Ok(HttpResponse::Ok().json(job))
+        }
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[post("/jobs/{program_id}/{version}/binary")]
+pub(crate) async fn report_binary(
+    request: HttpRequest,
+    token: web::Data<WorkerToken>,
+    artifact_store: web::Data<ArtifactStore>,
+    queue: web::Data<RemoteJobQueue>,
+    path: web::Path<(ProgramId, Version)>,
+) -> Result<HttpResponse, ManagerError> {
+    token.authorize(&request)?;
+    let (program_id, version) = path.into_inner();
+    if !queue.is_claimed(program_id, version) {
+        return Err(ManagerError::UnknownRemoteJob {
+            program_id,
+            version,
+        });
+    }
+
+    if !artifact_store.has_executable(program_id, version).await? {
+        return Err(ManagerError::ArtifactStoreError {
+            error: format!(
+                "the binary of program '{program_id}' version '{version}' has not been uploaded"
+            ),
+        });
+    }
+
+    queue.complete(program_id, version, true);
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/jobs/{program_id}/{version}/error")]
+pub(crate) async fn report_error(
+    request: HttpRequest,
+    token: web::Data<WorkerToken>,
+    config: web::Data<CompilerConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    path: web::Path<(ProgramId, Version)>,
+    body: web::Json<RemoteBuildError>,
+) -> Result<HttpResponse, ManagerError> {
+    token.authorize(&request)?;
+    let (program_id, version) = path.into_inner();
+    if !queue.is_claimed(program_id, version) {
+        return Err(ManagerError::UnknownRemoteJob {
+            program_id,
+            version,
+        });
+    }
+
+    // Store compiler output where the compiler expects to find the output of
+    // a local build.
+    let stdout_path = config.compiler_stdout_path(program_id);
+    fs::write(&stdout_path, &body.stdout)
+        .await
+        .map_err(|e| ManagerError::io_error(format!("writing '{}'", stdout_path.display()), e))?;
+    let stderr_path = config.compiler_stderr_path(program_id);
+    fs::write(&stderr_path, &body.stderr)
+        .await
+        .map_err(|e| ManagerError::io_error(format!("writing '{}'", stderr_path.display()), e))?;
+
+    queue.complete(program_id, version, false);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Remote compile worker.
+pub struct CompileWorker;
+
+impl CompileWorker {
+    /// Pull jobs from the compiler service and build them, forever.
+    pub async fn run(
+        config: &CompilerConfig,
+        worker_config: &CompileWorkerConfig,
+    ) -> Result<(), ManagerError> {
+        Compiler::create_working_directory(config).await?;
+        let token = config.worker_token()?;
+        let artifact_store = config.open_artifact_store()?;
+        let client = reqwest::Client::new();
+        let base_url = worker_config.compiler_url.trim_end_matches('/');
+
+        loop {
+            match Self::claim(&client, base_url, &token).await {
+                Ok(Some(job)) => {
+                    let program_id = job.program_id;
+                    let version = job.version;
+                    info!("Building program {program_id} version {version}");
+                    let result =
+                        Self::build(&client, base_url, &token, &artifact_store, config, job).await;
+                    if let Err(e) = result {
+                        error!("Failed to build program {program_id} version {version}: {e}");
+                    }
+                }
+                Ok(None) => sleep(WORKER_POLL_INTERVAL).await,
+                Err(e) => {
+                    warn!("Failed to fetch a job from the compiler service: {e}");
+                    sleep(WORKER_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    async fn claim(
+        client: &reqwest::Client,
+        base_url: &str,
+        token: &str,
+    ) -> Result<Option<RemoteJob>, ManagerError> {
+        let response = client
+            .post(format!("{base_url}/jobs/claim"))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(remote_worker_error)?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(remote_worker_error)?;
+        Ok(Some(response.json().await.map_err(remote_worker_error)?))
+    }
+
+    async fn build(
+        client: &reqwest::Client,
+        base_url: &str,
+        token: &str,
+        artifact_store: &ArtifactStore,
+        config: &CompilerConfig,
+        job: RemoteJob,
+    ) -> Result<(), ManagerError> {
+        let program_id = job.program_id;
+        let version = job.version;
+
+        let rust_file_path = config.rust_program_path(program_id);
+        let rust_source_dir = rust_file_path.parent().unwrap();
+        fs::create_dir_all(rust_source_dir).await.map_err(|e| {
+            ManagerError::io_error(format!("creating '{}'", rust_source_dir.display()), e)
+        })?;
+        fs::write(&rust_file_path, &job.main_rs)
+            .await
+            .map_err(|e| {
+                ManagerError::io_error(format!("writing '{}'", rust_file_path.display()), e)
+            })?;
+        Compiler::write_project_toml(config, program_id).await?;
+        Compiler::write_workspace_toml(config, program_id).await?;
+
+        let exit_status = Compiler::run_cargo_build(config, program_id)
+            .await?
+            .wait()
+            .await
+            .map_err(|e| ManagerError::io_error("waiting for 'cargo build'".to_string(), e))?;
+
+        let job_url = format!("{base_url}/jobs/{program_id}/{version}");
+        let request = if exit_status.success() {
+            let executable = config.target_executable(program_id);
+            let binary = fs::read(&executable).await.map_err(|e| {
+                ManagerError::io_error(format!("reading '{}'", executable.display()), e)
+            })?;
+            artifact_store
+                .put_executable(program_id, version, binary)
+                .await?;
+            client.post(format!("{job_url}/binary"))
+        } else {
+            let read = |path: std::path::PathBuf| async move {
+                fs::read_to_string(&path)
+                    .await
+                    .map_err(|e| ManagerError::io_error(format!("reading '{}'", path.display()), e))
+            };
+            let output = RemoteBuildError {
+                stdout: read(config.compiler_stdout_path(program_id)).await?,
+                stderr: read(config.compiler_stderr_path(program_id)).await?,
+            };
+            client.post(format!("{job_url}/error")).json(&output)
+        };
+
+        request
+            .bearer_auth(token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(remote_worker_error)?;
+        Ok(())
+    }
+}
+
+fn remote_worker_error(error: reqwest::Error) -> ManagerError {
+    ManagerError::RemoteWorkerError {
+        error: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RemoteJob, RemoteJobQueue, WorkerToken};
+    use crate::db::{ProgramId, Version};
+    use actix_web::test::TestRequest;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn job(version: i64) -> RemoteJob {
+        RemoteJob {
+            program_id: ProgramId(Uuid::nil()),
+            version: Version(version),
+            main_rs: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_job_queue() {
+        let queue = RemoteJobQueue::default();
+        let pid = ProgramId(Uuid::nil());
+
+        let mut r1 = queue.push(job(1));
+        let r2 = queue.push(job(2));
+
+        // Jobs are handed out in order, once each.
+        assert_eq!(queue.claim().unwrap().version, Version(1));
+        assert_eq!(queue.claim().unwrap().version, Version(2));
+        assert!(queue.claim().is_none());
+
+        // Claimed jobs that time out are handed out again.
+        queue.requeue_expired(Duration::ZERO);
+        assert!(!queue.is_claimed(pid, Version(1)));
+        assert_eq!(queue.claim().unwrap().version, Version(1));
+
+        assert!(queue.complete(pid, Version(2), true));
+        assert!(r2.await.unwrap());
+        assert!(!queue.complete(pid, Version(2), true));
+
+        // Cancelled jobs are dropped without notifying the compiler.
+        queue.cancel(pid, Version(1));
+        assert!(r1.try_recv().is_err());
+        assert!(queue.claim().is_none());
+    }
+
+    #[test]
+    fn worker_token() {
+        let token = WorkerToken("secret".to_string());
+        let authorize = |header: Option<&str>| {
+            let mut request = TestRequest::default();
+            if let Some(header) = header {
+                request = request.insert_header(("Authorization", header));
+            }
+            token.authorize(&request.to_http_request()).is_ok()
+        };
+
+        assert!(authorize(Some("Bearer secret")));
+        assert!(!authorize(None));
+        assert!(!authorize(Some("secret")));
+        assert!(!authorize(Some("Bearer secre")));
+        assert!(!authorize(Some("Bearer secret1")));
+    }
+}
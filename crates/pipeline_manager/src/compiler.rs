@@ -1,18 +1,23 @@
 use crate::auth::TenantId;
+use crate::compile_worker::{self, RemoteJob, RemoteJobQueue, WorkerToken};
 use crate::config::CompilerConfig;
 use crate::db::storage::Storage;
-use crate::db::{DBError, ProgramId, ProjectDB, Version};
+use crate::db::{DBError, ProgramId, ProjectDB, TenantUsage, Version};
 use crate::error::ManagerError;
+use crate::health::{with_heartbeat, COMPILER_SERVICE};
+use crate::webhooks::{self, WebhookPayload};
 use actix_files::NamedFile;
 use actix_web::{get, web, HttpRequest, HttpServer, Responder};
-use futures_util::join;
 use log::warn;
 use log::{debug, error, info, trace};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::{
+    os::unix::{fs::PermissionsExt, process::ExitStatusExt},
+    path::Path,
     process::{ExitStatus, Stdio},
     sync::Arc,
+    time::Instant,
 };
 use tokio::fs::DirEntry;
 use tokio::io::AsyncWriteExt;
@@ -21,7 +26,7 @@ use tokio::{
     fs::{File, OpenOptions},
     process::{Child, Command},
     select, spawn,
-    sync::Mutex,
+    sync::{oneshot, watch, Mutex},
     time::{sleep, Duration},
 };
 use utoipa::ToSchema;
@@ -39,10 +44,12 @@ const COMPILER_POLL_INTERVAL: Duration = Duration::from_millis(1000);
 /// future.
 const GC_POLL_INTERVAL: Duration = Duration::from_secs(3);
 
-/// A SQL compiler error.
+/// A SQL compiler error or warning.
 ///
 /// The SQL compiler returns a list of errors in the following JSON format if
-/// it's invoked with the `-je` option.
+/// it's invoked with the `-je` option.  Line and column numbers are 1-based
+/// and refer to the program code; they are 0 for messages that are not
+/// associated with a specific part of the program.
 ///
 /// ```ignore
 ///  [ {
@@ -52,6 +59,7 @@ const GC_POLL_INTERVAL: Duration = Duration::from_secs(3);
 /// "endColumn" : 13,
 /// "warning" : false,
 /// "errorType" : "Error parsing SQL",
+/// "errorCode" : "ParseError",
 /// "message" : "Encountered \"<EOF>\" at line 14, column 13."
 /// } ]
 /// ```
@@ -59,12 +67,25 @@ const GC_POLL_INTERVAL: Duration = Duration::from_secs(3);
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct SqlCompilerMessage {
+    /// First line of the code fragment the message refers to.
     start_line_number: usize,
+    /// First column of the code fragment the message refers to.
     start_column: usize,
+    /// Last line of the code fragment the message refers to.
     end_line_number: usize,
+    /// Last column of the code fragment the message refers to.
     end_column: usize,
+    /// `true` for warnings, `false` for errors.
     warning: bool,
+    /// Human-readable description of the kind of error.
     error_type: String,
+    /// Machine-readable identifier of the kind of error, e.g.,
+    /// `DuplicateDefinition`.
+    ///
+    /// Absent in messages produced by older versions of the SQL compiler.
+    #[serde(default)]
+    error_code: Option<String>,
+    /// Error message.
     message: String,
 }
 
@@ -121,18 +142,125 @@ pub struct Compiler {}
 /// crate.
 const MAIN_FUNCTION: &str = r#"
 fn main() {
-    dbsp_adapters::server::server_main(|workers| circuit(workers).map(|(dbsp, catalog)| (Box::new(dbsp) as Box<dyn dbsp_adapters::DbspCircuitHandle>, Box::new(catalog) as Box<dyn dbsp_adapters::CircuitCatalog>)).map_err(|e| dbsp_adapters::ControllerError::dbsp_error(e))).unwrap_or_else(|e| {
+    dbsp_adapters::server::server_main_with_source_map(SQL_SOURCE_MAP, |workers| {
+        let (dbsp, mut catalog) = circuit(workers).map_err(|e| dbsp_adapters::ControllerError::dbsp_error(e))?;
+        catalog.set_schema_json(SQL_SCHEMA)?;
+        Ok((Box::new(dbsp) as Box<dyn dbsp_adapters::DbspCircuitHandle>, Box::new(catalog) as Box<dyn dbsp_adapters::CircuitCatalog>))
+    }).unwrap_or_else(|e| {
         eprintln!("{e}");
         std::process::exit(1);
     });
 }"#;
 
-// Simple endpoint to serve compiled binaries
-#[get("/binary/{program_id}/{version}")]
-async fn index(
-    state: web::Data<CompilerConfig>,
-    req: HttpRequest,
-) -> Result<impl Responder, ManagerError> {
+/// Prefix of the comment the SQL compiler emits before each operator in the
+/// generated code with the range of SQL code the operator implements, e.g.,
+/// `// sql: 3:1--5:20`.
+const SQL_POSITION_COMMENT: &str = "// sql: ";
+
+/// Generates the `SQL_SOURCE_MAP` static injected in each generated pipeline
+/// crate, which maps the lines of `main.rs` that instantiate operators to the
+/// fragments of the SQL program these operators implement.
+fn sql_source_map(rust_code: &str, sql_code: &str) -> String {
+    let sql_lines: Vec<&str> = sql_code.lines().collect();
+    let mut entries = Vec::new();
+    let mut fragment: Option<String> = None;
+
+    for (index, line) in rust_code.lines().enumerate() {
+        let line = line.trim_start();
+        if let Some(range) = line.strip_prefix(SQL_POSITION_COMMENT) {
+            fragment = sql_fragment(&sql_lines, range);
+        } else if !line.starts_with("//") {
+            if let Some(fragment) = fragment.take() {
+                entries.push(format!("({}, {fragment:?})", index + 1));
+            }
+        }
+    }
+
+    format!(
+        "\nstatic SQL_SOURCE_MAP: &[(u32, &str)] = &[{}];\n",
+        entries.join(", ")
+    )
+}
+
+/// Generates the `SQL_SCHEMA` static injected in each generated pipeline
+/// crate, which contains the program schema generated by the SQL compiler.
+/// The pipeline uses it to describe its tables and views via the `/catalog`
+/// endpoint.
+fn sql_schema(schema: &str) -> String {
+    format!("\nstatic SQL_SCHEMA: &str = {schema:?};\n")
+}
+
+/// Extracts the fragment of SQL code in `range`, formatted as
+/// `start_line:start_column--end_line:end_column` with 1-based, inclusive
+/// positions.  Whitespace in the fragment is collapsed to a single space.
+fn sql_fragment(sql_lines: &[&str], range: &str) -> Option<String> {
+    fn position(position: &str) -> Option<(usize, usize)> {
+        let (line, column) = position.split_once(':')?;
+        Some((line.trim().parse().ok()?, column.trim().parse().ok()?))
+    }
+
+    let (start, end) = range.split_once("--")?;
+    let (start_line, start_column) = position(start)?;
+    let (end_line, end_column) = position(end)?;
+    if start_line == 0 || start_column == 0 || end_line < start_line {
+        return None;
+    }
+
+    let mut fragment = String::new();
+    for line_number in start_line..=end_line {
+        let line: Vec<char> = sql_lines.get(line_number - 1)?.chars().collect();
+        let from = if line_number == start_line {
+            start_column - 1
+        } else {
+            0
+        };
+        let to = if line_number == end_line {
+            end_column.min(line.len())
+        } else {
+            line.len()
+        };
+        if from < to {
+            fragment.extend(&line[from..to]);
+        }
+        fragment.push(' ');
+    }
+
+    let fragment = fragment.split_whitespace().collect::<Vec<_>>().join(" ");
+    if fragment.is_empty() {
+        None
+    } else {
+        Some(fragment)
+    }
+}
+
+/// Shell script that runs a JIT-compiled program, see
+/// [`Compiler::write_jit_launcher`].
+fn jit_launcher_script(pipeline_path: &Path, ir: &str, schema: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         cat > ir.json <<'FELDERA_IR_EOF'\n{ir}\nFELDERA_IR_EOF\n\
+         cat > schema.json <<'FELDERA_SCHEMA_EOF'\n{schema}\nFELDERA_SCHEMA_EOF\n\
+         exec '{}' --ir ir.json --schema schema.json --optimize --release \"$@\"\n",
+        pipeline_path.display()
+    )
+}
+
+/// Name of the dataflow IR artifact of a program compiled with the JIT
+/// backend.
+pub(crate) const JIT_IR_ARTIFACT: &str = "ir.json";
+
+/// Name of the schema artifact of a program compiled with the JIT backend.
+pub(crate) const JIT_SCHEMA_ARTIFACT: &str = "schema.json";
+
+/// Artifacts stored next to the launcher of a program compiled with the
+/// JIT backend.
+///
+/// The compiler serves them at `<binary_ref>/<artifact>`, so that runners
+/// can instantiate the circuit without executing the launcher.
+pub(crate) const JIT_ARTIFACTS: [&str; 2] = [JIT_IR_ARTIFACT, JIT_SCHEMA_ARTIFACT];
+
+/// Parse the `program_id` and `version` parameters of a binary request.
+fn binary_request_params(req: &HttpRequest) -> Result<(ProgramId, Version), ManagerError> {
     let program_id = match req.match_info().get("program_id") {
         None => Err(ManagerError::MissingUrlEncodedParam {
             param: "program_id",
@@ -153,38 +281,101 @@ async fn index(
             Ok(version) => Ok(version),
         },
     }?;
-    let version = Version(version);
+    Ok((program_id, Version(version)))
+}
+
+// Simple endpoint to serve compiled binaries
+#[get("/binary/{program_id}/{version}")]
+async fn index(
+    state: web::Data<CompilerConfig>,
+    req: HttpRequest,
+) -> Result<impl Responder, ManagerError> {
+    let (program_id, version) = binary_request_params(&req)?;
     let path = state.versioned_executable(program_id, version);
     Ok(NamedFile::open_async(path).await)
 }
 
+// Serve the artifacts of programs compiled with the JIT backend
+#[get("/binary/{program_id}/{version}/{artifact}")]
+async fn jit_artifact(
+    state: web::Data<CompilerConfig>,
+    req: HttpRequest,
+) -> Result<impl Responder, ManagerError> {
+    let (program_id, version) = binary_request_params(&req)?;
+    let artifact = req.match_info().get("artifact").unwrap_or_default();
+    if !JIT_ARTIFACTS.contains(&artifact) {
+        return Ok(None);
+    }
+    let path = state.versioned_jit_artifact(program_id, version, artifact);
+    Ok(Some(NamedFile::open_async(path).await))
+}
+
 impl Compiler {
+    /// Run the compiler service until `shutdown` is set to `true`.
+    ///
+    /// On shutdown, the service stops picking up new compilation jobs and
+    /// waits up to [`CompilerConfig::shutdown_drain_secs`] for the current
+    /// job to complete.  A job that doesn't complete in time is cancelled and
+    /// its program is returned to the queue.
     pub async fn run(
         config: &CompilerConfig,
         db: Arc<Mutex<ProjectDB>>,
+        shutdown: watch::Receiver<bool>,
     ) -> Result<(), ManagerError> {
         Self::create_working_directory(config).await?;
-        let compiler_task = spawn(Self::compiler_task(config.clone(), db.clone()));
+        let remote_jobs = Arc::new(RemoteJobQueue::default());
+        let compiler_task = spawn(with_heartbeat(
+            db.clone(),
+            COMPILER_SERVICE,
+            Self::compiler_task(config.clone(), db.clone(), remote_jobs.clone(), shutdown),
+        ));
         let gc_task = spawn(Self::gc_task(config.clone(), db));
         let config_copy = web::Data::new(config.clone());
+        let remote_jobs = web::Data::from(remote_jobs);
+        // Remote worker routes are only served when remote workers are
+        // enabled, and require the worker token.
+        let remote_worker_data = if config.remote_workers {
+            Some((
+                web::Data::new(WorkerToken(config.worker_token()?)),
+                web::Data::new(config.open_artifact_store()?),
+            ))
+        } else {
+            None
+        };
+        let address = config.binary_ref_bind_address.clone();
         let port = config.binary_ref_port;
-        let http = spawn(
-            HttpServer::new(move || {
-                actix_web::App::new()
-                    .app_data(config_copy.clone())
-                    .service(index)
-            })
-            .bind(("0.0.0.0", port))
-            .unwrap()
-            .run(),
-        );
-        let r = join!(compiler_task, gc_task, http);
-        r.0.unwrap()?;
+        let http = HttpServer::new(move || {
+            let app = actix_web::App::new()
+                .app_data(config_copy.clone())
+                .service(index)
+                .service(jit_artifact);
+            match &remote_worker_data {
+                Some((token, artifact_store)) => app
+                    .app_data(token.clone())
+                    .app_data(artifact_store.clone())
+                    .app_data(remote_jobs.clone())
+                    .service(compile_worker::claim_job)
+                    .service(compile_worker::report_binary)
+                    .service(compile_worker::report_error),
+                None => app,
+            }
+        })
+        .bind((address.as_str(), port))
+        .map_err(|e| ManagerError::io_error(format!("binding to '{address}:{port}'"), e))?
+        .run();
+        let http_handle = http.handle();
+        spawn(http);
+        let r = compiler_task.await;
+        gc_task.abort();
+        http_handle.stop(true).await;
+        r.unwrap()?;
 
         Ok(())
     }
 
-    async fn create_working_directory(config: &CompilerConfig) -> Result<(), ManagerError> {
+    pub(crate) async fn create_working_directory(
+        config: &CompilerConfig,
+    ) -> Result<(), ManagerError> {
         fs::create_dir_all(&config.workspace_dir())
             .await
             .map_err(|e| {
@@ -274,7 +465,7 @@ impl Compiler {
         Ok(())
     }
 
-    async fn run_cargo_build(
+    pub(crate) async fn run_cargo_build(
         config: &CompilerConfig,
         program_id: ProgramId,
     ) -> Result<Child, ManagerError> {
@@ -315,6 +506,16 @@ impl Compiler {
             command.arg("--release");
         }
 
+        command.envs(config.cargo_profile_env());
+        if let Some(rustflags) = config.rustflags() {
+            // Preserve flags set in the manager's environment.
+            let rustflags = match std::env::var("RUSTFLAGS") {
+                Ok(flags) if !flags.is_empty() => format!("{flags} {rustflags}"),
+                _ => rustflags,
+            };
+            command.env("RUSTFLAGS", rustflags);
+        }
+
         command
             .spawn()
             .map_err(|e| ManagerError::io_error("starting 'cargo'".to_string(), e))
@@ -332,20 +533,36 @@ impl Compiler {
             config.versioned_executable(program_id, version)
         );
 
-        // Save the file locally and record a path to it as a "file://" scheme URL in the DB.
-        // This requires any entity accessing it to have access to the same filesystem
-        let source = config.target_executable(program_id);
-        let destination = config.versioned_executable(program_id, version);
-        fs::copy(&source, &destination).await.map_err(|e| {
-            ManagerError::io_error(
-                format!(
-                    "copying '{}' to '{}'",
-                    source.display(),
-                    destination.display()
-                ),
-                e,
+        // Binaries built by remote workers are stored in the artifact store,
+        // which runners fetch them from directly.
+        if config.remote_workers && !config.jit {
+            let artifact_store = config.open_artifact_store()?;
+            db.create_compiled_binary_ref(
+                program_id,
+                version,
+                artifact_store.executable_url(program_id, version),
             )
-        })?;
+            .await?;
+            return Ok(());
+        }
+
+        // Save the file locally and record a path to it as a "file://" scheme URL in the DB.
+        // This requires any entity accessing it to have access to the same filesystem.
+        // JIT launchers are written there by `write_jit_launcher`.
+        if !config.jit {
+            let source = config.target_executable(program_id);
+            let destination = config.versioned_executable(program_id, version);
+            fs::copy(&source, &destination).await.map_err(|e| {
+                ManagerError::io_error(
+                    format!(
+                        "copying '{}' to '{}'",
+                        source.display(),
+                        destination.display()
+                    ),
+                    e,
+                )
+            })?;
+        }
 
         db.create_compiled_binary_ref(
             program_id,
@@ -359,8 +576,54 @@ impl Compiler {
         Ok(())
     }
 
+    /// Write the executable and the artifacts of a program compiled with the
+    /// JIT backend.
+    ///
+    /// The executable is a shell script that embeds the dataflow IR and the
+    /// program schema and runs them with the JIT pipeline executable.  The
+    /// script is self-contained, so runners can fetch and run it like a
+    /// natively compiled program.  The IR and the schema are also stored as
+    /// separate artifacts (see [`JIT_ARTIFACTS`]) for runners that
+    /// instantiate the circuit themselves.
+    async fn write_jit_launcher(
+        config: &CompilerConfig,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<(), ManagerError> {
+        let ir_path = config.jit_ir_path(program_id);
+        let ir = fs::read_to_string(&ir_path)
+            .await
+            .map_err(|e| ManagerError::io_error(format!("reading '{}'", ir_path.display()), e))?;
+        let schema_path = config.schema_path(program_id);
+        let schema = fs::read_to_string(&schema_path).await.map_err(|e| {
+            ManagerError::io_error(format!("reading '{}'", schema_path.display()), e)
+        })?;
+
+        for (artifact, contents) in [(JIT_IR_ARTIFACT, &ir), (JIT_SCHEMA_ARTIFACT, &schema)] {
+            let destination = config.versioned_jit_artifact(program_id, version, artifact);
+            fs::write(&destination, contents).await.map_err(|e| {
+                ManagerError::io_error(format!("writing '{}'", destination.display()), e)
+            })?;
+        }
+
+        let script = jit_launcher_script(&config.jit_pipeline_path(), &ir, &schema);
+        let destination = config.versioned_executable(program_id, version);
+        fs::write(&destination, script).await.map_err(|e| {
+            ManagerError::io_error(format!("writing '{}'", destination.display()), e)
+        })?;
+        fs::set_permissions(&destination, std::fs::Permissions::from_mode(0o755))
+            .await
+            .map_err(|e| {
+                ManagerError::io_error(
+                    format!("setting permissions of '{}'", destination.display()),
+                    e,
+                )
+            })?;
+        Ok(())
+    }
+
     /// Generate workspace-level `Cargo.toml`.
-    async fn write_workspace_toml(
+    pub(crate) async fn write_workspace_toml(
         config: &CompilerConfig,
         program_id: ProgramId,
     ) -> Result<(), ManagerError> {
@@ -377,7 +640,7 @@ impl Compiler {
     }
 
     /// Generate project-level `Cargo.toml`.
-    async fn write_project_toml(
+    pub(crate) async fn write_project_toml(
         config: &CompilerConfig,
         program_id: ProgramId,
     ) -> Result<(), ManagerError> {
@@ -408,6 +671,15 @@ impl Compiler {
         Ok(())
     }
 
+    /// Add the time `job` has been running for to the tenant's compile time.
+    async fn record_compile_time(db: &ProjectDB, job: &CompilationJob) -> Result<(), DBError> {
+        let usage = TenantUsage {
+            compile_time_ms: job.started.elapsed().as_millis() as i64,
+            ..Default::default()
+        };
+        db.record_tenant_usage(job.tenant_id, &usage).await
+    }
+
     async fn gc_task(
         config: CompilerConfig,
         db: Arc<Mutex<ProjectDB>>,
@@ -421,6 +693,15 @@ impl Compiler {
     async fn binary_path_to_parts(path: &DirEntry) -> Option<(ProgramId, Version)> {
         let file_name = path.file_name();
         let file_name = file_name.to_str().unwrap();
+        // JIT artifacts are removed along with the executable they belong to.
+        let file_name = JIT_ARTIFACTS
+            .iter()
+            .find_map(|artifact| {
+                file_name
+                    .strip_suffix(artifact)
+                    .and_then(|name| name.strip_suffix('.'))
+            })
+            .unwrap_or(file_name);
         if file_name.starts_with("project") {
             let parts: Vec<&str> = file_name.split('_').collect();
             if parts.len() != 3
@@ -519,11 +800,15 @@ impl Compiler {
     async fn compiler_task(
         config: CompilerConfig,
         db: Arc<Mutex<ProjectDB>>,
+        remote_jobs: Arc<RemoteJobQueue>,
+        shutdown: watch::Receiver<bool>,
     ) -> Result<(), ManagerError> {
-        Self::do_compiler_task(config, db).await.map_err(|e| {
-            error!("compiler task failed; error: '{e}'");
-            e
-        })
+        Self::do_compiler_task(config, db, remote_jobs, shutdown)
+            .await
+            .map_err(|e| {
+                error!("compiler task failed; error: '{e}'");
+                e
+            })
     }
 
     /// Invoked at startup so the compiler service can align its
@@ -605,9 +890,10 @@ impl Compiler {
             }
             // If the program was supposed to be further in the compilation chain, but
             // we don't have the binary artifact available locally, we need to queue the program
-            // for compilation again. TODO: this behavior will change when the compiler uploads
-            // artifacts remotely and not on its local filesystem.
+            // for compilation again.  Binaries built by remote workers are kept in the artifact
+            // store rather than locally.
             else if (program.status.is_compiling() || program.status == ProgramStatus::Success)
+                && !(config.remote_workers && !config.jit)
                 && !map.contains(&(program.program_id.0, program.version.0))
             {
                 info!(
@@ -628,18 +914,69 @@ impl Compiler {
         Ok(())
     }
 
+    /// Cancel a job that didn't complete before shutdown and return its
+    /// program to the queue, so that it is compiled from scratch on restart
+    /// instead of being left in a compiling state.
+    async fn checkpoint_job(
+        db: &Arc<Mutex<ProjectDB>>,
+        mut job: CompilationJob,
+    ) -> Result<(), ManagerError> {
+        warn!(
+            "Cancelling compilation of program {} version {} (tenant {}) on shutdown; it will be compiled again on restart",
+            job.program_id, job.version, job.tenant_id
+        );
+        job.cancel().await;
+        let db = db.lock().await;
+        Self::record_compile_time(&db, &job).await?;
+        db.set_program_status_guarded(
+            job.tenant_id,
+            job.program_id,
+            job.version,
+            ProgramStatus::Pending,
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn do_compiler_task(
         /* command_receiver: Receiver<CompilerCommand>, */
         config: CompilerConfig,
         db: Arc<Mutex<ProjectDB>>,
+        remote_jobs: Arc<RemoteJobQueue>,
+        mut shutdown: watch::Receiver<bool>,
     ) -> Result<(), ManagerError> {
         let mut job: Option<CompilationJob> = None;
+        // Set once shutdown is requested: the time by which the current job
+        // must complete.
+        let mut drain_deadline: Option<Instant> = None;
         Self::reconcile_local_state(&config, &db).await?;
         loop {
+            // Webhook notification about the outcome of a finished job.  Sent
+            // after releasing the database lock.
+            let mut notification: Option<WebhookPayload> = None;
+            if drain_deadline.is_none() && *shutdown.borrow() {
+                info!("Compiler shutting down");
+                drain_deadline =
+                    Some(Instant::now() + Duration::from_secs(config.shutdown_drain_secs));
+            }
+            if let Some(deadline) = drain_deadline {
+                if job.is_none() {
+                    return Ok(());
+                }
+                if Instant::now() >= deadline {
+                    let job = job.take().unwrap();
+                    Self::checkpoint_job(&db, job).await?;
+                    return Ok(());
+                }
+            }
             select! {
+                // Shutdown requested -- stop picking up new jobs and drain
+                // the current one.
+                Ok(()) = shutdown.changed(), if drain_deadline.is_none() => continue,
                 // Wake up every `COMPILER_POLL_INTERVAL` to check
                 // if we need to abort ongoing compilation.
                 _ = sleep(COMPILER_POLL_INTERVAL) => {
+                    remote_jobs.requeue_expired(Duration::from_secs(config.remote_worker_timeout_secs));
                     let mut cancel = false;
                     if let Some(job) = &job {
                         // Program was deleted, updated or the user changed its status
@@ -654,8 +991,9 @@ impl Compiler {
                         }
                     }
                     if cancel {
-                        job.unwrap().cancel().await;
-                        job = None;
+                        let mut job = job.take().unwrap();
+                        job.cancel().await;
+                        Self::record_compile_time(&*db.lock().await, &job).await?;
                     }
                 }
                 // Compilation job finished - start the next stage of the compilation
@@ -672,6 +1010,9 @@ impl Compiler {
                     let program_id = job.as_ref().unwrap().program_id;
                     let version = job.as_ref().unwrap().version;
                     let db = db.lock().await;
+                    // Account for the time spent in this stage before starting
+                    // the next one or finishing the job.
+                    Self::record_compile_time(&db, job.as_ref().unwrap()).await?;
 
                     match exit_status {
                         Ok(status) if status.success() && job.as_ref().unwrap().is_sql() => {
@@ -700,9 +1041,29 @@ impl Compiler {
                             let schema = serde_json::from_str(&schema_json)
                                 .map_err(|e| { ManagerError::invalid_program_schema(e.to_string()) })?;
                             db.set_program_schema(tenant_id, program_id, schema).await?;
-                            info!("Invoking rust compiler for program {program_id} version {version} (tenant {tenant_id}). This will take a while.");
-                            debug!("Set ProgramStatus::CompilingRust '{program_id}', version '{version}'");
-                            job = Some(CompilationJob::rust(tenant_id, &config, program_id, version).await?);
+
+                            // The compiler reports warnings in the same format
+                            // as errors.  Warnings are informational, so we
+                            // ignore output we cannot parse.
+                            let output = job.as_ref().unwrap().error_output(&config).await?;
+                            if let Ok(warnings) = serde_json::from_str::<Vec<SqlCompilerMessage>>(&output) {
+                                db.set_program_warnings(tenant_id, program_id, warnings).await?;
+                            }
+
+                            if config.jit {
+                                // Nothing left to compile -- the IR is
+                                // compiled when the pipeline starts.
+                                Self::write_jit_launcher(&config, program_id, version).await?;
+                                Self::version_binary(&config, &db, program_id, version).await?;
+                                db.set_program_status_guarded(tenant_id, program_id, version, ProgramStatus::Success).await?;
+                                info!("Successfully compiled program {program_id} version {version} (tenant {tenant_id}) with the JIT backend.");
+                                notification = Some(WebhookPayload::program(tenant_id, program_id, version, &ProgramStatus::Success));
+                                job = None;
+                            } else {
+                                info!("Invoking rust compiler for program {program_id} version {version} (tenant {tenant_id}). This will take a while.");
+                                debug!("Set ProgramStatus::CompilingRust '{program_id}', version '{version}'");
+                                job = Some(CompilationJob::rust(tenant_id, &config, &remote_jobs, program_id, version).await?);
+                            }
                         }
                         Ok(status) if status.success() && job.as_ref().unwrap().is_rust() => {
                             Self::version_binary(&config, &db, program_id, version).await?;
@@ -710,6 +1071,7 @@ impl Compiler {
                             db.set_program_status_guarded(tenant_id, program_id, version, ProgramStatus::Success).await?;
                             info!("Successfully invoked rust compiler for program {program_id} version {version} (tenant {tenant_id}).");
                             debug!("Set ProgramStatus::Success '{program_id}', version '{version}'");
+                            notification = Some(WebhookPayload::program(tenant_id, program_id, version, &ProgramStatus::Success));
                             job = None;
                         }
                         Ok(status) => {
@@ -727,7 +1089,8 @@ impl Compiler {
                                     // and we return a system error:
                                     ProgramStatus::SystemError(format!("{output}\nexit code: {status}"))
                             };
-                            db.set_program_status_guarded(tenant_id, program_id, version, status).await?;
+                            db.set_program_status_guarded(tenant_id, program_id, version, status.clone()).await?;
+                            notification = Some(WebhookPayload::program(tenant_id, program_id, version, &status));
                             job = None;
                         }
                         Err(e) => {
@@ -736,14 +1099,18 @@ impl Compiler {
                             } else {
                                 ProgramStatus::SystemError(format!("I/O error with sql-to-dbsp: {e}"))
                             };
-                            db.set_program_status_guarded(tenant_id, program_id, version, status).await?;
+                            db.set_program_status_guarded(tenant_id, program_id, version, status.clone()).await?;
+                            notification = Some(WebhookPayload::program(tenant_id, program_id, version, &status));
                             job = None;
                         }
                     }
                 }
             }
+            if let Some(payload) = notification {
+                webhooks::notify(&db, payload).await;
+            }
             // Pick the next program from the queue.
-            if job.is_none() {
+            if job.is_none() && drain_deadline.is_none() {
                 let program = {
                     let db = db.lock().await;
                     if let Some((tenant_id, program_id, version)) = db.next_job().await? {
@@ -787,12 +1154,25 @@ enum Stage {
     Rust,
 }
 
+enum CompilerProcess {
+    /// Compiler running on this host.
+    Local(Child),
+    /// Rust build dispatched to a remote worker.  Resolves to `true` once the
+    /// worker uploads the compiled binary.
+    Remote {
+        result: oneshot::Receiver<bool>,
+        queue: Arc<RemoteJobQueue>,
+    },
+}
+
 struct CompilationJob {
     stage: Stage,
     tenant_id: TenantId,
     program_id: ProgramId,
     version: Version,
-    compiler_process: Child,
+    compiler_process: CompilerProcess,
+    /// Time when the current stage of the job was started.
+    started: Instant,
 }
 
 impl CompilationJob {
@@ -843,40 +1223,47 @@ impl CompilationJob {
             ManagerError::io_error(format!("creating error log '{}'", stderr_path.display()), e)
         })?;
 
-        // `main.rs` file.
-        let rust_file = File::create(&rust_file_path).await.map_err(|e| {
+        // `main.rs` file, or the dataflow IR when compiling with the JIT
+        // backend.
+        let output_file_path = if config.jit {
+            config.jit_ir_path(program_id)
+        } else {
+            rust_file_path
+        };
+        let output_file = File::create(&output_file_path).await.map_err(|e| {
             ManagerError::io_error(
-                format!("failed to create '{}'", rust_file_path.display()),
+                format!("failed to create '{}'", output_file_path.display()),
                 e,
             )
         })?;
 
-        // Run compiler, direct output to `main.rs`.
+        // Run compiler, direct output to `main.rs` or the IR file.
         let schema_path = config.schema_path(program_id);
-        let compiler_process = Command::new(config.sql_compiler_path())
+        let mut command = Command::new(config.sql_compiler_path());
+        command
             .arg("-js")
             .arg(schema_path)
             .arg(sql_file_path.as_os_str())
-            .arg("-i")
+            .arg(if config.jit { "-j" } else { "-i" })
             .arg("-je")
             .arg("-alltables")
             .stdin(Stdio::null())
             .stderr(Stdio::from(err_file.into_std().await))
-            .stdout(Stdio::from(rust_file.into_std().await))
-            .spawn()
-            .map_err(|e| {
-                ManagerError::io_error(
-                    format!("starting SQL compiler '{}'", sql_file_path.display()),
-                    e,
-                )
-            })?;
+            .stdout(Stdio::from(output_file.into_std().await));
+        let compiler_process = command.spawn().map_err(|e| {
+            ManagerError::io_error(
+                format!("starting SQL compiler '{}'", sql_file_path.display()),
+                e,
+            )
+        })?;
 
         Ok(Self {
             tenant_id,
             stage: Stage::Sql,
             program_id,
             version,
-            compiler_process,
+            compiler_process: CompilerProcess::Local(compiler_process),
+            started: Instant::now(),
         })
     }
 
@@ -884,6 +1271,7 @@ impl CompilationJob {
     async fn rust(
         tenant_id: TenantId,
         config: &CompilerConfig,
+        remote_jobs: &Arc<RemoteJobQueue>,
         program_id: ProgramId,
         version: Version,
     ) -> Result<Self, ManagerError> {
@@ -896,21 +1284,58 @@ impl CompilationJob {
             .await
             .map_err(|e| ManagerError::io_error(format!("opening '{}'", rust_path.display()), e))?;
 
+        // Map operators in the generated code back to the SQL program.
+        let rust_code = fs::read_to_string(&rust_path)
+            .await
+            .map_err(|e| ManagerError::io_error(format!("reading '{}'", rust_path.display()), e))?;
+        let sql_file_path = config.sql_file_path(program_id);
+        let sql_code = fs::read_to_string(&sql_file_path).await.map_err(|e| {
+            ManagerError::io_error(format!("reading '{}'", sql_file_path.display()), e)
+        })?;
+
+        let schema_path = config.schema_path(program_id);
+        let schema = fs::read_to_string(&schema_path).await.map_err(|e| {
+            ManagerError::io_error(format!("reading '{}'", schema_path.display()), e)
+        })?;
+
+        main_rs
+            .write_all(sql_source_map(&rust_code, &sql_code).as_bytes())
+            .await
+            .map_err(|e| ManagerError::io_error(format!("writing '{}'", rust_path.display()), e))?;
+        main_rs
+            .write_all(sql_schema(&schema).as_bytes())
+            .await
+            .map_err(|e| ManagerError::io_error(format!("writing '{}'", rust_path.display()), e))?;
         main_rs
             .write_all(MAIN_FUNCTION.as_bytes())
             .await
             .map_err(|e| ManagerError::io_error(format!("writing '{}'", rust_path.display()), e))?;
         drop(main_rs);
 
-        // Write `project/Cargo.toml`.
-        Compiler::write_project_toml(config, program_id).await?;
+        let compiler_process = if config.remote_workers {
+            let main_rs = fs::read_to_string(&rust_path).await.map_err(|e| {
+                ManagerError::io_error(format!("reading '{}'", rust_path.display()), e)
+            })?;
+            let result = remote_jobs.push(RemoteJob {
+                program_id,
+                version,
+                main_rs,
+            });
+            CompilerProcess::Remote {
+                result,
+                queue: remote_jobs.clone(),
+            }
+        } else {
+            // Write `project/Cargo.toml`.
+            Compiler::write_project_toml(config, program_id).await?;
 
-        // Write workspace `Cargo.toml`.  The workspace contains SQL libs and the
-        // generated project crate.
-        Compiler::write_workspace_toml(config, program_id).await?;
+            // Write workspace `Cargo.toml`.  The workspace contains SQL libs and the
+            // generated project crate.
+            Compiler::write_workspace_toml(config, program_id).await?;
 
-        // Run cargo, direct stdout and stderr to the same file.
-        let compiler_process = Compiler::run_cargo_build(config, program_id).await?;
+            // Run cargo, direct stdout and stderr to the same file.
+            CompilerProcess::Local(Compiler::run_cargo_build(config, program_id).await?)
+        };
 
         Ok(Self {
             tenant_id,
@@ -918,14 +1343,27 @@ impl CompilationJob {
             program_id,
             version,
             compiler_process,
+            started: Instant::now(),
         })
     }
 
     /// Async-wait for the compiler to terminate.
     async fn wait(&mut self) -> Result<ExitStatus, ManagerError> {
-        let exit_status = self.compiler_process.wait().await.map_err(|e| {
-            ManagerError::io_error("waiting for the compiler process".to_string(), e)
-        })?;
+        let exit_status = match &mut self.compiler_process {
+            CompilerProcess::Local(process) => process.wait().await.map_err(|e| {
+                ManagerError::io_error("waiting for the compiler process".to_string(), e)
+            })?,
+            // Report remote builds as if they were local processes that
+            // exited with status 0 or 1.  The worker has already stored the
+            // output of a failed build where `error_output` expects it.
+            CompilerProcess::Remote { result, .. } => {
+                ExitStatus::from_raw(if result.await.unwrap_or(false) {
+                    0
+                } else {
+                    1 << 8
+                })
+            }
+        };
         Ok(exit_status)
         // doesn't update status
     }
@@ -957,7 +1395,12 @@ impl CompilationJob {
 
     /// Kill (Rust or SQL) compiler process.
     async fn cancel(&mut self) {
-        let _ = self.compiler_process.kill().await;
+        match &mut self.compiler_process {
+            CompilerProcess::Local(process) => {
+                let _ = process.kill().await;
+            }
+            CompilerProcess::Remote { queue, .. } => queue.cancel(self.program_id, self.version),
+        }
     }
 }
 
@@ -996,6 +1439,189 @@ mod test {
         assert_eq!(ProgramStatus::Pending, programdesc.status);
     }
 
+    #[test]
+    fn test_jit_launcher_script() {
+        let script = super::jit_launcher_script(
+            std::path::Path::new("/usr/bin/pipeline"),
+            "{\"nodes\": {}}",
+            "{\"inputs\": []}",
+        );
+        assert_eq!(
+            script,
+            r#"#!/bin/sh
+cat > ir.json <<'FELDERA_IR_EOF'
+{"nodes": {}}
+FELDERA_IR_EOF
+cat > schema.json <<'FELDERA_SCHEMA_EOF'
+{"inputs": []}
+FELDERA_SCHEMA_EOF
+exec '/usr/bin/pipeline' --ir ir.json --schema schema.json --optimize --release "$@"
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jit_artifacts() {
+        use clap::Parser;
+
+        let tmp_dir = TempDir::new().unwrap();
+        let conf = CompilerConfig::try_parse_from([
+            "compiler",
+            "--compiler-working-directory",
+            tmp_dir.path().to_str().unwrap(),
+            "--jit",
+        ])
+        .unwrap();
+        let program_id = ProgramId(Uuid::now_v7());
+        let version = Version(3);
+
+        // Outputs of the SQL compiler.
+        fs::create_dir_all(conf.project_dir(program_id))
+            .await
+            .unwrap();
+        fs::create_dir_all(conf.binaries_dir()).await.unwrap();
+        fs::write(conf.jit_ir_path(program_id), "{\"nodes\": {}}")
+            .await
+            .unwrap();
+        fs::write(conf.schema_path(program_id), "{\"inputs\": []}")
+            .await
+            .unwrap();
+
+        super::Compiler::write_jit_launcher(&conf, program_id, version)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(conf.versioned_jit_artifact(
+                program_id,
+                version,
+                super::JIT_IR_ARTIFACT
+            ))
+            .await
+            .unwrap(),
+            "{\"nodes\": {}}"
+        );
+        assert_eq!(
+            fs::read_to_string(conf.versioned_jit_artifact(
+                program_id,
+                version,
+                super::JIT_SCHEMA_ARTIFACT
+            ))
+            .await
+            .unwrap(),
+            "{\"inputs\": []}"
+        );
+
+        // The garbage collector attributes the artifacts to the program
+        // version, like the launcher.
+        let mut entries = fs::read_dir(conf.binaries_dir()).await.unwrap();
+        let mut count = 0;
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            assert_eq!(
+                super::Compiler::binary_path_to_parts(&entry).await,
+                Some((program_id, version))
+            );
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_rustc_config() {
+        use clap::Parser;
+
+        let conf = CompilerConfig::try_parse_from(["compiler"]).unwrap();
+        assert_eq!(conf.rustflags(), None);
+        assert!(conf.cargo_profile_env().is_empty());
+
+        let conf = CompilerConfig::try_parse_from([
+            "compiler",
+            "--rustc-target-cpu",
+            "native",
+            "--rustc-codegen-units",
+            "1",
+            "--rustc-lto",
+            "thin",
+            "--extra-rustflags",
+            "-C debuginfo=1",
+        ])
+        .unwrap();
+        assert_eq!(
+            conf.rustflags(),
+            Some("-C target-cpu=native -C debuginfo=1".to_string())
+        );
+        assert_eq!(
+            conf.cargo_profile_env(),
+            vec![
+                (
+                    "CARGO_PROFILE_RELEASE_CODEGEN_UNITS".to_string(),
+                    "1".to_string()
+                ),
+                ("CARGO_PROFILE_RELEASE_LTO".to_string(), "thin".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sql_compiler_message_error_code() {
+        let messages: Vec<super::SqlCompilerMessage> = serde_json::from_str(
+            r#"[ {
+  "startLineNumber" : 2,
+  "startColumn" : 1,
+  "endLineNumber" : 2,
+  "endColumn" : 7,
+  "warning" : true,
+  "errorType" : "Duplicate definition",
+  "errorCode" : "DuplicateDefinition",
+  "message" : "View v already defined"
+}, {
+  "startLineNumber" : 14,
+  "startColumn" : 13,
+  "endLineNumber" : 14,
+  "endColumn" : 13,
+  "warning" : false,
+  "errorType" : "Error parsing SQL",
+  "message" : "Encountered \"<EOF>\" at line 14, column 13."
+} ]"#,
+        )
+        .unwrap();
+        assert!(messages[0].warning);
+        assert_eq!(
+            messages[0].error_code.as_deref(),
+            Some("DuplicateDefinition")
+        );
+        assert_eq!(messages[1].error_code, None);
+    }
+
+    #[test]
+    fn test_sql_source_map() {
+        let sql = "CREATE TABLE t (x int);\nCREATE VIEW v AS\n  SELECT x + 1\n  FROM t;";
+        let rust = r#"
+    // DBSPSourceOperator 1
+    // sql: 1:1--1:22
+    let stream1 = circuit.add_source(t);
+    // DBSPMapOperator 2
+    // sql: 3:3--4:8
+    let stream2: Stream<_, _> = stream1.map(move |t| t.0 + 1);
+    // DBSPNoopOperator 3
+    let stream3 = stream2.noop();
+    // DBSPFilterOperator 4
+    // sql: 9:1--9:2
+    let stream4 = stream3.filter(move |t| true);
+"#;
+        assert_eq!(
+            super::sql_source_map(rust, sql),
+            "\nstatic SQL_SOURCE_MAP: &[(u32, &str)] = &[(4, \"CREATE TABLE t (x int)\"), (7, \"SELECT x + 1 FROM t\")];\n"
+        );
+    }
+
+    #[test]
+    fn test_sql_schema() {
+        assert_eq!(
+            super::sql_schema(r#"{"inputs": [], "outputs": []}"#),
+            "\nstatic SQL_SCHEMA: &str = \"{\\\"inputs\\\": [], \\\"outputs\\\": []}\";\n"
+        );
+    }
+
     #[tokio::test]
     async fn test_compiler_reconcile_no_local_binary() {
         let tid = TenantRecord::default().id;
@@ -1009,6 +1635,18 @@ mod test {
             compiler_working_directory: workdir.to_owned(),
             binary_ref_host: "127.0.0.1".to_string(),
             binary_ref_port: 9090,
+            binary_ref_bind_address: "0.0.0.0".to_string(),
+            shutdown_drain_secs: 60,
+            remote_workers: false,
+            remote_worker_timeout_secs: 3600,
+            worker_token_file: None,
+            artifact_store: None,
+            jit: false,
+            jit_pipeline_path: None,
+            rustc_target_cpu: None,
+            rustc_codegen_units: None,
+            rustc_lto: None,
+            extra_rustflags: None,
         };
 
         let (db, _temp) = crate::db::test::setup_pg().await;
@@ -1074,6 +1712,18 @@ mod test {
             compiler_working_directory: workdir.to_owned(),
             binary_ref_host: "127.0.0.1".to_string(),
             binary_ref_port: 9090,
+            binary_ref_bind_address: "0.0.0.0".to_string(),
+            shutdown_drain_secs: 60,
+            remote_workers: false,
+            remote_worker_timeout_secs: 3600,
+            worker_token_file: None,
+            artifact_store: None,
+            jit: false,
+            jit_pipeline_path: None,
+            rustc_target_cpu: None,
+            rustc_codegen_units: None,
+            rustc_lto: None,
+            extra_rustflags: None,
         };
 
         let (db, _temp) = crate::db::test::setup_pg().await;
@@ -1120,6 +1770,18 @@ mod test {
             compiler_working_directory: workdir.to_owned(),
             binary_ref_host: "127.0.0.1".to_string(),
             binary_ref_port: 9090,
+            binary_ref_bind_address: "0.0.0.0".to_string(),
+            shutdown_drain_secs: 60,
+            remote_workers: false,
+            remote_worker_timeout_secs: 3600,
+            worker_token_file: None,
+            artifact_store: None,
+            jit: false,
+            jit_pipeline_path: None,
+            rustc_target_cpu: None,
+            rustc_codegen_units: None,
+            rustc_lto: None,
+            extra_rustflags: None,
         };
 
         let (db, _temp) = crate::db::test::setup_pg().await;
@@ -1173,4 +1835,35 @@ mod test {
         assert!(!path1.exists());
         assert!(!path2.exists());
     }
+
+    #[tokio::test]
+    async fn test_compiler_checkpoint_on_shutdown() {
+        let tid = TenantRecord::default().id;
+        let (db, _temp) = crate::db::test::setup_pg().await;
+        let db = Arc::new(Mutex::new(db));
+
+        let (pid, vid) = create_program(&db, "p1").await;
+        db.lock()
+            .await
+            .set_program_for_compilation(tid, pid, vid, ProgramStatus::CompilingRust)
+            .await
+            .unwrap();
+
+        // A job that won't complete before shutdown.
+        let job = super::CompilationJob {
+            stage: super::Stage::Rust,
+            tenant_id: tid,
+            program_id: pid,
+            version: vid,
+            compiler_process: super::CompilerProcess::Local(
+                tokio::process::Command::new("sleep")
+                    .arg("1000")
+                    .spawn()
+                    .unwrap(),
+            ),
+            started: std::time::Instant::now(),
+        };
+        super::Compiler::checkpoint_job(&db, job).await.unwrap();
+        check_program_status_pending(&db, "p1").await;
+    }
 }
@@ -1,6 +1,8 @@
+use crate::artifact_store::{is_object_store_url, ArtifactStore};
 use crate::db::{PipelineId, ProgramId, Version};
+use crate::error::ManagerError;
 use anyhow::{Error as AnyError, Result as AnyResult};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use serde::Deserialize;
 use std::{
     fs::{canonicalize, create_dir_all},
@@ -15,6 +17,10 @@ fn default_server_address() -> String {
     "127.0.0.1".to_string()
 }
 
+const fn default_trash_retention_days() -> u32 {
+    7
+}
+
 fn default_working_directory() -> String {
     ".".to_string()
 }
@@ -31,15 +37,25 @@ fn default_binary_ref_port() -> u16 {
     9090
 }
 
+fn default_shutdown_drain_secs() -> u64 {
+    60
+}
+
+fn default_remote_worker_timeout_secs() -> u64 {
+    3600
+}
+
 /// Pipeline manager configuration read from a YAML config file or from command
 /// line arguments.
 #[derive(Parser, Deserialize, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct DatabaseConfig {
     /// Point to a relational database to use for state management. Accepted
-    /// values are `postgres://<host>:<port>` or `postgres-embed`. For
-    /// postgres-embed we create a DB in the current working directory. For
-    /// postgres, we use the connection string as provided.
+    /// values are `postgres://<host>:<port>`, `postgres-embed`, or
+    /// `sqlite://<path>`. For postgres-embed we create a DB in the current
+    /// working directory. For postgres, we use the connection string as
+    /// provided. For sqlite, we open or create a SQLite database at the given
+    /// path, which requires the `lite` feature.
     #[serde(default = "default_db_connection_string")]
     #[arg(short, long, default_value_t = default_db_connection_string())]
     pub db_connection_string: String,
@@ -56,7 +72,9 @@ pub struct DatabaseConfig {
 impl DatabaseConfig {
     /// Database connection string.
     pub(crate) fn database_connection_string(&self) -> String {
-        if self.db_connection_string.starts_with("postgres") {
+        if self.db_connection_string.starts_with("postgres")
+            || self.db_connection_string.starts_with("sqlite://")
+        {
             // this starts_with works for `postgres://` and `postgres-embed`
             self.db_connection_string.clone()
         } else {
@@ -65,6 +83,17 @@ impl DatabaseConfig {
     }
 }
 
+/// Format of the log messages printed by the manager.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
 /// Pipeline manager configuration read from a YAML config file or from command
 /// line arguments.
 #[derive(Parser, Deserialize, Debug, Clone)]
@@ -118,6 +147,42 @@ pub struct ApiServerConfig {
     #[serde(default)]
     #[arg(long)]
     pub dev_mode: bool,
+
+    /// Number of days deleted pipelines and connectors are kept in the trash
+    /// before being permanently deleted, defaults to 7.
+    #[serde(default = "default_trash_retention_days")]
+    #[arg(long, default_value_t = default_trash_retention_days())]
+    pub trash_retention_days: u32,
+
+    /// Alerting rules and notification channels YAML file.
+    ///
+    /// Alerting is disabled if no file is specified.
+    #[serde(default)]
+    #[arg(long)]
+    pub alerting_config: Option<String>,
+
+    /// Format of log messages, defaults to `text`.
+    ///
+    /// With `json`, each message is printed as a JSON object on its own line.
+    /// Messages logged while handling an HTTP request include the request
+    /// id, the route and the tenant id, so logs can be indexed without
+    /// parsing free-form text.
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Run in lite mode, a single-process setup for laptops and demos.
+    ///
+    /// Stores the manager's state in an embedded SQLite database in the
+    /// working directory, compiles programs with the JIT backend, and runs
+    /// pipelines inside the manager process, so no Postgres server or Rust
+    /// toolchain is required.  Requires the manager to be built with the
+    /// `lite` feature.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub lite: bool,
 }
 
 impl ApiServerConfig {
@@ -147,6 +212,13 @@ impl ApiServerConfig {
         Ok(self)
     }
 
+    /// Where lite mode stores the database.
+    ///
+    /// e.g., `<working-directory>/feldera.db`
+    pub fn lite_database_path(&self) -> PathBuf {
+        Path::new(&self.api_server_working_directory).join("feldera.db")
+    }
+
     /// Where Postgres embed stores the database.
     ///
     /// e.g., `<working-directory>/data`
@@ -210,6 +282,101 @@ pub struct CompilerConfig {
     /// for runners.
     #[arg(long, default_value_t = default_binary_ref_port())]
     pub binary_ref_port: u16,
+
+    /// The address the compiler's HTTP server, which serves compiled
+    /// binaries to runners and jobs to remote compile workers, listens on.
+    #[arg(long, default_value = "0.0.0.0")]
+    pub binary_ref_bind_address: String,
+
+    /// How long the compiler waits, on shutdown, for an ongoing compilation
+    /// to finish.
+    ///
+    /// Compilations that are still running after this many seconds are
+    /// cancelled and their programs are returned to the queue, so they are
+    /// compiled again on restart.
+    #[serde(default = "default_shutdown_drain_secs")]
+    #[arg(long, default_value_t = default_shutdown_drain_secs())]
+    pub shutdown_drain_secs: u64,
+
+    /// Dispatch Rust builds to remote compile workers instead of running
+    /// `cargo` on this host.
+    ///
+    /// Workers pull jobs from the HTTP server listening on
+    /// `binary_ref_port` and upload compiled binaries to the
+    /// `artifact_store`.  Requires `worker_token_file` and `artifact_store`.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub remote_workers: bool,
+
+    /// How long a remote worker may take to build a program before the job
+    /// is handed to another worker.
+    #[serde(default = "default_remote_worker_timeout_secs")]
+    #[arg(long, default_value_t = default_remote_worker_timeout_secs())]
+    pub remote_worker_timeout_secs: u64,
+
+    /// File containing the token that remote compile workers present to the
+    /// compiler service in an `Authorization: Bearer <token>` header.
+    ///
+    /// Required with `remote_workers`; compile workers must be started with
+    /// a file containing the same token.
+    #[arg(long)]
+    pub worker_token_file: Option<String>,
+
+    /// Where remote compile workers store the binaries they build and
+    /// runners fetch them from: either a path to a directory shared by all
+    /// hosts or an object store URL of the form `s3://<bucket>/<prefix>`,
+    /// `gs://<bucket>/<prefix>`, or `az://<container>/<prefix>`.  Object
+    /// store credentials are read from the environment.
+    ///
+    /// Required with `remote_workers` and by compile workers.
+    #[arg(long)]
+    pub artifact_store: Option<String>,
+
+    /// Compile programs with the JIT backend instead of generating and
+    /// building Rust code.
+    ///
+    /// JIT-compiled programs start much faster, but run slower than
+    /// programs compiled to native code.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub jit: bool,
+
+    /// Location of the `pipeline` executable that runs JIT-compiled
+    /// programs.
+    ///
+    /// Defaults to `pipeline` in the directory of the manager executable.
+    #[arg(long)]
+    pub jit_pipeline_path: Option<String>,
+
+    /// CPU to generate code for, passed to `rustc` as `-C target-cpu`.
+    ///
+    /// Set to `native` to optimize pipelines for the CPU of the host that
+    /// compiles them.  Pipelines compiled this way may not run on hosts with
+    /// a different CPU.
+    #[arg(long)]
+    pub rustc_target_cpu: Option<String>,
+
+    /// Number of code generation units used when compiling pipelines.
+    ///
+    /// Fewer units produce faster code; more units compile faster.  Defaults
+    /// to the `cargo` default for the profile.
+    #[arg(long)]
+    pub rustc_codegen_units: Option<u32>,
+
+    /// Link-time optimization setting used when compiling pipelines: one of
+    /// `off`, `thin` or `fat`.  Defaults to the `cargo` default for the
+    /// profile.
+    #[arg(long)]
+    pub rustc_lto: Option<String>,
+
+    /// Additional flags passed to `rustc` via `RUSTFLAGS` when compiling
+    /// pipelines, e.g., `-C debuginfo=1`.
+    #[arg(long, allow_hyphen_values = true)]
+    pub extra_rustflags: Option<String>,
 }
 
 impl CompilerConfig {
@@ -243,6 +410,25 @@ impl CompilerConfig {
         Path::new(&self.binaries_dir()).join(Self::binary_name(program_id, version))
     }
 
+    /// Location of an artifact of a program version compiled with the JIT
+    /// backend, stored next to its executable.
+    ///
+    /// `artifact` is one of [`JIT_ARTIFACTS`](crate::compiler::JIT_ARTIFACTS),
+    /// e.g.,
+    /// `<working-directory>/binaries/
+    /// project_0188e0cd-d8b0-71d5-bb5a-2f66c7b07dfb_v11.ir.json`
+    pub(crate) fn versioned_jit_artifact(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+        artifact: &str,
+    ) -> PathBuf {
+        Path::new(&self.binaries_dir()).join(format!(
+            "{}.{artifact}",
+            Self::binary_name(program_id, version)
+        ))
+    }
+
     /// Location of the compiled executable for the project in the cargo target
     /// dir.
     /// Note: This is generally not an executable that's run as a pipeline.
@@ -298,6 +484,38 @@ impl CompilerConfig {
     pub(crate) fn workspace_toml_path(&self) -> PathBuf {
         self.workspace_dir().join("Cargo.toml")
     }
+
+    /// Flags to add to `RUSTFLAGS` when compiling pipelines, if any.
+    pub(crate) fn rustflags(&self) -> Option<String> {
+        let flags: Vec<String> = self
+            .rustc_target_cpu
+            .iter()
+            .map(|cpu| format!("-C target-cpu={cpu}"))
+            .chain(self.extra_rustflags.iter().cloned())
+            .collect();
+        if flags.is_empty() {
+            None
+        } else {
+            Some(flags.join(" "))
+        }
+    }
+
+    /// Environment variables that override settings of the `cargo` profile
+    /// used to compile pipelines.
+    pub(crate) fn cargo_profile_env(&self) -> Vec<(String, String)> {
+        let profile = if self.debug { "DEV" } else { "RELEASE" };
+        let mut env = Vec::new();
+        if let Some(codegen_units) = self.rustc_codegen_units {
+            env.push((
+                format!("CARGO_PROFILE_{profile}_CODEGEN_UNITS"),
+                codegen_units.to_string(),
+            ));
+        }
+        if let Some(lto) = &self.rustc_lto {
+            env.push((format!("CARGO_PROFILE_{profile}_LTO"), lto.clone()));
+        }
+        env
+    }
     /// Convert all directory paths in the `self` to absolute paths.
     ///
     /// Converts `working_directory` `sql_compiler_home`, and
@@ -338,9 +556,70 @@ impl CompilerConfig {
                 .into_owned();
         }
 
+        if self.remote_workers
+            && (self.worker_token_file.is_none() || self.artifact_store.is_none())
+        {
+            return Err(AnyError::msg(
+                "'remote_workers' requires 'worker_token_file' and 'artifact_store' to be set",
+            ));
+        }
+
+        // Runners locate binaries in a local artifact store via absolute
+        // `file://` URLs.
+        if let Some(path) = self
+            .artifact_store
+            .as_mut()
+            .filter(|path| !is_object_store_url(path))
+        {
+            create_dir_all(&path).map_err(|e| {
+                AnyError::msg(format!(
+                    "unable to create or open artifact store directory '{path}': {e}"
+                ))
+            })?;
+            *path = canonicalize(&path)
+                .map_err(|e| {
+                    AnyError::msg(format!(
+                        "failed to access artifact store directory '{path}': {e}"
+                    ))
+                })?
+                .to_string_lossy()
+                .into_owned();
+        }
+
         Ok(self)
     }
 
+    /// Read the remote worker token from `worker_token_file`, ignoring
+    /// leading and trailing whitespace.
+    pub(crate) fn worker_token(&self) -> Result<String, ManagerError> {
+        let Some(path) = &self.worker_token_file else {
+            return Err(ManagerError::RemoteWorkerError {
+                error: "no worker token file is configured".to_string(),
+            });
+        };
+        let token = std::fs::read_to_string(path)
+            .map_err(|e| ManagerError::io_error(format!("reading token file '{path}'"), e))?;
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(ManagerError::io_error(
+                format!("reading token file '{path}'"),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "the file is empty"),
+            ));
+        }
+        Ok(token.to_string())
+    }
+
+    /// The artifact store remote compile workers upload binaries to.
+    pub(crate) fn open_artifact_store(&self) -> Result<ArtifactStore, ManagerError> {
+        let location =
+            self.artifact_store
+                .as_deref()
+                .ok_or_else(|| ManagerError::ArtifactStoreError {
+                    error: "no artifact store is configured".to_string(),
+                })?;
+        ArtifactStore::open(location)
+    }
+
     /// SQL compiler executable.
     pub(crate) fn sql_compiler_path(&self) -> PathBuf {
         Path::new(&self.sql_compiler_home)
@@ -370,6 +649,35 @@ impl CompilerConfig {
     pub(crate) fn compiler_stderr_path(&self, program_id: ProgramId) -> PathBuf {
         self.project_dir(program_id).join("err.log")
     }
+
+    /// Dataflow IR generated by the SQL compiler when compiling with the JIT
+    /// backend.
+    pub(crate) fn jit_ir_path(&self, program_id: ProgramId) -> PathBuf {
+        self.project_dir(program_id).join("ir.json")
+    }
+
+    /// Executable that runs JIT-compiled programs.
+    pub(crate) fn jit_pipeline_path(&self) -> PathBuf {
+        match &self.jit_pipeline_path {
+            Some(path) => PathBuf::from(path),
+            None => std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.join("pipeline")))
+                .unwrap_or_else(|| PathBuf::from("pipeline")),
+        }
+    }
+}
+
+/// Remote compile worker configuration.
+///
+/// The worker builds programs in its own working directory, configured via
+/// [`CompilerConfig`].
+#[derive(Parser, Deserialize, Debug, Clone)]
+pub struct CompileWorkerConfig {
+    /// URL of the compiler service to pull jobs from, i.e., the service's
+    /// `binary_ref_host` and `binary_ref_port`.
+    #[arg(long, default_value = "http://127.0.0.1:9090")]
+    pub compiler_url: String,
 }
 
 #[derive(Parser, Deserialize, Debug, Clone)]
@@ -386,6 +694,18 @@ pub struct LocalRunnerConfig {
     #[serde(default = "default_server_address")]
     #[arg(long, default_value_t = default_server_address())]
     pub pipeline_host: String,
+
+    /// Run pipelines inside the runner process instead of as separate
+    /// processes.
+    ///
+    /// Only supports programs compiled with the JIT backend (see
+    /// `--jit`).  In-process pipelines start faster and use less memory,
+    /// but a pipeline that crashes takes down the runner.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub in_process: bool,
 }
 
 impl LocalRunnerConfig {
@@ -441,4 +761,15 @@ impl LocalRunnerConfig {
         self.pipeline_dir(pipeline_id)
             .join(dbsp_adapters::server::SERVER_PORT_FILE)
     }
+
+    /// Location to write the bearer token required by the pipeline.
+    pub(crate) fn auth_token_file_path(&self, pipeline_id: PipelineId) -> PathBuf {
+        self.pipeline_dir(pipeline_id).join("auth_token")
+    }
+
+    /// Location for the certificate served by the pipeline over HTTPS
+    pub(crate) fn cert_file_path(&self, pipeline_id: PipelineId) -> PathBuf {
+        self.pipeline_dir(pipeline_id)
+            .join(dbsp_adapters::server::SERVER_CERT_FILE)
+    }
 }
@@ -1,4 +1,4 @@
-use super::{ConnectorId, PipelineId, ProgramId, Version};
+use super::{ConnectorId, DeploymentId, PipelineId, ProgramId, Version, WebhookId};
 use crate::auth::TenantId;
 use actix_web::{
     body::BoxBody, http::StatusCode, HttpResponse, HttpResponseBuilder, ResponseError,
@@ -36,6 +36,12 @@ pub enum DBError {
         error: Box<pg_embed::pg_errors::PgEmbedError>,
         backtrace: Backtrace,
     },
+    #[cfg(feature = "lite")]
+    #[serde(serialize_with = "serialize_sqlite_error")]
+    SqliteError {
+        error: Box<rusqlite::Error>,
+        backtrace: Backtrace,
+    },
     // Catch-all error for unexpected invalid data extracted from DB.
     // We can split it into several separate error variants if needed.
     #[serde(serialize_with = "serialize_invalid_data")]
@@ -66,6 +72,12 @@ pub enum DBError {
     UnknownTenant {
         tenant_id: TenantId,
     },
+    UnknownWebhook {
+        webhook_id: WebhookId,
+    },
+    UnknownDeployment {
+        deployment_id: DeploymentId,
+    },
     UnknownAttachedConnector {
         pipeline_id: PipelineId,
         name: String,
@@ -193,6 +205,21 @@ where
     ser.end()
 }
 
+#[cfg(feature = "lite")]
+fn serialize_sqlite_error<S>(
+    error: &rusqlite::Error,
+    backtrace: &Backtrace,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut ser = serializer.serialize_struct("SqliteError", 2)?;
+    ser.serialize_field("error", &error.to_string())?;
+    ser.serialize_field("backtrace", &backtrace.to_string())?;
+    ser.end()
+}
+
 fn serialize_invalid_data<S>(
     error: &String,
     backtrace: &Backtrace,
@@ -295,6 +322,16 @@ impl From<pg_embed::pg_errors::PgEmbedError> for DBError {
     }
 }
 
+#[cfg(feature = "lite")]
+impl From<rusqlite::Error> for DBError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::SqliteError {
+            error: Box::new(error),
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
 impl Display for DBError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -311,6 +348,10 @@ impl Display for DBError {
             DBError::PgEmbedError { error, .. } => {
                 write!(f, "PG-embed error: '{error}'")
             }
+            #[cfg(feature = "lite")]
+            DBError::SqliteError { error, .. } => {
+                write!(f, "Unexpected SQLite error: '{error}'")
+            }
             DBError::InvalidData { error, .. } => {
                 write!(f, "Invalid DB data '{error}'")
             }
@@ -344,6 +385,12 @@ impl Display for DBError {
             DBError::UnknownTenant { tenant_id } => {
                 write!(f, "Unknown tenant id '{tenant_id}'")
             }
+            DBError::UnknownWebhook { webhook_id } => {
+                write!(f, "Unknown webhook id '{webhook_id}'")
+            }
+            DBError::UnknownDeployment { deployment_id } => {
+                write!(f, "Unknown deployment id '{deployment_id}'")
+            }
             DBError::DuplicateName => {
                 write!(f, "An entity with this name already exists")
             }
@@ -410,6 +457,8 @@ impl DetailedError for DBError {
             Self::PostgresMigrationError { .. } => Cow::from("PostgresMigrationError"),
             #[cfg(feature = "pg-embed")]
             Self::PgEmbedError { .. } => Cow::from("PgEmbedError"),
+            #[cfg(feature = "lite")]
+            Self::SqliteError { .. } => Cow::from("SqliteError"),
             Self::InvalidData { .. } => Cow::from("InvalidData"),
             Self::InvalidStatus { .. } => Cow::from("InvalidStatus"),
             Self::UnknownProgram { .. } => Cow::from("UnknownProgram"),
@@ -418,6 +467,8 @@ impl DetailedError for DBError {
             Self::UnknownPipeline { .. } => Cow::from("UnknownPipeline"),
             Self::UnknownConnector { .. } => Cow::from("UnknownConnector"),
             Self::UnknownTenant { .. } => Cow::from("UnknownTenant"),
+            Self::UnknownWebhook { .. } => Cow::from("UnknownWebhook"),
+            Self::UnknownDeployment { .. } => Cow::from("UnknownDeployment"),
             Self::UnknownAttachedConnector { .. } => Cow::from("UnknownAttachedConnector"),
             Self::UnknownName { .. } => Cow::from("UnknownName"),
             Self::DuplicateName => Cow::from("DuplicateName"),
@@ -440,6 +491,8 @@ impl DetailedError for DBError {
             Self::UnknownProgram { .. } => Level::Info,
             Self::UnknownPipeline { .. } => Level::Info,
             Self::UnknownConnector { .. } => Level::Info,
+            Self::UnknownWebhook { .. } => Level::Info,
+            Self::UnknownDeployment { .. } => Level::Info,
             Self::UnknownName { .. } => Level::Info,
             _ => Level::Error,
         }
@@ -456,6 +509,8 @@ impl ResponseError for DBError {
             Self::PostgresMigrationError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             #[cfg(feature = "pg-embed")]
             Self::PgEmbedError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "lite")]
+            Self::SqliteError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidData { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidStatus { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::UnknownProgram { .. } => StatusCode::NOT_FOUND,
@@ -467,6 +522,8 @@ impl ResponseError for DBError {
             // TODO: should we report not found instead?
             Self::UnknownTenant { .. } => StatusCode::UNAUTHORIZED,
             Self::UnknownAttachedConnector { .. } => StatusCode::NOT_FOUND,
+            Self::UnknownWebhook { .. } => StatusCode::NOT_FOUND,
+            Self::UnknownDeployment { .. } => StatusCode::NOT_FOUND,
             // This error should never bubble up till here
             Self::DuplicateKey { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidKey => StatusCode::UNAUTHORIZED,
@@ -2,7 +2,7 @@
 use crate::config::ApiServerConfig;
 use crate::{
     auth::{TenantId, TenantRecord},
-    compiler::ProgramStatus,
+    compiler::{ProgramStatus, SqlCompilerMessage},
     config::DatabaseConfig,
 };
 use async_trait::async_trait;
@@ -37,8 +37,13 @@ pub(crate) mod test;
 
 #[cfg(feature = "pg-embed")]
 mod pg_setup;
+#[cfg(feature = "lite")]
+mod sqlite;
 pub(crate) mod storage;
 
+#[cfg(feature = "lite")]
+pub use sqlite::SqliteDB;
+
 mod error;
 pub use error::DBError;
 
@@ -52,19 +57,29 @@ mod embedded {
 /// The API assumes that the caller holds a database lock, and therefore
 /// doesn't use transactions (and hence doesn't need to deal with conflicts).
 ///
+/// The database is either a Postgres server or, in lite mode, an embedded
+/// SQLite database (see [`ProjectDB::connect`]).
+///
 /// # Compilation queue
 ///
 /// We use the `status` and `status_since` columns to maintain the compilation
 /// queue.  A program is enqueued for compilation by setting its status to
 /// [`ProgramStatus::Pending`].  The `status_since` column is set to the current
 /// time, which determines the position of the program in the queue.
-pub struct ProjectDB {
+pub enum ProjectDB {
+    Postgres(PostgresDB),
+    #[cfg(feature = "lite")]
+    Sqlite(SqliteDB),
+}
+
+/// Project database stored in Postgres.
+pub struct PostgresDB {
     pub config: tokio_postgres::Config,
     pool: Pool,
     // Used in dev mode for having an embedded Postgres DB live through the
     // lifetime of the program.
     #[cfg(feature = "pg-embed")]
-    #[allow(dead_code)] // It has to stay alive until ProjectDB is dropped.
+    #[allow(dead_code)] // It has to stay alive until PostgresDB is dropped.
     pg_inst: Option<pg_embed::postgres::PgEmbed>,
 }
 
@@ -118,6 +133,30 @@ impl Display for AttachedConnectorId {
     }
 }
 
+/// Unique webhook id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[repr(transparent)]
+#[serde(transparent)]
+pub struct WebhookId(#[cfg_attr(test, proptest(strategy = "test::limited_uuid()"))] pub Uuid);
+impl Display for WebhookId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Unique deployment id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[repr(transparent)]
+#[serde(transparent)]
+pub struct DeploymentId(#[cfg_attr(test, proptest(strategy = "test::limited_uuid()"))] pub Uuid);
+impl Display for DeploymentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Version number.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
@@ -195,6 +234,15 @@ impl ProgramStatus {
     }
 }
 
+/// Decode the `warnings` column of the `program` table.
+fn warnings_from_column(warnings: Option<String>) -> Result<Vec<SqlCompilerMessage>, DBError> {
+    warnings
+        .map(|warnings| serde_json::from_str(&warnings))
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|e| DBError::invalid_data(format!("Error parsing program warnings: {e}")))
+}
+
 /// A struct containting the tables (inputs) and views for a program.
 ///
 /// Parse from the JSON data-type of the DDL generated by the SQL compiler.
@@ -276,6 +324,13 @@ pub(crate) struct ProgramDescr {
     pub version: Version,
     /// Program compilation status.
     pub status: ProgramStatus,
+    /// Warnings reported by the SQL compiler for the current version of the
+    /// program.
+    ///
+    /// Unlike errors, which are reported as part of the
+    /// [`ProgramStatus::SqlError`] status, warnings are reported for programs
+    /// that compile successfully too.
+    pub warnings: Vec<SqlCompilerMessage>,
     /// A JSON description of the SQL tables and view declarations including
     /// field names and types.
     ///
@@ -459,7 +514,10 @@ pub enum PipelineStatus {
     ///    The runner passes the shutdown request to the pipeline to perform a
     ///    graceful shutdown; transitions to the
     ///    [`ShuttingDown`](`Self::ShuttingDown`) state.
-    /// 3. An unexpected runtime error renders the pipeline
+    /// 3. The pipeline is drained via its `/drain` endpoint and exits after
+    ///    processing all of its inputs; transitions to the
+    ///    [`Shutdown`](`Self::Shutdown`) state.
+    /// 4. An unexpected runtime error renders the pipeline
     ///    [`Failed`](`Self::Failed`).
     Running,
 
@@ -677,6 +735,9 @@ impl PipelineRevision {
             }
             let input_endpoint_config = InputEndpointConfig {
                 stream: Cow::from(ac.relation_name.clone()),
+                columns: None,
+                lineage: None,
+                dedup: None,
                 connector_config: connector.unwrap().config.clone(),
             };
             expanded_inputs.insert(Cow::from(ac.name.clone()), input_endpoint_config);
@@ -713,6 +774,25 @@ impl PipelineRevision {
     }
 }
 
+/// A program that is waiting to be compiled or is being compiled.
+#[derive(Deserialize, Serialize, ToSchema, Debug, Eq, PartialEq, Clone)]
+pub(crate) struct CompilationJob {
+    /// Tenant that owns the program.
+    pub tenant_id: TenantId,
+    /// Program being compiled.
+    pub program_id: ProgramId,
+    /// Program version being compiled.
+    pub version: Version,
+    /// Compilation phase: [`Pending`](`ProgramStatus::Pending`) while the job
+    /// is queued, [`CompilingSql`](`ProgramStatus::CompilingSql`) or
+    /// [`CompilingRust`](`ProgramStatus::CompilingRust`) once the compiler
+    /// has picked it up.
+    pub status: ProgramStatus,
+    /// Time when the job entered its current phase.  For pending jobs, this
+    /// is the time the job was queued.
+    pub status_since: DateTime<Utc>,
+}
+
 /// Pipeline descriptor.
 #[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone)]
 pub(crate) struct PipelineDescr {
@@ -762,6 +842,18 @@ pub(crate) struct PipelineRuntimeState {
     /// Time when the pipeline started executing.
     #[cfg_attr(test, proptest(value = "Utc::now()"))]
     pub created: DateTime<Utc>,
+
+    /// Certificate (in PEM format) served by the pipeline if it is reachable
+    /// over HTTPS.  Used to verify the pipeline when forwarding requests to
+    /// it.
+    pub tls_certificate: Option<String>,
+
+    /// Bearer token required by the pipeline's HTTP server.
+    ///
+    /// Only used by the runner to authenticate requests it forwards to the
+    /// pipeline; never reported to clients.
+    #[serde(skip)]
+    pub auth_token: Option<String>,
 }
 
 impl PipelineRuntimeState {
@@ -779,6 +871,14 @@ impl PipelineRuntimeState {
         self.location = location;
     }
 
+    pub(crate) fn set_tls_certificate(&mut self, tls_certificate: Option<String>) {
+        self.tls_certificate = tls_certificate;
+    }
+
+    pub(crate) fn set_auth_token(&mut self, auth_token: Option<String>) {
+        self.auth_token = auth_token;
+    }
+
     pub(crate) fn set_created(&mut self) {
         self.created = Utc::now();
     }
@@ -819,6 +919,177 @@ pub(crate) struct ConnectorDescr {
     pub config: ConnectorConfig,
 }
 
+/// A pipeline in the trash.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone)]
+pub(crate) struct DeletedPipeline {
+    /// Configuration of the pipeline at the time it was deleted.
+    pub descriptor: PipelineDescr,
+
+    /// Time when the pipeline was moved to the trash.
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// A connector in the trash.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone)]
+pub(crate) struct DeletedConnector {
+    /// Configuration of the connector at the time it was deleted.
+    pub descriptor: ConnectorDescr,
+
+    /// Time when the connector was moved to the trash.
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Pipeline lifecycle events that webhooks can subscribe to.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WebhookEvent {
+    /// The pipeline was deployed and is now running or paused.
+    Deployed,
+    /// The pipeline failed.
+    Failed,
+    /// The pipeline was shut down.
+    Shutdown,
+    /// The pipeline was deployed again after it had failed.
+    Restarted,
+    /// A program was compiled successfully.
+    Compiled,
+    /// A program failed to compile.
+    CompilationFailed,
+    /// An input or output connector of a running pipeline reported a fatal
+    /// error or started encountering transport errors.
+    ConnectorDegraded,
+}
+
+impl WebhookEvent {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deployed => "deployed",
+            Self::Failed => "failed",
+            Self::Shutdown => "shutdown",
+            Self::Restarted => "restarted",
+            Self::Compiled => "compiled",
+            Self::CompilationFailed => "compilation_failed",
+            Self::ConnectorDegraded => "connector_degraded",
+        }
+    }
+}
+
+impl TryFrom<&str> for WebhookEvent {
+    type Error = DBError;
+
+    fn try_from(value: &str) -> Result<Self, DBError> {
+        match value {
+            "deployed" => Ok(Self::Deployed),
+            "failed" => Ok(Self::Failed),
+            "shutdown" => Ok(Self::Shutdown),
+            "restarted" => Ok(Self::Restarted),
+            "compiled" => Ok(Self::Compiled),
+            "compilation_failed" => Ok(Self::CompilationFailed),
+            "connector_degraded" => Ok(Self::ConnectorDegraded),
+            _ => Err(DBError::invalid_data(format!(
+                "Invalid webhook event '{value}'"
+            ))),
+        }
+    }
+}
+
+/// Webhook descriptor.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone)]
+pub(crate) struct WebhookDescr {
+    /// Unique webhook id.
+    pub webhook_id: WebhookId,
+    /// URL that event notifications are posted to.
+    pub url: String,
+    /// Events the webhook is subscribed to.
+    pub events: Vec<WebhookEvent>,
+    /// How failed deliveries are retried.
+    pub retry_policy: WebhookRetryPolicy,
+}
+
+/// Retry policy for failed webhook deliveries.
+///
+/// A delivery fails if the webhook cannot be reached or responds with a
+/// non-2xx status.  The delay between attempts doubles after every failed
+/// attempt.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub(crate) struct WebhookRetryPolicy {
+    /// Max number of delivery attempts per notification, including the
+    /// first one.
+    #[cfg_attr(test, proptest(strategy = "1..10u32"))]
+    pub max_attempts: u32,
+    /// Delay before the first retry, in milliseconds.
+    #[cfg_attr(test, proptest(strategy = "0..100_000u64"))]
+    pub initial_delay_ms: u64,
+}
+
+impl Default for WebhookRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 1000,
+        }
+    }
+}
+
+impl WebhookRetryPolicy {
+    /// Largest accepted value of `max_attempts`.
+    pub const MAX_ATTEMPTS: u32 = 20;
+
+    /// Largest delay between two delivery attempts, in milliseconds.
+    /// Also the largest accepted value of `initial_delay_ms`.
+    pub const MAX_DELAY_MS: u64 = 3_600_000;
+
+    /// Checks that the policy is within the supported limits.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > Self::MAX_ATTEMPTS {
+            return Err(format!(
+                "'max_attempts' must be between 1 and {}",
+                Self::MAX_ATTEMPTS
+            ));
+        }
+        if self.initial_delay_ms > Self::MAX_DELAY_MS {
+            return Err(format!(
+                "'initial_delay_ms' must not exceed {}",
+                Self::MAX_DELAY_MS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A webhook along with the secret used to sign its payloads.
+///
+/// The secret is only revealed to the client when the webhook is created.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub(crate) struct WebhookSubscription {
+    pub descriptor: WebhookDescr,
+    pub secret: String,
+}
+
+/// Deployment descriptor.
+///
+/// A deployment groups pipelines that must be started and shut down
+/// together.
+#[derive(Deserialize, Serialize, ToSchema, Eq, PartialEq, Debug, Clone)]
+pub(crate) struct DeploymentDescr {
+    /// Unique deployment id.
+    pub deployment_id: DeploymentId,
+    /// Deployment name.
+    pub name: String,
+    /// Deployment description.
+    pub description: String,
+    /// Pipelines of the deployment, in the order in which they are started.
+    /// Pipelines are shut down in reverse order.
+    pub pipelines: Vec<PipelineId>,
+    /// Runtime configuration shared by all pipelines of the deployment.
+    ///
+    /// When set, it replaces the configuration of each pipeline when the
+    /// deployment is started.
+    pub config: Option<RuntimeConfig>,
+}
+
 /// Permission types for invoking pipeline manager APIs
 #[derive(Serialize, ToSchema, Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
@@ -827,6 +1098,36 @@ pub(crate) enum ApiPermission {
     Write,
 }
 
+/// Resources consumed by a tenant since it was created.
+///
+/// Also used to describe an increment to the counters
+/// (see [`Storage::record_tenant_usage`]).
+#[derive(Deserialize, Serialize, ToSchema, Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub(crate) struct TenantUsage {
+    /// Total time spent compiling the tenant's programs, in milliseconds.
+    #[cfg_attr(test, proptest(strategy = "0..1_000_000i64"))]
+    pub compile_time_ms: i64,
+    /// Total time the tenant's pipelines were deployed, in milliseconds.
+    #[cfg_attr(test, proptest(strategy = "0..1_000_000i64"))]
+    pub run_time_ms: i64,
+    /// Total number of bytes received by the input endpoints of the
+    /// tenant's pipelines.
+    #[cfg_attr(test, proptest(strategy = "0..1_000_000i64"))]
+    pub ingress_bytes: i64,
+    /// Total number of bytes sent by the output endpoints of the
+    /// tenant's pipelines.
+    #[cfg_attr(test, proptest(strategy = "0..1_000_000i64"))]
+    pub egress_bytes: i64,
+}
+
+impl TenantUsage {
+    /// True if all counters are zero.
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 fn convert_bigint_to_time(created_secs: i64) -> Result<DateTime<Utc>, DBError> {
     let created_naive =
         NaiveDateTime::from_timestamp_millis(created_secs * 1000).ok_or_else(|| {
@@ -841,10 +1142,21 @@ fn convert_bigint_to_time(created_secs: i64) -> Result<DateTime<Utc>, DBError> {
     ))
 }
 
+fn deserialize_error_response(
+    pipeline_id: PipelineId,
+    error_str: &str,
+) -> Result<ErrorResponse, DBError> {
+    serde_json::from_str::<ErrorResponse>(error_str).map_err(|_| {
+        DBError::invalid_data(format!(
+            "Unexpected pipeline error format for pipeline '{pipeline_id}': {error_str}"
+        ))
+    })
+}
+
 // The goal for these methods is to avoid multiple DB interactions as much as
 // possible and if not, use transactions
 #[async_trait]
-impl Storage for ProjectDB {
+impl Storage for PostgresDB {
     async fn list_programs(
         &self,
         tenant_id: TenantId,
@@ -854,7 +1166,7 @@ impl Storage for ProjectDB {
         let stmt = manager
             .prepare_cached(
                 r#"SELECT id, name, description, version, status, error, schema,
-                CASE WHEN $2 IS TRUE THEN code ELSE null END
+                CASE WHEN $2 IS TRUE THEN code ELSE null END, warnings
                 FROM program WHERE tenant_id = $1"#,
             )
             .await?;
@@ -878,6 +1190,7 @@ impl Storage for ProjectDB {
                 version: Version(row.get(3)),
                 schema,
                 status,
+                warnings: warnings_from_column(row.get(8))?,
                 code: row.get(7),
             });
         }
@@ -913,9 +1226,9 @@ impl Storage for ProjectDB {
                 ],
             )
             .await
-            .map_err(ProjectDB::maybe_unique_violation)
+            .map_err(PostgresDB::maybe_unique_violation)
             .map_err(|e| {
-                ProjectDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
+                PostgresDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
             })?;
 
         Ok((ProgramId(id), Version(1)))
@@ -946,7 +1259,8 @@ impl Storage for ProjectDB {
                             code = $3,
                             status = (CASE WHEN code = $3 THEN status ELSE NULL END),
                             error = (CASE WHEN code = $3 THEN error ELSE NULL END),
-                            schema = (CASE WHEN code = $3 THEN schema ELSE NULL END)
+                            schema = (CASE WHEN code = $3 THEN schema ELSE NULL END),
+                            warnings = (CASE WHEN code = $3 THEN warnings ELSE NULL END)
                     WHERE id = $4 AND tenant_id = $5
                     RETURNING version
                 ",
@@ -965,7 +1279,7 @@ impl Storage for ProjectDB {
                         ],
                     )
                     .await
-                    .map_err(ProjectDB::maybe_unique_violation)?
+                    .map_err(PostgresDB::maybe_unique_violation)?
             }
             _ => {
                 let manager = self.pool.get().await?;
@@ -985,7 +1299,7 @@ impl Storage for ProjectDB {
                         ],
                     )
                     .await
-                    .map_err(ProjectDB::maybe_unique_violation)?
+                    .map_err(PostgresDB::maybe_unique_violation)?
             }
         };
 
@@ -1009,7 +1323,7 @@ impl Storage for ProjectDB {
         let stmt = manager
             .prepare_cached(
                 "SELECT name, description, version, status, error, schema,
-                CASE WHEN $3 IS TRUE THEN code ELSE null END
+                CASE WHEN $3 IS TRUE THEN code ELSE null END, warnings
                 FROM program WHERE id = $1 AND tenant_id = $2",
             )
             .await?;
@@ -1029,6 +1343,7 @@ impl Storage for ProjectDB {
                 .transpose()
                 .map_err(|e| DBError::invalid_data(format!("Error parsing program schema: {e}")))?;
             let code: Option<String> = row.get(6);
+            let warnings = warnings_from_column(row.get(7))?;
 
             let status = ProgramStatus::from_columns(status.as_deref(), error)?;
             Ok(Some(ProgramDescr {
@@ -1037,6 +1352,7 @@ impl Storage for ProjectDB {
                 description,
                 version,
                 status,
+                warnings,
                 schema,
                 code,
             }))
@@ -1056,7 +1372,7 @@ impl Storage for ProjectDB {
         let stmt = manager
             .prepare_cached(
                 "SELECT id, description, version, status, error, schema, tenant_id,
-                 CASE WHEN $3 IS TRUE THEN code ELSE null END
+                 CASE WHEN $3 IS TRUE THEN code ELSE null END, warnings
                  FROM program WHERE name = $1 AND tenant_id = $2",
             )
             .await?;
@@ -1076,6 +1392,7 @@ impl Storage for ProjectDB {
                 .transpose()
                 .map_err(|e| DBError::invalid_data(format!("Error parsing program schema: {e}")))?;
             let code: Option<String> = row.get(7);
+            let warnings = warnings_from_column(row.get(8))?;
 
             let status = ProgramStatus::from_columns(status.as_deref(), error)?;
             Ok(Some(ProgramDescr {
@@ -1084,6 +1401,7 @@ impl Storage for ProjectDB {
                 description,
                 version,
                 status,
+                warnings,
                 schema,
                 code,
             }))
@@ -1108,7 +1426,8 @@ impl Storage for ProjectDB {
                  error = (CASE WHEN version = $4 THEN $2 ELSE error END),
                  status_since = (CASE WHEN version = $4 THEN now()
                                  ELSE status_since END),
-                 schema = (CASE WHEN version = $4 THEN NULL ELSE schema END)
+                 schema = (CASE WHEN version = $4 THEN NULL ELSE schema END),
+                 warnings = (CASE WHEN version = $4 THEN NULL ELSE warnings END)
                  WHERE id = $3 AND tenant_id = $5",
             )
             .await?;
@@ -1193,6 +1512,28 @@ impl Storage for ProjectDB {
         Ok(())
     }
 
+    async fn set_program_warnings(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        warnings: Vec<SqlCompilerMessage>,
+    ) -> Result<(), DBError> {
+        let warnings = serde_json::to_string(&warnings).map_err(|e| {
+            DBError::invalid_data(format!(
+                "Error serializing program warnings '{warnings:?}'.\nError: {e}"
+            ))
+        })?;
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("UPDATE program SET warnings = $1 WHERE id = $2 AND tenant_id = $3")
+            .await?;
+        manager
+            .execute(&stmt, &[&warnings, &program_id.0, &tenant_id.0])
+            .await?;
+
+        Ok(())
+    }
+
     async fn delete_program(
         &self,
         tenant_id: TenantId,
@@ -1206,7 +1547,7 @@ impl Storage for ProjectDB {
             .execute(&stmt, &[&program_id.0, &tenant_id.0])
             .await
             .map_err(|e| {
-                ProjectDB::maybe_program_id_in_use_foreign_key_constraint_err(
+                PostgresDB::maybe_program_id_in_use_foreign_key_constraint_err(
                     e.into(),
                     Some(program_id),
                 )
@@ -1222,7 +1563,8 @@ impl Storage for ProjectDB {
         let manager = self.pool.get().await?;
         let stmt = manager
             .prepare_cached(
-                r#"SELECT id, name, description, version, status, error, schema, tenant_id
+                r#"SELECT id, name, description, version, status, error, schema, tenant_id,
+                   warnings
                    FROM program"#,
             )
             .await?;
@@ -1249,6 +1591,7 @@ impl Storage for ProjectDB {
                     version: Version(row.get(3)),
                     schema,
                     status,
+                    warnings: warnings_from_column(row.get(8))?,
                     code: None,
                 },
             ));
@@ -1295,6 +1638,63 @@ impl Storage for ProjectDB {
         }
     }
 
+    async fn count_pending_programs(&self) -> Result<u64, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("SELECT count(*) FROM program WHERE status = 'pending'")
+            .await?;
+        let row = manager.query_one(&stmt, &[]).await?;
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    async fn list_compilation_jobs(&self) -> Result<Vec<CompilationJob>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT tenant_id, id, version, status, extract(epoch from status_since)::bigint
+                FROM program
+                WHERE status IN ('pending', 'compiling_sql', 'compiling_rust')
+                ORDER BY status_since",
+            )
+            .await?;
+        let rows = manager.query(&stmt, &[]).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let status: Option<String> = row.get(3);
+            let status = ProgramStatus::from_columns(status.as_deref(), None)?;
+            result.push(CompilationJob {
+                tenant_id: TenantId(row.get(0)),
+                program_id: ProgramId(row.get(1)),
+                version: Version(row.get(2)),
+                status,
+                status_since: convert_bigint_to_time(row.get(4))?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn count_pipelines_by_status(&self) -> Result<Vec<(PipelineStatus, u64)>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT rt.current_status, count(*)
+                FROM pipeline_runtime_state rt
+                JOIN pipeline p ON p.id = rt.id
+                WHERE p.deleted_at IS NULL
+                GROUP BY rt.current_status",
+            )
+            .await?;
+        let rows = manager.query(&stmt, &[]).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let status = PipelineStatus::try_from(row.get::<_, String>(0))?;
+            result.push((status, row.get::<_, i64>(1) as u64));
+        }
+        Ok(result)
+    }
+
     /// Version the current pipeline object and all state reachable from it.
     ///
     /// We store the last revision number in the pipeline object itself.
@@ -1328,7 +1728,7 @@ impl Storage for ProjectDB {
                     SELECT prog.code, c.config, ac.name, ac.config, ac.is_input, p.config
                                         FROM pipeline p
                                         INNER JOIN program prog ON p.program_id = prog.id
-                                        LEFT OUTER JOIN attached_connector ac ON ac.pipeline_id = p.id
+                                        LEFT OUTER JOIN live_attached_connector ac ON ac.pipeline_id = p.id
                                         LEFT OUTER JOIN connector c ON ac.connector_id = c.id
                                         WHERE p.id = $1
                 ),
@@ -1353,12 +1753,12 @@ impl Storage for ProjectDB {
             .await?;
         let insert_connector_history = txn
             .prepare_cached(
-                "INSERT INTO connector_history SELECT $1 as revision, c.* FROM connector c, attached_connector ac WHERE ac.pipeline_id = $2 AND ac.connector_id = c.id",
+                "INSERT INTO connector_history SELECT $1 as revision, c.* FROM connector c, attached_connector ac WHERE ac.pipeline_id = $2 AND ac.connector_id = c.id AND c.deleted_at IS NULL",
             )
             .await?;
         let insert_attached_connector_history = txn
             .prepare_cached(
-                "INSERT INTO attached_connector_history SELECT $1 as revision, * FROM attached_connector ac WHERE ac.pipeline_id = $2",
+                "INSERT INTO attached_connector_history SELECT $1 as revision, * FROM live_attached_connector ac WHERE ac.pipeline_id = $2",
             )
             .await?;
         let update_revision = txn
@@ -1476,11 +1876,12 @@ impl Storage for ProjectDB {
                                                 'is_input', is_input))
                             FILTER (WHERE ac.name IS NOT NULL),
                     '[]'),
-            rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created
+            rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created,
+            rt.tls_certificate, rt.auth_token
             FROM pipeline p
             INNER JOIN pipeline_runtime_state rt on p.id = rt.id
-            LEFT JOIN attached_connector ac on p.id = ac.pipeline_id
-            WHERE p.tenant_id = $1
+            LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+            WHERE p.tenant_id = $1 AND p.deleted_at IS NULL
             GROUP BY p.id, rt.id;",
             )
             .await?;
@@ -1512,11 +1913,12 @@ impl Storage for ProjectDB {
                                                     'is_input', is_input))
                                 FILTER (WHERE ac.name IS NOT NULL),
                         '[]'),
-                rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created
+                rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created,
+                rt.tls_certificate, rt.auth_token
                 FROM pipeline p
                 INNER JOIN pipeline_runtime_state rt on p.id = rt.id
-                LEFT JOIN attached_connector ac on p.id = ac.pipeline_id
-                WHERE p.id = $1 AND p.tenant_id = $2
+                LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+                WHERE p.id = $1 AND p.tenant_id = $2 AND p.deleted_at IS NULL
                 GROUP BY p.id, rt.id
                 ",
             )
@@ -1546,8 +1948,8 @@ impl Storage for ProjectDB {
                                 FILTER (WHERE ac.name IS NOT NULL),
                         '[]')
                 FROM pipeline p
-                LEFT JOIN attached_connector ac on p.id = ac.pipeline_id
-                WHERE p.id = $1 AND p.tenant_id = $2
+                LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+                WHERE p.id = $1 AND p.tenant_id = $2 AND p.deleted_at IS NULL
                 GROUP BY p.id
                 ",
             )
@@ -1569,7 +1971,8 @@ impl Storage for ProjectDB {
         let manager = self.pool.get().await?;
         let stmt = manager
             .prepare_cached(
-                "SELECT location, desired_status, current_status, status_since, error, created
+                "SELECT location, desired_status, current_status, status_since, error, created,
+                    tls_certificate, auth_token
                 FROM pipeline_runtime_state
                 WHERE id = $1 AND tenant_id = $2",
             )
@@ -1598,8 +2001,8 @@ impl Storage for ProjectDB {
                                 FILTER (WHERE ac.name IS NOT NULL),
                         '[]')
                 FROM pipeline p
-                LEFT JOIN attached_connector ac on p.id = ac.pipeline_id
-                WHERE p.name = $1 AND p.tenant_id = $2
+                LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+                WHERE p.name = $1 AND p.tenant_id = $2 AND p.deleted_at IS NULL
                 GROUP BY p.id
                 ",
             )
@@ -1628,11 +2031,12 @@ impl Storage for ProjectDB {
                                                     'is_input', is_input))
                                 FILTER (WHERE ac.name IS NOT NULL),
                         '[]'),
-                rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created
+                rt.location, rt.desired_status, rt.current_status, rt.status_since, rt.error, rt.created,
+                rt.tls_certificate, rt.auth_token
                 FROM pipeline p
                 INNER JOIN pipeline_runtime_state rt on p.id = rt.id
-                LEFT JOIN attached_connector ac on p.id = ac.pipeline_id
-                WHERE p.name = $1 AND p.tenant_id = $2
+                LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+                WHERE p.name = $1 AND p.tenant_id = $2 AND p.deleted_at IS NULL
                 GROUP BY p.id, rt.id
                 ",
             )
@@ -1681,16 +2085,16 @@ impl Storage for ProjectDB {
             ],
         )
         .await
-        .map_err(ProjectDB::maybe_unique_violation)
+        .map_err(PostgresDB::maybe_unique_violation)
         .map_err(|e| {
-            ProjectDB::maybe_tenant_id_foreign_key_constraint_err(
+            PostgresDB::maybe_tenant_id_foreign_key_constraint_err(
                 e,
                 tenant_id,
                 program_id.map(|e| e.0),
             )
         })
         .map_err(|e| {
-            ProjectDB::maybe_program_id_not_found_foreign_key_constraint_err(e, program_id)
+            PostgresDB::maybe_program_id_not_found_foreign_key_constraint_err(e, program_id)
         })?;
 
         txn.execute(&new_runtime_state, &[&id, &tenant_id.0])
@@ -1735,7 +2139,9 @@ impl Storage for ProjectDB {
         let mut client = self.pool.get().await?;
         let txn = client.transaction().await?;
         let find_pipeline_id = txn
-            .prepare_cached("SELECT id FROM pipeline WHERE id = $1 AND tenant_id = $2")
+            .prepare_cached(
+                "SELECT id FROM pipeline WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
+            )
             .await?;
         let delete_ac = txn
             .prepare_cached(
@@ -1781,9 +2187,9 @@ impl Storage for ProjectDB {
                 ],
             )
             .await
-            .map_err(ProjectDB::maybe_unique_violation)
+            .map_err(PostgresDB::maybe_unique_violation)
             .map_err(|e| {
-                ProjectDB::maybe_program_id_not_found_foreign_key_constraint_err(e, program_id)
+                PostgresDB::maybe_program_id_not_found_foreign_key_constraint_err(e, program_id)
             })?;
         txn.commit().await?;
         match row {
@@ -1812,7 +2218,9 @@ impl Storage for ProjectDB {
                     current_status = $4,
                     status_since = $5,
                     created = $6,
-                    error = $7
+                    error = $7,
+                    tls_certificate = $8,
+                    auth_token = $9
                 WHERE id = $1 AND tenant_id = $2
                 ",
             )
@@ -1832,6 +2240,8 @@ impl Storage for ProjectDB {
                         .error
                         .as_ref()
                         .map(|e| serde_json::to_string(&e).unwrap()),
+                    &state.tls_certificate,
+                    &state.auth_token,
                 ],
             )
             .await?;
@@ -1898,7 +2308,7 @@ impl Storage for ProjectDB {
     ) -> Result<bool, DBError> {
         let manager = self.pool.get().await?;
         let stmt = manager
-            .prepare_cached("SELECT is_input FROM attached_connector WHERE name = $1 AND pipeline_id = $2 AND tenant_id = $3")
+            .prepare_cached("SELECT is_input FROM live_attached_connector WHERE name = $1 AND pipeline_id = $2 AND tenant_id = $3")
             .await?;
         let row = manager
             .query_opt(&stmt, &[&name, &pipeline_id.0, &tenant_id.0])
@@ -1920,7 +2330,10 @@ impl Storage for ProjectDB {
     ) -> Result<bool, DBError> {
         let manager = self.pool.get().await?;
         let stmt = manager
-            .prepare_cached("DELETE FROM pipeline WHERE id = $1 AND tenant_id = $2")
+            .prepare_cached(
+                "UPDATE pipeline SET deleted_at = extract(epoch from now())
+                WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
+            )
             .await?;
         let res = manager
             .execute(&stmt, &[&pipeline_id.0, &tenant_id.0])
@@ -1928,6 +2341,63 @@ impl Storage for ProjectDB {
         Ok(res > 0)
     }
 
+    async fn restore_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "UPDATE pipeline SET deleted_at = NULL
+                WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NOT NULL",
+            )
+            .await?;
+        let res = manager
+            .execute(&stmt, &[&pipeline_id.0, &tenant_id.0])
+            .await
+            .map_err(PostgresDB::maybe_unique_violation)?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownPipeline { pipeline_id })
+        }
+    }
+
+    async fn list_deleted_pipelines(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedPipeline>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT p.id, version, p.name, description, p.config, program_id,
+            COALESCE(json_agg(json_build_object('name', ac.name,
+                                                'connector_id', connector_id,
+                                                'config', ac.config,
+                                                'is_input', is_input))
+                            FILTER (WHERE ac.name IS NOT NULL),
+                    '[]'),
+            p.deleted_at
+            FROM pipeline p
+            LEFT JOIN live_attached_connector ac on p.id = ac.pipeline_id
+            WHERE p.tenant_id = $1 AND p.deleted_at IS NOT NULL
+            GROUP BY p.id;",
+            )
+            .await?;
+
+        let rows: Vec<Row> = manager.query(&stmt, &[&tenant_id.0]).await?;
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(DeletedPipeline {
+                descriptor: self.row_to_pipeline_descr(&row).await?,
+                deleted_at: convert_bigint_to_time(row.get(7))?,
+            });
+        }
+
+        Ok(result)
+    }
+
     async fn new_connector(
         &self,
         tenant_id: TenantId,
@@ -1947,9 +2417,9 @@ impl Storage for ProjectDB {
                 &[&id, &name, &description, &config.to_yaml(), &tenant_id.0],
             )
             .await
-            .map_err(ProjectDB::maybe_unique_violation)
+            .map_err(PostgresDB::maybe_unique_violation)
             .map_err(|e| {
-                ProjectDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
+                PostgresDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
             })?;
         Ok(ConnectorId(id))
     }
@@ -1958,7 +2428,7 @@ impl Storage for ProjectDB {
         let manager = self.pool.get().await?;
         let stmt = manager
             .prepare_cached(
-                "SELECT id, name, description, config FROM connector WHERE tenant_id = $1",
+                "SELECT id, name, description, config FROM connector WHERE tenant_id = $1 AND deleted_at IS NULL",
             )
             .await?;
         let rows = manager.query(&stmt, &[&tenant_id.0]).await?;
@@ -1985,7 +2455,7 @@ impl Storage for ProjectDB {
         let manager = self.pool.get().await?;
         let stmt = manager
             .prepare_cached(
-                "SELECT id, description, config FROM connector WHERE name = $1 AND tenant_id = $2",
+                "SELECT id, description, config FROM connector WHERE name = $1 AND tenant_id = $2 AND deleted_at IS NULL",
             )
             .await?;
         let row = manager.query_opt(&stmt, &[&name, &tenant_id.0]).await?;
@@ -2014,7 +2484,7 @@ impl Storage for ProjectDB {
         let manager = self.pool.get().await?;
         let stmt = manager
             .prepare_cached(
-                "SELECT name, description, config FROM connector WHERE id = $1 AND tenant_id = $2",
+                "SELECT name, description, config FROM connector WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
             )
             .await?;
 
@@ -2077,14 +2547,19 @@ impl Storage for ProjectDB {
         tenant_id: TenantId,
         connector_id: ConnectorId,
     ) -> Result<(), DBError> {
+        // Attachments of the connector are kept, but hidden from pipelines
+        // by the `live_attached_connector` view until the connector is
+        // restored.
         let manager = self.pool.get().await?;
         let stmt = manager
-            .prepare_cached("DELETE FROM connector WHERE id = $1 AND tenant_id = $2")
+            .prepare_cached(
+                "UPDATE connector SET deleted_at = extract(epoch from now())
+                WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
+            )
             .await?;
         let res = manager
             .execute(&stmt, &[&connector_id.0, &tenant_id.0])
             .await?;
-
         if res > 0 {
             Ok(())
         } else {
@@ -2092,61 +2567,129 @@ impl Storage for ProjectDB {
         }
     }
 
-    async fn store_api_key_hash(
+    async fn restore_connector(
         &self,
         tenant_id: TenantId,
-        key: String,
-        scopes: Vec<ApiPermission>,
+        connector_id: ConnectorId,
     ) -> Result<(), DBError> {
-        let mut hasher = sha::Sha256::new();
-        hasher.update(key.as_bytes());
-        let hash = openssl::base64::encode_block(&hasher.finish());
         let manager = self.pool.get().await?;
         let stmt = manager
-            .prepare_cached("INSERT INTO api_key (hash, tenant_id, scopes) VALUES ($1, $2, $3)")
+            .prepare_cached(
+                "UPDATE connector SET deleted_at = NULL
+                WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NOT NULL",
+            )
             .await?;
         let res = manager
-            .execute(
-                &stmt,
-                &[
-                    &hash,
-                    &tenant_id.0,
-                    &scopes
-                        .iter()
-                        .map(|scope| match scope {
-                            ApiPermission::Read => "read",
-                            ApiPermission::Write => "write",
-                        })
-                        .collect::<Vec<&str>>(),
-                ],
-            )
+            .execute(&stmt, &[&connector_id.0, &tenant_id.0])
             .await
-            .map_err(Self::maybe_unique_violation)
-            .map_err(|e| {
-                ProjectDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
-            })?;
+            .map_err(PostgresDB::maybe_unique_violation)?;
         if res > 0 {
             Ok(())
         } else {
-            Err(DBError::duplicate_key())
+            Err(DBError::UnknownConnector { connector_id })
         }
     }
 
-    async fn validate_api_key(
+    async fn list_deleted_connectors(
         &self,
-        api_key: String,
-    ) -> Result<(TenantId, Vec<ApiPermission>), DBError> {
-        let mut hasher = sha::Sha256::new();
-        hasher.update(api_key.as_bytes());
-        let hash = openssl::base64::encode_block(&hasher.finish());
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedConnector>, DBError> {
         let manager = self.pool.get().await?;
         let stmt = manager
-            .prepare_cached("SELECT tenant_id, scopes FROM api_key WHERE hash = $1")
+            .prepare_cached(
+                "SELECT id, name, description, config, deleted_at FROM connector WHERE tenant_id = $1 AND deleted_at IS NOT NULL",
+            )
             .await?;
-        let res = manager
-            .query_one(&stmt, &[&hash])
-            .await
-            .map_err(|_| DBError::InvalidKey)?;
+        let rows = manager.query(&stmt, &[&tenant_id.0]).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(DeletedConnector {
+                descriptor: ConnectorDescr {
+                    connector_id: ConnectorId(row.get(0)),
+                    name: row.get(1),
+                    description: row.get(2),
+                    config: ConnectorConfig::from_yaml_str(row.get(3)),
+                },
+                deleted_at: convert_bigint_to_time(row.get(4))?,
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64, DBError> {
+        let mut client = self.pool.get().await?;
+        let txn = client.transaction().await?;
+        let purge_pipelines = txn
+            .prepare_cached("DELETE FROM pipeline WHERE deleted_at < $1")
+            .await?;
+        let purge_connectors = txn
+            .prepare_cached("DELETE FROM connector WHERE deleted_at < $1")
+            .await?;
+        let deleted_before = deleted_before.timestamp();
+        let pipelines = txn.execute(&purge_pipelines, &[&deleted_before]).await?;
+        let connectors = txn.execute(&purge_connectors, &[&deleted_before]).await?;
+        txn.commit().await?;
+
+        Ok(pipelines + connectors)
+    }
+
+    async fn store_api_key_hash(
+        &self,
+        tenant_id: TenantId,
+        key: String,
+        scopes: Vec<ApiPermission>,
+    ) -> Result<(), DBError> {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(key.as_bytes());
+        let hash = openssl::base64::encode_block(&hasher.finish());
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("INSERT INTO api_key (hash, tenant_id, scopes) VALUES ($1, $2, $3)")
+            .await?;
+        let res = manager
+            .execute(
+                &stmt,
+                &[
+                    &hash,
+                    &tenant_id.0,
+                    &scopes
+                        .iter()
+                        .map(|scope| match scope {
+                            ApiPermission::Read => "read",
+                            ApiPermission::Write => "write",
+                        })
+                        .collect::<Vec<&str>>(),
+                ],
+            )
+            .await
+            .map_err(Self::maybe_unique_violation)
+            .map_err(|e| {
+                PostgresDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
+            })?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::duplicate_key())
+        }
+    }
+
+    async fn validate_api_key(
+        &self,
+        api_key: String,
+    ) -> Result<(TenantId, Vec<ApiPermission>), DBError> {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(api_key.as_bytes());
+        let hash = openssl::base64::encode_block(&hasher.finish());
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("SELECT tenant_id, scopes FROM api_key WHERE hash = $1")
+            .await?;
+        let res = manager
+            .query_one(&stmt, &[&hash])
+            .await
+            .map_err(|_| DBError::InvalidKey)?;
         let tenant_id = TenantId(res.get(0));
         let vec: Vec<String> = res.get(1);
         let vec = vec
@@ -2167,19 +2710,805 @@ impl Storage for ProjectDB {
         tenant_name: String,
         provider: String,
     ) -> Result<TenantId, DBError> {
-        let manager = self.pool.get().await?;
-        let stmt = manager
-            .prepare_cached("SELECT id FROM tenant WHERE tenant = $1 AND provider = $2")
-            .await?;
-
-        let res = manager.query_opt(&stmt, &[&tenant_name, &provider]).await?;
-        match res {
-            Some(row) => Ok(TenantId(row.get(0))),
-            None => {
-                self.create_tenant_if_not_exists(Uuid::now_v7(), tenant_name, provider)
-                    .await
-            }
-        }
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("SELECT id FROM tenant WHERE tenant = $1 AND provider = $2")
+            .await?;
+
+        let res = manager.query_opt(&stmt, &[&tenant_name, &provider]).await?;
+        match res {
+            Some(row) => Ok(TenantId(row.get(0))),
+            None => {
+                self.create_tenant_if_not_exists(Uuid::now_v7(), tenant_name, provider)
+                    .await
+            }
+        }
+    }
+
+    async fn create_tenant_if_not_exists(
+        &self,
+        tenant_id: Uuid,
+        tenant_name: String,
+        provider: String,
+    ) -> Result<TenantId, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("INSERT INTO tenant (id, tenant, provider) VALUES ($1, $2, $3) ON CONFLICT (tenant, provider) DO UPDATE SET tenant = excluded.tenant, provider = excluded.provider RETURNING id")
+            .await?;
+
+        // Unfortunately, doing a read-if-exists-else-insert is not very ergonomic. To
+        // do so in a single query requires us to do a redundant UPDATE on conflict,
+        // where we set the tenant and provider values to their existing values
+        // (excluded.{tenant, provider})
+        let res = manager
+            .query_one(&stmt, &[&tenant_id, &tenant_name, &provider])
+            .await?;
+        Ok(TenantId(res.get(0)))
+    }
+
+    async fn create_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+        url: String,
+    ) -> Result<(), DBError> {
+        let conn = self.pool.get().await?;
+        let stmt = conn
+            .prepare_cached("INSERT INTO compiled_binary VALUES ($1, $2, $3)")
+            .await?;
+
+        let _res = conn
+            .execute(&stmt, &[&program_id.0, &version.0, &url])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<Option<String>, DBError> {
+        let conn = self.pool.get().await?;
+        let stmt = conn
+            .prepare_cached(
+                "SELECT url FROM compiled_binary WHERE program_id = $1 AND version = $2",
+            )
+            .await?;
+        let res = conn.query_opt(&stmt, &[&program_id.0, &version.0]).await?;
+        Ok(res.map(|e| e.get(0)))
+    }
+
+    async fn delete_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<(), DBError> {
+        let conn = self.pool.get().await?;
+        let stmt = conn
+            .prepare_cached("DELETE FROM compiled_binary WHERE program_id = $1 AND version = $2")
+            .await?;
+        let _res = conn.execute(&stmt, &[&program_id.0, &version.0]).await?;
+        Ok(())
+    }
+
+    async fn record_tenant_usage(
+        &self,
+        tenant_id: TenantId,
+        usage: &TenantUsage,
+    ) -> Result<(), DBError> {
+        let conn = self.pool.get().await?;
+        let stmt = conn
+            .prepare_cached(
+                "INSERT INTO tenant_usage (tenant_id, compile_time_ms, run_time_ms, ingress_bytes, egress_bytes)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (tenant_id) DO UPDATE SET
+                    compile_time_ms = tenant_usage.compile_time_ms + EXCLUDED.compile_time_ms,
+                    run_time_ms = tenant_usage.run_time_ms + EXCLUDED.run_time_ms,
+                    ingress_bytes = tenant_usage.ingress_bytes + EXCLUDED.ingress_bytes,
+                    egress_bytes = tenant_usage.egress_bytes + EXCLUDED.egress_bytes",
+            )
+            .await?;
+        conn.execute(
+            &stmt,
+            &[
+                &tenant_id.0,
+                &usage.compile_time_ms,
+                &usage.run_time_ms,
+                &usage.ingress_bytes,
+                &usage.egress_bytes,
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: TenantId) -> Result<TenantUsage, DBError> {
+        let conn = self.pool.get().await?;
+        let stmt = conn
+            .prepare_cached(
+                "SELECT compile_time_ms, run_time_ms, ingress_bytes, egress_bytes
+                FROM tenant_usage WHERE tenant_id = $1",
+            )
+            .await?;
+        let row = conn.query_opt(&stmt, &[&tenant_id.0]).await?;
+        Ok(row
+            .map(|row| TenantUsage {
+                compile_time_ms: row.get(0),
+                run_time_ms: row.get(1),
+                ingress_bytes: row.get(2),
+                egress_bytes: row.get(3),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn new_webhook(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        retry_policy: &WebhookRetryPolicy,
+    ) -> Result<WebhookId, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "INSERT INTO webhook (id, tenant_id, url, secret, events, max_attempts, initial_retry_delay_ms) VALUES($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .await?;
+        let events: Vec<&str> = events.iter().map(WebhookEvent::as_str).collect();
+        let max_attempts = i32::try_from(retry_policy.max_attempts).map_err(|_| {
+            DBError::invalid_data(format!(
+                "webhook 'max_attempts' out of range: {}",
+                retry_policy.max_attempts
+            ))
+        })?;
+        let initial_delay_ms = i64::try_from(retry_policy.initial_delay_ms).map_err(|_| {
+            DBError::invalid_data(format!(
+                "webhook 'initial_delay_ms' out of range: {}",
+                retry_policy.initial_delay_ms
+            ))
+        })?;
+        manager
+            .execute(
+                &stmt,
+                &[
+                    &id,
+                    &tenant_id.0,
+                    &url,
+                    &secret,
+                    &events,
+                    &max_attempts,
+                    &initial_delay_ms,
+                ],
+            )
+            .await
+            .map_err(PostgresDB::maybe_unique_violation)
+            .map_err(|e| {
+                PostgresDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
+            })?;
+        Ok(WebhookId(id))
+    }
+
+    async fn list_webhooks(&self, tenant_id: TenantId) -> Result<Vec<WebhookDescr>, DBError> {
+        Ok(self
+            .list_webhook_subscriptions(tenant_id)
+            .await?
+            .into_iter()
+            .map(|s| s.descriptor)
+            .collect())
+    }
+
+    async fn delete_webhook(
+        &self,
+        tenant_id: TenantId,
+        webhook_id: WebhookId,
+    ) -> Result<(), DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("DELETE FROM webhook WHERE id = $1 AND tenant_id = $2")
+            .await?;
+        let res = manager
+            .execute(&stmt, &[&webhook_id.0, &tenant_id.0])
+            .await?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownWebhook { webhook_id })
+        }
+    }
+
+    async fn list_webhook_subscriptions(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<WebhookSubscription>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT id, url, secret, events, max_attempts, initial_retry_delay_ms FROM webhook WHERE tenant_id = $1 ORDER BY id",
+            )
+            .await?;
+        let rows = manager.query(&stmt, &[&tenant_id.0]).await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let events: Vec<String> = row.get(3);
+            let events = events
+                .iter()
+                .map(|e| WebhookEvent::try_from(e.as_str()))
+                .collect::<Result<Vec<_>, _>>()?;
+            result.push(WebhookSubscription {
+                descriptor: WebhookDescr {
+                    webhook_id: WebhookId(row.get(0)),
+                    url: row.get(1),
+                    events,
+                    retry_policy: WebhookRetryPolicy {
+                        max_attempts: u32::try_from(row.get::<_, i32>(4)).map_err(|_| {
+                            DBError::invalid_data("negative webhook 'max_attempts'".to_string())
+                        })?,
+                        initial_delay_ms: u64::try_from(row.get::<_, i64>(5)).map_err(|_| {
+                            DBError::invalid_data("negative webhook 'initial_delay_ms'".to_string())
+                        })?,
+                    },
+                },
+                secret: row.get(2),
+            });
+        }
+        Ok(result)
+    }
+
+    async fn new_deployment(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        pipelines: &[PipelineId],
+        config: &Option<RuntimeConfig>,
+    ) -> Result<DeploymentId, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "INSERT INTO deployment (id, tenant_id, name, description, pipelines, config)
+                VALUES($1, $2, $3, $4, $5, $6)",
+            )
+            .await?;
+        let pipelines: Vec<Uuid> = pipelines.iter().map(|p| p.0).collect();
+        let config = config.as_ref().map(RuntimeConfig::to_yaml);
+        manager
+            .execute(
+                &stmt,
+                &[&id, &tenant_id.0, &name, &description, &pipelines, &config],
+            )
+            .await
+            .map_err(PostgresDB::maybe_unique_violation)
+            .map_err(|e| {
+                PostgresDB::maybe_tenant_id_foreign_key_constraint_err(e, tenant_id, None)
+            })?;
+        Ok(DeploymentId(id))
+    }
+
+    async fn list_deployments(&self, tenant_id: TenantId) -> Result<Vec<DeploymentDescr>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT id, name, description, pipelines, config FROM deployment
+                WHERE tenant_id = $1 ORDER BY id",
+            )
+            .await?;
+        let rows = manager.query(&stmt, &[&tenant_id.0]).await?;
+        Ok(rows.iter().map(Self::row_to_deployment_descr).collect())
+    }
+
+    async fn get_deployment_by_id(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentDescr, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "SELECT id, name, description, pipelines, config FROM deployment
+                WHERE id = $1 AND tenant_id = $2",
+            )
+            .await?;
+        let row = manager
+            .query_opt(&stmt, &[&deployment_id.0, &tenant_id.0])
+            .await?;
+        row.map(|row| Self::row_to_deployment_descr(&row))
+            .ok_or(DBError::UnknownDeployment { deployment_id })
+    }
+
+    async fn delete_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("DELETE FROM deployment WHERE id = $1 AND tenant_id = $2")
+            .await?;
+        let res = manager
+            .execute(&stmt, &[&deployment_id.0, &tenant_id.0])
+            .await?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownDeployment { deployment_id })
+        }
+    }
+
+    async fn check_connection(&self) -> Result<(), DBError> {
+        let manager = self.pool.get().await?;
+        manager.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+
+    async fn record_heartbeat(&self, service: &str, now: DateTime<Utc>) -> Result<(), DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached(
+                "INSERT INTO service_heartbeat (service, last_seen) VALUES ($1, $2)
+                ON CONFLICT (service) DO UPDATE SET last_seen = EXCLUDED.last_seen",
+            )
+            .await?;
+        manager
+            .execute(&stmt, &[&service, &now.timestamp()])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_heartbeat(&self, service: &str) -> Result<Option<DateTime<Utc>>, DBError> {
+        let manager = self.pool.get().await?;
+        let stmt = manager
+            .prepare_cached("SELECT last_seen FROM service_heartbeat WHERE service = $1")
+            .await?;
+        match manager.query_opt(&stmt, &[&service]).await? {
+            Some(row) => Ok(Some(convert_bigint_to_time(row.get(0))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Forward a method call to the database backend.
+macro_rules! dispatch {
+    ($self:ident, $method:ident($($arg:expr),*)) => {
+        match $self {
+            ProjectDB::Postgres(db) => db.$method($($arg),*).await,
+            #[cfg(feature = "lite")]
+            ProjectDB::Sqlite(db) => db.$method($($arg),*).await,
+        }
+    };
+}
+
+#[async_trait]
+impl Storage for ProjectDB {
+    async fn list_programs(
+        &self,
+        tenant_id: TenantId,
+        with_code: bool,
+    ) -> Result<Vec<ProgramDescr>, DBError> {
+        dispatch!(self, list_programs(tenant_id, with_code))
+    }
+
+    async fn new_program(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        program_name: &str,
+        program_description: &str,
+        program_code: &str,
+    ) -> Result<(ProgramId, Version), DBError> {
+        dispatch!(
+            self,
+            new_program(
+                tenant_id,
+                id,
+                program_name,
+                program_description,
+                program_code
+            )
+        )
+    }
+
+    async fn update_program(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        program_name: &str,
+        program_description: &str,
+        program_code: &Option<String>,
+    ) -> Result<Version, DBError> {
+        dispatch!(
+            self,
+            update_program(
+                tenant_id,
+                program_id,
+                program_name,
+                program_description,
+                program_code
+            )
+        )
+    }
+
+    async fn get_program_if_exists(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        with_code: bool,
+    ) -> Result<Option<ProgramDescr>, DBError> {
+        dispatch!(
+            self,
+            get_program_if_exists(tenant_id, program_id, with_code)
+        )
+    }
+
+    async fn lookup_program(
+        &self,
+        tenant_id: TenantId,
+        program_name: &str,
+        with_code: bool,
+    ) -> Result<Option<ProgramDescr>, DBError> {
+        dispatch!(self, lookup_program(tenant_id, program_name, with_code))
+    }
+
+    async fn set_program_for_compilation(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        version: Version,
+        status: ProgramStatus,
+    ) -> Result<(), DBError> {
+        dispatch!(
+            self,
+            set_program_for_compilation(tenant_id, program_id, version, status)
+        )
+    }
+
+    async fn set_program_status_guarded(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        expected_version: Version,
+        status: ProgramStatus,
+    ) -> Result<(), DBError> {
+        dispatch!(
+            self,
+            set_program_status_guarded(tenant_id, program_id, expected_version, status)
+        )
+    }
+
+    async fn set_program_schema(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        schema: ProgramSchema,
+    ) -> Result<(), DBError> {
+        dispatch!(self, set_program_schema(tenant_id, program_id, schema))
+    }
+
+    async fn set_program_warnings(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        warnings: Vec<SqlCompilerMessage>,
+    ) -> Result<(), DBError> {
+        dispatch!(self, set_program_warnings(tenant_id, program_id, warnings))
+    }
+
+    async fn delete_program(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, delete_program(tenant_id, program_id))
+    }
+
+    async fn all_programs(&self) -> Result<Vec<(TenantId, ProgramDescr)>, DBError> {
+        dispatch!(self, all_programs())
+    }
+
+    async fn all_pipelines(&self) -> Result<Vec<(TenantId, PipelineId)>, DBError> {
+        dispatch!(self, all_pipelines())
+    }
+
+    async fn next_job(&self) -> Result<Option<(TenantId, ProgramId, Version)>, DBError> {
+        dispatch!(self, next_job())
+    }
+
+    async fn count_pending_programs(&self) -> Result<u64, DBError> {
+        dispatch!(self, count_pending_programs())
+    }
+
+    async fn list_compilation_jobs(&self) -> Result<Vec<CompilationJob>, DBError> {
+        dispatch!(self, list_compilation_jobs())
+    }
+
+    async fn count_pipelines_by_status(&self) -> Result<Vec<(PipelineStatus, u64)>, DBError> {
+        dispatch!(self, count_pipelines_by_status())
+    }
+
+    async fn create_pipeline_revision(
+        &self,
+        new_revision_id: Uuid,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<Revision, DBError> {
+        dispatch!(
+            self,
+            create_pipeline_revision(new_revision_id, tenant_id, pipeline_id)
+        )
+    }
+
+    async fn get_last_committed_pipeline_revision(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineRevision, DBError> {
+        dispatch!(
+            self,
+            get_last_committed_pipeline_revision(tenant_id, pipeline_id)
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn new_pipeline(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        program_id: Option<ProgramId>,
+        pipline_name: &str,
+        pipeline_description: &str,
+        config: &RuntimeConfig,
+        connectors: &Option<Vec<AttachedConnector>>,
+    ) -> Result<(PipelineId, Version), DBError> {
+        dispatch!(
+            self,
+            new_pipeline(
+                tenant_id,
+                id,
+                program_id,
+                pipline_name,
+                pipeline_description,
+                config,
+                connectors
+            )
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn update_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        program_id: Option<ProgramId>,
+        pipline_name: &str,
+        pipeline_description: &str,
+        config: &Option<RuntimeConfig>,
+        connectors: &Option<Vec<AttachedConnector>>,
+    ) -> Result<Version, DBError> {
+        dispatch!(
+            self,
+            update_pipeline(
+                tenant_id,
+                pipeline_id,
+                program_id,
+                pipline_name,
+                pipeline_description,
+                config,
+                connectors
+            )
+        )
+    }
+
+    async fn delete_config(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, delete_config(tenant_id, pipeline_id))
+    }
+
+    async fn attached_connector_is_input(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        name: &str,
+    ) -> Result<bool, DBError> {
+        dispatch!(
+            self,
+            attached_connector_is_input(tenant_id, pipeline_id, name)
+        )
+    }
+
+    async fn delete_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<bool, DBError> {
+        dispatch!(self, delete_pipeline(tenant_id, pipeline_id))
+    }
+
+    async fn restore_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, restore_pipeline(tenant_id, pipeline_id))
+    }
+
+    async fn list_deleted_pipelines(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedPipeline>, DBError> {
+        dispatch!(self, list_deleted_pipelines(tenant_id))
+    }
+
+    async fn get_pipeline_descr_by_id(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineDescr, DBError> {
+        dispatch!(self, get_pipeline_descr_by_id(tenant_id, pipeline_id))
+    }
+
+    async fn get_pipeline_by_id(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<Pipeline, DBError> {
+        dispatch!(self, get_pipeline_by_id(tenant_id, pipeline_id))
+    }
+
+    async fn get_pipeline_descr_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<PipelineDescr, DBError> {
+        dispatch!(self, get_pipeline_descr_by_name(tenant_id, name))
+    }
+
+    async fn get_pipeline_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<Pipeline, DBError> {
+        dispatch!(self, get_pipeline_by_name(tenant_id, name))
+    }
+
+    async fn get_pipeline_runtime_state(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineRuntimeState, DBError> {
+        dispatch!(self, get_pipeline_runtime_state(tenant_id, pipeline_id))
+    }
+
+    async fn update_pipeline_runtime_state(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        state: &PipelineRuntimeState,
+    ) -> Result<(), DBError> {
+        dispatch!(
+            self,
+            update_pipeline_runtime_state(tenant_id, pipeline_id, state)
+        )
+    }
+
+    async fn set_pipeline_desired_status(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        desired_status: PipelineStatus,
+    ) -> Result<(), DBError> {
+        dispatch!(
+            self,
+            set_pipeline_desired_status(tenant_id, pipeline_id, desired_status)
+        )
+    }
+
+    async fn list_pipelines(&self, tenant_id: TenantId) -> Result<Vec<Pipeline>, DBError> {
+        dispatch!(self, list_pipelines(tenant_id))
+    }
+
+    async fn new_connector(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        config: &ConnectorConfig,
+    ) -> Result<ConnectorId, DBError> {
+        dispatch!(
+            self,
+            new_connector(tenant_id, id, name, description, config)
+        )
+    }
+
+    async fn list_connectors(&self, tenant_id: TenantId) -> Result<Vec<ConnectorDescr>, DBError> {
+        dispatch!(self, list_connectors(tenant_id))
+    }
+
+    async fn get_connector_by_id(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<ConnectorDescr, DBError> {
+        dispatch!(self, get_connector_by_id(tenant_id, connector_id))
+    }
+
+    async fn get_connector_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<ConnectorDescr, DBError> {
+        dispatch!(self, get_connector_by_name(tenant_id, name))
+    }
+
+    async fn update_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+        connector_name: &str,
+        description: &str,
+        config: &Option<ConnectorConfig>,
+    ) -> Result<(), DBError> {
+        dispatch!(
+            self,
+            update_connector(tenant_id, connector_id, connector_name, description, config)
+        )
+    }
+
+    async fn delete_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, delete_connector(tenant_id, connector_id))
+    }
+
+    async fn restore_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, restore_connector(tenant_id, connector_id))
+    }
+
+    async fn list_deleted_connectors(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedConnector>, DBError> {
+        dispatch!(self, list_deleted_connectors(tenant_id))
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64, DBError> {
+        dispatch!(self, purge_deleted(deleted_before))
+    }
+
+    async fn store_api_key_hash(
+        &self,
+        tenant_id: TenantId,
+        key: String,
+        permissions: Vec<ApiPermission>,
+    ) -> Result<(), DBError> {
+        dispatch!(self, store_api_key_hash(tenant_id, key, permissions))
+    }
+
+    async fn validate_api_key(
+        &self,
+        key: String,
+    ) -> Result<(TenantId, Vec<ApiPermission>), DBError> {
+        dispatch!(self, validate_api_key(key))
+    }
+
+    async fn get_or_create_tenant_id(
+        &self,
+        tenant_name: String,
+        provider: String,
+    ) -> Result<TenantId, DBError> {
+        dispatch!(self, get_or_create_tenant_id(tenant_name, provider))
     }
 
     async fn create_tenant_if_not_exists(
@@ -2188,19 +3517,10 @@ impl Storage for ProjectDB {
         tenant_name: String,
         provider: String,
     ) -> Result<TenantId, DBError> {
-        let manager = self.pool.get().await?;
-        let stmt = manager
-            .prepare_cached("INSERT INTO tenant (id, tenant, provider) VALUES ($1, $2, $3) ON CONFLICT (tenant, provider) DO UPDATE SET tenant = excluded.tenant, provider = excluded.provider RETURNING id")
-            .await?;
-
-        // Unfortunately, doing a read-if-exists-else-insert is not very ergonomic. To
-        // do so in a single query requires us to do a redundant UPDATE on conflict,
-        // where we set the tenant and provider values to their existing values
-        // (excluded.{tenant, provider})
-        let res = manager
-            .query_one(&stmt, &[&tenant_id, &tenant_name, &provider])
-            .await?;
-        Ok(TenantId(res.get(0)))
+        dispatch!(
+            self,
+            create_tenant_if_not_exists(tenant_id, tenant_name, provider)
+        )
     }
 
     async fn create_compiled_binary_ref(
@@ -2209,15 +3529,7 @@ impl Storage for ProjectDB {
         version: Version,
         url: String,
     ) -> Result<(), DBError> {
-        let conn = self.pool.get().await?;
-        let stmt = conn
-            .prepare_cached("INSERT INTO compiled_binary VALUES ($1, $2, $3)")
-            .await?;
-
-        let _res = conn
-            .execute(&stmt, &[&program_id.0, &version.0, &url])
-            .await?;
-        Ok(())
+        dispatch!(self, create_compiled_binary_ref(program_id, version, url))
     }
 
     async fn get_compiled_binary_ref(
@@ -2225,14 +3537,7 @@ impl Storage for ProjectDB {
         program_id: ProgramId,
         version: Version,
     ) -> Result<Option<String>, DBError> {
-        let conn = self.pool.get().await?;
-        let stmt = conn
-            .prepare_cached(
-                "SELECT url FROM compiled_binary WHERE program_id = $1 AND version = $2",
-            )
-            .await?;
-        let res = conn.query_opt(&stmt, &[&program_id.0, &version.0]).await?;
-        Ok(res.map(|e| e.get(0)))
+        dispatch!(self, get_compiled_binary_ref(program_id, version))
     }
 
     async fn delete_compiled_binary_ref(
@@ -2240,16 +3545,171 @@ impl Storage for ProjectDB {
         program_id: ProgramId,
         version: Version,
     ) -> Result<(), DBError> {
-        let conn = self.pool.get().await?;
-        let stmt = conn
-            .prepare_cached("DELETE FROM compiled_binary WHERE program_id = $1 AND version = $2")
-            .await?;
-        let _res = conn.execute(&stmt, &[&program_id.0, &version.0]).await?;
-        Ok(())
+        dispatch!(self, delete_compiled_binary_ref(program_id, version))
+    }
+
+    async fn record_tenant_usage(
+        &self,
+        tenant_id: TenantId,
+        usage: &TenantUsage,
+    ) -> Result<(), DBError> {
+        dispatch!(self, record_tenant_usage(tenant_id, usage))
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: TenantId) -> Result<TenantUsage, DBError> {
+        dispatch!(self, get_tenant_usage(tenant_id))
+    }
+
+    async fn new_webhook(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        retry_policy: &WebhookRetryPolicy,
+    ) -> Result<WebhookId, DBError> {
+        dispatch!(
+            self,
+            new_webhook(tenant_id, id, url, secret, events, retry_policy)
+        )
+    }
+
+    async fn list_webhooks(&self, tenant_id: TenantId) -> Result<Vec<WebhookDescr>, DBError> {
+        dispatch!(self, list_webhooks(tenant_id))
+    }
+
+    async fn delete_webhook(
+        &self,
+        tenant_id: TenantId,
+        webhook_id: WebhookId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, delete_webhook(tenant_id, webhook_id))
+    }
+
+    async fn list_webhook_subscriptions(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<WebhookSubscription>, DBError> {
+        dispatch!(self, list_webhook_subscriptions(tenant_id))
+    }
+
+    async fn new_deployment(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        pipelines: &[PipelineId],
+        config: &Option<RuntimeConfig>,
+    ) -> Result<DeploymentId, DBError> {
+        dispatch!(
+            self,
+            new_deployment(tenant_id, id, name, description, pipelines, config)
+        )
+    }
+
+    async fn list_deployments(&self, tenant_id: TenantId) -> Result<Vec<DeploymentDescr>, DBError> {
+        dispatch!(self, list_deployments(tenant_id))
+    }
+
+    async fn get_deployment_by_id(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentDescr, DBError> {
+        dispatch!(self, get_deployment_by_id(tenant_id, deployment_id))
+    }
+
+    async fn delete_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), DBError> {
+        dispatch!(self, delete_deployment(tenant_id, deployment_id))
+    }
+
+    async fn check_connection(&self) -> Result<(), DBError> {
+        dispatch!(self, check_connection())
+    }
+
+    async fn record_heartbeat(&self, service: &str, now: DateTime<Utc>) -> Result<(), DBError> {
+        dispatch!(self, record_heartbeat(service, now))
+    }
+
+    async fn get_heartbeat(&self, service: &str) -> Result<Option<DateTime<Utc>>, DBError> {
+        dispatch!(self, get_heartbeat(service))
     }
 }
 
 impl ProjectDB {
+    /// Connect to the project database.
+    ///
+    /// Connection strings that start with `sqlite://` open (or create) an
+    /// embedded SQLite database at the given path, which requires the `lite`
+    /// feature.  All other connection strings refer to a Postgres database.
+    pub async fn connect(
+        db_config: &DatabaseConfig,
+        #[cfg(feature = "pg-embed")] api_config: Option<&ApiServerConfig>,
+    ) -> Result<Self, DBError> {
+        let connection_str = db_config.database_connection_string();
+        if let Some(path) = connection_str.strip_prefix("sqlite://") {
+            #[cfg(feature = "lite")]
+            return Ok(Self::Sqlite(
+                SqliteDB::connect(path, &db_config.initial_sql).await?,
+            ));
+            #[cfg(not(feature = "lite"))]
+            panic!("Unsupported connection string {connection_str}: SQLite databases require the 'lite' feature (database path: '{path}')");
+        }
+
+        Ok(Self::Postgres(
+            PostgresDB::connect(
+                db_config,
+                #[cfg(feature = "pg-embed")]
+                api_config,
+            )
+            .await?,
+        ))
+    }
+
+    /// Current size and utilization of the connection pool.
+    ///
+    /// Returns `None` for databases that don't use a connection pool.
+    pub(crate) fn pool_status(&self) -> Option<deadpool_postgres::Status> {
+        match self {
+            ProjectDB::Postgres(db) => Some(db.pool_status()),
+            #[cfg(feature = "lite")]
+            ProjectDB::Sqlite(_) => None,
+        }
+    }
+
+    /// See [`PostgresDB::is_program_version_in_use`].
+    pub(crate) async fn is_program_version_in_use(
+        &self,
+        program_id: Uuid,
+        version: i64,
+    ) -> Result<bool, DBError> {
+        dispatch!(self, is_program_version_in_use(program_id, version))
+    }
+
+    pub(crate) async fn pipeline_is_committable(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(PipelineDescr, ProgramDescr, Vec<ConnectorDescr>), DBError> {
+        dispatch!(self, pipeline_is_committable(tenant_id, pipeline_id))
+    }
+
+    pub(crate) async fn pipeline_config(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineConfig, DBError> {
+        dispatch!(self, pipeline_config(tenant_id, pipeline_id))
+    }
+}
+
+impl PostgresDB {
     pub async fn connect(
         db_config: &DatabaseConfig,
         #[cfg(feature = "pg-embed")] api_config: Option<&ApiServerConfig>,
@@ -2291,7 +3751,7 @@ impl ProjectDB {
         config: tokio_postgres::Config,
         initial_sql: &Option<String>,
     ) -> Result<Self, DBError> {
-        let db = ProjectDB::initialize(
+        let db = PostgresDB::initialize(
             config,
             initial_sql,
             #[cfg(feature = "pg-embed")]
@@ -2324,7 +3784,7 @@ impl ProjectDB {
         let config = connection_str.parse::<tokio_postgres::Config>()?;
         debug!("Opening connection to {:?}", connection_str);
 
-        let db = ProjectDB::initialize(
+        let db = PostgresDB::initialize(
             config,
             initial_sql,
             #[cfg(feature = "pg-embed")]
@@ -2378,15 +3838,9 @@ impl ProjectDB {
         return Ok(Self { config, pool });
     }
 
-    fn deserialize_error_response(
-        pipeline_id: PipelineId,
-        error_str: &str,
-    ) -> Result<ErrorResponse, DBError> {
-        serde_json::from_str::<ErrorResponse>(error_str).map_err(|_| {
-            DBError::invalid_data(format!(
-                "Unexpected pipeline error format for pipeline '{pipeline_id}': {error_str}"
-            ))
-        })
+    /// Current size and utilization of the connection pool.
+    pub(crate) fn pool_status(&self) -> deadpool_postgres::Status {
+        self.pool.status()
     }
 
     async fn row_to_pipeline_descr(&self, row: &Row) -> Result<PipelineDescr, DBError> {
@@ -2416,9 +3870,11 @@ impl ProjectDB {
                 status_since: convert_bigint_to_time(row.get(3))?,
                 error: row
                     .get::<_, Option<String>>(4)
-                    .map(|s| Self::deserialize_error_response(pipeline_id, &s))
+                    .map(|s| deserialize_error_response(pipeline_id, &s))
                     .transpose()?,
                 created: convert_bigint_to_time(row.get(5))?,
+                tls_certificate: row.get(6),
+                auth_token: row.get(7),
             })
         } else {
             Err(DBError::UnknownPipeline { pipeline_id })
@@ -2445,9 +3901,11 @@ impl ProjectDB {
             status_since: convert_bigint_to_time(row.get(10))?,
             error: row
                 .get::<_, Option<String>>(11)
-                .map(|s| Self::deserialize_error_response(pipeline_id, &s))
+                .map(|s| deserialize_error_response(pipeline_id, &s))
                 .transpose()?,
             created: convert_bigint_to_time(row.get(12))?,
+            tls_certificate: row.get(13),
+            auth_token: row.get(14),
         };
 
         Ok(Pipeline { descriptor, state })
@@ -2527,7 +3985,7 @@ impl ProjectDB {
         let stmt = manager
             .prepare_cached(
                 "SELECT
-                name, description, version, status, error, schema, code
+                name, description, version, status, error, schema, code, warnings
                 FROM program_history WHERE id = $1 AND tenant_id = $2 AND revision = $3",
             )
             .await?;
@@ -2548,12 +4006,14 @@ impl ProjectDB {
                 .map_err(|e| DBError::invalid_data(format!("Error parsing program schema: {e}")))?;
             let status = ProgramStatus::from_columns(status.as_deref(), error)?;
             let code = row.get(6);
+            let warnings = warnings_from_column(row.get(7))?;
             Ok(ProgramDescr {
                 program_id,
                 name,
                 description,
                 version,
                 status,
+                warnings,
                 schema,
                 code,
             })
@@ -2641,7 +4101,8 @@ impl ProjectDB {
             FROM connector c, attached_connector ac
             WHERE ac.pipeline_id = $1
             AND ac.connector_id = c.id
-            AND c.tenant_id = $2",
+            AND c.tenant_id = $2
+            AND c.deleted_at IS NULL",
             )
             .await?;
 
@@ -2681,7 +4142,7 @@ impl ProjectDB {
         let stmt = txn.prepare_cached("INSERT INTO attached_connector (name, pipeline_id, connector_id, is_input, config, tenant_id)
             SELECT $2, $3, id, $5, $6, tenant_id
             FROM connector
-            WHERE tenant_id = $1 AND id = $4")
+            WHERE tenant_id = $1 AND id = $4 AND deleted_at IS NULL")
         .await?;
         let rows = txn
             .execute(
@@ -2732,6 +4193,18 @@ impl ProjectDB {
         Ok(attached_connectors)
     }
 
+    fn row_to_deployment_descr(row: &Row) -> DeploymentDescr {
+        let pipelines: Vec<Uuid> = row.get(3);
+        let config: Option<String> = row.get(4);
+        DeploymentDescr {
+            deployment_id: DeploymentId(row.get(0)),
+            name: row.get(1),
+            description: row.get(2),
+            pipelines: pipelines.into_iter().map(PipelineId).collect(),
+            config: config.as_deref().map(RuntimeConfig::from_yaml),
+        }
+    }
+
     /// Helper to convert postgres error into a `DBError` if the underlying
     /// low-level error thrown by the database matches.
     fn maybe_unique_violation(err: PgError) -> DBError {
@@ -2741,6 +4214,8 @@ impl ProjectDB {
                     Some("program_pkey") => DBError::unique_key_violation("program_pkey"),
                     Some("connector_pkey") => DBError::unique_key_violation("connector_pkey"),
                     Some("pipeline_pkey") => DBError::unique_key_violation("pipeline_pkey"),
+                    Some("webhook_pkey") => DBError::unique_key_violation("webhook_pkey"),
+                    Some("deployment_pkey") => DBError::unique_key_violation("deployment_pkey"),
                     Some("api_key_pkey") => DBError::duplicate_key(),
                     Some(_constraint) => DBError::DuplicateName,
                     None => DBError::DuplicateName,
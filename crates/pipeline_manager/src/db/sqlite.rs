@@ -0,0 +1,2009 @@
+//! Project database stored in an embedded SQLite database.
+//!
+//! Used in lite mode, where the manager runs as a single process without an
+//! external Postgres server.  The schema (see `migrations_sqlite/`) mirrors
+//! the Postgres schema, and the queries below mirror the ones in
+//! [`PostgresDB`](super::PostgresDB), so both backends implement the same
+//! semantics, including the error reported when a constraint is violated.
+//!
+//! The database is accessed through a single connection.  Queries against a
+//! local SQLite database are fast, so we run them directly on the async
+//! runtime instead of handing them off to a blocking thread.
+
+use super::{
+    convert_bigint_to_time, deserialize_error_response, storage::Storage, warnings_from_column,
+    ApiPermission, AttachedConnector, CompilationJob, ConnectorDescr, ConnectorId, DBError,
+    DeletedConnector, DeletedPipeline, DeploymentDescr, DeploymentId, Pipeline, PipelineDescr,
+    PipelineId, PipelineRevision, PipelineRuntimeState, PipelineStatus, ProgramDescr, ProgramId,
+    ProgramSchema, Revision, TenantUsage, Version, WebhookDescr, WebhookEvent, WebhookId,
+    WebhookRetryPolicy, WebhookSubscription,
+};
+use crate::{
+    auth::{TenantId, TenantRecord},
+    compiler::{ProgramStatus, SqlCompilerMessage},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use dbsp_adapters::{ConnectorConfig, PipelineConfig, RuntimeConfig};
+use log::debug;
+use openssl::sha;
+use rusqlite::{
+    ffi, functions::FunctionFlags, params, Connection, ErrorCode, OptionalExtension, Row,
+};
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("./migrations_sqlite/");
+}
+
+/// Number of notifications buffered for each subscriber.  A subscriber that
+/// falls further behind observes a [`broadcast::error::RecvError::Lagged`]
+/// error and must resynchronize with the database.
+const NOTIFICATION_CAPACITY: usize = 1024;
+
+/// Columns of the `program` and `program_history` tables decoded by
+/// [`read_program`].
+const PROGRAM_COLUMNS: &str =
+    "id, name, description, version, status, error, schema, code, warnings, tenant_id";
+
+/// Columns of a pipeline `p` decoded by [`read_pipeline_descr`].
+const PIPELINE_COLUMNS: &str = "p.id, p.version, p.name, p.description, p.config, p.program_id";
+
+/// Columns of a pipeline runtime state `rt` decoded by [`read_runtime_state`].
+const RUNTIME_STATE_COLUMNS: &str = "rt.location, rt.desired_status, rt.current_status, \
+    rt.status_since, rt.error, rt.created, rt.tls_certificate, rt.auth_token";
+
+/// Project database stored in an embedded SQLite database.
+pub struct SqliteDB {
+    conn: Mutex<Connection>,
+    /// Notifications sent by the database triggers, as `(channel, payload)`
+    /// pairs in the same format as the Postgres notifications.
+    notifications: broadcast::Sender<(String, String)>,
+}
+
+impl SqliteDB {
+    /// Open (or create) the database at `path` and bring its schema up to
+    /// date.
+    ///
+    /// `initial_sql` is the path of a file with SQL statements to run after
+    /// the migrations.
+    pub async fn connect(path: &str, initial_sql: &Option<String>) -> Result<Self, DBError> {
+        debug!("Opening SQLite database {:?}", path);
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        let sender = AssertUnwindSafe(notifications.clone());
+        conn.create_scalar_function("notify", 2, FunctionFlags::SQLITE_UTF8, move |ctx| {
+            let channel: String = ctx.get(0)?;
+            let payload: String = ctx.get(1)?;
+            // Fails if nobody is subscribed, in which case there's
+            // nobody to notify.
+            let _ = sender.send((channel, payload));
+            Ok(true)
+        })?;
+
+        embedded::migrations::runner().run(&mut conn)?;
+        if let Some(initial_sql_file) = &initial_sql {
+            if let Ok(initial_sql) = tokio::fs::read_to_string(initial_sql_file).await {
+                conn.execute_batch(&initial_sql)?;
+            } else {
+                log::warn!("initial SQL file '{}' does not exist", initial_sql_file);
+            }
+        }
+
+        let db = Self {
+            conn: Mutex::new(conn),
+            notifications,
+        };
+        let default_tenant = TenantRecord::default();
+        db.create_tenant_if_not_exists(
+            default_tenant.id.0,
+            default_tenant.tenant,
+            default_tenant.provider,
+        )
+        .await?;
+        Ok(db)
+    }
+
+    /// Subscribe to the notifications sent when programs, pipelines, and
+    /// pipeline runtime states change.
+    ///
+    /// Receives `(channel, payload)` pairs, where `channel` is one of
+    /// `program`, `pipeline`, or `pipeline_runtime_state`, and `payload`
+    /// has the same format as the payload of the corresponding Postgres
+    /// notification.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<(String, String)> {
+        self.notifications.subscribe()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn execute_batch(&self, sql: &str) -> Result<(), DBError> {
+        Ok(self.conn().execute_batch(sql)?)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave the database in an
+        // inconsistent state: uncommitted transactions are rolled back when
+        // dropped.
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// See [`PostgresDB::is_program_version_in_use`](super::PostgresDB::is_program_version_in_use).
+    pub(crate) async fn is_program_version_in_use(
+        &self,
+        program_id: Uuid,
+        version: i64,
+    ) -> Result<bool, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT EXISTS(SELECT 1 FROM program prog
+                WHERE prog.id = ?1
+                AND prog.version = ?2)
+            OR
+            EXISTS(SELECT 1 FROM pipeline p, pipeline_history ph, program_history progh
+                WHERE ph.program_id = ?1
+                AND ph.revision = p.last_revision
+                AND ph.program_id = progh.id
+                AND progh.version = ?2)",
+        )?;
+        Ok(stmt.query_row(params![program_id.to_string(), version], |row| row.get(0))?)
+    }
+
+    pub(crate) async fn pipeline_is_committable(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(PipelineDescr, ProgramDescr, Vec<ConnectorDescr>), DBError> {
+        pipeline_is_committable(&self.conn(), tenant_id, pipeline_id)
+    }
+
+    pub(crate) async fn pipeline_config(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineConfig, DBError> {
+        let conn = self.conn();
+        let pipeline = pipeline_descr_by_id(&conn, tenant_id, pipeline_id)?;
+        let connectors = connectors_for_pipeline_id(&conn, tenant_id, pipeline_id)?;
+        PipelineRevision::generate_pipeline_config(&pipeline, &connectors)
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteDB {
+    async fn list_programs(
+        &self,
+        tenant_id: TenantId,
+        with_code: bool,
+    ) -> Result<Vec<ProgramDescr>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PROGRAM_COLUMNS} FROM program WHERE tenant_id = ?1"
+        ))?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_program(row, with_code)?.1);
+        }
+        Ok(result)
+    }
+
+    async fn new_program(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        program_name: &str,
+        program_description: &str,
+        program_code: &str,
+    ) -> Result<(ProgramId, Version), DBError> {
+        debug!("new_program {program_name} {program_description} {program_code}");
+        let conn = self.conn();
+        check_id_is_new(&conn, "program", "program_pkey", id)?;
+        conn.prepare_cached(
+            "INSERT INTO program (id, version, tenant_id, name, description, code, schema, status, error, status_since)
+            VALUES(?1, 1, ?2, ?3, ?4, ?5, NULL, NULL, NULL, ?6)",
+        )?
+        .execute(params![
+            id.to_string(),
+            tenant_id.0.to_string(),
+            program_name,
+            program_description,
+            program_code,
+            Utc::now().timestamp_micros(),
+        ])
+        .map_err(|e| maybe_tenant_id_foreign_key_constraint_err(e, tenant_id))?;
+
+        Ok((ProgramId(id), Version(1)))
+    }
+
+    async fn update_program(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        program_name: &str,
+        program_description: &str,
+        program_code: &Option<String>,
+    ) -> Result<Version, DBError> {
+        let conn = self.conn();
+        let version: Option<i64> = match program_code {
+            // Only increment `version` if new code actually differs from the
+            // current version.
+            Some(code) => conn
+                .prepare_cached(
+                    "UPDATE program
+                    SET
+                        version = (CASE WHEN code = ?3 THEN version ELSE version + 1 END),
+                        name = ?1,
+                        description = ?2,
+                        code = ?3,
+                        status = (CASE WHEN code = ?3 THEN status ELSE NULL END),
+                        error = (CASE WHEN code = ?3 THEN error ELSE NULL END),
+                        schema = (CASE WHEN code = ?3 THEN schema ELSE NULL END),
+                        warnings = (CASE WHEN code = ?3 THEN warnings ELSE NULL END)
+                    WHERE id = ?4 AND tenant_id = ?5
+                    RETURNING version",
+                )?
+                .query_row(
+                    params![
+                        program_name,
+                        program_description,
+                        code,
+                        program_id.0.to_string(),
+                        tenant_id.0.to_string(),
+                    ],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(maybe_unique_violation)?,
+            None => conn
+                .prepare_cached(
+                    "UPDATE program SET name = ?1, description = ?2 WHERE id = ?3 AND tenant_id = ?4 RETURNING version",
+                )?
+                .query_row(
+                    params![
+                        program_name,
+                        program_description,
+                        program_id.0.to_string(),
+                        tenant_id.0.to_string(),
+                    ],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(maybe_unique_violation)?,
+        };
+
+        version
+            .map(Version)
+            .ok_or(DBError::UnknownProgram { program_id })
+    }
+
+    async fn get_program_if_exists(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        with_code: bool,
+    ) -> Result<Option<ProgramDescr>, DBError> {
+        program_if_exists(&self.conn(), tenant_id, program_id, with_code)
+    }
+
+    async fn lookup_program(
+        &self,
+        tenant_id: TenantId,
+        program_name: &str,
+        with_code: bool,
+    ) -> Result<Option<ProgramDescr>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PROGRAM_COLUMNS} FROM program WHERE name = ?1 AND tenant_id = ?2"
+        ))?;
+        let mut rows = stmt.query(params![program_name, tenant_id.0.to_string()])?;
+        rows.next()?
+            .map(|row| Ok(read_program(row, with_code)?.1))
+            .transpose()
+    }
+
+    async fn set_program_for_compilation(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        expected_version: Version,
+        status: ProgramStatus,
+    ) -> Result<(), DBError> {
+        let (status, error) = status.to_columns();
+        self.conn()
+            .prepare_cached(
+                "UPDATE program SET
+                 status = (CASE WHEN version = ?4 THEN ?1 ELSE status END),
+                 error = (CASE WHEN version = ?4 THEN ?2 ELSE error END),
+                 status_since = (CASE WHEN version = ?4 THEN ?6
+                                 ELSE status_since END),
+                 schema = (CASE WHEN version = ?4 THEN NULL ELSE schema END),
+                 warnings = (CASE WHEN version = ?4 THEN NULL ELSE warnings END)
+                 WHERE id = ?3 AND tenant_id = ?5",
+            )?
+            .execute(params![
+                status,
+                error,
+                program_id.0.to_string(),
+                expected_version.0,
+                tenant_id.0.to_string(),
+                Utc::now().timestamp_micros(),
+            ])?;
+
+        Ok(())
+    }
+
+    async fn set_program_status_guarded(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        expected_version: Version,
+        status: ProgramStatus,
+    ) -> Result<(), DBError> {
+        let (status, error) = status.to_columns();
+        // We could perform the guard in the WHERE clause, but that does not
+        // tell us whether the ID existed or not.
+        // Instead, we use a case statement for the guard.
+        let modified_rows = self
+            .conn()
+            .prepare_cached(
+                "UPDATE program SET
+                 status = (CASE WHEN version = ?4 THEN ?1 ELSE status END),
+                 error = (CASE WHEN version = ?4 THEN ?2 ELSE error END),
+                 status_since = (CASE WHEN version = ?4 THEN ?6
+                                 ELSE status_since END)
+                 WHERE id = ?3 AND tenant_id = ?5",
+            )?
+            .execute(params![
+                status,
+                error,
+                program_id.0.to_string(),
+                expected_version.0,
+                tenant_id.0.to_string(),
+                Utc::now().timestamp_micros(),
+            ])?;
+
+        if modified_rows == 0 {
+            Err(DBError::UnknownProgram { program_id })
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn set_program_schema(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        schema: ProgramSchema,
+    ) -> Result<(), DBError> {
+        let schema = serde_json::to_string(&schema).map_err(|e| {
+            DBError::invalid_data(format!(
+                "Error serializing program schema '{schema:?}'.\nError: {e}"
+            ))
+        })?;
+        self.conn()
+            .prepare_cached("UPDATE program SET schema = ?1 WHERE id = ?2 AND tenant_id = ?3")?
+            .execute(params![
+                schema,
+                program_id.0.to_string(),
+                tenant_id.0.to_string()
+            ])?;
+
+        Ok(())
+    }
+
+    async fn set_program_warnings(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        warnings: Vec<SqlCompilerMessage>,
+    ) -> Result<(), DBError> {
+        let warnings = serde_json::to_string(&warnings).map_err(|e| {
+            DBError::invalid_data(format!(
+                "Error serializing program warnings '{warnings:?}'.\nError: {e}"
+            ))
+        })?;
+        self.conn()
+            .prepare_cached("UPDATE program SET warnings = ?1 WHERE id = ?2 AND tenant_id = ?3")?
+            .execute(params![
+                warnings,
+                program_id.0.to_string(),
+                tenant_id.0.to_string()
+            ])?;
+
+        Ok(())
+    }
+
+    async fn delete_program(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached("DELETE FROM program WHERE id = ?1 AND tenant_id = ?2")?
+            .execute(params![program_id.0.to_string(), tenant_id.0.to_string()])
+            .map_err(|e| {
+                // The only foreign key that references a program without
+                // cascading is `pipeline.program_id`.
+                if is_foreign_key_violation(&e) {
+                    DBError::ProgramInUseByPipeline { program_id }
+                } else {
+                    e.into()
+                }
+            })?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownProgram { program_id })
+        }
+    }
+
+    async fn all_programs(&self) -> Result<Vec<(TenantId, ProgramDescr)>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!("SELECT {PROGRAM_COLUMNS} FROM program"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_program(row, false)?);
+        }
+        Ok(result)
+    }
+
+    async fn all_pipelines(&self) -> Result<Vec<(TenantId, PipelineId)>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached("SELECT tenant_id, id FROM pipeline")?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push((TenantId(get_uuid(row, 0)?), PipelineId(get_uuid(row, 1)?)));
+        }
+        Ok(result)
+    }
+
+    async fn next_job(&self) -> Result<Option<(TenantId, ProgramId, Version)>, DBError> {
+        let conn = self.conn();
+        // Find the oldest pending project.
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, version, tenant_id FROM program WHERE status = 'pending' AND status_since = (SELECT min(status_since) FROM program WHERE status = 'pending')",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        if let Some(row) = rows.next()? {
+            let program_id = ProgramId(get_uuid(row, 0)?);
+            let version = Version(row.get(1)?);
+            let tenant_id = TenantId(get_uuid(row, 2)?);
+            Ok(Some((tenant_id, program_id, version)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn count_pending_programs(&self) -> Result<u64, DBError> {
+        let count: i64 = self
+            .conn()
+            .prepare_cached("SELECT count(*) FROM program WHERE status = 'pending'")?
+            .query_row([], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    async fn list_compilation_jobs(&self) -> Result<Vec<CompilationJob>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT tenant_id, id, version, status, status_since
+            FROM program
+            WHERE status IN ('pending', 'compiling_sql', 'compiling_rust')
+            ORDER BY status_since",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let status: Option<String> = row.get(3)?;
+            let status_since: i64 = row.get(4)?;
+            result.push(CompilationJob {
+                tenant_id: TenantId(get_uuid(row, 0)?),
+                program_id: ProgramId(get_uuid(row, 1)?),
+                version: Version(row.get(2)?),
+                status: ProgramStatus::from_columns(status.as_deref(), None)?,
+                status_since: convert_micros_to_time(status_since)?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn count_pipelines_by_status(&self) -> Result<Vec<(PipelineStatus, u64)>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT rt.current_status, count(*)
+            FROM pipeline_runtime_state rt
+            JOIN pipeline p ON p.id = rt.id
+            WHERE p.deleted_at IS NULL
+            GROUP BY rt.current_status",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let status = PipelineStatus::try_from(row.get::<_, String>(0)?)?;
+            result.push((status, row.get::<_, i64>(1)? as u64));
+        }
+        Ok(result)
+    }
+
+    async fn create_pipeline_revision(
+        &self,
+        revision: Uuid,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<Revision, DBError> {
+        let mut conn = self.conn();
+        let txn = conn.transaction()?;
+
+        let prev_revision: Option<String> = txn
+            .prepare_cached("SELECT last_revision FROM pipeline WHERE id = ?1 AND tenant_id = ?2")?
+            .query_row(
+                params![pipeline_id.0.to_string(), tenant_id.0.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+
+        // Check if we actually changed something before writing a new revision
+        //
+        // Note: What fields are checked is ultimately determined by whatever is
+        // used by the pipeline configuration, e.g., what the `start` function
+        // uses in `runner.rs` to write the config/metadata:
+        if let Some(prev_revision) = prev_revision {
+            let changes: i64 = txn
+                .prepare_cached(
+                    "WITH ph_entry AS (
+                        SELECT progh.code, ch.config AS connector_config, ach.name,
+                            ach.config AS relation_name, ach.is_input, ph.config
+                        FROM pipeline_history ph
+                        INNER JOIN program_history progh ON ph.program_id = progh.id AND progh.revision = ?2
+                        LEFT OUTER JOIN attached_connector_history ach ON ach.pipeline_id = ph.id AND ach.revision = ?2
+                        LEFT OUTER JOIN connector_history ch ON ach.connector_id = ch.id AND ch.revision = ?2
+                        WHERE ph.id = ?1 AND ph.revision = ?2
+                    ),
+                    p_entry AS (
+                        SELECT prog.code, c.config AS connector_config, ac.name,
+                            ac.config AS relation_name, ac.is_input, p.config
+                        FROM pipeline p
+                        INNER JOIN program prog ON p.program_id = prog.id
+                        LEFT OUTER JOIN live_attached_connector ac ON ac.pipeline_id = p.id
+                        LEFT OUTER JOIN connector c ON ac.connector_id = c.id
+                        WHERE p.id = ?1
+                    ),
+                    diff_1 AS (
+                        SELECT * FROM ph_entry EXCEPT SELECT * FROM p_entry
+                    ),
+                    diff_2 AS (
+                        SELECT * FROM p_entry EXCEPT SELECT * FROM ph_entry
+                    )
+                    SELECT COUNT(*) FROM (SELECT * FROM diff_1 UNION ALL SELECT * FROM diff_2)",
+                )?
+                .query_row(params![pipeline_id.0.to_string(), prev_revision], |row| {
+                    row.get(0)
+                })?;
+            if changes == 0 {
+                return Err(DBError::RevisionNotChanged);
+            }
+        }
+
+        let (_pipeline, program, _connectors) =
+            pipeline_is_committable(&txn, tenant_id, pipeline_id)?;
+
+        // Copy all pipeline data to history tables
+        let revision_str = revision.to_string();
+        let pipeline_id_str = pipeline_id.0.to_string();
+        txn.prepare_cached(
+            "INSERT INTO program_history SELECT ?1 AS revision, * FROM program WHERE id = ?2",
+        )?
+        .execute(params![revision_str, program.program_id.0.to_string()])?;
+        txn.prepare_cached(
+            "INSERT INTO pipeline_history SELECT ?1 AS revision, * FROM pipeline WHERE id = ?2",
+        )?
+        .execute(params![revision_str, pipeline_id_str])?;
+        txn.prepare_cached(
+            "INSERT INTO connector_history SELECT ?1 AS revision, c.* FROM connector c, attached_connector ac WHERE ac.pipeline_id = ?2 AND ac.connector_id = c.id AND c.deleted_at IS NULL",
+        )?
+        .execute(params![revision_str, pipeline_id_str])?;
+        txn.prepare_cached(
+            "INSERT INTO attached_connector_history SELECT ?1 AS revision, * FROM live_attached_connector ac WHERE ac.pipeline_id = ?2",
+        )?
+        .execute(params![revision_str, pipeline_id_str])?;
+
+        // Update the revision of the pipeline object
+        txn.prepare_cached(
+            "UPDATE pipeline SET last_revision = ?1 WHERE id = ?2 AND tenant_id = ?3",
+        )?
+        .execute(params![
+            revision_str,
+            pipeline_id_str,
+            tenant_id.0.to_string()
+        ])?;
+
+        txn.commit()?;
+
+        Ok(Revision(revision))
+    }
+
+    async fn get_last_committed_pipeline_revision(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineRevision, DBError> {
+        let conn = self.conn();
+        let last_revision: Option<String> = conn
+            .prepare_cached("SELECT last_revision FROM pipeline WHERE id = ?1 AND tenant_id = ?2")?
+            .query_row(
+                params![pipeline_id.0.to_string(), tenant_id.0.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+        let revision = Revision(parse_uuid(
+            &last_revision.ok_or(DBError::NoRevisionAvailable { pipeline_id })?,
+        )?);
+
+        let pipeline = committed_pipeline_by_id(&conn, tenant_id, pipeline_id, revision)?;
+        // expect() is ok here - we don't allow to commit something without a program
+        let program_id = pipeline
+            .program_id
+            .expect("pre-condition: pipeline has a program");
+        let program = committed_program_by_id(&conn, tenant_id, program_id, revision)?;
+        let connectors = committed_connectors_by_id(&conn, tenant_id, pipeline_id, revision)?;
+
+        Ok(PipelineRevision::new(
+            revision, pipeline, connectors, program,
+        ))
+    }
+
+    async fn new_pipeline(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        program_id: Option<ProgramId>,
+        pipline_name: &str,
+        pipeline_description: &str,
+        config: &RuntimeConfig,
+        connectors: &Option<Vec<AttachedConnector>>,
+    ) -> Result<(PipelineId, Version), DBError> {
+        let mut conn = self.conn();
+        let txn = conn.transaction()?;
+        check_id_is_new(&txn, "pipeline", "pipeline_pkey", id)?;
+
+        txn.prepare_cached(
+            "INSERT INTO pipeline (id, program_id, version, name, description, config, tenant_id) VALUES(?1, ?2, 1, ?3, ?4, ?5, ?6)",
+        )?
+        .execute(params![
+            id.to_string(),
+            program_id.map(|id| id.0.to_string()),
+            pipline_name,
+            pipeline_description,
+            RuntimeConfig::to_yaml(config),
+            tenant_id.0.to_string(),
+        ])
+        .map_err(|e| maybe_program_id_not_found_foreign_key_constraint_err(e, program_id))?;
+
+        let now = Utc::now().timestamp();
+        txn.prepare_cached(
+            "INSERT INTO pipeline_runtime_state (id, tenant_id, desired_status, current_status, status_since, created) VALUES(?1, ?2, 'shutdown', 'shutdown', ?3, ?3)",
+        )?
+        .execute(params![id.to_string(), tenant_id.0.to_string(), now])?;
+
+        let pipeline_id = PipelineId(id);
+        if let Some(connectors) = connectors {
+            // Add the connectors.
+            for ac in connectors {
+                attach_connector(&txn, tenant_id, pipeline_id, ac)?;
+            }
+        }
+        txn.commit()?;
+
+        Ok((pipeline_id, Version(1)))
+    }
+
+    async fn update_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        program_id: Option<ProgramId>,
+        pipline_name: &str,
+        pipeline_description: &str,
+        config: &Option<RuntimeConfig>,
+        connectors: &Option<Vec<AttachedConnector>>,
+    ) -> Result<Version, DBError> {
+        log::trace!(
+            "Updating config {} {} {} {} {:?} {:?}",
+            pipeline_id.0,
+            program_id
+                .map(|pid| pid.0.to_string())
+                .unwrap_or("<not set>".into()),
+            pipline_name,
+            pipeline_description,
+            config,
+            connectors
+        );
+        let mut conn = self.conn();
+        let txn = conn.transaction()?;
+
+        // First check whether the pipeline exists. Without this check, subsequent
+        // calls will fail.
+        let exists = txn
+            .prepare_cached(
+                "SELECT id FROM pipeline WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NULL",
+            )?
+            .exists(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+        if !exists {
+            return Err(DBError::UnknownPipeline { pipeline_id });
+        }
+        if let Some(connectors) = connectors {
+            // Delete all existing attached connectors.
+            txn.prepare_cached(
+                "DELETE FROM attached_connector WHERE pipeline_id = ?1 AND tenant_id = ?2",
+            )?
+            .execute(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+
+            // Rewrite the new set of connectors.
+            for ac in connectors {
+                attach_connector(&txn, tenant_id, pipeline_id, ac)?;
+            }
+        }
+        let config = config.as_ref().map(RuntimeConfig::to_yaml);
+        let version: Option<i64> = txn
+            .prepare_cached(
+                "UPDATE pipeline SET version = version + 1, name = ?1, description = ?2, config = COALESCE(?3, config), program_id = ?4 WHERE id = ?5 AND tenant_id = ?6 RETURNING version",
+            )?
+            .query_row(
+                params![
+                    pipline_name,
+                    pipeline_description,
+                    config,
+                    program_id.map(|id| id.0.to_string()),
+                    pipeline_id.0.to_string(),
+                    tenant_id.0.to_string(),
+                ],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| maybe_program_id_not_found_foreign_key_constraint_err(e, program_id))?;
+        txn.commit()?;
+
+        version
+            .map(Version)
+            .ok_or(DBError::UnknownPipeline { pipeline_id })
+    }
+
+    async fn delete_config(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached("DELETE FROM pipeline WHERE id = ?1 AND tenant_id = ?2")?
+            .execute(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownPipeline { pipeline_id })
+        }
+    }
+
+    async fn attached_connector_is_input(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        name: &str,
+    ) -> Result<bool, DBError> {
+        self.conn()
+            .prepare_cached("SELECT is_input FROM live_attached_connector WHERE name = ?1 AND pipeline_id = ?2 AND tenant_id = ?3")?
+            .query_row(
+                params![name, pipeline_id.0.to_string(), tenant_id.0.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| DBError::UnknownAttachedConnector {
+                pipeline_id,
+                name: name.to_string(),
+            })
+    }
+
+    async fn delete_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<bool, DBError> {
+        let res = self
+            .conn()
+            .prepare_cached(
+                "UPDATE pipeline SET deleted_at = ?3
+                WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NULL",
+            )?
+            .execute(params![
+                pipeline_id.0.to_string(),
+                tenant_id.0.to_string(),
+                Utc::now().timestamp()
+            ])?;
+        Ok(res > 0)
+    }
+
+    async fn restore_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached(
+                "UPDATE pipeline SET deleted_at = NULL
+                WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NOT NULL",
+            )?
+            .execute(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])
+            .map_err(maybe_unique_violation)?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownPipeline { pipeline_id })
+        }
+    }
+
+    async fn list_deleted_pipelines(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedPipeline>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PIPELINE_COLUMNS}, p.deleted_at FROM pipeline p
+            WHERE p.tenant_id = ?1 AND p.deleted_at IS NOT NULL"
+        ))?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(DeletedPipeline {
+                descriptor: read_pipeline_descr(&conn, row, None)?,
+                deleted_at: convert_bigint_to_time(row.get(6)?)?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn get_pipeline_descr_by_id(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineDescr, DBError> {
+        pipeline_descr_by_id(&self.conn(), tenant_id, pipeline_id)
+    }
+
+    async fn get_pipeline_by_id(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<Pipeline, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PIPELINE_COLUMNS}, {RUNTIME_STATE_COLUMNS}
+            FROM pipeline p
+            INNER JOIN pipeline_runtime_state rt on p.id = rt.id
+            WHERE p.id = ?1 AND p.tenant_id = ?2 AND p.deleted_at IS NULL"
+        ))?;
+        let mut rows = stmt.query(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+        let row = rows
+            .next()?
+            .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+        read_pipeline(&conn, row)
+    }
+
+    async fn get_pipeline_descr_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<PipelineDescr, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PIPELINE_COLUMNS} FROM pipeline p
+            WHERE p.name = ?1 AND p.tenant_id = ?2 AND p.deleted_at IS NULL"
+        ))?;
+        let mut rows = stmt.query(params![name, tenant_id.0.to_string()])?;
+        let row = rows.next()?.ok_or(DBError::UnknownName { name })?;
+        read_pipeline_descr(&conn, row, None)
+    }
+
+    async fn get_pipeline_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<Pipeline, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PIPELINE_COLUMNS}, {RUNTIME_STATE_COLUMNS}
+            FROM pipeline p
+            INNER JOIN pipeline_runtime_state rt on p.id = rt.id
+            WHERE p.name = ?1 AND p.tenant_id = ?2 AND p.deleted_at IS NULL"
+        ))?;
+        let mut rows = stmt.query(params![name, tenant_id.0.to_string()])?;
+        let row = rows.next()?.ok_or(DBError::UnknownName { name })?;
+        read_pipeline(&conn, row)
+    }
+
+    async fn get_pipeline_runtime_state(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<PipelineRuntimeState, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {RUNTIME_STATE_COLUMNS}
+            FROM pipeline_runtime_state rt
+            WHERE rt.id = ?1 AND rt.tenant_id = ?2"
+        ))?;
+        let mut rows = stmt.query(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+        let row = rows
+            .next()?
+            .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+        read_runtime_state(pipeline_id, row, 0)
+    }
+
+    async fn update_pipeline_runtime_state(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        state: &PipelineRuntimeState,
+    ) -> Result<(), DBError> {
+        let current_status: &'static str = state.current_status.into();
+        let modified_rows = self
+            .conn()
+            .prepare_cached(
+                "UPDATE pipeline_runtime_state
+                SET location = ?3,
+                    current_status = ?4,
+                    status_since = ?5,
+                    created = ?6,
+                    error = ?7,
+                    tls_certificate = ?8,
+                    auth_token = ?9
+                WHERE id = ?1 AND tenant_id = ?2",
+            )?
+            .execute(params![
+                pipeline_id.0.to_string(),
+                tenant_id.0.to_string(),
+                state.location,
+                current_status,
+                state.status_since.timestamp(),
+                state.created.timestamp(),
+                state
+                    .error
+                    .as_ref()
+                    .map(|e| serde_json::to_string(&e).unwrap()),
+                state.tls_certificate,
+                state.auth_token,
+            ])?;
+
+        if modified_rows == 0 {
+            return Err(DBError::UnknownPipeline { pipeline_id });
+        }
+        Ok(())
+    }
+
+    async fn set_pipeline_desired_status(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        desired_status: PipelineStatus,
+    ) -> Result<(), DBError> {
+        let desired_status: &'static str = desired_status.into();
+        let modified_rows = self
+            .conn()
+            .prepare_cached(
+                "UPDATE pipeline_runtime_state
+                SET desired_status = ?3
+                WHERE tenant_id = ?1 AND id = ?2",
+            )?
+            .execute(params![
+                tenant_id.0.to_string(),
+                pipeline_id.0.to_string(),
+                desired_status
+            ])?;
+
+        if modified_rows == 0 {
+            return Err(DBError::UnknownPipeline { pipeline_id });
+        }
+        Ok(())
+    }
+
+    async fn list_pipelines(&self, tenant_id: TenantId) -> Result<Vec<Pipeline>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {PIPELINE_COLUMNS}, {RUNTIME_STATE_COLUMNS}
+            FROM pipeline p
+            INNER JOIN pipeline_runtime_state rt on p.id = rt.id
+            WHERE p.tenant_id = ?1 AND p.deleted_at IS NULL"
+        ))?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_pipeline(&conn, row)?);
+        }
+        Ok(result)
+    }
+
+    async fn new_connector(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        config: &ConnectorConfig,
+    ) -> Result<ConnectorId, DBError> {
+        debug!("new_connector {name} {description} {config:?}");
+        let conn = self.conn();
+        check_id_is_new(&conn, "connector", "connector_pkey", id)?;
+        conn.prepare_cached(
+            "INSERT INTO connector (id, name, description, config, tenant_id) VALUES(?1, ?2, ?3, ?4, ?5)",
+        )?
+        .execute(params![
+            id.to_string(),
+            name,
+            description,
+            config.to_yaml(),
+            tenant_id.0.to_string()
+        ])
+        .map_err(|e| maybe_tenant_id_foreign_key_constraint_err(e, tenant_id))?;
+        Ok(ConnectorId(id))
+    }
+
+    async fn list_connectors(&self, tenant_id: TenantId) -> Result<Vec<ConnectorDescr>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, config FROM connector WHERE tenant_id = ?1 AND deleted_at IS NULL",
+        )?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_connector(row)?);
+        }
+        Ok(result)
+    }
+
+    async fn get_connector_by_id(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<ConnectorDescr, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, config FROM connector WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NULL",
+        )?;
+        let mut rows = stmt.query(params![connector_id.0.to_string(), tenant_id.0.to_string()])?;
+        let row = rows
+            .next()?
+            .ok_or(DBError::UnknownConnector { connector_id })?;
+        read_connector(row)
+    }
+
+    async fn get_connector_by_name(
+        &self,
+        tenant_id: TenantId,
+        name: String,
+    ) -> Result<ConnectorDescr, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, config FROM connector WHERE name = ?1 AND tenant_id = ?2 AND deleted_at IS NULL",
+        )?;
+        let mut rows = stmt.query(params![name, tenant_id.0.to_string()])?;
+        let row = rows.next()?.ok_or(DBError::UnknownName { name })?;
+        read_connector(row)
+    }
+
+    async fn update_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+        connector_name: &str,
+        description: &str,
+        config: &Option<ConnectorConfig>,
+    ) -> Result<(), DBError> {
+        let descr = self.get_connector_by_id(tenant_id, connector_id).await?;
+        let config = config.clone().unwrap_or(descr.config);
+        self.conn()
+            .prepare_cached(
+                "UPDATE connector SET name = ?1, description = ?2, config = ?3 WHERE id = ?4",
+            )?
+            .execute(params![
+                connector_name,
+                description,
+                config.to_yaml(),
+                connector_id.0.to_string()
+            ])
+            .map_err(maybe_unique_violation)?;
+
+        Ok(())
+    }
+
+    async fn delete_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<(), DBError> {
+        // Attachments of the connector are kept, but hidden from pipelines
+        // by the `live_attached_connector` view until the connector is
+        // restored.
+        let res = self
+            .conn()
+            .prepare_cached(
+                "UPDATE connector SET deleted_at = ?3
+                WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NULL",
+            )?
+            .execute(params![
+                connector_id.0.to_string(),
+                tenant_id.0.to_string(),
+                Utc::now().timestamp()
+            ])?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownConnector { connector_id })
+        }
+    }
+
+    async fn restore_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached(
+                "UPDATE connector SET deleted_at = NULL
+                WHERE id = ?1 AND tenant_id = ?2 AND deleted_at IS NOT NULL",
+            )?
+            .execute(params![connector_id.0.to_string(), tenant_id.0.to_string()])
+            .map_err(maybe_unique_violation)?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownConnector { connector_id })
+        }
+    }
+
+    async fn list_deleted_connectors(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedConnector>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, config, deleted_at FROM connector WHERE tenant_id = ?1 AND deleted_at IS NOT NULL",
+        )?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(DeletedConnector {
+                descriptor: read_connector(row)?,
+                deleted_at: convert_bigint_to_time(row.get(4)?)?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64, DBError> {
+        let mut conn = self.conn();
+        let txn = conn.transaction()?;
+        let deleted_before = deleted_before.timestamp();
+        let pipelines = txn
+            .prepare_cached("DELETE FROM pipeline WHERE deleted_at < ?1")?
+            .execute(params![deleted_before])?;
+        let connectors = txn
+            .prepare_cached("DELETE FROM connector WHERE deleted_at < ?1")?
+            .execute(params![deleted_before])?;
+        txn.commit()?;
+
+        Ok((pipelines + connectors) as u64)
+    }
+
+    async fn store_api_key_hash(
+        &self,
+        tenant_id: TenantId,
+        key: String,
+        scopes: Vec<ApiPermission>,
+    ) -> Result<(), DBError> {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(key.as_bytes());
+        let hash = openssl::base64::encode_block(&hasher.finish());
+        let scopes: Vec<&str> = scopes
+            .iter()
+            .map(|scope| match scope {
+                ApiPermission::Read => "read",
+                ApiPermission::Write => "write",
+            })
+            .collect();
+        let res = self
+            .conn()
+            .prepare_cached("INSERT INTO api_key (hash, tenant_id, scopes) VALUES (?1, ?2, ?3)")?
+            .execute(params![hash, tenant_id.0.to_string(), to_json(&scopes)?])
+            .map_err(|e| maybe_tenant_id_foreign_key_constraint_err(e, tenant_id))?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::duplicate_key())
+        }
+    }
+
+    async fn validate_api_key(
+        &self,
+        api_key: String,
+    ) -> Result<(TenantId, Vec<ApiPermission>), DBError> {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(api_key.as_bytes());
+        let hash = openssl::base64::encode_block(&hasher.finish());
+        let (tenant_id, scopes): (String, String) = self
+            .conn()
+            .prepare_cached("SELECT tenant_id, scopes FROM api_key WHERE hash = ?1")?
+            .query_row(params![hash], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|_| DBError::InvalidKey)?;
+        let scopes: Vec<String> = from_json(&scopes)?;
+        let scopes = scopes
+            .iter()
+            .map(|s| {
+                if s == "read" {
+                    ApiPermission::Read
+                } else {
+                    ApiPermission::Write
+                }
+            })
+            .collect();
+        Ok((TenantId(parse_uuid(&tenant_id)?), scopes))
+    }
+
+    async fn get_or_create_tenant_id(
+        &self,
+        tenant_name: String,
+        provider: String,
+    ) -> Result<TenantId, DBError> {
+        let tenant_id: Option<String> = self
+            .conn()
+            .prepare_cached("SELECT id FROM tenant WHERE tenant = ?1 AND provider = ?2")?
+            .query_row(params![tenant_name, provider], |row| row.get(0))
+            .optional()?;
+        match tenant_id {
+            Some(tenant_id) => Ok(TenantId(parse_uuid(&tenant_id)?)),
+            None => {
+                self.create_tenant_if_not_exists(Uuid::now_v7(), tenant_name, provider)
+                    .await
+            }
+        }
+    }
+
+    async fn create_tenant_if_not_exists(
+        &self,
+        tenant_id: Uuid,
+        tenant_name: String,
+        provider: String,
+    ) -> Result<TenantId, DBError> {
+        // See the Postgres implementation for why we update on conflict.
+        let tenant_id: String = self
+            .conn()
+            .prepare_cached("INSERT INTO tenant (id, tenant, provider) VALUES (?1, ?2, ?3) ON CONFLICT (tenant, provider) DO UPDATE SET tenant = excluded.tenant, provider = excluded.provider RETURNING id")?
+            .query_row(params![tenant_id.to_string(), tenant_name, provider], |row| {
+                row.get(0)
+            })?;
+        Ok(TenantId(parse_uuid(&tenant_id)?))
+    }
+
+    async fn create_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+        url: String,
+    ) -> Result<(), DBError> {
+        self.conn()
+            .prepare_cached("INSERT INTO compiled_binary VALUES (?1, ?2, ?3)")?
+            .execute(params![program_id.0.to_string(), version.0, url])?;
+        Ok(())
+    }
+
+    async fn get_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<Option<String>, DBError> {
+        Ok(self
+            .conn()
+            .prepare_cached(
+                "SELECT url FROM compiled_binary WHERE program_id = ?1 AND version = ?2",
+            )?
+            .query_row(params![program_id.0.to_string(), version.0], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    async fn delete_compiled_binary_ref(
+        &self,
+        program_id: ProgramId,
+        version: Version,
+    ) -> Result<(), DBError> {
+        self.conn()
+            .prepare_cached("DELETE FROM compiled_binary WHERE program_id = ?1 AND version = ?2")?
+            .execute(params![program_id.0.to_string(), version.0])?;
+        Ok(())
+    }
+
+    async fn record_tenant_usage(
+        &self,
+        tenant_id: TenantId,
+        usage: &TenantUsage,
+    ) -> Result<(), DBError> {
+        self.conn()
+            .prepare_cached(
+                "INSERT INTO tenant_usage (tenant_id, compile_time_ms, run_time_ms, ingress_bytes, egress_bytes)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (tenant_id) DO UPDATE SET
+                    compile_time_ms = tenant_usage.compile_time_ms + EXCLUDED.compile_time_ms,
+                    run_time_ms = tenant_usage.run_time_ms + EXCLUDED.run_time_ms,
+                    ingress_bytes = tenant_usage.ingress_bytes + EXCLUDED.ingress_bytes,
+                    egress_bytes = tenant_usage.egress_bytes + EXCLUDED.egress_bytes",
+            )?
+            .execute(params![
+                tenant_id.0.to_string(),
+                usage.compile_time_ms,
+                usage.run_time_ms,
+                usage.ingress_bytes,
+                usage.egress_bytes,
+            ])?;
+        Ok(())
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: TenantId) -> Result<TenantUsage, DBError> {
+        Ok(self
+            .conn()
+            .prepare_cached(
+                "SELECT compile_time_ms, run_time_ms, ingress_bytes, egress_bytes
+                FROM tenant_usage WHERE tenant_id = ?1",
+            )?
+            .query_row(params![tenant_id.0.to_string()], |row| {
+                Ok(TenantUsage {
+                    compile_time_ms: row.get(0)?,
+                    run_time_ms: row.get(1)?,
+                    ingress_bytes: row.get(2)?,
+                    egress_bytes: row.get(3)?,
+                })
+            })
+            .optional()?
+            .unwrap_or_default())
+    }
+
+    async fn new_webhook(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        retry_policy: &WebhookRetryPolicy,
+    ) -> Result<WebhookId, DBError> {
+        let events: Vec<&str> = events.iter().map(WebhookEvent::as_str).collect();
+        let conn = self.conn();
+        check_id_is_new(&conn, "webhook", "webhook_pkey", id)?;
+        conn.prepare_cached(
+            "INSERT INTO webhook (id, tenant_id, url, secret, events, max_attempts, initial_retry_delay_ms) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?
+        .execute(params![
+            id.to_string(),
+            tenant_id.0.to_string(),
+            url,
+            secret,
+            to_json(&events)?,
+            retry_policy.max_attempts,
+            retry_policy.initial_delay_ms as i64,
+        ])
+        .map_err(|e| maybe_tenant_id_foreign_key_constraint_err(e, tenant_id))?;
+        Ok(WebhookId(id))
+    }
+
+    async fn list_webhooks(&self, tenant_id: TenantId) -> Result<Vec<WebhookDescr>, DBError> {
+        Ok(self
+            .list_webhook_subscriptions(tenant_id)
+            .await?
+            .into_iter()
+            .map(|s| s.descriptor)
+            .collect())
+    }
+
+    async fn delete_webhook(
+        &self,
+        tenant_id: TenantId,
+        webhook_id: WebhookId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached("DELETE FROM webhook WHERE id = ?1 AND tenant_id = ?2")?
+            .execute(params![webhook_id.0.to_string(), tenant_id.0.to_string()])?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownWebhook { webhook_id })
+        }
+    }
+
+    async fn list_webhook_subscriptions(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<WebhookSubscription>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, url, secret, events, max_attempts, initial_retry_delay_ms FROM webhook WHERE tenant_id = ?1 ORDER BY id",
+        )?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let events: Vec<String> = from_json(&row.get::<_, String>(3)?)?;
+            let events = events
+                .iter()
+                .map(|e| WebhookEvent::try_from(e.as_str()))
+                .collect::<Result<Vec<_>, _>>()?;
+            result.push(WebhookSubscription {
+                descriptor: WebhookDescr {
+                    webhook_id: WebhookId(get_uuid(row, 0)?),
+                    url: row.get(1)?,
+                    events,
+                    retry_policy: WebhookRetryPolicy {
+                        max_attempts: row.get(4)?,
+                        initial_delay_ms: row.get::<_, i64>(5)? as u64,
+                    },
+                },
+                secret: row.get(2)?,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn new_deployment(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        pipelines: &[PipelineId],
+        config: &Option<RuntimeConfig>,
+    ) -> Result<DeploymentId, DBError> {
+        let pipelines: Vec<Uuid> = pipelines.iter().map(|p| p.0).collect();
+        let config = config.as_ref().map(RuntimeConfig::to_yaml);
+        let conn = self.conn();
+        check_id_is_new(&conn, "deployment", "deployment_pkey", id)?;
+        conn.prepare_cached(
+            "INSERT INTO deployment (id, tenant_id, name, description, pipelines, config)
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+        )?
+        .execute(params![
+            id.to_string(),
+            tenant_id.0.to_string(),
+            name,
+            description,
+            to_json(&pipelines)?,
+            config,
+        ])
+        .map_err(|e| maybe_tenant_id_foreign_key_constraint_err(e, tenant_id))?;
+        Ok(DeploymentId(id))
+    }
+
+    async fn list_deployments(&self, tenant_id: TenantId) -> Result<Vec<DeploymentDescr>, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, pipelines, config FROM deployment
+            WHERE tenant_id = ?1 ORDER BY id",
+        )?;
+        let mut rows = stmt.query(params![tenant_id.0.to_string()])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_deployment(row)?);
+        }
+        Ok(result)
+    }
+
+    async fn get_deployment_by_id(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentDescr, DBError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, description, pipelines, config FROM deployment
+            WHERE id = ?1 AND tenant_id = ?2",
+        )?;
+        let mut rows = stmt.query(params![
+            deployment_id.0.to_string(),
+            tenant_id.0.to_string()
+        ])?;
+        let row = rows
+            .next()?
+            .ok_or(DBError::UnknownDeployment { deployment_id })?;
+        read_deployment(row)
+    }
+
+    async fn delete_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), DBError> {
+        let res = self
+            .conn()
+            .prepare_cached("DELETE FROM deployment WHERE id = ?1 AND tenant_id = ?2")?
+            .execute(params![
+                deployment_id.0.to_string(),
+                tenant_id.0.to_string()
+            ])?;
+        if res > 0 {
+            Ok(())
+        } else {
+            Err(DBError::UnknownDeployment { deployment_id })
+        }
+    }
+
+    async fn check_connection(&self) -> Result<(), DBError> {
+        self.conn().execute_batch("SELECT 1")?;
+        Ok(())
+    }
+
+    async fn record_heartbeat(&self, service: &str, now: DateTime<Utc>) -> Result<(), DBError> {
+        self.conn()
+            .prepare_cached(
+                "INSERT INTO service_heartbeat (service, last_seen) VALUES (?1, ?2)
+                ON CONFLICT (service) DO UPDATE SET last_seen = EXCLUDED.last_seen",
+            )?
+            .execute(params![service, now.timestamp()])?;
+        Ok(())
+    }
+
+    async fn get_heartbeat(&self, service: &str) -> Result<Option<DateTime<Utc>>, DBError> {
+        self.conn()
+            .prepare_cached("SELECT last_seen FROM service_heartbeat WHERE service = ?1")?
+            .query_row(params![service], |row| row.get(0))
+            .optional()?
+            .map(convert_bigint_to_time)
+            .transpose()
+    }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, DBError> {
+    Uuid::parse_str(value)
+        .map_err(|e| DBError::invalid_data(format!("Error parsing UUID '{value}': {e}")))
+}
+
+/// Read a UUID stored as a string.
+fn get_uuid(row: &Row, idx: usize) -> Result<Uuid, DBError> {
+    parse_uuid(&row.get::<_, String>(idx)?)
+}
+
+fn get_optional_uuid(row: &Row, idx: usize) -> Result<Option<Uuid>, DBError> {
+    row.get::<_, Option<String>>(idx)?
+        .map(|value| parse_uuid(&value))
+        .transpose()
+}
+
+/// Encode an array column.
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, DBError> {
+    serde_json::to_string(value)
+        .map_err(|e| DBError::invalid_data(format!("Error serializing array: {e}")))
+}
+
+/// Decode an array column.
+fn from_json<T: serde::de::DeserializeOwned>(value: &str) -> Result<T, DBError> {
+    serde_json::from_str(value)
+        .map_err(|e| DBError::invalid_data(format!("Error parsing array '{value}': {e}")))
+}
+
+fn convert_micros_to_time(micros: i64) -> Result<DateTime<Utc>, DBError> {
+    let naive = NaiveDateTime::from_timestamp_micros(micros).ok_or_else(|| {
+        DBError::invalid_data(format!(
+            "Invalid timestamp in 'program.status_since' column: {micros}"
+        ))
+    })?;
+    Ok(DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc))
+}
+
+/// Decode a row that starts with [`PROGRAM_COLUMNS`].
+fn read_program(row: &Row, with_code: bool) -> Result<(TenantId, ProgramDescr), DBError> {
+    let status: Option<String> = row.get(4)?;
+    let status = ProgramStatus::from_columns(status.as_deref(), row.get(5)?)?;
+    let schema: Option<ProgramSchema> = row
+        .get::<_, Option<String>>(6)?
+        .map(|s| serde_json::from_str(&s))
+        .transpose()
+        .map_err(|e| DBError::invalid_data(format!("Error parsing program schema: {e}")))?;
+    let code = if with_code { row.get(7)? } else { None };
+
+    Ok((
+        TenantId(get_uuid(row, 9)?),
+        ProgramDescr {
+            program_id: ProgramId(get_uuid(row, 0)?),
+            name: row.get(1)?,
+            description: row.get(2)?,
+            version: Version(row.get(3)?),
+            status,
+            warnings: warnings_from_column(row.get(8)?)?,
+            schema,
+            code,
+        },
+    ))
+}
+
+/// Decode a row that starts with [`PIPELINE_COLUMNS`] and look up its
+/// attached connectors, either the live ones or, if `revision` is
+/// specified, the ones committed in that revision.
+fn read_pipeline_descr(
+    conn: &Connection,
+    row: &Row,
+    revision: Option<Revision>,
+) -> Result<PipelineDescr, DBError> {
+    let pipeline_id = PipelineId(get_uuid(row, 0)?);
+    Ok(PipelineDescr {
+        pipeline_id,
+        program_id: get_optional_uuid(row, 5)?.map(ProgramId),
+        version: Version(row.get(1)?),
+        name: row.get(2)?,
+        description: row.get(3)?,
+        config: RuntimeConfig::from_yaml(&row.get::<_, String>(4)?),
+        attached_connectors: attached_connectors(conn, pipeline_id, revision)?,
+    })
+}
+
+/// Decode the [`RUNTIME_STATE_COLUMNS`] that start at column `offset`.
+fn read_runtime_state(
+    pipeline_id: PipelineId,
+    row: &Row,
+    offset: usize,
+) -> Result<PipelineRuntimeState, DBError> {
+    Ok(PipelineRuntimeState {
+        location: row.get::<_, Option<String>>(offset)?.unwrap_or_default(),
+        desired_status: row.get::<_, String>(offset + 1)?.try_into()?,
+        current_status: row.get::<_, String>(offset + 2)?.try_into()?,
+        status_since: convert_bigint_to_time(row.get(offset + 3)?)?,
+        error: row
+            .get::<_, Option<String>>(offset + 4)?
+            .map(|s| deserialize_error_response(pipeline_id, &s))
+            .transpose()?,
+        created: convert_bigint_to_time(row.get(offset + 5)?)?,
+        tls_certificate: row.get(offset + 6)?,
+        auth_token: row.get(offset + 7)?,
+    })
+}
+
+/// Decode a row with the [`PIPELINE_COLUMNS`] followed by the
+/// [`RUNTIME_STATE_COLUMNS`].
+fn read_pipeline(conn: &Connection, row: &Row) -> Result<Pipeline, DBError> {
+    let descriptor = read_pipeline_descr(conn, row, None)?;
+    let state = read_runtime_state(descriptor.pipeline_id, row, 6)?;
+    Ok(Pipeline { descriptor, state })
+}
+
+fn read_connector(row: &Row) -> Result<ConnectorDescr, DBError> {
+    Ok(ConnectorDescr {
+        connector_id: ConnectorId(get_uuid(row, 0)?),
+        name: row.get(1)?,
+        description: row.get(2)?,
+        config: ConnectorConfig::from_yaml_str(&row.get::<_, String>(3)?),
+    })
+}
+
+fn read_deployment(row: &Row) -> Result<DeploymentDescr, DBError> {
+    let pipelines: Vec<Uuid> = from_json(&row.get::<_, String>(3)?)?;
+    let config: Option<String> = row.get(4)?;
+    Ok(DeploymentDescr {
+        deployment_id: DeploymentId(get_uuid(row, 0)?),
+        name: row.get(1)?,
+        description: row.get(2)?,
+        pipelines: pipelines.into_iter().map(PipelineId).collect(),
+        config: config.as_deref().map(RuntimeConfig::from_yaml),
+    })
+}
+
+/// Retrieve the connectors attached to a pipeline.
+///
+/// Returns the live attached connectors, or the ones committed in `revision`.
+fn attached_connectors(
+    conn: &Connection,
+    pipeline_id: PipelineId,
+    revision: Option<Revision>,
+) -> Result<Vec<AttachedConnector>, DBError> {
+    let mut stmt;
+    let mut rows = match revision {
+        None => {
+            stmt = conn.prepare_cached(
+                "SELECT name, connector_id, config, is_input FROM live_attached_connector
+                WHERE pipeline_id = ?1 ORDER BY name",
+            )?;
+            stmt.query(params![pipeline_id.0.to_string()])?
+        }
+        Some(revision) => {
+            stmt = conn.prepare_cached(
+                "SELECT name, connector_id, config, is_input FROM attached_connector_history
+                WHERE pipeline_id = ?1 AND revision = ?2 ORDER BY name",
+            )?;
+            stmt.query(params![pipeline_id.0.to_string(), revision.0.to_string()])?
+        }
+    };
+
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        result.push(AttachedConnector {
+            name: row.get(0)?,
+            connector_id: ConnectorId(get_uuid(row, 1)?),
+            relation_name: row.get(2)?,
+            is_input: row.get(3)?,
+        });
+    }
+    Ok(result)
+}
+
+fn program_if_exists(
+    conn: &Connection,
+    tenant_id: TenantId,
+    program_id: ProgramId,
+    with_code: bool,
+) -> Result<Option<ProgramDescr>, DBError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {PROGRAM_COLUMNS} FROM program WHERE id = ?1 AND tenant_id = ?2"
+    ))?;
+    let mut rows = stmt.query(params![program_id.0.to_string(), tenant_id.0.to_string()])?;
+    rows.next()?
+        .map(|row| Ok(read_program(row, with_code)?.1))
+        .transpose()
+}
+
+fn pipeline_descr_by_id(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+) -> Result<PipelineDescr, DBError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {PIPELINE_COLUMNS} FROM pipeline p
+        WHERE p.id = ?1 AND p.tenant_id = ?2 AND p.deleted_at IS NULL"
+    ))?;
+    let mut rows = stmt.query(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+    let row = rows
+        .next()?
+        .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+    read_pipeline_descr(conn, row, None)
+}
+
+/// Retrieve all connectors referenced by a pipeline.
+fn connectors_for_pipeline_id(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+) -> Result<Vec<ConnectorDescr>, DBError> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT c.id, c.name, c.description, c.config
+        FROM connector c, attached_connector ac
+        WHERE ac.pipeline_id = ?1
+        AND ac.connector_id = c.id
+        AND c.tenant_id = ?2
+        AND c.deleted_at IS NULL",
+    )?;
+    let mut rows = stmt.query(params![pipeline_id.0.to_string(), tenant_id.0.to_string()])?;
+
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        result.push(read_connector(row)?);
+    }
+    Ok(result)
+}
+
+/// See [`PostgresDB::pipeline_is_committable`](super::PostgresDB::pipeline_is_committable).
+fn pipeline_is_committable(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+) -> Result<(PipelineDescr, ProgramDescr, Vec<ConnectorDescr>), DBError> {
+    let pipeline = pipeline_descr_by_id(conn, tenant_id, pipeline_id)?;
+    let program_id = pipeline.program_id.ok_or(DBError::ProgramNotSet)?;
+    let program = program_if_exists(conn, tenant_id, program_id, true)?
+        .ok_or(DBError::UnknownProgram { program_id })?;
+    let connectors = connectors_for_pipeline_id(conn, tenant_id, pipeline_id)?;
+    // Check that this configuration forms a valid snapshot
+    PipelineRevision::validate(&pipeline, &connectors, &program)?;
+    Ok((pipeline, program, connectors))
+}
+
+fn committed_program_by_id(
+    conn: &Connection,
+    tenant_id: TenantId,
+    program_id: ProgramId,
+    revision: Revision,
+) -> Result<ProgramDescr, DBError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {PROGRAM_COLUMNS} FROM program_history
+        WHERE id = ?1 AND tenant_id = ?2 AND revision = ?3"
+    ))?;
+    let mut rows = stmt.query(params![
+        program_id.0.to_string(),
+        tenant_id.0.to_string(),
+        revision.0.to_string()
+    ])?;
+    let row = rows.next()?.ok_or(DBError::UnknownProgram { program_id })?;
+    Ok(read_program(row, true)?.1)
+}
+
+fn committed_pipeline_by_id(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+    revision: Revision,
+) -> Result<PipelineDescr, DBError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {PIPELINE_COLUMNS} FROM pipeline_history p
+        WHERE p.id = ?1 AND p.tenant_id = ?2 AND p.revision = ?3"
+    ))?;
+    let mut rows = stmt.query(params![
+        pipeline_id.0.to_string(),
+        tenant_id.0.to_string(),
+        revision.0.to_string()
+    ])?;
+    let row = rows
+        .next()?
+        .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+    read_pipeline_descr(conn, row, Some(revision))
+}
+
+fn committed_connectors_by_id(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+    revision: Revision,
+) -> Result<Vec<ConnectorDescr>, DBError> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT ch.id, ch.name, ch.description, ch.config
+        FROM connector_history ch, attached_connector_history ach
+        WHERE ach.pipeline_id = ?1 AND ach.connector_id = ch.id AND ch.tenant_id = ?2 AND ch.revision = ?3",
+    )?;
+    let mut rows = stmt.query(params![
+        pipeline_id.0.to_string(),
+        tenant_id.0.to_string(),
+        revision.0.to_string()
+    ])?;
+
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        result.push(read_connector(row)?);
+    }
+    Ok(result)
+}
+
+/// Attach connector to the pipeline.
+///
+/// # Precondition
+/// - A valid pipeline for `pipeline_id` must exist.
+fn attach_connector(
+    conn: &Connection,
+    tenant_id: TenantId,
+    pipeline_id: PipelineId,
+    ac: &AttachedConnector,
+) -> Result<(), DBError> {
+    let rows = conn
+        .prepare_cached(
+            "INSERT INTO attached_connector (name, pipeline_id, connector_id, is_input, config, tenant_id)
+            SELECT ?2, ?3, id, ?5, ?6, tenant_id
+            FROM connector
+            WHERE tenant_id = ?1 AND id = ?4 AND deleted_at IS NULL",
+        )?
+        .execute(params![
+            tenant_id.0.to_string(),
+            ac.name,
+            pipeline_id.0.to_string(),
+            ac.connector_id.0.to_string(),
+            ac.is_input,
+            ac.relation_name,
+        ])
+        .map_err(maybe_unique_violation)?;
+    if rows == 0 {
+        Err(DBError::UnknownConnector {
+            connector_id: ac.connector_id,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Fail with the same error as a violation of the primary key of `table`.
+///
+/// SQLite doesn't specify in which order it checks the constraints of a
+/// table, so we check the primary key explicitly to report a duplicate id
+/// before a duplicate name, like Postgres.
+fn check_id_is_new(
+    conn: &Connection,
+    table: &str,
+    constraint: &'static str,
+    id: Uuid,
+) -> Result<(), DBError> {
+    let exists = conn
+        .prepare_cached(&format!("SELECT 1 FROM {table} WHERE id = ?1"))?
+        .exists(params![id.to_string()])?;
+    if exists {
+        Err(DBError::unique_key_violation(constraint))
+    } else {
+        Ok(())
+    }
+}
+
+fn is_foreign_key_violation(err: &rusqlite::Error) -> bool {
+    matches!(err, rusqlite::Error::SqliteFailure(e, _)
+        if e.extended_code == ffi::SQLITE_CONSTRAINT_FOREIGNKEY)
+}
+
+/// Helper to convert a SQLite error into a `DBError` if the underlying
+/// low-level error thrown by the database matches.
+fn maybe_unique_violation(err: rusqlite::Error) -> DBError {
+    if let rusqlite::Error::SqliteFailure(e, message) = &err {
+        if e.code == ErrorCode::ConstraintViolation {
+            match e.extended_code {
+                ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+                    // The message has the form "UNIQUE constraint failed: <table>.<column>".
+                    let table = message
+                        .as_deref()
+                        .and_then(|m| m.rsplit(' ').next())
+                        .and_then(|column| column.split('.').next());
+                    return match table {
+                        Some("program") => DBError::unique_key_violation("program_pkey"),
+                        Some("connector") => DBError::unique_key_violation("connector_pkey"),
+                        Some("pipeline") => DBError::unique_key_violation("pipeline_pkey"),
+                        Some("webhook") => DBError::unique_key_violation("webhook_pkey"),
+                        Some("deployment") => DBError::unique_key_violation("deployment_pkey"),
+                        Some("api_key") => DBError::duplicate_key(),
+                        _ => DBError::DuplicateName,
+                    };
+                }
+                ffi::SQLITE_CONSTRAINT_UNIQUE => return DBError::DuplicateName,
+                _ => {}
+            }
+        }
+    }
+    err.into()
+}
+
+/// Like [`maybe_unique_violation`], but also converts a foreign key
+/// violation into an `UnknownProgram` error, for statements that write
+/// `pipeline.program_id`.
+fn maybe_program_id_not_found_foreign_key_constraint_err(
+    err: rusqlite::Error,
+    program_id: Option<ProgramId>,
+) -> DBError {
+    if is_foreign_key_violation(&err) {
+        if let Some(program_id) = program_id {
+            return DBError::UnknownProgram { program_id };
+        }
+    }
+    maybe_unique_violation(err)
+}
+
+/// Like [`maybe_unique_violation`], but also converts a foreign key
+/// violation into an `UnknownTenant` error, for inserts into tables whose
+/// only foreign key is `tenant_id`.
+fn maybe_tenant_id_foreign_key_constraint_err(
+    err: rusqlite::Error,
+    tenant_id: TenantId,
+) -> DBError {
+    if is_foreign_key_violation(&err) {
+        DBError::UnknownTenant { tenant_id }
+    } else {
+        maybe_unique_violation(err)
+    }
+}
@@ -1,11 +1,15 @@
 use super::{
-    ApiPermission, AttachedConnector, ConnectorDescr, ConnectorId, DBError, Pipeline,
-    PipelineDescr, PipelineId, PipelineRevision, PipelineRuntimeState, PipelineStatus,
-    ProgramDescr, ProgramId, ProgramSchema, Revision, Version,
+    ApiPermission, AttachedConnector, CompilationJob, ConnectorDescr, ConnectorId, DBError,
+    DeletedConnector, DeletedPipeline, DeploymentDescr, DeploymentId, Pipeline, PipelineDescr,
+    PipelineId, PipelineRevision, PipelineRuntimeState, PipelineStatus, ProgramDescr, ProgramId,
+    ProgramSchema, Revision, TenantUsage, Version, WebhookDescr, WebhookEvent, WebhookId,
+    WebhookRetryPolicy, WebhookSubscription,
 };
 use crate::api::ProgramStatus;
 use crate::auth::TenantId;
+use crate::compiler::SqlCompilerMessage;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use dbsp_adapters::{ConnectorConfig, RuntimeConfig};
 use uuid::Uuid;
 
@@ -191,6 +195,18 @@ pub(crate) trait Storage {
         schema: ProgramSchema,
     ) -> Result<(), DBError>;
 
+    /// Update the warnings reported by the SQL compiler for the current
+    /// version of the program.
+    ///
+    /// Warnings are cleared when the program is modified or queued for
+    /// compilation.
+    async fn set_program_warnings(
+        &self,
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        warnings: Vec<SqlCompilerMessage>,
+    ) -> Result<(), DBError>;
+
     /// Delete program from the database.
     ///
     /// This will delete all program configs and pipelines.
@@ -214,6 +230,18 @@ pub(crate) trait Storage {
     /// if there are no pending programs in the DB.
     async fn next_job(&self) -> Result<Option<(TenantId, ProgramId, Version)>, DBError>;
 
+    /// Number of programs waiting to be compiled.
+    async fn count_pending_programs(&self) -> Result<u64, DBError>;
+
+    /// Programs that are queued for compilation or are being compiled, in
+    /// the order in which they entered their current phase.
+    async fn list_compilation_jobs(&self) -> Result<Vec<CompilationJob>, DBError>;
+
+    /// Number of pipelines in each status, excluding pipelines in the trash.
+    ///
+    /// Statuses without pipelines are omitted.
+    async fn count_pipelines_by_status(&self) -> Result<Vec<(PipelineStatus, u64)>, DBError>;
+
     /// Version the configuration for a pipeline.
     ///
     /// Returns the revision number for that snapshot.
@@ -275,13 +303,37 @@ pub(crate) trait Storage {
         name: &str,
     ) -> Result<bool, DBError>;
 
-    /// Delete `pipeline` from the DB.
+    /// Move `pipeline` to the trash.
+    ///
+    /// Pipelines in the trash are hidden from all lookups except
+    /// [`Self::get_pipeline_runtime_state`] and [`Self::all_pipelines`],
+    /// which are used to manage the pipeline's runtime.  Their names can be
+    /// reused by new pipelines.
+    ///
+    /// Returns `false` if the pipeline does not exist or is already in the
+    /// trash.
     async fn delete_pipeline(
         &self,
         tenant_id: TenantId,
         pipeline_id: PipelineId,
     ) -> Result<bool, DBError>;
 
+    /// Move a pipeline out of the trash.
+    ///
+    /// Fails with [`DBError::DuplicateName`] if another pipeline has taken
+    /// its name.
+    async fn restore_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+    ) -> Result<(), DBError>;
+
+    /// List pipelines in the trash.
+    async fn list_deleted_pipelines(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedPipeline>, DBError>;
+
     /// Retrieve pipeline for a given id.
     async fn get_pipeline_descr_by_id(
         &self,
@@ -369,15 +421,38 @@ pub(crate) trait Storage {
         config: &Option<ConnectorConfig>,
     ) -> Result<(), DBError>;
 
-    /// Delete connector from the database.
+    /// Move connector to the trash.
     ///
-    /// This will delete all connector configs and pipelines.
+    /// The connector remains attached to pipelines, but its attachments are
+    /// hidden from pipeline lookups until the connector is restored.
     async fn delete_connector(
         &self,
         tenant_id: TenantId,
         connector_id: ConnectorId,
     ) -> Result<(), DBError>;
 
+    /// Move a connector out of the trash.
+    ///
+    /// Fails with [`DBError::DuplicateName`] if another connector has taken
+    /// its name.
+    async fn restore_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> Result<(), DBError>;
+
+    /// List connectors in the trash.
+    async fn list_deleted_connectors(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<DeletedConnector>, DBError>;
+
+    /// Permanently delete all pipelines and connectors that were moved to
+    /// the trash before `deleted_before`.
+    ///
+    /// Returns the number of deleted objects.
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64, DBError>;
+
     /// Persist a hash of API key in the database
     async fn store_api_key_hash(
         &self,
@@ -430,4 +505,84 @@ pub(crate) trait Storage {
         program_id: ProgramId,
         version: Version,
     ) -> Result<(), DBError>;
+
+    /// Add `usage` to the resource usage counters of a tenant.
+    async fn record_tenant_usage(
+        &self,
+        tenant_id: TenantId,
+        usage: &TenantUsage,
+    ) -> Result<(), DBError>;
+
+    /// Retrieve the cumulative resource usage of a tenant.
+    ///
+    /// Returns all-zero counters for tenants that haven't consumed
+    /// any resources yet.
+    async fn get_tenant_usage(&self, tenant_id: TenantId) -> Result<TenantUsage, DBError>;
+
+    /// Register a webhook that is notified about the program and pipeline
+    /// lifecycle `events` of the tenant.  Failed deliveries are retried
+    /// according to `retry_policy`.
+    async fn new_webhook(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        retry_policy: &WebhookRetryPolicy,
+    ) -> Result<WebhookId, DBError>;
+
+    /// List the webhooks of a tenant.
+    async fn list_webhooks(&self, tenant_id: TenantId) -> Result<Vec<WebhookDescr>, DBError>;
+
+    /// Delete a webhook.
+    async fn delete_webhook(
+        &self,
+        tenant_id: TenantId,
+        webhook_id: WebhookId,
+    ) -> Result<(), DBError>;
+
+    /// List the webhooks of a tenant along with their signing secrets.
+    async fn list_webhook_subscriptions(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Vec<WebhookSubscription>, DBError>;
+
+    /// Create a deployment of `pipelines`, which are started in the given
+    /// order and share the runtime `config`, if any.
+    async fn new_deployment(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        pipelines: &[PipelineId],
+        config: &Option<RuntimeConfig>,
+    ) -> Result<DeploymentId, DBError>;
+
+    /// List the deployments of a tenant.
+    async fn list_deployments(&self, tenant_id: TenantId) -> Result<Vec<DeploymentDescr>, DBError>;
+
+    /// Retrieve a deployment by id.
+    async fn get_deployment_by_id(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentDescr, DBError>;
+
+    /// Delete a deployment.  Its pipelines are not affected.
+    async fn delete_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), DBError>;
+
+    /// Check that the database is reachable.
+    async fn check_connection(&self) -> Result<(), DBError>;
+
+    /// Record that `service` is alive at time `now`.
+    async fn record_heartbeat(&self, service: &str, now: DateTime<Utc>) -> Result<(), DBError>;
+
+    /// Retrieve the time of the last heartbeat of `service`, if any.
+    async fn get_heartbeat(&self, service: &str) -> Result<Option<DateTime<Utc>>, DBError>;
 }
@@ -1,10 +1,15 @@
 use super::{
-    storage::Storage, AttachedConnector, ConnectorDescr, ConnectorId, DBError, PipelineId,
-    PipelineRevision, PipelineStatus, ProgramDescr, ProgramId, ProgramStatus, ProjectDB, Revision,
-    Version,
+    storage::Storage, AttachedConnector, CompilationJob, ConnectorDescr, ConnectorId, DBError,
+    PipelineId, PipelineRevision, PipelineStatus, PostgresDB, ProgramDescr, ProgramId,
+    ProgramStatus, ProjectDB, Revision, Version,
+};
+use super::{
+    ApiPermission, DeletedConnector, DeletedPipeline, DeploymentDescr, DeploymentId, Pipeline,
+    PipelineDescr, PipelineRuntimeState, ProgramSchema, TenantUsage, WebhookDescr, WebhookEvent,
+    WebhookId, WebhookRetryPolicy, WebhookSubscription,
 };
-use super::{ApiPermission, Pipeline, PipelineDescr, PipelineRuntimeState, ProgramSchema};
 use crate::auth::{self, TenantId, TenantRecord};
+use crate::compiler::SqlCompilerMessage;
 use crate::db::Relation;
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDateTime, Utc};
@@ -39,7 +44,10 @@ impl Drop for DbHandle {
         // shutdown postgres). Otherwise postgres log an error that the
         // directory is already gone during shutdown which could be
         // confusing for a developer.
-        if let Some(pg) = self.db.pg_inst.as_mut() {
+        let ProjectDB::Postgres(db) = &mut self.db else {
+            return;
+        };
+        if let Some(pg) = db.pg_inst.as_mut() {
             let _r = async {
                 pg.stop_db().await.unwrap();
             };
@@ -112,10 +120,10 @@ pub(crate) async fn setup_pg() -> (ProjectDB, tempfile::TempDir) {
         .await
         .unwrap();
     let db_uri = pg.db_uri.clone();
-    let conn = ProjectDB::connect_inner(&db_uri, &Some("".to_string()), Some(pg))
+    let conn = PostgresDB::connect_inner(&db_uri, &Some("".to_string()), Some(pg))
         .await
         .unwrap();
-    (conn, _temp_dir)
+    (ProjectDB::Postgres(conn), _temp_dir)
 }
 
 #[cfg(not(feature = "pg-embed"))]
@@ -154,11 +162,11 @@ pub(crate) async fn setup_pg() -> (ProjectDB, tokio_postgres::Config) {
     log::debug!("tests connecting to: {config:#?}");
 
     config.dbname(&test_db);
-    let conn = ProjectDB::with_config(config.clone(), &Some("".to_string()))
+    let conn = PostgresDB::with_config(config.clone(), &Some("".to_string()))
         .await
         .unwrap();
 
-    (conn, config)
+    (ProjectDB::Postgres(conn), config)
 }
 
 pub fn test_connector_config() -> ConnectorConfig {
@@ -197,6 +205,7 @@ async fn program_creation() {
         description: "program desc".to_string(),
         version: res.1,
         status: ProgramStatus::None,
+        warnings: vec![],
         schema: None,
         code: None,
     };
@@ -211,6 +220,7 @@ async fn program_creation() {
         description: "program desc".to_string(),
         version: res.1,
         status: ProgramStatus::None,
+        warnings: vec![],
         schema: None,
         code: Some("ignored".to_string()),
     };
@@ -457,6 +467,63 @@ async fn project_pending() {
     // so it won't get picked up twice?
 }
 
+#[tokio::test]
+async fn compilation_jobs() {
+    let handle = test_setup().await;
+    let tenant_id = TenantRecord::default().id;
+    let (uid1, v1) = handle
+        .db
+        .new_program(tenant_id, Uuid::now_v7(), "test1", "", "ignored")
+        .await
+        .unwrap();
+    let (uid2, v2) = handle
+        .db
+        .new_program(tenant_id, Uuid::now_v7(), "test2", "", "ignored")
+        .await
+        .unwrap();
+    let (_uid3, _v3) = handle
+        .db
+        .new_program(tenant_id, Uuid::now_v7(), "test3", "", "ignored")
+        .await
+        .unwrap();
+    assert!(handle.db.list_compilation_jobs().await.unwrap().is_empty());
+
+    handle
+        .db
+        .set_program_for_compilation(tenant_id, uid1, v1, ProgramStatus::Pending)
+        .await
+        .unwrap();
+    handle
+        .db
+        .set_program_for_compilation(tenant_id, uid2, v2, ProgramStatus::Pending)
+        .await
+        .unwrap();
+    handle
+        .db
+        .set_program_status_guarded(tenant_id, uid1, v1, ProgramStatus::CompilingSql)
+        .await
+        .unwrap();
+
+    // Program 3 was never queued.
+    let jobs = handle.db.list_compilation_jobs().await.unwrap();
+    assert_eq!(jobs.len(), 2);
+    let job1 = jobs.iter().find(|j| j.program_id == uid1).unwrap();
+    assert_eq!(job1.tenant_id, tenant_id);
+    assert_eq!(job1.version, v1);
+    assert_eq!(job1.status, ProgramStatus::CompilingSql);
+    let job2 = jobs.iter().find(|j| j.program_id == uid2).unwrap();
+    assert_eq!(job2.status, ProgramStatus::Pending);
+
+    handle
+        .db
+        .set_program_status_guarded(tenant_id, uid1, v1, ProgramStatus::Success)
+        .await
+        .unwrap();
+    let jobs = handle.db.list_compilation_jobs().await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].program_id, uid2);
+}
+
 #[tokio::test]
 async fn update_status() {
     let handle = test_setup().await;
@@ -568,6 +635,391 @@ async fn save_api_key() {
     }
 }
 
+#[tokio::test]
+async fn tenant_usage() {
+    let handle = test_setup().await;
+    let tenant_id = TenantRecord::default().id;
+    let usage = handle.db.get_tenant_usage(tenant_id).await.unwrap();
+    assert!(usage.is_empty());
+
+    let delta = TenantUsage {
+        compile_time_ms: 1000,
+        run_time_ms: 2000,
+        ingress_bytes: 3000,
+        egress_bytes: 4000,
+    };
+    handle
+        .db
+        .record_tenant_usage(tenant_id, &delta)
+        .await
+        .unwrap();
+    handle
+        .db
+        .record_tenant_usage(tenant_id, &delta)
+        .await
+        .unwrap();
+    let usage = handle.db.get_tenant_usage(tenant_id).await.unwrap();
+    assert_eq!(
+        TenantUsage {
+            compile_time_ms: 2000,
+            run_time_ms: 4000,
+            ingress_bytes: 6000,
+            egress_bytes: 8000,
+        },
+        usage
+    );
+}
+
+#[tokio::test]
+async fn webhooks() {
+    let handle = test_setup().await;
+    let tenant_id = TenantRecord::default().id;
+    let events = vec![
+        WebhookEvent::Deployed,
+        WebhookEvent::Failed,
+        WebhookEvent::CompilationFailed,
+        WebhookEvent::ConnectorDegraded,
+    ];
+    let retry_policy = WebhookRetryPolicy {
+        max_attempts: 3,
+        initial_delay_ms: 500,
+    };
+    let webhook_id = handle
+        .db
+        .new_webhook(
+            tenant_id,
+            Uuid::now_v7(),
+            "http://localhost/hook",
+            "secret",
+            &events,
+            &retry_policy,
+        )
+        .await
+        .unwrap();
+    let webhooks = handle.db.list_webhooks(tenant_id).await.unwrap();
+    assert_eq!(
+        vec![WebhookDescr {
+            webhook_id,
+            url: "http://localhost/hook".to_string(),
+            events,
+            retry_policy,
+        }],
+        webhooks
+    );
+    let subscriptions = handle
+        .db
+        .list_webhook_subscriptions(tenant_id)
+        .await
+        .unwrap();
+    assert_eq!("secret", subscriptions[0].secret);
+
+    handle
+        .db
+        .delete_webhook(tenant_id, webhook_id)
+        .await
+        .unwrap();
+    assert!(handle.db.list_webhooks(tenant_id).await.unwrap().is_empty());
+    let err = handle
+        .db
+        .delete_webhook(tenant_id, webhook_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::UnknownWebhook { .. }));
+}
+
+#[test]
+fn webhook_retry_policy_limits() {
+    let policy = |max_attempts, initial_delay_ms| WebhookRetryPolicy {
+        max_attempts,
+        initial_delay_ms,
+    };
+    assert!(WebhookRetryPolicy::default().validate().is_ok());
+    assert!(policy(1, 0).validate().is_ok());
+    assert!(policy(
+        WebhookRetryPolicy::MAX_ATTEMPTS,
+        WebhookRetryPolicy::MAX_DELAY_MS
+    )
+    .validate()
+    .is_ok());
+    assert!(policy(0, 1000).validate().is_err());
+    assert!(policy(u32::MAX, 1000).validate().is_err());
+    assert!(policy(5, WebhookRetryPolicy::MAX_DELAY_MS + 1)
+        .validate()
+        .is_err());
+    assert!(policy(5, u64::MAX).validate().is_err());
+}
+
+#[tokio::test]
+async fn deployments() {
+    let handle = test_setup().await;
+    let tenant_id = TenantRecord::default().id;
+    let pipelines = vec![PipelineId(Uuid::now_v7()), PipelineId(Uuid::now_v7())];
+    let config = Some(RuntimeConfig::from_yaml("workers: 2"));
+    let deployment_id = handle
+        .db
+        .new_deployment(
+            tenant_id,
+            Uuid::now_v7(),
+            "d1",
+            "some deployment",
+            &pipelines,
+            &config,
+        )
+        .await
+        .unwrap();
+    let descr = DeploymentDescr {
+        deployment_id,
+        name: "d1".to_string(),
+        description: "some deployment".to_string(),
+        pipelines,
+        config,
+    };
+    assert_eq!(
+        descr,
+        handle
+            .db
+            .get_deployment_by_id(tenant_id, deployment_id)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        vec![descr],
+        handle.db.list_deployments(tenant_id).await.unwrap()
+    );
+
+    let err = handle
+        .db
+        .new_deployment(tenant_id, Uuid::now_v7(), "d1", "", &[], &None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::DuplicateName));
+
+    handle
+        .db
+        .delete_deployment(tenant_id, deployment_id)
+        .await
+        .unwrap();
+    assert!(handle
+        .db
+        .list_deployments(tenant_id)
+        .await
+        .unwrap()
+        .is_empty());
+    let err = handle
+        .db
+        .get_deployment_by_id(tenant_id, deployment_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::UnknownDeployment { .. }));
+}
+
+#[tokio::test]
+async fn heartbeats() {
+    let handle = test_setup().await;
+    handle.db.check_connection().await.unwrap();
+    assert_eq!(None, handle.db.get_heartbeat("compiler").await.unwrap());
+
+    let first = DateTime::<Utc>::from_naive_utc_and_offset(
+        NaiveDateTime::from_timestamp_opt(1_700_000_000, 0).unwrap(),
+        Utc,
+    );
+    handle.db.record_heartbeat("compiler", first).await.unwrap();
+    assert_eq!(
+        Some(first),
+        handle.db.get_heartbeat("compiler").await.unwrap()
+    );
+
+    let second = first + chrono::Duration::seconds(10);
+    handle
+        .db
+        .record_heartbeat("compiler", second)
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(second),
+        handle.db.get_heartbeat("compiler").await.unwrap()
+    );
+    assert_eq!(None, handle.db.get_heartbeat("runner").await.unwrap());
+}
+
+#[tokio::test]
+async fn soft_delete() {
+    let handle = test_setup().await;
+    let tenant_id = TenantRecord::default().id;
+    let connector_id = handle
+        .db
+        .new_connector(tenant_id, Uuid::now_v7(), "c", "", &test_connector_config())
+        .await
+        .unwrap();
+    let ac = AttachedConnector {
+        name: "foo".to_string(),
+        is_input: true,
+        connector_id,
+        relation_name: "".to_string(),
+    };
+    let rc = RuntimeConfig::from_yaml("");
+    let (pipeline_id, _) = handle
+        .db
+        .new_pipeline(
+            tenant_id,
+            Uuid::now_v7(),
+            None,
+            "p",
+            "",
+            &rc,
+            &Some(vec![ac]),
+        )
+        .await
+        .unwrap();
+
+    // Deleted pipelines are hidden, but keep their runtime state.
+    assert!(handle
+        .db
+        .delete_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap());
+    assert!(!handle
+        .db
+        .delete_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap());
+    assert!(handle
+        .db
+        .list_pipelines(tenant_id)
+        .await
+        .unwrap()
+        .is_empty());
+    let err = handle
+        .db
+        .get_pipeline_by_id(tenant_id, pipeline_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::UnknownPipeline { .. }));
+    handle
+        .db
+        .get_pipeline_runtime_state(tenant_id, pipeline_id)
+        .await
+        .unwrap();
+    let deleted = handle.db.list_deleted_pipelines(tenant_id).await.unwrap();
+    assert_eq!(1, deleted.len());
+    assert_eq!(pipeline_id, deleted[0].descriptor.pipeline_id);
+
+    // The name of a deleted pipeline can be reused, but then the deleted
+    // pipeline cannot be restored until the name is free again.
+    let (new_pipeline_id, _) = handle
+        .db
+        .new_pipeline(tenant_id, Uuid::now_v7(), None, "p", "", &rc, &None)
+        .await
+        .unwrap();
+    let err = handle
+        .db
+        .restore_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::DuplicateName));
+    assert!(handle
+        .db
+        .delete_pipeline(tenant_id, new_pipeline_id)
+        .await
+        .unwrap());
+
+    // Deleting a connector hides it from the pipelines it is attached to.
+    handle
+        .db
+        .delete_connector(tenant_id, connector_id)
+        .await
+        .unwrap();
+    assert!(handle
+        .db
+        .list_connectors(tenant_id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        1,
+        handle
+            .db
+            .list_deleted_connectors(tenant_id)
+            .await
+            .unwrap()
+            .len()
+    );
+
+    // Restore both objects.  The pipeline gets its connector back once the
+    // connector is restored.
+    handle
+        .db
+        .restore_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap();
+    let pipeline = handle
+        .db
+        .get_pipeline_by_id(tenant_id, pipeline_id)
+        .await
+        .unwrap();
+    assert!(pipeline.descriptor.attached_connectors.is_empty());
+    handle
+        .db
+        .restore_connector(tenant_id, connector_id)
+        .await
+        .unwrap();
+    let pipeline = handle
+        .db
+        .get_pipeline_by_id(tenant_id, pipeline_id)
+        .await
+        .unwrap();
+    assert_eq!(1, pipeline.descriptor.attached_connectors.len());
+    assert_eq!(
+        connector_id,
+        pipeline.descriptor.attached_connectors[0].connector_id
+    );
+    handle
+        .db
+        .get_connector_by_id(tenant_id, connector_id)
+        .await
+        .unwrap();
+    let err = handle
+        .db
+        .restore_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::UnknownPipeline { .. }));
+
+    // Purging only removes objects deleted before the cutoff.
+    handle
+        .db
+        .delete_pipeline(tenant_id, pipeline_id)
+        .await
+        .unwrap();
+    handle
+        .db
+        .delete_connector(tenant_id, connector_id)
+        .await
+        .unwrap();
+    let cutoff = Utc::now() - chrono::Duration::days(1);
+    assert_eq!(0, handle.db.purge_deleted(cutoff).await.unwrap());
+    let cutoff = Utc::now() + chrono::Duration::days(1);
+    assert_eq!(3, handle.db.purge_deleted(cutoff).await.unwrap());
+    assert!(handle
+        .db
+        .list_deleted_pipelines(tenant_id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(handle
+        .db
+        .list_deleted_connectors(tenant_id)
+        .await
+        .unwrap()
+        .is_empty());
+    let err = handle
+        .db
+        .get_pipeline_runtime_state(tenant_id, pipeline_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DBError::UnknownPipeline { .. }));
+}
+
 /// A Function that commits twice and checks the second time errors, returns
 /// revision of first commit.
 async fn commit_check(handle: &DbHandle, tenant_id: TenantId, pipeline_id: PipelineId) -> Revision {
@@ -840,6 +1292,14 @@ async fn versioning() {
         cpu_profiler: true,
         min_batch_size_records: 0,
         max_buffering_delay_usecs: 0,
+        column_statistics: Vec::new(),
+        checkpoint: None,
+        tracing: None,
+        on_error: Default::default(),
+        tls: None,
+        memory_warning_threshold_mb: None,
+        history: BTreeMap::new(),
+        replay: None,
     };
     handle
         .db
@@ -1034,9 +1494,13 @@ enum StorageAction {
     SetProgramForCompilation(TenantId, ProgramId, Version, ProgramStatus),
     SetProgramStatusGuarded(TenantId, ProgramId, Version, ProgramStatus),
     SetProgramSchema(TenantId, ProgramId, ProgramSchema),
+    SetProgramWarnings(TenantId, ProgramId, Vec<SqlCompilerMessage>),
     DeleteProgram(TenantId, ProgramId),
     AllPrograms,
     NextJob,
+    CountPendingPrograms,
+    ListCompilationJobs,
+    CountPipelinesByStatus,
     NewPipeline(
         TenantId,
         #[proptest(strategy = "limited_uuid()")] Uuid,
@@ -1062,6 +1526,8 @@ enum StorageAction {
     UpdatePipelineRuntimeState(TenantId, PipelineId, PipelineRuntimeState),
     SetPipelineDesiredStatus(TenantId, PipelineId, PipelineStatus),
     DeletePipeline(TenantId, PipelineId),
+    RestorePipeline(TenantId, PipelineId),
+    ListDeletedPipelines(TenantId),
     GetPipelineById(TenantId, PipelineId),
     GetPipelineByName(TenantId, String),
     GetPipelineDescrById(TenantId, PipelineId),
@@ -1087,6 +1553,8 @@ enum StorageAction {
         #[proptest(strategy = "limited_option_connector()")] Option<ConnectorConfig>,
     ),
     DeleteConnector(TenantId, ConnectorId),
+    RestoreConnector(TenantId, ConnectorId),
+    ListDeletedConnectors(TenantId),
     StoreApiKeyHash(TenantId, String, Vec<ApiPermission>),
     ValidateApiKey(TenantId, String),
     CreatePipelineRevision(
@@ -1095,11 +1563,36 @@ enum StorageAction {
         PipelineId,
     ),
     GetCommittedPipeline(TenantId, PipelineId),
-}
-
-fn check_responses<T: Debug + PartialEq>(step: usize, model: DBResult<T>, impl_: DBResult<T>) {
-    match (model, impl_) {
-        (Ok(mr), Ok(ir)) => assert_eq!(
+    RecordTenantUsage(TenantId, TenantUsage),
+    GetTenantUsage(TenantId),
+    NewWebhook(
+        TenantId,
+        #[proptest(strategy = "limited_uuid()")] Uuid,
+        String,
+        String,
+        Vec<WebhookEvent>,
+        WebhookRetryPolicy,
+    ),
+    ListWebhooks(TenantId),
+    DeleteWebhook(TenantId, WebhookId),
+    ListWebhookSubscriptions(TenantId),
+    NewDeployment(
+        TenantId,
+        #[proptest(strategy = "limited_uuid()")] Uuid,
+        String,
+        String,
+        Vec<PipelineId>,
+        // See `NewPipeline`.
+        Option<(u16, bool, u64, u64)>,
+    ),
+    ListDeployments(TenantId),
+    GetDeploymentById(TenantId, DeploymentId),
+    DeleteDeployment(TenantId, DeploymentId),
+}
+
+fn check_responses<T: Debug + PartialEq>(step: usize, model: DBResult<T>, impl_: DBResult<T>) {
+    match (model, impl_) {
+        (Ok(mr), Ok(ir)) => assert_eq!(
             mr, ir,
             "mismatch detected with model (left) and right (impl)"
         ),
@@ -1202,7 +1695,7 @@ fn compare_pipelines(mut model_response: Vec<Pipeline>, mut impl_response: Vec<P
 
 async fn create_tenants_if_not_exists(
     model: &Mutex<DbModel>,
-    handle: &DbHandle,
+    db: &ProjectDB,
     tenant_id: TenantId,
 ) -> DBResult<()> {
     let mut m = model.lock().await;
@@ -1213,21 +1706,71 @@ async fn create_tenants_if_not_exists(
             provider: Uuid::now_v7().to_string(),
         };
         m.tenants.insert(tenant_id, rec.clone());
-        handle
-            .db
-            .pool
-            .get()
-            .await
-            .unwrap()
-            .execute(
-                "INSERT INTO tenant VALUES ($1, $2, $3)",
-                &[&rec.id.0, &rec.tenant, &rec.provider],
-            )
-            .await?;
+        match db {
+            ProjectDB::Postgres(db) => {
+                db.pool
+                    .get()
+                    .await
+                    .unwrap()
+                    .execute(
+                        "INSERT INTO tenant VALUES ($1, $2, $3)",
+                        &[&rec.id.0, &rec.tenant, &rec.provider],
+                    )
+                    .await?;
+            }
+            #[cfg(feature = "lite")]
+            ProjectDB::Sqlite(db) => db.execute_batch(&format!(
+                "INSERT INTO tenant VALUES ('{}', '{}', '{}')",
+                rec.id.0, rec.tenant, rec.provider
+            ))?,
+        }
     }
     Ok(())
 }
 
+/// Empty all tables in the database.
+async fn reset_db(db: &ProjectDB) {
+    match db {
+        // TRUNCATE TABLE also resets the sequence ids (with RESTART IDENTITY).
+        ProjectDB::Postgres(db) => {
+            db.pool.get().await.unwrap()
+                .execute("DO $$ DECLARE r RECORD;
+                    BEGIN
+                        FOR r IN (SELECT tablename FROM pg_tables WHERE schemaname =current_schema()) LOOP
+                        EXECUTE 'TRUNCATE TABLE ' || quote_ident(r.tablename) || ' RESTART IDENTITY CASCADE';
+                        END LOOP;
+                    END $$;",
+                    &[],
+                )
+                .await
+                .unwrap();
+        }
+        #[cfg(feature = "lite")]
+        ProjectDB::Sqlite(db) => {
+            let tables = [
+                "attached_connector_history",
+                "attached_connector",
+                "pipeline_runtime_state",
+                "pipeline_history",
+                "pipeline",
+                "connector_history",
+                "connector",
+                "compiled_binary",
+                "program_history",
+                "program",
+                "api_key",
+                "tenant_usage",
+                "webhook",
+                "service_heartbeat",
+                "deployment",
+                "tenant",
+            ];
+            let statements: String = tables.iter().map(|t| format!("DELETE FROM {t};")).collect();
+            db.execute_batch(&statements).unwrap();
+        }
+    }
+}
+
 /// Compare the database storage implementation with our model using in-memory
 /// data-structures. Ideally, the two behave the same.
 #[test]
@@ -1235,7 +1778,26 @@ fn db_impl_behaves_like_model() {
     let _r = env_logger::try_init();
     let runtime = tokio::runtime::Runtime::new().unwrap();
     let handle = runtime.block_on(async { test_setup().await });
+    check_impl_behaves_like_model(&runtime, &handle.db);
+}
 
+/// Same as [`db_impl_behaves_like_model`] for the embedded SQLite database.
+#[cfg(feature = "lite")]
+#[test]
+fn sqlite_impl_behaves_like_model() {
+    let _r = env_logger::try_init();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let db = runtime.block_on(async {
+        ProjectDB::Sqlite(
+            super::sqlite::SqliteDB::connect(":memory:", &None)
+                .await
+                .unwrap(),
+        )
+    });
+    check_impl_behaves_like_model(&runtime, &db);
+}
+
+fn check_impl_behaves_like_model(runtime: &tokio::runtime::Runtime, db: &ProjectDB) {
     // We use the lower-level proptest API `TestRunner` here because if we use
     // `proptest!` it was very difficult to get drop() called on `handle` (I
     // tried putting it in Tokio OnceCell, std OnceCell, and static_init with
@@ -1245,281 +1807,730 @@ fn db_impl_behaves_like_model() {
     config.max_shrink_iters = u32::MAX;
     config.source_file = Some("src/db/test.rs");
     let mut runner = TestRunner::new(config);
-    let res = runner
-        .run(
-            &prop::collection::vec(any::<StorageAction>(), 0..256),
-            |actions| {
-                let model = Mutex::new(DbModel::default());
-                runtime.block_on(async {
-                    // We empty all tables in the database before each test
-                    // (with TRUNCATE TABLE). We also reset the sequence ids
-                    // (with RESTART IDENTITY)
-                    handle
-                        .db
-                        .pool.get().await.unwrap()
-                        .execute("DO $$ DECLARE r RECORD;
-                            BEGIN
-                                FOR r IN (SELECT tablename FROM pg_tables WHERE schemaname =current_schema()) LOOP
-                                EXECUTE 'TRUNCATE TABLE ' || quote_ident(r.tablename) || ' RESTART IDENTITY CASCADE';
-                                END LOOP;
-                            END $$;",
-                            &[],
-                        )
-                        .await
-                        .unwrap();
-
-                    for (i, action) in actions.into_iter().enumerate() {
-                        match action {
-                            StorageAction::ListPrograms(tenant_id, with_code) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.list_programs(tenant_id, with_code).await;
-                                let impl_response = handle.db.list_programs(tenant_id, with_code).await;
-                                if model_response.is_ok() && impl_response.is_ok() {
-                                    let m = model_response.unwrap();
-                                    let mut i = impl_response.unwrap();
-                                    // Impl does not guarantee order of rows returned by SELECT
-                                    i.sort_by(|a, b| a.program_id.cmp(&b.program_id));
-                                    assert_eq!(m, i);
-                                } else {
-                                    check_responses(i, model_response, impl_response);
-                                }
-                            }
-                            StorageAction::NewProgram(tenant_id, id, name, description, code) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response =
-                                    model.new_program(tenant_id, id, &name, &description, &code).await;
-                                let impl_response =
-                                    handle.db.new_program(tenant_id, id, &name, &description, &code).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::UpdateProgram(tenant_id, program_id, name, description, code) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model
-                                    .update_program(tenant_id, program_id, &name, &description, &code)
-                                    .await;
-                                let impl_response = handle
-                                    .db
-                                    .update_program(tenant_id, program_id, &name, &description, &code)
-                                    .await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetProgramIfExists(tenant_id,program_id, with_code) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_program_if_exists(tenant_id, program_id, with_code).await;
-                                let impl_response =
-                                    handle.db.get_program_if_exists(tenant_id, program_id, with_code).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::LookupProgram(tenant_id, name, with_code) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.lookup_program(tenant_id, &name, with_code).await;
-                                let impl_response = handle.db.lookup_program(tenant_id, &name, with_code).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::SetProgramForCompilation(tenant_id, program_id, version, status) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response =
-                                    model.set_program_for_compilation(tenant_id, program_id, version, status.clone()).await;
-                                let impl_response =
-                                    handle.db.set_program_for_compilation(tenant_id, program_id, version, status.clone()).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::SetProgramStatusGuarded(tenant_id, program_id, version, status) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model
-                                    .set_program_status_guarded(tenant_id, program_id, version, status.clone())
-                                    .await;
-                                let impl_response = handle
-                                    .db
-                                    .set_program_status_guarded(tenant_id, program_id, version, status.clone())
-                                    .await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::SetProgramSchema(tenant_id,program_id, schema) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response =
-                                    model.set_program_schema(tenant_id, program_id, schema.clone()).await;
-                                let impl_response =
-                                    handle.db.set_program_schema(tenant_id, program_id, schema).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::DeleteProgram(tenant_id, program_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.delete_program(tenant_id, program_id).await;
-                                let impl_response = handle.db.delete_program(tenant_id, program_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::AllPrograms => {
-                                let mut model_response = model.all_programs().await.unwrap();
-                                let mut impl_response = handle.db.all_programs().await.unwrap();
-                                model_response.sort_by(|a, b| a.1.program_id.cmp(&b.1.program_id));
-                                impl_response.sort_by(|a, b| a.1.program_id.cmp(&b.1.program_id));
-                                check_responses(i, Ok(model_response), Ok(impl_response));
-                            }
-                            StorageAction::NextJob => {
-                                let model_response = model.next_job().await;
-                                let impl_response = handle.db.next_job().await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetPipelineById(tenant_id, pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_pipeline_by_id(tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.get_pipeline_by_id(tenant_id, pipeline_id).await;
-                                compare_pipeline(i, model_response, impl_response);
-                            }
-                            StorageAction::GetPipelineByName(tenant_id, name) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_pipeline_by_name(tenant_id, name.clone()).await;
-                                let impl_response = handle.db.get_pipeline_by_name(tenant_id, name).await;
-                                compare_pipeline(i, model_response, impl_response);
-                            }
-                            StorageAction::GetPipelineDescrById(tenant_id, pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_pipeline_descr_by_id(tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.get_pipeline_descr_by_id(tenant_id, pipeline_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetPipelineDescrByName(tenant_id, name) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_pipeline_descr_by_name(tenant_id, name.clone()).await;
-                                let impl_response = handle.db.get_pipeline_descr_by_name(tenant_id, name).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetPipelineRuntimeState(tenant_id, pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_pipeline_runtime_state(tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.get_pipeline_runtime_state(tenant_id, pipeline_id).await;
-                                compare_pipeline_runtime_state(i, model_response, impl_response);
-                            }
-                            StorageAction::ListPipelines(tenant_id,) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.list_pipelines(tenant_id, ).await.unwrap();
-                                let mut impl_response = handle.db.list_pipelines(tenant_id, ).await.unwrap();
+    let res = runner.run(
+        &prop::collection::vec(any::<StorageAction>(), 0..256),
+        |actions| {
+            let model = Mutex::new(DbModel::default());
+            runtime.block_on(async {
+                // We empty all tables in the database before each test.
+                reset_db(db).await;
+
+                for (i, action) in actions.into_iter().enumerate() {
+                    match action {
+                        StorageAction::ListPrograms(tenant_id, with_code) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_programs(tenant_id, with_code).await;
+                            let impl_response = db.list_programs(tenant_id, with_code).await;
+                            if model_response.is_ok() && impl_response.is_ok() {
+                                let m = model_response.unwrap();
+                                let mut i = impl_response.unwrap();
                                 // Impl does not guarantee order of rows returned by SELECT
-                                impl_response.sort_by(|a, b| a.descriptor.pipeline_id.cmp(&b.descriptor.pipeline_id));
-                                compare_pipelines(model_response, impl_response);
-                            }
-                            StorageAction::NewPipeline(tenant_id, id, program_id, name, description, config, connectors) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let config = RuntimeConfig {
-                                    workers: config.0,
-                                    cpu_profiler: config.1,
-                                    min_batch_size_records: config.2,
-                                    max_buffering_delay_usecs: config.3,
-                                };
-                                let model_response =
-                                    model.new_pipeline(tenant_id, id, program_id, &name, &description, &config, &connectors.clone()).await;
-                                let impl_response =
-                                    handle.db.new_pipeline(tenant_id, id, program_id, &name, &description, &config, &connectors).await;
-                                    check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::UpdatePipeline(tenant_id,pipeline_id, program_id, name, description, config, connectors) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let config = config.map(|config| RuntimeConfig {
-                                    workers: config.0,
-                                    cpu_profiler: config.1,
-                                    min_batch_size_records: config.2,
-                                    max_buffering_delay_usecs: config.3,
-                                });
-                                let model_response = model
-                                    .update_pipeline(tenant_id, pipeline_id, program_id, &name, &description, &config, &connectors.clone())
-                                    .await;
-                                let impl_response = handle
-                                    .db
-                                    .update_pipeline(tenant_id, pipeline_id, program_id, &name, &description, &config, &connectors)
-                                    .await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::UpdatePipelineRuntimeState(tenant_id, pipeline_id, state) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.update_pipeline_runtime_state(tenant_id, pipeline_id, &state).await;
-                                let impl_response = handle.db.update_pipeline_runtime_state(tenant_id, pipeline_id, &state).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::SetPipelineDesiredStatus(tenant_id, pipeline_id, status) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.set_pipeline_desired_status(tenant_id, pipeline_id, status).await;
-                                let impl_response = handle.db.set_pipeline_desired_status(tenant_id, pipeline_id, status).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::DeletePipeline(tenant_id,pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.delete_pipeline(tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.delete_pipeline(tenant_id, pipeline_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::ListConnectors(tenant_id,) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.list_connectors(tenant_id).await.unwrap();
-                                let mut impl_response = handle.db.list_connectors(tenant_id).await.unwrap();
-                                // Impl does not guarantee order of rows returned by SELECT
-                                impl_response.sort_by(|a, b| a.connector_id.cmp(&b.connector_id));
-                                assert_eq!(model_response, impl_response);
-                            }
-                            StorageAction::NewConnector(tenant_id, id, name, description, config) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response =
-                                    model.new_connector(tenant_id, id, &name, &description, &config).await;
-                                let impl_response =
-                                    handle.db.new_connector(tenant_id, id, &name, &description, &config).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetConnectorById(tenant_id,connector_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_connector_by_id(tenant_id, connector_id).await;
-                                let impl_response = handle.db.get_connector_by_id(tenant_id, connector_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetConnectorByName(tenant_id,name) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_connector_by_name(tenant_id, name.clone()).await;
-                                let impl_response = handle.db.get_connector_by_name(tenant_id, name).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::UpdateConnector(tenant_id,connector_id, name, description, config) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response =
-                                    model.update_connector(tenant_id, connector_id, &name, &description, &config).await;
-                                let impl_response =
-                                    handle.db.update_connector(tenant_id, connector_id, &name, &description, &config).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::DeleteConnector(tenant_id,connector_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.delete_connector(tenant_id, connector_id).await;
-                                let impl_response = handle.db.delete_connector(tenant_id, connector_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::StoreApiKeyHash(tenant_id,key, permissions) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.store_api_key_hash(tenant_id, key.clone(), permissions.clone()).await;
-                                let impl_response = handle.db.store_api_key_hash(tenant_id, key.clone(), permissions.clone()).await;
-                                check_responses(i, model_response, impl_response);
-                            },
-                            StorageAction::ValidateApiKey(tenant_id,key) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.validate_api_key(key.clone()).await;
-                                let impl_response = handle.db.validate_api_key(key.clone()).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::CreatePipelineRevision(new_revision_id, tenant_id, pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.create_pipeline_revision(new_revision_id, tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.create_pipeline_revision(new_revision_id, tenant_id, pipeline_id).await;
-                                check_responses(i, model_response, impl_response);
-                            }
-                            StorageAction::GetCommittedPipeline(tenant_id, pipeline_id) => {
-                                create_tenants_if_not_exists(&model, &handle, tenant_id).await.unwrap();
-                                let model_response = model.get_last_committed_pipeline_revision(tenant_id, pipeline_id).await;
-                                let impl_response = handle.db.get_last_committed_pipeline_revision(tenant_id, pipeline_id).await;
+                                i.sort_by(|a, b| a.program_id.cmp(&b.program_id));
+                                assert_eq!(m, i);
+                            } else {
                                 check_responses(i, model_response, impl_response);
                             }
                         }
+                        StorageAction::NewProgram(tenant_id, id, name, description, code) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .new_program(tenant_id, id, &name, &description, &code)
+                                .await;
+                            let impl_response = db
+                                .new_program(tenant_id, id, &name, &description, &code)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::UpdateProgram(
+                            tenant_id,
+                            program_id,
+                            name,
+                            description,
+                            code,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .update_program(tenant_id, program_id, &name, &description, &code)
+                                .await;
+                            let impl_response = db
+                                .update_program(tenant_id, program_id, &name, &description, &code)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetProgramIfExists(tenant_id, program_id, with_code) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .get_program_if_exists(tenant_id, program_id, with_code)
+                                .await;
+                            let impl_response = db
+                                .get_program_if_exists(tenant_id, program_id, with_code)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::LookupProgram(tenant_id, name, with_code) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.lookup_program(tenant_id, &name, with_code).await;
+                            let impl_response =
+                                db.lookup_program(tenant_id, &name, with_code).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::SetProgramForCompilation(
+                            tenant_id,
+                            program_id,
+                            version,
+                            status,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .set_program_for_compilation(
+                                    tenant_id,
+                                    program_id,
+                                    version,
+                                    status.clone(),
+                                )
+                                .await;
+                            let impl_response = db
+                                .set_program_for_compilation(
+                                    tenant_id,
+                                    program_id,
+                                    version,
+                                    status.clone(),
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::SetProgramStatusGuarded(
+                            tenant_id,
+                            program_id,
+                            version,
+                            status,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .set_program_status_guarded(
+                                    tenant_id,
+                                    program_id,
+                                    version,
+                                    status.clone(),
+                                )
+                                .await;
+                            let impl_response = db
+                                .set_program_status_guarded(
+                                    tenant_id,
+                                    program_id,
+                                    version,
+                                    status.clone(),
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::SetProgramSchema(tenant_id, program_id, schema) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .set_program_schema(tenant_id, program_id, schema.clone())
+                                .await;
+                            let impl_response =
+                                db.set_program_schema(tenant_id, program_id, schema).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::SetProgramWarnings(tenant_id, program_id, warnings) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .set_program_warnings(tenant_id, program_id, warnings.clone())
+                                .await;
+                            let impl_response = db
+                                .set_program_warnings(tenant_id, program_id, warnings)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::DeleteProgram(tenant_id, program_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.delete_program(tenant_id, program_id).await;
+                            let impl_response = db.delete_program(tenant_id, program_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::AllPrograms => {
+                            let mut model_response = model.all_programs().await.unwrap();
+                            let mut impl_response = db.all_programs().await.unwrap();
+                            model_response.sort_by(|a, b| a.1.program_id.cmp(&b.1.program_id));
+                            impl_response.sort_by(|a, b| a.1.program_id.cmp(&b.1.program_id));
+                            check_responses(i, Ok(model_response), Ok(impl_response));
+                        }
+                        StorageAction::NextJob => {
+                            let model_response = model.next_job().await;
+                            let impl_response = db.next_job().await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::CountPendingPrograms => {
+                            let model_response = model.count_pending_programs().await;
+                            let impl_response = db.count_pending_programs().await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListCompilationJobs => {
+                            // Timestamps differ between the model and the impl.
+                            let strip = |jobs: Vec<CompilationJob>| {
+                                let mut jobs: Vec<_> = jobs
+                                    .into_iter()
+                                    .map(|j| (j.tenant_id, j.program_id, j.version, j.status))
+                                    .collect();
+                                jobs.sort_by(|a, b| a.1.cmp(&b.1));
+                                jobs
+                            };
+                            let model_response = model.list_compilation_jobs().await.map(strip);
+                            let impl_response = db.list_compilation_jobs().await.map(strip);
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::CountPipelinesByStatus => {
+                            let model_response = model.count_pipelines_by_status().await.unwrap();
+                            let mut impl_response = db.count_pipelines_by_status().await.unwrap();
+                            // Impl does not guarantee order of rows returned by SELECT
+                            impl_response.sort_by_key(|(status, _)| <&'static str>::from(*status));
+                            assert_eq!(model_response, impl_response);
+                        }
+                        StorageAction::GetPipelineById(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_pipeline_by_id(tenant_id, pipeline_id).await;
+                            let impl_response = db.get_pipeline_by_id(tenant_id, pipeline_id).await;
+                            compare_pipeline(i, model_response, impl_response);
+                        }
+                        StorageAction::GetPipelineByName(tenant_id, name) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_pipeline_by_name(tenant_id, name.clone()).await;
+                            let impl_response = db.get_pipeline_by_name(tenant_id, name).await;
+                            compare_pipeline(i, model_response, impl_response);
+                        }
+                        StorageAction::GetPipelineDescrById(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_pipeline_descr_by_id(tenant_id, pipeline_id).await;
+                            let impl_response =
+                                db.get_pipeline_descr_by_id(tenant_id, pipeline_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetPipelineDescrByName(tenant_id, name) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .get_pipeline_descr_by_name(tenant_id, name.clone())
+                                .await;
+                            let impl_response =
+                                db.get_pipeline_descr_by_name(tenant_id, name).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetPipelineRuntimeState(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .get_pipeline_runtime_state(tenant_id, pipeline_id)
+                                .await;
+                            let impl_response =
+                                db.get_pipeline_runtime_state(tenant_id, pipeline_id).await;
+                            compare_pipeline_runtime_state(i, model_response, impl_response);
+                        }
+                        StorageAction::ListPipelines(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_pipelines(tenant_id).await.unwrap();
+                            let mut impl_response = db.list_pipelines(tenant_id).await.unwrap();
+                            // Impl does not guarantee order of rows returned by SELECT
+                            impl_response.sort_by(|a, b| {
+                                a.descriptor.pipeline_id.cmp(&b.descriptor.pipeline_id)
+                            });
+                            compare_pipelines(model_response, impl_response);
+                        }
+                        StorageAction::NewPipeline(
+                            tenant_id,
+                            id,
+                            program_id,
+                            name,
+                            description,
+                            config,
+                            connectors,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let config = RuntimeConfig {
+                                workers: config.0,
+                                cpu_profiler: config.1,
+                                min_batch_size_records: config.2,
+                                max_buffering_delay_usecs: config.3,
+                                column_statistics: Vec::new(),
+                                checkpoint: None,
+                                tracing: None,
+                                on_error: Default::default(),
+                                tls: None,
+                                memory_warning_threshold_mb: None,
+                                history: BTreeMap::new(),
+                                replay: None,
+                            };
+                            let model_response = model
+                                .new_pipeline(
+                                    tenant_id,
+                                    id,
+                                    program_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                    &connectors.clone(),
+                                )
+                                .await;
+                            let impl_response = db
+                                .new_pipeline(
+                                    tenant_id,
+                                    id,
+                                    program_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                    &connectors,
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::UpdatePipeline(
+                            tenant_id,
+                            pipeline_id,
+                            program_id,
+                            name,
+                            description,
+                            config,
+                            connectors,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let config = config.map(|config| RuntimeConfig {
+                                workers: config.0,
+                                cpu_profiler: config.1,
+                                min_batch_size_records: config.2,
+                                max_buffering_delay_usecs: config.3,
+                                column_statistics: Vec::new(),
+                                checkpoint: None,
+                                tracing: None,
+                                on_error: Default::default(),
+                                tls: None,
+                                memory_warning_threshold_mb: None,
+                                history: BTreeMap::new(),
+                                replay: None,
+                            });
+                            let model_response = model
+                                .update_pipeline(
+                                    tenant_id,
+                                    pipeline_id,
+                                    program_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                    &connectors.clone(),
+                                )
+                                .await;
+                            let impl_response = db
+                                .update_pipeline(
+                                    tenant_id,
+                                    pipeline_id,
+                                    program_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                    &connectors,
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::UpdatePipelineRuntimeState(
+                            tenant_id,
+                            pipeline_id,
+                            state,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .update_pipeline_runtime_state(tenant_id, pipeline_id, &state)
+                                .await;
+                            let impl_response = db
+                                .update_pipeline_runtime_state(tenant_id, pipeline_id, &state)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::SetPipelineDesiredStatus(tenant_id, pipeline_id, status) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .set_pipeline_desired_status(tenant_id, pipeline_id, status)
+                                .await;
+                            let impl_response = db
+                                .set_pipeline_desired_status(tenant_id, pipeline_id, status)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::DeletePipeline(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.delete_pipeline(tenant_id, pipeline_id).await;
+                            let impl_response = db.delete_pipeline(tenant_id, pipeline_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::RestorePipeline(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.restore_pipeline(tenant_id, pipeline_id).await;
+                            let impl_response = db.restore_pipeline(tenant_id, pipeline_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListDeletedPipelines(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.list_deleted_pipelines(tenant_id).await.unwrap();
+                            let mut impl_response =
+                                db.list_deleted_pipelines(tenant_id).await.unwrap();
+                            // Impl does not guarantee order of rows returned by SELECT
+                            impl_response.sort_by(|a, b| {
+                                a.descriptor.pipeline_id.cmp(&b.descriptor.pipeline_id)
+                            });
+                            // Deletion timestamps differ between the model and the impl.
+                            let model_response: Vec<_> =
+                                model_response.into_iter().map(|p| p.descriptor).collect();
+                            let impl_response: Vec<_> =
+                                impl_response.into_iter().map(|p| p.descriptor).collect();
+                            assert_eq!(model_response, impl_response);
+                        }
+                        StorageAction::ListConnectors(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_connectors(tenant_id).await.unwrap();
+                            let mut impl_response = db.list_connectors(tenant_id).await.unwrap();
+                            // Impl does not guarantee order of rows returned by SELECT
+                            impl_response.sort_by(|a, b| a.connector_id.cmp(&b.connector_id));
+                            assert_eq!(model_response, impl_response);
+                        }
+                        StorageAction::NewConnector(tenant_id, id, name, description, config) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .new_connector(tenant_id, id, &name, &description, &config)
+                                .await;
+                            let impl_response = db
+                                .new_connector(tenant_id, id, &name, &description, &config)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetConnectorById(tenant_id, connector_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_connector_by_id(tenant_id, connector_id).await;
+                            let impl_response =
+                                db.get_connector_by_id(tenant_id, connector_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetConnectorByName(tenant_id, name) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_connector_by_name(tenant_id, name.clone()).await;
+                            let impl_response = db.get_connector_by_name(tenant_id, name).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::UpdateConnector(
+                            tenant_id,
+                            connector_id,
+                            name,
+                            description,
+                            config,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .update_connector(
+                                    tenant_id,
+                                    connector_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                )
+                                .await;
+                            let impl_response = db
+                                .update_connector(
+                                    tenant_id,
+                                    connector_id,
+                                    &name,
+                                    &description,
+                                    &config,
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::DeleteConnector(tenant_id, connector_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.delete_connector(tenant_id, connector_id).await;
+                            let impl_response = db.delete_connector(tenant_id, connector_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::RestoreConnector(tenant_id, connector_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.restore_connector(tenant_id, connector_id).await;
+                            let impl_response = db.restore_connector(tenant_id, connector_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListDeletedConnectors(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.list_deleted_connectors(tenant_id).await.unwrap();
+                            let mut impl_response =
+                                db.list_deleted_connectors(tenant_id).await.unwrap();
+                            // Impl does not guarantee order of rows returned by SELECT
+                            impl_response.sort_by(|a, b| {
+                                a.descriptor.connector_id.cmp(&b.descriptor.connector_id)
+                            });
+                            // Deletion timestamps differ between the model and the impl.
+                            let model_response: Vec<_> =
+                                model_response.into_iter().map(|c| c.descriptor).collect();
+                            let impl_response: Vec<_> =
+                                impl_response.into_iter().map(|c| c.descriptor).collect();
+                            assert_eq!(model_response, impl_response);
+                        }
+                        StorageAction::StoreApiKeyHash(tenant_id, key, permissions) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .store_api_key_hash(tenant_id, key.clone(), permissions.clone())
+                                .await;
+                            let impl_response = db
+                                .store_api_key_hash(tenant_id, key.clone(), permissions.clone())
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ValidateApiKey(tenant_id, key) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.validate_api_key(key.clone()).await;
+                            let impl_response = db.validate_api_key(key.clone()).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::CreatePipelineRevision(
+                            new_revision_id,
+                            tenant_id,
+                            pipeline_id,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .create_pipeline_revision(new_revision_id, tenant_id, pipeline_id)
+                                .await;
+                            let impl_response = db
+                                .create_pipeline_revision(new_revision_id, tenant_id, pipeline_id)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetCommittedPipeline(tenant_id, pipeline_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .get_last_committed_pipeline_revision(tenant_id, pipeline_id)
+                                .await;
+                            let impl_response = db
+                                .get_last_committed_pipeline_revision(tenant_id, pipeline_id)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::RecordTenantUsage(tenant_id, usage) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.record_tenant_usage(tenant_id, &usage).await;
+                            let impl_response = db.record_tenant_usage(tenant_id, &usage).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetTenantUsage(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.get_tenant_usage(tenant_id).await;
+                            let impl_response = db.get_tenant_usage(tenant_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::NewWebhook(
+                            tenant_id,
+                            id,
+                            url,
+                            secret,
+                            events,
+                            retry_policy,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model
+                                .new_webhook(tenant_id, id, &url, &secret, &events, &retry_policy)
+                                .await;
+                            let impl_response = db
+                                .new_webhook(tenant_id, id, &url, &secret, &events, &retry_policy)
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListWebhooks(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_webhooks(tenant_id).await;
+                            let impl_response = db.list_webhooks(tenant_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::DeleteWebhook(tenant_id, webhook_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.delete_webhook(tenant_id, webhook_id).await;
+                            let impl_response = db.delete_webhook(tenant_id, webhook_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListWebhookSubscriptions(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_webhook_subscriptions(tenant_id).await;
+                            let impl_response = db.list_webhook_subscriptions(tenant_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::NewDeployment(
+                            tenant_id,
+                            id,
+                            name,
+                            description,
+                            pipelines,
+                            config,
+                        ) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let config = config.map(|config| RuntimeConfig {
+                                workers: config.0,
+                                cpu_profiler: config.1,
+                                min_batch_size_records: config.2,
+                                max_buffering_delay_usecs: config.3,
+                                column_statistics: Vec::new(),
+                                checkpoint: None,
+                                tracing: None,
+                                on_error: Default::default(),
+                                tls: None,
+                                memory_warning_threshold_mb: None,
+                                history: BTreeMap::new(),
+                                replay: None,
+                            });
+                            let model_response = model
+                                .new_deployment(
+                                    tenant_id,
+                                    id,
+                                    &name,
+                                    &description,
+                                    &pipelines,
+                                    &config,
+                                )
+                                .await;
+                            let impl_response = db
+                                .new_deployment(
+                                    tenant_id,
+                                    id,
+                                    &name,
+                                    &description,
+                                    &pipelines,
+                                    &config,
+                                )
+                                .await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::ListDeployments(tenant_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response = model.list_deployments(tenant_id).await;
+                            let impl_response = db.list_deployments(tenant_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::GetDeploymentById(tenant_id, deployment_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.get_deployment_by_id(tenant_id, deployment_id).await;
+                            let impl_response =
+                                db.get_deployment_by_id(tenant_id, deployment_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
+                        StorageAction::DeleteDeployment(tenant_id, deployment_id) => {
+                            create_tenants_if_not_exists(&model, db, tenant_id)
+                                .await
+                                .unwrap();
+                            let model_response =
+                                model.delete_deployment(tenant_id, deployment_id).await;
+                            let impl_response =
+                                db.delete_deployment(tenant_id, deployment_id).await;
+                            check_responses(i, model_response, impl_response);
+                        }
                     }
-                });
-                Ok(())
-            },
-        );
+                }
+            });
+            Ok(())
+        },
+    );
 
     if let Err(e) = res {
         panic!("{e:#}");
@@ -1539,6 +2550,27 @@ struct DbModel {
     pub api_keys: BTreeMap<String, (TenantId, Vec<ApiPermission>)>,
     pub connectors: BTreeMap<(TenantId, ConnectorId), ConnectorDescr>,
     pub tenants: BTreeMap<TenantId, TenantRecord>,
+    pub usage: BTreeMap<TenantId, TenantUsage>,
+    // Pipelines and connectors in the trash and the time they were deleted.
+    pub deleted_pipelines: BTreeMap<(TenantId, PipelineId), DateTime<Utc>>,
+    pub deleted_connectors: BTreeMap<(TenantId, ConnectorId), DateTime<Utc>>,
+    pub webhooks: BTreeMap<(TenantId, WebhookId), WebhookSubscription>,
+    pub deployments: BTreeMap<(TenantId, DeploymentId), DeploymentDescr>,
+    pub heartbeats: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl DbModel {
+    /// `pipeline` as returned by lookups: attachments of connectors in the
+    /// trash are hidden.
+    fn visible_pipeline(&self, tenant_id: TenantId, pipeline: &Pipeline) -> Pipeline {
+        let mut pipeline = pipeline.clone();
+        pipeline.descriptor.attached_connectors.retain(|ac| {
+            !self
+                .deleted_connectors
+                .contains_key(&(tenant_id, ac.connector_id))
+        });
+        pipeline
+    }
 }
 
 #[async_trait]
@@ -1592,6 +2624,7 @@ impl Storage for Mutex<DbModel> {
                     name: program_name.to_owned(),
                     description: program_description.to_owned(),
                     status: ProgramStatus::None,
+                    warnings: vec![],
                     schema: None,
                     version,
                     code: Some(program_code.to_owned()),
@@ -1636,6 +2669,7 @@ impl Storage for Mutex<DbModel> {
                         p.code = program_code.to_owned();
                         p.version.0 += 1;
                         p.schema = None;
+                        p.warnings = vec![];
                         p.status = ProgramStatus::None;
                     }
                 }
@@ -1691,6 +2725,7 @@ impl Storage for Mutex<DbModel> {
                 p.status = status;
                 *t = SystemTime::now();
                 p.schema = None;
+                p.warnings = vec![];
             }
         });
 
@@ -1730,6 +2765,20 @@ impl Storage for Mutex<DbModel> {
         Ok(())
     }
 
+    async fn set_program_warnings(
+        &self,
+        tenant_id: TenantId,
+        program_id: super::ProgramId,
+        warnings: Vec<SqlCompilerMessage>,
+    ) -> DBResult<()> {
+        let mut s = self.lock().await;
+        let _r = s.programs.get_mut(&(tenant_id, program_id)).map(|(p, _)| {
+            p.warnings = warnings;
+        });
+
+        Ok(())
+    }
+
     async fn delete_program(
         &self,
         tenant_id: TenantId,
@@ -1791,6 +2840,47 @@ impl Storage for Mutex<DbModel> {
             .unwrap_or(Ok(None))
     }
 
+    async fn count_pending_programs(&self) -> DBResult<u64> {
+        let s = self.lock().await;
+        Ok(s.programs
+            .values()
+            .filter(|p| p.0.status == ProgramStatus::Pending)
+            .count() as u64)
+    }
+
+    async fn list_compilation_jobs(&self) -> DBResult<Vec<CompilationJob>> {
+        let s = self.lock().await;
+        let mut jobs: Vec<(&(TenantId, ProgramId), &ProgramData)> = s
+            .programs
+            .iter()
+            .filter(|(_, v)| v.0.status == ProgramStatus::Pending || v.0.status.is_compiling())
+            .collect();
+        jobs.sort_by(|(_, t1), (_, t2)| t1.1.cmp(&t2.1));
+        Ok(jobs
+            .into_iter()
+            .map(|(k, v)| CompilationJob {
+                tenant_id: k.0,
+                program_id: v.0.program_id,
+                version: v.0.version,
+                status: v.0.status.clone(),
+                status_since: DateTime::<Utc>::from(v.1),
+            })
+            .collect())
+    }
+
+    async fn count_pipelines_by_status(&self) -> DBResult<Vec<(PipelineStatus, u64)>> {
+        let s = self.lock().await;
+        let mut counts: BTreeMap<&'static str, (PipelineStatus, u64)> = BTreeMap::new();
+        for (key, pipeline) in s.pipelines.iter() {
+            if s.deleted_pipelines.contains_key(key) {
+                continue;
+            }
+            let status = pipeline.state.current_status;
+            counts.entry(status.into()).or_insert((status, 0)).1 += 1;
+        }
+        Ok(counts.into_values().collect())
+    }
+
     async fn create_pipeline_revision(
         &self,
         new_revision_id: Uuid,
@@ -1802,6 +2892,7 @@ impl Storage for Mutex<DbModel> {
         let pipeline = s
             .pipelines
             .get(&(tenant_id, pipeline_id))
+            .map(|p| s.visible_pipeline(tenant_id, p))
             .ok_or(DBError::UnknownPipeline { pipeline_id })?;
 
         if let Some(program_id) = pipeline.descriptor.program_id {
@@ -1920,11 +3011,11 @@ impl Storage for Mutex<DbModel> {
         if s.pipelines.keys().any(|k| k.1 == PipelineId(id)) {
             return Err(DBError::unique_key_violation("pipeline_pkey"));
         }
-        // UNIQUE constraint on name
+        // UNIQUE constraint on the name of live pipelines
         if let Some(_) = s
             .pipelines
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_pipelines.contains_key(k.0))
             .map(|k| k.1)
             .find(|c| c.descriptor.name == pipeline_name)
         {
@@ -1942,7 +3033,10 @@ impl Storage for Mutex<DbModel> {
             for ac in connectors {
                 // Check that all attached connectors point to a valid
                 // connector_id
-                if !db_connectors.contains_key(&(tenant_id, ac.connector_id)) {
+                if !db_connectors.contains_key(&(tenant_id, ac.connector_id))
+                    || s.deleted_connectors
+                        .contains_key(&(tenant_id, ac.connector_id))
+                {
                     return Err(DBError::UnknownConnector {
                         connector_id: ac.connector_id,
                     });
@@ -1976,6 +3070,8 @@ impl Storage for Mutex<DbModel> {
                     status_since: Utc::now(),
                     error: None,
                     created: Utc::now(),
+                    tls_certificate: None,
+                    auth_token: None,
                 },
             },
         );
@@ -1997,17 +3093,23 @@ impl Storage for Mutex<DbModel> {
         let db_connectors = s.connectors.clone();
         let db_programs = s.programs.clone();
 
-        // pipeline must exist
+        // pipeline must exist and must not be in the trash
         s.pipelines
             .get_mut(&(tenant_id, pipeline_id))
             .ok_or(DBError::UnknownPipeline { pipeline_id })?;
+        if s.deleted_pipelines.contains_key(&(tenant_id, pipeline_id)) {
+            return Err(DBError::UnknownPipeline { pipeline_id });
+        }
 
         let mut new_acs: Vec<AttachedConnector> = vec![];
         if let Some(connectors) = connectors {
             for ac in connectors {
                 // Check that all attached connectors point to a valid
                 // connector_id
-                if !db_connectors.contains_key(&(tenant_id, ac.connector_id)) {
+                if !db_connectors.contains_key(&(tenant_id, ac.connector_id))
+                    || s.deleted_connectors
+                        .contains_key(&(tenant_id, ac.connector_id))
+                {
                     return Err(DBError::UnknownConnector {
                         connector_id: ac.connector_id,
                     });
@@ -2028,11 +3130,11 @@ impl Storage for Mutex<DbModel> {
                 .clone();
         }
 
-        // UNIQUE constraint on name
+        // UNIQUE constraint on the name of live pipelines
         if let Some(c) = s
             .pipelines
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_pipelines.contains_key(k.0))
             .map(|k| k.1)
             .find(|c| c.descriptor.name == pipeline_name)
         {
@@ -2101,13 +3203,45 @@ impl Storage for Mutex<DbModel> {
         pipeline_id: super::PipelineId,
     ) -> DBResult<bool> {
         let mut s = self.lock().await;
-        let _r = s.history.remove(&(tenant_id, pipeline_id));
         // TODO: Our APIs sometimes are not consistent we return a bool here but
         // other calls fail silently on delete/lookups
-        Ok(s.pipelines
+        if !s.pipelines.contains_key(&(tenant_id, pipeline_id))
+            || s.deleted_pipelines.contains_key(&(tenant_id, pipeline_id))
+        {
+            return Ok(false);
+        }
+        s.deleted_pipelines
+            .insert((tenant_id, pipeline_id), Utc::now());
+        Ok(true)
+    }
+
+    async fn restore_pipeline(&self, tenant_id: TenantId, pipeline_id: PipelineId) -> DBResult<()> {
+        let mut s = self.lock().await;
+        if !s.deleted_pipelines.contains_key(&(tenant_id, pipeline_id)) {
+            return Err(DBError::UnknownPipeline { pipeline_id });
+        }
+        let name = &s.pipelines[&(tenant_id, pipeline_id)].descriptor.name;
+        if s.pipelines.iter().any(|(k, p)| {
+            k.0 == tenant_id && !s.deleted_pipelines.contains_key(k) && &p.descriptor.name == name
+        }) {
+            return Err(DBError::DuplicateName);
+        }
+        s.deleted_pipelines
             .remove(&(tenant_id, pipeline_id))
-            .map(|_| true)
-            .unwrap_or(false))
+            .map(|_| ())
+            .ok_or(DBError::UnknownPipeline { pipeline_id })
+    }
+
+    async fn list_deleted_pipelines(&self, tenant_id: TenantId) -> DBResult<Vec<DeletedPipeline>> {
+        let s = self.lock().await;
+        Ok(s.deleted_pipelines
+            .iter()
+            .filter(|k| k.0 .0 == tenant_id)
+            .map(|(k, deleted_at)| DeletedPipeline {
+                descriptor: s.visible_pipeline(tenant_id, &s.pipelines[k]).descriptor,
+                deleted_at: *deleted_at,
+            })
+            .collect())
     }
 
     async fn get_pipeline_by_id(
@@ -2118,7 +3252,8 @@ impl Storage for Mutex<DbModel> {
         let s = self.lock().await;
         s.pipelines
             .get(&(tenant_id, pipeline_id))
-            .cloned()
+            .filter(|_| !s.deleted_pipelines.contains_key(&(tenant_id, pipeline_id)))
+            .map(|p| s.visible_pipeline(tenant_id, p))
             .ok_or(DBError::UnknownPipeline { pipeline_id })
     }
 
@@ -2137,9 +3272,12 @@ impl Storage for Mutex<DbModel> {
         tenant_id: TenantId,
         pipeline_id: PipelineId,
     ) -> Result<PipelineRuntimeState, DBError> {
-        self.get_pipeline_by_id(tenant_id, pipeline_id)
-            .await
-            .map(|p| p.state)
+        // Unlike other lookups, this also returns pipelines in the trash.
+        let s = self.lock().await;
+        s.pipelines
+            .get(&(tenant_id, pipeline_id))
+            .map(|p| p.state.clone())
+            .ok_or(DBError::UnknownPipeline { pipeline_id })
     }
 
     async fn update_pipeline_runtime_state(
@@ -2160,6 +3298,8 @@ impl Storage for Mutex<DbModel> {
         pipeline.state.status_since = state.status_since;
         pipeline.state.error = state.error.clone();
         pipeline.state.created = state.created;
+        pipeline.state.tls_certificate = state.tls_certificate.clone();
+        pipeline.state.auth_token = state.auth_token.clone();
 
         Ok(())
     }
@@ -2189,8 +3329,8 @@ impl Storage for Mutex<DbModel> {
         let s = self.lock().await;
         s.pipelines
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
-            .map(|k| k.1.clone())
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_pipelines.contains_key(k.0))
+            .map(|k| s.visible_pipeline(tenant_id, k.1))
             .find(|p| p.descriptor.name == name)
             .ok_or(DBError::UnknownName { name })
     }
@@ -2206,13 +3346,11 @@ impl Storage for Mutex<DbModel> {
     }
 
     async fn list_pipelines(&self, tenant_id: TenantId) -> DBResult<Vec<super::Pipeline>> {
-        Ok(self
-            .lock()
-            .await
-            .pipelines
+        let s = self.lock().await;
+        Ok(s.pipelines
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
-            .map(|k| k.1.clone())
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_pipelines.contains_key(k.0))
+            .map(|k| s.visible_pipeline(tenant_id, k.1))
             .collect())
     }
 
@@ -2228,9 +3366,10 @@ impl Storage for Mutex<DbModel> {
         if s.connectors.keys().any(|k| k.1 == ConnectorId(id)) {
             return Err(DBError::unique_key_violation("connector_pkey"));
         }
+        // UNIQUE constraint on the name of live connectors
         if s.connectors
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_connectors.contains_key(k.0))
             .map(|k| k.1.clone())
             .any(|c| c.name == name)
         {
@@ -2254,7 +3393,7 @@ impl Storage for Mutex<DbModel> {
         let s = self.lock().await;
         Ok(s.connectors
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_connectors.contains_key(k.0))
             .map(|k| k.1.clone())
             .collect())
     }
@@ -2267,6 +3406,10 @@ impl Storage for Mutex<DbModel> {
         let s = self.lock().await;
         s.connectors
             .get(&(tenant_id, connector_id))
+            .filter(|_| {
+                !s.deleted_connectors
+                    .contains_key(&(tenant_id, connector_id))
+            })
             .cloned()
             .ok_or(DBError::UnknownConnector { connector_id })
     }
@@ -2279,7 +3422,7 @@ impl Storage for Mutex<DbModel> {
         let s = self.lock().await;
         s.connectors
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_connectors.contains_key(k.0))
             .map(|k| k.1.clone())
             .find(|c| c.name == name)
             .ok_or(DBError::UnknownName { name })
@@ -2294,15 +3437,18 @@ impl Storage for Mutex<DbModel> {
         config: &Option<ConnectorConfig>,
     ) -> DBResult<()> {
         let mut s = self.lock().await;
-        // `connector_id` needs to exist
-        if s.connectors.get(&(tenant_id, connector_id)).is_none() {
+        // `connector_id` needs to exist and must not be in the trash
+        if s.connectors.get(&(tenant_id, connector_id)).is_none()
+            || s.deleted_connectors
+                .contains_key(&(tenant_id, connector_id))
+        {
             return Err(DBError::UnknownConnector { connector_id }.into());
         }
-        // UNIQUE constraint on name
+        // UNIQUE constraint on the name of live connectors
         if let Some(c) = s
             .connectors
             .iter()
-            .filter(|k| k.0 .0 == tenant_id)
+            .filter(|k| k.0 .0 == tenant_id && !s.deleted_connectors.contains_key(k.0))
             .map(|k| k.1)
             .find(|c| c.name == connector_name)
             .cloned()
@@ -2330,17 +3476,87 @@ impl Storage for Mutex<DbModel> {
         connector_id: super::ConnectorId,
     ) -> DBResult<()> {
         let mut s = self.lock().await;
-        s.connectors
-            .remove(&(tenant_id, connector_id))
-            .ok_or(DBError::UnknownConnector { connector_id })?;
-        s.pipelines.values_mut().for_each(|c| {
-            c.descriptor
-                .attached_connectors
-                .retain(|c| c.connector_id != connector_id);
-        });
+        if !s.connectors.contains_key(&(tenant_id, connector_id))
+            || s.deleted_connectors
+                .contains_key(&(tenant_id, connector_id))
+        {
+            return Err(DBError::UnknownConnector { connector_id });
+        }
+        s.deleted_connectors
+            .insert((tenant_id, connector_id), Utc::now());
         Ok(())
     }
 
+    async fn restore_connector(
+        &self,
+        tenant_id: TenantId,
+        connector_id: ConnectorId,
+    ) -> DBResult<()> {
+        let mut s = self.lock().await;
+        if !s
+            .deleted_connectors
+            .contains_key(&(tenant_id, connector_id))
+        {
+            return Err(DBError::UnknownConnector { connector_id });
+        }
+        let name = &s.connectors[&(tenant_id, connector_id)].name;
+        if s.connectors.iter().any(|(k, c)| {
+            k.0 == tenant_id && !s.deleted_connectors.contains_key(k) && &c.name == name
+        }) {
+            return Err(DBError::DuplicateName);
+        }
+        s.deleted_connectors
+            .remove(&(tenant_id, connector_id))
+            .map(|_| ())
+            .ok_or(DBError::UnknownConnector { connector_id })
+    }
+
+    async fn list_deleted_connectors(
+        &self,
+        tenant_id: TenantId,
+    ) -> DBResult<Vec<DeletedConnector>> {
+        let s = self.lock().await;
+        Ok(s.deleted_connectors
+            .iter()
+            .filter(|k| k.0 .0 == tenant_id)
+            .map(|(k, deleted_at)| DeletedConnector {
+                descriptor: s.connectors[k].clone(),
+                deleted_at: *deleted_at,
+            })
+            .collect())
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> DBResult<u64> {
+        let mut s = self.lock().await;
+        let pipelines: Vec<_> = s
+            .deleted_pipelines
+            .iter()
+            .filter(|(_, deleted_at)| **deleted_at < deleted_before)
+            .map(|(k, _)| *k)
+            .collect();
+        let connectors: Vec<_> = s
+            .deleted_connectors
+            .iter()
+            .filter(|(_, deleted_at)| **deleted_at < deleted_before)
+            .map(|(k, _)| *k)
+            .collect();
+        for k in pipelines.iter() {
+            s.deleted_pipelines.remove(k);
+            s.pipelines.remove(k);
+            s.history.remove(k);
+        }
+        for k in connectors.iter() {
+            s.deleted_connectors.remove(k);
+            s.connectors.remove(k);
+            s.pipelines.values_mut().for_each(|p| {
+                p.descriptor
+                    .attached_connectors
+                    .retain(|ac| ac.connector_id != k.1);
+            });
+        }
+        Ok((pipelines.len() + connectors.len()) as u64)
+    }
+
     async fn store_api_key_hash(
         &self,
         tenant_id: TenantId,
@@ -2413,4 +3629,158 @@ impl Storage for Mutex<DbModel> {
     ) -> Result<(), DBError> {
         todo!("Unimplemented");
     }
+
+    async fn record_tenant_usage(&self, tenant_id: TenantId, usage: &TenantUsage) -> DBResult<()> {
+        let mut s = self.lock().await;
+        let total = s.usage.entry(tenant_id).or_default();
+        total.compile_time_ms += usage.compile_time_ms;
+        total.run_time_ms += usage.run_time_ms;
+        total.ingress_bytes += usage.ingress_bytes;
+        total.egress_bytes += usage.egress_bytes;
+        Ok(())
+    }
+
+    async fn get_tenant_usage(&self, tenant_id: TenantId) -> DBResult<TenantUsage> {
+        let s = self.lock().await;
+        Ok(s.usage.get(&tenant_id).copied().unwrap_or_default())
+    }
+
+    async fn new_webhook(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        retry_policy: &WebhookRetryPolicy,
+    ) -> DBResult<WebhookId> {
+        let mut s = self.lock().await;
+        let webhook_id = WebhookId(id);
+        if s.webhooks.keys().any(|k| k.1 == webhook_id) {
+            return Err(DBError::unique_key_violation("webhook_pkey"));
+        }
+        s.webhooks.insert(
+            (tenant_id, webhook_id),
+            WebhookSubscription {
+                descriptor: WebhookDescr {
+                    webhook_id,
+                    url: url.to_owned(),
+                    events: events.to_vec(),
+                    retry_policy: *retry_policy,
+                },
+                secret: secret.to_owned(),
+            },
+        );
+        Ok(webhook_id)
+    }
+
+    async fn list_webhooks(&self, tenant_id: TenantId) -> DBResult<Vec<WebhookDescr>> {
+        Ok(self
+            .list_webhook_subscriptions(tenant_id)
+            .await?
+            .into_iter()
+            .map(|s| s.descriptor)
+            .collect())
+    }
+
+    async fn delete_webhook(&self, tenant_id: TenantId, webhook_id: WebhookId) -> DBResult<()> {
+        let mut s = self.lock().await;
+        s.webhooks
+            .remove(&(tenant_id, webhook_id))
+            .map(|_| ())
+            .ok_or(DBError::UnknownWebhook { webhook_id })
+    }
+
+    async fn list_webhook_subscriptions(
+        &self,
+        tenant_id: TenantId,
+    ) -> DBResult<Vec<WebhookSubscription>> {
+        let s = self.lock().await;
+        Ok(s.webhooks
+            .iter()
+            .filter(|k| k.0 .0 == tenant_id)
+            .map(|k| k.1.clone())
+            .collect())
+    }
+    async fn new_deployment(
+        &self,
+        tenant_id: TenantId,
+        id: Uuid,
+        name: &str,
+        description: &str,
+        pipelines: &[PipelineId],
+        config: &Option<RuntimeConfig>,
+    ) -> DBResult<DeploymentId> {
+        let mut s = self.lock().await;
+        let deployment_id = DeploymentId(id);
+        if s.deployments.keys().any(|k| k.1 == deployment_id) {
+            return Err(DBError::unique_key_violation("deployment_pkey"));
+        }
+        // UNIQUE constraint on name
+        if s.deployments
+            .iter()
+            .any(|(k, d)| k.0 == tenant_id && d.name == name)
+        {
+            return Err(DBError::DuplicateName);
+        }
+        s.deployments.insert(
+            (tenant_id, deployment_id),
+            DeploymentDescr {
+                deployment_id,
+                name: name.to_owned(),
+                description: description.to_owned(),
+                pipelines: pipelines.to_vec(),
+                config: config.clone(),
+            },
+        );
+        Ok(deployment_id)
+    }
+
+    async fn list_deployments(&self, tenant_id: TenantId) -> DBResult<Vec<DeploymentDescr>> {
+        let s = self.lock().await;
+        Ok(s.deployments
+            .iter()
+            .filter(|k| k.0 .0 == tenant_id)
+            .map(|k| k.1.clone())
+            .collect())
+    }
+
+    async fn get_deployment_by_id(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> DBResult<DeploymentDescr> {
+        let s = self.lock().await;
+        s.deployments
+            .get(&(tenant_id, deployment_id))
+            .cloned()
+            .ok_or(DBError::UnknownDeployment { deployment_id })
+    }
+
+    async fn delete_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> DBResult<()> {
+        let mut s = self.lock().await;
+        s.deployments
+            .remove(&(tenant_id, deployment_id))
+            .map(|_| ())
+            .ok_or(DBError::UnknownDeployment { deployment_id })
+    }
+
+    async fn check_connection(&self) -> DBResult<()> {
+        Ok(())
+    }
+
+    async fn record_heartbeat(&self, service: &str, now: DateTime<Utc>) -> DBResult<()> {
+        let mut s = self.lock().await;
+        s.heartbeats.insert(service.to_string(), now);
+        Ok(())
+    }
+
+    async fn get_heartbeat(&self, service: &str) -> DBResult<Option<DateTime<Utc>>> {
+        let s = self.lock().await;
+        Ok(s.heartbeats.get(service).cloned())
+    }
 }
@@ -35,14 +35,23 @@ const RETRY_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(2
 /// redundnacy between sync() calls and receiving notifications from the
 /// registered LISTEN calls
 pub async fn listen(conn: Arc<tokio::sync::Mutex<ProjectDB>>, tx: UnboundedSender<DbNotification>) {
+    #[cfg(feature = "lite")]
+    {
+        let notifications = match &*conn.lock().await {
+            ProjectDB::Sqlite(db) => Some(db.subscribe()),
+            ProjectDB::Postgres(_) => None,
+        };
+        if let Some(notifications) = notifications {
+            return listen_sqlite(conn, notifications, tx).await;
+        }
+    }
+    let config = match &*conn.lock().await {
+        ProjectDB::Postgres(db) => db.config.clone(),
+        #[cfg(feature = "lite")]
+        ProjectDB::Sqlite(_) => unreachable!("handled above"),
+    };
     loop {
-        let (client, mut connection) = conn
-            .lock()
-            .await
-            .config
-            .connect(tokio_postgres::NoTls)
-            .await
-            .unwrap();
+        let (client, mut connection) = config.connect(tokio_postgres::NoTls).await.unwrap();
         let client = Arc::new(client);
         // Not clear why the calls to LISTEN block forever when we call it from the same
         // thread. But we need to call LISTEN before we can poll for async
@@ -111,6 +120,53 @@ pub async fn listen(conn: Arc<tokio::sync::Mutex<ProjectDB>>, tx: UnboundedSende
     }
 }
 
+/// Same as [`listen`] for the embedded SQLite database, whose triggers
+/// deliver notifications in-process.
+#[cfg(feature = "lite")]
+async fn listen_sqlite(
+    conn: Arc<tokio::sync::Mutex<ProjectDB>>,
+    mut notifications: tokio::sync::broadcast::Receiver<(String, String)>,
+    tx: UnboundedSender<DbNotification>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    // Subscribed before synchronizing, so no change is missed.
+    while let Err(e) = sync(&conn, &tx).await {
+        error!("Synchronize attempt returned an error {:?}", e);
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+    loop {
+        trace!("Waiting for notification");
+        match notifications.recv().await {
+            Ok((channel, payload)) => match parse_notification(&channel, &payload) {
+                Ok(n) => {
+                    debug!("Handling notification {:?}", n);
+                    let res = tx.send(n);
+                    if res.is_err() {
+                        error!("Received send error {:?}", res);
+                    }
+                }
+                Err(e) => {
+                    error!("Unexpected notification from the DB {:?}", e);
+                }
+            },
+            // Notifications were dropped because we fell behind: resynchronize,
+            // like after a reconnect to Postgres.
+            Err(RecvError::Lagged(n)) => {
+                warn!("Missed {n} notifications, resynchronizing");
+                while let Err(e) = sync(&conn, &tx).await {
+                    error!("Synchronize attempt returned an error {:?}", e);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+            }
+            Err(RecvError::Closed) => {
+                error!("Reconciler notification channel closed");
+                return;
+            }
+        }
+    }
+}
+
 /// Synchronizes with the current state of the pipeline and program tables,
 /// issuing an Add event per row.
 async fn sync(
@@ -17,7 +17,7 @@
 //! `dbsp_adapters` crate, i.e., errors returned by the pipeline manager and
 //! by individual pipelines have the same format.
 
-use crate::db::DBError;
+use crate::db::{DBError, ProgramId, Version};
 use crate::runner::RunnerError;
 use actix_web::{
     body::BoxBody, http::StatusCode, HttpResponse, HttpResponseBuilder, ResponseError,
@@ -73,6 +73,36 @@ pub enum ManagerError {
     RustCompilerError {
         error: String,
     },
+    InvalidManifest {
+        error: String,
+    },
+    InvalidWebhookUrl {
+        url: String,
+        error: String,
+    },
+    InvalidWebhookRetryPolicy {
+        error: String,
+    },
+    InvalidDeployment {
+        error: String,
+    },
+    InvalidDeploymentAction {
+        action: String,
+    },
+    PrometheusError {
+        error: String,
+    },
+    UnknownRemoteJob {
+        program_id: ProgramId,
+        version: Version,
+    },
+    RemoteWorkerError {
+        error: String,
+    },
+    UnauthorizedWorker,
+    ArtifactStoreError {
+        error: String,
+    },
 }
 
 impl ManagerError {
@@ -171,6 +201,42 @@ impl Display for ManagerError {
             Self::RustCompilerError { error } => {
                 write!(f, "Error compiling generated Rust code: {error}")
             }
+            Self::InvalidManifest { error } => {
+                write!(f, "Invalid manifest: {error}")
+            }
+            Self::InvalidWebhookUrl { url, error } => {
+                write!(f, "Invalid webhook URL '{url}': {error}")
+            }
+            Self::InvalidWebhookRetryPolicy { error } => {
+                write!(f, "Invalid webhook retry policy: {error}")
+            }
+            Self::InvalidDeployment { error } => {
+                write!(f, "Invalid deployment: {error}")
+            }
+            Self::InvalidDeploymentAction { action } => {
+                write!(f, "Invalid deployment action '{action}'; valid actions are: 'start' or 'shutdown'")
+            }
+            Self::PrometheusError { error } => {
+                write!(f, "Error retrieving Prometheus metrics: '{error}'")
+            }
+            Self::UnknownRemoteJob {
+                program_id,
+                version,
+            } => {
+                write!(
+                    f,
+                    "No remote build of program '{program_id}' version '{version}' is in progress"
+                )
+            }
+            Self::RemoteWorkerError { error } => {
+                write!(f, "Error communicating with the compiler service: {error}")
+            }
+            Self::UnauthorizedWorker => {
+                write!(f, "Request is missing a valid remote worker token")
+            }
+            Self::ArtifactStoreError { error } => {
+                write!(f, "Error accessing the artifact store: {error}")
+            }
         }
     }
 }
@@ -189,6 +255,16 @@ impl ResponseError for ManagerError {
             Self::IoError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidProgramSchema { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::RustCompilerError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidManifest { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidWebhookUrl { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidWebhookRetryPolicy { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidDeployment { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidDeploymentAction { .. } => StatusCode::BAD_REQUEST,
+            Self::PrometheusError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UnknownRemoteJob { .. } => StatusCode::NOT_FOUND,
+            Self::RemoteWorkerError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UnauthorizedWorker => StatusCode::UNAUTHORIZED,
+            Self::ArtifactStoreError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
@@ -211,6 +287,16 @@ impl DetailedError for ManagerError {
             Self::IoError { .. } => Cow::from("ManagerIoError"),
             Self::InvalidProgramSchema { .. } => Cow::from("InvalidProgramSchema"),
             Self::RustCompilerError { .. } => Cow::from("RustCompilerError"),
+            Self::InvalidManifest { .. } => Cow::from("InvalidManifest"),
+            Self::InvalidWebhookUrl { .. } => Cow::from("InvalidWebhookUrl"),
+            Self::InvalidWebhookRetryPolicy { .. } => Cow::from("InvalidWebhookRetryPolicy"),
+            Self::InvalidDeployment { .. } => Cow::from("InvalidDeployment"),
+            Self::InvalidDeploymentAction { .. } => Cow::from("InvalidDeploymentAction"),
+            Self::PrometheusError { .. } => Cow::from("PrometheusError"),
+            Self::UnknownRemoteJob { .. } => Cow::from("UnknownRemoteJob"),
+            Self::RemoteWorkerError { .. } => Cow::from("RemoteWorkerError"),
+            Self::UnauthorizedWorker => Cow::from("UnauthorizedWorker"),
+            Self::ArtifactStoreError { .. } => Cow::from("ArtifactStoreError"),
         }
     }
 
@@ -0,0 +1,151 @@
+//! Liveness and readiness of the manager.
+//!
+//! The compiler and the runner may run in processes separate from the API
+//! server, so they report their liveness by periodically recording a
+//! heartbeat in the database (see [`with_heartbeat`]).  The API server
+//! considers a service to be alive if its last heartbeat is recent enough.
+use crate::db::{storage::Storage, DBError, ProjectDB};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::Duration};
+use tokio::{select, sync::Mutex, time::timeout};
+
+/// Name under which the compiler records its heartbeat.
+pub(crate) const COMPILER_SERVICE: &str = "compiler";
+
+/// Name under which the runner records its heartbeat.
+pub(crate) const RUNNER_SERVICE: &str = "runner";
+
+/// How often services record their heartbeat.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A service whose last heartbeat is older than this is considered dead.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Max time a single readiness check may take.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs `task`, recording a heartbeat for `service` every
+/// `HEARTBEAT_INTERVAL` until the task completes.
+pub(crate) async fn with_heartbeat<F>(
+    db: Arc<Mutex<ProjectDB>>,
+    service: &'static str,
+    task: F,
+) -> F::Output
+where
+    F: Future,
+{
+    tokio::pin!(task);
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        select! {
+            output = &mut task => return output,
+            _ = interval.tick() => {
+                // `task` may be suspended while holding the database lock, so
+                // record the heartbeat from a separate task instead of
+                // blocking here.
+                let db = db.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = db.lock().await.record_heartbeat(service, Utc::now()).await {
+                        warn!("Failed to record heartbeat of the {service}: {e}");
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Outcome of a single readiness check.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub(crate) struct CheckResult {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn from_result(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self {
+                ok: true,
+                error: None,
+            },
+            Err(error) => Self {
+                ok: false,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Body of the `/readyz` response.
+#[derive(Serialize, Debug)]
+pub(crate) struct ReadinessReport {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, CheckResult>,
+}
+
+/// Checks that the database is reachable and that the compiler and the
+/// runner are alive.
+pub(crate) async fn readiness(db: &Arc<Mutex<ProjectDB>>) -> ReadinessReport {
+    let mut checks = BTreeMap::new();
+    let database = run_check(async { db.lock().await.check_connection().await }).await;
+    checks.insert("database", CheckResult::from_result(database));
+    for service in [COMPILER_SERVICE, RUNNER_SERVICE] {
+        let result = run_check(async { db.lock().await.get_heartbeat(service).await })
+            .await
+            .and_then(|last_seen| check_heartbeat(last_seen, Utc::now()));
+        checks.insert(service, CheckResult::from_result(result));
+    }
+    ReadinessReport {
+        ready: checks.values().all(|check| check.ok),
+        checks,
+    }
+}
+
+async fn run_check<T, F>(check: F) -> Result<T, String>
+where
+    F: Future<Output = Result<T, DBError>>,
+{
+    match timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!(
+            "timed out after {} seconds",
+            CHECK_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+fn check_heartbeat(last_seen: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), String> {
+    let last_seen = match last_seen {
+        Some(last_seen) => last_seen,
+        None => return Err("no heartbeat recorded".to_string()),
+    };
+    let age = (now - last_seen).to_std().unwrap_or_default();
+    if age > HEARTBEAT_TIMEOUT {
+        Err(format!("last heartbeat {} seconds ago", age.as_secs()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_heartbeat;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn heartbeat_age() {
+        let now = Utc::now();
+        assert!(check_heartbeat(None, now).is_err());
+        assert!(check_heartbeat(Some(now - Duration::seconds(5)), now).is_ok());
+        // Clocks of different processes may be slightly skewed.
+        assert!(check_heartbeat(Some(now + Duration::seconds(1)), now).is_ok());
+        assert_eq!(
+            check_heartbeat(Some(now - Duration::seconds(60)), now),
+            Err("last heartbeat 60 seconds ago".to_string())
+        );
+    }
+}
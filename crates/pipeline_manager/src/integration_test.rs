@@ -14,7 +14,7 @@ use tokio::{sync::OnceCell, time::sleep};
 
 use crate::{
     compiler::Compiler,
-    config::{ApiServerConfig, CompilerConfig, DatabaseConfig, LocalRunnerConfig},
+    config::{ApiServerConfig, CompilerConfig, DatabaseConfig, LocalRunnerConfig, LogFormat},
     db::{Pipeline, PipelineStatus},
 };
 use std::sync::Arc;
@@ -28,7 +28,7 @@ const TEST_DBSP_DEFAULT_PORT: u16 = 8089;
 static LOCAL_DBSP_INSTANCE: OnceCell<TempDir> = OnceCell::const_new();
 
 async fn initialize_local_pipeline_manager_instance() -> TempDir {
-    crate::logging::init_logging("[manager]".cyan());
+    crate::logging::init_logging("[manager]".cyan(), LogFormat::Text);
     println!("Performing one time initialization for integration tests.");
     println!("Initializing a postgres container");
     let _output = Command::new("docker")
@@ -61,6 +61,10 @@ async fn initialize_local_pipeline_manager_instance() -> TempDir {
         dev_mode: false,
         dump_openapi: false,
         config_file: None,
+        trash_retention_days: 7,
+        alerting_config: None,
+        log_format: LogFormat::Text,
+        lite: false,
     }
     .canonicalize()
     .unwrap();
@@ -72,12 +76,25 @@ async fn initialize_local_pipeline_manager_instance() -> TempDir {
         precompile: true,
         binary_ref_host: "127.0.0.1".to_string(),
         binary_ref_port: 9090,
+        binary_ref_bind_address: "0.0.0.0".to_string(),
+        shutdown_drain_secs: 60,
+        remote_workers: false,
+        remote_worker_timeout_secs: 3600,
+        worker_token_file: None,
+        artifact_store: None,
+        jit: false,
+        jit_pipeline_path: None,
+        rustc_target_cpu: None,
+        rustc_codegen_units: None,
+        rustc_lto: None,
+        extra_rustflags: None,
     }
     .canonicalize()
     .unwrap();
     let local_runner_config = LocalRunnerConfig {
         runner_working_directory: workdir.to_owned(),
         pipeline_host: "127.0.0.1".to_owned(),
+        in_process: false,
     }
     .canonicalize()
     .unwrap();
@@ -110,8 +127,9 @@ async fn initialize_local_pipeline_manager_instance() -> TempDir {
                 .unwrap();
                 let db = Arc::new(Mutex::new(db));
                 let db_clone = db.clone();
+                let (_shutdown_sender, shutdown) = tokio::sync::watch::channel(false);
                 let _compiler = tokio::spawn(async move {
-                    crate::compiler::Compiler::run(&compiler_config.clone(), db_clone)
+                    crate::compiler::Compiler::run(&compiler_config.clone(), db_clone, shutdown)
                         .await
                         .unwrap();
                 });
@@ -776,7 +794,7 @@ async fn json_ingress() {
     assert_eq!(req.status(), StatusCode::BAD_REQUEST);
     let body = req.body().await.unwrap();
     let error = std::str::from_utf8(&body).unwrap();
-    assert_eq!(error, "{\"message\":\"Errors parsing input data (2 errors):\\n    Parse error (event #2): failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\\nInvalid fragment: '[40, \\\"foo\\\", \\\"buzz\\\"]'\\n    Parse error (event #3): failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\\nInvalid fragment: '[true, true, \\\"\\\"]'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\",\"event_number\":2,\"field\":\"C2\",\"invalid_bytes\":null,\"invalid_text\":\"[40, \\\"foo\\\", \\\"buzz\\\"]\",\"suggestion\":null},{\"description\":\"failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\",\"event_number\":3,\"field\":\"C1\",\"invalid_bytes\":null,\"invalid_text\":\"[true, true, \\\"\\\"]\",\"suggestion\":null}],\"num_errors\":2}}");
+    assert_eq!(error, "{\"message\":\"Errors parsing input data (0 records accepted, 2 errors):\\n    Parse error (event #2, line 1): failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\\nInvalid fragment: '[40, \\\"foo\\\", \\\"buzz\\\"]'\\n    Parse error (event #3, line 1): failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\\nInvalid fragment: '[true, true, \\\"\\\"]'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\",\"event_number\":2,\"expected_type\":null,\"field\":\"C2\",\"field_value\":null,\"invalid_bytes\":null,\"invalid_text\":\"[40, \\\"foo\\\", \\\"buzz\\\"]\",\"line_number\":1,\"suggestion\":null},{\"description\":\"failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\",\"event_number\":3,\"expected_type\":null,\"field\":\"C1\",\"field_value\":null,\"invalid_bytes\":null,\"invalid_text\":\"[true, true, \\\"\\\"]\",\"line_number\":1,\"suggestion\":null}],\"num_errors\":2,\"num_records\":0}}");
 
     // Even records that are parsed successfully don't get ingested when
     // using array format.
@@ -795,7 +813,7 @@ async fn json_ingress() {
     assert_eq!(req.status(), StatusCode::BAD_REQUEST);
     let body = req.body().await.unwrap();
     let error = std::str::from_utf8(&body).unwrap();
-    assert_eq!(error, "{\"message\":\"Errors parsing input data (2 errors):\\n    Parse error (event #2): failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\\nInvalid fragment: '[40, \\\"foo\\\", \\\"buzz\\\"]'\\n    Parse error (event #3): failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\\nInvalid fragment: '[true, true, \\\"\\\"]'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\",\"event_number\":2,\"field\":\"C2\",\"invalid_bytes\":null,\"invalid_text\":\"[40, \\\"foo\\\", \\\"buzz\\\"]\",\"suggestion\":null},{\"description\":\"failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\",\"event_number\":3,\"field\":\"C1\",\"invalid_bytes\":null,\"invalid_text\":\"[true, true, \\\"\\\"]\",\"suggestion\":null}],\"num_errors\":2}}");
+    assert_eq!(error, "{\"message\":\"Errors parsing input data (1 records accepted, 2 errors):\\n    Parse error (event #2, line 1): failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\\nInvalid fragment: '[40, \\\"foo\\\", \\\"buzz\\\"]'\\n    Parse error (event #3, line 1): failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\\nInvalid fragment: '[true, true, \\\"\\\"]'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize JSON record: error parsing field 'C2': invalid type: string \\\"foo\\\", expected a boolean at line 1 column 10\",\"event_number\":2,\"expected_type\":null,\"field\":\"C2\",\"field_value\":null,\"invalid_bytes\":null,\"invalid_text\":\"[40, \\\"foo\\\", \\\"buzz\\\"]\",\"line_number\":1,\"suggestion\":null},{\"description\":\"failed to deserialize JSON record: error parsing field 'C1': invalid type: boolean `true`, expected i32 at line 1 column 5\",\"event_number\":3,\"expected_type\":null,\"field\":\"C1\",\"field_value\":null,\"invalid_bytes\":null,\"invalid_text\":\"[true, true, \\\"\\\"]\",\"line_number\":1,\"suggestion\":null}],\"num_errors\":2,\"num_records\":1}}");
 
     // Even records that are parsed successfully don't get ingested when
     // using array format.
@@ -835,7 +853,7 @@ not_a_number,true,ΑαΒβΓγΔδ
     assert_eq!(req.status(), StatusCode::BAD_REQUEST);
     let body = req.body().await.unwrap();
     let error = std::str::from_utf8(&body).unwrap();
-    assert_eq!(error, "{\"message\":\"Errors parsing input data (1 errors):\\n    Parse error (event #2): failed to deserialize CSV record: error parsing field 'C1': field 0: invalid digit found in string\\nInvalid fragment: 'not_a_number,true,ΑαΒβΓγΔδ\\n'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize CSV record: error parsing field 'C1': field 0: invalid digit found in string\",\"event_number\":2,\"field\":\"C1\",\"invalid_bytes\":null,\"invalid_text\":\"not_a_number,true,ΑαΒβΓγΔδ\\n\",\"suggestion\":null}],\"num_errors\":1}}");
+    assert_eq!(error, "{\"message\":\"Errors parsing input data (2 records accepted, 1 errors):\\n    Parse error (event #2, line 2): failed to deserialize CSV record: error parsing field 'C1': field 0: invalid digit found in string\\nInvalid value: 'not_a_number' (expected INTEGER)\\nInvalid fragment: 'not_a_number,true,ΑαΒβΓγΔδ\\n'\",\"error_code\":\"ParseErrors\",\"details\":{\"errors\":[{\"description\":\"failed to deserialize CSV record: error parsing field 'C1': field 0: invalid digit found in string\",\"event_number\":2,\"expected_type\":\"INTEGER\",\"field\":\"C1\",\"field_value\":\"not_a_number\",\"invalid_bytes\":null,\"invalid_text\":\"not_a_number,true,ΑαΒβΓγΔδ\\n\",\"line_number\":2,\"suggestion\":null}],\"num_errors\":1,\"num_records\":2}}");
 
     let quantiles = config.quantiles_json(&id, "T1").await;
     assert_eq!(
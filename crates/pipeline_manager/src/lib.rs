@@ -1,10 +1,17 @@
+mod alerting;
+mod apply;
+mod artifact_store;
 mod auth;
 mod error;
+mod health;
 #[cfg(test)]
 #[cfg(feature = "integration-test")]
 mod integration_test;
+mod metrics;
+mod webhooks;
 
 pub mod api;
+pub mod compile_worker;
 pub mod compiler;
 pub mod config;
 pub mod db;
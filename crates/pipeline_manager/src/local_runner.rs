@@ -1,22 +1,33 @@
 /// A local runner that watches for pipeline objects in the API
-/// and instantiates them locally as processes.
+/// and instantiates them locally, either as processes or, with
+/// [`LocalRunnerConfig::in_process`], as servers inside the runner process.
+use crate::compiler::{JIT_IR_ARTIFACT, JIT_SCHEMA_ARTIFACT};
 use crate::db_notifier::{DbNotification, Operation};
 use crate::pipeline_automata::{fetch_binary_ref, PipelineAutomaton};
 use crate::pipeline_automata::{PipelineExecutionDesc, PipelineExecutor};
 use crate::{
     api::ManagerError,
+    auth::TenantId,
     config::LocalRunnerConfig,
     db::{PipelineId, ProjectDB},
+    health::{with_heartbeat, RUNNER_SERVICE},
     runner::RunnerError,
 };
 use async_trait::async_trait;
+use clap::Parser;
+use dbsp_adapters::{
+    jit::{start_circuit_from_files, CircuitConfig},
+    server::{start_embedded_server, EmbeddedServer, ServerArgs, DRAINED_EXIT_CODE},
+};
 use log::trace;
 use std::{
     collections::BTreeMap,
+    path::{Path, PathBuf},
     process::Stdio,
     process::{Child, Command},
     sync::Arc,
 };
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Notify;
 use tokio::{
     fs,
@@ -46,29 +57,8 @@ impl PipelineExecutor for ProcessRunner {
         let program_id = ped.program_id;
         let version = ped.version;
 
-        log::debug!("Pipeline config is '{:?}'", ped.config);
-
-        // Create pipeline directory (delete old directory if exists); write metadata
-        // and config files to it.
-        let pipeline_dir = self.config.pipeline_dir(pipeline_id);
-
-        let _ = remove_dir_all(&pipeline_dir).await;
-        create_dir_all(&pipeline_dir).await.map_err(|e| {
-            ManagerError::io_error(
-                format!("creating pipeline directory '{}'", pipeline_dir.display()),
-                e,
-            )
-        })?;
-        let config_file_path = self.config.config_file_path(pipeline_id);
-        let expanded_config = serde_yaml::to_string(&ped.config).unwrap();
-        fs::write(&config_file_path, &expanded_config)
-            .await
-            .map_err(|e| {
-                ManagerError::io_error(
-                    format!("writing config file '{}'", config_file_path.display()),
-                    e,
-                )
-            })?;
+        let (config_file_path, auth_token_file_path) =
+            prepare_pipeline_dir(&self.config, &ped).await?;
 
         let fetched_executable = fetch_binary_ref(
             &self.config,
@@ -85,6 +75,8 @@ impl PipelineExecutor for ProcessRunner {
             .current_dir(self.config.pipeline_dir(pipeline_id))
             .arg("--config-file")
             .arg(&config_file_path)
+            .arg("--auth-token-file")
+            .arg(&auth_token_file_path)
             .stdin(Stdio::null())
             .spawn()
             .map_err(|e| RunnerError::PipelineStartupError {
@@ -96,22 +88,11 @@ impl PipelineExecutor for ProcessRunner {
     }
 
     async fn get_location(&mut self) -> Result<Option<String>, ManagerError> {
-        let port_file_path = self.config.port_file_path(self.pipeline_id);
-        let host = &self.config.pipeline_host;
-
-        match fs::read_to_string(port_file_path).await {
-            Ok(port) => {
-                let parse = port.trim().parse::<u16>();
-                match parse {
-                    Ok(port) => Ok(Some(format!("{host}:{port}"))),
-                    Err(e) => Err(ManagerError::from(RunnerError::PortFileParseError {
-                        pipeline_id: self.pipeline_id,
-                        error: e.to_string(),
-                    })),
-                }
-            }
-            Err(_) => Ok(None),
-        }
+        read_location(&self.config, self.pipeline_id).await
+    }
+
+    async fn get_tls_certificate(&mut self) -> Result<Option<String>, ManagerError> {
+        read_tls_certificate(&self.config, self.pipeline_id).await
     }
 
     async fn check_if_shutdown(&mut self) -> bool {
@@ -121,20 +102,239 @@ impl PipelineExecutor for ProcessRunner {
             .unwrap_or(true)
     }
 
+    async fn check_if_drained(&mut self) -> bool {
+        self.pipeline_process
+            .as_mut()
+            .and_then(|p| p.try_wait().ok().flatten())
+            .map(|status| status.code() == Some(DRAINED_EXIT_CODE))
+            .unwrap_or(false)
+    }
+
     async fn shutdown(&mut self) -> Result<(), ManagerError> {
         self.pipeline_process = None;
-        match remove_dir_all(self.config.pipeline_dir(self.pipeline_id)).await {
-            Ok(_) => (),
-            Err(e) => {
-                log::warn!(
-                    "Failed to delete pipeline directory for pipeline {}: {}",
-                    self.pipeline_id,
-                    e
-                );
-            }
+        remove_pipeline_dir(&self.config, self.pipeline_id).await;
+        Ok(())
+    }
+}
+
+/// A handle to a pipeline server running on a thread of the runner process
+/// that stops the pipeline on `drop`.
+///
+/// Only programs compiled with the JIT backend can run in-process: the
+/// runner downloads the dataflow IR and schema artifacts of the program
+/// (see [`JIT_ARTIFACTS`](crate::compiler::JIT_ARTIFACTS)) and instantiates
+/// the circuit itself.
+pub struct InProcessRunner {
+    pipeline_id: PipelineId,
+    server: Option<EmbeddedServer>,
+    config: Arc<LocalRunnerConfig>,
+}
+
+#[async_trait]
+impl PipelineExecutor for InProcessRunner {
+    async fn start(&mut self, ped: PipelineExecutionDesc) -> Result<(), ManagerError> {
+        let pipeline_id = ped.pipeline_id;
+        let (config_file_path, auth_token_file_path) =
+            prepare_pipeline_dir(&self.config, &ped).await?;
+
+        let pipeline_dir = self.config.pipeline_dir(pipeline_id);
+        let ir_path = pipeline_dir.join(JIT_IR_ARTIFACT);
+        let schema_path = pipeline_dir.join(JIT_SCHEMA_ARTIFACT);
+        for (artifact, path) in [
+            (JIT_IR_ARTIFACT, &ir_path),
+            (JIT_SCHEMA_ARTIFACT, &schema_path),
+        ] {
+            fetch_jit_artifact(pipeline_id, &ped.binary_ref, artifact, path).await?;
         }
+
+        let args = ServerArgs::try_parse_from([
+            "pipeline".into(),
+            "--config-file".into(),
+            config_file_path.into_os_string(),
+            "--auth-token-file".into(),
+            auth_token_file_path.into_os_string(),
+            "--working-directory".into(),
+            pipeline_dir.into_os_string(),
+        ])
+        .map_err(|e| RunnerError::PipelineStartupError {
+            pipeline_id,
+            error: e.to_string(),
+        })?;
+        let ir_path = ir_path.display().to_string();
+        let schema_path = schema_path.display().to_string();
+        self.server = Some(start_embedded_server(args, &[], move |workers| {
+            let config = CircuitConfig::default()
+                .release(true)
+                .optimize(true)
+                .workers(workers);
+            start_circuit_from_files(&schema_path, &ir_path, config)
+        }));
         Ok(())
     }
+
+    async fn get_location(&mut self) -> Result<Option<String>, ManagerError> {
+        read_location(&self.config, self.pipeline_id).await
+    }
+
+    async fn get_tls_certificate(&mut self) -> Result<Option<String>, ManagerError> {
+        read_tls_certificate(&self.config, self.pipeline_id).await
+    }
+
+    async fn check_if_shutdown(&mut self) -> bool {
+        self.server
+            .as_ref()
+            .map_or(true, EmbeddedServer::is_finished)
+    }
+
+    async fn check_if_drained(&mut self) -> bool {
+        self.server
+            .as_ref()
+            .map_or(false, |server| server.is_finished() && server.is_drained())
+    }
+
+    async fn shutdown(&mut self) -> Result<(), ManagerError> {
+        if let Some(server) = self.server.take() {
+            // Stopping the circuit joins its worker threads.
+            let _ = tokio::task::spawn_blocking(move || server.stop()).await;
+        }
+        remove_pipeline_dir(&self.config, self.pipeline_id).await;
+        Ok(())
+    }
+}
+
+/// Download `artifact` of a program compiled with the JIT backend, whose
+/// launcher is at `binary_ref`, to `path`.
+async fn fetch_jit_artifact(
+    pipeline_id: PipelineId,
+    binary_ref: &str,
+    artifact: &str,
+    path: &Path,
+) -> Result<(), ManagerError> {
+    let url = format!("{binary_ref}/{artifact}");
+    let not_jit = || RunnerError::PipelineStartupError {
+        pipeline_id,
+        error: "in-process pipelines require programs compiled with the JIT backend".to_string(),
+    };
+    if !binary_ref.starts_with("http://") && !binary_ref.starts_with("https://") {
+        return Err(not_jit().into());
+    }
+    let fetch_error = |e: reqwest::Error| RunnerError::BinaryFetchError {
+        pipeline_id,
+        error: format!("Fetching '{url}' returned an error: {e}"),
+    };
+    let response = reqwest::get(&url).await.map_err(fetch_error)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(not_jit().into());
+    }
+    let contents = response
+        .error_for_status()
+        .map_err(fetch_error)?
+        .bytes()
+        .await
+        .map_err(fetch_error)?;
+    fs::write(path, contents)
+        .await
+        .map_err(|e| ManagerError::io_error(format!("writing '{}'", path.display()), e))
+}
+
+/// Create the pipeline directory (deleting the old directory if it exists)
+/// and write the pipeline config and auth token files to it.
+///
+/// Returns the paths of the config and the auth token files.
+async fn prepare_pipeline_dir(
+    config: &LocalRunnerConfig,
+    ped: &PipelineExecutionDesc,
+) -> Result<(PathBuf, PathBuf), ManagerError> {
+    let pipeline_id = ped.pipeline_id;
+    log::debug!("Pipeline config is '{:?}'", ped.config);
+
+    let pipeline_dir = config.pipeline_dir(pipeline_id);
+
+    let _ = remove_dir_all(&pipeline_dir).await;
+    create_dir_all(&pipeline_dir).await.map_err(|e| {
+        ManagerError::io_error(
+            format!("creating pipeline directory '{}'", pipeline_dir.display()),
+            e,
+        )
+    })?;
+    let config_file_path = config.config_file_path(pipeline_id);
+    let expanded_config = serde_yaml::to_string(&ped.config).unwrap();
+    fs::write(&config_file_path, &expanded_config)
+        .await
+        .map_err(|e| {
+            ManagerError::io_error(
+                format!("writing config file '{}'", config_file_path.display()),
+                e,
+            )
+        })?;
+
+    // Make the token only readable by the owner.
+    let auth_token_file_path = config.auth_token_file_path(pipeline_id);
+    let write_token = async {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&auth_token_file_path)
+            .await?
+            .write_all(ped.auth_token.as_bytes())
+            .await
+    };
+    write_token.await.map_err(|e| {
+        ManagerError::io_error(
+            format!("writing token file '{}'", auth_token_file_path.display()),
+            e,
+        )
+    })?;
+
+    Ok((config_file_path, auth_token_file_path))
+}
+
+/// Read the address of the pipeline's HTTP server from the port file that the
+/// server writes once it is listening.
+async fn read_location(
+    config: &LocalRunnerConfig,
+    pipeline_id: PipelineId,
+) -> Result<Option<String>, ManagerError> {
+    let port_file_path = config.port_file_path(pipeline_id);
+    let host = &config.pipeline_host;
+
+    match fs::read_to_string(port_file_path).await {
+        Ok(port) => {
+            let parse = port.trim().parse::<u16>();
+            match parse {
+                Ok(port) => Ok(Some(format!("{host}:{port}"))),
+                Err(e) => Err(ManagerError::from(RunnerError::PortFileParseError {
+                    pipeline_id,
+                    error: e.to_string(),
+                })),
+            }
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+async fn read_tls_certificate(
+    config: &LocalRunnerConfig,
+    pipeline_id: PipelineId,
+) -> Result<Option<String>, ManagerError> {
+    // The pipeline writes its certificate before the port file, so it is
+    // either in place by now or the pipeline is not using TLS.
+    let cert_file_path = config.cert_file_path(pipeline_id);
+    match fs::read_to_string(&cert_file_path).await {
+        Ok(certificate) => Ok(Some(certificate)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ManagerError::io_error(
+            format!("reading certificate file '{}'", cert_file_path.display()),
+            e,
+        )),
+    }
+}
+
+async fn remove_pipeline_dir(config: &LocalRunnerConfig, pipeline_id: PipelineId) {
+    if let Err(e) = remove_dir_all(config.pipeline_dir(pipeline_id)).await {
+        log::warn!("Failed to delete pipeline directory for pipeline {pipeline_id}: {e}");
+    }
 }
 
 /// Starts a runner that executes pipelines locally
@@ -148,13 +348,20 @@ impl PipelineExecutor for ProcessRunner {
 /// recorded in the database.  In the latter case, the error message is
 /// returned to the client.
 ///
+/// With [`LocalRunnerConfig::in_process`], the runner instead starts the
+/// pipeline server on a thread of its own process (see [`InProcessRunner`]).
+///
 /// # Shutting down a pipeline
 ///
 /// To shutdown the pipeline, the runner sends a `/shutdown` HTTP request to the
 /// pipeline.  This request is asynchronous: the pipeline may continue running
 /// for a few seconds after the request succeeds.
 pub async fn run(db: Arc<Mutex<ProjectDB>>, config: &LocalRunnerConfig) {
-    let runner_task = spawn(reconcile(db, Arc::new(config.clone())));
+    let runner_task = spawn(with_heartbeat(
+        db.clone(),
+        RUNNER_SERVICE,
+        reconcile(db, Arc::new(config.clone())),
+    ));
     runner_task.await.unwrap().unwrap();
 }
 
@@ -177,21 +384,33 @@ async fn reconcile(
                         .entry(pipeline_id)
                         .or_insert_with(|| {
                             let notifier = Arc::new(Notify::new());
-                            let pipeline_handle = ProcessRunner {
-                                pipeline_id,
-                                pipeline_process: None,
-                                config: config.clone(),
-                            };
-                            spawn(
-                                PipelineAutomaton::new(
+                            if config.in_process {
+                                let pipeline_handle = InProcessRunner {
+                                    pipeline_id,
+                                    server: None,
+                                    config: config.clone(),
+                                };
+                                spawn_automaton(
+                                    pipeline_id,
+                                    tenant_id,
+                                    &db,
+                                    &notifier,
+                                    pipeline_handle,
+                                );
+                            } else {
+                                let pipeline_handle = ProcessRunner {
+                                    pipeline_id,
+                                    pipeline_process: None,
+                                    config: config.clone(),
+                                };
+                                spawn_automaton(
                                     pipeline_id,
                                     tenant_id,
-                                    db.clone(),
-                                    notifier.clone(),
+                                    &db,
+                                    &notifier,
                                     pipeline_handle,
-                                )
-                                .run(),
-                            );
+                                );
+                            }
                             notifier
                         })
                         .notify_one();
@@ -206,3 +425,24 @@ async fn reconcile(
         }
     }
 }
+
+fn spawn_automaton<T>(
+    pipeline_id: PipelineId,
+    tenant_id: TenantId,
+    db: &Arc<Mutex<ProjectDB>>,
+    notifier: &Arc<Notify>,
+    pipeline_handle: T,
+) where
+    T: PipelineExecutor + 'static,
+{
+    spawn(
+        PipelineAutomaton::new(
+            pipeline_id,
+            tenant_id,
+            db.clone(),
+            notifier.clone(),
+            pipeline_handle,
+        )
+        .run(),
+    );
+}
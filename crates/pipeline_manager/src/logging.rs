@@ -1,10 +1,41 @@
+use crate::{auth::TenantId, config::LogFormat};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    Error as ActixError,
+};
 use colored::ColoredString;
 use env_logger::Env;
-use std::io::Write;
+use log::Record;
+use serde_json::{json, Value};
+use std::{
+    future::Future,
+    io::Write,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
 
-pub fn init_logging(name: ColoredString) {
-    let _ = env_logger::Builder::from_env(Env::default().default_filter_or("info"))
-        .format(move |buf, record| {
+/// Header that carries the id of a request.  Clients can set it to correlate
+/// their logs with the manager's; otherwise the manager generates an id.  The
+/// id is returned in the same header of the response.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The HTTP request handled by the current task.
+struct RequestContext {
+    request_id: String,
+    route: String,
+    /// Set once the request is authenticated.
+    tenant_id: Mutex<Option<TenantId>>,
+}
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: Arc<RequestContext>;
+}
+
+pub fn init_logging(name: ColoredString, format: LogFormat) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    match format {
+        LogFormat::Text => builder.format(move |buf, record| {
             let t = chrono::Utc::now();
             let t = format!("{}", t.format("%Y-%m-%d %H:%M:%S"));
             writeln!(
@@ -15,6 +46,119 @@ pub fn init_logging(name: ColoredString) {
                 name,
                 record.args()
             )
-        })
-        .try_init();
+        }),
+        LogFormat::Json => {
+            let service = name.trim_matches(|c| c == '[' || c == ']').to_string();
+            builder.format(move |buf, record| writeln!(buf, "{}", json_record(&service, record)))
+        }
+    };
+    let _ = builder.try_init();
+}
+
+/// Converts a log record into a JSON object, adding the id, route and tenant of
+/// the request being handled by the current task, if any.
+fn json_record(service: &str, record: &Record) -> Value {
+    let mut object = json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "service": service,
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    let _ = REQUEST_CONTEXT.try_with(|context| {
+        object["request_id"] = json!(context.request_id);
+        object["route"] = json!(context.route);
+        if let Some(tenant_id) = *context.tenant_id.lock().unwrap() {
+            object["tenant_id"] = json!(tenant_id.0);
+        }
+    });
+    object
+}
+
+/// Records the tenant of the request being handled by the current task, so
+/// that it's included in subsequent log messages.
+pub(crate) fn set_tenant_id(tenant_id: TenantId) {
+    let _ = REQUEST_CONTEXT.try_with(|context| {
+        *context.tenant_id.lock().unwrap() = Some(tenant_id);
+    });
+}
+
+/// Middleware that makes the id and route of each request available to log
+/// messages emitted while handling it.
+pub(crate) fn with_request_context<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, ActixError>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixError>,
+{
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::now_v7().to_string());
+    let context = Arc::new(RequestContext {
+        request_id,
+        route: req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string()),
+        tenant_id: Mutex::new(None),
+    });
+
+    // Inner services do part of their work when called and the rest when
+    // their future is polled; both must see the context.
+    let response = REQUEST_CONTEXT.sync_scope(context.clone(), || srv.call(req));
+    REQUEST_CONTEXT.scope(context.clone(), async move {
+        let mut response = response.await?;
+        if let Ok(value) = HeaderValue::from_str(&context.request_id) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+        Ok(response)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{json_record, set_tenant_id, RequestContext, REQUEST_CONTEXT};
+    use crate::auth::TenantId;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    #[test]
+    fn json_records() {
+        let object = json_record(
+            "manager",
+            &log::Record::builder()
+                .args(format_args!("Created program 1"))
+                .level(log::Level::Info)
+                .target("pipeline_manager::api")
+                .build(),
+        );
+        assert_eq!(object["level"], "INFO");
+        assert_eq!(object["service"], "manager");
+        assert_eq!(object["target"], "pipeline_manager::api");
+        assert_eq!(object["message"], "Created program 1");
+        assert!(object.get("request_id").is_none());
+
+        let context = Arc::new(RequestContext {
+            request_id: "42".to_string(),
+            route: "/v0/programs".to_string(),
+            tenant_id: Mutex::new(None),
+        });
+        let object = REQUEST_CONTEXT.sync_scope(context, || {
+            set_tenant_id(TenantId(Uuid::nil()));
+            json_record(
+                "manager",
+                &log::Record::builder()
+                    .args(format_args!("Created program 1"))
+                    .build(),
+            )
+        });
+        assert_eq!(object["request_id"], "42");
+        assert_eq!(object["route"], "/v0/programs");
+        assert_eq!(object["tenant_id"], Uuid::nil().to_string());
+    }
 }
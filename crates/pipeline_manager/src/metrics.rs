@@ -0,0 +1,196 @@
+//! Prometheus metrics of the manager process.
+//!
+//! Request latencies are recorded by a middleware as requests complete (see
+//! [`track_request`]).  All other metrics are gauges that mirror the state of
+//! the database and are refreshed when the metrics are scraped.
+use crate::{
+    db::{storage::Storage, PipelineStatus, ProjectDB},
+    error::ManagerError,
+};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    Error as ActixError,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::{future::Future, sync::Arc, time::Instant};
+use tokio::sync::Mutex;
+
+/// Every status a pipeline can be in, so that each status is reported even
+/// when no pipeline is in it.
+const PIPELINE_STATUSES: [PipelineStatus; 7] = [
+    PipelineStatus::Shutdown,
+    PipelineStatus::Provisioning,
+    PipelineStatus::Initializing,
+    PipelineStatus::Paused,
+    PipelineStatus::Running,
+    PipelineStatus::Failed,
+    PipelineStatus::ShuttingDown,
+];
+
+pub(crate) struct ManagerMetrics {
+    registry: Registry,
+    request_duration: HistogramVec,
+    db_pool_size: IntGauge,
+    db_pool_available: IntGauge,
+    db_pool_max_size: IntGauge,
+    compile_queue_depth: IntGauge,
+    pipelines: IntGaugeVec,
+}
+
+impl ManagerMetrics {
+    pub(crate) fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "manager_http_request_duration_seconds",
+                "Latency of HTTP requests handled by the manager",
+            ),
+            &["method", "route", "status"],
+        )?;
+        registry.register(Box::new(request_duration.clone()))?;
+
+        let db_pool_size = IntGauge::new(
+            "manager_db_pool_size",
+            "Number of open connections in the database connection pool",
+        )?;
+        registry.register(Box::new(db_pool_size.clone()))?;
+
+        let db_pool_available = IntGauge::new(
+            "manager_db_pool_available",
+            "Number of idle connections in the database connection pool; negative when requests are waiting for a connection",
+        )?;
+        registry.register(Box::new(db_pool_available.clone()))?;
+
+        let db_pool_max_size = IntGauge::new(
+            "manager_db_pool_max_size",
+            "Maximum number of connections in the database connection pool",
+        )?;
+        registry.register(Box::new(db_pool_max_size.clone()))?;
+
+        let compile_queue_depth = IntGauge::new(
+            "manager_compile_queue_depth",
+            "Number of programs waiting to be compiled",
+        )?;
+        registry.register(Box::new(compile_queue_depth.clone()))?;
+
+        let pipelines = IntGaugeVec::new(
+            Opts::new("manager_pipelines", "Number of pipelines in each status"),
+            &["status"],
+        )?;
+        registry.register(Box::new(pipelines.clone()))?;
+
+        Ok(Self {
+            registry,
+            request_duration,
+            db_pool_size,
+            db_pool_available,
+            db_pool_max_size,
+            compile_queue_depth,
+            pipelines,
+        })
+    }
+
+    /// Record the latency of a request.
+    ///
+    /// `route` is the route pattern (e.g., `/v0/pipelines/{pipeline_id}`)
+    /// rather than the request path, to keep the number of series bounded.
+    pub(crate) fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.request_duration
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(seconds);
+    }
+
+    /// Refresh the gauges from the database and encode all metrics in the
+    /// Prometheus text format.
+    pub(crate) async fn gather(&self, db: &Arc<Mutex<ProjectDB>>) -> Result<Vec<u8>, ManagerError> {
+        {
+            let db = db.lock().await;
+
+            // The embedded SQLite database has no connection pool.
+            if let Some(pool) = db.pool_status() {
+                self.db_pool_size.set(pool.size as i64);
+                self.db_pool_available.set(pool.available as i64);
+                self.db_pool_max_size.set(pool.max_size as i64);
+            }
+
+            self.compile_queue_depth
+                .set(db.count_pending_programs().await? as i64);
+
+            let counts = db.count_pipelines_by_status().await?;
+            for status in PIPELINE_STATUSES {
+                let count = counts
+                    .iter()
+                    .find(|(s, _)| *s == status)
+                    .map_or(0, |(_, count)| *count);
+                self.pipelines
+                    .with_label_values(&[status.into()])
+                    .set(count as i64);
+            }
+        }
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| ManagerError::PrometheusError {
+                error: e.to_string(),
+            })?;
+        Ok(buffer)
+    }
+}
+
+/// Middleware that records the latency of each request in `metrics`.
+pub(crate) fn track_request<S, B>(
+    metrics: Arc<ManagerMetrics>,
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, ActixError>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixError>,
+{
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let response = srv.call(req);
+    async move {
+        let response = response.await?;
+        let route = response
+            .request()
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        metrics.observe_request(
+            &method,
+            &route,
+            response.status().as_u16(),
+            start.elapsed().as_secs_f64(),
+        );
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ManagerMetrics;
+    use prometheus::{Encoder, TextEncoder};
+
+    #[test]
+    fn request_latencies() {
+        let metrics = ManagerMetrics::new().unwrap();
+        metrics.observe_request("GET", "/v0/pipelines/{pipeline_id}", 200, 0.01);
+        metrics.observe_request("GET", "/v0/pipelines/{pipeline_id}", 200, 0.02);
+        metrics.observe_request("POST", "/v0/programs", 400, 0.5);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&metrics.registry.gather(), &mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(
+            r#"manager_http_request_duration_seconds_count{method="GET",route="/v0/pipelines/{pipeline_id}",status="200"} 2"#
+        ));
+        assert!(text.contains(
+            r#"manager_http_request_duration_seconds_count{method="POST",route="/v0/programs",status="400"} 1"#
+        ));
+    }
+}
@@ -1,4 +1,5 @@
 //! This module contains helpers to build pipeline runners.
+use crate::artifact_store::ArtifactStore;
 use crate::db::{ProgramId, Version};
 use crate::runner::RunnerApi;
 use crate::{
@@ -7,9 +8,10 @@ use crate::{
     config::LocalRunnerConfig,
     db::{
         storage::Storage, DBError, PipelineId, PipelineRevision, PipelineRuntimeState,
-        PipelineStatus, ProjectDB,
+        PipelineStatus, ProjectDB, TenantUsage, WebhookEvent,
     },
     runner::RunnerError,
+    webhooks::{self, WebhookPayload},
 };
 use actix_web::http::{Method, StatusCode};
 use async_trait::async_trait;
@@ -18,7 +20,11 @@ use dbsp_adapters::{ErrorResponse, PipelineConfig};
 use log::{error, info};
 use serde::Deserialize;
 use serde_json::Value as JsonValue;
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Instant,
+};
 use tokio::io::AsyncWriteExt;
 use tokio::{fs, sync::Mutex, time::Duration};
 use tokio::{sync::Notify, time::timeout};
@@ -35,9 +41,20 @@ pub trait PipelineExecutor {
     /// reachable Ok(None) indicates that the pipeline is still initializing
     async fn get_location(&mut self) -> Result<Option<String>, ManagerError>;
 
+    /// Return the certificate (in PEM format) served by the pipeline's HTTP
+    /// server, or Ok(None) if the server doesn't use TLS.  Only called after
+    /// `get_location` has returned the location of the pipeline.
+    async fn get_tls_certificate(&mut self) -> Result<Option<String>, ManagerError> {
+        Ok(None)
+    }
+
     /// Returns whether the pipeline has been shutdown
     async fn check_if_shutdown(&mut self) -> bool;
 
+    /// Returns whether the pipeline has terminated after processing all of
+    /// its inputs in response to a `/drain` request
+    async fn check_if_drained(&mut self) -> bool;
+
     /// Initiates pipeline shutdown (e.g., send a SIGTERM successfully to the
     /// process)
     async fn shutdown(&mut self) -> Result<(), ManagerError>;
@@ -56,6 +73,178 @@ where
     pipeline_handle: T,
     db: Arc<Mutex<ProjectDB>>,
     notifier: Arc<Notify>,
+    /// Tracks resources consumed by the pipeline since it was last deployed,
+    /// or since the automaton adopted the running pipeline.  `None` while
+    /// the pipeline is not running.
+    usage: Option<UsageSampler>,
+    /// Last status of the pipeline observed or written by the automaton.
+    /// Used to detect lifecycle events reported to webhooks.
+    last_status: Option<PipelineStatus>,
+    /// True if the pipeline failed and has not been successfully deployed
+    /// since.
+    failed: bool,
+    /// Tracks the health of the pipeline's connectors since it was last
+    /// deployed.
+    connectors: ConnectorMonitor,
+}
+
+/// Computes increments of tenant resource usage counters from periodic
+/// samples of pipeline statistics.
+struct UsageSampler {
+    /// Time of the last sample.
+    sampled_at: Instant,
+    /// Total number of bytes received by input endpoints as of the last
+    /// sample.
+    ingress_bytes: u64,
+    /// Total number of bytes sent by output endpoints as of the last sample.
+    egress_bytes: u64,
+}
+
+impl UsageSampler {
+    fn new() -> Self {
+        Self {
+            sampled_at: Instant::now(),
+            ingress_bytes: 0,
+            egress_bytes: 0,
+        }
+    }
+
+    /// Starts tracking a pipeline that was already running when the
+    /// automaton took it over, e.g., after a manager restart.
+    ///
+    /// Resources consumed before the takeover were either already recorded
+    /// by the previous manager or can't be attributed, so the totals
+    /// reported in `stats` serve as the baseline.
+    fn adopt(stats: &JsonValue) -> Self {
+        let mut sampler = Self::new();
+        sampler.sample(Some(stats));
+        sampler
+    }
+
+    /// Returns resources consumed since the previous sample by the pipeline
+    /// tracked by `sampler`.
+    ///
+    /// If the pipeline is not tracked yet and `stats` is available, starts
+    /// tracking it with [`Self::adopt`] and returns no usage.
+    fn sample_or_adopt(sampler: &mut Option<Self>, stats: Option<&JsonValue>) -> TenantUsage {
+        match sampler {
+            Some(sampler) => sampler.sample(stats),
+            None => {
+                if let Some(stats) = stats {
+                    *sampler = Some(Self::adopt(stats));
+                }
+                TenantUsage::default()
+            }
+        }
+    }
+
+    /// Sum up a metric across all endpoints in the `inputs` or `outputs`
+    /// section of a pipeline status descriptor.
+    fn sum_endpoint_metric(stats: &JsonValue, endpoints: &str, metric: &str) -> u64 {
+        stats
+            .get(endpoints)
+            .and_then(JsonValue::as_array)
+            .map(|endpoints| {
+                endpoints
+                    .iter()
+                    .filter_map(|endpoint| endpoint.get("metrics")?.get(metric)?.as_u64())
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Returns resources consumed since the previous sample.
+    ///
+    /// `stats` is the status descriptor returned by the pipeline's `/stats`
+    /// endpoint, if available.
+    fn sample(&mut self, stats: Option<&JsonValue>) -> TenantUsage {
+        let now = Instant::now();
+        let mut usage = TenantUsage {
+            run_time_ms: now.duration_since(self.sampled_at).as_millis() as i64,
+            ..Default::default()
+        };
+        self.sampled_at = now;
+
+        if let Some(stats) = stats {
+            let ingress_bytes = Self::sum_endpoint_metric(stats, "inputs", "total_bytes");
+            let egress_bytes = Self::sum_endpoint_metric(stats, "outputs", "transmitted_bytes");
+            usage.ingress_bytes = ingress_bytes.saturating_sub(self.ingress_bytes) as i64;
+            usage.egress_bytes = egress_bytes.saturating_sub(self.egress_bytes) as i64;
+            self.ingress_bytes = ingress_bytes;
+            self.egress_bytes = egress_bytes;
+        }
+
+        usage
+    }
+}
+
+/// Detects connectors that become degraded from periodic samples of
+/// pipeline statistics.
+///
+/// A connector is degraded if it reported a fatal error or its number of
+/// transport errors has grown since the previous sample.
+#[derive(Default)]
+struct ConnectorMonitor {
+    /// Number of transport errors of each endpoint as of the last sample.
+    transport_errors: BTreeMap<String, u64>,
+    /// Endpoints that were degraded as of the last sample.
+    degraded: BTreeSet<String>,
+    /// Endpoints that reported a fatal error.
+    failed: BTreeSet<String>,
+}
+
+impl ConnectorMonitor {
+    /// Returns endpoints that became degraded since the previous sample,
+    /// along with a description of the problem.
+    ///
+    /// `stats` is the status descriptor returned by the pipeline's `/stats`
+    /// endpoint.
+    fn sample(&mut self, stats: &JsonValue) -> Vec<(String, String)> {
+        let mut degraded = Vec::new();
+        for section in ["inputs", "outputs"] {
+            let endpoints = stats.get(section).and_then(JsonValue::as_array);
+            for endpoint in endpoints.into_iter().flatten() {
+                let name = match endpoint.get("endpoint_name").and_then(JsonValue::as_str) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if let Some(error) = endpoint.get("fatal_error").and_then(JsonValue::as_str) {
+                    if self.failed.insert(name.to_string()) {
+                        degraded.push((
+                            name.to_string(),
+                            format!("Connector '{name}' failed: {error}"),
+                        ));
+                    }
+                    continue;
+                }
+                let errors = endpoint
+                    .get("metrics")
+                    .and_then(|metrics| metrics.get("num_transport_errors"))
+                    .and_then(JsonValue::as_u64)
+                    .unwrap_or(0);
+                let previous = self
+                    .transport_errors
+                    .insert(name.to_string(), errors)
+                    .unwrap_or(0);
+                if errors > previous {
+                    // Only notify when the connector starts failing, not
+                    // on every sample while it keeps failing.
+                    if self.degraded.insert(name.to_string()) {
+                        degraded.push((
+                            name.to_string(),
+                            format!(
+                                "Connector '{name}' encountered {} transport errors",
+                                errors - previous
+                            ),
+                        ));
+                    }
+                } else {
+                    self.degraded.remove(name);
+                }
+            }
+        }
+        degraded
+    }
 }
 
 /// A description of a pipeline to execute
@@ -67,9 +256,15 @@ pub struct PipelineExecutionDesc {
     pub version: Version,
     pub config: PipelineConfig,
     pub binary_ref: String,
+    /// Bearer token the pipeline must require from its clients.
+    pub auth_token: String,
 }
 
-fn to_execution_desc(pr: PipelineRevision, binary_ref: String) -> PipelineExecutionDesc {
+fn to_execution_desc(
+    pr: PipelineRevision,
+    binary_ref: String,
+    auth_token: String,
+) -> PipelineExecutionDesc {
     PipelineExecutionDesc {
         pipeline_id: pr.pipeline.pipeline_id,
         pipeline_name: pr.pipeline.name,
@@ -77,9 +272,20 @@ fn to_execution_desc(pr: PipelineRevision, binary_ref: String) -> PipelineExecut
         version: pr.program.version,
         config: pr.config,
         binary_ref,
+        auth_token,
     }
 }
 
+/// Generate a random bearer token for a new deployment of a pipeline.
+fn new_auth_token(pipeline_id: PipelineId) -> Result<String, ManagerError> {
+    let mut bytes = [0u8; 32];
+    openssl::rand::rand_bytes(&mut bytes).map_err(|e| RunnerError::PipelineStartupError {
+        pipeline_id,
+        error: format!("error generating authentication token: {e}"),
+    })?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
 impl<T: PipelineExecutor> PipelineAutomaton<T> {
     /// The frequency of polling the pipeline during normal operation
     /// when we don't normally expect its state to change.
@@ -121,6 +327,10 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
             pipeline_handle,
             db,
             notifier,
+            usage: None,
+            last_status: None,
+            failed: false,
+            connectors: ConnectorMonitor::default(),
         }
     }
 
@@ -165,6 +375,10 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                 }
             }
             let mut pipeline = result.unwrap();
+            if self.last_status.is_none() {
+                self.last_status = Some(pipeline.current_status);
+                self.failed = pipeline.current_status == PipelineStatus::Failed;
+            }
 
             // Handle deployment request.
             if pipeline.current_status == PipelineStatus::Shutdown
@@ -172,6 +386,8 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
             {
                 self.update_pipeline_status(&mut pipeline, PipelineStatus::Provisioning, None)
                     .await;
+                let auth_token = new_auth_token(self.pipeline_id)?;
+                pipeline.set_auth_token(Some(auth_token.clone()));
                 let revision = db
                     .get_last_committed_pipeline_revision(self.tenant_id, self.pipeline_id)
                     .await?;
@@ -191,7 +407,8 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                     .into());
                 }
                 drop(db);
-                let execution_desc = to_execution_desc(revision, executable_ref.unwrap());
+                let execution_desc =
+                    to_execution_desc(revision, executable_ref.unwrap(), auth_token);
 
                 match self.pipeline_handle.start(execution_desc).await {
                     Ok(_) => {
@@ -216,8 +433,16 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                 // Poll its port file.  On success, go to `Initializing` state.
                 (PipelineStatus::Provisioning, PipelineStatus::Running)
                 | (PipelineStatus::Provisioning, PipelineStatus::Paused) => {
-                    match self.pipeline_handle.get_location().await {
-                        Ok(Some(location)) => {
+                    let location = match self.pipeline_handle.get_location().await {
+                        Ok(Some(location)) => self
+                            .pipeline_handle
+                            .get_tls_certificate()
+                            .await
+                            .map(|certificate| Some((location, certificate))),
+                        result => result.map(|_| None),
+                    };
+                    match location {
+                        Ok(Some((location, certificate))) => {
                             self.update_pipeline_status(
                                 &mut pipeline,
                                 PipelineStatus::Initializing,
@@ -225,8 +450,11 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                             )
                             .await;
                             pipeline.set_location(location);
+                            pipeline.set_tls_certificate(certificate);
                             pipeline.set_created();
                             self.update_pipeline_runtime_state(&pipeline).await?;
+                            self.usage = Some(UsageSampler::new());
+                            self.connectors = ConnectorMonitor::default();
                             poll_timeout = Self::INITIALIZATION_POLL_PERIOD;
                         }
                         Ok(None) => {
@@ -265,7 +493,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                         self.pipeline_id,
                         Method::GET,
                         "stats",
-                        &pipeline.location,
+                        &pipeline,
                     )
                     .await
                     {
@@ -331,7 +559,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                         self.pipeline_id,
                         Method::GET,
                         "start",
-                        &pipeline.location,
+                        &pipeline,
                     )
                     .await
                     {
@@ -360,7 +588,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                         self.pipeline_id,
                         Method::GET,
                         "pause",
-                        &pipeline.location,
+                        &pipeline,
                     )
                     .await
                     {
@@ -392,7 +620,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                         self.pipeline_id,
                         Method::GET,
                         "shutdown",
-                        &pipeline.location,
+                        &pipeline,
                     )
                     .await
                     {
@@ -421,6 +649,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                 (PipelineStatus::ShuttingDown, _) => {
                     if self.pipeline_handle.check_if_shutdown().await {
                         let _ = self.pipeline_handle.shutdown().await;
+                        self.finish_usage().await?;
                         self.update_pipeline_status(&mut pipeline, PipelineStatus::Shutdown, None)
                             .await;
                         self.update_pipeline_runtime_state(&pipeline).await?;
@@ -443,10 +672,34 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                         self.pipeline_id,
                         Method::GET,
                         "stats",
-                        &pipeline.location,
+                        &pipeline,
                     )
                     .await
                     {
+                        Err(_) if self.pipeline_handle.check_if_drained().await => {
+                            // The pipeline was drained and exited.  This is a
+                            // successful termination: move to the `Shutdown`
+                            // state, so that the pipeline is not restarted.
+                            let _ = self.pipeline_handle.shutdown().await;
+                            self.finish_usage().await?;
+                            self.db
+                                .lock()
+                                .await
+                                .set_pipeline_desired_status(
+                                    self.tenant_id,
+                                    self.pipeline_id,
+                                    PipelineStatus::Shutdown,
+                                )
+                                .await?;
+                            pipeline.desired_status = PipelineStatus::Shutdown;
+                            self.update_pipeline_status(
+                                &mut pipeline,
+                                PipelineStatus::Shutdown,
+                                None,
+                            )
+                            .await;
+                            self.update_pipeline_runtime_state(&pipeline).await?;
+                        }
                         Err(e) => {
                             // Cannot reach the pipeline.
                             self.force_kill_pipeline(&mut pipeline, Some(e)).await?;
@@ -459,6 +712,8 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
                                 self.force_kill_pipeline_on_error(&mut pipeline, status, &body)
                                     .await?;
                             } else {
+                                self.record_usage(Some(&body)).await?;
+                                self.notify_degraded_connectors(&body).await;
                                 let global_metrics = if let Some(metrics) =
                                     body.get("global_metrics")
                                 {
@@ -545,14 +800,87 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
     }
 
     async fn update_pipeline_runtime_state(
-        &self,
+        &mut self,
         state: &PipelineRuntimeState,
     ) -> Result<(), DBError> {
         self.db
             .lock()
             .await
             .update_pipeline_runtime_state(self.tenant_id, self.pipeline_id, state)
-            .await
+            .await?;
+        self.notify_status_change(state).await;
+        Ok(())
+    }
+
+    /// Notify webhooks if the pipeline status change constitutes a
+    /// lifecycle event.
+    async fn notify_status_change(&mut self, state: &PipelineRuntimeState) {
+        let previous = self.last_status.replace(state.current_status);
+        if previous == Some(state.current_status) {
+            return;
+        }
+        let event = match state.current_status {
+            PipelineStatus::Running | PipelineStatus::Paused
+                if !matches!(
+                    previous,
+                    Some(PipelineStatus::Running | PipelineStatus::Paused)
+                ) =>
+            {
+                if std::mem::take(&mut self.failed) {
+                    WebhookEvent::Restarted
+                } else {
+                    WebhookEvent::Deployed
+                }
+            }
+            PipelineStatus::Failed => {
+                self.failed = true;
+                WebhookEvent::Failed
+            }
+            PipelineStatus::Shutdown => WebhookEvent::Shutdown,
+            _ => return,
+        };
+        webhooks::notify(
+            &self.db,
+            WebhookPayload::pipeline(self.tenant_id, self.pipeline_id, event, state.error.clone()),
+        )
+        .await;
+    }
+
+    /// Notify webhooks about connectors that became degraded since the
+    /// previous sample of pipeline statistics.
+    async fn notify_degraded_connectors(&mut self, stats: &JsonValue) {
+        for (connector, message) in self.connectors.sample(stats) {
+            webhooks::notify(
+                &self.db,
+                WebhookPayload::connector(self.tenant_id, self.pipeline_id, &connector, message),
+            )
+            .await;
+        }
+    }
+
+    /// Add resources consumed by the pipeline since the previous sample to
+    /// the tenant's usage counters.
+    ///
+    /// Starts tracking pipelines that were already running when the
+    /// automaton was created the first time it receives their statistics.
+    async fn record_usage(&mut self, stats: Option<&JsonValue>) -> Result<(), DBError> {
+        let usage = UsageSampler::sample_or_adopt(&mut self.usage, stats);
+        if !usage.is_empty() {
+            self.db
+                .lock()
+                .await
+                .record_tenant_usage(self.tenant_id, &usage)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Record the remaining run time of a pipeline that is being shut down
+    /// and stop tracking its usage.
+    async fn finish_usage(&mut self) -> Result<(), DBError> {
+        self.record_usage(None).await?;
+        self.usage = None;
+        Ok(())
     }
 
     // We store timestamps in the DB and retrieve them as Utc times;
@@ -576,6 +904,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
         ErrorResponse: for<'a> From<&'a E>,
     {
         let _ = self.pipeline_handle.shutdown().await;
+        self.finish_usage().await?;
 
         if pipeline.desired_status == PipelineStatus::Shutdown {
             self.update_pipeline_status(
@@ -604,6 +933,7 @@ impl<T: PipelineExecutor> PipelineAutomaton<T> {
         error: &JsonValue,
     ) -> Result<(), DBError> {
         let _ = self.pipeline_handle.shutdown().await;
+        self.finish_usage().await?;
         let error = Self::error_response_from_json(self.pipeline_id, status, error);
 
         if pipeline.desired_status == PipelineStatus::Shutdown {
@@ -665,14 +995,26 @@ pub async fn fetch_binary_ref(
                 }.into()),
             }
         }
-        // Access a file over HTTP/HTTPS
+        // Access a file over HTTP/HTTPS or in the artifact store
         // TODO: implement retries
-        "http" | "https" => {
-            let resp = reqwest::get(binary_ref).await;
+        "http" | "https" | "s3" | "gs" | "az" => {
+            let resp = if matches!(parsed.scheme(), "http" | "https") {
+                match reqwest::get(binary_ref).await {
+                    Ok(resp) => Ok(resp
+                        .bytes()
+                        .await
+                        .expect("Binary reference should be accessible as bytes")
+                        .to_vec()),
+                    Err(e) => Err(e.to_string()),
+                }
+            } else {
+                ArtifactStore::fetch(binary_ref)
+                    .await
+                    .map_err(|e| e.to_string())
+            };
             match resp {
                 Ok(resp) => {
-                    let resp = resp.bytes().await.expect("Binary reference should be accessible as bytes");
-                    let resp_ref = resp.as_ref();
+                    let resp_ref = resp.as_slice();
                     let path = config.binary_file_path(pipeline_id, program_id, version);
                     let mut file = tokio::fs::File::options()
                         .create(true)
@@ -721,9 +1063,9 @@ async fn pipeline_http_request_json_response(
     pipeline_id: PipelineId,
     method: Method,
     endpoint: &str,
-    port: &str,
+    state: &PipelineRuntimeState,
 ) -> Result<(StatusCode, JsonValue), RunnerError> {
-    let response = RunnerApi::pipeline_http_request(pipeline_id, method, endpoint, port).await?;
+    let response = RunnerApi::pipeline_http_request(pipeline_id, method, endpoint, state).await?;
     let status = response.status();
 
     let value = response
@@ -736,3 +1078,52 @@ async fn pipeline_http_request_json_response(
 
     Ok((status, value))
 }
+
+#[cfg(test)]
+mod test {
+    use super::UsageSampler;
+    use serde_json::json;
+
+    fn stats(ingress_bytes: u64, egress_bytes: u64) -> serde_json::Value {
+        json!({
+            "inputs": [{"metrics": {"total_bytes": ingress_bytes}}],
+            "outputs": [{"metrics": {"transmitted_bytes": egress_bytes}}],
+        })
+    }
+
+    // A pipeline deployed by the automaton is tracked from zero.
+    #[test]
+    fn usage_of_deployed_pipeline() {
+        let mut sampler = Some(UsageSampler::new());
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, Some(&stats(100, 10)));
+        assert_eq!((usage.ingress_bytes, usage.egress_bytes), (100, 10));
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, Some(&stats(150, 30)));
+        assert_eq!((usage.ingress_bytes, usage.egress_bytes), (50, 20));
+    }
+
+    // A pipeline that was already running when the automaton started, e.g.,
+    // after a manager restart, is tracked from its first stats poll.
+    #[test]
+    fn usage_of_adopted_pipeline() {
+        let mut sampler = None;
+
+        // Nothing to sample without stats.
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, None);
+        assert!(usage.is_empty());
+        assert!(sampler.is_none());
+
+        // The first poll sets the baseline.
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, Some(&stats(1000, 500)));
+        assert!(usage.is_empty());
+        assert!(sampler.is_some());
+
+        // Subsequent polls record increments over the baseline.
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, Some(&stats(1200, 800)));
+        assert_eq!((usage.ingress_bytes, usage.egress_bytes), (200, 300));
+        assert!(usage.run_time_ms >= 0);
+
+        // Shutting down records the remaining run time only.
+        let usage = UsageSampler::sample_or_adopt(&mut sampler, None);
+        assert_eq!((usage.ingress_bytes, usage.egress_bytes), (0, 0));
+    }
+}
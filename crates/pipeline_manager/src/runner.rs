@@ -1,18 +1,38 @@
 use crate::{
     api::ManagerError,
     auth::TenantId,
-    db::{storage::Storage, DBError, PipelineId, PipelineRuntimeState, PipelineStatus, ProjectDB},
+    db::{
+        storage::Storage, DBError, DeploymentId, PipelineId, PipelineRuntimeState, PipelineStatus,
+        ProjectDB,
+    },
 };
 use actix_web::{
     body::BoxBody,
-    http::{Method, StatusCode},
+    http::{
+        header::{self, HeaderValue},
+        Method, StatusCode,
+    },
     web::Payload,
     HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError,
 };
-use dbsp_adapters::{DetailedError, ErrorResponse};
+use dbsp_adapters::{DetailedError, ErrorResponse, RuntimeConfig};
+use log::warn;
+use openssl::{
+    error::ErrorStack,
+    ssl::{SslConnector, SslMethod},
+    x509::{store::X509StoreBuilder, X509},
+};
 use serde::Serialize;
-use std::{borrow::Cow, error::Error as StdError, fmt, fmt::Display, sync::Arc, time::Duration};
-use tokio::sync::Mutex;
+use serde_json::Value as JsonValue;
+use std::{
+    borrow::Cow,
+    error::Error as StdError,
+    fmt,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex, time::sleep};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
@@ -58,6 +78,11 @@ pub enum RunnerError {
         pipeline_id: PipelineId,
         error: String,
     },
+    DeploymentStartError {
+        deployment_id: DeploymentId,
+        pipeline_id: PipelineId,
+        error: String,
+    },
 }
 
 impl DetailedError for RunnerError {
@@ -76,6 +101,7 @@ impl DetailedError for RunnerError {
                 Cow::from("IllegalPipelineStateTransition")
             }
             Self::BinaryFetchError { .. } => Cow::from("BinaryFetchError"),
+            Self::DeploymentStartError { .. } => Cow::from("DeploymentStartError"),
         }
     }
 }
@@ -134,6 +160,13 @@ impl Display for RunnerError {
             Self::BinaryFetchError { pipeline_id, error } => {
                 write!(f, "Failed to fetch binary executable for running pipeline '{pipeline_id}': '{error}'")
             }
+            Self::DeploymentStartError {
+                deployment_id,
+                pipeline_id,
+                error,
+            } => {
+                write!(f, "Failed to start pipeline '{pipeline_id}' of deployment '{deployment_id}': '{error}'. Pipelines started as part of the deployment have been shut down.")
+            }
         }
     }
 }
@@ -152,6 +185,7 @@ impl ResponseError for RunnerError {
             Self::PipelineStartupError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::IllegalPipelineStateTransition { .. } => StatusCode::BAD_REQUEST,
             Self::BinaryFetchError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::DeploymentStartError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
@@ -160,6 +194,24 @@ impl ResponseError for RunnerError {
     }
 }
 
+/// Changes made by [`RunnerApi::start_deployment`] so far, undone if a
+/// pipeline fails to start.
+#[derive(Default)]
+struct DeploymentRollback {
+    /// Pipelines that were shut down and have been asked to start.
+    started: Vec<PipelineId>,
+
+    /// Previous configurations of pipelines whose configuration was replaced
+    /// by the shared configuration of the deployment.
+    configs: Vec<(PipelineId, RuntimeConfig)>,
+}
+
+/// How often deployment actions poll the status of a pipeline.
+const DEPLOYMENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long deployment actions wait for each pipeline to start or shut down.
+const DEPLOYMENT_PIPELINE_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Interface to express pipeline desired states to runners and also
 /// connect to streams
 pub struct RunnerApi {
@@ -185,7 +237,7 @@ impl RunnerApi {
         Ok(())
     }
 
-    /// Delete the pipeline.
+    /// Move the pipeline to the trash.
     ///
     /// Botht the desired and the actual states of the pipeline must be equal
     /// to [`PipelineStatus::Shutdown`] or [`PipelineStatus::Failed`].
@@ -205,11 +257,13 @@ impl RunnerApi {
             .await?;
         Self::validate_desired_state_request(pipeline_id, &pipeline_state, None)?;
 
-        db.delete_pipeline(tenant_id, pipeline_id).await?;
+        if !db.delete_pipeline(tenant_id, pipeline_id).await? {
+            return Err(DBError::UnknownPipeline { pipeline_id }.into());
+        }
 
         // No need to do anything else since the pipeline was in the `Shutdown` state.
         // The pipeline tokio task will self-destruct when it polls pipeline
-        // state and discovers it has been deleted.
+        // state and discovers that the pipeline has been purged from the trash.
 
         Ok(())
     }
@@ -244,6 +298,211 @@ impl RunnerApi {
         Ok(())
     }
 
+    /// Start the pipelines of a deployment one by one, in order.
+    ///
+    /// Waits for each pipeline to be running before starting the next one.
+    /// If a pipeline fails to start, the pipelines started so far, including
+    /// the one that failed, are shut down in reverse order, and pipelines
+    /// whose configuration was replaced by the shared configuration of the
+    /// deployment get their previous configuration back.  Pipelines that
+    /// were already deployed before this call are left running.
+    pub(crate) async fn start_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), ManagerError> {
+        let deployment = self
+            .db
+            .lock()
+            .await
+            .get_deployment_by_id(tenant_id, deployment_id)
+            .await?;
+
+        let mut rollback = DeploymentRollback::default();
+        for pipeline_id in deployment.pipelines {
+            if let Err(e) = self
+                .start_deployment_pipeline(
+                    tenant_id,
+                    pipeline_id,
+                    &deployment.config,
+                    &mut rollback,
+                )
+                .await
+            {
+                self.rollback_deployment(tenant_id, deployment_id, rollback)
+                    .await;
+                Err(RunnerError::DeploymentStartError {
+                    deployment_id,
+                    pipeline_id,
+                    error: e.to_string(),
+                })?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start a pipeline of a deployment and wait until it's running.
+    ///
+    /// Records the changes made to the pipeline in `rollback` before making
+    /// them, so that they get undone if this or a later pipeline of the
+    /// deployment fails to start.
+    async fn start_deployment_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        config: &Option<RuntimeConfig>,
+        rollback: &mut DeploymentRollback,
+    ) -> Result<(), ManagerError> {
+        {
+            let db = self.db.lock().await;
+            let descr = db.get_pipeline_descr_by_id(tenant_id, pipeline_id).await?;
+            let state = db
+                .get_pipeline_runtime_state(tenant_id, pipeline_id)
+                .await?;
+            if state.current_status != PipelineStatus::Shutdown
+                || state.desired_status != PipelineStatus::Shutdown
+            {
+                // Already deployed: leave the pipeline alone.
+                return Ok(());
+            }
+
+            // The shared configuration takes effect the next time the
+            // pipeline is deployed.
+            if let Some(config) = config {
+                if descr.config != *config {
+                    rollback.configs.push((pipeline_id, descr.config.clone()));
+                    db.update_pipeline(
+                        tenant_id,
+                        pipeline_id,
+                        descr.program_id,
+                        &descr.name,
+                        &descr.description,
+                        &Some(config.clone()),
+                        &None,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        rollback.started.push(pipeline_id);
+        self.start_pipeline(tenant_id, pipeline_id).await?;
+        self.wait_for_status(tenant_id, pipeline_id, PipelineStatus::Running)
+            .await
+    }
+
+    /// Undo the changes recorded in `rollback` by a failed deployment start.
+    ///
+    /// Doesn't wait for the pipelines to shut down, and only logs errors:
+    /// the error that caused the rollback must be reported either way.
+    async fn rollback_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+        rollback: DeploymentRollback,
+    ) {
+        for pipeline_id in rollback.started.iter().rev() {
+            if let Err(e) = self.shutdown_pipeline(tenant_id, *pipeline_id).await {
+                warn!("Failed to shut down pipeline {pipeline_id} while rolling back deployment {deployment_id}: {e}");
+            }
+        }
+
+        let db = self.db.lock().await;
+        for (pipeline_id, config) in rollback.configs {
+            let result = match db.get_pipeline_descr_by_id(tenant_id, pipeline_id).await {
+                Ok(descr) => db
+                    .update_pipeline(
+                        tenant_id,
+                        pipeline_id,
+                        descr.program_id,
+                        &descr.name,
+                        &descr.description,
+                        &Some(config),
+                        &None,
+                    )
+                    .await
+                    .map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Failed to restore the configuration of pipeline {pipeline_id} while rolling back deployment {deployment_id}: {e}");
+            }
+        }
+    }
+
+    /// Shut down the pipelines of a deployment one by one, in reverse order.
+    ///
+    /// Waits for each pipeline to shut down before shutting down the
+    /// previous one.
+    pub(crate) async fn shutdown_deployment(
+        &self,
+        tenant_id: TenantId,
+        deployment_id: DeploymentId,
+    ) -> Result<(), ManagerError> {
+        let deployment = self
+            .db
+            .lock()
+            .await
+            .get_deployment_by_id(tenant_id, deployment_id)
+            .await?;
+
+        for pipeline_id in deployment.pipelines.into_iter().rev() {
+            self.shutdown_pipeline(tenant_id, pipeline_id).await?;
+            self.wait_for_status(tenant_id, pipeline_id, PipelineStatus::Shutdown)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the current status of a pipeline to become `status`.
+    ///
+    /// Fails if the pipeline fails or doesn't reach `status` within
+    /// [`DEPLOYMENT_PIPELINE_TIMEOUT`].
+    async fn wait_for_status(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        status: PipelineStatus,
+    ) -> Result<(), ManagerError> {
+        let deadline = Instant::now() + DEPLOYMENT_PIPELINE_TIMEOUT;
+        loop {
+            let state = self
+                .db
+                .lock()
+                .await
+                .get_pipeline_runtime_state(tenant_id, pipeline_id)
+                .await?;
+            if state.current_status == status {
+                return Ok(());
+            }
+            if state.current_status == PipelineStatus::Failed {
+                Err(RunnerError::PipelineStartupError {
+                    pipeline_id,
+                    error: state
+                        .error
+                        .map(|e| e.message)
+                        .unwrap_or_else(|| "pipeline failed".to_string()),
+                })?
+            }
+            if Instant::now() >= deadline {
+                if status == PipelineStatus::Shutdown {
+                    Err(RunnerError::PipelineShutdownTimeout {
+                        pipeline_id,
+                        timeout: DEPLOYMENT_PIPELINE_TIMEOUT,
+                    })?
+                } else {
+                    Err(RunnerError::PipelineInitializationTimeout {
+                        pipeline_id,
+                        timeout: DEPLOYMENT_PIPELINE_TIMEOUT,
+                    })?
+                }
+            }
+            sleep(DEPLOYMENT_POLL_INTERVAL).await;
+        }
+    }
+
     /// Check the `request` is a valid new desired state given the current
     /// runtime state of the pipeline.  `request` value of `None` represents
     /// the request to delete the pipeline.
@@ -313,6 +572,8 @@ impl RunnerApi {
         // another manager instance.
 
         let db = self.db.lock().await;
+        // Pipelines in the trash cannot be deployed.
+        db.get_pipeline_descr_by_id(tenant_id, pipeline_id).await?;
         let pipeline_state = db
             .get_pipeline_runtime_state(tenant_id, pipeline_id)
             .await?;
@@ -362,6 +623,31 @@ impl RunnerApi {
         pipeline_id: PipelineId,
         method: Method,
         endpoint: &str,
+    ) -> Result<HttpResponse, ManagerError> {
+        self.forward_with_body(tenant_id, pipeline_id, method, endpoint, None)
+            .await
+    }
+
+    /// Forward HTTP request with a JSON body to the pipeline.
+    pub(crate) async fn forward_json_to_pipeline(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        method: Method,
+        endpoint: &str,
+        body: &JsonValue,
+    ) -> Result<HttpResponse, ManagerError> {
+        self.forward_with_body(tenant_id, pipeline_id, method, endpoint, Some(body))
+            .await
+    }
+
+    async fn forward_with_body(
+        &self,
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        method: Method,
+        endpoint: &str,
+        body: Option<&JsonValue>,
     ) -> Result<HttpResponse, ManagerError> {
         let pipeline_state = self
             .db
@@ -377,19 +663,22 @@ impl RunnerApi {
             _ => {}
         }
 
-        Self::do_forward_to_pipeline(pipeline_id, method, endpoint, &pipeline_state.location).await
+        Self::do_forward_to_pipeline(pipeline_id, method, endpoint, &pipeline_state, body).await
     }
 
     /// Forward HTTP request to pipeline.  Assumes that the pipeline is running.
-    /// Takes pipeline port as an argument instead of reading it from the
-    /// database.
+    /// Takes pipeline runtime state as an argument instead of reading it from
+    /// the database.
     async fn do_forward_to_pipeline(
         pipeline_id: PipelineId,
         method: Method,
         endpoint: &str,
-        location: &str,
+        state: &PipelineRuntimeState,
+        body: Option<&JsonValue>,
     ) -> Result<HttpResponse, ManagerError> {
-        let response = Self::pipeline_http_request(pipeline_id, method, endpoint, location).await?;
+        let response =
+            Self::pipeline_http_request_with_body(pipeline_id, method, endpoint, state, body)
+                .await?;
         let status = response.status();
 
         let mut response_builder = HttpResponse::build(status);
@@ -419,11 +708,25 @@ impl RunnerApi {
         pipeline_id: PipelineId,
         method: Method,
         endpoint: &str,
-        location: &str,
+        state: &PipelineRuntimeState,
     ) -> Result<reqwest::Response, RunnerError> {
-        let client = reqwest::Client::new();
-        client
-            .request(method, &format!("http://{location}/{endpoint}",))
+        Self::pipeline_http_request_with_body(pipeline_id, method, endpoint, state, None).await
+    }
+
+    /// Send HTTP request with an optional JSON body to pipeline.
+    async fn pipeline_http_request_with_body(
+        pipeline_id: PipelineId,
+        method: Method,
+        endpoint: &str,
+        state: &PipelineRuntimeState,
+        body: Option<&JsonValue>,
+    ) -> Result<reqwest::Response, RunnerError> {
+        let client = pipeline_http_client(pipeline_id, state)?;
+        let mut request = client.request(method, pipeline_url(state, endpoint));
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        request
             .send()
             .await
             .map_err(|e| RunnerError::HttpForwardError {
@@ -453,23 +756,40 @@ impl RunnerApi {
             }
             _ => {}
         }
-        let location = pipeline_state.location;
-
         // TODO: it might be better to have ?name={}, otherwise we have to
         // restrict name format
-        let url = format!("http://{location}/{endpoint}?{}", req.query_string());
-
-        let client = awc::Client::new();
+        let url = format!(
+            "{}?{}",
+            pipeline_url(&pipeline_state, endpoint),
+            req.query_string()
+        );
+
+        let client = match &pipeline_state.tls_certificate {
+            None => awc::Client::new(),
+            Some(certificate) => awc::Client::builder()
+                .connector(
+                    awc::Connector::new()
+                        .openssl(pipeline_ssl_connector(pipeline_id, certificate)?),
+                )
+                .finish(),
+        };
 
-        let mut request = client.request(req.method().clone(), url);
+        // Forward compressed responses, e.g., egress streams requested with
+        // `Accept-Encoding: gzip`, to the client as is.
+        let mut request = client.request(req.method().clone(), url).no_decompress();
 
+        // Replace the client's credentials, which are meant for the manager,
+        // with the pipeline's token.
         for header in req
             .headers()
             .into_iter()
-            .filter(|(h, _)| *h != "connection")
+            .filter(|(h, _)| *h != "connection" && *h != header::AUTHORIZATION)
         {
             request = request.append_header(header);
         }
+        if let Some(authorization) = pipeline_authorization(pipeline_id, &pipeline_state)? {
+            request = request.insert_header((header::AUTHORIZATION, authorization));
+        }
 
         let response =
             request
@@ -487,3 +807,86 @@ impl RunnerApi {
         Ok(builder.streaming(response))
     }
 }
+
+/// URL of `endpoint` of the pipeline's HTTP server.
+///
+/// Pipelines whose server is configured for TLS report their certificate
+/// along with their location, and are reached over HTTPS.
+pub(crate) fn pipeline_url(state: &PipelineRuntimeState, endpoint: &str) -> String {
+    let scheme = if state.tls_certificate.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{scheme}://{}/{endpoint}", state.location)
+}
+
+/// HTTP client for sending requests to the pipeline, which only trusts the
+/// certificate of the pipeline, if any, and authenticates with the pipeline's
+/// bearer token.
+pub(crate) fn pipeline_http_client(
+    pipeline_id: PipelineId,
+    state: &PipelineRuntimeState,
+) -> Result<reqwest::Client, RunnerError> {
+    let tls_error = |e: reqwest::Error| RunnerError::HttpForwardError {
+        pipeline_id,
+        error: format!("invalid pipeline TLS certificate: {e}"),
+    };
+
+    let mut builder = reqwest::Client::builder();
+    if let Some(authorization) = pipeline_authorization(pipeline_id, state)? {
+        builder = builder.default_headers(reqwest::header::HeaderMap::from_iter([(
+            header::AUTHORIZATION,
+            authorization,
+        )]));
+    }
+    if let Some(certificate) = &state.tls_certificate {
+        builder = builder.tls_built_in_root_certs(false).add_root_certificate(
+            reqwest::Certificate::from_pem(certificate.as_bytes()).map_err(tls_error)?,
+        );
+    }
+    builder.build().map_err(tls_error)
+}
+
+/// Value of the `Authorization` header for requests to the pipeline.
+fn pipeline_authorization(
+    pipeline_id: PipelineId,
+    state: &PipelineRuntimeState,
+) -> Result<Option<HeaderValue>, RunnerError> {
+    state
+        .auth_token
+        .as_ref()
+        .map(|token| {
+            let mut value = HeaderValue::from_str(&format!("Bearer {token}")).map_err(|e| {
+                RunnerError::HttpForwardError {
+                    pipeline_id,
+                    error: format!("invalid pipeline authentication token: {e}"),
+                }
+            })?;
+            value.set_sensitive(true);
+            Ok(value)
+        })
+        .transpose()
+}
+
+/// TLS connector for streaming requests to the pipeline, which only trusts
+/// `certificate`.
+fn pipeline_ssl_connector(
+    pipeline_id: PipelineId,
+    certificate: &str,
+) -> Result<SslConnector, RunnerError> {
+    let tls_error = |e: ErrorStack| RunnerError::HttpForwardError {
+        pipeline_id,
+        error: format!("invalid pipeline TLS certificate: {e}"),
+    };
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).map_err(tls_error)?;
+    let mut store = X509StoreBuilder::new().map_err(tls_error)?;
+    for cert in X509::stack_from_pem(certificate.as_bytes()).map_err(tls_error)? {
+        store.add_cert(cert).map_err(tls_error)?;
+    }
+    builder
+        .set_verify_cert_store(store.build())
+        .map_err(tls_error)?;
+    Ok(builder.build())
+}
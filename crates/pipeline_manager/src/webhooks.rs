@@ -0,0 +1,327 @@
+//! Delivery of program and pipeline lifecycle notifications to tenant
+//! webhooks.
+//!
+//! Each notification is a JSON-encoded [`WebhookPayload`] sent in a `POST`
+//! request to the webhook URL.  Requests carry the following headers:
+//!
+//! * `X-Feldera-Event` - name of the event, e.g., `failed`.
+//! * `X-Feldera-Timestamp` - time the request was signed, in seconds since
+//!   the epoch.
+//! * `X-Feldera-Signature` - `sha256=<hex>`, where `<hex>` is the
+//!   HMAC-SHA256 of `<timestamp>.<body>` keyed with the webhook secret.
+//!
+//! Receivers should recompute the signature and reject requests with stale
+//! timestamps to prevent replay.
+//!
+//! Deliveries that fail or receive a non-2xx response are retried with
+//! exponential backoff, as configured by the [`WebhookRetryPolicy`] of the
+//! webhook.  Delivery happens in the background and never blocks the
+//! compiler or the pipeline automaton.
+use crate::{
+    auth::TenantId,
+    compiler::ProgramStatus,
+    db::{
+        storage::Storage, PipelineId, ProgramId, ProjectDB, Version, WebhookEvent,
+        WebhookRetryPolicy, WebhookSubscription,
+    },
+};
+use chrono::{DateTime, Utc};
+use dbsp_adapters::ErrorResponse;
+use log::{debug, error, warn};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use serde_json::json;
+use std::{borrow::Cow, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Timeout of a single delivery attempt.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Length of generated webhook secrets.
+const SECRET_LENGTH: usize = 64;
+
+/// Body of a webhook notification.
+#[derive(Serialize, Debug)]
+pub(crate) struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub tenant_id: TenantId,
+    /// Pipeline the event refers to.  `null` for program events.
+    pub pipeline_id: Option<PipelineId>,
+    /// Program the event refers to, for program events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_id: Option<ProgramId>,
+    /// Program version, for program events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+    /// Name of the connector endpoint, for `connector_degraded` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// Error that caused the pipeline, program, or connector to fail, if
+    /// any.
+    pub error: Option<ErrorResponse>,
+}
+
+impl WebhookPayload {
+    /// Payload of a pipeline lifecycle event.
+    pub(crate) fn pipeline(
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        event: WebhookEvent,
+        error: Option<ErrorResponse>,
+    ) -> Self {
+        Self {
+            event,
+            tenant_id,
+            pipeline_id: Some(pipeline_id),
+            program_id: None,
+            version: None,
+            connector: None,
+            timestamp: Utc::now(),
+            error,
+        }
+    }
+
+    /// Payload of a `compiled` or `compilation_failed` event, depending on
+    /// the final compilation `status` of the program.
+    pub(crate) fn program(
+        tenant_id: TenantId,
+        program_id: ProgramId,
+        version: Version,
+        status: &ProgramStatus,
+    ) -> Self {
+        let (event, error) = if status.has_failed_to_compile() {
+            let error_code = match status {
+                ProgramStatus::SqlError(_) => "SqlError",
+                ProgramStatus::RustError(_) => "RustError",
+                _ => "SystemError",
+            };
+            let error = ErrorResponse {
+                message: format!("Program {program_id} version {version} failed to compile"),
+                error_code: Cow::Borrowed(error_code),
+                details: serde_json::to_value(status).unwrap_or_default(),
+            };
+            (WebhookEvent::CompilationFailed, Some(error))
+        } else {
+            (WebhookEvent::Compiled, None)
+        };
+        Self {
+            event,
+            tenant_id,
+            pipeline_id: None,
+            program_id: Some(program_id),
+            version: Some(version),
+            connector: None,
+            timestamp: Utc::now(),
+            error,
+        }
+    }
+
+    /// Payload of a `connector_degraded` event.
+    pub(crate) fn connector(
+        tenant_id: TenantId,
+        pipeline_id: PipelineId,
+        connector: &str,
+        message: String,
+    ) -> Self {
+        let error = ErrorResponse {
+            message,
+            error_code: Cow::Borrowed("ConnectorDegraded"),
+            details: json!({ "endpoint_name": connector }),
+        };
+        Self {
+            event: WebhookEvent::ConnectorDegraded,
+            tenant_id,
+            pipeline_id: Some(pipeline_id),
+            program_id: None,
+            version: None,
+            connector: Some(connector.to_string()),
+            timestamp: Utc::now(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Generates a random secret for signing webhook payloads.
+pub(crate) fn generate_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Computes the value of the `X-Feldera-Signature` header.
+pub(crate) fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let key = PKey::hmac(secret.as_bytes()).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+    signer.update(timestamp.to_string().as_bytes()).unwrap();
+    signer.update(b".").unwrap();
+    signer.update(body.as_bytes()).unwrap();
+    let signature = signer.sign_to_vec().unwrap();
+    let hex: String = signature.iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+/// Notifies all webhooks of the tenant subscribed to the event of `payload`.
+///
+/// Returns once the deliveries have been scheduled.  Must not be called
+/// while holding the database lock.
+pub(crate) async fn notify(db: &Arc<Mutex<ProjectDB>>, payload: WebhookPayload) {
+    let tenant_id = payload.tenant_id;
+    let event = payload.event;
+    let subscriptions = match db.lock().await.list_webhook_subscriptions(tenant_id).await {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => {
+            error!("Failed to retrieve webhooks for tenant {tenant_id}: {e}");
+            return;
+        }
+    };
+    let subscriptions: Vec<WebhookSubscription> = subscriptions
+        .into_iter()
+        .filter(|s| s.descriptor.events.contains(&event))
+        .collect();
+    if subscriptions.is_empty() {
+        return;
+    }
+
+    let body = serde_json::to_string(&payload).unwrap();
+    for subscription in subscriptions {
+        tokio::spawn(deliver(subscription, event, body.clone()));
+    }
+}
+
+async fn deliver(subscription: WebhookSubscription, event: WebhookEvent, body: String) {
+    let client = reqwest::Client::new();
+    let url = &subscription.descriptor.url;
+    let WebhookRetryPolicy {
+        max_attempts,
+        initial_delay_ms,
+    } = subscription.descriptor.retry_policy;
+    // Always make at least one attempt.
+    let max_attempts = max_attempts.clamp(1, WebhookRetryPolicy::MAX_ATTEMPTS);
+
+    for attempt in 1..=max_attempts {
+        // Sign every attempt separately, so that the timestamp reflects
+        // the time the request was sent.
+        let timestamp = Utc::now().timestamp();
+        let result = client
+            .post(url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header("X-Feldera-Event", event.as_str())
+            .header("X-Feldera-Timestamp", timestamp.to_string())
+            .header(
+                "X-Feldera-Signature",
+                sign(&subscription.secret, timestamp, &body),
+            )
+            .body(body.clone())
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                debug!(
+                    "Delivered '{}' event to webhook {}",
+                    event.as_str(),
+                    subscription.descriptor.webhook_id
+                );
+                return;
+            }
+            Ok(response) => warn!(
+                "Webhook {} responded with status {} (attempt {attempt}/{max_attempts})",
+                subscription.descriptor.webhook_id,
+                response.status()
+            ),
+            Err(e) => warn!(
+                "Failed to deliver to webhook {} (attempt {attempt}/{max_attempts}): {e}",
+                subscription.descriptor.webhook_id
+            ),
+        }
+        if attempt < max_attempts {
+            tokio::time::sleep(retry_delay(initial_delay_ms, attempt)).await;
+        }
+    }
+    error!(
+        "Giving up on delivering '{}' event to webhook {}",
+        event.as_str(),
+        subscription.descriptor.webhook_id
+    );
+}
+
+/// Delay before retrying a delivery after `attempt` failed attempts: the
+/// initial delay doubles after each failure, up to
+/// [`WebhookRetryPolicy::MAX_DELAY_MS`].
+fn retry_delay(initial_delay_ms: u64, attempt: u32) -> Duration {
+    let delay_ms = initial_delay_ms
+        .saturating_mul(1u64 << attempt.saturating_sub(1).min(32))
+        .min(WebhookRetryPolicy::MAX_DELAY_MS);
+    Duration::from_millis(delay_ms)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{retry_delay, sign, WebhookPayload};
+    use crate::{
+        auth::TenantId,
+        compiler::ProgramStatus,
+        db::{ProgramId, Version, WebhookEvent},
+    };
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn signature() {
+        // Reference value computed with
+        // `echo -n '1700000000.{}' | openssl dgst -sha256 -hmac secret`.
+        assert_eq!(
+            sign("secret", 1700000000, "{}"),
+            "sha256=b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163"
+        );
+        assert_ne!(
+            sign("secret", 1700000000, "{}"),
+            sign("other", 1700000000, "{}")
+        );
+    }
+
+    #[test]
+    fn retry_delays() {
+        assert_eq!(retry_delay(1000, 1), Duration::from_secs(1));
+        assert_eq!(retry_delay(1000, 2), Duration::from_secs(2));
+        assert_eq!(retry_delay(1000, 4), Duration::from_secs(8));
+        assert_eq!(retry_delay(1000, 100), Duration::from_secs(3600));
+        assert_eq!(retry_delay(u64::MAX, 100), Duration::from_secs(3600));
+        assert_eq!(retry_delay(0, 100), Duration::ZERO);
+    }
+
+    #[test]
+    fn program_payload() {
+        let tenant_id = TenantId(Uuid::nil());
+        let program_id = ProgramId(Uuid::nil());
+
+        let payload =
+            WebhookPayload::program(tenant_id, program_id, Version(1), &ProgramStatus::Success);
+        assert_eq!(payload.event, WebhookEvent::Compiled);
+        assert!(payload.error.is_none());
+
+        let payload = WebhookPayload::program(
+            tenant_id,
+            program_id,
+            Version(2),
+            &ProgramStatus::RustError("error".to_string()),
+        );
+        assert_eq!(payload.event, WebhookEvent::CompilationFailed);
+        assert_eq!(payload.error.unwrap().error_code, "RustError");
+        let json = serde_json::to_value(WebhookPayload::program(
+            tenant_id,
+            program_id,
+            Version(2),
+            &ProgramStatus::Success,
+        ))
+        .unwrap();
+        assert_eq!(json["event"], "compiled");
+        assert_eq!(json["version"], 2);
+        assert!(json["pipeline_id"].is_null());
+        assert!(json.get("connector").is_none());
+    }
+}
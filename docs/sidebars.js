@@ -59,13 +59,14 @@ const sidebars = {
         'sql/decimal',
         'sql/string',
         'sql/array',
+        'sql/geo',
         'sql/datetime'
       ]
     },
     {
       type: 'category',
       label: 'API References',
-      items: ['api/rest', 'api/json', 'api/csv', 'api/rust']
+      items: ['api/rest', 'api/json', 'api/csv', 'api/text', 'api/auto', 'api/raw', 'api/sql-statements', 'api/avro', 'api/rust']
     },
     'papers',
     {
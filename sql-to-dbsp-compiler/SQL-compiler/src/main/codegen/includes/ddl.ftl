@@ -140,6 +140,30 @@ SqlCreate SqlCreateType(Span s, boolean replace) :
     }
 }
 
+SqlCreate SqlCreateAggregate(Span s, boolean replace) :
+{
+    final SqlIdentifier id;
+    SqlDataTypeSpec argType;
+    SqlDataTypeSpec resultType;
+    boolean nullable;
+}
+{
+    <AGGREGATE>
+    id = SimpleIdentifier()
+    <LPAREN>
+    argType = DataType()
+    nullable = NullableOptDefaultTrue()
+    { argType = argType.withNullable(nullable); }
+    <RPAREN>
+    <RETURNS>
+    resultType = DataType()
+    nullable = NullableOptDefaultTrue()
+    { resultType = resultType.withNullable(nullable); }
+    {
+        return new SqlCreateAggregate(s.end(this), replace, id, argType, resultType);
+    }
+}
+
 SqlCreate SqlCreateExtendedTable(Span s, boolean replace) :
 {
     final boolean ifNotExists;
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2022 VMware, Inc.
+ * SPDX-License-Identifier: MIT
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+package org.dbsp.sqlCompiler.circuit.operator;
+
+import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
+import org.dbsp.sqlCompiler.compiler.visitors.outer.CircuitVisitor;
+import org.dbsp.sqlCompiler.ir.expression.DBSPExpression;
+import org.dbsp.sqlCompiler.ir.type.DBSPType;
+import org.dbsp.sqlCompiler.ir.type.DBSPTypeIndexedZSet;
+
+import javax.annotation.Nullable;
+import java.util.List;
+import java.util.Objects;
+
+/**
+ * Finds the occurrences of a pattern in each group of an indexed collection.
+ * The values of a group are the events, in ascending order.  The function
+ * computes the measures of a match.  Implemented by the DBSP
+ * 'match_recognize' operator, which evaluates the pattern with a finite
+ * automaton and is incremental: like the window aggregate, it consumes
+ * changes and produces changes.
+ */
+public class DBSPMatchRecognizeOperator extends DBSPUnaryOperator {
+    /** Expression that builds the DBSP Pattern. */
+    public final DBSPExpression pattern;
+    public final DBSPType keyType;
+    public final DBSPType measuresType;
+    public final DBSPType weightType;
+
+    public DBSPMatchRecognizeOperator(
+            CalciteObject node, DBSPExpression pattern, DBSPExpression function,
+            DBSPType keyType, DBSPType measuresType, DBSPType weightType,
+            DBSPOperator input) {
+        super(node, "match_recognize", function,
+                new DBSPTypeIndexedZSet(node, keyType, measuresType, weightType),
+                true, input);
+        this.pattern = pattern;
+        this.keyType = keyType;
+        this.measuresType = measuresType;
+        this.weightType = weightType;
+    }
+
+    @Override
+    public DBSPOperator withFunction(@Nullable DBSPExpression expression, DBSPType outputType) {
+        DBSPTypeIndexedZSet ixOutputType = outputType.to(DBSPTypeIndexedZSet.class);
+        return new DBSPMatchRecognizeOperator(
+                this.getNode(), this.pattern, Objects.requireNonNull(expression),
+                ixOutputType.keyType, ixOutputType.elementType, ixOutputType.weightType,
+                this.input());
+    }
+
+    @Override
+    public DBSPOperator withInputs(List<DBSPOperator> newInputs, boolean force) {
+        if (force || this.inputsDiffer(newInputs))
+            return new DBSPMatchRecognizeOperator(
+                    this.getNode(), this.pattern, this.getFunction(),
+                    this.keyType, this.measuresType, this.weightType,
+                    newInputs.get(0));
+        return this;
+    }
+
+    @Override
+    public void accept(CircuitVisitor visitor) {
+        if (visitor.preorder(this).stop()) return;
+        visitor.postorder(this);
+    }
+}
@@ -32,6 +32,7 @@ import org.dbsp.util.SqlLexicalRulesConverter;
 import javax.annotation.Nullable;
 import java.util.HashMap;
 import java.util.Map;
+import java.util.Objects;
 
 /**
  * Packages options for a compiler from SQL to Rust.
@@ -108,6 +109,9 @@ public class CompilerOptions {
         @Parameter(names = "-d", description = "SQL syntax dialect used",
                    converter = SqlLexicalRulesConverter.class)
         public Lex lexicalRules;
+        @Parameter(names = "-udaf", description = "Rust file implementing the user-defined aggregates; it is included in the generated code")
+        @Nullable
+        public String udafFile = null;
 
         IO() {
             this.lexicalRules = Lex.ORACLE;
@@ -118,6 +122,7 @@ public class CompilerOptions {
          */
         public boolean same(IO io) {
             if (jit != io.jit) return false;
+            if (!Objects.equals(udafFile, io.udafFile)) return false;
             return lexicalRules == io.lexicalRules;
         }
 
@@ -133,6 +138,7 @@ public class CompilerOptions {
                     ", inputFile='" + inputFile + '\'' +
                     ", functionName='" + functionName + '\'' +
                     ", lexicalRules=" + lexicalRules +
+                    ", udafFile='" + udafFile + '\'' +
                     '}';
         }
     }
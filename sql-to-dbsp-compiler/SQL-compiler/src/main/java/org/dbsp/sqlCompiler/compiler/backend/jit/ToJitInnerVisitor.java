@@ -532,14 +532,61 @@ public class ToJitInnerVisitor extends InnerVisitor implements IWritesLogs {
         put("extract_millennium_Date", new FT("dbsp.date.millennium", new DBSPTypeInteger(CalciteObject.EMPTY, INT64,64, true,false)));
         put("extract_epoch_Date", new FT("dbsp.date.epoch", new DBSPTypeInteger(CalciteObject.EMPTY, INT64,64, true,false)));
         put("print", new FT("dbsp.io.str.print", new DBSPTypeVoid()));
+        put("regexp_match", new FT("dbsp.str.regexp_match", new DBSPTypeBool(CalciteObject.EMPTY, false)));
+        put("regexp_replace", new FT("dbsp.str.regexp_replace", new DBSPTypeString(CalciteObject.EMPTY, DBSPTypeString.UNLIMITED_PRECISION, false, false)));
     }};
 
+    /**
+     * Regular expression and JSON functions are named by the Rust backend
+     * after the nullability of each argument, e.g., regexp_match_N; strip
+     * this suffix.
+     */
+    static String stripNullabilitySuffix(String function) {
+        if (function.startsWith("regexp_") || function.startsWith("json_"))
+            return function.replaceAll("[_N]+$", "");
+        return function;
+    }
+
+    /**
+     * Translate a function that produces a nullable string.
+     * JIT functions cannot return NULL, so we call 'predicate' to compute
+     * whether the result is defined, and 'function' to compute the value.
+     * Both are called with the arguments of 'expression'.
+     */
+    JITInstructionPair nullableStringCall(
+            String predicate, String function, DBSPApplyExpression expression) {
+        JITInstructionPair defined = this.createFunctionCall(predicate,
+                new DBSPTypeBool(CalciteObject.EMPTY, false), expression, expression.arguments);
+        JITInstructionPair value = this.createFunctionCall(function,
+                new DBSPTypeString(CalciteObject.EMPTY, DBSPTypeString.UNLIMITED_PRECISION, false, false),
+                expression, expression.arguments);
+        // If any argument is null 'defined' is false.
+        JITInstructionRef isNull = this.insertUnary(
+                JITUnaryInstruction.Operation.NOT, defined.value, JITBoolType.INSTANCE);
+        return new JITInstructionPair(value.value, isNull);
+    }
+
     @Override
     public VisitDecision preorder(DBSPApplyExpression expression) {
         JITScalarType resultType = this.convertScalarType(expression);
         DBSPPathExpression path = expression.function.as(DBSPPathExpression.class);
         if (path != null) {
-            String function = path.path.toString();
+            String function = stripNullabilitySuffix(path.path.toString());
+            switch (function) {
+                case "regexp_extract":
+                    // NULL if the pattern does not match
+                    this.map(expression, this.nullableStringCall(
+                            "dbsp.str.regexp_match", "dbsp.str.regexp_extract", expression));
+                    return VisitDecision.STOP;
+                case "json_value":
+                case "json_query":
+                    // NULL if the path does not select a single suitable item
+                    this.map(expression, this.nullableStringCall(
+                            "dbsp.str." + function + "_defined", "dbsp.str." + function, expression));
+                    return VisitDecision.STOP;
+                default:
+                    break;
+            }
             if (function.endsWith("N"))
                 function = function.substring(0, function.length() - 1);
             FT jitFunction = functionTranslation.get(function);
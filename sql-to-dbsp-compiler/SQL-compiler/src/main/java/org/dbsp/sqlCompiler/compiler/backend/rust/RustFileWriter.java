@@ -18,7 +18,8 @@ import org.dbsp.util.IndentStream;
 import org.dbsp.util.Linq;
 import org.dbsp.util.Logger;
 import org.dbsp.util.ProgramAndTester;
-
+import org.dbsp.util.Utilities;
+import java.io.File;
 import java.io.FileNotFoundException;
 import java.io.PrintStream;
 import java.io.UnsupportedEncodingException;
@@ -140,12 +141,17 @@ public class RustFileWriter implements ICompilerComponent {
             "use tuple::declare_tuples;\n" +
             "use sqllib::{\n" +
             "    *,\n" +
+            "    aggregates::*,\n" +
             "    casts::*,\n" +
+            "    geometry::*,\n" +
             "    geopoint::*,\n" +
             "    timestamp::*,\n" +
             "    interval::*,\n" +
+            "    json::*,\n" +
             "    string::*,\n" +
+            "    text::*,\n" +
             "    operators::*,\n" +
+            "    pattern::*,\n" +
             "};\n" +
             "#[cfg(test)]\n" +
             "use sqlvalue::*;\n" +
@@ -178,6 +184,7 @@ public class RustFileWriter implements ICompilerComponent {
             "use sqllib::{\n" +
             "    *,\n" +
             "    casts::*,\n" +
+            "    geometry::*,\n" +
             "    geopoint::*,\n" +
             "    timestamp::*,\n" +
             "    interval::*,\n" +
@@ -311,6 +318,14 @@ public class RustFileWriter implements ICompilerComponent {
                     .append(this.getCompiler().getWeightTypeImplementation().toString())
                     .append(";")
                     .newline();
+            String udafFile = this.compiler.options.ioOptions.udafFile;
+            if (udafFile != null) {
+                // Defines the udaf module used by user-defined aggregates
+                stream.append("include!(")
+                        .append(Utilities.doubleQuote(new File(udafFile).getAbsolutePath()))
+                        .append(");")
+                        .newline();
+            }
             this.generateStructures(used, stream);
         }
         return stream.toString();
@@ -26,6 +26,7 @@ package org.dbsp.sqlCompiler.compiler.backend.rust;
 import org.dbsp.sqlCompiler.circuit.*;
 import org.dbsp.sqlCompiler.circuit.operator.*;
 import org.dbsp.sqlCompiler.compiler.IErrorReporter;
+import org.dbsp.sqlCompiler.compiler.errors.SourcePositionRange;
 import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
 import org.dbsp.sqlCompiler.compiler.visitors.VisitDecision;
 import org.dbsp.sqlCompiler.compiler.visitors.outer.CircuitVisitor;
@@ -270,6 +271,25 @@ public class ToRustVisitor extends CircuitVisitor {
         return VisitDecision.STOP;
     }
 
+    @Override
+    public VisitDecision preorder(DBSPMatchRecognizeOperator operator) {
+        this.writeComments(operator)
+                .append("let ")
+                .append(operator.getName())
+                .append(": ");
+        new DBSPTypeStream(operator.outputType).accept(this.innerVisitor);
+        this.builder.append(" = ")
+                .append(operator.input().getName())
+                .append(".")
+                .append(operator.operation)
+                .append("(");
+        operator.pattern.accept(this.innerVisitor);
+        this.builder.append(", ");
+        operator.getFunction().accept(this.innerVisitor);
+        this.builder.append(");");
+        return VisitDecision.STOP;
+    }
+
     @Override
     public VisitDecision preorder(DBSPIncrementalAggregateOperator operator) {
         DBSPType streamType = new DBSPTypeStream(operator.outputType);
@@ -319,8 +339,12 @@ public class ToRustVisitor extends CircuitVisitor {
     }
 
      IIndentStream writeComments(DBSPOperator operator) {
+        // The "sql:" line is used by the pipeline manager to map operators
+        // back to the SQL code they implement.
+        SourcePositionRange position = operator.getSourcePosition();
         return this.writeComments(operator.getClass().getSimpleName() + " " + operator.id +
-                (operator.comment != null ? "\n" + operator.comment : ""));
+                (operator.comment != null ? "\n" + operator.comment : "") +
+                (position.isValid() ? "\nsql: " + position : ""));
     }
 
     @Override
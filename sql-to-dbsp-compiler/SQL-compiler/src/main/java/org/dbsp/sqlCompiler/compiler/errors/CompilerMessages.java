@@ -17,31 +17,43 @@ public class CompilerMessages {
         public final SourcePositionRange range;
         public final boolean warning;
         public final String errorType;
+        /**
+         * Stable identifier for the kind of error, meant to be consumed by tools.
+         * Unlike errorType it does not contain spaces, e.g., DuplicateDefinition.
+         */
+        public final String errorCode;
         public final String message;
 
-        protected Error(SourcePositionRange range, boolean warning, String errorType, String message) {
+        protected Error(SourcePositionRange range, boolean warning, String errorType,
+                        String errorCode, String message) {
             this.range = range;
             this.warning = warning;
             this.errorType = errorType;
+            this.errorCode = errorCode;
             this.message = message;
         }
 
+        protected Error(SourcePositionRange range, boolean warning, String errorType, String message) {
+            this(range, warning, errorType, CompilerMessages.errorCode(errorType), message);
+        }
+
         Error(SqlParseException e) {
-            this(new SourcePositionRange(e.getPos()), false, "Error parsing SQL", e.getMessage());
+            this(new SourcePositionRange(e.getPos()), false, "Error parsing SQL", "ParseError", e.getMessage());
         }
 
         Error(CalciteContextException e) {
             this(new SourcePositionRange(
                     new SourcePosition(e.getPosLine(), e.getPosColumn()),
                     new SourcePosition(e.getEndPosLine(), e.getEndPosColumn())),
-            false, "Error in SQL statement",
+            false, "Error in SQL statement", "SqlStatementError",
                 (e.getCause() != null) ? e.getCause().getMessage() :
                         (e.getMessage() != null) ? e.getMessage() : "");
         }
 
         Error(Throwable e) {
             this(SourcePositionRange.INVALID, false,
-                    "This is a bug in the compiler (please report it to the developers)", e.getMessage());
+                    "This is a bug in the compiler (please report it to the developers)",
+                    "CompilerBug", e.getMessage());
         }
 
         Error(BaseCompilerException e) {
@@ -80,6 +92,7 @@ public class CompilerMessages {
             result.put("endColumn", this.range.end.column);
             result.put("warning", this.warning);
             result.put("errorType", this.errorType);
+            result.put("errorCode", this.errorCode);
             result.put("message", this.message);
             return result;
         }
@@ -92,6 +105,24 @@ public class CompilerMessages {
         }
     }
 
+    /**
+     * Convert an error type such as "Duplicate definition" into an error code
+     * such as "DuplicateDefinition".
+     */
+    static String errorCode(String errorType) {
+        StringBuilder builder = new StringBuilder();
+        boolean upper = true;
+        for (char c: errorType.toCharArray()) {
+            if (Character.isLetterOrDigit(c)) {
+                builder.append(upper ? Character.toUpperCase(c) : c);
+                upper = false;
+            } else {
+                upper = true;
+            }
+        }
+        return builder.toString();
+    }
+
     public final DBSPCompiler compiler;
     public final List<Error> messages;
     public int exitCode = 0;
@@ -31,9 +31,12 @@ import org.apache.calcite.sql.fun.*;
 import org.dbsp.sqlCompiler.compiler.ICompilerComponent;
 import org.dbsp.sqlCompiler.compiler.DBSPCompiler;
 import org.dbsp.sqlCompiler.compiler.errors.UnimplementedException;
+import org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler.UserAggregateFunction;
 import org.dbsp.sqlCompiler.ir.DBSPAggregate;
 import org.dbsp.sqlCompiler.ir.expression.literal.DBSPLiteral;
 import org.dbsp.sqlCompiler.ir.expression.literal.DBSPI64Literal;
+import org.dbsp.sqlCompiler.ir.path.DBSPPath;
+import org.dbsp.sqlCompiler.ir.path.DBSPSimplePathSegment;
 import org.dbsp.sqlCompiler.ir.type.*;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeInteger;
 import org.dbsp.sqlCompiler.ir.expression.*;
@@ -374,6 +377,53 @@ public class AggregateCompiler implements ICompilerComponent {
                 node, zero, this.makeRowClosure(increment, accumulator), post, postZero, semigroup, null));
     }
 
+    /**
+     * Call a generic function from the runtime library that implements part
+     * of the user-defined aggregate 'aggregate'.
+     */
+    static DBSPExpression callUserAggregate(
+            String function, DBSPType aggregate, DBSPType returnType, DBSPExpression... arguments) {
+        DBSPType[] typeArgs;
+        if (function.equals("udaf_increment"))
+            // The weight type is inferred
+            typeArgs = new DBSPType[] { aggregate, DBSPTypeAny.getDefault() };
+        else
+            typeArgs = new DBSPType[] { aggregate };
+        DBSPExpression path = new DBSPPath(new DBSPSimplePathSegment(function, typeArgs)).toExpression();
+        return new DBSPApplyExpression(path, returnType, arguments);
+    }
+
+    void processUser(UserAggregateFunction function) {
+        CalciteObject node = new CalciteObject(function);
+        if (this.filterArgument >= 0)
+            throw new UnimplementedException(node);
+        TypeCompiler typeCompiler = this.compiler.getTypeCompiler();
+        DBSPType argumentType = typeCompiler.convertType(function.argumentType, false);
+        DBSPType declaredResultType = typeCompiler.convertType(function.resultType, false);
+        // The implementation is registered in the udaf module by the user_aggregates! macro
+        DBSPType aggregate = new DBSPTypeUser(node, USER, "udaf::" + function.getName(), false);
+        DBSPType accumulatorType = new DBSPTypeUser(node, USER, "UdafAccumulator", false, aggregate);
+
+        DBSPExpression zero = callUserAggregate("udaf_zero", aggregate, accumulatorType);
+        DBSPVariablePath accumulator = accumulatorType.var(this.genAccumulatorName());
+        DBSPExpression value = this.getAggregatedValue().cast(argumentType);
+        // DISTINCT aggregates see each value once, irrespective of its weight
+        DBSPExpression weight = this.isDistinct ? new DBSPI64Literal(1L) : this.compiler.weightVar;
+        // The accumulator is stored in a tuple behind a mutable reference, and need not be Copy
+        DBSPExpression increment = callUserAggregate(
+                "udaf_increment", aggregate, accumulatorType, accumulator.applyClone(), value.borrow(), weight);
+
+        DBSPVariablePath a = accumulatorType.var(this.genAccumulatorName());
+        DBSPExpression finish = callUserAggregate("udaf_finish", aggregate, declaredResultType, a);
+        if (!declaredResultType.mayBeNull)
+            finish = finish.some();
+        DBSPClosureExpression post = new DBSPClosureExpression(node, finish, a.asParameter());
+        DBSPExpression postZero = DBSPLiteral.none(this.nullableResultType);
+        DBSPType semigroup = new DBSPTypeUser(node, USER, "UserAggregateSemigroup", false, aggregate);
+        this.setFoldingFunction(new DBSPAggregate.Implementation(
+                node, zero, this.makeRowClosure(increment, accumulator), post, postZero, semigroup, null));
+    }
+
     public DBSPAggregate.Implementation compile() {
         boolean success =
                 this.process(this.aggFunction, SqlCountAggFunction.class, this::processCount) ||
@@ -381,7 +431,8 @@ public class AggregateCompiler implements ICompilerComponent {
                 this.process(this.aggFunction, SqlSumAggFunction.class, this::processSum) ||
                 this.process(this.aggFunction, SqlSumEmptyIsZeroAggFunction.class, this::processSumZero) ||
                 this.process(this.aggFunction, SqlAvgAggFunction.class, this::processAvg) ||
-                this.process(this.aggFunction, SqlSingleValueAggFunction.class, this::processSingle);
+                this.process(this.aggFunction, SqlSingleValueAggFunction.class, this::processSingle) ||
+                this.process(this.aggFunction, UserAggregateFunction.class, this::processUser);
         if (!success || this.foldingFunction == null)
             throw new UnimplementedException(new CalciteObject(this.aggFunction));
         return this.foldingFunction;
@@ -48,7 +48,9 @@ import org.dbsp.sqlCompiler.circuit.DBSPPartialCircuit;
 import org.dbsp.sqlCompiler.compiler.DBSPCompiler;
 import org.dbsp.sqlCompiler.ir.DBSPAggregate;
 import org.dbsp.sqlCompiler.ir.expression.literal.DBSPBoolLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPI32Literal;
 import org.dbsp.sqlCompiler.ir.expression.literal.DBSPLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPVecLiteral;
 import org.dbsp.sqlCompiler.ir.expression.literal.DBSPZSetLiteral;
 import org.dbsp.sqlCompiler.ir.path.DBSPPath;
 import org.dbsp.sqlCompiler.ir.expression.*;
@@ -1071,6 +1073,188 @@ public class CalciteToDBSPCompiler extends RelVisitor
         this.assignOperator(sort, sortElement);
     }
 
+    /**
+     * A variable in a MATCH_RECOGNIZE pattern, with its quantifier.
+     */
+    static class PatternVariable {
+        public final String name;
+        public final int min;
+        /** -1 if unbounded. */
+        public final int max;
+
+        PatternVariable(String name, int min, int max) {
+            this.name = name;
+            this.min = min;
+            this.max = max;
+        }
+    }
+
+    /**
+     * Flatten a MATCH_RECOGNIZE pattern into a sequence of variables.
+     * Only concatenations of (possibly quantified) variables are supported.
+     */
+    void flattenPattern(RexNode pattern, List<PatternVariable> variables) {
+        CalciteObject node = new CalciteObject(pattern);
+        if (pattern instanceof RexLiteral) {
+            String name = Objects.requireNonNull(((RexLiteral) pattern).getValueAs(String.class));
+            variables.add(new PatternVariable(name, 1, 1));
+            return;
+        }
+        if (!(pattern instanceof RexCall))
+            throw new UnimplementedException(node);
+        RexCall call = (RexCall) pattern;
+        switch (call.op.kind) {
+            case PATTERN_CONCAT:
+                for (RexNode operand: call.operands)
+                    this.flattenPattern(operand, variables);
+                break;
+            case PATTERN_QUANTIFIER: {
+                RexNode operand = call.operands.get(0);
+                boolean reluctant = RexLiteral.booleanValue(call.operands.get(3));
+                if (!(operand instanceof RexLiteral) || reluctant)
+                    throw new UnimplementedException(node);
+                String name = Objects.requireNonNull(((RexLiteral) operand).getValueAs(String.class));
+                variables.add(new PatternVariable(name,
+                        RexLiteral.intValue(call.operands.get(1)),
+                        RexLiteral.intValue(call.operands.get(2))));
+                break;
+            }
+            default:
+                // Alternation, permutation, exclusion
+                throw new UnimplementedException(node);
+        }
+    }
+
+    public void visitMatch(LogicalMatch match) {
+        // Index the rows by the partition keys, pairing each row with its
+        // sort key, so the values of each group are ordered by the sort key.
+        // Then find the matches in each group with the DBSP match_recognize
+        // operator, and flatten the partition keys and measures into a row:
+        // index(|t| (partition_key, (order_key, t)))
+        //     .match_recognize(pattern, measures)
+        //     .map(|kv| (kv.0..., kv.1...))
+        CalciteObject node = new CalciteObject(match);
+        if (match.isAllRows() || match.isStrictStart() || match.isStrictEnd() ||
+                !match.getSubsets().isEmpty() || match.getInterval() != null)
+            throw new UnimplementedException(node);
+        RelNode input = match.getInput();
+        DBSPTypeTuple inputRowType = this.convertType(input.getRowType(), false).to(DBSPTypeTuple.class);
+        DBSPTypeTuple type = this.convertType(match.getRowType(), false).to(DBSPTypeTuple.class);
+        DBSPOperator opInput = this.getOperator(input);
+
+        List<PatternVariable> variables = new ArrayList<>();
+        this.flattenPattern(match.getPattern(), variables);
+        List<String> names = Linq.map(variables, v -> v.name);
+        if (new HashSet<>(names).size() != names.size())
+            // A variable that appears multiple times in the pattern
+            throw new UnimplementedException(node);
+
+        boolean skipToNextRow;
+        RexNode after = match.getAfter();
+        if (!(after instanceof RexLiteral))
+            // AFTER MATCH SKIP TO FIRST/LAST variable
+            throw new UnimplementedException(node);
+        SqlMatchRecognize.AfterOption afterOption = Objects.requireNonNull(
+                ((RexLiteral) after).getValueAs(SqlMatchRecognize.AfterOption.class));
+        switch (afterOption) {
+            case SKIP_TO_NEXT_ROW:
+                skipToNextRow = true;
+                break;
+            case SKIP_PAST_LAST_ROW:
+                skipToNextRow = false;
+                break;
+            default:
+                throw new UnimplementedException(node);
+        }
+
+        // Index by the partition keys
+        DBSPVariablePath t = inputRowType.ref().var("t");
+        List<DBSPExpression> partitionFields = new ArrayList<>();
+        for (int field: match.getPartitionKeys())
+            partitionFields.add(t.field(field).applyCloneIfNeeded());
+        DBSPExpression keyExpression = new DBSPRawTupleExpression(
+                partitionFields.toArray(new DBSPExpression[0]));
+        DBSPType keyType = keyExpression.getType();
+        List<DBSPExpression> orderFields = new ArrayList<>();
+        for (RelFieldCollation collation: match.getOrderKeys().getFieldCollations()) {
+            int field = collation.getFieldIndex();
+            // Values are sorted using the Rust order, which places NULLs first
+            if (collation.getDirection() != RelFieldCollation.Direction.ASCENDING ||
+                    inputRowType.getFieldType(field).mayBeNull)
+                throw new UnimplementedException(node);
+            orderFields.add(t.field(field).applyCloneIfNeeded());
+        }
+        DBSPExpression orderKey = new DBSPRawTupleExpression(
+                orderFields.toArray(new DBSPExpression[0]));
+        DBSPTypeRawTuple valueType = new DBSPTypeRawTuple(orderKey.getType(), inputRowType);
+        DBSPExpression partitionKeys =
+                new DBSPRawTupleExpression(
+                        keyExpression,
+                        new DBSPRawTupleExpression(orderKey, DBSPTupleExpression.flatten(t)))
+                        .closure(t.asParameter());
+        DBSPIndexOperator index = new DBSPIndexOperator(
+                node, partitionKeys, keyType, valueType, new DBSPTypeWeight(),
+                opInput.isMultiset, opInput);
+        this.circuit.addOperator(index);
+
+        // The pattern; predicates only see the rows.
+        DBSPType variableType = new DBSPTypeUser(node, USER, "PatternVariable", false, valueType);
+        DBSPExpression[] patternVariables = new DBSPExpression[variables.size()];
+        for (int i = 0; i < variables.size(); i++) {
+            PatternVariable variable = variables.get(i);
+            DBSPVariablePath v = inputRowType.ref().var("v");
+            DBSPVariablePath prev = inputRowType.ref().var("prev");
+            DBSPVariablePath hasPrev = new DBSPTypeBool(node, false).var("has_prev");
+            RexNode definition = match.getPatternDefinitions().get(variable.name);
+            DBSPExpression predicate;
+            if (definition == null) {
+                // Variables without a definition match any row
+                predicate = new DBSPBoolLiteral(true);
+            } else {
+                PatternExpressionCompiler definitionCompiler = PatternExpressionCompiler.forDefinition(
+                        variable.name, names, v, prev, hasPrev, this.compiler);
+                predicate = ExpressionCompiler.wrapBoolIfNeeded(definitionCompiler.compile(definition));
+            }
+            patternVariables[i] = new DBSPApplyExpression(node, "pattern_variable", variableType,
+                    predicate.closure(v.asParameter(), prev.asParameter(), hasPrev.asParameter()),
+                    new DBSPI32Literal(variable.min), new DBSPI32Literal(variable.max));
+        }
+        DBSPExpression pattern = new DBSPApplyExpression(node, "pattern_new",
+                new DBSPTypeUser(node, USER, "Pattern", false, valueType),
+                new DBSPVecLiteral(patternVariables), new DBSPBoolLiteral(skipToNextRow));
+
+        // The measures
+        DBSPType matchType = new DBSPTypeUser(node, USER, "PatternMatch", false, valueType);
+        DBSPVariablePath m = matchType.ref().var("m");
+        PatternExpressionCompiler measureCompiler = PatternExpressionCompiler.forMeasures(
+                names, m, inputRowType, this.compiler);
+        int partitionCount = partitionFields.size();
+        DBSPExpression[] measureFields = new DBSPExpression[type.size() - partitionCount];
+        int next = 0;
+        for (RexNode measure: match.getMeasures().values()) {
+            measureFields[next] = measureCompiler.compile(measure).cast(type.getFieldType(partitionCount + next));
+            next++;
+        }
+        DBSPExpression measuresTuple = new DBSPTupleExpression(measureFields);
+        DBSPType measuresType = measuresTuple.getType();
+        DBSPExpression measures = measuresTuple.closure(m.asParameter());
+        DBSPMatchRecognizeOperator matchRecognize = new DBSPMatchRecognizeOperator(node,
+                pattern, measures, keyType, measuresType, new DBSPTypeWeight(), index);
+        this.circuit.addOperator(matchRecognize);
+
+        // The output row: the partition keys followed by the measures
+        DBSPTypeRawTuple kvType = new DBSPTypeRawTuple(keyType.ref(), measuresType.ref());
+        DBSPVariablePath kv = kvType.var("kv");
+        DBSPExpression[] outputFields = new DBSPExpression[type.size()];
+        for (int i = 0; i < partitionCount; i++)
+            outputFields[i] = kv.field(0).field(i).applyCloneIfNeeded();
+        for (int i = 0; i < measureFields.length; i++)
+            outputFields[partitionCount + i] = kv.field(1).field(i).applyCloneIfNeeded();
+        DBSPExpression mapper = new DBSPTupleExpression(outputFields).closure(kv.asParameter());
+        DBSPMapOperator map = new DBSPMapOperator(node, mapper, type, new DBSPTypeWeight(), matchRecognize);
+        this.assignOperator(match, map);
+    }
+
     @Override
     public void visit(
             RelNode node, int ordinal,
@@ -1103,6 +1287,7 @@ public class CalciteToDBSPCompiler extends RelVisitor
                 this.visitIfMatches(node, LogicalIntersect.class, this::visitIntersect) ||
                 this.visitIfMatches(node, LogicalWindow.class, this::visitWindow) ||
                 this.visitIfMatches(node, LogicalSort.class, this::visitSort) ||
+                this.visitIfMatches(node, LogicalMatch.class, this::visitMatch) ||
                 this.visitIfMatches(node, Uncollect.class, this::visitUncollect);
         if (!success)
             throw new UnimplementedException(new CalciteObject(node));
@@ -1172,6 +1357,10 @@ public class CalciteToDBSPCompiler extends RelVisitor
                 this.circuit.addOperator(result);
             }
             return null;
+        } else if (statement.is(CreateAggregateStatement.class)) {
+            // Nothing to do: the aggregate has been registered with Calcite,
+            // and calls are compiled by the AggregateCompiler.
+            return null;
         } else if (statement.is(TableModifyStatement.class)) {
             TableModifyStatement modify = statement.to(TableModifyStatement.class);
             // The type of the data must be extracted from the modified table
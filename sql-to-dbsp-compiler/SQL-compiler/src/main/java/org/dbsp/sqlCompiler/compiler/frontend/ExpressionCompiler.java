@@ -41,6 +41,7 @@ import org.dbsp.sqlCompiler.ir.type.*;
 import org.dbsp.sqlCompiler.ir.type.primitive.*;
 import org.dbsp.util.*;
 import org.locationtech.jts.geom.Coordinate;
+import org.locationtech.jts.geom.Geometry;
 import org.locationtech.jts.geom.Point;
 
 import javax.annotation.Nullable;
@@ -160,6 +161,15 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
                         Objects.requireNonNull(literal.getValueAs(TimestampString.class)));
             } else if (type.is(DBSPTypeDate.class)) {
                 return new DBSPDateLiteral(node, type, Objects.requireNonNull(literal.getValueAs(DateString.class)));
+            } else if (type.is(DBSPTypeGeometry.class)) {
+                // Calcite folds constant spatial expressions into JTS geometries;
+                // we rebuild them at runtime from their WKT representation.
+                Geometry geometry = Objects.requireNonNull(literal.getValueAs(Geometry.class));
+                DBSPExpression result = new DBSPApplyExpression(node, "st_geomfromtext_s",
+                        type.setMayBeNull(false), new DBSPStringLiteral(geometry.toText()));
+                if (type.mayBeNull)
+                    result = result.some();
+                return result;
             } else if (type.is(DBSPTypeGeoPoint.class)) {
                 Point point = literal.getValueAs(Point.class);
                 Coordinate c = Objects.requireNonNull(point).getCoordinate();
@@ -398,6 +408,36 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
         return this.compileFunction(this.getCallName(call), node, resultType, ops, expectedArgCount);
     }
 
+    /**
+     * Compile a call to JSON_VALUE or JSON_QUERY.
+     * Only the default behaviors are supported: NULL ON EMPTY, NULL ON ERROR,
+     * and WITHOUT ARRAY WRAPPER.  The result is NULL when the path does not
+     * select a single item of the right kind.
+     * @param  call Call that is being compiled.
+     * @param  node CalciteObject holding the call.
+     * @param  resultType Type of result produced by call.
+     * @param  ops  Translated operands for the call.
+     */
+    DBSPExpression compileJsonFunction(
+            RexCall call, CalciteObject node, DBSPType resultType, List<DBSPExpression> ops) {
+        if (ops.size() < 2)
+            throw new UnimplementedException(node);
+        for (DBSPExpression op: ops.subList(2, ops.size())) {
+            DBSPKeywordLiteral keyword = op.as(DBSPKeywordLiteral.class);
+            if (keyword == null ||
+                    (!keyword.keyword.equals("NULL") && !keyword.keyword.equals("WITHOUT_ARRAY")))
+                throw new UnimplementedException(node);
+        }
+        DBSPType stringType = new DBSPTypeString(
+                CalciteObject.EMPTY, DBSPTypeString.UNLIMITED_PRECISION, false, true);
+        DBSPExpression result = this.compileFunction(call, node, stringType, ops.subList(0, 2), 2);
+        resultType = resultType.setMayBeNull(true);
+        if (!resultType.is(DBSPTypeString.class))
+            // JSON_VALUE(... RETURNING type)
+            return result.cast(resultType);
+        return result;
+    }
+
     /**
      * Compile a function call into a Rust function.
      * One of the arguments is a keyword.
@@ -448,6 +488,10 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
             call = (RexCall)RexUtil.expandSearch(this.rexBuilder, null, call);
         }
         List<DBSPExpression> ops = Linq.map(call.operands, e -> e.accept(this));
+        if (call.op.kind == SqlKind.JSON_VALUE_EXPRESSION)
+            // Calcite wraps the document argument of JSON functions in this
+            // operator, which has type ANY; the JSON functions take strings.
+            return ops.get(0);
         DBSPType type = this.typeCompiler.convertType(call.getType(), false);
         switch (call.op.kind) {
             case TIMES:
@@ -578,14 +622,12 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
                 return result;
             }
             case ST_POINT: {
-                if (ops.size() != 2)
-                    throw new UnimplementedException("Expected only 2 operands", node);
-                DBSPExpression left = ops.get(0);
-                DBSPExpression right = ops.get(1);
-                String functionName = "make_geopoint" + type.nullableSuffix() +
-                        "_" + left.getType().baseTypeWithSuffix() +
-                        "_" + right.getType().baseTypeWithSuffix();
-                return new DBSPApplyExpression(node, functionName, type, left, right);
+                // Calcite only folds points with constant coordinates.
+                List<DBSPExpression> args = Linq.map(ops,
+                        op -> op.cast(new DBSPTypeDouble(CalciteObject.EMPTY, op.getType().mayBeNull)));
+                boolean nullable = Linq.any(args, arg -> arg.getType().mayBeNull);
+                return this.compilePolymorphicFunction(call, node, type.setMayBeNull(nullable), args, 2)
+                        .cast(type);
             }
             case OTHER_FUNCTION: {
                 String opName = call.op.getName().toLowerCase();
@@ -616,6 +658,18 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
                         return this.compilePolymorphicFunction(call, node, type,
                                 ops, 1);
                     }
+                    case "st_geomfromtext":
+                    case "st_geogfromtext":
+                    case "st_astext":
+                        return this.compilePolymorphicFunction(call, node, type, ops, 1);
+                    case "st_grid_cells": {
+                        this.validateArgCount(node, ops.size(), 2);
+                        DBSPExpression size = ops.get(1);
+                        size = size.cast(new DBSPTypeDouble(CalciteObject.EMPTY, size.getType().mayBeNull));
+                        return this.compilePolymorphicFunction(call, node, type, Linq.list(ops.get(0), size), 2);
+                    }
+                    case "st_contains":
+                    case "st_within":
                     case "st_distance":
                     case "power": {
                         return this.compilePolymorphicFunction(call, node, type,
@@ -624,8 +678,15 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
                     case "split":
                         return this.compileFunction(call, node, type, ops, 1, 2);
                     case "overlay":
-                    // case "regexp_replace":
                         return this.compileFunction(call, node, type, ops, 3, 4);
+                    case "regexp_replace":
+                        return this.compileFunction(call, node, type, ops, 3);
+                    case "regexp_extract":
+                        // Result is NULL if the pattern does not match
+                        return this.compileFunction(call, node, type.setMayBeNull(true), ops, 2);
+                    case "json_value":
+                    case "json_query":
+                        return this.compileJsonFunction(call, node, type, ops);
                     case "char_length":
                     case "ascii":
                     case "chr":
@@ -645,9 +706,20 @@ public class ExpressionCompiler extends RexVisitorImpl<DBSPExpression> implement
                         return new DBSPApplyExpression(node, opName, type, ops.get(0), ops.get(1));
                     case "repeat":
                     case "left":
+                    case "regexp_match":
+                    case "text_contains":
+                    case "text_phrase":
                         return this.compileFunction(call, node, type, ops, 2);
                     case "replace":
                         return this.compileFunction(call, node, type, ops, 3);
+                    case "zscore":
+                    case "is_outlier": {
+                        // Arguments are converted to nullable doubles, so a single
+                        // implementation handles all argument types.
+                        DBSPType argType = new DBSPTypeDouble(CalciteObject.EMPTY, true);
+                        List<DBSPExpression> args = Linq.map(ops, op -> op.cast(argType));
+                        return this.compileFunction(call, node, type, args, opName.equals("zscore") ? 3 : 4);
+                    }
                     case "division":
                         return makeBinaryExpression(node, type, DBSPOpcode.DIV, ops);
                     case "element": {
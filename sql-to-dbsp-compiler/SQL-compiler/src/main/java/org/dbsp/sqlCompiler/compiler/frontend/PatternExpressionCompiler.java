@@ -0,0 +1,220 @@
+/*
+ * Copyright 2022 VMware, Inc.
+ * SPDX-License-Identifier: MIT
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+package org.dbsp.sqlCompiler.compiler.frontend;
+
+import org.apache.calcite.rex.RexCall;
+import org.apache.calcite.rex.RexLiteral;
+import org.apache.calcite.rex.RexNode;
+import org.apache.calcite.rex.RexPatternFieldRef;
+import org.apache.calcite.sql.SqlKind;
+import org.dbsp.sqlCompiler.compiler.DBSPCompiler;
+import org.dbsp.sqlCompiler.compiler.errors.InternalCompilerError;
+import org.dbsp.sqlCompiler.compiler.errors.UnimplementedException;
+import org.dbsp.sqlCompiler.ir.expression.DBSPApplyExpression;
+import org.dbsp.sqlCompiler.ir.expression.DBSPExpression;
+import org.dbsp.sqlCompiler.ir.expression.DBSPFieldExpression;
+import org.dbsp.sqlCompiler.ir.expression.DBSPIfExpression;
+import org.dbsp.sqlCompiler.ir.expression.DBSPVariablePath;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPUSizeLiteral;
+import org.dbsp.sqlCompiler.ir.type.DBSPType;
+import org.dbsp.sqlCompiler.ir.type.DBSPTypeTuple;
+import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeBool;
+
+import javax.annotation.Nullable;
+import java.util.List;
+import java.util.Objects;
+
+/**
+ * Compiles the expressions that appear in a MATCH_RECOGNIZE clause.
+ * DEFINE predicates are evaluated on a single row, and can refer to the
+ * previous row in the partition using PREV.  MEASURES are evaluated on a
+ * complete match, and refer to the first or last row matched by a pattern
+ * variable.
+ */
+public class PatternExpressionCompiler extends ExpressionCompiler {
+    /** Names of the pattern variables; the position of a variable in
+     * this list identifies it at runtime. */
+    final List<String> variables;
+    /** Variable being defined, when compiling a DEFINE predicate. */
+    @Nullable
+    final String defined;
+    /** Previous row in the partition, when compiling a DEFINE predicate. */
+    @Nullable
+    final DBSPVariablePath prev;
+    /** True if there is a previous row, when compiling a DEFINE predicate. */
+    @Nullable
+    final DBSPVariablePath hasPrev;
+    /** The match, when compiling MEASURES. */
+    @Nullable
+    final DBSPVariablePath match;
+    /** Type of the rows that are being matched. */
+    final DBSPType rowType;
+
+    PatternExpressionCompiler(@Nullable DBSPVariablePath row, List<String> variables,
+                              @Nullable String defined, @Nullable DBSPVariablePath prev,
+                              @Nullable DBSPVariablePath hasPrev, @Nullable DBSPVariablePath match,
+                              DBSPType rowType, DBSPCompiler compiler) {
+        super(row, compiler);
+        this.variables = variables;
+        this.defined = defined;
+        this.prev = prev;
+        this.hasPrev = hasPrev;
+        this.match = match;
+        this.rowType = rowType;
+    }
+
+    /**
+     * Create a compiler for the DEFINE predicate of a pattern variable.
+     * @param defined    Variable being defined.
+     * @param variables  All pattern variables.
+     * @param row        Reference to the current row.
+     * @param prev       Reference to the previous row.
+     * @param hasPrev    Boolean that is false for the first row of a partition.
+     */
+    public static PatternExpressionCompiler forDefinition(
+            String defined, List<String> variables, DBSPVariablePath row,
+            DBSPVariablePath prev, DBSPVariablePath hasPrev, DBSPCompiler compiler) {
+        return new PatternExpressionCompiler(row, variables, defined, prev, hasPrev,
+                null, row.getType().deref(), compiler);
+    }
+
+    /**
+     * Create a compiler for the MEASURES of a match.
+     * @param variables  All pattern variables.
+     * @param match      Reference to the match.
+     * @param rowType    Type of the rows that are matched.
+     */
+    public static PatternExpressionCompiler forMeasures(
+            List<String> variables, DBSPVariablePath match, DBSPType rowType, DBSPCompiler compiler) {
+        return new PatternExpressionCompiler(null, variables, null, null, null,
+                match, rowType, compiler);
+    }
+
+    boolean isDefinition() {
+        return this.match == null;
+    }
+
+    @Override
+    public DBSPExpression visitPatternFieldRef(RexPatternFieldRef fieldRef) {
+        if (this.isDefinition()) {
+            this.checkCurrentRow(fieldRef);
+            return this.visitInputRef(fieldRef);
+        }
+        // In MEASURES a plain reference denotes the last row matched by the variable.
+        return this.matchedField(fieldRef, true);
+    }
+
+    @Override
+    public DBSPExpression visitCall(RexCall call) {
+        CalciteObject node = new CalciteObject(call);
+        switch (call.op.kind) {
+            case FINAL:
+            case RUNNING:
+                // We only produce one row per match, so these do not make a difference.
+                return call.operands.get(0).accept(this);
+            case PREV: {
+                if (!this.isDefinition())
+                    throw new UnimplementedException(node);
+                RexPatternFieldRef field = this.getFieldRef(call);
+                int offset = this.getOffset(call);
+                if (offset == 0)
+                    return field.accept(this);
+                if (offset != 1)
+                    throw new UnimplementedException(node);
+                this.checkCurrentRow(field);
+                DBSPExpression value = new DBSPFieldExpression(
+                        node, Objects.requireNonNull(this.prev), field.getIndex()).applyCloneIfNeeded();
+                DBSPType type = value.getType().setMayBeNull(true);
+                return new DBSPIfExpression(node, Objects.requireNonNull(this.hasPrev),
+                        value.cast(type), DBSPLiteral.none(type));
+            }
+            case FIRST:
+            case LAST: {
+                RexPatternFieldRef field = this.getFieldRef(call);
+                if (this.getOffset(call) != 0)
+                    throw new UnimplementedException(node);
+                if (this.isDefinition()) {
+                    // The last row of the variable being defined is the current row.
+                    if (call.op.kind == SqlKind.FIRST)
+                        throw new UnimplementedException(node);
+                    return field.accept(this);
+                }
+                return this.matchedField(field, call.op.kind == SqlKind.LAST);
+            }
+            case NEXT:
+            case CLASSIFIER:
+            case MATCH_NUMBER:
+                throw new UnimplementedException(node);
+            default:
+                return super.visitCall(call);
+        }
+    }
+
+    RexPatternFieldRef getFieldRef(RexCall call) {
+        RexNode operand = call.operands.get(0);
+        if (!(operand instanceof RexPatternFieldRef))
+            throw new UnimplementedException(new CalciteObject(call));
+        return (RexPatternFieldRef) operand;
+    }
+
+    int getOffset(RexCall call) {
+        if (call.operands.size() < 2)
+            return 0;
+        RexNode operand = call.operands.get(1);
+        if (!(operand instanceof RexLiteral))
+            throw new UnimplementedException(new CalciteObject(call));
+        return RexLiteral.intValue(operand);
+    }
+
+    /** A DEFINE predicate can only refer to the current row through the variable being defined. */
+    void checkCurrentRow(RexPatternFieldRef fieldRef) {
+        String alpha = fieldRef.getAlpha();
+        if (!alpha.equals(this.defined) && !alpha.equals("*"))
+            throw new UnimplementedException(new CalciteObject(fieldRef));
+    }
+
+    /**
+     * Field of the first or last row matched by a pattern variable,
+     * or NULL if the variable did not match any row.
+     */
+    DBSPExpression matchedField(RexPatternFieldRef fieldRef, boolean last) {
+        CalciteObject node = new CalciteObject(fieldRef);
+        int variable = this.variables.indexOf(fieldRef.getAlpha());
+        if (variable < 0)
+            // e.g., a reference to all rows of the match
+            throw new UnimplementedException(node);
+        DBSPVariablePath match = Objects.requireNonNull(this.match);
+        if (fieldRef.getIndex() >= this.rowType.to(DBSPTypeTuple.class).size())
+            throw new InternalCompilerError("Index in row out of bounds ", node);
+        DBSPExpression index = new DBSPUSizeLiteral(variable);
+        DBSPExpression row = new DBSPApplyExpression(node,
+                last ? "pattern_last" : "pattern_first", this.rowType.ref(), match, index);
+        DBSPExpression value = new DBSPFieldExpression(node, row, fieldRef.getIndex()).applyCloneIfNeeded();
+        DBSPType type = value.getType().setMayBeNull(true);
+        DBSPExpression has = new DBSPApplyExpression(node, "pattern_has",
+                new DBSPTypeBool(CalciteObject.EMPTY, false), match, index);
+        return new DBSPIfExpression(node, has, value.cast(type), DBSPLiteral.none(type));
+    }
+}
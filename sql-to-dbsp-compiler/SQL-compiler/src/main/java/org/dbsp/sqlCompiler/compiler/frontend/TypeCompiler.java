@@ -29,6 +29,7 @@ import org.apache.calcite.sql.type.SqlTypeName;
 import org.dbsp.sqlCompiler.compiler.ICompilerComponent;
 import org.dbsp.sqlCompiler.compiler.DBSPCompiler;
 import org.dbsp.sqlCompiler.compiler.errors.SourcePositionRange;
+import org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler.CalciteCompiler;
 import org.dbsp.sqlCompiler.ir.type.*;
 import org.dbsp.sqlCompiler.ir.type.primitive.*;
 import org.dbsp.sqlCompiler.compiler.errors.UnimplementedException;
@@ -145,7 +146,6 @@ public class TypeCompiler implements ICompilerComponent {
                 case DISTINCT:
                 case STRUCTURED:
                 case ROW:
-                case OTHER:
                 case CURSOR:
                 case COLUMN_LIST:
                 case DYNAMIC_STAR:
@@ -169,7 +169,11 @@ public class TypeCompiler implements ICompilerComponent {
                 case INTERVAL_SECOND:
                     return new DBSPTypeMillisInterval(node, nullable);
                 case GEOMETRY:
-                    return new DBSPTypeGeoPoint(CalciteObject.EMPTY, nullable);
+                    return new DBSPTypeGeometry(node, nullable);
+                case OTHER:
+                    if (CalciteCompiler.isGeography(dt))
+                        return new DBSPTypeGeography(node, nullable);
+                    throw new UnimplementedException(node);
                 case TIMESTAMP:
                     return new DBSPTypeTimestamp(CalciteObject.EMPTY, nullable);
                 case DATE:
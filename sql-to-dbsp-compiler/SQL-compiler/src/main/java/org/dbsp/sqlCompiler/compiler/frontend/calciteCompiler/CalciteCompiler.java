@@ -57,6 +57,7 @@ import org.apache.calcite.sql.type.*;
 import org.apache.calcite.sql.util.SqlOperatorTables;
 import org.apache.calcite.sql.util.SqlShuttle;
 import org.apache.calcite.sql.validate.SqlConformanceEnum;
+import org.apache.calcite.sql.validate.SqlNameMatcher;
 import org.apache.calcite.sql.validate.SqlValidator;
 import org.apache.calcite.sql.validate.SqlValidatorUtil;
 import org.apache.calcite.sql2rel.RelDecorrelator;
@@ -117,6 +118,10 @@ public class CalciteCompiler implements IWritesLogs {
      */
     private final ValidateTypes validateTypes;
     private final IErrorReporter errorReporter;
+    /**
+     * Aggregate functions declared with CREATE AGGREGATE.
+     */
+    private final UserAggregateTable userAggregates;
 
     /**
      * This class rewrites instances of the division operator in the SQL AST
@@ -208,6 +213,52 @@ public class CalciteCompiler implements IWritesLogs {
     }
 
 
+    /**
+     * TEXT_CONTAINS(text, query) is true if 'text' contains every token of 'query'.
+     * TEXT_PHRASE(text, phrase) is true if the tokens of 'phrase' occur in 'text'
+     * consecutively.  Tokens are case-insensitive alphanumeric words. */
+    static class TextMatchFunction extends SqlFunction {
+        public TextMatchFunction(String name) {
+            super(name,
+                    SqlKind.OTHER_FUNCTION,
+                    ReturnTypes.BOOLEAN_NULLABLE,
+                    null,
+                    OperandTypes.STRING_STRING,
+                    SqlFunctionCategory.STRING);
+        }
+    }
+
+    /**
+     * ZSCORE(value, mean, stddev) is the number of standard deviations by which
+     * 'value' deviates from 'mean'; NULL if 'stddev' is zero.
+     * IS_OUTLIER(value, mean, stddev, k) is true if 'value' lies more than 'k'
+     * standard deviations away from 'mean'.  Both are typically applied to the
+     * results of AVG and STDDEV window aggregates. */
+    static class AnomalyFunction extends SqlFunction {
+        public AnomalyFunction(String name, SqlReturnTypeInference returnType, SqlOperandTypeChecker operandTypes) {
+            super(name,
+                    SqlKind.OTHER_FUNCTION,
+                    returnType.andThen(SqlTypeTransforms.FORCE_NULLABLE),
+                    null,
+                    operandTypes,
+                    SqlFunctionCategory.NUMERIC);
+        }
+    }
+
+    /**
+     * REGEXP_MATCH(string, pattern) is true if 'pattern' matches any part of 'string'.
+     * Same as 'string RLIKE pattern'. */
+    static class RegexpMatchFunction extends SqlFunction {
+        public RegexpMatchFunction() {
+            super("REGEXP_MATCH",
+                    SqlKind.OTHER_FUNCTION,
+                    ReturnTypes.BOOLEAN_NULLABLE,
+                    null,
+                    OperandTypes.STRING_STRING,
+                    SqlFunctionCategory.STRING);
+        }
+    }
+
     static class RlikeFunction extends SqlFunction {
         public RlikeFunction() {
             super("RLIKE",
@@ -225,6 +276,140 @@ public class CalciteCompiler implements IWritesLogs {
         }
     }
 
+    /**
+     * Calcite has no GEOGRAPHY type, so we represent it as a user-defined type.
+     * A GEOGRAPHY value is a geometry whose coordinates are longitude and
+     * latitude in degrees. */
+    public static RelDataType createGeographyType(boolean nullable) {
+        return new ObjectSqlType(SqlTypeName.OTHER,
+                new SqlIdentifier("GEOGRAPHY", SqlParserPos.ZERO),
+                nullable, null, RelDataTypeComparability.ALL);
+    }
+
+    public static boolean isGeography(RelDataType type) {
+        if (type.getSqlTypeName() != SqlTypeName.OTHER)
+            return false;
+        SqlIdentifier name = type.getSqlIdentifier();
+        return name != null && name.isSimple() && name.getSimple().equals("GEOGRAPHY");
+    }
+
+    /**
+     * Accepts operands that are all GEOMETRY values, or all GEOGRAPHY values. */
+    static class GeoOperandTypeChecker implements SqlOperandTypeChecker {
+        final int operandCount;
+
+        GeoOperandTypeChecker(int operandCount) {
+            this.operandCount = operandCount;
+        }
+
+        @Override
+        public boolean checkOperandTypes(SqlCallBinding callBinding, boolean throwOnFailure) {
+            boolean geography = false;
+            for (int i = 0; i < this.operandCount; i++) {
+                RelDataType type = callBinding.getOperandType(i);
+                boolean isGeography = isGeography(type);
+                boolean ok = isGeography || type.getSqlTypeName() == SqlTypeName.GEOMETRY;
+                if (i == 0)
+                    geography = isGeography;
+                else
+                    ok = ok && geography == isGeography;
+                if (!ok) {
+                    if (throwOnFailure)
+                        throw callBinding.newValidationSignatureError();
+                    return false;
+                }
+            }
+            return true;
+        }
+
+        @Override
+        public SqlOperandCountRange getOperandCountRange() {
+            return SqlOperandCountRanges.of(this.operandCount);
+        }
+
+        @Override
+        public String getAllowedSignatures(SqlOperator op, String opName) {
+            String geometry = String.join(", ", Collections.nCopies(this.operandCount, "GEOMETRY"));
+            String geography = String.join(", ", Collections.nCopies(this.operandCount, "GEOGRAPHY"));
+            return opName + "(" + geometry + ")\n" + opName + "(" + geography + ")";
+        }
+    }
+
+    /**
+     * Spatial functions implemented by the runtime library.  These take
+     * precedence over the Calcite spatial functions with the same names,
+     * which only handle GEOMETRY values.
+     * ST_GEOMFROMTEXT(wkt) and ST_GEOGFROMTEXT(wkt) parse a WKT string.
+     * ST_ASTEXT(g) returns the WKT representation of 'g'.
+     * ST_CONTAINS(a, b), ST_WITHIN(a, b), and ST_DISTANCE(a, b) compare two
+     * geometries or two geographies; distances between geographies are in meters.
+     * ST_GRID_CELLS(g, size) returns the ids of the cells of size 'size' of a
+     * regular grid that intersect the bounding box of 'g'. */
+    static class GeoFunction extends SqlFunction {
+        public GeoFunction(String name, SqlReturnTypeInference returnType, SqlOperandTypeChecker operandTypes) {
+            super(name,
+                    SqlKind.OTHER_FUNCTION,
+                    returnType.andThen(SqlTypeTransforms.TO_NULLABLE),
+                    null,
+                    operandTypes,
+                    SqlFunctionCategory.SYSTEM);
+        }
+
+        @Override
+        public boolean isDeterministic() {
+            // Calcite cannot constant-fold functions that it does not implement.
+            return false;
+        }
+    }
+
+    static SqlOperatorTable geoFunctions() {
+        SqlReturnTypeInference geography = opBinding -> createGeographyType(false);
+        SqlReturnTypeInference bigintArray = opBinding -> opBinding.getTypeFactory().createArrayType(
+                opBinding.getTypeFactory().createSqlType(SqlTypeName.BIGINT), -1);
+        return SqlOperatorTables.of(
+                new GeoFunction("ST_GEOMFROMTEXT", ReturnTypes.explicit(SqlTypeName.GEOMETRY), OperandTypes.STRING),
+                new GeoFunction("ST_GEOGFROMTEXT", geography, OperandTypes.STRING),
+                new GeoFunction("ST_ASTEXT", ReturnTypes.explicit(SqlTypeName.VARCHAR), new GeoOperandTypeChecker(1)),
+                new GeoFunction("ST_CONTAINS", ReturnTypes.BOOLEAN, new GeoOperandTypeChecker(2)),
+                new GeoFunction("ST_WITHIN", ReturnTypes.BOOLEAN, new GeoOperandTypeChecker(2)),
+                new GeoFunction("ST_DISTANCE", ReturnTypes.DOUBLE, new GeoOperandTypeChecker(2)),
+                new GeoFunction("ST_GRID_CELLS", bigintArray, family(SqlTypeFamily.GEO, SqlTypeFamily.NUMERIC)));
+    }
+
+    /**
+     * Operator table holding the user-defined aggregates.
+     * Unlike the other operator tables it grows during compilation,
+     * as CREATE AGGREGATE statements are processed.
+     */
+    static class UserAggregateTable implements SqlOperatorTable {
+        final List<SqlOperator> aggregates = new ArrayList<>();
+
+        @Override
+        public void lookupOperatorOverloads(SqlIdentifier opName, @Nullable SqlFunctionCategory category,
+                                            SqlSyntax syntax, List<SqlOperator> operatorList,
+                                            SqlNameMatcher nameMatcher) {
+            if (!opName.isSimple() || syntax != SqlSyntax.FUNCTION)
+                return;
+            for (SqlOperator op: this.aggregates) {
+                if (nameMatcher.matches(op.getName(), opName.getSimple()))
+                    operatorList.add(op);
+            }
+        }
+
+        @Override
+        public List<SqlOperator> getOperatorList() {
+            return this.aggregates;
+        }
+
+        @Nullable
+        SqlOperator get(String name) {
+            for (SqlOperator op: this.aggregates)
+                if (op.getName().equalsIgnoreCase(name))
+                    return op;
+            return null;
+        }
+    }
+
     public static final RelDataTypeSystem TYPE_SYSTEM = new RelDataTypeSystemImpl() {
         @Override
         public int getMaxNumericPrecision() {
@@ -319,10 +504,14 @@ public class CalciteCompiler implements IWritesLogs {
         rootSchema.add("NUMBER", factory -> factory.createSqlType(SqlTypeName.DECIMAL));
         rootSchema.add("TEXT", factory -> factory.createSqlType(SqlTypeName.VARCHAR));
         rootSchema.add("BOOL", factory -> factory.createSqlType(SqlTypeName.BOOLEAN));
+        rootSchema.add("GEOGRAPHY", factory -> createGeographyType(false));
         Prepare.CatalogReader catalogReader = new CalciteCatalogReader(
                 rootSchema, Collections.singletonList(catalog.schemaName), this.typeFactory, connectionConfig);
 
+        this.userAggregates = new UserAggregateTable();
         SqlOperatorTable operatorTable = SqlOperatorTables.chain(
+                // Must come before the SPATIAL library, whose functions it overrides.
+                geoFunctions(),
                 // Libraries of user-defined functions supported.
                 SqlLibraryOperatorTableFactory.INSTANCE.getOperatorTable(
                         // Standard SQL functions
@@ -332,7 +521,15 @@ public class CalciteCompiler implements IWritesLogs {
                                 SqlLibrary.BIG_QUERY,
                                 SqlLibrary.SPARK,
                                 SqlLibrary.SPATIAL)),
-                SqlOperatorTables.of(new SqlDivideFunction(), new RlikeFunction(), new WriteLogFunction())
+                SqlOperatorTables.of(new SqlDivideFunction(), new RlikeFunction(), new WriteLogFunction(),
+                        new RegexpMatchFunction(),
+                        new TextMatchFunction("TEXT_CONTAINS"), new TextMatchFunction("TEXT_PHRASE"),
+                        new AnomalyFunction("ZSCORE", ReturnTypes.DOUBLE,
+                                family(SqlTypeFamily.NUMERIC, SqlTypeFamily.NUMERIC, SqlTypeFamily.NUMERIC)),
+                        new AnomalyFunction("IS_OUTLIER", ReturnTypes.BOOLEAN,
+                                family(SqlTypeFamily.NUMERIC, SqlTypeFamily.NUMERIC,
+                                        SqlTypeFamily.NUMERIC, SqlTypeFamily.NUMERIC))),
+                this.userAggregates
         );
 
         SqlValidator.Config validatorConfig = SqlValidator.Config.DEFAULT
@@ -812,6 +1009,22 @@ public class CalciteCompiler implements IWritesLogs {
                 outputs.add(new OutputViewDescription(view));
                 return view;
             }
+
+            if (node instanceof SqlCreateAggregate) {
+                SqlCreateAggregate ca = (SqlCreateAggregate) node;
+                if (ca.getReplace())
+                    throw new UnsupportedException("OR REPLACE not supported", object);
+                String name = ca.name.getSimple();
+                if (this.userAggregates.get(name) != null)
+                    throw new CompilationError("Aggregate " + Utilities.singleQuote(name) +
+                            " already defined", object);
+                RelDataType argumentType = this.convertType(ca.argumentType);
+                RelDataType resultType = this.convertType(ca.resultType);
+                RelDataType nullableResultType = this.typeFactory.createTypeWithNullability(resultType, true);
+                this.userAggregates.aggregates.add(
+                        new UserAggregateFunction(name, argumentType, resultType, nullableResultType));
+                return new CreateAggregateStatement(node, sqlStatement, name, argumentType, resultType, comment);
+            }
         }
 
         if (SqlKind.DML.contains(node.getKind())) {
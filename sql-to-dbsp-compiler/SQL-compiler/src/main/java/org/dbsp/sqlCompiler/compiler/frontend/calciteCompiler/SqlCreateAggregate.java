@@ -0,0 +1,56 @@
+package org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler;
+
+import com.google.common.collect.ImmutableList;
+import org.apache.calcite.sql.SqlCreate;
+import org.apache.calcite.sql.SqlDataTypeSpec;
+import org.apache.calcite.sql.SqlIdentifier;
+import org.apache.calcite.sql.SqlKind;
+import org.apache.calcite.sql.SqlNode;
+import org.apache.calcite.sql.SqlOperator;
+import org.apache.calcite.sql.SqlSpecialOperator;
+import org.apache.calcite.sql.SqlWriter;
+import org.apache.calcite.sql.parser.SqlParserPos;
+
+import java.util.List;
+
+/**
+ * Parse tree for a CREATE AGGREGATE statement:
+ * CREATE AGGREGATE name(argumentType) RETURNS resultType.
+ * The statement declares the signature of a user-defined aggregate
+ * whose implementation is supplied by the runtime.
+ */
+public class SqlCreateAggregate extends SqlCreate {
+    private static final SqlOperator OPERATOR =
+            new SqlSpecialOperator("CREATE AGGREGATE", SqlKind.OTHER_DDL);
+
+    public final SqlIdentifier name;
+    public final SqlDataTypeSpec argumentType;
+    public final SqlDataTypeSpec resultType;
+
+    public SqlCreateAggregate(SqlParserPos pos, boolean replace, SqlIdentifier name,
+                              SqlDataTypeSpec argumentType, SqlDataTypeSpec resultType) {
+        super(OPERATOR, pos, replace, false);
+        this.name = name;
+        this.argumentType = argumentType;
+        this.resultType = resultType;
+    }
+
+    @Override public List<SqlNode> getOperandList() {
+        return ImmutableList.of(this.name, this.argumentType, this.resultType);
+    }
+
+    @Override public void unparse(SqlWriter writer, int leftPrec, int rightPrec) {
+        writer.keyword(this.getReplace() ? "CREATE OR REPLACE" : "CREATE");
+        writer.keyword("AGGREGATE");
+        this.name.unparse(writer, 0, 0);
+        SqlWriter.Frame frame = writer.startList("(", ")");
+        this.argumentType.unparse(writer, 0, 0);
+        if (Boolean.FALSE.equals(this.argumentType.getNullable()))
+            writer.keyword("NOT NULL");
+        writer.endList(frame);
+        writer.keyword("RETURNS");
+        this.resultType.unparse(writer, 0, 0);
+        if (Boolean.FALSE.equals(this.resultType.getNullable()))
+            writer.keyword("NOT NULL");
+    }
+}
@@ -0,0 +1,46 @@
+package org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler;
+
+import org.apache.calcite.rel.type.RelDataType;
+import org.apache.calcite.sql.SqlAggFunction;
+import org.apache.calcite.sql.SqlFunctionCategory;
+import org.apache.calcite.sql.SqlKind;
+import org.apache.calcite.sql.type.OperandTypes;
+import org.apache.calcite.sql.type.ReturnTypes;
+import org.apache.calcite.sql.type.SqlTypeFamily;
+import org.apache.calcite.util.Optionality;
+
+import java.util.Objects;
+
+/**
+ * An aggregate function declared with CREATE AGGREGATE.
+ * The implementation is supplied by the runtime, under the same name,
+ * using the user_aggregates! macro from sqllib.
+ * Aggregates of empty collections are always NULL, so the result is nullable.
+ */
+public class UserAggregateFunction extends SqlAggFunction {
+    public final RelDataType argumentType;
+    public final RelDataType resultType;
+
+    public UserAggregateFunction(String name, RelDataType argumentType, RelDataType resultType,
+                                 RelDataType nullableResultType) {
+        super(name,
+                null,
+                SqlKind.OTHER_FUNCTION,
+                ReturnTypes.explicit(nullableResultType),
+                null,
+                OperandTypes.family(familyOf(argumentType)),
+                SqlFunctionCategory.USER_DEFINED_FUNCTION,
+                false,
+                false,
+                Optionality.FORBIDDEN);
+        this.argumentType = argumentType;
+        this.resultType = resultType;
+    }
+
+    static SqlTypeFamily familyOf(RelDataType type) {
+        if (CalciteCompiler.isGeography(type))
+            // GEOGRAPHY is not part of any Calcite type family
+            return SqlTypeFamily.ANY;
+        return Objects.requireNonNull(type.getSqlTypeName().getFamily());
+    }
+}
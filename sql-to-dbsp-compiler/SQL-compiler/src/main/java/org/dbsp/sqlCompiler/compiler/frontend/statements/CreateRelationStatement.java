@@ -38,6 +38,7 @@ import org.apache.calcite.schema.impl.AbstractTable;
 import org.apache.calcite.sql.SqlIdentifier;
 import org.apache.calcite.sql.SqlNode;
 import org.apache.calcite.util.JsonBuilder;
+import org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler.CalciteCompiler;
 import org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler.RelColumnMetadata;
 import org.dbsp.sqlCompiler.compiler.errors.UnsupportedException;
 import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
@@ -138,6 +139,9 @@ public abstract class CreateRelationStatement extends FrontEndStatement {
                 // Is there a better way to do this?
                 String json = mapper.writeValueAsString(object);
                 JsonNode repr = mapper.readTree(json);
+                if (CalciteCompiler.isGeography(col.getType()))
+                    // Calcite describes user-defined types as OTHER
+                    ((ObjectNode) repr).put("type", "GEOGRAPHY");
                 column.set("columntype", repr);
             } catch (JsonProcessingException e) {
                 throw new RuntimeException(e);
@@ -174,6 +174,14 @@ public abstract class InnerVisitor implements IRTransform, IWritesLogs {
         return this.preorder((DBSPTypeGeo) node);
     }
 
+    public VisitDecision preorder(DBSPTypeGeometry node) {
+        return this.preorder((DBSPTypeGeo) node);
+    }
+
+    public VisitDecision preorder(DBSPTypeGeography node) {
+        return this.preorder((DBSPTypeGeo) node);
+    }
+
     public VisitDecision preorder(DBSPFunction node) {
         return this.preorder((IDBSPInnerNode) node);
     }
@@ -626,6 +634,14 @@ public abstract class InnerVisitor implements IRTransform, IWritesLogs {
         this.postorder((DBSPTypeGeo) node);
     }
 
+    public void postorder(DBSPTypeGeometry node) {
+        this.postorder((DBSPTypeGeo) node);
+    }
+
+    public void postorder(DBSPTypeGeography node) {
+        this.postorder((DBSPTypeGeo) node);
+    }
+
     public void postorder(DBSPFunction node) {
         this.postorder((IDBSPInnerNode) node);
     }
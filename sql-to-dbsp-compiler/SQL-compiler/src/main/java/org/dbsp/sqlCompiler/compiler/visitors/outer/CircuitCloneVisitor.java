@@ -145,6 +145,9 @@ public class CircuitCloneVisitor extends CircuitVisitor implements IWritesLogs {
     @Override
     public void postorder(DBSPWindowAggregateOperator operator) { this.replace(operator); }
 
+    @Override
+    public void postorder(DBSPMatchRecognizeOperator operator) { this.replace(operator); }
+
     @Override
     public void postorder(DBSPNoopOperator operator) { this.replace(operator); }
 
@@ -234,6 +237,11 @@ public class CircuitCloneVisitor extends CircuitVisitor implements IWritesLogs {
         this.replace(operator);
     }
 
+    @Override
+    public void postorder(DBSPIncrementalTextSearchOperator operator) {
+        this.replace(operator);
+    }
+
     @Override
     public void postorder(DBSPIncrementalDistinctOperator operator) {
         this.replace(operator);
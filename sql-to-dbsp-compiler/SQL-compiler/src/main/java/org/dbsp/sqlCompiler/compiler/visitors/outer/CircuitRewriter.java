@@ -37,6 +37,7 @@ import org.dbsp.sqlCompiler.circuit.operator.DBSPIndexOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPJoinOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPMapIndexOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPMapOperator;
+import org.dbsp.sqlCompiler.circuit.operator.DBSPMatchRecognizeOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPOperator;
 import org.dbsp.sqlCompiler.compiler.IErrorReporter;
 import org.dbsp.sqlCompiler.compiler.visitors.VisitDecision;
@@ -80,6 +81,7 @@ public class CircuitRewriter extends CircuitCloneVisitor {
     // - DBSPDistinctOperator
     // - DBSPFilterOperator
     // - DBSPIncrementalDistinctOperator
+    // - DBSPIncrementalTextSearchOperator
     // - DBSPIntegralOperator
     // - DBSPNegateOperator
     // - DBSPNoopOperator
@@ -241,6 +243,27 @@ public class CircuitRewriter extends CircuitCloneVisitor {
         this.map(operator, result);
     }
 
+    @Override
+    public void postorder(DBSPMatchRecognizeOperator operator) {
+        DBSPType keyType = this.transform(operator.keyType);
+        DBSPType measuresType = this.transform(operator.measuresType);
+        DBSPType weightType = this.transform(operator.weightType);
+        DBSPExpression pattern = this.transform(operator.pattern);
+        DBSPExpression function = this.transform(operator.getFunction());
+        DBSPOperator input = this.mapped(operator.input());
+        DBSPOperator result = operator;
+        if (!keyType.sameType(operator.keyType)
+                || !measuresType.sameType(operator.measuresType)
+                || !weightType.sameType(operator.weightType)
+                || pattern != operator.pattern
+                || function != operator.getFunction()
+                || input != operator.input()) {
+            result = new DBSPMatchRecognizeOperator(operator.getNode(), pattern, function,
+                    keyType, measuresType, weightType, input);
+        }
+        this.map(operator, result);
+    }
+
     @Override
     public void postorder(DBSPJoinOperator operator) {
         DBSPType elementResultType = this.transform(operator.elementResultType);
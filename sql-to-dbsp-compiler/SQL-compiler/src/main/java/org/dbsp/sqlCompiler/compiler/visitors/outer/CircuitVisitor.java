@@ -193,6 +193,14 @@ public abstract class CircuitVisitor
         return this.preorder((DBSPOperator) node);
     }
 
+    public VisitDecision preorder(DBSPIncrementalTextSearchOperator node) {
+        return this.preorder((DBSPOperator) node);
+    }
+
+    public VisitDecision preorder(DBSPMatchRecognizeOperator node) {
+        return this.preorder((DBSPUnaryOperator) node);
+    }
+
     ////////////////////////////////////
 
     @SuppressWarnings("EmptyMethod")
@@ -229,6 +237,14 @@ public abstract class CircuitVisitor
         this.postorder((DBSPOperator) node);
     }
 
+    public void postorder(DBSPIncrementalTextSearchOperator node) {
+        this.postorder((DBSPOperator) node);
+    }
+
+    public void postorder(DBSPMatchRecognizeOperator node) {
+        this.postorder((DBSPUnaryOperator) node);
+    }
+
     public void postorder(DBSPAggregateOperatorBase node) {
         this.postorder((DBSPUnaryOperator) node);
     }
@@ -25,6 +25,10 @@ package org.dbsp.sqlCompiler.compiler.visitors.outer;
 
 import org.dbsp.sqlCompiler.circuit.operator.*;
 import org.dbsp.sqlCompiler.compiler.IErrorReporter;
+import org.dbsp.sqlCompiler.ir.expression.DBSPClosureExpression;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPStringLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPZSetLiteral;
+import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeWeight;
 import org.dbsp.util.Linq;
 import org.dbsp.sqlCompiler.compiler.errors.UnimplementedException;
 
@@ -67,9 +71,41 @@ public class OptimizeIncrementalVisitor extends CircuitCloneVisitor {
 
     @Override
     public void postorder(DBSPFilterOperator operator) {
+        DBSPOperator source = this.mapped(operator.input());
+        if (source.is(DBSPIntegralOperator.class)) {
+            TextFilterAnalyzer.TextFilter textFilter = TextFilterAnalyzer.analyze(
+                    operator.getFunction().to(DBSPClosureExpression.class));
+            if (textFilter != null) {
+                this.textSearch(operator, source.inputs.get(0), textFilter);
+                return;
+            }
+        }
         this.linear(operator);
     }
 
+    /**
+     * Replace a filter whose condition contains a text match with a text
+     * search, which maintains an inverted index of its input instead of
+     * evaluating the text match on every row.
+     */
+    void textSearch(DBSPFilterOperator operator, DBSPOperator input, TextFilterAnalyzer.TextFilter textFilter) {
+        DBSPZSetLiteral queries = new DBSPZSetLiteral(
+                new DBSPTypeWeight(), new DBSPStringLiteral(textFilter.query));
+        DBSPConstantOperator constant = new DBSPConstantOperator(operator.getNode(), queries, false);
+        this.addOperator(constant);
+        DBSPDifferentialOperator diff = new DBSPDifferentialOperator(operator.getNode(), constant);
+        this.addOperator(diff);
+        DBSPOperator result = new DBSPIncrementalTextSearchOperator(
+                operator.getNode(), textFilter.textFunction, input, diff);
+        if (textFilter.residual != null) {
+            this.addOperator(result);
+            result = new DBSPFilterOperator(operator.getNode(), textFilter.residual, result);
+        }
+        this.addOperator(result);
+        DBSPIntegralOperator integral = new DBSPIntegralOperator(operator.getNode(), result);
+        this.map(operator, integral);
+    }
+
     @Override
     public void postorder(DBSPNegateOperator operator) {
         this.linear(operator);
@@ -87,6 +123,9 @@ public class OptimizeIncrementalVisitor extends CircuitCloneVisitor {
     @Override
     public void postorder(DBSPWindowAggregateOperator operator) { this.linear(operator); }
 
+    @Override
+    public void postorder(DBSPMatchRecognizeOperator operator) { this.linear(operator); }
+
     @Override
     public void postorder(DBSPMapIndexOperator operator) {
         this.linear(operator);
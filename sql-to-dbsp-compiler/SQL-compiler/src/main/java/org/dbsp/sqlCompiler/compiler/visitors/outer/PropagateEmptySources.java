@@ -8,11 +8,13 @@ import org.dbsp.sqlCompiler.circuit.operator.DBSPFilterOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPIncrementalAggregateOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPIncrementalDistinctOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPIncrementalJoinOperator;
+import org.dbsp.sqlCompiler.circuit.operator.DBSPIncrementalTextSearchOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPIndexOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPIntegralOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPJoinOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPMapIndexOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPMapOperator;
+import org.dbsp.sqlCompiler.circuit.operator.DBSPMatchRecognizeOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPNegateOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPNoopOperator;
 import org.dbsp.sqlCompiler.circuit.operator.DBSPOperator;
@@ -103,6 +105,12 @@ public class PropagateEmptySources extends CircuitCloneVisitor {
             super.postorder(operator);
     }
 
+    @Override
+    public void postorder(DBSPMatchRecognizeOperator operator) {
+        if (this.replaceUnary(operator))
+            super.postorder(operator);
+    }
+
     @Override
     public void postorder(DBSPIncrementalDistinctOperator operator) {
         if (this.replaceUnary(operator))
@@ -220,4 +228,18 @@ public class PropagateEmptySources extends CircuitCloneVisitor {
         }
         super.postorder(operator);
     }
+    @Override
+    public void postorder(DBSPIncrementalTextSearchOperator operator) {
+        for (DBSPOperator prev: operator.inputs) {
+            DBSPOperator source = this.mapped(prev);
+            if (this.emptySources.contains(source)) {
+                DBSPLiteral value = this.emptyLiteral(operator.getType());
+                DBSPConstantOperator result = new DBSPConstantOperator(operator.getNode(), value, operator.isMultiset);
+                this.emptySources.add(result);
+                this.map(operator, result);
+                return;
+            }
+        }
+        super.postorder(operator);
+    }
 }
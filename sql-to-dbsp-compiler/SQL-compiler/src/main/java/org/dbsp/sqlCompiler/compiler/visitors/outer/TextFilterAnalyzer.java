@@ -0,0 +1,185 @@
+/*
+ * Copyright 2022 VMware, Inc.
+ * SPDX-License-Identifier: MIT
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+package org.dbsp.sqlCompiler.compiler.visitors.outer;
+
+import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
+import org.dbsp.sqlCompiler.compiler.frontend.ExpressionCompiler;
+import org.dbsp.sqlCompiler.ir.expression.*;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPStrLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPStringLiteral;
+import org.dbsp.sqlCompiler.ir.type.DBSPType;
+import org.dbsp.sqlCompiler.ir.type.DBSPTypeAny;
+import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeBool;
+import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeStr;
+
+import javax.annotation.Nullable;
+import java.util.ArrayList;
+import java.util.List;
+
+/**
+ * Decomposes the condition of a filter into a text search and a residual
+ * condition.  A filter can use a text index if one of the conjuncts of
+ * its condition is a TEXT_CONTAINS or TEXT_PHRASE call whose first argument
+ * is a column of the filtered row and whose second argument is a string
+ * literal.  The index finds the rows that contain every token of the
+ * literal.  TEXT_PHRASE also requires the tokens to be contiguous, so it
+ * is kept in the residual condition and checked on the rows found.
+ */
+public class TextFilterAnalyzer {
+    /**
+     * Result of the decomposition.
+     */
+    public static class TextFilter {
+        /** Closure that returns the text of a row as a string slice. */
+        public final DBSPClosureExpression textFunction;
+        /** The query that the text must match. */
+        public final String query;
+        /** Condition that must also hold for the rows found; null if none. */
+        @Nullable
+        public final DBSPClosureExpression residual;
+
+        TextFilter(DBSPClosureExpression textFunction, String query,
+                   @Nullable DBSPClosureExpression residual) {
+            this.textFunction = textFunction;
+            this.query = query;
+            this.residual = residual;
+        }
+    }
+
+    /**
+     * Analyze the condition of a filter.
+     * @param condition Closure with a single parameter, the row.
+     * @return Null if no conjunct of the condition can use a text index.
+     */
+    @Nullable
+    public static TextFilter analyze(DBSPClosureExpression condition) {
+        if (condition.parameters.length != 1)
+            return null;
+        List<DBSPExpression> conjuncts = new ArrayList<>();
+        conjuncts(condition.body, conjuncts);
+        for (int i = 0; i < conjuncts.size(); i++) {
+            DBSPApplyExpression call = textMatch(conjuncts.get(i));
+            if (call == null)
+                continue;
+            DBSPExpression text = asField(call.arguments[0]);
+            DBSPStringLiteral query = asStringLiteral(call.arguments[1]);
+            if (text == null || query == null || query.isNull || query.value == null)
+                continue;
+
+            List<DBSPExpression> residual = new ArrayList<>(conjuncts);
+            if (!functionName(call).startsWith("text_phrase"))
+                residual.remove(i);
+            DBSPClosureExpression textFunction = asStr(text).closure(condition.parameters);
+            return new TextFilter(textFunction, query.value, conjunction(condition, residual));
+        }
+        return null;
+    }
+
+    /**
+     * Split a boolean expression into a list of conjuncts; each conjunct
+     * must be true for the expression to be true.
+     */
+    static void conjuncts(DBSPExpression expression, List<DBSPExpression> result) {
+        DBSPUnaryExpression unary = expression.as(DBSPUnaryExpression.class);
+        if (unary != null && unary.operation == DBSPOpcode.WRAP_BOOL) {
+            conjuncts(unary.source, result);
+            return;
+        }
+        DBSPBinaryExpression binary = expression.as(DBSPBinaryExpression.class);
+        if (binary != null && binary.operation == DBSPOpcode.AND) {
+            conjuncts(binary.left, result);
+            conjuncts(binary.right, result);
+            return;
+        }
+        result.add(expression);
+    }
+
+    @Nullable
+    static DBSPClosureExpression conjunction(DBSPClosureExpression condition, List<DBSPExpression> conjuncts) {
+        @Nullable
+        DBSPExpression result = null;
+        for (DBSPExpression conjunct: conjuncts) {
+            DBSPExpression expr = ExpressionCompiler.wrapBoolIfNeeded(conjunct);
+            if (result == null)
+                result = expr;
+            else
+                result = new DBSPBinaryExpression(condition.getNode(),
+                        new DBSPTypeBool(CalciteObject.EMPTY, false), DBSPOpcode.AND, result, expr);
+        }
+        if (result == null)
+            return null;
+        return result.closure(condition.parameters);
+    }
+
+    static String functionName(DBSPApplyExpression call) {
+        return call.function.toString();
+    }
+
+    @Nullable
+    static DBSPApplyExpression textMatch(DBSPExpression expression) {
+        DBSPApplyExpression call = expression.as(DBSPApplyExpression.class);
+        if (call == null || call.arguments.length != 2)
+            return null;
+        String name = functionName(call);
+        if (name.startsWith("text_contains") || name.startsWith("text_phrase"))
+            return call;
+        return null;
+    }
+
+    /**
+     * If 'expression' is a (possibly cloned) field of the row return the field.
+     */
+    @Nullable
+    static DBSPExpression asField(DBSPExpression expression) {
+        DBSPCloneExpression clone = expression.as(DBSPCloneExpression.class);
+        if (clone != null)
+            expression = clone.expression;
+        DBSPFieldExpression field = expression.as(DBSPFieldExpression.class);
+        if (field == null || !field.expression.is(DBSPVariablePath.class))
+            return null;
+        return field;
+    }
+
+    @Nullable
+    static DBSPStringLiteral asStringLiteral(DBSPExpression expression) {
+        DBSPCastExpression cast = expression.as(DBSPCastExpression.class);
+        if (cast != null)
+            expression = cast.source;
+        return expression.as(DBSPStringLiteral.class);
+    }
+
+    /**
+     * Borrow a string field as a string slice.  A NULL string is borrowed
+     * as the empty string, which matches no query.
+     */
+    static DBSPExpression asStr(DBSPExpression field) {
+        DBSPType str = new DBSPTypeStr(field.getNode(), false).ref();
+        if (!field.getType().mayBeNull)
+            return new DBSPApplyMethodExpression(field.getNode(), "as_str", str, field);
+        DBSPExpression option = new DBSPApplyMethodExpression(
+                field.getNode(), "as_deref", DBSPTypeAny.getDefault(), field);
+        return new DBSPApplyMethodExpression(
+                field.getNode(), "unwrap_or", str, option, new DBSPStrLiteral(""));
+    }
+}
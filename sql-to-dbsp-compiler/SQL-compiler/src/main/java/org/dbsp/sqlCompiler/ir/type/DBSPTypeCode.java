@@ -8,6 +8,8 @@ public enum DBSPTypeCode {
     DECIMAL("decimal", "Decimal", "Decimal"),
     DOUBLE("d", "F64", "F64"),
     FLOAT("f", "F32", "F32"),
+    GEOGRAPHY("geography", "Geography", ""),
+    GEOMETRY("geometry", "Geometry", ""),
     GEOPOINT("geopoint", "GeoPoint", ""),
     INT8("i8", "i8", ""),
     INT16("i16", "i16", "I16"),
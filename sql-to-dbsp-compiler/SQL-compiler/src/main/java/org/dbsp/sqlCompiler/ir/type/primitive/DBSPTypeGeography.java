@@ -0,0 +1,75 @@
+/*
+ * Copyright 2022 VMware, Inc.
+ * SPDX-License-Identifier: MIT
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+package org.dbsp.sqlCompiler.ir.type.primitive;
+
+import org.dbsp.sqlCompiler.compiler.errors.UnsupportedException;
+import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
+import org.dbsp.sqlCompiler.compiler.visitors.inner.InnerVisitor;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPLiteral;
+import org.dbsp.sqlCompiler.ir.type.DBSPType;
+import org.dbsp.sqlCompiler.ir.type.DBSPTypeCode;
+
+import java.util.Objects;
+
+/**
+ * A geometry on the surface of the earth, with coordinates
+ * given as longitude and latitude in degrees.
+ */
+public class DBSPTypeGeography extends DBSPTypeGeo {
+    public DBSPTypeGeography(CalciteObject node, boolean mayBeNull) {
+        super(node, DBSPTypeCode.GEOGRAPHY, mayBeNull);
+    }
+
+    @Override
+    public void accept(InnerVisitor visitor) {
+        if (visitor.preorder(this).stop()) return;
+        visitor.push(this);
+        visitor.pop(this);
+        visitor.postorder(this);
+    }
+
+    @Override
+    public DBSPType setMayBeNull(boolean mayBeNull) {
+        if (this.mayBeNull == mayBeNull)
+            return this;
+        return new DBSPTypeGeography(this.getNode(), mayBeNull);
+    }
+
+    @Override
+    public int hashCode() {
+        return Objects.hash(this.mayBeNull, 17);
+    }
+
+    @Override
+    public boolean sameType(DBSPType type) {
+        if (!super.sameNullability(type))
+            return false;
+        return type.is(DBSPTypeGeography.class);
+    }
+
+    @Override
+    public DBSPLiteral defaultValue() {
+        throw new UnsupportedException("Default value for type 'geography'", this.getNode());
+    }
+}
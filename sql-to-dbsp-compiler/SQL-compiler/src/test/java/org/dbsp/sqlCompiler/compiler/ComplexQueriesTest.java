@@ -24,11 +24,13 @@
 package org.dbsp.sqlCompiler.compiler;
 
 import org.dbsp.sqlCompiler.circuit.DBSPCircuit;
+import org.dbsp.sqlCompiler.circuit.operator.DBSPMatchRecognizeOperator;
 import org.dbsp.sqlCompiler.compiler.backend.jit.ToJitVisitor;
 import org.dbsp.sqlCompiler.compiler.backend.jit.ir.JITProgram;
 import org.dbsp.sqlCompiler.compiler.backend.rust.RustFileWriter;
 import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
 import org.dbsp.sqlCompiler.compiler.frontend.calciteCompiler.CalciteCompiler;
+import org.dbsp.sqlCompiler.compiler.visitors.outer.CircuitVisitor;
 import org.dbsp.sqlCompiler.ir.expression.DBSPTupleExpression;
 import org.dbsp.sqlCompiler.ir.expression.literal.*;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeDouble;
@@ -702,6 +704,79 @@ public class ComplexQueriesTest extends BaseSQLTests {
         this.addRustTestCase("ComplexQueriesTest.demographicsTest", compiler, getCircuit(compiler), ip);
     }
 
+    static final String TICKER_DDL = "CREATE TABLE ticker (\n" +
+            "    symbol VARCHAR NOT NULL,\n" +
+            "    ts BIGINT NOT NULL,\n" +
+            "    price INT NOT NULL\n" +
+            ")";
+    static final String TICKER_QUERY = "CREATE VIEW V AS SELECT * FROM ticker\n" +
+            "MATCH_RECOGNIZE (\n" +
+            "    PARTITION BY symbol\n" +
+            "    ORDER BY ts\n" +
+            "    MEASURES\n" +
+            "        STRT.price AS start_price,\n" +
+            "        LAST(DOWN.price) AS bottom_price,\n" +
+            "        LAST(UP.price) AS end_price\n" +
+            "    ONE ROW PER MATCH\n" +
+            "    AFTER MATCH SKIP PAST LAST ROW\n" +
+            "    PATTERN (STRT DOWN+ UP+)\n" +
+            "    DEFINE\n" +
+            "        DOWN AS DOWN.price < PREV(DOWN.price),\n" +
+            "        UP AS UP.price > PREV(UP.price)\n" +
+            ")";
+
+    static DBSPZSetLiteral.Contents tickerRows(int firstTs, int... prices) {
+        DBSPTupleExpression[] rows = new DBSPTupleExpression[prices.length];
+        for (int i = 0; i < prices.length; i++)
+            rows[i] = new DBSPTupleExpression(
+                    new DBSPStringLiteral("A"), new DBSPI64Literal(firstTs + i), new DBSPI32Literal(prices[i]));
+        return new DBSPZSetLiteral.Contents(rows);
+    }
+
+    static DBSPTupleExpression tickerMatch(int start, int bottom, int end) {
+        return new DBSPTupleExpression(new DBSPStringLiteral("A"),
+                new DBSPI32Literal(start), new DBSPI32Literal(bottom), new DBSPI32Literal(end));
+    }
+
+    @Test
+    public void matchRecognizeTest() {
+        DBSPCompiler compiler = testCompiler();
+        compiler.compileStatement(TICKER_DDL);
+        compiler.compileStatement(TICKER_QUERY);
+        Assert.assertFalse(compiler.hasErrors());
+        DBSPZSetLiteral.Contents input = tickerRows(0, 10, 8, 6, 9, 12, 11);
+        DBSPZSetLiteral.Contents output = new DBSPZSetLiteral.Contents(tickerMatch(10, 6, 12));
+        InputOutputPair ip = new InputOutputPair(input, output);
+        this.addRustTestCase("ComplexQueriesTest.matchRecognizeTest", compiler, getCircuit(compiler), ip);
+    }
+
+    @Test
+    public void matchRecognizeIncrementalTest() {
+        DBSPCompiler compiler = new DBSPCompiler(this.testOptions(true, true, false));
+        compiler.compileStatement(TICKER_DDL);
+        compiler.compileStatement(TICKER_QUERY);
+        Assert.assertFalse(compiler.hasErrors());
+        DBSPCircuit circuit = getCircuit(compiler);
+        int[] matchOperators = new int[1];
+        CircuitVisitor visitor = new CircuitVisitor(compiler) {
+            @Override
+            public void postorder(DBSPMatchRecognizeOperator operator) {
+                matchOperators[0]++;
+            }
+        };
+        visitor.apply(circuit);
+        Assert.assertEquals(1, matchOperators[0]);
+
+        // The match found in the first step is extended by the second one.
+        InputOutputPair first = new InputOutputPair(
+                tickerRows(0, 10, 8, 6, 9),
+                new DBSPZSetLiteral.Contents(tickerMatch(10, 6, 9)));
+        DBSPZSetLiteral.Contents changes = new DBSPZSetLiteral.Contents(tickerMatch(10, 6, 12));
+        changes.add(tickerMatch(10, 6, 9), -1);
+        InputOutputPair second = new InputOutputPair(tickerRows(4, 12, 11), changes);
+        this.addRustTestCase("ComplexQueriesTest.matchRecognizeIncrementalTest", compiler, circuit, first, second);
+    }
+
     @Test
     public void taxiTest() {
         String ddl = "CREATE TABLE green_tripdata\n" +
@@ -27,12 +27,15 @@ package org.dbsp.sqlCompiler.compiler;
 
 import org.dbsp.sqlCompiler.circuit.DBSPCircuit;
 import org.dbsp.sqlCompiler.compiler.frontend.CalciteObject;
+import org.dbsp.sqlCompiler.ir.expression.DBSPApplyExpression;
 import org.dbsp.sqlCompiler.ir.expression.DBSPExpression;
 import org.dbsp.sqlCompiler.ir.expression.DBSPTupleExpression;
 import org.dbsp.sqlCompiler.ir.expression.literal.*;
+import org.dbsp.sqlCompiler.ir.type.DBSPTypeTuple;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeBool;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeDecimal;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeDouble;
+import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeGeometry;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeInteger;
 import org.dbsp.sqlCompiler.ir.type.primitive.DBSPTypeString;
 import org.junit.Ignore;
@@ -498,8 +501,9 @@ public class EndToEndTests extends BaseSQLTests {
         String query = "SELECT ST_POINT(0, 0)";
         this.testQuery(query, new DBSPZSetLiteral.Contents(
                 new DBSPTupleExpression(
-                        new DBSPGeoPointLiteral(CalciteObject.EMPTY,
-                                new DBSPDoubleLiteral(0), new DBSPDoubleLiteral(0), false).some())));
+                        new DBSPApplyExpression("st_geomfromtext_s",
+                                new DBSPTypeGeometry(CalciteObject.EMPTY, false),
+                                new DBSPStringLiteral("POINT (0 0)")).some())));
     }
 
     @Test
@@ -509,6 +513,44 @@ public class EndToEndTests extends BaseSQLTests {
                 new DBSPTupleExpression(new DBSPDoubleLiteral(1).some())));
     }
 
+    @Test
+    public void zscoreTest() {
+        String query = "SELECT ZSCORE(T.COL2, 10, 2), ZSCORE(T.COL2, 10, 0) FROM T";
+        this.testQuery(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPDoubleLiteral(1.0, true), new DBSPDoubleLiteral(null, true)),
+                new DBSPTupleExpression(new DBSPDoubleLiteral(-4.5, true), new DBSPDoubleLiteral(null, true))));
+    }
+
+    @Test
+    public void isOutlierTest() {
+        String query = "SELECT IS_OUTLIER(T.COL2, 10, 2, 3), IS_OUTLIER(T.COL2, T.COL6, 1, 1) FROM T";
+        this.testQuery(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPBoolLiteral(false, true), new DBSPBoolLiteral(null, true)),
+                new DBSPTupleExpression(new DBSPBoolLiteral(true, true), new DBSPBoolLiteral(false, true))));
+    }
+
+    @Test
+    public void textContainsTest() {
+        String query = "SELECT T.COL1 FROM T WHERE TEXT_CONTAINS(T.COL4, 'HI')";
+        this.testQuery(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPI32Literal(10)),
+                new DBSPTupleExpression(new DBSPI32Literal(10))));
+    }
+
+    @Test
+    public void textContainsConjunctTest() {
+        String query = "SELECT T.COL2 FROM T WHERE T.COL3 AND TEXT_CONTAINS(T.COL4, 'hi')";
+        this.testQuery(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPDoubleLiteral(12.0))));
+    }
+
+    @Test
+    public void textPhraseTest() {
+        String query = "SELECT T.COL1 FROM T WHERE TEXT_PHRASE(T.COL4, 'hi there')";
+        this.testQuery(query, DBSPZSetLiteral.Contents.emptyWithElementType(
+                new DBSPTypeTuple(new DBSPTypeInteger(CalciteObject.EMPTY, INT32, 32, true, false))));
+    }
+
     @Test
     public void leftOuterJoinTest() {
         String query = "SELECT T1.COL3, T2.COL3 FROM T AS T1 LEFT JOIN T AS T2 ON T1.COL1 = T2.COL5";
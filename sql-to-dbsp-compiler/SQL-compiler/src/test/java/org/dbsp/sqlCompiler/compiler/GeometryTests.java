@@ -0,0 +1,82 @@
+package org.dbsp.sqlCompiler.compiler;
+
+import org.dbsp.sqlCompiler.circuit.DBSPCircuit;
+import org.dbsp.sqlCompiler.ir.expression.DBSPTupleExpression;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPBoolLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPDoubleLiteral;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPZSetLiteral;
+import org.junit.Assert;
+import org.junit.Test;
+
+public class GeometryTests extends BaseSQLTests {
+    public DBSPCompiler compileQuery(String statements, String query) {
+        DBSPCompiler compiler = this.testCompiler();
+        compiler.compileStatements(statements);
+        compiler.compileStatement(query);
+        return compiler;
+    }
+
+    void testQuery(String statements, String query, InputOutputPair... streams) {
+        query = "CREATE VIEW V AS " + query;
+        DBSPCompiler compiler = this.compileQuery(statements, query);
+        DBSPCircuit circuit = getCircuit(compiler);
+        this.addRustTestCase(query, compiler, circuit, streams);
+    }
+
+    void testConstant(String query, DBSPZSetLiteral.Contents result) {
+        this.testQuery("", query, new InputOutputPair(
+                new DBSPZSetLiteral.Contents[0], new DBSPZSetLiteral.Contents[] { result }));
+    }
+
+    @Test
+    public void testContains() {
+        String query = "SELECT " +
+                "ST_CONTAINS(ST_GEOMFROMTEXT('POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0))'), ST_GEOMFROMTEXT('POINT (1 1)')), " +
+                "ST_WITHIN(ST_GEOMFROMTEXT('POINT (1 1)'), ST_GEOMFROMTEXT('POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0))')), " +
+                "ST_CONTAINS(ST_GEOMFROMTEXT('POINT (1 1)'), ST_GEOMFROMTEXT('POINT (2 2)'))";
+        this.testConstant(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(
+                        new DBSPBoolLiteral(true),
+                        new DBSPBoolLiteral(true),
+                        new DBSPBoolLiteral(false))));
+    }
+
+    @Test
+    public void testDistance() {
+        String query = "SELECT ST_DISTANCE(ST_GEOMFROMTEXT('POINT (0 0)'), ST_GEOMFROMTEXT('POINT (3 4)'))";
+        this.testConstant(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPDoubleLiteral(5.0))));
+    }
+
+    @Test
+    public void testGeographyDistance() {
+        // One degree of longitude at the equator is about 111 km.
+        String query = "SELECT ST_DISTANCE(ST_GEOGFROMTEXT('POINT (0 0)'), ST_GEOGFROMTEXT('POINT (1 0)')) " +
+                "BETWEEN 111000 AND 112000";
+        this.testConstant(query, new DBSPZSetLiteral.Contents(
+                new DBSPTupleExpression(new DBSPBoolLiteral(true))));
+    }
+
+    @Test
+    public void testColumns() {
+        String ddl = "CREATE TABLE PLACES (\n" +
+                "ID INTEGER,\n" +
+                "LOCATION GEOMETRY NOT NULL,\n" +
+                "REGION GEOGRAPHY)";
+        String query = "SELECT ID, ST_ASTEXT(LOCATION), ST_ASTEXT(REGION), ST_GRID_CELLS(LOCATION, 10), " +
+                "ST_POINT(ID, ID), ST_DISTANCE(REGION, ST_GEOGFROMTEXT('POINT (0 0)')) FROM PLACES " +
+                "WHERE ST_CONTAINS(ST_GEOMFROMTEXT('POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0))'), LOCATION)";
+        this.testQuery(ddl, query);
+    }
+
+    @Test
+    public void testMixedTypes() {
+        String query = "CREATE VIEW V AS SELECT " +
+                "ST_DISTANCE(ST_GEOMFROMTEXT('POINT (0 0)'), ST_GEOGFROMTEXT('POINT (0 0)'))";
+        DBSPCompiler compiler = this.testCompiler();
+        compiler.options.optimizerOptions.throwOnError = false;
+        compiler.compileStatement(query);
+        Assert.assertTrue(compiler.messages.exitCode != 0);
+        Assert.assertTrue(compiler.messages.toString().contains("Cannot apply 'ST_DISTANCE' to arguments of type"));
+    }
+}
@@ -23,6 +23,12 @@
 
 package org.dbsp.sqlCompiler.compiler;
 
+import org.dbsp.sqlCompiler.circuit.DBSPCircuit;
+import org.dbsp.sqlCompiler.circuit.operator.DBSPIncrementalTextSearchOperator;
+import org.dbsp.sqlCompiler.compiler.visitors.outer.CircuitVisitor;
+import org.junit.Assert;
+import org.junit.Test;
+
 public class OptimizedIncrementalTests extends NaiveIncrementalTests {
     @Override
     public DBSPCompiler testCompiler() {
@@ -33,4 +39,21 @@ public class OptimizedIncrementalTests extends NaiveIncrementalTests {
     public void invokeTestQueryBase(String query, InputOutputPair... streams) {
         super.testQueryBase(query, streams);
     }
+
+    @Test
+    public void textSearchUsesIndexTest() {
+        String query = "CREATE VIEW V AS SELECT T.COL1 FROM T " +
+                "WHERE TEXT_CONTAINS(T.COL4, 'hi') AND T.COL3";
+        DBSPCompiler compiler = this.compileQuery(query);
+        DBSPCircuit circuit = getCircuit(compiler);
+        int[] searches = new int[1];
+        CircuitVisitor visitor = new CircuitVisitor(compiler) {
+            @Override
+            public void postorder(DBSPIncrementalTextSearchOperator operator) {
+                searches[0]++;
+            }
+        };
+        visitor.apply(circuit);
+        Assert.assertEquals(1, searches[0]);
+    }
 }
@@ -0,0 +1,66 @@
+package org.dbsp.sqlCompiler.compiler;
+
+import org.dbsp.sqlCompiler.circuit.DBSPCircuit;
+import org.dbsp.sqlCompiler.ir.expression.DBSPTupleExpression;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPI32Literal;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPI64Literal;
+import org.dbsp.sqlCompiler.ir.expression.literal.DBSPZSetLiteral;
+import org.junit.Assert;
+import org.junit.Test;
+
+/**
+ * Tests for aggregates declared with CREATE AGGREGATE.
+ * The implementations are in src/test/resources/udaf.rs.
+ */
+public class UserAggregateTests extends BaseSQLTests {
+    static final String ddl = "CREATE AGGREGATE SUM_SQUARES(INTEGER) RETURNS BIGINT;\n" +
+            "CREATE TABLE T (G INTEGER NOT NULL, X INTEGER)";
+
+    @Override
+    public DBSPCompiler testCompiler() {
+        DBSPCompiler compiler = super.testCompiler();
+        compiler.options.ioOptions.udafFile = "src/test/resources/udaf.rs";
+        return compiler;
+    }
+
+    void testQuery(String query, InputOutputPair... streams) {
+        query = "CREATE VIEW V AS " + query;
+        DBSPCompiler compiler = this.testCompiler();
+        compiler.compileStatements(ddl);
+        compiler.compileStatement(query);
+        DBSPCircuit circuit = getCircuit(compiler);
+        this.addRustTestCase(query, compiler, circuit, streams);
+    }
+
+    static final DBSPZSetLiteral.Contents input = new DBSPZSetLiteral.Contents(
+            new DBSPTupleExpression(new DBSPI32Literal(1), new DBSPI32Literal(2, true)),
+            new DBSPTupleExpression(new DBSPI32Literal(1), new DBSPI32Literal(3, true)),
+            new DBSPTupleExpression(new DBSPI32Literal(2), new DBSPI32Literal(null, true)));
+
+    @Test
+    public void testGroupBy() {
+        this.testQuery("SELECT G, SUM_SQUARES(X) FROM T GROUP BY G",
+                new InputOutputPair(input, new DBSPZSetLiteral.Contents(
+                        new DBSPTupleExpression(new DBSPI32Literal(1), new DBSPI64Literal(13L, true)),
+                        new DBSPTupleExpression(new DBSPI32Literal(2), new DBSPI64Literal((Long) null, true)))));
+    }
+
+    @Test
+    public void testWithOtherAggregates() {
+        this.testQuery("SELECT SUM_SQUARES(X), COUNT(*), SUM_SQUARES(G) FROM T",
+                new InputOutputPair(input, new DBSPZSetLiteral.Contents(
+                        new DBSPTupleExpression(
+                                new DBSPI64Literal(13L, true),
+                                new DBSPI64Literal(3L),
+                                new DBSPI64Literal(6L, true)))));
+    }
+
+    @Test
+    public void testArgumentType() {
+        DBSPCompiler compiler = this.noThrowCompiler();
+        compiler.compileStatements(ddl);
+        compiler.compileStatement("CREATE VIEW V AS SELECT SUM_SQUARES('a') FROM T");
+        Assert.assertTrue(compiler.messages.exitCode != 0);
+        Assert.assertTrue(compiler.messages.toString().contains("Cannot apply 'SUM_SQUARES' to arguments of type"));
+    }
+}
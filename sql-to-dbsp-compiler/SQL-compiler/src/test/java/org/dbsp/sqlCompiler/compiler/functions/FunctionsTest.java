@@ -52,4 +52,36 @@ public class FunctionsTest extends PostgresBaseTest {
                 "------\n" +
                 "5");
     }
+
+    @Test
+    public void testJsonValue() {
+        this.q("SELECT JSON_VALUE('{\"user\": {\"name\": \"alice\", \"id\": 7}}', '$.user.name');\n" +
+                "result\n" +
+                "---------\n" +
+                " alice");
+        this.q("SELECT JSON_VALUE('{\"tags\": [\"a\", \"b\"]}', 'lax $.tags[1]');\n" +
+                "result\n" +
+                "---------\n" +
+                " b");
+        this.q("SELECT JSON_VALUE('{\"user\": {\"name\": \"alice\"}}', '$.user');\n" +
+                "result\n" +
+                "---------\n" +
+                "NULL");
+        this.q("SELECT JSON_VALUE('not json', '$.user');\n" +
+                "result\n" +
+                "---------\n" +
+                "NULL");
+    }
+
+    @Test
+    public void testJsonQuery() {
+        this.q("SELECT JSON_QUERY('{\"user\": {\"name\": \"alice\"}}', '$.user');\n" +
+                "result\n" +
+                "---------\n" +
+                " {\"name\":\"alice\"}");
+        this.q("SELECT JSON_QUERY('{\"user\": {\"name\": \"alice\"}}', 'strict $.user.name');\n" +
+                "result\n" +
+                "---------\n" +
+                "NULL");
+    }
 }
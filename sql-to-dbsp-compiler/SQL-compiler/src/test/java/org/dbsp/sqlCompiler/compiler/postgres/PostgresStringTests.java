@@ -500,6 +500,23 @@ public class PostgresStringTests extends PostgresBaseTest {
                 " t");
     }
 
+    @Test
+    public void testRegexpFunctions() {
+        // These are not postgres functions
+        this.q("SELECT REGEXP_MATCH('GET /index.html 200', '[45][0-9]{2}$') AS \"false\";\n" +
+                " false \n" +
+                "-------\n" +
+                " f");
+        this.q("SELECT REGEXP_EXTRACT('GET /index.html 200', '([0-9]+)$') AS status;\n" +
+                " status \n" +
+                "--------\n" +
+                " 200");
+        this.q("SELECT REGEXP_REPLACE('user=alice id=42', '([a-z]+)=', '\\1: ') AS kv;\n" +
+                "       kv        \n" +
+                "-----------------\n" +
+                " user: alice id: 42");
+    }
+
     @Test
     public void testRlike2() {
         // This is not a postgres operator
@@ -0,0 +1,38 @@
+// User-defined aggregates used by UserAggregateTests.
+// This file is included in the generated code.
+
+/// Sum of the squares of the non-null values; NULL if there are none.
+pub struct SumSquares;
+
+impl UserAggregate for SumSquares {
+    type Input = Option<i32>;
+    // Sum of the squares and number of non-null values.
+    type Accumulator = (i64, i64);
+    type Output = Option<i64>;
+
+    fn zero() -> Self::Accumulator {
+        (0, 0)
+    }
+
+    fn increment(acc: Self::Accumulator, value: &Self::Input, weight: i64) -> Self::Accumulator {
+        match value {
+            None => acc,
+            Some(v) => {
+                let v = *v as i64;
+                (acc.0 + v * v * weight, acc.1 + weight)
+            }
+        }
+    }
+
+    fn combine(left: &Self::Accumulator, right: &Self::Accumulator) -> Self::Accumulator {
+        (left.0 + right.0, left.1 + right.1)
+    }
+
+    fn finish(acc: Self::Accumulator) -> Self::Output {
+        (acc.1 != 0).then_some(acc.0)
+    }
+}
+
+user_aggregates! {
+    SUM_SQUARES => SumSquares,
+}
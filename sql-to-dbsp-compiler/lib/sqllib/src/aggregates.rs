@@ -0,0 +1,125 @@
+// Runtime support for user-defined aggregate functions.
+//
+// A user-defined aggregate is declared in SQL with
+// `CREATE AGGREGATE HDR_P99(DOUBLE) RETURNS DOUBLE` and implemented by a
+// type implementing the `UserAggregate` trait.  Aggregates are made
+// visible to the generated code under their SQL name by the
+// `user_aggregates!` macro, which creates a `udaf` module containing one
+// type alias per aggregate.  The SQL compiler includes the file containing
+// the implementations and the macro invocation in the generated code
+// (option `-udaf`).  For an aggregate named `HDR_P99` the generated code
+// uses:
+// - `udaf_zero::<udaf::HDR_P99>()` as the initial accumulator value,
+// - `udaf_increment::<udaf::HDR_P99, _>(acc, value, weight)` to fold a row
+//   into the accumulator,
+// - `UserAggregateSemigroup<udaf::HDR_P99>` to combine partial aggregates,
+// - `udaf_finish::<udaf::HDR_P99>(acc)` to produce the final result.
+
+use dbsp::algebra::Semigroup;
+use num::ToPrimitive;
+use std::marker::PhantomData;
+
+/// An incremental aggregate function supplied by the user.
+///
+/// The accumulator must form a commutative semigroup under `combine`
+/// with `zero` as its identity: the engine aggregates groups piecewise and
+/// combines the partial results in arbitrary order.
+pub trait UserAggregate: 'static {
+    /// Type of the value being aggregated: the Rust type of the SQL
+    /// argument type, e.g., `Option<i32>` for a nullable `INTEGER`.
+    type Input;
+    /// Intermediate aggregation state.  Circuits store accumulators, so
+    /// they must also implement `dbsp::DBData`.
+    type Accumulator: Clone;
+    /// Type of the aggregate result: the Rust type of the SQL result type.
+    type Output;
+
+    /// Accumulator value for an empty group.
+    fn zero() -> Self::Accumulator;
+
+    /// Add `weight` copies of `value` to the accumulator.  The weight is
+    /// negative when values are removed from the group.
+    fn increment(acc: Self::Accumulator, value: &Self::Input, weight: i64) -> Self::Accumulator;
+
+    /// Combine two partial aggregates.
+    fn combine(left: &Self::Accumulator, right: &Self::Accumulator) -> Self::Accumulator;
+
+    /// Compute the result of the aggregate from the accumulator.
+    fn finish(acc: Self::Accumulator) -> Self::Output;
+}
+
+/// Accumulator type of the user-defined aggregate `A`.
+pub type UdafAccumulator<A> = <A as UserAggregate>::Accumulator;
+
+#[inline(always)]
+pub fn udaf_zero<A>() -> A::Accumulator
+where
+    A: UserAggregate,
+{
+    A::zero()
+}
+
+#[inline(always)]
+pub fn udaf_increment<A, W>(acc: A::Accumulator, value: &A::Input, weight: W) -> A::Accumulator
+where
+    A: UserAggregate,
+    W: ToPrimitive,
+{
+    let weight = weight
+        .to_i64()
+        .expect("weight does not fit in a 64-bit integer");
+    A::increment(acc, value, weight)
+}
+
+#[inline(always)]
+pub fn udaf_finish<A>(acc: A::Accumulator) -> A::Output
+where
+    A: UserAggregate,
+{
+    A::finish(acc)
+}
+
+/// Semigroup combining the accumulators of the user-defined aggregate `A`.
+pub struct UserAggregateSemigroup<A>(PhantomData<A>);
+
+// Implemented by hand, since `A` itself need not be `Clone`.
+impl<A> Clone for UserAggregateSemigroup<A> {
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A> Semigroup<A::Accumulator> for UserAggregateSemigroup<A>
+where
+    A: UserAggregate,
+{
+    fn combine(left: &A::Accumulator, right: &A::Accumulator) -> A::Accumulator {
+        A::combine(left, right)
+    }
+}
+
+// Register user-defined aggregates under their SQL names.
+// user_aggregates! {
+//     HDR_P99 => my_crate::HdrP99,
+//     ST_UNION_AGG => my_crate::GeoUnion,
+// }
+// creates a module `udaf` containing a type alias for each aggregate and
+// the list `udaf::NAMES` of all registered names.
+#[macro_export]
+macro_rules! user_aggregates {
+    ($($name:ident => $implementation:ty),* $(,)?) => {
+        #[allow(non_camel_case_types)]
+        pub mod udaf {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $(pub type $name = $implementation;)*
+
+            pub const NAMES: &[&str] = &[$(stringify!($name)),*];
+
+            pub fn is_registered(name: &str) -> bool {
+                NAMES.iter().any(|n| n.eq_ignore_ascii_case(name))
+            }
+        }
+    };
+}
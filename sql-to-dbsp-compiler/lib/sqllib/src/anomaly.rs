@@ -0,0 +1,25 @@
+//! Anomaly detection functions
+
+use dbsp::algebra::F64;
+
+/// Number of standard deviations by which `value` deviates from `mean`.
+/// NULL if any argument is NULL or `stddev` is zero.
+pub fn zscoreNNN(value: Option<F64>, mean: Option<F64>, stddev: Option<F64>) -> Option<F64> {
+    let stddev = stddev?;
+    if stddev == F64::new(0.0) {
+        return None;
+    }
+    Some((value? - mean?) / stddev)
+}
+
+/// True if `value` lies more than `k` standard deviations away from `mean`.
+/// NULL if any argument is NULL.
+pub fn is_outlierNNNN(
+    value: Option<F64>,
+    mean: Option<F64>,
+    stddev: Option<F64>,
+    k: Option<F64>,
+) -> Option<bool> {
+    let deviation = (value?.into_inner() - mean?.into_inner()).abs();
+    Some(deviation > k?.into_inner() * stddev?.into_inner().abs())
+}
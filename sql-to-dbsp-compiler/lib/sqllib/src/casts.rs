@@ -4,7 +4,7 @@
 
 use std::cmp::Ordering;
 
-use crate::{geopoint::*, interval::*, timestamp::*};
+use crate::{geometry::*, geopoint::*, interval::*, timestamp::*};
 use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike, NaiveTime};
 use dbsp::algebra::{HasOne, HasZero, F32, F64};
 use num::{FromPrimitive, One, ToPrimitive, Zero};
@@ -715,6 +715,20 @@ pub fn cast_to_geopointN_geopoint(value: GeoPoint) -> Option<GeoPoint> {
     Some(value)
 }
 
+/////////// cast to Geometry
+
+#[inline]
+pub fn cast_to_geometryN_geometry(value: Geometry) -> Option<Geometry> {
+    Some(value)
+}
+
+/////////// cast to Geography
+
+#[inline]
+pub fn cast_to_geographyN_geography(value: Geography) -> Option<Geography> {
+    Some(value)
+}
+
 /////////// cast to String
 
 // True if the size means "unlimited"
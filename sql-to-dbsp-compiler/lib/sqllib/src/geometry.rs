@@ -0,0 +1,803 @@
+// GEOMETRY and GEOGRAPHY values.
+//
+// Values are stored in their canonical (little-endian, 2D) WKB encoding,
+// which gives them the `Eq`, `Ord` and `Hash` implementations required
+// from values stored in collections.  Values are serialized as WKT, and
+// can be deserialized from WKT text, hex-encoded WKB, or raw WKB bytes.
+
+use crate::{some_polymorphic_function1, some_polymorphic_function2};
+use ::serde::de::{Error as _, SeqAccess, Visitor};
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use dbsp::algebra::F64;
+use geo::{
+    BoundingRect, Coord, EuclideanDistance, HaversineDistance, Intersects, LineString, LinesIter,
+    MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Relate,
+};
+use geo_types::GeometryCollection;
+use size_of::*;
+use std::fmt;
+use std::str::FromStr;
+use wkt::{ToWkt, TryFromWkt};
+
+// Largest number of grid cells returned by `st_grid_cells`.
+const MAX_GRID_CELLS: usize = 1 << 16;
+
+#[derive(
+    Eq,
+    Ord,
+    Clone,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    SizeOf,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct Geometry(Vec<u8>);
+
+// The default value is the empty geometry collection.
+impl Default for Geometry {
+    fn default() -> Self {
+        Self::from_geo(&GeometryCollection::<f64>(Vec::new()).into())
+    }
+}
+
+impl Geometry {
+    pub fn from_geo(geometry: &geo::Geometry<f64>) -> Self {
+        let mut wkb = Vec::new();
+        write_wkb(&mut wkb, geometry);
+        Self(wkb)
+    }
+
+    pub fn from_wkt(wkt: &str) -> Result<Self, String> {
+        let geometry = geo::Geometry::<f64>::try_from_wkt_str(wkt)
+            .map_err(|e| format!("invalid WKT '{wkt}': {e}"))?;
+        Ok(Self::from_geo(&geometry))
+    }
+
+    pub fn from_wkb(wkb: &[u8]) -> Result<Self, String> {
+        let mut reader = WkbReader { data: wkb, pos: 0 };
+        let geometry = reader.read_geometry()?;
+        if reader.pos != wkb.len() {
+            return Err(format!(
+                "invalid WKB: {} trailing bytes",
+                wkb.len() - reader.pos
+            ));
+        }
+        Ok(Self::from_geo(&geometry))
+    }
+
+    pub fn from_hex_wkb(hex: &str) -> Result<Self, String> {
+        if !hex.is_ascii() || hex.len() % 2 != 0 {
+            return Err("invalid hex-encoded WKB".to_string());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| format!("invalid hex-encoded WKB: {e}"))?;
+        Self::from_wkb(&bytes)
+    }
+
+    pub fn wkb(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_geo(&self) -> geo::Geometry<f64> {
+        // The encoding is always produced by `write_wkb`.
+        WkbReader {
+            data: &self.0,
+            pos: 0,
+        }
+        .read_geometry()
+        .unwrap()
+    }
+
+    pub fn to_wkt(&self) -> String {
+        self.to_geo().wkt_string()
+    }
+
+    // Minimum bounding rectangle, or `None` for empty geometries.
+    pub fn bounding_rect(&self) -> Option<geo::Rect<f64>> {
+        self.to_geo().bounding_rect()
+    }
+
+    pub fn contains(&self, other: &Geometry) -> bool {
+        let left = self.to_geo();
+        let right = other.to_geo();
+        // Cheap bounding box test before the exact predicate.
+        match (left.bounding_rect(), right.bounding_rect()) {
+            (Some(l), Some(r)) if rect_contains(&l, &r) => left.relate(&right).is_contains(),
+            _ => false,
+        }
+    }
+
+    pub fn within(&self, other: &Geometry) -> bool {
+        other.contains(self)
+    }
+
+    pub fn distance(&self, other: &Geometry) -> F64 {
+        let left = self.to_geo();
+        let right = other.to_geo();
+        if left.intersects(&right) {
+            return F64::new(0.0);
+        }
+        let left = segments(&left);
+        let right = segments(&right);
+        let distance = left
+            .iter()
+            .flat_map(|l| right.iter().map(move |r| l.euclidean_distance(r)))
+            .fold(f64::INFINITY, f64::min);
+        F64::new(distance)
+    }
+
+    // Cells of a square grid with the specified cell size that intersect
+    // the bounding box of the geometry.  Two geometries can only intersect
+    // if they share a cell, so joining on grid cells before applying an
+    // exact predicate such as `st_contains` avoids a cross product.
+    pub fn grid_cells(&self, cell_size: f64) -> Vec<i64> {
+        if cell_size.is_nan() || cell_size <= 0.0 {
+            panic!("grid cell size must be positive, got {cell_size}");
+        }
+        let Some(rect) = self.bounding_rect() else {
+            return Vec::new();
+        };
+        let cell = |v: f64| (v / cell_size).floor() as i64;
+        let (min_x, min_y) = (cell(rect.min().x), cell(rect.min().y));
+        let (max_x, max_y) = (cell(rect.max().x), cell(rect.max().y));
+        let count = ((max_x - min_x + 1) as u128) * ((max_y - min_y + 1) as u128);
+        if count > MAX_GRID_CELLS as u128 {
+            panic!("geometry covers {count} grid cells of size {cell_size}, more than the maximum of {MAX_GRID_CELLS}");
+        }
+        let mut result = Vec::with_capacity(count as usize);
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                result.push(grid_cell_id(x, y));
+            }
+        }
+        result
+    }
+}
+
+// Pack the coordinates of a grid cell into a single value.
+fn grid_cell_id(x: i64, y: i64) -> i64 {
+    ((x as i32 as i64) << 32) | (y as i32 as u32 as i64)
+}
+
+fn rect_contains(outer: &geo::Rect<f64>, inner: &geo::Rect<f64>) -> bool {
+    outer.min().x <= inner.min().x
+        && outer.min().y <= inner.min().y
+        && outer.max().x >= inner.max().x
+        && outer.max().y >= inner.max().y
+}
+
+// All segments of a geometry; isolated points are represented as
+// degenerate segments.
+fn segments(geometry: &geo::Geometry<f64>) -> Vec<geo::Line<f64>> {
+    match geometry {
+        geo::Geometry::Point(p) => vec![geo::Line::new(p.0, p.0)],
+        geo::Geometry::MultiPoint(mp) => mp.iter().map(|p| geo::Line::new(p.0, p.0)).collect(),
+        geo::Geometry::GeometryCollection(gc) => gc.iter().flat_map(segments).collect(),
+        geometry => geometry.lines_iter().collect(),
+    }
+}
+
+impl fmt::Debug for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_wkt())
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_wkt())
+    }
+}
+
+impl FromStr for Geometry {
+    type Err = String;
+
+    // WKB always starts with a byte order marker (`00` or `01` in hex),
+    // while WKT always starts with a geometry type name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('0') {
+            Self::from_hex_wkb(s)
+        } else {
+            Self::from_wkt(s)
+        }
+    }
+}
+
+impl Serialize for Geometry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_wkt())
+    }
+}
+
+struct GeometryVisitor;
+
+impl<'de> Visitor<'de> for GeometryVisitor {
+    type Value = Geometry;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a geometry encoded as WKT, hex-encoded WKB, or WKB bytes")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: ::serde::de::Error,
+    {
+        Geometry::from_str(v).map_err(E::custom)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: ::serde::de::Error,
+    {
+        Geometry::from_wkb(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Geometry::from_wkb(&bytes).map_err(A::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Geometry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(GeometryVisitor)
+    }
+}
+
+// A geometry whose coordinates are (longitude, latitude) pairs in degrees.
+// Distances are measured on the sphere, in meters.
+#[derive(
+    Default,
+    Eq,
+    Ord,
+    Clone,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    SizeOf,
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[serde(transparent)]
+pub struct Geography(Geometry);
+
+impl Geography {
+    pub fn new(geometry: Geometry) -> Self {
+        Self(geometry)
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.0
+    }
+
+    // Points are compared using the haversine formula.  For other shapes
+    // the distance is measured between the pair of points that are closest
+    // in (longitude, latitude) space, which is accurate for shapes that
+    // are small compared to the size of the Earth.
+    pub fn distance(&self, other: &Geography) -> F64 {
+        let left = self.0.to_geo();
+        let right = other.0.to_geo();
+        if left.intersects(&right) {
+            return F64::new(0.0);
+        }
+        let mut closest: Option<(f64, Point<f64>, Point<f64>)> = None;
+        for l in segments(&left) {
+            for r in segments(&right) {
+                let (lp, rp) = closest_points(&l, &r);
+                let d = lp.euclidean_distance(&rp);
+                if closest.map_or(true, |(best, _, _)| d < best) {
+                    closest = Some((d, lp, rp));
+                }
+            }
+        }
+        match closest {
+            Some((_, lp, rp)) => F64::new(lp.haversine_distance(&rp)),
+            None => F64::new(f64::INFINITY),
+        }
+    }
+}
+
+// The closest pair of points on two non-intersecting segments.  One of the
+// points is always an endpoint of one of the segments.
+fn closest_points(left: &geo::Line<f64>, right: &geo::Line<f64>) -> (Point<f64>, Point<f64>) {
+    use geo::{Closest, ClosestPoint};
+
+    let project = |line: &geo::Line<f64>, p: Point<f64>| match line.closest_point(&p) {
+        Closest::Intersection(c) | Closest::SinglePoint(c) => c,
+        Closest::Indeterminate => line.start_point(),
+    };
+    let candidates = [
+        (left.start_point(), project(right, left.start_point())),
+        (left.end_point(), project(right, left.end_point())),
+        (project(left, right.start_point()), right.start_point()),
+        (project(left, right.end_point()), right.end_point()),
+    ];
+    candidates
+        .into_iter()
+        .min_by(|a, b| {
+            a.0.euclidean_distance(&a.1)
+                .total_cmp(&b.0.euclidean_distance(&b.1))
+        })
+        .unwrap()
+}
+
+impl fmt::Debug for Geography {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+//////////////////// WKB encoding
+
+const WKB_POINT: u32 = 1;
+const WKB_LINESTRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTIPOINT: u32 = 4;
+const WKB_MULTILINESTRING: u32 = 5;
+const WKB_MULTIPOLYGON: u32 = 6;
+const WKB_GEOMETRYCOLLECTION: u32 = 7;
+
+fn write_header(out: &mut Vec<u8>, code: u32) {
+    out.push(1);
+    out.extend_from_slice(&code.to_le_bytes());
+}
+
+fn write_coord(out: &mut Vec<u8>, coord: &Coord<f64>) {
+    out.extend_from_slice(&coord.x.to_le_bytes());
+    out.extend_from_slice(&coord.y.to_le_bytes());
+}
+
+fn write_count(out: &mut Vec<u8>, count: usize) {
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+}
+
+fn write_coords(out: &mut Vec<u8>, line: &LineString<f64>) {
+    write_count(out, line.0.len());
+    line.0.iter().for_each(|c| write_coord(out, c));
+}
+
+fn write_rings(out: &mut Vec<u8>, polygon: &Polygon<f64>) {
+    // An empty polygon has an empty exterior ring and no rings in WKB.
+    if polygon.exterior().0.is_empty() {
+        write_count(out, 0);
+        return;
+    }
+    write_count(out, 1 + polygon.interiors().len());
+    write_coords(out, polygon.exterior());
+    polygon
+        .interiors()
+        .iter()
+        .for_each(|r| write_coords(out, r));
+}
+
+fn write_wkb(out: &mut Vec<u8>, geometry: &geo::Geometry<f64>) {
+    match geometry {
+        geo::Geometry::Point(p) => {
+            write_header(out, WKB_POINT);
+            write_coord(out, &p.0);
+        }
+        geo::Geometry::Line(l) => {
+            write_wkb(out, &geo::Geometry::LineString(LineString::from(*l)));
+        }
+        geo::Geometry::LineString(l) => {
+            write_header(out, WKB_LINESTRING);
+            write_coords(out, l);
+        }
+        geo::Geometry::Polygon(p) => {
+            write_header(out, WKB_POLYGON);
+            write_rings(out, p);
+        }
+        geo::Geometry::MultiPoint(mp) => {
+            write_header(out, WKB_MULTIPOINT);
+            write_count(out, mp.0.len());
+            mp.iter()
+                .for_each(|p| write_wkb(out, &geo::Geometry::Point(*p)));
+        }
+        geo::Geometry::MultiLineString(ml) => {
+            write_header(out, WKB_MULTILINESTRING);
+            write_count(out, ml.0.len());
+            ml.iter()
+                .for_each(|l| write_wkb(out, &geo::Geometry::LineString(l.clone())));
+        }
+        geo::Geometry::MultiPolygon(mp) => {
+            write_header(out, WKB_MULTIPOLYGON);
+            write_count(out, mp.0.len());
+            mp.iter()
+                .for_each(|p| write_wkb(out, &geo::Geometry::Polygon(p.clone())));
+        }
+        geo::Geometry::GeometryCollection(gc) => {
+            write_header(out, WKB_GEOMETRYCOLLECTION);
+            write_count(out, gc.0.len());
+            gc.iter().for_each(|g| write_wkb(out, g));
+        }
+        geo::Geometry::Rect(r) => write_wkb(out, &geo::Geometry::Polygon(r.to_polygon())),
+        geo::Geometry::Triangle(t) => write_wkb(out, &geo::Geometry::Polygon(t.to_polygon())),
+    }
+}
+
+struct WkbReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| "invalid WKB: unexpected end of input".to_string())?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32, String> {
+        let bytes = self.take::<4>()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_f64(&mut self, little_endian: bool) -> Result<f64, String> {
+        let bytes = self.take::<8>()?;
+        Ok(if little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_coord(&mut self, little_endian: bool) -> Result<Coord<f64>, String> {
+        let x = self.read_f64(little_endian)?;
+        let y = self.read_f64(little_endian)?;
+        Ok(Coord { x, y })
+    }
+
+    fn read_count(&mut self, little_endian: bool) -> Result<usize, String> {
+        let count = self.read_u32(little_endian)? as usize;
+        // Every element takes at least one byte; reject counts that cannot
+        // possibly fit in the input before allocating.
+        if count > self.data.len() - self.pos {
+            return Err(format!(
+                "invalid WKB: element count {count} exceeds input size"
+            ));
+        }
+        Ok(count)
+    }
+
+    fn read_coords(&mut self, little_endian: bool) -> Result<LineString<f64>, String> {
+        let count = self.read_count(little_endian)?;
+        (0..count)
+            .map(|_| self.read_coord(little_endian))
+            .collect::<Result<Vec<_>, _>>()
+            .map(LineString::new)
+    }
+
+    fn read_polygon(&mut self, little_endian: bool) -> Result<Polygon<f64>, String> {
+        let count = self.read_count(little_endian)?;
+        let mut rings = (0..count)
+            .map(|_| self.read_coords(little_endian))
+            .collect::<Result<Vec<_>, _>>()?;
+        if rings.is_empty() {
+            return Ok(Polygon::new(LineString::new(Vec::new()), Vec::new()));
+        }
+        let exterior = rings.remove(0);
+        Ok(Polygon::new(exterior, rings))
+    }
+
+    fn read_header(&mut self) -> Result<(bool, u32), String> {
+        let little_endian = match self.take::<1>()?[0] {
+            0 => false,
+            1 => true,
+            b => return Err(format!("invalid WKB: unknown byte order {b}")),
+        };
+        let code = self.read_u32(little_endian)?;
+        Ok((little_endian, code))
+    }
+
+    // Read a nested geometry that must have the specified type.
+    fn read_member(&mut self, expected: u32) -> Result<geo::Geometry<f64>, String> {
+        let start = self.pos;
+        let (_, code) = self.read_header()?;
+        if code != expected {
+            return Err(format!(
+                "invalid WKB: expected geometry type {expected}, found {code}"
+            ));
+        }
+        self.pos = start;
+        self.read_geometry()
+    }
+
+    fn read_geometry(&mut self) -> Result<geo::Geometry<f64>, String> {
+        let (le, code) = self.read_header()?;
+        match code {
+            WKB_POINT => Ok(Point(self.read_coord(le)?).into()),
+            WKB_LINESTRING => Ok(self.read_coords(le)?.into()),
+            WKB_POLYGON => Ok(self.read_polygon(le)?.into()),
+            WKB_MULTIPOINT => {
+                let count = self.read_count(le)?;
+                let mut points = Vec::with_capacity(count);
+                for _ in 0..count {
+                    if let geo::Geometry::Point(p) = self.read_member(WKB_POINT)? {
+                        points.push(p);
+                    }
+                }
+                Ok(MultiPoint(points).into())
+            }
+            WKB_MULTILINESTRING => {
+                let count = self.read_count(le)?;
+                let mut lines = Vec::with_capacity(count);
+                for _ in 0..count {
+                    if let geo::Geometry::LineString(l) = self.read_member(WKB_LINESTRING)? {
+                        lines.push(l);
+                    }
+                }
+                Ok(MultiLineString(lines).into())
+            }
+            WKB_MULTIPOLYGON => {
+                let count = self.read_count(le)?;
+                let mut polygons = Vec::with_capacity(count);
+                for _ in 0..count {
+                    if let geo::Geometry::Polygon(p) = self.read_member(WKB_POLYGON)? {
+                        polygons.push(p);
+                    }
+                }
+                Ok(MultiPolygon(polygons).into())
+            }
+            WKB_GEOMETRYCOLLECTION => {
+                let count = self.read_count(le)?;
+                let geometries = (0..count)
+                    .map(|_| self.read_geometry())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(GeometryCollection(geometries).into())
+            }
+            code => Err(format!(
+                "invalid WKB: unsupported geometry type {code}; only 2D geometries are supported"
+            )),
+        }
+    }
+}
+
+//////////////////// SQL functions
+
+pub fn st_geomfromtext_s(value: String) -> Geometry {
+    Geometry::from_wkt(&value).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn st_geomfromtext_sN(value: Option<String>) -> Option<Geometry> {
+    value.map(st_geomfromtext_s)
+}
+
+pub fn st_geogfromtext_s(value: String) -> Geography {
+    Geography::new(st_geomfromtext_s(value))
+}
+
+pub fn st_geogfromtext_sN(value: Option<String>) -> Option<Geography> {
+    value.map(st_geogfromtext_s)
+}
+
+pub fn st_point_d_d(x: F64, y: F64) -> Geometry {
+    Geometry::from_geo(&Point::new(x.into_inner(), y.into_inner()).into())
+}
+
+some_polymorphic_function2!(st_point, d, F64, d, F64, Geometry);
+
+pub fn st_astext_geometry(value: Geometry) -> String {
+    value.to_wkt()
+}
+
+some_polymorphic_function1!(st_astext, geometry, Geometry, String);
+
+pub fn st_astext_geography(value: Geography) -> String {
+    value.0.to_wkt()
+}
+
+some_polymorphic_function1!(st_astext, geography, Geography, String);
+
+pub fn st_contains_geometry_geometry(left: Geometry, right: Geometry) -> bool {
+    left.contains(&right)
+}
+
+some_polymorphic_function2!(st_contains, geometry, Geometry, geometry, Geometry, bool);
+
+pub fn st_within_geometry_geometry(left: Geometry, right: Geometry) -> bool {
+    left.within(&right)
+}
+
+some_polymorphic_function2!(st_within, geometry, Geometry, geometry, Geometry, bool);
+
+pub fn st_distance_geometry_geometry(left: Geometry, right: Geometry) -> F64 {
+    left.distance(&right)
+}
+
+some_polymorphic_function2!(st_distance, geometry, Geometry, geometry, Geometry, F64);
+
+pub fn st_contains_geography_geography(left: Geography, right: Geography) -> bool {
+    left.0.contains(&right.0)
+}
+
+some_polymorphic_function2!(
+    st_contains,
+    geography,
+    Geography,
+    geography,
+    Geography,
+    bool
+);
+
+pub fn st_within_geography_geography(left: Geography, right: Geography) -> bool {
+    left.0.within(&right.0)
+}
+
+some_polymorphic_function2!(st_within, geography, Geography, geography, Geography, bool);
+
+pub fn st_distance_geography_geography(left: Geography, right: Geography) -> F64 {
+    left.distance(&right)
+}
+
+some_polymorphic_function2!(st_distance, geography, Geography, geography, Geography, F64);
+
+pub fn st_grid_cells_geometry_d(value: Geometry, cell_size: F64) -> Vec<i64> {
+    value.grid_cells(cell_size.into_inner())
+}
+
+some_polymorphic_function2!(st_grid_cells, geometry, Geometry, d, F64, Vec<i64>);
+
+#[cfg(test)]
+mod test {
+    use super::{
+        st_contains_geometry_geometry, st_distance_geography_geography,
+        st_distance_geometry_geometry, st_geogfromtext_s, st_geomfromtext_s,
+        st_grid_cells_geometry_d, st_point_d_d, st_within_geometry_geometry, Geometry,
+    };
+    use dbsp::algebra::F64;
+    use std::str::FromStr;
+
+    const SHAPES: &[&str] = &[
+        "POINT (1 2)",
+        "LINESTRING (0 0, 1 1, 2 0)",
+        "POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0), (2 2, 4 2, 4 4, 2 2))",
+        "MULTIPOINT ((0 0), (1 1))",
+        "MULTILINESTRING ((0 0, 1 1), (2 2, 3 3))",
+        "MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)), ((5 5, 6 5, 6 6, 5 5)))",
+        "GEOMETRYCOLLECTION (POINT (1 2), LINESTRING (0 0, 1 1))",
+    ];
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02X}")).collect()
+    }
+
+    #[test]
+    fn wkt_round_trip() {
+        for shape in SHAPES {
+            let geometry = Geometry::from_wkt(shape).unwrap();
+            assert_eq!(Geometry::from_wkt(&geometry.to_wkt()).unwrap(), geometry);
+        }
+    }
+
+    #[test]
+    fn wkb_round_trip() {
+        for shape in SHAPES {
+            let geometry = Geometry::from_wkt(shape).unwrap();
+            assert_eq!(Geometry::from_wkb(geometry.wkb()).unwrap(), geometry);
+            assert_eq!(
+                Geometry::from_hex_wkb(&hex(geometry.wkb())).unwrap(),
+                geometry
+            );
+            assert_eq!(Geometry::from_str(&hex(geometry.wkb())).unwrap(), geometry);
+        }
+        let empty = Geometry::default();
+        assert_eq!(Geometry::from_wkb(empty.wkb()).unwrap(), empty);
+    }
+
+    #[test]
+    fn wkb_encoding() {
+        let point = Geometry::from_wkt("POINT (1 2)").unwrap();
+        assert_eq!(
+            hex(point.wkb()),
+            "0101000000000000000000F03F0000000000000040"
+        );
+        // Big-endian input is accepted and normalized to little-endian.
+        let big_endian =
+            Geometry::from_hex_wkb("00000000013FF00000000000004000000000000000").unwrap();
+        assert_eq!(big_endian, point);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(Geometry::from_wkt("POINT (1)").is_err());
+        assert!(Geometry::from_wkt("CIRCLE (0 0, 1)").is_err());
+        assert!(Geometry::from_hex_wkb("0101").is_err());
+        assert!(Geometry::from_hex_wkb("01010").is_err());
+        assert!(Geometry::from_hex_wkb("ZZ").is_err());
+        // Trailing bytes.
+        assert!(Geometry::from_hex_wkb("0101000000000000000000F03F000000000000004000").is_err());
+        // 3D point.
+        assert!(Geometry::from_hex_wkb(
+            "01E9030000000000000000F03F00000000000000400000000000000840"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        for shape in SHAPES {
+            let geometry = Geometry::from_wkt(shape).unwrap();
+            let json = serde_json::to_string(&geometry).unwrap();
+            assert_eq!(serde_json::from_str::<Geometry>(&json).unwrap(), geometry);
+            let bytes = serde_json::to_string(geometry.wkb()).unwrap();
+            assert_eq!(serde_json::from_str::<Geometry>(&bytes).unwrap(), geometry);
+        }
+    }
+
+    #[test]
+    fn predicates() {
+        let square = st_geomfromtext_s("POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0))".to_string());
+        let inside = st_point_d_d(F64::new(1.0), F64::new(1.0));
+        let outside = st_point_d_d(F64::new(20.0), F64::new(1.0));
+        assert!(st_contains_geometry_geometry(
+            square.clone(),
+            inside.clone()
+        ));
+        assert!(!st_contains_geometry_geometry(
+            square.clone(),
+            outside.clone()
+        ));
+        assert!(st_within_geometry_geometry(inside.clone(), square.clone()));
+        assert!(!st_within_geometry_geometry(square.clone(), inside.clone()));
+        assert_eq!(
+            st_distance_geometry_geometry(inside, square.clone()),
+            F64::new(0.0)
+        );
+        assert_eq!(
+            st_distance_geometry_geometry(outside, square),
+            F64::new(10.0)
+        );
+    }
+
+    #[test]
+    fn geography_distance() {
+        let origin = st_geogfromtext_s("POINT (0 0)".to_string());
+        let east = st_geogfromtext_s("POINT (1 0)".to_string());
+        // One degree of longitude at the equator is about 111 km.
+        let distance = st_distance_geography_geography(origin, east).into_inner();
+        assert!((111_000.0..112_000.0).contains(&distance), "{distance}");
+    }
+
+    #[test]
+    fn grid_cells() {
+        let point = st_geomfromtext_s("POINT (5 5)".to_string());
+        assert_eq!(st_grid_cells_geometry_d(point, F64::new(10.0)), vec![0]);
+        let line = st_geomfromtext_s("LINESTRING (5 5, 15 5)".to_string());
+        assert_eq!(
+            st_grid_cells_geometry_d(line, F64::new(10.0)),
+            vec![0, 1 << 32]
+        );
+        let negative = st_geomfromtext_s("POINT (-5 -5)".to_string());
+        assert_eq!(st_grid_cells_geometry_d(negative, F64::new(10.0)), vec![-1]);
+        assert!(st_grid_cells_geometry_d(Geometry::default(), F64::new(10.0)).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! SQL/JSON path functions over strings
+
+use serde_json::Value;
+use std::{cell::RefCell, collections::HashMap};
+
+// Max number of compiled paths cached by each worker.
+const PATH_CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    // Compiled JSON paths indexed by their text.  Paths are almost always
+    // constants, so each one is parsed once per worker thread.  Invalid
+    // paths are cached as `None`.
+    static PATH_CACHE: RefCell<HashMap<String, Option<JsonPath>>> = RefCell::new(HashMap::new());
+
+    // The last document parsed by this worker and its parsed value, or
+    // `None` if it is not valid JSON.  Queries usually extract several
+    // fields from the same column, so consecutive calls for the same row
+    // parse the document only once.
+    static DOCUMENT_CACHE: RefCell<Option<(String, Option<Value>)>> = RefCell::new(None);
+}
+
+#[derive(Debug)]
+enum Step {
+    // `.name` or `."name"`
+    Member(String),
+    // `.*`
+    AnyMember,
+    // `[n]`
+    Index(usize),
+    // `[*]`
+    AnyIndex,
+}
+
+// A parsed SQL/JSON path, e.g., `lax $.items[0].name`.
+#[derive(Debug)]
+struct JsonPath {
+    // In strict mode structural errors, such as accessing a member of an
+    // array, make the whole path fail.  In lax mode (the default) arrays
+    // are unwrapped automatically and missing items are skipped.
+    strict: bool,
+    steps: Vec<Step>,
+}
+
+impl JsonPath {
+    fn parse(path: &str) -> Option<Self> {
+        let path = path.trim();
+        let (strict, path) = if let Some(rest) = path.strip_prefix("strict ") {
+            (true, rest.trim_start())
+        } else if let Some(rest) = path.strip_prefix("lax ") {
+            (false, rest.trim_start())
+        } else {
+            (false, path)
+        };
+
+        let mut rest = path.strip_prefix('$')?.trim_start();
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(member) = rest.strip_prefix('.') {
+                let member = member.trim_start();
+                if let Some(after) = member.strip_prefix('*') {
+                    steps.push(Step::AnyMember);
+                    rest = after;
+                } else if let Some(quoted) = member.strip_prefix('"') {
+                    let end = quoted.find('"')?;
+                    steps.push(Step::Member(quoted[..end].to_string()));
+                    rest = &quoted[end + 1..];
+                } else {
+                    let end = member
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(member.len());
+                    if end == 0 {
+                        return None;
+                    }
+                    steps.push(Step::Member(member[..end].to_string()));
+                    rest = &member[end..];
+                }
+            } else if let Some(index) = rest.strip_prefix('[') {
+                let end = index.find(']')?;
+                let index_text = index[..end].trim();
+                if index_text == "*" {
+                    steps.push(Step::AnyIndex);
+                } else {
+                    steps.push(Step::Index(index_text.parse().ok()?));
+                }
+                rest = &index[end + 1..];
+            } else {
+                return None;
+            }
+            rest = rest.trim_start();
+        }
+
+        Some(Self { strict, steps })
+    }
+
+    // Returns the sequence of items selected by the path, or `None` if
+    // evaluation fails in strict mode.
+    fn evaluate<'a>(&self, root: &'a Value) -> Option<Vec<&'a Value>> {
+        let mut items = vec![root];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for item in items {
+                match (step, item) {
+                    (Step::Member(name), Value::Object(object)) => match object.get(name) {
+                        Some(value) => next.push(value),
+                        None if self.strict => return None,
+                        None => {}
+                    },
+                    (Step::AnyMember, Value::Object(object)) => next.extend(object.values()),
+                    (Step::Member(_) | Step::AnyMember, Value::Array(array)) if !self.strict => {
+                        for element in array {
+                            match (step, element) {
+                                (Step::Member(name), Value::Object(object)) => {
+                                    next.extend(object.get(name))
+                                }
+                                (Step::AnyMember, Value::Object(object)) => {
+                                    next.extend(object.values())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Step::Index(index), Value::Array(array)) => match array.get(*index) {
+                        Some(value) => next.push(value),
+                        None if self.strict => return None,
+                        None => {}
+                    },
+                    (Step::AnyIndex, Value::Array(array)) => next.extend(array),
+                    // Lax mode treats any other item as an array of one element.
+                    (Step::Index(0) | Step::AnyIndex, item) if !self.strict => next.push(item),
+                    _ if self.strict => return None,
+                    _ => {}
+                }
+            }
+            items = next;
+        }
+        Some(items)
+    }
+}
+
+// Evaluates `path` over the JSON document `document` and invokes `f` with
+// the selected items.  Returns `None` if the path or the document is
+// invalid, or if the path cannot be evaluated.
+fn with_items<T>(
+    document: String,
+    path: &str,
+    f: impl FnOnce(&[&Value]) -> Option<T>,
+) -> Option<T> {
+    PATH_CACHE.with(|paths| {
+        let mut paths = paths.borrow_mut();
+        if !paths.contains_key(path) {
+            if paths.len() >= PATH_CACHE_CAPACITY {
+                paths.clear();
+            }
+            paths.insert(path.to_string(), JsonPath::parse(path));
+        }
+        let path = paths[path].as_ref()?;
+
+        DOCUMENT_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let cached = matches!(&*cache, Some((text, _)) if *text == document);
+            if !cached {
+                let value = serde_json::from_str(&document).ok();
+                *cache = Some((document, value));
+            }
+            let root = cache.as_ref()?.1.as_ref()?;
+            f(&path.evaluate(root)?)
+        })
+    })
+}
+
+// Returns the scalar selected by `path` in `document` as a string.  Returns
+// `None` if the path selects nothing, more than one item, a JSON null, an
+// object or an array, or if either argument is invalid.
+pub fn json_value__(document: String, path: String) -> Option<String> {
+    with_items(document, &path, |items| match items {
+        [Value::String(string)] => Some(string.clone()),
+        [Value::Number(number)] => Some(number.to_string()),
+        [Value::Bool(bool)] => Some(bool.to_string()),
+        _ => None,
+    })
+}
+
+pub fn json_valueN_(document: Option<String>, path: String) -> Option<String> {
+    json_value__(document?, path)
+}
+
+pub fn json_value_N(document: String, path: Option<String>) -> Option<String> {
+    json_value__(document, path?)
+}
+
+pub fn json_valueNN(document: Option<String>, path: Option<String>) -> Option<String> {
+    json_value__(document?, path?)
+}
+
+// Returns the object or array selected by `path` in `document` serialized
+// as JSON.  Returns `None` if the path selects nothing, more than one item,
+// or a scalar, or if either argument is invalid.
+pub fn json_query__(document: String, path: String) -> Option<String> {
+    with_items(document, &path, |items| match items {
+        [item @ (Value::Object(_) | Value::Array(_))] => Some(item.to_string()),
+        _ => None,
+    })
+}
+
+pub fn json_queryN_(document: Option<String>, path: String) -> Option<String> {
+    json_query__(document?, path)
+}
+
+pub fn json_query_N(document: String, path: Option<String>) -> Option<String> {
+    json_query__(document, path?)
+}
+
+pub fn json_queryNN(document: Option<String>, path: Option<String>) -> Option<String> {
+    json_query__(document?, path?)
+}
@@ -1,10 +1,16 @@
 #![allow(non_snake_case)]
 
+pub mod aggregates;
+pub mod anomaly;
 pub mod casts;
+pub mod geometry;
 pub mod geopoint;
 pub mod interval;
+pub mod json;
 pub mod operators;
+pub mod pattern;
 pub mod string;
+pub mod text;
 pub mod timestamp;
 
 use crate::interval::ShortInterval;
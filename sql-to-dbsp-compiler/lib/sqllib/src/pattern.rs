@@ -0,0 +1,58 @@
+//! Support for MATCH_RECOGNIZE
+//!
+//! The rows of a partition are indexed by their ORDER BY key, so the
+//! events of a pattern are `(key, row)` pairs.  The functions below only
+//! expose the rows to generated code.
+
+use dbsp::operator::{AfterMatch, Pattern, PatternVariable};
+
+// Generated code names this type in the signature of the measures closure.
+pub use dbsp::operator::PatternMatch;
+
+// Create a pattern variable that matches between `min` and `max`
+// consecutive rows; a negative `max` means unbounded.  `define` is
+// invoked with the current row, the previous row in the partition and a
+// flag that is false for the first row of the partition, in which case
+// the current row is passed in place of the previous one.
+pub fn pattern_variable<K, V, F>(define: F, min: i32, max: i32) -> PatternVariable<(K, V)>
+where
+    F: Fn(&V, &V, bool) -> bool + 'static,
+{
+    let max = if max < 0 { usize::MAX } else { max as usize };
+    PatternVariable::new(
+        move |(_, row), prev| match prev {
+            Some((_, prev)) => define(row, prev, true),
+            None => define(row, row, false),
+        },
+        min as usize,
+        max,
+    )
+}
+
+pub fn pattern_new<V>(variables: Vec<PatternVariable<V>>, skip_to_next_row: bool) -> Pattern<V> {
+    let after_match = if skip_to_next_row {
+        AfterMatch::SkipToNextRow
+    } else {
+        AfterMatch::SkipPastLastRow
+    };
+    Pattern::new(variables).with_after_match(after_match)
+}
+
+pub fn pattern_has<K, V>(m: &PatternMatch<'_, (K, V)>, variable: usize) -> bool {
+    m.first(variable).is_some()
+}
+
+// The following functions must only be called for variables for which
+// `pattern_has` is true.
+
+pub fn pattern_first<'a, K, V>(m: &PatternMatch<'a, (K, V)>, variable: usize) -> &'a V {
+    &m.first(variable)
+        .expect("pattern variable did not match any row")
+        .1
+}
+
+pub fn pattern_last<'a, K, V>(m: &PatternMatch<'a, (K, V)>, variable: usize) -> &'a V {
+    &m.last(variable)
+        .expect("pattern variable did not match any row")
+        .1
+}
@@ -7,6 +7,7 @@ use crate::{
 
 use like::{Escape, Like};
 use regex::Regex;
+use std::{cell::RefCell, collections::HashMap};
 
 pub fn concat_s_s(mut left: String, right: String) -> String {
     left.reserve(right.len());
@@ -75,10 +76,34 @@ pub fn like2N_(value: Option<String>, pattern: String) -> bool {
     }
 }
 
+// Max number of compiled regular expressions cached by each worker.
+const REGEX_CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    // Compiled regular expressions indexed by pattern.  Patterns are almost
+    // always constants, so compiling them once per worker thread (i.e., once
+    // per copy of the circuit) avoids compiling them for each row.  Invalid
+    // patterns are cached as `None`.
+    static REGEX_CACHE: RefCell<HashMap<String, Option<Regex>>> = RefCell::new(HashMap::new());
+}
+
+// Invokes `f` with the compiled regular expression for `pattern`, or `None`
+// if the pattern is invalid.
+fn with_regex<T>(pattern: &str, f: impl FnOnce(Option<&Regex>) -> T) -> T {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if !cache.contains_key(pattern) {
+            if cache.len() >= REGEX_CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(pattern.to_string(), Regex::new(pattern).ok());
+        }
+        f(cache[pattern].as_ref())
+    })
+}
+
 pub fn rlike__(value: String, pattern: String) -> bool {
-    // TODO: the regex should not be created for each row.
-    let re = Regex::new(&pattern);
-    re.map_or_else(|_| false, |re| re.is_match(&value))
+    with_regex(&pattern, |re| re.map_or(false, |re| re.is_match(&value)))
 }
 
 pub fn rlikeN_(value: Option<String>, pattern: String) -> bool {
@@ -88,6 +113,77 @@ pub fn rlikeN_(value: Option<String>, pattern: String) -> bool {
     }
 }
 
+pub fn regexp_match__(value: String, pattern: String) -> bool {
+    rlike__(value, pattern)
+}
+
+some_function2!(regexp_match, String, String, bool);
+
+// Returns the part of `value` matched by the first capturing group of
+// `pattern`, or the whole match if `pattern` has no capturing groups.
+// Returns `None` if `pattern` is invalid or does not match.
+pub fn regexp_extract__(value: String, pattern: String) -> Option<String> {
+    with_regex(&pattern, |re| {
+        let captures = re?.captures(&value)?;
+        let group = if captures.len() > 1 {
+            captures.get(1)
+        } else {
+            captures.get(0)
+        };
+        Some(group.map_or_else(String::new, |m| m.as_str().to_string()))
+    })
+}
+
+pub fn regexp_extractN_(value: Option<String>, pattern: String) -> Option<String> {
+    regexp_extract__(value?, pattern)
+}
+
+pub fn regexp_extract_N(value: String, pattern: Option<String>) -> Option<String> {
+    regexp_extract__(value, pattern?)
+}
+
+pub fn regexp_extractNN(value: Option<String>, pattern: Option<String>) -> Option<String> {
+    regexp_extract__(value?, pattern?)
+}
+
+// Converts a SQL replacement string, where `\N` refers to the N-th
+// capturing group and `\\` is a backslash, to the syntax used by `Regex`.
+fn regexp_replacement(replacement: &str) -> String {
+    let mut result = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(d) if d.is_ascii_digit() => {
+                    result.push_str(&format!("${{{d}}}"));
+                    chars.next();
+                }
+                Some('\\') => {
+                    result.push('\\');
+                    chars.next();
+                }
+                _ => result.push('\\'),
+            },
+            '$' => result.push_str("$$"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+// Replaces all matches of `pattern` in `value`.  Returns `value` unchanged
+// if `pattern` is invalid.
+pub fn regexp_replace___(value: String, pattern: String, replacement: String) -> String {
+    with_regex(&pattern, |re| match re {
+        None => value,
+        Some(re) => re
+            .replace_all(&value, regexp_replacement(&replacement).as_str())
+            .into_owned(),
+    })
+}
+
+some_function3!(regexp_replace, String, String, String, String);
+
 pub fn like3___(value: String, pattern: String, escape: String) -> bool {
     let escaped = pattern.as_str().escape(escape.as_str()).unwrap();
     Like::<true>::like(value.as_str(), escaped.as_str()).unwrap()
@@ -0,0 +1,24 @@
+//! Full-text search predicates
+
+use crate::some_function2;
+use dbsp::operator::text_index::tokenize;
+
+/// True if `text` contains every token of `query`, in any order.
+/// A query without tokens matches nothing.
+pub fn text_contains__(text: String, query: String) -> bool {
+    let tokens: Vec<String> = tokenize(&text).collect();
+    let mut query = tokenize(&query).peekable();
+    query.peek().is_some() && query.all(|q| tokens.contains(&q))
+}
+
+some_function2!(text_contains, String, String, bool);
+
+/// True if the tokens of `phrase` occur in `text` contiguously and in the
+/// same order.  A phrase without tokens matches nothing.
+pub fn text_phrase__(text: String, phrase: String) -> bool {
+    let tokens: Vec<String> = tokenize(&text).collect();
+    let phrase: Vec<String> = tokenize(&phrase).collect();
+    !phrase.is_empty() && tokens.windows(phrase.len()).any(|w| w == phrase.as_slice())
+}
+
+some_function2!(text_phrase, String, String, bool);